@@ -1,121 +1,14 @@
 use rvr_isa::{
-    DecodedInstr, InstrArgs, OP_ADD, OP_ADDI, OP_AUIPC, OP_BEQ, OP_BGE, OP_BGEU, OP_BLT, OP_BLTU,
-    OP_BNE, OP_C_ADD, OP_C_ADDI, OP_C_ADDI4SPN, OP_C_ADDI16SP, OP_C_BEQZ, OP_C_BNEZ, OP_C_J,
-    OP_C_JAL, OP_C_JALR, OP_C_JR, OP_C_LD, OP_C_LDSP, OP_C_LI, OP_C_LUI, OP_C_LW, OP_C_LWSP,
-    OP_C_MV, OP_JAL, OP_JALR, OP_LB, OP_LBU, OP_LD, OP_LH, OP_LHU, OP_LUI, OP_LW, OP_LWU, OpId,
-    Xlen,
+    DecodedInstr, EXT_ZCMP, InstrArgs, OP_ADD, OP_ADDI, OP_AUIPC, OP_BEQ, OP_BGE, OP_BGEU, OP_BLT,
+    OP_BLTU, OP_BNE, OP_C_ADD, OP_C_ADDI, OP_C_ADDI4SPN, OP_C_ADDI16SP, OP_C_BEQZ, OP_C_BNEZ,
+    OP_C_J, OP_C_JAL, OP_C_JALR, OP_C_JR, OP_C_LBU, OP_C_LD, OP_C_LDSP, OP_C_LH, OP_C_LHU, OP_C_LI,
+    OP_C_LUI, OP_C_LW, OP_C_LWSP, OP_C_MV, OP_C_SLLI, OP_C_SRLI, OP_CM_POPRET, OP_CM_POPRETZ,
+    OP_JAL, OP_JALR, OP_LB, OP_LBU, OP_LD, OP_LH, OP_LHU, OP_LUI, OP_LW, OP_LWU, OP_SH1ADD,
+    OP_SH2ADD, OP_SH3ADD, OP_SLLI, OP_SRLI, OpId, REG_RA, REG_ZERO, Xlen, zcmp_written_regs,
 };
 
-use super::{MAX_VALUES, NUM_REGS, extract_written_reg};
-
-// TODO: use proper rust enum instead of kind flag
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-enum ValueKind {
-    Unknown,
-    Constant,
-}
-
-#[derive(Clone, Debug, PartialEq, Eq)]
-pub(super) struct RegisterValue {
-    kind: ValueKind,
-    pub(super) values: Vec<u64>,
-}
-
-impl RegisterValue {
-    pub(super) const fn unknown() -> Self {
-        Self {
-            kind: ValueKind::Unknown,
-            values: Vec::new(),
-        }
-    }
-
-    pub(super) fn constant(value: u64) -> Self {
-        Self {
-            kind: ValueKind::Constant,
-            values: vec![value],
-        }
-    }
-
-    pub(super) fn is_constant(&self) -> bool {
-        self.kind == ValueKind::Constant
-    }
-
-    pub(super) fn add_value(&mut self, value: u64) {
-        if self.kind != ValueKind::Constant {
-            return;
-        }
-
-        match self.values.binary_search(&value) {
-            Ok(_) => {}
-            Err(idx) => {
-                // TODO: add comment for what's happening here
-                // Keep constants sorted+deduplicated; degrade to Unknown if the set explodes.
-                if self.values.len() >= MAX_VALUES {
-                    self.kind = ValueKind::Unknown;
-                    self.values.clear();
-                } else {
-                    self.values.insert(idx, value);
-                }
-            }
-        }
-    }
-
-    pub(super) fn merge(&self, other: &Self) -> Self {
-        if self.kind == ValueKind::Unknown || other.kind == ValueKind::Unknown {
-            return Self::unknown();
-        }
-
-        let mut merged = Vec::with_capacity(self.values.len() + other.values.len());
-        let mut i = 0;
-        let mut j = 0;
-
-        // TODO: abstract to a separate function
-        while i < self.values.len() && j < other.values.len() {
-            let a = self.values[i];
-            let b = other.values[j];
-            match a.cmp(&b) {
-                std::cmp::Ordering::Equal => {
-                    merged.push(a);
-                    i += 1;
-                    j += 1;
-                }
-                std::cmp::Ordering::Less => {
-                    merged.push(a);
-                    i += 1;
-                }
-                std::cmp::Ordering::Greater => {
-                    merged.push(b);
-                    j += 1;
-                }
-            }
-
-            if merged.len() > MAX_VALUES {
-                return Self::unknown();
-            }
-        }
-
-        while i < self.values.len() {
-            merged.push(self.values[i]);
-            i += 1;
-            if merged.len() > MAX_VALUES {
-                return Self::unknown();
-            }
-        }
-
-        while j < other.values.len() {
-            merged.push(other.values[j]);
-            j += 1;
-            if merged.len() > MAX_VALUES {
-                return Self::unknown();
-            }
-        }
-
-        Self {
-            kind: ValueKind::Constant,
-            values: merged,
-        }
-    }
-}
+use super::value::RegisterValue;
+use super::{NUM_REGS, extract_written_reg};
 
 // TODO: make const generic
 #[derive(Clone, Debug)]
@@ -163,14 +56,6 @@ impl RegisterState {
         self.regs[idx] = value;
     }
 
-    pub(super) fn set_constant(&mut self, reg: u8, value: u64) {
-        self.set(reg, RegisterValue::constant(value));
-    }
-
-    pub(super) fn set_unknown(&mut self, reg: u8) {
-        self.set(reg, RegisterValue::unknown());
-    }
-
     pub(super) fn merge(&mut self, other: &Self) -> bool {
         let mut changed = false;
         for idx in 1..NUM_REGS {
@@ -197,6 +82,22 @@ pub(super) enum InstrKind {
     Jalr,
     Load,
     Branch,
+    /// `rd = rs1 << imm`.
+    ShiftLeft,
+    /// `rd = rs1 >> imm` (logical).
+    ShiftRight,
+    /// `rd = (rs1 << imm) + rs2` (Zba `shNadd`).
+    ShiftAdd,
+}
+
+/// Unsigned branch comparisons, the ones that bound a switch index; other
+/// branches do not narrow register values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum UnsignedCmp {
+    /// `bltu`: taken if `rs1 < rs2`.
+    Ltu,
+    /// `bgeu`: taken if `rs1 >= rs2`.
+    Geu,
 }
 
 // TODO: use elaborate rust enums
@@ -210,6 +111,11 @@ pub(super) struct DecodedInstruction {
     // Load width in bytes when `kind == Load` (0 for non-load ops).
     pub(super) load_width_bytes: u8,
     pub(super) is_unsigned: bool,
+    /// Comparison of an unsigned branch (`kind == Branch`).
+    pub(super) unsigned_cmp: Option<UnsignedCmp>,
+    /// Registers written with untracked values besides `rd`, a bit per
+    /// register (Zcmp pops restore whole register lists).
+    pub(super) clobbers: u32,
 }
 
 impl DecodedInstruction {
@@ -222,6 +128,18 @@ impl DecodedInstruction {
             imm: 0,
             load_width_bytes: 0,
             is_unsigned: false,
+            unsigned_cmp: None,
+            clobbers: 0,
+        }
+    }
+
+    /// `ret`: `jalr zero, 0(ra)`.
+    pub(super) const fn ret() -> Self {
+        Self {
+            kind: InstrKind::Jalr,
+            rd: Some(REG_ZERO),
+            rs1: Some(REG_RA),
+            ..Self::unknown()
         }
     }
 
@@ -238,10 +156,25 @@ impl DecodedInstruction {
             OP_JAL | OP_C_J | OP_C_JAL => Self::decode_j(instr, InstrKind::Jal),
             OP_JALR | OP_C_JR | OP_C_JALR => Self::decode_i(instr, InstrKind::Jalr),
             OP_LB | OP_LBU | OP_LH | OP_LHU | OP_LW | OP_LWU | OP_LD | OP_C_LW | OP_C_LWSP
-            | OP_C_LD | OP_C_LDSP => Self::decode_load(opid, instr),
+            | OP_C_LD | OP_C_LDSP | OP_C_LBU | OP_C_LHU | OP_C_LH => Self::decode_load(opid, instr),
             OP_BEQ | OP_BNE | OP_BLT | OP_BGE | OP_BLTU | OP_BGEU | OP_C_BEQZ | OP_C_BNEZ => {
-                Self::decode_branch(instr)
+                Self::decode_branch(opid, instr)
             }
+            OP_SLLI | OP_C_SLLI => Self::decode_i(instr, InstrKind::ShiftLeft),
+            OP_SRLI | OP_C_SRLI => Self::decode_i(instr, InstrKind::ShiftRight),
+            OP_SH1ADD | OP_SH2ADD | OP_SH3ADD => Self::decode_shift_add(opid, instr),
+            // Return through the `ra` the pop just restored
+            OP_CM_POPRET | OP_CM_POPRETZ => Self {
+                kind: InstrKind::Jalr,
+                rd: Some(REG_ZERO),
+                rs1: Some(REG_RA),
+                clobbers: zcmp_written_regs(opid, &instr.args),
+                ..Self::unknown()
+            },
+            _ if opid.ext == EXT_ZCMP => Self {
+                clobbers: zcmp_written_regs(opid, &instr.args),
+                ..Self::unknown()
+            },
             _ => {
                 let rd = extract_written_reg(&instr.args);
                 let mut decoded = Self::unknown();
@@ -261,6 +194,8 @@ impl DecodedInstruction {
                 imm,
                 load_width_bytes: 0,
                 is_unsigned: false,
+                unsigned_cmp: None,
+                clobbers: 0,
             },
             _ => Self::unknown(),
         }
@@ -276,6 +211,8 @@ impl DecodedInstruction {
                 imm,
                 load_width_bytes: 0,
                 is_unsigned: false,
+                unsigned_cmp: None,
+                clobbers: 0,
             },
             _ => Self::unknown(),
         }
@@ -291,6 +228,8 @@ impl DecodedInstruction {
                 imm: 0,
                 load_width_bytes: 0,
                 is_unsigned: false,
+                unsigned_cmp: None,
+                clobbers: 0,
             },
             _ => Self::unknown(),
         }
@@ -306,6 +245,8 @@ impl DecodedInstruction {
                 imm: 0,
                 load_width_bytes: 0,
                 is_unsigned: false,
+                unsigned_cmp: None,
+                clobbers: 0,
             },
             _ => Self::unknown(),
         }
@@ -321,6 +262,8 @@ impl DecodedInstruction {
                 imm,
                 load_width_bytes: 0,
                 is_unsigned: false,
+                unsigned_cmp: None,
+                clobbers: 0,
             },
             _ => Self::unknown(),
         }
@@ -331,9 +274,9 @@ impl DecodedInstruction {
             InstrArgs::I { rd, rs1, imm } => {
                 let (load_width_bytes, is_unsigned) = match opid {
                     OP_LB => (1, false),
-                    OP_LBU => (1, true),
-                    OP_LH => (2, false),
-                    OP_LHU => (2, true),
+                    OP_LBU | OP_C_LBU => (1, true),
+                    OP_LH | OP_C_LH => (2, false),
+                    OP_LHU | OP_C_LHU => (2, true),
                     OP_LW | OP_C_LW | OP_C_LWSP => (4, false),
                     OP_LWU => (4, true),
                     OP_LD | OP_C_LD | OP_C_LDSP => (8, false),
@@ -347,13 +290,32 @@ impl DecodedInstruction {
                     imm,
                     load_width_bytes,
                     is_unsigned,
+                    unsigned_cmp: None,
+                    clobbers: 0,
                 }
             }
             _ => Self::unknown(),
         }
     }
 
-    fn decode_branch<X: Xlen>(instr: &DecodedInstr<X>) -> Self {
+    fn decode_shift_add<X: Xlen>(opid: OpId, instr: &DecodedInstr<X>) -> Self {
+        let shamt = match opid {
+            OP_SH1ADD => 1,
+            OP_SH2ADD => 2,
+            _ => 3,
+        };
+        Self {
+            imm: shamt,
+            ..Self::decode_r(instr, InstrKind::ShiftAdd)
+        }
+    }
+
+    fn decode_branch<X: Xlen>(opid: OpId, instr: &DecodedInstr<X>) -> Self {
+        let unsigned_cmp = match opid {
+            OP_BLTU => Some(UnsignedCmp::Ltu),
+            OP_BGEU => Some(UnsignedCmp::Geu),
+            _ => None,
+        };
         match instr.args.clone() {
             InstrArgs::B { rs1, rs2, imm } => Self {
                 kind: InstrKind::Branch,
@@ -363,6 +325,8 @@ impl DecodedInstruction {
                 imm,
                 load_width_bytes: 0,
                 is_unsigned: false,
+                unsigned_cmp,
+                clobbers: 0,
             },
             _ => Self::unknown(),
         }
@@ -375,6 +339,12 @@ impl DecodedInstruction {
         )
     }
 
+    /// Registers in [`Self::clobbers`].
+    pub(super) fn clobbered(&self) -> impl Iterator<Item = u8> {
+        let clobbers = self.clobbers;
+        (0..u8::try_from(NUM_REGS).unwrap_or(u8::MAX)).filter(move |reg| clobbers & (1 << reg) != 0)
+    }
+
     pub(super) fn is_static_call(&self) -> bool {
         // TODO: explain why rd != check
         // `jal x0, ...` is an unconditional jump; non-zero rd means link register is written.
@@ -4,12 +4,12 @@
 // Flow: collect candidate targets -> propagate register facts -> emit CFG relations.
 
 use rayon::prelude::*;
-use rustc_hash::{FxBuildHasher, FxHashMap, FxHashSet};
-use tracing::{debug, trace, trace_span};
+use rustc_hash::{FxHashMap, FxHashSet};
+use tracing::{debug, trace_span};
 
 use rvr_isa::{InstrArgs, Xlen};
 
-use crate::InstructionTable;
+use crate::{InstructionTable, ParallelTime};
 
 // TODO: derive for I or E
 const NUM_REGS: usize = 32;
@@ -21,8 +21,12 @@ const MAX_ITERATIONS_MULTIPLIER: usize = 20;
 const MAX_JUMP_TABLE_SCAN: usize = 256;
 
 mod data;
+mod transfer;
+mod value;
+mod worklist;
 
-use data::{DecodedInstruction, InstrKind, RegisterState, RegisterValue};
+use data::{DecodedInstruction, InstrKind, RegisterState};
+use worklist::WorklistContext;
 
 // TODO: explain each member
 /// Result of CFG discovery for one instruction table.
@@ -33,12 +37,17 @@ pub struct ControlFlowResult {
     pub predecessors: FxHashMap<u64, FxHashSet<u64>>,
     /// Indirect jumps that could not be resolved to concrete targets.
     pub unresolved_dynamic_jumps: FxHashSet<u64>,
+    /// Indirect jumps whose targets value propagation proved (a constant
+    /// base or a bounds-checked jump table): jump PC -> sorted targets.
+    pub resolved_dynamic_jumps: FxHashMap<u64, Vec<u64>>,
     /// Basic block leaders.
     pub leaders: FxHashSet<u64>,
     /// Callee entry -> potential return sites.
     pub call_return_map: FxHashMap<u64, FxHashSet<u64>>,
     /// Block leader -> owning function entry.
     pub block_to_function: FxHashMap<u64, u64>,
+    /// Time spent in the per-function worklists.
+    pub time: ParallelTime,
 }
 
 // TODO: does this need to be a struct
@@ -48,7 +57,7 @@ pub struct ControlFlowAnalyzer;
 impl ControlFlowAnalyzer {
     pub fn analyze<X: Xlen>(instruction_table: &InstructionTable<X>) -> ControlFlowResult {
         // TODO: avoid multiple linear scans - use some iterator abstraction?
-        let (function_entries, internal_targets, return_sites) = {
+        let (function_entries, internal_targets, return_sites, fallback_seeds) = {
             let _span = trace_span!("collect_targets").entered();
             collect_potential_targets(instruction_table)
         };
@@ -73,18 +82,23 @@ impl ControlFlowAnalyzer {
             }
         }
 
-        let (successors, unresolved_dynamic_jumps) = {
+        let worklist = {
             let _span = trace_span!("worklist").entered();
-            worklist(
+            WorklistContext {
                 instruction_table,
-                &function_entries,
-                &internal_targets,
-                &return_sites,
-                &sorted_function_entries,
-                &func_internal_targets,
-                &call_return_map,
-            )
+                function_entries: &function_entries,
+                internal_targets: &internal_targets,
+                return_sites: &return_sites,
+                fallback_seeds: &fallback_seeds,
+                sorted_function_entries: &sorted_function_entries,
+                func_internal_targets: &func_internal_targets,
+                call_return_map: &call_return_map,
+            }
+            .run()
         };
+        let successors = worklist.successors;
+        let unresolved_dynamic_jumps = worklist.unresolved_dynamic_jumps;
+        let resolved_dynamic_jumps = worklist.resolved_dynamic_jumps;
 
         let leaders = {
             let _span = trace_span!("compute_leaders").entered();
@@ -102,6 +116,7 @@ impl ControlFlowAnalyzer {
         debug!(
             functions = function_entries.len(),
             leaders = leaders.len(),
+            resolved = resolved_dynamic_jumps.len(),
             unresolved = unresolved_dynamic_jumps.len(),
             "CFG analysis complete"
         );
@@ -126,19 +141,29 @@ impl ControlFlowAnalyzer {
             successors,
             predecessors,
             unresolved_dynamic_jumps,
+            resolved_dynamic_jumps,
             leaders,
             call_return_map,
             block_to_function,
+            time: worklist.time,
         }
     }
 }
 
+/// Function entries, internal targets, return sites, and the internal
+/// targets only branches reach (see [`WorklistContext::fallback_seeds`]).
 fn collect_potential_targets<X: Xlen>(
     instruction_table: &InstructionTable<X>,
-) -> (FxHashSet<u64>, FxHashSet<u64>, FxHashSet<u64>) {
+) -> (
+    FxHashSet<u64>,
+    FxHashSet<u64>,
+    FxHashSet<u64>,
+    FxHashSet<u64>,
+) {
     let mut function_entries = FxHashSet::default();
     let mut internal_targets = FxHashSet::default();
     let mut return_sites = FxHashSet::default();
+    let mut branch_targets = FxHashSet::default();
 
     // Add all entry points (ELF entry + any library exports)
     function_entries.extend(instruction_table.entry_points().iter().copied());
@@ -156,9 +181,26 @@ fn collect_potential_targets<X: Xlen>(
         &mut function_entries,
         &mut internal_targets,
         &mut return_sites,
+        &mut branch_targets,
     );
 
-    (function_entries, internal_targets, return_sites)
+    let fallback_seeds: FxHashSet<u64> = branch_targets
+        .iter()
+        .copied()
+        .filter(|pc| {
+            !internal_targets.contains(pc)
+                && !function_entries.contains(pc)
+                && !return_sites.contains(pc)
+        })
+        .collect();
+    internal_targets.extend(branch_targets);
+
+    (
+        function_entries,
+        internal_targets,
+        return_sites,
+        fallback_seeds,
+    )
 }
 
 fn scan_instruction_targets<X: Xlen>(
@@ -167,12 +209,14 @@ fn scan_instruction_targets<X: Xlen>(
     function_entries: &mut FxHashSet<u64>,
     internal_targets: &mut FxHashSet<u64>,
     return_sites: &mut FxHashSet<u64>,
+    branch_targets: &mut FxHashSet<u64>,
 ) {
     let mut context = TargetScanContext {
         instruction_table,
         function_entries,
         internal_targets,
         return_sites,
+        branch_targets,
     };
     let mut pc = instruction_table.base_address();
     let end = instruction_table.end_address();
@@ -191,12 +235,16 @@ fn scan_instruction_targets<X: Xlen>(
             continue;
         }
 
-        let Some(instr) = instruction_table.get_at_pc(pc) else {
+        // A hooked body's branches and calls are never reached through it
+        let Some(instr) = instruction_table
+            .get_at_pc(pc)
+            .filter(|_| !instruction_table.in_hooked_body(pc))
+        else {
             pc += size;
             continue;
         };
 
-        let decoded = DecodedInstruction::from_instr(instr);
+        let decoded = decode_at(instruction_table, pc, instr);
         update_targets_for_decoded(&mut context, regs, pc, size, instr, &decoded);
 
         pc += size;
@@ -208,6 +256,7 @@ struct TargetScanContext<'a, X: Xlen> {
     function_entries: &'a mut FxHashSet<u64>,
     internal_targets: &'a mut FxHashSet<u64>,
     return_sites: &'a mut FxHashSet<u64>,
+    branch_targets: &'a mut FxHashSet<u64>,
 }
 
 fn update_targets_for_decoded<X: Xlen>(
@@ -218,6 +267,9 @@ fn update_targets_for_decoded<X: Xlen>(
     instr: &rvr_isa::DecodedInstr<X>,
     decoded: &DecodedInstruction,
 ) {
+    for reg in decoded.clobbered() {
+        regs[reg as usize] = None;
+    }
     match decoded.kind {
         InstrKind::Lui => handle_lui(regs, decoded),
         InstrKind::Auipc => handle_auipc(regs, decoded, pc),
@@ -228,7 +280,9 @@ fn update_targets_for_decoded<X: Xlen>(
         InstrKind::Jal => handle_jal(regs, decoded, context, pc, size),
         InstrKind::Jalr => handle_jalr(regs, decoded, context, pc, size),
         InstrKind::Branch => handle_branch(decoded, context, pc, size),
-        InstrKind::Unknown => handle_unknown(regs, instr),
+        InstrKind::Unknown | InstrKind::ShiftLeft | InstrKind::ShiftRight | InstrKind::ShiftAdd => {
+            handle_unknown(regs, instr);
+        }
     }
 }
 
@@ -366,9 +420,9 @@ fn handle_branch<X: Xlen>(
 ) {
     let target = add_signed(pc, decoded.imm);
     if context.instruction_table.is_valid_pc(target) {
-        context.internal_targets.insert(target);
+        context.branch_targets.insert(target);
     }
-    context.internal_targets.insert(pc + size);
+    context.branch_targets.insert(pc + size);
 }
 
 const fn handle_unknown<X: Xlen>(
@@ -380,6 +434,20 @@ const fn handle_unknown<X: Xlen>(
     }
 }
 
+/// Decode `instr` at `pc` for analysis. A hooked function's entry is
+/// lifted as its hook and a return, so it decodes as `ret`.
+fn decode_at<X: Xlen>(
+    instruction_table: &InstructionTable<X>,
+    pc: u64,
+    instr: &rvr_isa::DecodedInstr<X>,
+) -> DecodedInstruction {
+    if instruction_table.is_hooked_entry(pc) {
+        DecodedInstruction::ret()
+    } else {
+        DecodedInstruction::from_instr(instr)
+    }
+}
+
 fn build_call_return_map<X: Xlen>(
     instruction_table: &InstructionTable<X>,
 ) -> FxHashMap<u64, FxHashSet<u64>> {
@@ -401,11 +469,14 @@ fn build_call_return_map<X: Xlen>(
             continue;
         }
 
-        let Some(instr) = instruction_table.get_at_pc(pc) else {
+        let Some(instr) = instruction_table
+            .get_at_pc(pc)
+            .filter(|_| !instruction_table.in_hooked_body(pc))
+        else {
             pc += size;
             continue;
         };
-        let decoded = DecodedInstruction::from_instr(instr);
+        let decoded = decode_at(instruction_table, pc, instr);
 
         if decoded.is_static_call() {
             let callee = add_signed(pc, decoded.imm);
@@ -420,113 +491,6 @@ fn build_call_return_map<X: Xlen>(
     call_return_map
 }
 
-fn worklist<X: Xlen>(
-    instruction_table: &InstructionTable<X>,
-    function_entries: &FxHashSet<u64>,
-    internal_targets: &FxHashSet<u64>,
-    return_sites: &FxHashSet<u64>,
-    sorted_function_entries: &[u64],
-    func_internal_targets: &FxHashMap<u64, FxHashSet<u64>>,
-    call_return_map: &FxHashMap<u64, FxHashSet<u64>>,
-) -> (FxHashMap<u64, FxHashSet<u64>>, FxHashSet<u64>) {
-    // Pre-allocate with estimated capacity to reduce rehashing
-    let estimated_size = function_entries.len() + internal_targets.len();
-    let mut states: FxHashMap<u64, RegisterState> =
-        FxHashMap::with_capacity_and_hasher(estimated_size, FxBuildHasher);
-    let mut worklist = Vec::with_capacity(estimated_size);
-    let mut in_worklist: FxHashSet<u64> =
-        FxHashSet::with_capacity_and_hasher(estimated_size, FxBuildHasher);
-    let mut successors: FxHashMap<u64, FxHashSet<u64>> =
-        FxHashMap::with_capacity_and_hasher(estimated_size, FxBuildHasher);
-    let mut unresolved_dynamic_jumps: FxHashSet<u64> = FxHashSet::default();
-
-    // TODO: shouldn't this only be entry points and not all functions
-    // Add all entry points to worklist
-    for addr in function_entries {
-        if in_worklist.insert(*addr) {
-            states.insert(*addr, RegisterState::new());
-            worklist.push(*addr);
-        }
-    }
-
-    // TODO: why do worklist from here too?
-    for addr in internal_targets {
-        if in_worklist.insert(*addr) {
-            states.insert(*addr, RegisterState::new());
-            worklist.push(*addr);
-        }
-    }
-
-    // TODO: maybe this should be a function
-    let span = instruction_table.end_address() - instruction_table.base_address();
-    let max_iterations = usize::try_from(span)
-        .unwrap_or(usize::MAX / MAX_ITERATIONS_MULTIPLIER)
-        .saturating_mul(MAX_ITERATIONS_MULTIPLIER);
-
-    // TODO: more idiomatic rust
-    let mut idx = 0;
-    while idx < worklist.len() {
-        if idx > max_iterations {
-            break;
-        }
-
-        let pc = worklist[idx];
-        idx += 1;
-        in_worklist.remove(&pc);
-
-        let state = match states.get(&pc) {
-            Some(state) => state.clone(),
-            None => continue,
-        };
-
-        let size = u64::from(instruction_table.instruction_size_at_pc(pc));
-        if size == 0 {
-            continue;
-        }
-
-        let Some(instr) = instruction_table.get_at_pc(pc) else {
-            continue;
-        };
-        let decoded = DecodedInstruction::from_instr(instr);
-
-        let succs = get_successors(
-            instruction_table,
-            pc,
-            size,
-            &decoded,
-            &state,
-            function_entries,
-            return_sites,
-            sorted_function_entries,
-            func_internal_targets,
-            call_return_map,
-            &mut unresolved_dynamic_jumps,
-        );
-
-        // TODO: should probably consume state
-        let state_out = transfer(instruction_table, pc, size, &decoded, state);
-
-        for target in &succs {
-            if let Some(existing) = states.get_mut(target) {
-                if existing.merge(&state_out) && in_worklist.insert(*target) {
-                    worklist.push(*target);
-                }
-            } else {
-                states.insert(*target, state_out.clone());
-                if in_worklist.insert(*target) {
-                    worklist.push(*target);
-                }
-            }
-        }
-
-        successors.entry(pc).or_default().extend(succs);
-    }
-
-    trace!(iterations = idx, "worklist complete");
-
-    (successors, unresolved_dynamic_jumps)
-}
-
 // Many parameters needed for inter-procedural analysis context
 // TODO: remvove unnecessary clippy allows
 #[allow(clippy::too_many_arguments)]
@@ -567,7 +531,7 @@ fn scan_jump_table_targets<X: Xlen>(
         // This instruction is a valid jump target
         targets.insert(pc);
 
-        let decoded = DecodedInstruction::from_instr(instr);
+        let decoded = decode_at(instruction_table, pc, instr);
 
         // Stop at terminators
         if decoded.is_return() {
@@ -593,6 +557,28 @@ fn scan_jump_table_targets<X: Xlen>(
     targets
 }
 
+/// Valid targets of a `jalr` whose base register has known values (a
+/// constant, or every entry of a bounded jump table).
+fn jalr_targets<X: Xlen>(
+    instruction_table: &InstructionTable<X>,
+    decoded: &DecodedInstruction,
+    state: &RegisterState,
+) -> Option<Vec<u64>> {
+    let rs1 = decoded.rs1?;
+    // The instruction overwrites its base first (`cm.popret` restores `ra`)
+    if decoded.clobbered().any(|reg| reg == rs1) {
+        return None;
+    }
+    let bases = state.get_ref(rs1).possible_values()?;
+    Some(
+        bases
+            .into_iter()
+            .map(|base| add_signed(base, decoded.imm) & !1u64)
+            .filter(|&target| instruction_table.is_valid_pc(target))
+            .collect(),
+    )
+}
+
 // TODO: can this be encapsulated or split
 #[allow(clippy::too_many_arguments)]
 fn get_successors<X: Xlen>(
@@ -622,19 +608,11 @@ fn get_successors<X: Xlen>(
         }
         InstrKind::Jalr => {
             let mut resolved = false;
-            if let Some(rs1) = decoded.rs1 {
-                let base = state.get_ref(rs1);
-                if base.is_constant() && !base.values.is_empty() {
-                    for value in &base.values {
-                        let target = add_signed(*value, decoded.imm) & !1u64;
-                        if instruction_table.is_valid_pc(target) {
-                            result.insert(target);
-                        }
-                    }
-                    resolved = true;
-                    if decoded.is_call() {
-                        result.insert(pc + size);
-                    }
+            if let Some(targets) = jalr_targets(instruction_table, decoded, state) {
+                result.extend(targets);
+                resolved = true;
+                if decoded.is_call() {
+                    result.insert(pc + size);
                 }
             }
 
@@ -694,116 +672,6 @@ fn get_successors<X: Xlen>(
     result
 }
 
-fn transfer<X: Xlen>(
-    instruction_table: &InstructionTable<X>,
-    pc: u64,
-    size: u64,
-    decoded: &DecodedInstruction,
-    mut state: RegisterState,
-) -> RegisterState {
-    match decoded.kind {
-        InstrKind::Lui => {
-            if let Some(rd) = decoded.rd {
-                state.set_constant(rd, sign_extend_i32(decoded.imm));
-            }
-        }
-        InstrKind::Auipc => {
-            if let Some(rd) = decoded.rd {
-                state.set_constant(rd, add_signed(pc, decoded.imm));
-            }
-        }
-        InstrKind::Addi => {
-            if let (Some(rd), Some(rs1)) = (decoded.rd, decoded.rs1) {
-                let base = state.get(rs1);
-                // TODO: avoid the  empty check
-                if base.is_constant() && !base.values.is_empty() {
-                    let mut result =
-                        RegisterValue::constant(add_signed(base.values[0], decoded.imm));
-                    for value in base.values.iter().skip(1) {
-                        result.add_value(add_signed(*value, decoded.imm));
-                        if !result.is_constant() {
-                            break;
-                        }
-                    }
-                    state.set(rd, result);
-                } else {
-                    state.set_unknown(rd);
-                }
-            }
-        }
-        InstrKind::Add => {
-            if let (Some(rd), Some(rs1), Some(rs2)) = (decoded.rd, decoded.rs1, decoded.rs2) {
-                let lhs = state.get(rs1);
-                let rhs = state.get(rs2);
-                // TODO: should be some form of match without the is empty check
-                if lhs.is_constant()
-                    && rhs.is_constant()
-                    && !lhs.values.is_empty()
-                    && !rhs.values.is_empty()
-                {
-                    let mut result =
-                        RegisterValue::constant(lhs.values[0].wrapping_add(rhs.values[0]));
-                    'outer: for l in &lhs.values {
-                        for r in &rhs.values {
-                            if l == &lhs.values[0] && r == &rhs.values[0] {
-                                continue;
-                            }
-                            result.add_value(l.wrapping_add(*r));
-                            if !result.is_constant() {
-                                break 'outer;
-                            }
-                        }
-                    }
-                    state.set(rd, result);
-                } else {
-                    state.set_unknown(rd);
-                }
-            }
-        }
-        InstrKind::Move => {
-            if let (Some(rd), Some(rs1)) = (decoded.rd, decoded.rs1) {
-                let value = state.get(rs1);
-                state.set(rd, value);
-            }
-        }
-        InstrKind::Load => {
-            if let (Some(rd), Some(rs1)) = (decoded.rd, decoded.rs1) {
-                let base = state.get(rs1);
-                let mut resolved = false;
-                // TODO: why != 2 check, explain what's happening here
-                // Ignore SP-relative loads for readonly constant propagation: stack values are
-                // runtime-dependent and create many false positives for code-pointer recovery.
-                if rs1 != 2 && base.is_constant() && !base.values.is_empty() {
-                    let addr = add_signed(base.values[0], decoded.imm);
-                    if let Some(raw) =
-                        instruction_table.read_readonly(addr, decoded.load_width_bytes as usize)
-                    {
-                        let extended =
-                            extend_loaded_value(raw, decoded.load_width_bytes, decoded.is_unsigned);
-                        state.set_constant(rd, extended);
-                        resolved = true;
-                    }
-                }
-                if !resolved {
-                    state.set_unknown(rd);
-                }
-            }
-        }
-        InstrKind::Jal | InstrKind::Jalr => {
-            if let Some(rd) = decoded.rd {
-                state.set_constant(rd, pc + size);
-            }
-        }
-        _ => {
-            if let Some(rd) = decoded.rd {
-                state.set_unknown(rd);
-            }
-        }
-    }
-
-    state
-}
-
 fn compute_leaders<X: Xlen>(
     instruction_table: &InstructionTable<X>,
     successors: &FxHashMap<u64, FxHashSet<u64>>,
@@ -824,11 +692,12 @@ fn compute_leaders<X: Xlen>(
             continue;
         }
         if let Some(instr) = instruction_table.get_at_pc(pc) {
-            let decoded = DecodedInstruction::from_instr(instr);
+            let decoded = decode_at(instruction_table, pc, instr);
             if decoded.is_control_flow() {
                 leaders.extend(succs.iter().copied());
                 let next_pc = pc + size;
-                if instruction_table.is_valid_pc(next_pc) {
+                if instruction_table.is_valid_pc(next_pc) && !instruction_table.is_hooked_entry(pc)
+                {
                     leaders.insert(next_pc);
                 }
             }
@@ -0,0 +1,101 @@
+//! Register-state transfer through one instruction, and along branch edges.
+
+use rvr_isa::Xlen;
+
+use super::data::{DecodedInstruction, InstrKind, RegisterState, UnsignedCmp};
+use super::value::RegisterValue;
+use super::{add_signed, extend_loaded_value, sign_extend_i32};
+use crate::InstructionTable;
+
+/// Stack pointer register.
+const REG_SP: u8 = 2;
+
+/// State after the instruction at `pc`.
+pub(super) fn transfer<X: Xlen>(
+    instruction_table: &InstructionTable<X>,
+    pc: u64,
+    size: u64,
+    decoded: &DecodedInstruction,
+    mut state: RegisterState,
+) -> RegisterState {
+    for reg in decoded.clobbered() {
+        state.set(reg, RegisterValue::unknown());
+    }
+    let Some(rd) = decoded.rd else {
+        return state;
+    };
+    let rs1 = decoded.rs1.map(|r| state.get(r));
+    let rs2 = decoded.rs2.map(|r| state.get(r));
+    let value = match (decoded.kind, rs1, rs2) {
+        (InstrKind::Lui, ..) => RegisterValue::constant(sign_extend_i32(decoded.imm)),
+        (InstrKind::Auipc, ..) => RegisterValue::constant(add_signed(pc, decoded.imm)),
+        (InstrKind::Addi, Some(base), _) => base.offset(sign_extend_i32(decoded.imm)),
+        (InstrKind::Add, Some(lhs), Some(rhs)) => lhs.add(&rhs),
+        (InstrKind::Move, Some(value), _) => value,
+        (InstrKind::ShiftLeft, Some(value), _) => value.shl(decoded.imm.cast_unsigned()),
+        (InstrKind::ShiftRight, Some(value), _) => value.shr(decoded.imm.cast_unsigned()),
+        (InstrKind::ShiftAdd, Some(lhs), Some(rhs)) => {
+            lhs.shl(decoded.imm.cast_unsigned()).add(&rhs)
+        }
+        // Ignore SP-relative loads for readonly constant propagation: stack values are
+        // runtime-dependent and create many false positives for code-pointer recovery.
+        (InstrKind::Load, Some(base), _) if decoded.rs1 != Some(REG_SP) => {
+            load_readonly(instruction_table, decoded, &base)
+        }
+        (InstrKind::Jal | InstrKind::Jalr, ..) => RegisterValue::constant(pc + size),
+        _ => RegisterValue::unknown(),
+    };
+    state.set(rd, value);
+    state
+}
+
+/// Load through every address `base` can hold; known only if all of them
+/// are in read-only data (a jump table read through a bounded index).
+fn load_readonly<X: Xlen>(
+    instruction_table: &InstructionTable<X>,
+    decoded: &DecodedInstruction,
+    base: &RegisterValue,
+) -> RegisterValue {
+    let width = decoded.load_width_bytes;
+    let loaded: Option<Vec<u64>> = base.possible_values().and_then(|addrs| {
+        addrs
+            .into_iter()
+            .map(|addr| {
+                let addr = add_signed(addr, decoded.imm);
+                instruction_table
+                    .read_readonly(addr, usize::from(width))
+                    .map(|raw| extend_loaded_value(raw, width, decoded.is_unsigned))
+            })
+            .collect()
+    });
+    loaded.map_or_else(RegisterValue::unknown, RegisterValue::from_values)
+}
+
+/// State along the edge of the branch at `pc` to `target`, if the branch
+/// narrows it: an unsigned compare against a constant bounds the other
+/// operand on one side (`bltu idx, n` / `bgeu n, idx` for `idx < n`, and
+/// `bgeu idx, n` / `bltu n, idx` on the fall-through side).
+pub(super) fn branch_edge_state(
+    decoded: &DecodedInstruction,
+    pc: u64,
+    size: u64,
+    state: &RegisterState,
+    target: u64,
+) -> Option<RegisterState> {
+    let cmp = decoded.unsigned_cmp?;
+    let (rs1, rs2) = (decoded.rs1?, decoded.rs2?);
+    let taken_target = add_signed(pc, decoded.imm);
+    if taken_target == pc + size {
+        return None;
+    }
+    // Whether `rs1 < rs2` holds along this edge (else `rs1 >= rs2` does)
+    let less = (cmp == UnsignedCmp::Ltu) == (target == taken_target);
+    let (reg, bound) = if less {
+        (rs1, state.get_ref(rs2).single()?)
+    } else {
+        (rs2, state.get_ref(rs1).single()?.checked_add(1)?)
+    };
+    let mut refined = state.clone();
+    refined.set(reg, state.get_ref(reg).bounded_below(bound));
+    Some(refined)
+}
@@ -0,0 +1,348 @@
+//! Abstract register values: small constant sets and strided index ranges.
+//!
+//! A range comes from an unsigned bounds check (`bltu`/`bgeu` against a
+//! constant) and survives the shifts and adds that scale it into a table
+//! address, so a load from read-only data through it yields every entry of
+//! a jump table.
+
+use super::MAX_VALUES;
+
+/// Most entries a range may cover. Switches bounded above this keep their
+/// dynamic dispatch.
+pub(super) const MAX_RANGE_ENTRIES: u64 = 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ValueKind {
+    Unknown,
+    Constant,
+    /// `base + i * stride` for `i` in `0..count`.
+    Strided {
+        base: u64,
+        stride: u64,
+        count: u64,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) struct RegisterValue {
+    kind: ValueKind,
+    /// Sorted, deduplicated values of a constant (empty otherwise).
+    pub(super) values: Vec<u64>,
+}
+
+impl RegisterValue {
+    pub(super) const fn unknown() -> Self {
+        Self {
+            kind: ValueKind::Unknown,
+            values: Vec::new(),
+        }
+    }
+
+    pub(super) fn constant(value: u64) -> Self {
+        Self {
+            kind: ValueKind::Constant,
+            values: vec![value],
+        }
+    }
+
+    /// A constant taking any of `values`. Not capped at `MAX_VALUES`: a
+    /// jump table load yields one value per entry.
+    pub(super) fn from_values(mut values: Vec<u64>) -> Self {
+        if values.is_empty() {
+            return Self::unknown();
+        }
+        values.sort_unstable();
+        values.dedup();
+        Self {
+            kind: ValueKind::Constant,
+            values,
+        }
+    }
+
+    /// An index known to be below `bound`.
+    const fn below(bound: u64) -> Self {
+        if bound == 0 || bound > MAX_RANGE_ENTRIES {
+            return Self::unknown();
+        }
+        Self {
+            kind: ValueKind::Strided {
+                base: 0,
+                stride: 1,
+                count: bound,
+            },
+            values: Vec::new(),
+        }
+    }
+
+    pub(super) fn is_constant(&self) -> bool {
+        self.kind == ValueKind::Constant
+    }
+
+    /// The only value, if there is exactly one.
+    pub(super) fn single(&self) -> Option<u64> {
+        match self.values.as_slice() {
+            [value] if self.is_constant() => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Every value this register can take, if known.
+    pub(super) fn possible_values(&self) -> Option<Vec<u64>> {
+        match self.kind {
+            ValueKind::Unknown => None,
+            ValueKind::Constant => Some(self.values.clone()),
+            ValueKind::Strided {
+                base,
+                stride,
+                count,
+            } => Some(
+                (0..count)
+                    .map(|i| base.wrapping_add(i.wrapping_mul(stride)))
+                    .collect(),
+            ),
+        }
+    }
+
+    pub(super) fn add_value(&mut self, value: u64) {
+        if self.kind != ValueKind::Constant {
+            return;
+        }
+
+        match self.values.binary_search(&value) {
+            Ok(_) => {}
+            Err(idx) => {
+                // Keep constants sorted+deduplicated; degrade to Unknown if the set explodes.
+                if self.values.len() >= MAX_VALUES {
+                    self.kind = ValueKind::Unknown;
+                    self.values.clear();
+                } else {
+                    self.values.insert(idx, value);
+                }
+            }
+        }
+    }
+
+    /// Apply `f` to every value of a constant.
+    fn map(&self, f: impl Fn(u64) -> u64) -> Self {
+        if self.is_constant() {
+            Self::from_values(self.values.iter().map(|&v| f(v)).collect())
+        } else {
+            Self::unknown()
+        }
+    }
+
+    /// `self + offset`.
+    pub(super) fn offset(&self, offset: u64) -> Self {
+        match self.kind {
+            ValueKind::Strided {
+                base,
+                stride,
+                count,
+            } => Self {
+                kind: ValueKind::Strided {
+                    base: base.wrapping_add(offset),
+                    stride,
+                    count,
+                },
+                values: Vec::new(),
+            },
+            _ => self.map(|v| v.wrapping_add(offset)),
+        }
+    }
+
+    /// `self + other`.
+    pub(super) fn add(&self, other: &Self) -> Self {
+        if let Some(value) = other.single() {
+            return self.offset(value);
+        }
+        if let Some(value) = self.single() {
+            return other.offset(value);
+        }
+        if !self.is_constant() || !other.is_constant() {
+            return Self::unknown();
+        }
+        let mut result = Self::constant(self.values[0].wrapping_add(other.values[0]));
+        'outer: for l in &self.values {
+            for r in &other.values {
+                result.add_value(l.wrapping_add(*r));
+                if !result.is_constant() {
+                    break 'outer;
+                }
+            }
+        }
+        result
+    }
+
+    /// `self << shamt`. A range only shifts if no set bit is shifted out.
+    pub(super) fn shl(&self, shamt: u32) -> Self {
+        match self.kind {
+            ValueKind::Strided {
+                base,
+                stride,
+                count,
+            } => {
+                let last = (count - 1)
+                    .checked_mul(stride)
+                    .and_then(|span| base.checked_add(span));
+                if last.is_none_or(|last| last.leading_zeros() < shamt) {
+                    return Self::unknown();
+                }
+                Self {
+                    kind: ValueKind::Strided {
+                        base: base << shamt,
+                        stride: stride << shamt,
+                        count,
+                    },
+                    values: Vec::new(),
+                }
+            }
+            _ => self.map(|v| v.wrapping_shl(shamt)),
+        }
+    }
+
+    /// `self >> shamt` (logical). A range only shifts if no set bit is
+    /// shifted out.
+    pub(super) fn shr(&self, shamt: u32) -> Self {
+        match self.kind {
+            ValueKind::Strided {
+                base,
+                stride,
+                count,
+            } => {
+                if base.trailing_zeros() < shamt || stride.trailing_zeros() < shamt {
+                    return Self::unknown();
+                }
+                Self {
+                    kind: ValueKind::Strided {
+                        base: base >> shamt,
+                        stride: stride >> shamt,
+                        count,
+                    },
+                    values: Vec::new(),
+                }
+            }
+            _ => self.map(|v| v.wrapping_shr(shamt)),
+        }
+    }
+
+    /// Narrow to the values below `bound` (unsigned), as on the edge of a
+    /// bounds check. An unknown value becomes the range `0..bound`.
+    pub(super) fn bounded_below(&self, bound: u64) -> Self {
+        match self.kind {
+            ValueKind::Unknown => Self::below(bound),
+            ValueKind::Constant => {
+                let values: Vec<u64> = self.values.iter().copied().filter(|&v| v < bound).collect();
+                // An edge no value can take is dead; keep the value as is.
+                if values.is_empty() {
+                    self.clone()
+                } else {
+                    Self::from_values(values)
+                }
+            }
+            ValueKind::Strided { .. } => self.clone(),
+        }
+    }
+
+    pub(super) fn merge(&self, other: &Self) -> Self {
+        if self == other {
+            return self.clone();
+        }
+        if !self.is_constant() || !other.is_constant() {
+            return Self::unknown();
+        }
+        // Jump table values may exceed `MAX_VALUES`; merging never grows a
+        // set past the larger input
+        let limit = MAX_VALUES.max(self.values.len()).max(other.values.len());
+
+        let mut merged = Vec::with_capacity(self.values.len() + other.values.len());
+        let mut i = 0;
+        let mut j = 0;
+
+        while i < self.values.len() && j < other.values.len() {
+            let a = self.values[i];
+            let b = other.values[j];
+            match a.cmp(&b) {
+                std::cmp::Ordering::Equal => {
+                    merged.push(a);
+                    i += 1;
+                    j += 1;
+                }
+                std::cmp::Ordering::Less => {
+                    merged.push(a);
+                    i += 1;
+                }
+                std::cmp::Ordering::Greater => {
+                    merged.push(b);
+                    j += 1;
+                }
+            }
+
+            if merged.len() > limit {
+                return Self::unknown();
+            }
+        }
+
+        merged.extend_from_slice(&self.values[i..]);
+        merged.extend_from_slice(&other.values[j..]);
+        if merged.len() > limit {
+            return Self::unknown();
+        }
+
+        Self {
+            kind: ValueKind::Constant,
+            values: merged,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TABLE: u64 = 0x8000_1000;
+    const CASES: u64 = 7;
+
+    #[test]
+    fn test_bounded_index_scales_to_table_addresses() {
+        // bltu a0, 7 -> a0 in 0..7; slli a0, a0, 2; add a0, a0, TABLE
+        let index = RegisterValue::unknown().bounded_below(CASES);
+        let addr = index.shl(2).add(&RegisterValue::constant(TABLE));
+        let addrs = addr.possible_values().unwrap();
+        assert_eq!(addrs.len(), 7);
+        assert_eq!(addrs[0], TABLE);
+        assert_eq!(addrs[6], TABLE + 24);
+    }
+
+    #[test]
+    fn test_zero_extend_and_scale() {
+        // slli a0, a0, 32; srli a0, a0, 30 scales by 4
+        let index = RegisterValue::unknown().bounded_below(CASES);
+        let scaled = index.shl(32).shr(30);
+        assert_eq!(scaled, index.shl(2));
+    }
+
+    #[test]
+    fn test_unbounded_ranges_stay_unknown() {
+        let index = RegisterValue::unknown().bounded_below(MAX_RANGE_ENTRIES + 1);
+        assert_eq!(index, RegisterValue::unknown());
+        // Bits shifted out lose the range
+        let wide = RegisterValue::unknown().bounded_below(CASES).shl(62);
+        assert_eq!(wide, RegisterValue::unknown());
+        let misaligned = RegisterValue::unknown().bounded_below(CASES).shr(1);
+        assert_eq!(misaligned, RegisterValue::unknown());
+    }
+
+    #[test]
+    fn test_merge_keeps_large_sets() {
+        let table = RegisterValue::unknown()
+            .bounded_below(MAX_RANGE_ENTRIES)
+            .possible_values()
+            .map(RegisterValue::from_values)
+            .unwrap();
+        assert_eq!(table.merge(&table), table);
+        assert_eq!(
+            table.merge(&RegisterValue::constant(MAX_RANGE_ENTRIES)),
+            RegisterValue::unknown()
+        );
+    }
+}
@@ -0,0 +1,320 @@
+//! Per-function register-state worklist.
+//!
+//! Each function region (from one function entry up to the next) runs its own
+//! worklist, in parallel with the others. Edges that leave a region are kept as
+//! successors, but register state does not flow along them: the target is
+//! seeded in its owning region with an unknown state instead. Function entries,
+//! internal targets and return sites are seeded that way from the start, which
+//! is what merging call/return edges converges to anyway. Targets only branches
+//! reach are seeded last, and only if propagation never got there, so a bounds
+//! check keeps its narrowed state into the code it guards. Any other
+//! cross-region target starts another round for its region, until no new seeds
+//! appear. Regions are re-run from scratch with sorted seeds, so the result does
+//! not depend on the number of threads.
+
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::Duration;
+
+use rayon::prelude::*;
+use rustc_hash::{FxBuildHasher, FxHashMap, FxHashSet};
+use tracing::trace;
+
+use rvr_isa::Xlen;
+
+use super::data::{DecodedInstruction, InstrKind, RegisterState};
+use super::transfer::{branch_edge_state, transfer};
+use super::{MAX_ITERATIONS_MULTIPLIER, binary_search_le, decode_at, get_successors, jalr_targets};
+use crate::{InstructionTable, ParallelTime, timed};
+
+/// Program-wide facts shared by every region.
+pub(super) struct WorklistContext<'a, X: Xlen> {
+    pub(super) instruction_table: &'a InstructionTable<X>,
+    pub(super) function_entries: &'a FxHashSet<u64>,
+    pub(super) internal_targets: &'a FxHashSet<u64>,
+    pub(super) return_sites: &'a FxHashSet<u64>,
+    /// Internal targets only branches reach: seeded with an unknown state
+    /// only once a region's worklist drains without reaching them.
+    pub(super) fallback_seeds: &'a FxHashSet<u64>,
+    pub(super) sorted_function_entries: &'a [u64],
+    pub(super) func_internal_targets: &'a FxHashMap<u64, FxHashSet<u64>>,
+    pub(super) call_return_map: &'a FxHashMap<u64, FxHashSet<u64>>,
+}
+
+/// Merged worklist output.
+pub(super) struct WorklistResult {
+    pub(super) successors: FxHashMap<u64, FxHashSet<u64>>,
+    pub(super) unresolved_dynamic_jumps: FxHashSet<u64>,
+    pub(super) resolved_dynamic_jumps: FxHashMap<u64, Vec<u64>>,
+    pub(super) time: ParallelTime,
+}
+
+/// Output of one region's worklist.
+#[derive(Default)]
+struct RegionResult {
+    successors: Vec<(u64, FxHashSet<u64>)>,
+    unresolved_dynamic_jumps: Vec<u64>,
+    resolved_dynamic_jumps: Vec<(u64, Vec<u64>)>,
+    /// Successors outside the region.
+    exits: Vec<u64>,
+    work: Duration,
+}
+
+impl<X: Xlen> WorklistContext<'_, X> {
+    /// Region key for `pc`: the nearest function entry at or below it.
+    fn region_of(&self, pc: u64) -> u64 {
+        binary_search_le(self.sorted_function_entries, pc)
+            .unwrap_or_else(|| self.instruction_table.base_address())
+    }
+
+    /// End (exclusive) of the region starting at `key`.
+    fn region_end(&self, key: u64) -> u64 {
+        let next = self.sorted_function_entries.partition_point(|&e| e <= key);
+        self.sorted_function_entries
+            .get(next)
+            .copied()
+            .unwrap_or_else(|| self.instruction_table.end_address())
+    }
+
+    /// Run all regions to a fixpoint and merge their results in PC order.
+    pub(super) fn run(&self) -> WorklistResult {
+        let mut seeds: BTreeMap<u64, BTreeSet<u64>> = BTreeMap::new();
+        let initial = self
+            .function_entries
+            .iter()
+            .chain(self.internal_targets)
+            .chain(self.return_sites)
+            .filter(|pc| !self.fallback_seeds.contains(pc));
+        for &pc in initial {
+            seeds.entry(self.region_of(pc)).or_default().insert(pc);
+        }
+        let mut fallback: BTreeMap<u64, Vec<u64>> = BTreeMap::new();
+        for &pc in self.fallback_seeds {
+            fallback.entry(self.region_of(pc)).or_default().push(pc);
+        }
+        for pcs in fallback.values_mut() {
+            pcs.sort_unstable();
+        }
+        // A region with only fallback seeds still runs
+        for &key in fallback.keys() {
+            seeds.entry(key).or_default();
+        }
+
+        let mut results: BTreeMap<u64, RegionResult> = BTreeMap::new();
+        let mut dirty: Vec<u64> = seeds.keys().copied().collect();
+        let mut time = ParallelTime::default();
+        let mut rounds = 0usize;
+
+        while !dirty.is_empty() {
+            rounds += 1;
+            let (round, wall) = timed(|| {
+                dirty
+                    .par_iter()
+                    .map(|&key| {
+                        let fallback = fallback.get(&key).map_or(&[][..], Vec::as_slice);
+                        (key, self.run_region(key, &seeds[&key], fallback))
+                    })
+                    .collect::<Vec<_>>()
+            });
+            time.wall += wall;
+
+            let mut next: BTreeSet<u64> = BTreeSet::new();
+            for (key, result) in round {
+                time.work += result.work;
+                for &exit in &result.exits {
+                    let owner = self.region_of(exit);
+                    if seeds.entry(owner).or_default().insert(exit) {
+                        next.insert(owner);
+                    }
+                }
+                results.insert(key, result);
+            }
+            dirty = next.into_iter().collect();
+        }
+
+        trace!(regions = results.len(), rounds, "worklist complete");
+
+        let mut successors: FxHashMap<u64, FxHashSet<u64>> = FxHashMap::default();
+        let mut unresolved_dynamic_jumps = FxHashSet::default();
+        let mut resolved_dynamic_jumps = FxHashMap::default();
+        for result in results.into_values() {
+            successors.extend(result.successors);
+            unresolved_dynamic_jumps.extend(result.unresolved_dynamic_jumps);
+            resolved_dynamic_jumps.extend(result.resolved_dynamic_jumps);
+        }
+
+        WorklistResult {
+            successors,
+            unresolved_dynamic_jumps,
+            resolved_dynamic_jumps,
+            time,
+        }
+    }
+
+    /// Propagate register state through one region from its seeds.
+    fn run_region(&self, key: u64, seeds: &BTreeSet<u64>, fallback: &[u64]) -> RegionResult {
+        let (propagated, work) = timed(|| self.propagate(key, seeds, fallback));
+
+        let mut successors: Vec<(u64, FxHashSet<u64>)> =
+            propagated.successors.into_iter().collect();
+        successors.sort_unstable_by_key(|&(pc, _)| pc);
+        let mut unresolved_dynamic_jumps: Vec<u64> = propagated.unresolved.into_iter().collect();
+        unresolved_dynamic_jumps.sort_unstable();
+        let mut resolved_dynamic_jumps: Vec<(u64, Vec<u64>)> =
+            propagated.resolved.into_iter().collect();
+        resolved_dynamic_jumps.sort_unstable_by_key(|&(pc, _)| pc);
+        let mut exits: Vec<u64> = propagated.exits.into_iter().collect();
+        exits.sort_unstable();
+
+        RegionResult {
+            successors,
+            unresolved_dynamic_jumps,
+            resolved_dynamic_jumps,
+            exits,
+            work,
+        }
+    }
+
+    fn propagate(&self, key: u64, seeds: &BTreeSet<u64>, fallback: &[u64]) -> Propagated {
+        let table = self.instruction_table;
+        let end = self.region_end(key);
+        let in_region = |pc: u64| pc >= key && pc < end;
+
+        let mut states: FxHashMap<u64, RegisterState> =
+            FxHashMap::with_capacity_and_hasher(seeds.len(), FxBuildHasher);
+        let mut worklist: Vec<u64> = seeds.iter().copied().collect();
+        let mut in_worklist: FxHashSet<u64> = worklist.iter().copied().collect();
+        for &pc in seeds {
+            states.insert(pc, RegisterState::new());
+        }
+        let mut successors: FxHashMap<u64, FxHashSet<u64>> = FxHashMap::default();
+        let mut unresolved_dynamic_jumps = FxHashSet::default();
+        let mut resolved: FxHashMap<u64, Vec<u64>> = FxHashMap::default();
+        let mut exits = FxHashSet::default();
+
+        let max_iterations = usize::try_from(end - key)
+            .unwrap_or(usize::MAX / MAX_ITERATIONS_MULTIPLIER)
+            .saturating_mul(MAX_ITERATIONS_MULTIPLIER);
+
+        let mut idx = 0;
+        loop {
+            while idx < worklist.len() && idx <= max_iterations {
+                let pc = worklist[idx];
+                idx += 1;
+                in_worklist.remove(&pc);
+
+                let Some(state) = states.get(&pc).cloned() else {
+                    continue;
+                };
+                let size = u64::from(table.instruction_size_at_pc(pc));
+                if size == 0 {
+                    continue;
+                }
+                let Some(instr) = table.get_at_pc(pc) else {
+                    continue;
+                };
+                let decoded = decode_at(table, pc, instr);
+
+                let succs = get_successors(
+                    table,
+                    pc,
+                    size,
+                    &decoded,
+                    &state,
+                    self.function_entries,
+                    self.return_sites,
+                    self.sorted_function_entries,
+                    self.func_internal_targets,
+                    self.call_return_map,
+                    &mut unresolved_dynamic_jumps,
+                );
+                if decoded.is_indirect_jump() {
+                    record_jump_targets(table, pc, &decoded, &state, &mut resolved);
+                }
+                let state_out = transfer(table, pc, size, &decoded, state);
+
+                for &target in &succs {
+                    if !in_region(target) {
+                        exits.insert(target);
+                        continue;
+                    }
+                    let edge_state = (decoded.kind == InstrKind::Branch)
+                        .then(|| branch_edge_state(&decoded, pc, size, &state_out, target))
+                        .flatten();
+                    let edge_state = edge_state.as_ref().unwrap_or(&state_out);
+                    let changed = match states.entry(target) {
+                        Entry::Occupied(mut existing) => existing.get_mut().merge(edge_state),
+                        Entry::Vacant(slot) => {
+                            slot.insert(edge_state.clone());
+                            true
+                        }
+                    };
+                    if changed && in_worklist.insert(target) {
+                        worklist.push(target);
+                    }
+                }
+
+                successors.entry(pc).or_default().extend(succs);
+            }
+            if idx < worklist.len() {
+                break;
+            }
+            // Fallback seeds propagation never reached enter with an unknown state
+            let pending: Vec<u64> = fallback
+                .iter()
+                .copied()
+                .filter(|pc| !states.contains_key(pc))
+                .collect();
+            if pending.is_empty() {
+                break;
+            }
+            for pc in pending {
+                states.insert(pc, RegisterState::new());
+                in_worklist.insert(pc);
+                worklist.push(pc);
+            }
+        }
+
+        // States that never settled may still widen: targets proven so far
+        // are not proofs.
+        if idx < worklist.len() {
+            trace!(region = key, "worklist hit the iteration limit");
+            resolved.clear();
+        }
+
+        Propagated {
+            successors,
+            unresolved: unresolved_dynamic_jumps,
+            resolved,
+            exits,
+        }
+    }
+}
+
+/// Record the proven targets of the indirect jump at `pc`. The last visit
+/// sees the final state, so it decides the entry.
+fn record_jump_targets<X: Xlen>(
+    table: &InstructionTable<X>,
+    pc: u64,
+    decoded: &DecodedInstruction,
+    state: &RegisterState,
+    resolved: &mut FxHashMap<u64, Vec<u64>>,
+) {
+    match jalr_targets(table, decoded, state) {
+        Some(mut targets) if !targets.is_empty() => {
+            targets.sort_unstable();
+            resolved.insert(pc, targets);
+        }
+        _ => {
+            resolved.remove(&pc);
+        }
+    }
+}
+
+/// Raw output of [`WorklistContext::propagate`].
+struct Propagated {
+    successors: FxHashMap<u64, FxHashSet<u64>>,
+    unresolved: FxHashSet<u64>,
+    resolved: FxHashMap<u64, Vec<u64>>,
+    exits: FxHashSet<u64>,
+}
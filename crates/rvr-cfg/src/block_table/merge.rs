@@ -0,0 +1,184 @@
+use std::collections::{HashMap, VecDeque};
+
+use rustc_hash::{FxHashMap, FxHashSet};
+use rvr_isa::{ExtensionRegistry, Xlen};
+use tracing::trace;
+
+use super::{BasicBlock, BlockLimits, BlockTable, BlockTransform};
+
+struct MergeContext<'a, X: Xlen> {
+    entry_points: &'a [u64],
+    start_to_idx: &'a HashMap<u64, usize>,
+    limits: BlockLimits,
+    registry: &'a ExtensionRegistry<X>,
+}
+
+impl<X: Xlen> BlockTable<X> {
+    /// Merge blocks where successor has single predecessor.
+    ///
+    /// A chain that would outgrow `limits` is split: the block that did not
+    /// fit stays a block of its own and starts a new chain.
+    ///
+    /// Returns number of blocks absorbed.
+    pub fn merge_blocks(&mut self, limits: BlockLimits, registry: &ExtensionRegistry<X>) -> usize {
+        if self.blocks.is_empty() {
+            return 0;
+        }
+
+        let entry_points: Vec<u64> = self.instruction_table.entry_points().to_vec();
+
+        // Build lookup map
+        let start_to_idx: HashMap<u64, usize> = self
+            .blocks
+            .iter()
+            .enumerate()
+            .map(|(i, b)| (b.start, i))
+            .collect();
+
+        // Find absorbable blocks
+        let mut absorbed = FxHashSet::default();
+        for block in &self.blocks {
+            if absorbed.contains(&block.start) {
+                continue;
+            }
+            if let Some(target) = self.get_merge_target(block, &entry_points, registry) {
+                absorbed.insert(target);
+            }
+        }
+
+        if absorbed.is_empty() {
+            return 0;
+        }
+
+        // Build merged blocks with continuation chains
+        // TODO: maybe shouldn't be in state if being cleared - doesn't seem idiomatic
+        self.absorbed_to_merged.clear();
+        self.block_continuations.clear();
+        self.transforms.clear();
+
+        // TODO: doesn't seem idiomatic - think in abstract that this should be some recursive algorithm to keep on merging
+        //       static jump targets or maybe this is something else and should be handled separate from general merging
+        let mut heads: VecDeque<usize> = (0..self.blocks.len())
+            .filter(|&idx| !absorbed.contains(&self.blocks[idx].start))
+            .collect();
+        let mut merged: FxHashMap<u64, BasicBlock> = FxHashMap::default();
+        let context = MergeContext {
+            entry_points: &entry_points,
+            start_to_idx: &start_to_idx,
+            limits,
+            registry,
+        };
+        while let Some(head_idx) = heads.pop_front() {
+            let (block, split) = self.merge_chain(head_idx, &mut absorbed, &context);
+            merged.insert(block.start, block);
+            heads.extend(split);
+        }
+
+        let merged: Vec<_> = self
+            .blocks
+            .iter()
+            .filter_map(|b| merged.remove(&b.start))
+            .collect();
+        let absorbed_count = self.blocks.len() - merged.len();
+        self.blocks = merged;
+        if absorbed_count > 0 {
+            trace!(absorbed = absorbed_count, "merge_blocks complete");
+        }
+        absorbed_count
+    }
+
+    /// Follow the merge chain from the block at `head_idx`.
+    ///
+    /// Returns the merged block, and the index of the block the chain was
+    /// split before if it hit the limits (no longer in `absorbed`).
+    fn merge_chain(
+        &mut self,
+        head_idx: usize,
+        absorbed: &mut FxHashSet<u64>,
+        context: &MergeContext<'_, X>,
+    ) -> (BasicBlock, Option<usize>) {
+        let block = self.blocks[head_idx].clone();
+        let mut continuations = Vec::new();
+        let mut count = block.instruction_count;
+        let mut last_pc = block.last_pc;
+        let mut current_block = block.clone();
+        let mut split = None;
+
+        // Follow continuation chain
+        // TODO: what is continuations
+        while let Some(target_pc) =
+            self.get_merge_target(&current_block, context.entry_points, context.registry)
+        {
+            if !absorbed.contains(&target_pc) {
+                break;
+            }
+            let Some(&target_idx) = context.start_to_idx.get(&target_pc) else {
+                break;
+            };
+            let target_block = self.blocks[target_idx].clone();
+            if !context.limits.allows(
+                count,
+                continuations.len() + 1,
+                target_block.instruction_count,
+            ) {
+                absorbed.remove(&target_pc);
+                split = Some(target_idx);
+                break;
+            }
+
+            self.absorbed_to_merged.insert(target_pc, block.start);
+            continuations.push((target_block.start, target_block.end));
+            self.transforms
+                .entry(block.start)
+                .or_default()
+                .push(BlockTransform::Merged {
+                    start: target_block.start,
+                    end: target_block.end,
+                });
+            count += target_block.instruction_count;
+            last_pc = target_block.last_pc;
+            current_block = target_block;
+        }
+
+        if !continuations.is_empty() {
+            self.block_continuations.insert(block.start, continuations);
+        }
+
+        // Keep original end - continuations handle absorbed blocks
+        (
+            BasicBlock::new(block.start, block.end, count, last_pc),
+            split,
+        )
+    }
+
+    /// Get merge target if block can merge with its successor.
+    fn get_merge_target(
+        &self,
+        block: &BasicBlock,
+        entry_points: &[u64],
+        registry: &ExtensionRegistry<X>,
+    ) -> Option<u64> {
+        let instr = self.instruction_table.get_at_pc(block.last_pc)?;
+        let ir = registry.lift(instr);
+
+        // TODO: maybe can be encapsulated into something
+        let target_pc = match &ir.terminator {
+            rvr_ir::Terminator::Fall { target } => target.map(|t| X::to_u64(t))?,
+            rvr_ir::Terminator::Jump { target } => X::to_u64(*target),
+            _ => return None,
+        };
+
+        // Don't merge into entry point blocks
+        if entry_points.contains(&target_pc) {
+            return None;
+        }
+
+        // Must have exactly one predecessor (this block's last instruction)
+        let preds = self.predecessors.get(&target_pc)?;
+        if preds.len() != 1 || !preds.contains(&block.last_pc) {
+            return None;
+        }
+
+        Some(target_pc)
+    }
+}
@@ -0,0 +1,560 @@
+//! Block table for CFG analysis and block transforms.
+//!
+//! Supports merge, tail-dup, and superblock transforms.
+
+use std::collections::HashMap;
+
+use rayon::prelude::*;
+use rustc_hash::{FxHashMap, FxHashSet};
+use rvr_ir::OverrideExpansion;
+use rvr_isa::{ExtensionRegistry, Xlen};
+use tracing::{debug, trace_span};
+
+use crate::analysis::ControlFlowAnalyzer;
+use crate::{InstructionTable, ParallelTime, timed};
+
+mod merge;
+mod superblock;
+mod tail_dup;
+#[cfg(test)]
+mod tests;
+
+// TODO: why both end and last_pc - maybe should have terminator type field
+/// Basic block with start/end addresses.
+#[derive(Clone, Debug)]
+pub struct BasicBlock {
+    /// Starting PC.
+    pub start: u64,
+    /// Ending PC (exclusive).
+    pub end: u64,
+    /// Number of instructions in this block.
+    pub instruction_count: usize,
+    /// PC of the last instruction.
+    pub last_pc: u64,
+}
+
+impl BasicBlock {
+    #[must_use]
+    pub const fn new(start: u64, end: u64, instruction_count: usize, last_pc: u64) -> Self {
+        Self {
+            start,
+            end,
+            instruction_count,
+            last_pc,
+        }
+    }
+
+    // TODO: better name that clarifies that this is bytes
+    /// Size of block in bytes.
+    #[must_use]
+    pub const fn size(&self) -> u64 {
+        self.end - self.start
+    }
+}
+
+/// A transform applied to a block, recorded per block for debugging.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockTransform {
+    /// Absorbed the single-predecessor successor block at `start..end`.
+    Merged { start: u64, end: u64 },
+    /// Appended a copy of the small block at `start..end`.
+    TailDuplicated { start: u64, end: u64 },
+    /// Absorbed the fall-through block at `start..end` after a branch.
+    Superblock { start: u64, end: u64 },
+}
+
+impl BlockTransform {
+    /// Range of the absorbed block.
+    #[must_use]
+    pub const fn range(self) -> (u64, u64) {
+        match self {
+            Self::Merged { start, end }
+            | Self::TailDuplicated { start, end }
+            | Self::Superblock { start, end } => (start, end),
+        }
+    }
+}
+
+// TODO: seems like there's redundancy here
+/// Block table with CFG analysis and transforms.
+pub struct BlockTable<X: Xlen> {
+    /// List of basic blocks.
+    pub blocks: Vec<BasicBlock>,
+    // TODO: use fxhashmap
+    /// Absorbed PC -> merged block start mapping (for dispatch table).
+    pub absorbed_to_merged: HashMap<u64, u64>,
+    /// Block continuations: `merged_start` -> list of (start, end) ranges.
+    pub block_continuations: HashMap<u64, Vec<(u64, u64)>>,
+    /// Taken path inlines: `branch_pc` -> (`inline_start`, `inline_end`).
+    pub taken_inlines: HashMap<u64, (u64, u64)>,
+    /// Transforms applied to each block: `block_start` -> transforms in order.
+    pub transforms: HashMap<u64, Vec<BlockTransform>>,
+    /// Predecessors map: PC -> set of predecessor PCs.
+    pub predecessors: FxHashMap<u64, FxHashSet<u64>>,
+    /// Successors map: PC -> set of successor PCs.
+    pub successors: FxHashMap<u64, FxHashSet<u64>>,
+    /// Unresolved dynamic jumps.
+    pub unresolved_jumps: FxHashSet<u64>,
+    /// Dynamic jumps with proven targets: jump PC -> sorted targets.
+    pub resolved_jumps: FxHashMap<u64, Vec<u64>>,
+    /// Call return map: callee -> set of return addresses.
+    pub call_return_map: FxHashMap<u64, FxHashSet<u64>>,
+    /// Block to function mapping: `block_start` -> `function_entry`.
+    pub block_to_function: FxHashMap<u64, u64>,
+    /// Time spent in parallel CFG analysis.
+    pub analysis_time: ParallelTime,
+    /// Reference to instruction table.
+    instruction_table: InstructionTable<X>,
+}
+
+// TODO: superblock stuff should be encapsulated
+/// Default limits for block transforms.
+pub const DEFAULT_SUPERBLOCK_DEPTH: usize = 100;
+pub const DEFAULT_SUPERBLOCK_MAX_INSTRS: usize = 4096;
+pub const DEFAULT_TAIL_DUP_SIZE: usize = 100;
+pub const DEFAULT_TAKEN_INLINE_SIZE: usize = 50;
+
+/// Size limits for blocks grown by merging and superblock formation.
+///
+/// Once absorbing the next block would exceed a limit, the block ends at that
+/// boundary and the next block is emitted on its own, reached through a
+/// regular block-to-block transfer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlockLimits {
+    /// Maximum instructions in one block.
+    pub max_instrs: usize,
+    /// Maximum basic blocks absorbed into one block (including the head).
+    pub max_blocks: usize,
+}
+
+impl Default for BlockLimits {
+    fn default() -> Self {
+        Self {
+            max_instrs: DEFAULT_SUPERBLOCK_MAX_INSTRS,
+            max_blocks: DEFAULT_SUPERBLOCK_DEPTH,
+        }
+    }
+}
+
+impl BlockLimits {
+    /// Whether a block of `instrs` instructions made of `blocks` basic blocks
+    /// can absorb a block of `next_instrs` instructions.
+    #[must_use]
+    pub const fn allows(&self, instrs: usize, blocks: usize, next_instrs: usize) -> bool {
+        instrs + next_instrs <= self.max_instrs && blocks < self.max_blocks
+    }
+}
+
+impl<X: Xlen> BlockTable<X> {
+    /// Create a new block table from an instruction table with CFG analysis.
+    pub fn from_instruction_table(
+        instruction_table: InstructionTable<X>,
+        registry: &ExtensionRegistry<X>,
+    ) -> Self {
+        let mut table = Self {
+            blocks: Vec::new(),
+            absorbed_to_merged: HashMap::new(),
+            block_continuations: HashMap::new(),
+            taken_inlines: HashMap::new(),
+            transforms: HashMap::new(),
+            predecessors: FxHashMap::default(),
+            successors: FxHashMap::default(),
+            unresolved_jumps: FxHashSet::default(),
+            resolved_jumps: FxHashMap::default(),
+            call_return_map: FxHashMap::default(),
+            block_to_function: FxHashMap::default(),
+            analysis_time: ParallelTime::default(),
+            instruction_table,
+        };
+        table.build_blocks(registry);
+        debug!(
+            blocks = table.blocks.len(),
+            resolved_jumps = table.resolved_jumps.len(),
+            unresolved_jumps = table.unresolved_jumps.len(),
+            "built block table"
+        );
+        table
+    }
+
+    /// Create a block table with linear blocks (one instruction per block).
+    #[must_use]
+    pub fn linear(instruction_table: InstructionTable<X>) -> Self {
+        let mut table = Self {
+            blocks: Vec::new(),
+            absorbed_to_merged: HashMap::new(),
+            block_continuations: HashMap::new(),
+            taken_inlines: HashMap::new(),
+            transforms: HashMap::new(),
+            predecessors: FxHashMap::default(),
+            successors: FxHashMap::default(),
+            unresolved_jumps: FxHashSet::default(),
+            resolved_jumps: FxHashMap::default(),
+            call_return_map: FxHashMap::default(),
+            block_to_function: FxHashMap::default(),
+            analysis_time: ParallelTime::default(),
+            instruction_table,
+        };
+        table.build_linear_blocks();
+        table
+    }
+
+    /// Build linear blocks (one instruction per block).
+    fn build_linear_blocks(&mut self) {
+        let base = self.instruction_table.base_address();
+        let end = self.instruction_table.end_address();
+        let mut pc = base;
+
+        while pc < end {
+            if !self.instruction_table.is_valid_pc(pc) {
+                pc += 2; // Skip to next slot
+                continue;
+            }
+            let size = u64::from(self.instruction_table.instruction_size_at_pc(pc));
+            if size == 0 {
+                pc += 2;
+                continue;
+            }
+            self.blocks.push(BasicBlock::new(pc, pc + size, 1, pc));
+            pc += size;
+        }
+    }
+
+    /// Build blocks using CFG analysis.
+    fn build_blocks(&mut self, registry: &ExtensionRegistry<X>) {
+        // Helper blocks of override expansions re-enter guest code at PCs the
+        // decoded CFG knows nothing about; pin them as entry points so they
+        // stay block leaders and are never merged or duplicated away.
+        let expansion_targets = self.expansion_exit_targets(registry);
+        self.instruction_table.add_entry_points(expansion_targets);
+
+        let analysis = ControlFlowAnalyzer::analyze(&self.instruction_table);
+
+        self.predecessors = analysis.predecessors;
+        self.successors = analysis.successors;
+        self.unresolved_jumps = analysis.unresolved_dynamic_jumps;
+        self.resolved_jumps = analysis.resolved_dynamic_jumps;
+        self.call_return_map = analysis.call_return_map;
+        self.block_to_function = analysis.block_to_function;
+        self.analysis_time = analysis.time;
+
+        {
+            let _span = trace_span!("create_blocks").entered();
+            self.create_blocks_from_leaders(&analysis.leaders, registry);
+        }
+    }
+
+    /// Collect guest PCs reachable from helper blocks of override expansions.
+    fn expansion_exit_targets(&self, registry: &ExtensionRegistry<X>) -> Vec<u64> {
+        let mut targets: Vec<u64> = self
+            .instruction_table
+            .valid_instructions()
+            .filter(|(_, instr)| registry.may_expand(instr.opid))
+            .map(|(_, instr)| registry.lift_expanded(instr))
+            .filter(OverrideExpansion::has_helpers)
+            .flat_map(|expansion| expansion.exit_targets())
+            .filter(|&pc| self.instruction_table.is_valid_pc(pc))
+            .collect();
+        targets.sort_unstable();
+        targets.dedup();
+        targets
+    }
+
+    /// Create blocks from leader set.
+    ///
+    /// Each leader's extent depends only on the leader set, so blocks are
+    /// built in parallel and collected in leader order.
+    fn create_blocks_from_leaders(
+        &mut self,
+        leaders: &FxHashSet<u64>,
+        registry: &ExtensionRegistry<X>,
+    ) {
+        // Sort leaders
+        // TODO: why sort everywere, maybe just store sorted everywhere
+        let mut sorted_leaders: Vec<u64> = leaders.iter().copied().collect();
+        sorted_leaders.sort_unstable();
+
+        let end = self.instruction_table.end_address();
+
+        let (blocks, wall) = timed(|| {
+            sorted_leaders
+                .par_iter()
+                .enumerate()
+                .map(|(i, &block_start)| {
+                    // Find max end PC (next leader or table end)
+                    let max_end = sorted_leaders.get(i + 1).copied().unwrap_or(end).min(end);
+                    timed(|| self.block_from_leader(block_start, max_end, leaders, registry))
+                })
+                .collect::<Vec<_>>()
+        });
+
+        let mut time = ParallelTime {
+            wall,
+            ..ParallelTime::default()
+        };
+        self.blocks = blocks
+            .into_iter()
+            .filter_map(|(block, work)| {
+                time.work += work;
+                block
+            })
+            .collect();
+        self.analysis_time += time;
+    }
+
+    /// Extent of the block starting at `block_start`, up to `max_end`.
+    fn block_from_leader(
+        &self,
+        block_start: u64,
+        max_end: u64,
+        leaders: &FxHashSet<u64>,
+        registry: &ExtensionRegistry<X>,
+    ) -> Option<BasicBlock> {
+        if !self.instruction_table.is_valid_pc(block_start) {
+            return None;
+        }
+
+        let end = self.instruction_table.end_address();
+        let mut pc = block_start;
+        let mut instruction_count = 0;
+        let mut last_pc = block_start;
+
+        while pc < max_end && pc < end {
+            if !self.instruction_table.is_valid_pc(pc) {
+                break;
+            }
+
+            let size = u64::from(self.instruction_table.instruction_size_at_pc(pc));
+            if size == 0 {
+                break;
+            }
+
+            instruction_count += 1;
+            last_pc = pc;
+
+            // Check if this instruction ends the block (a hooked entry
+            // returns)
+            if let Some(instr) = self.instruction_table.get_at_pc(pc) {
+                let ir = registry.lift(instr);
+                if ir.terminator.is_control_flow() || self.instruction_table.is_hooked_entry(pc) {
+                    pc += size;
+                    break;
+                }
+            }
+
+            let next_pc = pc + size;
+
+            // Stop before reaching next leader
+            if leaders.contains(&next_pc) && next_pc != block_start {
+                pc = next_pc;
+                break;
+            }
+
+            pc = next_pc;
+        }
+
+        (instruction_count > 0 && pc > block_start)
+            .then(|| BasicBlock::new(block_start, pc, instruction_count, last_pc))
+    }
+
+    /// Get instruction table reference.
+    #[must_use]
+    pub const fn instruction_table(&self) -> &InstructionTable<X> {
+        &self.instruction_table
+    }
+
+    /// Get number of blocks.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    // TODO: can i use some trait for this
+    /// Check if empty.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    /// Transforms applied to the block starting at `start`, in order.
+    #[must_use]
+    pub fn transforms(&self, start: u64) -> &[BlockTransform] {
+        self.transforms.get(&start).map_or(&[], Vec::as_slice)
+    }
+
+    // TODO: can i use some trait for this
+    /// Get block by index.
+    #[must_use]
+    pub fn get(&self, index: usize) -> Option<&BasicBlock> {
+        self.blocks.get(index)
+    }
+
+    // TODO: can i use some trait for this
+    /// Iterate over blocks.
+    pub fn iter(&self) -> impl Iterator<Item = &BasicBlock> {
+        self.blocks.iter()
+    }
+
+    // ============= Block Transforms =============
+
+    /// Split blocks longer than `max_instrs` at instruction boundaries.
+    ///
+    /// Each piece falls through to the next like a block that ends before a
+    /// leader, so straight-line code of any length stays within the limit.
+    ///
+    /// Returns number of blocks added.
+    pub fn split_long_blocks(&mut self, max_instrs: usize) -> usize {
+        let max_instrs = max_instrs.max(1);
+        if self
+            .blocks
+            .iter()
+            .all(|b| b.instruction_count <= max_instrs)
+        {
+            return 0;
+        }
+
+        let before = self.blocks.len();
+        let mut split = Vec::with_capacity(before);
+        for block in std::mem::take(&mut self.blocks) {
+            if block.instruction_count <= max_instrs {
+                split.push(block);
+                continue;
+            }
+            let function = self.block_to_function.get(&block.start).copied();
+            let mut start = block.start;
+            let mut remaining = block.instruction_count;
+            while remaining > max_instrs {
+                let mut pc = start;
+                let mut last_pc = start;
+                for _ in 0..max_instrs {
+                    last_pc = pc;
+                    pc += u64::from(self.instruction_table.instruction_size_at_pc(pc));
+                }
+                split.push(BasicBlock::new(start, pc, max_instrs, last_pc));
+                if let Some(function) = function {
+                    self.block_to_function.insert(pc, function);
+                }
+                start = pc;
+                remaining -= max_instrs;
+            }
+            split.push(BasicBlock::new(start, block.end, remaining, block.last_pc));
+        }
+
+        let added = split.len() - before;
+        debug!(added, max_instrs, "split long blocks");
+        self.blocks = split;
+        added
+    }
+
+    /// Apply all transforms in order: split, merge, tail-dup, superblock.
+    pub fn optimize(
+        &mut self,
+        limits: BlockLimits,
+        registry: &ExtensionRegistry<X>,
+    ) -> (usize, usize, usize) {
+        self.split_long_blocks(limits.max_instrs);
+        let merged = {
+            let _span = trace_span!("merge_blocks").entered();
+            self.merge_blocks(limits, registry)
+        };
+        // TODO: both of these are similar and there should be a generic way to do this
+        let tail_duped = {
+            let _span = trace_span!("tail_duplicate").entered();
+            self.tail_duplicate(DEFAULT_TAIL_DUP_SIZE, registry)
+        };
+        let superblocked = {
+            let _span = trace_span!("form_superblocks").entered();
+            self.form_superblocks(limits, registry)
+        };
+
+        // Fix any stale mappings from chained absorptions
+        // TODO: this shouldn't be a separate function and should happen above
+        self.fix_stale_mappings();
+
+        (merged, tail_duped, superblocked)
+    }
+
+    /// Fix stale `absorbed_to_merged` mappings by following chains.
+    ///
+    /// After multiple transform passes, a block A might map to block B,
+    /// which was subsequently absorbed into block C. This method follows
+    /// chains to ensure all mappings point to actually remaining blocks.
+    pub fn fix_stale_mappings(&mut self) {
+        // Build set of remaining block starts
+        let remaining: FxHashSet<u64> = self.blocks.iter().map(|b| b.start).collect();
+
+        // For each absorbed block, follow chain to find final target
+        let mut to_update = Vec::new();
+        let mut to_remove = Vec::new();
+
+        for (&absorbed_pc, &target_pc) in &self.absorbed_to_merged {
+            if remaining.contains(&target_pc) {
+                // Already points to a remaining block
+                continue;
+            }
+
+            // Follow chain
+            let mut current = target_pc;
+            let mut found = false;
+            let mut visited = FxHashSet::default();
+            visited.insert(absorbed_pc);
+
+            while !visited.contains(&current) {
+                visited.insert(current);
+
+                if remaining.contains(&current) {
+                    // Found final target
+                    to_update.push((absorbed_pc, current));
+                    found = true;
+                    break;
+                }
+
+                match self.absorbed_to_merged.get(&current) {
+                    Some(&next) => current = next,
+                    None => break, // Broken chain
+                }
+            }
+
+            if !found {
+                // Broken chain - remove mapping
+                to_remove.push(absorbed_pc);
+            }
+        }
+
+        // Apply updates
+        for (pc, target) in to_update {
+            self.absorbed_to_merged.insert(pc, target);
+
+            let mut moved_range = None;
+            for ranges in self.block_continuations.values_mut() {
+                if let Some(pos) = ranges.iter().position(|(start, _)| *start == pc) {
+                    moved_range = Some(ranges.remove(pos));
+                    break;
+                }
+            }
+
+            if let Some(range) = moved_range {
+                self.block_continuations
+                    .entry(target)
+                    .or_default()
+                    .push(range);
+            }
+
+            let mut moved_transform = None;
+            for transforms in self.transforms.values_mut() {
+                if let Some(pos) = transforms.iter().position(|t| t.range().0 == pc) {
+                    moved_transform = Some(transforms.remove(pos));
+                    break;
+                }
+            }
+
+            if let Some(transform) = moved_transform {
+                self.transforms.entry(target).or_default().push(transform);
+            }
+        }
+
+        // Remove broken chains
+        for pc in to_remove {
+            self.absorbed_to_merged.remove(&pc);
+        }
+    }
+}
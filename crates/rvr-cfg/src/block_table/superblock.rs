@@ -0,0 +1,272 @@
+use std::collections::HashMap;
+
+use rustc_hash::FxHashSet;
+use rvr_isa::{ExtensionRegistry, Xlen};
+use tracing::trace;
+
+use super::{BasicBlock, BlockLimits, BlockTable, BlockTransform, DEFAULT_TAKEN_INLINE_SIZE};
+
+type SuperblockPlan = (
+    FxHashSet<u64>,
+    HashMap<u64, Vec<u64>>,
+    Vec<(u64, (u64, u64))>,
+);
+
+struct SuperblockContext<'a, X: Xlen> {
+    entry_points: &'a [u64],
+    start_to_idx: &'a HashMap<u64, usize>,
+    merge_targets: &'a FxHashSet<u64>,
+    registry: &'a ExtensionRegistry<X>,
+}
+
+impl<X: Xlen> BlockTable<X> {
+    /// Form superblocks by absorbing fall-through blocks after branches.
+    ///
+    /// A superblock ends before the first block that would take it past
+    /// `limits`.
+    ///
+    /// Returns number of blocks absorbed.
+    pub fn form_superblocks(
+        &mut self,
+        limits: BlockLimits,
+        registry: &ExtensionRegistry<X>,
+    ) -> usize {
+        if self.blocks.is_empty() {
+            return 0;
+        }
+
+        let entry_points = self.instruction_table.entry_points();
+        let start_to_idx: HashMap<u64, usize> = self
+            .blocks
+            .iter()
+            .enumerate()
+            .map(|(i, b)| (b.start, i))
+            .collect();
+        let merge_targets: FxHashSet<u64> = self.absorbed_to_merged.values().copied().collect();
+
+        let (absorbed, superblock_chains, pending_inlines) = self.collect_superblock_chains(
+            entry_points,
+            &start_to_idx,
+            &merge_targets,
+            limits,
+            registry,
+        );
+
+        for (pc, range) in pending_inlines {
+            self.taken_inlines.insert(pc, range);
+        }
+
+        if absorbed.is_empty() {
+            return 0;
+        }
+
+        self.apply_superblock_chains(&superblock_chains, &start_to_idx);
+
+        let new_blocks: Vec<_> = self
+            .blocks
+            .iter()
+            .filter(|b| !absorbed.contains(&b.start))
+            .cloned()
+            .collect();
+
+        let absorbed_count = self.blocks.len() - new_blocks.len();
+        self.blocks = new_blocks;
+        if absorbed_count > 0 {
+            trace!(
+                absorbed = absorbed_count,
+                taken_inlines = self.taken_inlines.len(),
+                "form_superblocks complete"
+            );
+        }
+        absorbed_count
+    }
+
+    fn collect_superblock_chains(
+        &self,
+        entry_points: &[u64],
+        start_to_idx: &HashMap<u64, usize>,
+        merge_targets: &FxHashSet<u64>,
+        limits: BlockLimits,
+        registry: &ExtensionRegistry<X>,
+    ) -> SuperblockPlan {
+        let mut absorbed = FxHashSet::default();
+        let mut superblock_heads = FxHashSet::default();
+        let mut superblock_chains: HashMap<u64, Vec<u64>> = HashMap::new();
+        let mut pending_inlines = Vec::new();
+        let context = SuperblockContext {
+            entry_points,
+            start_to_idx,
+            merge_targets,
+            registry,
+        };
+
+        for block in &self.blocks {
+            if absorbed.contains(&block.start) || merge_targets.contains(&block.start) {
+                continue;
+            }
+
+            let Some(instr) = self.instruction_table.get_at_pc(block.last_pc) else {
+                continue;
+            };
+            let ir = registry.lift(instr);
+            let rvr_ir::Terminator::Branch { target, .. } = &ir.terminator else {
+                continue;
+            };
+            let taken_pc = X::to_u64(*target);
+
+            if let Some(inline) = self.maybe_inline_taken_path(block, taken_pc, &absorbed, &context)
+            {
+                pending_inlines.push(inline);
+            }
+
+            let fall_pc = block.end;
+            if entry_points.contains(&fall_pc) || !start_to_idx.contains_key(&fall_pc) {
+                continue;
+            }
+
+            superblock_heads.insert(block.start);
+
+            let chain = self.build_superblock_chain(
+                block,
+                &superblock_heads,
+                &mut absorbed,
+                &context,
+                limits,
+            );
+
+            if !chain.is_empty() {
+                superblock_chains.insert(block.start, chain);
+            }
+        }
+
+        (absorbed, superblock_chains, pending_inlines)
+    }
+
+    fn maybe_inline_taken_path(
+        &self,
+        block: &BasicBlock,
+        taken_pc: u64,
+        absorbed: &FxHashSet<u64>,
+        context: &SuperblockContext<'_, X>,
+    ) -> Option<(u64, (u64, u64))> {
+        if context.entry_points.contains(&taken_pc)
+            || !context.start_to_idx.contains_key(&taken_pc)
+            || absorbed.contains(&taken_pc)
+            || context.merge_targets.contains(&taken_pc)
+        {
+            return None;
+        }
+
+        let preds = self.predecessors.get(&taken_pc)?;
+        if preds.len() != 1 {
+            return None;
+        }
+
+        let taken_idx = context.start_to_idx[&taken_pc];
+        let taken_block = &self.blocks[taken_idx];
+        if taken_block.instruction_count > DEFAULT_TAKEN_INLINE_SIZE {
+            return None;
+        }
+
+        let taken_instr = self.instruction_table.get_at_pc(taken_block.last_pc)?;
+        let taken_ir = context.registry.lift(taken_instr);
+        if matches!(taken_ir.terminator, rvr_ir::Terminator::Branch { .. }) {
+            return None;
+        }
+
+        Some((block.last_pc, (taken_block.start, taken_block.end)))
+    }
+
+    fn build_superblock_chain(
+        &self,
+        head: &BasicBlock,
+        superblock_heads: &FxHashSet<u64>,
+        absorbed: &mut FxHashSet<u64>,
+        context: &SuperblockContext<'_, X>,
+        limits: BlockLimits,
+    ) -> Vec<u64> {
+        let mut chain = Vec::new();
+        let mut current_pc = head.end;
+        let mut instrs = head.instruction_count;
+        let mut blocks = 1 + self
+            .block_continuations
+            .get(&head.start)
+            .map_or(0, Vec::len);
+
+        loop {
+            if absorbed.contains(&current_pc) || context.entry_points.contains(&current_pc) {
+                break;
+            }
+            if !context.start_to_idx.contains_key(&current_pc) {
+                break;
+            }
+            if context.merge_targets.contains(&current_pc) || superblock_heads.contains(&current_pc)
+            {
+                break;
+            }
+            if self
+                .predecessors
+                .get(&current_pc)
+                .is_some_and(|preds| preds.len() > 1)
+            {
+                break;
+            }
+
+            let current_idx = context.start_to_idx[&current_pc];
+            let current_block = &self.blocks[current_idx];
+            if !limits.allows(instrs, blocks, current_block.instruction_count) {
+                break;
+            }
+            let Some(term_instr) = self.instruction_table.get_at_pc(current_block.last_pc) else {
+                break;
+            };
+            let term_ir = context.registry.lift(term_instr);
+
+            chain.push(current_pc);
+            absorbed.insert(current_pc);
+            instrs += current_block.instruction_count;
+            blocks += 1 + self
+                .block_continuations
+                .get(&current_pc)
+                .map_or(0, Vec::len);
+
+            match &term_ir.terminator {
+                rvr_ir::Terminator::Fall { target } => {
+                    current_pc = target.map_or(current_block.end, |t| X::to_u64(t));
+                }
+                rvr_ir::Terminator::Jump { target } => {
+                    current_pc = X::to_u64(*target);
+                }
+                _ => break,
+            }
+        }
+
+        chain
+    }
+
+    fn apply_superblock_chains(
+        &mut self,
+        superblock_chains: &HashMap<u64, Vec<u64>>,
+        start_to_idx: &HashMap<u64, usize>,
+    ) {
+        for (head_start, chain) in superblock_chains {
+            for &absorbed_start in chain {
+                self.absorbed_to_merged.insert(absorbed_start, *head_start);
+
+                let absorbed_idx = start_to_idx[&absorbed_start];
+                let absorbed_block = &self.blocks[absorbed_idx];
+                self.block_continuations
+                    .entry(*head_start)
+                    .or_default()
+                    .push((absorbed_block.start, absorbed_block.end));
+                self.transforms
+                    .entry(*head_start)
+                    .or_default()
+                    .push(BlockTransform::Superblock {
+                        start: absorbed_block.start,
+                        end: absorbed_block.end,
+                    });
+            }
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+
+use rustc_hash::FxHashSet;
+use rvr_ir::{Terminator, is_synthetic_pc};
+use rvr_isa::{ExtensionRegistry, Xlen};
+use tracing::trace;
+
+use super::{BasicBlock, BlockTable, BlockTransform};
+
+/// Check whether a terminator continues into an override's helper block.
+fn jumps_to_helper<X: Xlen>(terminator: &Terminator<X>) -> bool {
+    terminator
+        .static_targets()
+        .into_iter()
+        .any(|pc| is_synthetic_pc::<X>(X::to_u64(pc)))
+}
+
+impl<X: Xlen> BlockTable<X> {
+    // TODO: see if can be simplified - maybe should be separate block type
+    /// Duplicate small blocks with multiple predecessors into each predecessor.
+    ///
+    /// Returns number of blocks eliminated.
+    pub fn tail_duplicate(
+        &mut self,
+        max_dup_size: usize,
+        registry: &ExtensionRegistry<X>,
+    ) -> usize {
+        if self.blocks.is_empty() {
+            return 0;
+        }
+
+        let entry_points = self.instruction_table.entry_points();
+        let start_to_idx: HashMap<u64, usize> = self
+            .blocks
+            .iter()
+            .enumerate()
+            .map(|(i, b)| (b.start, i))
+            .collect();
+        let last_pc_to_block_start: HashMap<u64, u64> =
+            self.blocks.iter().map(|b| (b.last_pc, b.start)).collect();
+
+        let to_duplicate =
+            self.collect_tail_duplicate_candidates(entry_points, max_dup_size, registry);
+        if to_duplicate.is_empty() {
+            return 0;
+        }
+
+        for &dup_start in &to_duplicate {
+            let Some(&dup_idx) = start_to_idx.get(&dup_start) else {
+                continue;
+            };
+            let dup_range = {
+                let dup_block = &self.blocks[dup_idx];
+                (dup_block.start, dup_block.end)
+            };
+            let Some(preds) = self.predecessors.get(&dup_start) else {
+                continue;
+            };
+
+            let valid_preds =
+                self.collect_tail_duplicate_predecessors(preds, &last_pc_to_block_start, registry);
+            if valid_preds.is_empty() {
+                continue;
+            }
+            self.apply_tail_duplication(dup_start, dup_range, &valid_preds);
+        }
+
+        let new_blocks: Vec<_> = self
+            .blocks
+            .iter()
+            .filter(|b| !to_duplicate.contains(&b.start))
+            .cloned()
+            .collect();
+
+        let eliminated = self.blocks.len() - new_blocks.len();
+        self.blocks = new_blocks;
+        if eliminated > 0 {
+            trace!(eliminated = eliminated, "tail_duplicate complete");
+        }
+        eliminated
+    }
+
+    fn collect_tail_duplicate_candidates(
+        &self,
+        entry_points: &[u64],
+        max_dup_size: usize,
+        registry: &ExtensionRegistry<X>,
+    ) -> FxHashSet<u64> {
+        let mut to_duplicate = FxHashSet::default();
+
+        for block in &self.blocks {
+            if entry_points.contains(&block.start) {
+                continue;
+            }
+            if block.instruction_count > max_dup_size {
+                continue;
+            }
+
+            let Some(preds) = self.predecessors.get(&block.start) else {
+                continue;
+            };
+            if preds.len() < 2 {
+                continue;
+            }
+
+            if !self.block_ends_with_fall(block, registry) {
+                continue;
+            }
+
+            if preds
+                .iter()
+                .all(|&pred_pc| self.pred_is_unconditional_jump(pred_pc, registry))
+            {
+                to_duplicate.insert(block.start);
+            }
+        }
+
+        to_duplicate
+    }
+
+    fn block_ends_with_fall(&self, block: &BasicBlock, registry: &ExtensionRegistry<X>) -> bool {
+        self.instruction_table
+            .get_at_pc(block.last_pc)
+            .is_some_and(|instr| {
+                matches!(
+                    registry.lift(instr).terminator,
+                    rvr_ir::Terminator::Fall { .. }
+                )
+            })
+    }
+
+    fn pred_is_unconditional_jump(&self, pred_pc: u64, registry: &ExtensionRegistry<X>) -> bool {
+        self.instruction_table
+            .get_at_pc(pred_pc)
+            .is_some_and(|instr| {
+                let ir = registry.lift(instr);
+                matches!(ir.terminator, rvr_ir::Terminator::Jump { .. })
+                    && !jumps_to_helper::<X>(&ir.terminator)
+            })
+    }
+
+    fn collect_tail_duplicate_predecessors(
+        &self,
+        preds: &FxHashSet<u64>,
+        last_pc_to_block_start: &HashMap<u64, u64>,
+        registry: &ExtensionRegistry<X>,
+    ) -> Vec<u64> {
+        let mut valid_preds: Vec<(u64, bool)> = preds
+            .iter()
+            .filter_map(|&pred_pc| {
+                let pred_start = *last_pc_to_block_start.get(&pred_pc)?;
+                let instr = self.instruction_table.get_at_pc(pred_pc)?;
+                let ir = registry.lift(instr);
+                if jumps_to_helper::<X>(&ir.terminator) {
+                    return None;
+                }
+                let (is_direct, is_explicit) = match &ir.terminator {
+                    rvr_ir::Terminator::Jump { .. } | rvr_ir::Terminator::Branch { .. } => {
+                        (true, true)
+                    }
+                    rvr_ir::Terminator::Fall { .. } => (true, false),
+                    _ => (false, false),
+                };
+                if is_direct {
+                    Some((pred_start, is_explicit))
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        valid_preds.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        valid_preds.into_iter().map(|(addr, _)| addr).collect()
+    }
+
+    fn apply_tail_duplication(
+        &mut self,
+        dup_start: u64,
+        dup_range: (u64, u64),
+        valid_preds: &[u64],
+    ) {
+        let mut first_pred = true;
+        for &pred_start in valid_preds {
+            self.block_continuations
+                .entry(pred_start)
+                .or_default()
+                .push(dup_range);
+            self.transforms
+                .entry(pred_start)
+                .or_default()
+                .push(BlockTransform::TailDuplicated {
+                    start: dup_range.0,
+                    end: dup_range.1,
+                });
+
+            if first_pred {
+                self.absorbed_to_merged.insert(dup_start, pred_start);
+                first_pred = false;
+            }
+        }
+    }
+}
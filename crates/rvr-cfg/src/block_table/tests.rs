@@ -0,0 +1,309 @@
+use super::*;
+use rvr_ir::{Rv64, Terminator};
+
+#[test]
+fn test_block_table_linear() {
+    let registry = ExtensionRegistry::<Rv64>::standard();
+    // Two ADDI instructions
+    let code = [
+        0x93, 0x00, 0xa0, 0x02, // addi x1, x0, 42
+        0x13, 0x01, 0xb0, 0x03, // addi x2, x0, 59
+    ];
+    let instr_table = InstructionTable::from_bytes(&code, 0x8000_0000, &registry);
+    let block_table = BlockTable::linear(instr_table);
+
+    assert_eq!(block_table.len(), 2);
+    assert_eq!(block_table.blocks[0].start, 0x8000_0000);
+    assert_eq!(block_table.blocks[0].end, 0x8000_0004);
+    assert_eq!(block_table.blocks[1].start, 0x8000_0004);
+    assert_eq!(block_table.blocks[1].end, 0x8000_0008);
+}
+
+#[test]
+fn test_block_table_with_branch() {
+    let registry = ExtensionRegistry::<Rv64>::standard();
+    // BEQ x0, x0, +4 (always taken)
+    let code = [
+        0x63, 0x02, 0x00, 0x00, // beq x0, x0, 4
+        0x13, 0x00, 0x00, 0x00, // nop (unreachable)
+        0x93, 0x00, 0xa0, 0x02, // addi x1, x0, 42
+    ];
+    let instr_table = InstructionTable::from_bytes(&code, 0x8000_0000, &registry);
+    let block_table = BlockTable::from_instruction_table(instr_table, &registry);
+
+    // Should have at least 2 blocks (branch creates leader at target)
+    assert!(block_table.len() >= 2);
+}
+
+#[test]
+fn test_hooked_function_body_is_skipped() {
+    let registry = ExtensionRegistry::<Rv64>::standard();
+    let code = [
+        0xef, 0x00, 0x80, 0x00, // jal ra, 8
+        0x73, 0x00, 0x00, 0x00, // ecall
+        0x93, 0x80, 0x10, 0x00, // f: addi x1, x1, 1
+        0x63, 0x04, 0x00, 0x00, // beq x0, x0, 8
+        0x67, 0x80, 0x00, 0x00, // ret
+        0x67, 0x80, 0x00, 0x00, // ret
+    ];
+    let blocks = |hooked: bool| {
+        let mut instr_table = InstructionTable::from_bytes(&code, 0x8000_0000, &registry);
+        if hooked {
+            instr_table.add_hooked_function(0x8000_0008, 0x8000_0018);
+        }
+        let block_table = BlockTable::from_instruction_table(instr_table, &registry);
+        block_table
+            .blocks
+            .iter()
+            .map(|block| (block.start, block.end))
+            .collect::<Vec<_>>()
+    };
+    assert!(blocks(false).contains(&(0x8000_0014, 0x8000_0018)));
+    assert_eq!(
+        blocks(true),
+        [
+            (0x8000_0000, 0x8000_0004),
+            (0x8000_0004, 0x8000_0008),
+            (0x8000_0008, 0x8000_000c),
+        ]
+    );
+}
+
+#[test]
+fn test_merge_records_transform() {
+    let registry = ExtensionRegistry::<Rv64>::standard();
+    let code = [
+        0x6f, 0x00, 0x40, 0x00, // j 4
+        0x93, 0x00, 0xa0, 0x02, // addi x1, x0, 42
+        0x73, 0x00, 0x00, 0x00, // ecall
+    ];
+    let instr_table = InstructionTable::from_bytes(&code, 0x8000_0000, &registry);
+    let mut block_table = BlockTable::from_instruction_table(instr_table, &registry);
+
+    assert_eq!(
+        block_table.merge_blocks(BlockLimits::default(), &registry),
+        1
+    );
+    assert_eq!(
+        block_table.transforms(0x8000_0000),
+        [BlockTransform::Merged {
+            start: 0x8000_0004,
+            end: 0x8000_000c,
+        }]
+    );
+    assert!(block_table.transforms(0x8000_0004).is_empty());
+}
+
+/// `count` x (`addi x1, x1, 1; j +4`), then `ecall`.
+fn jump_chain(count: usize) -> Vec<u8> {
+    let piece = [
+        0x93, 0x80, 0x10, 0x00, // addi x1, x1, 1
+        0x6f, 0x00, 0x40, 0x00, // j 4
+    ];
+    let mut code = piece.repeat(count);
+    code.extend_from_slice(&[0x73, 0x00, 0x00, 0x00]); // ecall
+    code
+}
+
+fn starts_and_sizes(block_table: &BlockTable<Rv64>) -> Vec<(u64, usize)> {
+    block_table
+        .iter()
+        .map(|b| (b.start, b.instruction_count))
+        .collect()
+}
+
+#[test]
+fn test_merge_respects_limits() {
+    let registry = ExtensionRegistry::<Rv64>::standard();
+    let build = |limits| {
+        let instr_table = InstructionTable::from_bytes(&jump_chain(4), 0x8000_0000, &registry);
+        let mut block_table = BlockTable::from_instruction_table(instr_table, &registry);
+        block_table.merge_blocks(limits, &registry);
+        block_table
+    };
+
+    let unlimited = build(BlockLimits::default());
+    assert_eq!(
+        starts_and_sizes(&unlimited),
+        [(0x8000_0000, 4), (0x8000_0010, 4), (0x8000_0020, 1)]
+    );
+
+    for limits in [
+        BlockLimits {
+            max_instrs: 3,
+            ..BlockLimits::default()
+        },
+        BlockLimits {
+            max_blocks: 1,
+            ..BlockLimits::default()
+        },
+    ] {
+        let limited = build(limits);
+        assert_eq!(
+            starts_and_sizes(&limited),
+            [
+                (0x8000_0000, 2),
+                (0x8000_0008, 2),
+                (0x8000_0010, 2),
+                (0x8000_0018, 2),
+                (0x8000_0020, 1),
+            ],
+            "{limits:?}"
+        );
+        assert!(limited.absorbed_to_merged.is_empty());
+    }
+}
+
+#[test]
+fn test_split_long_blocks() {
+    let registry = ExtensionRegistry::<Rv64>::standard();
+    // 10 x `addi x1, x1, 1`, then `ecall`
+    let mut code = [0x93, 0x80, 0x10, 0x00].repeat(10);
+    code.extend_from_slice(&[0x73, 0x00, 0x00, 0x00]);
+    let instr_table = InstructionTable::from_bytes(&code, 0x8000_0000, &registry);
+    let mut block_table = BlockTable::from_instruction_table(instr_table, &registry);
+    assert_eq!(starts_and_sizes(&block_table), [(0x8000_0000, 11)]);
+
+    assert_eq!(block_table.split_long_blocks(4), 2);
+    assert_eq!(
+        starts_and_sizes(&block_table),
+        [(0x8000_0000, 4), (0x8000_0010, 4), (0x8000_0020, 3)]
+    );
+    let ends: Vec<_> = block_table.iter().map(|b| (b.end, b.last_pc)).collect();
+    assert_eq!(
+        ends,
+        [
+            (0x8000_0010, 0x8000_000c),
+            (0x8000_0020, 0x8000_001c),
+            (0x8000_002c, 0x8000_0028),
+        ]
+    );
+    assert_eq!(block_table.split_long_blocks(4), 0);
+}
+
+#[test]
+fn test_superblock_respects_instr_limit() {
+    let registry = ExtensionRegistry::<Rv64>::standard();
+    let code = [
+        0x63, 0x84, 0x00, 0x00, // beq x1, x0, 8
+        0x93, 0x80, 0x10, 0x00, // addi x1, x1, 1
+        0x93, 0x80, 0x10, 0x00, // addi x1, x1, 1
+        0x73, 0x00, 0x00, 0x00, // ecall
+    ];
+    let build = |limits| {
+        let instr_table = InstructionTable::from_bytes(&code, 0x8000_0000, &registry);
+        let mut block_table = BlockTable::from_instruction_table(instr_table, &registry);
+        block_table.optimize(limits, &registry);
+        block_table
+    };
+
+    let unlimited = build(BlockLimits::default());
+    assert_eq!(
+        unlimited.transforms(0x8000_0000),
+        [BlockTransform::Superblock {
+            start: 0x8000_0004,
+            end: 0x8000_0008,
+        }]
+    );
+
+    let limited = build(BlockLimits {
+        max_instrs: 1,
+        ..BlockLimits::default()
+    });
+    assert!(limited.transforms(0x8000_0000).is_empty());
+    assert!(limited.iter().any(|b| b.start == 0x8000_0004));
+}
+
+#[test]
+fn test_analysis_independent_of_thread_count() {
+    let registry = ExtensionRegistry::<Rv64>::standard();
+    let code = [
+        0xef, 0x00, 0xc0, 0x00, // jal ra, 12 (call 0x8000000c)
+        0x73, 0x00, 0x00, 0x00, // ecall
+        0x13, 0x00, 0x00, 0x00, // nop (unreachable)
+        0x13, 0x05, 0x10, 0x00, // addi a0, x0, 1
+        0x67, 0x80, 0x00, 0x00, // ret
+    ];
+    let build = |threads| {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap()
+            .install(|| {
+                let instr_table = InstructionTable::from_bytes(&code, 0x8000_0000, &registry);
+                BlockTable::from_instruction_table(instr_table, &registry)
+            })
+    };
+    let extent = |table: &BlockTable<Rv64>| {
+        let blocks: Vec<_> = table.iter().map(|b| (b.start, b.end)).collect();
+        let mut edges: Vec<_> = table
+            .successors
+            .iter()
+            .flat_map(|(&pc, succs)| succs.iter().map(move |&s| (pc, s)))
+            .collect();
+        edges.sort_unstable();
+        (blocks, edges)
+    };
+
+    let serial = build(1);
+    let parallel = build(4);
+    assert_eq!(extent(&serial), extent(&parallel));
+    // The callee's return reaches the call's return site across regions
+    assert!(serial.successors[&0x8000_0010].contains(&0x8000_0004));
+    assert!(serial.blocks.iter().any(|b| b.start == 0x8000_0004));
+}
+
+#[test]
+fn test_expansion_exit_target_is_leader() {
+    use rvr_ir::{HelperBlock, InstrIR};
+    use rvr_isa::{DecodedInstr, InstructionOverride, OP_ECALL};
+
+    struct SkipAhead;
+    impl InstructionOverride<Rv64> for SkipAhead {
+        fn lift(
+            &self,
+            instr: &DecodedInstr<Rv64>,
+            _default: &dyn Fn(&DecodedInstr<Rv64>) -> InstrIR<Rv64>,
+        ) -> InstrIR<Rv64> {
+            InstrIR::new(
+                instr.pc,
+                instr.size,
+                instr.opid.pack(),
+                instr.raw,
+                Vec::new(),
+                Terminator::jump(OverrideExpansion::<Rv64>::helper_target(0)),
+            )
+        }
+
+        fn expand(
+            &self,
+            instr: &DecodedInstr<Rv64>,
+            default_lift: &dyn Fn(&DecodedInstr<Rv64>) -> InstrIR<Rv64>,
+        ) -> OverrideExpansion<Rv64> {
+            OverrideExpansion::new(self.lift(instr, default_lift))
+                .with_helper(HelperBlock::new(Vec::new(), Terminator::jump(instr.pc + 8)))
+        }
+    }
+
+    let registry = ExtensionRegistry::<Rv64>::standard().with_override(OP_ECALL, SkipAhead);
+    let code = [
+        0x73, 0x00, 0x00, 0x00, // ecall
+        0x93, 0x00, 0xa0, 0x02, // addi x1, x0, 42
+        0x13, 0x01, 0xb0, 0x03, // addi x2, x0, 59
+        0x67, 0x80, 0x00, 0x00, // ret
+    ];
+    let instr_table = InstructionTable::from_bytes(&code, 0x8000_0000, &registry);
+    let mut block_table = BlockTable::from_instruction_table(instr_table, &registry);
+    block_table.merge_blocks(BlockLimits::default(), &registry);
+    block_table.tail_duplicate(8, &registry);
+
+    assert!(block_table.blocks.iter().any(|b| b.start == 0x8000_0008));
+    assert_eq!(block_table.blocks[0].end, 0x8000_0004);
+}
+
+#[test]
+fn test_basic_block() {
+    let block = BasicBlock::new(0x1000, 0x1010, 4, 0x100c);
+    assert_eq!(block.size(), 16);
+    assert_eq!(block.instruction_count, 4);
+}
@@ -78,6 +78,8 @@ pub struct InstructionTable<X: Xlen> {
     entry_points: Vec<u64>,
     /// Read-only segments for constant propagation.
     ro_segments: Vec<RoSegment>,
+    /// Hooked functions as `[entry, end)` (see `add_hooked_function`).
+    hooked_functions: Vec<(u64, u64)>,
 }
 
 impl<X: Xlen> InstructionTable<X> {
@@ -97,6 +99,7 @@ impl<X: Xlen> InstructionTable<X> {
             end_address,
             entry_points: vec![base_address],
             ro_segments: vec![RoSegment::new(base_address, end_address, code.to_vec())],
+            hooked_functions: Vec::new(),
         };
 
         table.decode_all(code, 0, registry);
@@ -121,6 +124,7 @@ impl<X: Xlen> InstructionTable<X> {
             end_address,
             entry_points: vec![entry_point],
             ro_segments: Vec::new(),
+            hooked_functions: Vec::new(),
         }
     }
 
@@ -266,6 +270,28 @@ impl<X: Xlen> InstructionTable<X> {
         }
     }
 
+    /// Mark the function `[entry, end)` as hooked: its entry instruction is
+    /// lifted as the hook followed by a return, so CFG analysis treats it
+    /// as a return and only reaches the body through other references
+    /// (e.g. code pointers in data).
+    pub fn add_hooked_function(&mut self, entry: u64, end: u64) {
+        self.hooked_functions.push((entry, end.max(entry)));
+    }
+
+    /// Check if `pc` is the entry of a hooked function.
+    #[must_use]
+    pub fn is_hooked_entry(&self, pc: u64) -> bool {
+        self.hooked_functions.iter().any(|&(entry, _)| entry == pc)
+    }
+
+    /// Check if `pc` is in the body of a hooked function, past its entry.
+    #[must_use]
+    pub fn in_hooked_body(&self, pc: u64) -> bool {
+        self.hooked_functions
+            .iter()
+            .any(|&(entry, end)| pc > entry && pc < end)
+    }
+
     /// Get total number of slots.
     #[must_use]
     pub const fn len(&self) -> usize {
@@ -3,6 +3,8 @@
 mod analysis;
 mod block_table;
 mod instruction_table;
+mod timing;
 
 pub use block_table::*;
 pub use instruction_table::*;
+pub use timing::*;
@@ -0,0 +1,38 @@
+//! Timing for parallel analysis phases.
+
+use std::ops::AddAssign;
+use std::time::{Duration, Instant};
+
+/// Wall-clock and summed per-task time of a parallel phase.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ParallelTime {
+    /// Elapsed wall-clock time.
+    pub wall: Duration,
+    /// Sum of per-task times (what one thread would have spent).
+    pub work: Duration,
+}
+
+impl ParallelTime {
+    /// Speedup of the phase over running its tasks on one thread.
+    #[must_use]
+    pub fn speedup(&self) -> f64 {
+        if self.wall.is_zero() {
+            return 1.0;
+        }
+        self.work.as_secs_f64() / self.wall.as_secs_f64()
+    }
+}
+
+impl AddAssign for ParallelTime {
+    fn add_assign(&mut self, other: Self) {
+        self.wall += other.wall;
+        self.work += other.work;
+    }
+}
+
+/// Run `f`, returning its result and elapsed time.
+pub fn timed<R>(f: impl FnOnce() -> R) -> (R, Duration) {
+    let start = Instant::now();
+    let result = f();
+    (result, start.elapsed())
+}
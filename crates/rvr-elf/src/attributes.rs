@@ -0,0 +1,188 @@
+//! The `.riscv.attributes` section (`SHT_RISCV_ATTRIBUTES`).
+//!
+//! Build attributes record what the object was compiled for. The section is
+//! a format version byte (`'A'`), then per-vendor subsections of
+//! file-scope attributes: a ULEB128 tag followed by a ULEB128 value for
+//! even tags and a NUL-terminated string for odd tags.
+
+/// Format version byte at the start of the section.
+const FORMAT_VERSION: u8 = b'A';
+/// Vendor name of the standard RISC-V attributes.
+const VENDOR: &str = "riscv";
+/// Sub-subsection tag of attributes that apply to the whole file.
+const TAG_FILE: u8 = 1;
+/// `Tag_RISCV_stack_align`: stack alignment in bytes.
+pub const TAG_RISCV_STACK_ALIGN: u64 = 4;
+/// `Tag_RISCV_arch`: the ISA string, e.g. `rv64i2p1_m2p0_a2p1_c2p0`.
+pub const TAG_RISCV_ARCH: u64 = 5;
+
+/// RISC-V build attributes of an ELF.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ArchAttributes {
+    /// ISA string (`Tag_RISCV_arch`).
+    pub arch: Option<String>,
+    /// Stack alignment in bytes (`Tag_RISCV_stack_align`).
+    pub stack_align: Option<u64>,
+}
+
+impl ArchAttributes {
+    /// Parse the contents of a `.riscv.attributes` section.
+    ///
+    /// Returns `None` if the format version is unknown. Parsing stops at
+    /// the first malformed subsection, keeping what was read before it.
+    #[must_use]
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let (&version, mut rest) = data.split_first()?;
+        if version != FORMAT_VERSION {
+            return None;
+        }
+        let mut attributes = Self::default();
+        while rest.len() >= 4 {
+            let len = read_len(rest)?.min(rest.len());
+            let (subsection, tail) = rest.split_at(len);
+            rest = tail;
+            let Some(vendor_end) = subsection
+                .get(4..)
+                .and_then(|s| s.iter().position(|&b| b == 0))
+            else {
+                break;
+            };
+            if &subsection[4..4 + vendor_end] == VENDOR.as_bytes() {
+                attributes.parse_vendor(&subsection[4 + vendor_end + 1..]);
+            }
+        }
+        Some(attributes)
+    }
+
+    /// Parse the sub-subsections of the `riscv` vendor subsection.
+    fn parse_vendor(&mut self, mut data: &[u8]) {
+        while data.len() >= 5 {
+            let tag = data[0];
+            let Some(len) = read_len(&data[1..]).map(|len| len.min(data.len())) else {
+                return;
+            };
+            if len < 5 {
+                return;
+            }
+            if tag == TAG_FILE {
+                self.parse_file_attributes(&data[5..len]);
+            }
+            data = &data[len..];
+        }
+    }
+
+    fn parse_file_attributes(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            let Some((tag, rest)) = read_uleb128(data) else {
+                return;
+            };
+            if tag % 2 == 0 {
+                let Some((value, rest)) = read_uleb128(rest) else {
+                    return;
+                };
+                if tag == TAG_RISCV_STACK_ALIGN {
+                    self.stack_align = Some(value);
+                }
+                data = rest;
+            } else {
+                let Some(end) = rest.iter().position(|&b| b == 0) else {
+                    return;
+                };
+                if tag == TAG_RISCV_ARCH {
+                    self.arch = Some(String::from_utf8_lossy(&rest[..end]).into_owned());
+                }
+                data = &rest[end + 1..];
+            }
+        }
+    }
+
+    /// Serialize as the contents of a `.riscv.attributes` section.
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        let mut attributes = Vec::new();
+        if let Some(align) = self.stack_align {
+            push_uleb128(&mut attributes, TAG_RISCV_STACK_ALIGN);
+            push_uleb128(&mut attributes, align);
+        }
+        if let Some(arch) = &self.arch {
+            push_uleb128(&mut attributes, TAG_RISCV_ARCH);
+            attributes.extend_from_slice(arch.as_bytes());
+            attributes.push(0);
+        }
+
+        let mut file = vec![TAG_FILE];
+        push_len(&mut file, 5 + attributes.len());
+        file.extend_from_slice(&attributes);
+
+        let mut out = vec![FORMAT_VERSION];
+        push_len(&mut out, 4 + VENDOR.len() + 1 + file.len());
+        out.extend_from_slice(VENDOR.as_bytes());
+        out.push(0);
+        out.extend_from_slice(&file);
+        out
+    }
+}
+
+fn read_len(data: &[u8]) -> Option<usize> {
+    let bytes = data.get(..4)?.try_into().ok()?;
+    usize::try_from(u32::from_le_bytes(bytes)).ok()
+}
+
+fn push_len(out: &mut Vec<u8>, len: usize) {
+    let len = u32::try_from(len).expect("attributes section too large");
+    out.extend_from_slice(&len.to_le_bytes());
+}
+
+fn read_uleb128(data: &[u8]) -> Option<(u64, &[u8])> {
+    let mut value = 0u64;
+    for (i, &byte) in data.iter().enumerate().take(10) {
+        value |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Some((value, &data[i + 1..]));
+        }
+    }
+    None
+}
+
+fn push_uleb128(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = u8::try_from(value & 0x7f).unwrap_or(0);
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attributes_round_trip() {
+        let attributes = ArchAttributes {
+            arch: Some("rv64i2p1_m2p0_a2p1_c2p0_zicsr2p0".to_string()),
+            stack_align: Some(16),
+        };
+        assert_eq!(
+            ArchAttributes::parse(&attributes.encode()),
+            Some(attributes)
+        );
+    }
+
+    #[test]
+    fn test_attributes_skip_unknown_tags_and_vendors() {
+        // Laid out as GCC writes it: stack_align, arch, unaligned_access,
+        // priv_spec, priv_spec_minor
+        let mut data = b"A\x2b\x00\x00\x00riscv\x00\x01\x21\x00\x00\x00\x04\x10\x05".to_vec();
+        data.extend_from_slice(b"rv32i2p1_m2p0_c2p0\x00\x06\x00\x08\x01\x0a\x0b");
+        // A vendor subsection that is not ours
+        data.extend_from_slice(b"\x0b\x00\x00\x00gnu\x00\x01\x00\x00");
+        let attributes = ArchAttributes::parse(&data).unwrap();
+        assert_eq!(attributes.arch.as_deref(), Some("rv32i2p1_m2p0_c2p0"));
+        assert_eq!(attributes.stack_align, Some(16));
+        assert_eq!(ArchAttributes::parse(b"B"), None);
+    }
+}
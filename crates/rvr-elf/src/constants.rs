@@ -6,7 +6,9 @@ pub const ELF_CLASS_32: u8 = 1;
 pub const ELF_CLASS_64: u8 = 2;
 pub const ELF_DATA_LSB: u8 = 1;
 pub const ELF_VERSION_CURRENT: u8 = 1;
+pub const ELF_TYPE_REL: u16 = 1; // Relocatable object (.o)
 pub const ELF_TYPE_EXEC: u16 = 2;
+pub const ELF_TYPE_DYN: u16 = 3; // Position-independent executable or shared object
 pub const ELF_MACHINE_RISCV: u16 = 243;
 
 // Program header constants
@@ -40,6 +42,12 @@ pub const SHT_NOBITS: u32 = 8;
 pub const SHT_REL: u32 = 9;
 pub const SHT_SHLIB: u32 = 10;
 pub const SHT_DYNSYM: u32 = 11;
+pub const SHT_RISCV_ATTRIBUTES: u32 = 0x7000_0003; // .riscv.attributes
+
+// Special section indices
+pub const SHN_UNDEF: u16 = 0;
+pub const SHN_ABS: u16 = 0xFFF1;
+pub const SHN_COMMON: u16 = 0xFFF2;
 
 // Section flags
 pub const SHF_WRITE: u64 = 0x1;
@@ -58,6 +66,89 @@ pub const STT_FUNC: u8 = 2;
 pub const STT_SECTION: u8 = 3;
 pub const STT_FILE: u8 = 4;
 
+// Dynamic section tags
+pub const DT_NULL: u64 = 0;
+pub const DT_NEEDED: u64 = 1;
+
+// RISC-V relocation types (RISC-V ELF psABI)
+pub const R_RISCV_NONE: u32 = 0;
+pub const R_RISCV_32: u32 = 1; // S + A, 32-bit word
+pub const R_RISCV_64: u32 = 2; // S + A, 64-bit word
+pub const R_RISCV_RELATIVE: u32 = 3; // Load bias + addend
+pub const R_RISCV_BRANCH: u32 = 16; // S + A - P, B-type
+pub const R_RISCV_JAL: u32 = 17; // S + A - P, J-type
+pub const R_RISCV_CALL: u32 = 18; // S + A - P, AUIPC + JALR pair
+pub const R_RISCV_CALL_PLT: u32 = 19; // Same as R_RISCV_CALL without a PLT
+pub const R_RISCV_PCREL_HI20: u32 = 23; // S + A - P, U-type
+pub const R_RISCV_PCREL_LO12_I: u32 = 24; // Low 12 bits of the paired PCREL_HI20, I-type
+pub const R_RISCV_PCREL_LO12_S: u32 = 25; // Low 12 bits of the paired PCREL_HI20, S-type
+pub const R_RISCV_HI20: u32 = 26; // S + A, U-type
+pub const R_RISCV_LO12_I: u32 = 27; // S + A, I-type
+pub const R_RISCV_LO12_S: u32 = 28; // S + A, S-type
+pub const R_RISCV_ALIGN: u32 = 43; // Linker relaxation padding
+pub const R_RISCV_RVC_BRANCH: u32 = 44; // S + A - P, CB-type
+pub const R_RISCV_RVC_JUMP: u32 = 45; // S + A - P, CJ-type
+pub const R_RISCV_RELAX: u32 = 51; // Previous relocation may be relaxed
+
+/// psABI name of relocation type `r_type`.
+#[must_use]
+pub const fn relocation_name(r_type: u32) -> &'static str {
+    match r_type {
+        R_RISCV_NONE => "R_RISCV_NONE",
+        R_RISCV_32 => "R_RISCV_32",
+        R_RISCV_64 => "R_RISCV_64",
+        R_RISCV_RELATIVE => "R_RISCV_RELATIVE",
+        4 => "R_RISCV_COPY",
+        5 => "R_RISCV_JUMP_SLOT",
+        6 => "R_RISCV_TLS_DTPMOD32",
+        7 => "R_RISCV_TLS_DTPMOD64",
+        8 => "R_RISCV_TLS_DTPREL32",
+        9 => "R_RISCV_TLS_DTPREL64",
+        10 => "R_RISCV_TLS_TPREL32",
+        11 => "R_RISCV_TLS_TPREL64",
+        R_RISCV_BRANCH => "R_RISCV_BRANCH",
+        R_RISCV_JAL => "R_RISCV_JAL",
+        R_RISCV_CALL => "R_RISCV_CALL",
+        R_RISCV_CALL_PLT => "R_RISCV_CALL_PLT",
+        20 => "R_RISCV_GOT_HI20",
+        21 => "R_RISCV_TLS_GOT_HI20",
+        22 => "R_RISCV_TLS_GD_HI20",
+        R_RISCV_PCREL_HI20 => "R_RISCV_PCREL_HI20",
+        R_RISCV_PCREL_LO12_I => "R_RISCV_PCREL_LO12_I",
+        R_RISCV_PCREL_LO12_S => "R_RISCV_PCREL_LO12_S",
+        R_RISCV_HI20 => "R_RISCV_HI20",
+        R_RISCV_LO12_I => "R_RISCV_LO12_I",
+        R_RISCV_LO12_S => "R_RISCV_LO12_S",
+        29 => "R_RISCV_TPREL_HI20",
+        30 => "R_RISCV_TPREL_LO12_I",
+        31 => "R_RISCV_TPREL_LO12_S",
+        32 => "R_RISCV_TPREL_ADD",
+        33 => "R_RISCV_ADD8",
+        34 => "R_RISCV_ADD16",
+        35 => "R_RISCV_ADD32",
+        36 => "R_RISCV_ADD64",
+        37 => "R_RISCV_SUB8",
+        38 => "R_RISCV_SUB16",
+        39 => "R_RISCV_SUB32",
+        40 => "R_RISCV_SUB64",
+        R_RISCV_ALIGN => "R_RISCV_ALIGN",
+        R_RISCV_RVC_BRANCH => "R_RISCV_RVC_BRANCH",
+        R_RISCV_RVC_JUMP => "R_RISCV_RVC_JUMP",
+        R_RISCV_RELAX => "R_RISCV_RELAX",
+        52 => "R_RISCV_SUB6",
+        53 => "R_RISCV_SET6",
+        54 => "R_RISCV_SET8",
+        55 => "R_RISCV_SET16",
+        56 => "R_RISCV_SET32",
+        57 => "R_RISCV_32_PCREL",
+        58 => "R_RISCV_IRELATIVE",
+        59 => "R_RISCV_PLT32",
+        60 => "R_RISCV_SET_ULEB128",
+        61 => "R_RISCV_SUB_ULEB128",
+        _ => "R_RISCV_<unknown>",
+    }
+}
+
 // RISC-V ELF e_flags (RISC-V ELF psABI)
 pub const EF_RISCV_RVC: u32 = 0x1; // Uses C (compressed) extension
 pub const EF_RISCV_FLOAT_ABI_SOFT: u32 = 0x0; // Soft-float ABI
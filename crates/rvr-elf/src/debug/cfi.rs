@@ -0,0 +1,695 @@
+//! Call frame information from `.eh_frame`.
+//!
+//! Parses the CIEs and FDEs of `.eh_frame` and runs the CFA program of the
+//! FDE covering a PC, giving the rules to recover the caller's registers
+//! there. The rules RISC-V compilers emit are supported; a CFA defined by a
+//! DWARF expression is not, and registers saved by expression are reported
+//! as [`RegisterRule::Undefined`].
+
+use rustc_hash::FxHashMap;
+use rvr_isa::Xlen;
+
+use crate::image::ElfImage;
+
+/// Section holding the call frame information.
+pub const EH_FRAME_SECTION: &str = ".eh_frame";
+
+/// `DW_EH_PE_*` pointer encodings.
+const DW_EH_PE_OMIT: u8 = 0xff;
+const DW_EH_PE_ABSPTR: u8 = 0x00;
+const DW_EH_PE_ULEB128: u8 = 0x01;
+const DW_EH_PE_UDATA2: u8 = 0x02;
+const DW_EH_PE_UDATA4: u8 = 0x03;
+const DW_EH_PE_UDATA8: u8 = 0x04;
+const DW_EH_PE_SLEB128: u8 = 0x09;
+const DW_EH_PE_SDATA2: u8 = 0x0a;
+const DW_EH_PE_SDATA4: u8 = 0x0b;
+const DW_EH_PE_SDATA8: u8 = 0x0c;
+const DW_EH_PE_PCREL: u8 = 0x10;
+/// Application bits (pcrel, datarel, ...) of an encoding, without indirect.
+const DW_EH_PE_APPLICATION: u8 = 0x70;
+
+/// How to recover a register's value in the caller's frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RegisterRule {
+    /// Unchanged by this frame (callee-saved and not yet saved).
+    SameValue,
+    /// Not recoverable.
+    Undefined,
+    /// Saved in memory at `CFA + offset`.
+    Offset(i64),
+    /// The value `CFA + offset`.
+    ValOffset(i64),
+    /// Held in another register.
+    Register(u16),
+}
+
+/// Rules to recover the caller's registers at one PC.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnwindRow {
+    /// Register the CFA (the stack pointer at the call) is based on.
+    pub cfa_register: u16,
+    /// Offset of the CFA from `cfa_register`.
+    pub cfa_offset: i64,
+    /// Column holding the return address (`ra` on RISC-V).
+    pub return_address: u16,
+    rules: Vec<(u16, RegisterRule)>,
+}
+
+impl UnwindRow {
+    /// Rule for `reg`; registers without one keep their value.
+    #[must_use]
+    pub fn rule(&self, reg: u16) -> RegisterRule {
+        self.rules
+            .iter()
+            .find(|&&(r, _)| r == reg)
+            .map_or(RegisterRule::SameValue, |&(_, rule)| rule)
+    }
+
+    /// Registers with an explicit rule.
+    pub fn rules(&self) -> impl Iterator<Item = (u16, RegisterRule)> + '_ {
+        self.rules.iter().copied()
+    }
+
+    fn set(&mut self, reg: u16, rule: RegisterRule) {
+        if let Some(entry) = self.rules.iter_mut().find(|(r, _)| *r == reg) {
+            entry.1 = rule;
+        } else {
+            self.rules.push((reg, rule));
+        }
+    }
+}
+
+/// Common information entry.
+#[derive(Clone, Debug)]
+struct Cie {
+    code_align: u64,
+    data_align: i64,
+    return_address: u16,
+    fde_encoding: u8,
+    has_augmentation_data: bool,
+    /// Initial instructions (offsets into the section).
+    instructions: (usize, usize),
+}
+
+/// Frame description entry.
+#[derive(Clone, Debug)]
+struct Fde {
+    start: u64,
+    end: u64,
+    cie: usize,
+    /// CFA program (offsets into the section).
+    instructions: (usize, usize),
+}
+
+/// Parsed `.eh_frame` of an image.
+#[derive(Clone, Debug, Default)]
+pub struct CallFrameInfo {
+    data: Vec<u8>,
+    /// Section address (base of `DW_EH_PE_pcrel` pointers).
+    addr: u64,
+    address_size: usize,
+    cies: Vec<Cie>,
+    /// Sorted by start address.
+    fdes: Vec<Fde>,
+}
+
+impl CallFrameInfo {
+    /// Call frame information of `image`, empty without an `.eh_frame`.
+    #[must_use]
+    pub fn from_image<X: Xlen>(image: &ElfImage<X>) -> Self {
+        image
+            .sections
+            .iter()
+            .find(|s| s.name == EH_FRAME_SECTION)
+            .map_or_else(Self::default, |section| {
+                Self::parse(section.data.clone(), X::to_u64(section.addr), X::REG_BYTES)
+            })
+    }
+
+    /// Parse `.eh_frame` contents loaded at `addr`.
+    ///
+    /// Malformed entries and FDEs with unsupported pointer encodings are
+    /// skipped.
+    #[must_use]
+    pub fn parse(data: Vec<u8>, addr: u64, address_size: usize) -> Self {
+        let mut info = Self {
+            data,
+            addr,
+            address_size,
+            ..Self::default()
+        };
+        let mut cie_index = FxHashMap::default();
+        let mut pos = 0;
+        while let Some((entry, id_pos, end)) = info.entry_at(pos) {
+            let mut reader = info.reader(id_pos + 4, end);
+            let id = info.reader(id_pos, end).u32();
+            match id {
+                Some(0) => {
+                    if let Some(cie) = info.parse_cie(&mut reader) {
+                        cie_index.insert(entry, info.cies.len());
+                        info.cies.push(cie);
+                    }
+                }
+                Some(offset) => {
+                    let cie = id_pos
+                        .checked_sub(offset as usize)
+                        .and_then(|cie| cie_index.get(&cie).copied());
+                    if let Some(fde) = cie.and_then(|cie| info.parse_fde(&mut reader, cie)) {
+                        info.fdes.push(fde);
+                    }
+                }
+                None => break,
+            }
+            pos = end;
+        }
+        info.fdes.sort_by_key(|fde| fde.start);
+        info
+    }
+
+    /// Check whether no function has unwind information.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.fdes.is_empty()
+    }
+
+    /// Rules to recover the caller's registers at `pc`, if an FDE covers it
+    /// and its CFA program is supported.
+    #[must_use]
+    pub fn row(&self, pc: u64) -> Option<UnwindRow> {
+        let index = self.fdes.partition_point(|fde| fde.start <= pc);
+        let fde = self.fdes.get(index.checked_sub(1)?)?;
+        if pc >= fde.end {
+            return None;
+        }
+        let cie = &self.cies[fde.cie];
+        let mut machine = CfaMachine::new(cie, fde.start, u64::MAX);
+        self.execute(&mut machine, cie.instructions)?;
+        machine.initial = Some(machine.row.clone());
+        machine.loc = fde.start;
+        machine.pc = pc;
+        self.execute(&mut machine, fde.instructions)?;
+        Some(machine.row)
+    }
+
+    /// Entry starting at `pos`: `(entry, id_pos, end)`, or `None` at the
+    /// terminator or end of the section.
+    fn entry_at(&self, pos: usize) -> Option<(usize, usize, usize)> {
+        let mut reader = self.reader(pos, self.data.len());
+        let length = match reader.u32()? {
+            0 => return None,
+            0xffff_ffff => usize::try_from(reader.u64()?).ok()?,
+            length => length as usize,
+        };
+        let id_pos = reader.pos;
+        let end = id_pos.checked_add(length)?;
+        (end <= self.data.len()).then_some((pos, id_pos, end))
+    }
+
+    fn reader(&self, pos: usize, end: usize) -> Reader<'_> {
+        Reader {
+            data: &self.data[..end],
+            pos,
+            addr: self.addr,
+            address_size: self.address_size,
+        }
+    }
+
+    fn parse_cie(&self, reader: &mut Reader<'_>) -> Option<Cie> {
+        let version = reader.u8()?;
+        let augmentation = reader.cstr()?;
+        if augmentation.contains("eh") {
+            reader.skip(self.address_size)?;
+        }
+        let code_align = reader.uleb()?;
+        let data_align = reader.sleb()?;
+        let return_address = if version == 1 {
+            u16::from(reader.u8()?)
+        } else {
+            u16::try_from(reader.uleb()?).ok()?
+        };
+
+        let mut fde_encoding = DW_EH_PE_ABSPTR;
+        let has_augmentation_data = augmentation.starts_with('z');
+        if has_augmentation_data {
+            let length = usize::try_from(reader.uleb()?).ok()?;
+            let data_end = reader.pos.checked_add(length)?;
+            for c in augmentation.chars().skip(1) {
+                match c {
+                    'R' => fde_encoding = reader.u8()?,
+                    'L' => {
+                        reader.u8()?;
+                    }
+                    'P' => {
+                        let encoding = reader.u8()?;
+                        reader.pointer(encoding & !0x80)?;
+                    }
+                    _ => break,
+                }
+            }
+            reader.pos = data_end;
+        }
+        Some(Cie {
+            code_align,
+            data_align,
+            return_address,
+            fde_encoding,
+            has_augmentation_data,
+            instructions: (reader.pos, reader.data.len()),
+        })
+    }
+
+    fn parse_fde(&self, reader: &mut Reader<'_>, cie: usize) -> Option<Fde> {
+        let encoding = self.cies[cie].fde_encoding;
+        let start = reader.pointer(encoding)?;
+        // The range is a length: same format, never pc-relative
+        let range = reader.pointer(encoding & 0x0f)?;
+        if self.cies[cie].has_augmentation_data {
+            let length = usize::try_from(reader.uleb()?).ok()?;
+            reader.skip(length)?;
+        }
+        Some(Fde {
+            start,
+            end: start.checked_add(range)?,
+            cie,
+            instructions: (reader.pos, reader.data.len()),
+        })
+    }
+
+    /// Run the CFA program in `start..end` until the location passes the
+    /// machine's PC.
+    fn execute(&self, machine: &mut CfaMachine<'_>, (start, end): (usize, usize)) -> Option<()> {
+        let mut reader = self.reader(start, end);
+        while reader.pos < end {
+            let op = reader.u8()?;
+            let advance = match op >> 6 {
+                0 => machine.extended(op, &mut reader)?,
+                primary => machine.primary(primary, op & 0x3f, &mut reader)?,
+            };
+            match advance {
+                Advance::None => {}
+                Advance::Stop => break,
+                Advance::By(delta) => {
+                    let delta = delta.checked_mul(machine.cie.code_align)?;
+                    machine.loc = machine.loc.checked_add(delta)?;
+                    if machine.loc > machine.pc {
+                        break;
+                    }
+                }
+            }
+        }
+        Some(())
+    }
+}
+
+/// Effect of a CFA instruction on the location.
+enum Advance {
+    None,
+    /// Advance by this many code alignment units.
+    By(u64),
+    /// The location jumped past the PC.
+    Stop,
+}
+
+/// State of a running CFA program.
+struct CfaMachine<'a> {
+    cie: &'a Cie,
+    row: UnwindRow,
+    /// Row after the CIE's initial instructions, for `DW_CFA_restore`.
+    initial: Option<UnwindRow>,
+    /// Rows pushed by `DW_CFA_remember_state`.
+    saved: Vec<UnwindRow>,
+    /// Location the row describes.
+    loc: u64,
+    /// Stop once the location passes this PC.
+    pc: u64,
+}
+
+impl<'a> CfaMachine<'a> {
+    const fn new(cie: &'a Cie, loc: u64, pc: u64) -> Self {
+        Self {
+            cie,
+            row: UnwindRow {
+                cfa_register: 0,
+                cfa_offset: 0,
+                return_address: cie.return_address,
+                rules: Vec::new(),
+            },
+            initial: None,
+            saved: Vec::new(),
+            loc,
+            pc,
+        }
+    }
+
+    /// Opcodes with an operand in their low six bits.
+    fn primary(&mut self, primary: u8, operand: u8, reader: &mut Reader<'_>) -> Option<Advance> {
+        let reg = u16::from(operand);
+        match primary {
+            // DW_CFA_advance_loc
+            1 => return Some(Advance::By(u64::from(operand))),
+            // DW_CFA_offset
+            2 => {
+                let offset = self.factored(reader.uleb()?)?;
+                self.row.set(reg, RegisterRule::Offset(offset));
+            }
+            // DW_CFA_restore
+            _ => self.restore(reg),
+        }
+        Some(Advance::None)
+    }
+
+    /// Opcodes with the top two bits clear.
+    fn extended(&mut self, op: u8, reader: &mut Reader<'_>) -> Option<Advance> {
+        match op {
+            // DW_CFA_nop
+            0x00 => Some(Advance::None),
+            0x01..=0x04 => self.advance(op, reader),
+            0x0c..=0x0e | 0x12 | 0x13 => {
+                self.define_cfa(op, reader)?;
+                Some(Advance::None)
+            }
+            _ => {
+                self.set_rule(op, reader)?;
+                Some(Advance::None)
+            }
+        }
+    }
+
+    fn advance(&mut self, op: u8, reader: &mut Reader<'_>) -> Option<Advance> {
+        let delta = match op {
+            // DW_CFA_set_loc
+            0x01 => {
+                let target = reader.pointer(self.cie.fde_encoding)?;
+                if target > self.pc {
+                    return Some(Advance::Stop);
+                }
+                self.loc = target;
+                return Some(Advance::None);
+            }
+            // DW_CFA_advance_loc1, 2 and 4
+            0x02 => u64::from(reader.u8()?),
+            0x03 => u64::from(reader.u16()?),
+            _ => u64::from(reader.u32()?),
+        };
+        Some(Advance::By(delta))
+    }
+
+    fn define_cfa(&mut self, op: u8, reader: &mut Reader<'_>) -> Option<()> {
+        match op {
+            // DW_CFA_def_cfa
+            0x0c => {
+                self.row.cfa_register = reader.reg()?;
+                self.row.cfa_offset = i64::try_from(reader.uleb()?).ok()?;
+            }
+            // DW_CFA_def_cfa_register
+            0x0d => self.row.cfa_register = reader.reg()?,
+            // DW_CFA_def_cfa_offset
+            0x0e => self.row.cfa_offset = i64::try_from(reader.uleb()?).ok()?,
+            // DW_CFA_def_cfa_sf
+            0x12 => {
+                self.row.cfa_register = reader.reg()?;
+                self.row.cfa_offset = self.factored_sf(reader.sleb()?)?;
+            }
+            // DW_CFA_def_cfa_offset_sf
+            _ => self.row.cfa_offset = self.factored_sf(reader.sleb()?)?,
+        }
+        Some(())
+    }
+
+    /// Register rule opcodes; `None` for unsupported ones such as
+    /// `DW_CFA_def_cfa_expression`.
+    fn set_rule(&mut self, op: u8, reader: &mut Reader<'_>) -> Option<()> {
+        match op {
+            // DW_CFA_offset_extended
+            0x05 => {
+                let reg = reader.reg()?;
+                let offset = self.factored(reader.uleb()?)?;
+                self.row.set(reg, RegisterRule::Offset(offset));
+            }
+            // DW_CFA_restore_extended
+            0x06 => self.restore(reader.reg()?),
+            // DW_CFA_undefined
+            0x07 => self.row.set(reader.reg()?, RegisterRule::Undefined),
+            // DW_CFA_same_value
+            0x08 => self.row.set(reader.reg()?, RegisterRule::SameValue),
+            // DW_CFA_register
+            0x09 => {
+                let reg = reader.reg()?;
+                self.row.set(reg, RegisterRule::Register(reader.reg()?));
+            }
+            // DW_CFA_remember_state
+            0x0a => self.saved.push(self.row.clone()),
+            // DW_CFA_restore_state
+            0x0b => self.row = self.saved.pop()?,
+            // DW_CFA_expression, DW_CFA_val_expression
+            0x10 | 0x16 => {
+                let reg = reader.reg()?;
+                let length = usize::try_from(reader.uleb()?).ok()?;
+                reader.skip(length)?;
+                self.row.set(reg, RegisterRule::Undefined);
+            }
+            // DW_CFA_offset_extended_sf
+            0x11 => {
+                let reg = reader.reg()?;
+                let offset = self.factored_sf(reader.sleb()?)?;
+                self.row.set(reg, RegisterRule::Offset(offset));
+            }
+            // DW_CFA_val_offset
+            0x14 => {
+                let reg = reader.reg()?;
+                let offset = self.factored(reader.uleb()?)?;
+                self.row.set(reg, RegisterRule::ValOffset(offset));
+            }
+            // DW_CFA_val_offset_sf
+            0x15 => {
+                let reg = reader.reg()?;
+                let offset = self.factored_sf(reader.sleb()?)?;
+                self.row.set(reg, RegisterRule::ValOffset(offset));
+            }
+            // DW_CFA_GNU_args_size
+            0x2e => {
+                reader.uleb()?;
+            }
+            // DW_CFA_GNU_negative_offset_extended
+            0x2f => {
+                let reg = reader.reg()?;
+                let offset = self.factored(reader.uleb()?)?.checked_neg()?;
+                self.row.set(reg, RegisterRule::Offset(offset));
+            }
+            _ => return None,
+        }
+        Some(())
+    }
+
+    /// Reset `reg` to its rule after the CIE's initial instructions.
+    fn restore(&mut self, reg: u16) {
+        let rule = self
+            .initial
+            .as_ref()
+            .map_or(RegisterRule::SameValue, |initial| initial.rule(reg));
+        self.row.set(reg, rule);
+    }
+
+    /// Unsigned operand scaled by the data alignment factor.
+    fn factored(&self, offset: u64) -> Option<i64> {
+        self.factored_sf(i64::try_from(offset).ok()?)
+    }
+
+    /// Signed operand scaled by the data alignment factor.
+    const fn factored_sf(&self, offset: i64) -> Option<i64> {
+        offset.checked_mul(self.cie.data_align)
+    }
+}
+
+/// Little-endian cursor over `.eh_frame` bytes.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    /// Section address, for pc-relative pointers.
+    addr: u64,
+    address_size: usize,
+}
+
+impl Reader<'_> {
+    fn bytes<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let bytes = self.data.get(self.pos..self.pos.checked_add(N)?)?;
+        self.pos += N;
+        bytes.try_into().ok()
+    }
+
+    fn skip(&mut self, len: usize) -> Option<()> {
+        let end = self.pos.checked_add(len)?;
+        (end <= self.data.len()).then(|| self.pos = end)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.bytes::<1>().map(|[b]| b)
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.bytes().map(u16::from_le_bytes)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.bytes().map(u32::from_le_bytes)
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        self.bytes().map(u64::from_le_bytes)
+    }
+
+    fn uleb(&mut self) -> Option<u64> {
+        let mut value = 0u64;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8()?;
+            if shift < 64 {
+                value |= u64::from(byte & 0x7f) << shift;
+            }
+            shift += 7;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+    }
+
+    fn sleb(&mut self) -> Option<i64> {
+        let mut value = 0i64;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8()?;
+            if shift < 64 {
+                value |= i64::from(byte & 0x7f) << shift;
+            }
+            shift += 7;
+            if byte & 0x80 == 0 {
+                if shift < 64 && byte & 0x40 != 0 {
+                    value |= -1i64 << shift;
+                }
+                return Some(value);
+            }
+        }
+    }
+
+    fn reg(&mut self) -> Option<u16> {
+        u16::try_from(self.uleb()?).ok()
+    }
+
+    fn cstr(&mut self) -> Option<String> {
+        let len = self.data.get(self.pos..)?.iter().position(|&b| b == 0)?;
+        let s = std::str::from_utf8(&self.data[self.pos..self.pos + len]).ok()?;
+        self.pos += len + 1;
+        Some(s.to_string())
+    }
+
+    /// Read a `DW_EH_PE_*` encoded pointer (absolute or pc-relative).
+    fn pointer(&mut self, encoding: u8) -> Option<u64> {
+        if encoding == DW_EH_PE_OMIT {
+            return Some(0);
+        }
+        let base = match encoding & DW_EH_PE_APPLICATION {
+            DW_EH_PE_ABSPTR => 0,
+            DW_EH_PE_PCREL => self.addr.checked_add(self.pos as u64)?,
+            _ => return None,
+        };
+        let value = match encoding & 0x0f {
+            DW_EH_PE_ABSPTR if self.address_size == 4 => u64::from(self.u32()?),
+            DW_EH_PE_ABSPTR | DW_EH_PE_UDATA8 | DW_EH_PE_SDATA8 => self.u64()?,
+            DW_EH_PE_ULEB128 => self.uleb()?,
+            DW_EH_PE_UDATA2 => u64::from(self.u16()?),
+            DW_EH_PE_UDATA4 => u64::from(self.u32()?),
+            DW_EH_PE_SLEB128 => self.sleb()?.cast_unsigned(),
+            DW_EH_PE_SDATA2 => i64::from(self.u16()?.cast_signed()).cast_unsigned(),
+            DW_EH_PE_SDATA4 => i64::from(self.u32()?.cast_signed()).cast_unsigned(),
+            _ => return None,
+        };
+        let pointer = base.wrapping_add(value);
+        Some(if self.address_size == 4 {
+            pointer & 0xffff_ffff
+        } else {
+            pointer
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECTION: u64 = 0x2000;
+    const FUNC: u64 = 0x1000;
+
+    /// Pad `entry` with `DW_CFA_nop` and prefix its length.
+    fn entry(mut body: Vec<u8>) -> Vec<u8> {
+        while !body.len().is_multiple_of(4) {
+            body.push(0);
+        }
+        let mut out = u32::try_from(body.len()).unwrap().to_le_bytes().to_vec();
+        out.extend(body);
+        out
+    }
+
+    /// CIE and FDE as GCC emits them for
+    /// `addi sp,sp,-16; sd ra,8(sp); sd s0,0(sp); addi s0,sp,16; ...`.
+    fn eh_frame() -> Vec<u8> {
+        // CIE: version 1, "zR", code align 1, data align -8, ra = x1,
+        // pcrel sdata4 pointers; CFA = sp
+        let cie = entry(vec![
+            0, 0, 0, 0, 1, b'z', b'R', 0, 1, 0x78, 1, 1, 0x1b, 0x0c, 2, 0,
+        ]);
+        let cie_len = cie.len();
+        let mut fde = Vec::new();
+        // CIE pointer: back from this field to the CIE
+        fde.extend_from_slice(&u32::try_from(cie_len + 4).unwrap().to_le_bytes());
+        let pc_field = SECTION + cie_len as u64 + 8;
+        let delta = i32::try_from(i64::try_from(FUNC).unwrap() - i64::try_from(pc_field).unwrap());
+        fde.extend_from_slice(&delta.unwrap().to_le_bytes());
+        fde.extend_from_slice(&0x20u32.to_le_bytes());
+        fde.extend_from_slice(&[
+            0,    // augmentation data length
+            0x44, // advance 4
+            0x0e, 16,   // CFA = sp + 16
+            0x48, // advance 8
+            0x81, 1, // ra at CFA - 8
+            0x88, 2,    // s0 at CFA - 16
+            0x44, // advance 4
+            0x0c, 8, 16, // CFA = s0 + 16
+        ]);
+        let mut data = cie;
+        data.extend(entry(fde));
+        data.extend_from_slice(&[0, 0, 0, 0]);
+        data
+    }
+
+    #[test]
+    fn test_parse_eh_frame_rows() {
+        let cfi = CallFrameInfo::parse(eh_frame(), SECTION, 8);
+        assert!(!cfi.is_empty());
+
+        let entry = cfi.row(FUNC).unwrap();
+        assert_eq!((entry.cfa_register, entry.cfa_offset), (2, 0));
+        assert_eq!(entry.return_address, 1);
+        assert_eq!(entry.rule(1), RegisterRule::SameValue);
+
+        let adjusted = cfi.row(FUNC + 4).unwrap();
+        assert_eq!((adjusted.cfa_register, adjusted.cfa_offset), (2, 16));
+
+        let saved = cfi.row(FUNC + 0xc).unwrap();
+        assert_eq!(saved.rule(1), RegisterRule::Offset(-8));
+        assert_eq!(saved.rule(8), RegisterRule::Offset(-16));
+
+        let body = cfi.row(FUNC + 0x1c).unwrap();
+        assert_eq!((body.cfa_register, body.cfa_offset), (8, 16));
+        assert_eq!(body.rule(1), RegisterRule::Offset(-8));
+
+        assert!(cfi.row(FUNC + 0x20).is_none());
+        assert!(cfi.row(FUNC - 4).is_none());
+    }
+
+    #[test]
+    fn test_parse_empty_eh_frame() {
+        assert!(CallFrameInfo::parse(Vec::new(), SECTION, 8).is_empty());
+        assert!(CallFrameInfo::parse(vec![0; 4], SECTION, 4).is_empty());
+    }
+}
@@ -1,7 +1,8 @@
 //! Debug info extraction using llvm-addr2line.
 //!
 //! Resolves instruction addresses to source <file:line:function> mappings
-//! for generating #line directives in emitted C code.
+//! for generating #line directives in emitted C code. Call frame
+//! information for unwinding the guest stack is parsed by [`CallFrameInfo`].
 
 use std::io::Write;
 use std::process::Command;
@@ -10,6 +11,10 @@ use rustc_hash::FxHashMap;
 use rvr_ir::SourceLoc;
 use tempfile::NamedTempFile;
 
+mod cfi;
+
+pub use cfi::*;
+
 /// Debug info for an ELF file, mapping addresses to source locations.
 #[derive(Debug, Default)]
 pub struct DebugInfo {
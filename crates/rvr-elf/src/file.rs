@@ -2,11 +2,13 @@
 
 use rvr_isa::Xlen;
 
+use crate::attributes::ArchAttributes;
 use crate::constants::{
-    EF_RISCV_RVC, EF_RISCV_RVE, ELF_CLASS_32, ELF_CLASS_64, ELF_DATA_LSB, ELF_MAGIC, SHF_ALLOC,
-    SHT_NOBITS, SHT_PROGBITS, SHT_SYMTAB, STT_FUNC,
+    DT_NEEDED, DT_NULL, EF_RISCV_RVC, EF_RISCV_RVE, ELF_CLASS_32, ELF_CLASS_64, ELF_DATA_LSB,
+    ELF_MAGIC, SHF_ALLOC, SHT_DYNAMIC, SHT_NOBITS, SHT_PROGBITS, SHT_RELA, SHT_RISCV_ATTRIBUTES,
+    SHT_SYMTAB, STT_FUNC,
 };
-use crate::header::{ElfHeader, LoadedSection, ProgramHeader, SectionHeader, Symbol};
+use crate::header::{ElfHeader, LoadedSection, ProgramHeader, Relocation, SectionHeader, Symbol};
 use crate::{ElfError, Result};
 
 /// Read little-endian u16 from bytes.
@@ -50,11 +52,19 @@ fn read_le64(data: &[u8], offset: usize) -> u64 {
 /// Parsed ELF file.
 #[derive(Clone, Debug)]
 pub struct ElfFile<X: Xlen> {
+    pub e_type: u16,
     pub entry_point: X::Reg,
     pub e_flags: u32,
     pub sections: Vec<LoadedSection<X>>,
     pub program_headers: Vec<ProgramHeader<X>>,
     pub symbols: Vec<Symbol<X>>,
+    /// Entries of every `SHT_RELA` section (e.g. `.rela.dyn`, or `.rela.text`
+    /// in a relocatable object).
+    pub relocations: Vec<Relocation<X>>,
+    /// Shared libraries named by `DT_NEEDED` entries in `.dynamic`.
+    pub needed: Vec<String>,
+    /// Build attributes from `.riscv.attributes`, if present.
+    pub attributes: Option<ArchAttributes>,
 }
 
 impl<X: Xlen> ElfFile<X> {
@@ -80,13 +90,20 @@ impl<X: Xlen> ElfFile<X> {
         let strtab = Self::find_string_table(&all_sections, &header);
         let sections = Self::load_allocatable_sections(data, &all_sections, strtab.as_ref());
         let symbols = Self::parse_symbols(data, &all_sections);
+        let relocations = Self::parse_relocations(data, &all_sections);
+        let needed = Self::parse_needed(data, &all_sections);
+        let attributes = Self::parse_attributes(data, &all_sections);
 
         Ok(Self {
+            e_type: header.e_type,
             entry_point: header.entry,
             e_flags: header.flags,
             sections,
             program_headers,
             symbols,
+            relocations,
+            needed,
+            attributes,
         })
     }
 
@@ -170,6 +187,7 @@ impl<X: Xlen> ElfFile<X> {
             version,
             abi,
             abi_version,
+            e_type: read_le16(data, 16),
             entry: X::from_u64(u64::from(read_le32(data, 24))),
             phoff: X::from_u64(u64::from(read_le32(data, 28))),
             shoff: X::from_u64(u64::from(read_le32(data, 32))),
@@ -199,6 +217,7 @@ impl<X: Xlen> ElfFile<X> {
             version,
             abi,
             abi_version,
+            e_type: read_le16(data, 16),
             entry: X::from_u64(read_le64(data, 24)),
             phoff: X::from_u64(read_le64(data, 32)),
             shoff: X::from_u64(read_le64(data, 40)),
@@ -329,7 +348,7 @@ impl<X: Xlen> ElfFile<X> {
     ) -> Vec<LoadedSection<X>> {
         let mut loaded = Vec::new();
 
-        for section in sections {
+        for (index, section) in sections.iter().enumerate() {
             // Load sections with SHF_ALLOC flag
             if (X::to_u64(section.flags) & SHF_ALLOC) != 0 {
                 let section_data = Self::load_section_data(data, section);
@@ -348,6 +367,9 @@ impl<X: Xlen> ElfFile<X> {
                     size: section.size,
                     flags: X::to_u64(section.flags),
                     data: section_data,
+                    // Fewer than `e_shnum` (u16) sections are parsed
+                    index: u16::try_from(index).unwrap_or(u16::MAX),
+                    align: X::to_u64(section.addralign),
                 });
             }
         }
@@ -492,6 +514,105 @@ impl<X: Xlen> ElfFile<X> {
             })
         }
     }
+
+    /// Parse the entries of every `SHT_RELA` section.
+    fn parse_relocations(data: &[u8], sections: &[SectionHeader<X>]) -> Vec<Relocation<X>> {
+        let entsize = if X::VALUE == 64 { 24 } else { 12 };
+        let mut relocations = Vec::new();
+
+        for rela in sections.iter().filter(|s| s.sh_type == SHT_RELA) {
+            let rela_offset = usize::try_from(X::to_u64(rela.offset)).unwrap_or(0);
+            let rela_size = usize::try_from(X::to_u64(rela.size)).unwrap_or(0);
+
+            for i in 0..rela_size / entsize {
+                let offset = rela_offset + i * entsize;
+                if offset + entsize > data.len() {
+                    break;
+                }
+                relocations.push(Self::parse_relocation(data, offset, rela.info));
+            }
+        }
+
+        relocations
+    }
+
+    /// Parse a single `Elf_Rela` entry (bounds already checked) applying
+    /// to section `section`.
+    fn parse_relocation(data: &[u8], offset: usize, section: u32) -> Relocation<X> {
+        if X::VALUE == 64 {
+            // r_info: symbol index in the high word, type in the low word
+            Relocation {
+                offset: X::from_u64(read_le64(data, offset)),
+                r_type: read_le32(data, offset + 8),
+                sym: read_le32(data, offset + 12),
+                addend: read_le64(data, offset + 16).cast_signed(),
+                section,
+            }
+        } else {
+            // r_info: symbol index in the upper 24 bits, type in the low byte
+            let info = read_le32(data, offset + 4);
+            Relocation {
+                offset: X::from_u64(u64::from(read_le32(data, offset))),
+                r_type: info & 0xff,
+                sym: info >> 8,
+                addend: i64::from(read_le32(data, offset + 8).cast_signed()),
+                section,
+            }
+        }
+    }
+
+    /// Attributes in the `SHT_RISCV_ATTRIBUTES` section.
+    fn parse_attributes(data: &[u8], sections: &[SectionHeader<X>]) -> Option<ArchAttributes> {
+        let section = sections
+            .iter()
+            .find(|s| s.sh_type == SHT_RISCV_ATTRIBUTES)?;
+        let offset = usize::try_from(X::to_u64(section.offset)).ok()?;
+        let size = usize::try_from(X::to_u64(section.size)).ok()?;
+        ArchAttributes::parse(data.get(offset..offset.checked_add(size)?)?)
+    }
+
+    /// Names of the `DT_NEEDED` entries in the `SHT_DYNAMIC` section.
+    fn parse_needed(data: &[u8], sections: &[SectionHeader<X>]) -> Vec<String> {
+        let mut needed = Vec::new();
+
+        let Some(dynamic) = sections.iter().find(|s| s.sh_type == SHT_DYNAMIC) else {
+            return needed;
+        };
+        // Names are offsets into the string table linked via sh_link
+        let Some(strtab) = sections.get(usize::try_from(dynamic.link).unwrap_or(usize::MAX)) else {
+            return needed;
+        };
+
+        let strtab_offset = usize::try_from(X::to_u64(strtab.offset)).unwrap_or(0);
+        let dynamic_offset = usize::try_from(X::to_u64(dynamic.offset)).unwrap_or(0);
+        let dynamic_size = usize::try_from(X::to_u64(dynamic.size)).unwrap_or(0);
+        let word = X::REG_BYTES;
+
+        for i in 0..dynamic_size / (2 * word) {
+            let offset = dynamic_offset + i * 2 * word;
+            if offset + 2 * word > data.len() {
+                break;
+            }
+            let (tag, value) = if X::VALUE == 64 {
+                (read_le64(data, offset), read_le64(data, offset + 8))
+            } else {
+                (
+                    u64::from(read_le32(data, offset)),
+                    u64::from(read_le32(data, offset + 4)),
+                )
+            };
+            match tag {
+                DT_NULL => break,
+                DT_NEEDED => {
+                    let name_offset = usize::try_from(value).unwrap_or(0);
+                    needed.push(Self::extract_string(data, strtab_offset, name_offset));
+                }
+                _ => {}
+            }
+        }
+
+        needed
+    }
 }
 
 // TODO: move somewhere else
@@ -0,0 +1,136 @@
+//! Guest unit tests registered with `rvr_rt::rvr_test!`.
+//!
+//! Each entry of the `.rvr_tests` section is three XLEN-sized words: the
+//! address and byte length of the test name (UTF-8, not NUL-terminated)
+//! and the address of the test function.
+
+use rvr_isa::Xlen;
+
+use crate::image::ElfImage;
+use crate::{ElfError, Result};
+
+/// Link section holding the registered guest tests.
+pub const GUEST_TESTS_SECTION: &str = ".rvr_tests";
+
+/// Words per `.rvr_tests` entry: name pointer, name length, function.
+const ENTRY_WORDS: usize = 3;
+
+/// A test function registered in the guest image.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GuestTest {
+    /// Test name, usually `module::path::function`.
+    pub name: String,
+    /// Address of the test function.
+    pub addr: u64,
+}
+
+impl<X: Xlen> ElfImage<X> {
+    /// Guest tests registered in the `.rvr_tests` section, in link order.
+    ///
+    /// Returns an empty list if the image has no such section.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the section size is not a whole number of
+    /// entries, or an entry's name is not valid UTF-8 in a loaded segment.
+    pub fn guest_tests(&self) -> Result<Vec<GuestTest>> {
+        let Some(section) = self.sections.iter().find(|s| s.name == GUEST_TESTS_SECTION) else {
+            return Ok(Vec::new());
+        };
+
+        let entry_size = ENTRY_WORDS * X::REG_BYTES;
+        if section.data.len() % entry_size != 0 {
+            return Err(ElfError::InvalidTestSection(section.data.len()));
+        }
+
+        section
+            .data
+            .chunks_exact(entry_size)
+            .enumerate()
+            .map(|(index, entry)| {
+                let word = |i: usize| {
+                    let mut bytes = [0u8; 8];
+                    bytes[..X::REG_BYTES]
+                        .copy_from_slice(&entry[i * X::REG_BYTES..(i + 1) * X::REG_BYTES]);
+                    u64::from_le_bytes(bytes)
+                };
+                let invalid = || ElfError::InvalidTestEntry(index);
+                let name_len = usize::try_from(word(1)).map_err(|_| invalid())?;
+                let name = self
+                    .bytes_at(word(0), name_len)
+                    .filter(|bytes| bytes.len() == name_len)
+                    .ok_or_else(invalid)?;
+                let name = std::str::from_utf8(name).map_err(|_| invalid())?;
+                Ok(GuestTest {
+                    name: name.to_string(),
+                    addr: word(2),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{PF_R, PF_X};
+    use crate::writer::ElfWriter;
+    use rvr_isa::{Rv32, Rv64};
+
+    const BASE: u64 = 0x1000;
+    const NAMES: &[u8] = b"tests::onetests::two";
+
+    /// Image with two tests: the names at `BASE`, the entries right after.
+    fn image_bytes<X: Xlen>(name_len: u64) -> Vec<u8> {
+        let entries_addr = BASE + NAMES.len() as u64;
+        let mut data = NAMES.to_vec();
+        for (offset, func) in [(0, 0x2000), (10, 0x2040)] {
+            for value in [BASE + offset, name_len, func] {
+                data.extend_from_slice(&value.to_le_bytes()[..X::REG_BYTES]);
+            }
+        }
+        let entries_size = (data.len() - NAMES.len()) as u64;
+        ElfWriter::<X>::new(BASE)
+            .with_segment(BASE, PF_R | PF_X, data)
+            .with_section(GUEST_TESTS_SECTION, entries_addr, entries_size)
+            .build()
+    }
+
+    #[test]
+    fn test_guest_tests_roundtrip() {
+        let expected = [
+            GuestTest {
+                name: "tests::one".to_string(),
+                addr: 0x2000,
+            },
+            GuestTest {
+                name: "tests::two".to_string(),
+                addr: 0x2040,
+            },
+        ];
+        let rv64 = ElfImage::<Rv64>::parse(&image_bytes::<Rv64>(10)).unwrap();
+        assert_eq!(rv64.guest_tests().unwrap(), expected);
+        let rv32 = ElfImage::<Rv32>::parse(&image_bytes::<Rv32>(10)).unwrap();
+        assert_eq!(rv32.guest_tests().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_guest_tests_invalid_name() {
+        let image = ElfImage::<Rv64>::parse(&image_bytes::<Rv64>(0x1_0000)).unwrap();
+        assert!(matches!(
+            image.guest_tests(),
+            Err(ElfError::InvalidTestEntry(0))
+        ));
+    }
+
+    #[test]
+    fn test_guest_tests_missing_section() {
+        let image = ElfImage::<Rv64>::parse(
+            &ElfWriter::<Rv64>::new(BASE)
+                .with_segment(BASE, PF_R | PF_X, vec![0; 4])
+                .build(),
+        )
+        .unwrap();
+        assert!(image.guest_tests().unwrap().is_empty());
+    }
+}
@@ -11,6 +11,7 @@ pub struct ElfHeader<X: Xlen> {
     pub version: u8,
     pub abi: u8,
     pub abi_version: u8,
+    pub e_type: u16,
     pub entry: X::Reg,
     pub phoff: X::Reg,
     pub shoff: X::Reg,
@@ -59,6 +60,10 @@ pub struct LoadedSection<X: Xlen> {
     pub size: X::Reg,
     pub flags: u64,
     pub data: Vec<u8>,
+    /// Index in the section header table.
+    pub index: u16,
+    /// Required alignment (`sh_addralign`, 0 or 1 for none).
+    pub align: u64,
 }
 
 /// ELF symbol.
@@ -77,3 +82,20 @@ pub struct Symbol<X: Xlen> {
     /// Section index.
     pub shndx: u16,
 }
+
+/// Relocation with addend (`Elf_Rela`).
+#[derive(Clone, Debug)]
+pub struct Relocation<X: Xlen> {
+    /// Address to patch (before the load bias); offset into `section` in a
+    /// relocatable object.
+    pub offset: X::Reg,
+    /// Relocation type (`R_RISCV_*`).
+    pub r_type: u32,
+    /// Symbol table index.
+    pub sym: u32,
+    /// Addend (sign-extended for ELFCLASS32).
+    pub addend: i64,
+    /// Index of the section the relocation applies to (`sh_info` of its
+    /// `SHT_RELA` section; 0 for dynamic relocations).
+    pub section: u32,
+}
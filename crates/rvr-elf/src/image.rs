@@ -2,13 +2,23 @@
 
 use rvr_isa::Xlen;
 
+use crate::attributes::ArchAttributes;
 use crate::constants::{
-    EF_RISCV_RVC, EF_RISCV_RVE, MAX_SEGMENTS, PF_R, PF_W, PF_X, PT_LOAD, SHF_EXECINSTR, STT_FUNC,
+    EF_RISCV_RVC, EF_RISCV_RVE, ELF_TYPE_DYN, ELF_TYPE_REL, MAX_SEGMENTS, PF_R, PF_W, PF_X,
+    PT_LOAD, R_RISCV_NONE, R_RISCV_RELATIVE, SHF_EXECINSTR, SHN_ABS, SHN_UNDEF, STT_FUNC,
 };
 use crate::file::ElfFile;
-use crate::header::{LoadedSection, ProgramHeader, Symbol};
+use crate::header::{LoadedSection, ProgramHeader, Relocation, Symbol};
+use crate::relocatable::{ENTRY_SYMBOL, SectionLayout};
 use crate::{ElfError, Result};
 
+/// Load bias for position-independent (`ET_DYN`) executables when none is
+/// given.
+///
+/// PIEs are linked at address 0; the bias keeps the first page of guest
+/// memory unused.
+pub const DEFAULT_LOAD_BIAS: u64 = 0x1_0000;
+
 /// A memory segment with virtual address and data.
 ///
 /// The `data` field contains only the file data (filesz bytes).
@@ -79,26 +89,74 @@ pub struct ElfImage<X: Xlen> {
     pub memory_segments: Vec<MemorySegment<X>>,
     pub sections: Vec<LoadedSection<X>>,
     pub symbols: Vec<Symbol<X>>,
+    /// Bias added to every address of a position-independent image
+    /// (0 for `ET_EXEC`).
+    pub load_bias: u64,
+    /// Build attributes from `.riscv.attributes`, if present.
+    pub attributes: Option<ArchAttributes>,
+    /// Relocations applied while loading, with `offset` at the loaded
+    /// address they patched.
+    pub relocations: Vec<Relocation<X>>,
 }
 
 impl<X: Xlen> ElfImage<X> {
     /// Parse ELF from raw bytes.
     ///
+    /// Position-independent executables are loaded at [`DEFAULT_LOAD_BIAS`].
+    ///
     /// # Errors
     ///
     /// Returns an error if the ELF file is invalid or has unsupported segments.
     pub fn parse(data: &[u8]) -> Result<Self> {
+        Self::parse_with_load_bias(data, None)
+    }
+
+    /// Parse ELF from raw bytes, loading a position-independent (`ET_DYN`)
+    /// executable at `load_bias` ([`DEFAULT_LOAD_BIAS`] if `None`).
+    ///
+    /// The entry point, segments, sections and symbols are rebased by the
+    /// bias, and `R_RISCV_RELATIVE` relocations are applied to the segment
+    /// data. `ET_EXEC` images are loaded as linked and ignore `load_bias`.
+    /// A relocatable object (`ET_REL`) is linked with its sections
+    /// [packed](SectionLayout::packed) from the bias and enters at
+    /// [`ENTRY_SYMBOL`]; see [`parse_relocatable`](Self::parse_relocatable).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the ELF file is invalid or has unsupported
+    /// segments, names shared libraries (`DT_NEEDED`), has relocations other
+    /// than `R_RISCV_RELATIVE`, or the bias breaks segment alignment.
+    pub fn parse_with_load_bias(data: &[u8], load_bias: Option<u64>) -> Result<Self> {
         let elf = ElfFile::<X>::parse(data)?;
+        if elf.e_type == ELF_TYPE_REL {
+            let layout =
+                SectionLayout::packed(&elf.sections, load_bias.unwrap_or(DEFAULT_LOAD_BIAS));
+            return Self::link(elf, &layout, ENTRY_SYMBOL);
+        }
+        // Dynamic symbols and the PLT are not resolved
+        if let Some(library) = elf.needed.first() {
+            return Err(ElfError::DynamicDependency(library.clone()));
+        }
         let loadable = Self::validate_segments(&elf, data)?;
         let segments = Self::load_segments(&loadable, data);
 
-        Ok(Self {
+        let mut image = Self {
             entry_point: elf.entry_point,
             e_flags: elf.e_flags,
             memory_segments: segments,
             sections: elf.sections,
             symbols: elf.symbols,
-        })
+            load_bias: 0,
+            attributes: elf.attributes,
+            relocations: Vec::new(),
+        };
+        if elf.e_type == ELF_TYPE_DYN {
+            let bias = load_bias.unwrap_or(DEFAULT_LOAD_BIAS);
+            Self::validate_load_bias(&loadable, bias)?;
+            image.rebase(bias);
+            image.apply_relocations(elf.relocations)?;
+        }
+        Ok(image)
     }
 
     /// Look up a symbol by name.
@@ -121,6 +179,58 @@ impl<X: Xlen> ElfImage<X> {
             .map(|s| X::to_u64(s.value))
     }
 
+    /// Look up a function symbol by name, returning its `[start, end)`
+    /// range (empty if the symbol has no size).
+    ///
+    /// Only returns symbols with `STT_FUNC` type.
+    pub fn lookup_function_range(&self, name: &str) -> Option<(u64, u64)> {
+        self.symbols
+            .iter()
+            .find(|s| s.name == name && s.sym_type == STT_FUNC)
+            .map(|s| {
+                let start = X::to_u64(s.value);
+                (start, start.saturating_add(X::to_u64(s.size)))
+            })
+    }
+
+    /// Build attributes (ISA string, stack alignment) the ELF was compiled
+    /// with, if it has a `.riscv.attributes` section.
+    pub const fn arch_attributes(&self) -> Option<&ArchAttributes> {
+        self.attributes.as_ref()
+    }
+
+    /// Relocations applied while loading (dynamic ones of a
+    /// position-independent executable, static ones of a relocatable
+    /// object), at the addresses they patched; empty for `ET_EXEC`.
+    pub fn relocations(&self) -> &[Relocation<X>] {
+        &self.relocations
+    }
+
+    /// Name of the function symbol whose extent contains `addr`.
+    pub fn function_containing(&self, addr: u64) -> Option<&str> {
+        self.function_symbol(addr).map(|s| s.name.as_str())
+    }
+
+    /// Function symbol whose extent contains `addr`.
+    pub fn function_symbol(&self, addr: u64) -> Option<&Symbol<X>> {
+        self.symbols
+            .iter()
+            .filter(|s| s.sym_type == STT_FUNC && !s.name.is_empty())
+            .find(|s| {
+                let start = X::to_u64(s.value);
+                addr >= start && addr < start + X::to_u64(s.size).max(1)
+            })
+    }
+
+    /// File-backed bytes at `addr`, up to `len` bytes from a single segment.
+    pub fn bytes_at(&self, addr: u64, len: usize) -> Option<&[u8]> {
+        self.memory_segments.iter().find_map(|seg| {
+            let offset = usize::try_from(addr.checked_sub(X::to_u64(seg.virtual_start))?).ok()?;
+            let data = seg.data.get(offset..).filter(|d| !d.is_empty())?;
+            Some(&data[..len.min(data.len())])
+        })
+    }
+
     /// Create ELF image from raw bytecode (not an actual ELF file).
     pub fn from_bytecode(bytecode: Vec<u8>, entry_point: X::Reg) -> Self {
         let end = X::from_u64(X::to_u64(entry_point) + bytecode.len() as u64);
@@ -137,6 +247,9 @@ impl<X: Xlen> ElfImage<X> {
             memory_segments: vec![segment],
             sections: Vec::new(),
             symbols: Vec::new(),
+            load_bias: 0,
+            attributes: None,
+            relocations: Vec::new(),
         }
     }
 
@@ -222,6 +335,112 @@ impl<X: Xlen> ElfImage<X> {
         Ok(loadable)
     }
 
+    /// Check that `bias` keeps every segment aligned and inside the address
+    /// space.
+    fn validate_load_bias(program_headers: &[ProgramHeader<X>], bias: u64) -> Result<()> {
+        for phdr in program_headers {
+            let align = X::to_u64(phdr.align);
+            if align.is_power_of_two() && !bias.is_multiple_of(align) {
+                return Err(ElfError::MisalignedLoadBias { bias, align });
+            }
+            let end = (X::to_u64(phdr.vaddr) + X::to_u64(phdr.memsz))
+                .checked_add(bias)
+                .ok_or(ElfError::VirtualAddressOverflow)?;
+            if X::to_u64(X::from_u64(end)) != end {
+                return Err(ElfError::VirtualAddressOverflow);
+            }
+        }
+        Ok(())
+    }
+
+    /// Move the image up by `bias`.
+    fn rebase(&mut self, bias: u64) {
+        let add = |addr: X::Reg| X::from_u64(X::to_u64(addr).wrapping_add(bias));
+
+        self.entry_point = add(self.entry_point);
+        for segment in &mut self.memory_segments {
+            segment.virtual_start = add(segment.virtual_start);
+            segment.virtual_end = add(segment.virtual_end);
+        }
+        for section in &mut self.sections {
+            section.addr = add(section.addr);
+        }
+        for symbol in &mut self.symbols {
+            if symbol.shndx != SHN_UNDEF && symbol.shndx != SHN_ABS {
+                symbol.value = add(symbol.value);
+            }
+        }
+        self.load_bias = bias;
+    }
+
+    /// Apply dynamic relocations to the (rebased) segment data.
+    fn apply_relocations(&mut self, relocations: Vec<Relocation<X>>) -> Result<()> {
+        for mut reloc in relocations {
+            let addr = X::to_u64(reloc.offset).wrapping_add(self.load_bias);
+            match reloc.r_type {
+                R_RISCV_NONE => {}
+                R_RISCV_RELATIVE => {
+                    let value = self.load_bias.wrapping_add_signed(reloc.addend);
+                    self.write_word(addr, value)?;
+                }
+                r_type => {
+                    return Err(ElfError::UnsupportedRelocation {
+                        r_type,
+                        offset: addr,
+                    });
+                }
+            }
+            reloc.offset = X::from_u64(addr);
+            self.relocations.push(reloc);
+        }
+        Ok(())
+    }
+
+    /// Write an XLEN-sized word at `addr` into its segment, and into any
+    /// loaded section data covering it.
+    fn write_word(&mut self, addr: u64, value: u64) -> Result<()> {
+        self.write_bytes(addr, &value.to_le_bytes()[..X::REG_BYTES])
+    }
+
+    /// Write `bytes` at `addr` into its segment, and into any loaded
+    /// section data covering it.
+    pub(crate) fn write_bytes(&mut self, addr: u64, bytes: &[u8]) -> Result<()> {
+        let len = bytes.len() as u64;
+
+        let segment = self
+            .memory_segments
+            .iter_mut()
+            .find(|seg| {
+                addr >= X::to_u64(seg.virtual_start)
+                    && addr.saturating_add(len) <= X::to_u64(seg.virtual_end)
+            })
+            .ok_or(ElfError::RelocationOutOfBounds(addr))?;
+        let offset = usize::try_from(addr - X::to_u64(segment.virtual_start))
+            .map_err(|_| ElfError::RelocationOutOfBounds(addr))?;
+        // A relocated word in BSS becomes file data
+        if segment.data.len() < offset + bytes.len() {
+            segment.data.resize(offset + bytes.len(), 0);
+        }
+        segment.data[offset..offset + bytes.len()].copy_from_slice(bytes);
+
+        for section in &mut self.sections {
+            let Some(offset) = addr.checked_sub(X::to_u64(section.addr)) else {
+                continue;
+            };
+            let Ok(offset) = usize::try_from(offset) else {
+                continue;
+            };
+            if let Some(dst) = section
+                .data
+                .get_mut(offset..)
+                .and_then(|data| data.get_mut(..bytes.len()))
+            {
+                dst.copy_from_slice(bytes);
+            }
+        }
+        Ok(())
+    }
+
     fn load_segments(
         program_headers: &[ProgramHeader<X>],
         file_data: &[u8],
@@ -258,7 +477,128 @@ impl<X: Xlen> ElfImage<X> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use rvr_isa::Rv64;
+    use crate::constants::{ELF_TYPE_EXEC, R_RISCV_64, STT_FUNC, STT_OBJECT};
+    use crate::writer::ElfWriter;
+    use rvr_isa::{Rv32, Rv64};
+
+    /// PIE linked at 0: code in `.text`, then a data word at 0x1000 that a
+    /// `R_RISCV_RELATIVE` relocation points at `.text + 4`.
+    fn pie_writer<X: Xlen>() -> ElfWriter<X> {
+        ElfWriter::<X>::new(0)
+            .with_e_type(ELF_TYPE_DYN)
+            .with_segment(0, PF_R | PF_X, vec![0x13; 8])
+            .with_segment(0x1000, PF_R | PF_W, vec![0; X::REG_BYTES])
+            .with_section(".text", 0, 8)
+            .with_section(".data", 0x1000, X::REG_BYTES as u64)
+            .with_symbol("main", 0, STT_FUNC)
+            .with_symbol("pointer", 0x1000, STT_OBJECT)
+            .with_symbol("CONSTANT", 0x42, STT_OBJECT)
+            .with_relocation(0x1000, R_RISCV_RELATIVE, 4)
+    }
+
+    fn check_pie<X: Xlen>(load_bias: Option<u64>, bias: u64) {
+        let image =
+            ElfImage::<X>::parse_with_load_bias(&pie_writer::<X>().build(), load_bias).unwrap();
+        assert_eq!(image.load_bias, bias);
+        assert_eq!(X::to_u64(image.entry_point), bias);
+        assert_eq!(
+            X::to_u64(image.memory_segments[1].virtual_start),
+            bias + 0x1000
+        );
+        assert_eq!(image.lookup_function("main"), Some(bias));
+        assert_eq!(image.lookup_symbol("pointer"), Some(bias + 0x1000));
+        // Absolute symbols are not rebased
+        assert_eq!(image.lookup_symbol("CONSTANT"), Some(0x42));
+
+        let target = (bias + 4).to_le_bytes();
+        let word = &target[..X::REG_BYTES];
+        assert_eq!(image.bytes_at(bias + 0x1000, X::REG_BYTES), Some(word));
+        assert_eq!(X::to_u64(image.sections[1].addr), bias + 0x1000);
+        assert_eq!(image.sections[1].data, word);
+        let relocations = image.relocations();
+        assert_eq!(relocations.len(), 1);
+        assert_eq!(X::to_u64(relocations[0].offset), bias + 0x1000);
+    }
+
+    #[test]
+    fn test_parse_pie_rebases_and_relocates() {
+        check_pie::<Rv64>(None, DEFAULT_LOAD_BIAS);
+        check_pie::<Rv64>(Some(0x40_0000), 0x40_0000);
+        check_pie::<Rv32>(None, DEFAULT_LOAD_BIAS);
+        check_pie::<Rv32>(Some(0x40_0000), 0x40_0000);
+    }
+
+    #[test]
+    fn test_parse_pie_errors() {
+        let needed = pie_writer::<Rv64>().with_needed("libc.so.6").build();
+        assert!(matches!(
+            ElfImage::<Rv64>::parse(&needed),
+            Err(ElfError::DynamicDependency(library)) if library == "libc.so.6"
+        ));
+
+        let symbolic = pie_writer::<Rv64>()
+            .with_relocation(0x1000, R_RISCV_64, 0)
+            .build();
+        assert!(matches!(
+            ElfImage::<Rv64>::parse(&symbolic),
+            Err(ElfError::UnsupportedRelocation {
+                r_type: R_RISCV_64,
+                offset
+            }) if offset == DEFAULT_LOAD_BIAS + 0x1000
+        ));
+
+        let outside = pie_writer::<Rv64>()
+            .with_relocation(0x8000, R_RISCV_RELATIVE, 0)
+            .build();
+        assert!(matches!(
+            ElfImage::<Rv64>::parse(&outside),
+            Err(ElfError::RelocationOutOfBounds(_))
+        ));
+
+        let pie = pie_writer::<Rv64>().build();
+        assert!(matches!(
+            ElfImage::<Rv64>::parse_with_load_bias(&pie, Some(0x1_0800)),
+            Err(ElfError::MisalignedLoadBias { .. })
+        ));
+        assert!(matches!(
+            ElfImage::<Rv32>::parse_with_load_bias(
+                &pie_writer::<Rv32>().build(),
+                Some(0xffff_f000)
+            ),
+            Err(ElfError::VirtualAddressOverflow)
+        ));
+    }
+
+    #[test]
+    fn test_parse_exec_ignores_load_bias() {
+        let exec = pie_writer::<Rv64>().with_e_type(ELF_TYPE_EXEC).build();
+        let image = ElfImage::<Rv64>::parse_with_load_bias(&exec, Some(0x40_0000)).unwrap();
+        assert_eq!(image.load_bias, 0);
+        assert_eq!(image.entry_point, 0);
+        assert_eq!(image.bytes_at(0x1000, 8), Some(&[0; 8][..]));
+    }
+
+    #[test]
+    fn test_arch_attributes() {
+        let attributes = ArchAttributes {
+            arch: Some("rv32i2p1_m2p0".to_string()),
+            stack_align: Some(16),
+        };
+        let elf = ElfWriter::<Rv32>::new(0)
+            .with_segment(0, PF_R | PF_X, vec![0x13; 4])
+            .with_attributes(attributes.clone())
+            .build();
+        let image = ElfImage::<Rv32>::parse(&elf).unwrap();
+        assert_eq!(image.arch_attributes(), Some(&attributes));
+        // Not an allocated section, so not loaded
+        assert!(image.sections.is_empty());
+
+        let bare = pie_writer::<Rv32>().build();
+        assert_eq!(
+            ElfImage::<Rv32>::parse(&bare).unwrap().arch_attributes(),
+            None
+        );
+    }
 
     #[test]
     fn test_from_bytecode() {
@@ -271,6 +611,17 @@ mod tests {
         assert_eq!(image.memory_segments[0].data, bytecode);
     }
 
+    #[test]
+    fn test_bytes_at() {
+        let bytecode = vec![0x93, 0x00, 0x10, 0x00, 0xff, 0xff];
+        let image = ElfImage::<Rv64>::from_bytecode(bytecode, 0x8000_0000_u64);
+
+        assert_eq!(image.bytes_at(0x8000_0004, 4), Some(&[0xff, 0xff][..]));
+        assert_eq!(image.bytes_at(0x8000_0000, 2), Some(&[0x93, 0x00][..]));
+        assert_eq!(image.bytes_at(0x7fff_fffe, 4), None);
+        assert_eq!(image.bytes_at(0x8000_0006, 4), None);
+    }
+
     #[test]
     fn test_segment_properties() {
         let segment = MemorySegment::<Rv64> {
@@ -1,16 +1,24 @@
 //! ELF parser for RISC-V binaries.
 
+mod attributes;
 mod constants;
 pub mod debug;
 mod file;
+mod guest_test;
 mod header;
 mod image;
+mod relocatable;
+mod writer;
 
+pub use attributes::{ArchAttributes, TAG_RISCV_ARCH, TAG_RISCV_STACK_ALIGN};
 pub use constants::*;
-pub use debug::DebugInfo;
+pub use debug::{CallFrameInfo, DebugInfo, EH_FRAME_SECTION, RegisterRule, UnwindRow};
 pub use file::*;
+pub use guest_test::{GUEST_TESTS_SECTION, GuestTest};
 pub use header::*;
 pub use image::*;
+pub use relocatable::{ENTRY_SYMBOL, SectionLayout};
+pub use writer::ElfWriter;
 
 use thiserror::Error;
 
@@ -46,6 +54,39 @@ pub enum ElfError {
     TooManySegments,
     #[error("Overlapping virtual address ranges")]
     OverlappingSegments,
+    #[error("Dynamically linked executable needs {0}; only static executables are supported")]
+    DynamicDependency(String),
+    #[error(
+        "Unsupported relocation {} (type {r_type}) at 0x{offset:x}",
+        relocation_name(*.r_type)
+    )]
+    UnsupportedRelocation { r_type: u32, offset: u64 },
+    #[error("Relocation at 0x{0:x} is outside every loaded segment")]
+    RelocationOutOfBounds(u64),
+    #[error(
+        "Relocation {} at 0x{offset:x} does not reach its target",
+        relocation_name(*.r_type)
+    )]
+    RelocationOutOfRange { r_type: u32, offset: u64 },
+    #[error(
+        "Relocation {} at 0x{offset:x} has no R_RISCV_PCREL_HI20 at its symbol",
+        relocation_name(*.r_type)
+    )]
+    UnpairedPcrelLo12 { r_type: u32, offset: u64 },
+    #[error("Expected a relocatable object, got ELF type {0}")]
+    NotRelocatable(u16),
+    #[error("Section {0} has no address in the section layout")]
+    UnplacedSection(String),
+    #[error("Undefined symbol {0}")]
+    UndefinedSymbol(String),
+    #[error("Entry symbol {0} not found")]
+    MissingEntrySymbol(String),
+    #[error("Load bias 0x{bias:x} is not a multiple of the segment alignment 0x{align:x}")]
+    MisalignedLoadBias { bias: u64, align: u64 },
+    #[error("Guest test section size {0} is not a whole number of entries")]
+    InvalidTestSection(usize),
+    #[error("Guest test entry {0} has an invalid name")]
+    InvalidTestEntry(usize),
 }
 
 pub type Result<T> = std::result::Result<T, ElfError>;
@@ -0,0 +1,571 @@
+//! Static linking of relocatable objects (`ET_REL`).
+//!
+//! An object file has sections but no program headers. Its allocated
+//! sections are placed at the addresses of a [`SectionLayout`], symbol
+//! values become absolute, and the static relocations the assembler left in
+//! `.rela.*` are resolved against them. Nothing is relaxed, so
+//! `R_RISCV_RELAX` and `R_RISCV_ALIGN` are no-ops.
+
+use rustc_hash::FxHashMap;
+use rvr_isa::{
+    Xlen, decode_funct3, decode_opcode, decode_rd, decode_rs1, decode_rs2, encode_b, encode_i,
+    encode_j, encode_s, encode_u,
+};
+
+use crate::constants::{
+    ELF_TYPE_REL, MAX_SEGMENTS, PF_R, PF_W, PF_X, R_RISCV_32, R_RISCV_64, R_RISCV_ALIGN,
+    R_RISCV_BRANCH, R_RISCV_CALL, R_RISCV_CALL_PLT, R_RISCV_HI20, R_RISCV_JAL, R_RISCV_LO12_I,
+    R_RISCV_LO12_S, R_RISCV_NONE, R_RISCV_PCREL_HI20, R_RISCV_PCREL_LO12_I, R_RISCV_PCREL_LO12_S,
+    R_RISCV_RELAX, R_RISCV_RVC_BRANCH, R_RISCV_RVC_JUMP, SHF_EXECINSTR, SHF_WRITE, SHN_COMMON,
+    SHN_UNDEF,
+};
+use crate::file::ElfFile;
+use crate::header::{LoadedSection, Relocation, Symbol};
+use crate::image::{ElfImage, MemorySegment};
+use crate::{ElfError, Result};
+
+/// Symbol a relocatable object loaded with [`ElfImage::parse`] enters at.
+pub const ENTRY_SYMBOL: &str = "_start";
+
+/// Boundary each permission group of a [packed](SectionLayout::packed)
+/// layout starts on.
+const PAGE_SIZE: u64 = 0x1000;
+
+/// Order of the permission groups in a packed layout.
+const PACKED_ORDER: [u32; 4] = [PF_R | PF_X, PF_R, PF_R | PF_W, PF_R | PF_W | PF_X];
+
+/// Base address of each allocated section of a relocatable object, by
+/// section name.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SectionLayout {
+    bases: FxHashMap<String, u64>,
+}
+
+impl SectionLayout {
+    /// Layout placing no sections.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Place section `name` at `base`.
+    #[must_use]
+    pub fn with_section(mut self, name: &str, base: u64) -> Self {
+        self.bases.insert(name.to_string(), base);
+        self
+    }
+
+    /// Base address of section `name`, if placed.
+    #[must_use]
+    pub fn base(&self, name: &str) -> Option<u64> {
+        self.bases.get(name).copied()
+    }
+
+    /// Default layout: `sections` packed upwards from `base`, code first,
+    /// then read-only and then writable data.
+    ///
+    /// Sections keep their header order within a group and their
+    /// alignment; each group starts on a new page.
+    #[must_use]
+    pub fn packed<X: Xlen>(sections: &[LoadedSection<X>], base: u64) -> Self {
+        let mut layout = Self::new();
+        let mut addr = base;
+        for group in PACKED_ORDER {
+            let start = addr;
+            for section in sections.iter().filter(|s| segment_flags(s.flags) == group) {
+                addr = addr.next_multiple_of(section.align.max(1));
+                layout.bases.insert(section.name.clone(), addr);
+                addr = addr.saturating_add(X::to_u64(section.size));
+            }
+            if addr != start {
+                addr = addr.next_multiple_of(PAGE_SIZE);
+            }
+        }
+        layout
+    }
+}
+
+/// `PF_*` flags of a segment holding a section with `SHF_*` flags.
+const fn segment_flags(section_flags: u64) -> u32 {
+    let mut flags = PF_R;
+    if section_flags & SHF_WRITE != 0 {
+        flags |= PF_W;
+    }
+    if section_flags & SHF_EXECINSTR != 0 {
+        flags |= PF_X;
+    }
+    flags
+}
+
+impl<X: Xlen> ElfImage<X> {
+    /// Link a relocatable object (`ET_REL`) with its allocated sections at
+    /// the addresses of `layout`, entering at symbol `entry`.
+    ///
+    /// Placed sections become memory segments (adjacent sections with the
+    /// same permissions share one), symbol values become absolute, and the
+    /// static relocations are applied to the segment data and kept in
+    /// [`relocations`](Self::relocations). Empty sections need not be
+    /// placed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the ELF file is invalid or not relocatable, a
+    /// section is missing from `layout`, placed sections overlap, `entry`
+    /// is undefined, or a relocation is unsupported, refers to an undefined
+    /// symbol or does not reach its target.
+    pub fn parse_relocatable(data: &[u8], layout: &SectionLayout, entry: &str) -> Result<Self> {
+        let elf = ElfFile::<X>::parse(data)?;
+        if elf.e_type != ELF_TYPE_REL {
+            return Err(ElfError::NotRelocatable(elf.e_type));
+        }
+        Self::link(elf, layout, entry)
+    }
+
+    /// Link the parsed relocatable object `elf`.
+    pub(crate) fn link(elf: ElfFile<X>, layout: &SectionLayout, entry: &str) -> Result<Self> {
+        // Base address of each placed section, by header index
+        let mut bases = FxHashMap::default();
+        let mut sections = elf.sections;
+        for section in &mut sections {
+            let size = X::to_u64(section.size);
+            let Some(base) = layout.base(&section.name) else {
+                if size == 0 {
+                    continue;
+                }
+                return Err(ElfError::UnplacedSection(section.name.clone()));
+            };
+            let end = base
+                .checked_add(size)
+                .ok_or(ElfError::VirtualAddressOverflow)?;
+            if X::to_u64(X::from_u64(end)) != end {
+                return Err(ElfError::VirtualAddressOverflow);
+            }
+            section.addr = X::from_u64(base);
+            bases.insert(section.index, base);
+        }
+
+        // Symbol values are offsets into their section
+        let mut symbols = elf.symbols;
+        for symbol in &mut symbols {
+            if let Some(base) = bases.get(&symbol.shndx) {
+                symbol.value = X::from_u64(base.wrapping_add(X::to_u64(symbol.value)));
+            }
+        }
+        let entry_point = symbols
+            .iter()
+            .find(|s| s.name == entry && is_defined(s))
+            .map(|s| s.value)
+            .ok_or_else(|| ElfError::MissingEntrySymbol(entry.to_string()))?;
+
+        let mut image = Self {
+            entry_point,
+            e_flags: elf.e_flags,
+            memory_segments: Self::section_segments(&sections, &bases)?,
+            sections,
+            symbols,
+            load_bias: 0,
+            attributes: elf.attributes,
+            relocations: Vec::new(),
+        };
+        image.apply_static_relocations(elf.relocations, &bases)?;
+        Ok(image)
+    }
+
+    /// Segments of the placed, non-empty sections; a section starting
+    /// within its alignment of the previous segment's end joins it if
+    /// their permissions match.
+    fn section_segments(
+        sections: &[LoadedSection<X>],
+        bases: &FxHashMap<u16, u64>,
+    ) -> Result<Vec<MemorySegment<X>>> {
+        let mut placed: Vec<&LoadedSection<X>> = sections
+            .iter()
+            .filter(|s| bases.contains_key(&s.index) && X::to_u64(s.size) > 0)
+            .collect();
+        placed.sort_by_key(|s| X::to_u64(s.addr));
+
+        let mut segments: Vec<MemorySegment<X>> = Vec::new();
+        for section in placed {
+            let start = X::to_u64(section.addr);
+            let end = X::from_u64(start + X::to_u64(section.size));
+            let flags = segment_flags(section.flags);
+            if let Some(last) = segments.last_mut() {
+                let last_end = X::to_u64(last.virtual_end);
+                if start < last_end {
+                    return Err(ElfError::OverlappingSegments);
+                }
+                if last.flags == flags && start - last_end < section.align.max(1) {
+                    let offset = usize::try_from(start - X::to_u64(last.virtual_start))
+                        .map_err(|_| ElfError::VirtualAddressOverflow)?;
+                    last.data.resize(offset, 0);
+                    last.data.extend_from_slice(&section.data);
+                    last.virtual_end = end;
+                    continue;
+                }
+            }
+            segments.push(MemorySegment {
+                virtual_start: section.addr,
+                virtual_end: end,
+                data: section.data.clone(),
+                flags,
+            });
+        }
+
+        if segments.is_empty() {
+            return Err(ElfError::NoLoadableSegments);
+        }
+        if segments.len() > MAX_SEGMENTS {
+            return Err(ElfError::TooManySegments);
+        }
+        Ok(segments)
+    }
+
+    /// Apply the relocations of placed sections (others, such as those of
+    /// debug sections, are dropped).
+    fn apply_static_relocations(
+        &mut self,
+        relocations: Vec<Relocation<X>>,
+        bases: &FxHashMap<u16, u64>,
+    ) -> Result<()> {
+        let relocations: Vec<Relocation<X>> = relocations
+            .into_iter()
+            .filter_map(|mut reloc| {
+                let base = u16::try_from(reloc.section)
+                    .ok()
+                    .and_then(|index| bases.get(&index))?;
+                reloc.offset = X::from_u64(base.wrapping_add(X::to_u64(reloc.offset)));
+                Some(reloc)
+            })
+            .collect();
+
+        // A PCREL_LO12 relocation's symbol is the AUIPC carrying the
+        // PCREL_HI20, whose PC-relative value it takes the low bits of
+        let mut pcrel_hi = FxHashMap::default();
+        for reloc in relocations
+            .iter()
+            .filter(|r| r.r_type == R_RISCV_PCREL_HI20)
+        {
+            pcrel_hi.insert(X::to_u64(reloc.offset), self.pc_relative(reloc)?);
+        }
+
+        for reloc in &relocations {
+            self.apply_static_relocation(reloc, &pcrel_hi)?;
+        }
+        self.relocations = relocations;
+        Ok(())
+    }
+
+    fn apply_static_relocation(
+        &mut self,
+        reloc: &Relocation<X>,
+        pcrel_hi: &FxHashMap<u64, i64>,
+    ) -> Result<()> {
+        let place = X::to_u64(reloc.offset);
+        let r_type = reloc.r_type;
+        let out_of_range = || ElfError::RelocationOutOfRange {
+            r_type,
+            offset: place,
+        };
+        match r_type {
+            R_RISCV_NONE | R_RISCV_RELAX | R_RISCV_ALIGN => Ok(()),
+            R_RISCV_32 => {
+                let value = self.absolute(reloc)?;
+                // Zero- or sign-extended to 64 bits
+                let word = u32::try_from(value)
+                    .or_else(|_| i32::try_from(value.cast_signed()).map(i32::cast_unsigned))
+                    .map_err(|_| out_of_range())?;
+                self.write_bytes(place, &word.to_le_bytes())
+            }
+            R_RISCV_64 => {
+                let value = self.absolute(reloc)?;
+                self.write_bytes(place, &value.to_le_bytes())
+            }
+            R_RISCV_HI20 => {
+                let value = Self::sign_extend(self.absolute(reloc)?);
+                let hi = hi20(value).ok_or_else(out_of_range)?;
+                self.patch(place, |insn| with_u_imm(insn, hi))
+            }
+            R_RISCV_LO12_I => {
+                let value = Self::sign_extend(self.absolute(reloc)?);
+                self.patch(place, |insn| with_i_imm(insn, lo12(value)))
+            }
+            R_RISCV_LO12_S => {
+                let value = Self::sign_extend(self.absolute(reloc)?);
+                self.patch(place, |insn| with_s_imm(insn, lo12(value)))
+            }
+            R_RISCV_PCREL_HI20 => {
+                let value = self.pc_relative(reloc)?;
+                let hi = hi20(value).ok_or_else(out_of_range)?;
+                self.patch(place, |insn| with_u_imm(insn, hi))
+            }
+            R_RISCV_PCREL_LO12_I | R_RISCV_PCREL_LO12_S => {
+                let value = *pcrel_hi.get(&self.symbol_value(reloc)?).ok_or(
+                    ElfError::UnpairedPcrelLo12 {
+                        r_type,
+                        offset: place,
+                    },
+                )?;
+                if r_type == R_RISCV_PCREL_LO12_I {
+                    self.patch(place, |insn| with_i_imm(insn, lo12(value)))
+                } else {
+                    self.patch(place, |insn| with_s_imm(insn, lo12(value)))
+                }
+            }
+            R_RISCV_CALL | R_RISCV_CALL_PLT => {
+                // AUIPC + JALR pair
+                let value = self.pc_relative(reloc)?;
+                let hi = hi20(value).ok_or_else(out_of_range)?;
+                self.patch(place, |insn| with_u_imm(insn, hi))?;
+                self.patch(place + 4, |insn| with_i_imm(insn, lo12(value)))
+            }
+            R_RISCV_BRANCH => {
+                let offset = pc_offset(self.pc_relative(reloc)?, 13).ok_or_else(out_of_range)?;
+                self.patch(place, |insn| {
+                    let (rs1, rs2) = (decode_rs1(insn), decode_rs2(insn));
+                    encode_b(decode_opcode(insn), decode_funct3(insn), rs1, rs2, offset)
+                })
+            }
+            R_RISCV_JAL => {
+                let offset = pc_offset(self.pc_relative(reloc)?, 21).ok_or_else(out_of_range)?;
+                self.patch(place, |insn| {
+                    encode_j(decode_opcode(insn), decode_rd(insn), offset)
+                })
+            }
+            R_RISCV_RVC_BRANCH => {
+                let offset = pc_offset(self.pc_relative(reloc)?, 9).ok_or_else(out_of_range)?;
+                self.patch_compressed(place, |insn| with_cb_offset(insn, offset))
+            }
+            R_RISCV_RVC_JUMP => {
+                let offset = pc_offset(self.pc_relative(reloc)?, 12).ok_or_else(out_of_range)?;
+                self.patch_compressed(place, |insn| with_cj_offset(insn, offset))
+            }
+            _ => Err(ElfError::UnsupportedRelocation {
+                r_type,
+                offset: place,
+            }),
+        }
+    }
+
+    /// Address of the symbol `reloc` refers to (0 for none).
+    fn symbol_value(&self, reloc: &Relocation<X>) -> Result<u64> {
+        let symbol = usize::try_from(reloc.sym)
+            .ok()
+            .and_then(|index| self.symbols.get(index))
+            .ok_or_else(|| ElfError::UndefinedSymbol(format!("#{}", reloc.sym)))?;
+        if reloc.sym != 0 && !is_defined(symbol) {
+            return Err(ElfError::UndefinedSymbol(symbol.name.clone()));
+        }
+        Ok(X::to_u64(symbol.value))
+    }
+
+    /// `S + A`.
+    fn absolute(&self, reloc: &Relocation<X>) -> Result<u64> {
+        Ok(self.symbol_value(reloc)?.wrapping_add_signed(reloc.addend))
+    }
+
+    /// `S + A - P`, wrapped to XLEN.
+    fn pc_relative(&self, reloc: &Relocation<X>) -> Result<i64> {
+        let value = self.absolute(reloc)?.wrapping_sub(X::to_u64(reloc.offset));
+        Ok(Self::sign_extend(value))
+    }
+
+    /// `value` truncated to XLEN and sign-extended.
+    fn sign_extend(value: u64) -> i64 {
+        if X::VALUE == 32 {
+            i64::from(X::truncate_to_32(X::from_u64(value)).cast_signed())
+        } else {
+            value.cast_signed()
+        }
+    }
+
+    /// Rewrite the 32-bit instruction at `addr` with `patch`.
+    fn patch(&mut self, addr: u64, patch: impl FnOnce(u32) -> u32) -> Result<()> {
+        let insn = self
+            .bytes_at(addr, 4)
+            .and_then(|bytes| <[u8; 4]>::try_from(bytes).ok())
+            .ok_or(ElfError::RelocationOutOfBounds(addr))?;
+        let insn = patch(u32::from_le_bytes(insn));
+        self.write_bytes(addr, &insn.to_le_bytes())
+    }
+
+    /// Rewrite the compressed instruction at `addr` with `patch`.
+    fn patch_compressed(&mut self, addr: u64, patch: impl FnOnce(u16) -> u16) -> Result<()> {
+        let insn = self
+            .bytes_at(addr, 2)
+            .and_then(|bytes| <[u8; 2]>::try_from(bytes).ok())
+            .ok_or(ElfError::RelocationOutOfBounds(addr))?;
+        let insn = patch(u16::from_le_bytes(insn));
+        self.write_bytes(addr, &insn.to_le_bytes())
+    }
+}
+
+/// Whether `symbol` is defined in the object (possibly as absolute).
+const fn is_defined<X: Xlen>(symbol: &Symbol<X>) -> bool {
+    symbol.shndx != SHN_UNDEF && symbol.shndx != SHN_COMMON
+}
+
+/// Upper 20 bits of `value`, rounded so adding the sign-extended
+/// [`lo12`] restores it; `None` beyond ±2 GiB.
+fn hi20(value: i64) -> Option<u32> {
+    let hi = i32::try_from(value.checked_add(0x800)?).ok()? >> 12;
+    Some(hi.cast_unsigned() & 0xF_FFFF)
+}
+
+/// Low 12 bits of `value` (sign-extended by the instruction).
+fn lo12(value: i64) -> i32 {
+    i32::try_from(value & 0xFFF).unwrap_or_default()
+}
+
+/// `offset` if it is even and fits a `bits`-bit signed immediate.
+fn pc_offset(offset: i64, bits: u32) -> Option<i32> {
+    let limit = 1i64 << (bits - 1);
+    if offset % 2 != 0 || !(-limit..limit).contains(&offset) {
+        return None;
+    }
+    i32::try_from(offset).ok()
+}
+
+const fn with_u_imm(insn: u32, hi: u32) -> u32 {
+    encode_u(decode_opcode(insn), decode_rd(insn), hi)
+}
+
+const fn with_i_imm(insn: u32, imm: i32) -> u32 {
+    let (rd, rs1) = (decode_rd(insn), decode_rs1(insn));
+    encode_i(decode_opcode(insn), rd, decode_funct3(insn), rs1, imm)
+}
+
+const fn with_s_imm(insn: u32, imm: i32) -> u32 {
+    let (rs1, rs2) = (decode_rs1(insn), decode_rs2(insn));
+    encode_s(decode_opcode(insn), decode_funct3(insn), rs1, rs2, imm)
+}
+
+/// `c.beqz`/`c.bnez` with branch offset `offset`.
+fn with_cb_offset(insn: u16, offset: i32) -> u16 {
+    let imm = u16::try_from(offset & 0x1FF).unwrap_or_default();
+    (insn & 0xE383)
+        | (((imm >> 8) & 0x1) << 12)
+        | (((imm >> 3) & 0x3) << 10)
+        | (((imm >> 6) & 0x3) << 5)
+        | (((imm >> 1) & 0x3) << 3)
+        | (((imm >> 5) & 0x1) << 2)
+}
+
+/// `c.j`/`c.jal` with jump offset `offset`.
+fn with_cj_offset(insn: u16, offset: i32) -> u16 {
+    let imm = u16::try_from(offset & 0xFFF).unwrap_or_default();
+    (insn & 0xE003)
+        | (((imm >> 11) & 0x1) << 12)
+        | (((imm >> 4) & 0x1) << 11)
+        | (((imm >> 8) & 0x3) << 9)
+        | (((imm >> 10) & 0x1) << 8)
+        | (((imm >> 6) & 0x1) << 7)
+        | (((imm >> 7) & 0x1) << 6)
+        | (((imm >> 1) & 0x7) << 3)
+        | (((imm >> 5) & 0x1) << 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::SHF_ALLOC;
+    use crate::writer::ElfWriter;
+    use rvr_isa::{
+        Rv32, Rv64, decode_b_imm, decode_i_imm, decode_j_imm, decode_s_imm, decode_u_imm,
+    };
+
+    /// `jal ra, 0`, `beq a0, a1, 0`, `auipc t0, 0`, `addi a0, t0, 0` and
+    /// `sd a0, 0(t0)`.
+    const JAL: u32 = 0x0000_00EF;
+    const BEQ: u32 = 0x00B5_0063;
+    const AUIPC: u32 = 0x0000_0297;
+    const ADDI: u32 = 0x0002_8513;
+    const SD: u32 = 0x00A2_B023;
+    /// `c.beqz a0, 0` and `c.j 0`.
+    const C_BEQZ: u16 = 0xC101;
+    const C_J: u16 = 0xA001;
+
+    fn section(name: &str, flags: u64, size: u64, align: u64) -> LoadedSection<Rv64> {
+        LoadedSection {
+            name: name.to_string(),
+            addr: 0,
+            size,
+            flags: flags | SHF_ALLOC,
+            data: vec![0; usize::try_from(size).unwrap()],
+            index: 0,
+            align,
+        }
+    }
+
+    #[test]
+    fn test_packed_layout() {
+        let sections = [
+            section(".data", SHF_WRITE, 0x10, 8),
+            section(".text", SHF_EXECINSTR, 0x32, 4),
+            section(".rodata", 0, 0x8, 16),
+            section(".text.helper", SHF_EXECINSTR, 0x4, 4),
+            section(".bss", SHF_WRITE, 0x20, 16),
+        ];
+        let layout = SectionLayout::packed(&sections, 0x1_0000);
+        let expected = SectionLayout::new()
+            .with_section(".text", 0x1_0000)
+            .with_section(".text.helper", 0x1_0034)
+            .with_section(".rodata", 0x1_1000)
+            .with_section(".data", 0x1_2000)
+            .with_section(".bss", 0x1_2010);
+        assert_eq!(layout, expected);
+        assert_eq!(layout.base(".comment"), None);
+    }
+
+    #[test]
+    fn test_patch_immediates() {
+        let value = 0x1234_5FFF;
+        let hi = hi20(value).unwrap();
+        assert_eq!(decode_u_imm(with_u_imm(AUIPC, hi)), 0x1234_6000);
+        let addi = with_i_imm(ADDI, lo12(value));
+        assert_eq!(decode_i_imm(addi), -1);
+        assert_eq!((decode_rd(addi), decode_rs1(addi)), (10, 5));
+        assert_eq!(decode_s_imm(with_s_imm(SD, lo12(-0x10))), -0x10);
+        // AUIPC reaches ±2 GiB
+        assert_eq!(hi20(i64::from(i32::MAX)), None);
+        assert_eq!(hi20(-0x8000_0000), Some(0x8_0000));
+
+        let offset = pc_offset(-0x1000, 13).unwrap();
+        let (rs1, rs2) = (decode_rs1(BEQ), decode_rs2(BEQ));
+        let branch = encode_b(decode_opcode(BEQ), decode_funct3(BEQ), rs1, rs2, offset);
+        assert_eq!(decode_b_imm(branch), -0x1000);
+        assert_eq!((decode_rs1(branch), decode_rs2(branch)), (10, 11));
+        assert_eq!(pc_offset(0x1000, 13), None);
+        assert_eq!(pc_offset(3, 13), None);
+
+        let offset = pc_offset(0xF_FFFE, 21).unwrap();
+        let jump = encode_j(decode_opcode(JAL), decode_rd(JAL), offset);
+        assert_eq!(decode_j_imm(jump), 0xF_FFFE);
+        assert_eq!(decode_rd(jump), 1);
+    }
+
+    #[test]
+    fn test_patch_compressed_offsets() {
+        assert_eq!(with_cb_offset(C_BEQZ, 8), 0xC501);
+        assert_eq!(with_cb_offset(C_BEQZ, -2), 0xDD7D);
+        assert_eq!(with_cj_offset(C_J, 4), 0xA011);
+        assert_eq!(with_cj_offset(C_J, -2), 0xBFFD);
+    }
+
+    #[test]
+    fn test_sign_extend_to_xlen() {
+        assert_eq!(ElfImage::<Rv32>::sign_extend(0xFFFF_FFF8), -8);
+        assert_eq!(ElfImage::<Rv32>::sign_extend(0x1_0000_0004), 4);
+        assert_eq!(ElfImage::<Rv64>::sign_extend(0xFFFF_FFF8), 0xFFFF_FFF8);
+    }
+
+    #[test]
+    fn test_parse_relocatable_rejects_executables() {
+        let exec = ElfWriter::<Rv64>::new(0x1000)
+            .with_segment(0x1000, PF_R | PF_X, vec![0x13; 4])
+            .build();
+        assert!(matches!(
+            ElfImage::<Rv64>::parse_relocatable(&exec, &SectionLayout::new(), ENTRY_SYMBOL),
+            Err(ElfError::NotRelocatable(2))
+        ));
+    }
+}
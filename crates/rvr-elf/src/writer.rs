@@ -0,0 +1,633 @@
+//! Minimal ELF writer for synthesized test programs.
+
+use std::marker::PhantomData;
+
+use rvr_isa::Xlen;
+
+use crate::attributes::ArchAttributes;
+use crate::constants::{
+    DT_NEEDED, DT_NULL, ELF_CLASS_32, ELF_CLASS_64, ELF_DATA_LSB, ELF_MACHINE_RISCV, ELF_MAGIC,
+    ELF_TYPE_EXEC, ELF_VERSION_CURRENT, PT_LOAD, SHF_ALLOC, SHN_ABS, SHT_DYNAMIC, SHT_PROGBITS,
+    SHT_RELA, SHT_RISCV_ATTRIBUTES, SHT_STRTAB, SHT_SYMTAB, STB_GLOBAL, STT_FUNC,
+};
+
+/// Size of the `e_ident` array.
+const EI_NIDENT: usize = 16;
+/// ELF header size for ELFCLASS32 / ELFCLASS64.
+const EHDR_SIZE_32: u16 = 52;
+const EHDR_SIZE_64: u16 = 64;
+/// Program header size for ELFCLASS32 / ELFCLASS64.
+const PHDR_SIZE_32: u16 = 32;
+const PHDR_SIZE_64: u16 = 56;
+/// Section header size for ELFCLASS32 / ELFCLASS64.
+const SHDR_SIZE_32: u16 = 40;
+const SHDR_SIZE_64: u16 = 64;
+/// Segment alignment recorded in program headers.
+const SEGMENT_ALIGN: u64 = 0x1000;
+/// Symbol table entry size for ELFCLASS32 / ELFCLASS64.
+const SYM_SIZE_32: usize = 16;
+const SYM_SIZE_64: usize = 24;
+/// Relocation entry (`Elf_Rela`) size for ELFCLASS32 / ELFCLASS64.
+const RELA_SIZE_32: usize = 12;
+const RELA_SIZE_64: usize = 24;
+
+struct WriterSegment {
+    vaddr: u64,
+    flags: u32,
+    data: Vec<u8>,
+}
+
+struct WriterSection {
+    name: String,
+    vaddr: u64,
+    size: u64,
+}
+
+struct WriterSymbol {
+    name: String,
+    value: u64,
+    size: u64,
+    sym_type: u8,
+}
+
+struct WriterRelocation {
+    offset: u64,
+    r_type: u32,
+    addend: i64,
+}
+
+/// Builds executable RISC-V ELF files from raw segments.
+///
+/// Emits an ELF header followed by one `PT_LOAD` program header per segment
+/// and the segment contents. Section headers are written only if sections
+/// ([`with_section`](Self::with_section)), symbols
+/// ([`with_symbol`](Self::with_symbol)), relocations, needed libraries or
+/// attributes are added. Intended for small synthesized programs in tests.
+pub struct ElfWriter<X: Xlen> {
+    entry: u64,
+    e_type: u16,
+    e_flags: u32,
+    segments: Vec<WriterSegment>,
+    sections: Vec<WriterSection>,
+    symbols: Vec<WriterSymbol>,
+    relocations: Vec<WriterRelocation>,
+    needed: Vec<String>,
+    attributes: Option<ArchAttributes>,
+    _marker: PhantomData<X>,
+}
+
+impl<X: Xlen> ElfWriter<X> {
+    /// Create a writer for an executable starting at `entry`.
+    #[must_use]
+    pub const fn new(entry: u64) -> Self {
+        Self {
+            entry,
+            e_type: ELF_TYPE_EXEC,
+            e_flags: 0,
+            segments: Vec::new(),
+            sections: Vec::new(),
+            symbols: Vec::new(),
+            relocations: Vec::new(),
+            needed: Vec::new(),
+            attributes: None,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Set the `e_type` field (default `ELF_TYPE_EXEC`; `ELF_TYPE_DYN` for
+    /// a position-independent executable).
+    #[must_use]
+    pub const fn with_e_type(mut self, e_type: u16) -> Self {
+        self.e_type = e_type;
+        self
+    }
+
+    /// Set the `e_flags` field (e.g. `EF_RISCV_RVC`).
+    #[must_use]
+    pub const fn with_e_flags(mut self, e_flags: u32) -> Self {
+        self.e_flags = e_flags;
+        self
+    }
+
+    /// Add a loadable segment at `vaddr` with `PF_*` flags.
+    #[must_use]
+    pub fn with_segment(mut self, vaddr: u64, flags: u32, data: Vec<u8>) -> Self {
+        self.segments.push(WriterSegment { vaddr, flags, data });
+        self
+    }
+
+    /// Add an allocated `SHT_PROGBITS` section named `name` covering `size`
+    /// bytes at `vaddr`, inside a segment added with
+    /// [`with_segment`](Self::with_segment).
+    #[must_use]
+    pub fn with_section(mut self, name: &str, vaddr: u64, size: u64) -> Self {
+        self.sections.push(WriterSection {
+            name: name.to_string(),
+            vaddr,
+            size,
+        });
+        self
+    }
+
+    /// Add a global symbol of type `sym_type` (`STT_*`) to `.symtab`.
+    ///
+    /// The symbol is defined in the section containing `value`, or is
+    /// absolute if no section does.
+    #[must_use]
+    pub fn with_symbol(mut self, name: &str, value: u64, sym_type: u8) -> Self {
+        self.symbols.push(WriterSymbol {
+            name: name.to_string(),
+            value,
+            size: 0,
+            sym_type,
+        });
+        self
+    }
+
+    /// Add a global `STT_FUNC` symbol covering `size` bytes at `value`.
+    #[must_use]
+    pub fn with_function(mut self, name: &str, value: u64, size: u64) -> Self {
+        self.symbols.push(WriterSymbol {
+            name: name.to_string(),
+            value,
+            size,
+            sym_type: STT_FUNC,
+        });
+        self
+    }
+
+    /// Add an `Elf_Rela` entry of type `r_type` (`R_RISCV_*`, no symbol) to
+    /// `.rela.dyn`.
+    #[must_use]
+    pub fn with_relocation(mut self, offset: u64, r_type: u32, addend: i64) -> Self {
+        self.relocations.push(WriterRelocation {
+            offset,
+            r_type,
+            addend,
+        });
+        self
+    }
+
+    /// Add a `DT_NEEDED` entry for `library` to `.dynamic`.
+    #[must_use]
+    pub fn with_needed(mut self, library: &str) -> Self {
+        self.needed.push(library.to_string());
+        self
+    }
+
+    /// Add a `.riscv.attributes` section with `attributes`.
+    #[must_use]
+    pub fn with_attributes(mut self, attributes: ArchAttributes) -> Self {
+        self.attributes = Some(attributes);
+        self
+    }
+
+    /// Serialize the ELF file.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are more than `u16::MAX` segments or sections, if a
+    /// section lies outside every segment, or if an address does not fit in
+    /// a 32-bit word when writing ELFCLASS32.
+    #[must_use]
+    pub fn build(&self) -> Vec<u8> {
+        let is_64 = X::VALUE == 64;
+        let (ehdr_size, phdr_size, shdr_size) = if is_64 {
+            (EHDR_SIZE_64, PHDR_SIZE_64, SHDR_SIZE_64)
+        } else {
+            (EHDR_SIZE_32, PHDR_SIZE_32, SHDR_SIZE_32)
+        };
+        let phnum = u16::try_from(self.segments.len()).expect("too many segments");
+        let phoff = u64::from(ehdr_size);
+        let segments_offset = phoff + u64::from(phdr_size) * u64::from(phnum);
+        let mut data_offset = segments_offset;
+        let segments_end = segments_offset
+            + self
+                .segments
+                .iter()
+                .map(|s| s.data.len() as u64)
+                .sum::<u64>();
+
+        // Non-loaded data follows the segment contents: the symbol and
+        // string tables, then the section headers
+        let (headers, tables) = self.section_headers(segments_offset, segments_end);
+        let (shoff, shnum) = if headers.is_empty() {
+            (0, 0)
+        } else {
+            let shnum = u16::try_from(headers.len()).expect("too many sections");
+            (segments_end + tables.len() as u64, shnum)
+        };
+
+        let mut out = Vec::new();
+
+        // ELF header
+        out.extend_from_slice(&ELF_MAGIC.to_le_bytes());
+        out.push(if is_64 { ELF_CLASS_64 } else { ELF_CLASS_32 });
+        out.push(ELF_DATA_LSB);
+        out.push(ELF_VERSION_CURRENT);
+        out.resize(EI_NIDENT, 0);
+        out.extend_from_slice(&self.e_type.to_le_bytes());
+        out.extend_from_slice(&ELF_MACHINE_RISCV.to_le_bytes());
+        out.extend_from_slice(&u32::from(ELF_VERSION_CURRENT).to_le_bytes());
+        push_word(&mut out, is_64, self.entry);
+        push_word(&mut out, is_64, phoff);
+        push_word(&mut out, is_64, shoff);
+        out.extend_from_slice(&self.e_flags.to_le_bytes());
+        out.extend_from_slice(&ehdr_size.to_le_bytes());
+        out.extend_from_slice(&phdr_size.to_le_bytes());
+        out.extend_from_slice(&phnum.to_le_bytes());
+        out.extend_from_slice(&shdr_size.to_le_bytes());
+        out.extend_from_slice(&shnum.to_le_bytes());
+        out.extend_from_slice(&shnum.saturating_sub(1).to_le_bytes()); // e_shstrndx
+
+        // Program headers
+        for seg in &self.segments {
+            let size = seg.data.len() as u64;
+            out.extend_from_slice(&PT_LOAD.to_le_bytes());
+            if is_64 {
+                out.extend_from_slice(&seg.flags.to_le_bytes());
+            }
+            push_word(&mut out, is_64, data_offset);
+            push_word(&mut out, is_64, seg.vaddr);
+            push_word(&mut out, is_64, seg.vaddr); // p_paddr
+            push_word(&mut out, is_64, size); // p_filesz
+            push_word(&mut out, is_64, size); // p_memsz
+            if !is_64 {
+                out.extend_from_slice(&seg.flags.to_le_bytes());
+            }
+            push_word(&mut out, is_64, SEGMENT_ALIGN);
+            data_offset += size;
+        }
+
+        // Segment contents, in program header order
+        for seg in &self.segments {
+            out.extend_from_slice(&seg.data);
+        }
+
+        out.extend_from_slice(&tables);
+        for header in &headers {
+            header.write(&mut out, is_64, usize::from(shdr_size));
+        }
+
+        out
+    }
+
+    /// Section headers (null first, `.shstrtab` last) and the table data
+    /// they refer to, which starts at file offset `tables_offset`.
+    ///
+    /// Returns no headers if there are no sections or symbols.
+    fn section_headers(
+        &self,
+        segments_offset: u64,
+        tables_offset: u64,
+    ) -> (Vec<RawSection>, Vec<u8>) {
+        if self.sections.is_empty()
+            && self.symbols.is_empty()
+            && self.relocations.is_empty()
+            && self.needed.is_empty()
+            && self.attributes.is_none()
+        {
+            return (Vec::new(), Vec::new());
+        }
+
+        let mut headers = vec![RawSection::default()];
+        let mut tables = Vec::new();
+        let mut shstrtab = vec![0u8];
+
+        for section in &self.sections {
+            headers.push(RawSection {
+                name: push_str(&mut shstrtab, &section.name),
+                sh_type: SHT_PROGBITS,
+                flags: SHF_ALLOC,
+                addr: section.vaddr,
+                offset: self.file_offset(segments_offset, section.vaddr),
+                size: section.size,
+                ..RawSection::default()
+            });
+        }
+
+        if !self.symbols.is_empty() {
+            let is_64 = X::VALUE == 64;
+            let sym_size = if is_64 { SYM_SIZE_64 } else { SYM_SIZE_32 };
+            let mut strtab = vec![0u8];
+            // Index 0 is the null symbol
+            let mut symtab = vec![0u8; sym_size];
+            for symbol in &self.symbols {
+                let name = push_str(&mut strtab, &symbol.name);
+                let info = (STB_GLOBAL << 4) | symbol.sym_type;
+                let shndx = self.section_index(symbol.value);
+                symtab.extend_from_slice(&name.to_le_bytes());
+                if is_64 {
+                    symtab.extend_from_slice(&[info, 0]);
+                    symtab.extend_from_slice(&shndx.to_le_bytes());
+                    push_word(&mut symtab, is_64, symbol.value);
+                    push_word(&mut symtab, is_64, symbol.size);
+                } else {
+                    push_word(&mut symtab, is_64, symbol.value);
+                    push_word(&mut symtab, is_64, symbol.size);
+                    symtab.extend_from_slice(&[info, 0]);
+                    symtab.extend_from_slice(&shndx.to_le_bytes());
+                }
+            }
+
+            let strtab_index = u32::try_from(headers.len() + 1).expect("too many sections");
+            headers.push(RawSection {
+                name: push_str(&mut shstrtab, ".symtab"),
+                sh_type: SHT_SYMTAB,
+                offset: tables_offset + tables.len() as u64,
+                size: symtab.len() as u64,
+                link: strtab_index,
+                entsize: sym_size as u64,
+                ..RawSection::default()
+            });
+            tables.extend_from_slice(&symtab);
+            headers.push(RawSection {
+                name: push_str(&mut shstrtab, ".strtab"),
+                sh_type: SHT_STRTAB,
+                offset: tables_offset + tables.len() as u64,
+                size: strtab.len() as u64,
+                ..RawSection::default()
+            });
+            tables.extend_from_slice(&strtab);
+        }
+
+        self.push_relocations(&mut headers, &mut shstrtab, &mut tables, tables_offset);
+        self.push_dynamic(&mut headers, &mut shstrtab, &mut tables, tables_offset);
+        self.push_attributes(&mut headers, &mut shstrtab, &mut tables, tables_offset);
+
+        let name = push_str(&mut shstrtab, ".shstrtab");
+        headers.push(RawSection {
+            name,
+            sh_type: SHT_STRTAB,
+            offset: tables_offset + tables.len() as u64,
+            size: shstrtab.len() as u64,
+            ..RawSection::default()
+        });
+        tables.extend_from_slice(&shstrtab);
+
+        (headers, tables)
+    }
+
+    /// Append `.rela.dyn` (if there are relocations) to the headers and
+    /// tables.
+    fn push_relocations(
+        &self,
+        headers: &mut Vec<RawSection>,
+        shstrtab: &mut Vec<u8>,
+        tables: &mut Vec<u8>,
+        tables_offset: u64,
+    ) {
+        if self.relocations.is_empty() {
+            return;
+        }
+        let is_64 = X::VALUE == 64;
+        let mut rela = Vec::new();
+        for reloc in &self.relocations {
+            push_word(&mut rela, is_64, reloc.offset);
+            // r_info with symbol index 0
+            push_word(&mut rela, is_64, u64::from(reloc.r_type));
+            push_word(
+                &mut rela,
+                is_64,
+                reloc.addend.cast_unsigned() & word_mask(is_64),
+            );
+        }
+        headers.push(RawSection {
+            name: push_str(shstrtab, ".rela.dyn"),
+            sh_type: SHT_RELA,
+            offset: tables_offset + tables.len() as u64,
+            size: rela.len() as u64,
+            entsize: if is_64 { RELA_SIZE_64 } else { RELA_SIZE_32 } as u64,
+            ..RawSection::default()
+        });
+        tables.extend_from_slice(&rela);
+    }
+
+    /// Append `.riscv.attributes` (if set) to the headers and tables.
+    fn push_attributes(
+        &self,
+        headers: &mut Vec<RawSection>,
+        shstrtab: &mut Vec<u8>,
+        tables: &mut Vec<u8>,
+        tables_offset: u64,
+    ) {
+        let Some(attributes) = &self.attributes else {
+            return;
+        };
+        let data = attributes.encode();
+        headers.push(RawSection {
+            name: push_str(shstrtab, ".riscv.attributes"),
+            sh_type: SHT_RISCV_ATTRIBUTES,
+            offset: tables_offset + tables.len() as u64,
+            size: data.len() as u64,
+            ..RawSection::default()
+        });
+        tables.extend_from_slice(&data);
+    }
+
+    /// Append `.dynamic` and `.dynstr` (if there are needed libraries) to
+    /// the headers and tables.
+    fn push_dynamic(
+        &self,
+        headers: &mut Vec<RawSection>,
+        shstrtab: &mut Vec<u8>,
+        tables: &mut Vec<u8>,
+        tables_offset: u64,
+    ) {
+        if self.needed.is_empty() {
+            return;
+        }
+        let is_64 = X::VALUE == 64;
+        let mut dynstr = vec![0u8];
+        let mut dynamic = Vec::new();
+        for library in &self.needed {
+            let name = push_str(&mut dynstr, library);
+            push_word(&mut dynamic, is_64, DT_NEEDED);
+            push_word(&mut dynamic, is_64, u64::from(name));
+        }
+        push_word(&mut dynamic, is_64, DT_NULL);
+        push_word(&mut dynamic, is_64, 0);
+
+        let dynstr_index = u32::try_from(headers.len() + 1).expect("too many sections");
+        headers.push(RawSection {
+            name: push_str(shstrtab, ".dynamic"),
+            sh_type: SHT_DYNAMIC,
+            offset: tables_offset + tables.len() as u64,
+            size: dynamic.len() as u64,
+            link: dynstr_index,
+            entsize: 2 * X::REG_BYTES as u64,
+            ..RawSection::default()
+        });
+        tables.extend_from_slice(&dynamic);
+        headers.push(RawSection {
+            name: push_str(shstrtab, ".dynstr"),
+            sh_type: SHT_STRTAB,
+            offset: tables_offset + tables.len() as u64,
+            size: dynstr.len() as u64,
+            ..RawSection::default()
+        });
+        tables.extend_from_slice(&dynstr);
+    }
+
+    /// Header index of the section containing `addr` (sections follow the
+    /// null header in order), or `SHN_ABS`.
+    fn section_index(&self, addr: u64) -> u16 {
+        self.sections
+            .iter()
+            .position(|section| (section.vaddr..section.vaddr + section.size).contains(&addr))
+            .map_or(SHN_ABS, |index| {
+                u16::try_from(index + 1).expect("too many sections")
+            })
+    }
+
+    /// File offset of `vaddr`, given the offset of the first segment's data.
+    fn file_offset(&self, segments_offset: u64, vaddr: u64) -> u64 {
+        let mut offset = segments_offset;
+        for seg in &self.segments {
+            let len = seg.data.len() as u64;
+            if (seg.vaddr..seg.vaddr + len).contains(&vaddr) {
+                return offset + (vaddr - seg.vaddr);
+            }
+            offset += len;
+        }
+        panic!("section at {vaddr:#x} is outside every segment");
+    }
+}
+
+/// Section header fields written by [`ElfWriter`]; info and alignment are
+/// always zero.
+#[derive(Default)]
+struct RawSection {
+    name: u32,
+    sh_type: u32,
+    flags: u64,
+    addr: u64,
+    offset: u64,
+    size: u64,
+    link: u32,
+    entsize: u64,
+}
+
+impl RawSection {
+    fn write(&self, out: &mut Vec<u8>, is_64: bool, shdr_size: usize) {
+        let start = out.len();
+        out.extend_from_slice(&self.name.to_le_bytes());
+        out.extend_from_slice(&self.sh_type.to_le_bytes());
+        push_word(out, is_64, self.flags);
+        push_word(out, is_64, self.addr);
+        push_word(out, is_64, self.offset);
+        push_word(out, is_64, self.size);
+        out.extend_from_slice(&self.link.to_le_bytes());
+        out.extend_from_slice(&0u32.to_le_bytes()); // sh_info
+        push_word(out, is_64, 0); // sh_addralign
+        push_word(out, is_64, self.entsize);
+        debug_assert_eq!(out.len(), start + shdr_size);
+    }
+}
+
+/// Append `s` NUL-terminated to a string table; returns its offset.
+fn push_str(table: &mut Vec<u8>, s: &str) -> u32 {
+    let offset = u32::try_from(table.len()).expect("string table too large");
+    table.extend_from_slice(s.as_bytes());
+    table.push(0);
+    offset
+}
+
+/// Mask of the bits in an ELF word.
+const fn word_mask(is_64: bool) -> u64 {
+    if is_64 { u64::MAX } else { u32::MAX as u64 }
+}
+
+fn push_word(out: &mut Vec<u8>, is_64: bool, value: u64) {
+    if is_64 {
+        out.extend_from_slice(&value.to_le_bytes());
+    } else {
+        let value = u32::try_from(value).expect("value does not fit in ELFCLASS32 word");
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{PF_R, PF_W, PF_X, STT_OBJECT};
+    use crate::image::ElfImage;
+    use rvr_isa::{Rv32, Rv64};
+
+    const TEXT: [u8; 8] = [0x93, 0x00, 0x10, 0x00, 0x73, 0x00, 0x00, 0x00]; // addi x1, x0, 1; ecall
+
+    #[test]
+    fn test_write_rv64_roundtrip() {
+        let bytes = ElfWriter::<Rv64>::new(0x8000_0000)
+            .with_segment(0x8000_0000, PF_R | PF_X, TEXT.to_vec())
+            .with_segment(0x8000_1000, PF_R | PF_W, vec![0x5A; 16])
+            .build();
+
+        let image = ElfImage::<Rv64>::parse(&bytes).unwrap();
+        assert_eq!(image.entry_point, 0x8000_0000);
+        assert_eq!(image.memory_segments.len(), 2);
+        assert_eq!(image.memory_segments[0].data, TEXT);
+        assert!(image.memory_segments[0].is_executable());
+        assert_eq!(image.memory_segments[1].virtual_start, 0x8000_1000);
+        assert_eq!(image.memory_segments[1].data, vec![0x5A; 16]);
+        assert!(!image.memory_segments[1].is_readonly());
+    }
+
+    #[test]
+    fn test_write_sections_and_symbols() {
+        for bytes in [
+            ElfWriter::<Rv64>::new(0x1000)
+                .with_segment(0x1000, PF_R | PF_X, TEXT.to_vec())
+                .with_section(".text", 0x1000, 8)
+                .with_symbol("start", 0x1000, STT_FUNC)
+                .with_symbol("data", 0x1004, STT_OBJECT)
+                .build(),
+            ElfWriter::<Rv32>::new(0x1000)
+                .with_segment(0x1000, PF_R | PF_X, TEXT.to_vec())
+                .with_section(".text", 0x1000, 8)
+                .with_symbol("start", 0x1000, STT_FUNC)
+                .with_symbol("data", 0x1004, STT_OBJECT)
+                .build(),
+        ] {
+            let is_64 = bytes[4] == ELF_CLASS_64;
+            let (sections, symbols) = if is_64 {
+                let image = ElfImage::<Rv64>::parse(&bytes).unwrap();
+                assert_eq!(image.lookup_function("start"), Some(0x1000));
+                (image.sections.len(), image.lookup_symbol("data"))
+            } else {
+                let image = ElfImage::<Rv32>::parse(&bytes).unwrap();
+                assert_eq!(image.lookup_function("start"), Some(0x1000));
+                assert_eq!(image.sections[0].name, ".text");
+                assert_eq!(image.sections[0].data, TEXT);
+                (image.sections.len(), image.lookup_symbol("data"))
+            };
+            assert_eq!(sections, 1);
+            assert_eq!(symbols, Some(0x1004));
+        }
+    }
+
+    #[test]
+    fn test_write_function_size() {
+        let bytes = ElfWriter::<Rv64>::new(0x1000)
+            .with_segment(0x1000, PF_R | PF_X, TEXT.to_vec())
+            .with_function("start", 0x1000, 8)
+            .build();
+        let image = ElfImage::<Rv64>::parse(&bytes).unwrap();
+        assert_eq!(image.function_containing(0x1004), Some("start"));
+        assert_eq!(image.function_symbol(0x1004).unwrap().size, 8);
+        assert_eq!(image.function_symbol(0x1008).map(|s| s.value), None);
+    }
+
+    #[test]
+    fn test_write_rv32_roundtrip() {
+        let bytes = ElfWriter::<Rv32>::new(0x1000)
+            .with_segment(0x1000, PF_R | PF_X, TEXT.to_vec())
+            .build();
+
+        let image = ElfImage::<Rv32>::parse(&bytes).unwrap();
+        assert_eq!(image.entry_point, 0x1000);
+        assert_eq!(image.memory_segments.len(), 1);
+        assert_eq!(image.memory_segments[0].virtual_end, 0x1008);
+        assert!(image.memory_segments[0].is_executable());
+    }
+}
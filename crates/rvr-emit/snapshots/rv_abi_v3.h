@@ -0,0 +1,82 @@
+/* Generated by RVR: ABI of the library in this directory. Check
+ * rv_abi_info.abi_version against RV_ABI_VERSION before using anything
+ * else; the RvState offsets hold for this build only. */
+#pragma once
+
+#include <stdint.h>
+
+typedef struct RvState RvState;
+
+/* Build metadata of a library (tracer_kind: 0 none, 255 custom) */
+typedef struct RvAbiInfo {
+    uint32_t abi_version;
+    uint32_t state_layout_version;
+    uint32_t xlen;
+    uint32_t num_regs;
+    uint32_t memory_bits;
+    uint32_t instret_mode;
+    uint32_t tracer_kind;
+    uint32_t flags;
+} RvAbiInfo;
+
+/* Bits of RvAbiInfo.flags */
+enum RvAbiFeature {
+    RV_ABI_EXPORT_FUNCTIONS = 0x1,
+    RV_ABI_FIXED_ADDRESSES = 0x2,
+    RV_ABI_LOAD_BIAS = 0x4,
+    RV_ABI_LAZY_SEGMENTS = 0x8,
+    RV_ABI_CHECK_CANCEL = 0x10,
+    RV_ABI_HTIF = 0x20,
+};
+
+/* RvAbiInfo.instret_mode values */
+enum RvAbiInstretMode {
+    RV_ABI_INSTRET_OFF = 0,
+    RV_ABI_INSTRET_COUNT = 1,
+    RV_ABI_INSTRET_SUSPEND = 2,
+    RV_ABI_INSTRET_PER_INSTRUCTION = 3,
+};
+
+/* RvState.exit_cause values */
+enum RvAbiExitCause {
+    RV_ABI_EXIT_GUEST = 0,
+    RV_ABI_EXIT_HTIF = 1,
+    RV_ABI_EXIT_HOST_STOP = 2,
+    RV_ABI_EXIT_ILLEGAL_INSTRUCTION = 3,
+    RV_ABI_EXIT_INVALID_TARGET = 4,
+    RV_ABI_EXIT_CODE_WRITE = 5,
+    RV_ABI_EXIT_STACK_OVERFLOW = 6,
+    RV_ABI_EXIT_MEMORY_FAULT = 7,
+    RV_ABI_EXIT_UNRESOLVED_JUMP = 8,
+    RV_ABI_EXIT_HTIF_STALL = 9,
+    RV_ABI_EXIT_GOLDEN_MISMATCH = 10,
+};
+
+/* This build; RvState offsets are in bytes */
+enum {
+    RV_ABI_VERSION = 3,
+    RV_ABI_XLEN = 64,
+    RV_ABI_NUM_REGS = 32,
+    RV_ABI_MEMORY_BITS = 32,
+    RV_ABI_REG_BYTES = 8,
+    RV_ABI_OFFSET_REGS = 0,
+    RV_ABI_OFFSET_PC = 256,
+    RV_ABI_OFFSET_INSTRET = 264,
+    RV_ABI_OFFSET_RESERVATION_ADDR = 272,
+    RV_ABI_OFFSET_RESERVATION_VALID = 280,
+    RV_ABI_OFFSET_HAS_EXITED = 281,
+    RV_ABI_OFFSET_EXIT_CODE = 282,
+    RV_ABI_OFFSET_CANCEL_REQUESTED = 283,
+    RV_ABI_OFFSET_EXIT_CAUSE = 284,
+    RV_ABI_OFFSET_EXIT_INFO = 288,
+    RV_ABI_OFFSET_BRK = 296,
+    RV_ABI_OFFSET_START_BRK = 304,
+    RV_ABI_OFFSET_MEMORY = 312,
+    RV_ABI_OFFSET_IO = 320,
+    RV_ABI_OFFSET_TRACER = 328,
+    RV_ABI_FLAGS = 0x0,
+};
+
+extern const RvAbiInfo rv_abi_info;
+/* Execute from a PC. Returns: 0=continue, 1=exited, 2=suspended */
+int rv_execute_from(RvState* state, uint64_t pc);
@@ -0,0 +1,368 @@
+//! Stable ABI of generated libraries.
+//!
+//! Every library exports `rv_abi_info`, a const [`RvAbiInfo`] describing the
+//! build, and every output directory gets `rv_abi.h`, which declares it next
+//! to the entry point and the `RvState` offsets of the build. Hosts that load
+//! libraries without the runner check `abi_version` before anything else.
+
+use std::fmt::Write;
+
+use rvr_ir::Xlen;
+use rvr_trace_format::kind;
+
+use crate::c::{TracerKind, global_symbol, reg_type};
+use crate::config::{EmitConfig, InstretMode};
+use crate::layout::{ExitCause, RvStateLayout, STATE_LAYOUT_VERSION};
+
+/// Version of the generated-library ABI, exported in `rv_abi_info`.
+///
+/// Bump it whenever `rv_abi.h` of the default configuration changes (a
+/// field of `RvState` or `RvAbiInfo` moves, or a value changes meaning) and
+/// add the new header as `snapshots/rv_abi_v<N>.h`.
+pub const ABI_VERSION: u32 = 3;
+
+/// File name of the ABI header.
+pub const ABI_HEADER: &str = "rv_abi.h";
+
+/// Unprefixed name of the exported [`RvAbiInfo`].
+pub const ABI_INFO_SYMBOL: &str = "rv_abi_info";
+
+/// Feature bits of [`RvAbiInfo::flags`].
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AbiFeature {
+    /// Guest functions can be called through `RV_CALL_RETURN_PC`.
+    ExportFunctions = 1 << 0,
+    /// State and memory live at `RV_FIXED_STATE_ADDR`/`RV_FIXED_MEMORY_ADDR`.
+    FixedAddresses = 1 << 1,
+    /// The guest was compiled for the load bias `RV_LOAD_BIAS`.
+    LoadBias = 1 << 2,
+    /// Guest memory must be mapped from `<name>.segments`.
+    LazySegments = 1 << 3,
+    /// The guest stops when the host sets `RvState::cancel_requested`.
+    CheckCancel = 1 << 4,
+    /// HTIF `tohost` writes are handled by the library.
+    Htif = 1 << 5,
+}
+
+impl AbiFeature {
+    /// Every feature, in bit order.
+    pub const ALL: [Self; 6] = [
+        Self::ExportFunctions,
+        Self::FixedAddresses,
+        Self::LoadBias,
+        Self::LazySegments,
+        Self::CheckCancel,
+        Self::Htif,
+    ];
+
+    /// Name of the feature's bit in `rv_abi.h`.
+    #[must_use]
+    pub const fn c_name(self) -> &'static str {
+        match self {
+            Self::ExportFunctions => "RV_ABI_EXPORT_FUNCTIONS",
+            Self::FixedAddresses => "RV_ABI_FIXED_ADDRESSES",
+            Self::LoadBias => "RV_ABI_LOAD_BIAS",
+            Self::LazySegments => "RV_ABI_LAZY_SEGMENTS",
+            Self::CheckCancel => "RV_ABI_CHECK_CANCEL",
+            Self::Htif => "RV_ABI_HTIF",
+        }
+    }
+}
+
+/// Instret modes of [`RvAbiInfo::instret_mode`], by their `rv_abi.h` names.
+const INSTRET_MODES: [(InstretMode, &str); 4] = [
+    (InstretMode::Off, "RV_ABI_INSTRET_OFF"),
+    (InstretMode::Count, "RV_ABI_INSTRET_COUNT"),
+    (InstretMode::Suspend, "RV_ABI_INSTRET_SUSPEND"),
+    (
+        InstretMode::PerInstruction,
+        "RV_ABI_INSTRET_PER_INSTRUCTION",
+    ),
+];
+
+/// Build metadata every library exports as `rv_abi_info` (`RvAbiInfo` in
+/// `rv_abi.h`).
+///
+/// `abi_version` stays the first field in every version.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RvAbiInfo {
+    /// [`ABI_VERSION`] of the emitter.
+    pub abi_version: u32,
+    /// [`STATE_LAYOUT_VERSION`] of the emitter.
+    pub state_layout_version: u32,
+    /// Register width in bits (32 or 64).
+    pub xlen: u32,
+    /// Guest register count (16 for RVE).
+    pub num_regs: u32,
+    /// Log2 of the guest memory size.
+    pub memory_bits: u32,
+    /// Instret mode (`InstretMode::as_c_mode`).
+    pub instret_mode: u32,
+    /// Tracer kind (`RV_TRACER_KIND`).
+    pub tracer_kind: u32,
+    /// [`AbiFeature`] bits.
+    pub flags: u32,
+}
+
+impl RvAbiInfo {
+    /// Field names, in declaration order.
+    const FIELDS: [&str; 8] = [
+        "abi_version",
+        "state_layout_version",
+        "xlen",
+        "num_regs",
+        "memory_bits",
+        "instret_mode",
+        "tracer_kind",
+        "flags",
+    ];
+
+    /// Metadata of a library built with `config`.
+    #[must_use]
+    pub fn new<X: Xlen>(config: &EmitConfig<X>) -> Self {
+        let tracer_kind = config.tracer_config.builtin_kind().map_or_else(
+            || {
+                if config.has_tracing() {
+                    kind::CUSTOM
+                } else {
+                    kind::NONE
+                }
+            },
+            TracerKind::as_c_kind,
+        );
+        let features = [
+            (AbiFeature::ExportFunctions, config.export_functions),
+            (AbiFeature::FixedAddresses, config.fixed_addresses.is_some()),
+            (AbiFeature::LoadBias, config.load_bias.is_some()),
+            (AbiFeature::LazySegments, config.lazy_segment_init()),
+            (AbiFeature::CheckCancel, config.check_cancel()),
+            (AbiFeature::Htif, config.htif_enabled()),
+        ];
+        let flags = features
+            .into_iter()
+            .filter(|(_, enabled)| *enabled)
+            .fold(0, |flags, (feature, _)| flags | feature as u32);
+        Self {
+            abi_version: ABI_VERSION,
+            state_layout_version: STATE_LAYOUT_VERSION,
+            xlen: u32::from(X::VALUE),
+            num_regs: u32::try_from(config.num_regs).unwrap_or(u32::MAX),
+            memory_bits: u32::from(config.memory_bits),
+            instret_mode: config.instret_mode.as_c_mode(),
+            tracer_kind,
+            flags,
+        }
+    }
+
+    /// The same metadata without `feature`.
+    #[must_use]
+    pub const fn without(mut self, feature: AbiFeature) -> Self {
+        self.flags &= !(feature as u32);
+        self
+    }
+
+    /// Whether the library was built with `feature`.
+    #[must_use]
+    pub const fn has(self, feature: AbiFeature) -> bool {
+        self.flags & feature as u32 != 0
+    }
+
+    /// Field values, in declaration order (for the asm backends).
+    #[must_use]
+    pub const fn words(self) -> [u32; 8] {
+        [
+            self.abi_version,
+            self.state_layout_version,
+            self.xlen,
+            self.num_regs,
+            self.memory_bits,
+            self.instret_mode,
+            self.tracer_kind,
+            self.flags,
+        ]
+    }
+
+    /// C definition of `rv_abi_info`, linked under `prefix` (the file must
+    /// include `rv_abi.h`).
+    #[must_use]
+    pub fn c_definition(self, prefix: &str) -> String {
+        let mut s = format!(
+            "const RvAbiInfo {} = {{\n",
+            global_symbol(prefix, ABI_INFO_SYMBOL)
+        );
+        for (field, value) in Self::FIELDS.iter().zip(self.words()) {
+            writeln!(s, "    .{field} = {value},").unwrap();
+        }
+        s.push_str("};\n");
+        s
+    }
+}
+
+/// Render `rv_abi.h` for a library built with `config`.
+///
+/// The types are shared by every build of this ABI version, so a host
+/// includes one library's header; the constants describe this build and
+/// carry the program's symbol prefix.
+#[must_use]
+pub fn gen_abi_header<X: Xlen>(config: &EmitConfig<X>) -> String {
+    let prefix = &config.symbol_prefix;
+    let info = RvAbiInfo::new(config);
+    let layout = RvStateLayout::new(config);
+    let rtype = reg_type::<X>();
+
+    let mut s = String::from(
+        r"/* Generated by RVR: ABI of the library in this directory. Check
+ * rv_abi_info.abi_version against RV_ABI_VERSION before using anything
+ * else; the RvState offsets hold for this build only. */
+#pragma once
+
+#include <stdint.h>
+
+typedef struct RvState RvState;
+
+/* Build metadata of a library (tracer_kind: 0 none, 255 custom) */
+typedef struct RvAbiInfo {
+",
+    );
+    for field in RvAbiInfo::FIELDS {
+        writeln!(s, "    uint32_t {field};").unwrap();
+    }
+    s.push_str("} RvAbiInfo;\n\n/* Bits of RvAbiInfo.flags */\nenum RvAbiFeature {\n");
+    for feature in AbiFeature::ALL {
+        writeln!(s, "    {} = {:#x},", feature.c_name(), feature as u32).unwrap();
+    }
+    s.push_str("};\n\n/* RvAbiInfo.instret_mode values */\nenum RvAbiInstretMode {\n");
+    for (mode, name) in INSTRET_MODES {
+        writeln!(s, "    {name} = {},", mode.as_c_mode()).unwrap();
+    }
+    s.push_str("};\n\n/* RvState.exit_cause values */\nenum RvAbiExitCause {\n");
+    for cause in ExitCause::ALL {
+        let name = cause.c_name().replacen("RV_", "RV_ABI_", 1);
+        writeln!(s, "    {name} = {},", cause as u32).unwrap();
+    }
+    s.push_str("};\n\n");
+
+    let mut constants = vec![
+        ("RV_ABI_VERSION", ABI_VERSION as usize),
+        ("RV_ABI_XLEN", usize::from(X::VALUE)),
+        ("RV_ABI_NUM_REGS", layout.num_regs),
+        ("RV_ABI_MEMORY_BITS", usize::from(config.memory_bits)),
+        ("RV_ABI_REG_BYTES", layout.reg_bytes),
+        ("RV_ABI_OFFSET_REGS", layout.offset_regs),
+        ("RV_ABI_OFFSET_PC", layout.offset_pc),
+        ("RV_ABI_OFFSET_INSTRET", layout.offset_instret),
+    ];
+    if layout.instret_suspend {
+        constants.push(("RV_ABI_OFFSET_TARGET_INSTRET", layout.offset_target_instret));
+    }
+    constants.extend([
+        (
+            "RV_ABI_OFFSET_RESERVATION_ADDR",
+            layout.offset_reservation_addr,
+        ),
+        (
+            "RV_ABI_OFFSET_RESERVATION_VALID",
+            layout.offset_reservation_valid,
+        ),
+        ("RV_ABI_OFFSET_HAS_EXITED", layout.offset_has_exited),
+        ("RV_ABI_OFFSET_EXIT_CODE", layout.offset_exit_code),
+        (
+            "RV_ABI_OFFSET_CANCEL_REQUESTED",
+            layout.offset_cancel_requested,
+        ),
+        ("RV_ABI_OFFSET_EXIT_CAUSE", layout.offset_exit_cause),
+        ("RV_ABI_OFFSET_EXIT_INFO", layout.offset_exit_info),
+        ("RV_ABI_OFFSET_BRK", layout.offset_brk),
+        ("RV_ABI_OFFSET_START_BRK", layout.offset_start_brk),
+        ("RV_ABI_OFFSET_MEMORY", layout.offset_memory),
+        ("RV_ABI_OFFSET_IO", layout.offset_io),
+        ("RV_ABI_OFFSET_TRACER", layout.offset_tracer),
+    ]);
+    s.push_str("/* This build; RvState offsets are in bytes */\nenum {\n");
+    for (name, value) in constants {
+        writeln!(s, "    {} = {value},", global_symbol(prefix, name)).unwrap();
+    }
+    writeln!(
+        s,
+        "    {} = {:#x},",
+        global_symbol(prefix, "RV_ABI_FLAGS"),
+        info.flags
+    )
+    .unwrap();
+
+    write!(
+        s,
+        r"}};
+
+extern const RvAbiInfo {info_symbol};
+/* Execute from a PC. Returns: 0=continue, 1=exited, 2=suspended */
+int {execute_from}(RvState* state, {rtype} pc);
+",
+        info_symbol = global_symbol(prefix, ABI_INFO_SYMBOL),
+        execute_from = global_symbol(prefix, "rv_execute_from"),
+    )
+    .unwrap();
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use rvr_ir::{Rv32, Rv64};
+
+    use super::*;
+
+    fn snapshot_path(version: u32) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("snapshots")
+            .join(format!("rv_abi_v{version}.h"))
+    }
+
+    /// A header change without an `ABI_VERSION` bump fails here.
+    #[test]
+    fn test_abi_header_snapshot() {
+        let header = gen_abi_header(&EmitConfig::<Rv64>::default());
+        let path = snapshot_path(ABI_VERSION);
+        let snapshot = std::fs::read_to_string(&path).unwrap_or_default();
+        assert!(
+            header == snapshot,
+            "rv_abi.h of the default config differs from {}; bump ABI_VERSION \
+             and add the new header as its snapshot:\n{header}",
+            path.display()
+        );
+    }
+
+    #[test]
+    fn test_abi_info_features() {
+        let config = EmitConfig::<Rv32>::default();
+        let info = RvAbiInfo::new(&config);
+        assert_eq!(info.abi_version, ABI_VERSION);
+        assert_eq!(info.xlen, 32);
+        assert!(!info.has(AbiFeature::CheckCancel));
+
+        let mut config = config.with_load_bias(0x1000);
+        config.flags.set_check_cancel(true);
+        let info = RvAbiInfo::new(&config);
+        assert!(info.has(AbiFeature::LoadBias));
+        assert!(info.has(AbiFeature::CheckCancel));
+        assert!(
+            !info
+                .without(AbiFeature::CheckCancel)
+                .has(AbiFeature::CheckCancel)
+        );
+        assert!(info.c_definition("").contains("    .flags = 20,\n"));
+    }
+
+    #[test]
+    fn test_abi_header_prefixed_names() {
+        let mut config = EmitConfig::<Rv32>::default();
+        config.symbol_prefix = "g_".to_string();
+        let header = gen_abi_header(&config);
+        assert!(header.contains("extern const RvAbiInfo g_rv_abi_info;\n"));
+        assert!(header.contains("    g_RV_ABI_OFFSET_PC = 128,\n"));
+        assert!(header.contains("int g_rv_execute_from(RvState* state, uint32_t pc);\n"));
+    }
+}
@@ -4,6 +4,7 @@ use rvr_ir::Xlen;
 
 use super::Arm64Emitter;
 use super::registers::reserved;
+use crate::config::{DISPATCH_TABLE_ALIGN, DISPATCH_TABLE_HUGEPAGE_ALIGN};
 
 impl<X: Xlen> Arm64Emitter<X> {
     /// Emit a jump via the dispatch table.
@@ -77,10 +78,16 @@ impl<X: Xlen> Arm64Emitter<X> {
         }
     }
 
-    /// Emit the jump table in .rodata section.
+    /// Emit the jump table in .rodata section, or in its own huge-page
+    /// aligned section (see `EmitConfig::dispatch_table_hugepages`).
     pub fn emit_jump_table(&mut self) {
-        self.emit_raw(".section .rodata");
-        self.emit_raw(".balign 4");
+        if self.config.dispatch_table_hugepages() {
+            self.emit_raw(".section .rodata.rv_dispatch, \"a\"");
+            self.emit_raw(&format!(".balign {DISPATCH_TABLE_HUGEPAGE_ALIGN}"));
+        } else {
+            self.emit_raw(".section .rodata");
+            self.emit_raw(&format!(".balign {DISPATCH_TABLE_ALIGN}"));
+        }
         self.emit_label("jump_table");
 
         let text_start = self.inputs.text_start;
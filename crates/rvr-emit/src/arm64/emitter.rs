@@ -722,7 +722,7 @@ impl<X: Xlen> Arm64Emitter<X> {
             self.emitf(format!("cbz x2, {ok_label}")); // all zeros is valid
             self.emit("cmn x2, #1"); // compare with -1 (all ones)
             self.emitf(format!("b.eq {ok_label}")); // all ones is valid
-            self.emit("b asm_trap");
+            self.emit("b asm_trap_memory");
             self.emit_label(&ok_label);
         }
 
@@ -3,23 +3,30 @@
 //! Generates assembly to handle HTIF protocol used by riscv-tests for:
 //! - Exit signaling (exit code via tohost)
 //! - Syscall handling (via `handle_tohost_write`)
+//! - The `fromhost` poll watchdog (via `htif_poll`)
 //!
 //! The HTIF protocol writes to the tohost address:
 //! - If value & 1 == 1: exit with code = value >> 1
 //! - Otherwise: syscall (call `handle_tohost_write`)
+//!
+//! Word and doubleword register loads from `fromhost` call `htif_poll`,
+//! which stops the guest with `HtifStall` once it has polled
+//! `htif_poll_limit` times without an answer.
 
-use rvr_ir::Xlen;
+use rvr_ir::{Expr, Xlen};
 
 use super::Arm64Emitter;
 use super::registers::reserved;
-use crate::htif::TOHOST_ADDR;
+use crate::htif::{FROMHOST_ADDR, TOHOST_ADDR};
+use crate::layout::ExitCause;
 
 impl<X: Xlen> Arm64Emitter<X> {
     /// Emit HTIF tohost check for memory stores.
     ///
     /// Called after the guest address is computed in x0 and value is in x1.
     /// If the address matches `TOHOST_ADDR` and value indicates exit (LSB=1),
-    /// sets `has_exited` and `exit_code`, then branches to `asm_exit`.
+    /// sets `has_exited`, `exit_code`, `exit_cause` and `exit_info`, then
+    /// branches to `asm_exit`.
     ///
     /// For syscall requests (LSB=0), calls `handle_tohost_write` and skips
     /// the normal store (the handler does any needed memory writes).
@@ -59,6 +66,18 @@ impl<X: Xlen> Arm64Emitter<X> {
             reserved::STATE_PTR,
             exit_code
         ));
+        // exit_info keeps the whole value >> 1 (the riscv-tests test number)
+        self.emitf(format!(
+            "str x2, [{}, #{}]",
+            reserved::STATE_PTR,
+            self.layout.offset_exit_info
+        ));
+        self.emitf(format!("mov w0, #{}", ExitCause::Htif as u32));
+        self.emitf(format!(
+            "str w0, [{}, #{}]",
+            reserved::STATE_PTR,
+            self.layout.offset_exit_cause
+        ));
         self.emit("b asm_exit");
 
         self.emit_label(&not_exit_label);
@@ -94,4 +113,53 @@ impl<X: Xlen> Arm64Emitter<X> {
 
         done_store_label
     }
+
+    /// Whether a register load of `width` bytes gets the `fromhost` poll
+    /// check, as in the C backend.
+    pub(super) const fn htif_polls(&self, width: u8) -> bool {
+        self.config.htif_enabled() && self.config.htif_poll_limit > 0 && (width == 4 || width == 8)
+    }
+
+    /// Emit the `fromhost` poll check before a register load from
+    /// `base + offset`.
+    ///
+    /// If the address matches `FROMHOST_ADDR` (as `(uint32_t)addr`, like the
+    /// C backend), calls `htif_poll(state)`. When that reports a stall (it
+    /// sets the exit fields), stores the PC of the load, leaves instret at
+    /// the instructions retired before it and branches to `asm_exit`.
+    pub(super) fn emit_htif_poll(&mut self, base: &Expr<X>, offset: i16) {
+        let not_fromhost = self.next_label("not_fromhost");
+        let base_reg = self.emit_expr_as_addr(base);
+        if offset != 0 {
+            self.emit_add_offset("x0", &base_reg, offset.into());
+        } else if base_reg != "x0" {
+            self.emitf(format!("mov x0, {base_reg}"));
+        }
+        self.load_imm(
+            "w2",
+            u64::from(u32::try_from(FROMHOST_ADDR).expect("fromhost fits in u32")),
+        );
+        self.emit("cmp w0, w2");
+        self.emitf(format!("b.ne {not_fromhost}"));
+
+        self.save_hot_regs_to_state();
+        self.emitf(format!("mov x0, {}", reserved::STATE_PTR));
+        self.emit("bl htif_poll");
+        self.restore_hot_regs_from_state();
+        // Only the low byte of a returned bool is defined
+        self.emit("tst w0, #0xff");
+        self.emitf(format!("b.eq {not_fromhost}"));
+
+        self.emit_store_next_pc_imm(self.current_pc);
+        if self.config.instret_mode.counts() && !self.config.instret_mode.per_instruction() {
+            // The load's instruction was counted on entry but never retires
+            self.emitf(format!(
+                "sub {}, {}, #1",
+                reserved::INSTRET,
+                reserved::INSTRET
+            ));
+        }
+        self.emit("b asm_exit");
+        self.emit_label(&not_fromhost);
+    }
 }
@@ -0,0 +1,239 @@
+//! A extension lowering for ARM64.
+//!
+//! AMOs map to exclusive retry loops, or to single LSE instructions with
+//! `EmitConfig::arm64_use_lse`. The aq/rl bits select the acquire/release
+//! forms.
+//!
+//! LR/SC keep the IR lowering: the host exclusive monitor can be lost at
+//! any time (e.g. across a suspend or a context switch), so SC would fail
+//! nondeterministically and differ from the other backends. The IR follows
+//! `EmitConfig::lrsc_model` on every backend.
+
+use rvr_ir::{InstrIR, Xlen};
+use rvr_isa::{EXT_A, decode_funct3, decode_rd, decode_rs1, decode_rs2};
+
+use crate::arm64::Arm64Emitter;
+use crate::arm64::registers::reserved;
+
+/// Status register of exclusive stores (x30 is saved by the prologue and
+/// only clobbered by extern calls).
+const STATUS: &str = "w30";
+
+/// Read-modify-write operation of an AMO.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum AmoOp {
+    Swap,
+    Add,
+    Xor,
+    And,
+    Or,
+    Min,
+    Max,
+    Minu,
+    Maxu,
+}
+
+impl AmoOp {
+    const fn from_funct5(funct5: u32) -> Option<Self> {
+        Some(match funct5 {
+            0x01 => Self::Swap,
+            0x00 => Self::Add,
+            0x04 => Self::Xor,
+            0x0C => Self::And,
+            0x08 => Self::Or,
+            0x10 => Self::Min,
+            0x14 => Self::Max,
+            0x18 => Self::Minu,
+            0x1C => Self::Maxu,
+            _ => return None,
+        })
+    }
+
+    /// LSE instruction, before the ordering suffix. `And` clears the
+    /// complement of the source.
+    const fn lse_mnemonic(self) -> &'static str {
+        match self {
+            Self::Swap => "swp",
+            Self::Add => "ldadd",
+            Self::Xor => "ldeor",
+            Self::And => "ldclr",
+            Self::Or => "ldset",
+            Self::Min => "ldsmin",
+            Self::Max => "ldsmax",
+            Self::Minu => "ldumin",
+            Self::Maxu => "ldumax",
+        }
+    }
+}
+
+/// Operand fields of an A-extension instruction.
+struct Atomic {
+    rd: u8,
+    rs1: u8,
+    rs2: u8,
+    /// Access width in bytes (4 for .W, 8 for .D).
+    width: u8,
+    acquire: bool,
+    release: bool,
+}
+
+impl Atomic {
+    const fn decode(raw: u32) -> Self {
+        Self {
+            rd: decode_rd(raw),
+            rs1: decode_rs1(raw),
+            rs2: decode_rs2(raw),
+            width: if decode_funct3(raw) == 3 { 8 } else { 4 },
+            acquire: (raw >> 26) & 1 != 0,
+            release: (raw >> 25) & 1 != 0,
+        }
+    }
+
+    /// Host register `n` at the access width.
+    fn reg(&self, n: u8) -> String {
+        if self.width == 8 {
+            format!("x{n}")
+        } else {
+            format!("w{n}")
+        }
+    }
+
+    /// Ordering suffix of LSE instructions.
+    const fn lse_ordering(&self) -> &'static str {
+        match (self.acquire, self.release) {
+            (true, true) => "al",
+            (true, false) => "a",
+            (false, true) => "l",
+            (false, false) => "",
+        }
+    }
+
+    const fn load_exclusive(&self) -> &'static str {
+        if self.acquire { "ldaxr" } else { "ldxr" }
+    }
+
+    const fn store_exclusive(&self) -> &'static str {
+        if self.release { "stlxr" } else { "stxr" }
+    }
+}
+
+impl<X: Xlen> Arm64Emitter<X> {
+    /// Emit an AMO with host atomics.
+    ///
+    /// Returns false for other instructions (LR/SC included), and when
+    /// tracing is on: traced builds keep the IR lowering so every hook fires
+    /// as on the C backend.
+    pub(super) fn try_emit_atomic(&mut self, instr: &InstrIR<X>) -> bool {
+        if instr.op >> 8 != u16::from(EXT_A) || self.config.has_tracing() {
+            return false;
+        }
+        let Some(op) = AmoOp::from_funct5((instr.raw >> 27) & 0x1F) else {
+            return false;
+        };
+        self.emit_amo(&Atomic::decode(instr.raw), op);
+        true
+    }
+
+    /// AMO: one LSE instruction, or a load/store-exclusive retry loop.
+    fn emit_amo(&mut self, amo: &Atomic, op: AmoOp) {
+        let src = self.atomic_src(amo);
+        self.load_atomic_addr(amo.rs1);
+        self.emit_atomic_host_addr();
+        let old = amo.reg(1);
+        if self.config.arm64_use_lse() {
+            let operand = if op == AmoOp::And {
+                let inverted = amo.reg(2);
+                self.emitf(format!("mvn {inverted}, {src}"));
+                inverted
+            } else {
+                src
+            };
+            self.emitf(format!(
+                "{}{} {operand}, {old}, [x0]",
+                op.lse_mnemonic(),
+                amo.lse_ordering()
+            ));
+        } else {
+            let retry_label = self.next_label("amo_retry");
+            let new = if op == AmoOp::Swap {
+                src.clone()
+            } else {
+                amo.reg(2)
+            };
+            self.emit_label(&retry_label);
+            self.emitf(format!("{} {old}, [x0]", amo.load_exclusive()));
+            match op {
+                AmoOp::Swap => {}
+                AmoOp::Add => self.emitf(format!("add {new}, {old}, {src}")),
+                AmoOp::Xor => self.emitf(format!("eor {new}, {old}, {src}")),
+                AmoOp::And => self.emitf(format!("and {new}, {old}, {src}")),
+                AmoOp::Or => self.emitf(format!("orr {new}, {old}, {src}")),
+                AmoOp::Min | AmoOp::Max | AmoOp::Minu | AmoOp::Maxu => {
+                    // Condition under which the old value is kept
+                    let keep_old = match op {
+                        AmoOp::Min => "lt",
+                        AmoOp::Max => "gt",
+                        AmoOp::Minu => "lo",
+                        _ => "hi",
+                    };
+                    self.emitf(format!("cmp {old}, {src}"));
+                    self.emitf(format!("csel {new}, {old}, {src}, {keep_old}"));
+                }
+            }
+            self.emitf(format!("{} {STATUS}, {new}, [x0]", amo.store_exclusive()));
+            self.emitf(format!("cbnz {STATUS}, {retry_label}"));
+        }
+        // Any AMO clears the reservation, as in the IR lowering
+        self.clear_reservation();
+        self.write_atomic_result(amo);
+    }
+
+    /// Source operand (rs2) at the access width. Cold registers go through
+    /// the cold cache (x17), so x0-x2 stay free.
+    fn atomic_src(&mut self, amo: &Atomic) -> String {
+        if amo.rs2 == 0 {
+            return if amo.width == 8 { "xzr" } else { "wzr" }.to_string();
+        }
+        let reg = self.load_rv_to_temp(amo.rs2, Self::temp2());
+        if amo.width == 8 {
+            Self::reg_64(&reg)
+        } else {
+            Self::reg_32(&reg)
+        }
+    }
+
+    /// Load the guest address in rs1 into x0.
+    fn load_atomic_addr(&mut self, rs1: u8) {
+        let addr = self.load_rv_as_addr(rs1, "x0");
+        if addr != "x0" {
+            self.emitf(format!("mov x0, {addr}"));
+        }
+    }
+
+    /// Turn the guest address in x0 into a host pointer. Exclusive and LSE
+    /// instructions only take a base register.
+    fn emit_atomic_host_addr(&mut self) {
+        self.apply_address_mode("x0");
+        self.emitf(format!("add x0, {}, x0", reserved::MEMORY_PTR));
+    }
+
+    fn clear_reservation(&mut self) {
+        self.emitf(format!(
+            "strb wzr, [{}, #{}]",
+            reserved::STATE_PTR,
+            self.layout.offset_reservation_valid
+        ));
+    }
+
+    /// Write the loaded value in x1 to rd; .W values are sign-extended on RV64.
+    fn write_atomic_result(&mut self, amo: &Atomic) {
+        if amo.rd == 0 {
+            return;
+        }
+        if X::VALUE == 64 && amo.width == 4 {
+            self.emit("sxtw x1, w1");
+        }
+        self.store_to_rv(amo.rd, Self::temp2());
+        self.cold_cache_invalidate(amo.rd);
+    }
+}
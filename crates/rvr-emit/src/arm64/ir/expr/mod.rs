@@ -83,7 +83,7 @@ impl<X: Xlen> Arm64Emitter<X> {
         }
     }
 
-    fn emit_store_next_pc_imm(&mut self, next_pc: u64) {
+    pub(crate) fn emit_store_next_pc_imm(&mut self, next_pc: u64) {
         let pc_offset = self.layout.offset_pc;
         if X::VALUE == 32 {
             self.load_imm("w1", next_pc);
@@ -259,6 +259,10 @@ impl<X: Xlen> Arm64Emitter<X> {
         self.emit_label(&continue_label);
     }
 
+    /// Branch to `label` if `cond` holds (fails, with `invert`) by setting
+    /// flags with one `cmp`/`cmn`/`cbz` and branching on them, without
+    /// materializing the condition. Returns false if `cond` is not a
+    /// comparison (or a `&&`/`||` of comparisons).
     pub(super) fn try_emit_compare_branch(
         &mut self,
         cond: &Expr<X>,
@@ -271,15 +275,13 @@ impl<X: Xlen> Arm64Emitter<X> {
         if let Some(handled) = self.try_emit_compare_logic(*op, left, right, label, invert) {
             return handled;
         }
-        let cond_code = match (op, invert) {
-            (BinaryOp::Eq, false) | (BinaryOp::Ne, true) => "eq",
-            (BinaryOp::Ne, false) | (BinaryOp::Eq, true) => "ne",
-            (BinaryOp::Lt, false) | (BinaryOp::Ge, true) => "lt",
-            (BinaryOp::Ge, false) | (BinaryOp::Lt, true) => "ge",
-            (BinaryOp::Ltu, false) | (BinaryOp::Geu, true) => "lo",
-            (BinaryOp::Geu, false) | (BinaryOp::Ltu, true) => "hs",
-            _ => return false,
+        // A constant on the left (`blt zero, a0`, i.e. bgtz) compares the
+        // other operand against it instead, with the condition mirrored
+        let swap = Self::is_const_operand(left) && !Self::is_const_operand(right);
+        let Some(cond_code) = Self::branch_cond_code(*op, invert, swap) else {
+            return false;
         };
+        let (left, right) = if swap { (right, left) } else { (left, right) };
 
         let temp1 = Self::temp1();
         let temp2 = Self::temp2();
@@ -290,8 +292,13 @@ impl<X: Xlen> Arm64Emitter<X> {
             left_reg
         };
 
-        if matches!(op, BinaryOp::Eq | BinaryOp::Ne) && Self::is_zero_expr(right) {
-            let branch = if cond_code == "eq" { "cbz" } else { "cbnz" };
+        // Unsigned "at most 0" is "is 0", and "above 0" is "not 0"
+        if Self::is_zero_expr(right) && matches!(cond_code, "eq" | "ne" | "ls" | "hi") {
+            let branch = if matches!(cond_code, "eq" | "ls") {
+                "cbz"
+            } else {
+                "cbnz"
+            };
             self.emitf(format!("{branch} {left_reg}, {label}"));
             return true;
         }
@@ -339,6 +346,38 @@ impl<X: Xlen> Arm64Emitter<X> {
         true
     }
 
+    /// Condition code of a branch on `op`, negated with `invert`, and
+    /// mirrored with `swap` for operands compared in reverse order.
+    const fn branch_cond_code(op: BinaryOp, invert: bool, swap: bool) -> Option<&'static str> {
+        let op = match (op, invert) {
+            (BinaryOp::Eq, true) => BinaryOp::Ne,
+            (BinaryOp::Ne, true) => BinaryOp::Eq,
+            (BinaryOp::Lt, true) => BinaryOp::Ge,
+            (BinaryOp::Ge, true) => BinaryOp::Lt,
+            (BinaryOp::Ltu, true) => BinaryOp::Geu,
+            (BinaryOp::Geu, true) => BinaryOp::Ltu,
+            (op, _) => op,
+        };
+        Some(match (op, swap) {
+            (BinaryOp::Eq, _) => "eq",
+            (BinaryOp::Ne, _) => "ne",
+            (BinaryOp::Lt, false) => "lt",
+            (BinaryOp::Lt, true) => "gt",
+            (BinaryOp::Ge, false) => "ge",
+            (BinaryOp::Ge, true) => "le",
+            (BinaryOp::Ltu, false) => "lo",
+            (BinaryOp::Ltu, true) => "hi",
+            (BinaryOp::Geu, false) => "hs",
+            (BinaryOp::Geu, true) => "ls",
+            _ => return None,
+        })
+    }
+
+    /// x0 or an immediate: a comparison operand that needs no register.
+    const fn is_const_operand(expr: &Expr<X>) -> bool {
+        matches!(expr, Expr::Imm(_) | Expr::Read(ReadExpr::Reg(0)))
+    }
+
     fn try_emit_compare_logic(
         &mut self,
         op: BinaryOp,
@@ -445,7 +484,7 @@ impl<X: Xlen> Arm64Emitter<X> {
 
     /// Emit an expression for use as a 64-bit address.
     /// For RV32, ensures the result is zero-extended to 64-bit.
-    pub(super) fn emit_expr_as_addr(&mut self, expr: &Expr<X>) -> String {
+    pub(crate) fn emit_expr_as_addr(&mut self, expr: &Expr<X>) -> String {
         match expr {
             Expr::Read(ReadExpr::Reg(reg)) => self.load_rv_as_addr(*reg, "x0"),
             Expr::Imm(val) => {
@@ -577,7 +616,7 @@ impl<X: Xlen> Arm64Emitter<X> {
     fn emit_expr_read_csr(&mut self, csr: u16, dest: &str) -> String {
         let instret_off = self.layout.offset_instret;
         match csr {
-            0xC00 | 0xC02 | 0xB00 | 0xB02 => {
+            0xC00 | 0xC01 | 0xC02 | 0xB00 | 0xB02 => {
                 if self.config.instret_mode.counts() {
                     if X::VALUE == 32 {
                         let instret32 = Self::reg_32(reserved::INSTRET);
@@ -593,7 +632,7 @@ impl<X: Xlen> Arm64Emitter<X> {
                     ));
                 }
             }
-            0xC80 | 0xC82 | 0xB80 | 0xB82 if X::VALUE == 32 => {
+            0xC80 | 0xC81 | 0xC82 | 0xB80 | 0xB82 if X::VALUE == 32 => {
                 if self.config.instret_mode.counts() {
                     let dest64 = Self::reg_64(dest);
                     self.emitf(format!("lsr {dest64}, {}, #32", reserved::INSTRET));
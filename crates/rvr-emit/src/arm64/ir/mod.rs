@@ -4,6 +4,7 @@
 
 use rvr_ir::{Stmt, WriteTarget, Xlen};
 
+mod atomic;
 mod expr;
 mod stmt;
 mod terminator;
@@ -1,4 +1,4 @@
-use rvr_ir::{Stmt, WriteTarget, Xlen};
+use rvr_ir::{Expr, ReadExpr, Stmt, WriteTarget, Xlen};
 
 use crate::arm64::Arm64Emitter;
 use crate::arm64::registers::reserved;
@@ -18,6 +18,19 @@ impl<X: Xlen> Arm64Emitter<X> {
     }
 
     fn emit_write_stmt(&mut self, target: &WriteTarget<X>, value: &rvr_ir::Expr<X>) {
+        if let (
+            WriteTarget::Reg(_),
+            Expr::Read(ReadExpr::Mem {
+                base,
+                offset,
+                width,
+                ..
+            }),
+        ) = (target, value)
+            && self.htif_polls(*width)
+        {
+            self.emit_htif_poll(base, *offset);
+        }
         match target {
             WriteTarget::Reg(reg) => self.emit_write_reg(*reg, value),
             WriteTarget::Mem {
@@ -1,8 +1,11 @@
-use rvr_ir::{BinaryOp, Expr, InstrIR, Terminator, Xlen};
+use std::collections::HashMap;
+
+use rvr_ir::{BinaryOp, Expr, InstrIR, Terminator, Xlen, block_ends, early_jump_target};
 
 use super::stmt_writes_to_exited;
 use crate::arm64::Arm64Emitter;
 use crate::arm64::registers::reserved;
+use crate::config::DISPATCH_PREFETCH_DISTANCE;
 
 impl<X: Xlen> Arm64Emitter<X> {
     /// Emit a terminator, using the actual fall-through PC from the output stream.
@@ -84,7 +87,7 @@ impl<X: Xlen> Arm64Emitter<X> {
             }
             Terminator::Trap { message } => {
                 self.emit_comment(&format!("trap: {message}"));
-                self.emit("b asm_trap");
+                self.emit("b asm_trap_illegal");
             }
         }
     }
@@ -98,6 +101,7 @@ impl<X: Xlen> Arm64Emitter<X> {
         fall_pc: u64,
     ) {
         let pc = X::to_u64(instr.pc);
+        self.current_pc = pc;
         self.emit_trace_pc(pc, instr.raw);
         if !self.config.instret_mode.per_instruction() {
             self.emit_instret_increment(1, pc);
@@ -117,12 +121,14 @@ impl<X: Xlen> Arm64Emitter<X> {
             }
         }
 
-        let stmt_count = instr.statements.len();
-        for (idx, stmt) in instr.statements.iter().enumerate() {
-            if skip_last_temp_cmp && idx + 1 == stmt_count {
-                continue;
+        if !self.try_emit_atomic(instr) {
+            let stmt_count = instr.statements.len();
+            for (idx, stmt) in instr.statements.iter().enumerate() {
+                if skip_last_temp_cmp && idx + 1 == stmt_count {
+                    continue;
+                }
+                self.emit_stmt(stmt);
             }
-            self.emit_stmt(stmt);
         }
 
         // If the instruction might set has_exited, check and branch to asm_exit
@@ -141,6 +147,8 @@ impl<X: Xlen> Arm64Emitter<X> {
             self.emit_instret_post_check(instr, fall_pc, pc);
         }
 
+        // Every back-edge ends a block, so `SuspendGranularity::BackEdges`
+        // needs no checks beyond the block ends
         if is_last_in_block
             && self.config.instret_mode.suspends()
             && !self.config.instret_mode.per_instruction()
@@ -200,19 +208,76 @@ impl<X: Xlen> Arm64Emitter<X> {
 
     /// Emit code for a linear instruction stream.
     pub fn emit_instructions(&mut self, instrs: &[InstrIR<X>]) {
+        let ends = block_ends(instrs);
         self.emit_raw("// Generated code instructions");
         self.emit_blank();
+        let prefetches = self.dispatch_prefetches(instrs);
         for (i, instr) in instrs.iter().enumerate() {
             let pc = X::to_u64(instr.pc);
             if self.label_pcs.contains(&pc) {
                 self.emit_pc_label(pc);
             }
+            if self.config.emit_guest_pc_map()
+                && let Some(loc) = self.inputs.guest_pc_lines.asm_loc(pc)
+            {
+                self.emit(&loc);
+            }
+            if let Some(addr) = prefetches.get(&i) {
+                self.emit_dispatch_prefetch(addr);
+            }
             let fall_pc = if i + 1 < instrs.len() {
                 X::to_u64(instrs[i + 1].pc)
             } else {
                 pc + u64::from(instr.size)
             };
-            self.emit_instruction(instr, true, fall_pc);
+            self.emit_instruction(instr, ends[i], fall_pc);
+        }
+    }
+
+    /// Dispatch table prefetches of `instrs`: the jump address whose slot
+    /// to prefetch ahead of an instruction, by instruction index (see
+    /// `EmitConfig::prefetch_dispatch`). Labels start a new run, since
+    /// other paths enter there.
+    fn dispatch_prefetches<'a>(&self, instrs: &'a [InstrIR<X>]) -> HashMap<usize, &'a Expr<X>> {
+        let mut prefetches = HashMap::new();
+        if !self.config.prefetch_dispatch() {
+            return prefetches;
+        }
+        let mut start = 0;
+        for (i, instr) in instrs.iter().enumerate() {
+            if self.label_pcs.contains(&X::to_u64(instr.pc)) {
+                start = i;
+            }
+            if let Some((at, addr)) =
+                early_jump_target(&instrs[start..=i], DISPATCH_PREFETCH_DISTANCE)
+            {
+                prefetches.insert(start + at, addr);
+            }
+        }
+        prefetches
+    }
+
+    /// Prefetch the jump table slot for target `addr` (see
+    /// `EmitConfig::prefetch_dispatch`). A prefetch never faults, so out of
+    /// range targets need no check.
+    fn emit_dispatch_prefetch(&mut self, addr: &Expr<X>) {
+        let base_reg = self.emit_expr_as_addr(addr);
+        if base_reg != "x0" {
+            self.emitf(format!("mov x0, {base_reg}"));
+        }
+        let text_start = self.inputs.text_start;
+        if X::VALUE == 32 {
+            self.load_imm("w2", text_start);
+            self.emit("sub w0, w0, w2");
+            self.emit("lsr w0, w0, #1");
+        } else {
+            self.load_imm("x2", text_start);
+            self.emit("sub x0, x0, x2");
+            self.emit("lsr x0, x0, #1");
         }
+        self.emit("adrp x1, jump_table");
+        self.emit("add x1, x1, :lo12:jump_table");
+        self.emit("add x1, x1, x0, lsl #2");
+        self.emit("prfm pldl1keep, [x1]");
     }
 }
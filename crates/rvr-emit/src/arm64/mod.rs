@@ -9,6 +9,7 @@
 //! - `emitter` - Core emission helpers and register access
 //! - `dispatch` - Jump table and dispatch logic
 //! - `prologue` - Header, prologue, epilogue, runtime wrapper
+//! - `htif` - HTIF tohost handling and `fromhost` poll watchdog
 //! - `ir` - IR translation (expressions, statements, terminators)
 //! - `registers` - Register mapping
 
@@ -52,6 +53,8 @@ pub struct Arm64Emitter<X: Xlen> {
     pub(self) spill_depth: usize,
     /// Cached cold register (RV reg number) stored in `COLD_CACHE`.
     pub(self) cold_cache: Option<u8>,
+    /// PC of the instruction being emitted.
+    pub(self) current_pc: u64,
 }
 
 impl<X: Xlen> Arm64Emitter<X> {
@@ -81,6 +84,7 @@ impl<X: Xlen> Arm64Emitter<X> {
             label_counter: 0,
             spill_depth: 0,
             cold_cache: None,
+            current_pc: 0,
         }
     }
 
@@ -153,8 +157,22 @@ mod tests {
             valid_addresses: [0x8000_0000u64, 0x8000_0004, 0x8000_0008]
                 .into_iter()
                 .collect(),
+            code_ranges: Vec::new(),
+            vector: false,
             absorbed_to_merged: std::collections::HashMap::new(),
+            block_to_function: std::collections::HashMap::new(),
+            function_hot_regs: std::collections::HashMap::new(),
+            synthetic_blocks: std::collections::HashMap::new(),
+            quarantined: std::collections::BTreeMap::new(),
+            cold_blocks: std::collections::HashSet::new(),
+            exported_functions: Vec::new(),
             initial_brk: 0x8000_1000,
+            elf_hash: 0,
+            memory_layout: None,
+            guest_pc_lines: std::sync::Arc::default(),
+            host_hooks: Vec::new(),
+            golden: None,
+            interpreted: std::collections::BTreeSet::new(),
         }
     }
 
@@ -173,6 +191,11 @@ mod tests {
         let asm = emitter.assembly();
         assert!(asm.contains(".arch armv8-a"));
         assert!(asm.contains("PC_OFFSET"));
+
+        let config = EmitConfig::<Rv64>::default().with_arm64_lse(true);
+        let mut emitter = Arm64Emitter::new(config, test_inputs());
+        emitter.emit_header();
+        assert!(emitter.assembly().contains(".arch armv8.1-a+lse"));
     }
 
     #[test]
@@ -197,14 +220,162 @@ mod tests {
         assert!(asm.contains("asm_trap:"));
     }
 
+    /// Emit one A-extension instruction (opcode AMO, .D if `is_64`).
+    fn emit_atomic(
+        config: EmitConfig<Rv64>,
+        funct5: u8,
+        aq: bool,
+        rl: bool,
+        is_64: bool,
+    ) -> String {
+        use rvr_isa::{AExtension, InstructionExtension, encode_r};
+
+        let funct7 = (funct5 << 2) | (u8::from(aq) << 1) | u8::from(rl);
+        let raw = encode_r(0x2F, 10, if is_64 { 3 } else { 2 }, 11, 12, funct7);
+        let decoded = InstructionExtension::<Rv64>::decode32(&AExtension, raw, 0x8000_0000)
+            .expect("A instruction");
+        let instr = InstructionExtension::<Rv64>::lift(&AExtension, &decoded);
+        let mut emitter = Arm64Emitter::new(config, test_inputs());
+        emitter.emit_instructions(&[instr]);
+        emitter.assembly().to_string()
+    }
+
+    #[test]
+    fn test_amo_exclusive_loop() {
+        // amoadd.d.aqrl a0, a2, (a1)
+        let asm = emit_atomic(EmitConfig::default(), 0x00, true, true, true);
+        assert!(asm.contains("ldaxr x1, [x0]"));
+        assert!(asm.contains("add x2, x1, "));
+        assert!(asm.contains("stlxr w30, x2, [x0]"));
+        assert!(asm.contains("cbnz w30, .Lamo_retry_"));
+        assert!(!asm.contains("ldadd"));
+    }
+
+    #[test]
+    fn test_amo_lse() {
+        let config = EmitConfig::default().with_arm64_lse(true);
+        // amoand.w.aq a0, a2, (a1): ldclr of the complement, sign-extended
+        let asm = emit_atomic(config.clone(), 0x0C, true, false, false);
+        assert!(asm.contains("mvn w2, "));
+        assert!(asm.contains("ldclra w2, w1, [x0]"));
+        assert!(asm.contains("sxtw x1, w1"));
+        assert!(!asm.contains("ldxr"));
+        // amomaxu.d a0, a2, (a1)
+        let asm = emit_atomic(config, 0x1C, false, false, true);
+        assert!(asm.contains("ldumax "));
+        assert!(asm.contains(", x1, [x0]"));
+    }
+
+    #[test]
+    fn test_lr_sc_use_ir_lowering() {
+        // lr.w.aq a0, (a1): the exclusive monitor would make SC nondeterministic
+        let asm = emit_atomic(EmitConfig::default(), 0x02, true, false, false);
+        assert!(!asm.contains("ldaxr"));
+        // sc.d.rl a0, a2, (a1): store only with a matching reservation
+        let asm = emit_atomic(EmitConfig::default(), 0x03, false, true, true);
+        assert!(asm.contains(".Lif_else_"));
+        assert!(!asm.contains("stlxr"));
+    }
+
+    #[test]
+    fn test_traced_atomics_use_ir_lowering() {
+        let config = EmitConfig::default()
+            .with_tracer(crate::c::TracerConfig::builtin(crate::c::TracerKind::Diff));
+        let asm = emit_atomic(config, 0x00, false, false, true);
+        assert!(!asm.contains("ldxr"));
+    }
+
+    /// Emit `b<funct3> rs1, rs2, +8` with instret counting off.
+    fn emit_branch<X: Xlen>(funct3: u8, rs1: u8, rs2: u8) -> String {
+        use rvr_isa::{BaseExtension, InstructionExtension, encode_b};
+
+        let raw = encode_b(0x63, funct3, rs1, rs2, 8);
+        let decoded =
+            InstructionExtension::<X>::decode32(&BaseExtension, raw, X::from_u64(0x8000_0000))
+                .expect("branch instruction");
+        let instr = InstructionExtension::<X>::lift(&BaseExtension, &decoded);
+        let config = EmitConfig::<X>::default().with_instret_mode(crate::InstretMode::Off);
+        let mut emitter = Arm64Emitter::new(config, test_inputs());
+        emitter.emit_instructions(&[instr]);
+        emitter.assembly().to_string()
+    }
+
+    #[test]
+    fn test_branches_set_flags_directly() {
+        // (funct3, a0 ? a1, a0 ? zero, zero ? a0)
+        let cases = [
+            (0, "b.eq", "cbz", "cbz"),
+            (1, "b.ne", "cbnz", "cbnz"),
+            (4, "b.lt", "b.lt", "b.gt"),
+            (5, "b.ge", "b.ge", "b.le"),
+            (6, "b.lo", "b.lo", "cbnz"),
+            (7, "b.hs", "b.hs", "cbz"),
+        ];
+        for (funct3, regs, zero_right, zero_left) in cases {
+            for (rs1, rs2, branch) in [(10, 11, regs), (10, 0, zero_right), (0, 10, zero_left)] {
+                for asm in [
+                    emit_branch::<Rv64>(funct3, rs1, rs2),
+                    emit_branch::<rvr_ir::Rv32>(funct3, rs1, rs2),
+                ] {
+                    let context = format!("funct3 {funct3}, x{rs1}, x{rs2}:\n{asm}");
+                    assert!(asm.contains(&format!("{branch} ")), "{context}");
+                    assert!(!asm.contains("cset"), "{context}");
+                    // x0 is an immediate, never materialized in a register
+                    assert!(rs1 != 0 && rs2 != 0 || !asm.contains("mov "), "{context}");
+                }
+            }
+        }
+    }
+
     #[test]
     fn test_emit_jump_table() {
         let config = EmitConfig::<Rv64>::default();
         let mut emitter = Arm64Emitter::new(config, test_inputs());
         emitter.emit_jump_table();
         let asm = emitter.assembly();
-        assert!(asm.contains("jump_table:"));
+        assert!(asm.contains(".section .rodata\n.balign 64\njump_table:"));
         assert!(asm.contains(".word"));
+
+        let config = EmitConfig::<Rv64>::default().with_dispatch_table_hugepages(true);
+        let mut emitter = Arm64Emitter::new(config, test_inputs());
+        emitter.emit_jump_table();
+        let asm = emitter.assembly();
+        assert!(asm.contains(".section .rodata.rv_dispatch, \"a\"\n.balign 2097152\n"));
+    }
+
+    #[test]
+    fn test_dispatch_prefetch() {
+        use rvr_ir::{Expr, InstrIR, Stmt, Terminator};
+
+        // a1 is written by the instruction at `start`, then three more run
+        // before `jalr zero, 0(a1)`
+        let emit = |start: u64, prefetch: bool| {
+            let mut instrs: Vec<InstrIR<Rv64>> = (0..4)
+                .map(|i| {
+                    let pc = start + 4 * i;
+                    let rd = if i == 0 { 11 } else { 10 };
+                    let stmt = Stmt::write_reg(rd, Expr::add(Expr::reg(rd), Expr::imm(1)));
+                    InstrIR::new(pc, 4, 0, 0, vec![stmt], Terminator::fall(pc + 4))
+                })
+                .collect();
+            let jump = Terminator::jump_dyn(Expr::reg(11));
+            instrs.push(InstrIR::new(start + 16, 4, 0, 0, Vec::new(), jump));
+            let config = EmitConfig::<Rv64>::default().with_prefetch_dispatch(prefetch);
+            let mut emitter = Arm64Emitter::new(config, test_inputs());
+            emitter.emit_instructions(&instrs);
+            emitter.assembly().to_string()
+        };
+        // (start, prefetch, expected): the label at 0x8000_0008 is entered
+        // from elsewhere, leaving too few instructions after it
+        let cases = [
+            (0x8000_0010, true, true),
+            (0x8000_0010, false, false),
+            (0x8000_0000, true, false),
+        ];
+        for (start, prefetch, expected) in cases {
+            let asm = emit(start, prefetch);
+            assert_eq!(asm.contains("prfm pldl1keep, [x1]"), expected, "{asm}");
+        }
     }
 
     #[test]
@@ -229,4 +400,30 @@ mod tests {
         assert!(asm.contains("asm_trap:"));
         assert!(asm.contains("jump_table:"));
     }
+
+    #[test]
+    fn test_htif_poll_before_fromhost_load() {
+        use rvr_ir::{Expr, InstrIR, Stmt, Terminator};
+
+        let load = InstrIR::new(
+            0x8000_0000,
+            4,
+            0,
+            0,
+            vec![Stmt::write_reg(6, Expr::mem(Expr::reg(8), 0x40, 8, false))],
+            Terminator::fall(0x8000_0004),
+        );
+        for (limit, polls) in [(1000, true), (0, false)] {
+            let config = EmitConfig::<Rv64>::default()
+                .with_tohost(true)
+                .with_htif_poll_limit(limit);
+            let mut emitter = Arm64Emitter::new(config, test_inputs());
+            emitter.generate_instructions(std::slice::from_ref(&load));
+            assert_eq!(
+                emitter.assembly().contains("bl htif_poll"),
+                polls,
+                "limit {limit}"
+            );
+        }
+    }
 }
@@ -2,15 +2,22 @@
 
 use rvr_ir::Xlen;
 
-use crate::c::TracerKind;
+use crate::abi::{ABI_INFO_SYMBOL, AbiFeature, RvAbiInfo};
+use crate::c::{TracerKind, asm_file_directive};
+use crate::layout::{ASM_TRAP_ENTRIES, STATE_LAYOUT_VERSION};
 
-use super::Arm64Emitter;
 use super::registers::reserved;
+use super::{Arm64Emitter, HOT_REG_SLOTS};
 
 impl<X: Xlen> Arm64Emitter<X> {
     /// Emit the assembly file header.
     pub fn emit_header(&mut self) {
-        self.emit_raw(".arch armv8-a");
+        // LSE atomics are ARMv8.1
+        if self.config.arm64_use_lse() {
+            self.emit_raw(".arch armv8.1-a+lse");
+        } else {
+            self.emit_raw(".arch armv8-a");
+        }
         self.emit_blank();
         self.emit_raw("// Constants");
         self.emitf(format!(".set PC_OFFSET, {}", self.layout.offset_pc));
@@ -30,6 +37,14 @@ impl<X: Xlen> Arm64Emitter<X> {
             ".set EXIT_CODE_OFFSET, {}",
             self.layout.offset_exit_code
         ));
+        self.emitf(format!(
+            ".set EXIT_CAUSE_OFFSET, {}",
+            self.layout.offset_exit_cause
+        ));
+        self.emitf(format!(
+            ".set EXIT_INFO_OFFSET, {}",
+            self.layout.offset_exit_info
+        ));
         self.emitf(format!(".set MEMORY_OFFSET, {}", self.layout.offset_memory));
 
         // Fixed address constants if enabled
@@ -38,6 +53,10 @@ impl<X: Xlen> Arm64Emitter<X> {
             self.emitf(format!(".set RV_MEMORY_ADDR, 0x{:x}", fixed.memory_addr));
         }
 
+        if self.config.emit_guest_pc_map() {
+            self.emit_raw(&asm_file_directive());
+        }
+
         self.emit_blank();
     }
 
@@ -164,8 +183,22 @@ impl<X: Xlen> Arm64Emitter<X> {
         self.emit("ret");
         self.emit_blank();
 
-        self.emit_label("asm_trap");
-        self.emit_comment("Trap handler - set exit flag and exit (exit_code stays 0)");
+        self.emit_comment("Trap entries - record the cause, then stop");
+        let exit_cause = self.layout.offset_exit_cause;
+        for (i, (label, cause)) in ASM_TRAP_ENTRIES.iter().enumerate() {
+            self.emit_label(label);
+            self.emitf(format!("mov w0, #{}", *cause as u32));
+            self.emitf(format!(
+                "str w0, [{}, #{}]",
+                reserved::STATE_PTR,
+                exit_cause
+            ));
+            if i + 1 < ASM_TRAP_ENTRIES.len() {
+                self.emit("b asm_trap_stop");
+            }
+        }
+        self.emit_label("asm_trap_stop");
+        self.emit_comment("Set exit flag and exit (exit_code stays 0)");
         let has_exited = self.layout.offset_has_exited;
         self.emit("mov w0, #1");
         self.emitf(format!(
@@ -223,6 +256,15 @@ impl<X: Xlen> Arm64Emitter<X> {
         self.emit_raw(".section .rodata");
         self.emit_blank();
 
+        // rv_abi_info (asm code never polls cancel_requested)
+        let abi = RvAbiInfo::new(&self.config).without(AbiFeature::CheckCancel);
+        let words: Vec<String> = abi.words().iter().map(u32::to_string).collect();
+        self.emit_raw(".balign 4");
+        self.emit_raw(&format!(".global {ABI_INFO_SYMBOL}"));
+        self.emit_label(ABI_INFO_SYMBOL);
+        self.emitf(format!(".word {}", words.join(", ")));
+        self.emit_blank();
+
         // RV_TRACER_KIND
         let tracer_kind = self
             .config
@@ -249,6 +291,18 @@ impl<X: Xlen> Arm64Emitter<X> {
         self.emitf(format!(".word {instret_mode}"));
         self.emit_blank();
 
+        // RV_NUM_REGS
+        let num_regs = self.config.num_regs;
+        self.emit_raw(".global RV_NUM_REGS");
+        self.emit_label("RV_NUM_REGS");
+        self.emitf(format!(".word {num_regs}"));
+        self.emit_blank();
+
+        self.emit_raw(".global RV_STATE_LAYOUT_VERSION");
+        self.emit_label("RV_STATE_LAYOUT_VERSION");
+        self.emitf(format!(".word {STATE_LAYOUT_VERSION}"));
+        self.emit_blank();
+
         // Fixed addresses (if enabled)
         if let Some(fixed) = self.config.fixed_addresses {
             self.emit_raw(".global RV_FIXED_STATE_ADDR");
@@ -261,5 +315,33 @@ impl<X: Xlen> Arm64Emitter<X> {
             self.emitf(format!(".quad 0x{:x}", fixed.memory_addr));
             self.emit_blank();
         }
+
+        // Load bias (position-independent ELFs only)
+        if let Some(bias) = self.config.load_bias {
+            self.emit_raw(".global RV_LOAD_BIAS");
+            self.emit_label("RV_LOAD_BIAS");
+            self.emitf(format!(".quad 0x{bias:x}"));
+            self.emit_blank();
+        }
+
+        if self.config.lazy_segment_init() {
+            self.emit_raw(".global RV_LAZY_SEGMENTS");
+            self.emit_label("RV_LAZY_SEGMENTS");
+            self.emitf(".word 1");
+            self.emit_blank();
+        }
+
+        // Guest register in each hot register slot (0 if unused), for
+        // debuggers that follow the host code with the guest PC map
+        if self.config.emit_guest_pc_map() {
+            let slots: Vec<String> = (0..HOT_REG_SLOTS)
+                .map(|slot| self.config.hot_regs.get(slot).copied().unwrap_or(0))
+                .map(|reg| reg.to_string())
+                .collect();
+            self.emit_raw(".global RV_HOT_REGS");
+            self.emit_label("RV_HOT_REGS");
+            self.emitf(format!(".byte {}", slots.join(", ")));
+            self.emit_blank();
+        }
     }
 }
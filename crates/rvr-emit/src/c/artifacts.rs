@@ -0,0 +1,146 @@
+//! `CArtifacts` - a generated C project held in memory.
+//!
+//! `CProject::render_all` produces every file without touching the
+//! filesystem, for hosts that post-process the C or compile it themselves.
+//! `CArtifacts::write_to` lays the files out on disk; `CProject::write_all`
+//! is render plus write.
+
+use std::fs;
+use std::path::Path;
+
+use tracing::{debug, trace};
+
+use super::makefile::{COMPILE_COMMANDS, CompileCommand, render_compile_commands};
+use super::manifest::{PARTS_MANIFEST, PartsManifest, write_if_changed};
+use super::project::{ProjectStats, partition_file_name};
+
+/// Generated C project held in memory.
+///
+/// File names are relative to the project directory.
+#[derive(Clone, Debug, Default)]
+pub struct CArtifacts {
+    /// Base name of the generated files.
+    pub base_name: String,
+    /// File name of the main header.
+    pub header_name: String,
+    /// Main header.
+    pub header: String,
+    /// Partition sources as (file name, source), in PC order.
+    pub parts: Vec<(String, String)>,
+    /// Other sources and headers as (file name, contents): blocks header,
+    /// dispatch, memory, runtime support and optional sidecars.
+    pub files: Vec<(String, String)>,
+    /// Segment binaries the memory source `#embed`s, as (file name, bytes).
+    pub segment_bins: Vec<(String, Vec<u8>)>,
+    /// Makefile building `lib<base_name>.so`; not needed when the host
+    /// compiles the sources itself.
+    pub makefile: String,
+    /// Compile commands of the sources, written as `compile_commands.json`
+    /// for editors and tools by `write_to`.
+    pub compile_commands: Vec<CompileCommand>,
+    /// Partitioning and deduplication results.
+    pub stats: ProjectStats,
+    /// Part index of each entry of `parts`.
+    pub(super) part_indices: Vec<usize>,
+    /// Start PC and hash of each part, recorded next to the written parts.
+    pub(super) manifest: PartsManifest,
+}
+
+impl CArtifacts {
+    /// All text files as (file name, contents): the header, the parts, the
+    /// other files and the Makefile.
+    pub fn text_files(&self) -> impl Iterator<Item = (&str, &str)> {
+        std::iter::once((self.header_name.as_str(), self.header.as_str()))
+            .chain(self.parts.iter().chain(&self.files).map(as_strs))
+            .chain(std::iter::once(("Makefile", self.makefile.as_str())))
+    }
+
+    /// Add a file named like the last component of `path`.
+    pub(super) fn push_file(&mut self, path: &Path, contents: String) {
+        let name = path
+            .file_name()
+            .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+        self.files.push((name, contents));
+    }
+
+    /// Write the project into `dir`.
+    ///
+    /// Files already holding the same contents are left untouched, so make
+    /// only rebuilds what changed; parts are compared with the parts
+    /// manifest of the last emission into `dir`, and its parts that no
+    /// longer exist are removed. `compile_commands.json` is written with
+    /// `dir` as the compile directory. Returns the stats with the unchanged
+    /// parts counted.
+    ///
+    /// # Errors
+    /// Returns any I/O error while writing the project files.
+    pub fn write_to(&self, dir: &Path) -> std::io::Result<ProjectStats> {
+        fs::create_dir_all(dir)?;
+        let manifest_path = dir.join(PARTS_MANIFEST);
+        let previous = PartsManifest::load(&manifest_path);
+
+        let mut written = 0;
+        for (idx, (name, source)) in self.part_indices.iter().zip(&self.parts) {
+            let path = dir.join(name);
+            let unchanged = previous
+                .parts
+                .get(idx)
+                .zip(self.manifest.parts.get(idx))
+                .is_some_and(|(old, new)| old.hash == new.hash)
+                && path.exists();
+            if !unchanged {
+                trace!(path = %path.display(), "writing partition");
+                fs::write(path, source)?;
+                written += 1;
+            }
+        }
+        // Parts of the previous emission that no longer exist
+        for idx in previous.parts.keys() {
+            if !self.manifest.parts.contains_key(idx) {
+                let path = dir.join(partition_file_name(&self.base_name, *idx));
+                trace!(path = %path.display(), "removing stale partition");
+                remove_if_exists(&path)?;
+                remove_if_exists(&path.with_extension("o"))?;
+            }
+        }
+        write_if_changed(&manifest_path, self.manifest.render())?;
+        debug!(
+            written,
+            unchanged = self.parts.len() - written,
+            "wrote partitions"
+        );
+
+        let other_files = std::iter::once((self.header_name.as_str(), self.header.as_str()))
+            .chain(self.files.iter().map(as_strs))
+            .chain(std::iter::once(("Makefile", self.makefile.as_str())));
+        for (name, contents) in other_files {
+            let path = dir.join(name);
+            trace!(path = %path.display(), "writing file");
+            write_if_changed(&path, contents)?;
+        }
+        for (name, data) in &self.segment_bins {
+            let path = dir.join(name);
+            trace!(path = %path.display(), size = data.len(), "writing segment binary");
+            write_if_changed(&path, data)?;
+        }
+        let compile_commands = render_compile_commands(dir, &self.compile_commands);
+        write_if_changed(&dir.join(COMPILE_COMMANDS), compile_commands)?;
+
+        Ok(ProjectStats {
+            unchanged_partitions: self.parts.len() - written,
+            ..self.stats.clone()
+        })
+    }
+}
+
+fn as_strs((name, contents): &(String, String)) -> (&str, &str) {
+    (name, contents)
+}
+
+/// Remove a file, ignoring that it does not exist.
+fn remove_if_exists(path: &Path) -> std::io::Result<()> {
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
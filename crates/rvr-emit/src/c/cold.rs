@@ -0,0 +1,182 @@
+//! Outlined cold paths.
+//!
+//! Traps, exits and suspensions are rare, but emitted inline each one
+//! stores the exit status and every hot register, which bloats the hot
+//! block functions. With `EmitConfig::outline_cold_paths` a block instead
+//! stores the guest PC (and the faulting address, if any) and tail-calls a
+//! shared `cold, noinline` helper for the kind of stop, leaving a compare
+//! and a jump in the hot path.
+//!
+//! Helpers take the global block signature, so the call is a `musttail`
+//! transfer like a jump to another block and the stop returns through the
+//! same chain. Portable code saves its locals on every exit anyway and
+//! keeps the paths inline.
+
+use std::fmt::Write;
+
+use super::config::CDialect;
+use super::namespace::global_symbol;
+use super::signature::{FnSignature, state_ref};
+use crate::config::EmitFlags;
+use crate::layout::ExitCause;
+
+/// Kind of rare stop with a shared helper.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColdPath {
+    /// Suspension at an instret target or on cancellation: the guest can
+    /// resume at the stored PC.
+    Suspend,
+    /// The guest already recorded its exit (HTIF `tohost`, host hooks).
+    Exit,
+    /// Illegal or unsupported instruction.
+    IllegalInstruction,
+    /// Jump or branch to an address with no recompiled block, which is
+    /// stored in `exit_info`.
+    InvalidTarget,
+    /// Store into recompiled code at the address in `exit_info`.
+    CodeWrite,
+    /// Store into the stack guard at the address in `exit_info`.
+    StackOverflow,
+    /// Dynamic jump to the target in `exit_info`, which the dispatch table
+    /// lacks; the caller also stored the target's source register in
+    /// `exit_code`.
+    UnresolvedJump,
+}
+
+impl ColdPath {
+    /// Every kind, in definition order.
+    pub const ALL: [Self; 7] = [
+        Self::Suspend,
+        Self::Exit,
+        Self::IllegalInstruction,
+        Self::InvalidTarget,
+        Self::CodeWrite,
+        Self::StackOverflow,
+        Self::UnresolvedJump,
+    ];
+
+    /// Linked name of the helper function.
+    #[must_use]
+    pub fn fn_name(self, prefix: &str) -> String {
+        let name = match self {
+            Self::Suspend => "rv_cold_suspend",
+            Self::Exit => "rv_cold_exit",
+            Self::IllegalInstruction => "rv_cold_illegal_instruction",
+            Self::InvalidTarget => "rv_cold_invalid_target",
+            Self::CodeWrite => "rv_cold_code_write",
+            Self::StackOverflow => "rv_cold_stack_overflow",
+            Self::UnresolvedJump => "rv_cold_unresolved_jump",
+        };
+        global_symbol(prefix, name)
+    }
+
+    /// Whether the caller stores an address in `exit_info`.
+    #[must_use]
+    pub const fn takes_info(self) -> bool {
+        matches!(
+            self,
+            Self::InvalidTarget | Self::CodeWrite | Self::StackOverflow | Self::UnresolvedJump
+        )
+    }
+
+    /// Statements recording why execution stopped, run after the caller
+    /// stored `pc` (and `exit_info` if [`takes_info`](Self::takes_info)).
+    #[must_use]
+    pub fn status_stores(self, state: &str) -> Vec<String> {
+        let trap = |status: &str, code: &str, cause: ExitCause| {
+            vec![
+                format!("{state}->has_exited = {status};"),
+                format!("{state}->exit_code = {code};"),
+                format!("{state}->exit_cause = {};", cause.c_name()),
+            ]
+        };
+        let store_trap = |code: &str, cause: ExitCause| {
+            let mut stores = vec![format!("{state}->csrs[CSR_MTVAL] = {state}->exit_info;")];
+            stores.extend(trap("RV_TRAPPED", code, cause));
+            stores
+        };
+        match self {
+            Self::Suspend | Self::Exit => Vec::new(),
+            Self::IllegalInstruction => {
+                let mut stores = trap("RV_TRAPPED", "1", ExitCause::IllegalInstruction);
+                stores.push(format!("{state}->exit_info = 0;"));
+                stores
+            }
+            Self::InvalidTarget => trap("true", "1", ExitCause::InvalidTarget),
+            Self::CodeWrite => store_trap("RV_CODE_WRITE_TRAP", ExitCause::CodeWrite),
+            Self::StackOverflow => store_trap("RV_STACK_OVERFLOW_TRAP", ExitCause::StackOverflow),
+            Self::UnresolvedJump => vec![
+                format!("{state}->has_exited = RV_TRAPPED;"),
+                format!(
+                    "{state}->exit_cause = {};",
+                    ExitCause::UnresolvedJump.c_name()
+                ),
+            ],
+        }
+    }
+}
+
+/// Whether blocks call the cold path helpers: requested and clang code.
+#[must_use]
+pub const fn outlines_cold_paths(flags: EmitFlags, dialect: CDialect) -> bool {
+    flags.outline_cold_paths() && !dialect.is_portable()
+}
+
+/// Declarations of the helpers, for files whose blocks call them.
+#[must_use]
+pub fn cold_path_declarations(sig: &FnSignature, prefix: &str) -> String {
+    let mut s = String::from("/* Outlined trap, exit and suspension paths */\n");
+    for path in ColdPath::ALL {
+        let _ = writeln!(s, "{};", sig.fn_decl(&path.fn_name(prefix), &[]));
+    }
+    s
+}
+
+/// Definitions of the helpers: record the stop, save the arguments to
+/// state and return.
+#[must_use]
+pub fn gen_cold_paths(sig: &FnSignature, prefix: &str) -> String {
+    let state = state_ref(sig.fixed_addresses);
+    let mut s = String::new();
+    for path in ColdPath::ALL {
+        let _ = writeln!(
+            s,
+            "{} {{",
+            sig.fn_decl(&path.fn_name(prefix), &["cold", "noinline"])
+        );
+        for store in path.status_stores(state) {
+            let _ = writeln!(s, "    {store}");
+        }
+        if !sig.save_to_state.is_empty() {
+            let _ = writeln!(s, "    {}", sig.save_to_state.trim_start());
+        }
+        let _ = writeln!(s, "    {}\n}}\n", sig.stop());
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use rvr_ir::Rv64;
+
+    use super::*;
+    use crate::config::EmitConfig;
+
+    #[test]
+    fn test_cold_path_helpers() {
+        let config = EmitConfig::<Rv64>::default().with_outline_cold_paths(true);
+        assert!(outlines_cold_paths(config.flags, config.c_dialect));
+        let sig = FnSignature::new(&config);
+        let helpers = gen_cold_paths(&sig, "p_");
+        for path in ColdPath::ALL {
+            assert!(helpers.contains(&sig.fn_decl(&path.fn_name("p_"), &["cold", "noinline"])));
+        }
+        assert!(helpers.contains(" p_rv_cold_exit("));
+        assert!(helpers.contains("state->exit_cause = RV_EXIT_CODE_WRITE;"));
+        assert!(helpers.contains("state->csrs[CSR_MTVAL] = state->exit_info;"));
+        assert!(helpers.contains(sig.save_to_state.trim_start()));
+        assert!(!outlines_cold_paths(config.flags, CDialect::Portable));
+        let inline = EmitConfig::<Rv64>::default();
+        assert!(!outlines_cold_paths(inline.flags, inline.c_dialect));
+    }
+}
@@ -17,6 +17,7 @@ pub const DEFAULT_CLANG_COMMAND: &str = "clang-22";
 /// Clang vs GCC is auto-detected from the command name to determine flags:
 /// - Clang: C23, thin LTO, `preserve_none`, musttail
 /// - GCC: C2x, standard LTO
+/// - Either, with [`CDialect::Portable`]: C11, standard LTO
 ///
 /// For clang, the linker (lld) version is auto-derived from the compiler
 /// command (e.g., "clang-20" → "lld-20"). Use `with_linker()` to override.
@@ -102,6 +103,22 @@ impl Compiler {
         format!("llvm-addr2line{}", self.version_suffix())
     }
 
+    /// Get llvm-dwarfdump command, derived like [`addr2line`](Self::addr2line).
+    #[must_use]
+    pub fn dwarfdump(&self) -> String {
+        format!("llvm-dwarfdump{}", self.version_suffix())
+    }
+
+    /// Statement telling the optimizer that `cond` holds, if the compiler
+    /// has one that emits no code. GCC gets none: branching to
+    /// `__builtin_unreachable` would turn an address the guard pages catch
+    /// into a jump to wherever the compiler left off.
+    #[must_use]
+    pub fn assume(&self, cond: &str) -> Option<String> {
+        self.is_clang()
+            .then(|| format!("__builtin_assume({cond});"))
+    }
+
     /// Extract version suffix from compiler command.
     ///
     /// - "clang" → ""
@@ -144,6 +161,109 @@ impl std::fmt::Display for Compiler {
     }
 }
 
+/// Compiler caches tried, in order, by [`CompilerLauncher::Auto`].
+pub const AUTO_COMPILER_LAUNCHERS: [&str; 2] = ["ccache", "sccache"];
+
+/// Command prefixed to the generated Makefile's compile rules, such as a
+/// compiler cache. Part files are content-addressed, so unchanged parts
+/// hit the cache across output directories.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum CompilerLauncher {
+    /// The first of [`AUTO_COMPILER_LAUNCHERS`] on `PATH`, if any.
+    #[default]
+    Auto,
+    /// Invoke the compiler directly.
+    None,
+    /// Prefix compile rules with this command.
+    Command(String),
+}
+
+impl CompilerLauncher {
+    /// Command to prefix compile rules with, if any. `Auto` searches `PATH`.
+    #[must_use]
+    pub fn resolve(&self) -> Option<String> {
+        match self {
+            Self::Auto => AUTO_COMPILER_LAUNCHERS
+                .into_iter()
+                .find(|command| on_path(command))
+                .map(str::to_string),
+            Self::None => None,
+            Self::Command(command) => Some(command.clone()),
+        }
+    }
+}
+
+impl FromStr for CompilerLauncher {
+    type Err = String;
+
+    /// `auto`, `none`, or a command.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "" => Err("compiler launcher cannot be empty".to_string()),
+            "auto" => Ok(Self::Auto),
+            "none" => Ok(Self::None),
+            command => Ok(Self::Command(command.to_string())),
+        }
+    }
+}
+
+/// Whether an executable named `command` is in a `PATH` directory.
+pub(super) fn on_path(command: &str) -> bool {
+    std::env::var_os("PATH")
+        .is_some_and(|path| std::env::split_paths(&path).any(|dir| dir.join(command).is_file()))
+}
+
+/// Minimum GCC major version with `[[gnu::musttail]]`; older GCC
+/// gets [`CDialect::Portable`] code.
+pub const GCC_MUSTTAIL_VERSION: u32 = 15;
+
+/// C dialect of the generated code.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CDialect {
+    /// C23 for clang: blocks chain with `[[clang::musttail]]` calls under
+    /// `preserve_none`, hot registers stay in argument registers, and
+    /// constants are `constexpr`.
+    #[default]
+    Clang,
+    /// Plain C11 any C compiler builds. Blocks return the next block to a
+    /// trampoline loop in `rv_execute_from`, hot registers are locals
+    /// spilled to `RvState` at block boundaries, and constants are `enum`s
+    /// or `static const`. Slower, but needs no clang extensions.
+    Portable,
+}
+
+impl CDialect {
+    /// Whether this is [`CDialect::Portable`].
+    #[must_use]
+    pub const fn is_portable(self) -> bool {
+        matches!(self, Self::Portable)
+    }
+
+    /// Declare a typed constant.
+    ///
+    /// Portable C has no `constexpr`: a constant needed in constant
+    /// expressions (array sizes, case labels) becomes an `enum`, so its
+    /// value must fit an `int`; others become `static const`.
+    #[must_use]
+    pub fn constant(self, ty: &str, name: &str, value: &str, in_const_exprs: bool) -> String {
+        match self {
+            Self::Clang => format!("constexpr {ty} {name} = {value};"),
+            Self::Portable if in_const_exprs => format!("enum {{ {name} = {value} }};"),
+            Self::Portable => format!("static const {ty} {name} = {value};"),
+        }
+    }
+
+    /// Assert `cond` at compile time. C11 `_Static_assert` needs a message,
+    /// so it repeats the condition.
+    #[must_use]
+    pub fn static_assert(self, cond: &str) -> String {
+        match self {
+            Self::Clang => format!("static_assert({cond});"),
+            Self::Portable => format!("_Static_assert({cond}, \"{cond}\");"),
+        }
+    }
+}
+
 /// `x86_64` `preserve_none`: 12 argument registers available.
 ///
 /// R12, R13, R14, R15, RDI, RSI, RDX, RCX, R8, R9, R11, RAX.
@@ -248,6 +368,7 @@ mod tests {
         // Versioned clang-20 -> llvm-addr2line-20
         let c = Compiler::new("clang-20");
         assert_eq!(c.addr2line(), "llvm-addr2line-20");
+        assert_eq!(c.dwarfdump(), "llvm-dwarfdump-20");
 
         // Path with version
         let c = Compiler::new("/opt/llvm/bin/clang-18");
@@ -261,6 +382,23 @@ mod tests {
         assert_eq!(c.addr2line(), "llvm-addr2line");
     }
 
+    #[test]
+    fn test_compiler_specific_constructs() {
+        assert_eq!(
+            Compiler::new("clang-20").assume("x < 4").as_deref(),
+            Some("__builtin_assume(x < 4);")
+        );
+        assert_eq!(Compiler::gcc().assume("x < 4"), None);
+        assert_eq!(
+            CDialect::Clang.static_assert("x == 1"),
+            "static_assert(x == 1);"
+        );
+        assert_eq!(
+            CDialect::Portable.static_assert("x == 1"),
+            "_Static_assert(x == 1, \"x == 1\");"
+        );
+    }
+
     #[test]
     fn test_compute_num_hot_regs() {
         let tracer = TracerConfig::none();
@@ -291,4 +429,16 @@ mod tests {
             10
         );
     }
+
+    #[test]
+    fn test_compiler_launcher_from_str() {
+        assert_eq!("auto".parse(), Ok(CompilerLauncher::Auto));
+        assert_eq!("none".parse(), Ok(CompilerLauncher::None));
+        assert_eq!(
+            "sccache".parse(),
+            Ok(CompilerLauncher::Command("sccache".to_string()))
+        );
+        assert!("".parse::<CompilerLauncher>().is_err());
+        assert_eq!(CompilerLauncher::None.resolve(), None);
+    }
 }
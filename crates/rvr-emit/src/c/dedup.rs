@@ -0,0 +1,210 @@
+//! Identical-block deduplication.
+//!
+//! Monomorphized code has many byte-identical functions at different PCs.
+//! Blocks whose emitted C bodies match (ignoring comments and `#line`
+//! directives) are emitted once; the other blocks of the group become
+//! `alias` symbols of that body, so dispatch tables and direct tail calls
+//! keep referring to `B_<pc>` unchanged.
+//!
+//! The emitted body contains every PC-dependent constant (exit and suspend
+//! PCs, static successors, `auipc` results), so only blocks whose behavior
+//! does not depend on their own PC compare equal: typically returns and
+//! indirect jumps, or blocks that jump to the same absolute successors.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use rvr_ir::Xlen;
+
+use super::namespace::block_name;
+use super::signature::FnSignature;
+
+/// Deduplication results.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DedupStats {
+    /// Groups of two or more identical blocks.
+    pub groups: usize,
+    /// Blocks emitted as aliases of a group's canonical body.
+    pub aliases: usize,
+    /// Emitted C bytes saved (alias bodies minus their declarations).
+    pub bytes_saved: usize,
+}
+
+/// Canonical body and aliases for each rendered block.
+#[derive(Clone, Debug, Default)]
+pub struct BlockDedup {
+    /// Alias PC -> canonical PC.
+    pub alias_of: HashMap<u64, u64>,
+    /// Canonical PC -> alias PCs, in PC order.
+    pub aliases: HashMap<u64, Vec<u64>>,
+    /// Summary counts.
+    pub stats: DedupStats,
+    /// Prefix of block function names (see `EmitConfig::symbol_prefix`).
+    pub symbol_prefix: String,
+}
+
+impl BlockDedup {
+    /// Group `(pc, rendered block)` pairs by body.
+    ///
+    /// The first block of each group (in the given order) is canonical.
+    #[must_use]
+    pub fn new<X: Xlen>(blocks: &[(u64, &str)], sig: &FnSignature, symbol_prefix: &str) -> Self {
+        let mut canonical: HashMap<String, u64> = HashMap::new();
+        let mut dedup = Self {
+            symbol_prefix: symbol_prefix.to_string(),
+            ..Self::default()
+        };
+        for &(pc, text) in blocks {
+            let Some(body) = block_body(text) else {
+                continue;
+            };
+            match canonical.get(&body) {
+                Some(&canon) => {
+                    dedup.alias_of.insert(pc, canon);
+                    let aliases = dedup.aliases.entry(canon).or_default();
+                    if aliases.is_empty() {
+                        dedup.stats.groups += 1;
+                    }
+                    aliases.push(pc);
+                    dedup.stats.aliases += 1;
+                    let decl = alias_declaration::<X>(symbol_prefix, pc, canon, sig).len();
+                    dedup.stats.bytes_saved += text.len().saturating_sub(decl);
+                }
+                None => {
+                    canonical.insert(body, pc);
+                }
+            }
+        }
+        dedup
+    }
+
+    /// Alias declarations to emit after the canonical block at `pc`.
+    #[must_use]
+    pub fn alias_declarations<X: Xlen>(&self, pc: u64, sig: &FnSignature) -> String {
+        self.aliases
+            .get(&pc)
+            .into_iter()
+            .flatten()
+            .map(|&alias| alias_declaration::<X>(&self.symbol_prefix, alias, pc, sig))
+            .collect()
+    }
+}
+
+/// Declare the block at `alias` as another name for the block at `canon`.
+///
+/// `alias` must be in the translation unit that defines `canon`.
+fn alias_declaration<X: Xlen>(prefix: &str, alias: u64, canon: u64, sig: &FnSignature) -> String {
+    let target = format!("alias(\"{}\")", block_name::<X>(prefix, canon));
+    let mut decl = String::new();
+    writeln!(
+        decl,
+        "{};",
+        sig.fn_decl(&block_name::<X>(prefix, alias), &[&target])
+    )
+    .unwrap();
+    decl
+}
+
+/// The semantic part of a rendered block: the lines after the function
+/// header, without comments and `#line` directives.
+///
+/// Returns `None` if the text has no block function header.
+fn block_body(text: &str) -> Option<String> {
+    let mut lines = text.lines();
+    lines.find(|line| {
+        (line.contains(" void ") || line.contains(" RvNext ")) && line.contains("B_")
+    })?;
+    let mut body = String::with_capacity(text.len());
+    for line in lines {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("//") || trimmed.starts_with("#line ") {
+            continue;
+        }
+        body.push_str(line);
+        body.push('\n');
+    }
+    Some(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EmitConfig;
+    use rvr_ir::{Rv32, Rv64};
+
+    const PARAMS: &str = "RvState* restrict state";
+
+    fn sig() -> FnSignature {
+        let mut sig = FnSignature::new(&EmitConfig::<Rv64>::default());
+        sig.params = PARAMS.to_string();
+        sig
+    }
+
+    fn block(pc: u64, body: &str) -> String {
+        format!(
+            "// Block: {pc:#x}\n__attribute__((preserve_none, nonnull(1))) void B_{pc:016x}({PARAMS}) {{\n    // PC: {pc:#x} ADDI\n#line 7 \"f{pc}.rs\"\n{body}}}\n\n"
+        )
+    }
+
+    #[test]
+    fn test_block_body_ignores_comments() {
+        let a = block(0x1000, "    x = 1;\n");
+        let b = block(0x2000, "    x = 1;\n");
+        assert_eq!(block_body(&a), Some("    x = 1;\n}\n\n".to_string()));
+        assert_eq!(block_body(&a), block_body(&b));
+        assert_eq!(block_body("// no header\n"), None);
+    }
+
+    #[test]
+    fn test_dedup_groups() {
+        let ret = "    return dispatch_table[dispatch_index(ra)](state);\n";
+        let texts = [
+            (0x1000, block(0x1000, ret)),
+            (
+                0x1010,
+                block(0x1010, "    return B_0000000000001000(state);\n"),
+            ),
+            (0x1020, block(0x1020, ret)),
+            (0x1030, block(0x1030, ret)),
+            (
+                0x1040,
+                block(0x1040, "    return B_0000000000001000(state);\n"),
+            ),
+            (
+                0x1050,
+                block(0x1050, "    return B_0000000000001010(state);\n"),
+            ),
+        ];
+        let blocks: Vec<(u64, &str)> = texts.iter().map(|(pc, t)| (*pc, t.as_str())).collect();
+        let dedup = BlockDedup::new::<Rv64>(&blocks, &sig(), "");
+
+        assert_eq!(dedup.stats.groups, 2);
+        assert_eq!(dedup.stats.aliases, 3);
+        assert_eq!(dedup.aliases[&0x1000], [0x1020, 0x1030]);
+        assert_eq!(dedup.alias_of[&0x1040], 0x1010);
+        assert!(!dedup.alias_of.contains_key(&0x1050));
+
+        let saved: usize = [0x1020, 0x1030, 0x1040]
+            .iter()
+            .map(|pc| {
+                let text = &texts.iter().find(|(p, _)| p == pc).unwrap().1;
+                text.len() - alias_declaration::<Rv64>("", *pc, 0, &sig()).len()
+            })
+            .sum();
+        assert_eq!(dedup.stats.bytes_saved, saved);
+    }
+
+    #[test]
+    fn test_alias_declarations() {
+        let ret = "    return;\n";
+        let texts = [block(0x10, ret), block(0x20, ret)];
+        let blocks = [(0x10, texts[0].as_str()), (0x20, texts[1].as_str())];
+        let dedup = BlockDedup::new::<Rv32>(&blocks, &sig(), "");
+
+        assert_eq!(
+            dedup.alias_declarations::<Rv32>(0x10, &sig()),
+            "__attribute__((preserve_none, alias(\"B_00000010\"))) void B_00000020(RvState* restrict state);\n"
+        );
+        assert_eq!(dedup.alias_declarations::<Rv32>(0x20, &sig()), "");
+    }
+}
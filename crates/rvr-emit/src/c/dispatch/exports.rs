@@ -0,0 +1,274 @@
+//! Metadata constants and C API helpers exported by dispatch.c.
+
+use std::fmt::Write;
+
+use rvr_ir::Xlen;
+
+use super::DispatchConfig;
+use crate::c::tracer::{TracerKind, block_profile_slots, page_bitmap_words};
+use crate::layout::STATE_LAYOUT_VERSION;
+use crate::memory_layout::{
+    GuardPolicy, LAYOUT_REGION_WORDS, LayoutProfile, MEMORY_LAYOUT_WORDS, MemoryLayout,
+};
+
+/// Metadata constants `dispatch.c` may define, by unprefixed name. Only
+/// the first five are always present.
+pub const METADATA_SYMBOLS: &[&str] = &[
+    "RV_TRACER_KIND",
+    "RV_EXPORT_FUNCTIONS",
+    "RV_INSTRET_MODE",
+    "RV_NUM_REGS",
+    "RV_STATE_LAYOUT_VERSION",
+    "RV_CALL_RETURN_PC",
+    "RV_FIXED_STATE_ADDR",
+    "RV_FIXED_MEMORY_ADDR",
+    "RV_LOAD_BIAS",
+    "RV_LAZY_SEGMENTS",
+    "RV_CHECK_CANCEL",
+    "RV_TRACER_PAGE_SHIFT",
+    "RV_TRACER_PAGE_WORDS",
+    "RV_TRACER_PROFILE_BASE",
+    "RV_TRACER_PROFILE_SLOTS",
+    "RV_TRACER_SAMPLE_INTERVAL",
+    "RV_LAYOUT_PROFILE",
+    "RV_LAYOUT_REGIONS",
+    "RV_LAYOUT_GUARD",
+    "RV_MEMORY_LAYOUT",
+    "RV_QUARANTINE_COUNT",
+    "RV_QUARANTINE_PCS",
+    "RV_QUARANTINE_REASONS",
+    "RV_PREOPEN_COUNT",
+    "RV_PREOPEN_PATHS",
+];
+
+pub(super) fn gen_api_helpers<X: Xlen>(cfg: &DispatchConfig<X>) -> String {
+    let tracer_kind_val = cfg.tracer_kind_value();
+
+    let export_functions_val: u32 = u32::from(cfg.export_functions);
+    let instret_mode_val: u32 = cfg.instret_mode.as_c_mode();
+    let num_regs = cfg.num_regs;
+    let sym = |name| cfg.symbol(name);
+
+    let fixed_addr_exports = cfg.fixed_addresses.map_or_else(String::new, |fixed| {
+        format!(
+            "const uint64_t {} = {:#x}ull;\nconst uint64_t {} = {:#x}ull;\n",
+            sym("RV_FIXED_STATE_ADDR"),
+            fixed.state_addr,
+            sym("RV_FIXED_MEMORY_ADDR"),
+            fixed.memory_addr
+        )
+    });
+
+    // The runner loads a position-independent ELF at the same bias
+    let load_bias_export = cfg.load_bias.map_or_else(String::new, |bias| {
+        format!("const uint64_t {} = {bias:#x}ull;\n", sym("RV_LOAD_BIAS"))
+    });
+
+    // The runner maps guest memory from `<name>.segments` instead of the ELF
+    let lazy_segments_export = if cfg.lazy_segment_init {
+        format!("const uint32_t {} = 1;\n", sym("RV_LAZY_SEGMENTS"))
+    } else {
+        String::new()
+    };
+
+    // The runner can only interrupt guests that poll `cancel_requested`
+    let check_cancel_export = if cfg.flags.check_cancel() {
+        format!("const uint32_t {} = 1;\n", sym("RV_CHECK_CANCEL"))
+    } else {
+        String::new()
+    };
+
+    // The runner sizes the buffers it hands to the tracer from these
+    let tracer_exports = match cfg.tracer_kind {
+        Some(TracerKind::PageAccess) => format!(
+            "const uint32_t {} = {};\nconst uint64_t {} = {};\n",
+            sym("RV_TRACER_PAGE_SHIFT"),
+            cfg.tracer_page_shift,
+            sym("RV_TRACER_PAGE_WORDS"),
+            page_bitmap_words(cfg.tracer_page_shift, cfg.memory_bits)
+        ),
+        Some(TracerKind::BlockProfile | TracerKind::Coverage) => format!(
+            "const uint64_t {} = {:#x}ull;\nconst uint64_t {} = {};\n",
+            sym("RV_TRACER_PROFILE_BASE"),
+            cfg.inputs.text_start,
+            sym("RV_TRACER_PROFILE_SLOTS"),
+            block_profile_slots(&(cfg.inputs.text_start..cfg.inputs.pc_end))
+        ),
+        _ => String::new(),
+    };
+    // Diff flows refuse sampled libraries
+    let sample_export = cfg
+        .tracer_sample_interval
+        .map_or_else(String::new, |interval| {
+            format!(
+                "const uint32_t {} = {interval};\n",
+                sym("RV_TRACER_SAMPLE_INTERVAL")
+            )
+        });
+
+    let quarantine_exports = gen_quarantine_exports(cfg);
+    let preopen_exports = cfg
+        .preopens
+        .as_deref()
+        .map_or_else(String::new, |preopens| gen_preopen_exports(cfg, preopens));
+    let layout_exports = cfg
+        .layout
+        .as_ref()
+        .map_or_else(String::new, |layout| gen_layout_exports(cfg, layout));
+    let memory_layout_exports = cfg
+        .inputs
+        .memory_layout
+        .as_ref()
+        .map_or_else(String::new, |layout| gen_memory_layout_exports(cfg, layout));
+
+    format!(
+        r"/* Minimal C API - state management happens in Rust */
+
+/* Exported metadata constants (read via dlsym) */
+{abi_info}const uint32_t {tracer_kind} = {tracer_kind_val};
+const uint32_t {export_functions} = {export_functions_val};
+const uint32_t {instret_mode} = {instret_mode_val};
+const uint32_t {num_regs_sym} = {num_regs};
+const uint32_t {layout_version} = {STATE_LAYOUT_VERSION};
+{tracer_exports}{sample_export}{fixed_addr_exports}{load_bias_export}{lazy_segments_export}{check_cancel_export}{quarantine_exports}{preopen_exports}{layout_exports}{memory_layout_exports}",
+        abi_info = cfg.abi.c_definition(&cfg.symbol_prefix),
+        tracer_kind = sym("RV_TRACER_KIND"),
+        export_functions = sym("RV_EXPORT_FUNCTIONS"),
+        instret_mode = sym("RV_INSTRET_MODE"),
+        num_regs_sym = sym("RV_NUM_REGS"),
+        layout_version = sym("RV_STATE_LAYOUT_VERSION"),
+    )
+}
+
+/// Export the layout profile, so the runner can check the ELF it loads.
+///
+/// `RV_LAYOUT_REGIONS` holds code, data, stack and heap as start/end pairs;
+/// `RV_LAYOUT_GUARD` (the stack guard gap) is only present for guarded layouts.
+fn gen_layout_exports<X: Xlen>(cfg: &DispatchConfig<X>, layout: &LayoutProfile) -> String {
+    let regions = layout.spec().regions;
+    let words: Vec<String> = regions
+        .to_words()
+        .iter()
+        .map(|word| format!("{word:#x}ull"))
+        .collect();
+    let mut s = format!(
+        "/* Layout profile (checked against the ELF at load) */\n\
+         const char {}[] = \"{}\";\n\
+         const uint64_t {}[{LAYOUT_REGION_WORDS}] = {{ {} }};\n",
+        cfg.symbol("RV_LAYOUT_PROFILE"),
+        layout.name(),
+        cfg.symbol("RV_LAYOUT_REGIONS"),
+        words.join(", ")
+    );
+    if let GuardPolicy::Gap(gap) = regions.guard {
+        writeln!(
+            s,
+            "const uint64_t {} = {gap:#x}ull;",
+            cfg.symbol("RV_LAYOUT_GUARD")
+        )
+        .unwrap();
+    }
+    s
+}
+
+/// Export the planned heap, stack, mmap arena and stack guard, so the
+/// runner places the stack pointer, diagnoses overflows and reports the
+/// layout.
+///
+/// `RV_MEMORY_LAYOUT` holds heap, stack, arena and guard as start/end pairs.
+fn gen_memory_layout_exports<X: Xlen>(cfg: &DispatchConfig<X>, layout: &MemoryLayout) -> String {
+    let words: Vec<String> = layout
+        .to_words()
+        .iter()
+        .map(|word| format!("{word:#x}ull"))
+        .collect();
+    format!(
+        "/* Heap, stack, mmap arena and stack guard placement */\n\
+         const uint64_t {}[{MEMORY_LAYOUT_WORDS}] = {{ {} }};\n",
+        cfg.symbol("RV_MEMORY_LAYOUT"),
+        words.join(", ")
+    )
+}
+
+/// Export the quarantined stub PCs and their lift errors.
+///
+/// The runner maps an exit at one of these PCs to a quarantined-block fault.
+fn gen_quarantine_exports<X: Xlen>(cfg: &DispatchConfig<X>) -> String {
+    let inputs = &cfg.inputs;
+    if inputs.quarantined.is_empty() {
+        return String::new();
+    }
+
+    let mut s = String::from("/* Quarantined blocks: lift errors replaced by trap stubs */\n");
+    writeln!(
+        s,
+        "const uint32_t {} = {};",
+        cfg.symbol("RV_QUARANTINE_COUNT"),
+        inputs.quarantined.len()
+    )
+    .unwrap();
+    writeln!(
+        s,
+        "const uint64_t {}[] = {{",
+        cfg.symbol("RV_QUARANTINE_PCS")
+    )
+    .unwrap();
+    for pc in inputs.quarantined.keys() {
+        writeln!(s, "    {pc:#x}ull,").unwrap();
+    }
+    writeln!(
+        s,
+        "}};\nconst char *const {}[] = {{",
+        cfg.symbol("RV_QUARANTINE_REASONS")
+    )
+    .unwrap();
+    for reason in inputs.quarantined.values() {
+        writeln!(s, "    \"{}\",", c_string_escape(reason)).unwrap();
+    }
+    s.push_str("};\n");
+    s
+}
+
+/// Export the guest paths the syscall policy allows the host to preopen.
+///
+/// The runner refuses to bind host directories to any other guest path.
+fn gen_preopen_exports<X: Xlen>(cfg: &DispatchConfig<X>, preopens: &[String]) -> String {
+    let mut s = String::from("/* Syscall policy: guest directories the host may preopen */\n");
+    writeln!(
+        s,
+        "const uint32_t {} = {};",
+        cfg.symbol("RV_PREOPEN_COUNT"),
+        preopens.len()
+    )
+    .unwrap();
+    // C has no empty arrays; the runner reads no entries when the count is 0
+    writeln!(
+        s,
+        "const char *const {}[] = {{",
+        cfg.symbol("RV_PREOPEN_PATHS")
+    )
+    .unwrap();
+    for path in preopens {
+        writeln!(s, "    \"{}\",", c_string_escape(path)).unwrap();
+    }
+    if preopens.is_empty() {
+        s.push_str("    \"\",\n");
+    }
+    s.push_str("};\n");
+    s
+}
+
+fn c_string_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' | '\\' => {
+                out.push('\\');
+                out.push(c);
+            }
+            c if c.is_ascii_graphic() || c == ' ' => out.push(c),
+            _ => out.push('?'),
+        }
+    }
+    out
+}
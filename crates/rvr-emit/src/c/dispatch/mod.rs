@@ -0,0 +1,305 @@
+//! Dispatch table generation for recompiled C code.
+//!
+//! Generates dispatch.c containing:
+//! - Trap handler for invalid addresses
+//! - Return trampoline for `rv_call_*` wrappers (export-functions mode)
+//! - Dispatch table mapping PC -> block function (flat or per-function)
+//! - Runtime execution function
+
+mod exports;
+mod table;
+
+use std::fmt::Write;
+
+use rvr_ir::Xlen;
+use rvr_trace_format::kind;
+
+use super::cold::{gen_cold_paths, outlines_cold_paths};
+use super::namespace::global_symbol;
+use super::signature::{FnSignature, state_ref};
+use super::tracer::TracerKind;
+use crate::abi::{ABI_HEADER, RvAbiInfo};
+use crate::config::{
+    DispatchMode, EmitConfig, EmitFlags, FixedAddressConfig, InstretMode, SyscallMode,
+};
+use crate::inputs::EmitInputs;
+use crate::memory_layout::LayoutProfile;
+
+use exports::gen_api_helpers;
+use table::{gen_flat_table, gen_function_tables};
+
+pub use exports::METADATA_SYMBOLS;
+pub use table::flat_lookup;
+
+/// Instruction slot size (2 bytes for compressed instruction support).
+pub const INSTRUCTION_SIZE: u64 = 2;
+
+/// Dispatch generation configuration.
+pub struct DispatchConfig<X: Xlen> {
+    /// Base name for output files.
+    pub base_name: String,
+    /// Derived inputs (`entry_point`, `pc_end`, `valid_addresses`, `initial_brk`).
+    pub inputs: EmitInputs,
+    /// Instret counting mode.
+    pub instret_mode: InstretMode,
+    /// Number of guest registers (16 for RVE).
+    pub num_regs: usize,
+    /// Dispatch table layout.
+    pub dispatch_mode: DispatchMode,
+    /// Linked name of the symbol the flat table's entries are offsets from,
+    /// if it holds offsets instead of function pointers.
+    pub table_anchor: Option<String>,
+    /// Function signature.
+    pub sig: FnSignature,
+    /// Memory address bits.
+    pub memory_bits: u8,
+    /// Whether tracing is enabled.
+    pub has_tracing: bool,
+    /// Built-in tracer kind when available.
+    pub tracer_kind: Option<TracerKind>,
+    /// Log2 of the tracer page size (page-granular tracers).
+    pub tracer_page_shift: u32,
+    /// Tracer sample interval, if the tracer is sampled.
+    pub tracer_sample_interval: Option<u32>,
+    /// Export functions mode: compiled for calling exported functions.
+    pub export_functions: bool,
+    /// Fixed addresses configuration (if enabled).
+    pub fixed_addresses: Option<FixedAddressConfig>,
+    /// Layout profile the guest was checked against (if any).
+    pub layout: Option<LayoutProfile>,
+    /// Load bias for position-independent ELFs (if set).
+    pub load_bias: Option<u64>,
+    /// Guest memory is mapped from the segment image (`<name>.segments`).
+    pub lazy_segment_init: bool,
+    /// Emit flags (the exports record `check_cancel`).
+    pub flags: EmitFlags,
+    /// Prefix for global symbols (see `EmitConfig::symbol_prefix`).
+    pub symbol_prefix: String,
+    /// Guest paths the syscall policy lets the host preopen (if restricted).
+    pub preopens: Option<Vec<String>>,
+    /// Exported ABI metadata (`rv_abi_info`).
+    pub abi: RvAbiInfo,
+    _marker: std::marker::PhantomData<X>,
+}
+
+impl<X: Xlen> DispatchConfig<X> {
+    /// Create dispatch config from emit config.
+    pub fn new(config: &EmitConfig<X>, base_name: impl Into<String>, inputs: EmitInputs) -> Self {
+        Self {
+            base_name: base_name.into(),
+            inputs,
+            instret_mode: config.instret_mode,
+            num_regs: config.num_regs,
+            dispatch_mode: config.dispatch_mode,
+            table_anchor: config
+                .dispatch_table_relative()
+                .then(|| global_symbol(&config.symbol_prefix, "dispatch_table")),
+            sig: FnSignature::new(config),
+            memory_bits: config.memory_bits,
+            has_tracing: !config.tracer_config.is_none(),
+            tracer_kind: config.tracer_config.builtin_kind(),
+            tracer_page_shift: config.tracer_config.page_shift(),
+            tracer_sample_interval: config
+                .tracer_config
+                .is_sampled()
+                .then_some(config.tracer_config.sample_interval),
+            export_functions: config.export_functions,
+            fixed_addresses: config.fixed_addresses,
+            layout: config.layout,
+            load_bias: config.load_bias,
+            lazy_segment_init: config.lazy_segment_init(),
+            flags: config.flags,
+            symbol_prefix: config.symbol_prefix.clone(),
+            preopens: config
+                .syscall_policy
+                .as_ref()
+                .filter(|_| config.syscall_mode == SyscallMode::Linux)
+                .map(|policy| policy.preopens().to_vec()),
+            abi: RvAbiInfo::new(config),
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    /// `RV_TRACER_KIND`: the built-in tracer's kind, 255 for a custom
+    /// tracer, 0 without one.
+    #[must_use]
+    pub fn tracer_kind_value(&self) -> u32 {
+        self.tracer_kind.map_or(
+            if self.has_tracing {
+                kind::CUSTOM
+            } else {
+                kind::NONE
+            },
+            TracerKind::as_c_kind,
+        )
+    }
+
+    /// Linked name of the fixed-name global symbol `name`.
+    fn symbol(&self, name: &str) -> String {
+        global_symbol(&self.symbol_prefix, name)
+    }
+}
+
+/// Generate the dispatch.c file.
+#[must_use]
+pub fn gen_dispatch_file<X: Xlen>(cfg: &DispatchConfig<X>) -> String {
+    let mut s = String::new();
+
+    // Include blocks header, and the ABI header for `rv_abi_info`
+    writeln!(
+        s,
+        "#include \"{}_blocks.h\"\n#include \"{ABI_HEADER}\"\n",
+        cfg.base_name
+    )
+    .unwrap();
+
+    // Trap handler
+    s.push_str(&gen_trap_handler(cfg));
+    s.push('\n');
+
+    // Outlined trap, exit and suspension paths
+    if outlines_cold_paths(cfg.flags, cfg.sig.dialect) {
+        s.push_str(&gen_cold_paths(&cfg.sig, &cfg.symbol_prefix));
+    }
+
+    // Return trampoline for exported function calls
+    if cfg.export_functions {
+        s.push_str(&gen_call_return_handler(cfg));
+        s.push('\n');
+    }
+
+    // C API helper functions
+    s.push_str(&gen_api_helpers(cfg));
+    s.push('\n');
+
+    // Dispatch table(s)
+    match cfg.dispatch_mode {
+        DispatchMode::Flat => s.push_str(&gen_flat_table::<X>(cfg)),
+        DispatchMode::PerFunction => s.push_str(&gen_function_tables::<X>(cfg)),
+    }
+
+    // Runtime functions
+    s.push_str(&gen_runtime_functions(cfg));
+
+    s
+}
+
+/// Return address for exported function calls: the first slot past the code.
+const fn call_return_pc(inputs: &EmitInputs) -> u64 {
+    let slots = inputs
+        .pc_end
+        .saturating_sub(inputs.text_start)
+        .div_ceil(INSTRUCTION_SIZE);
+    inputs.text_start + slots * INSTRUCTION_SIZE
+}
+
+fn gen_trap_handler<X: Xlen>(cfg: &DispatchConfig<X>) -> String {
+    let state = state_ref(cfg.fixed_addresses.is_some());
+
+    format!(
+        r"/* Trap handler for invalid addresses - replaces NULL checks */
+{decl} {{
+    {state}->has_exited = RV_TRAPPED;
+    {state}->exit_code = 1;
+    {state}->exit_cause = RV_EXIT_INVALID_TARGET;
+    {state}->exit_info = 0;
+    {save_to_state}
+    {stop}
+}}
+",
+        decl = cfg.sig.fn_decl(&cfg.symbol("rv_trap"), &["cold"]),
+        state = state,
+        save_to_state = entry_save_to_state(&cfg.sig),
+        stop = cfg.sig.stop(),
+    )
+}
+
+fn gen_call_return_handler<X: Xlen>(cfg: &DispatchConfig<X>) -> String {
+    let state = state_ref(cfg.fixed_addresses.is_some());
+
+    format!(
+        r"/* Return trampoline for rv_call_* wrappers (exports.h): ra points here */
+const {rtype} {return_pc} = {pc:#x}ull;
+
+{decl} {{
+    {state}->pc = {pc:#x};
+    {save_to_state}
+    {stop}
+}}
+",
+        pc = call_return_pc(&cfg.inputs),
+        rtype = super::signature::reg_type::<X>(),
+        decl = cfg.sig.fn_decl(&cfg.symbol("rv_call_return"), &["cold"]),
+        return_pc = cfg.symbol("RV_CALL_RETURN_PC"),
+        save_to_state = entry_save_to_state(&cfg.sig),
+        stop = cfg.sig.stop(),
+    )
+}
+
+/// Code saving the arguments of a handler entered like a block. Portable
+/// blocks save everything before returning to the trampoline.
+fn entry_save_to_state(sig: &FnSignature) -> &str {
+    if sig.dialect.is_portable() {
+        ""
+    } else {
+        &sig.save_to_state
+    }
+}
+
+fn gen_runtime_functions<X: Xlen>(cfg: &DispatchConfig<X>) -> String {
+    let suspend_check = if cfg.instret_mode.suspends() {
+        "\n    if (state->target_instret <= state->instret) return 2;"
+    } else {
+        ""
+    };
+
+    let trace_init = if cfg.has_tracing {
+        "trace_init(&state->tracer);"
+    } else {
+        ""
+    };
+
+    let trace_fini = if cfg.has_tracing {
+        "trace_fini(&state->tracer);"
+    } else {
+        ""
+    };
+
+    let reg_type = super::signature::reg_type::<X>();
+    let dispatch = match cfg.dispatch_mode {
+        DispatchMode::Flat => flat_lookup(
+            cfg.table_anchor.is_some(),
+            &cfg.symbol("dispatch_table"),
+            "start_pc",
+        ),
+        DispatchMode::PerFunction => format!("{}(start_pc)", cfg.symbol("dispatch_lookup")),
+    };
+
+    // Portable blocks return the next block instead of tail calling it
+    let run = if cfg.sig.dialect.is_portable() {
+        format!(
+            "for (rv_fn next = {dispatch}; next; next = next({}).fn) {{}}",
+            cfg.sig.args_from_state
+        )
+    } else {
+        format!("{dispatch}({});", cfg.sig.args_from_state)
+    };
+
+    let execute_from = cfg.symbol("rv_execute_from");
+    format!(
+        r"/* Execute from given PC. Returns: 0=continue, 1=exited, 2=suspended */
+__attribute__((hot, nonnull))
+int {execute_from}(RvState* restrict state, {reg_type} start_pc) {{
+    {trace_init}
+    state->pc = start_pc;
+    {run}
+    {trace_fini}
+    if (state->has_exited) return 1;{suspend_check}
+    return 0;
+}}
+"
+    )
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,255 @@
+//! Dispatch tables mapping PC -> block function (flat or per-function).
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use rvr_ir::Xlen;
+
+use super::{DispatchConfig, INSTRUCTION_SIZE, call_return_pc};
+use crate::c::namespace::block_name;
+use crate::c::signature::reg_type;
+use crate::config::{DISPATCH_TABLE_ALIGN, DISPATCH_TABLE_HUGEPAGE_ALIGN};
+use crate::inputs::EmitInputs;
+
+#[must_use]
+pub fn flat_lookup(relative: bool, table: &str, pc: &str) -> String {
+    if relative {
+        format!("dispatch_target(dispatch_index({pc}))")
+    } else {
+        format!("{table}[dispatch_index({pc})]")
+    }
+}
+
+/// Flat table entries in slot order: the symbol each slot dispatches to.
+fn flat_entries<X: Xlen>(cfg: &DispatchConfig<X>) -> Vec<String> {
+    let inputs = &cfg.inputs;
+    let block = |pc| block_name::<X>(&cfg.symbol_prefix, pc);
+    let mut entries = Vec::new();
+    let mut addr = inputs.text_start;
+    while addr < inputs.pc_end {
+        if inputs.valid_addresses.contains(&addr) {
+            // Block start - point to its own function
+            entries.push(block(addr));
+        } else if let Some(&merged) = inputs.absorbed_to_merged.get(&addr) {
+            // Absorbed block - point to merged block's function
+            entries.push(block(merged));
+        } else {
+            entries.push(cfg.symbol("rv_trap"));
+        }
+        addr += INSTRUCTION_SIZE;
+    }
+    if cfg.export_functions {
+        // Return trampoline at RV_CALL_RETURN_PC
+        entries.push(cfg.symbol("rv_call_return"));
+    }
+    entries
+}
+
+pub(super) fn gen_flat_table<X: Xlen>(cfg: &DispatchConfig<X>) -> String {
+    if let Some(anchor) = &cfg.table_anchor {
+        return gen_relative_flat_table(cfg, anchor);
+    }
+    let mut s = String::from("/* Dispatch table: PC -> block function */\n");
+    // Relocated at load time, so not in a `.rodata` section
+    let placement = if cfg.flags.dispatch_table_hugepages() {
+        format!("aligned({DISPATCH_TABLE_HUGEPAGE_ALIGN}), section(\".data.rel.ro.rv_dispatch\")")
+    } else {
+        format!("aligned({DISPATCH_TABLE_ALIGN})")
+    };
+    writeln!(
+        s,
+        "const rv_fn {}[] __attribute__(({placement})) = {{",
+        cfg.symbol("dispatch_table")
+    )
+    .unwrap();
+    let call_return = cfg.symbol("rv_call_return");
+    for entry in flat_entries(cfg) {
+        if entry == call_return {
+            writeln!(s, "    {entry}, /* RV_CALL_RETURN_PC */").unwrap();
+        } else {
+            writeln!(s, "    {entry},").unwrap();
+        }
+    }
+    s.push_str("};\n\n");
+    s
+}
+
+/// Flat table of `int32_t` offsets from the table to each block.
+///
+/// C has no constant expression for the difference of two function
+/// addresses, so the table is assembled from a top-level `asm` block that
+/// uses the real (prefixed) symbol names. Its targets are made hidden: the
+/// linker then resolves each `.long target - table` statically instead of
+/// leaving a dynamic relocation per entry.
+fn gen_relative_flat_table<X: Xlen>(cfg: &DispatchConfig<X>, table: &str) -> String {
+    let entries = flat_entries(cfg);
+    let targets: BTreeSet<&String> = entries.iter().collect();
+
+    let mut s = String::from("/* Dispatch table: PC -> block offset from the table */\n");
+    s.push_str("__asm__(\n");
+    let (section, align) = if cfg.flags.dispatch_table_hugepages() {
+        (
+            ".rodata.rv_dispatch, \\\"a\\\"",
+            DISPATCH_TABLE_HUGEPAGE_ALIGN,
+        )
+    } else {
+        (".rodata", DISPATCH_TABLE_ALIGN)
+    };
+    writeln!(s, "    \".pushsection {section}\\n\"").unwrap();
+    writeln!(s, "    \".balign {align}\\n\"").unwrap();
+    writeln!(s, "    \".globl {table}\\n\"").unwrap();
+    writeln!(s, "    \".hidden {table}\\n\"").unwrap();
+    writeln!(s, "    \".type {table}, @object\\n\"").unwrap();
+    for target in &targets {
+        writeln!(s, "    \".hidden {target}\\n\"").unwrap();
+    }
+    writeln!(s, "    \"{table}:\\n\"").unwrap();
+    for entry in &entries {
+        writeln!(s, "    \".long {entry} - {table}\\n\"").unwrap();
+    }
+    writeln!(s, "    \".size {table}, . - {table}\\n\"").unwrap();
+    s.push_str("    \".popsection\\n\");\n\n");
+    s
+}
+
+/// Group dispatchable PCs into per-function tables of `(pc, block)` pairs.
+///
+/// Tables are contiguous runs of sorted PCs belonging to the same function, so
+/// their PC ranges never overlap. PCs with no known function stay in the
+/// preceding run.
+pub(super) fn function_tables(inputs: &EmitInputs) -> Vec<Vec<(u64, u64)>> {
+    let in_range = |pc: &u64| (inputs.text_start..inputs.pc_end).contains(pc);
+    let mut entries: Vec<(u64, u64)> = inputs
+        .valid_addresses
+        .iter()
+        .copied()
+        .filter(in_range)
+        .map(|pc| (pc, pc))
+        .chain(
+            inputs
+                .absorbed_to_merged
+                .iter()
+                .filter(|(pc, _)| in_range(pc) && !inputs.valid_addresses.contains(pc))
+                .map(|(&pc, &merged)| (pc, merged)),
+        )
+        .collect();
+    entries.sort_unstable();
+
+    let mut tables: Vec<Vec<(u64, u64)>> = Vec::new();
+    let mut current = None;
+    for (pc, block) in entries {
+        let function = inputs
+            .block_to_function
+            .get(&pc)
+            .or_else(|| inputs.block_to_function.get(&block))
+            .copied()
+            .or(current);
+        match tables.last_mut() {
+            Some(table) if function == current => table.push((pc, block)),
+            _ => tables.push(vec![(pc, block)]),
+        }
+        current = function;
+    }
+    tables
+}
+
+pub(super) fn gen_function_tables<X: Xlen>(cfg: &DispatchConfig<X>) -> String {
+    let inputs = &cfg.inputs;
+    let rtype = reg_type::<X>();
+    let width = if X::VALUE == 64 { 16 } else { 8 };
+    let tables = function_tables(inputs);
+
+    let mut s = String::from("/* Per-function dispatch: sorted block PCs -> block function */\n");
+    for table in &tables {
+        let first = table[0].0;
+        writeln!(
+            s,
+            "static const {rtype} dispatch_pcs_{first:0width$x}[] = {{"
+        )
+        .unwrap();
+        for (pc, _) in table {
+            writeln!(s, "    {pc:#x},").unwrap();
+        }
+        s.push_str("};\n");
+        writeln!(s, "static const rv_fn dispatch_fns_{first:0width$x}[] = {{").unwrap();
+        for (_, block) in table {
+            writeln!(s, "    {},", block_name::<X>(&cfg.symbol_prefix, *block)).unwrap();
+        }
+        s.push_str("};\n\n");
+    }
+
+    writeln!(
+        s,
+        r"typedef struct {{
+    {rtype} first_pc;
+    {rtype} last_pc;
+    uint32_t count;
+    const {rtype}* pcs;
+    const rv_fn* fns;
+}} rv_dispatch_function;
+"
+    )
+    .unwrap();
+
+    let trap = cfg.symbol("rv_trap");
+    let mut body = if tables.is_empty() {
+        format!("    (void)pc;\n    return {trap};\n")
+    } else {
+        s.push_str("/* Function table: sorted by first block PC */\n");
+        s.push_str("static const rv_dispatch_function dispatch_functions[] = {\n");
+        for table in &tables {
+            let first = table[0].0;
+            let last = table[table.len() - 1].0;
+            writeln!(
+                s,
+                "    {{ {first:#x}, {last:#x}, {}, dispatch_pcs_{first:0width$x}, dispatch_fns_{first:0width$x} }},",
+                table.len()
+            )
+            .unwrap();
+        }
+        s.push_str("};\n\n");
+        format!(
+            r"    uint32_t lo = 0;
+    uint32_t hi = {count};
+    while (lo < hi) {{
+        uint32_t mid = lo + (hi - lo) / 2;
+        if (dispatch_functions[mid].first_pc <= pc) lo = mid + 1; else hi = mid;
+    }}
+    if (lo == 0) return {trap};
+    const rv_dispatch_function* f = &dispatch_functions[lo - 1];
+    if (pc > f->last_pc) return {trap};
+    lo = 0;
+    hi = f->count;
+    while (lo < hi) {{
+        uint32_t mid = lo + (hi - lo) / 2;
+        if (f->pcs[mid] < pc) lo = mid + 1; else hi = mid;
+    }}
+    return f->pcs[lo] == pc ? f->fns[lo] : {trap};
+",
+            count = tables.len()
+        )
+    };
+
+    if cfg.export_functions {
+        body.insert_str(
+            0,
+            &format!(
+                "    if (pc == {:#x}) return {};\n",
+                call_return_pc(inputs),
+                cfg.symbol("rv_call_return")
+            ),
+        );
+    }
+
+    writeln!(
+        s,
+        r"/* Resolve a dynamic jump target; unknown PCs map to rv_trap */
+__attribute__((hot, pure))
+rv_fn {lookup}({rtype} pc) {{
+{body}}}
+",
+        lookup = cfg.symbol("dispatch_lookup")
+    )
+    .unwrap();
+    s
+}
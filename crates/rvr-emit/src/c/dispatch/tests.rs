@@ -0,0 +1,355 @@
+use super::table::function_tables;
+use super::*;
+use crate::NUM_REGS_E;
+use crate::c::tracer::{TracerConfig, page_bitmap_words};
+use crate::layout::STATE_LAYOUT_VERSION;
+use crate::memory_layout::{AddrRange, MemoryLayout};
+use rvr_ir::{Rv32, Rv64};
+
+#[test]
+fn test_gen_dispatch() {
+    let config = EmitConfig::<Rv64>::standard();
+    let mut inputs = EmitInputs::new(0x8000_0000, 0x8000_0010).with_initial_brk(0x8001_0000);
+    inputs.valid_addresses.insert(0x8000_0000_u64);
+    inputs.valid_addresses.insert(0x8000_0004_u64);
+
+    let dispatch_cfg = DispatchConfig::new(&config, "test", inputs);
+
+    let dispatch = gen_dispatch_file::<Rv64>(&dispatch_cfg);
+
+    assert!(dispatch.contains("dispatch_table"));
+    assert!(dispatch.contains("B_0000000080000000"));
+    assert!(dispatch.contains("B_0000000080000004"));
+    assert!(dispatch.contains("rv_trap"));
+    assert!(dispatch.contains("rv_execute_from"));
+    assert!(!dispatch.contains("RV_QUARANTINE_COUNT"));
+}
+
+#[test]
+fn test_quarantine_exports() {
+    let config = EmitConfig::<Rv64>::standard();
+    let mut inputs = EmitInputs::new(0x8000_0000, 0x8000_0010);
+    inputs.valid_addresses.insert(0x8000_0000_u64);
+    inputs.valid_addresses.insert(0x8000_0008_u64);
+    inputs
+        .quarantined
+        .insert(0x8000_0008, "undecodable \"bytes\"".to_string());
+
+    let dispatch_cfg = DispatchConfig::new(&config, "test", inputs);
+    let dispatch = gen_dispatch_file::<Rv64>(&dispatch_cfg);
+
+    assert!(dispatch.contains("const uint32_t RV_QUARANTINE_COUNT = 1;"));
+    assert!(dispatch.contains("    0x80000008ull,"));
+    assert!(dispatch.contains(r#"    "undecodable \"bytes\"","#));
+}
+
+#[test]
+fn test_page_access_exports_page_shift() {
+    let inputs = || {
+        let mut inputs = EmitInputs::new(0x8000_0000, 0x8000_0010);
+        inputs.valid_addresses.insert(0x8000_0000_u64);
+        inputs
+    };
+
+    let mut config = EmitConfig::<Rv64>::standard();
+    let dispatch = gen_dispatch_file::<Rv64>(&DispatchConfig::new(&config, "test", inputs()));
+    assert!(!dispatch.contains("RV_TRACER_PAGE_SHIFT"));
+
+    config.tracer_config = TracerConfig::page_access().with_page_size(1 << 16);
+    let dispatch = gen_dispatch_file::<Rv64>(&DispatchConfig::new(&config, "test", inputs()));
+    assert!(dispatch.contains("const uint32_t RV_TRACER_KIND = 9;"));
+    assert!(dispatch.contains("const uint32_t RV_TRACER_PAGE_SHIFT = 16;"));
+    let words = page_bitmap_words(16, config.memory_bits);
+    assert!(dispatch.contains(&format!("const uint64_t RV_TRACER_PAGE_WORDS = {words};")));
+}
+
+#[test]
+fn test_sampled_tracer_exports_interval() {
+    let inputs = || {
+        let mut inputs = EmitInputs::new(0x8000_0000, 0x8000_0010);
+        inputs.valid_addresses.insert(0x8000_0000_u64);
+        inputs
+    };
+
+    let mut config = EmitConfig::<Rv64>::standard();
+    config.tracer_config = TracerConfig::builtin(TracerKind::BufferedDiff);
+    let dispatch = gen_dispatch_file::<Rv64>(&DispatchConfig::new(&config, "test", inputs()));
+    assert!(!dispatch.contains("RV_TRACER_SAMPLE_INTERVAL"));
+
+    config.tracer_config =
+        TracerConfig::builtin(TracerKind::BufferedDiff).with_sample_interval(10_000);
+    let dispatch = gen_dispatch_file::<Rv64>(&DispatchConfig::new(&config, "test", inputs()));
+    assert!(dispatch.contains("const uint32_t RV_TRACER_SAMPLE_INTERVAL = 10000;"));
+}
+
+#[test]
+fn test_block_profile_exports_slots() {
+    let mut inputs = EmitInputs::new(0x8000_0000, 0x8000_0010);
+    inputs.valid_addresses.insert(0x8000_0000_u64);
+
+    let mut config = EmitConfig::<Rv64>::standard();
+    config.tracer_config = TracerConfig::block_profile();
+    let dispatch = gen_dispatch_file::<Rv64>(&DispatchConfig::new(&config, "test", inputs));
+    assert!(dispatch.contains("const uint32_t RV_TRACER_KIND = 10;"));
+    assert!(dispatch.contains("const uint64_t RV_TRACER_PROFILE_BASE = 0x80000000ull;"));
+    assert!(dispatch.contains("const uint64_t RV_TRACER_PROFILE_SLOTS = 8;"));
+    assert!(!dispatch.contains("RV_TRACER_PAGE_SHIFT"));
+}
+
+#[test]
+fn test_load_bias_export() {
+    let mut inputs = EmitInputs::new(0x1_0000, 0x1_0010);
+    inputs.valid_addresses.insert(0x1_0000_u64);
+
+    let config = EmitConfig::<Rv64>::standard();
+    let dispatch = gen_dispatch_file::<Rv64>(&DispatchConfig::new(&config, "test", inputs.clone()));
+    assert!(!dispatch.contains("RV_LOAD_BIAS"));
+
+    let config = config.with_load_bias(0x40_0000);
+    let dispatch = gen_dispatch_file::<Rv64>(&DispatchConfig::new(&config, "test", inputs));
+    assert!(dispatch.contains("const uint64_t RV_LOAD_BIAS = 0x400000ull;"));
+}
+
+#[test]
+fn test_num_regs_export() {
+    let mut inputs = EmitInputs::new(0x1_0000, 0x1_0010);
+    inputs.valid_addresses.insert(0x1_0000_u64);
+
+    let mut config = EmitConfig::<Rv32>::standard();
+    let dispatch = gen_dispatch_file::<Rv32>(&DispatchConfig::new(&config, "test", inputs.clone()));
+    assert!(dispatch.contains("const uint32_t RV_NUM_REGS = 32;"));
+    assert!(dispatch.contains(&format!(
+        "const uint32_t RV_STATE_LAYOUT_VERSION = {STATE_LAYOUT_VERSION};"
+    )));
+
+    config.set_num_regs(NUM_REGS_E);
+    let dispatch = gen_dispatch_file::<Rv32>(&DispatchConfig::new(&config, "test", inputs));
+    assert!(dispatch.contains("const uint32_t RV_NUM_REGS = 16;"));
+}
+
+#[test]
+fn test_lazy_segments_export() {
+    let mut inputs = EmitInputs::new(0x1_0000, 0x1_0010);
+    inputs.valid_addresses.insert(0x1_0000_u64);
+
+    let config = EmitConfig::<Rv64>::standard();
+    let dispatch = gen_dispatch_file::<Rv64>(&DispatchConfig::new(&config, "test", inputs.clone()));
+    assert!(!dispatch.contains("RV_LAZY_SEGMENTS"));
+    assert!(dispatch.contains("#include \"rv_abi.h\"\n"));
+    assert!(dispatch.contains("const RvAbiInfo rv_abi_info = {\n    .abi_version = 3,\n"));
+
+    let config = config.with_lazy_segment_init(true);
+    let dispatch = gen_dispatch_file::<Rv64>(&DispatchConfig::new(&config, "test", inputs));
+    assert!(dispatch.contains("const uint32_t RV_LAZY_SEGMENTS = 1;"));
+}
+
+#[test]
+fn test_check_cancel_export() {
+    let mut inputs = EmitInputs::new(0x1_0000, 0x1_0010);
+    inputs.valid_addresses.insert(0x1_0000_u64);
+
+    let config = EmitConfig::<Rv64>::standard();
+    let dispatch = gen_dispatch_file::<Rv64>(&DispatchConfig::new(&config, "test", inputs.clone()));
+    assert!(!dispatch.contains("RV_CHECK_CANCEL"));
+
+    let config = config.with_check_cancel(true);
+    let dispatch = gen_dispatch_file::<Rv64>(&DispatchConfig::new(&config, "test", inputs));
+    assert!(dispatch.contains("const uint32_t RV_CHECK_CANCEL = 1;"));
+}
+
+#[test]
+fn test_relative_table_uses_linked_names() {
+    let mut inputs = EmitInputs::new(0x1_0000, 0x1_0004);
+    inputs.valid_addresses.insert(0x1_0000_u64);
+
+    let mut config = EmitConfig::<Rv64>::standard().with_dispatch_table_relative(true);
+    config.symbol_prefix = "p0_".into();
+    let dispatch = gen_dispatch_file::<Rv64>(&DispatchConfig::new(&config, "test", inputs));
+    assert!(!dispatch.contains("const rv_fn dispatch_table[]"));
+    assert!(dispatch.contains("\"p0_dispatch_table:\\n\""));
+    assert!(dispatch.contains("\".hidden p0_B_0000000000010000\\n\""));
+    assert!(dispatch.contains("\".long p0_B_0000000000010000 - p0_dispatch_table\\n\""));
+    assert!(dispatch.contains("\".long p0_rv_trap - p0_dispatch_table\\n\""));
+    assert!(dispatch.contains("dispatch_target(dispatch_index(start_pc))"));
+}
+
+#[test]
+fn test_dispatch_table_placement() {
+    let mut inputs = EmitInputs::new(0x1_0000, 0x1_0004);
+    inputs.valid_addresses.insert(0x1_0000_u64);
+    let standard = EmitConfig::<Rv64>::standard();
+    // (relative, hugepages, expected)
+    let cases = [
+        (
+            false,
+            false,
+            "const rv_fn dispatch_table[] __attribute__((aligned(64))) = {",
+        ),
+        (
+            false,
+            true,
+            "__attribute__((aligned(2097152), section(\".data.rel.ro.rv_dispatch\")))",
+        ),
+        (
+            true,
+            false,
+            "\".pushsection .rodata\\n\"\n    \".balign 64\\n\"",
+        ),
+        (
+            true,
+            true,
+            "\".pushsection .rodata.rv_dispatch, \\\"a\\\"\\n\"\n    \".balign 2097152\\n\"",
+        ),
+    ];
+    for (relative, hugepages, expected) in cases {
+        let config = standard
+            .clone()
+            .with_dispatch_table_relative(relative)
+            .with_dispatch_table_hugepages(hugepages);
+        let dispatch =
+            gen_dispatch_file::<Rv64>(&DispatchConfig::new(&config, "test", inputs.clone()));
+        assert!(
+            dispatch.contains(expected),
+            "{relative} {hugepages}:\n{dispatch}"
+        );
+    }
+}
+
+#[test]
+fn test_absorbed_mapping() {
+    let config = EmitConfig::<Rv64>::standard();
+    let mut inputs = EmitInputs::new(0x8000_0000, 0x8000_0008).with_initial_brk(0x8001_0000);
+    inputs.valid_addresses.insert(0x8000_0000_u64);
+    inputs
+        .absorbed_to_merged
+        .insert(0x8000_0002_u64, 0x8000_0000_u64);
+
+    let dispatch_cfg = DispatchConfig::new(&config, "test", inputs);
+
+    let dispatch = gen_dispatch_file::<Rv64>(&dispatch_cfg);
+
+    // Address 0x80000002 should point to B_0000000080000000
+    assert!(dispatch.contains("B_0000000080000000,\n    B_0000000080000000,"));
+}
+
+#[test]
+fn test_function_tables_split_by_function() {
+    let mut inputs = EmitInputs::new(0x8000_0000, 0x8000_0020);
+    inputs
+        .valid_addresses
+        .extend([0x8000_0000_u64, 0x8000_0008, 0x8000_0010, 0x8000_0018]);
+    inputs
+        .absorbed_to_merged
+        .insert(0x8000_0004_u64, 0x8000_0000_u64);
+    inputs.block_to_function.extend([
+        (0x8000_0000_u64, 0x8000_0000_u64),
+        (0x8000_0008, 0x8000_0000),
+        (0x8000_0010, 0x8000_0010),
+    ]);
+
+    // 0x80000004 inherits its merged block's function, 0x80000018 the preceding one
+    let tables = function_tables(&inputs);
+    assert_eq!(
+        tables,
+        vec![
+            vec![
+                (0x8000_0000, 0x8000_0000),
+                (0x8000_0004, 0x8000_0000),
+                (0x8000_0008, 0x8000_0008),
+            ],
+            vec![(0x8000_0010, 0x8000_0010), (0x8000_0018, 0x8000_0018)],
+        ]
+    );
+}
+
+#[test]
+fn test_gen_dispatch_per_function() {
+    let config = EmitConfig::<Rv64>::standard().with_dispatch_mode(DispatchMode::PerFunction);
+    let mut inputs = EmitInputs::new(0x8000_0000, 0x8000_0010);
+    inputs
+        .valid_addresses
+        .extend([0x8000_0000_u64, 0x8000_0008]);
+    inputs.block_to_function.extend([
+        (0x8000_0000_u64, 0x8000_0000_u64),
+        (0x8000_0008, 0x8000_0008),
+    ]);
+
+    let dispatch = gen_dispatch_file::<Rv64>(&DispatchConfig::new(&config, "test", inputs));
+
+    assert!(!dispatch.contains("dispatch_table"));
+    assert!(dispatch.contains("static const uint64_t dispatch_pcs_0000000080000008[]"));
+    assert!(dispatch.contains("static const rv_fn dispatch_fns_0000000080000000[]"));
+    assert!(dispatch.contains("uint32_t hi = 2;"));
+    assert!(dispatch.contains("rv_fn dispatch_lookup(uint64_t pc)"));
+    assert!(dispatch.contains("dispatch_lookup(start_pc)("));
+}
+
+#[test]
+fn test_gen_dispatch_per_function_empty() {
+    let config = EmitConfig::<Rv64>::standard().with_dispatch_mode(DispatchMode::PerFunction);
+    let inputs = EmitInputs::new(0x8000_0000, 0x8000_0000);
+
+    let dispatch = gen_dispatch_file::<Rv64>(&DispatchConfig::new(&config, "test", inputs));
+
+    assert!(!dispatch.contains("dispatch_functions"));
+    assert!(dispatch.contains("return rv_trap;"));
+}
+
+#[test]
+fn test_call_return_trampoline() {
+    let mut config = EmitConfig::<Rv64>::standard();
+    config.export_functions = true;
+    let mut inputs = EmitInputs::new(0x8000_0000, 0x8000_0007);
+    inputs.valid_addresses.insert(0x8000_0000_u64);
+
+    let flat = gen_dispatch_file::<Rv64>(&DispatchConfig::new(&config, "test", inputs.clone()));
+    assert!(flat.contains("const uint64_t RV_CALL_RETURN_PC = 0x80000008ull;"));
+    assert!(flat.contains("void rv_call_return("));
+    assert!(flat.contains("    rv_trap,\n    rv_call_return, /* RV_CALL_RETURN_PC */\n};"));
+
+    let config = config.with_dispatch_mode(DispatchMode::PerFunction);
+    let per_function =
+        gen_dispatch_file::<Rv64>(&DispatchConfig::new(&config, "test", inputs.clone()));
+    assert!(per_function.contains("    if (pc == 0x80000008) return rv_call_return;\n"));
+
+    let mut config = EmitConfig::<Rv32>::standard();
+    config.export_functions = true;
+    let rv32 = gen_dispatch_file::<Rv32>(&DispatchConfig::new(&config, "test", inputs));
+    assert!(rv32.contains("const uint32_t RV_CALL_RETURN_PC = 0x80000008ull;"));
+}
+
+#[test]
+fn test_layout_exports() {
+    let inputs = EmitInputs::new(0x0020_1000, 0x0020_1004);
+    let mut config = EmitConfig::<Rv32>::standard();
+    let plain = gen_dispatch_file::<Rv32>(&DispatchConfig::new(&config, "test", inputs.clone()));
+    assert!(!plain.contains("RV_LAYOUT"));
+
+    config.layout = Some(LayoutProfile::Rv32ZkVm);
+    let dispatch = gen_dispatch_file::<Rv32>(&DispatchConfig::new(&config, "test", inputs));
+    assert!(dispatch.contains("const char RV_LAYOUT_PROFILE[] = \"rv32-zkvm\";"));
+    assert!(dispatch.contains(
+        "const uint64_t RV_LAYOUT_REGIONS[8] = { 0x201000ull, 0x10000000ull, 0x201000ull, 0x78000000ull, 0x1000ull, 0x200000ull, 0x201000ull, 0x78000000ull };"
+    ));
+    assert!(dispatch.contains("const uint64_t RV_LAYOUT_GUARD = 0x1000ull;"));
+}
+
+#[test]
+fn test_memory_layout_exports() {
+    let inputs = EmitInputs::new(0x1_0000, 0x1_0004);
+    let config = EmitConfig::<Rv64>::standard();
+    let plain = gen_dispatch_file::<Rv64>(&DispatchConfig::new(&config, "test", inputs.clone()));
+    assert!(!plain.contains("RV_MEMORY_LAYOUT"));
+
+    let inputs = inputs.with_memory_layout(Some(MemoryLayout {
+        segments: Vec::new(),
+        heap: AddrRange::new(0x1_1000, 0x2_1000),
+        stack: AddrRange::new(0xfff0_0000, 0x1_0000_0000),
+        mmap: None,
+        stack_guard: Some(AddrRange::new(0xffef_f000, 0xfff0_0000)),
+    }));
+    let dispatch = gen_dispatch_file::<Rv64>(&DispatchConfig::new(&config, "test", inputs));
+    assert!(dispatch.contains(
+        "const uint64_t RV_MEMORY_LAYOUT[8] = { 0x11000ull, 0x21000ull, 0xfff00000ull, 0x100000000ull, 0x0ull, 0x0ull, 0xffeff000ull, 0xfff00000ull };"
+    ));
+}
@@ -0,0 +1,179 @@
+//! Embedding API of static archives.
+//!
+//! A host that links `lib<name>.a` instead of `dlopen`-ing `lib<name>.so`
+//! has no `dlsym` to find the entry points and `RV_*` metadata. The
+//! program then also gets `rv_embed.h` and `<name>_embed.c`, which define
+//! `rv_embed_info`: a const struct with the build metadata, the entry
+//! points and a table of every `RV_*` constant the program defines, looked
+//! up by its unprefixed name. Constants the program does not define are
+//! weak references, left out of the table at run time by their null
+//! address.
+
+use std::fmt::Write;
+
+use rvr_ir::Xlen;
+
+use super::dispatch::{DispatchConfig, METADATA_SYMBOLS};
+use super::namespace::global_symbol;
+use super::signature::reg_type;
+use crate::abi::ABI_INFO_SYMBOL;
+use crate::layout::STATE_LAYOUT_VERSION;
+
+/// File name of the embedding header.
+pub const EMBED_HEADER: &str = "rv_embed.h";
+
+/// Entry points listed in the table next to the metadata.
+const ENTRY_POINTS: [&str; 2] = ["rv_execute_from", "rv_init_memory"];
+
+/// Render `rv_embed.h`, declaring `rv_embed_info` and the entry points
+/// under their linked (prefixed) names.
+///
+/// A host linking several programs includes one of their headers and
+/// declares the others' `rv_embed_info`.
+#[must_use]
+pub fn gen_embed_header<X: Xlen>(cfg: &DispatchConfig<X>) -> String {
+    let rtype = reg_type::<X>();
+    let name = |symbol| global_symbol(&cfg.symbol_prefix, symbol);
+    format!(
+        r"/* Generated by RVR: embedding API of lib{base}.a, for hosts that link the
+ * program instead of loading lib{base}.so. Include {base}.h for RvState. */
+#pragma once
+
+#include <stdint.h>
+
+typedef struct RvState RvState;
+
+/* A metadata constant or entry point, by its unprefixed name */
+typedef struct RvEmbedSymbol {{
+    const char* name;
+    const void* addr;
+}} RvEmbedSymbol;
+
+/* Build metadata of an embedded program */
+typedef struct RvEmbedInfo {{
+    uint32_t state_layout_version;
+    uint32_t xlen;
+    uint32_t num_regs;
+    uint32_t instret_mode;
+    uint32_t tracer_kind;
+    uint32_t export_functions;
+    /* Execute from a PC. Returns: 0=continue, 1=exited, 2=suspended */
+    int (*execute_from)(RvState* state, {rtype} pc);
+    /* Write embedded segment data into zeroed guest memory (NULL if none) */
+    void (*init_memory)(RvState* state);
+    /* RV_* constants and entry points; addr is NULL for those the program
+     * does not define */
+    const RvEmbedSymbol* symbols;
+    uint64_t symbol_count;
+}} RvEmbedInfo;
+
+extern const RvEmbedInfo {info};
+int {execute_from}(RvState* state, {rtype} pc);
+",
+        base = cfg.base_name,
+        info = name("rv_embed_info"),
+        execute_from = name("rv_execute_from"),
+    )
+}
+
+/// Render `<name>_embed.c`, defining `rv_embed_info`.
+#[must_use]
+pub fn gen_embed_source<X: Xlen>(cfg: &DispatchConfig<X>) -> String {
+    let rtype = reg_type::<X>();
+    let name = |symbol| global_symbol(&cfg.symbol_prefix, symbol);
+    let symbols: Vec<&str> = METADATA_SYMBOLS
+        .iter()
+        .copied()
+        .chain([ABI_INFO_SYMBOL])
+        .collect();
+
+    let mut s = format!(
+        "/* Generated by RVR: embedding table of lib{}.a (see {EMBED_HEADER}) */\n\n\
+         #include <stddef.h>\n\n\
+         #include \"{EMBED_HEADER}\"\n\n",
+        cfg.base_name
+    );
+    s.push_str("/* Defined elsewhere in the program, if at all */\n");
+    for symbol in &symbols {
+        writeln!(
+            s,
+            "extern const char {}[] __attribute__((weak));",
+            name(symbol)
+        )
+        .unwrap();
+    }
+    let execute_from = name("rv_execute_from");
+    let init_memory = name("rv_init_memory");
+    writeln!(s, "int {execute_from}(RvState* state, {rtype} pc);").unwrap();
+    writeln!(
+        s,
+        "void {init_memory}(RvState* state) __attribute__((weak));\n"
+    )
+    .unwrap();
+
+    s.push_str("static const RvEmbedSymbol rv_embed_symbols[] = {\n");
+    for symbol in symbols.iter().chain(&ENTRY_POINTS) {
+        writeln!(s, "    {{ \"{symbol}\", (const void*){} }},", name(symbol)).unwrap();
+    }
+    s.push_str("};\n\n");
+
+    write!(
+        s,
+        r"const RvEmbedInfo {info} = {{
+    .state_layout_version = {STATE_LAYOUT_VERSION},
+    .xlen = {xlen},
+    .num_regs = {num_regs},
+    .instret_mode = {instret_mode},
+    .tracer_kind = {tracer_kind},
+    .export_functions = {export_functions},
+    .execute_from = {execute_from},
+    .init_memory = {init_memory},
+    .symbols = rv_embed_symbols,
+    .symbol_count = sizeof(rv_embed_symbols) / sizeof(rv_embed_symbols[0]),
+}};
+",
+        info = name("rv_embed_info"),
+        xlen = X::VALUE,
+        num_regs = cfg.num_regs,
+        instret_mode = cfg.instret_mode.as_c_mode(),
+        tracer_kind = cfg.tracer_kind_value(),
+        export_functions = u32::from(cfg.export_functions),
+    )
+    .unwrap();
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use rvr_ir::{Rv32, Rv64};
+
+    use super::*;
+    use crate::config::EmitConfig;
+    use crate::inputs::EmitInputs;
+
+    fn dispatch_config<X: Xlen>(prefix: &str) -> DispatchConfig<X> {
+        let mut config = EmitConfig::<X>::standard();
+        config.symbol_prefix = prefix.to_string();
+        DispatchConfig::new(&config, "guest", EmitInputs::new(0x1000, 0x1010))
+    }
+
+    #[test]
+    fn test_embed_header_prefixed_names() {
+        let header = gen_embed_header(&dispatch_config::<Rv32>("g_"));
+        assert!(header.contains("extern const RvEmbedInfo g_rv_embed_info;\n"));
+        assert!(header.contains("int g_rv_execute_from(RvState* state, uint32_t pc);\n"));
+        assert!(header.contains("embedding API of libguest.a"));
+    }
+
+    #[test]
+    fn test_embed_source_table() {
+        let source = gen_embed_source(&dispatch_config::<Rv64>("g_"));
+        assert!(source.contains("extern const char g_RV_NUM_REGS[] __attribute__((weak));\n"));
+        assert!(source.contains("    { \"RV_NUM_REGS\", (const void*)g_RV_NUM_REGS },\n"));
+        assert!(source.contains("    { \"rv_init_memory\", (const void*)g_rv_init_memory },\n"));
+        assert!(source.contains("const RvEmbedInfo g_rv_embed_info = {\n"));
+        assert!(source.contains("    .xlen = 64,\n"));
+        assert!(source.contains("    { \"rv_abi_info\", (const void*)g_rv_abi_info },\n"));
+        assert!(!source.contains("\"dispatch_table\""));
+    }
+}
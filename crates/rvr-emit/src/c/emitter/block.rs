@@ -1,9 +1,13 @@
 //! Block and instruction rendering for the C emitter.
 
-use rvr_ir::{InstrIR, Terminator, Xlen};
+use rvr_ir::{BlockIR, Expr, InstrIR, Terminator, Xlen, jump_source_reg};
 use rvr_isa::op_mnemonic;
 
 use super::CEmitter;
+use crate::c::cold::ColdPath;
+use crate::c::namespace::{block_name, global_symbol, hot_body_name};
+use crate::c::signature::FnSignature;
+use crate::c::signature::reg_type;
 
 impl<X: Xlen> CEmitter<X> {
     // ============= Block rendering =============
@@ -20,7 +24,11 @@ impl<X: Xlen> CEmitter<X> {
         end_pc: u64,
         instr_count: usize,
     ) {
-        let pc_str = Self::fmt_pc(start_pc);
+        self.block_start = start_pc;
+        if self.sig.hot_regs != self.block_sig(start_pc).hot_regs {
+            self.sig = self.block_sig(start_pc).clone();
+        }
+        let block = self.block_body_fn(start_pc);
         if self.config.emit_comments() && instr_count > 0 {
             let start_comment = Self::fmt_pc_comment(start_pc);
             let end_comment = Self::fmt_pc_comment(end_pc.saturating_sub(1));
@@ -30,31 +38,102 @@ impl<X: Xlen> CEmitter<X> {
         }
 
         // Function attributes differ based on whether fixed addresses are used
-        let attrs = if self.sig.fixed_addresses {
-            // No nonnull since state/memory aren't pointer arguments
-            "__attribute__((preserve_none))"
-        } else {
-            // nonnull(1) for state pointer (first argument)
-            "__attribute__((preserve_none, nonnull(1)))"
-        };
+        let mut attrs = Vec::new();
+        if !self.sig.fixed_addresses {
+            // nonnull(1) for state pointer (first argument); with fixed
+            // addresses state/memory aren't pointer arguments
+            attrs.push("nonnull(1)");
+        }
+        // Blocks the profile never entered go to the compiler's cold section
+        if self.inputs.cold_blocks.contains(&start_pc) {
+            attrs.push("cold");
+        }
+
+        // The prologue belongs to the first (guest) instruction
+        if self.config.emit_guest_pc_map() {
+            let guest_pc = self
+                .inputs
+                .synthetic_blocks
+                .get(&start_pc)
+                .map_or(start_pc, |info| info.owner_pc);
+            self.emit_guest_pc_line(guest_pc, 0);
+        }
+
+        let decl = self.sig.fn_decl(&block, &attrs);
+        self.write(&format!("{decl} {{\n"));
+        if !self.sig.prologue.is_empty() {
+            let prologue = self.sig.prologue.clone();
+            self.writeln(1, &prologue);
+        }
+    }
 
-        self.write(&format!(
-            "{} void B_{}({}) {{\n",
-            attrs, pc_str, self.sig.params
-        ));
+    /// Block function name for `pc`.
+    pub(super) fn block_fn(&self, pc: u64) -> String {
+        block_name::<X>(&self.config.symbol_prefix, pc)
     }
 
-    /// Format PC for block names (hex without 0x prefix).
-    pub(super) fn fmt_pc(pc: u64) -> String {
-        if X::VALUE == 64 {
-            format!("{pc:016x}")
+    /// Linked name of the fixed-name global symbol `name`.
+    pub(super) fn symbol(&self, name: &str) -> String {
+        global_symbol(&self.config.symbol_prefix, name)
+    }
+
+    /// Name of the tracer hook `hook` as blocks call it.
+    pub(super) fn trace_hook(&self, hook: &str) -> String {
+        self.config.tracer_config.hook_name(hook)
+    }
+
+    /// Signature of the block at `pc`: its function's, if the function has
+    /// its own hot registers.
+    fn block_sig(&self, pc: u64) -> &FnSignature {
+        self.inputs
+            .block_to_function
+            .get(&pc)
+            .and_then(|function| self.function_sigs.get(function))
+            .unwrap_or(&self.global_sig)
+    }
+
+    /// Whether the block at `pc` has a body separate from its `B_<pc>`
+    /// entry: clang code passing its function's own hot registers.
+    fn has_hot_body(&self, pc: u64) -> bool {
+        !self.sig.dialect.is_portable() && self.inputs.block_hot_regs(pc).is_some()
+    }
+
+    /// Name of the function holding the code of the block at `pc`.
+    fn block_body_fn(&self, pc: u64) -> String {
+        if self.has_hot_body(pc) {
+            hot_body_name::<X>(&self.config.symbol_prefix, pc)
         } else {
-            format!("{pc:08x}")
+            self.block_fn(pc)
         }
     }
 
+    /// Tail call into the block at `pc`, with glue if its hot registers
+    /// differ from this block's.
+    pub(super) fn jump_to_block(&self, pc: u64) -> String {
+        self.sig
+            .transfer(self.block_sig(pc), &self.block_body_fn(pc))
+    }
+
     /// Render block footer.
+    ///
+    /// A block with a separate body also gets its `B_<pc>` entry, which
+    /// takes the global hot registers and continues in the body.
     pub fn render_block_footer(&mut self) {
+        self.origins.push((self.out.len(), None));
+        self.write("}\n\n");
+        let pc = self.block_start;
+        if !self.has_hot_body(pc) {
+            return;
+        }
+        let attrs: &[&str] = if self.sig.fixed_addresses {
+            &[]
+        } else {
+            &["nonnull(1)"]
+        };
+        let decl = self.global_sig.fn_decl(&self.block_fn(pc), attrs);
+        let call = self.global_sig.transfer(&self.sig, &self.block_body_fn(pc));
+        self.write(&format!("{decl} {{\n"));
+        self.writeln(1, &call);
         self.write("}\n\n");
     }
 
@@ -86,8 +165,13 @@ impl<X: Xlen> CEmitter<X> {
         use_simple_branch: bool,
     ) {
         self.current_pc = X::to_u64(ir.pc);
+        self.mark_origin(self.current_pc);
         self.current_op = ir.op;
         self.current_raw = ir.raw;
+        self.synthetic = self.inputs.synthetic_blocks.get(&self.current_pc).copied();
+        self.guest_pc = self.synthetic.map_or(self.current_pc, |info| info.owner_pc);
+        self.jump_reg = jump_source_reg(ir).unwrap_or(0);
+        let retires = self.synthetic.is_none_or(|info| info.retires());
 
         // Optional: emit comment with PC and instruction mnemonic
         if self.config.emit_comments() {
@@ -109,14 +193,19 @@ impl<X: Xlen> CEmitter<X> {
         }
 
         // Emit #line directive for source-level debugging
-        if self.config.emit_line_info()
+        if self.config.emit_guest_pc_map() {
+            self.emit_guest_pc_line(self.guest_pc, indent);
+        } else if self.config.emit_line_info()
             && let Some(ref loc) = ir.source_loc
             && loc.is_valid()
         {
             self.writeln(indent, &format!("#line {} \"{}\"", loc.line, loc.file));
         }
 
-        self.emit_trace_pc();
+        // Helper blocks are not guest instructions; only their effects are traced
+        if self.synthetic.is_none() {
+            self.emit_trace_pc();
+        }
 
         // Render statements
         for stmt in &ir.statements {
@@ -127,10 +216,12 @@ impl<X: Xlen> CEmitter<X> {
             self.render_exit_check(indent);
         }
 
-        self.instr_idx += 1;
+        if retires {
+            self.instr_idx += 1;
+        }
 
         // Per-instruction instret check: update and potentially suspend after every instruction
-        if self.config.instret_mode.per_instruction() {
+        if self.config.instret_mode.per_instruction() && retires {
             // Update instret by 1 for this instruction
             self.writeln(indent, "instret += 1;");
             // For non-last instructions, check suspension inline
@@ -148,7 +239,10 @@ impl<X: Xlen> CEmitter<X> {
         if is_last {
             // Update instret BEFORE the terminator (tail call) so the incremented value is passed
             // Skip bulk update if per-instruction mode (already updated above)
-            if self.config.instret_mode.counts() && !self.config.instret_mode.per_instruction() {
+            if self.config.instret_mode.counts()
+                && !self.config.instret_mode.per_instruction()
+                && self.instr_idx > 0
+            {
                 self.render_instret_update_impl(self.instr_idx as u64, indent);
             }
             if use_simple_branch {
@@ -164,8 +258,64 @@ impl<X: Xlen> CEmitter<X> {
             } = &ir.terminator
             {
                 let cond_str = self.render_expr(cond);
-                self.render_side_exit_impl(&cond_str, X::to_u64(*target), *hint, indent);
+                let next_pc =
+                    next_instr_pc.unwrap_or_else(|| X::to_u64(ir.pc) + u64::from(ir.size));
+                self.render_side_exit_impl(&cond_str, X::to_u64(*target), *hint, next_pc, indent);
             }
         }
     }
+
+    /// Render a block run by the block interpreter (see `c::interp`): its
+    /// instructions as a table passed to `rv_interpret`, then a dynamic
+    /// jump to the PC it returns.
+    pub fn render_interpreted_block(&mut self, block: &BlockIR<X>) {
+        let start_pc = X::to_u64(block.start_pc);
+        let end_pc = X::to_u64(block.end_pc);
+        let count = block.instructions.len();
+        self.render_block_header_with_count(start_pc, end_pc, count);
+        self.render_instret_check(start_pc);
+        self.render_block_trace(start_pc);
+        self.render_golden_point(start_pc);
+        self.mark_origin(start_pc);
+
+        let state = self.state_ref();
+        let rtype = reg_type::<X>();
+        self.writeln(1, "static const RvInterpInstr instrs[] = {");
+        for instr in &block.instructions {
+            let pc = Self::fmt_addr(X::to_u64(instr.pc));
+            let entry = format!("{{{pc}, 0x{:08x}u, {}}},", instr.raw, instr.op);
+            self.writeln(2, &entry);
+        }
+        self.writeln(1, "};");
+        // The interpreter works on `RvState`: hot registers go through it
+        let save_to_state = self.sig.save_to_state.clone();
+        if !save_to_state.is_empty() {
+            self.writeln(1, &save_to_state);
+        }
+        let interpret = self.symbol("rv_interpret");
+        self.writeln(
+            1,
+            &format!("{rtype} next_pc = {interpret}({state}, instrs, {count});"),
+        );
+        let load_from_state = self.sig.load_from_state.trim_start().to_string();
+        if !load_from_state.is_empty() {
+            self.writeln(1, &load_from_state);
+        }
+        if self.sig.counts_instret {
+            self.writeln(1, &format!("instret = {state}->instret;"));
+        }
+        self.writeln(1, &format!("if (unlikely({state}->has_exited)) {{"));
+        self.render_cold_stop(ColdPath::Exit, "next_pc", "", 2);
+        self.writeln(1, "}");
+
+        // The dynamic jump reports misses against the last instruction
+        if let Some(last) = block.instructions.last() {
+            self.current_pc = X::to_u64(last.pc);
+            self.current_op = last.op;
+            self.guest_pc = self.current_pc;
+        }
+        self.jump_reg = 0;
+        self.render_jump_dynamic(&Expr::var("next_pc"), Some("next_pc"));
+        self.render_block_footer();
+    }
 }
@@ -2,7 +2,9 @@
 
 use rvr_ir::{BinaryOp, Expr, ReadExpr, Stmt, TernaryOp, UnaryOp, WriteTarget, Xlen};
 
-use crate::htif::TOHOST_ADDR;
+use crate::c::cold::ColdPath;
+use crate::hooks::is_hook_fn;
+use crate::htif::{FROMHOST_ADDR, TOHOST_ADDR};
 
 use super::CEmitter;
 
@@ -293,7 +295,17 @@ impl<X: Xlen> CEmitter<X> {
 
     fn render_extern_call(&self, name: &str, args: &[Expr<X>]) -> String {
         let args: Vec<String> = args.iter().map(|a| self.render_expr(a)).collect();
-        format!("{name}({})", args.join(", "))
+        format!("{}({})", self.extern_name(name), args.join(", "))
+    }
+
+    /// Linked name of extern function `name`: runtime functions carry the
+    /// program's symbol prefix, hooks are `static inline` in the header.
+    fn extern_name(&self, name: &str) -> String {
+        if is_hook_fn(name) {
+            name.to_string()
+        } else {
+            self.symbol(name)
+        }
     }
 
     /// Render read expression.
@@ -458,6 +470,20 @@ impl<X: Xlen> CEmitter<X> {
     }
 
     fn render_write_stmt(&mut self, target: &WriteTarget<X>, value: &Expr<X>, indent: usize) {
+        if let (
+            WriteTarget::Reg(_),
+            Expr::Read(ReadExpr::Mem {
+                base,
+                offset,
+                width: 4 | 8,
+                ..
+            }),
+        ) = (target, value)
+            && self.config.htif_enabled()
+            && self.config.htif_poll_limit > 0
+        {
+            self.render_htif_poll(base, *offset, indent);
+        }
         let value_str = self.render_expr(value);
         let state = self.state_ref();
         match target {
@@ -533,6 +559,19 @@ impl<X: Xlen> CEmitter<X> {
     ) {
         let base_str = self.render_expr(base);
 
+        if self.config.detect_code_writes() && !self.inputs.code_ranges.is_empty() {
+            let store = (base_str.as_str(), offset, width);
+            self.render_store_trap("rv_is_code_write", ColdPath::CodeWrite, store, indent);
+        }
+        if self
+            .inputs
+            .checked_stack_guard(self.config.address_mode)
+            .is_some()
+        {
+            let store = (base_str.as_str(), offset, width);
+            let trap = ColdPath::StackOverflow;
+            self.render_store_trap("rv_is_stack_overflow", trap, store, indent);
+        }
         if self.config.htif_enabled() && (width == 4 || width == 8) {
             self.render_mem_write_tohost(&base_str, offset, value_str, width, indent);
         } else if self.config.has_tracing() {
@@ -560,6 +599,58 @@ impl<X: Xlen> CEmitter<X> {
         }
     }
 
+    /// Trap with exit code and cause `trap` before a `(base, offset, width)`
+    /// store that `check` flags (recompiled code, stack guard), with the
+    /// store address in `mtval` and `exit_info` and the store not retired.
+    fn render_store_trap(
+        &mut self,
+        check: &str,
+        trap: ColdPath,
+        (base, offset, width): (&str, i16, u8),
+        indent: usize,
+    ) {
+        self.writeln(
+            indent,
+            &format!("if (unlikely({check}({base} + {offset}, {width}))) {{"),
+        );
+        self.render_retired_before_current(indent + 1);
+        let pc_lit = Self::fmt_addr(self.guest_pc);
+        let addr = format!("{base} + {offset}");
+        self.render_cold_stop(trap, &pc_lit, &addr, indent + 1);
+        self.writeln(indent, "}");
+    }
+
+    /// Count the instructions before the current one as retired ahead of a
+    /// stop that does not retire it (per-instruction mode has counted them
+    /// already).
+    fn render_retired_before_current(&mut self, indent: usize) {
+        if self.config.instret_mode.counts()
+            && !self.config.instret_mode.per_instruction()
+            && self.instr_idx > 0
+        {
+            self.render_instret_update_impl(self.instr_idx as u64, indent);
+        }
+    }
+
+    /// Render the HTIF poll watchdog ahead of a load that may read
+    /// `fromhost`: stop at the load, not retired, once `htif_poll` reports
+    /// a stalled handshake.
+    fn render_htif_poll(&mut self, base: &Expr<X>, offset: i16, indent: usize) {
+        let base = self.render_expr(base);
+        let state = self.state_ref();
+        let poll = self.symbol("htif_poll");
+        self.writeln(
+            indent,
+            &format!(
+                "if (unlikely((uint32_t){base} + {offset} == 0x{FROMHOST_ADDR:x}u && {poll}({state}))) {{"
+            ),
+        );
+        self.render_retired_before_current(indent + 1);
+        let pc_lit = Self::fmt_addr(self.guest_pc);
+        self.render_cold_stop(ColdPath::Exit, &pc_lit, "", indent + 1);
+        self.writeln(indent, "}");
+    }
+
     fn render_write_csr(&mut self, csr: u16, value_str: &str, state: &str, indent: usize) {
         let state_arg = if self.uses_fixed_addresses() {
             String::new()
@@ -622,9 +713,25 @@ impl<X: Xlen> CEmitter<X> {
         self.writeln(indent, "}");
     }
 
+    /// Render a call statement. Function hooks see and change the guest
+    /// registers in state, so the hot registers are flushed before the
+    /// call and reloaded after.
     fn render_extern_stmt(&mut self, fn_name: &str, args: &[Expr<X>], indent: usize) {
         let args_str: Vec<String> = args.iter().map(|a| self.render_expr(a)).collect();
-        self.writeln(indent, &format!("{fn_name}({});", args_str.join(", ")));
+        let call = format!("{}({});", self.extern_name(fn_name), args_str.join(", "));
+        if !is_hook_fn(fn_name) {
+            self.writeln(indent, &call);
+            return;
+        }
+        let save_to_state = self.sig.save_to_state.trim_start().to_string();
+        let load_from_state = self.sig.load_from_state.trim_start().to_string();
+        if !save_to_state.is_empty() {
+            self.writeln(indent, &save_to_state);
+        }
+        self.writeln(indent, &call);
+        if !load_from_state.is_empty() {
+            self.writeln(indent, &load_from_state);
+        }
     }
 
     /// Render memory write with tohost check.
@@ -638,23 +745,8 @@ impl<X: Xlen> CEmitter<X> {
         width: u8,
         indent: usize,
     ) {
-        let pc_lit = Self::fmt_addr(self.current_pc);
-        let save_to_state = self.sig.save_to_state.clone();
-
-        // Build instret update if needed
-        let instret_update = if self.config.instret_mode.counts() {
-            format!("instret += {};", self.instr_idx + 1)
-        } else {
-            String::new()
-        };
-
-        // Build save_to_state call if needed
-        let save_call = if save_to_state.is_empty() {
-            String::new()
-        } else {
-            format!(" {save_to_state}")
-        };
-
+        let pc_lit = Self::fmt_addr(self.guest_pc);
+        let retires = self.synthetic.is_none_or(|info| info.retires());
         let state = self.state_ref();
 
         // Generate the tohost check
@@ -664,17 +756,17 @@ impl<X: Xlen> CEmitter<X> {
         );
         self.writeln(
             indent + 1,
-            &format!("handle_tohost_write({state}, {value});"),
+            &format!("{}({state}, {value});", self.symbol("handle_tohost_write")),
         );
         self.writeln(
             indent + 1,
             &format!("if (unlikely({state}->has_exited)) {{"),
         );
-        self.writeln(indent + 2, &format!("{state}->pc = {pc_lit};"));
-        if !instret_update.is_empty() || !save_call.is_empty() {
-            self.writeln(indent + 2, &format!("{instret_update}{save_call}"));
+        if self.config.instret_mode.counts() {
+            let count = self.instr_idx + usize::from(retires);
+            self.writeln(indent + 2, &format!("instret += {count};"));
         }
-        self.writeln(indent + 2, "return;");
+        self.render_cold_stop(ColdPath::Exit, &pc_lit, "", indent + 2);
         self.writeln(indent + 1, "}");
         self.writeln(indent, "} else {");
 
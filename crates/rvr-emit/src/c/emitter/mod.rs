@@ -14,8 +14,12 @@ mod terminator;
 #[cfg(test)]
 mod tests;
 
-use rvr_ir::Xlen;
+use std::collections::HashMap;
 
+use rvr_ir::{SyntheticBlockInfo, Xlen};
+
+use super::golden::GoldenMode;
+use super::pc_map::GUEST_PC_MAP;
 use super::signature::{FnSignature, state_ref};
 use crate::config::EmitConfig;
 use crate::inputs::EmitInputs;
@@ -24,8 +28,14 @@ use crate::inputs::EmitInputs;
 pub struct CEmitter<X: Xlen> {
     pub config: EmitConfig<X>,
     pub inputs: EmitInputs,
-    /// Function signature for block functions.
+    /// Function signature of the block being rendered.
     pub sig: FnSignature,
+    /// Signature with the global hot registers, taken by every `B_<pc>`.
+    global_sig: FnSignature,
+    /// Signatures of functions with their own hot registers, by entry.
+    function_sigs: HashMap<u64, FnSignature>,
+    /// Start PC of the block being rendered.
+    block_start: u64,
     /// Output buffer.
     pub out: String,
     /// Register type name ("`uint32_t`" or "`uint64_t`").
@@ -34,12 +44,24 @@ pub struct CEmitter<X: Xlen> {
     signed_type: &'static str,
     /// Current instruction PC.
     current_pc: u64,
+    /// Guest PC reported on exits (the owning instruction inside helper blocks).
+    guest_pc: u64,
+    /// Register the current instruction's dynamic jump target comes from
+    /// (0 if unknown), reported when the target has no block.
+    jump_reg: u8,
+    /// Helper block info when emitting an override helper block.
+    synthetic: Option<SyntheticBlockInfo>,
     /// Current instruction op (packed `OpId` for tracing).
     current_op: u16,
     /// Current instruction raw bytes (for spike tracer).
     current_raw: u32,
     /// Instruction index within block (for instret).
     instr_idx: usize,
+    /// Output offset where each instruction's code starts, with its PC
+    /// (`None` for the block footer).
+    origins: Vec<(usize, Option<u64>)>,
+    /// Golden trace points at block entries, if capturing or checking.
+    golden: Option<GoldenMode>,
 }
 
 impl<X: Xlen> CEmitter<X> {
@@ -52,28 +74,47 @@ impl<X: Xlen> CEmitter<X> {
             ("uint32_t", "int32_t")
         };
         let sig = FnSignature::new(&config);
+        let function_sigs = inputs
+            .function_hot_regs
+            .iter()
+            .map(|(&entry, hot_regs)| (entry, FnSignature::with_hot_regs(&config, hot_regs)))
+            .collect();
+        let golden = GoldenMode::new(&config.tracer_config, inputs.golden.as_deref());
 
         Self {
             config,
             inputs,
+            global_sig: sig.clone(),
             sig,
+            function_sigs,
+            block_start: 0,
             out: String::with_capacity(4096),
             reg_type,
             signed_type,
             current_pc: 0,
+            guest_pc: 0,
+            jump_reg: 0,
+            synthetic: None,
             current_op: 0,
             current_raw: 0,
             instr_idx: 0,
+            origins: Vec::new(),
+            golden,
         }
     }
 
     /// Reset output buffer.
     pub fn reset(&mut self) {
         self.out.clear();
+        self.block_start = 0;
         self.current_pc = 0;
+        self.guest_pc = 0;
+        self.jump_reg = 0;
+        self.synthetic = None;
         self.current_op = 0;
         self.current_raw = 0;
         self.instr_idx = 0;
+        self.origins.clear();
     }
 
     /// Get output string.
@@ -88,6 +129,28 @@ impl<X: Xlen> CEmitter<X> {
         self.out
     }
 
+    /// Mark the start of the code for the instruction at `pc`.
+    pub fn mark_origin(&mut self, pc: u64) {
+        self.origins.push((self.out.len(), Some(pc)));
+    }
+
+    /// Emit the `guest_pc.map` line directive of the guest instruction at `pc`.
+    pub fn emit_guest_pc_line(&mut self, pc: u64, indent: usize) {
+        if let Some(line) = self.inputs.guest_pc_lines.line(pc) {
+            self.writeln(indent, &format!("#line {line} \"{GUEST_PC_MAP}\""));
+        }
+    }
+
+    /// Output offsets where each rendered instruction starts, with its PC.
+    ///
+    /// Code up to the next offset was emitted for that instruction; this is
+    /// the same mapping the `#line` directives follow. The block footer is
+    /// marked with `None`.
+    #[must_use]
+    pub fn origins(&self) -> &[(usize, Option<u64>)] {
+        &self.origins
+    }
+
     /// Check if address is valid.
     pub(super) fn is_valid_address(&self, addr: u64) -> bool {
         self.inputs.is_valid_address(addr)
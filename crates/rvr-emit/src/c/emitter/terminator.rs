@@ -3,6 +3,10 @@
 use rvr_ir::{BlockIR, BranchHint, Expr, InstrIR, Stmt, Terminator, WriteTarget, Xlen};
 
 use super::CEmitter;
+use crate::c::cold::{ColdPath, outlines_cold_paths};
+use crate::c::dispatch::flat_lookup;
+use crate::config::{DISPATCH_PREFETCH_DISTANCE, DispatchMode};
+use crate::hooks::is_hook_fn;
 
 impl<X: Xlen> CEmitter<X> {
     pub(super) fn render_terminator(&mut self, term: &Terminator<X>, fall_pc: u64) {
@@ -39,7 +43,7 @@ impl<X: Xlen> CEmitter<X> {
             }
             Terminator::Trap { message } => {
                 self.writeln(1, &format!("// TRAP: {message}"));
-                self.render_exit("1");
+                self.render_trap_impl(1);
             }
         }
     }
@@ -59,43 +63,63 @@ impl<X: Xlen> CEmitter<X> {
         if self.is_valid_address(target) {
             // Resolve absorbed addresses to their merged block
             let resolved = self.inputs.resolve_address(target);
-            let pc_str = Self::fmt_pc(resolved);
-            self.writeln(
-                indent,
-                &format!(
-                    "[[clang::musttail]] return B_{}({});",
-                    pc_str, self.sig.args
-                ),
-            );
+            self.render_cancel_check(target, indent);
+            let call = self.jump_to_block(resolved);
+            self.writeln(indent, &call);
         } else {
-            self.render_exit_impl("1", indent);
+            let pc_lit = Self::fmt_addr(self.guest_pc);
+            let target_lit = Self::fmt_addr(target);
+            self.render_cold_stop(ColdPath::InvalidTarget, &pc_lit, &target_lit, indent);
         }
     }
 
     /// Render instret check with custom indent.
     fn render_instret_check_impl(&mut self, pc: u64, indent: usize) {
-        if !self.config.instret_mode.suspends() {
+        // Helper blocks cannot be resumed through dispatch; suspend at guest PCs only
+        if !self.config.instret_mode.suspends() || self.inputs.synthetic_blocks.contains_key(&pc) {
             return;
         }
-        let save_to_state = self.sig.save_to_state.clone();
         let pc_lit = Self::fmt_addr(pc);
         let state = self.state_ref();
         self.writeln(
             indent,
             &format!("if (unlikely({state}->target_instret <= instret)) {{"),
         );
-        self.writeln(indent + 1, &format!("{state}->pc = {pc_lit};"));
-        if !save_to_state.is_empty() {
-            self.writeln(indent + 1, &save_to_state);
+        self.render_cold_stop(ColdPath::Suspend, &pc_lit, "", indent + 1);
+        self.writeln(indent, "}");
+    }
+
+    /// Render a cancellation check before a backward edge to `target`.
+    ///
+    /// Every loop has a backward edge, so polling there bounds how long a
+    /// cancelled guest keeps running. The guest stops resumable at `target`.
+    fn render_cancel_check(&mut self, target: u64, indent: usize) {
+        // Helper blocks cannot be resumed through dispatch; stop at guest PCs only
+        if !self.config.check_cancel()
+            || target > self.current_pc
+            || self.inputs.synthetic_blocks.contains_key(&target)
+        {
+            return;
         }
-        self.writeln(indent + 1, "return;");
+        let state = self.state_ref();
+        // Volatile: the host sets the flag from another thread
+        self.writeln(
+            indent,
+            &format!("if (unlikely(*(volatile uint8_t*)&{state}->cancel_requested)) {{"),
+        );
+        let pc_lit = Self::fmt_addr(target);
+        self.render_cold_stop(ColdPath::Suspend, &pc_lit, "", indent + 1);
         self.writeln(indent, "}");
     }
 
     /// Render dynamic jump.
     ///
     /// If `pre_eval_var` is set, use that variable name instead of rendering the expression.
-    fn render_jump_dynamic(&mut self, target_expr: &Expr<X>, pre_eval_var: Option<&str>) {
+    pub(super) fn render_jump_dynamic(
+        &mut self,
+        target_expr: &Expr<X>,
+        pre_eval_var: Option<&str>,
+    ) {
         self.render_jump_dynamic_impl(target_expr, pre_eval_var, 1);
     }
 
@@ -116,12 +140,70 @@ impl<X: Xlen> CEmitter<X> {
             self.render_instret_check_dynamic(&target, indent);
         }
 
-        self.writeln(
-            indent,
-            &format!(
-                "[[clang::musttail]] return dispatch_table[dispatch_index({})]({});",
-                target, self.sig.args
+        let lookup = match self.config.dispatch_mode {
+            DispatchMode::Flat => flat_lookup(
+                self.config.dispatch_table_relative(),
+                &self.symbol("dispatch_table"),
+                &target,
             ),
+            DispatchMode::PerFunction => format!("{}({target})", self.symbol("dispatch_lookup")),
+        };
+        // Dispatch tables hold entries taking the global hot registers
+        if !self.config.report_jump_sites() {
+            // A miss enters `rv_trap`; the call stays free of PC constants
+            // so identical blocks still deduplicate
+            let call = self.sig.transfer(&self.global_sig, &lookup);
+            self.writeln(indent, &call);
+            return;
+        }
+        // Scoped so `next` never clashes with another jump in the function
+        self.writeln(indent, "{");
+        let body = indent + 1;
+        self.writeln(body, &format!("rv_fn next = {lookup};"));
+        // A miss reports the jump site, target and source register
+        // instead of entering `rv_trap`, which knows none of them
+        let trap = self.symbol("rv_trap");
+        self.writeln(body, &format!("if (unlikely(next == {trap})) {{"));
+        let state = self.state_ref();
+        self.writeln(
+            body + 1,
+            &format!("{state}->exit_code = {};", self.jump_reg),
+        );
+        let pc_lit = Self::fmt_addr(self.guest_pc);
+        self.render_cold_stop(ColdPath::UnresolvedJump, &pc_lit, &target, body + 1);
+        self.writeln(body, "}");
+        let call = self.sig.transfer(&self.global_sig, "next");
+        self.writeln(body, &call);
+        self.writeln(indent, "}");
+    }
+
+    /// Where to prefetch the dispatch table slot of `block`'s closing
+    /// dynamic jump: the index of the instruction to prefetch ahead of, and
+    /// the jump address (see `EmitConfig::prefetch_dispatch`).
+    pub(crate) fn dispatch_prefetch<'b>(
+        &self,
+        block: &'b BlockIR<X>,
+    ) -> Option<(usize, &'b Expr<X>)> {
+        let unresolved = matches!(
+            block.instructions.last()?.terminator,
+            Terminator::JumpDyn { resolved: None, .. }
+        );
+        if !unresolved
+            || !self.config.prefetch_dispatch()
+            || self.config.dispatch_mode != DispatchMode::Flat
+        {
+            return None;
+        }
+        rvr_ir::early_jump_target(&block.instructions, DISPATCH_PREFETCH_DISTANCE)
+    }
+
+    /// Render a prefetch of the dispatch table slot for target `addr`.
+    pub(crate) fn render_dispatch_prefetch(&mut self, addr: &Expr<X>) {
+        let target = self.render_expr(addr);
+        let table = self.symbol("dispatch_table");
+        self.writeln(
+            1,
+            &format!("__builtin_prefetch(&{table}[dispatch_index({target})]);"),
         );
     }
 
@@ -130,17 +212,12 @@ impl<X: Xlen> CEmitter<X> {
         if !self.config.instret_mode.suspends() {
             return;
         }
-        let save_to_state = self.sig.save_to_state.clone();
         let state = self.state_ref();
         self.writeln(
             indent,
             &format!("if (unlikely({state}->target_instret <= instret)) {{"),
         );
-        self.writeln(indent + 1, &format!("{state}->pc = {target_var};"));
-        if !save_to_state.is_empty() {
-            self.writeln(indent + 1, &save_to_state);
-        }
-        self.writeln(indent + 1, "return;");
+        self.render_cold_stop(ColdPath::Suspend, target_var, "", indent + 1);
         self.writeln(indent, "}");
     }
 
@@ -150,53 +227,42 @@ impl<X: Xlen> CEmitter<X> {
     }
 
     /// Render jump with resolved targets with custom indent.
+    ///
+    /// Proven targets become a `switch` of direct tail calls; any other
+    /// value still goes through dispatch.
     fn render_jump_resolved_impl(&mut self, targets: &[u64], fallback: &Expr<X>, indent: usize) {
+        let targets: Vec<u64> = targets
+            .iter()
+            .copied()
+            .filter(|&target| self.is_valid_address(target))
+            .collect();
         if targets.is_empty() {
             self.render_jump_dynamic_impl(fallback, None, indent);
             return;
         }
 
         let target_var = self.render_expr(fallback);
-        if targets.len() > 1 {
-            self.writeln(
-                indent,
-                &format!("{} target = {};", self.reg_type, target_var),
-            );
-        }
-
-        let var_name = if targets.len() > 1 {
-            "target"
-        } else {
-            &target_var
-        };
-
+        // Scoped so `target` never clashes with another jump in the function
+        self.writeln(indent, "{");
+        let body = indent + 1;
+        self.writeln(body, &format!("{} target = {};", self.reg_type, target_var));
+        self.writeln(body, "switch (target) {");
         for target in targets {
-            if self.is_valid_address(*target) {
-                let pc_str = Self::fmt_pc(*target);
-                let addr_lit = Self::fmt_addr(*target);
-                self.writeln(indent, &format!("if ({var_name} == {addr_lit}) {{"));
-                // In per-instruction mode, check for suspension before the tail call
-                if self.config.instret_mode.per_instruction() {
-                    self.render_instret_check_impl(*target, indent + 1);
-                }
-                self.writeln(
-                    indent + 1,
-                    &format!(
-                        "[[clang::musttail]] return B_{}({});",
-                        pc_str, self.sig.args
-                    ),
-                );
-                self.writeln(indent, "}");
-            }
+            let addr_lit = Self::fmt_addr(target);
+            self.writeln(body + 1, &format!("case {addr_lit}: {{"));
+            self.render_instret_check_impl(target, body + 2);
+            self.render_cancel_check(target, body + 2);
+            let call = self.jump_to_block(self.inputs.resolve_address(target));
+            self.writeln(body + 2, &call);
+            self.writeln(body + 1, "}");
         }
+        self.writeln(body + 1, "default:");
+        self.writeln(body + 2, "break;");
+        self.writeln(body, "}");
 
         // Fallback to dispatch table
-        let pre_eval = if targets.len() > 1 {
-            Some("target")
-        } else {
-            None
-        };
-        self.render_jump_dynamic_impl(fallback, pre_eval, indent);
+        self.render_jump_dynamic_impl(fallback, Some("target"), body);
+        self.writeln(indent, "}");
     }
 
     /// Render branch with both taken and not-taken paths.
@@ -211,100 +277,74 @@ impl<X: Xlen> CEmitter<X> {
         };
 
         // Tracing hooks (if enabled)
-        let trace_taken = if self.config.has_tracing() {
-            let target_lit = Self::fmt_addr(target);
-            let state = self.state_ref();
-            format!(
-                "trace_branch_taken(&{}->tracer, {}, {}, {});\n    ",
-                state,
-                Self::fmt_addr(self.current_pc),
-                self.current_op,
-                target_lit
-            )
-        } else {
-            String::new()
-        };
-
-        let trace_not_taken = if self.config.has_tracing() {
-            let fall_lit = Self::fmt_addr(fall_pc);
-            let state = self.state_ref();
-            format!(
-                "trace_branch_not_taken(&{}->tracer, {}, {}, {});\n",
-                state,
-                Self::fmt_addr(self.current_pc),
-                self.current_op,
-                fall_lit
-            )
-        } else {
-            String::new()
-        };
-
-        // Pre-clone values that need to outlive the mutable borrows
-        let args = self.sig.args.clone();
-        let save_to_state = self.sig.save_to_state.clone();
+        let (trace_taken, trace_not_taken) = self.branch_trace_calls(target, fall_pc);
 
         if self.is_valid_address(target) {
             // Resolve absorbed addresses to their merged block
             let resolved = self.inputs.resolve_address(target);
-            let pc_str = Self::fmt_pc(resolved);
             self.writeln(1, &format!("if ({cond_str}) {{"));
             if !trace_taken.is_empty() {
-                self.writeln(2, trace_taken.trim_end());
+                self.writeln(2, &trace_taken);
             }
             // In suspend modes, check for suspension before the tail call
             if self.config.instret_mode.suspends() {
                 self.render_instret_check_impl(target, 2);
             }
-            self.writeln(
-                2,
-                &format!("[[clang::musttail]] return B_{pc_str}({args});"),
-            );
+            self.render_cancel_check(target, 2);
+            let call = self.jump_to_block(resolved);
+            self.writeln(2, &call);
         } else {
-            let state = self.state_ref();
             self.writeln(1, &format!("if ({cond_str}) {{"));
             if !trace_taken.is_empty() {
-                self.writeln(2, trace_taken.trim_end());
+                self.writeln(2, &trace_taken);
             }
-            self.writeln(2, &format!("{state}->has_exited = true;"));
-            self.writeln(2, &format!("{state}->exit_code = 1;"));
             let pc_lit = Self::fmt_addr(target);
-            self.writeln(2, &format!("{state}->pc = {pc_lit};"));
-            if !save_to_state.is_empty() {
-                self.writeln(2, &save_to_state);
-            }
-            self.writeln(2, "return;");
+            self.render_cold_stop(ColdPath::InvalidTarget, &pc_lit, &pc_lit, 2);
         }
         self.writeln(1, "}");
 
         // Emit trace_branch_not_taken for fall-through path
         if !trace_not_taken.is_empty() {
-            self.write(&trace_not_taken);
+            self.writeln(1, &trace_not_taken);
         }
 
-        // Emit fall-through musttail return
+        // Emit fall-through tail call
         if self.is_valid_address(fall_pc) {
             let resolved = self.inputs.resolve_address(fall_pc);
-            let pc_str = Self::fmt_pc(resolved);
             // In suspend modes, check for suspension before the tail call
             if self.config.instret_mode.suspends() {
                 self.render_instret_check_impl(fall_pc, 1);
             }
-            self.writeln(
-                1,
-                &format!("[[clang::musttail]] return B_{pc_str}({args});"),
-            );
+            let call = self.jump_to_block(resolved);
+            self.writeln(1, &call);
         } else {
             // Invalid fall address - exit
-            let state = self.state_ref();
-            self.writeln(1, &format!("{state}->has_exited = true;"));
-            self.writeln(1, &format!("{state}->exit_code = 1;"));
             let pc_lit = Self::fmt_addr(fall_pc);
-            self.writeln(1, &format!("{state}->pc = {pc_lit};"));
-            if !save_to_state.is_empty() {
-                self.writeln(1, &save_to_state);
-            }
-            self.writeln(1, "return;");
+            self.render_cold_stop(ColdPath::InvalidTarget, &pc_lit, &pc_lit, 1);
+        }
+    }
+
+    /// `trace_branch_taken` and `trace_branch_not_taken` calls for the branch
+    /// at the current PC (empty without tracing).
+    fn branch_trace_calls(&self, target: u64, fall_pc: u64) -> (String, String) {
+        if !self.config.has_tracing() {
+            return (String::new(), String::new());
         }
+        let state = self.state_ref();
+        let pc_lit = Self::fmt_addr(self.current_pc);
+        let op = self.current_op;
+        (
+            format!(
+                "{}(&{state}->tracer, {pc_lit}, {op}, {});",
+                self.trace_hook("trace_branch_taken"),
+                Self::fmt_addr(target)
+            ),
+            format!(
+                "{}(&{state}->tracer, {pc_lit}, {op}, {});",
+                self.trace_hook("trace_branch_not_taken"),
+                Self::fmt_addr(fall_pc)
+            ),
+        )
     }
 
     /// Render superblock side exit (branch with instret update).
@@ -313,6 +353,7 @@ impl<X: Xlen> CEmitter<X> {
         cond: &str,
         target: u64,
         hint: BranchHint,
+        fall_pc: u64,
         indent: usize,
     ) {
         let cond_str = match hint {
@@ -321,40 +362,39 @@ impl<X: Xlen> CEmitter<X> {
             BranchHint::None => cond.to_string(),
         };
 
-        let args = self.sig.args.clone();
-        let save_to_state_no_instret = self.sig.save_to_state_no_instret.clone();
+        let (trace_taken, trace_not_taken) = self.branch_trace_calls(target, fall_pc);
 
         if self.is_valid_address(target) {
             let resolved = self.inputs.resolve_address(target);
-            let pc_str = Self::fmt_pc(resolved);
             self.writeln(indent, &format!("if ({cond_str}) {{"));
+            if !trace_taken.is_empty() {
+                self.writeln(indent + 1, &trace_taken);
+            }
             if self.config.instret_mode.counts() {
                 self.writeln(indent + 1, &format!("instret += {};", self.instr_idx));
             }
-            self.writeln(
-                indent + 1,
-                &format!("[[clang::musttail]] return B_{pc_str}({args});"),
-            );
+            // The target block checks on entry; back-edges also check here
+            if self.config.suspend_granularity.back_edges() && target <= self.current_pc {
+                self.render_instret_check_impl(target, indent + 1);
+            }
+            self.render_cancel_check(target, indent + 1);
+            let call = self.jump_to_block(resolved);
+            self.writeln(indent + 1, &call);
         } else {
-            let state = self.state_ref();
             self.writeln(indent, &format!("if ({cond_str}) {{"));
-            self.writeln(indent + 1, &format!("{state}->has_exited = true;"));
-            self.writeln(indent + 1, &format!("{state}->exit_code = 1;"));
-            let pc_lit = Self::fmt_addr(target);
-            self.writeln(indent + 1, &format!("{state}->pc = {pc_lit};"));
-            if self.config.instret_mode.counts() {
-                self.writeln(
-                    indent + 1,
-                    &format!("{}->instret = instret + {};", state, self.instr_idx),
-                );
+            if !trace_taken.is_empty() {
+                self.writeln(indent + 1, &trace_taken);
             }
-            // Use save_to_state_no_instret since we already handled instret above
-            if !save_to_state_no_instret.is_empty() {
-                self.writeln(indent + 1, &save_to_state_no_instret);
+            if self.config.instret_mode.counts() {
+                self.writeln(indent + 1, &format!("instret += {};", self.instr_idx));
             }
-            self.writeln(indent + 1, "return;");
+            let pc_lit = Self::fmt_addr(target);
+            self.render_cold_stop(ColdPath::InvalidTarget, &pc_lit, &pc_lit, indent + 1);
         }
         self.writeln(indent, "}");
+        if !trace_not_taken.is_empty() {
+            self.writeln(indent, &trace_not_taken);
+        }
     }
 
     /// Render exit with `save_to_state`.
@@ -364,16 +404,47 @@ impl<X: Xlen> CEmitter<X> {
 
     /// Render exit with custom indent.
     fn render_exit_impl(&mut self, code: &str, indent: usize) {
-        let save_to_state = self.sig.save_to_state.clone();
         let state = self.state_ref();
         self.writeln(indent, &format!("{state}->has_exited = true;"));
         self.writeln(indent, &format!("{state}->exit_code = {code};"));
-        let pc_lit = Self::fmt_addr(self.current_pc);
-        self.writeln(indent, &format!("{state}->pc = {pc_lit};"));
+        let pc_lit = Self::fmt_addr(self.guest_pc);
+        self.render_cold_stop(ColdPath::Exit, &pc_lit, "", indent);
+    }
+
+    /// Render a trap: exit code 1 with `RV_TRAPPED` status, so the saved pc
+    /// and registers can be unwound.
+    fn render_trap_impl(&mut self, indent: usize) {
+        let pc_lit = Self::fmt_addr(self.guest_pc);
+        self.render_cold_stop(ColdPath::IllegalInstruction, &pc_lit, "", indent);
+    }
+
+    /// Stop at `pc` (a C expression) on a rare path, with `info` in
+    /// `exit_info` if `path` takes it.
+    ///
+    /// Outlined, this stores the two and tail-calls the shared helper;
+    /// otherwise the helper's body is emitted inline.
+    pub(super) fn render_cold_stop(&mut self, path: ColdPath, pc: &str, info: &str, indent: usize) {
+        let state = self.state_ref();
+        self.writeln(indent, &format!("{state}->pc = {pc};"));
+        if path.takes_info() {
+            self.writeln(indent, &format!("{state}->exit_info = {info};"));
+        }
+        if outlines_cold_paths(self.config.flags, self.config.c_dialect) {
+            // Helpers take the global hot registers, like dispatch entries
+            let call = self
+                .sig
+                .transfer(&self.global_sig, &path.fn_name(&self.config.symbol_prefix));
+            self.writeln(indent, &call);
+            return;
+        }
+        for store in path.status_stores(state) {
+            self.writeln(indent, &store);
+        }
+        let save_to_state = self.sig.save_to_state.clone();
         if !save_to_state.is_empty() {
             self.writeln(indent, &save_to_state);
         }
-        self.writeln(indent, "return;");
+        self.writeln(indent, self.sig.stop());
     }
 
     pub(super) fn statements_write_exit(stmts: &[Stmt<X>]) -> bool {
@@ -394,22 +465,22 @@ impl<X: Xlen> CEmitter<X> {
                         return true;
                     }
                 }
-                Stmt::ExternCall { .. } => {}
+                // A host hook may stop the guest
+                Stmt::ExternCall { fn_name, .. } => {
+                    if is_hook_fn(fn_name) {
+                        return true;
+                    }
+                }
             }
         }
         false
     }
 
     pub(super) fn render_exit_check(&mut self, indent: usize) {
-        let save_to_state = self.sig.save_to_state.clone();
         let state = self.state_ref();
-        let pc_lit = Self::fmt_addr(self.current_pc);
+        let pc_lit = Self::fmt_addr(self.guest_pc);
         self.writeln(indent, &format!("if (unlikely({state}->has_exited)) {{"));
-        self.writeln(indent + 1, &format!("{state}->pc = {pc_lit};"));
-        if !save_to_state.is_empty() {
-            self.writeln(indent + 1, &save_to_state);
-        }
-        self.writeln(indent + 1, "return;");
+        self.render_cold_stop(ColdPath::Exit, &pc_lit, "", indent + 1);
         self.writeln(indent, "}");
     }
 
@@ -434,9 +505,16 @@ impl<X: Xlen> CEmitter<X> {
 
         self.render_block_header_with_count(start_pc, end_pc, block.instructions.len());
         self.render_block_trace(start_pc);
+        self.render_golden_point(start_pc);
 
         let num_instrs = block.instructions.len();
+        let prefetch = self.dispatch_prefetch(block);
         for (i, instr) in block.instructions.iter().enumerate() {
+            if let Some((at, addr)) = prefetch
+                && at == i
+            {
+                self.render_dispatch_prefetch(addr);
+            }
             let is_last = i == num_instrs - 1;
             // For per-instruction mode, pass the next instruction's PC
             let next_instr_pc = if !is_last && i + 1 < num_instrs {
@@ -461,27 +539,39 @@ impl<X: Xlen> CEmitter<X> {
         }
     }
 
-    /// Render `trace_pc` call for current instruction.
-    pub fn emit_trace_pc(&mut self) {
-        if self.config.has_tracing() {
-            let pc_lit = Self::fmt_addr(self.current_pc);
-            let state = self.state_ref();
-            self.writeln(
-                1,
-                &format!(
-                    "trace_pc(&{state}->tracer, {}, {});",
-                    pc_lit, self.current_op
-                ),
-            );
-            // Emit trace_opcode for Spike-compatible tracing
+    /// Render the golden trace point check at block entry: on every
+    /// `RV_GOLDEN_INTERVAL`-th entry, checksum the registers and stop if
+    /// `rv_golden_point` reports a divergence.
+    pub fn render_golden_point(&mut self, pc: u64) {
+        if self.golden.is_none() {
+            return;
+        }
+        let pc_lit = Self::fmt_addr(pc);
+        let state = self.state_ref();
+        self.writeln(1, &format!("if (unlikely(rv_golden_due({state}))) {{"));
+        self.writeln(2, "uint64_t golden = RV_GOLDEN_SEED;");
+        for reg in (1..self.config.num_regs).filter_map(|reg| u8::try_from(reg).ok()) {
+            let value = self.sig.reg_read(reg);
             self.writeln(
-                1,
-                &format!(
-                    "trace_opcode(&{state}->tracer, {}, {}, 0x{:x});",
-                    pc_lit, self.current_op, self.current_raw
-                ),
+                2,
+                &format!("golden = rv_golden_mix(golden, (uint64_t){value});"),
             );
         }
+        self.writeln(
+            2,
+            &format!(
+                "if (unlikely({}({state}, {pc_lit}, golden))) {{",
+                self.symbol("rv_golden_point")
+            ),
+        );
+        self.render_cold_stop(ColdPath::Exit, &pc_lit, "", 3);
+        self.writeln(2, "}");
+        self.writeln(1, "}");
+    }
+
+    /// Render `trace_pc` call for current instruction.
+    pub fn emit_trace_pc(&mut self) {
+        self.emit_trace_pc_for(self.current_pc, self.current_op, self.current_raw);
     }
 
     /// Render `trace_pc` call for a specific instruction (used for taken-inline branches).
@@ -489,32 +579,20 @@ impl<X: Xlen> CEmitter<X> {
         if self.config.has_tracing() {
             let pc_lit = Self::fmt_addr(pc);
             let state = self.state_ref();
-            self.writeln(1, &format!("trace_pc(&{state}->tracer, {pc_lit}, {op});"));
+            let trace_pc = self.trace_hook("trace_pc");
+            // trace_opcode for Spike-compatible tracing
+            let trace_opcode = self.trace_hook("trace_opcode");
+            self.writeln(1, &format!("{trace_pc}(&{state}->tracer, {pc_lit}, {op});"));
             self.writeln(
                 1,
-                &format!("trace_opcode(&{state}->tracer, {pc_lit}, {op}, 0x{raw:x});"),
+                &format!("{trace_opcode}(&{state}->tracer, {pc_lit}, {op}, 0x{raw:x});"),
             );
         }
     }
 
     /// Render instret check and early suspend if needed.
     pub(crate) fn render_instret_check(&mut self, pc: u64) {
-        if !self.config.instret_mode.suspends() {
-            return;
-        }
-        let save_to_state = self.sig.save_to_state.clone();
-        let pc_lit = Self::fmt_addr(pc);
-        let state = self.state_ref();
-        self.writeln(
-            1,
-            &format!("if (unlikely({state}->target_instret <= instret)) {{"),
-        );
-        self.writeln(2, &format!("{state}->pc = {pc_lit};"));
-        if !save_to_state.is_empty() {
-            self.writeln(2, &save_to_state);
-        }
-        self.writeln(2, "return;");
-        self.writeln(1, "}");
+        self.render_instret_check_impl(pc, 1);
     }
 
     // ============= Taken-inline support =============
@@ -529,6 +607,27 @@ impl<X: Xlen> CEmitter<X> {
         self.writeln(1, &format!("if ({cond_str}) {{"));
     }
 
+    /// Render the taken path's entry into the block inlined at
+    /// `inline_start`.
+    ///
+    /// Retires the branch at `branch_pc` and the instructions before it,
+    /// returning their count for the not-taken path. With
+    /// `SuspendGranularity::BackEdges`, an inlined block at or before the
+    /// branch closes a loop without a block entry, so it checks the limit.
+    pub(crate) fn render_taken_inline_entry(&mut self, branch_pc: u64, inline_start: u64) -> u64 {
+        let retired = if self.config.instret_mode.per_instruction() {
+            1
+        } else {
+            self.instr_idx as u64 + 1
+        };
+        self.render_instret_update_impl(retired, 2);
+        if self.config.suspend_granularity.back_edges() && inline_start <= branch_pc {
+            self.render_instret_check_impl(inline_start, 2);
+        }
+        self.instr_idx = 0;
+        retired
+    }
+
     /// Render branch close for taken-inline: `}`
     pub fn render_branch_close(&mut self) {
         self.writeln(1, "}");
@@ -546,7 +645,7 @@ impl<X: Xlen> CEmitter<X> {
         self.render_instruction_impl(ir, is_last, fall_pc, next_instr_pc, indent, true);
     }
 
-    /// Render terminator with custom indent (simplified, no tracing).
+    /// Render terminator with custom indent (simplified).
     ///
     /// Used for inlined blocks in superblocks where branches are side-exits.
     pub(super) fn render_terminator_simple(
@@ -593,12 +692,12 @@ impl<X: Xlen> CEmitter<X> {
             }
             Terminator::Trap { message } => {
                 self.writeln(indent, &format!("// TRAP: {message}"));
-                self.render_exit_impl("1", indent);
+                self.render_trap_impl(indent);
             }
         }
     }
 
-    /// Render branch (simplified, no tracing, no fall-through).
+    /// Render branch (simplified, no fall-through).
     ///
     /// Used for inlined blocks where fall-through continues to next instruction.
     fn render_branch_simple(
@@ -606,7 +705,7 @@ impl<X: Xlen> CEmitter<X> {
         cond: &str,
         target: u64,
         hint: BranchHint,
-        _fall_pc: u64,
+        fall_pc: u64,
         indent: usize,
     ) {
         let cond_str = match hint {
@@ -615,30 +714,29 @@ impl<X: Xlen> CEmitter<X> {
             BranchHint::None => cond.to_string(),
         };
 
-        let args = self.sig.args.clone();
-        let save_to_state = self.sig.save_to_state.clone();
+        let (trace_taken, trace_not_taken) = self.branch_trace_calls(target, fall_pc);
 
         if self.is_valid_address(target) {
             let resolved = self.inputs.resolve_address(target);
-            let pc_str = Self::fmt_pc(resolved);
             self.writeln(indent, &format!("if ({cond_str}) {{"));
-            self.writeln(
-                indent + 1,
-                &format!("[[clang::musttail]] return B_{pc_str}({args});"),
-            );
+            if !trace_taken.is_empty() {
+                self.writeln(indent + 1, &trace_taken);
+            }
+            self.render_cancel_check(target, indent + 1);
+            let call = self.jump_to_block(resolved);
+            self.writeln(indent + 1, &call);
         } else {
-            let state = self.state_ref();
             self.writeln(indent, &format!("if ({cond_str}) {{"));
-            self.writeln(indent + 1, &format!("{state}->has_exited = true;"));
-            self.writeln(indent + 1, &format!("{state}->exit_code = 1;"));
-            let pc_lit = Self::fmt_addr(target);
-            self.writeln(indent + 1, &format!("{state}->pc = {pc_lit};"));
-            if !save_to_state.is_empty() {
-                self.writeln(indent + 1, &save_to_state);
+            if !trace_taken.is_empty() {
+                self.writeln(indent + 1, &trace_taken);
             }
-            self.writeln(indent + 1, "return;");
+            let pc_lit = Self::fmt_addr(target);
+            self.render_cold_stop(ColdPath::InvalidTarget, &pc_lit, &pc_lit, indent + 1);
         }
         self.writeln(indent, "}");
+        if !trace_not_taken.is_empty() {
+            self.writeln(indent, &trace_not_taken);
+        }
     }
 
     /// Render instret update with custom indent.
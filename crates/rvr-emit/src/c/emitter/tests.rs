@@ -56,3 +56,523 @@ fn test_render_add_hot() {
     let result = emitter.render_expr(&expr);
     assert_eq!(result, "(ra + 0xaULL)");
 }
+
+#[test]
+fn test_render_synthetic_helper_block() {
+    use crate::InstretMode;
+    use rvr_ir::{BlockIR, HelperInstret, InstrIR, SyntheticBlockInfo, Terminator, synthetic_pc};
+
+    let mut config = EmitConfig::<Rv64>::default();
+    config.instret_mode = InstretMode::Suspend;
+    let helper_pc = synthetic_pc::<Rv64>(0);
+    let mut inputs = EmitInputs::new(0x1000, 0x1008);
+    inputs.valid_addresses.extend([0x1000, helper_pc]);
+    inputs.synthetic_blocks.insert(
+        helper_pc,
+        SyntheticBlockInfo {
+            owner_pc: 0x1000,
+            instret: HelperInstret::Uncounted,
+        },
+    );
+
+    // Primary jumps into the helper without a suspend check for the helper PC
+    let mut emitter = CEmitter::new(config.clone(), inputs.clone());
+    let mut primary = BlockIR::new(0x1000);
+    primary.push(InstrIR::new(
+        0x1000,
+        4,
+        0,
+        0,
+        Vec::new(),
+        Terminator::jump(helper_pc),
+    ));
+    emitter.render_block(&primary);
+    let out = emitter.output();
+    assert!(out.contains("instret += 1;"));
+    assert!(out.contains("return B_ffffffffffffffff("));
+    assert!(!out.contains("target_instret"));
+
+    // Helper retires nothing and reports the owner PC on exit
+    let mut emitter = CEmitter::new(config, inputs);
+    let mut helper = BlockIR::new(helper_pc);
+    helper.push(InstrIR::new(
+        helper_pc,
+        0,
+        0,
+        0,
+        Vec::new(),
+        Terminator::exit(Expr::imm(0)),
+    ));
+    emitter.render_block(&helper);
+    let out = emitter.output();
+    assert!(!out.contains("instret +="));
+    assert!(out.contains("state->pc = 0x0000000000001000ULL;"));
+}
+
+#[test]
+fn test_origins_follow_instructions() {
+    use rvr_ir::{BlockIR, InstrIR, Stmt, Terminator};
+
+    let config = EmitConfig::<Rv64>::default();
+    let mut emitter = CEmitter::new(config, EmitInputs::new(0x1000, 0x1008));
+    let mut block = BlockIR::new(0x1000);
+    block.push(InstrIR::new(
+        0x1000,
+        4,
+        0,
+        0,
+        vec![Stmt::write_reg(10, Expr::imm(1))],
+        Terminator::default(),
+    ));
+    block.push(InstrIR::new(
+        0x1004,
+        4,
+        0,
+        0,
+        vec![Stmt::write_reg(11, Expr::imm(2))],
+        Terminator::exit(Expr::reg(10)),
+    ));
+    emitter.render_block(&block);
+
+    let origins = emitter.origins();
+    assert_eq!(
+        origins.iter().map(|&(_, pc)| pc).collect::<Vec<_>>(),
+        [Some(0x1000), Some(0x1004), None]
+    );
+    let out = emitter.output();
+    let first = &out[origins[0].0..origins[1].0];
+    assert!(first.contains("a0 = 0x1ULL;") && !first.contains("a1"));
+    assert!(out[origins[1].0..origins[2].0].contains("a1 = 0x2ULL;"));
+    assert_eq!(&out[origins[2].0..], "}\n\n");
+
+    emitter.reset();
+    assert!(emitter.origins().is_empty());
+}
+
+#[test]
+fn test_guest_pc_map_line_directives() {
+    use crate::c::GuestPcLines;
+    use rvr_ir::{BlockIR, InstrIR, Stmt, Terminator};
+
+    let config = EmitConfig::<Rv64>::default().with_guest_pc_map(true);
+    let mut block = BlockIR::new(0x1000);
+    block.push(InstrIR::new(
+        0x1000,
+        4,
+        0,
+        0,
+        vec![Stmt::write_reg(10, Expr::imm(1))],
+        Terminator::default(),
+    ));
+    block.push(InstrIR::new(
+        0x1004,
+        4,
+        0,
+        0,
+        Vec::new(),
+        Terminator::exit(Expr::reg(10)),
+    ));
+    let mut inputs = EmitInputs::new(0x1000, 0x1008);
+    inputs.guest_pc_lines = std::sync::Arc::new(GuestPcLines::new(
+        [&block],
+        &std::collections::HashMap::new(),
+    ));
+    let mut emitter = CEmitter::new(config, inputs);
+    emitter.render_block(&block);
+
+    let out = emitter.output();
+    // The prologue and the first instruction map to 0x1000
+    assert!(out.contains("#line 1 \"guest_pc.map\"\n__attribute__"));
+    let first = out
+        .rfind("#line 1 \"guest_pc.map\"")
+        .expect("line of 0x1000");
+    let second = out
+        .find("#line 2 \"guest_pc.map\"")
+        .expect("line of 0x1004");
+    assert!(first < out.find("a0 = 0x1ULL;").unwrap());
+    assert!(out.find("a0 = 0x1ULL;").unwrap() < second);
+}
+
+#[test]
+fn test_cold_block_attribute() {
+    let config = EmitConfig::<Rv64>::default();
+    let mut inputs = EmitInputs::new(0x1000, 0x1008);
+    inputs.cold_blocks.insert(0x1004);
+    let mut emitter = CEmitter::new(config, inputs);
+
+    emitter.render_block_header(0x1000, 0x1004);
+    assert!(
+        emitter
+            .output()
+            .starts_with("__attribute__((preserve_none, nonnull(1))) void")
+    );
+    emitter.reset();
+    emitter.render_block_header(0x1004, 0x1008);
+    assert!(
+        emitter.output().starts_with(
+            "__attribute__((preserve_none, nonnull(1), cold)) void B_0000000000001004("
+        )
+    );
+}
+
+#[test]
+fn test_trap_records_status_and_pc() {
+    use rvr_ir::{BlockIR, InstrIR, Terminator};
+
+    let trap = |config: EmitConfig<Rv64>| {
+        let mut emitter = CEmitter::new(config, EmitInputs::new(0x1000, 0x1004));
+        let mut block = BlockIR::new(0x1000);
+        block.push(InstrIR::new(
+            0x1000,
+            4,
+            0,
+            0,
+            Vec::new(),
+            Terminator::trap("unimp"),
+        ));
+        emitter.render_block(&block);
+        emitter.take_output()
+    };
+
+    let config = EmitConfig::<Rv64>::default();
+    let out = trap(config.clone());
+    assert!(out.contains("state->has_exited = RV_TRAPPED;"), "{out}");
+    assert!(out.contains("state->exit_code = 1;"));
+    assert!(out.contains("state->pc = 0x0000000000001000ULL;"));
+
+    // Outlined, the block stores the pc and tail-calls the shared helper
+    let out = trap(config.with_outline_cold_paths(true));
+    assert!(
+        out.contains(
+            "state->pc = 0x0000000000001000ULL;\n    \
+             [[clang::musttail]] return rv_cold_illegal_instruction("
+        ),
+        "{out}"
+    );
+    assert!(!out.contains("has_exited"));
+}
+
+#[test]
+fn test_dynamic_jump_miss_records_site() {
+    use rvr_ir::{BlockIR, InstrIR, Stmt, Terminator};
+
+    let jump = |config: EmitConfig<Rv64>| {
+        let mut emitter = CEmitter::new(config, EmitInputs::new(0x1000, 0x1004));
+        let mut block = BlockIR::new(0x1000);
+        // jalr t0, 0(t0): the target comes from t0 through a temporary
+        block.push(InstrIR::new(
+            0x1000,
+            4,
+            0,
+            0,
+            vec![
+                Stmt::write_temp(0, Expr::reg(5)),
+                Stmt::write_reg(5, Expr::imm(0x1004)),
+            ],
+            Terminator::jump_dyn(Expr::temp(0)),
+        ));
+        emitter.render_block(&block);
+        emitter.take_output()
+    };
+
+    // Off by default: a plain table call, a miss enters `rv_trap`
+    let out = jump(EmitConfig::<Rv64>::default());
+    assert!(!out.contains("rv_trap"), "{out}");
+    assert!(!out.contains("0x0000000000001000ULL"), "{out}");
+
+    let config = EmitConfig::<Rv64>::default().with_report_jump_sites(true);
+    let out = jump(config.clone());
+    assert!(out.contains("if (unlikely(next == rv_trap)) {"), "{out}");
+    assert!(out.contains("state->exit_code = 5;"));
+    assert!(out.contains("state->pc = 0x0000000000001000ULL;"));
+    assert!(out.contains("state->exit_cause = RV_EXIT_UNRESOLVED_JUMP;"));
+    assert!(out.contains("return next("));
+
+    let out = jump(config.with_outline_cold_paths(true));
+    assert!(
+        out.contains("[[clang::musttail]] return rv_cold_unresolved_jump("),
+        "{out}"
+    );
+}
+
+#[test]
+fn test_store_checks_code_write() {
+    use rvr_ir::{BlockIR, InstrIR, Stmt, Terminator};
+
+    let store = |config: EmitConfig<Rv64>| {
+        let mut inputs = EmitInputs::new(0x1000, 0x1008);
+        inputs.code_ranges = vec![(0x1000, 0x1008)];
+        let mut emitter = CEmitter::new(config, inputs);
+        let mut block = BlockIR::new(0x1000);
+        block.push(InstrIR::new(
+            0x1000,
+            4,
+            0,
+            0,
+            Vec::new(),
+            Terminator::Fall { target: None },
+        ));
+        block.push(InstrIR::new(
+            0x1004,
+            4,
+            0,
+            0,
+            vec![Stmt::write_mem(Expr::reg(11), 8, Expr::imm(0), 1)],
+            Terminator::trap("end"),
+        ));
+        emitter.render_block(&block);
+        emitter.take_output()
+    };
+
+    let mut config = EmitConfig::<Rv64>::default();
+    config.hot_regs.clear();
+    let out = store(config.clone());
+    assert!(
+        out.contains("if (unlikely(rv_is_code_write(state->regs[11] + 8, 1))) {"),
+        "{out}"
+    );
+    // The store does not retire
+    assert!(out.contains(
+        "instret += 1;\n        state->pc = 0x0000000000001004ULL;\n        \
+         state->exit_info = state->regs[11] + 8;\n        \
+         state->csrs[CSR_MTVAL] = state->exit_info;\n        state->has_exited = RV_TRAPPED;"
+    ));
+    assert!(out.contains("state->exit_code = RV_CODE_WRITE_TRAP;"));
+    assert!(out.contains("state->exit_cause = RV_EXIT_CODE_WRITE;"));
+
+    let out = store(config.clone().with_outline_cold_paths(true));
+    assert!(out.contains(
+        "state->exit_info = state->regs[11] + 8;\n        \
+         [[clang::musttail]] return rv_cold_code_write("
+    ));
+
+    let out = store(config.with_detect_code_writes(false));
+    assert!(!out.contains("rv_is_code_write"));
+}
+
+#[test]
+fn test_store_checks_stack_guard() {
+    use crate::{AddrRange, AddressMode, MemoryLayout};
+    use rvr_ir::{BlockIR, InstrIR, Stmt, Terminator};
+
+    let store = |config: EmitConfig<Rv64>| {
+        let inputs = EmitInputs::new(0x1000, 0x1004).with_memory_layout(Some(MemoryLayout {
+            segments: Vec::new(),
+            heap: AddrRange::new(0x2000, 0x3000),
+            stack: AddrRange::new(0x5000, 0x6000),
+            mmap: None,
+            stack_guard: Some(AddrRange::new(0x4000, 0x5000)),
+        }));
+        let mut emitter = CEmitter::new(config, inputs);
+        let mut block = BlockIR::new(0x1000);
+        block.push(InstrIR::new(
+            0x1000,
+            4,
+            0,
+            0,
+            vec![Stmt::write_mem(Expr::reg(2), -8, Expr::imm(0), 8)],
+            Terminator::trap("end"),
+        ));
+        emitter.render_block(&block);
+        emitter.take_output()
+    };
+
+    let mut config = EmitConfig::<Rv64>::default();
+    config.hot_regs.clear();
+    let out = store(config.clone());
+    assert!(
+        out.contains("if (unlikely(rv_is_stack_overflow(state->regs[2] + -8, 8))) {"),
+        "{out}"
+    );
+    assert!(out.contains("state->exit_code = RV_STACK_OVERFLOW_TRAP;"));
+    let out = store(config.clone().with_outline_cold_paths(true));
+    assert!(out.contains("return rv_cold_stack_overflow("));
+
+    let out = store(config.with_address_mode(AddressMode::Unchecked));
+    assert!(!out.contains("rv_is_stack_overflow"));
+}
+
+#[test]
+fn test_portable_block_returns_next() {
+    use rvr_ir::{BlockIR, InstrIR, Terminator};
+
+    let mut config = EmitConfig::<Rv64>::default().with_c_dialect(crate::CDialect::Portable);
+    config.hot_regs = vec![1];
+    let mut inputs = EmitInputs::new(0x1000, 0x1008);
+    inputs.valid_addresses.insert(0x1004);
+    let mut emitter = CEmitter::new(config, inputs);
+    let mut block = BlockIR::new(0x1000);
+    block.push(InstrIR::new(
+        0x1000,
+        4,
+        0,
+        0,
+        Vec::new(),
+        Terminator::fall(0x1004),
+    ));
+    emitter.render_block(&block);
+
+    // Hot registers are loaded on entry and spilled before returning the
+    // next block to the trampoline
+    let out = emitter.output();
+    assert!(
+        out.contains(
+            "RvNext B_0000000000001000(RvState* restrict state, uint8_t* restrict memory) {"
+        ),
+        "{out}"
+    );
+    assert!(out.contains("uint64_t ra = state->regs[1];"));
+    assert!(out.contains("state->regs[1] = ra; return (RvNext){ B_0000000000001004 };"));
+    assert!(!out.contains("musttail"));
+    assert!(!out.contains("preserve_none"));
+}
+
+#[test]
+fn test_check_cancel_on_backward_edges() {
+    use rvr_ir::{BlockIR, InstrIR, Terminator};
+
+    let render = |config: EmitConfig<Rv64>, target: u64| {
+        let mut inputs = EmitInputs::new(0x1000, 0x1010);
+        inputs.valid_addresses.insert(0x1000);
+        inputs.valid_addresses.insert(0x100c);
+        let mut emitter = CEmitter::new(config, inputs);
+        let mut block = BlockIR::new(0x1004);
+        block.push(InstrIR::new(
+            0x1004,
+            4,
+            0,
+            0,
+            Vec::new(),
+            Terminator::jump(target),
+        ));
+        emitter.render_block(&block);
+        emitter.output().to_string()
+    };
+    let check = "if (unlikely(*(volatile uint8_t*)&state->cancel_requested)) {";
+    let config = EmitConfig::<Rv64>::default().with_check_cancel(true);
+
+    let out = render(config.clone(), 0x1000);
+    assert!(out.contains(check), "{out}");
+    assert!(out.contains("state->pc = 0x0000000000001000ULL;"));
+    assert!(!render(config, 0x100c).contains(check));
+    assert!(!render(EmitConfig::<Rv64>::default(), 0x1000).contains(check));
+}
+
+#[test]
+fn test_per_function_hot_regs_glue() {
+    use crate::InstretMode;
+    use rvr_ir::{BlockIR, InstrIR, Stmt, Terminator};
+
+    let mut config = EmitConfig::<Rv64>::default();
+    config.instret_mode = InstretMode::Off;
+    config.hot_regs = vec![1, 2, 10];
+    let mut inputs = EmitInputs::new(0x1000, 0x2004);
+    inputs.valid_addresses.extend([0x1000, 0x2000]);
+    inputs
+        .block_to_function
+        .extend([(0x1000, 0x1000), (0x2000, 0x2000)]);
+    inputs.function_hot_regs.insert(0x1000, vec![1, 5, 10]);
+    let render = |pc: u64, stmts: Vec<Stmt<Rv64>>, terminator: Terminator<Rv64>| {
+        let mut emitter = CEmitter::new(config.clone(), inputs.clone());
+        let mut block = BlockIR::new(pc);
+        block.push(InstrIR::new(pc, 4, 0, 0, stmts, terminator));
+        emitter.render_block(&block);
+        emitter.output().to_string()
+    };
+
+    // The body takes t0 in sp's slot and spills it leaving the function
+    let out = render(
+        0x1000,
+        vec![Stmt::write_reg(5, Expr::imm(1))],
+        Terminator::jump(0x2000),
+    );
+    assert!(out.contains(
+        "void B_0000000000001000_hot(RvState* restrict state, uint8_t* restrict memory, \
+         uint64_t ra, uint64_t t0, uint64_t a0) {"
+    ));
+    assert!(out.contains("t0 = 0x1ULL;"));
+    assert!(out.contains(
+        "state->regs[5] = t0; [[clang::musttail]] return \
+         B_0000000000002000(state, memory, ra, state->regs[2], a0);"
+    ));
+    // Dispatch and other functions enter through B_<pc>
+    assert!(out.contains(
+        "void B_0000000000001000(RvState* restrict state, uint8_t* restrict memory, \
+         uint64_t ra, uint64_t sp, uint64_t a0) {\n    state->regs[2] = sp; \
+         [[clang::musttail]] return B_0000000000001000_hot(state, memory, ra, state->regs[5], a0);"
+    ));
+
+    // Transfers into the function go straight to the body; dynamic jumps
+    // leave it with the global registers
+    let out = render(0x2000, Vec::new(), Terminator::jump(0x1000));
+    assert!(out.contains(
+        "state->regs[2] = sp; [[clang::musttail]] return \
+         B_0000000000001000_hot(state, memory, ra, state->regs[5], a0);"
+    ));
+    assert!(!out.contains("B_0000000000002000_hot"));
+    let out = render(0x1000, Vec::new(), Terminator::jump_dyn(Expr::reg(1)));
+    assert!(
+        out.contains("state->regs[5] = t0; [[clang::musttail]] return "),
+        "{out}"
+    );
+    assert!(
+        out.contains("(state, memory, ra, state->regs[2], a0);"),
+        "{out}"
+    );
+}
+
+#[test]
+fn test_dispatch_prefetch() {
+    use rvr_ir::{BlockIR, InstrIR, Stmt, Terminator};
+
+    let render = |config: EmitConfig<Rv64>| {
+        let mut emitter = CEmitter::new(config, EmitInputs::new(0x1000, 0x1014));
+        let mut block = BlockIR::new(0x1000);
+        let addi = |rd: u8| Stmt::write_reg(rd, Expr::add(Expr::reg(rd), Expr::imm(1)));
+        // a1 is final after the first instruction, three ahead of the jump
+        for (pc, rd) in [(0x1000, 11), (0x1004, 10), (0x1008, 10), (0x100c, 10)] {
+            block.push(InstrIR::new(
+                pc,
+                4,
+                0,
+                0,
+                vec![addi(rd)],
+                Terminator::fall(pc + 4),
+            ));
+        }
+        block.push(InstrIR::new(
+            0x1010,
+            4,
+            0,
+            0,
+            Vec::new(),
+            Terminator::jump_dyn(Expr::reg(11)),
+        ));
+        emitter.render_block(&block);
+        emitter.take_output()
+    };
+
+    let mut config = EmitConfig::<Rv64>::default();
+    config.hot_regs.clear();
+    let prefetch = "__builtin_prefetch(&dispatch_table[dispatch_index(state->regs[11])]);";
+    let cases = [
+        (config.clone(), false),
+        (config.clone().with_prefetch_dispatch(true), true),
+        (
+            config
+                .clone()
+                .with_prefetch_dispatch(true)
+                .with_dispatch_mode(crate::DispatchMode::PerFunction),
+            false,
+        ),
+    ];
+    for (config, expected) in cases {
+        let out = render(config);
+        assert_eq!(out.contains(prefetch), expected, "{out}");
+        if expected {
+            let at = out.find(prefetch).unwrap();
+            assert!(out[..at].contains("state->regs[11] = "), "{out}");
+            assert!(!out[..at].contains("state->regs[10] = "), "{out}");
+        }
+    }
+}
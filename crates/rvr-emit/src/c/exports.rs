@@ -0,0 +1,430 @@
+//! Export header generation for export-functions mode.
+//!
+//! Generates `exports.h` with one `rv_call_<symbol>` wrapper per exported guest
+//! function, so C host code can call guest functions without the Rust runner.
+//! A wrapper sets the argument registers, points `ra` at the return trampoline
+//! (`RV_CALL_RETURN_PC`, see dispatch.c), runs the function and returns `a0`.
+
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+use rvr_ir::Xlen;
+use rvr_isa::{REG_A0, REG_RA};
+
+use super::config::CDialect;
+use super::namespace::global_symbol;
+use super::signature::reg_type;
+
+/// File name of the export header.
+pub const EXPORTS_HEADER: &str = "exports.h";
+
+/// Integer argument registers (`a0`-`a7`).
+pub const MAX_CALL_ARGS: usize = 8;
+
+/// A C wrapper for one exported function symbol.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExportWrapper {
+    /// Wrapper suffix: `rv_call_<name>`.
+    pub name: String,
+    /// Original symbol name.
+    pub symbol: String,
+    /// Function address.
+    pub pc: u64,
+    /// Integer argument registers, if the symbol encodes its parameter
+    /// types.
+    pub arg_count: Option<usize>,
+}
+
+/// Replace characters that are not valid in a C identifier with `_`.
+#[must_use]
+pub fn sanitize_c_identifier(symbol: &str) -> String {
+    let mut name: String = symbol
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if name.is_empty() {
+        name.push('_');
+    }
+    name
+}
+
+/// Number of integer argument registers an Itanium-mangled (C++) symbol
+/// takes on `X`.
+///
+/// C and Rust symbols do not encode parameter types and return `None`.
+/// Floating-point and variadic parameters are not counted. Constructors,
+/// destructors and cv- or ref-qualified member functions count `this`;
+/// other member functions mangle like namespace-scope functions, so theirs
+/// is not counted. Integers and `long double` twice XLEN wide count a
+/// register pair. A class type counts one register, as the mangling does
+/// not give its size.
+#[must_use]
+pub fn mangled_arg_count<X: Xlen>(symbol: &str) -> Option<usize> {
+    if !symbol.starts_with("_Z") {
+        return None;
+    }
+    let demangled = cpp_demangle::Symbol::new(symbol).ok()?.demangle().ok()?;
+    let (name, params) = split_signature(&demangled)?;
+    let this = usize::from(takes_this(symbol, name));
+    Some(
+        this + split_top_level(params)
+            .into_iter()
+            .map(arg_regs::<X>)
+            .sum::<usize>(),
+    )
+}
+
+/// Integer argument registers a parameter of type `ty` takes on `X`.
+fn arg_regs<X: Xlen>(ty: &str) -> usize {
+    match ty {
+        "" | "void" | "..." | "float" | "double" => 0,
+        // 2 * XLEN wide; wider values are passed by reference
+        "__int128" | "unsigned __int128" | "long double" if X::VALUE == 64 => 2,
+        "long long" | "unsigned long long" if X::VALUE == 32 => 2,
+        _ => 1,
+    }
+}
+
+/// True if the function is certainly a non-static member: cv- or
+/// ref-qualified (`_ZN` followed by a qualifier), a constructor or a
+/// destructor.
+fn takes_this(symbol: &str, name: &str) -> bool {
+    let qualified = symbol
+        .strip_prefix("_ZN")
+        .and_then(|nested| nested.chars().next())
+        .is_some_and(|c| "rVKRO".contains(c));
+    let scopes = split_scope(name);
+    let structor = match scopes.as_slice() {
+        [.., class, last] => {
+            let base = |scope: &str| scope.split('<').next().unwrap_or_default().to_owned();
+            last.starts_with('~') || base(class) == base(last)
+        }
+        _ => false,
+    };
+    qualified || structor
+}
+
+/// The name before and the contents of the last top-level parenthesized
+/// group (the parameters).
+fn split_signature(demangled: &str) -> Option<(&str, &str)> {
+    let mut depth = 0usize;
+    let mut open = 0;
+    let mut last = None;
+    for (i, c) in demangled.char_indices() {
+        match c {
+            '(' => {
+                if depth == 0 {
+                    open = i + 1;
+                }
+                depth += 1;
+            }
+            ')' => {
+                depth = depth.checked_sub(1)?;
+                if depth == 0 {
+                    last = Some((&demangled[..open - 1], &demangled[open..i]));
+                }
+            }
+            _ => {}
+        }
+    }
+    if depth == 0 { last } else { None }
+}
+
+/// Split a qualified name on `::` outside any brackets.
+fn split_scope(name: &str) -> Vec<&str> {
+    let mut scopes = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    let bytes = name.as_bytes();
+    for (i, &b) in bytes.iter().enumerate() {
+        match b {
+            b'(' | b'<' => depth += 1,
+            b')' | b'>' => depth = depth.saturating_sub(1),
+            b':' if depth == 0 && i >= start && bytes.get(i + 1) == Some(&b':') => {
+                scopes.push(&name[start..i]);
+                start = i + 2;
+            }
+            _ => {}
+        }
+    }
+    scopes.push(&name[start..]);
+    scopes
+}
+
+/// Split on commas outside any brackets, trimming each part.
+fn split_top_level(list: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in list.char_indices() {
+        match c {
+            '(' | '<' | '[' | '{' => depth += 1,
+            ')' | '>' | ']' | '}' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                parts.push(list[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(list[start..].trim());
+    parts
+}
+
+/// Build wrappers for `(symbol, pc)` exports with unique C names.
+///
+/// Exports are ordered by symbol then PC. When several symbols sanitize to
+/// the same name, the first keeps it and the rest get `_2`, `_3`, ... suffixes
+/// (skipping names already in use).
+#[must_use]
+pub fn export_wrappers<X: Xlen>(exports: &[(String, u64)]) -> Vec<ExportWrapper> {
+    let mut exports: Vec<_> = exports.iter().collect();
+    exports.sort();
+    exports.dedup();
+
+    let bases: Vec<String> = exports
+        .iter()
+        .map(|(symbol, _)| sanitize_c_identifier(symbol))
+        .collect();
+    let mut taken: HashSet<String> = bases.iter().cloned().collect();
+    let mut next_suffix: HashMap<&str, usize> = HashMap::new();
+
+    exports
+        .iter()
+        .zip(&bases)
+        .map(|((symbol, pc), base)| {
+            let name = match next_suffix.entry(base.as_str()) {
+                Entry::Vacant(entry) => {
+                    entry.insert(2);
+                    base.clone()
+                }
+                Entry::Occupied(mut entry) => loop {
+                    let suffix = entry.get_mut();
+                    let candidate = format!("{base}_{suffix}");
+                    *suffix += 1;
+                    if taken.insert(candidate.clone()) {
+                        break candidate;
+                    }
+                },
+            };
+            ExportWrapper {
+                name,
+                symbol: symbol.clone(),
+                pc: *pc,
+                arg_count: mangled_arg_count::<X>(symbol),
+            }
+        })
+        .collect()
+}
+
+/// Generate `exports.h` for the given `(symbol, pc)` exports, calling the
+/// runtime under its symbol `prefix`.
+#[must_use]
+pub fn gen_exports_header<X: Xlen>(
+    base_name: &str,
+    prefix: &str,
+    num_regs: usize,
+    dialect: CDialect,
+    exports: &[(String, u64)],
+) -> String {
+    let rtype = reg_type::<X>();
+    let return_pc = global_symbol(prefix, "RV_CALL_RETURN_PC");
+    let execute_from = global_symbol(prefix, "rv_execute_from");
+    let a0 = usize::from(REG_A0);
+    let ra = REG_RA;
+    // RV32E/RV64E only have a0-a5
+    let max_args = MAX_CALL_ARGS.min(num_regs.saturating_sub(a0));
+    let max_args_constant =
+        dialect.constant("int", "RV_CALL_MAX_ARGS", &max_args.to_string(), true);
+
+    let mut s = format!(
+        r#"#pragma once
+/* Wrappers for calling exported guest functions from C.
+ *
+ * rv_call_<symbol>() passes its arguments in a0-a{last}, returns to the
+ * trampoline at RV_CALL_RETURN_PC and yields a0. Argument registers and ra
+ * are restored afterwards. If the guest exits or traps instead of returning,
+ * state->pc is not RV_CALL_RETURN_PC.
+ */
+#include "{base_name}.h"
+
+{max_args_constant}
+
+/* Return address installed by the wrappers (defined in {base_name}_dispatch.c) */
+extern const {rtype} {return_pc};
+
+static inline {rtype} rv_call_pc(RvState* state, {rtype} pc, const {rtype} args[RV_CALL_MAX_ARGS]) {{
+    {rtype} saved[RV_CALL_MAX_ARGS];
+    {rtype} saved_ra = state->regs[{ra}];
+    for (int i = 0; i < RV_CALL_MAX_ARGS; i++) {{
+        saved[i] = state->regs[{a0} + i];
+        state->regs[{a0} + i] = args[i];
+    }}
+    state->regs[{ra}] = ({rtype}){return_pc};
+    {execute_from}(state, pc);
+    {rtype} result = state->regs[{a0}];
+    for (int i = 0; i < RV_CALL_MAX_ARGS; i++) {{
+        state->regs[{a0} + i] = saved[i];
+    }}
+    state->regs[{ra}] = saved_ra;
+    return result;
+}}
+"#,
+        last = max_args.saturating_sub(1),
+    );
+
+    let params = (0..max_args).fold(String::new(), |mut params, i| {
+        write!(params, ", {rtype} a{i}").unwrap();
+        params
+    });
+    let args: Vec<String> = (0..max_args).map(|i| format!("a{i}")).collect();
+    let args = args.join(", ");
+    for wrapper in export_wrappers::<X>(exports) {
+        let symbol = wrapper.symbol.replace("*/", "* /");
+        s.push('\n');
+        writeln!(s, "/* {symbol} @ {:#x} */", wrapper.pc).unwrap();
+        if let Some(count) = wrapper.arg_count.filter(|&n| n > max_args) {
+            writeln!(
+                s,
+                "/* NOTE: takes {count} integer argument registers; only the first {max_args} are passed (the rest go on the guest stack) */"
+            )
+            .unwrap();
+        }
+        writeln!(
+            s,
+            r"static inline {rtype} rv_call_{name}(RvState* state{params}) {{
+    const {rtype} args[RV_CALL_MAX_ARGS] = {{{args}}};
+    return rv_call_pc(state, {pc:#x}ull, args);
+}}",
+            name = wrapper.name,
+            pc = wrapper.pc,
+        )
+        .unwrap();
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rvr_ir::{Rv32, Rv64};
+
+    fn exports(symbols: &[(&str, u64)]) -> Vec<(String, u64)> {
+        symbols.iter().map(|&(s, pc)| (s.to_string(), pc)).collect()
+    }
+
+    #[test]
+    fn test_sanitize_c_identifier() {
+        assert_eq!(sanitize_c_identifier("main"), "main");
+        assert_eq!(sanitize_c_identifier("foo.cold"), "foo_cold");
+        assert_eq!(sanitize_c_identifier("$x"), "_x");
+        assert_eq!(sanitize_c_identifier(""), "_");
+    }
+
+    #[test]
+    fn test_export_wrapper_collisions() {
+        let wrappers = export_wrappers::<Rv64>(&exports(&[
+            ("foo_2", 0x30),
+            ("foo.bar", 0x20),
+            ("foo$bar", 0x10),
+            ("foo_bar", 0x40),
+            ("foo$bar", 0x10),
+        ]));
+        let names: Vec<(&str, &str)> = wrappers
+            .iter()
+            .map(|w| (w.symbol.as_str(), w.name.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("foo$bar", "foo_bar"),
+                ("foo.bar", "foo_bar_2"),
+                ("foo_2", "foo_2"),
+                ("foo_bar", "foo_bar_3"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mangled_arg_count() {
+        assert_eq!(mangled_arg_count::<Rv64>("add"), None);
+        assert_eq!(
+            mangled_arg_count::<Rv64>("_ZN4core3fmt5write17h0123456789abcdefE"),
+            None
+        );
+        // add(int, int)
+        assert_eq!(mangled_arg_count::<Rv64>("_Z3addii"), Some(2));
+        // f()
+        assert_eq!(mangled_arg_count::<Rv64>("_Z1fv"), Some(0));
+        // f(int, double, long)
+        assert_eq!(mangled_arg_count::<Rv64>("_Z1fidl"), Some(2));
+        // f(std::pair<int, int>*, void (*)(int, int))
+        assert_eq!(mangled_arg_count::<Rv64>("_Z1fPSt4pairIiiEPFviiE"), Some(2));
+        // nine ints
+        assert_eq!(mangled_arg_count::<Rv64>("_Z4manyiiiiiiiii"), Some(9));
+    }
+
+    #[test]
+    fn test_mangled_arg_count_this() {
+        // Foo::get(int) const
+        assert_eq!(mangled_arg_count::<Rv64>("_ZNK3Foo3getEi"), Some(2));
+        // Foo::take(int) &&
+        assert_eq!(mangled_arg_count::<Rv64>("_ZNO3Foo4takeEi"), Some(2));
+        // Foo::Foo(int), Foo::~Foo()
+        assert_eq!(mangled_arg_count::<Rv64>("_ZN3FooC2Ei"), Some(2));
+        assert_eq!(mangled_arg_count::<Rv64>("_ZN3FooD2Ev"), Some(1));
+        // Box<int>::Box(int)
+        assert_eq!(mangled_arg_count::<Rv64>("_ZN3BoxIiEC2Ei"), Some(2));
+        // ns::f(int) mangles like an unqualified member function
+        assert_eq!(mangled_arg_count::<Rv64>("_ZN2ns1fEi"), Some(1));
+    }
+
+    #[test]
+    fn test_mangled_arg_count_register_pairs() {
+        // f(__int128, unsigned __int128, long double)
+        assert_eq!(mangled_arg_count::<Rv64>("_Z1fnoe"), Some(6));
+        assert_eq!(mangled_arg_count::<Rv32>("_Z1fnoe"), Some(3));
+        // f(long long, unsigned long long)
+        assert_eq!(mangled_arg_count::<Rv64>("_Z1fxy"), Some(2));
+        assert_eq!(mangled_arg_count::<Rv32>("_Z1fxy"), Some(4));
+    }
+
+    #[test]
+    fn test_gen_exports_header() {
+        let header = gen_exports_header::<Rv64>(
+            "rv",
+            "g_",
+            32,
+            CDialect::Clang,
+            &exports(&[("_Z4manyiiiiiiiii", 0x8000_0010), ("add", 0x8000_0000)]),
+        );
+
+        assert!(header.contains("#include \"rv.h\""));
+        assert!(header.contains("constexpr int RV_CALL_MAX_ARGS = 8;"));
+        assert!(header.contains("extern const uint64_t g_RV_CALL_RETURN_PC;\n"));
+        assert!(header.contains("    g_rv_execute_from(state, pc);\n"));
+        assert!(header.contains(
+            "static inline uint64_t rv_call_add(RvState* state, uint64_t a0, uint64_t a1, uint64_t a2, uint64_t a3, uint64_t a4, uint64_t a5, uint64_t a6, uint64_t a7) {"
+        ));
+        assert!(header.contains("return rv_call_pc(state, 0x80000000ull, args);"));
+        assert!(header.contains("/* NOTE: takes 9 integer argument registers"));
+        assert_eq!(header.matches("/* NOTE").count(), 1);
+    }
+
+    #[test]
+    fn test_gen_exports_header_rve() {
+        let header = gen_exports_header::<Rv32>(
+            "rv",
+            "",
+            16,
+            CDialect::Portable,
+            &exports(&[("add", 0x1000)]),
+        );
+
+        assert!(header.contains("enum { RV_CALL_MAX_ARGS = 6 };"));
+        assert!(header.contains("extern const uint32_t RV_CALL_RETURN_PC;\n"));
+        assert!(header.contains("uint32_t a5) {"));
+        assert!(!header.contains("a6"));
+    }
+}
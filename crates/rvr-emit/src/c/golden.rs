@@ -0,0 +1,380 @@
+//! Golden trace self-checks.
+//!
+//! Every `interval`-th block entry is a golden trace point: the block folds
+//! `x1..` into a rolling checksum (`rvr_trace_format::golden_mix`) and calls
+//! `rv_golden_point` with its PC. The golden tracer writes the points to a
+//! file; with [`EmitConfig::embedded_golden`](crate::EmitConfig::embedded_golden)
+//! the library carries that file's points (LZ4-compressed) and
+//! `rv_golden_point` compares against them instead, stopping with
+//! `RV_EXIT_GOLDEN_MISMATCH` at the first block that differs.
+//!
+//! Both builds share the sampling countdown in `RvState`, so the points
+//! line up as long as the two lift the program into the same blocks: check
+//! with the block shaping options the golden run was captured with.
+
+use std::fmt::Write;
+
+use rvr_trace_format::{
+    GOLDEN_CHECKSUM_PRIME, GOLDEN_CHECKSUM_ROTATE, GOLDEN_CHECKSUM_SEED, GOLDEN_TRACE_HEADER_SIZE,
+    GOLDEN_TRACE_MAGIC, GOLDEN_TRACE_RECORD_SIZE, GOLDEN_TRACE_VERSION,
+};
+
+use super::config::CDialect;
+use super::lz4::{LZ4_DECODER, lz4_compress};
+use super::namespace::global_symbol;
+use super::tracer::{TracerConfig, TracerKind};
+
+/// One golden trace point: a sampled block entry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GoldenPoint {
+    /// Block PC.
+    pub pc: u64,
+    /// Register checksum at block entry.
+    pub checksum: u64,
+}
+
+/// A golden trace as written by the golden tracer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GoldenTrace {
+    /// Blocks entered per trace point.
+    pub interval: u32,
+    /// Content hash of the ELF the trace was captured from.
+    pub elf_hash: u64,
+    /// Trace points in execution order.
+    pub points: Vec<GoldenPoint>,
+}
+
+impl GoldenTrace {
+    /// Parse a golden trace file.
+    ///
+    /// # Errors
+    /// Returns a description of the problem if `data` is not a golden trace
+    /// of this version, or has no trace points.
+    pub fn parse(data: &[u8]) -> Result<Self, String> {
+        let (header, records) = data
+            .split_at_checked(GOLDEN_TRACE_HEADER_SIZE)
+            .ok_or("truncated header")?;
+        if header[..8] != GOLDEN_TRACE_MAGIC {
+            return Err("not a golden trace (bad magic)".to_string());
+        }
+        let version = le_u32(&header[8..12]);
+        if version != GOLDEN_TRACE_VERSION {
+            return Err(format!(
+                "unsupported version {version} (expected {GOLDEN_TRACE_VERSION})"
+            ));
+        }
+        let interval = le_u32(&header[12..16]);
+        if interval == 0 {
+            return Err("block sample interval is 0".to_string());
+        }
+        if records.len() % GOLDEN_TRACE_RECORD_SIZE != 0 {
+            return Err("truncated trace point".to_string());
+        }
+        if records.is_empty() {
+            return Err("no trace points".to_string());
+        }
+        let points = records
+            .chunks_exact(GOLDEN_TRACE_RECORD_SIZE)
+            .map(|record| GoldenPoint {
+                pc: le_u64(&record[..8]),
+                checksum: le_u64(&record[8..]),
+            })
+            .collect();
+        Ok(Self {
+            interval,
+            elf_hash: le_u64(&header[16..24]),
+            points,
+        })
+    }
+
+    /// Encode the trace in the golden tracer's file format.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(
+            GOLDEN_TRACE_HEADER_SIZE + self.points.len() * GOLDEN_TRACE_RECORD_SIZE,
+        );
+        out.extend_from_slice(&GOLDEN_TRACE_MAGIC);
+        out.extend_from_slice(&GOLDEN_TRACE_VERSION.to_le_bytes());
+        out.extend_from_slice(&self.interval.to_le_bytes());
+        out.extend_from_slice(&self.elf_hash.to_le_bytes());
+        out.extend_from_slice(&self.records());
+        out
+    }
+
+    /// The trace point records, without the header.
+    fn records(&self) -> Vec<u8> {
+        self.points
+            .iter()
+            .flat_map(|p| [p.pc.to_le_bytes(), p.checksum.to_le_bytes()])
+            .flatten()
+            .collect()
+    }
+}
+
+const fn le_u32(bytes: &[u8]) -> u32 {
+    let mut word = [0; 4];
+    word.copy_from_slice(bytes);
+    u32::from_le_bytes(word)
+}
+
+const fn le_u64(bytes: &[u8]) -> u64 {
+    let mut word = [0; 8];
+    word.copy_from_slice(bytes);
+    u64::from_le_bytes(word)
+}
+
+/// What blocks do at golden trace points.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GoldenMode {
+    /// Hand the point to the golden tracer, every `interval`-th block.
+    Capture(u32),
+    /// Compare against the embedded trace, every `interval`-th block.
+    Check(u32),
+}
+
+impl GoldenMode {
+    /// Mode of a build with `tracer` and the embedded trace `golden`: an
+    /// embedded trace is checked (whatever the tracer), the golden tracer
+    /// captures, and other builds have no trace points.
+    #[must_use]
+    pub fn new(tracer: &TracerConfig, golden: Option<&GoldenTrace>) -> Option<Self> {
+        golden.map_or_else(
+            || {
+                matches!(tracer.builtin_kind(), Some(TracerKind::Golden))
+                    .then(|| Self::Capture(tracer.sample_interval.max(1)))
+            },
+            |trace| Some(Self::Check(trace.interval)),
+        )
+    }
+
+    /// Blocks entered per trace point.
+    #[must_use]
+    pub const fn interval(self) -> u32 {
+        match self {
+            Self::Capture(interval) | Self::Check(interval) => interval,
+        }
+    }
+}
+
+/// Main header helpers for golden trace points: the checksum mix, the
+/// sampling countdown and `rv_golden_point`, linked under `prefix` in check
+/// mode.
+#[must_use]
+pub fn gen_golden_helpers(mode: GoldenMode, dialect: CDialect, prefix: &str) -> String {
+    let constants = [
+        (
+            "uint64_t",
+            "RV_GOLDEN_INTERVAL",
+            format!("{}ull", mode.interval()),
+        ),
+        (
+            "uint64_t",
+            "RV_GOLDEN_SEED",
+            format!("{GOLDEN_CHECKSUM_SEED:#x}ull"),
+        ),
+        (
+            "uint64_t",
+            "RV_GOLDEN_PRIME",
+            format!("{GOLDEN_CHECKSUM_PRIME:#x}ull"),
+        ),
+    ]
+    .map(|(ty, name, value)| dialect.constant(ty, name, &value, false))
+    .join("\n");
+    let name = global_symbol(prefix, "rv_golden_point");
+    let point = match mode {
+        GoldenMode::Capture(_) => format!(
+            r"/* Golden trace point: record it (never stops) */
+static inline bool {name}(RvState* restrict state, uint64_t pc, uint64_t sum) {{
+    trace_golden(&state->tracer, pc, sum);
+    return false;
+}}"
+        ),
+        GoldenMode::Check(_) => format!(
+            r"/* Golden trace point: compare with the embedded trace; true (and the
+ * guest stopped with RV_EXIT_GOLDEN_MISMATCH) if it differs */
+bool {name}(RvState* restrict state, uint64_t pc, uint64_t sum);"
+        ),
+    };
+    let rotate = GOLDEN_CHECKSUM_ROTATE;
+    let unrotate = 64 - rotate;
+    format!(
+        r"/* Golden trace points: register checksum every RV_GOLDEN_INTERVAL-th block */
+{constants}
+
+static inline uint64_t rv_golden_mix(uint64_t sum, uint64_t value) {{
+    uint64_t x = (sum ^ value) * RV_GOLDEN_PRIME;
+    return (x << {rotate}) | (x >> {unrotate});
+}}
+
+/* Count down a block entry; true at a trace point */
+static inline bool rv_golden_due(RvState* restrict state) {{
+    if (likely(state->golden_countdown != 0)) {{
+        state->golden_countdown--;
+        return false;
+    }}
+    state->golden_countdown = RV_GOLDEN_INTERVAL - 1;
+    return true;
+}}
+
+{point}
+
+"
+    )
+}
+
+/// `<base>_golden.c`: the embedded trace and `rv_golden_point` (check mode).
+#[must_use]
+pub fn gen_golden_source(
+    base_name: &str,
+    prefix: &str,
+    trace: &GoldenTrace,
+    dialect: CDialect,
+) -> String {
+    let packed = lz4_compress(&trace.records());
+    let count = dialect.constant(
+        "uint64_t",
+        "RV_GOLDEN_COUNT",
+        &format!("{}ull", trace.points.len()),
+        false,
+    );
+    let mut s = format!(
+        r#"/* Embedded golden trace: {} points, every {} blocks, ELF hash {:#018x} */
+#include "{base_name}.h"
+#include <stddef.h>
+#include <string.h>
+
+{LZ4_DECODER}{count}
+
+/* (pc, checksum) records, LZ4 to {} bytes */
+static const uint8_t rv_golden_packed[] = {{
+"#,
+        trace.points.len(),
+        trace.interval,
+        trace.elf_hash,
+        packed.len(),
+    );
+    for chunk in packed.chunks(16) {
+        let bytes: Vec<String> = chunk.iter().map(|b| format!("{b:#04x}")).collect();
+        writeln!(s, "    {},", bytes.join(", ")).unwrap();
+    }
+    writeln!(
+        s,
+        "}};\n\nstatic uint64_t rv_golden_records[{}];",
+        2 * trace.points.len()
+    )
+    .unwrap();
+    write!(
+        s,
+        r"
+
+__attribute__((constructor)) static void rv_golden_unpack(void) {{
+    lz4_decode((uint8_t*)rv_golden_records, rv_golden_packed, sizeof(rv_golden_packed));
+}}
+
+bool {}(RvState* restrict state, uint64_t pc, uint64_t sum) {{
+    uint64_t pos = state->golden_pos++;
+    if (likely(pos < RV_GOLDEN_COUNT && rv_golden_records[2 * pos] == pc
+               && rv_golden_records[2 * pos + 1] == sum)) {{
+        return false;
+    }}
+    state->exit_code = 1;
+    state->exit_cause = RV_EXIT_GOLDEN_MISMATCH;
+    state->exit_info = pos;
+    state->has_exited = true;
+    return true;
+}}
+",
+        global_symbol(prefix, "rv_golden_point")
+    )
+    .unwrap();
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trace() -> GoldenTrace {
+        GoldenTrace {
+            interval: 4,
+            elf_hash: 0xfeed,
+            points: vec![
+                GoldenPoint {
+                    pc: 0x1000,
+                    checksum: 1,
+                },
+                GoldenPoint {
+                    pc: 0x1040,
+                    checksum: 2,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_parse_round_trips() {
+        let trace = trace();
+        assert_eq!(GoldenTrace::parse(&trace.to_bytes()), Ok(trace));
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed() {
+        let bytes = trace().to_bytes();
+        let mut bad_magic = bytes.clone();
+        bad_magic[0] = b'X';
+        let mut zero_interval = bytes.clone();
+        zero_interval[12..16].fill(0);
+        let cases: [(&[u8], &str); 5] = [
+            (&bytes[..10], "truncated header"),
+            (&bad_magic, "bad magic"),
+            (&zero_interval, "interval is 0"),
+            (&bytes[..bytes.len() - 1], "truncated trace point"),
+            (&bytes[..GOLDEN_TRACE_HEADER_SIZE], "no trace points"),
+        ];
+        for (data, expected) in cases {
+            let err = GoldenTrace::parse(data).unwrap_err();
+            assert!(err.contains(expected), "{expected}: got {err}");
+        }
+    }
+
+    #[test]
+    fn test_mode() {
+        let golden = TracerConfig::golden().with_sample_interval(8);
+        let cases = [
+            (TracerConfig::none(), None, None),
+            (golden.clone(), None, Some(GoldenMode::Capture(8))),
+            (
+                TracerConfig::none(),
+                Some(trace()),
+                Some(GoldenMode::Check(4)),
+            ),
+            (golden, Some(trace()), Some(GoldenMode::Check(4))),
+        ];
+        for (tracer, embedded, expected) in cases {
+            assert_eq!(GoldenMode::new(&tracer, embedded.as_ref()), expected);
+        }
+    }
+
+    #[test]
+    fn test_helpers_by_mode() {
+        let capture = gen_golden_helpers(GoldenMode::Capture(8), CDialect::Portable, "");
+        assert!(capture.contains("static const uint64_t RV_GOLDEN_INTERVAL = 8ull;"));
+        assert!(capture.contains("trace_golden(&state->tracer, pc, sum);"));
+        let check = gen_golden_helpers(GoldenMode::Check(4), CDialect::Portable, "p_");
+        assert!(check.contains("RV_GOLDEN_INTERVAL = 4ull;"));
+        assert!(check.contains(
+            "bool p_rv_golden_point(RvState* restrict state, uint64_t pc, uint64_t sum);"
+        ));
+        assert!(!check.contains("trace_golden"));
+    }
+
+    #[test]
+    fn test_source_embeds_records() {
+        let src = gen_golden_source("prog", "p_", &trace(), CDialect::Portable);
+        assert!(src.contains("#include \"prog.h\""));
+        assert!(src.contains("static const uint64_t RV_GOLDEN_COUNT = 2ull;"));
+        assert!(src.contains("lz4_decode((uint8_t*)rv_golden_records"));
+        assert!(src.contains("state->exit_cause = RV_EXIT_GOLDEN_MISMATCH;"));
+        assert!(src.contains("bool p_rv_golden_point(RvState* restrict state"));
+    }
+}
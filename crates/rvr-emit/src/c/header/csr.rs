@@ -1,7 +1,7 @@
-use super::{HeaderConfig, STATE_FIXED_REF, Xlen, reg_type};
+use super::{HeaderConfig, STATE_FIXED_REF, Write, Xlen, reg_type};
 
 const CSR_HEADER_PREFIX: &str = r"/* CSR access */
-__attribute__((hot, pure, ";
+__attribute__((hot, ";
 const CSR_HEADER_MID: &str = r"always_inline))
 static inline ";
 const CSR_HEADER_SWITCH: &str = r" rd_csr(";
@@ -10,6 +10,7 @@ const CSR_HEADER_BODY_PREFIX: &str = r") {
     switch (csr) {
         case CSR_MCYCLE:
         case CSR_CYCLE:
+        case CSR_TIME:
         case CSR_MINSTRET:
         case CSR_INSTRET:
             return (";
@@ -17,12 +18,14 @@ const CSR_HEADER_BODY_MID: &str = r")(";
 const CSR_HEADER_BODY_MID2: &str = r");
         case CSR_MCYCLEH:
         case CSR_CYCLEH:
+        case CSR_TIMEH:
         case CSR_MINSTRETH:
         case CSR_INSTRETH:
             return (";
 const CSR_HEADER_BODY_MID3: &str = r")(";
 const CSR_HEADER_BODY_SUFFIX: &str = r" >> 32);
-        default:
+";
+const CSR_HEADER_BODY_DEFAULT: &str = r"        default:
             return ";
 const CSR_HEADER_BODY_END: &str = r"->csrs[csr];
     }
@@ -40,10 +43,13 @@ const CSR_HEADER_WRITE_BODY: &str = r" val) {
         case CSR_MINSTRETH:
         case CSR_CYCLE:
         case CSR_CYCLEH:
+        case CSR_TIME:
+        case CSR_TIMEH:
         case CSR_INSTRET:
         case CSR_INSTRETH:
             return;
-        default:
+";
+const CSR_HEADER_WRITE_DEFAULT: &str = r"        default:
             ";
 const CSR_HEADER_WRITE_SUFFIX: &str = r"->csrs[csr] = val;
     }
@@ -157,16 +163,20 @@ static inline uint64_t rv_remuw(uint32_t a, uint32_t b) {
 
 struct CsrHeaderArgs<'a> {
     rtype: &'a str,
+    pure: &'a str,
     instret_param: &'a str,
     state_param_rd: &'a str,
     state_param_wr: &'a str,
     state_ref: &'a str,
     nonnull: &'a str,
     instret_val: &'a str,
+    hook_csrs: &'a [u16],
+    vector: bool,
 }
 
 fn push_csr_header(out: &mut String, args: &CsrHeaderArgs<'_>) {
     out.push_str(CSR_HEADER_PREFIX);
+    out.push_str(args.pure);
     out.push_str(args.nonnull);
     out.push_str(CSR_HEADER_MID);
     out.push_str(args.rtype);
@@ -183,6 +193,19 @@ fn push_csr_header(out: &mut String, args: &CsrHeaderArgs<'_>) {
     out.push_str(CSR_HEADER_BODY_MID3);
     out.push_str(args.instret_val);
     out.push_str(CSR_HEADER_BODY_SUFFIX);
+    if args.vector {
+        out.push_str("        case CSR_VLENB:\n            return RV_VLENB;\n");
+    }
+    if !args.hook_csrs.is_empty() {
+        push_hook_cases(out, args.hook_csrs);
+        writeln!(
+            out,
+            "            return ({})rv_csr_read({}, csr);",
+            args.rtype, args.state_ref
+        )
+        .expect("formatting rd_csr hook case");
+    }
+    out.push_str(CSR_HEADER_BODY_DEFAULT);
     out.push_str(args.state_ref);
     out.push_str(CSR_HEADER_BODY_END);
     out.push_str(args.nonnull);
@@ -191,10 +214,50 @@ fn push_csr_header(out: &mut String, args: &CsrHeaderArgs<'_>) {
     out.push_str(CSR_HEADER_WRITE_MID);
     out.push_str(args.rtype);
     out.push_str(CSR_HEADER_WRITE_BODY);
+    if !args.hook_csrs.is_empty() {
+        push_hook_cases(out, args.hook_csrs);
+        writeln!(
+            out,
+            "            rv_csr_write({}, csr, val);\n            return;",
+            args.state_ref
+        )
+        .expect("formatting wr_csr hook case");
+    }
+    out.push_str(CSR_HEADER_WRITE_DEFAULT);
     out.push_str(args.state_ref);
     out.push_str(CSR_HEADER_WRITE_SUFFIX);
 }
 
+fn push_hook_cases(out: &mut String, hook_csrs: &[u16]) {
+    for csr in hook_csrs {
+        writeln!(out, "        case 0x{csr:x}:").expect("formatting hook CSR case");
+    }
+}
+
+/// `rv_csr_read`/`rv_csr_write`: custom hook CSRs go to the host's hooks in
+/// `RvIo`, or to their `csrs` slot until the host installs them.
+fn gen_csr_hooks(rtype: &str) -> String {
+    format!(
+        r"/* Custom hook CSRs: forwarded to the host */
+static inline uint64_t rv_csr_read(const RvState* restrict s, uint32_t csr) {{
+    const RvIo* io = s->io;
+    if (io && io->csr_read) return io->csr_read(io->csr_ctx, csr);
+    return s->csrs[csr];
+}}
+
+static inline void rv_csr_write(RvState* restrict s, uint32_t csr, {rtype} val) {{
+    RvIo* io = s->io;
+    if (io && io->csr_write) {{
+        io->csr_write(io->csr_ctx, csr, val);
+        return;
+    }}
+    s->csrs[csr] = val;
+}}
+
+"
+    )
+}
+
 pub(super) fn gen_csr_functions<X: Xlen>(cfg: &HeaderConfig<X>) -> String {
     let rtype = reg_type::<X>();
     let instret_param = if cfg.instret_mode.counts() {
@@ -227,14 +290,25 @@ pub(super) fn gen_csr_functions<X: Xlen>(cfg: &HeaderConfig<X>) -> String {
         };
 
     let mut out = String::new();
+    if !cfg.hook_csrs.is_empty() {
+        out.push_str(&gen_csr_hooks(rtype));
+    }
     let args = CsrHeaderArgs {
         rtype,
+        // Hook CSR reads call out to the host
+        pure: if cfg.hook_csrs.is_empty() {
+            "pure, "
+        } else {
+            ""
+        },
         instret_param,
         state_param_rd,
         state_param_wr,
         state_ref,
         nonnull,
         instret_val: &instret_val,
+        hook_csrs: &cfg.hook_csrs,
+        vector: cfg.vlen.is_some(),
     };
     push_csr_header(&mut out, &args);
     out.push_str(CSR_DIV_HELPERS);
@@ -243,3 +317,73 @@ pub(super) fn gen_csr_functions<X: Xlen>(cfg: &HeaderConfig<X>) -> String {
     out.push_str(CSR_WORD_DIV_HELPERS);
     out
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::c::{HeaderConfig, gen_header};
+    use crate::{CustomCsr, EmitConfig, EmitInputs};
+    use rvr_ir::Rv64;
+
+    fn header(config: &EmitConfig<Rv64>) -> String {
+        let inputs = EmitInputs::new(0x8000_0000, 0x8000_0008);
+        gen_header::<Rv64>(&HeaderConfig::new(
+            "test",
+            config,
+            &inputs,
+            vec![0x8000_0000],
+        ))
+    }
+
+    #[test]
+    fn test_hook_csrs_call_host() {
+        let config = EmitConfig::<Rv64>::standard().with_custom_csrs(vec![
+            CustomCsr::hook(0x8c1),
+            CustomCsr::storage(0x8c2),
+            CustomCsr::hook(0x8c0),
+            CustomCsr::hook(0x8c0),
+            // Counters keep their built-in behavior
+            CustomCsr::hook(0xc00),
+        ]);
+        let header = header(&config);
+        assert!(header.contains("static inline uint64_t rv_csr_read("));
+        assert!(header.contains("        case 0x8c0:\n        case 0x8c1:\n            return (uint64_t)rv_csr_read(s, csr);"));
+        assert!(header.contains(
+            "        case 0x8c0:\n        case 0x8c1:\n            rv_csr_write(s, csr, val);"
+        ));
+        assert!(!header.contains("case 0x8c2:"));
+        assert!(!header.contains("case 0xc00:"));
+        // Reads call out, so rd_csr is no longer pure
+        assert!(header.contains(
+            "__attribute__((hot, nonnull, always_inline))\nstatic inline uint64_t rd_csr("
+        ));
+    }
+
+    #[test]
+    fn test_no_hook_csrs() {
+        let header = header(&EmitConfig::<Rv64>::standard());
+        assert!(!header.contains("rv_csr_read("));
+        assert!(header.contains(
+            "__attribute__((hot, pure, nonnull, always_inline))\nstatic inline uint64_t rd_csr("
+        ));
+    }
+
+    #[test]
+    fn test_vector_serves_vlenb() {
+        let config = EmitConfig::<Rv64>::standard().with_vlen(256);
+        let mut inputs = EmitInputs::new(0x8000_0000, 0x8000_0008);
+        inputs.vector = true;
+        let vector = gen_header::<Rv64>(&HeaderConfig::new(
+            "test",
+            &config,
+            &inputs,
+            vec![0x8000_0000],
+        ));
+        assert!(vector.contains("constexpr uint32_t RV_VLENB = 32;"));
+        assert!(vector.contains("        case CSR_VLENB:\n            return RV_VLENB;"));
+        assert!(vector.contains("void rv_vle(RvState* restrict state, "));
+
+        let scalar = header(&config);
+        assert!(!scalar.contains("CSR_VLENB"));
+        assert!(!scalar.contains("rv_vle("));
+    }
+}
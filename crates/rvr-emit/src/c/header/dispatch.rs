@@ -1,6 +1,24 @@
-use super::{HeaderConfig, Write, Xlen, reg_type};
+use super::{DispatchMode, HeaderConfig, Write, Xlen, block_name, global_symbol, reg_type};
 
 pub(super) fn gen_fn_type<X: Xlen>(cfg: &HeaderConfig<X>) -> String {
+    if cfg.sig.dialect.is_portable() {
+        // Blocks return the next block to the trampoline in rv_execute_from
+        let params = if cfg.sig.params.is_empty() {
+            "void"
+        } else {
+            &cfg.sig.params
+        };
+        return format!(
+            r"/* Block function type: returns the next block, or NULL to stop */
+typedef struct RvNext RvNext;
+typedef RvNext (*rv_fn)({params});
+struct RvNext {{
+    rv_fn fn;
+}};
+
+"
+        );
+    }
     format!(
         r"/* Block function type */
 typedef __attribute__((preserve_none)) void (*rv_fn)({});
@@ -12,40 +30,84 @@ typedef __attribute__((preserve_none)) void (*rv_fn)({});
 
 pub(super) fn gen_block_declarations<X: Xlen>(cfg: &HeaderConfig<X>) -> String {
     let mut decls = String::from("/* Block forward declarations */\n");
-    let width = if X::VALUE == 64 { 16 } else { 8 };
     for &addr in &cfg.block_addresses {
-        writeln!(
-            decls,
-            "__attribute__((preserve_none)) void B_{addr:0width$x}({});",
-            cfg.sig.params,
-            addr = addr,
-            width = width
-        )
-        .unwrap();
+        let name = block_name::<X>(&cfg.symbol_prefix, addr);
+        writeln!(decls, "{};", cfg.sig.fn_decl(&name, &[])).unwrap();
     }
     decls
 }
 
 /// Generate syscall runtime function declarations.
-pub(super) fn gen_syscall_declarations<X: Xlen>() -> String {
+pub(super) fn gen_syscall_declarations<X: Xlen>(prefix: &str) -> String {
     let rtype = reg_type::<X>();
     format!(
         r"/* Syscall runtime helpers (provided by runtime) */
-{rtype} rv_sys_write(RvState* restrict state, {rtype} fd, {rtype} buf, {rtype} count);
-{rtype} rv_sys_read(RvState* restrict state, {rtype} fd, {rtype} buf, {rtype} count);
-{rtype} rv_sys_brk(RvState* restrict state, {rtype} addr);
-{rtype} rv_sys_mmap(RvState* restrict state, {rtype} addr, {rtype} len, {rtype} prot, {rtype} flags, {rtype} fd, {rtype} off);
-{rtype} rv_sys_fstat(RvState* restrict state, {rtype} fd, {rtype} statbuf);
-{rtype} rv_sys_getrandom(RvState* restrict state, {rtype} buf, {rtype} len, {rtype} flags);
-{rtype} rv_sys_clock_gettime(RvState* restrict state, {rtype} clk_id, {rtype} tp);
+{rtype} {prefix}rv_sys_write(RvState* restrict state, {rtype} fd, {rtype} buf, {rtype} count);
+void {prefix}rv_putchar(RvState* restrict state, {rtype} c);
+{rtype} {prefix}rv_sys_read(RvState* restrict state, {rtype} fd, {rtype} buf, {rtype} count);
+{rtype} {prefix}rv_sys_readv(RvState* restrict state, {rtype} fd, {rtype} iov, {rtype} iovcnt);
+{rtype} {prefix}rv_sys_writev(RvState* restrict state, {rtype} fd, {rtype} iov, {rtype} iovcnt);
+{rtype} {prefix}rv_sys_openat(RvState* restrict state, {rtype} dirfd, {rtype} path, {rtype} flags, {rtype} mode);
+{rtype} {prefix}rv_sys_close(RvState* restrict state, {rtype} fd);
+{rtype} {prefix}rv_sys_lseek(RvState* restrict state, {rtype} fd, {rtype} offset, {rtype} whence);
+{rtype} {prefix}rv_sys_brk(RvState* restrict state, {rtype} addr);
+{rtype} {prefix}rv_sys_mmap(RvState* restrict state, {rtype} addr, {rtype} len, {rtype} prot, {rtype} flags, {rtype} fd, {rtype} off);
+{rtype} {prefix}rv_sys_munmap(RvState* restrict state, {rtype} addr, {rtype} len);
+{rtype} {prefix}rv_sys_mremap(RvState* restrict state, {rtype} old_addr, {rtype} old_len, {rtype} new_len, {rtype} flags, {rtype} new_addr);
+{rtype} {prefix}rv_sys_fstat(RvState* restrict state, {rtype} fd, {rtype} statbuf);
+{rtype} {prefix}rv_sys_fstatat(RvState* restrict state, {rtype} dirfd, {rtype} path, {rtype} statbuf, {rtype} flags);
+{rtype} {prefix}rv_sys_getrandom(RvState* restrict state, {rtype} buf, {rtype} len, {rtype} flags);
+{rtype} {prefix}rv_sys_clock_gettime(RvState* restrict state, {rtype} clk_id, {rtype} tp);
+{rtype} {prefix}rv_sys_futex(RvState* restrict state, {rtype} uaddr, {rtype} op, {rtype} val, {rtype} timeout);
+{rtype} {prefix}rv_has_host_syscall(RvState* restrict state, {rtype} num);
+{rtype} {prefix}rv_host_syscall(RvState* restrict state, {rtype} num, {rtype} a0, {rtype} a1, {rtype} a2, {rtype} a3, {rtype} a4, {rtype} a5);
+
+",
+    )
+}
+
+/// Generate native memory intrinsic declarations.
+pub(super) fn gen_mem_intrinsic_declarations<X: Xlen>(prefix: &str) -> String {
+    let rtype = reg_type::<X>();
+    format!(
+        r"/* Native memory intrinsics (provided by runtime) */
+{rtype} {prefix}rv_memcpy(RvState* restrict state, {rtype} dst, {rtype} src, {rtype} n);
+{rtype} {prefix}rv_memset(RvState* restrict state, {rtype} dst, {rtype} c, {rtype} n);
+{rtype} {prefix}rv_memcmp(RvState* restrict state, {rtype} a, {rtype} b, {rtype} n);
 
 ",
     )
 }
 
 pub(super) fn gen_dispatch<X: Xlen>(cfg: &HeaderConfig<X>) -> String {
-    let text_start = cfg.text_start;
     let rtype = reg_type::<X>();
+    let prefix = &cfg.symbol_prefix;
+    let lookup = match cfg.dispatch_mode {
+        DispatchMode::Flat => gen_flat_lookup::<X>(cfg),
+        DispatchMode::PerFunction => format!(
+            r"/* Dispatch: function table search, then per-function block search */
+__attribute__((hot, pure))
+rv_fn {prefix}dispatch_lookup({rtype} pc);
+"
+        ),
+    };
+
+    format!(
+        r"{lookup}
+/* Runtime function - only this is needed from C */
+int {prefix}rv_execute_from(RvState* restrict state, {rtype} start_pc);
+
+/* Metadata constant (read via dlsym) */
+extern const uint32_t {prefix}RV_TRACER_KIND;
+
+",
+    )
+}
+
+fn gen_flat_lookup<X: Xlen>(cfg: &HeaderConfig<X>) -> String {
+    let rtype = reg_type::<X>();
+    let text_start = cfg.text_start;
+    let table = global_symbol(&cfg.symbol_prefix, "dispatch_table");
 
     // Fast path: power-of-2 text_start allows single AND instruction
     // Slow path: subtraction needed for arbitrary text_start
@@ -66,21 +128,27 @@ pub(super) fn gen_dispatch<X: Xlen>(cfg: &HeaderConfig<X>) -> String {
         )
     };
 
+    let table = if cfg.dispatch_table_relative {
+        format!(
+            r#"/* Dispatch table of offsets from the table itself (no relocations) */
+extern const int32_t {table}[] __attribute__((visibility("hidden")));
+
+static inline rv_fn dispatch_target(uint64_t idx) {{
+    return (rv_fn)((uintptr_t){table} + (uintptr_t)(intptr_t){table}[idx]);
+}}
+"#
+        )
+    } else {
+        format!("extern const rv_fn {table}[];\n")
+    };
+
     format!(
         r"{comment}
 static inline uint64_t dispatch_index({rtype} pc) {{
     {dispatch_body}
 }}
 
-extern const rv_fn dispatch_table[];
-
-/* Runtime function - only this is needed from C */
-int rv_execute_from(RvState* restrict state, {rtype} start_pc);
-
-/* Metadata constant (read via dlsym) */
-extern const uint32_t RV_TRACER_KIND;
-
-",
+{table}",
     )
 }
 
@@ -0,0 +1,87 @@
+//! Function hooks served by the host (see `HookKind::HostCall`).
+
+use crate::hooks::FunctionHook;
+use crate::layout::ExitCause;
+
+use super::{HeaderConfig, MEMORY_FIXED_REF, Write, Xlen, reg_type};
+
+/// First argument register (a0); a0..a5 go to the host.
+const REG_A0: usize = 10;
+/// Argument registers passed to the host.
+const HOOK_ARGS: usize = 6;
+
+/// `rv_hook_call` and one `rv_hook_<symbol>` per host-call hook.
+pub(super) fn gen_function_hooks<X: Xlen>(cfg: &HeaderConfig<X>) -> String {
+    let rtype = reg_type::<X>();
+    let memory = if cfg.fixed_addresses.is_some() {
+        MEMORY_FIXED_REF
+    } else {
+        "state->memory"
+    };
+    let host_stop = ExitCause::HostStop.c_name();
+    let mut s = format!(
+        r"/* Function hooks: a hooked guest function's entry calls rv_hook_<symbol> with the hot
+ * registers flushed to state and reloads them after. The host's hook gets a0..a5 and may
+ * change them; without one the guest stops with {host_stop} and the entry PC in exit_info */
+static inline void rv_hook_call(RvState* restrict state, const char* name, uint64_t pc) {{
+    const RvIo* io = state->io;
+    uint64_t args[{HOOK_ARGS}];
+    for (int i = 0; i < {HOOK_ARGS}; i++) {{
+        args[i] = (uint64_t)state->regs[{REG_A0} + i];
+    }}
+    if (io && io->hook &&
+        io->hook(io->hook_ctx, name, args, {memory}, RV_MEMORY_MASK + 1) == 0) {{
+        for (int i = 0; i < {HOOK_ARGS}; i++) {{
+            state->regs[{REG_A0} + i] = ({rtype})args[i];
+        }}
+        return;
+    }}
+    state->exit_code = 1;
+    state->exit_cause = {host_stop};
+    state->exit_info = pc;
+    state->has_exited = true;
+}}
+
+"
+    );
+    for (hook, pc) in &cfg.host_hooks {
+        push_hook_fn(&mut s, hook, *pc);
+    }
+    s
+}
+
+fn push_hook_fn(s: &mut String, hook: &FunctionHook, pc: u64) {
+    let _ = writeln!(
+        s,
+        "static inline void {}(RvState* restrict state) {{\n    rv_hook_call(state, \"{}\", {pc:#x}ull);\n}}\n",
+        hook.fn_name(),
+        hook.symbol,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use rvr_ir::Rv32;
+
+    use super::*;
+    use crate::config::EmitConfig;
+    use crate::hooks::HookKind;
+    use crate::inputs::EmitInputs;
+
+    #[test]
+    fn test_gen_function_hooks() {
+        let mut cfg = HeaderConfig::<Rv32>::new(
+            "rv32",
+            &EmitConfig::default(),
+            &EmitInputs::default(),
+            Vec::new(),
+        );
+        cfg.host_hooks = vec![(FunctionHook::new("checksum", HookKind::HostCall), 0x1000)];
+        let src = gen_function_hooks(&cfg);
+        assert!(src.contains("state->regs[10 + i] = (uint32_t)args[i];"));
+        assert!(src.contains("state->exit_cause = RV_EXIT_HOST_STOP;"));
+        assert!(src.contains(
+            "static inline void rv_hook_checksum(RvState* restrict state) {\n    rv_hook_call(state, \"checksum\", 0x1000ull);\n}"
+        ));
+    }
+}
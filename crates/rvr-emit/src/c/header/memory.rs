@@ -1,27 +1,37 @@
 use super::{HeaderConfig, MEMORY_FIXED_REF, Xlen, reg_type};
+use crate::memory_layout::AddrRange;
 
-pub(super) fn gen_memory_functions<X: Xlen>(cfg: &HeaderConfig<X>) -> String {
-    let addr_type = reg_type::<X>();
-
+/// Body of `phys_addr` for the address mode.
+fn gen_phys_addr_body<X: Xlen>(cfg: &HeaderConfig<X>) -> String {
     // Address translation mode:
     // - Unchecked: assume valid + passthrough, guard pages catch OOB
     // - Wrap: mask to memory size, matches sv39/sv48 behavior
     // - Bounds: trap on invalid + assume + mask, explicit errors
     // Generate phys_addr body based on AddressMode semantics
     let mode = cfg.address_mode;
-    let phys_addr_body = if mode.assumes_valid() {
+    let assume = cfg
+        .compiler
+        .assume("addr <= RV_MEMORY_MASK")
+        .map_or_else(String::new, |assume| format!("    {assume}\n"));
+    if mode.assumes_valid() {
         // Unchecked: assume valid, no masking (guard pages catch OOB)
-        "    __builtin_assume(addr <= RV_MEMORY_MASK);\n    return addr;".to_string()
+        format!("{assume}    return addr;")
     } else if mode.needs_bounds_check() {
         // Bounds: check bounds + trap + mask
         format!(
-            "    if (unlikely((int{0}_t)(addr << ({0} - MEMORY_BITS)) >> ({0} - MEMORY_BITS) != (int{0}_t)addr)) __builtin_trap();\n    __builtin_assume(addr <= RV_MEMORY_MASK);\n    return addr & RV_MEMORY_MASK;",
+            "    if (unlikely((int{0}_t)(addr << ({0} - MEMORY_BITS)) >> ({0} - MEMORY_BITS) != (int{0}_t)addr)) __builtin_trap();\n{assume}    return addr & RV_MEMORY_MASK;",
             X::VALUE
         )
     } else {
         // Wrap: mask only
         "    return addr & RV_MEMORY_MASK;".to_string()
-    };
+    }
+}
+
+pub(super) fn gen_memory_functions<X: Xlen>(cfg: &HeaderConfig<X>) -> String {
+    let addr_type = reg_type::<X>();
+
+    let phys_addr_body = gen_phys_addr_body(cfg);
 
     // Conditional parts based on fixed address mode
     let (mem_param, mem_ref, nonnull) = if cfg.fixed_addresses.is_some() {
@@ -30,12 +40,14 @@ pub(super) fn gen_memory_functions<X: Xlen>(cfg: &HeaderConfig<X>) -> String {
         ("uint8_t* restrict memory, ", "memory", "nonnull, ")
     };
 
+    let store_checks = gen_store_checks(cfg);
+
     format!(
         r"/* Translate virtual address to physical. */
 static inline {addr_type} phys_addr({addr_type} addr) {{
 {phys_addr_body}
 }}
-
+{store_checks}
 /* Memory access: compute phys base first, then add offset. */
 __attribute__((hot, pure, {nonnull}always_inline))
 static inline uint32_t rd_mem_u8({mem_param}{addr_type} base, int16_t off) {{
@@ -124,3 +136,131 @@ static inline void wr_mem_u64({mem_param}{addr_type} base, int16_t off, uint64_t
 ",
     )
 }
+
+/// Predicates stores are checked against before they retire.
+fn gen_store_checks<X: Xlen>(cfg: &HeaderConfig<X>) -> String {
+    let stack_guard_check = cfg
+        .stack_guard
+        .map_or_else(String::new, gen_stack_guard_check::<X>);
+    gen_code_write_check::<X>(&cfg.code_ranges) + &stack_guard_check
+}
+
+/// `rv_is_code_write`: whether a store of `width` bytes at `addr` overlaps
+/// recompiled code.
+///
+/// Stores outside the span of all ranges, nearly all of them, take one
+/// branch on two comparisons; with several ranges, stores inside the span
+/// also check each range.
+fn gen_code_write_check<X: Xlen>(ranges: &[(u64, u64)]) -> String {
+    let (Some(&(lo, _)), Some(&(_, hi))) = (ranges.first(), ranges.last()) else {
+        return String::new();
+    };
+    let overlaps = if ranges.len() == 1 {
+        "true".to_string()
+    } else {
+        ranges
+            .iter()
+            .map(|(start, end)| format!("(start < {end:#x}ull && end > {start:#x}ull)"))
+            .collect::<Vec<_>>()
+            .join(" ||\n           ")
+    };
+    format!(
+        r"
+/* Recompiled code: a store overlapping it traps with RV_CODE_WRITE_TRAP */
+__attribute__((hot, pure, always_inline))
+static inline bool rv_is_code_write({addr_type} addr, uint32_t width) {{
+    uint64_t start = phys_addr(addr);
+    uint64_t end = start + width;
+    if (likely(start >= {hi:#x}ull || end <= {lo:#x}ull)) return false;
+    return {overlaps};
+}}
+",
+        addr_type = reg_type::<X>(),
+    )
+}
+
+/// `rv_is_stack_overflow`: whether a store of `width` bytes at `addr`
+/// reaches into the stack guard.
+fn gen_stack_guard_check<X: Xlen>(guard: AddrRange) -> String {
+    format!(
+        r"
+/* Stack guard: a store into it traps with RV_STACK_OVERFLOW_TRAP */
+__attribute__((hot, pure, always_inline))
+static inline bool rv_is_stack_overflow({addr_type} addr, uint32_t width) {{
+    uint64_t start = phys_addr(addr);
+    return start < {end:#x}ull && start + width > {start:#x}ull;
+}}
+",
+        addr_type = reg_type::<X>(),
+        start = guard.start,
+        end = guard.end,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::c::{HeaderConfig, gen_header};
+    use crate::{AddrRange, AddressMode, EmitConfig, EmitInputs, MemoryLayout};
+    use rvr_ir::Rv64;
+
+    fn header(config: &EmitConfig<Rv64>, code_ranges: Vec<(u64, u64)>) -> String {
+        let mut inputs = EmitInputs::new(0x1000, 0x1008);
+        inputs.code_ranges = code_ranges;
+        gen_header::<Rv64>(&HeaderConfig::new("test", config, &inputs, vec![0x1000]))
+    }
+
+    #[test]
+    fn test_code_write_check_single_range() {
+        let header = header(&EmitConfig::standard(), vec![(0x1000, 0x1008)]);
+        assert!(
+            header.contains("static inline bool rv_is_code_write(uint64_t addr, uint32_t width) {")
+        );
+        assert!(header.contains(
+            "    if (likely(start >= 0x1008ull || end <= 0x1000ull)) return false;\n    return true;\n"
+        ));
+    }
+
+    #[test]
+    fn test_code_write_check_ranges() {
+        let header = header(
+            &EmitConfig::standard(),
+            vec![(0x1000, 0x1008), (0x3000, 0x3100)],
+        );
+        assert!(
+            header.contains("if (likely(start >= 0x3100ull || end <= 0x1000ull)) return false;")
+        );
+        assert!(header.contains(
+            "    return (start < 0x1008ull && end > 0x1000ull) ||\n           (start < 0x3100ull && end > 0x3000ull);\n"
+        ));
+    }
+
+    #[test]
+    fn test_no_code_write_check() {
+        let ranges = vec![(0x1000, 0x1008)];
+        let disabled = EmitConfig::<Rv64>::standard().with_detect_code_writes(false);
+        assert!(!header(&disabled, ranges.clone()).contains("rv_is_code_write"));
+        let unchecked = EmitConfig::<Rv64>::standard().with_address_mode(AddressMode::Unchecked);
+        assert!(!header(&unchecked, ranges).contains("rv_is_code_write"));
+        assert!(!header(&EmitConfig::standard(), Vec::new()).contains("rv_is_code_write"));
+    }
+
+    #[test]
+    fn test_stack_guard_check() {
+        let inputs = EmitInputs::new(0x1000, 0x1008).with_memory_layout(Some(MemoryLayout {
+            segments: Vec::new(),
+            heap: AddrRange::new(0x2000, 0x3000),
+            stack: AddrRange::new(0x5000, 0x6000),
+            mmap: None,
+            stack_guard: Some(AddrRange::new(0x4000, 0x5000)),
+        }));
+        let header = |config: &EmitConfig<Rv64>| {
+            gen_header::<Rv64>(&HeaderConfig::new("test", config, &inputs, vec![0x1000]))
+        };
+        assert!(
+            header(&EmitConfig::standard())
+                .contains("    return start < 0x5000ull && start + width > 0x4000ull;\n")
+        );
+        let unchecked = EmitConfig::<Rv64>::standard().with_address_mode(AddressMode::Unchecked);
+        assert!(!header(&unchecked).contains("rv_is_stack_overflow"));
+    }
+}
@@ -9,36 +9,73 @@
 mod csr;
 mod dispatch;
 mod helpers;
+mod hooks;
 mod memory;
 mod prelude;
 mod state;
 mod trace;
 
+use std::collections::BTreeSet;
 use std::fmt::Write;
 
 use rvr_ir::Xlen;
+use rvr_isa::syscalls::BareMetalConfig;
 
+use super::Compiler;
+use super::cold::{cold_path_declarations, outlines_cold_paths};
+use super::golden::{GoldenMode, gen_golden_helpers};
+use super::namespace::{block_name, global_symbol};
 use super::signature::{FnSignature, MEMORY_FIXED_REF, STATE_FIXED_REF, reg_type};
 use super::tracer::TracerConfig;
-use crate::config::{AddressMode, EmitConfig, FixedAddressConfig, InstretMode, SyscallMode};
+use crate::config::{
+    AddressMode, DispatchMode, EmitConfig, EmitFlags, FixedAddressConfig, InstretMode, MAX_VLEN,
+    SyscallMode,
+};
+use crate::hooks::FunctionHook;
 use crate::inputs::EmitInputs;
 use crate::layout::RvStateLayout;
+use crate::memory_layout::AddrRange;
 
 use csr::gen_csr_functions;
-use dispatch::{gen_block_declarations, gen_dispatch, gen_fn_type, gen_syscall_declarations};
+use dispatch::{
+    gen_block_declarations, gen_dispatch, gen_fn_type, gen_mem_intrinsic_declarations,
+    gen_syscall_declarations,
+};
 use helpers::gen_helpers;
+use hooks::gen_function_hooks;
 use memory::gen_memory_functions;
 use prelude::{gen_constants, gen_pragma_and_includes};
-use state::gen_state_struct;
+use state::{gen_io_struct, gen_mmap_struct, gen_state_struct};
 use trace::gen_trace_helpers;
 
+use super::interp::gen_interp_declarations;
+use super::vector::gen_vector_declarations;
+
 /// Number of CSRs.
 pub const NUM_CSRS: usize = 4096;
 
+/// `exit_code` of a trap on a guest store into recompiled code (status
+/// `RV_TRAPPED`); the store address is in `mtval`.
+pub const CODE_WRITE_TRAP: u8 = 2;
+
+/// `exit_code` of a trap on a guest store into the stack guard (status
+/// `RV_TRAPPED`); the store address is in `mtval`.
+pub const STACK_OVERFLOW_TRAP: u8 = 3;
+
+/// Maximum live mmap arena mappings (matches `rvr_state::MMAP_MAX_REGIONS`).
+pub const MMAP_MAX_REGIONS: usize = 128;
+
+/// Bytes of vector register storage in `RvState` (matches
+/// `rvr_state::VREGS_BYTES`).
+pub const VREGS_BYTES: usize = 32 * MAX_VLEN as usize / 8;
+
 /// CSR addresses.
 pub const CSR_MISA: u32 = 0x301;
+pub const CSR_MTVAL: u32 = 0x343;
 pub const CSR_CYCLE: u32 = 0xC00;
 pub const CSR_CYCLEH: u32 = 0xC80;
+pub const CSR_TIME: u32 = 0xC01;
+pub const CSR_TIMEH: u32 = 0xC81;
 pub const CSR_INSTRET: u32 = 0xC02;
 pub const CSR_INSTRETH: u32 = 0xC82;
 pub const CSR_MCYCLE: u32 = 0xB00;
@@ -46,12 +83,33 @@ pub const CSR_MCYCLEH: u32 = 0xB80;
 pub const CSR_MINSTRET: u32 = 0xB02;
 pub const CSR_MINSTRETH: u32 = 0xB82;
 
+/// Counter CSRs served by `rd_csr`/`wr_csr` themselves. Time is
+/// deterministic: `time` counts retired instructions, like `cycle`.
+const COUNTER_CSRS: [u32; 10] = [
+    CSR_CYCLE,
+    CSR_CYCLEH,
+    CSR_TIME,
+    CSR_TIMEH,
+    CSR_INSTRET,
+    CSR_INSTRETH,
+    CSR_MCYCLE,
+    CSR_MCYCLEH,
+    CSR_MINSTRET,
+    CSR_MINSTRETH,
+];
+
 /// Header generation configuration.
 pub struct HeaderConfig<X: Xlen> {
     /// Base name for output files.
     pub base_name: String,
     /// Memory address bits.
     pub memory_bits: u8,
+    /// Heap end enforced by `brk`/`mmap` (heap size or layout guard), if any.
+    pub heap_limit: Option<u64>,
+    /// Arena served by the mmap allocator, if any.
+    pub mmap_arena: Option<AddrRange>,
+    /// Stack guard stores are checked against, if any.
+    pub stack_guard: Option<AddrRange>,
     /// Number of registers.
     pub num_registers: usize,
     /// Instret counting mode.
@@ -66,14 +124,43 @@ pub struct HeaderConfig<X: Xlen> {
     pub text_start: u64,
     /// Block start addresses.
     pub block_addresses: Vec<u64>,
+    /// Dispatch table layout.
+    pub dispatch_mode: DispatchMode,
+    /// Flat table holds offsets from the table instead of pointers.
+    pub dispatch_table_relative: bool,
     /// Function signature.
     pub sig: FnSignature,
+    /// C compiler the code is built with.
+    pub compiler: Compiler,
     /// Tracer configuration.
     pub tracer_config: TracerConfig,
     /// Syscall mode.
     pub syscall_mode: SyscallMode,
+    /// Bare-metal ECALLs mapped to built-in actions, if any.
+    pub baremetal_ecalls: Option<BareMetalConfig>,
     /// Fixed addresses for state and memory (optional).
     pub fixed_addresses: Option<FixedAddressConfig>,
+    /// Custom CSRs served by `rv_csr_read`/`rv_csr_write` (sorted, no counters).
+    pub hook_csrs: Vec<u16>,
+    /// Prefix for global symbols (see `EmitConfig::symbol_prefix`).
+    pub symbol_prefix: String,
+    /// Declare the native memory intrinsics (`rv_memcpy`, ...).
+    pub native_mem_intrinsics: bool,
+    /// Function hooks served by the host as `(hook, entry pc)`; each gets
+    /// an `rv_hook_<symbol>`.
+    pub host_hooks: Vec<(FunctionHook, u64)>,
+    /// Recompiled code ranges stores are checked against (empty unless
+    /// `detect_code_writes` is set).
+    pub code_ranges: Vec<(u64, u64)>,
+    /// Vector register length in bits, if the program has vector
+    /// instructions (declares the vector runtime and serves `vlenb`).
+    pub vlen: Option<u32>,
+    /// Emit flags (blocks call the cold path helpers if outlined).
+    pub flags: EmitFlags,
+    /// Golden trace points, if capturing or checking a golden trace.
+    pub golden: Option<GoldenMode>,
+    /// Blocks run by the block interpreter (declares it if any).
+    pub interpreted: BTreeSet<u64>,
     _marker: std::marker::PhantomData<X>,
 }
 
@@ -88,6 +175,13 @@ impl<X: Xlen> HeaderConfig<X> {
         Self {
             base_name: base_name.into(),
             memory_bits: config.memory_bits,
+            heap_limit: inputs
+                .memory_layout
+                .as_ref()
+                .map(|layout| layout.heap.end)
+                .or_else(|| config.heap_limit()),
+            mmap_arena: inputs.memory_layout.as_ref().and_then(|layout| layout.mmap),
+            stack_guard: inputs.checked_stack_guard(config.address_mode),
             num_registers: config.num_regs,
             instret_mode: config.instret_mode,
             htif_enabled: config.htif_enabled(),
@@ -95,10 +189,31 @@ impl<X: Xlen> HeaderConfig<X> {
             entry_point: inputs.entry_point,
             text_start: inputs.text_start,
             block_addresses,
+            dispatch_mode: config.dispatch_mode,
+            dispatch_table_relative: config.dispatch_table_relative(),
             sig: FnSignature::new(config),
+            compiler: config.compiler.clone(),
             tracer_config: config.tracer_config.clone(),
             syscall_mode: config.syscall_mode,
+            baremetal_ecalls: config.baremetal_ecalls.clone(),
             fixed_addresses: config.fixed_addresses,
+            hook_csrs: config
+                .hook_csrs()
+                .into_iter()
+                .filter(|&csr| !COUNTER_CSRS.contains(&u32::from(csr)))
+                .collect(),
+            symbol_prefix: config.symbol_prefix.clone(),
+            native_mem_intrinsics: config.native_mem_intrinsics(),
+            host_hooks: inputs.host_hooks.clone(),
+            code_ranges: if config.detect_code_writes() {
+                inputs.code_ranges.clone()
+            } else {
+                Vec::new()
+            },
+            vlen: inputs.vector.then_some(config.vlen),
+            flags: config.flags,
+            golden: GoldenMode::new(&config.tracer_config, inputs.golden.as_deref()),
+            interpreted: inputs.interpreted.clone(),
             _marker: std::marker::PhantomData,
         }
     }
@@ -108,6 +223,14 @@ impl<X: Xlen> HeaderConfig<X> {
     pub const fn reg_bytes() -> usize {
         X::REG_BYTES
     }
+
+    /// Check if the syscall runtime (`rv_sys_*`, `rv_putchar`, host
+    /// syscalls) is linked in and declared.
+    #[must_use]
+    pub fn syscall_runtime(&self) -> bool {
+        self.syscall_mode
+            .links_runtime(self.baremetal_ecalls.as_ref())
+    }
 }
 
 /// Generate the main header file.
@@ -118,6 +241,8 @@ pub fn gen_header<X: Xlen>(cfg: &HeaderConfig<X>) -> String {
     // Pragma and includes
     s.push_str(&gen_pragma_and_includes(cfg));
     s.push_str(&gen_constants::<X>(cfg));
+    s.push_str(gen_io_struct());
+    s.push_str(&gen_mmap_struct(cfg.sig.dialect));
     s.push_str(&gen_state_struct::<X>(cfg));
     s.push_str(&gen_memory_functions::<X>(cfg));
     s.push_str(&gen_csr_functions::<X>(cfg));
@@ -127,9 +252,28 @@ pub fn gen_header<X: Xlen>(cfg: &HeaderConfig<X>) -> String {
     if !cfg.tracer_config.is_none() {
         s.push_str(&gen_trace_helpers::<X>(cfg));
     }
+    if let Some(mode) = cfg.golden {
+        s.push_str(&gen_golden_helpers(
+            mode,
+            cfg.sig.dialect,
+            &cfg.symbol_prefix,
+        ));
+    }
 
-    if cfg.syscall_mode == SyscallMode::Linux {
-        s.push_str(&gen_syscall_declarations::<X>());
+    if cfg.syscall_runtime() {
+        s.push_str(&gen_syscall_declarations::<X>(&cfg.symbol_prefix));
+    }
+    if cfg.native_mem_intrinsics {
+        s.push_str(&gen_mem_intrinsic_declarations::<X>(&cfg.symbol_prefix));
+    }
+    if cfg.vlen.is_some() {
+        s.push_str(&gen_vector_declarations::<X>(&cfg.symbol_prefix));
+    }
+    if !cfg.interpreted.is_empty() {
+        s.push_str(&gen_interp_declarations::<X>(&cfg.symbol_prefix));
+    }
+    if !cfg.host_hooks.is_empty() {
+        s.push_str(&gen_function_hooks(cfg));
     }
     s.push_str(&gen_fn_type(cfg));
     s.push_str(&gen_dispatch::<X>(cfg));
@@ -141,15 +285,24 @@ pub fn gen_header<X: Xlen>(cfg: &HeaderConfig<X>) -> String {
 #[must_use]
 pub fn gen_blocks_header<X: Xlen>(cfg: &HeaderConfig<X>) -> String {
     let decls = gen_block_declarations(cfg);
+    let cold = if outlines_cold_paths(cfg.flags, cfg.sig.dialect) {
+        format!("{}\n", cold_path_declarations(&cfg.sig, &cfg.symbol_prefix))
+    } else {
+        String::new()
+    };
     format!(
         r#"#pragma once
 #include "{}.h"
 
 /* Trap handler for invalid addresses */
-__attribute__((preserve_none)) void rv_trap({});
+{};
 
-{}
+{}{}
 "#,
-        cfg.base_name, cfg.sig.params, decls
+        cfg.base_name,
+        cfg.sig
+            .fn_decl(&global_symbol(&cfg.symbol_prefix, "rv_trap"), &[]),
+        cold,
+        decls
     )
 }
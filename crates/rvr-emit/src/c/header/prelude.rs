@@ -1,7 +1,10 @@
 use super::{
     CSR_CYCLE, CSR_CYCLEH, CSR_INSTRET, CSR_INSTRETH, CSR_MCYCLE, CSR_MCYCLEH, CSR_MINSTRET,
-    CSR_MINSTRETH, CSR_MISA, HeaderConfig, Write, Xlen,
+    CSR_MINSTRETH, CSR_MISA, CSR_MTVAL, CSR_TIME, CSR_TIMEH, FixedAddressConfig, HeaderConfig,
+    Write, Xlen,
 };
+use crate::c::vector::{CSR_VL, CSR_VLENB, CSR_VTYPE};
+use crate::config::CDialect;
 
 pub(super) fn gen_pragma_and_includes<X: Xlen>(cfg: &HeaderConfig<X>) -> String {
     let htif_include = if cfg.htif_enabled {
@@ -36,62 +39,142 @@ static inline int unlikely(int x) {{ return __builtin_expect(!!(x), 0); }}
     )
 }
 
-pub(super) fn gen_constants<X: Xlen>(cfg: &HeaderConfig<X>) -> String {
-    let mut s = format!(
-        r"/* Architecture constants (C23 constexpr) */
-constexpr int XLEN = {xlen};
-
-/* Memory configuration */
-constexpr int MEMORY_BITS = {memory_bits};
-constexpr uint64_t RV_MEMORY_SIZE = 1ull << {memory_bits};
-constexpr uint64_t RV_MEMORY_MASK = (1ull << {memory_bits}) - 1;
-
-/* Entry point */
-constexpr uint32_t RV_ENTRY_POINT = {entry_point:#x};
+/// A constant's type, name, value, and whether constant expressions use it.
+type Constant = (&'static str, &'static str, String, bool);
 
-/* CSR addresses */
-constexpr uint32_t CSR_MISA      = {csr_misa:#x};
-constexpr uint32_t CSR_CYCLE     = {csr_cycle:#x};
-constexpr uint32_t CSR_CYCLEH    = {csr_cycleh:#x};
-constexpr uint32_t CSR_INSTRET   = {csr_instret:#x};
-constexpr uint32_t CSR_INSTRETH  = {csr_instreth:#x};
-constexpr uint32_t CSR_MCYCLE    = {csr_mcycle:#x};
-constexpr uint32_t CSR_MCYCLEH   = {csr_mcycleh:#x};
-constexpr uint32_t CSR_MINSTRET  = {csr_minstret:#x};
-constexpr uint32_t CSR_MINSTRETH = {csr_minstreth:#x};
+pub(super) fn gen_constants<X: Xlen>(cfg: &HeaderConfig<X>) -> String {
+    let dialect = cfg.sig.dialect;
+    let heap_end = cfg.heap_limit.map_or_else(
+        || "RV_MEMORY_SIZE".to_string(),
+        |end| format!("{end:#x}ull"),
+    );
+    let mmap_base = cfg.mmap_arena.map_or(0, |arena| arena.start);
+    let mmap_end = cfg.mmap_arena.map_or(0, |arena| arena.end);
+    let sections: [(&str, Vec<Constant>); 5] = [
+        (
+            "Architecture constants",
+            vec![("int", "XLEN", X::VALUE.to_string(), true)],
+        ),
+        (
+            "Memory configuration",
+            vec![
+                ("int", "MEMORY_BITS", cfg.memory_bits.to_string(), true),
+                (
+                    "uint64_t",
+                    "RV_MEMORY_SIZE",
+                    format!("1ull << {}", cfg.memory_bits),
+                    false,
+                ),
+                (
+                    "uint64_t",
+                    "RV_MEMORY_MASK",
+                    format!("(1ull << {}) - 1", cfg.memory_bits),
+                    false,
+                ),
+                ("uint64_t", "RV_HEAP_END", heap_end, false),
+                (
+                    "uint64_t",
+                    "RV_MMAP_BASE",
+                    format!("{mmap_base:#x}ull"),
+                    false,
+                ),
+                (
+                    "uint64_t",
+                    "RV_MMAP_END",
+                    format!("{mmap_end:#x}ull"),
+                    false,
+                ),
+            ],
+        ),
+        (
+            "Entry point",
+            vec![(
+                "uint32_t",
+                "RV_ENTRY_POINT",
+                format!("{:#x}", cfg.entry_point),
+                false,
+            )],
+        ),
+        (
+            "CSR addresses",
+            [
+                ("CSR_MISA", CSR_MISA),
+                ("CSR_MTVAL", CSR_MTVAL),
+                ("CSR_CYCLE", CSR_CYCLE),
+                ("CSR_CYCLEH", CSR_CYCLEH),
+                ("CSR_TIME", CSR_TIME),
+                ("CSR_TIMEH", CSR_TIMEH),
+                ("CSR_INSTRET", CSR_INSTRET),
+                ("CSR_INSTRETH", CSR_INSTRETH),
+                ("CSR_MCYCLE", CSR_MCYCLE),
+                ("CSR_MCYCLEH", CSR_MCYCLEH),
+                ("CSR_MINSTRET", CSR_MINSTRET),
+                ("CSR_MINSTRETH", CSR_MINSTRETH),
+            ]
+            .into_iter()
+            .map(|(name, csr)| ("uint32_t", name, format!("{csr:#x}"), true))
+            .collect(),
+        ),
+        (
+            "RISC-V division special values",
+            vec![
+                (
+                    "uint32_t",
+                    "RV_DIV_BY_ZERO",
+                    "UINT32_MAX".to_string(),
+                    false,
+                ),
+                ("int32_t", "RV_INT32_MIN", "INT32_MIN".to_string(), false),
+            ],
+        ),
+    ];
 
-/* RISC-V division special values */
-constexpr uint32_t RV_DIV_BY_ZERO = UINT32_MAX;
-constexpr int32_t  RV_INT32_MIN   = INT32_MIN;
+    let mut s = String::new();
+    for (title, constants) in sections {
+        let _ = writeln!(s, "/* {title} */");
+        for (ty, name, value, in_const_exprs) in constants {
+            let _ = writeln!(s, "{}", dialect.constant(ty, name, &value, in_const_exprs));
+        }
+        s.push('\n');
+    }
 
-",
-        xlen = X::VALUE,
-        memory_bits = cfg.memory_bits,
-        entry_point = cfg.entry_point,
-        csr_misa = CSR_MISA,
-        csr_cycle = CSR_CYCLE,
-        csr_cycleh = CSR_CYCLEH,
-        csr_instret = CSR_INSTRET,
-        csr_instreth = CSR_INSTRETH,
-        csr_mcycle = CSR_MCYCLE,
-        csr_mcycleh = CSR_MCYCLEH,
-        csr_minstret = CSR_MINSTRET,
-        csr_minstreth = CSR_MINSTRETH,
-    );
+    if let Some(vlen) = cfg.vlen {
+        s.push_str(&gen_vector_constants(vlen, dialect));
+    }
 
     // Add fixed address constants if enabled
     if let Some(fixed) = cfg.fixed_addresses {
-        write!(
-            s,
-            r"/* Fixed addresses for state and memory (requires runtime mapping) */
-constexpr uint64_t RV_STATE_ADDR  = {:#x}ull;
-constexpr uint64_t RV_MEMORY_ADDR = {:#x}ull;
+        s.push_str(&gen_fixed_address_constants(fixed, dialect));
+    }
 
-",
-            fixed.state_addr, fixed.memory_addr
-        )
-        .unwrap();
+    s
+}
+
+fn gen_vector_constants(vlen: u32, dialect: CDialect) -> String {
+    let mut s = "/* Vector configuration */\n".to_string();
+    for (name, value) in [
+        ("RV_VLEN", vlen.to_string()),
+        ("RV_VLENB", (vlen / 8).to_string()),
+        ("CSR_VL", format!("{CSR_VL:#x}")),
+        ("CSR_VTYPE", format!("{CSR_VTYPE:#x}")),
+        ("CSR_VLENB", format!("{CSR_VLENB:#x}")),
+    ] {
+        let _ = writeln!(s, "{}", dialect.constant("uint32_t", name, &value, true));
     }
+    s.push('\n');
+    s
+}
 
+fn gen_fixed_address_constants(fixed: FixedAddressConfig, dialect: CDialect) -> String {
+    let mut s =
+        "/* Fixed addresses for state and memory (requires runtime mapping) */\n".to_string();
+    for (name, addr) in [
+        ("RV_STATE_ADDR", fixed.state_addr),
+        ("RV_MEMORY_ADDR", fixed.memory_addr),
+    ] {
+        let value = format!("{addr:#x}ull");
+        let _ = writeln!(s, "{}", dialect.constant("uint64_t", name, &value, false));
+    }
+    s.push('\n');
     s
 }
@@ -1,5 +1,72 @@
-use super::{HeaderConfig, NUM_CSRS, RvStateLayout, Write, Xlen, reg_type};
+use crate::config::CDialect;
+use crate::layout::ExitCause;
+
+use super::{
+    CODE_WRITE_TRAP, HeaderConfig, MMAP_MAX_REGIONS, NUM_CSRS, RvStateLayout, STACK_OVERFLOW_TRAP,
+    VREGS_BYTES, Write, Xlen, reg_type,
+};
+
+/// Host hook table pointed to by `RvState::io`.
+pub(super) const fn gen_io_struct() -> &'static str {
+    r"/* Host hooks (installed by the host runner): guest stdio (see rv_sys_read/rv_sys_write),
+ * custom hook CSRs (see rv_csr_read/rv_csr_write; NULL when not installed) and host
+ * syscalls (see rv_host_syscall; syscall_nums sorted, syscall_count 0 when none),
+ * preopened files (see rv_sys_openat; open/close/seek/stat NULL when the host preopened
+ * nothing) and function hooks (see rv_hook_call; hook returns nonzero for a name it does
+ * not serve) */
+typedef struct RvStat {
+    uint64_t size;
+    uint32_t mode;
+    uint32_t blksize;
+    uint64_t blocks;
+} RvStat;
+
+typedef struct RvIo {
+    void* ctx;
+    int64_t (*read)(void* ctx, uint32_t fd, uint8_t* buf, size_t len);
+    int64_t (*write)(void* ctx, uint32_t fd, const uint8_t* buf, size_t len);
+    void* csr_ctx;
+    uint64_t (*csr_read)(void* ctx, uint32_t csr);
+    void (*csr_write)(void* ctx, uint32_t csr, uint64_t value);
+    void* syscall_ctx;
+    const uint64_t* syscall_nums;
+    uint64_t syscall_count;
+    int64_t (*syscall)(void* ctx, uint64_t num, const uint64_t* args, uint8_t* memory,
+                       uint64_t memory_size);
+    int64_t (*open)(void* ctx, int32_t dirfd, const char* path, uint64_t flags, uint64_t mode);
+    int64_t (*close)(void* ctx, uint32_t fd);
+    void* hook_ctx;
+    int32_t (*hook)(void* ctx, const char* name, uint64_t* args, uint8_t* memory,
+                    uint64_t memory_size);
+    int64_t (*seek)(void* ctx, uint32_t fd, int64_t offset, uint32_t whence);
+    int64_t (*stat)(void* ctx, int32_t dirfd, const char* path, uint32_t flags, RvStat* out);
+} RvIo;
+
+"
+}
 
+/// Live mmap arena mappings stored in `RvState::mmap` (see `rv_sys_mmap`).
+pub(super) fn gen_mmap_struct(dialect: CDialect) -> String {
+    let max_regions = dialect.constant(
+        "uint32_t",
+        "RV_MMAP_MAX_REGIONS",
+        &MMAP_MAX_REGIONS.to_string(),
+        true,
+    );
+    format!(
+        r"/* Anonymous mmap arena: live mappings, sorted and non-adjacent */
+{max_regions}
+typedef struct RvMmap {{
+    uint32_t count;
+    uint32_t _pad;
+    uint64_t regions[RV_MMAP_MAX_REGIONS][2];
+}} RvMmap;
+
+"
+    )
+}
+
+#[allow(clippy::too_many_lines)]
 pub(super) fn gen_state_struct<X: Xlen>(cfg: &HeaderConfig<X>) -> String {
     let rtype = reg_type::<X>();
     let has_tracer = !cfg.tracer_config.is_none();
@@ -17,19 +84,18 @@ pub(super) fn gen_state_struct<X: Xlen>(cfg: &HeaderConfig<X>) -> String {
     let offset_reservation_valid = layout.offset_reservation_valid;
     let offset_has_exited = layout.offset_has_exited;
     let offset_exit_code = layout.offset_exit_code;
+    let offset_exit_cause = layout.offset_exit_cause;
+    let offset_exit_info = layout.offset_exit_info;
     let offset_brk = layout.offset_brk;
     let offset_start_brk = layout.offset_start_brk;
     let offset_memory = layout.offset_memory;
+    let offset_io = layout.offset_io;
 
     // Tracer if enabled (before CSRs)
-    let offset_tracer = offset_memory + 8;
+    let offset_tracer = layout.offset_tracer;
 
     // CSRs at end (huge array, rarely accessed in hot paths)
-    let offset_csrs = if has_tracer {
-        offset_tracer // tracer size added by C compiler
-    } else {
-        offset_memory + 8
-    };
+    let offset_csrs = offset_tracer; // tracer size (if any) added by C compiler
 
     // Optional suspender field
     let suspender_field = if layout.instret_suspend {
@@ -38,8 +104,8 @@ pub(super) fn gen_state_struct<X: Xlen>(cfg: &HeaderConfig<X>) -> String {
         String::new()
     };
 
-    // Compute pad offset (after exit_code)
-    let offset_pad0 = offset_exit_code + 1;
+    // Cancellation flag (after exit_code)
+    let offset_cancel_requested = offset_exit_code + 1;
 
     // Optional tracer field (before CSRs)
     let tracer_field = if has_tracer {
@@ -57,8 +123,37 @@ pub(super) fn gen_state_struct<X: Xlen>(cfg: &HeaderConfig<X>) -> String {
         offset_csrs.to_string()
     };
 
+    let mut exit_causes = "enum RvExitCause {\n".to_string();
+    for cause in ExitCause::ALL {
+        let _ = writeln!(exit_causes, "    {} = {},", cause.c_name(), cause as u32);
+    }
+    exit_causes.push_str("};\n");
+
+    let dialect = cfg.sig.dialect;
+    let trapped = dialect.constant("uint8_t", "RV_TRAPPED", "3", true);
+    let code_write_trap = dialect.constant(
+        "uint8_t",
+        "RV_CODE_WRITE_TRAP",
+        &CODE_WRITE_TRAP.to_string(),
+        true,
+    );
+    let stack_overflow_trap = dialect.constant(
+        "uint8_t",
+        "RV_STACK_OVERFLOW_TRAP",
+        &STACK_OVERFLOW_TRAP.to_string(),
+        true,
+    );
+
     let mut s = format!(
-        r"/* VM State - hot fields first for cache locality */
+        r"/* has_exited after a guest trap (exit_code 1, pc and registers saved) */
+{trapped}
+/* exit_code of a trap on a store into recompiled code (address in mtval) */
+{code_write_trap}
+/* exit_code of a trap on a store into the stack guard (address in mtval) */
+{stack_overflow_trap}
+/* exit_cause values (exit_info holds the cause's address or test number) */
+{exit_causes}
+/* VM State - hot fields first for cache locality */
 typedef struct RvState {{
     /* Hot path fields (small offsets for efficient addressing) */
     {rtype} regs[{num_regs}];           /* offset {offset_regs} */
@@ -72,7 +167,9 @@ typedef struct RvState {{
     /* Execution control */
     uint8_t has_exited;                 /* offset {offset_has_exited} */
     uint8_t exit_code;                  /* offset {offset_exit_code} */
-    uint8_t _pad0;                      /* offset {offset_pad0} */
+    uint8_t cancel_requested;           /* offset {offset_cancel_requested}, set by the host */
+    uint32_t exit_cause;                /* offset {offset_exit_cause} */
+    uint64_t exit_info;                 /* offset {offset_exit_info} */
 
     /* Heap management */
     {rtype} brk;                        /* offset {offset_brk} */
@@ -80,13 +177,27 @@ typedef struct RvState {{
 
     /* Cold fields (rarely accessed in hot paths) */
     uint8_t* memory;                    /* offset {offset_memory} */
+    RvIo* io;                           /* offset {offset_io} */
 {tracer_field}
     /* CSRs at end (large array, rarely used) */
     {rtype} csrs[{num_csrs}];           /* offset {csr_offset_comment} */
+
+    /* mmap arena allocator (only used by mmap syscalls) */
+    RvMmap mmap;
+    uint8_t vregs[{VREGS_BYTES}];         /* vector registers (only used by vector code) */
+    uint64_t trace_countdown;           /* instructions until the next sample (sampled tracers) */
+
+    /* HTIF poll watchdog (only used by HTIF guests) */
+    uint64_t htif_polls;                /* fromhost loads since it changed or a request */
+    uint64_t htif_fromhost;             /* fromhost as last seen */
+    uint64_t htif_syscall;              /* last requested HTIF syscall, 0 if none */
+
+    /* Golden trace points (only used when capturing or checking a golden trace) */
+    uint64_t golden_countdown;          /* block entries until the next point */
+    uint64_t golden_pos;                /* index of the next point */
 }} RvState;
 
 ",
-        rtype = rtype,
         num_regs = cfg.num_registers,
         num_csrs = NUM_CSRS,
         offset_regs = offset_regs,
@@ -97,39 +208,39 @@ typedef struct RvState {{
         offset_reservation_valid = offset_reservation_valid,
         offset_has_exited = offset_has_exited,
         offset_exit_code = offset_exit_code,
-        offset_pad0 = offset_pad0,
+        offset_cancel_requested = offset_cancel_requested,
+        offset_exit_cause = offset_exit_cause,
+        offset_exit_info = offset_exit_info,
         offset_brk = offset_brk,
         offset_start_brk = offset_start_brk,
         offset_memory = offset_memory,
+        offset_io = offset_io,
         tracer_field = tracer_field,
         csr_offset_comment = csr_offset_comment,
     );
 
-    // Layout verification (C23 static_assert without message)
-    // Only verify offsets that are statically known (not tracer-dependent)
-    let mut asserts = format!(
-        r"/* Layout verification (C23 static_assert) */
-static_assert(offsetof(RvState, regs) == {offset_regs});
-static_assert(offsetof(RvState, pc) == {offset_pc});
-static_assert(offsetof(RvState, instret) == {offset_instret});
-static_assert(offsetof(RvState, reservation_addr) == {offset_reservation_addr});
-static_assert(offsetof(RvState, has_exited) == {offset_has_exited});
-static_assert(offsetof(RvState, brk) == {offset_brk});
-static_assert(offsetof(RvState, memory) == {offset_memory});
-
-",
-    );
-
-    // Add CSR offset verification only if no tracer (otherwise it's dynamic)
+    // Layout verification, only of offsets that are statically known
+    // (the CSR offset depends on the tracer)
+    let mut offsets = vec![
+        ("regs", offset_regs),
+        ("pc", offset_pc),
+        ("instret", offset_instret),
+        ("reservation_addr", offset_reservation_addr),
+        ("has_exited", offset_has_exited),
+        ("exit_cause", offset_exit_cause),
+        ("exit_info", offset_exit_info),
+        ("brk", offset_brk),
+        ("memory", offset_memory),
+        ("io", offset_io),
+    ];
     if !has_tracer {
-        writeln!(
-            asserts,
-            "static_assert(offsetof(RvState, csrs) == {offset_csrs});"
-        )
-        .unwrap();
-        asserts.push('\n');
+        offsets.push(("csrs", offset_csrs));
     }
-
-    s.push_str(&asserts);
+    s.push_str("/* Layout verification */\n");
+    for (field, offset) in offsets {
+        let cond = format!("offsetof(RvState, {field}) == {offset}");
+        writeln!(s, "{}", cfg.sig.dialect.static_assert(&cond)).unwrap();
+    }
+    s.push('\n');
     s
 }
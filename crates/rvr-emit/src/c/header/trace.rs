@@ -1,6 +1,8 @@
 use std::fmt::Write;
 
 use super::{HeaderConfig, STATE_FIXED_REF, Xlen, reg_type};
+use crate::c::TracerConfig;
+use crate::config::CDialect;
 
 const TRACE_MEM_READS_HEADER: &str =
     "/* Traced memory read helpers - call optimized base functions. */\n";
@@ -11,19 +13,110 @@ const TRACE_REGVAL_HEADER: &str =
     "\n/* Traced hot register helpers - for registers in local vars/args */\n";
 const TRACE_CSR_HEADER: &str = "\n/* Traced CSR access - call trace functions */\n";
 
+/// Tracer hooks a sampled tracer gates, with their parameters after
+/// `Tracer* t, pc, op` (`R` is the register type). Generated code calls
+/// them through wrappers named by `TracerConfig::hook_name`.
+const SAMPLED_HOOKS: [(&str, &[(&str, &str)]); 16] = [
+    ("trace_pc", &[]),
+    ("trace_opcode", &[("uint32_t", "opcode")]),
+    ("trace_reg_read", &[("uint8_t", "reg"), ("R", "value")]),
+    ("trace_reg_write", &[("uint8_t", "reg"), ("R", "value")]),
+    (
+        "trace_mem_read_byte",
+        &[("R", "addr"), ("uint8_t", "value")],
+    ),
+    (
+        "trace_mem_read_halfword",
+        &[("R", "addr"), ("uint16_t", "value")],
+    ),
+    (
+        "trace_mem_read_word",
+        &[("R", "addr"), ("uint32_t", "value")],
+    ),
+    (
+        "trace_mem_read_dword",
+        &[("R", "addr"), ("uint64_t", "value")],
+    ),
+    (
+        "trace_mem_write_byte",
+        &[("R", "addr"), ("uint8_t", "value")],
+    ),
+    (
+        "trace_mem_write_halfword",
+        &[("R", "addr"), ("uint16_t", "value")],
+    ),
+    (
+        "trace_mem_write_word",
+        &[("R", "addr"), ("uint32_t", "value")],
+    ),
+    (
+        "trace_mem_write_dword",
+        &[("R", "addr"), ("uint64_t", "value")],
+    ),
+    ("trace_branch_taken", &[("R", "target")]),
+    ("trace_branch_not_taken", &[("R", "target")]),
+    ("trace_csr_read", &[("uint16_t", "csr"), ("R", "value")]),
+    ("trace_csr_write", &[("uint16_t", "csr"), ("R", "value")]),
+];
+
+/// Wrappers that pass every `interval`-th instruction on to the tracer.
+///
+/// `trace_pc` post-decrements `RvState::trace_countdown`, so the fast path
+/// is a decrement and a branch; at zero it samples the instruction and
+/// restarts the countdown. The other hooks run while the countdown is fresh
+/// from a sample, which lasts until the next instruction's `trace_pc`.
+fn gen_sampled_hooks(rtype: &str, tracer: &TracerConfig, dialect: CDialect) -> String {
+    let interval = tracer.sample_interval;
+    let interval_constant = dialect.constant(
+        "uint64_t",
+        "RV_SAMPLE_INTERVAL",
+        &format!("{interval}ull"),
+        false,
+    );
+    let trace_pc = tracer.hook_name("trace_pc");
+    let mut out = format!(
+        r"/* Tracer sampling: hooks see one instruction in {interval} */
+{interval_constant}
+
+static inline RvState* rv_tracer_state(Tracer* t) {{
+    return (RvState*)((char*)t - offsetof(RvState, tracer));
+}}
+
+static inline int rv_trace_sampled(Tracer* t) {{
+    return rv_tracer_state(t)->trace_countdown == RV_SAMPLE_INTERVAL - 1;
+}}
+
+__attribute__((hot, always_inline))
+static inline void {trace_pc}(Tracer* t, {rtype} pc, uint16_t op) {{
+    RvState* s = rv_tracer_state(t);
+    if (likely(s->trace_countdown-- != 0)) return;
+    s->trace_countdown = RV_SAMPLE_INTERVAL - 1;
+    trace_pc(t, pc, op);
+}}
+"
+    );
+    for (name, params) in &SAMPLED_HOOKS[1..] {
+        let mut decl = String::new();
+        let mut args = String::new();
+        for (ty, param) in *params {
+            let ty = if *ty == "R" { rtype } else { ty };
+            let _ = write!(decl, ", {ty} {param}");
+            let _ = write!(args, ", {param}");
+        }
+        let _ = write!(
+            out,
+            "\n__attribute__((hot, always_inline))\nstatic inline void {}(Tracer* t, {rtype} pc, uint16_t op{decl}) {{\n    if (unlikely(rv_trace_sampled(t))) {name}(t, pc, op{args});\n}}\n",
+            tracer.hook_name(name)
+        );
+    }
+    out.push('\n');
+    out
+}
+
 pub(super) fn gen_trace_helpers<X: Xlen>(cfg: &HeaderConfig<X>) -> String {
     let rtype = reg_type::<X>();
     let addr_type = reg_type::<X>();
-    let instret_param = if cfg.instret_mode.counts() {
-        ", uint64_t instret"
-    } else {
-        ""
-    };
-    let instret_arg = if cfg.instret_mode.counts() {
-        ", instret"
-    } else {
-        ""
-    };
+    let suffix = cfg.tracer_config.hook_suffix();
 
     let (mem_param, mem_arg, state_param, state_arg, state_ref) = if cfg.fixed_addresses.is_some() {
         ("", "", "", "", STATE_FIXED_REF)
@@ -37,72 +130,88 @@ pub(super) fn gen_trace_helpers<X: Xlen>(cfg: &HeaderConfig<X>) -> String {
         )
     };
 
-    let mut out = String::new();
-    push_trace_mem_reads(&mut out, addr_type, mem_param, mem_arg);
-    push_trace_mem_writes(&mut out, addr_type, mem_param, mem_arg);
-    push_trace_reg_helpers(&mut out, rtype, addr_type, state_param, state_ref);
-    push_trace_regval_helpers(&mut out, rtype, addr_type);
+    let mut out = if cfg.tracer_config.is_sampled() {
+        gen_sampled_hooks(rtype, &cfg.tracer_config, cfg.sig.dialect)
+    } else {
+        String::new()
+    };
+    push_trace_mem_reads(&mut out, addr_type, mem_param, mem_arg, suffix);
+    push_trace_mem_writes(&mut out, addr_type, mem_param, mem_arg, suffix);
+    push_trace_reg_helpers(&mut out, rtype, addr_type, state_param, state_ref, suffix);
+    push_trace_regval_helpers(&mut out, rtype, addr_type, suffix);
     push_trace_csr_helpers(
         &mut out,
         rtype,
         addr_type,
         state_param,
         state_arg,
-        instret_param,
-        instret_arg,
+        cfg.instret_mode.counts(),
+        suffix,
     );
     out
 }
 
-fn push_trace_mem_reads(out: &mut String, addr_type: &str, mem_param: &str, mem_arg: &str) {
+fn push_trace_mem_reads(
+    out: &mut String,
+    addr_type: &str,
+    mem_param: &str,
+    mem_arg: &str,
+    suffix: &str,
+) {
     out.push_str(TRACE_MEM_READS_HEADER);
     writeln!(
         out,
-        "__attribute__((hot, nonnull, always_inline))\nstatic inline uint32_t trd_mem_u8(Tracer* t, {addr_type} pc, uint16_t op, {mem_param}{addr_type} base, int16_t off) {{\n    uint32_t val = rd_mem_u8({mem_arg}base, off);\n    trace_mem_read_byte(t, pc, op, phys_addr(base) + off, (uint8_t)val);\n    return val;\n}}\n")
+        "__attribute__((hot, nonnull, always_inline))\nstatic inline uint32_t trd_mem_u8(Tracer* t, {addr_type} pc, uint16_t op, {mem_param}{addr_type} base, int16_t off) {{\n    uint32_t val = rd_mem_u8({mem_arg}base, off);\n    trace_mem_read_byte{suffix}(t, pc, op, phys_addr(base) + off, (uint8_t)val);\n    return val;\n}}\n")
     .expect("formatting trd_mem_u8");
     writeln!(
         out,
-        "__attribute__((hot, nonnull, always_inline))\nstatic inline int32_t trd_mem_i8(Tracer* t, {addr_type} pc, uint16_t op, {mem_param}{addr_type} base, int16_t off) {{\n    int32_t val = rd_mem_i8({mem_arg}base, off);\n    trace_mem_read_byte(t, pc, op, phys_addr(base) + off, (uint8_t)val);\n    return val;\n}}\n")
+        "__attribute__((hot, nonnull, always_inline))\nstatic inline int32_t trd_mem_i8(Tracer* t, {addr_type} pc, uint16_t op, {mem_param}{addr_type} base, int16_t off) {{\n    int32_t val = rd_mem_i8({mem_arg}base, off);\n    trace_mem_read_byte{suffix}(t, pc, op, phys_addr(base) + off, (uint8_t)val);\n    return val;\n}}\n")
     .expect("formatting trd_mem_i8");
     writeln!(
         out,
-        "__attribute__((hot, nonnull, always_inline))\nstatic inline uint32_t trd_mem_u16(Tracer* t, {addr_type} pc, uint16_t op, {mem_param}{addr_type} base, int16_t off) {{\n    uint32_t val = rd_mem_u16({mem_arg}base, off);\n    trace_mem_read_halfword(t, pc, op, phys_addr(base) + off, (uint16_t)val);\n    return val;\n}}\n")
+        "__attribute__((hot, nonnull, always_inline))\nstatic inline uint32_t trd_mem_u16(Tracer* t, {addr_type} pc, uint16_t op, {mem_param}{addr_type} base, int16_t off) {{\n    uint32_t val = rd_mem_u16({mem_arg}base, off);\n    trace_mem_read_halfword{suffix}(t, pc, op, phys_addr(base) + off, (uint16_t)val);\n    return val;\n}}\n")
     .expect("formatting trd_mem_u16");
     writeln!(
         out,
-        "__attribute__((hot, nonnull, always_inline))\nstatic inline int32_t trd_mem_i16(Tracer* t, {addr_type} pc, uint16_t op, {mem_param}{addr_type} base, int16_t off) {{\n    int32_t val = rd_mem_i16({mem_arg}base, off);\n    trace_mem_read_halfword(t, pc, op, phys_addr(base) + off, (uint16_t)val);\n    return val;\n}}\n")
+        "__attribute__((hot, nonnull, always_inline))\nstatic inline int32_t trd_mem_i16(Tracer* t, {addr_type} pc, uint16_t op, {mem_param}{addr_type} base, int16_t off) {{\n    int32_t val = rd_mem_i16({mem_arg}base, off);\n    trace_mem_read_halfword{suffix}(t, pc, op, phys_addr(base) + off, (uint16_t)val);\n    return val;\n}}\n")
     .expect("formatting trd_mem_i16");
     writeln!(
         out,
-        "__attribute__((hot, nonnull, always_inline))\nstatic inline uint32_t trd_mem_u32(Tracer* t, {addr_type} pc, uint16_t op, {mem_param}{addr_type} base, int16_t off) {{\n    uint32_t val = rd_mem_u32({mem_arg}base, off);\n    trace_mem_read_word(t, pc, op, phys_addr(base) + off, val);\n    return val;\n}}\n")
+        "__attribute__((hot, nonnull, always_inline))\nstatic inline uint32_t trd_mem_u32(Tracer* t, {addr_type} pc, uint16_t op, {mem_param}{addr_type} base, int16_t off) {{\n    uint32_t val = rd_mem_u32({mem_arg}base, off);\n    trace_mem_read_word{suffix}(t, pc, op, phys_addr(base) + off, val);\n    return val;\n}}\n")
     .expect("formatting trd_mem_u32");
     writeln!(
         out,
-        "__attribute__((hot, nonnull, always_inline))\nstatic inline int64_t trd_mem_i32(Tracer* t, {addr_type} pc, uint16_t op, {mem_param}{addr_type} base, int16_t off) {{\n    int64_t val = rd_mem_i32({mem_arg}base, off);\n    trace_mem_read_word(t, pc, op, phys_addr(base) + off, (uint32_t)val);\n    return val;\n}}\n")
+        "__attribute__((hot, nonnull, always_inline))\nstatic inline int64_t trd_mem_i32(Tracer* t, {addr_type} pc, uint16_t op, {mem_param}{addr_type} base, int16_t off) {{\n    int64_t val = rd_mem_i32({mem_arg}base, off);\n    trace_mem_read_word{suffix}(t, pc, op, phys_addr(base) + off, (uint32_t)val);\n    return val;\n}}\n")
     .expect("formatting trd_mem_i32");
     writeln!(
         out,
-        "__attribute__((hot, nonnull, always_inline))\nstatic inline uint64_t trd_mem_u64(Tracer* t, {addr_type} pc, uint16_t op, {mem_param}{addr_type} base, int16_t off) {{\n    uint64_t val = rd_mem_u64({mem_arg}base, off);\n    trace_mem_read_dword(t, pc, op, phys_addr(base) + off, val);\n    return val;\n}}")
+        "__attribute__((hot, nonnull, always_inline))\nstatic inline uint64_t trd_mem_u64(Tracer* t, {addr_type} pc, uint16_t op, {mem_param}{addr_type} base, int16_t off) {{\n    uint64_t val = rd_mem_u64({mem_arg}base, off);\n    trace_mem_read_dword{suffix}(t, pc, op, phys_addr(base) + off, val);\n    return val;\n}}")
     .expect("formatting trd_mem_u64");
 }
 
-fn push_trace_mem_writes(out: &mut String, addr_type: &str, mem_param: &str, mem_arg: &str) {
+fn push_trace_mem_writes(
+    out: &mut String,
+    addr_type: &str,
+    mem_param: &str,
+    mem_arg: &str,
+    suffix: &str,
+) {
     out.push_str(TRACE_MEM_WRITES_HEADER);
     writeln!(
         out,
-        "__attribute__((hot, nonnull, always_inline))\nstatic inline void twr_mem_u8(Tracer* t, {addr_type} pc, uint16_t op, {mem_param}{addr_type} base, int16_t off, uint32_t val) {{\n    trace_mem_write_byte(t, pc, op, phys_addr(base) + off, (uint8_t)val);\n    wr_mem_u8({mem_arg}base, off, val);\n}}\n")
+        "__attribute__((hot, nonnull, always_inline))\nstatic inline void twr_mem_u8(Tracer* t, {addr_type} pc, uint16_t op, {mem_param}{addr_type} base, int16_t off, uint32_t val) {{\n    trace_mem_write_byte{suffix}(t, pc, op, phys_addr(base) + off, (uint8_t)val);\n    wr_mem_u8({mem_arg}base, off, val);\n}}\n")
     .expect("formatting twr_mem_u8");
     writeln!(
         out,
-        "__attribute__((hot, nonnull, always_inline))\nstatic inline void twr_mem_u16(Tracer* t, {addr_type} pc, uint16_t op, {mem_param}{addr_type} base, int16_t off, uint32_t val) {{\n    trace_mem_write_halfword(t, pc, op, phys_addr(base) + off, (uint16_t)val);\n    wr_mem_u16({mem_arg}base, off, val);\n}}\n")
+        "__attribute__((hot, nonnull, always_inline))\nstatic inline void twr_mem_u16(Tracer* t, {addr_type} pc, uint16_t op, {mem_param}{addr_type} base, int16_t off, uint32_t val) {{\n    trace_mem_write_halfword{suffix}(t, pc, op, phys_addr(base) + off, (uint16_t)val);\n    wr_mem_u16({mem_arg}base, off, val);\n}}\n")
     .expect("formatting twr_mem_u16");
     writeln!(
         out,
-        "__attribute__((hot, nonnull, always_inline))\nstatic inline void twr_mem_u32(Tracer* t, {addr_type} pc, uint16_t op, {mem_param}{addr_type} base, int16_t off, uint32_t val) {{\n    trace_mem_write_word(t, pc, op, phys_addr(base) + off, val);\n    wr_mem_u32({mem_arg}base, off, val);\n}}\n")
+        "__attribute__((hot, nonnull, always_inline))\nstatic inline void twr_mem_u32(Tracer* t, {addr_type} pc, uint16_t op, {mem_param}{addr_type} base, int16_t off, uint32_t val) {{\n    trace_mem_write_word{suffix}(t, pc, op, phys_addr(base) + off, val);\n    wr_mem_u32({mem_arg}base, off, val);\n}}\n")
     .expect("formatting twr_mem_u32");
     writeln!(
         out,
-        "__attribute__((hot, nonnull, always_inline))\nstatic inline void twr_mem_u64(Tracer* t, {addr_type} pc, uint16_t op, {mem_param}{addr_type} base, int16_t off, uint64_t val) {{\n    trace_mem_write_dword(t, pc, op, phys_addr(base) + off, val);\n    wr_mem_u64({mem_arg}base, off, val);\n}}")
+        "__attribute__((hot, nonnull, always_inline))\nstatic inline void twr_mem_u64(Tracer* t, {addr_type} pc, uint16_t op, {mem_param}{addr_type} base, int16_t off, uint64_t val) {{\n    trace_mem_write_dword{suffix}(t, pc, op, phys_addr(base) + off, val);\n    wr_mem_u64({mem_arg}base, off, val);\n}}")
     .expect("formatting twr_mem_u64");
 }
 
@@ -112,27 +221,28 @@ fn push_trace_reg_helpers(
     addr_type: &str,
     state_param: &str,
     state_ref: &str,
+    suffix: &str,
 ) {
     out.push_str(TRACE_REG_HEADER);
     writeln!(
         out,
-        "__attribute__((hot, nonnull, always_inline))\nstatic inline {rtype} trd_reg(Tracer* t, {addr_type} pc, uint16_t op, {state_param}uint8_t reg) {{\n    {rtype} val = {state_ref}->regs[reg];\n    trace_reg_read(t, pc, op, reg, val);\n    return val;\n}}\n")
+        "__attribute__((hot, nonnull, always_inline))\nstatic inline {rtype} trd_reg(Tracer* t, {addr_type} pc, uint16_t op, {state_param}uint8_t reg) {{\n    {rtype} val = {state_ref}->regs[reg];\n    trace_reg_read{suffix}(t, pc, op, reg, val);\n    return val;\n}}\n")
     .expect("formatting trd_reg");
     writeln!(
         out,
-        "__attribute__((hot, nonnull, always_inline))\nstatic inline void twr_reg(Tracer* t, {addr_type} pc, uint16_t op, {state_param}uint8_t reg, {rtype} val) {{\n    trace_reg_write(t, pc, op, reg, val);\n    {state_ref}->regs[reg] = val;\n}}")
+        "__attribute__((hot, nonnull, always_inline))\nstatic inline void twr_reg(Tracer* t, {addr_type} pc, uint16_t op, {state_param}uint8_t reg, {rtype} val) {{\n    trace_reg_write{suffix}(t, pc, op, reg, val);\n    {state_ref}->regs[reg] = val;\n}}")
     .expect("formatting twr_reg");
 }
 
-fn push_trace_regval_helpers(out: &mut String, rtype: &str, addr_type: &str) {
+fn push_trace_regval_helpers(out: &mut String, rtype: &str, addr_type: &str, suffix: &str) {
     out.push_str(TRACE_REGVAL_HEADER);
     writeln!(
         out,
-        "__attribute__((hot, always_inline))\nstatic inline {rtype} trd_regval(Tracer* t, {addr_type} pc, uint16_t op, uint8_t reg, {rtype} val) {{\n    trace_reg_read(t, pc, op, reg, val);\n    return val;\n}}\n")
+        "__attribute__((hot, always_inline))\nstatic inline {rtype} trd_regval(Tracer* t, {addr_type} pc, uint16_t op, uint8_t reg, {rtype} val) {{\n    trace_reg_read{suffix}(t, pc, op, reg, val);\n    return val;\n}}\n")
     .expect("formatting trd_regval");
     writeln!(
         out,
-        "__attribute__((hot, always_inline))\nstatic inline {rtype} twr_regval(Tracer* t, {addr_type} pc, uint16_t op, uint8_t reg, {rtype} val) {{\n    trace_reg_write(t, pc, op, reg, val);\n    return val;\n}}")
+        "__attribute__((hot, always_inline))\nstatic inline {rtype} twr_regval(Tracer* t, {addr_type} pc, uint16_t op, uint8_t reg, {rtype} val) {{\n    trace_reg_write{suffix}(t, pc, op, reg, val);\n    return val;\n}}")
     .expect("formatting twr_regval");
 }
 
@@ -142,16 +252,70 @@ fn push_trace_csr_helpers(
     addr_type: &str,
     state_param: &str,
     state_arg: &str,
-    instret_param: &str,
-    instret_arg: &str,
+    counts_instret: bool,
+    suffix: &str,
 ) {
+    let (instret_param, instret_arg) = if counts_instret {
+        (", uint64_t instret", ", instret")
+    } else {
+        ("", "")
+    };
     out.push_str(TRACE_CSR_HEADER);
     writeln!(
         out,
-        "__attribute__((hot, nonnull))\nstatic inline {rtype} trd_csr(Tracer* t, {addr_type} pc, uint16_t op, {state_param}uint16_t csr{instret_param}) {{\n    {rtype} val = rd_csr({state_arg}csr{instret_arg});\n    trace_csr_read(t, pc, op, csr, val);\n    return val;\n}}\n")
+        "__attribute__((hot, nonnull))\nstatic inline {rtype} trd_csr(Tracer* t, {addr_type} pc, uint16_t op, {state_param}uint16_t csr{instret_param}) {{\n    {rtype} val = rd_csr({state_arg}csr{instret_arg});\n    trace_csr_read{suffix}(t, pc, op, csr, val);\n    return val;\n}}\n")
     .expect("formatting trd_csr");
     writeln!(
         out,
-        "__attribute__((hot, nonnull))\nstatic inline void twr_csr(Tracer* t, {addr_type} pc, uint16_t op, {state_param}uint16_t csr, {rtype} val) {{\n    trace_csr_write(t, pc, op, csr, val);\n    wr_csr({state_arg}csr, val);\n}}")
+        "__attribute__((hot, nonnull))\nstatic inline void twr_csr(Tracer* t, {addr_type} pc, uint16_t op, {state_param}uint16_t csr, {rtype} val) {{\n    trace_csr_write{suffix}(t, pc, op, csr, val);\n    wr_csr({state_arg}csr, val);\n}}")
     .expect("formatting twr_csr");
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::c::{HeaderConfig, TracerConfig, gen_header};
+    use crate::{EmitConfig, EmitInputs};
+    use rvr_ir::Rv64;
+
+    fn header(tracer: TracerConfig) -> String {
+        let mut config = EmitConfig::<Rv64>::standard();
+        config.tracer_config = tracer;
+        let inputs = EmitInputs::new(0x8000_0000, 0x8000_0008);
+        gen_header::<Rv64>(&HeaderConfig::new(
+            "test",
+            &config,
+            &inputs,
+            vec![0x8000_0000],
+        ))
+    }
+
+    #[test]
+    fn test_sampled_tracer_wraps_hooks() {
+        let header = header(TracerConfig::preflight().with_sample_interval(10_000));
+        assert!(header.contains("#include \"rv_tracer.h\"\n/* Branch prediction hints */"));
+        assert!(header.contains("constexpr uint64_t RV_SAMPLE_INTERVAL = 10000ull;"));
+        assert!(header.contains("    if (likely(s->trace_countdown-- != 0)) return;"));
+        assert!(header.contains("    trace_pc(t, pc, op);\n"));
+        assert!(header.contains(
+            "static inline void trace_mem_write_word_sampled(Tracer* t, uint64_t pc, uint16_t op, \
+             uint64_t addr, uint32_t value) {\n    if (unlikely(rv_trace_sampled(t))) \
+             trace_mem_write_word(t, pc, op, addr, value);\n}"
+        ));
+        // The helpers blocks call go through the wrappers
+        assert!(
+            header.contains(
+                "    trace_mem_write_word_sampled(t, pc, op, phys_addr(base) + off, val);"
+            )
+        );
+        // The wrappers need RvState
+        assert!(header.find("typedef struct RvState") < header.find("rv_tracer_state(Tracer* t)"));
+    }
+
+    #[test]
+    fn test_unsampled_tracer_calls_hooks() {
+        let header = header(TracerConfig::preflight().with_sample_interval(1));
+        assert!(header.contains("#include \"rv_tracer.h\"\n/* Branch prediction hints */"));
+        assert!(!header.contains("_sampled("));
+        assert!(!header.contains("RV_SAMPLE_INTERVAL"));
+    }
+}
@@ -2,17 +2,34 @@
 //!
 //! Generates C code to handle HTIF protocol used by riscv-tests for:
 //! - Exit signaling (exit code via tohost)
-//! - Syscall handling (write to stdout)
+//! - Syscall proxying: tohost points to a `magic_mem` block of
+//!   `{num, arg0, arg1, arg2}` dwords; the result goes to `magic_mem[0]`,
+//!   then tohost is cleared and fromhost set to 1 to end the guest's poll.
+//!   write (stdout/stderr), read (stdin), fstat (stdio) and exit are
+//!   supported; stdio goes through the runner's `RvIo` hooks when installed.
+//! - A poll watchdog: C blocks and the assembly backends call `htif_poll`
+//!   before loading `fromhost`, which stops the guest with
+//!   `RV_EXIT_HTIF_STALL` once it has polled `HTIF_POLL_LIMIT` times without
+//!   `fromhost` changing or a new request.
 
 use rvr_ir::Xlen;
 
-use crate::htif::{FROMHOST_ADDR, STDOUT_FD, SYS_WRITE, TOHOST_ADDR};
+use super::config::CDialect;
+use super::namespace::global_symbol;
+use crate::config::DEFAULT_HTIF_POLL_LIMIT;
+use crate::htif::{FROMHOST_ADDR, SYS_EXIT, SYS_FSTAT, SYS_READ, SYS_WRITE, TOHOST_ADDR};
 
 /// Configuration for HTIF code generation.
 pub struct HtifConfig {
     pub base_name: String,
     pub enabled: bool,
     pub verbose: bool,
+    pub dialect: CDialect,
+    /// Unchanged `fromhost` polls before the guest stops (0 disables the
+    /// watchdog).
+    pub poll_limit: u64,
+    /// Prefix for global symbols (see `EmitConfig::symbol_prefix`).
+    pub symbol_prefix: String,
 }
 
 impl HtifConfig {
@@ -22,6 +39,9 @@ impl HtifConfig {
             base_name: base_name.to_string(),
             enabled,
             verbose: false,
+            dialect: CDialect::default(),
+            poll_limit: DEFAULT_HTIF_POLL_LIMIT,
+            symbol_prefix: String::new(),
         }
     }
 
@@ -30,6 +50,32 @@ impl HtifConfig {
         self.verbose = verbose;
         self
     }
+
+    #[must_use]
+    pub const fn with_dialect(mut self, dialect: CDialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_poll_limit(mut self, limit: u64) -> Self {
+        self.poll_limit = limit;
+        self
+    }
+
+    #[must_use]
+    pub fn with_symbol_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.symbol_prefix = prefix.into();
+        self
+    }
+
+    /// Linked names of the tohost handler and the poll watchdog.
+    fn handler_names(&self) -> (String, String) {
+        (
+            global_symbol(&self.symbol_prefix, "handle_tohost_write"),
+            global_symbol(&self.symbol_prefix, "htif_poll"),
+        )
+    }
 }
 
 const fn addr_type<X: Xlen>() -> &'static str {
@@ -40,6 +86,20 @@ const fn addr_type<X: Xlen>() -> &'static str {
     }
 }
 
+/// Attribute specifier of `handle_tohost_write` with `attrs`, plus
+/// `preserve_most` in clang code so callers keep their registers live.
+fn handler_attrs(dialect: CDialect, attrs: &[&str]) -> String {
+    let mut attrs = attrs.to_vec();
+    if dialect == CDialect::Clang {
+        attrs.push("preserve_most");
+    }
+    if attrs.is_empty() {
+        String::new()
+    } else {
+        format!("__attribute__(({})) ", attrs.join(", "))
+    }
+}
+
 /// Generate HTIF header file content.
 #[must_use]
 pub fn gen_htif_header<X: Xlen>(cfg: &HtifConfig) -> String {
@@ -51,23 +111,54 @@ pub fn gen_htif_header<X: Xlen>(cfg: &HtifConfig) -> String {
     }
 
     let addr_type = addr_type::<X>();
+    // Syscall numbers are case labels; field size is 8 (64-bit fields)
+    let mut constants = String::new();
+    for (ty, name, value, in_const_exprs) in [
+        (
+            "uint64_t",
+            "HTIF_TOHOST_ADDR",
+            format!("{TOHOST_ADDR:#x}"),
+            false,
+        ),
+        (
+            "uint64_t",
+            "HTIF_FROMHOST_ADDR",
+            format!("{FROMHOST_ADDR:#x}"),
+            false,
+        ),
+        ("uint32_t", "HTIF_FIELD_SIZE", "8".to_string(), false),
+        ("uint64_t", "HTIF_SYS_READ", SYS_READ.to_string(), true),
+        ("uint64_t", "HTIF_SYS_WRITE", SYS_WRITE.to_string(), true),
+        ("uint64_t", "HTIF_SYS_FSTAT", SYS_FSTAT.to_string(), true),
+        ("uint64_t", "HTIF_SYS_EXIT", SYS_EXIT.to_string(), true),
+        (
+            "uint64_t",
+            "HTIF_POLL_LIMIT",
+            cfg.poll_limit.to_string(),
+            false,
+        ),
+    ] {
+        constants.push_str(&cfg.dialect.constant(ty, name, &value, in_const_exprs));
+        constants.push('\n');
+    }
+    let attrs = handler_attrs(cfg.dialect, &[]);
+    let (tohost, poll) = cfg.handler_names();
     format!(
         r"#pragma once
 
+#include <stdbool.h>
 #include <stdint.h>
 
 /* Forward declaration to avoid circular includes */
 typedef struct RvState RvState;
 
-/* HTIF constants (C23 constexpr) */
-constexpr uint64_t HTIF_TOHOST_ADDR   = {TOHOST_ADDR:#x};
-constexpr uint64_t HTIF_FROMHOST_ADDR = {FROMHOST_ADDR:#x};
-constexpr uint32_t HTIF_FIELD_SIZE    = 8;  /* 64-bit fields */
-constexpr uint64_t HTIF_SYS_WRITE     = {SYS_WRITE};
-constexpr uint64_t HTIF_STDOUT_FD     = {STDOUT_FD};
-
+/* HTIF constants */
+{constants}
 /* HTIF handler - called when writing to TOHOST address */
-__attribute__((preserve_most)) void handle_tohost_write(RvState* restrict state, {addr_type} value);
+{attrs}void {tohost}(RvState* restrict state, {addr_type} value);
+
+/* HTIF poll watchdog - called before loading FROMHOST; true once the guest stalled */
+{attrs}bool {poll}(RvState* restrict state);
 ",
     )
 }
@@ -85,67 +176,179 @@ pub fn gen_htif_source<X: Xlen>(cfg: &HtifConfig) -> String {
     }
 
     let addr_type = addr_type::<X>();
-
-    let print_code = if cfg.verbose {
-        format!(
-            r"for ({addr_type} i = 0; i < length; ++i) {{
-            fputc((int)(state->memory[buffer_addr + i] & 0xFFu), stdout);
-        }}
-        fflush(stdout);"
-        )
-    } else {
-        String::new()
-    };
+    let helpers = gen_syscall_helpers::<X>(cfg);
+    let attrs = handler_attrs(cfg.dialect, &["cold", "nonnull"]);
+    let (tohost, poll) = cfg.handler_names();
 
     format!(
         r#"#include "{base_name}.h"
 #include "{base_name}_htif.h"
 
-__attribute__((hot, pure, nonnull))
-static inline uint64_t read_memory_dword(RvState* restrict state, {addr_type} addr) {{
-    assert(addr <= RV_MEMORY_SIZE - 8 && "Memory dword read out of bounds");
-    uint64_t val;
-    memcpy(&val, &state->memory[addr], sizeof(val));
-    return val;
-}}
-
-__attribute__((cold, nonnull, preserve_most))
-void handle_tohost_write(RvState* restrict state, {addr_type} value) {{
+{helpers}
+{attrs}void {tohost}(RvState* restrict state, {addr_type} value) {{
     if (unlikely(value == 0)) return;
 
-    /* HTIF exit encoding: LSB=1 means exit, exit_code = value >> 1 */
+    /* HTIF exit encoding: LSB=1 means exit, exit_code = value >> 1
+       (riscv-tests: the failing test number, 0 on pass) */
     if ((value & 1u) == 1u) {{
         state->exit_code = (uint8_t)((value >> 1) & 0xFFu);
+        state->exit_cause = RV_EXIT_HTIF;
+        state->exit_info = (uint64_t)(value >> 1);
         state->has_exited = true;
         return;
     }}
 
-    /* HTIF syscall: 64-bit fields at offsets 0, 8, 16, 24 */
-    uint64_t syscall_num = read_memory_dword(state, value);
-    uint64_t arg0 = read_memory_dword(state, value + HTIF_FIELD_SIZE);
-    uint64_t arg1 = read_memory_dword(state, value + HTIF_FIELD_SIZE * 2);
-    uint64_t arg2 = read_memory_dword(state, value + HTIF_FIELD_SIZE * 3);
-
-    if (likely(syscall_num == HTIF_SYS_WRITE && arg0 == HTIF_STDOUT_FD)) {{
-        {addr_type} buffer_addr = ({addr_type})arg1;
-        {addr_type} length = ({addr_type})arg2;
-
-        {print_code}
+    /* HTIF syscall: magic_mem with 64-bit fields at offsets 0, 8, 16, 24 */
+    uint64_t magic_mem = value;
+    uint64_t syscall_num = read_memory_dword(state, magic_mem);
+    state->htif_syscall = syscall_num;
+    state->htif_polls = 0;
+    uint64_t arg0 = read_memory_dword(state, magic_mem + HTIF_FIELD_SIZE);
+    uint64_t arg1 = read_memory_dword(state, magic_mem + HTIF_FIELD_SIZE * 2);
+    uint64_t arg2 = read_memory_dword(state, magic_mem + HTIF_FIELD_SIZE * 3);
 
-        /* Write return value and signal completion */
-        memcpy(&state->memory[value], &length, sizeof({addr_type}));
-        {addr_type} one = 1;
-        memcpy(&state->memory[HTIF_FROMHOST_ADDR], &one, sizeof(one));
-    }} else {{
-        fprintf(stderr, "Unsupported syscall: %llu\\n", (unsigned long long)syscall_num);
+    int64_t ret;
+    switch (syscall_num) {{
+    case HTIF_SYS_WRITE:
+        ret = htif_sys_write(state, arg0, arg1, arg2);
+        break;
+    case HTIF_SYS_READ:
+        ret = htif_sys_read(state, arg0, arg1, arg2);
+        break;
+    case HTIF_SYS_FSTAT:
+        ret = htif_sys_fstat(state, arg0, arg1);
+        break;
+    case HTIF_SYS_EXIT:
+        state->exit_code = (uint8_t)(arg0 & 0xFFu);
+        state->has_exited = true;
+        return;
+    default:
+        fprintf(stderr, "Unsupported HTIF syscall: %llu\n", (unsigned long long)syscall_num);
         state->exit_code = 1;
+        state->exit_cause = RV_EXIT_HOST_STOP;
+        state->exit_info = syscall_num;
         state->has_exited = true;
+        return;
     }}
+
+    /* Respond in the order the guest expects: result, then tohost cleared,
+       then fromhost set (the guest polls fromhost, clears it, reads magic_mem[0]) */
+    write_memory_dword(state, magic_mem, (uint64_t)ret);
+    write_memory_dword(state, HTIF_TOHOST_ADDR, 0);
+    write_memory_dword(state, HTIF_FROMHOST_ADDR, 1);
+}}
+
+{attrs}bool {poll}(RvState* restrict state) {{
+    uint64_t fromhost = read_memory_dword(state, HTIF_FROMHOST_ADDR);
+    if (fromhost != state->htif_fromhost) {{
+        state->htif_fromhost = fromhost;
+        state->htif_polls = 0;
+        return false;
+    }}
+    if (++state->htif_polls <= HTIF_POLL_LIMIT) return false;
+
+    /* Stalled handshake: the caller stores the polling PC */
+    state->exit_code = 1;
+    state->exit_cause = RV_EXIT_HTIF_STALL;
+    state->exit_info = read_memory_dword(state, HTIF_TOHOST_ADDR);
+    state->has_exited = true;
+    return true;
 }}
 "#,
         base_name = cfg.base_name,
-        addr_type = addr_type,
-        print_code = print_code,
+    )
+}
+
+/// Guest memory accessors and the proxied stdio syscalls.
+fn gen_syscall_helpers<X: Xlen>(cfg: &HtifConfig) -> String {
+    let addr_type = addr_type::<X>();
+
+    // Without runner hooks, guest output is only shown in verbose mode
+    let print_code = if cfg.verbose {
+        r"FILE* out = (fd == 1) ? stdout : stderr;
+    fwrite(ptr, 1, n, out);
+    fflush(out);"
+    } else {
+        "/* Output discarded (HTIF verbose off) */"
+    };
+
+    format!(
+        r"static const int64_t kHtifEbadf = 9;
+static const int64_t kHtifEfault = 14;
+/* fesvr's riscv_stat: 128 bytes, st_mode (uint32_t) at offset 16 */
+static const size_t kHtifStatSize = 128;
+static const size_t kHtifStatModeOffset = 16;
+static const uint32_t kHtifCharDeviceMode = 0020620; /* S_IFCHR | 0620 */
+
+__attribute__((hot, pure, nonnull))
+static inline uint64_t read_memory_dword(RvState* restrict state, uint64_t addr) {{
+    uint64_t val;
+    memcpy(&val, &state->memory[phys_addr(({addr_type})addr)], sizeof(val));
+    return val;
+}}
+
+__attribute__((nonnull))
+static inline void write_memory_dword(RvState* restrict state, uint64_t addr, uint64_t val) {{
+    memcpy(&state->memory[phys_addr(({addr_type})addr)], &val, sizeof(val));
+}}
+
+/* Host pointer to a guest buffer */
+__attribute__((nonnull))
+static inline uint8_t* htif_ptr(RvState* restrict state, uint64_t addr) {{
+    return &state->memory[phys_addr(({addr_type})addr)];
+}}
+
+/* Length of the guest range [addr, addr + count), clamped to the end of guest memory */
+static inline size_t htif_span(uint64_t addr, uint64_t count) {{
+    uint64_t avail = RV_MEMORY_MASK - (uint64_t)phys_addr(({addr_type})addr) + 1;
+    return count < avail ? (size_t)count : (size_t)avail;
+}}
+
+/* Touch every page of a buffer handed to the host's I/O: the kernel does not
+ * fault in guest memory that is committed on demand */
+static inline void htif_touch(const uint8_t* ptr, size_t n) {{
+    for (size_t off = 0; off < n; off += 4096) {{
+        (void)((const volatile uint8_t*)ptr)[off];
+    }}
+    if (n > 0) {{
+        (void)((const volatile uint8_t*)ptr)[n - 1];
+    }}
+}}
+
+/* The whole buffer is written at once, so guest lines are never split */
+static int64_t htif_sys_write(RvState* restrict state, uint64_t fd, uint64_t buf, uint64_t len) {{
+    if (fd != 1 && fd != 2) return -kHtifEbadf;
+    uint8_t* ptr = htif_ptr(state, buf);
+    size_t n = htif_span(buf, len);
+    htif_touch(ptr, n);
+    if (state->io) {{
+        return state->io->write(state->io->ctx, (uint32_t)fd, ptr, n);
+    }}
+    {print_code}
+    return (int64_t)n;
+}}
+
+static int64_t htif_sys_read(RvState* restrict state, uint64_t fd, uint64_t buf, uint64_t len) {{
+    if (fd != 0) return -kHtifEbadf;
+    uint8_t* ptr = htif_ptr(state, buf);
+    size_t n = htif_span(buf, len);
+    htif_touch(ptr, n);
+    if (state->io) {{
+        return state->io->read(state->io->ctx, (uint32_t)fd, ptr, n);
+    }}
+    return (int64_t)fread(ptr, 1, n, stdin);
+}}
+
+/* stdio are character devices; nothing else is open */
+static int64_t htif_sys_fstat(RvState* restrict state, uint64_t fd, uint64_t statbuf) {{
+    if (fd > 2) return -kHtifEbadf;
+    if (htif_span(statbuf, kHtifStatSize) < kHtifStatSize) return -kHtifEfault;
+    uint8_t* ptr = htif_ptr(state, statbuf);
+    memset(ptr, 0, kHtifStatSize);
+    memcpy(ptr + kHtifStatModeOffset, &kHtifCharDeviceMode, sizeof(kHtifCharDeviceMode));
+    return 0;
+}}
+"
     )
 }
 
@@ -177,7 +380,25 @@ mod tests {
         let source = gen_htif_source::<Rv64>(&cfg);
         assert!(source.contains("handle_tohost_write"));
         assert!(source.contains("read_memory_dword"));
-        assert!(source.contains("HTIF_SYS_WRITE"));
+        assert!(source.contains("case HTIF_SYS_WRITE:"));
+        assert!(source.contains("case HTIF_SYS_READ:"));
+        assert!(source.contains("case HTIF_SYS_FSTAT:"));
+        assert!(source.contains("case HTIF_SYS_EXIT:"));
+        assert!(source.contains("write_memory_dword(state, HTIF_FROMHOST_ADDR, 1);"));
+        assert!(source.contains("state->htif_syscall = syscall_num;"));
+        assert!(source.contains("state->exit_cause = RV_EXIT_HTIF_STALL;"));
+        assert!(!source.contains("fwrite(ptr"));
+
+        let verbose = gen_htif_source::<Rv64>(&cfg.with_verbose(true));
+        assert!(verbose.contains("fwrite(ptr, 1, n, out);"));
+    }
+
+    #[test]
+    fn test_gen_htif_header_fromhost_follows_tohost_line() {
+        let header = gen_htif_header::<Rv64>(&HtifConfig::new("test", true));
+        assert!(header.contains("HTIF_TOHOST_ADDR = 0x80001000;"));
+        assert!(header.contains("HTIF_FROMHOST_ADDR = 0x80001040;"));
+        assert!(header.contains("HTIF_POLL_LIMIT = 1048576;"));
     }
 
     #[test]
@@ -0,0 +1,243 @@
+//! Decoding: opcode numbers, instruction field extraction, and the
+//! expansion of compressed instructions to their 32-bit forms.
+
+/// Major opcodes and the instruction field helpers.
+pub(super) const FIELDS: &str = r"
+enum {
+    OPC_LOAD = 0x03,
+    OPC_MISC_MEM = 0x0f,
+    OPC_OP_IMM = 0x13,
+    OPC_AUIPC = 0x17,
+    OPC_OP_IMM_32 = 0x1b,
+    OPC_STORE = 0x23,
+    OPC_AMO = 0x2f,
+    OPC_OP = 0x33,
+    OPC_LUI = 0x37,
+    OPC_OP_32 = 0x3b,
+    OPC_BRANCH = 0x63,
+    OPC_JALR = 0x67,
+    OPC_JAL = 0x6f,
+};
+
+/* Bits hi..lo of x */
+static inline uint32_t bits(uint32_t x, uint32_t hi, uint32_t lo) {
+    return (x >> lo) & ((1u << (hi - lo + 1)) - 1);
+}
+
+/* The low `width` bits of value, sign-extended */
+static inline reg_t sext(reg_t value, uint32_t width) {
+    uint32_t shift = XLEN - width;
+    return (reg_t)((sreg_t)(value << shift) >> shift);
+}
+
+static inline int32_t simm(uint32_t value, uint32_t width) {
+    uint32_t shift = 32 - width;
+    return (int32_t)(value << shift) >> shift;
+}
+";
+
+pub(super) const EXPAND: &str = r"
+static inline uint32_t enc_r(uint32_t opcode, uint32_t rd, uint32_t funct3, uint32_t rs1,
+                             uint32_t rs2, uint32_t funct7) {
+    return funct7 << 25 | rs2 << 20 | rs1 << 15 | funct3 << 12 | rd << 7 | opcode;
+}
+
+static inline uint32_t enc_i(uint32_t opcode, uint32_t rd, uint32_t funct3, uint32_t rs1,
+                             int32_t imm) {
+    return ((uint32_t)imm & 0xfff) << 20 | rs1 << 15 | funct3 << 12 | rd << 7 | opcode;
+}
+
+static inline uint32_t enc_s(uint32_t funct3, uint32_t rs1, uint32_t rs2, int32_t imm) {
+    uint32_t u = (uint32_t)imm;
+    return (u >> 5 & 0x7f) << 25 | rs2 << 20 | rs1 << 15 | funct3 << 12 | (u & 31) << 7 |
+           OPC_STORE;
+}
+
+static inline uint32_t enc_b(uint32_t funct3, uint32_t rs1, int32_t imm) {
+    uint32_t u = (uint32_t)imm;
+    return (u >> 12 & 1) << 31 | (u >> 5 & 0x3f) << 25 | rs1 << 15 | funct3 << 12 |
+           (u >> 1 & 0xf) << 8 | (u >> 11 & 1) << 7 | OPC_BRANCH;
+}
+
+static inline uint32_t enc_j(uint32_t rd, int32_t imm) {
+    uint32_t u = (uint32_t)imm;
+    return (u >> 20 & 1) << 31 | (u >> 1 & 0x3ff) << 21 | (u >> 11 & 1) << 20 |
+           (u >> 12 & 0xff) << 12 | rd << 7 | OPC_JAL;
+}
+
+/* Quadrant 0: stack-pointer based addi and register-based loads and stores */
+static uint32_t expand_q0(uint32_t c) {
+    uint32_t rd = 8 + bits(c, 4, 2);
+    uint32_t rs1 = 8 + bits(c, 9, 7);
+    int32_t imm_w = (int32_t)(bits(c, 12, 10) << 3 | bits(c, 6, 6) << 2 | bits(c, 5, 5) << 6);
+    int32_t imm_d = (int32_t)(bits(c, 12, 10) << 3 | bits(c, 6, 5) << 6);
+    int32_t imm_b = (int32_t)(bits(c, 5, 5) << 1 | bits(c, 6, 6));
+    int32_t imm_h = (int32_t)(bits(c, 5, 5) << 1);
+    switch (bits(c, 15, 13)) {
+    case 0: {
+        int32_t imm = (int32_t)(bits(c, 12, 11) << 4 | bits(c, 10, 7) << 6 | bits(c, 6, 6) << 2 |
+                                bits(c, 5, 5) << 3);
+        return imm ? enc_i(OPC_OP_IMM, rd, 0, 2, imm) : 0;
+    }
+    case 2:
+        return enc_i(OPC_LOAD, rd, 2, rs1, imm_w);
+    case 3:
+        return XLEN == 64 ? enc_i(OPC_LOAD, rd, 3, rs1, imm_d) : 0;
+    case 4:
+        /* Zcb */
+        switch (bits(c, 12, 10)) {
+        case 0:
+            return enc_i(OPC_LOAD, rd, 4, rs1, imm_b);
+        case 1:
+            return enc_i(OPC_LOAD, rd, bits(c, 6, 6) ? 1 : 5, rs1, imm_h);
+        case 2:
+            return enc_s(0, rs1, rd, imm_b);
+        case 3:
+            return bits(c, 6, 6) ? 0 : enc_s(1, rs1, rd, imm_h);
+        default:
+            return 0;
+        }
+    case 6:
+        return enc_s(2, rs1, rd, imm_w);
+    case 7:
+        return XLEN == 64 ? enc_s(3, rs1, rd, imm_d) : 0;
+    default:
+        return 0;
+    }
+}
+
+/* Quadrant 1 MISC-ALU on rd' (and rs2') */
+static uint32_t expand_misc_alu(uint32_t c) {
+    uint32_t rd = 8 + bits(c, 9, 7);
+    uint32_t rs2 = 8 + bits(c, 4, 2);
+    uint32_t shamt = bits(c, 12, 12) << 5 | bits(c, 6, 2);
+    bool wide_shamt = XLEN == 32 && bits(c, 12, 12);
+    switch (bits(c, 11, 10)) {
+    case 0:
+        return wide_shamt ? 0 : enc_i(OPC_OP_IMM, rd, 5, rd, (int32_t)shamt);
+    case 1:
+        return wide_shamt ? 0 : enc_i(OPC_OP_IMM, rd, 5, rd, (int32_t)(0x400 | shamt));
+    case 2:
+        return enc_i(OPC_OP_IMM, rd, 7, rd, simm(shamt, 6));
+    default:
+        break;
+    }
+    if (!bits(c, 12, 12)) {
+        static const uint32_t funct3[4] = {0, 4, 6, 7};
+        uint32_t f2 = bits(c, 6, 5);
+        return enc_r(OPC_OP, rd, funct3[f2], rd, rs2, f2 == 0 ? 0x20 : 0);
+    }
+    switch (bits(c, 6, 5)) {
+    case 0:
+        return XLEN == 64 ? enc_r(OPC_OP_32, rd, 0, rd, rs2, 0x20) : 0;
+    case 1:
+        return XLEN == 64 ? enc_r(OPC_OP_32, rd, 0, rd, rs2, 0) : 0;
+    case 2:
+        return enc_r(OPC_OP, rd, 0, rd, rs2, 1);
+    default:
+        break;
+    }
+    /* Zcb unary operations */
+    switch (bits(c, 4, 2)) {
+    case 0:
+        return enc_i(OPC_OP_IMM, rd, 7, rd, 0xff);
+    case 1:
+        return enc_i(OPC_OP_IMM, rd, 1, rd, 0x604);
+    case 2:
+        return enc_r(XLEN == 64 ? OPC_OP_32 : OPC_OP, rd, 4, rd, 0, 0x04);
+    case 3:
+        return enc_i(OPC_OP_IMM, rd, 1, rd, 0x605);
+    case 4:
+        return XLEN == 64 ? enc_r(OPC_OP_32, rd, 0, rd, 0, 0x04) : 0;
+    case 5:
+        return enc_i(OPC_OP_IMM, rd, 4, rd, -1);
+    default:
+        return 0;
+    }
+}
+
+/* Quadrant 1: immediates, MISC-ALU, jumps and branches */
+static uint32_t expand_q1(uint32_t c) {
+    uint32_t rd = bits(c, 11, 7);
+    int32_t imm = simm(bits(c, 12, 12) << 5 | bits(c, 6, 2), 6);
+    int32_t imm_j = simm(bits(c, 12, 12) << 11 | bits(c, 11, 11) << 4 | bits(c, 10, 9) << 8 |
+                             bits(c, 8, 8) << 10 | bits(c, 7, 7) << 6 | bits(c, 6, 6) << 7 |
+                             bits(c, 5, 3) << 1 | bits(c, 2, 2) << 5,
+                         12);
+    int32_t imm_b = simm(bits(c, 12, 12) << 8 | bits(c, 11, 10) << 3 | bits(c, 6, 5) << 6 |
+                             bits(c, 4, 3) << 1 | bits(c, 2, 2) << 5,
+                         9);
+    switch (bits(c, 15, 13)) {
+    case 0:
+        return enc_i(OPC_OP_IMM, rd, 0, rd, imm);
+    case 1:
+        if (XLEN == 32) {
+            return enc_j(1, imm_j);
+        }
+        return rd ? enc_i(OPC_OP_IMM_32, rd, 0, rd, imm) : 0;
+    case 2:
+        return enc_i(OPC_OP_IMM, rd, 0, 0, imm);
+    case 3:
+        if (rd == 2) {
+            int32_t imm16 = simm(bits(c, 12, 12) << 9 | bits(c, 6, 6) << 4 | bits(c, 5, 5) << 6 |
+                                     bits(c, 4, 3) << 7 | bits(c, 2, 2) << 5,
+                                 10);
+            return imm16 ? enc_i(OPC_OP_IMM, 2, 0, 2, imm16) : 0;
+        }
+        return imm ? ((uint32_t)imm & 0xfffff) << 12 | rd << 7 | OPC_LUI : 0;
+    case 4:
+        return expand_misc_alu(c);
+    case 5:
+        return enc_j(0, imm_j);
+    case 6:
+        return enc_b(0, 8 + bits(c, 9, 7), imm_b);
+    default:
+        return enc_b(1, 8 + bits(c, 9, 7), imm_b);
+    }
+}
+
+/* Quadrant 2: shifts, stack-pointer based loads and stores, moves and jumps */
+static uint32_t expand_q2(uint32_t c) {
+    uint32_t rd = bits(c, 11, 7);
+    uint32_t rs2 = bits(c, 6, 2);
+    uint32_t shamt = bits(c, 12, 12) << 5 | rs2;
+    switch (bits(c, 15, 13)) {
+    case 0:
+        return XLEN == 32 && bits(c, 12, 12) ? 0 : enc_i(OPC_OP_IMM, rd, 1, rd, (int32_t)shamt);
+    case 2: {
+        int32_t imm = (int32_t)(bits(c, 12, 12) << 5 | bits(c, 6, 4) << 2 | bits(c, 3, 2) << 6);
+        return rd ? enc_i(OPC_LOAD, rd, 2, 2, imm) : 0;
+    }
+    case 3: {
+        int32_t imm = (int32_t)(bits(c, 12, 12) << 5 | bits(c, 6, 5) << 3 | bits(c, 4, 2) << 6);
+        return XLEN == 64 && rd ? enc_i(OPC_LOAD, rd, 3, 2, imm) : 0;
+    }
+    case 4:
+        if (rs2 != 0) {
+            /* c.mv, c.add */
+            return enc_r(OPC_OP, rd, 0, bits(c, 12, 12) ? rd : 0, rs2, 0);
+        }
+        /* c.jr, c.jalr (rd = 0 is c.ebreak or reserved) */
+        return rd ? enc_i(OPC_JALR, bits(c, 12, 12), 0, rd, 0) : 0;
+    case 6:
+        return enc_s(2, 2, rs2, (int32_t)(bits(c, 12, 9) << 2 | bits(c, 8, 7) << 6));
+    case 7:
+        return XLEN == 64 ? enc_s(3, 2, rs2, (int32_t)(bits(c, 12, 10) << 3 | bits(c, 9, 7) << 6))
+                          : 0;
+    default:
+        return 0;
+    }
+}
+
+/* 32-bit form of compressed instruction c; 0 (illegal) if it has none here */
+static uint32_t expand(uint32_t c) {
+    switch (c & 3) {
+    case 0:
+        return expand_q0(c);
+    case 1:
+        return expand_q1(c);
+    default:
+        return expand_q2(c);
+    }
+}
+";
@@ -0,0 +1,790 @@
+//! Execution: tracer hooks, register and memory access, and `step`,
+//! which runs one decoded 32-bit instruction.
+
+use std::fmt::Write;
+
+use rvr_ir::Xlen;
+use rvr_isa::LrScModel;
+
+use crate::c::cold::ColdPath;
+use crate::c::namespace::global_symbol;
+
+use crate::config::EmitConfig;
+use crate::htif::{FROMHOST_ADDR, TOHOST_ADDR};
+use crate::inputs::EmitInputs;
+/// `load`, `store`, the reservation helpers and the HTIF poll watchdog.
+pub(super) fn push_memory_access<X: Xlen>(
+    s: &mut String,
+    config: &EmitConfig<X>,
+    inputs: &EmitInputs,
+) {
+    s.push_str(LOAD);
+
+    let htif = config.htif_enabled();
+    let poll = if htif && config.htif_poll_limit > 0 {
+        format!(
+            "    return width >= 4 && (uint32_t)addr == 0x{FROMHOST_ADDR:x}u && {}(state);",
+            global_symbol(&config.symbol_prefix, "htif_poll")
+        )
+    } else {
+        "    (void)state;\n    (void)addr;\n    (void)width;\n    return false;".to_string()
+    };
+    let _ = write!(
+        s,
+        r"
+/* Whether the HTIF poll watchdog stops a load of width bytes at addr */
+static inline bool htif_stalled(RvState* restrict state, reg_t addr, uint32_t width) {{
+{poll}
+}}
+"
+    );
+
+    s.push_str("\n/* Store width bytes of value at addr; false if the store stopped the guest */\nstatic bool store(RvState* restrict state, reg_t pc, uint16_t op, reg_t addr, uint32_t width,\n                  uint64_t value) {\n");
+    let mut checks = Vec::new();
+    if config.detect_code_writes() && !inputs.code_ranges.is_empty() {
+        checks.push(("rv_is_code_write", ColdPath::CodeWrite));
+    }
+    if inputs.checked_stack_guard(config.address_mode).is_some() {
+        checks.push(("rv_is_stack_overflow", ColdPath::StackOverflow));
+    }
+    for (check, path) in checks {
+        let _ = writeln!(s, "    if (unlikely({check}(addr, width))) {{");
+        s.push_str("        state->pc = pc;\n        state->exit_info = addr;\n");
+        for store in path.status_stores("state") {
+            let _ = writeln!(s, "        {store}");
+        }
+        s.push_str("        return false;\n    }\n");
+    }
+    if htif {
+        let retire = if config.instret_mode.counts() {
+            "            state->instret++;\n"
+        } else {
+            ""
+        };
+        let tohost = global_symbol(&config.symbol_prefix, "handle_tohost_write");
+        let _ = write!(
+            s,
+            r"    if (width >= 4 && (uint32_t)addr == 0x{TOHOST_ADDR:x}u) {{
+        {tohost}(state, (reg_t)value);
+        if (unlikely(state->has_exited)) {{
+            /* The store retires */
+            state->pc = pc;
+{retire}            return false;
+        }}
+        return true;
+    }}
+"
+        );
+    }
+    s.push_str(
+        r"    on_mem_write(state, pc, op, phys_addr(addr), width, value);
+    memcpy(guest_memory(state) + phys_addr(addr), &value, width);
+    return true;
+}
+",
+    );
+
+    let (reserve, reserved, invalidate) = match config.lrsc_model {
+        LrScModel::AddressReservation => (
+            "    state->reservation_addr = addr;\n    state->reservation_valid = 1;",
+            "    return state->reservation_valid && state->reservation_addr == addr;",
+            "    if (((addr ^ state->reservation_addr) & ~(reg_t)(RESERVATION_GRANULE - 1)) == 0) {\n        state->reservation_valid = 0;\n    }",
+        ),
+        LrScModel::AlwaysSucceed => (
+            "    (void)state;\n    (void)addr;",
+            "    (void)state;\n    (void)addr;\n    return true;",
+            "    (void)state;\n    (void)addr;",
+        ),
+    };
+    let _ = write!(
+        s,
+        r"
+/* LR reservation (see LrScModel) */
+enum {{ RESERVATION_GRANULE = {granule} }};
+
+static inline void reserve(RvState* restrict state, reg_t addr) {{
+{reserve}
+}}
+
+static inline bool reserved(RvState* restrict state, reg_t addr) {{
+{reserved}
+}}
+
+/* A store to the reserved granule clears the reservation */
+static inline void invalidate(RvState* restrict state, reg_t addr) {{
+{invalidate}
+}}
+",
+        granule = rvr_isa::RESERVATION_GRANULE,
+    );
+}
+
+/// The tracer hooks, or empty ones without a tracer.
+pub(super) fn push_trace_hooks<X: Xlen>(s: &mut String, config: &EmitConfig<X>) {
+    if config.has_tracing() {
+        s.push_str(&TRACE_HOOKS.replace("{sampled}", config.tracer_config.hook_suffix()));
+    } else {
+        s.push_str(NO_TRACE_HOOKS);
+    }
+}
+
+/// Register access and the bit-manipulation and M-extension helpers.
+pub(super) fn push_helpers<X: Xlen>(s: &mut String) {
+    s.push_str(REG_HELPERS);
+    s.push_str(if X::VALUE == 64 {
+        RV64_HELPERS
+    } else {
+        RV32_HELPERS
+    });
+}
+
+/// The ALU and AMO operations and `step`.
+pub(super) fn push_step<X: Xlen>(s: &mut String) {
+    s.push_str(ALU);
+    if X::VALUE == 64 {
+        s.push_str(ALU_32);
+    }
+    s.push_str(AMO);
+    s.push_str(STEP_HEAD);
+    if X::VALUE == 64 {
+        s.push_str(STEP_RV64);
+    }
+    s.push_str(STEP_TAIL);
+}
+
+/// Tracer hooks, called with `{sampled}` replaced by the hook suffix.
+const TRACE_HOOKS: &str = r"
+/* Tracer hooks, as compiled blocks call them */
+static inline void on_instr(RvState* restrict state, reg_t pc, uint16_t op, uint32_t raw) {
+    trace_pc{sampled}(&state->tracer, pc, op);
+    trace_opcode{sampled}(&state->tracer, pc, op, raw);
+}
+
+static inline void on_reg_read(RvState* restrict state, reg_t pc, uint16_t op, uint32_t reg,
+                               reg_t value) {
+    trace_reg_read{sampled}(&state->tracer, pc, op, (uint8_t)reg, value);
+}
+
+static inline void on_reg_write(RvState* restrict state, reg_t pc, uint16_t op, uint32_t reg,
+                                reg_t value) {
+    trace_reg_write{sampled}(&state->tracer, pc, op, (uint8_t)reg, value);
+}
+
+static inline void on_mem_read(RvState* restrict state, reg_t pc, uint16_t op, reg_t addr,
+                               uint32_t width, uint64_t value) {
+    switch (width) {
+    case 1:
+        trace_mem_read_byte{sampled}(&state->tracer, pc, op, addr, (uint8_t)value);
+        break;
+    case 2:
+        trace_mem_read_halfword{sampled}(&state->tracer, pc, op, addr, (uint16_t)value);
+        break;
+    case 4:
+        trace_mem_read_word{sampled}(&state->tracer, pc, op, addr, (uint32_t)value);
+        break;
+    default:
+        trace_mem_read_dword{sampled}(&state->tracer, pc, op, addr, value);
+        break;
+    }
+}
+
+static inline void on_mem_write(RvState* restrict state, reg_t pc, uint16_t op, reg_t addr,
+                                uint32_t width, uint64_t value) {
+    switch (width) {
+    case 1:
+        trace_mem_write_byte{sampled}(&state->tracer, pc, op, addr, (uint8_t)value);
+        break;
+    case 2:
+        trace_mem_write_halfword{sampled}(&state->tracer, pc, op, addr, (uint16_t)value);
+        break;
+    case 4:
+        trace_mem_write_word{sampled}(&state->tracer, pc, op, addr, (uint32_t)value);
+        break;
+    default:
+        trace_mem_write_dword{sampled}(&state->tracer, pc, op, addr, value);
+        break;
+    }
+}
+
+static inline void on_branch(RvState* restrict state, reg_t pc, uint16_t op, bool taken,
+                             reg_t target) {
+    if (taken) {
+        trace_branch_taken{sampled}(&state->tracer, pc, op, target);
+    } else {
+        trace_branch_not_taken{sampled}(&state->tracer, pc, op, target);
+    }
+}
+";
+
+const NO_TRACE_HOOKS: &str = r"
+/* No tracer */
+static inline void on_instr(RvState* restrict state, reg_t pc, uint16_t op, uint32_t raw) {}
+static inline void on_reg_read(RvState* restrict state, reg_t pc, uint16_t op, uint32_t reg,
+                               reg_t value) {}
+static inline void on_reg_write(RvState* restrict state, reg_t pc, uint16_t op, uint32_t reg,
+                                reg_t value) {}
+static inline void on_mem_read(RvState* restrict state, reg_t pc, uint16_t op, reg_t addr,
+                               uint32_t width, uint64_t value) {}
+static inline void on_mem_write(RvState* restrict state, reg_t pc, uint16_t op, reg_t addr,
+                                uint32_t width, uint64_t value) {}
+static inline void on_branch(RvState* restrict state, reg_t pc, uint16_t op, bool taken,
+                             reg_t target) {}
+";
+
+const REG_HELPERS: &str = r"
+static inline reg_t get_reg(RvState* restrict state, reg_t pc, uint16_t op, uint32_t reg) {
+    if (reg == 0) {
+        return 0;
+    }
+    reg_t value = state->regs[reg];
+    on_reg_read(state, pc, op, reg, value);
+    return value;
+}
+
+static inline void set_reg(RvState* restrict state, reg_t pc, uint16_t op, uint32_t reg,
+                           reg_t value) {
+    if (reg == 0) {
+        return;
+    }
+    on_reg_write(state, pc, op, reg, value);
+    state->regs[reg] = value;
+}
+
+static inline reg_t rotr(reg_t a, uint32_t shamt) {
+    shamt &= XLEN - 1;
+    return shamt ? (a >> shamt) | (a << (XLEN - shamt)) : a;
+}
+
+static inline reg_t rotl(reg_t a, uint32_t shamt) {
+    return rotr(a, (XLEN - (shamt & (XLEN - 1))) & (XLEN - 1));
+}
+
+static reg_t orc_b(reg_t a) {
+    reg_t out = 0;
+    for (uint32_t i = 0; i < XLEN; i += 8) {
+        if ((a >> i) & 0xff) {
+            out |= (reg_t)0xff << i;
+        }
+    }
+    return out;
+}
+
+static reg_t brev8(reg_t a) {
+    reg_t out = 0;
+    for (uint32_t i = 0; i < XLEN; i++) {
+        out |= ((a >> i) & 1) << ((i & ~7u) | (7 - (i & 7)));
+    }
+    return out;
+}
+
+static reg_t zip(reg_t a) {
+    reg_t out = 0;
+    for (uint32_t i = 0; i < XLEN / 2; i++) {
+        out |= ((a >> i) & 1) << (2 * i);
+        out |= ((a >> (i + XLEN / 2)) & 1) << (2 * i + 1);
+    }
+    return out;
+}
+
+static reg_t unzip(reg_t a) {
+    reg_t out = 0;
+    for (uint32_t i = 0; i < XLEN / 2; i++) {
+        out |= ((a >> (2 * i)) & 1) << i;
+        out |= ((a >> (2 * i + 1)) & 1) << (i + XLEN / 2);
+    }
+    return out;
+}
+";
+
+const RV64_HELPERS: &str = r"
+static inline reg_t clz(reg_t a) { return a ? (reg_t)__builtin_clzll(a) : 64; }
+static inline reg_t ctz(reg_t a) { return a ? (reg_t)__builtin_ctzll(a) : 64; }
+static inline reg_t cpop(reg_t a) { return (reg_t)__builtin_popcountll(a); }
+static inline reg_t rev8(reg_t a) { return __builtin_bswap64(a); }
+static inline reg_t mulh(reg_t a, reg_t b) { return rv_mulh64((int64_t)a, (int64_t)b); }
+static inline reg_t mulhsu(reg_t a, reg_t b) { return rv_mulhsu64((int64_t)a, b); }
+static inline reg_t mulhu(reg_t a, reg_t b) { return rv_mulhu64(a, b); }
+static inline reg_t div_s(reg_t a, reg_t b) { return rv_div64((int64_t)a, (int64_t)b); }
+static inline reg_t div_u(reg_t a, reg_t b) { return rv_divu64(a, b); }
+static inline reg_t rem_s(reg_t a, reg_t b) { return rv_rem64((int64_t)a, (int64_t)b); }
+static inline reg_t rem_u(reg_t a, reg_t b) { return rv_remu64(a, b); }
+";
+
+const RV32_HELPERS: &str = r"
+static inline reg_t clz(reg_t a) { return a ? (reg_t)__builtin_clz(a) : 32; }
+static inline reg_t ctz(reg_t a) { return a ? (reg_t)__builtin_ctz(a) : 32; }
+static inline reg_t cpop(reg_t a) { return (reg_t)__builtin_popcount(a); }
+static inline reg_t rev8(reg_t a) { return __builtin_bswap32(a); }
+static inline reg_t mulh(reg_t a, reg_t b) { return rv_mulh((int32_t)a, (int32_t)b); }
+static inline reg_t mulhsu(reg_t a, reg_t b) { return rv_mulhsu((int32_t)a, b); }
+static inline reg_t mulhu(reg_t a, reg_t b) { return rv_mulhu(a, b); }
+static inline reg_t div_s(reg_t a, reg_t b) { return rv_div((int32_t)a, (int32_t)b); }
+static inline reg_t div_u(reg_t a, reg_t b) { return rv_divu(a, b); }
+static inline reg_t rem_s(reg_t a, reg_t b) { return rv_rem((int32_t)a, (int32_t)b); }
+static inline reg_t rem_u(reg_t a, reg_t b) { return rv_remu(a, b); }
+";
+
+const LOAD: &str = r"
+/* Load width bytes at addr, sign-extended if sign */
+static reg_t load(RvState* restrict state, reg_t pc, uint16_t op, reg_t addr, uint32_t width,
+                  bool sign) {
+    uint64_t value = 0;
+    memcpy(&value, guest_memory(state) + phys_addr(addr), width);
+    on_mem_read(state, pc, op, phys_addr(addr), width, value);
+    return sign ? sext((reg_t)value, 8 * width) : (reg_t)value;
+}
+";
+
+const ALU: &str = r"
+/* OP-IMM: register-immediate operations (I, Zbb, Zbs, Zbkb) */
+static bool op_imm(uint32_t raw, reg_t a, reg_t* out) {
+    uint32_t imm12 = raw >> 20;
+    reg_t imm = sext(imm12, 12);
+    uint32_t shamt = imm12 & (XLEN - 1);
+    bool shift = XLEN == 64 || !bits(raw, 25, 25);
+    switch (bits(raw, 14, 12)) {
+    case 0:
+        *out = a + imm;
+        return true;
+    case 2:
+        *out = (sreg_t)a < (sreg_t)imm;
+        return true;
+    case 3:
+        *out = a < imm;
+        return true;
+    case 4:
+        *out = a ^ imm;
+        return true;
+    case 6:
+        *out = a | imm;
+        return true;
+    case 7:
+        *out = a & imm;
+        return true;
+    case 1:
+        switch (imm12) {
+        case 0x600:
+            *out = clz(a);
+            return true;
+        case 0x601:
+            *out = ctz(a);
+            return true;
+        case 0x602:
+            *out = cpop(a);
+            return true;
+        case 0x604:
+            *out = sext(a, 8);
+            return true;
+        case 0x605:
+            *out = sext(a, 16);
+            return true;
+        case 0x08f:
+            *out = zip(a);
+            return XLEN == 32;
+        default:
+            break;
+        }
+        switch (shift ? raw >> 26 : ~0u) {
+        case 0x00:
+            *out = a << shamt;
+            return true;
+        case 0x0a:
+            *out = a | ((reg_t)1 << shamt);
+            return true;
+        case 0x12:
+            *out = a & ~((reg_t)1 << shamt);
+            return true;
+        case 0x1a:
+            *out = a ^ ((reg_t)1 << shamt);
+            return true;
+        default:
+            return false;
+        }
+    default:
+        switch (imm12) {
+        case 0x287:
+            *out = orc_b(a);
+            return true;
+        case 0x687:
+            *out = brev8(a);
+            return true;
+        case 0x08f:
+            *out = unzip(a);
+            return XLEN == 32;
+        default:
+            break;
+        }
+        if (imm12 == (XLEN == 64 ? 0x6b8u : 0x698u)) {
+            *out = rev8(a);
+            return true;
+        }
+        switch (shift ? raw >> 26 : ~0u) {
+        case 0x00:
+            *out = a >> shamt;
+            return true;
+        case 0x10:
+            *out = (reg_t)((sreg_t)a >> shamt);
+            return true;
+        case 0x12:
+            *out = (a >> shamt) & 1;
+            return true;
+        case 0x18:
+            *out = rotr(a, shamt);
+            return true;
+        default:
+            return false;
+        }
+    }
+}
+
+/* OP: register-register operations (I, M, Zba, Zbb, Zbs, Zbkb, Zicond),
+ * keyed by funct7 and funct3 */
+static bool op_reg(uint32_t raw, reg_t a, reg_t b, reg_t* out) {
+    uint32_t shamt = (uint32_t)b & (XLEN - 1);
+    reg_t half = ((reg_t)1 << (XLEN / 2)) - 1;
+    switch ((raw >> 25) << 3 | bits(raw, 14, 12)) {
+    case 0x00 << 3 | 0: *out = a + b; return true;
+    case 0x00 << 3 | 1: *out = a << shamt; return true;
+    case 0x00 << 3 | 2: *out = (sreg_t)a < (sreg_t)b; return true;
+    case 0x00 << 3 | 3: *out = a < b; return true;
+    case 0x00 << 3 | 4: *out = a ^ b; return true;
+    case 0x00 << 3 | 5: *out = a >> shamt; return true;
+    case 0x00 << 3 | 6: *out = a | b; return true;
+    case 0x00 << 3 | 7: *out = a & b; return true;
+    case 0x20 << 3 | 0: *out = a - b; return true;
+    case 0x20 << 3 | 4: *out = ~(a ^ b); return true;
+    case 0x20 << 3 | 5: *out = (reg_t)((sreg_t)a >> shamt); return true;
+    case 0x20 << 3 | 6: *out = a | ~b; return true;
+    case 0x20 << 3 | 7: *out = a & ~b; return true;
+    case 0x01 << 3 | 0: *out = a * b; return true;
+    case 0x01 << 3 | 1: *out = mulh(a, b); return true;
+    case 0x01 << 3 | 2: *out = mulhsu(a, b); return true;
+    case 0x01 << 3 | 3: *out = mulhu(a, b); return true;
+    case 0x01 << 3 | 4: *out = div_s(a, b); return true;
+    case 0x01 << 3 | 5: *out = div_u(a, b); return true;
+    case 0x01 << 3 | 6: *out = rem_s(a, b); return true;
+    case 0x01 << 3 | 7: *out = rem_u(a, b); return true;
+    case 0x10 << 3 | 2: *out = (a << 1) + b; return true;
+    case 0x10 << 3 | 4: *out = (a << 2) + b; return true;
+    case 0x10 << 3 | 6: *out = (a << 3) + b; return true;
+    case 0x05 << 3 | 4: *out = (sreg_t)a < (sreg_t)b ? a : b; return true;
+    case 0x05 << 3 | 5: *out = a < b ? a : b; return true;
+    case 0x05 << 3 | 6: *out = (sreg_t)a > (sreg_t)b ? a : b; return true;
+    case 0x05 << 3 | 7: *out = a > b ? a : b; return true;
+    case 0x30 << 3 | 1: *out = rotl(a, shamt); return true;
+    case 0x30 << 3 | 5: *out = rotr(a, shamt); return true;
+    case 0x14 << 3 | 1: *out = a | ((reg_t)1 << shamt); return true;
+    case 0x24 << 3 | 1: *out = a & ~((reg_t)1 << shamt); return true;
+    case 0x24 << 3 | 5: *out = (a >> shamt) & 1; return true;
+    case 0x34 << 3 | 1: *out = a ^ ((reg_t)1 << shamt); return true;
+    case 0x07 << 3 | 5: *out = b == 0 ? 0 : a; return true;
+    case 0x07 << 3 | 7: *out = b != 0 ? 0 : a; return true;
+    /* pack (zext.h on RV32), packh */
+    case 0x04 << 3 | 4: *out = (a & half) | (b << (XLEN / 2)); return true;
+    case 0x04 << 3 | 7: *out = (a & 0xff) | ((b & 0xff) << 8); return true;
+    default: return false;
+    }
+}
+";
+
+const ALU_32: &str = r"
+static inline uint32_t rotr32(uint32_t a, uint32_t shamt) {
+    shamt &= 31;
+    return shamt ? (a >> shamt) | (a << (32 - shamt)) : a;
+}
+
+/* OP-IMM-32: word register-immediate operations (I, Zba, Zbb) */
+static bool op_imm_32(uint32_t raw, reg_t a, reg_t* out) {
+    uint32_t imm12 = raw >> 20;
+    uint32_t shamt = imm12 & 31;
+    uint32_t w = (uint32_t)a;
+    switch (bits(raw, 14, 12)) {
+    case 0:
+        *out = sext(a + sext(imm12, 12), 32);
+        return true;
+    case 1:
+        switch (imm12) {
+        case 0x600:
+            *out = w ? (reg_t)__builtin_clz(w) : 32;
+            return true;
+        case 0x601:
+            *out = w ? (reg_t)__builtin_ctz(w) : 32;
+            return true;
+        case 0x602:
+            *out = (reg_t)__builtin_popcount(w);
+            return true;
+        default:
+            break;
+        }
+        if (raw >> 26 == 0x02) {
+            /* slli.uw */
+            *out = (reg_t)w << (imm12 & 63);
+            return true;
+        }
+        *out = sext((uint32_t)(w << shamt), 32);
+        return raw >> 25 == 0;
+    case 5:
+        switch (raw >> 25) {
+        case 0x00:
+            *out = sext(w >> shamt, 32);
+            return true;
+        case 0x20:
+            *out = (reg_t)(sreg_t)((int32_t)w >> shamt);
+            return true;
+        case 0x30:
+            *out = sext(rotr32(w, shamt), 32);
+            return true;
+        default:
+            return false;
+        }
+    default:
+        return false;
+    }
+}
+
+/* OP-32: word register-register operations (I, M, Zba, Zbb, Zbkb) */
+static bool op_32(uint32_t raw, reg_t a, reg_t b, reg_t* out) {
+    uint32_t wa = (uint32_t)a;
+    uint32_t wb = (uint32_t)b;
+    uint32_t shamt = wb & 31;
+    switch ((raw >> 25) << 3 | bits(raw, 14, 12)) {
+    case 0x00 << 3 | 0: *out = sext(wa + wb, 32); return true;
+    case 0x00 << 3 | 1: *out = sext(wa << shamt, 32); return true;
+    case 0x00 << 3 | 5: *out = sext(wa >> shamt, 32); return true;
+    case 0x20 << 3 | 0: *out = sext(wa - wb, 32); return true;
+    case 0x20 << 3 | 5: *out = (reg_t)(sreg_t)((int32_t)wa >> shamt); return true;
+    case 0x01 << 3 | 0: *out = sext(wa * wb, 32); return true;
+    case 0x01 << 3 | 4: *out = rv_divw((int32_t)wa, (int32_t)wb); return true;
+    case 0x01 << 3 | 5: *out = rv_divuw(wa, wb); return true;
+    case 0x01 << 3 | 6: *out = rv_remw((int32_t)wa, (int32_t)wb); return true;
+    case 0x01 << 3 | 7: *out = rv_remuw(wa, wb); return true;
+    case 0x04 << 3 | 0: *out = (reg_t)wa + b; return true;
+    /* packw (zext.h) */
+    case 0x04 << 3 | 4: *out = sext((wa & 0xffff) | (wb << 16), 32); return true;
+    case 0x10 << 3 | 2: *out = ((reg_t)wa << 1) + b; return true;
+    case 0x10 << 3 | 4: *out = ((reg_t)wa << 2) + b; return true;
+    case 0x10 << 3 | 6: *out = ((reg_t)wa << 3) + b; return true;
+    case 0x30 << 3 | 1: *out = sext(rotr32(wa, (32 - shamt) & 31), 32); return true;
+    case 0x30 << 3 | 5: *out = sext(rotr32(wa, shamt), 32); return true;
+    default: return false;
+    }
+}
+";
+
+const AMO: &str = r"
+/* A extension: LR, SC and the AMOs */
+static reg_t amo(RvState* restrict state, reg_t pc, uint16_t op, uint32_t raw, reg_t next) {
+    uint32_t rd = bits(raw, 11, 7);
+    uint32_t width = bits(raw, 14, 12) == 3 ? 8 : 4;
+    uint32_t funct5 = raw >> 27;
+    reg_t addr = get_reg(state, pc, op, bits(raw, 19, 15));
+    if (funct5 == 0x02) {
+        /* lr */
+        if (bits(raw, 24, 20) != 0) {
+            return illegal(state, pc);
+        }
+        reg_t value = load(state, pc, op, addr, width, true);
+        reserve(state, addr);
+        set_reg(state, pc, op, rd, value);
+        return next;
+    }
+    if (funct5 > 0x1c || ((funct5 & 3) != 0 && funct5 != 0x01 && funct5 != 0x03)) {
+        return illegal(state, pc);
+    }
+    reg_t src = get_reg(state, pc, op, bits(raw, 24, 20));
+    if (funct5 == 0x03) {
+        /* sc */
+        if (reserved(state, addr)) {
+            if (!store(state, pc, op, addr, width, src)) {
+                return pc;
+            }
+            set_reg(state, pc, op, rd, 0);
+        } else {
+            set_reg(state, pc, op, rd, 1);
+        }
+        state->reservation_valid = 0;
+        return next;
+    }
+    /* Word AMOs compare sign-extended (min, max) or zero-extended (minu, maxu) words */
+    reg_t old = load(state, pc, op, addr, width, true);
+    reg_t signed_src = width == 4 ? sext(src, 32) : src;
+    reg_t mask = width == 4 ? (reg_t)0xffffffffu : ~(reg_t)0;
+    reg_t value;
+    switch (funct5) {
+    case 0x00:
+        value = old + src;
+        break;
+    case 0x01:
+        value = src;
+        break;
+    case 0x04:
+        value = old ^ src;
+        break;
+    case 0x08:
+        value = old | src;
+        break;
+    case 0x0c:
+        value = old & src;
+        break;
+    case 0x10:
+        value = (sreg_t)old < (sreg_t)signed_src ? old : src;
+        break;
+    case 0x14:
+        value = (sreg_t)old > (sreg_t)signed_src ? old : src;
+        break;
+    case 0x18:
+        value = (old & mask) < (src & mask) ? old : src;
+        break;
+    default:
+        value = (old & mask) > (src & mask) ? old : src;
+        break;
+    }
+    set_reg(state, pc, op, rd, old);
+    if (!store(state, pc, op, addr, width, value)) {
+        return pc;
+    }
+    state->reservation_valid = 0;
+    return next;
+}
+";
+
+const STEP_HEAD: &str = r"
+/* Execute raw (the 32-bit form of the size-byte instruction at pc): returns
+ * the next PC, or stops the guest with state->pc at the instruction */
+static reg_t step(RvState* restrict state, reg_t pc, uint16_t op, uint32_t raw, uint32_t size) {
+    uint32_t rd = bits(raw, 11, 7);
+    uint32_t funct3 = bits(raw, 14, 12);
+    uint32_t rs1 = bits(raw, 19, 15);
+    uint32_t rs2 = bits(raw, 24, 20);
+    reg_t imm_i = sext(raw >> 20, 12);
+    reg_t next = pc + size;
+    reg_t result = 0;
+    switch (raw & 0x7f) {
+    case OPC_LUI:
+        set_reg(state, pc, op, rd, sext(raw & 0xfffff000u, 32));
+        return next;
+    case OPC_AUIPC:
+        set_reg(state, pc, op, rd, pc + sext(raw & 0xfffff000u, 32));
+        return next;
+    case OPC_JAL: {
+        reg_t imm = sext(bits(raw, 31, 31) << 20 | bits(raw, 19, 12) << 12 |
+                             bits(raw, 20, 20) << 11 | bits(raw, 30, 21) << 1,
+                         21);
+        set_reg(state, pc, op, rd, next);
+        return pc + imm;
+    }
+    case OPC_JALR: {
+        if (funct3 != 0) {
+            break;
+        }
+        reg_t target = (get_reg(state, pc, op, rs1) + imm_i) & ~(reg_t)1;
+        set_reg(state, pc, op, rd, next);
+        return target;
+    }
+    case OPC_BRANCH: {
+        reg_t a = get_reg(state, pc, op, rs1);
+        reg_t b = get_reg(state, pc, op, rs2);
+        bool taken;
+        switch (funct3) {
+        case 0:
+            taken = a == b;
+            break;
+        case 1:
+            taken = a != b;
+            break;
+        case 4:
+            taken = (sreg_t)a < (sreg_t)b;
+            break;
+        case 5:
+            taken = (sreg_t)a >= (sreg_t)b;
+            break;
+        case 6:
+            taken = a < b;
+            break;
+        case 7:
+            taken = a >= b;
+            break;
+        default:
+            return illegal(state, pc);
+        }
+        reg_t target = pc + sext(bits(raw, 31, 31) << 12 | bits(raw, 7, 7) << 11 |
+                                     bits(raw, 30, 25) << 5 | bits(raw, 11, 8) << 1,
+                                 13);
+        on_branch(state, pc, op, taken, taken ? target : next);
+        return taken ? target : next;
+    }
+    case OPC_LOAD: {
+        /* lb, lh, lw, ld, lbu, lhu, lwu */
+        if (funct3 == 7 || (XLEN == 32 && (funct3 == 3 || funct3 == 6))) {
+            break;
+        }
+        uint32_t width = 1u << (funct3 & 3);
+        reg_t addr = get_reg(state, pc, op, rs1) + imm_i;
+        if (unlikely(htif_stalled(state, addr, width))) {
+            state->pc = pc;
+            return pc;
+        }
+        set_reg(state, pc, op, rd, load(state, pc, op, addr, width, funct3 < 4));
+        return next;
+    }
+    case OPC_STORE: {
+        if (funct3 > (XLEN == 64 ? 3u : 2u)) {
+            break;
+        }
+        reg_t addr = get_reg(state, pc, op, rs1) + sext(bits(raw, 31, 25) << 5 | rd, 12);
+        reg_t value = get_reg(state, pc, op, rs2);
+        if (!store(state, pc, op, addr, 1u << funct3, value)) {
+            return pc;
+        }
+        invalidate(state, addr);
+        return next;
+    }
+    case OPC_OP_IMM:
+        if (!op_imm(raw, get_reg(state, pc, op, rs1), &result)) {
+            break;
+        }
+        set_reg(state, pc, op, rd, result);
+        return next;
+    case OPC_OP: {
+        reg_t a = get_reg(state, pc, op, rs1);
+        if (!op_reg(raw, a, get_reg(state, pc, op, rs2), &result)) {
+            break;
+        }
+        set_reg(state, pc, op, rd, result);
+        return next;
+    }
+";
+
+const STEP_RV64: &str = r"    case OPC_OP_IMM_32:
+        if (!op_imm_32(raw, get_reg(state, pc, op, rs1), &result)) {
+            break;
+        }
+        set_reg(state, pc, op, rd, result);
+        return next;
+    case OPC_OP_32: {
+        reg_t a = get_reg(state, pc, op, rs1);
+        if (!op_32(raw, a, get_reg(state, pc, op, rs2), &result)) {
+            break;
+        }
+        set_reg(state, pc, op, rd, result);
+        return next;
+    }
+";
+
+const STEP_TAIL: &str = r"    case OPC_MISC_MEM:
+        /* fence, fence.i: one hart, and recompiled code is never modified */
+        if (funct3 > 1) {
+            break;
+        }
+        return next;
+    case OPC_AMO:
+        if (funct3 != 2 && !(XLEN == 64 && funct3 == 3)) {
+            break;
+        }
+        return amo(state, pc, op, raw, next);
+    default:
+        break;
+    }
+    return illegal(state, pc);
+}
+";
@@ -0,0 +1,259 @@
+//! Block interpreter.
+//!
+//! Blocks in `EmitInputs::interpreted` are not compiled: their functions
+//! hold a table of the block's instructions and call `rv_interpret`,
+//! generated here into `<base>_interp.c`, which decodes the original
+//! instruction bits and executes them on the registers in `RvState`. It
+//! runs the table while control stays on it and returns the next PC, where
+//! the block continues through the dispatch table like any dynamic jump.
+//!
+//! The interpreter covers the integer extensions (I, M, A, C, Zba, Zbb,
+//! Zbs, Zbkb, Zicond, Zcb, fences); [`uninterpretable`] tells which
+//! instructions it cannot run. Stores make the same code-write, stack-guard
+//! and HTIF checks as compiled code, and every access is traced, so a
+//! tracer sees interpreted and compiled blocks alike.
+
+mod decode;
+mod execute;
+
+use std::fmt::Write;
+
+use rvr_ir::{BlockIR, InstrIR, Stmt, Xlen};
+use rvr_isa::{
+    EXT_A, EXT_C, EXT_I, EXT_M, EXT_ZBA, EXT_ZBB, EXT_ZBKB, EXT_ZBS, EXT_ZCB, EXT_ZICOND,
+    EXT_ZIFENCEI, OP_C_EBREAK, OP_EBREAK, OP_ECALL, OP_MRET, OpId,
+};
+
+use super::cold::ColdPath;
+use super::namespace::global_symbol;
+use super::signature::{MEMORY_FIXED_REF, reg_type};
+use crate::config::EmitConfig;
+use crate::inputs::EmitInputs;
+
+use decode::{EXPAND, FIELDS};
+use execute::{push_helpers, push_memory_access, push_step, push_trace_hooks};
+
+/// Extensions the interpreter executes.
+const INTERPRETED_EXTENSIONS: [u8; 11] = [
+    EXT_I,
+    EXT_M,
+    EXT_A,
+    EXT_C,
+    EXT_ZIFENCEI,
+    EXT_ZBA,
+    EXT_ZBB,
+    EXT_ZBS,
+    EXT_ZBKB,
+    EXT_ZICOND,
+    EXT_ZCB,
+];
+
+/// System instructions of the interpreted extensions, which need the
+/// runtime.
+const SYSTEM_OPS: [OpId; 4] = [OP_ECALL, OP_EBREAK, OP_MRET, OP_C_EBREAK];
+
+/// Whether the interpreter cannot run `instr`: system, CSR, vector and
+/// push/pop instructions, and instructions lifted to runtime calls (hooks,
+/// intrinsics).
+#[must_use]
+pub fn uninterpretable<X: Xlen>(instr: &InstrIR<X>) -> bool {
+    let [ext, _] = instr.op.to_be_bytes();
+    !INTERPRETED_EXTENSIONS.contains(&ext)
+        || SYSTEM_OPS.iter().any(|op| op.pack() == instr.op)
+        || instr.statements.iter().any(calls_runtime)
+}
+
+fn calls_runtime<X: Xlen>(stmt: &Stmt<X>) -> bool {
+    match stmt {
+        Stmt::ExternCall { .. } => true,
+        Stmt::If {
+            then_stmts,
+            else_stmts,
+            ..
+        } => then_stmts.iter().chain(else_stmts).any(calls_runtime),
+        Stmt::Write { .. } => false,
+    }
+}
+
+/// First instruction of `block` the interpreter cannot run, if any.
+#[must_use]
+pub fn first_uninterpretable<X: Xlen>(block: &BlockIR<X>) -> Option<&InstrIR<X>> {
+    block.instructions.iter().find(|i| uninterpretable(i))
+}
+
+/// Interpreter declarations for the header.
+pub(super) fn gen_interp_declarations<X: Xlen>(prefix: &str) -> String {
+    let rtype = reg_type::<X>();
+    format!(
+        r"/* Block interpreter (see the _interp.c source) */
+typedef struct RvInterpInstr {{
+    {rtype} pc;
+    uint32_t raw;
+    uint16_t op;
+}} RvInterpInstr;
+
+{rtype} {prefix}rv_interpret(RvState* restrict state, const RvInterpInstr* instrs, uint32_t count);
+
+"
+    )
+}
+
+/// `<base>_interp.c`: `rv_interpret` for the configuration.
+#[must_use]
+pub fn gen_interp_source<X: Xlen>(
+    base_name: &str,
+    config: &EmitConfig<X>,
+    inputs: &EmitInputs,
+) -> String {
+    let rtype = reg_type::<X>();
+    let stype = if X::VALUE == 32 { "int32_t" } else { "int64_t" };
+    let memory = if config.fixed_addresses.is_some() {
+        format!("    (void)state;\n    return {MEMORY_FIXED_REF};")
+    } else {
+        "    return state->memory;".to_string()
+    };
+    let mut s = format!(
+        r#"#include "{base_name}.h"
+#include <stdbool.h>
+#include <stdint.h>
+#include <string.h>
+
+/* Block interpreter: executes interpreted blocks from their instruction bits */
+
+typedef {rtype} reg_t;
+typedef {stype} sreg_t;
+
+static inline uint8_t* guest_memory(RvState* restrict state) {{
+{memory}
+}}
+"#
+    );
+    push_trace_hooks(&mut s, config);
+    s.push_str(FIELDS);
+    push_helpers::<X>(&mut s);
+    push_stops(&mut s);
+    push_memory_access(&mut s, config, inputs);
+    s.push_str(EXPAND);
+    push_step::<X>(&mut s);
+    push_interpret(&mut s, config);
+    s
+}
+
+/// `illegal`: stop at an instruction the interpreter cannot execute.
+fn push_stops(s: &mut String) {
+    s.push_str("\n/* Stop at an illegal instruction */\nstatic reg_t illegal(RvState* restrict state, reg_t pc) {\n    state->pc = pc;\n");
+    for store in ColdPath::IllegalInstruction.status_stores("state") {
+        let _ = writeln!(s, "    {store}");
+    }
+    s.push_str("    return pc;\n}\n");
+}
+
+/// `rv_interpret`: run the table while control stays on it.
+fn push_interpret<X: Xlen>(s: &mut String, config: &EmitConfig<X>) {
+    let retire = if config.instret_mode.counts() {
+        "        state->instret++;\n"
+    } else {
+        ""
+    };
+    // Per-instruction suspension: stop after the instruction that reaches
+    // the target; the block suspends before its jump to the returned PC
+    let suspend = if config.instret_mode.per_instruction() {
+        "        if (unlikely(state->target_instret <= state->instret)) {\n            return next;\n        }\n"
+    } else {
+        ""
+    };
+    let interpret = global_symbol(&config.symbol_prefix, "rv_interpret");
+    let _ = write!(
+        s,
+        r"
+/* Run instrs from the first while control stays on them; returns the PC
+ * to continue at (where the guest stopped if it did) */
+reg_t {interpret}(RvState* restrict state, const RvInterpInstr* instrs, uint32_t count) {{
+    reg_t pc = instrs[0].pc;
+    for (uint32_t i = 0; i < count && instrs[i].pc == pc; i++) {{
+        uint16_t op = instrs[i].op;
+        uint32_t raw = instrs[i].raw;
+        uint32_t size = 4;
+        on_instr(state, pc, op, raw);
+        if ((raw & 3) != 3) {{
+            size = 2;
+            raw = expand(raw & 0xffff);
+        }}
+        reg_t next = step(state, pc, op, raw, size);
+        if (unlikely(state->has_exited)) {{
+            return state->pc;
+        }}
+{retire}{suspend}        pc = next;
+    }}
+    return pc;
+}}
+"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use rvr_ir::{Expr, InstrIR, Rv32, Rv64, Terminator};
+    use rvr_isa::{LrScModel, OP_ADD, OP_FENCE_I};
+
+    use super::*;
+    use crate::c::{TracerConfig, TracerKind};
+    use crate::htif::FROMHOST_ADDR;
+
+    #[test]
+    fn test_uninterpretable() {
+        let instr = |op: OpId, statements| {
+            InstrIR::<Rv64>::new(
+                0x1000,
+                4,
+                op.pack(),
+                0,
+                statements,
+                Terminator::Fall { target: None },
+            )
+        };
+        let call = Stmt::ExternCall {
+            fn_name: "rv_hook_checksum".into(),
+            args: vec![Expr::var("state")],
+        };
+        let cases = [
+            (instr(OP_ADD, Vec::new()), false),
+            (instr(OP_FENCE_I, Vec::new()), false),
+            (instr(OP_ECALL, Vec::new()), true),
+            (instr(OP_C_EBREAK, Vec::new()), true),
+            (instr(OpId::new(rvr_isa::EXT_ZICSR, 0), Vec::new()), true),
+            (instr(OpId::new(rvr_isa::EXT_V, 0), Vec::new()), true),
+            (instr(OP_ADD, vec![call]), true),
+        ];
+        for (instr, expected) in cases {
+            assert_eq!(uninterpretable(&instr), expected, "{}", instr.op);
+        }
+    }
+
+    #[test]
+    fn test_interp_source_follows_config() {
+        let inputs = EmitInputs::new(0x1000, 0x2000);
+        let plain = gen_interp_source::<Rv64>("rv", &EmitConfig::default(), &inputs);
+        assert!(plain.contains("#include \"rv.h\""));
+        assert!(plain.contains("reg_t rv_interpret(RvState* restrict state"));
+        assert!(plain.contains("case OPC_OP_32:"));
+        assert!(plain.contains("state->instret++;"));
+        assert!(plain.contains("state->reservation_valid = 1;"));
+        assert!(!plain.contains("trace_pc("));
+        assert!(!plain.contains("handle_tohost_write"));
+
+        let rv32 = gen_interp_source::<Rv32>("rv", &EmitConfig::default(), &inputs);
+        assert!(!rv32.contains("case OPC_OP_32:"));
+        assert!(rv32.contains("rv_mulh((int32_t)a, (int32_t)b)"));
+
+        let config = EmitConfig::<Rv64>::default()
+            .with_tracer(TracerConfig::builtin(TracerKind::Spike))
+            .with_tohost(true)
+            .with_lrsc_model(LrScModel::AlwaysSucceed);
+        let traced = gen_interp_source::<Rv64>("rv", &config, &inputs);
+        assert!(traced.contains("trace_pc(&state->tracer, pc, op);"));
+        assert!(traced.contains("handle_tohost_write(state, (reg_t)value);"));
+        assert!(traced.contains(&format!("0x{FROMHOST_ADDR:x}u && htif_poll(state)")));
+        assert!(!traced.contains("state->reservation_valid = 1;"));
+    }
+}
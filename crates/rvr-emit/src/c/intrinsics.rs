@@ -0,0 +1,178 @@
+//! Native memory intrinsics runtime.
+//!
+//! With `EmitConfig::native_mem_intrinsics`, the guest's `memcpy`, `memset`
+//! and `memcmp` are replaced by calls to `rv_memcpy`, `rv_memset` and
+//! `rv_memcmp` (see [`MemIntrinsic`]), which operate on guest memory with the
+//! host's vectorized `memmove`/`memset`/`memcmp`. Ranges that are not
+//! contiguous in guest memory (wrapping or out of bounds) fall back to byte
+//! accesses through `phys_addr`, so they wrap or trap like the guest loop.
+
+use rvr_ir::Xlen;
+
+use super::namespace::namespaced;
+use super::signature::{MEMORY_PLACEHOLDER, memory_ref, reg_type};
+
+/// A guest libc function with a native replacement.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MemIntrinsic {
+    /// `void* memcpy(void* dst, const void* src, size_t n)`, returns `dst`.
+    Memcpy,
+    /// `void* memset(void* dst, int c, size_t n)`, returns `dst`.
+    Memset,
+    /// `int memcmp(const void* a, const void* b, size_t n)`, returns the
+    /// difference of the first differing bytes (0 if equal).
+    Memcmp,
+}
+
+impl MemIntrinsic {
+    /// All intrinsics.
+    pub const ALL: [Self; 3] = [Self::Memcpy, Self::Memset, Self::Memcmp];
+
+    /// Guest symbol the intrinsic replaces.
+    #[must_use]
+    pub const fn symbol(self) -> &'static str {
+        match self {
+            Self::Memcpy => "memcpy",
+            Self::Memset => "memset",
+            Self::Memcmp => "memcmp",
+        }
+    }
+
+    /// Runtime helper called with `(state, a0, a1, a2)`; returns the new `a0`.
+    #[must_use]
+    pub const fn runtime_fn(self) -> &'static str {
+        match self {
+            Self::Memcpy => "rv_memcpy",
+            Self::Memset => "rv_memset",
+            Self::Memcmp => "rv_memcmp",
+        }
+    }
+}
+
+const INTRINSICS_BODY: &str = r"
+/* memmove rather than memcpy: overlapping guest buffers are not host UB */
+reg_t {prefix}rv_memcpy(RvState* restrict state, reg_t dst, reg_t src, reg_t n) {
+    if (n == 0) {
+        return dst;
+    }
+    uint8_t* d = host_range(state, dst, n);
+    const uint8_t* s = host_range(state, src, n);
+    if (d && s) {
+        memmove(d, s, (size_t)n);
+    } else {
+        uint8_t* mem = {memory};
+        for (reg_t i = 0; i < n; i++) {
+            mem[phys_addr(dst + i)] = mem[phys_addr(src + i)];
+        }
+    }
+    return dst;
+}
+
+reg_t {prefix}rv_memset(RvState* restrict state, reg_t dst, reg_t c, reg_t n) {
+    if (n == 0) {
+        return dst;
+    }
+    uint8_t* d = host_range(state, dst, n);
+    if (d) {
+        memset(d, (int)(uint8_t)c, (size_t)n);
+    } else {
+        uint8_t* mem = {memory};
+        for (reg_t i = 0; i < n; i++) {
+            mem[phys_addr(dst + i)] = (uint8_t)c;
+        }
+    }
+    return dst;
+}
+
+/* The byte difference (not just its sign), sign-extended like an int return */
+reg_t {prefix}rv_memcmp(RvState* restrict state, reg_t a, reg_t b, reg_t n) {
+    if (n == 0) {
+        return 0;
+    }
+    const uint8_t* pa = host_range(state, a, n);
+    const uint8_t* pb = host_range(state, b, n);
+    if (pa && pb) {
+        size_t i = 0;
+        while ((size_t)n - i >= 64 && memcmp(pa + i, pb + i, 64) == 0) {
+            i += 64;
+        }
+        for (; i < (size_t)n; i++) {
+            if (pa[i] != pb[i]) {
+                return (reg_t)(int32_t)((int32_t)pa[i] - (int32_t)pb[i]);
+            }
+        }
+        return 0;
+    }
+    const uint8_t* mem = {memory};
+    for (reg_t i = 0; i < n; i++) {
+        uint8_t x = mem[phys_addr(a + i)];
+        uint8_t y = mem[phys_addr(b + i)];
+        if (x != y) {
+            return (reg_t)(int32_t)((int32_t)x - (int32_t)y);
+        }
+    }
+    return 0;
+}
+";
+
+/// Generate the intrinsics runtime source (`<base>_intrinsics.c`).
+#[must_use]
+pub fn gen_mem_intrinsics_source<X: Xlen>(
+    base_name: &str,
+    fixed_addresses: bool,
+    prefix: &str,
+) -> String {
+    let rtype = reg_type::<X>();
+    let mem_ref = memory_ref(fixed_addresses);
+    format!(
+        r#"#include "{base_name}.h"
+#include <stdint.h>
+#include <string.h>
+
+/* Native memcpy/memset/memcmp on guest memory */
+
+typedef {rtype} reg_t;
+
+/* Host pointer to [addr, addr + n) if it is contiguous in guest memory
+ * (n > 0). phys_addr traps on either end like a guest access would. */
+static inline uint8_t* host_range(RvState* restrict state, reg_t addr, reg_t n) {{
+    (void)state;
+    reg_t last = addr + n - 1;
+    if (last < addr) {{
+        return NULL;
+    }}
+    uint64_t first = (uint64_t)phys_addr(addr);
+    if ((uint64_t)phys_addr(last) - first != (uint64_t)(n - 1)) {{
+        return NULL;
+    }}
+    return {mem_ref} + first;
+}}
+{body}"#,
+        body = namespaced(INTRINSICS_BODY, prefix).replace(MEMORY_PLACEHOLDER, mem_ref)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use rvr_ir::{Rv32, Rv64};
+
+    use super::*;
+    use crate::c::signature::MEMORY_FIXED_REF;
+
+    #[test]
+    fn test_gen_mem_intrinsics_source() {
+        let src = gen_mem_intrinsics_source::<Rv64>("rv64", false, "p_");
+        assert!(src.starts_with("#include \"rv64.h\"\n"));
+        assert!(src.contains("typedef uint64_t reg_t;"));
+        assert!(src.contains("    return state->memory + first;"));
+        assert!(src.contains("        uint8_t* mem = state->memory;"));
+        assert!(!src.contains(MEMORY_PLACEHOLDER));
+        for intrinsic in MemIntrinsic::ALL {
+            assert!(src.contains(&format!("reg_t p_{}(RvState*", intrinsic.runtime_fn())));
+        }
+
+        let fixed = gen_mem_intrinsics_source::<Rv32>("rv32", true, "");
+        assert!(fixed.contains("typedef uint32_t reg_t;"));
+        assert!(fixed.contains(&format!("    const uint8_t* mem = {MEMORY_FIXED_REF};")));
+    }
+}
@@ -0,0 +1,206 @@
+//! LZ4 block compression of embedded segment data.
+//!
+//! Segments at least as large as the configured threshold are stored in
+//! the library as LZ4 blocks and decoded straight into guest memory by
+//! `rv_init_memory` (see `memory.rs`), so a large data section costs a
+//! fraction of its size in generated source and library. The compressor is
+//! a greedy single-probe matcher: it trades ratio for speed, since a
+//! segment can be hundreds of megabytes. Its output is a standard LZ4 block
+//! (the final five bytes are literals, no match starts in the last twelve).
+
+/// Shortest match the format can encode.
+const MIN_MATCH: usize = 4;
+/// No match may start within this many bytes of the end.
+const MF_LIMIT: usize = 12;
+/// The block always ends with at least this many literals.
+const LAST_LITERALS: usize = 5;
+/// Farthest match offset (16-bit offsets).
+const MAX_OFFSET: usize = u16::MAX as usize;
+const HASH_BITS: u32 = 16;
+
+/// Decoder for the blocks `lz4_compress` writes. The input comes from rvr,
+/// so it is trusted: lengths are not checked against either buffer.
+pub(super) const LZ4_DECODER: &str = r"/* LZ4 block decoder for segments compressed by rvr (trusted input) */
+static size_t lz4_length(const uint8_t** src, size_t len) {
+    if (len == 15) {
+        uint8_t byte;
+        do {
+            byte = *(*src)++;
+            len += byte;
+        } while (byte == 255);
+    }
+    return len;
+}
+
+static void lz4_decode(uint8_t* dst, const uint8_t* src, size_t src_len) {
+    const uint8_t* end = src + src_len;
+    for (;;) {
+        uint8_t token = *src++;
+        size_t literals = lz4_length(&src, token >> 4);
+        memcpy(dst, src, literals);
+        dst += literals;
+        src += literals;
+        if (src >= end) {
+            return;
+        }
+        size_t offset = (size_t)src[0] | ((size_t)src[1] << 8);
+        src += 2;
+        size_t len = lz4_length(&src, token & 15) + 4;
+        /* Byte by byte: the match may overlap the bytes it produces */
+        const uint8_t* match = dst - offset;
+        for (size_t i = 0; i < len; i++) {
+            dst[i] = match[i];
+        }
+        dst += len;
+    }
+}
+
+";
+
+const fn hash(word: u32) -> usize {
+    (word.wrapping_mul(2_654_435_761) >> (32 - HASH_BITS)) as usize
+}
+
+fn read_word(data: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]])
+}
+
+/// Append the extra length bytes of a 4-bit length field that saturated.
+fn push_length(out: &mut Vec<u8>, mut len: usize) {
+    while len >= 255 {
+        out.push(255);
+        len -= 255;
+    }
+    out.push(u8::try_from(len).expect("remainder is below 255"));
+}
+
+/// A 4-bit length field: `len`, saturated at 15.
+fn nibble(len: usize) -> u8 {
+    u8::try_from(len.min(15)).expect("saturated at 15")
+}
+
+/// Append a sequence: `literals`, then the `(offset, len)` match (none for
+/// the final sequence).
+fn push_sequence(out: &mut Vec<u8>, literals: &[u8], matched: Option<(usize, usize)>) {
+    let match_code = matched.map_or(0, |(_, len)| len - MIN_MATCH);
+    out.push((nibble(literals.len()) << 4) | nibble(match_code));
+    if literals.len() >= 15 {
+        push_length(out, literals.len() - 15);
+    }
+    out.extend_from_slice(literals);
+    if let Some((offset, _)) = matched {
+        let offset = u16::try_from(offset).expect("matches are at most MAX_OFFSET back");
+        out.extend_from_slice(&offset.to_le_bytes());
+        if match_code >= 15 {
+            push_length(out, match_code - 15);
+        }
+    }
+}
+
+/// Compress `data` into one LZ4 block.
+#[must_use]
+pub fn lz4_compress(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() / 2 + 16);
+    let mut table = vec![usize::MAX; 1 << HASH_BITS];
+    let mut anchor = 0;
+    let mut pos = 0;
+    let limit = data.len().saturating_sub(MF_LIMIT);
+    while pos < limit {
+        let word = read_word(data, pos);
+        let slot = &mut table[hash(word)];
+        let candidate = std::mem::replace(slot, pos);
+        if candidate == usize::MAX
+            || pos - candidate > MAX_OFFSET
+            || read_word(data, candidate) != word
+        {
+            pos += 1;
+            continue;
+        }
+        let max_len = data.len() - LAST_LITERALS - pos;
+        let mut len = MIN_MATCH;
+        while len < max_len && data[candidate + len] == data[pos + len] {
+            len += 1;
+        }
+        push_sequence(&mut out, &data[anchor..pos], Some((pos - candidate, len)));
+        pos += len;
+        anchor = pos;
+    }
+    push_sequence(&mut out, &data[anchor..], None);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reference decoder, following the C one.
+    fn decode(src: &[u8]) -> Vec<u8> {
+        fn length(src: &[u8], pos: &mut usize, mut len: usize) -> usize {
+            if len == 15 {
+                loop {
+                    let byte = src[*pos];
+                    *pos += 1;
+                    len += usize::from(byte);
+                    if byte != 255 {
+                        break;
+                    }
+                }
+            }
+            len
+        }
+        let mut out = Vec::new();
+        let mut pos = 0;
+        loop {
+            let token = src[pos];
+            pos += 1;
+            let literals = length(src, &mut pos, usize::from(token >> 4));
+            out.extend_from_slice(&src[pos..pos + literals]);
+            pos += literals;
+            if pos >= src.len() {
+                return out;
+            }
+            let offset = usize::from(u16::from_le_bytes([src[pos], src[pos + 1]]));
+            pos += 2;
+            let len = length(src, &mut pos, usize::from(token & 15)) + MIN_MATCH;
+            for _ in 0..len {
+                out.push(out[out.len() - offset]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_lz4_round_trip() {
+        let mut text = Vec::new();
+        for i in 0..2000u32 {
+            text.extend_from_slice(
+                format!("{{\"block\": {i}, \"hash\": \"0x{:08x}\"}},", i * 7919).as_bytes(),
+            );
+        }
+        // Pseudo-random bytes do not compress
+        let mut x = 1u32;
+        let noise: Vec<u8> = (0..5000)
+            .map(|_| {
+                x ^= x << 13;
+                x ^= x >> 17;
+                x ^= x << 5;
+                x.to_le_bytes()[0]
+            })
+            .collect();
+        let zeros = vec![0; 100_000];
+        let cases: [&[u8]; 6] = [&[], b"a", b"abcdabcdabcd", &zeros, &text, &noise];
+        for data in cases {
+            let packed = lz4_compress(data);
+            assert_eq!(decode(&packed), data, "{} bytes", data.len());
+            assert!(packed.len() <= data.len() + data.len() / 255 + 16);
+        }
+        assert!(lz4_compress(&zeros).len() < 500);
+        assert!(lz4_compress(&text).len() < text.len() / 2);
+    }
+
+    #[test]
+    fn test_lz4_block_ends_with_literals() {
+        let packed = lz4_compress(&[7; 64]);
+        // One run-length match, then the last five bytes as literals
+        assert_eq!(packed[packed.len() - 6..], [0x50, 7, 7, 7, 7, 7]);
+    }
+}
@@ -0,0 +1,505 @@
+//! Makefiles of generated C projects.
+//!
+//! A program's Makefile builds `lib<base_name>.so` from its sources, and
+//! `lib<base_name>.a` for hosts that link it statically; a library's builds
+//! the objects of several programs and links them into one.
+//! Next to it, `compile_commands.json` gives editors and tools the same
+//! compile lines.
+
+use std::fmt::Write as FmtWrite;
+use std::path::Path;
+
+use rvr_ir::Xlen;
+use tracing::trace;
+
+use super::embed::EMBED_HEADER;
+use super::manifest::write_if_changed;
+use super::memory::segment_bin_name;
+use super::project::{CProject, partition_file_name};
+use crate::abi::ABI_HEADER;
+
+/// File name of the compilation database.
+pub const COMPILE_COMMANDS: &str = "compile_commands.json";
+
+/// Flags for position-independent objects of the shared library.
+const SHARED_FLAGS: &str = "-fPIC";
+
+/// Archiver of the static library.
+const ARCHIVER: &str = "ar";
+
+/// Makefile variable holding the optimization flag; the build driver
+/// overrides it on the make command line to retry failed parts.
+pub const OPT_VAR: &str = "OPT";
+
+/// Optimization flag of regular builds.
+const OPT_FLAG: &str = "-O3";
+
+/// Suffix of the stamp an object rule touches before compiling: the
+/// object's mtime minus the stamp's is the object's compile time.
+pub const COMPILE_STAMP_SUFFIX: &str = ".start";
+
+/// Word an object rule prints with the object's name once it is compiled,
+/// so the build driver can count finished objects as make runs.
+pub const COMPILED_MARKER: &str = "compiled";
+
+/// One entry of `compile_commands.json`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CompileCommand {
+    /// Source file, relative to the project directory.
+    pub file: String,
+    /// Compiler and arguments.
+    pub arguments: Vec<String>,
+}
+
+/// Render `compile_commands.json` for a project in `dir`.
+#[must_use]
+pub fn render_compile_commands(dir: &Path, commands: &[CompileCommand]) -> String {
+    let dir = std::path::absolute(dir).unwrap_or_else(|_| dir.to_path_buf());
+    let directory = json_string(&dir.to_string_lossy());
+    let entries: Vec<String> = commands
+        .iter()
+        .map(|command| {
+            let arguments: Vec<String> = command.arguments.iter().map(|a| json_string(a)).collect();
+            format!(
+                "  {{\n    \"directory\": {directory},\n    \"file\": {},\n    \"arguments\": [{}]\n  }}",
+                json_string(&command.file),
+                arguments.join(", ")
+            )
+        })
+        .collect();
+    format!("[\n{}\n]\n", entries.join(",\n"))
+}
+
+impl<X: Xlen> CProject<X> {
+    /// Render the Makefile building the project's parts `partitions`.
+    ///
+    /// Compiler flags are determined in Rust based on the compiler field:
+    /// - clang: uses `-std=c23`, `-flto=thin`, `-fuse-ld=lld`, `-fzero-call-used-regs=skip`
+    /// - gcc: uses `-std=c2x`, `-flto`, omits clang-specific flags
+    /// - portable dialect (any compiler): uses `-std=c11`, `-flto`
+    #[must_use]
+    pub fn render_makefile(&self, partitions: &[usize]) -> String {
+        let mut content = String::new();
+        self.write_toolchain(&mut content);
+        // A compiler killed mid-write must not leave an object make trusts
+        writeln!(content, ".DELETE_ON_ERROR:").unwrap();
+        writeln!(content).unwrap();
+
+        let srcs = self.sources(partitions);
+        writeln!(content, "SRCS = {}", srcs.join(" ")).unwrap();
+        writeln!(content, "OBJS = $(SRCS:.c=.o)").unwrap();
+        writeln!(content).unwrap();
+
+        // Targets
+        writeln!(content, "shared: lib{}.so", self.base_name).unwrap();
+        writeln!(content).unwrap();
+        writeln!(content, "static: lib{}.a", self.base_name).unwrap();
+        writeln!(content).unwrap();
+
+        writeln!(content, "lib{}.so: $(OBJS)", self.base_name).unwrap();
+        // Always use LDFLAGS - it may be empty if LTO disabled
+        writeln!(
+            content,
+            "\t$(CC) $(CFLAGS) $(LDFLAGS) -shared -o $@ $(OBJS)"
+        )
+        .unwrap();
+        writeln!(content).unwrap();
+
+        writeln!(content, "lib{}.a: $(OBJS)", self.base_name).unwrap();
+        writeln!(content, "\trm -f $@ && $(AR) rcs $@ $(OBJS)").unwrap();
+        writeln!(content).unwrap();
+
+        self.write_object_rules(&mut content);
+
+        writeln!(content, "clean:").unwrap();
+        writeln!(
+            content,
+            "\trm -f $(OBJS) $(OBJS:={COMPILE_STAMP_SUFFIX}) lib{}.so lib{}.a",
+            self.base_name, self.base_name
+        )
+        .unwrap();
+        writeln!(content).unwrap();
+
+        if self.config.symbol_prefix.is_empty() {
+            writeln!(content, ".PHONY: shared static clean").unwrap();
+        } else {
+            // Program of a shared library: the library Makefile builds the
+            // objects and links them (see `write_library_makefile`)
+            writeln!(content, "objs: $(OBJS)").unwrap();
+            writeln!(content).unwrap();
+            writeln!(content, "print-objs:").unwrap();
+            writeln!(content, "\t@echo $(OBJS)").unwrap();
+            writeln!(content).unwrap();
+            writeln!(content, ".PHONY: shared static clean objs print-objs").unwrap();
+        }
+        content
+    }
+
+    /// Compile commands of the project's parts `partitions` and its other
+    /// sources, as the Makefile runs them without the compiler launcher.
+    #[must_use]
+    pub fn compile_commands(&self, partitions: &[usize]) -> Vec<CompileCommand> {
+        let (cflags, _) = self.flags();
+        let compiler = self.config.compiler.to_string();
+        self.sources(partitions)
+            .into_iter()
+            .map(|file| {
+                let obj = format!("{}.o", file.trim_end_matches(".c"));
+                let arguments = [compiler.as_str(), OPT_FLAG]
+                    .into_iter()
+                    .chain(cflags.iter().copied())
+                    .chain([SHARED_FLAGS, "-c", &file, "-o", &obj])
+                    .map(str::to_string)
+                    .collect();
+                CompileCommand { file, arguments }
+            })
+            .collect()
+    }
+
+    /// Sources of the project's parts `partitions` and its other C files.
+    fn sources(&self, partitions: &[usize]) -> Vec<String> {
+        let mut srcs: Vec<String> = partitions
+            .iter()
+            .map(|&idx| partition_file_name(&self.base_name, idx))
+            .collect();
+        srcs.push(format!("{}_dispatch.c", self.base_name));
+        if self.config.syscall_runtime() {
+            srcs.push(format!("{}_syscalls.c", self.base_name));
+        }
+        if !self.segments.is_empty() {
+            srcs.push(format!("{}_memory.c", self.base_name));
+        }
+        if self.config.htif_enabled() {
+            srcs.push(format!("{}_htif.c", self.base_name));
+        }
+        if self.config.native_mem_intrinsics() {
+            srcs.push(format!("{}_intrinsics.c", self.base_name));
+        }
+        if self.inputs.vector {
+            srcs.push(format!("{}_vector.c", self.base_name));
+        }
+        if self.config.static_archive() {
+            srcs.push(format!("{}_embed.c", self.base_name));
+        }
+        if self.inputs.golden.is_some() {
+            srcs.push(format!("{}_golden.c", self.base_name));
+        }
+        if !self.inputs.interpreted.is_empty() {
+            srcs.push(format!("{}_interp.c", self.base_name));
+        }
+        srcs
+    }
+
+    /// Write the Makefile of a library holding several programs.
+    ///
+    /// Each program is a project in `<output_dir>/<program>` emitted with
+    /// the symbol prefix `<program>_`; the library links the objects of all
+    /// of them into `lib<base_name>.so`.
+    ///
+    /// # Errors
+    /// Returns any I/O error while writing the Makefile.
+    pub fn write_library_makefile(&self, programs: &[String]) -> std::io::Result<()> {
+        let mut content = String::new();
+        self.write_toolchain(&mut content);
+
+        writeln!(content, "PROGRAMS = {}", programs.join(" ")).unwrap();
+        writeln!(
+            content,
+            "OBJS = $(foreach p,$(PROGRAMS),$(addprefix $(p)/,$(shell $(MAKE) -s --no-print-directory -C $(p) print-objs)))"
+        )
+        .unwrap();
+        writeln!(content).unwrap();
+
+        writeln!(content, "shared: lib{}.so", self.base_name).unwrap();
+        writeln!(content).unwrap();
+
+        // Programs always rebuild (their Makefiles track their own inputs)
+        writeln!(content, "lib{}.so: programs", self.base_name).unwrap();
+        writeln!(
+            content,
+            "\t$(CC) $(CFLAGS) $(LDFLAGS) -shared -o $@ $(OBJS)"
+        )
+        .unwrap();
+        writeln!(content).unwrap();
+
+        writeln!(content, "programs:").unwrap();
+        writeln!(
+            content,
+            "\t@set -e; for p in $(PROGRAMS); do $(MAKE) -C $$p objs; done"
+        )
+        .unwrap();
+        writeln!(content).unwrap();
+
+        writeln!(content, "clean:").unwrap();
+        writeln!(
+            content,
+            "\t@set -e; for p in $(PROGRAMS); do $(MAKE) -C $$p clean; done"
+        )
+        .unwrap();
+        writeln!(content, "\trm -f lib{}.so", self.base_name).unwrap();
+        writeln!(content).unwrap();
+
+        writeln!(content, ".PHONY: shared programs clean").unwrap();
+
+        let path = self.makefile_path();
+        trace!(path = %path.display(), "writing library Makefile");
+        write_if_changed(&path, content).map(drop)
+    }
+
+    /// Makefile header: job limit, compiler, launcher and flags.
+    fn write_toolchain(&self, content: &mut String) {
+        writeln!(content, "# Generated by RVR").unwrap();
+        writeln!(content).unwrap();
+
+        // Limit parallel jobs (computed in Rust as nproc-2 by default)
+        writeln!(content, "MAKEFLAGS += -j{} -l{}", self.jobs, self.jobs).unwrap();
+        writeln!(content).unwrap();
+
+        writeln!(content, "CC = {}", self.config.compiler).unwrap();
+        writeln!(content, "AR = {ARCHIVER}").unwrap();
+        // Compiler cache prefixed to compile (not link) commands
+        let launcher = self.config.compiler_launcher.resolve();
+        match launcher {
+            Some(launcher) => writeln!(content, "LAUNCHER = {launcher}").unwrap(),
+            None => writeln!(content, "LAUNCHER =").unwrap(),
+        }
+        writeln!(content).unwrap();
+
+        let (cflags, ldflags) = self.flags();
+        writeln!(content, "{OPT_VAR} = {OPT_FLAG}").unwrap();
+        writeln!(content, "CFLAGS = $({OPT_VAR}) {}", cflags.join(" ")).unwrap();
+        if ldflags.is_empty() {
+            writeln!(content, "LDFLAGS =").unwrap();
+        } else {
+            writeln!(content, "LDFLAGS = {}", ldflags.join(" ")).unwrap();
+        }
+        writeln!(content, "SHARED_FLAGS = {SHARED_FLAGS}").unwrap();
+        writeln!(content).unwrap();
+    }
+
+    /// Compile and link flags, based on compiler type, without the
+    /// optimization level.
+    fn flags(&self) -> (Vec<&'static str>, Vec<String>) {
+        let compiler = &self.config.compiler;
+        let is_clang = compiler.is_clang();
+
+        let mut cflags = vec![
+            "-march=native",
+            "-pipe",
+            "-fomit-frame-pointer",
+            "-funroll-loops",
+            "-fno-stack-protector",
+            "-w",
+            "-DNDEBUG",
+        ];
+
+        // Line tables map host code to the guest PC map lines
+        if self.config.emit_guest_pc_map() {
+            cflags.push(if is_clang {
+                "-gline-tables-only"
+            } else {
+                "-g1"
+            });
+        }
+
+        let mut ldflags: Vec<String> = Vec::new();
+        // Archive members must be plain objects any host linker can use
+        let enable_lto = self.enable_lto && !self.config.static_archive();
+
+        if self.config.c_dialect.is_portable() {
+            // Plain C11 for any compiler: no clang-only codegen flags or
+            // linker, standard LTO
+            cflags.push("-std=c11");
+            if enable_lto {
+                cflags.push("-flto");
+                ldflags.push("-flto".to_string());
+            }
+        } else if is_clang {
+            cflags.push("-std=c23");
+            cflags.push("-fzero-call-used-regs=skip");
+            if enable_lto {
+                cflags.push("-flto=thin");
+                cflags.push("-fno-plt");
+                cflags.push("-fno-semantic-interposition");
+                ldflags.push("-flto=thin".to_string());
+                if let Some(linker) = compiler.linker() {
+                    ldflags.push(format!("-fuse-ld={linker}"));
+                }
+            }
+        } else {
+            // GCC
+            cflags.push("-std=c2x");
+            if enable_lto {
+                cflags.push("-flto");
+                ldflags.push("-flto".to_string());
+            }
+        }
+        ldflags.extend(
+            self.config
+                .dispatch_table_ldflags()
+                .iter()
+                .map(ToString::to_string),
+        );
+        (cflags, ldflags)
+    }
+
+    /// Object rules: headers every object depends on, plus per-file inputs.
+    ///
+    /// Parts declare the blocks they reference themselves, so only dispatch
+    /// depends on the blocks header and adding a block elsewhere does not
+    /// rebuild them.
+    fn write_object_rules(&self, content: &mut String) {
+        let mut hdrs = vec![format!("{}.h", self.base_name)];
+        if self.config.htif_enabled() {
+            hdrs.push(format!("{}_htif.h", self.base_name));
+        }
+        if self.config.has_tracing() {
+            hdrs.push("rv_tracer.h".to_string());
+        }
+        writeln!(content, "HDRS = {}", hdrs.join(" ")).unwrap();
+        writeln!(content).unwrap();
+
+        writeln!(content, "%.o: %.c $(HDRS)").unwrap();
+        writeln!(content, "\t@touch $@{COMPILE_STAMP_SUFFIX}").unwrap();
+        writeln!(
+            content,
+            "\t$(LAUNCHER) $(CC) $(CFLAGS) $(SHARED_FLAGS) -c $< -o $@"
+        )
+        .unwrap();
+        writeln!(content, "\t@echo {COMPILED_MARKER} $@").unwrap();
+        writeln!(content).unwrap();
+
+        writeln!(
+            content,
+            "{}_dispatch.o: {}_blocks.h {ABI_HEADER}",
+            self.base_name, self.base_name
+        )
+        .unwrap();
+        if self.config.static_archive() {
+            writeln!(content, "{}_embed.o: {EMBED_HEADER}", self.base_name).unwrap();
+        }
+        // Portable memory.c holds its segments inline rather than #embed
+        let bins: Vec<String> = self
+            .segments
+            .iter()
+            .enumerate()
+            .filter(|(_, seg)| seg.has_data() && !self.config.c_dialect.is_portable())
+            .map(|(i, seg)| segment_bin_name(i, seg.is_compressed(self.config.compress_segments)))
+            .collect();
+        if !bins.is_empty() {
+            writeln!(content, "{}_memory.o: {}", self.base_name, bins.join(" ")).unwrap();
+        }
+        // Flags live in the Makefile: rebuild everything when it changes
+        writeln!(content, "$(OBJS): Makefile").unwrap();
+    }
+}
+
+/// `text` as a JSON string literal.
+fn json_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use rvr_ir::Rv64;
+
+    use super::*;
+    use crate::config::{CompilerLauncher, EmitConfig};
+
+    #[test]
+    fn test_write_library_makefile() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = EmitConfig::<Rv64>::default();
+        config.symbol_prefix = "fib_".to_string();
+        let program = CProject::new(dir.path().join("fib"), "fib", config.clone());
+        let makefile = program.render_makefile(&[0]);
+        assert!(makefile.contains("objs: $(OBJS)"));
+        assert!(makefile.contains("print-objs:"));
+
+        let library = CProject::new(dir.path(), "lib", config);
+        library
+            .write_library_makefile(&["fib".to_string(), "sha".to_string()])
+            .unwrap();
+        let makefile = fs::read_to_string(library.makefile_path()).unwrap();
+        assert!(makefile.contains("PROGRAMS = fib sha\n"));
+        assert!(makefile.contains("liblib.so: programs\n"));
+        assert!(makefile.contains("$(MAKE) -C $$p objs"));
+    }
+
+    #[test]
+    fn test_makefile_compiler_launcher() {
+        let config = EmitConfig::<Rv64>::default()
+            .with_compiler_launcher(CompilerLauncher::Command("ccache".to_string()));
+        let makefile = CProject::new("/tmp/test", "rv64", config).render_makefile(&[0]);
+        assert!(makefile.contains("LAUNCHER = ccache\n"));
+        assert!(makefile.contains("\t$(LAUNCHER) $(CC) $(CFLAGS) $(SHARED_FLAGS) -c $< -o $@"));
+        // Linking does not go through the launcher
+        assert!(makefile.contains("\t$(CC) $(CFLAGS) $(LDFLAGS) -shared"));
+
+        let config = EmitConfig::<Rv64>::default().with_compiler_launcher(CompilerLauncher::None);
+        let makefile = CProject::new("/tmp/test", "rv64", config).render_makefile(&[0]);
+        assert!(makefile.contains("LAUNCHER =\n"));
+    }
+
+    #[test]
+    fn test_makefile_static_archive() {
+        let config = EmitConfig::<Rv64>::default();
+        let makefile = CProject::new("/tmp/test", "rv64", config.clone()).render_makefile(&[0]);
+        assert!(makefile.contains("librv64.a: $(OBJS)\n\trm -f $@ && $(AR) rcs $@ $(OBJS)\n"));
+        assert!(makefile.contains("-flto"));
+        assert!(!makefile.contains("rv64_embed.c"));
+
+        let config = config.with_static_archive(true);
+        let makefile = CProject::new("/tmp/test", "rv64", config).render_makefile(&[0]);
+        assert!(makefile.contains(" rv64_embed.c\n"));
+        assert!(makefile.contains("rv64_embed.o: rv_embed.h\n"));
+        assert!(!makefile.contains("-flto"));
+    }
+
+    #[test]
+    fn test_makefile_dispatch_table_hugepages() {
+        let config = EmitConfig::<Rv64>::default();
+        let makefile = CProject::new("/tmp/test", "rv64", config.clone()).render_makefile(&[0]);
+        assert!(!makefile.contains("max-page-size"));
+
+        let config = config.with_dispatch_table_hugepages(true);
+        let makefile = CProject::new("/tmp/test", "rv64", config).render_makefile(&[0]);
+        assert!(
+            makefile.contains("-Wl,-z,max-page-size=0x200000 -Wl,-z,common-page-size=0x200000")
+        );
+    }
+
+    #[test]
+    fn test_makefile_part_retry() {
+        let makefile =
+            CProject::new("/tmp/test", "rv64", EmitConfig::<Rv64>::default()).render_makefile(&[0]);
+        assert!(makefile.contains("OPT = -O3\nCFLAGS = $(OPT) -march=native "));
+        assert!(makefile.contains(".DELETE_ON_ERROR:\n"));
+        assert!(makefile.contains("%.o: %.c $(HDRS)\n\t@touch $@.start\n"));
+        assert!(makefile.contains(" -c $< -o $@\n\t@echo compiled $@\n"));
+        assert!(makefile.contains("\trm -f $(OBJS) $(OBJS:=.start) "));
+    }
+
+    #[test]
+    fn test_render_compile_commands() {
+        let config = EmitConfig::<Rv64>::default()
+            .with_compiler_launcher(CompilerLauncher::Command("ccache".to_string()));
+        let project = CProject::new("/tmp/test", "rv64", config);
+        let commands = project.compile_commands(&[0, 2]);
+        assert_eq!(commands[1].file, "rv64_part2.c");
+        assert_eq!(
+            commands[1].arguments[commands[1].arguments.len() - 5..],
+            ["-fPIC", "-c", "rv64_part2.c", "-o", "rv64_part2.o"]
+        );
+        assert_eq!(commands[2].file, "rv64_dispatch.c");
+        assert!(!commands[0].arguments.iter().any(|arg| arg == "ccache"));
+        assert_eq!(commands[0].arguments[1], "-O3");
+
+        let json = render_compile_commands(Path::new("/tmp/te\"st"), &commands[..1]);
+        assert!(json.starts_with("[\n  {\n    \"directory\": \"/tmp/te\\\"st\",\n"));
+        assert!(json.contains("\"file\": \"rv64_part0.c\""));
+        assert!(json.ends_with("\"-o\", \"rv64_part0.o\"]\n  }\n]\n"));
+    }
+}
@@ -0,0 +1,156 @@
+//! Parts manifest for incremental C emission.
+//!
+//! `parts.manifest` in the output directory records, for every partition
+//! file, the PC of its first block and a hash of its contents. Emitting into
+//! the same directory again uses it to:
+//! - leave part files whose contents did not change untouched, so make only
+//!   rebuilds the parts that did;
+//! - cut partitions at the same function entries, so a localized code change
+//!   only moves the blocks of one or two parts.
+//!
+//! A missing or unreadable manifest just means every part is written.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+/// Manifest file name in the output directory.
+pub const PARTS_MANIFEST: &str = "parts.manifest";
+
+const HEADER: &str = "# rvr parts manifest v1";
+
+/// One partition file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PartEntry {
+    /// Start PC of the first block in the part.
+    pub start_pc: u64,
+    /// `content_hash` of the part source.
+    pub hash: u64,
+}
+
+/// Partition files of one emission, by part index.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PartsManifest {
+    pub parts: BTreeMap<usize, PartEntry>,
+}
+
+impl PartsManifest {
+    /// Read a manifest; a missing or malformed file gives an empty one.
+    #[must_use]
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|text| Self::parse(&text))
+            .unwrap_or_default()
+    }
+
+    /// Parse the manifest format: a header line, then one
+    /// `<index> <start_pc> <hash>` line per part (PC and hash in hex).
+    #[must_use]
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut lines = text.lines();
+        if lines.next()? != HEADER {
+            return None;
+        }
+        let mut parts = BTreeMap::new();
+        for line in lines.filter(|line| !line.is_empty()) {
+            let mut fields = line.split_whitespace();
+            let idx = fields.next()?.parse().ok()?;
+            let start_pc = u64::from_str_radix(fields.next()?.strip_prefix("0x")?, 16).ok()?;
+            let hash = u64::from_str_radix(fields.next()?, 16).ok()?;
+            if fields.next().is_some() {
+                return None;
+            }
+            parts.insert(idx, PartEntry { start_pc, hash });
+        }
+        Some(Self { parts })
+    }
+
+    /// Render the manifest (see `parse`).
+    #[must_use]
+    pub fn render(&self) -> String {
+        let mut s = format!("{HEADER}\n");
+        for (idx, entry) in &self.parts {
+            let _ = writeln!(s, "{idx} {:#x} {:016x}", entry.start_pc, entry.hash);
+        }
+        s
+    }
+
+    /// Part index for each recorded start PC.
+    #[must_use]
+    pub fn index_by_start_pc(&self) -> BTreeMap<u64, usize> {
+        self.parts
+            .iter()
+            .map(|(&idx, entry)| (entry.start_pc, idx))
+            .collect()
+    }
+}
+
+/// Stable 64-bit FNV-1a hash of a part's contents.
+#[must_use]
+pub fn content_hash(bytes: &[u8]) -> u64 {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    bytes.iter().fold(OFFSET, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+    })
+}
+
+/// Write `contents` to `path` unless the file already holds exactly that,
+/// keeping its timestamp for make. Returns whether the file was written.
+///
+/// # Errors
+/// Returns any I/O error while writing the file.
+pub fn write_if_changed(path: &Path, contents: impl AsRef<[u8]>) -> std::io::Result<bool> {
+    let contents = contents.as_ref();
+    if fs::read(path).is_ok_and(|old| old == contents) {
+        return Ok(false);
+    }
+    fs::write(path, contents)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_roundtrip() {
+        let mut manifest = PartsManifest::default();
+        manifest.parts.insert(
+            0,
+            PartEntry {
+                start_pc: 0x8000_0000,
+                hash: content_hash(b"part0"),
+            },
+        );
+        manifest.parts.insert(
+            3,
+            PartEntry {
+                start_pc: 0x8000_1234,
+                hash: 1,
+            },
+        );
+        let text = manifest.render();
+        assert!(text.contains("3 0x80001234 0000000000000001\n"));
+        assert_eq!(PartsManifest::parse(&text), Some(manifest.clone()));
+        assert_eq!(manifest.index_by_start_pc().get(&0x8000_1234), Some(&3));
+    }
+
+    #[test]
+    fn test_malformed_manifest_is_ignored() {
+        assert_eq!(PartsManifest::parse("0 0x1000 00\n"), None);
+        assert_eq!(
+            PartsManifest::parse(&format!("{HEADER}\n0 4096 00\n")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_content_hash_is_stable() {
+        // FNV-1a test vectors
+        assert_eq!(content_hash(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(content_hash(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+}
@@ -1,12 +1,16 @@
 //! Memory initialization code generation.
 //!
 //! Generates memory.c containing:
-//! - Embedded ELF segment data
-//! - Memory initialization with guard pages
-//! - Memory cleanup function
+//! - Embedded ELF segment data, LZ4-compressed above a size threshold
+//! - `rv_init_memory`, which writes the segments into guest memory
 
+use std::borrow::Cow;
 use std::fmt::Write;
 
+use super::lz4::{LZ4_DECODER, lz4_compress};
+use super::namespace::global_symbol;
+use crate::Compression;
+
 /// Memory segment information.
 #[derive(Clone, Debug)]
 pub struct MemorySegment {
@@ -37,6 +41,19 @@ impl MemorySegment {
     pub const fn has_data(&self) -> bool {
         !self.data.is_empty()
     }
+
+    /// Check if the segment is embedded compressed under `compression`.
+    #[must_use]
+    pub fn is_compressed(&self, compression: Option<Compression>) -> bool {
+        self.has_data() && compression.is_some_and(|c| self.data.len() >= c.min_size())
+    }
+}
+
+/// Name of the file `#embed` reads the data of segment `index` from.
+#[must_use]
+pub fn segment_bin_name(index: usize, compressed: bool) -> String {
+    let ext = if compressed { "lz4" } else { "bin" };
+    format!("segment_{index}.{ext}")
 }
 
 /// Memory generation configuration.
@@ -49,6 +66,10 @@ pub struct MemoryConfig {
     pub memory_bits: u8,
     /// Initial program break.
     pub initial_brk: u64,
+    /// Compression of large segments (plain if unset).
+    pub compression: Option<Compression>,
+    /// Prefix for global symbols (see `EmitConfig::symbol_prefix`).
+    pub symbol_prefix: String,
 }
 
 impl MemoryConfig {
@@ -64,18 +85,83 @@ impl MemoryConfig {
             segments,
             memory_bits,
             initial_brk,
+            compression: None,
+            symbol_prefix: String::new(),
         }
     }
+
+    /// Compress segments as `compression` says.
+    #[must_use]
+    pub const fn with_compression(mut self, compression: Option<Compression>) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Link the global symbols under `prefix`.
+    #[must_use]
+    pub fn with_symbol_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.symbol_prefix = prefix.into();
+        self
+    }
+
+    /// Data of each segment as embedded in the library, compressed where
+    /// the segment reaches the compression threshold.
+    #[must_use]
+    pub fn pack_segments(&self) -> Vec<PackedSegment<'_>> {
+        self.segments
+            .iter()
+            .enumerate()
+            .filter(|(_, seg)| seg.has_data())
+            .map(|(index, seg)| {
+                let compress = seg.is_compressed(self.compression);
+                let data = if compress {
+                    Cow::Owned(lz4_compress(&seg.data))
+                } else {
+                    Cow::Borrowed(seg.data.as_slice())
+                };
+                PackedSegment {
+                    index,
+                    data,
+                    compressed: compress,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Embedded data of one segment.
+#[derive(Clone, Debug)]
+pub struct PackedSegment<'a> {
+    /// Index of the segment in [`MemoryConfig::segments`].
+    pub index: usize,
+    /// The bytes as embedded: the segment data, or an LZ4 block of it.
+    pub data: Cow<'a, [u8]>,
+    /// Whether `data` is an LZ4 block.
+    pub compressed: bool,
+}
+
+impl PackedSegment<'_> {
+    /// Name of the file `#embed` reads the data from.
+    #[must_use]
+    pub fn bin_name(&self) -> String {
+        segment_bin_name(self.index, self.compressed)
+    }
 }
 
-/// Generate the memory.c file.
+/// Generate the memory.c file, with the data of `packed` (see
+/// [`MemoryConfig::pack_segments`]) as byte arrays.
 #[must_use]
-pub fn gen_memory_file(cfg: &MemoryConfig) -> String {
-    let mut s = String::new();
+pub fn gen_memory_file(cfg: &MemoryConfig, packed: &[PackedSegment]) -> String {
+    let mut s = gen_memory_prelude(cfg);
+    for seg in packed {
+        s.push_str(&gen_segment_data(cfg, seg));
+    }
+    s.push_str(&gen_segment_table(cfg, packed));
+    s
+}
 
-    // Header
-    writeln!(
-        s,
+fn gen_memory_prelude(cfg: &MemoryConfig) -> String {
+    format!(
         r#"/* Embedded ELF memory segments */
 #include "{}.h"
 #include <sys/mman.h>
@@ -85,61 +171,35 @@ pub fn gen_memory_file(cfg: &MemoryConfig) -> String {
 "#,
         cfg.base_name
     )
-    .unwrap();
-
-    // Generate embedded data for each segment with data
-    for (i, seg) in cfg.segments.iter().enumerate() {
-        if seg.has_data() {
-            s.push_str(&gen_segment_data(i, seg));
-        }
-    }
-
-    // Generate segment metadata table
-    s.push_str(
-        r"/* Segment metadata */
-typedef struct {
-    uint64_t vaddr;
-    uint64_t filesz;
-    uint64_t memsz;
-    const uint8_t* data;
-} Segment;
-
-static const Segment segments[] = {
-",
-    );
-
-    for (i, seg) in cfg.segments.iter().enumerate() {
-        let data_ptr = if seg.has_data() {
-            format!("segment_{i}_data")
-        } else {
-            "nullptr".to_string()
-        };
-        writeln!(
-            s,
-            "    {{ {:#x}, {}, {}, {} }},",
-            seg.vaddr, seg.filesz, seg.memsz, data_ptr
-        )
-        .unwrap();
-    }
-
-    s.push_str("};\n\n");
+}
 
-    s
+fn gen_segment_comment(cfg: &MemoryConfig, packed: &PackedSegment) -> String {
+    let seg = &cfg.segments[packed.index];
+    let compressed = if packed.compressed {
+        format!(", LZ4 to {} bytes", packed.data.len())
+    } else {
+        String::new()
+    };
+    format!(
+        "/* Segment {}: {:#x} ({} bytes file, {} bytes mem{compressed}) */",
+        packed.index, seg.vaddr, seg.filesz, seg.memsz
+    )
 }
 
-fn gen_segment_data(index: usize, seg: &MemorySegment) -> String {
+fn gen_segment_data(cfg: &MemoryConfig, packed: &PackedSegment) -> String {
     let mut s = String::new();
 
+    writeln!(s, "{}", gen_segment_comment(cfg, packed)).unwrap();
     writeln!(
         s,
-        "/* Segment {}: {:#x} ({} bytes file, {} bytes mem) */",
-        index, seg.vaddr, seg.filesz, seg.memsz
+        "static const uint8_t segment_{}_data[] = {{",
+        packed.index
     )
     .unwrap();
-    writeln!(s, "static const uint8_t segment_{index}_data[] = {{").unwrap();
 
     // Write data as hex bytes
-    for (i, chunk) in seg.data.chunks(16).enumerate() {
+    let data = &packed.data;
+    for (i, chunk) in data.chunks(16).enumerate() {
         s.push_str("    ");
         for (j, byte) in chunk.iter().enumerate() {
             if j > 0 {
@@ -147,7 +207,7 @@ fn gen_segment_data(index: usize, seg: &MemorySegment) -> String {
             }
             write!(s, "{byte:#04x}").unwrap();
         }
-        if i * 16 + chunk.len() < seg.data.len() {
+        if i * 16 + chunk.len() < data.len() {
             s.push(',');
         }
         s.push('\n');
@@ -157,62 +217,16 @@ fn gen_segment_data(index: usize, seg: &MemorySegment) -> String {
     s
 }
 
-/// Write binary segment files for C23 #embed directive.
-/// Returns list of (filename, data) pairs.
-#[must_use]
-pub fn gen_segment_bins(cfg: &MemoryConfig) -> Vec<(String, Vec<u8>)> {
-    cfg.segments
-        .iter()
-        .enumerate()
-        .filter(|(_, seg)| seg.has_data())
-        .map(|(i, seg)| (format!("segment_{i}.bin"), seg.data.clone()))
-        .collect()
-}
-
-/// Generate memory.c using C23 #embed for large segments.
-#[must_use]
-pub fn gen_memory_file_with_embed(cfg: &MemoryConfig) -> String {
-    let mut s = String::new();
-
-    // Header
-    writeln!(
-        s,
-        r#"/* Embedded ELF memory segments */
-#include "{}.h"
-#include <sys/mman.h>
-#include <string.h>
-#include <stddef.h>
-
-"#,
-        cfg.base_name
-    )
-    .unwrap();
-
-    // Generate embedded data using #embed for segments with data
-    for (i, seg) in cfg.segments.iter().enumerate() {
-        if seg.has_data() {
-            writeln!(
-                s,
-                r#"/* Segment {}: {:#x} ({} bytes file, {} bytes mem) */
-static const uint8_t segment_{}_data[] = {{
-    #embed "segment_{}.bin"
-}};
-
-"#,
-                i, seg.vaddr, seg.filesz, seg.memsz, i, i
-            )
-            .unwrap();
-        }
-    }
-
-    // Generate segment metadata table
-    s.push_str(
+/// Segment metadata table and `rv_init_memory`.
+fn gen_segment_table(cfg: &MemoryConfig, packed: &[PackedSegment]) -> String {
+    let mut s = String::from(
         r"/* Segment metadata */
 typedef struct {
     uint64_t vaddr;
     uint64_t filesz;
     uint64_t memsz;
     const uint8_t* data;
+    uint64_t packed_size; /* LZ4 block size, 0 if data is plain */
 } Segment;
 
 static const Segment segments[] = {
@@ -220,21 +234,84 @@ static const Segment segments[] = {
     );
 
     for (i, seg) in cfg.segments.iter().enumerate() {
-        let data_ptr = if seg.has_data() {
-            format!("segment_{i}_data")
-        } else {
-            "nullptr".to_string()
-        };
+        let embedded = packed.iter().find(|p| p.index == i);
+        let data_ptr = embedded.map_or_else(|| "NULL".to_string(), |_| format!("segment_{i}_data"));
+        let packed_size = embedded
+            .filter(|p| p.compressed)
+            .map_or(0, |p| p.data.len());
         writeln!(
             s,
-            "    {{ {:#x}, {}, {}, {} }},",
-            seg.vaddr, seg.filesz, seg.memsz, data_ptr
+            "    {{ {:#x}, {}, {}, {}, {} }},",
+            seg.vaddr, seg.filesz, seg.memsz, data_ptr, packed_size
         )
         .unwrap();
     }
 
     s.push_str("};\n\n");
 
+    // The decoder is only referenced (and only defined) with a compressed segment
+    let copy = if packed.iter().any(|p| p.compressed) {
+        s.push_str(LZ4_DECODER);
+        r"        if (seg->packed_size != 0) {
+            lz4_decode(dst, seg->data, seg->packed_size);
+        } else {
+            memcpy(dst, seg->data, seg->filesz);
+        }"
+    } else {
+        "        memcpy(dst, seg->data, seg->filesz);"
+    };
+    let init_memory = global_symbol(&cfg.symbol_prefix, "rv_init_memory");
+    write!(
+        s,
+        r"/* Write the segment data into guest memory, which the host has zeroed */
+void {init_memory}(RvState* state) {{
+    for (size_t i = 0; i < sizeof(segments) / sizeof(segments[0]); i++) {{
+        const Segment* seg = &segments[i];
+        if (seg->data == NULL) {{
+            continue;
+        }}
+        uint8_t* dst = state->memory + seg->vaddr;
+{copy}
+    }}
+}}
+"
+    )
+    .unwrap();
+
+    s
+}
+
+/// Binary segment files for the C23 #embed directive: (filename, data)
+/// pairs, compressed ones named `segment_<i>.lz4`.
+#[must_use]
+pub fn gen_segment_bins<'a>(packed: &'a [PackedSegment]) -> Vec<(String, &'a [u8])> {
+    packed
+        .iter()
+        .map(|seg| (seg.bin_name(), seg.data.as_ref()))
+        .collect()
+}
+
+/// Generate memory.c using C23 #embed for the data of `packed`.
+#[must_use]
+pub fn gen_memory_file_with_embed(cfg: &MemoryConfig, packed: &[PackedSegment]) -> String {
+    let mut s = gen_memory_prelude(cfg);
+
+    for seg in packed {
+        writeln!(
+            s,
+            r#"{}
+static const uint8_t segment_{}_data[] = {{
+    #embed "{}"
+}};
+"#,
+            gen_segment_comment(cfg, seg),
+            seg.index,
+            seg.bin_name()
+        )
+        .unwrap();
+    }
+
+    s.push_str(&gen_segment_table(cfg, packed));
     s
 }
 
@@ -251,10 +328,41 @@ mod tests {
             vec![0x01, 0x02, 0x03, 0x04],
         )];
         let cfg = MemoryConfig::new("test", segments, 32, 0x8001_0000);
-        let memory = gen_memory_file(&cfg);
+        let memory = gen_memory_file(&cfg, &cfg.pack_segments());
 
         assert!(memory.contains("segment_0_data"));
         assert!(memory.contains("segments[]"));
+        assert!(memory.contains("void rv_init_memory(RvState* state) {"));
+        assert!(!memory.contains("lz4_decode"));
+    }
+
+    #[test]
+    fn test_compressed_segments() {
+        let segments = vec![
+            MemorySegment::new(0x1000, 4, 4, vec![1, 2, 3, 4]),
+            MemorySegment::new(0x2000, 4096, 8192, vec![0xAB; 4096]),
+        ];
+        let cfg = MemoryConfig::new("test", segments, 32, 0x4000)
+            .with_compression(Some(Compression::Lz4 { min_size: 1024 }));
+        let packed = cfg.pack_segments();
+        // The small segment stays plain
+        assert!(!packed[0].compressed);
+        assert_eq!(packed[0].data.as_ref(), [1, 2, 3, 4]);
+        assert!(packed[1].compressed);
+        assert!(packed[1].data.len() < 64);
+
+        let bins = gen_segment_bins(&packed);
+        assert_eq!(bins[0].0, "segment_0.bin");
+        assert_eq!(bins[1].0, "segment_1.lz4");
+
+        let memory = gen_memory_file_with_embed(&cfg, &packed);
+        assert!(memory.contains("    #embed \"segment_1.lz4\""));
+        assert!(memory.contains(&format!(
+            "    {{ 0x2000, 4096, 8192, segment_1_data, {} }},",
+            packed[1].data.len()
+        )));
+        assert!(memory.contains("    { 0x1000, 4, 4, segment_0_data, 0 },"));
+        assert!(memory.contains("lz4_decode(dst, seg->data, seg->packed_size);"));
     }
 
     #[test]
@@ -264,10 +372,11 @@ mod tests {
             MemorySegment::new(0x9000_0000, 0, 4096, vec![]), // BSS, no data
         ];
         let cfg = MemoryConfig::new("test", segments, 32, 0x8001_0000);
-        let bins = gen_segment_bins(&cfg);
+        let packed = cfg.pack_segments();
+        let bins = gen_segment_bins(&packed);
 
         assert_eq!(bins.len(), 1);
         assert_eq!(bins[0].0, "segment_0.bin");
-        assert_eq!(bins[0].1, vec![0x01, 0x02, 0x03, 0x04]);
+        assert_eq!(bins[0].1, [0x01, 0x02, 0x03, 0x04]);
     }
 }
@@ -1,27 +1,60 @@
 //! C code emission backend.
 //!
 //! Generates C code that can be compiled with clang/gcc.
-//! Uses blocks-as-functions with musttail for tail call optimization.
+//! Uses blocks-as-functions with musttail for tail call optimization, or in
+//! the portable dialect a trampoline loop over blocks returning the next one.
 
+mod artifacts;
+mod cold;
 pub mod config;
+mod dedup;
 mod dispatch;
+mod embed;
 mod emitter;
+mod exports;
+mod golden;
 mod header;
 mod htif;
+mod interp;
+mod intrinsics;
+mod lz4;
+mod makefile;
+mod manifest;
 mod memory;
+mod namespace;
+mod partition;
+mod pc_map;
+mod probe;
 mod project;
 mod signature;
 mod syscalls;
 mod tracer;
 mod tracers;
+mod vector;
 
+pub use artifacts::*;
+pub use cold::*;
 pub use config::*;
+pub use dedup::*;
 pub use dispatch::*;
+pub use embed::*;
 pub use emitter::*;
+pub use exports::*;
+pub use golden::*;
 pub use header::*;
 pub use htif::*;
+pub use interp::*;
+pub use intrinsics::*;
+pub use lz4::*;
+pub use makefile::*;
+pub use manifest::*;
 pub use memory::*;
+pub use namespace::*;
+pub use partition::*;
+pub use pc_map::*;
+pub use probe::*;
 pub use project::*;
 pub use signature::*;
 pub use syscalls::*;
 pub use tracer::*;
+pub use vector::*;
@@ -0,0 +1,68 @@
+//! Per-program symbol prefixes.
+//!
+//! Programs compiled into one shared library must not share global
+//! symbols: block functions and the fixed-name runtime symbols are emitted
+//! with the prefix in their names. Each program thus dispatches through its
+//! own `dispatch_table` and traps to its own `rv_trap`.
+
+use rvr_ir::Xlen;
+
+/// Placeholder for the symbol prefix in C templates that are not
+/// `format!` strings (see [`namespaced`]).
+pub const PREFIX_PLACEHOLDER: &str = "{prefix}";
+
+/// Block function name for `pc`.
+#[must_use]
+pub fn block_name<X: Xlen>(prefix: &str, pc: u64) -> String {
+    let width = if X::VALUE == 64 { 16 } else { 8 };
+    format!("{prefix}B_{pc:0width$x}")
+}
+
+/// Suffix of a block body that takes its function's own hot registers
+/// (`HotRegsMode::PerFunction`); `B_<pc>` stays the entry with the global
+/// hot registers.
+pub const HOT_BODY_SUFFIX: &str = "_hot";
+
+/// Name of the block body at `pc` with its function's hot registers.
+#[must_use]
+pub fn hot_body_name<X: Xlen>(prefix: &str, pc: u64) -> String {
+    format!("{}{HOT_BODY_SUFFIX}", block_name::<X>(prefix, pc))
+}
+
+/// Linked name of the fixed-name global symbol `name`.
+#[must_use]
+pub fn global_symbol(prefix: &str, name: &str) -> String {
+    format!("{prefix}{name}")
+}
+
+/// `template` with every [`PREFIX_PLACEHOLDER`] replaced by `prefix`.
+#[must_use]
+pub fn namespaced(template: &str, prefix: &str) -> String {
+    template.replace(PREFIX_PLACEHOLDER, prefix)
+}
+
+#[cfg(test)]
+mod tests {
+    use rvr_ir::{Rv32, Rv64};
+
+    use super::*;
+
+    #[test]
+    fn test_block_name() {
+        assert_eq!(block_name::<Rv32>("", 0x1000), "B_00001000");
+        assert_eq!(block_name::<Rv64>("a_", 0x1000), "a_B_0000000000001000");
+    }
+
+    #[test]
+    fn test_namespaced() {
+        let template = "void {prefix}rv_trap(void) { {prefix}rv_cold_exit(); }";
+        assert_eq!(
+            namespaced(template, "p_"),
+            "void p_rv_trap(void) { p_rv_cold_exit(); }"
+        );
+        assert_eq!(
+            namespaced(template, ""),
+            "void rv_trap(void) { rv_cold_exit(); }"
+        );
+    }
+}
@@ -0,0 +1,283 @@
+//! Splitting blocks into C part files balanced by compile cost.
+//!
+//! A part's compile time follows the amount of C in it, which tracks IR
+//! statements far better than instructions or blocks: one superblock can
+//! hold as much as hundreds of small blocks. Parts are filled towards an
+//! even share of the total cost, so `make -j` does not end with one part
+//! compiling long after the others.
+
+use std::collections::HashSet;
+
+use rvr_ir::{BlockIR, Xlen};
+
+use super::manifest::PartsManifest;
+use super::project::CProject;
+
+/// Estimated compile cost of `block`: one per IR statement and one per
+/// instruction for its terminator.
+#[must_use]
+pub fn block_cost<X: Xlen>(block: &BlockIR<X>) -> usize {
+    block
+        .instructions
+        .iter()
+        .map(|instr| 1 + instr.statements.len())
+        .sum()
+}
+
+impl<X: Xlen> CProject<X> {
+    /// Partition blocks by estimated compile cost.
+    ///
+    /// Returns list of (`partition_idx`, blocks) tuples in PC order.
+    pub fn partition_blocks<'a>(
+        &self,
+        blocks: &'a [BlockIR<X>],
+    ) -> Vec<(usize, Vec<&'a BlockIR<X>>)> {
+        self.partition_blocks_with(blocks, &PartsManifest::default())
+    }
+
+    /// Partition blocks, keeping the cuts and indices of a previous emission.
+    ///
+    /// Parts start at function entries: a block whose function differs from
+    /// the previous block's (blocks without a known function each count as
+    /// one). A function costing more than `target_part_cost` may also be cut
+    /// between its blocks. A new part starts where the previous emission
+    /// started one, where adding the next function would take the part past
+    /// `target_part_cost`, or where the part is closer to an even share of
+    /// the total cost without it than with it. A part starting at the same
+    /// PC as before keeps its index; new parts take the lowest free ones.
+    pub fn partition_blocks_with<'a>(
+        &self,
+        blocks: &'a [BlockIR<X>],
+        previous: &PartsManifest,
+    ) -> Vec<(usize, Vec<&'a BlockIR<X>>)> {
+        let previous_starts = previous.index_by_start_pc();
+        let target = self.config.target_part_cost.max(1);
+
+        let units = self.cut_units(blocks, target);
+        let total: usize = units.iter().map(|(_, cost)| cost).sum();
+        let num_parts = total.div_ceil(target).max(1);
+
+        let mut parts: Vec<Vec<&BlockIR<X>>> = Vec::new();
+        let mut current_cost = 0;
+        for (unit_blocks, cost) in units {
+            let start_pc = X::to_u64(unit_blocks[0].start_pc);
+            // Past the even share `total / num_parts` by more than it is
+            // short of it without this unit
+            let past_share = num_parts * (2 * current_cost + cost) > 2 * total;
+            if parts.is_empty()
+                || previous_starts.contains_key(&start_pc)
+                || current_cost + cost > target
+                || past_share
+            {
+                parts.push(Vec::new());
+                current_cost = 0;
+            }
+            if let Some(part) = parts.last_mut() {
+                part.extend(unit_blocks);
+            }
+            current_cost += cost;
+        }
+
+        let start_pc = |part: &[&BlockIR<X>]| X::to_u64(part[0].start_pc);
+        let kept: HashSet<usize> = parts
+            .iter()
+            .filter_map(|part| previous_starts.get(&start_pc(part)).copied())
+            .collect();
+        let mut next_free = 0;
+        parts
+            .into_iter()
+            .map(|part| {
+                let idx = if let Some(&idx) = previous_starts.get(&start_pc(&part)) {
+                    idx
+                } else {
+                    while kept.contains(&next_free) {
+                        next_free += 1;
+                    }
+                    next_free += 1;
+                    next_free - 1
+                };
+                (idx, part)
+            })
+            .collect()
+    }
+
+    /// Group blocks into the units parts are made of, with their costs:
+    /// whole functions, or single blocks of functions costing more than
+    /// `target`.
+    fn cut_units<'a>(
+        &self,
+        blocks: &'a [BlockIR<X>],
+        target: usize,
+    ) -> Vec<(Vec<&'a BlockIR<X>>, usize)> {
+        let mut functions: Vec<(Vec<&BlockIR<X>>, usize)> = Vec::new();
+        let mut current_function = None;
+        for block in blocks {
+            let pc = X::to_u64(block.start_pc);
+            let function = self.inputs.block_to_function.get(&pc).copied();
+            let starts_function = function.is_none() || function != current_function;
+            current_function = function;
+            let cost = block_cost(block);
+            match functions.last_mut() {
+                Some((function_blocks, function_cost)) if !starts_function => {
+                    function_blocks.push(block);
+                    *function_cost += cost;
+                }
+                _ => functions.push((vec![block], cost)),
+            }
+        }
+
+        functions
+            .into_iter()
+            .flat_map(|(function_blocks, cost)| {
+                if cost > target && function_blocks.len() > 1 {
+                    function_blocks
+                        .into_iter()
+                        .map(|block| (vec![block], block_cost(block)))
+                        .collect()
+                } else {
+                    vec![(function_blocks, cost)]
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rvr_ir::{Expr, InstrIR, Rv64, Stmt, Terminator};
+
+    use super::*;
+    use crate::c::manifest::PartEntry;
+    use crate::config::EmitConfig;
+    use crate::inputs::EmitInputs;
+
+    const HEAVY_BLOCKS: u64 = 4;
+    const LIGHT_BLOCKS: u64 = 45;
+
+    fn project(target_part_cost: usize) -> CProject<Rv64> {
+        let config = EmitConfig::<Rv64>::default().with_target_part_cost(target_part_cost);
+        CProject::new("/tmp/test", "rv64", config)
+    }
+
+    /// A block of `num_instrs` instructions with `stmts` statements each.
+    fn block(start_pc: u64, num_instrs: usize, stmts: usize) -> BlockIR<Rv64> {
+        let mut block = BlockIR::new(start_pc);
+        for pc in (start_pc..).step_by(4).take(num_instrs) {
+            let statements = vec![Stmt::write_reg(1, Expr::imm(0)); stmts];
+            block.push(InstrIR::new(pc, 4, 0, 0, statements, Terminator::default()));
+        }
+        block
+    }
+
+    fn starts(partitions: &[(usize, Vec<&BlockIR<Rv64>>)]) -> Vec<(usize, u64, usize)> {
+        partitions
+            .iter()
+            .map(|(idx, part)| (*idx, part[0].start_pc, part.len()))
+            .collect()
+    }
+
+    #[test]
+    fn test_partition_blocks() {
+        let blocks = vec![
+            block(0x1000, 5, 0),
+            block(0x2000, 3, 0), // partition 0 (8 total)
+            block(0x3000, 4, 0),
+            block(0x4000, 6, 0), // partition 1 (10 total)
+            block(0x5000, 2, 0), // partition 2
+        ];
+
+        let partitions = project(10).partition_blocks(&blocks);
+
+        assert_eq!(
+            starts(&partitions),
+            vec![(0, 0x1000, 2), (1, 0x3000, 2), (2, 0x5000, 1)]
+        );
+    }
+
+    #[test]
+    fn test_partition_blocks_balances_cost() {
+        // Superblocks with many statements next to many small blocks
+        let heavy = (0..HEAVY_BLOCKS).map(|i| block(0x1000 + i * 0x100, 10, 9));
+        let light = (0..LIGHT_BLOCKS).map(|i| block(0x10_0000 + i * 0x100, 10, 0));
+        let blocks: Vec<_> = heavy.chain(light).collect();
+
+        let partitions = project(100).partition_blocks(&blocks);
+
+        let sizes: Vec<usize> = partitions.iter().map(|(_, part)| part.len()).collect();
+        let costs: Vec<usize> = partitions
+            .iter()
+            .map(|(_, part)| part.iter().map(|b| block_cost(b)).sum())
+            .collect();
+        assert_ne!(sizes.iter().min(), sizes.iter().max());
+        let (min, max) = (costs.iter().min().unwrap(), costs.iter().max().unwrap());
+        assert!(max <= &(2 * min), "{costs:?}");
+    }
+
+    #[test]
+    fn test_partition_blocks_cuts_at_function_entries() {
+        let inputs = EmitInputs {
+            block_to_function: [(0x1000, 0x1000), (0x2000, 0x1000), (0x3000, 0x3000)]
+                .into_iter()
+                .collect(),
+            ..EmitInputs::default()
+        };
+        let project = project(10).with_inputs(inputs);
+        let blocks = vec![
+            block(0x1000, 5, 0),
+            block(0x2000, 4, 0), // same function: stays with 0x1000
+            block(0x3000, 2, 0),
+        ];
+
+        let partitions = project.partition_blocks(&blocks);
+
+        assert_eq!(starts(&partitions), vec![(0, 0x1000, 2), (1, 0x3000, 1)]);
+    }
+
+    #[test]
+    fn test_partition_blocks_splits_large_functions() {
+        let inputs = EmitInputs {
+            block_to_function: [(0x1000, 0x1000), (0x2000, 0x1000), (0x3000, 0x1000)]
+                .into_iter()
+                .collect(),
+            ..EmitInputs::default()
+        };
+        let project = project(10).with_inputs(inputs);
+        let blocks = vec![
+            block(0x1000, 8, 0),
+            block(0x2000, 8, 0),
+            block(0x3000, 2, 0),
+        ];
+
+        let partitions = project.partition_blocks(&blocks);
+
+        assert_eq!(starts(&partitions), vec![(0, 0x1000, 1), (1, 0x2000, 2)]);
+    }
+
+    #[test]
+    fn test_partition_blocks_keeps_previous_parts() {
+        let mut previous = PartsManifest::default();
+        for (idx, start_pc) in [(0, 0x1000), (1, 0x3000), (2, 0x5000)] {
+            previous.parts.insert(idx, PartEntry { start_pc, hash: 0 });
+        }
+        // 0x1000 grew past the target cost and 0x4000 is new
+        let blocks = vec![
+            block(0x1000, 6, 0),
+            block(0x2000, 6, 0),
+            block(0x3000, 4, 0),
+            block(0x4000, 2, 0),
+            block(0x5000, 2, 0),
+        ];
+
+        let partitions = project(10).partition_blocks_with(&blocks, &previous);
+
+        assert_eq!(
+            starts(&partitions),
+            vec![
+                (0, 0x1000, 1),
+                (3, 0x2000, 1),
+                (1, 0x3000, 2),
+                (2, 0x5000, 1)
+            ]
+        );
+    }
+}
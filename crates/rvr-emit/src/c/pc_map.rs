@@ -0,0 +1,173 @@
+//! Guest PC map: host addresses back to guest PCs.
+//!
+//! With [`EmitConfig::emit_guest_pc_map`](crate::EmitConfig::emit_guest_pc_map)
+//! every guest instruction is preceded by `#line <n> "guest_pc.map"` instead
+//! of its source location, and the library is built with line tables. The
+//! compiler then records, for each host instruction, which line of
+//! `guest_pc.map` it came from; line `n` of that sidecar file names the guest
+//! PC (`0x<pc> <mnemonic>`). Debuggers and profilers that show source lines
+//! thus show guest PCs, and `rvr addr2pc` resolves a host address directly.
+//!
+//! The assembly backends put a `.loc` directive naming the line before each
+//! guest instruction instead.
+//!
+//! Lines are numbered in guest PC order, so adding or removing an
+//! instruction renumbers everything after it and changes the generated
+//! sources of later parts.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+use rvr_ir::{BlockIR, InstrIR, SyntheticBlockInfo, Xlen};
+use rvr_isa::op_mnemonic;
+
+/// File name of the sidecar in the output directory.
+pub const GUEST_PC_MAP: &str = "guest_pc.map";
+
+/// DWARF file number the assembly backends give `guest_pc.map`.
+const ASM_FILE_NUMBER: u32 = 1;
+
+/// `.file` directive declaring `guest_pc.map` in assembly output.
+#[must_use]
+pub fn asm_file_directive() -> String {
+    format!(".file {ASM_FILE_NUMBER} \"{GUEST_PC_MAP}\"")
+}
+
+/// Line of `guest_pc.map` for each guest instruction.
+#[derive(Clone, Debug, Default)]
+pub struct GuestPcLines {
+    /// Guest PC -> line (1-based).
+    lines: HashMap<u64, u32>,
+    /// Line text, in line order.
+    entries: Vec<String>,
+}
+
+impl GuestPcLines {
+    /// Number the guest instructions of `blocks` in PC order.
+    ///
+    /// Instructions of helper blocks share the line of the guest instruction
+    /// that owns them.
+    pub fn new<'a, X: Xlen + 'a>(
+        blocks: impl IntoIterator<Item = &'a BlockIR<X>>,
+        synthetic_blocks: &HashMap<u64, SyntheticBlockInfo>,
+    ) -> Self {
+        Self::for_instructions(
+            blocks.into_iter().flat_map(|b| &b.instructions),
+            synthetic_blocks,
+        )
+    }
+
+    /// Number the guest instructions of a linear instruction stream (the
+    /// assembly backends) in PC order.
+    pub fn for_instructions<'a, X: Xlen + 'a>(
+        instrs: impl IntoIterator<Item = &'a InstrIR<X>>,
+        synthetic_blocks: &HashMap<u64, SyntheticBlockInfo>,
+    ) -> Self {
+        let mut mnemonics: BTreeMap<u64, &str> = BTreeMap::new();
+        for instr in instrs {
+            let pc = X::to_u64(instr.pc);
+            match synthetic_blocks.get(&pc) {
+                Some(info) => {
+                    mnemonics.entry(info.owner_pc).or_default();
+                }
+                None => {
+                    mnemonics.insert(pc, op_mnemonic(instr.op));
+                }
+            }
+        }
+        let mut lines = HashMap::with_capacity(mnemonics.len());
+        let mut entries = Vec::with_capacity(mnemonics.len());
+        for (line, (pc, mnemonic)) in (1..).zip(mnemonics) {
+            lines.insert(pc, line);
+            entries.push(format!("{pc:#x} {mnemonic}").trim_end().to_string());
+        }
+        Self { lines, entries }
+    }
+
+    /// Line of the guest instruction at `pc`.
+    #[must_use]
+    pub fn line(&self, pc: u64) -> Option<u32> {
+        self.lines.get(&pc).copied()
+    }
+
+    /// `.loc` directive attributing the assembly that follows to the guest
+    /// instruction at `pc`.
+    #[must_use]
+    pub fn asm_loc(&self, pc: u64) -> Option<String> {
+        self.line(pc)
+            .map(|line| format!(".loc {ASM_FILE_NUMBER} {line} 0"))
+    }
+
+    /// Contents of `guest_pc.map`.
+    #[must_use]
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for entry in &self.entries {
+            let _ = writeln!(out, "{entry}");
+        }
+        out
+    }
+}
+
+/// Guest PC named on `line` (1-based) of `guest_pc.map` contents.
+#[must_use]
+pub fn guest_pc_from_map(map: &str, line: u32) -> Option<u64> {
+    let entry = map
+        .lines()
+        .nth(usize::try_from(line).ok()?.checked_sub(1)?)?;
+    let pc = entry.split_whitespace().next()?;
+    u64::from_str_radix(pc.strip_prefix("0x")?, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rvr_ir::{HelperInstret, InstrIR, Rv64, Terminator};
+    use rvr_isa::{OP_ADDI, OP_LW};
+
+    fn block(instrs: &[(u64, u16)]) -> BlockIR<Rv64> {
+        let mut block = BlockIR::new(instrs[0].0);
+        for &(pc, op) in instrs {
+            block.push(InstrIR::new(
+                pc,
+                4,
+                op,
+                0,
+                Vec::new(),
+                Terminator::default(),
+            ));
+        }
+        block
+    }
+
+    #[test]
+    fn test_lines_follow_guest_pc_order() {
+        let (addi, lw) = (OP_ADDI.pack(), OP_LW.pack());
+        let blocks = [
+            block(&[(0x1008, lw)]),
+            block(&[(0x1000, addi), (0x1004, addi)]),
+            block(&[(0x9000, addi)]),
+        ];
+        let mut synthetic = HashMap::new();
+        synthetic.insert(
+            0x9000,
+            SyntheticBlockInfo {
+                owner_pc: 0x1004,
+                instret: HelperInstret::Uncounted,
+            },
+        );
+
+        let lines = GuestPcLines::new(&blocks, &synthetic);
+        assert_eq!(lines.line(0x1000), Some(1));
+        assert_eq!(lines.line(0x1004), Some(2));
+        assert_eq!(lines.line(0x1008), Some(3));
+        assert_eq!(lines.line(0x9000), None);
+        assert_eq!(lines.asm_loc(0x1008).as_deref(), Some(".loc 1 3 0"));
+        assert_eq!(lines.asm_loc(0x9000), None);
+        let map = lines.render();
+        assert_eq!(map, "0x1000 addi\n0x1004 addi\n0x1008 lw\n");
+        assert_eq!(guest_pc_from_map(&map, 3), Some(0x1008));
+        assert_eq!(guest_pc_from_map(&map, 0), None);
+        assert_eq!(guest_pc_from_map(&map, 4), None);
+    }
+}
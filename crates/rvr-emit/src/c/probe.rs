@@ -0,0 +1,444 @@
+//! C compiler probing.
+//!
+//! `<cc> --version` identifies the compiler's vendor and version, which
+//! decide whether it can build [`CDialect::Clang`] code. Probes are cached
+//! per compiler command for the life of the process.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::process::Command;
+use std::sync::{Mutex, PoisonError};
+
+use thiserror::Error;
+
+use super::{CDialect, Compiler, on_path};
+
+/// Compiler vendor, from its `--version` banner.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CompilerVendor {
+    /// LLVM clang.
+    Clang,
+    /// Apple clang, versioned separately from LLVM.
+    AppleClang,
+    /// GNU GCC.
+    Gcc,
+    /// A compiler none of the above.
+    Unknown,
+}
+
+impl fmt::Display for CompilerVendor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Clang => "clang",
+            Self::AppleClang => "Apple clang",
+            Self::Gcc => "GCC",
+            Self::Unknown => "unknown compiler",
+        })
+    }
+}
+
+/// `major.minor.patch` compiler version.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CompilerVersion {
+    /// Major version.
+    pub major: u32,
+    /// Minor version.
+    pub minor: u32,
+    /// Patch version.
+    pub patch: u32,
+}
+
+impl CompilerVersion {
+    /// Parse a dotted version such as `18.1.8` or `22.0.0git`; missing
+    /// components are 0. `None` unless it starts with a digit.
+    #[must_use]
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut parts = s.split('.').map(|part| {
+            let end = part
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(part.len());
+            part[..end].parse::<u32>().ok()
+        });
+        let major = parts.next()??;
+        let mut next = || parts.next().flatten().unwrap_or(0);
+        Some(Self {
+            major,
+            minor: next(),
+            patch: next(),
+        })
+    }
+}
+
+impl fmt::Display for CompilerVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// A probed compiler: vendor and version parsed from `--version`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompilerIdentity {
+    /// Vendor.
+    pub vendor: CompilerVendor,
+    /// Version; `None` for an unknown vendor or an unparsable banner.
+    pub version: Option<CompilerVersion>,
+    /// Full `--version` output.
+    pub version_output: String,
+}
+
+impl CompilerIdentity {
+    /// Identify a compiler from its `--version` output.
+    ///
+    /// Recognizes `Apple clang version 15.0.0 (...)`, `[Vendor] clang
+    /// version 18.1.8 (...)` and GCC's `gcc (Debian 12.2.0-14) 12.2.0`.
+    #[must_use]
+    pub fn parse(version_output: &str) -> Self {
+        let first = version_output.lines().next().unwrap_or_default();
+        let after = |marker: &str| {
+            first
+                .find(marker)
+                .and_then(|at| first[at + marker.len()..].split_whitespace().next())
+                .and_then(CompilerVersion::parse)
+        };
+        let (vendor, version) = if first.contains("Apple clang version") {
+            (CompilerVendor::AppleClang, after("Apple clang version "))
+        } else if first.contains("clang version") {
+            (CompilerVendor::Clang, after("clang version "))
+        } else if version_output.contains("Free Software Foundation") {
+            // The version follows the parenthesized package version, and
+            // may be followed by a date: `gcc (GCC) 15.1.1 20250521`
+            let tail = first.rfind(')').map_or(first, |at| &first[at + 1..]);
+            let version = tail.split_whitespace().find_map(CompilerVersion::parse);
+            (CompilerVendor::Gcc, version)
+        } else {
+            (CompilerVendor::Unknown, None)
+        };
+        Self {
+            vendor,
+            version,
+            version_output: version_output.to_string(),
+        }
+    }
+
+    /// Whether the compiler is GCC older than `major`.
+    #[must_use]
+    pub fn is_gcc_older_than(&self, major: u32) -> bool {
+        self.vendor == CompilerVendor::Gcc && self.version.is_some_and(|v| v.major < major)
+    }
+
+    /// Check this compiler has every capability `dialect` code needs.
+    ///
+    /// # Errors
+    /// Returns the first missing capability; any compiler builds portable C.
+    pub fn check_dialect(&self, command: &str, dialect: CDialect) -> Result<(), CompilerError> {
+        if dialect.is_portable() {
+            return Ok(());
+        }
+        let Some(version) = self
+            .version
+            .filter(|_| self.vendor != CompilerVendor::Unknown)
+        else {
+            return Err(CompilerError::Unrecognized {
+                command: command.to_string(),
+                banner: self.banner().to_string(),
+            });
+        };
+        for capability in CompilerCapability::ALL {
+            let minimum = capability.minimum(self.vendor);
+            if version.major < minimum {
+                return Err(CompilerError::TooOld {
+                    command: command.to_string(),
+                    identity: self.to_string(),
+                    capability,
+                    minimum: format!("{} {minimum}", self.vendor),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// First line of the `--version` output.
+    #[must_use]
+    pub fn banner(&self) -> &str {
+        self.version_output.lines().next().unwrap_or_default()
+    }
+}
+
+impl fmt::Display for CompilerIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.version {
+            Some(version) if self.vendor != CompilerVendor::Unknown => {
+                write!(f, "{} {version}", self.vendor)
+            }
+            _ => write!(f, "{} ({})", self.vendor, self.banner()),
+        }
+    }
+}
+
+/// Compiler features [`CDialect::Clang`] code uses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompilerCapability {
+    /// Guaranteed tail calls between blocks.
+    Musttail,
+    /// The `preserve_none` calling convention of block functions.
+    PreserveNone,
+    /// C23 `constexpr` constants.
+    Constexpr,
+}
+
+impl CompilerCapability {
+    /// Every capability, in the order they are checked.
+    pub const ALL: [Self; 3] = [Self::Musttail, Self::PreserveNone, Self::Constexpr];
+
+    /// Minimum major version of `vendor` with this capability.
+    #[must_use]
+    pub const fn minimum(self, vendor: CompilerVendor) -> u32 {
+        match vendor {
+            CompilerVendor::Clang => match self {
+                Self::Musttail => 13,
+                Self::PreserveNone | Self::Constexpr => 19,
+            },
+            CompilerVendor::AppleClang => match self {
+                Self::Musttail => 13,
+                Self::PreserveNone | Self::Constexpr => 17,
+            },
+            CompilerVendor::Gcc => match self {
+                Self::Musttail | Self::PreserveNone => super::GCC_MUSTTAIL_VERSION,
+                Self::Constexpr => 13,
+            },
+            CompilerVendor::Unknown => u32::MAX,
+        }
+    }
+}
+
+impl fmt::Display for CompilerCapability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Musttail => "musttail",
+            Self::PreserveNone => "preserve_none",
+            Self::Constexpr => "C23 constexpr",
+        })
+    }
+}
+
+/// A compiler that cannot build the requested code.
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum CompilerError {
+    #[error("compiler `{command}` did not run: {reason}")]
+    NotFound { command: String, reason: String },
+    #[error(
+        "compiler `{command}` is {identity}, but clang C needs {capability} \
+         (since {minimum}); upgrade it or use --c-dialect portable"
+    )]
+    TooOld {
+        command: String,
+        identity: String,
+        capability: CompilerCapability,
+        minimum: String,
+    },
+    #[error(
+        "compiler `{command}` is not a recognized clang or GCC (`{banner}`); \
+         use --c-dialect portable"
+    )]
+    Unrecognized { command: String, banner: String },
+    #[error("linker `{linker}` for compiler `{command}` not found; set one with --linker")]
+    LinkerNotFound { command: String, linker: String },
+}
+
+impl Compiler {
+    /// Run `--version` and identify the compiler. Successful probes are
+    /// cached per command.
+    ///
+    /// # Errors
+    /// Returns [`CompilerError::NotFound`] if the compiler does not run.
+    pub fn probe(&self) -> Result<CompilerIdentity, CompilerError> {
+        static PROBES: Mutex<Option<HashMap<String, CompilerIdentity>>> = Mutex::new(None);
+        let command = self.command();
+        if let Some(identity) = PROBES
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_ref()
+            .and_then(|probes| probes.get(command))
+        {
+            return Ok(identity.clone());
+        }
+        let not_found = |reason: String| CompilerError::NotFound {
+            command: command.to_string(),
+            reason,
+        };
+        let output = Command::new(command)
+            .arg("--version")
+            .output()
+            .map_err(|e| not_found(e.to_string()))?;
+        if !output.status.success() {
+            return Err(not_found(format!(
+                "--version exited with {}",
+                output.status
+            )));
+        }
+        let identity = CompilerIdentity::parse(&String::from_utf8_lossy(&output.stdout));
+        PROBES
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get_or_insert_with(HashMap::new)
+            .insert(command.to_string(), identity.clone());
+        Ok(identity)
+    }
+
+    /// Probe the compiler and check it can build `dialect` code, and, if
+    /// `link`, that its linker exists.
+    ///
+    /// # Errors
+    /// Names the compiler and what it lacks.
+    pub fn check(&self, dialect: CDialect, link: bool) -> Result<CompilerIdentity, CompilerError> {
+        let identity = self.probe()?;
+        identity.check_dialect(self.command(), dialect)?;
+        if link
+            && let Some(linker) = self.linker()
+            && !linker_exists(&linker)
+        {
+            return Err(CompilerError::LinkerNotFound {
+                command: self.command().to_string(),
+                linker,
+            });
+        }
+        Ok(identity)
+    }
+}
+
+/// Whether `-fuse-ld=<linker>` resolves: a path to a file, or `ld.<linker>`
+/// or `<linker>` on `PATH`.
+fn linker_exists(linker: &str) -> bool {
+    if linker.contains('/') {
+        return Path::new(linker).is_file();
+    }
+    on_path(&format!("ld.{linker}")) || on_path(linker)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLANG_18: &str = "Ubuntu clang version 18.1.3 (1ubuntu1)\nTarget: x86_64-pc-linux-gnu\n";
+    const CLANG_22: &str =
+        "Debian clang version 22.0.0 (++20250901)\nTarget: x86_64-pc-linux-gnu\n";
+    const APPLE_CLANG_16: &str =
+        "Apple clang version 16.0.0 (clang-1600.0.26.6)\nTarget: arm64-apple-darwin24.1.0\n";
+    const APPLE_CLANG_17: &str =
+        "Apple clang version 17.0.0 (clang-1700.0.13.3)\nTarget: arm64-apple-darwin24.4.0\n";
+    const GCC_12: &str =
+        "gcc (Debian 12.2.0-14) 12.2.0\nCopyright (C) 2022 Free Software Foundation, Inc.\n";
+    const GCC_15: &str =
+        "gcc (GCC) 15.1.1 20250521\nCopyright (C) 2025 Free Software Foundation, Inc.\n";
+    const TCC: &str = "tcc version 0.9.27 (x86_64 Linux)\n";
+
+    #[test]
+    fn test_identity_parse() {
+        let cases = [
+            (CLANG_18, CompilerVendor::Clang, Some((18, 1, 3))),
+            (CLANG_22, CompilerVendor::Clang, Some((22, 0, 0))),
+            (APPLE_CLANG_16, CompilerVendor::AppleClang, Some((16, 0, 0))),
+            (GCC_12, CompilerVendor::Gcc, Some((12, 2, 0))),
+            (GCC_15, CompilerVendor::Gcc, Some((15, 1, 1))),
+            (TCC, CompilerVendor::Unknown, None),
+        ];
+        for (banner, vendor, version) in cases {
+            let identity = CompilerIdentity::parse(banner);
+            assert_eq!(identity.vendor, vendor, "{banner}");
+            let version = version.map(|(major, minor, patch)| CompilerVersion {
+                major,
+                minor,
+                patch,
+            });
+            assert_eq!(identity.version, version, "{banner}");
+        }
+        assert_eq!(CompilerIdentity::parse(GCC_15).to_string(), "GCC 15.1.1");
+        assert!(CompilerIdentity::parse(GCC_12).is_gcc_older_than(15));
+        assert!(!CompilerIdentity::parse(CLANG_18).is_gcc_older_than(15));
+    }
+
+    /// A fake compiler printing `banner` for `--version`.
+    #[cfg(unix)]
+    fn fake_cc(dir: &Path, name: &str, banner: &str) -> Compiler {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = dir.join(name);
+        std::fs::write(&path, format!("#!/bin/sh\ncat <<'EOF'\n{banner}EOF\n")).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        Compiler::new(path.to_string_lossy())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_check_fake_compilers() {
+        let temp = tempfile::tempdir().unwrap();
+        let too_old = |capability| Some(capability);
+        let cases = [
+            ("clang-22", CLANG_22, None),
+            (
+                "clang-18",
+                CLANG_18,
+                too_old(CompilerCapability::PreserveNone),
+            ),
+            (
+                "clang",
+                APPLE_CLANG_16,
+                too_old(CompilerCapability::PreserveNone),
+            ),
+            ("clang", APPLE_CLANG_17, None),
+            ("gcc-12", GCC_12, too_old(CompilerCapability::Musttail)),
+            ("gcc-15", GCC_15, None),
+        ];
+        for (i, (name, banner, missing)) in cases.into_iter().enumerate() {
+            let dir = temp.path().join(i.to_string());
+            std::fs::create_dir(&dir).unwrap();
+            let compiler = fake_cc(&dir, name, banner);
+            let result = compiler.check(CDialect::Clang, false);
+            match missing {
+                None => assert_eq!(result.unwrap(), CompilerIdentity::parse(banner)),
+                Some(capability) => {
+                    let err = result.unwrap_err();
+                    assert!(
+                        matches!(&err, CompilerError::TooOld { capability: c, .. } if *c == capability),
+                        "{name}: {err}"
+                    );
+                    assert!(err.to_string().contains("--c-dialect portable"), "{err}");
+                }
+            }
+            // Any compiler that runs builds portable C
+            assert!(compiler.check(CDialect::Portable, false).is_ok());
+        }
+
+        let tcc = fake_cc(temp.path(), "tcc", TCC);
+        assert!(matches!(
+            tcc.check(CDialect::Clang, false),
+            Err(CompilerError::Unrecognized { .. })
+        ));
+        assert!(tcc.check(CDialect::Portable, false).is_ok());
+
+        let missing = Compiler::new(temp.path().join("no-such-cc").to_string_lossy());
+        assert!(matches!(
+            missing.check(CDialect::Portable, false),
+            Err(CompilerError::NotFound { .. })
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_check_linker() {
+        let temp = tempfile::tempdir().unwrap();
+        let clang = fake_cc(temp.path(), "clang-22", CLANG_22);
+        let linker = temp.path().join("ld.lld-22");
+        let err = clang
+            .clone()
+            .with_linker(linker.to_string_lossy())
+            .check(CDialect::Clang, true)
+            .unwrap_err();
+        assert!(matches!(err, CompilerError::LinkerNotFound { .. }), "{err}");
+
+        std::fs::write(&linker, "").unwrap();
+        let clang = clang.with_linker(linker.to_string_lossy());
+        assert!(clang.check(CDialect::Clang, true).is_ok());
+    }
+}
@@ -0,0 +1,367 @@
+//! `CProject` - C code generation orchestration.
+//!
+//! Coordinates emission of all C files:
+//! - Header files (main header + blocks header)
+//! - Partition files (blocks balanced by estimated compile cost, see
+//!   `partition`; optionally deduplicated; unchanged parts are not
+//!   rewritten, see `PartsManifest`)
+//! - Dispatch table
+//! - Memory initialization
+//! - Native memory intrinsics (`native_mem_intrinsics`)
+//! - Vector runtime (programs with vector instructions)
+//! - Export wrappers header (export-functions mode)
+//! - Makefile and `compile_commands.json` (see `makefile`)
+//!
+//! Everything is rendered into `CArtifacts` first; `write_all` then writes
+//! them to the output directory.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use rvr_ir::{BlockIR, Xlen};
+use tracing::{debug, info};
+
+use super::artifacts::CArtifacts;
+use super::dedup::DedupStats;
+use super::dispatch::{DispatchConfig, gen_dispatch_file};
+use super::embed::{gen_embed_header, gen_embed_source};
+use super::exports::gen_exports_header;
+use super::golden::gen_golden_source;
+use super::header::{HeaderConfig, gen_blocks_header, gen_header};
+use super::htif::{HtifConfig, gen_htif_header, gen_htif_source};
+use super::interp::gen_interp_source;
+use super::intrinsics::gen_mem_intrinsics_source;
+use super::manifest::PartsManifest;
+use super::memory::{
+    MemoryConfig, MemorySegment, gen_memory_file, gen_memory_file_with_embed, gen_segment_bins,
+};
+use super::syscalls::{SyscallsConfig, gen_syscalls_source};
+use super::tracer::gen_tracer_header;
+use super::vector::gen_vector_source;
+use crate::abi::{ABI_HEADER, gen_abi_header};
+use crate::config::EmitConfig;
+use crate::inputs::EmitInputs;
+
+mod parts;
+mod paths;
+#[cfg(test)]
+mod tests;
+
+pub use paths::partition_file_name;
+
+/// Results of writing a C project.
+#[derive(Clone, Debug, Default)]
+pub struct ProjectStats {
+    /// Number of partition files.
+    pub partitions: usize,
+    /// Partition files left untouched because their contents did not change.
+    pub unchanged_partitions: usize,
+    /// Identical-block deduplication (all zero unless `dedup_blocks` is set).
+    pub dedup: DedupStats,
+    /// C written for each block, in PC order.
+    pub blocks: Vec<EmittedBlock>,
+}
+
+/// C written for one block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EmittedBlock {
+    /// Start PC of the block.
+    pub pc: u64,
+    /// Index of the part file holding the block.
+    pub part: usize,
+    /// Bytes of the block function (0 for a deduplicated alias).
+    pub bytes: usize,
+    /// Lines of the block function (0 for a deduplicated alias).
+    pub lines: usize,
+}
+
+/// Callback receiving `(rendered, total)` part files as they are rendered.
+pub type PartProgress = Arc<dyn Fn(usize, usize) + Send + Sync>;
+
+/// C code generation project.
+pub struct CProject<X: Xlen> {
+    /// Output directory.
+    pub output_dir: PathBuf,
+    /// Base name for generated files.
+    pub base_name: String,
+    /// Emit configuration (includes compiler choice).
+    pub config: EmitConfig<X>,
+    /// Derived inputs for emission (entry point, `pc_end`, valid addresses, `initial_brk`).
+    pub inputs: EmitInputs,
+    /// Taken-inline mapping: `branch_pc` -> (`inline_start`, `inline_end`).
+    pub taken_inlines: HashMap<u64, (u64, u64)>,
+    /// Memory segments.
+    pub segments: Vec<MemorySegment>,
+    /// Enable LTO.
+    pub enable_lto: bool,
+    /// Number of parallel compilation jobs.
+    pub jobs: usize,
+    /// Progress callback for rendered part files (optional).
+    pub on_part: Option<PartProgress>,
+}
+
+impl<X: Xlen> CProject<X> {
+    /// Create a new `CProject`.
+    pub fn new(
+        output_dir: impl AsRef<Path>,
+        base_name: impl Into<String>,
+        config: EmitConfig<X>,
+    ) -> Self {
+        // Default to nproc-2 to leave headroom for system
+        let jobs =
+            std::thread::available_parallelism().map_or(4, |n| n.get().saturating_sub(2).max(1));
+        Self {
+            output_dir: output_dir.as_ref().to_path_buf(),
+            base_name: base_name.into(),
+            config,
+            inputs: EmitInputs::default(),
+            taken_inlines: HashMap::new(),
+            segments: Vec::new(),
+            enable_lto: true,
+            jobs,
+            on_part: None,
+        }
+    }
+
+    /// Set derived emission inputs.
+    #[must_use]
+    pub fn with_inputs(mut self, inputs: EmitInputs) -> Self {
+        self.inputs = inputs;
+        self
+    }
+
+    /// Set taken-inline mapping for branch inlining.
+    #[must_use]
+    pub fn with_taken_inlines(mut self, mapping: HashMap<u64, (u64, u64)>) -> Self {
+        self.taken_inlines = mapping;
+        self
+    }
+
+    /// Set memory segments.
+    #[must_use]
+    pub fn with_segments(mut self, segments: Vec<MemorySegment>) -> Self {
+        self.segments = segments;
+        self
+    }
+
+    /// Set compiler.
+    #[must_use]
+    pub fn with_compiler(mut self, compiler: crate::Compiler) -> Self {
+        self.config.compiler = compiler;
+        self
+    }
+
+    /// Report rendered part files to `callback`.
+    #[must_use]
+    pub fn with_part_progress(mut self, callback: Option<PartProgress>) -> Self {
+        self.on_part = callback;
+        self
+    }
+
+    /// Set number of parallel compilation jobs.
+    #[must_use]
+    pub const fn with_jobs(mut self, jobs: usize) -> Self {
+        self.jobs = jobs;
+        self
+    }
+
+    // ============= Path helpers =============
+
+    // ============= File generation =============
+
+    /// Render the runtime sources the configuration needs: HTIF, syscalls,
+    /// memory intrinsics, vector, golden trace and interpreter.
+    fn render_runtime(&self, artifacts: &mut CArtifacts) {
+        if self.config.htif_enabled() {
+            let htif_cfg = HtifConfig::new(&self.base_name, self.config.htif_enabled())
+                .with_dialect(self.config.c_dialect)
+                .with_verbose(self.config.htif_verbose())
+                .with_poll_limit(self.config.htif_poll_limit)
+                .with_symbol_prefix(&self.config.symbol_prefix);
+            artifacts.push_file(&self.htif_header_path(), gen_htif_header::<X>(&htif_cfg));
+            artifacts.push_file(&self.htif_source_path(), gen_htif_source::<X>(&htif_cfg));
+        }
+
+        let fixed_addresses = self.config.fixed_addresses.is_some();
+        if self.config.syscall_runtime() {
+            let cfg = SyscallsConfig::new(&self.base_name, fixed_addresses)
+                .with_symbol_prefix(&self.config.symbol_prefix);
+            artifacts.push_file(&self.syscalls_path(), gen_syscalls_source::<X>(&cfg));
+        }
+
+        if self.config.native_mem_intrinsics() {
+            let src = gen_mem_intrinsics_source::<X>(
+                &self.base_name,
+                fixed_addresses,
+                &self.config.symbol_prefix,
+            );
+            artifacts.push_file(&self.mem_intrinsics_path(), src);
+        }
+
+        // Vector runtime only for programs with vector instructions
+        if self.inputs.vector {
+            let src = gen_vector_source::<X>(
+                &self.base_name,
+                fixed_addresses,
+                &self.config.symbol_prefix,
+            );
+            artifacts.push_file(&self.vector_path(), src);
+        }
+
+        if let Some(trace) = &self.inputs.golden {
+            let src = gen_golden_source(
+                &self.base_name,
+                &self.config.symbol_prefix,
+                trace,
+                self.config.c_dialect,
+            );
+            artifacts.push_file(&self.golden_source_path(), src);
+        }
+
+        if !self.inputs.interpreted.is_empty() {
+            let src = gen_interp_source(&self.base_name, &self.config, &self.inputs);
+            artifacts.push_file(&self.interp_source_path(), src);
+        }
+    }
+
+    /// Render the memory source and, with `#embed`, the segment binaries.
+    fn render_memory(&self, artifacts: &mut CArtifacts) {
+        let mem_cfg = MemoryConfig::new(
+            &self.base_name,
+            self.segments.clone(),
+            self.config.memory_bits,
+            self.inputs.initial_brk,
+        )
+        .with_compression(self.config.compress_segments)
+        .with_symbol_prefix(&self.config.symbol_prefix);
+        let packed = mem_cfg.pack_segments();
+
+        // Portable C has no #embed: segments are byte arrays in memory.c
+        let memory = if self.config.c_dialect.is_portable() {
+            gen_memory_file(&mem_cfg, &packed)
+        } else {
+            artifacts.segment_bins = gen_segment_bins(&packed)
+                .into_iter()
+                .map(|(name, data)| (name, data.to_vec()))
+                .collect();
+            gen_memory_file_with_embed(&mem_cfg, &packed)
+        };
+        artifacts.push_file(&self.memory_path(), memory);
+    }
+
+    /// Render every generated file in memory.
+    ///
+    /// Parts are cut afresh, as `write_all` cuts them in an empty directory.
+    ///
+    /// # Errors
+    /// Returns `InvalidData` if a block has no instructions to terminate it,
+    /// or any I/O error while reading a custom tracer header.
+    pub fn render_all(&self, blocks: &[BlockIR<X>]) -> std::io::Result<CArtifacts> {
+        self.render_with(blocks, &PartsManifest::default())
+    }
+
+    /// Render every generated file, keeping the part cuts of `previous`.
+    fn render_with(
+        &self,
+        blocks: &[BlockIR<X>],
+        previous: &PartsManifest,
+    ) -> std::io::Result<CArtifacts> {
+        debug!(
+            base_name = %self.base_name,
+            blocks = blocks.len(),
+            "generating C project"
+        );
+
+        let block_addresses: Vec<u64> = blocks.iter().map(|b| X::to_u64(b.start_pc)).collect();
+        let header_cfg =
+            HeaderConfig::new(&self.base_name, &self.config, &self.inputs, block_addresses);
+        let mut artifacts = CArtifacts {
+            base_name: self.base_name.clone(),
+            header_name: format!("{}.h", self.base_name),
+            header: gen_header::<X>(&header_cfg),
+            ..CArtifacts::default()
+        };
+        artifacts.push_file(
+            &self.blocks_header_path(),
+            gen_blocks_header::<X>(&header_cfg),
+        );
+
+        self.render_parts(blocks, previous, &mut artifacts)?;
+
+        let dispatch_cfg = DispatchConfig::new(&self.config, &self.base_name, self.inputs.clone());
+        artifacts.push_file(&self.dispatch_path(), gen_dispatch_file::<X>(&dispatch_cfg));
+        artifacts.push_file(
+            &self.output_dir.join(ABI_HEADER),
+            gen_abi_header(&self.config),
+        );
+        if self.config.static_archive() {
+            artifacts.push_file(&self.embed_header_path(), gen_embed_header(&dispatch_cfg));
+            artifacts.push_file(&self.embed_source_path(), gen_embed_source(&dispatch_cfg));
+        }
+
+        if !self.segments.is_empty() {
+            self.render_memory(&mut artifacts);
+        }
+
+        self.render_runtime(&mut artifacts);
+
+        if self.config.emit_guest_pc_map() {
+            let map = self.inputs.guest_pc_lines.render();
+            artifacts.push_file(&self.guest_pc_map_path(), map);
+        }
+
+        if !self.config.tracer_config.is_none() {
+            let tracer_header = gen_tracer_header::<X>(
+                &self.config.tracer_config,
+                self.config.memory_bits,
+                &(self.inputs.text_start..self.inputs.pc_end),
+                self.inputs.elf_hash,
+                self.config.c_dialect,
+            )?;
+            artifacts.push_file(&self.tracer_header_path(), tracer_header);
+        }
+
+        // Export wrappers for C hosts
+        if self.config.export_functions {
+            let header = gen_exports_header::<X>(
+                &self.base_name,
+                &self.config.symbol_prefix,
+                self.config.num_regs,
+                self.config.c_dialect,
+                &self.inputs.exported_functions,
+            );
+            artifacts.push_file(&self.exports_header_path(), header);
+        }
+
+        let part_indices: Vec<usize> = artifacts.manifest.parts.keys().copied().collect();
+        artifacts.makefile = self.render_makefile(&part_indices);
+        artifacts.compile_commands = self.compile_commands(&part_indices);
+        Ok(artifacts)
+    }
+
+    /// Write all generated sources and headers.
+    ///
+    /// Parts keep the cuts of the last emission into the output directory,
+    /// and files whose contents did not change are left untouched, so make
+    /// only rebuilds what the change affected (see `CArtifacts::write_to`).
+    ///
+    /// Returns the number of partitions created and deduplication results.
+    ///
+    /// # Errors
+    /// Returns any I/O error while writing the project files.
+    pub fn write_all(&self, blocks: &[BlockIR<X>]) -> std::io::Result<ProjectStats> {
+        let previous = PartsManifest::load(&self.parts_manifest_path());
+        let artifacts = self.render_with(blocks, &previous)?;
+        let stats = artifacts.write_to(&self.output_dir)?;
+
+        info!(
+            output_dir = %self.output_dir.display(),
+            partitions = stats.partitions,
+            unchanged_partitions = stats.unchanged_partitions,
+            "C project generated"
+        );
+
+        Ok(stats)
+    }
+}
@@ -0,0 +1,404 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write as FmtWrite;
+
+use rvr_ir::{BlockIR, Xlen};
+use tracing::debug;
+
+use crate::c::artifacts::CArtifacts;
+use crate::c::cold::{cold_path_declarations, outlines_cold_paths};
+use crate::c::dedup::{BlockDedup, DedupStats};
+use crate::c::emitter::CEmitter;
+use crate::c::manifest::{PartEntry, PartsManifest, content_hash};
+use crate::c::namespace::{HOT_BODY_SUFFIX, global_symbol};
+use crate::c::signature::FnSignature;
+
+use super::paths::partition_file_name;
+use super::{CProject, EmittedBlock, ProjectStats};
+
+/// Block function names (`{prefix}B_<pc>`, or a `_hot` body) referenced
+/// in `code`, in PC order.
+pub(super) fn referenced_blocks<'a, X: Xlen>(code: &'a str, prefix: &str) -> BTreeSet<&'a str> {
+    let width = if X::VALUE == 64 { 16 } else { 8 };
+    let is_ident = |b: u8| b.is_ascii_alphanumeric() || b == b'_';
+    let bytes = code.as_bytes();
+    let pattern = format!("{prefix}B_");
+    code.match_indices(&pattern)
+        .filter_map(|(start, _)| {
+            let mut end = start + pattern.len() + width;
+            let hex = code.get(start + pattern.len()..end)?;
+            if code[end..].starts_with(HOT_BODY_SUFFIX) {
+                end += HOT_BODY_SUFFIX.len();
+            }
+            let boundary_before = start == 0 || !is_ident(bytes[start - 1]);
+            let boundary_after = bytes.get(end).is_none_or(|&b| !is_ident(b));
+            let hex = hex.bytes().all(|b| b.is_ascii_hexdigit());
+            (boundary_before && boundary_after && hex).then(|| &code[start..end])
+        })
+        .collect()
+}
+
+/// Sizes of the rendered `blocks` of part `part`.
+fn emitted_blocks<'a, X: Xlen>(
+    part: usize,
+    blocks: &'a [&BlockIR<X>],
+    rendered: &'a [String],
+    dedup: &'a BlockDedup,
+) -> impl Iterator<Item = EmittedBlock> + 'a {
+    blocks.iter().zip(rendered).map(move |(block, text)| {
+        let pc = X::to_u64(block.start_pc);
+        let alias = dedup.alias_of.contains_key(&pc);
+        EmittedBlock {
+            pc,
+            part,
+            bytes: if alias { 0 } else { text.len() },
+            lines: if alias { 0 } else { text.lines().count() },
+        }
+    })
+}
+
+impl<X: Xlen> CProject<X> {
+    /// Render one block function.
+    ///
+    /// The `block_map` is used for taken-inline support - when a branch has an
+    /// inline entry, we look up the inlined block by its start address.
+    ///
+    /// # Errors
+    /// Returns `InvalidData` if the block has no instructions to terminate it.
+    pub fn render_block(
+        &self,
+        emitter: &mut CEmitter<X>,
+        block: &BlockIR<X>,
+        block_map: &HashMap<u64, &BlockIR<X>>,
+    ) -> std::io::Result<String> {
+        use rvr_ir::Terminator;
+
+        emitter.reset();
+
+        let start_pc = X::to_u64(block.start_pc);
+        let end_pc = X::to_u64(block.end_pc);
+        let num_instrs = block.instructions.len();
+
+        if self.inputs.interpreted.contains(&start_pc) {
+            emitter.render_interpreted_block(block);
+            return Ok(emitter.output().to_string());
+        }
+
+        emitter.render_block_header_with_count(start_pc, end_pc, num_instrs);
+        emitter.render_instret_check(start_pc);
+        // After the check, so a block that suspends is not traced twice
+        emitter.render_block_trace(start_pc);
+        emitter.render_golden_point(start_pc);
+
+        if num_instrs == 0 {
+            emitter.render_block_footer();
+            return Ok(emitter.output().to_string());
+        }
+
+        // Get the last instruction to check for taken-inline
+        let Some(last_instr) = block.instructions.last() else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "block has no instructions",
+            ));
+        };
+        let last_pc = X::to_u64(last_instr.pc);
+
+        // Check if this block's last instruction is a branch with taken-inline
+        let taken_inline = if let Terminator::Branch { cond, hint, .. } = &last_instr.terminator {
+            if let Some(&(inline_start, _inline_end)) = self.taken_inlines.get(&last_pc) {
+                // Found a taken-inline entry for this branch
+                Some((cond.clone(), *hint, inline_start))
+            } else {
+                None
+            }
+        } else {
+            None
+        };
+
+        // Render all instructions except the last one normally
+        let prefetch = emitter.dispatch_prefetch(block);
+        for (i, instr) in block.instructions.iter().enumerate() {
+            if let Some((at, addr)) = prefetch
+                && at == i
+            {
+                emitter.render_dispatch_prefetch(addr);
+            }
+            let is_last = i == num_instrs - 1;
+            if is_last {
+                // Handle last instruction specially if it has taken-inline
+                if let Some((cond, hint, inline_start)) = &taken_inline {
+                    // Emit trace_pc for the branch instruction before rendering its statements
+                    emitter.mark_origin(last_pc);
+                    if self.config.emit_guest_pc_map() {
+                        emitter.emit_guest_pc_line(last_pc, 1);
+                    }
+                    emitter.emit_trace_pc_for(
+                        X::to_u64(last_instr.pc),
+                        last_instr.op,
+                        last_instr.raw,
+                    );
+
+                    // Render the last instruction's statements (but not terminator)
+                    for stmt in &last_instr.statements {
+                        emitter.render_stmt(stmt, 1);
+                    }
+
+                    // Render branch condition open
+                    let cond_str = emitter.render_expr(cond);
+                    emitter.render_branch_open(&cond_str, *hint);
+                    let retired = emitter.render_taken_inline_entry(last_pc, *inline_start);
+
+                    // Look up and render the inlined block
+                    if let Some(inline_block) = block_map.get(inline_start) {
+                        let inline_num_instrs = inline_block.instructions.len();
+                        let inline_end_pc = X::to_u64(inline_block.end_pc);
+
+                        // Render inlined instructions with extra indent
+                        for (j, inline_instr) in inline_block.instructions.iter().enumerate() {
+                            let is_inline_last = j == inline_num_instrs - 1;
+                            // For per-instruction mode, pass the next instruction's PC
+                            let next_instr_pc = if !is_inline_last && j + 1 < inline_num_instrs {
+                                Some(X::to_u64(inline_block.instructions[j + 1].pc))
+                            } else {
+                                None
+                            };
+                            emitter.render_instruction_indented(
+                                inline_instr,
+                                is_inline_last,
+                                inline_end_pc,
+                                next_instr_pc,
+                                2, // Extra indentation for if-block
+                            );
+                        }
+                        // Note: instret update is handled inside render_instruction_indented for is_last=true
+                    }
+
+                    // Close branch if-block
+                    emitter.render_branch_close();
+
+                    // Fall-through for not-taken path
+                    emitter.render_instret_update(retired);
+                    emitter.render_jump_static(end_pc);
+                } else {
+                    // No taken-inline, render normally
+                    emitter.render_instruction(instr, is_last, end_pc, None);
+                }
+            } else {
+                // For per-instruction mode, pass the next instruction's PC
+                let next_instr_pc = if i + 1 < num_instrs {
+                    Some(X::to_u64(block.instructions[i + 1].pc))
+                } else {
+                    None
+                };
+                emitter.render_instruction(instr, false, end_pc, next_instr_pc);
+            }
+        }
+
+        // Note: instret update is already done inside render_instruction for is_last=true
+
+        emitter.render_block_footer();
+        Ok(emitter.output().to_string())
+    }
+
+    /// Render a single partition source file.
+    ///
+    /// # Errors
+    /// Returns `InvalidData` if a block has no instructions to terminate it.
+    pub fn render_partition(
+        &self,
+        blocks: &[&BlockIR<X>],
+        block_map: &HashMap<u64, &BlockIR<X>>,
+    ) -> std::io::Result<String> {
+        let rendered = self.render_blocks(blocks, block_map)?;
+        Ok(self.partition_source(blocks, &rendered, &BlockDedup::default()))
+    }
+
+    /// Render the functions of `blocks` with one emitter.
+    fn render_blocks(
+        &self,
+        blocks: &[&BlockIR<X>],
+        block_map: &HashMap<u64, &BlockIR<X>>,
+    ) -> std::io::Result<Vec<String>> {
+        let mut emitter = CEmitter::new(self.config.clone(), self.inputs.clone());
+        blocks
+            .iter()
+            .map(|block| self.render_block(&mut emitter, block, block_map))
+            .collect()
+    }
+
+    /// Source of a partition of rendered blocks.
+    ///
+    /// Aliases are skipped; their declarations follow the canonical block.
+    /// The part declares the blocks it references instead of including the
+    /// blocks header, so adding or removing blocks elsewhere leaves it as is.
+    fn partition_source(
+        &self,
+        blocks: &[&BlockIR<X>],
+        rendered: &[String],
+        dedup: &BlockDedup,
+    ) -> String {
+        let sig = FnSignature::new(&self.config);
+        let mut body = String::new();
+        for (block, text) in blocks.iter().zip(rendered) {
+            let pc = X::to_u64(block.start_pc);
+            if dedup.alias_of.contains_key(&pc) {
+                continue;
+            }
+            body.push_str(text);
+            let aliases = dedup.alias_declarations::<X>(pc, &sig);
+            if !aliases.is_empty() {
+                body.push_str(&aliases);
+                body.push('\n');
+            }
+        }
+
+        let mut content = String::new();
+        let _ = write!(content, "#include \"{}.h\"\n\n", self.base_name);
+        let _ = writeln!(content, "/* Trap handler for invalid addresses */");
+        let prefix = &self.config.symbol_prefix;
+        let trap = global_symbol(prefix, "rv_trap");
+        let _ = writeln!(content, "{};\n", sig.fn_decl(&trap, &[]));
+        if outlines_cold_paths(self.config.flags, self.config.c_dialect) {
+            let _ = writeln!(content, "{}", cold_path_declarations(&sig, prefix));
+        }
+        let _ = writeln!(content, "/* Blocks referenced by this part */");
+        for name in referenced_blocks::<X>(&body, &self.config.symbol_prefix) {
+            let _ = writeln!(
+                content,
+                "{};",
+                self.block_decl_sig(name, &sig).fn_decl(name, &[])
+            );
+        }
+        content.push('\n');
+        content.push_str(&body);
+        content
+    }
+
+    /// Signature of the block function `name`: a `_hot` body takes its
+    /// function's hot registers, every other block `global`.
+    fn block_decl_sig(&self, name: &str, global: &FnSignature) -> FnSignature {
+        let hot_regs = name
+            .strip_suffix(HOT_BODY_SUFFIX)
+            .and_then(|entry| entry.rsplit_once("B_"))
+            .and_then(|(_, hex)| u64::from_str_radix(hex, 16).ok())
+            .and_then(|pc| self.inputs.block_hot_regs(pc));
+        hot_regs.map_or_else(
+            || global.clone(),
+            |hot_regs| FnSignature::with_hot_regs(&self.config, hot_regs),
+        )
+    }
+
+    /// Record part `idx` of `blocks` with source `content` in `artifacts`.
+    fn push_part(
+        &self,
+        artifacts: &mut CArtifacts,
+        idx: usize,
+        blocks: &[&BlockIR<X>],
+        content: String,
+    ) {
+        let hash = content_hash(content.as_bytes());
+        let start_pc = X::to_u64(blocks[0].start_pc);
+        artifacts
+            .manifest
+            .parts
+            .insert(idx, PartEntry { start_pc, hash });
+        artifacts
+            .parts
+            .push((partition_file_name(&self.base_name, idx), content));
+        artifacts.part_indices.push(idx);
+    }
+
+    /// Report `done` of `total` part files rendered, if anyone listens.
+    fn report_part(&self, done: usize, total: usize) {
+        if let Some(on_part) = &self.on_part {
+            on_part(done, total);
+        }
+    }
+
+    /// Render all partition sources, keeping the cuts of `previous`.
+    ///
+    /// With `dedup_blocks`, all blocks are rendered first and identical
+    /// bodies are emitted once (see `BlockDedup`).
+    pub(super) fn render_parts(
+        &self,
+        blocks: &[BlockIR<X>],
+        previous: &PartsManifest,
+        artifacts: &mut CArtifacts,
+    ) -> std::io::Result<()> {
+        // Build block lookup map for taken-inline support
+        let block_map: HashMap<u64, &BlockIR<X>> =
+            blocks.iter().map(|b| (X::to_u64(b.start_pc), b)).collect();
+
+        let partitions = self.partition_blocks_with(blocks, previous);
+        debug!(
+            total_blocks = blocks.len(),
+            partitions = partitions.len(),
+            target_part_cost = self.config.target_part_cost,
+            "partitioning blocks"
+        );
+
+        let mut block_sizes = Vec::with_capacity(blocks.len());
+        let dedup = if self.config.dedup_blocks() {
+            let mut emitter = CEmitter::new(self.config.clone(), self.inputs.clone());
+            let rendered = partitions
+                .iter()
+                .map(|(_, partition_blocks)| {
+                    partition_blocks
+                        .iter()
+                        .map(|block| self.render_block(&mut emitter, block, &block_map))
+                        .collect::<std::io::Result<Vec<_>>>()
+                })
+                .collect::<std::io::Result<Vec<_>>>()?;
+            let all: Vec<(u64, &str)> = partitions
+                .iter()
+                .flat_map(|(_, partition_blocks)| partition_blocks)
+                .zip(rendered.iter().flatten())
+                .map(|(block, text)| (X::to_u64(block.start_pc), text.as_str()))
+                // Bodies with their own hot registers differ in signature
+                .filter(|(pc, _)| self.inputs.block_hot_regs(*pc).is_none())
+                .collect();
+            let dedup = BlockDedup::new::<X>(
+                &all,
+                &FnSignature::new(&self.config),
+                &self.config.symbol_prefix,
+            );
+
+            for ((idx, partition_blocks), rendered) in partitions.iter().zip(&rendered) {
+                let content = self.partition_source(partition_blocks, rendered, &dedup);
+                block_sizes.extend(emitted_blocks::<X>(
+                    *idx,
+                    partition_blocks,
+                    rendered,
+                    &dedup,
+                ));
+                self.push_part(artifacts, *idx, partition_blocks, content);
+                self.report_part(artifacts.parts.len(), partitions.len());
+            }
+            dedup.stats
+        } else {
+            let no_dedup = BlockDedup::default();
+            for (idx, partition_blocks) in &partitions {
+                let rendered = self.render_blocks(partition_blocks, &block_map)?;
+                let content = self.partition_source(partition_blocks, &rendered, &no_dedup);
+                block_sizes.extend(emitted_blocks::<X>(
+                    *idx,
+                    partition_blocks,
+                    &rendered,
+                    &no_dedup,
+                ));
+                self.push_part(artifacts, *idx, partition_blocks, content);
+                self.report_part(artifacts.parts.len(), partitions.len());
+            }
+            DedupStats::default()
+        };
+        block_sizes.sort_by_key(|block| block.pc);
+
+        artifacts.stats = ProjectStats {
+            partitions: partitions.len(),
+            unchanged_partitions: 0,
+            dedup,
+            blocks: block_sizes,
+        };
+        Ok(())
+    }
+}
@@ -0,0 +1,142 @@
+use std::path::PathBuf;
+
+use rvr_ir::Xlen;
+
+use crate::c::embed::EMBED_HEADER;
+use crate::c::exports::EXPORTS_HEADER;
+use crate::c::manifest::PARTS_MANIFEST;
+use crate::c::pc_map::GUEST_PC_MAP;
+
+use super::CProject;
+
+/// File name of part `idx` of project `base_name`.
+#[must_use]
+pub fn partition_file_name(base_name: &str, idx: usize) -> String {
+    format!("{base_name}_part{idx}.c")
+}
+
+impl<X: Xlen> CProject<X> {
+    /// Path to main header file.
+    #[must_use]
+    pub fn header_path(&self) -> PathBuf {
+        self.output_dir.join(format!("{}.h", self.base_name))
+    }
+
+    /// Path to blocks header file.
+    #[must_use]
+    pub fn blocks_header_path(&self) -> PathBuf {
+        self.output_dir.join(format!("{}_blocks.h", self.base_name))
+    }
+
+    /// Path to partition file.
+    #[must_use]
+    pub fn partition_path(&self, idx: usize) -> PathBuf {
+        self.output_dir
+            .join(partition_file_name(&self.base_name, idx))
+    }
+
+    /// Path to the parts manifest.
+    #[must_use]
+    pub fn parts_manifest_path(&self) -> PathBuf {
+        self.output_dir.join(PARTS_MANIFEST)
+    }
+
+    /// Path to dispatch file.
+    #[must_use]
+    pub fn dispatch_path(&self) -> PathBuf {
+        self.output_dir
+            .join(format!("{}_dispatch.c", self.base_name))
+    }
+
+    /// Path to memory file.
+    #[must_use]
+    pub fn memory_path(&self) -> PathBuf {
+        self.output_dir.join(format!("{}_memory.c", self.base_name))
+    }
+
+    /// Path to HTIF header file.
+    #[must_use]
+    pub fn htif_header_path(&self) -> PathBuf {
+        self.output_dir.join(format!("{}_htif.h", self.base_name))
+    }
+
+    /// Path to HTIF source file.
+    #[must_use]
+    pub fn htif_source_path(&self) -> PathBuf {
+        self.output_dir.join(format!("{}_htif.c", self.base_name))
+    }
+
+    /// Path to syscalls source file.
+    #[must_use]
+    pub fn syscalls_path(&self) -> PathBuf {
+        self.output_dir
+            .join(format!("{}_syscalls.c", self.base_name))
+    }
+
+    /// Path to native memory intrinsics source file.
+    #[must_use]
+    pub fn mem_intrinsics_path(&self) -> PathBuf {
+        self.output_dir
+            .join(format!("{}_intrinsics.c", self.base_name))
+    }
+
+    /// Path to the vector runtime source file.
+    #[must_use]
+    pub fn vector_path(&self) -> PathBuf {
+        self.output_dir.join(format!("{}_vector.c", self.base_name))
+    }
+
+    /// Path to the embedded golden trace source file.
+    #[must_use]
+    pub fn golden_source_path(&self) -> PathBuf {
+        self.output_dir.join(format!("{}_golden.c", self.base_name))
+    }
+
+    /// Path to the block interpreter source file.
+    #[must_use]
+    pub fn interp_source_path(&self) -> PathBuf {
+        self.output_dir.join(format!("{}_interp.c", self.base_name))
+    }
+
+    /// Path to the guest PC map sidecar.
+    #[must_use]
+    pub fn guest_pc_map_path(&self) -> PathBuf {
+        self.output_dir.join(GUEST_PC_MAP)
+    }
+
+    /// Path to tracer header file.
+    #[must_use]
+    pub fn tracer_header_path(&self) -> PathBuf {
+        self.output_dir.join("rv_tracer.h")
+    }
+
+    /// Path to the embedding header of the static archive.
+    #[must_use]
+    pub fn embed_header_path(&self) -> PathBuf {
+        self.output_dir.join(EMBED_HEADER)
+    }
+
+    /// Path to the embedding table source of the static archive.
+    #[must_use]
+    pub fn embed_source_path(&self) -> PathBuf {
+        self.output_dir.join(format!("{}_embed.c", self.base_name))
+    }
+
+    /// Path to export wrappers header.
+    #[must_use]
+    pub fn exports_header_path(&self) -> PathBuf {
+        self.output_dir.join(EXPORTS_HEADER)
+    }
+
+    /// Path to Makefile.
+    #[must_use]
+    pub fn makefile_path(&self) -> PathBuf {
+        self.output_dir.join("Makefile")
+    }
+
+    /// Path to shared library.
+    #[must_use]
+    pub fn shared_lib_path(&self) -> PathBuf {
+        self.output_dir.join(format!("lib{}.so", self.base_name))
+    }
+}
@@ -0,0 +1,206 @@
+use std::fs;
+
+use super::parts::referenced_blocks;
+use super::*;
+use crate::c::emitter::CEmitter;
+use crate::c::makefile::COMPILE_COMMANDS;
+use rvr_ir::Rv64;
+
+#[test]
+fn test_project_paths() {
+    let config = EmitConfig::<Rv64>::default();
+    let project = CProject::new("/tmp/test", "rv64", config);
+
+    assert_eq!(project.header_path().to_str().unwrap(), "/tmp/test/rv64.h");
+    assert_eq!(
+        project.blocks_header_path().to_str().unwrap(),
+        "/tmp/test/rv64_blocks.h"
+    );
+    assert_eq!(
+        project.partition_path(0).to_str().unwrap(),
+        "/tmp/test/rv64_part0.c"
+    );
+    assert_eq!(
+        project.dispatch_path().to_str().unwrap(),
+        "/tmp/test/rv64_dispatch.c"
+    );
+    assert_eq!(
+        project.syscalls_path().to_str().unwrap(),
+        "/tmp/test/rv64_syscalls.c"
+    );
+    assert_eq!(
+        project.makefile_path().to_str().unwrap(),
+        "/tmp/test/Makefile"
+    );
+}
+
+#[test]
+fn test_partition_declares_referenced_blocks() {
+    let config = EmitConfig::<Rv64>::default();
+    let project = CProject::new("/tmp/test", "rv64", config);
+    let blocks = [create_dummy_block(0x1000, 2)];
+    let block_map = blocks.iter().map(|b| (b.start_pc, b)).collect();
+    let block_refs: Vec<_> = blocks.iter().collect();
+
+    let source = project.render_partition(&block_refs, &block_map).unwrap();
+
+    assert!(source.starts_with("#include \"rv64.h\"\n"));
+    assert!(!source.contains("rv64_blocks.h"));
+    assert!(source.contains("void B_0000000000001000("));
+    assert_eq!(
+        referenced_blocks::<Rv64>(
+            "B_0000000000002000(x); xB_0000000000003000; B_0000000000001000; B_12",
+            ""
+        )
+        .into_iter()
+        .collect::<Vec<_>>(),
+        vec!["B_0000000000001000", "B_0000000000002000"]
+    );
+    assert_eq!(
+        referenced_blocks::<Rv64>("B_0000000000002000_hot(x); B_0000000000001000_hotx;", "")
+            .into_iter()
+            .collect::<Vec<_>>(),
+        vec!["B_0000000000002000_hot"]
+    );
+}
+
+#[test]
+fn test_partition_with_symbol_prefix() {
+    let mut config = EmitConfig::<Rv64>::default();
+    config.symbol_prefix = "a_".to_string();
+    let project = CProject::new("/tmp/test", "a", config);
+    let blocks = [create_dummy_block(0x1000, 2)];
+    let block_map = blocks.iter().map(|b| (b.start_pc, b)).collect();
+    let block_refs: Vec<_> = blocks.iter().collect();
+
+    let source = project.render_partition(&block_refs, &block_map).unwrap();
+
+    assert!(source.contains("void a_B_0000000000001000("));
+    assert!(!source.contains(" B_0000000000001000("));
+    assert_eq!(
+        referenced_blocks::<Rv64>("a_B_0000000000002000(x); B_0000000000001000;", "a_")
+            .into_iter()
+            .collect::<Vec<_>>(),
+        vec!["a_B_0000000000002000"]
+    );
+}
+
+#[test]
+fn test_write_all_skips_unchanged_parts() {
+    let dir = tempfile::tempdir().unwrap();
+    let project = CProject::new(
+        dir.path(),
+        "rv64",
+        EmitConfig::default().with_target_part_cost(4),
+    );
+    let mut blocks: Vec<_> = (0..5)
+        .map(|i| create_dummy_block(0x1000 + i * 0x100, 4))
+        .collect();
+
+    let stats = project.write_all(&blocks).unwrap();
+    assert_eq!((stats.partitions, stats.unchanged_partitions), (5, 0));
+    let makefile = fs::read_to_string(project.makefile_path()).unwrap();
+    assert!(makefile.contains("%.o: %.c $(HDRS)"));
+    assert!(makefile.contains("rv64_dispatch.o: rv64_blocks.h"));
+
+    let stats = project.write_all(&blocks).unwrap();
+    assert_eq!((stats.partitions, stats.unchanged_partitions), (5, 5));
+
+    // Shrinking one block only rewrites its part
+    blocks[2] = create_dummy_block(0x1200, 3);
+    let stats = project.write_all(&blocks).unwrap();
+    assert_eq!((stats.partitions, stats.unchanged_partitions), (5, 4));
+
+    // Dropping a block removes its part
+    blocks.remove(4);
+    let stats = project.write_all(&blocks).unwrap();
+    assert_eq!((stats.partitions, stats.unchanged_partitions), (4, 4));
+    assert!(!project.partition_path(4).exists());
+    let makefile = fs::read_to_string(project.makefile_path()).unwrap();
+    assert!(!makefile.contains("rv64_part4.c"));
+}
+
+#[test]
+fn test_render_all_matches_write_all() {
+    let dir = tempfile::tempdir().unwrap();
+    let project = CProject::new(
+        dir.path(),
+        "rv64",
+        EmitConfig::default().with_target_part_cost(4),
+    );
+    let blocks = [create_dummy_block(0x1000, 4), create_dummy_block(0x2000, 2)];
+
+    let stats = project.write_all(&blocks).unwrap();
+    let artifacts = project.render_all(&blocks).unwrap();
+    assert_eq!(artifacts.stats.partitions, stats.partitions);
+    assert_eq!(artifacts.parts.len(), 2);
+    let files: Vec<_> = artifacts.text_files().collect();
+    assert!(files.iter().any(|&(name, _)| name == "rv64_dispatch.c"));
+    for (name, contents) in files {
+        assert_eq!(fs::read_to_string(dir.path().join(name)).unwrap(), contents);
+    }
+
+    // Writing the same artifacts again leaves every part untouched
+    let stats = artifacts.write_to(dir.path()).unwrap();
+    assert_eq!(stats.unchanged_partitions, 2);
+    let commands = fs::read_to_string(dir.path().join(COMPILE_COMMANDS)).unwrap();
+    assert!(commands.contains("\"file\": \"rv64_part1.c\""));
+}
+
+#[test]
+fn test_write_all_records_block_sizes() {
+    let dir = tempfile::tempdir().unwrap();
+    let project = CProject::new(
+        dir.path(),
+        "rv64",
+        EmitConfig::default().with_target_part_cost(4),
+    );
+    let blocks = [create_dummy_block(0x1000, 4), create_dummy_block(0x2000, 2)];
+
+    let stats = project.write_all(&blocks).unwrap();
+    let pcs: Vec<_> = stats.blocks.iter().map(|b| (b.pc, b.part)).collect();
+    assert_eq!(pcs, [(0x1000, 0), (0x2000, 1)]);
+    // Each block's function text is in its part
+    for block in &stats.blocks {
+        let part = fs::read_to_string(project.partition_path(block.part)).unwrap();
+        assert!(block.bytes > 0 && block.bytes < part.len());
+        assert!(block.lines > 0);
+        assert!(part.contains(&format!("B_{:016x}(", block.pc)));
+    }
+    assert!(stats.blocks[0].bytes > stats.blocks[1].bytes);
+}
+
+#[test]
+fn test_render_block_traces_entry() {
+    let block = create_dummy_block(0x1000, 2);
+    let block_map = HashMap::new();
+
+    let config = EmitConfig::<Rv64>::default();
+    let project = CProject::new("/tmp/test", "rv64", config.clone());
+    let mut emitter = CEmitter::new(config, EmitInputs::default());
+    let code = project
+        .render_block(&mut emitter, &block, &block_map)
+        .unwrap();
+    assert!(!code.contains("trace_block"));
+
+    let mut config = EmitConfig::<Rv64>::default();
+    config.tracer_config = crate::c::TracerConfig::block_profile();
+    let project = CProject::new("/tmp/test", "rv64", config.clone());
+    let mut emitter = CEmitter::new(config, EmitInputs::default());
+    let code = project
+        .render_block(&mut emitter, &block, &block_map)
+        .unwrap();
+    assert!(code.contains("trace_block(&state->tracer, 0x0000000000001000ULL);"));
+}
+
+fn create_dummy_block(start_pc: u64, num_instrs: usize) -> BlockIR<Rv64> {
+    use rvr_ir::{InstrIR, Terminator};
+
+    let mut block = BlockIR::new(start_pc);
+    for i in 0..num_instrs {
+        let pc = start_pc + (i as u64 * 4);
+        let ir = InstrIR::new(pc, 4, 0, 0, Vec::new(), Terminator::default());
+        block.push(ir);
+    }
+    block
+}
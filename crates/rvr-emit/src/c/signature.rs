@@ -12,6 +12,7 @@ use std::fmt::Write;
 use rvr_ir::Xlen;
 use rvr_isa::reg_name;
 
+use crate::c::CDialect;
 use crate::config::EmitConfig;
 
 // Re-export for backwards compatibility
@@ -40,6 +41,20 @@ pub const STATE_FIXED_REF: &str = "((RvState*)RV_STATE_ADDR)";
 /// Fixed address constant for memory.
 pub const MEMORY_FIXED_REF: &str = "((uint8_t*)RV_MEMORY_ADDR)";
 
+/// Placeholder for the guest memory pointer in C templates that are not
+/// `format!` strings (replaced by [`memory_ref`]).
+pub const MEMORY_PLACEHOLDER: &str = "{memory}";
+
+/// Guest memory pointer in runtime functions that take `state`.
+#[must_use]
+pub const fn memory_ref(fixed_addresses: bool) -> &'static str {
+    if fixed_addresses {
+        MEMORY_FIXED_REF
+    } else {
+        "state->memory"
+    }
+}
+
 /// Get state reference expression based on fixed address mode.
 /// In fixed address mode: `((RvState*)RV_STATE_ADDR)`
 /// In normal mode: `state`
@@ -74,20 +89,44 @@ pub struct FnSignature {
     /// Used in exit paths where instret is handled explicitly with increment.
     /// Example: "state->regs[1] = ra; state->regs[2] = sp;"
     pub save_to_state_no_instret: String,
+    /// Code to reload hot registers from state after a call that may
+    /// change them (function hooks).
+    /// Example: "ra = state->regs[1]; sp = state->regs[2];"
+    pub load_from_state: String,
+    /// Hot registers in argument order.
+    pub hot_regs: Vec<u8>,
     /// Set of hot register indices for fast lookup.
     pub hot_reg_set: HashSet<u8>,
+    /// Leading arguments every block function takes before its hot
+    /// registers (state, memory, instret, tracer passed variables).
+    /// Example: "state, memory, instret"
+    pub base_args: String,
     /// Whether instret counting is enabled.
     pub counts_instret: bool,
     /// Whether tracing is enabled for reg access.
     pub trace_regs: bool,
     /// Whether fixed addresses are used for state/memory.
     pub fixed_addresses: bool,
+    /// C dialect of the generated code.
+    pub dialect: CDialect,
+    /// Locals a portable block function loads from state on entry: the
+    /// values passed as arguments in clang code (instret, tracer passed
+    /// variables, hot registers). Empty in clang code.
+    /// Example: `"uint64_t instret = state->instret; uint64_t ra = state->regs[1];"`
+    pub prologue: String,
 }
 
 impl FnSignature {
     /// Create function signature from emit config.
     #[must_use]
     pub fn new<X: Xlen>(config: &EmitConfig<X>) -> Self {
+        Self::with_hot_regs(config, &config.hot_regs)
+    }
+
+    /// Create the signature of blocks with their own `hot_regs` (see
+    /// `HotRegsMode::PerFunction`).
+    #[must_use]
+    pub fn with_hot_regs<X: Xlen>(config: &EmitConfig<X>, hot_regs: &[u8]) -> Self {
         let rtype = reg_type::<X>();
         let counts_instret = config.instret_mode.counts();
         let trace_regs = !config.tracer_config.is_none();
@@ -100,6 +139,7 @@ impl FnSignature {
         let mut args_from_state = String::new();
         let mut save_to_state = String::new();
         let mut save_to_state_no_instret = String::new();
+        let mut load_from_state = String::new();
 
         if fixed_addresses {
             // With fixed addresses: state/memory are constants, not arguments
@@ -148,8 +188,9 @@ impl FnSignature {
         save_to_state_no_instret.push_str(&tracer_save);
 
         // Add hot registers
+        let base_args = args.clone();
         let mut hot_reg_set = HashSet::new();
-        for &reg in &config.hot_regs {
+        for &reg in hot_regs {
             hot_reg_set.insert(reg);
             let name = abi_name(reg);
             if params.is_empty() {
@@ -164,18 +205,136 @@ impl FnSignature {
             let reg_save = format!(" {state}->regs[{reg}] = {name};");
             save_to_state.push_str(&reg_save);
             save_to_state_no_instret.push_str(&reg_save);
+            let _ = write!(load_from_state, " {name} = {state}->regs[{reg}];");
         }
 
-        Self {
+        let mut sig = Self {
             params,
             args,
             args_from_state,
             save_to_state,
             save_to_state_no_instret,
+            load_from_state,
+            hot_regs: hot_regs.to_vec(),
             hot_reg_set,
+            base_args,
             counts_instret,
             trace_regs,
             fixed_addresses,
+            dialect: config.c_dialect,
+            prologue: String::new(),
+        };
+        if config.c_dialect.is_portable() {
+            sig.split_portable_prologue();
+        }
+        sig
+    }
+
+    /// Portable blocks take state and memory only; the rest are locals
+    /// loaded in the prologue that live for one block.
+    fn split_portable_prologue(&mut self) {
+        let base = if self.fixed_addresses { 0 } else { 2 };
+        let split = |list: &str| -> Vec<String> {
+            list.split(", ")
+                .filter(|item| !item.is_empty())
+                .map(str::to_string)
+                .collect()
+        };
+        let (params_list, args_list, from_state_list) = (
+            split(&self.params),
+            split(&self.args),
+            split(&self.args_from_state),
+        );
+        for (param, from_state) in params_list.iter().zip(&from_state_list).skip(base) {
+            if !self.prologue.is_empty() {
+                self.prologue.push(' ');
+            }
+            let _ = write!(self.prologue, "{param} = {from_state};");
+        }
+        self.params = params_list[..base.min(params_list.len())].join(", ");
+        self.args = args_list[..base.min(args_list.len())].join(", ");
+        self.args_from_state = from_state_list[..base.min(from_state_list.len())].join(", ");
+    }
+
+    /// Declaration of a block-like function `name` with extra `attrs`,
+    /// without the trailing `;` or body.
+    ///
+    /// Clang code adds `preserve_none` and returns `void`; portable code
+    /// returns the next block to the trampoline.
+    #[must_use]
+    pub fn fn_decl(&self, name: &str, attrs: &[&str]) -> String {
+        let mut all = Vec::with_capacity(attrs.len() + 1);
+        let ret = match self.dialect {
+            CDialect::Clang => {
+                all.push("preserve_none");
+                "void"
+            }
+            CDialect::Portable => "RvNext",
+        };
+        all.extend_from_slice(attrs);
+        let params = if self.params.is_empty() && self.dialect.is_portable() {
+            "void"
+        } else {
+            &self.params
+        };
+        if all.is_empty() {
+            format!("{ret} {name}({params})")
+        } else {
+            format!("__attribute__(({})) {ret} {name}({params})", all.join(", "))
+        }
+    }
+
+    /// Continue at block function `callee`: a guaranteed tail call in clang
+    /// code; in portable code, save the locals and return `callee` to the
+    /// trampoline.
+    #[must_use]
+    pub fn tail_call(&self, callee: &str) -> String {
+        match self.dialect {
+            CDialect::Clang => format!("[[clang::musttail]] return {callee}({});", self.args),
+            CDialect::Portable if self.save_to_state.is_empty() => {
+                format!("return (RvNext){{ {callee} }};")
+            }
+            CDialect::Portable => {
+                format!("{} return (RvNext){{ {callee} }};", self.save_to_state)
+            }
+        }
+    }
+
+    /// Continue at block function `callee`, which has signature `to`.
+    ///
+    /// Registers hot here but not in `to` are spilled to state, and the
+    /// registers `to` has hot are passed from locals or loaded from state.
+    /// Portable code keeps nothing live across blocks and needs no glue.
+    #[must_use]
+    pub fn transfer(&self, to: &Self, callee: &str) -> String {
+        if self.dialect.is_portable() || self.hot_regs == to.hot_regs {
+            return self.tail_call(callee);
+        }
+        let state = state_ref(self.fixed_addresses);
+        let mut glue = String::new();
+        for &reg in self.hot_regs.iter().filter(|reg| !to.is_hot_reg(**reg)) {
+            let _ = write!(glue, "{state}->regs[{reg}] = {}; ", abi_name(reg));
+        }
+        let mut args = to.base_args.clone();
+        for &reg in &to.hot_regs {
+            if !args.is_empty() {
+                args.push_str(", ");
+            }
+            if self.is_hot_reg(reg) {
+                args.push_str(abi_name(reg));
+            } else {
+                let _ = write!(args, "{state}->regs[{reg}]");
+            }
+        }
+        format!("{glue}[[clang::musttail]] return {callee}({args});")
+    }
+
+    /// Return that stops execution (after state is saved).
+    #[must_use]
+    pub const fn stop(&self) -> &'static str {
+        match self.dialect {
+            CDialect::Clang => "return;",
+            CDialect::Portable => "return (RvNext){ 0 };",
         }
     }
 
@@ -300,6 +459,32 @@ mod tests {
         assert!(sig.reg_write(2, "42").contains("->regs[2] = 42;"));
     }
 
+    #[test]
+    fn test_transfer_between_hot_sets() {
+        let mut config = EmitConfig::<Rv64>::new(32);
+        config.instret_mode = InstretMode::Count;
+        config.hot_regs = vec![1, 2, 10];
+        let global = FnSignature::new(&config);
+        let leaf = FnSignature::with_hot_regs(&config, &[1, 5, 10]);
+
+        assert_eq!(global.transfer(&global, "B_1"), global.tail_call("B_1"));
+        assert_eq!(
+            global.transfer(&leaf, "B_1_hot"),
+            "state->regs[2] = sp; [[clang::musttail]] return \
+             B_1_hot(state, memory, instret, ra, state->regs[5], a0);"
+        );
+        assert_eq!(
+            leaf.transfer(&global, "B_2"),
+            "state->regs[5] = t0; [[clang::musttail]] return \
+             B_2(state, memory, instret, ra, state->regs[2], a0);"
+        );
+
+        config.c_dialect = CDialect::Portable;
+        let global = FnSignature::new(&config);
+        let leaf = FnSignature::with_hot_regs(&config, &[1, 5, 10]);
+        assert_eq!(leaf.transfer(&global, "B_2"), leaf.tail_call("B_2"));
+    }
+
     #[test]
     fn test_abi_names() {
         assert_eq!(abi_name(0), "zero");
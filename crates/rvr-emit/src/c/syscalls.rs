@@ -5,43 +5,429 @@
 
 use rvr_ir::Xlen;
 
+use super::namespace::namespaced;
 use super::signature::{MEMORY_FIXED_REF, reg_type};
 
 const SYSCALLS_BODY: &str = r"
-reg_t rv_sys_write(RvState* restrict state, reg_t fd, reg_t buf, reg_t count) {
-    if (fd == 1 || fd == 2) {
-        FILE* out = (fd == 1) ? stdout : stderr;
+/* Length of the guest range [addr, addr + count), clamped to the end of guest memory */
+static inline size_t guest_span(reg_t addr, reg_t count) {
+    uint64_t avail = RV_MEMORY_MASK - (uint64_t)phys_addr(addr) + 1;
+    return (uint64_t)count < avail ? (size_t)count : (size_t)avail;
+}
+
+/* Touch every page of a buffer handed to the host's I/O: the kernel does not
+ * fault in guest memory that is committed on demand */
+static inline void guest_touch(const uint8_t* ptr, size_t n) {
+    for (size_t off = 0; off < n; off += 4096) {
+        (void)((const volatile uint8_t*)ptr)[off];
+    }
+    if (n > 0) {
+        (void)((const volatile uint8_t*)ptr)[n - 1];
+    }
+}
+
+/* Guest fds above 2 are files the host opened under a preopened directory */
+static inline bool host_file(const RvIo* io, reg_t fd) {
+    return fd > 2 && io && io->open;
+}
+
+static const int64_t kEperm = 1;
+static const int64_t kEnoent = 2;
+static const int64_t kEbadf = 9;
+static const int64_t kEfault = 14;
+static const int64_t kEinval = 22;
+static const int64_t kEspipe = 29;
+static const int64_t kEnametoolong = 36;
+
+/* stdin/stdout/stderr go through state->io when the host installed hooks */
+reg_t {prefix}rv_sys_write(RvState* restrict state, reg_t fd, reg_t buf, reg_t count) {
+    const RvIo* io = state->io;
+    if (fd == 1 || fd == 2 || host_file(io, fd)) {
         uint8_t* ptr = guest_ptr(state, buf);
-        size_t n = (size_t)count;
+        size_t n = guest_span(buf, count);
+        guest_touch(ptr, n);
+        if (io) {
+            return (reg_t)io->write(io->ctx, (uint32_t)fd, ptr, n);
+        }
+        FILE* out = (fd == 1) ? stdout : stderr;
         size_t written = fwrite(ptr, 1, n, out);
         fflush(out);
         return (reg_t)written;
     }
-    return (reg_t)-1;
+    return (reg_t)-kEbadf;
 }
 
-reg_t rv_sys_read(RvState* restrict state, reg_t fd, reg_t buf, reg_t count) {
-    if (fd == 0) {
+/* Bare-metal putchar ECALL: the low byte of c to stdout */
+void {prefix}rv_putchar(RvState* restrict state, reg_t c) {
+    const uint8_t byte = (uint8_t)c;
+    const RvIo* io = state->io;
+    if (io) {
+        io->write(io->ctx, 1, &byte, 1);
+        return;
+    }
+    fputc(byte, stdout);
+    fflush(stdout);
+}
+
+reg_t {prefix}rv_sys_read(RvState* restrict state, reg_t fd, reg_t buf, reg_t count) {
+    const RvIo* io = state->io;
+    if (fd == 0 || host_file(io, fd)) {
         uint8_t* ptr = guest_ptr(state, buf);
-        size_t n = (size_t)count;
+        size_t n = guest_span(buf, count);
+        guest_touch(ptr, n);
+        if (io) {
+            return (reg_t)io->read(io->ctx, (uint32_t)fd, ptr, n);
+        }
         size_t read = fread(ptr, 1, n, stdin);
         return (reg_t)read;
     }
-    return (reg_t)-1;
+    return (reg_t)-kEbadf;
+}
+
+/*
+ * readv/writev: like the kernel, check every iovec up front and fail with
+ * EFAULT before any I/O if one leaves guest memory, then move one iovec at
+ * a time and stop at the first short transfer. An error after some bytes
+ * moved returns the byte count instead.
+ */
+static const uint64_t kIovMax = 1024;
+
+static inline bool guest_range_ok(uint64_t addr, uint64_t len) {
+    return addr <= RV_MEMORY_MASK && len <= RV_MEMORY_MASK - addr + 1;
+}
+
+/* Iovec i of the guest array at iov: {base, len} in register-sized words */
+static bool guest_iovec(RvState* restrict state, reg_t iov, reg_t i, reg_t* base, reg_t* len) {
+    uint64_t at = (uint64_t)iov + (uint64_t)i * 2 * sizeof(reg_t);
+    if (!guest_range_ok(at, 2 * sizeof(reg_t))) {
+        return false;
+    }
+    const uint8_t* ptr = guest_ptr(state, (reg_t)at);
+    memcpy(base, ptr, sizeof(reg_t));
+    memcpy(len, ptr + sizeof(reg_t), sizeof(reg_t));
+    return guest_range_ok((uint64_t)*base, (uint64_t)*len);
+}
+
+static reg_t guest_vector_io(RvState* restrict state, reg_t fd, reg_t iov, reg_t iovcnt,
+                             bool write) {
+    if ((uint64_t)iovcnt > kIovMax) {
+        return (reg_t)-kEinval;
+    }
+    uint64_t total = 0;
+    reg_t base;
+    reg_t len;
+    for (reg_t i = 0; i < iovcnt; i++) {
+        if (!guest_iovec(state, iov, i, &base, &len)) {
+            return (reg_t)-kEfault;
+        }
+        total += (uint64_t)len;
+    }
+    if (total > (uint64_t)((reg_t)-1 >> 1)) {
+        return (reg_t)-kEinval;
+    }
+    reg_t done = 0;
+    for (reg_t i = 0; i < iovcnt; i++) {
+        guest_iovec(state, iov, i, &base, &len);
+        if (len == 0) {
+            continue;
+        }
+        reg_t n = write ? {prefix}rv_sys_write(state, fd, base, len) : {prefix}rv_sys_read(state, fd, base, len);
+        /* A negative errno reads as more than was asked for */
+        if (n > len) {
+            return done ? done : n;
+        }
+        done += n;
+        if (n < len) {
+            break;
+        }
+    }
+    return done;
+}
+
+reg_t {prefix}rv_sys_readv(RvState* restrict state, reg_t fd, reg_t iov, reg_t iovcnt) {
+    return guest_vector_io(state, fd, iov, iovcnt, false);
+}
+
+reg_t {prefix}rv_sys_writev(RvState* restrict state, reg_t fd, reg_t iov, reg_t iovcnt) {
+    return guest_vector_io(state, fd, iov, iovcnt, true);
+}
+
+/* Every path goes to the host, which checks it against its preopened
+ * directories before opening anything; without preopens, nothing opens */
+static const uint64_t kPathMax = 4096;
+
+/* Guest path at addr, or NULL if it is not NUL-terminated within kPathMax */
+static const char* guest_path(RvState* restrict state, reg_t addr) {
+    const char* str = (const char*)guest_ptr(state, addr);
+    return memchr(str, 0, guest_span(addr, kPathMax)) ? str : NULL;
+}
+
+reg_t {prefix}rv_sys_openat(RvState* restrict state, reg_t dirfd, reg_t path, reg_t flags, reg_t mode) {
+    const RvIo* io = state->io;
+    if (!io || !io->open) {
+        return (reg_t)-kEperm;
+    }
+    const char* str = guest_path(state, path);
+    if (!str) {
+        return (reg_t)-kEnametoolong;
+    }
+    return (reg_t)io->open(io->ctx, (int32_t)dirfd, str, (uint64_t)flags, (uint64_t)mode);
+}
+
+/* Closing stdio is accepted and changes nothing */
+reg_t {prefix}rv_sys_close(RvState* restrict state, reg_t fd) {
+    const RvIo* io = state->io;
+    if (host_file(io, fd)) {
+        return (reg_t)io->close(io->ctx, (uint32_t)fd);
+    }
+    return fd <= 2 ? 0 : (reg_t)-kEbadf;
+}
+
+/* stdio is not seekable, whatever the host's own streams are */
+reg_t {prefix}rv_sys_lseek(RvState* restrict state, reg_t fd, reg_t offset, reg_t whence) {
+    const RvIo* io = state->io;
+    if (host_file(io, fd)) {
+        return (reg_t)io->seek(io->ctx, (uint32_t)fd, (int64_t)(sreg_t)offset, (uint32_t)whence);
+    }
+    return (reg_t)(fd <= 2 ? -kEspipe : -kEbadf);
+}
+
+/*
+ * fstat/fstatat write the generic Linux struct stat (128 bytes, 64-bit
+ * fields, as newlib's kernel_stat reads it on RV32 too) with the size,
+ * mode and block size the host reports; ids, link count and times are 0.
+ * stdio is a character device.
+ */
+static const uint32_t kStdioMode = 0020620; /* S_IFCHR | 0620 */
+static const uint32_t kStdioBlksize = 1024;
+static const uint64_t kAtSymlinkNofollow = 0x100;
+static const uint64_t kAtEmptyPath = 0x1000;
+static const uint64_t kStatSize = 128;
+
+static reg_t guest_write_stat(RvState* restrict state, reg_t statbuf, const RvStat* st) {
+    if (!guest_range_ok((uint64_t)statbuf, kStatSize)) {
+        return (reg_t)-kEfault;
+    }
+    uint8_t* out = guest_ptr(state, statbuf);
+    const uint32_t nlink = 1;
+    const int64_t size = (int64_t)st->size;
+    const int32_t blksize = (int32_t)st->blksize;
+    const int64_t blocks = (int64_t)st->blocks;
+    memset(out, 0, (size_t)kStatSize);
+    memcpy(out + 16, &st->mode, sizeof(st->mode));
+    memcpy(out + 20, &nlink, sizeof(nlink));
+    memcpy(out + 48, &size, sizeof(size));
+    memcpy(out + 56, &blksize, sizeof(blksize));
+    memcpy(out + 64, &blocks, sizeof(blocks));
+    return 0;
+}
+
+/* Metadata of dirfd (path NULL) or of path relative to it */
+static reg_t guest_stat(RvState* restrict state, reg_t dirfd, const char* path, reg_t flags,
+                        reg_t statbuf) {
+    const RvIo* io = state->io;
+    RvStat st = {0};
+    if (path || host_file(io, dirfd)) {
+        if (!io || !io->stat) {
+            return (reg_t)(path ? -kEperm : -kEbadf);
+        }
+        int64_t ret = io->stat(io->ctx, (int32_t)dirfd, path, (uint32_t)flags, &st);
+        if (ret < 0) {
+            return (reg_t)ret;
+        }
+    } else if (dirfd <= 2) {
+        st.mode = kStdioMode;
+        st.blksize = kStdioBlksize;
+    } else {
+        return (reg_t)-kEbadf;
+    }
+    return guest_write_stat(state, statbuf, &st);
+}
+
+reg_t {prefix}rv_sys_fstat(RvState* restrict state, reg_t fd, reg_t statbuf) {
+    return guest_stat(state, fd, NULL, 0, statbuf);
+}
+
+reg_t {prefix}rv_sys_fstatat(RvState* restrict state, reg_t dirfd, reg_t path, reg_t statbuf,
+                     reg_t flags) {
+    const char* str = guest_path(state, path);
+    if (!str) {
+        return (reg_t)-kEnametoolong;
+    }
+    if (str[0] == '\0') {
+        return (flags & kAtEmptyPath) ? guest_stat(state, dirfd, NULL, 0, statbuf)
+                                      : (reg_t)-kEnoent;
+    }
+    return guest_stat(state, dirfd, str, flags & kAtSymlinkNofollow, statbuf);
+}
+
+/* Host syscalls: numbers the host registered in state->io, checked before the built-in table */
+reg_t {prefix}rv_has_host_syscall(RvState* restrict state, reg_t num) {
+    const RvIo* io = state->io;
+    if (!io || io->syscall_count == 0) {
+        return 0;
+    }
+    uint64_t lo = 0;
+    uint64_t hi = io->syscall_count;
+    while (lo < hi) {
+        uint64_t mid = lo + (hi - lo) / 2;
+        if (io->syscall_nums[mid] < (uint64_t)num) {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+    return lo < io->syscall_count && io->syscall_nums[lo] == (uint64_t)num;
 }
 
-reg_t rv_sys_brk(RvState* restrict state, reg_t addr) {
+reg_t {prefix}rv_host_syscall(RvState* restrict state, reg_t num, reg_t a0, reg_t a1, reg_t a2, reg_t a3,
+                      reg_t a4, reg_t a5) {
+    const uint64_t args[6] = {a0, a1, a2, a3, a4, a5};
+    const RvIo* io = state->io;
+    return (reg_t)io->syscall(io->syscall_ctx, num, args, guest_ptr(state, 0), RV_MEMORY_MASK + 1);
+}
+
+/* Like the kernel, a failed brk returns the unchanged break (libc reports ENOMEM) */
+reg_t {prefix}rv_sys_brk(RvState* restrict state, reg_t addr) {
     if (addr == 0) {
         return state->brk;
     }
-    if (addr >= state->start_brk && (uint64_t)addr < RV_MEMORY_SIZE) {
+    if (addr >= state->start_brk && (uint64_t)addr <= RV_HEAP_END) {
         state->brk = addr;
         return addr;
     }
     return state->brk;
 }
 
-reg_t rv_sys_mmap(
+/*
+ * Anonymous mmap arena [RV_MMAP_BASE, RV_MMAP_END), empty when disabled.
+ * state->mmap holds the live mappings, sorted with adjacent ones merged;
+ * new mappings go in the first free gap. Callers check that two region
+ * slots are free before an update, enough for one split and one insert.
+ */
+static const uint64_t kPageSize = 4096;
+static const int64_t kEnomem = 12;
+static const uint64_t kMapFixed = 0x10;
+static const uint64_t kMapAnonymous = 0x20;
+static const uint64_t kMremapMaymove = 1;
+
+/* Index of the first mapping ending above addr */
+static uint32_t mmap_find(const RvMmap* m, uint64_t addr) {
+    uint32_t lo = 0;
+    uint32_t hi = m->count;
+    while (lo < hi) {
+        uint32_t mid = lo + (hi - lo) / 2;
+        if (m->regions[mid][1] <= addr) {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+    return lo;
+}
+
+static bool mmap_is_free(const RvMmap* m, uint64_t start, uint64_t end) {
+    uint32_t i = mmap_find(m, start);
+    return i == m->count || m->regions[i][0] >= end;
+}
+
+static bool mmap_is_mapped(const RvMmap* m, uint64_t start, uint64_t end) {
+    uint32_t i = mmap_find(m, start);
+    return i < m->count && m->regions[i][0] <= start && m->regions[i][1] >= end;
+}
+
+static bool mmap_has_slots(const RvMmap* m) {
+    return m->count + 2 <= RV_MMAP_MAX_REGIONS;
+}
+
+/* Add [start, end), which must be free */
+static void mmap_insert(RvMmap* m, uint64_t start, uint64_t end) {
+    uint32_t i = mmap_find(m, start);
+    bool merge_prev = i > 0 && m->regions[i - 1][1] == start;
+    bool merge_next = i < m->count && m->regions[i][0] == end;
+    if (merge_prev && merge_next) {
+        m->regions[i - 1][1] = m->regions[i][1];
+        memmove(&m->regions[i], &m->regions[i + 1], (m->count - i - 1) * sizeof(m->regions[0]));
+        m->count--;
+    } else if (merge_prev) {
+        m->regions[i - 1][1] = end;
+    } else if (merge_next) {
+        m->regions[i][0] = start;
+    } else {
+        memmove(&m->regions[i + 1], &m->regions[i], (m->count - i) * sizeof(m->regions[0]));
+        m->regions[i][0] = start;
+        m->regions[i][1] = end;
+        m->count++;
+    }
+}
+
+/* Remove [start, end), splitting a mapping that spans it */
+static void mmap_remove(RvMmap* m, uint64_t start, uint64_t end) {
+    uint32_t i = mmap_find(m, start);
+    if (i < m->count && m->regions[i][0] < start && m->regions[i][1] > end) {
+        memmove(&m->regions[i + 2], &m->regions[i + 1], (m->count - i - 1) * sizeof(m->regions[0]));
+        m->regions[i + 1][0] = end;
+        m->regions[i + 1][1] = m->regions[i][1];
+        m->regions[i][1] = start;
+        m->count++;
+        return;
+    }
+    if (i < m->count && m->regions[i][0] < start) {
+        m->regions[i][1] = start;
+        i++;
+    }
+    uint32_t j = i;
+    while (j < m->count && m->regions[j][1] <= end) {
+        j++;
+    }
+    if (j < m->count && m->regions[j][0] < end) {
+        m->regions[j][0] = end;
+    }
+    memmove(&m->regions[i], &m->regions[j], (m->count - j) * sizeof(m->regions[0]));
+    m->count -= j - i;
+}
+
+/* Start of the first free gap of len bytes, or UINT64_MAX */
+static uint64_t mmap_find_gap(const RvMmap* m, uint64_t len) {
+    uint64_t start = RV_MMAP_BASE;
+    for (uint32_t i = 0; i < m->count; i++) {
+        if (m->regions[i][0] - start >= len) {
+            return start;
+        }
+        start = m->regions[i][1];
+    }
+    return RV_MMAP_END - start >= len ? start : UINT64_MAX;
+}
+
+static reg_t mmap_arena_map(RvState* restrict state, uint64_t addr, uint64_t len, uint64_t flags) {
+    RvMmap* m = &state->mmap;
+    /* fd is ignored for anonymous mappings, as on Linux */
+    if (!(flags & kMapAnonymous) || len == 0) {
+        return (reg_t)-kEinval;
+    }
+    if (len > RV_MMAP_END - RV_MMAP_BASE || !mmap_has_slots(m)) {
+        return (reg_t)-kEnomem;
+    }
+    uint64_t size = align_up(len, kPageSize);
+    uint64_t start;
+    if (flags & kMapFixed) {
+        if (addr % kPageSize != 0 || addr < RV_MMAP_BASE || addr > RV_MMAP_END - size) {
+            return (reg_t)-kEinval;
+        }
+        start = addr;
+        mmap_remove(m, start, start + size);
+    } else {
+        start = mmap_find_gap(m, size);
+        if (start == UINT64_MAX) {
+            return (reg_t)-kEnomem;
+        }
+    }
+    mmap_insert(m, start, start + size);
+    memset(guest_ptr(state, (reg_t)start), 0, (size_t)size);
+    return (reg_t)start;
+}
+
+reg_t {prefix}rv_sys_mmap(
     RvState* restrict state,
     reg_t addr,
     reg_t len,
@@ -50,37 +436,136 @@ reg_t rv_sys_mmap(
     reg_t fd,
     reg_t off
 ) {
-    (void)addr;
     (void)prot;
-    (void)flags;
     (void)fd;
     (void)off;
 
-    const reg_t page = 4096;
-    reg_t current = state->brk;
-    reg_t aligned_brk = align_up(current, page);
-    reg_t aligned_len = align_up(len, page);
-    reg_t new_brk = aligned_brk + aligned_len;
+    if (RV_MMAP_END != 0) {
+        return mmap_arena_map(state, (uint64_t)addr, (uint64_t)len, (uint64_t)flags);
+    }
+
+    /* No arena: bump the break; 64-bit math so RV32 cannot wrap past RV_HEAP_END */
+    const uint64_t page = 4096;
+    uint64_t aligned_brk = align_up((uint64_t)state->brk, page);
+    uint64_t avail = aligned_brk <= RV_HEAP_END ? RV_HEAP_END - aligned_brk : 0;
+    uint64_t aligned_len = align_up((uint64_t)len, page);
 
-    if ((uint64_t)new_brk < RV_MEMORY_SIZE) {
-        state->brk = new_brk;
+    if ((uint64_t)len <= avail && aligned_len <= avail) {
+        state->brk = (reg_t)(aligned_brk + aligned_len);
         memset(guest_ptr(state, aligned_brk), 0, (size_t)aligned_len);
-        return aligned_brk;
+        return (reg_t)aligned_brk;
     }
 
     return (reg_t)-12; /* ENOMEM */
 }
 
-reg_t rv_sys_fstat(RvState* restrict state, reg_t fd, reg_t statbuf) {
-    (void)state;
-    (void)statbuf;
-    if (fd == 1 || fd == 2) {
+/* Without an arena, mappings come from the break and are never freed */
+reg_t {prefix}rv_sys_munmap(RvState* restrict state, reg_t addr, reg_t len) {
+    if (RV_MMAP_END == 0) {
+        return 0;
+    }
+    RvMmap* m = &state->mmap;
+    uint64_t start = (uint64_t)addr;
+    if (start % kPageSize != 0 || len == 0 || start < RV_MMAP_BASE || start > RV_MMAP_END ||
+        (uint64_t)len > RV_MMAP_END - start) {
+        return (reg_t)-kEinval;
+    }
+    if (!mmap_has_slots(m)) {
+        return (reg_t)-kEnomem;
+    }
+    mmap_remove(m, start, start + align_up((uint64_t)len, kPageSize));
+    return 0;
+}
+
+/* Shrinks or grows in place; with MREMAP_MAYMOVE, moves if it cannot grow */
+reg_t {prefix}rv_sys_mremap(
+    RvState* restrict state,
+    reg_t old_addr,
+    reg_t old_len,
+    reg_t new_len,
+    reg_t flags,
+    reg_t new_addr
+) {
+    (void)new_addr;
+    if (RV_MMAP_END == 0) {
+        return (reg_t)-kEnomem;
+    }
+    RvMmap* m = &state->mmap;
+    uint64_t start = (uint64_t)old_addr;
+    uint64_t arena = RV_MMAP_END - RV_MMAP_BASE;
+    if (start % kPageSize != 0 || old_len == 0 || new_len == 0 || (flags & ~kMremapMaymove)) {
+        return (reg_t)-kEinval;
+    }
+    if ((uint64_t)old_len > arena || (uint64_t)new_len > arena || !mmap_has_slots(m)) {
+        return (reg_t)-kEnomem;
+    }
+    uint64_t old_size = align_up((uint64_t)old_len, kPageSize);
+    uint64_t new_size = align_up((uint64_t)new_len, kPageSize);
+    if (start < RV_MMAP_BASE || start > RV_MMAP_END - old_size ||
+        !mmap_is_mapped(m, start, start + old_size)) {
+        return (reg_t)-kEfault;
+    }
+
+    if (new_size <= old_size) {
+        mmap_remove(m, start + new_size, start + old_size);
+        return (reg_t)start;
+    }
+    uint64_t old_end = start + old_size;
+    uint64_t grow = new_size - old_size;
+    if (old_end <= RV_MMAP_END - grow && mmap_is_free(m, old_end, old_end + grow)) {
+        mmap_insert(m, old_end, old_end + grow);
+        memset(guest_ptr(state, (reg_t)old_end), 0, (size_t)grow);
+        return (reg_t)start;
+    }
+    if (!(flags & kMremapMaymove)) {
+        return (reg_t)-kEnomem;
+    }
+    uint64_t dest = mmap_find_gap(m, new_size);
+    if (dest == UINT64_MAX) {
+        return (reg_t)-kEnomem;
+    }
+    mmap_insert(m, dest, dest + new_size);
+    memcpy(guest_ptr(state, (reg_t)dest), guest_ptr(state, (reg_t)start), (size_t)old_size);
+    memset(guest_ptr(state, (reg_t)(dest + old_size)), 0, (size_t)grow);
+    mmap_remove(m, start, old_end);
+    return (reg_t)dest;
+}
+
+/* The guest is one thread: no one else can change a futex word or wake a
+ * waiter, so FUTEX_WAIT never blocks. It fails with EAGAIN if the word
+ * already changed. If it still matches, a wait with a timeout times out
+ * and one without fails with EDEADLK, since it could never end. FUTEX_WAKE
+ * wakes no one */
+static const reg_t kFutexCmdMask = 0x7f;
+static const reg_t kFutexWait = 0;
+static const reg_t kFutexWake = 1;
+static const reg_t kFutexWaitBitset = 9;
+static const reg_t kFutexWakeBitset = 10;
+static const int64_t kEagain = 11;
+static const int64_t kEdeadlk = 35;
+static const int64_t kEnosys = 38;
+static const int64_t kEtimedout = 110;
+
+reg_t {prefix}rv_sys_futex(RvState* restrict state, reg_t uaddr, reg_t op, reg_t val, reg_t timeout) {
+    reg_t cmd = op & kFutexCmdMask;
+    if (cmd == kFutexWait || cmd == kFutexWaitBitset) {
+        uint32_t word;
+        if (!guest_range_ok((uint64_t)uaddr, sizeof(word))) {
+            return (reg_t)-kEfault;
+        }
+        memcpy(&word, guest_ptr(state, uaddr), sizeof(word));
+        if (word != (uint32_t)val) {
+            return (reg_t)-kEagain;
+        }
+        return (reg_t)-(timeout != 0 ? kEtimedout : kEdeadlk);
+    }
+    if (cmd == kFutexWake || cmd == kFutexWakeBitset) {
         return 0;
     }
-    return (reg_t)-1;
+    return (reg_t)-kEnosys;
 }
 
-reg_t rv_sys_getrandom(RvState* restrict state, reg_t buf, reg_t len, reg_t flags) {
+reg_t {prefix}rv_sys_getrandom(RvState* restrict state, reg_t buf, reg_t len, reg_t flags) {
     (void)flags;
     static uint64_t rng_state = 0x123456789abcdef0ULL;
     uint8_t* ptr = guest_ptr(state, buf);
@@ -95,7 +580,13 @@ reg_t rv_sys_getrandom(RvState* restrict state, reg_t buf, reg_t len, reg_t flag
 }
 ";
 
-fn push_syscalls_header(out: &mut String, base_name: &str, rtype: &str, guest_ptr_impl: &str) {
+fn push_syscalls_header(
+    out: &mut String,
+    base_name: &str,
+    rtype: &str,
+    stype: &str,
+    guest_ptr_impl: &str,
+) {
     use std::fmt::Write;
 
     out.push_str("#include \"");
@@ -104,18 +595,26 @@ fn push_syscalls_header(out: &mut String, base_name: &str, rtype: &str, guest_pt
         ".h\"\n#include <stdint.h>\n#include <stdio.h>\n#include <stdlib.h>\n#include <string.h>\n#include <time.h>\n\nint clock_gettime(int clk_id, struct timespec* tp);\nstatic const int kClockRealtime = 0;\n\n/* Minimal Linux syscall helpers for recompiled guests */\n\ntypedef ",
     );
     out.push_str(rtype);
+    out.push_str(" reg_t;\ntypedef ");
+    out.push_str(stype);
     out.push_str(
-        " reg_t;\n\nstatic inline reg_t align_up(reg_t value, reg_t alignment) {\n    return (value + alignment - 1) & ~(alignment - 1);\n}\n\nstatic inline uint8_t* guest_ptr(RvState* restrict state, reg_t addr) {\n    (void)state;\n    ",
+        " sreg_t;\n\nstatic inline uint64_t align_up(uint64_t value, uint64_t alignment) {\n    return (value + alignment - 1) & ~(alignment - 1);\n}\n\nstatic inline uint8_t* guest_ptr(RvState* restrict state, reg_t addr) {\n    (void)state;\n    ",
     );
     out.push_str(guest_ptr_impl);
     writeln!(out, "\n}}").expect("formatting guest_ptr");
 }
 
-fn push_syscalls_clock(out: &mut String, write_secs_stmt: &str, write_nsec_stmt: &str) {
+fn push_syscalls_clock(
+    out: &mut String,
+    prefix: &str,
+    write_secs_stmt: &str,
+    write_nsec_stmt: &str,
+) {
     use std::fmt::Write;
 
+    write!(out, "\nreg_t {prefix}").expect("formatting clock_gettime");
     out.push_str(
-        "\nreg_t rv_sys_clock_gettime(RvState* restrict state, reg_t clk_id, reg_t tp) {\n    (void)clk_id;\n    (void)state;\n    struct timespec ts;\n    clock_gettime(kClockRealtime, &ts);\n    uint64_t secs = (uint64_t)ts.tv_sec;\n    uint64_t nsecs = (uint64_t)ts.tv_nsec;\n    ",
+        "rv_sys_clock_gettime(RvState* restrict state, reg_t clk_id, reg_t tp) {\n    (void)clk_id;\n    (void)state;\n    struct timespec ts;\n    clock_gettime(kClockRealtime, &ts);\n    uint64_t secs = (uint64_t)ts.tv_sec;\n    uint64_t nsecs = (uint64_t)ts.tv_nsec;\n    ",
     );
     writeln!(out, "{write_secs_stmt}").expect("formatting clock_gettime secs");
     writeln!(out, "{write_nsec_stmt}").expect("formatting clock_gettime nsecs");
@@ -128,6 +627,8 @@ pub struct SyscallsConfig {
     pub base_name: String,
     /// Whether fixed addresses are used.
     pub fixed_addresses: bool,
+    /// Prefix of the runtime functions (see `EmitConfig::symbol_prefix`).
+    pub symbol_prefix: String,
 }
 
 impl SyscallsConfig {
@@ -136,14 +637,23 @@ impl SyscallsConfig {
         Self {
             base_name: base_name.into(),
             fixed_addresses,
+            symbol_prefix: String::new(),
         }
     }
+
+    /// Set the prefix of the runtime functions.
+    #[must_use]
+    pub fn with_symbol_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.symbol_prefix = prefix.into();
+        self
+    }
 }
 
 /// Generate syscalls.c source.
 #[must_use]
 pub fn gen_syscalls_source<X: Xlen>(cfg: &SyscallsConfig) -> String {
     let rtype = reg_type::<X>();
+    let stype = if X::VALUE == 32 { "int32_t" } else { "int64_t" };
 
     // Memory access depends on fixed address mode
     let (mem_ref, mem_arg) = if cfg.fixed_addresses {
@@ -156,8 +666,13 @@ pub fn gen_syscalls_source<X: Xlen>(cfg: &SyscallsConfig) -> String {
     let write_mem_nsec_stmt = format!("wr_mem_u64({mem_arg}tp, 8, nsecs);");
 
     let mut out = String::new();
-    push_syscalls_header(&mut out, &cfg.base_name, rtype, &guest_ptr_impl);
-    out.push_str(SYSCALLS_BODY);
-    push_syscalls_clock(&mut out, &write_mem_secs_stmt, &write_mem_nsec_stmt);
+    push_syscalls_header(&mut out, &cfg.base_name, rtype, stype, &guest_ptr_impl);
+    out.push_str(&namespaced(SYSCALLS_BODY, &cfg.symbol_prefix));
+    push_syscalls_clock(
+        &mut out,
+        &cfg.symbol_prefix,
+        &write_mem_secs_stmt,
+        &write_mem_nsec_stmt,
+    );
     out
 }
@@ -6,10 +6,19 @@
 
 use std::fmt::Write;
 use std::fs;
+use std::ops::Range;
 use std::path::PathBuf;
 
 use rvr_ir::Xlen;
-
+use rvr_trace_format::kind;
+pub use rvr_trace_format::{
+    BINARY_TRACE_HEADER_SIZE, BINARY_TRACE_MAGIC, BINARY_TRACE_VERSION, COVERAGE_EXECUTED,
+    COVERAGE_NOT_TAKEN, COVERAGE_TAKEN, TRACE_FLAG_CSR, TRACE_FLAG_MEM, TRACE_FLAG_RD,
+    binary_trace_record_size,
+};
+
+use super::config::CDialect;
+use super::dispatch::INSTRUCTION_SIZE;
 use super::tracers;
 
 /// Built-in tracer kind.
@@ -33,6 +42,17 @@ pub enum TracerKind {
     Diff,
     /// Buffered diff tracer - captures N instruction states for block-level comparison.
     BufferedDiff,
+    /// Page access tracer - records read/written pages in bitmaps.
+    PageAccess,
+    /// Block profile tracer - counts entries per block.
+    BlockProfile,
+    /// Binary trace tracer - fixed-size records per instruction.
+    BinaryTrace,
+    /// Coverage tracer - flags executed instructions and taken branch edges.
+    Coverage,
+    /// Golden tracer - records register checksums at sampled block entries
+    /// (see `EmitConfig::embedded_golden`).
+    Golden,
 }
 
 impl TracerKind {
@@ -55,6 +75,11 @@ impl TracerKind {
             Self::Spike => "spike",
             Self::Diff => "diff",
             Self::BufferedDiff => "buffered-diff",
+            Self::PageAccess => "page-access",
+            Self::BlockProfile => "block-profile",
+            Self::BinaryTrace => "binary-trace",
+            Self::Coverage => "coverage",
+            Self::Golden => "golden",
         }
     }
 
@@ -62,15 +87,20 @@ impl TracerKind {
     #[must_use]
     pub const fn as_c_kind(self) -> u32 {
         match self {
-            Self::None => 0,
-            Self::Preflight => 1,
-            Self::Stats => 2,
-            Self::Ffi => 3,
-            Self::Dynamic => 4,
-            Self::Debug => 5,
-            Self::Spike => 6,
-            Self::Diff => 7,
-            Self::BufferedDiff => 8,
+            Self::None => kind::NONE,
+            Self::Preflight => kind::PREFLIGHT,
+            Self::Stats => kind::STATS,
+            Self::Ffi => kind::FFI,
+            Self::Dynamic => kind::DYNAMIC,
+            Self::Debug => kind::DEBUG,
+            Self::Spike => kind::SPIKE,
+            Self::Diff => kind::DIFF,
+            Self::BufferedDiff => kind::BUFFERED_DIFF,
+            Self::PageAccess => kind::PAGE_ACCESS,
+            Self::BlockProfile => kind::BLOCK_PROFILE,
+            Self::BinaryTrace => kind::BINARY_TRACE,
+            Self::Coverage => kind::COVERAGE,
+            Self::Golden => kind::GOLDEN,
         }
     }
 }
@@ -149,6 +179,36 @@ pub enum PassedVarKind {
     Value,
 }
 
+/// Records the binary trace tracer buffers before writing them out.
+pub const BINARY_TRACE_CHUNK_RECORDS: u32 = 4096;
+
+/// Suffix of the wrappers generated code calls a sampled tracer's
+/// instruction hooks through.
+pub const SAMPLED_HOOK_SUFFIX: &str = "_sampled";
+
+/// Default page size of the page access tracer (4KiB).
+pub const DEFAULT_TRACER_PAGE_SIZE: u64 = 4096;
+
+/// Number of pages in a `memory_bits` address space.
+#[must_use]
+pub const fn page_count(page_shift: u32, memory_bits: u8) -> u64 {
+    1u64 << (memory_bits as u32).saturating_sub(page_shift)
+}
+
+/// Words per page access bitmap: one bit per page.
+#[must_use]
+pub const fn page_bitmap_words(page_shift: u32, memory_bits: u8) -> u64 {
+    page_count(page_shift, memory_bits).div_ceil(u64::BITS as u64)
+}
+
+/// Counters in the block profile: one per dispatch slot in `text`.
+#[must_use]
+pub const fn block_profile_slots(text: &Range<u64>) -> u64 {
+    text.end
+        .saturating_sub(text.start)
+        .div_ceil(INSTRUCTION_SIZE)
+}
+
 /// Tracer configuration: source + passed variables.
 #[derive(Clone, Debug)]
 pub struct TracerConfig {
@@ -156,6 +216,11 @@ pub struct TracerConfig {
     pub source: TracerSource,
     /// Variables passed directly to block functions.
     pub passed_vars: Vec<PassedVar>,
+    /// Page size in bytes for page-granular tracers (a power of two).
+    pub page_size: u64,
+    /// Hooks see every `sample_interval`-th instruction (1 traces all); the
+    /// golden tracer records every `sample_interval`-th block entry instead.
+    pub sample_interval: u32,
 }
 
 impl TracerConfig {
@@ -174,6 +239,8 @@ impl TracerConfig {
         Self {
             source: TracerSource::Builtin(kind),
             passed_vars,
+            page_size: DEFAULT_TRACER_PAGE_SIZE,
+            sample_interval: 1,
         }
     }
 
@@ -219,6 +286,36 @@ impl TracerConfig {
         Self::builtin(TracerKind::Spike)
     }
 
+    /// Page access tracer (read/written page bitmaps).
+    #[must_use]
+    pub fn page_access() -> Self {
+        Self::builtin(TracerKind::PageAccess)
+    }
+
+    /// Block profile tracer (per-block entry counts).
+    #[must_use]
+    pub fn block_profile() -> Self {
+        Self::builtin(TracerKind::BlockProfile)
+    }
+
+    /// Binary trace tracer (fixed-size per-instruction records).
+    #[must_use]
+    pub fn binary_trace() -> Self {
+        Self::builtin(TracerKind::BinaryTrace)
+    }
+
+    /// Coverage tracer (executed instructions and branch edges).
+    #[must_use]
+    pub fn coverage() -> Self {
+        Self::builtin(TracerKind::Coverage)
+    }
+
+    /// Golden tracer (register checksums at sampled block entries).
+    #[must_use]
+    pub fn golden() -> Self {
+        Self::builtin(TracerKind::Golden)
+    }
+
     /// Custom tracer with inline header content.
     pub fn custom_inline(
         name: impl Into<String>,
@@ -231,6 +328,8 @@ impl TracerConfig {
                 header: header.into(),
             },
             passed_vars,
+            page_size: DEFAULT_TRACER_PAGE_SIZE,
+            sample_interval: 1,
         }
     }
 
@@ -246,6 +345,8 @@ impl TracerConfig {
                 path: path.into(),
             },
             passed_vars,
+            page_size: DEFAULT_TRACER_PAGE_SIZE,
+            sample_interval: 1,
         }
     }
 
@@ -256,6 +357,67 @@ impl TracerConfig {
         self
     }
 
+    /// Set the page size for page-granular tracers.
+    ///
+    /// # Panics
+    /// Panics if `page_size` is not a power of two.
+    #[must_use]
+    pub fn with_page_size(mut self, page_size: u64) -> Self {
+        assert!(
+            page_size.is_power_of_two(),
+            "tracer page size must be a power of two, got {page_size}"
+        );
+        self.page_size = page_size;
+        self
+    }
+
+    /// Sample the trace: the instruction hooks only see every `interval`-th
+    /// instruction (the first, then one every `interval`), so tracing costs
+    /// a decrement and a branch on the others. `trace_block`, `trace_init`
+    /// and `trace_fini` still see everything.
+    ///
+    /// Sampled traces have gaps, so they cannot be compared in lockstep.
+    ///
+    /// # Panics
+    /// Panics if `interval` is zero.
+    #[must_use]
+    pub fn with_sample_interval(mut self, interval: u32) -> Self {
+        assert!(interval > 0, "tracer sample interval must be at least 1");
+        self.sample_interval = interval;
+        self
+    }
+
+    /// Check if the hooks only see a sample of the instructions.
+    #[must_use]
+    pub const fn is_sampled(&self) -> bool {
+        self.sample_interval > 1
+            && !self.is_none()
+            && !matches!(self.builtin_kind(), Some(TracerKind::Golden))
+    }
+
+    /// Suffix generated code adds to the instruction hooks: that of their
+    /// sampling wrappers if sampled, else none.
+    #[must_use]
+    pub const fn hook_suffix(&self) -> &'static str {
+        if self.is_sampled() {
+            SAMPLED_HOOK_SUFFIX
+        } else {
+            ""
+        }
+    }
+
+    /// Name generated code calls the instruction hook `hook` by.
+    #[must_use]
+    pub fn hook_name(&self, hook: &str) -> String {
+        format!("{hook}{}", self.hook_suffix())
+    }
+
+    /// Log2 of the page size.
+    #[must_use]
+    pub const fn page_shift(&self) -> u32 {
+        self.page_size.trailing_zeros()
+    }
+
     /// Add a passed variable.
     pub fn push_passed_var(&mut self, var: PassedVar) {
         self.passed_vars.push(var);
@@ -299,6 +461,11 @@ impl TracerConfig {
             "dynamic" => Some(Self::dynamic()),
             "debug" => Some(Self::debug()),
             "spike" => Some(Self::spike()),
+            "page-access" => Some(Self::page_access()),
+            "block-profile" => Some(Self::block_profile()),
+            "binary-trace" => Some(Self::binary_trace()),
+            "coverage" => Some(Self::coverage()),
+            "golden" => Some(Self::golden()),
             _ => None,
         }
     }
@@ -376,11 +543,31 @@ impl Default for TracerConfig {
 
 /// Generate tracer header based on config.
 ///
+/// `memory_bits` sizes the bitmaps of page-granular tracers; `text` (the
+/// dispatch range `text_start..pc_end`) sizes the block profile counters.
+/// The buffered diff tracer steps its entries' instret by the sample
+/// interval. The binary trace tracer stamps `elf_hash` into its header.
+/// Built-in headers declare their constants in `dialect`.
+///
 /// # Errors
 /// Returns any error from reading a tracer header file from disk.
-pub fn gen_tracer_header<X: Xlen>(cfg: &TracerConfig) -> std::io::Result<String> {
+pub fn gen_tracer_header<X: Xlen>(
+    cfg: &TracerConfig,
+    memory_bits: u8,
+    text: &Range<u64>,
+    elf_hash: u64,
+    dialect: CDialect,
+) -> std::io::Result<String> {
     match &cfg.source {
-        TracerSource::Builtin(kind) => Ok(tracers::gen_tracer_header::<X>(*kind)),
+        TracerSource::Builtin(kind) => Ok(tracers::gen_tracer_header::<X>(
+            *kind,
+            cfg.page_shift(),
+            cfg.sample_interval,
+            memory_bits,
+            text,
+            elf_hash,
+            dialect,
+        )),
         TracerSource::Inline { header, .. } => Ok(header.clone()),
         TracerSource::File { path, .. } => fs::read_to_string(path),
     }
@@ -430,4 +617,113 @@ mod tests {
         ));
         assert!(TracerConfig::from_string("invalid").is_none());
     }
+
+    #[test]
+    fn test_tracer_config_page_size() {
+        let config = TracerConfig::page_access();
+        assert_eq!(config.page_size, DEFAULT_TRACER_PAGE_SIZE);
+        assert_eq!(config.page_shift(), 12);
+
+        let config = config.with_page_size(1 << 16);
+        assert_eq!(config.page_shift(), 16);
+        assert_eq!(TracerKind::PageAccess.as_c_kind(), 9);
+
+        // 32-bit memory, 64KiB pages: 2^16 pages in 1024 bitmap words
+        let header =
+            gen_tracer_header::<rvr_ir::Rv64>(&config, 32, &(0..0), 0, CDialect::Clang).unwrap();
+        assert!(header.contains("PAGE_ACCESS_SHIFT = 16;"));
+        assert!(header.contains("PAGE_ACCESS_MASK = 0xffffull;"));
+        assert!(header.contains("PAGE_ACCESS_WORDS = 1024;"));
+    }
+
+    #[test]
+    fn test_tracer_block_profile_header() {
+        let config = TracerConfig::from_string("block-profile").unwrap();
+        assert_eq!(config.builtin_kind(), Some(TracerKind::BlockProfile));
+        assert_eq!(TracerKind::BlockProfile.as_c_kind(), 10);
+
+        // 0x1000..0x1011 covers 9 two-byte slots
+        let text = 0x1000..0x1011;
+        assert_eq!(block_profile_slots(&text), 9);
+        let header =
+            gen_tracer_header::<rvr_ir::Rv64>(&config, 32, &text, 0, CDialect::Clang).unwrap();
+        assert!(header.contains("BLOCK_PROFILE_BASE = 0x1000ull;"));
+        assert!(header.contains("BLOCK_PROFILE_SLOTS = 9;"));
+        assert!(header.contains("t->counts[slot]++;"));
+    }
+
+    #[test]
+    fn test_tracer_coverage_header() {
+        let config = TracerConfig::from_string("coverage").unwrap();
+        assert_eq!(config.builtin_kind(), Some(TracerKind::Coverage));
+        assert_eq!(TracerKind::Coverage.as_c_kind(), 12);
+
+        let text = 0x1000..0x1011;
+        let header =
+            gen_tracer_header::<rvr_ir::Rv64>(&config, 32, &text, 0, CDialect::Clang).unwrap();
+        assert!(header.contains("COVERAGE_BASE = 0x1000ull;"));
+        assert!(header.contains("COVERAGE_SLOTS = 9;"));
+        assert!(header.contains("t->flags[slot] |= flag;"));
+        assert!(header.contains("coverage_mark(t, pc, 0x1);"));
+        assert!(header.contains("coverage_mark(t, pc, 0x2);"));
+        assert!(header.contains("coverage_mark(t, pc, 0x4);"));
+    }
+
+    #[test]
+    fn test_tracer_binary_trace_header() {
+        let config = TracerConfig::from_string("binary-trace").unwrap();
+        assert_eq!(config.builtin_kind(), Some(TracerKind::BinaryTrace));
+        assert_eq!(TracerKind::BinaryTrace.as_c_kind(), 11);
+        assert_eq!(binary_trace_record_size(64), 48);
+        assert_eq!(binary_trace_record_size(32), 32);
+
+        let header =
+            gen_tracer_header::<rvr_ir::Rv32>(&config, 32, &(0..0), 0xabcd, CDialect::Portable)
+                .unwrap();
+        assert!(header.contains("static const uint64_t TRACE_ELF_HASH = 0xabcdull;"));
+        assert!(header.contains("sizeof(TraceRecord) == 32"));
+        assert!(header.contains("0x52, 0x56, 0x52, 0x54"));
+    }
+
+    #[test]
+    fn test_tracer_diff_layout_asserts() {
+        let cases = [
+            (
+                TracerKind::BufferedDiff,
+                32,
+                &[
+                    "sizeof(DiffEntry) == 48",
+                    "offsetof(DiffEntry, instret) == 40",
+                ][..],
+            ),
+            (
+                TracerKind::BufferedDiff,
+                64,
+                &["sizeof(DiffEntry) == 64", "offsetof(Tracer, instret) == 96"],
+            ),
+            (TracerKind::Diff, 32, &["sizeof(Tracer) == 36"]),
+            (TracerKind::Diff, 64, &["offsetof(Tracer, csr_value) == 48"]),
+        ];
+        for (kind, xlen, expected) in cases {
+            let config = TracerConfig::builtin(kind);
+            let header = if xlen == 32 {
+                gen_tracer_header::<rvr_ir::Rv32>(&config, 32, &(0..0), 0, CDialect::Clang)
+            } else {
+                gen_tracer_header::<rvr_ir::Rv64>(&config, 32, &(0..0), 0, CDialect::Clang)
+            }
+            .unwrap();
+            for assert in expected {
+                assert!(
+                    header.contains(&format!("static_assert({assert});")),
+                    "{kind:?} RV{xlen}: {assert}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "power of two")]
+    fn test_tracer_config_page_size_not_power_of_two() {
+        let _ = TracerConfig::page_access().with_page_size(3000);
+    }
 }
@@ -0,0 +1,239 @@
+//! Binary trace tracer header generation.
+
+use rvr_ir::Xlen;
+
+use super::super::config::CDialect;
+use super::super::signature::reg_type;
+use super::super::tracer::{
+    BINARY_TRACE_CHUNK_RECORDS, BINARY_TRACE_MAGIC, BINARY_TRACE_VERSION, TRACE_FLAG_CSR,
+    TRACE_FLAG_MEM, TRACE_FLAG_RD, binary_trace_record_size,
+};
+
+#[allow(clippy::too_many_lines)]
+pub fn gen_tracer_binary_trace<X: Xlen>(elf_hash: u64, dialect: CDialect) -> String {
+    let rtype = reg_type::<X>();
+    let xlen = X::VALUE;
+    let record_size = binary_trace_record_size(xlen);
+    let magic = BINARY_TRACE_MAGIC
+        .iter()
+        .map(|b| format!("{b:#04x}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let constants = [
+        (
+            "uint32_t",
+            "TRACE_VERSION",
+            BINARY_TRACE_VERSION.to_string(),
+        ),
+        ("uint32_t", "TRACE_XLEN", xlen.to_string()),
+        ("uint64_t", "TRACE_ELF_HASH", format!("{elf_hash:#x}ull")),
+        (
+            "uint32_t",
+            "TRACE_CHUNK",
+            BINARY_TRACE_CHUNK_RECORDS.to_string(),
+        ),
+        ("uint8_t", "TRACE_FLAG_RD", TRACE_FLAG_RD.to_string()),
+        ("uint8_t", "TRACE_FLAG_MEM", TRACE_FLAG_MEM.to_string()),
+        ("uint8_t", "TRACE_FLAG_CSR", TRACE_FLAG_CSR.to_string()),
+    ]
+    .map(|(ty, name, value)| dialect.constant(ty, name, &value, false))
+    .join("\n");
+
+    format!(
+        r#"/* Binary trace tracer - fixed-size records of every retired instruction.
+ *
+ * File: header (magic, version u32, xlen u32, ELF hash u64), then one
+ * TraceRecord per instruction, little-endian. Records are buffered and
+ * written TRACE_CHUNK at a time. The trace_dump binary prints a trace; the
+ * trace comparison tooling reads it like a Spike log.
+ *
+ * Set RVR_TRACE_FILE environment variable to specify output file.
+ * Default: /tmp/rvr_trace.bin
+ */
+#pragma once
+
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+{constants}
+
+typedef struct TraceRecord {{
+    uint64_t instret;
+    {rtype} pc;
+    {rtype} rd_value;
+    {rtype} mem_addr;
+    {rtype} csr_value;
+    uint32_t opcode;
+    uint16_t csr;
+    uint8_t rd;
+    uint8_t flags;
+}} TraceRecord;
+
+_Static_assert(sizeof(TraceRecord) == {record_size}, "TraceRecord layout");
+
+typedef struct Tracer {{
+    FILE* fp;
+    TraceRecord* records;
+    uint32_t len;
+    uint8_t has_pending;
+    TraceRecord pending;
+    uint64_t count;
+}} Tracer;
+
+static inline void trace_write_chunk(Tracer* t) {{
+    if (t->fp && t->len) {{
+        fwrite(t->records, sizeof(TraceRecord), t->len, t->fp);
+    }}
+    t->len = 0;
+}}
+
+static inline void trace_flush(Tracer* t) {{
+    if (!t->has_pending || !t->records) return;
+    t->records[t->len++] = t->pending;
+    if (t->len == TRACE_CHUNK) {{
+        trace_write_chunk(t);
+    }}
+    t->has_pending = 0;
+}}
+
+static inline void trace_init(Tracer* t) {{
+    if (!t) return;
+    static const uint8_t magic[] = {{ {magic} }};
+    const char* path = getenv("RVR_TRACE_FILE");
+    if (!path) path = "/tmp/rvr_trace.bin";
+    t->fp = fopen(path, "wb");
+    t->records = (TraceRecord*)malloc(sizeof(TraceRecord) * TRACE_CHUNK);
+    t->len = 0;
+    t->has_pending = 0;
+    t->count = 0;
+    if (t->fp) {{
+        uint32_t version = TRACE_VERSION;
+        uint32_t xlen = TRACE_XLEN;
+        uint64_t elf_hash = TRACE_ELF_HASH;
+        fwrite(magic, 1, sizeof(magic), t->fp);
+        fwrite(&version, sizeof(version), 1, t->fp);
+        fwrite(&xlen, sizeof(xlen), 1, t->fp);
+        fwrite(&elf_hash, sizeof(elf_hash), 1, t->fp);
+    }}
+}}
+
+static inline void trace_fini(Tracer* t) {{
+    if (!t) return;
+    trace_flush(t);
+    trace_write_chunk(t);
+    if (t->fp) {{
+        fclose(t->fp);
+        t->fp = NULL;
+    }}
+    free(t->records);
+    t->records = NULL;
+    fprintf(stderr, "binary-trace: %llu instructions traced\n", (unsigned long long)t->count);
+}}
+
+/* Block entry */
+static inline void trace_block(Tracer* t, {rtype} pc) {{
+    (void)t; (void)pc;
+}}
+
+/* Instruction dispatch - flush previous, start new */
+static inline void trace_pc(Tracer* t, {rtype} pc, uint16_t op) {{
+    (void)op;
+    trace_flush(t);
+    memset(&t->pending, 0, sizeof(t->pending));
+    t->pending.instret = ++t->count;
+    t->pending.pc = pc;
+    t->has_pending = 1;
+}}
+
+/* Opcode details */
+static inline void trace_opcode(Tracer* t, {rtype} pc, uint16_t op, uint32_t opcode) {{
+    (void)pc; (void)op;
+    t->pending.opcode = opcode;
+}}
+
+static inline void trace_mem(Tracer* t, {rtype} addr) {{
+    t->pending.mem_addr = addr;
+    t->pending.flags |= TRACE_FLAG_MEM;
+}}
+
+/* Register access */
+static inline void trace_reg_read(Tracer* t, {rtype} pc, uint16_t op, uint8_t reg, {rtype} value) {{
+    (void)t; (void)pc; (void)op; (void)reg; (void)value;
+}}
+
+static inline void trace_reg_write(Tracer* t, {rtype} pc, uint16_t op, uint8_t reg, {rtype} value) {{
+    (void)pc; (void)op;
+    if (reg != 0) {{
+        t->pending.rd = reg;
+        t->pending.rd_value = value;
+        t->pending.flags |= TRACE_FLAG_RD;
+    }}
+}}
+
+/* Memory reads */
+static inline void trace_mem_read_byte(Tracer* t, {rtype} pc, uint16_t op, {rtype} addr, uint8_t value) {{
+    (void)pc; (void)op; (void)value;
+    trace_mem(t, addr);
+}}
+
+static inline void trace_mem_read_halfword(Tracer* t, {rtype} pc, uint16_t op, {rtype} addr, uint16_t value) {{
+    (void)pc; (void)op; (void)value;
+    trace_mem(t, addr);
+}}
+
+static inline void trace_mem_read_word(Tracer* t, {rtype} pc, uint16_t op, {rtype} addr, uint32_t value) {{
+    (void)pc; (void)op; (void)value;
+    trace_mem(t, addr);
+}}
+
+static inline void trace_mem_read_dword(Tracer* t, {rtype} pc, uint16_t op, {rtype} addr, uint64_t value) {{
+    (void)pc; (void)op; (void)value;
+    trace_mem(t, addr);
+}}
+
+/* Memory writes */
+static inline void trace_mem_write_byte(Tracer* t, {rtype} pc, uint16_t op, {rtype} addr, uint8_t value) {{
+    (void)pc; (void)op; (void)value;
+    trace_mem(t, addr);
+}}
+
+static inline void trace_mem_write_halfword(Tracer* t, {rtype} pc, uint16_t op, {rtype} addr, uint16_t value) {{
+    (void)pc; (void)op; (void)value;
+    trace_mem(t, addr);
+}}
+
+static inline void trace_mem_write_word(Tracer* t, {rtype} pc, uint16_t op, {rtype} addr, uint32_t value) {{
+    (void)pc; (void)op; (void)value;
+    trace_mem(t, addr);
+}}
+
+static inline void trace_mem_write_dword(Tracer* t, {rtype} pc, uint16_t op, {rtype} addr, uint64_t value) {{
+    (void)pc; (void)op; (void)value;
+    trace_mem(t, addr);
+}}
+
+/* Control flow */
+static inline void trace_branch_taken(Tracer* t, {rtype} pc, uint16_t op, {rtype} target) {{
+    (void)t; (void)pc; (void)op; (void)target;
+}}
+
+static inline void trace_branch_not_taken(Tracer* t, {rtype} pc, uint16_t op, {rtype} target) {{
+    (void)t; (void)pc; (void)op; (void)target;
+}}
+
+/* CSR access */
+static inline void trace_csr_read(Tracer* t, {rtype} pc, uint16_t op, uint16_t csr, {rtype} value) {{
+    (void)t; (void)pc; (void)op; (void)csr; (void)value;
+}}
+
+static inline void trace_csr_write(Tracer* t, {rtype} pc, uint16_t op, uint16_t csr, {rtype} value) {{
+    (void)pc; (void)op;
+    t->pending.csr = csr;
+    t->pending.csr_value = value;
+    t->pending.flags |= TRACE_FLAG_CSR;
+}}
+"#,
+    )
+}
@@ -0,0 +1,76 @@
+//! Block profile tracer header generation.
+
+use rvr_ir::Xlen;
+
+use super::super::config::CDialect;
+use super::super::signature::reg_type;
+
+pub fn gen_tracer_block_profile<X: Xlen>(text_start: u64, slots: u64, dialect: CDialect) -> String {
+    let rtype = reg_type::<X>();
+    let base = dialect.constant(
+        "uint64_t",
+        "BLOCK_PROFILE_BASE",
+        &format!("{text_start:#x}ull"),
+        false,
+    );
+    let slots = dialect.constant("uint64_t", "BLOCK_PROFILE_SLOTS", &slots.to_string(), false);
+    format!(
+        r"/* Block profile tracer - counts block entries.
+ *
+ * One host-allocated counter per 2-byte text slot, indexed like the flat
+ * dispatch table. trace_block is called with a constant PC, so each block
+ * increments a fixed counter. Code merged into a block (merges, superblocks,
+ * tail duplicates) is counted at the block's entry PC.
+ */
+#pragma once
+
+#include <stdint.h>
+
+{base}
+{slots}
+
+typedef struct Tracer {{
+    uint64_t* counts;
+}} Tracer;
+
+static inline void trace_init(Tracer* t) {{}}
+static inline void trace_fini(Tracer* t) {{}}
+
+/* Block entry; helper blocks at synthetic PCs fall outside the text range */
+static inline void trace_block(Tracer* t, {rtype} pc) {{
+    uint64_t slot = ((uint64_t)pc - BLOCK_PROFILE_BASE) >> 1;
+    if (slot < BLOCK_PROFILE_SLOTS) {{
+        t->counts[slot]++;
+    }}
+}}
+
+/* Instruction dispatch */
+static inline void trace_pc(Tracer* t, {rtype} pc, uint16_t op) {{}}
+static inline void trace_opcode(Tracer* t, {rtype} pc, uint16_t op, uint32_t opcode) {{}}
+
+/* Register access */
+static inline void trace_reg_read(Tracer* t, {rtype} pc, uint16_t op, uint8_t reg, {rtype} value) {{}}
+static inline void trace_reg_write(Tracer* t, {rtype} pc, uint16_t op, uint8_t reg, {rtype} value) {{}}
+
+/* Memory reads */
+static inline void trace_mem_read_byte(Tracer* t, {rtype} pc, uint16_t op, {rtype} addr, uint8_t value) {{}}
+static inline void trace_mem_read_halfword(Tracer* t, {rtype} pc, uint16_t op, {rtype} addr, uint16_t value) {{}}
+static inline void trace_mem_read_word(Tracer* t, {rtype} pc, uint16_t op, {rtype} addr, uint32_t value) {{}}
+static inline void trace_mem_read_dword(Tracer* t, {rtype} pc, uint16_t op, {rtype} addr, uint64_t value) {{}}
+
+/* Memory writes */
+static inline void trace_mem_write_byte(Tracer* t, {rtype} pc, uint16_t op, {rtype} addr, uint8_t value) {{}}
+static inline void trace_mem_write_halfword(Tracer* t, {rtype} pc, uint16_t op, {rtype} addr, uint16_t value) {{}}
+static inline void trace_mem_write_word(Tracer* t, {rtype} pc, uint16_t op, {rtype} addr, uint32_t value) {{}}
+static inline void trace_mem_write_dword(Tracer* t, {rtype} pc, uint16_t op, {rtype} addr, uint64_t value) {{}}
+
+/* Control flow */
+static inline void trace_branch_taken(Tracer* t, {rtype} pc, uint16_t op, {rtype} target) {{}}
+static inline void trace_branch_not_taken(Tracer* t, {rtype} pc, uint16_t op, {rtype} target) {{}}
+
+/* CSR access */
+static inline void trace_csr_read(Tracer* t, {rtype} pc, uint16_t op, uint16_t csr, {rtype} value) {{}}
+static inline void trace_csr_write(Tracer* t, {rtype} pc, uint16_t op, uint16_t csr, {rtype} value) {{}}
+"
+    )
+}
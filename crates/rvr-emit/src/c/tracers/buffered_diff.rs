@@ -7,14 +7,39 @@
 //! - PC and opcode
 //! - Register write (rd, value)
 //! - Memory access (addr, value, width, `is_write`)
+//! - CSR write (csr, value)
+//! - Instret: instructions before this one, counted by the tracer; entries of
+//!   a sampled tracer are `sample_interval` apart
+
+use std::mem::{offset_of, size_of};
 
 use rvr_ir::Xlen;
+use rvr_trace_format::{BufferedDiffTracer, DiffEntry};
 
+use super::super::config::CDialect;
 use super::super::signature::reg_type;
 
 #[allow(clippy::too_many_lines)]
-pub fn gen_tracer_buffered_diff<X: Xlen>() -> String {
+pub fn gen_tracer_buffered_diff<X: Xlen>(sample_interval: u32, dialect: CDialect) -> String {
     let rtype = reg_type::<X>();
+    let entry_size = size_of::<DiffEntry<X::Reg>>();
+    let entry_rd_value = offset_of!(DiffEntry<X::Reg>, rd_value);
+    let entry_csr_value = offset_of!(DiffEntry<X::Reg>, csr_value);
+    let entry_instret = offset_of!(DiffEntry<X::Reg>, instret);
+    let tracer_size = size_of::<BufferedDiffTracer<X::Reg>>();
+    let tracer_current = offset_of!(BufferedDiffTracer<X::Reg>, current);
+    let tracer_instret = offset_of!(BufferedDiffTracer<X::Reg>, instret);
+    let layout_asserts = [
+        format!("sizeof(DiffEntry) == {entry_size}"),
+        format!("offsetof(DiffEntry, rd_value) == {entry_rd_value}"),
+        format!("offsetof(DiffEntry, csr_value) == {entry_csr_value}"),
+        format!("offsetof(DiffEntry, instret) == {entry_instret}"),
+        format!("sizeof(Tracer) == {tracer_size}"),
+        format!("offsetof(Tracer, current) == {tracer_current}"),
+        format!("offsetof(Tracer, instret) == {tracer_instret}"),
+    ]
+    .map(|cond| dialect.static_assert(&cond))
+    .join("\n        ");
 
     format!(
         r"
@@ -27,6 +52,7 @@ pub fn gen_tracer_buffered_diff<X: Xlen>() -> String {
          */
         #pragma once
         
+        #include <stddef.h>
         #include <stdint.h>
         
         /* Single instruction's captured state - must match Rust DiffEntry layout */
@@ -41,7 +67,11 @@ pub fn gen_tracer_buffered_diff<X: Xlen>() -> String {
             {rtype} mem_addr;        // Address accessed
             {rtype} mem_value;       // Value read or written
             uint8_t mem_width;       // Access width: 1/2/4/8 bytes
-            uint8_t _pad[7];         // Padding to align struct
+            uint8_t has_csr;         // Non-zero if a CSR was written
+            uint16_t csr;            // CSR written
+            uint8_t _pad[4];         // Padding to align csr_value
+            {rtype} csr_value;       // Value written to the CSR
+            uint64_t instret;        // Instructions before this one
         }} DiffEntry;
         
         /* Tracer state - ring buffer of entries */
@@ -55,8 +85,12 @@ pub fn gen_tracer_buffered_diff<X: Xlen>() -> String {
             DiffEntry current;
             uint8_t current_valid;   // Non-zero if current has data
             uint8_t _pad[7];
+            uint64_t instret;        // Instructions seen (sampled ones count the interval)
         }} Tracer;
         
+        /* Layout verification against rvr_trace_format */
+        {layout_asserts}
+        
         /* Initialize tracer - called before execution */
         static inline void trace_init(Tracer* t) {{
             if (!t) return;
@@ -104,6 +138,8 @@ pub fn gen_tracer_buffered_diff<X: Xlen>() -> String {
             }}
         
             // Start new entry
+            t->current.instret = t->instret;
+            t->instret += {sample_interval};
             t->current.pc = pc;
             t->current.opcode = 0;
             t->current.rd = 0;
@@ -114,6 +150,9 @@ pub fn gen_tracer_buffered_diff<X: Xlen>() -> String {
             t->current.mem_addr = 0;
             t->current.mem_value = 0;
             t->current.mem_width = 0;
+            t->current.has_csr = 0;
+            t->current.csr = 0;
+            t->current.csr_value = 0;
             t->current_valid = 1;
         }}
         
@@ -231,13 +270,18 @@ pub fn gen_tracer_buffered_diff<X: Xlen>() -> String {
             (void)t; (void)pc; (void)op; (void)target;
         }}
         
-        /* CSR access (not tracked for diff) */
+        /* CSR reads (not tracked; the value reaches rd) */
         static inline void trace_csr_read(Tracer* t, {rtype} pc, uint16_t op, uint16_t csr, {rtype} value) {{
             (void)t; (void)pc; (void)op; (void)csr; (void)value;
         }}
         
+        /* CSR write - record CSR and new value */
         static inline void trace_csr_write(Tracer* t, {rtype} pc, uint16_t op, uint16_t csr, {rtype} value) {{
-            (void)t; (void)pc; (void)op; (void)csr; (void)value;
+            (void)pc; (void)op;
+            if (!t) return;
+            t->current.csr = csr;
+            t->current.csr_value = value;
+            t->current.has_csr = 1;
         }}
         ",
     )
@@ -0,0 +1,85 @@
+//! Coverage tracer header generation.
+
+use rvr_ir::Xlen;
+
+use super::super::config::CDialect;
+use super::super::signature::reg_type;
+use super::super::tracer::{COVERAGE_EXECUTED, COVERAGE_NOT_TAKEN, COVERAGE_TAKEN};
+
+pub fn gen_tracer_coverage<X: Xlen>(text_start: u64, slots: u64, dialect: CDialect) -> String {
+    let rtype = reg_type::<X>();
+    let base = dialect.constant(
+        "uint64_t",
+        "COVERAGE_BASE",
+        &format!("{text_start:#x}ull"),
+        false,
+    );
+    let slots = dialect.constant("uint64_t", "COVERAGE_SLOTS", &slots.to_string(), false);
+    format!(
+        r"/* Coverage tracer - flags executed instructions and branch edges.
+ *
+ * One host-allocated flag byte per 2-byte text slot, indexed like the flat
+ * dispatch table. Hooks get constant PCs, so each sets a fixed byte. Flags
+ * are per instruction rather than per block entry, so code merged into a
+ * block (superblocks, tail duplicates) is covered line by line.
+ */
+#pragma once
+
+#include <stdint.h>
+
+{base}
+{slots}
+
+typedef struct Tracer {{
+    uint8_t* flags;
+}} Tracer;
+
+static inline void trace_init(Tracer* t) {{}}
+static inline void trace_fini(Tracer* t) {{}}
+
+/* Set `flag` in the slot of `pc`; helper code at synthetic PCs falls outside */
+static inline void coverage_mark(Tracer* t, {rtype} pc, uint8_t flag) {{
+    uint64_t slot = ((uint64_t)pc - COVERAGE_BASE) >> 1;
+    if (slot < COVERAGE_SLOTS) {{
+        t->flags[slot] |= flag;
+    }}
+}}
+
+static inline void trace_block(Tracer* t, {rtype} pc) {{}}
+
+/* Instruction dispatch */
+static inline void trace_pc(Tracer* t, {rtype} pc, uint16_t op) {{
+    coverage_mark(t, pc, {COVERAGE_EXECUTED:#x});
+}}
+static inline void trace_opcode(Tracer* t, {rtype} pc, uint16_t op, uint32_t opcode) {{}}
+
+/* Register access */
+static inline void trace_reg_read(Tracer* t, {rtype} pc, uint16_t op, uint8_t reg, {rtype} value) {{}}
+static inline void trace_reg_write(Tracer* t, {rtype} pc, uint16_t op, uint8_t reg, {rtype} value) {{}}
+
+/* Memory reads */
+static inline void trace_mem_read_byte(Tracer* t, {rtype} pc, uint16_t op, {rtype} addr, uint8_t value) {{}}
+static inline void trace_mem_read_halfword(Tracer* t, {rtype} pc, uint16_t op, {rtype} addr, uint16_t value) {{}}
+static inline void trace_mem_read_word(Tracer* t, {rtype} pc, uint16_t op, {rtype} addr, uint32_t value) {{}}
+static inline void trace_mem_read_dword(Tracer* t, {rtype} pc, uint16_t op, {rtype} addr, uint64_t value) {{}}
+
+/* Memory writes */
+static inline void trace_mem_write_byte(Tracer* t, {rtype} pc, uint16_t op, {rtype} addr, uint8_t value) {{}}
+static inline void trace_mem_write_halfword(Tracer* t, {rtype} pc, uint16_t op, {rtype} addr, uint16_t value) {{}}
+static inline void trace_mem_write_word(Tracer* t, {rtype} pc, uint16_t op, {rtype} addr, uint32_t value) {{}}
+static inline void trace_mem_write_dword(Tracer* t, {rtype} pc, uint16_t op, {rtype} addr, uint64_t value) {{}}
+
+/* Control flow: edges of conditional branches */
+static inline void trace_branch_taken(Tracer* t, {rtype} pc, uint16_t op, {rtype} target) {{
+    coverage_mark(t, pc, {COVERAGE_TAKEN:#x});
+}}
+static inline void trace_branch_not_taken(Tracer* t, {rtype} pc, uint16_t op, {rtype} target) {{
+    coverage_mark(t, pc, {COVERAGE_NOT_TAKEN:#x});
+}}
+
+/* CSR access */
+static inline void trace_csr_read(Tracer* t, {rtype} pc, uint16_t op, uint16_t csr, {rtype} value) {{}}
+static inline void trace_csr_write(Tracer* t, {rtype} pc, uint16_t op, uint16_t csr, {rtype} value) {{}}
+"
+    )
+}
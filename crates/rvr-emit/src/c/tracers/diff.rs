@@ -7,14 +7,29 @@
 //! - PC and opcode
 //! - Register write (rd, value)
 //! - Memory access (addr, value, width, `is_write`)
+//! - CSR write (csr, value)
+
+use std::mem::{offset_of, size_of};
 
 use rvr_ir::Xlen;
+use rvr_trace_format::DiffTracer;
 
+use super::super::config::CDialect;
 use super::super::signature::reg_type;
 
 #[allow(clippy::too_many_lines)]
-pub fn gen_tracer_diff<X: Xlen>() -> String {
+pub fn gen_tracer_diff<X: Xlen>(dialect: CDialect) -> String {
     let rtype = reg_type::<X>();
+    let tracer_size = size_of::<DiffTracer<X::Reg>>();
+    let tracer_rd_value = offset_of!(DiffTracer<X::Reg>, rd_value);
+    let tracer_csr_value = offset_of!(DiffTracer<X::Reg>, csr_value);
+    let layout_asserts = [
+        format!("sizeof(Tracer) == {tracer_size}"),
+        format!("offsetof(Tracer, rd_value) == {tracer_rd_value}"),
+        format!("offsetof(Tracer, csr_value) == {tracer_csr_value}"),
+    ]
+    .map(|cond| dialect.static_assert(&cond))
+    .join("\n        ");
 
     format!(
         r"
@@ -25,6 +40,7 @@ pub fn gen_tracer_diff<X: Xlen>() -> String {
          */
         #pragma once
         
+        #include <stddef.h>
         #include <stdint.h>
         
         typedef struct Tracer {{
@@ -46,14 +62,23 @@ pub fn gen_tracer_diff<X: Xlen>() -> String {
             uint8_t has_rd;          // Non-zero if register was written
             uint8_t has_mem;         // Non-zero if memory was accessed
             uint8_t valid;           // Non-zero if instruction was traced
+        
+            // CSR write (at most one per Zicsr instruction)
+            uint8_t has_csr;         // Non-zero if a CSR was written
+            uint16_t csr;            // CSR written
+            {rtype} csr_value;       // Value written
         }} Tracer;
         
+        /* Layout verification against rvr_trace_format */
+        {layout_asserts}
+        
         /* Initialize tracer (no-op for diff tracer) */
         static inline void trace_init(Tracer* t) {{
             if (!t) return;
             t->valid = 0;
             t->has_rd = 0;
             t->has_mem = 0;
+            t->has_csr = 0;
         }}
         
         /* Finalize tracer (no-op for diff tracer) */
@@ -79,6 +104,7 @@ pub fn gen_tracer_diff<X: Xlen>() -> String {
             t->is_write = 0;
             t->has_rd = 0;
             t->has_mem = 0;
+            t->has_csr = 0;
             t->valid = 1;
         }}
         
@@ -186,13 +212,17 @@ pub fn gen_tracer_diff<X: Xlen>() -> String {
             (void)t; (void)pc; (void)op; (void)target;
         }}
         
-        /* CSR access (not tracked for diff) */
+        /* CSR reads (not tracked; the value reaches rd) */
         static inline void trace_csr_read(Tracer* t, {rtype} pc, uint16_t op, uint16_t csr, {rtype} value) {{
             (void)t; (void)pc; (void)op; (void)csr; (void)value;
         }}
         
+        /* CSR write - record CSR and new value */
         static inline void trace_csr_write(Tracer* t, {rtype} pc, uint16_t op, uint16_t csr, {rtype} value) {{
-            (void)t; (void)pc; (void)op; (void)csr; (void)value;
+            (void)pc; (void)op;
+            t->csr = csr;
+            t->csr_value = value;
+            t->has_csr = 1;
         }}
         ",
     )
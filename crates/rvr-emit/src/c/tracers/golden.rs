@@ -0,0 +1,120 @@
+//! Golden tracer header generation.
+
+use rvr_ir::Xlen;
+use rvr_trace_format::{GOLDEN_TRACE_MAGIC, GOLDEN_TRACE_VERSION};
+
+use super::super::config::CDialect;
+use super::super::signature::reg_type;
+
+pub fn gen_tracer_golden<X: Xlen>(interval: u32, elf_hash: u64, dialect: CDialect) -> String {
+    let rtype = reg_type::<X>();
+    let magic = GOLDEN_TRACE_MAGIC
+        .iter()
+        .map(|b| format!("{b:#04x}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let constants = [
+        (
+            "uint32_t",
+            "GOLDEN_VERSION",
+            GOLDEN_TRACE_VERSION.to_string(),
+        ),
+        ("uint32_t", "GOLDEN_INTERVAL", interval.to_string()),
+        ("uint64_t", "GOLDEN_ELF_HASH", format!("{elf_hash:#x}ull")),
+    ]
+    .map(|(ty, name, value)| dialect.constant(ty, name, &value, false))
+    .join("\n");
+
+    format!(
+        r#"/* Golden tracer - register checksums at every GOLDEN_INTERVAL-th block entry.
+ *
+ * File: header (magic, version u32, interval u32, ELF hash u64), then one
+ * (pc u64, checksum u64) record per golden trace point, little-endian.
+ * Blocks compute the checksums and call trace_golden (see rv_golden_point);
+ * a build with EmitConfig::embedded_golden checks against the file.
+ *
+ * Set RVR_TRACE_FILE environment variable to specify output file.
+ * Default: /tmp/rvr_golden.bin
+ */
+#pragma once
+
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+
+{constants}
+
+typedef struct Tracer {{
+    FILE* fp;
+    uint64_t points;
+}} Tracer;
+
+static inline void trace_init(Tracer* t) {{
+    if (!t) return;
+    static const uint8_t magic[] = {{ {magic} }};
+    const char* path = getenv("RVR_TRACE_FILE");
+    if (!path) path = "/tmp/rvr_golden.bin";
+    t->fp = fopen(path, "wb");
+    t->points = 0;
+    if (t->fp) {{
+        uint32_t version = GOLDEN_VERSION;
+        uint32_t interval = GOLDEN_INTERVAL;
+        uint64_t elf_hash = GOLDEN_ELF_HASH;
+        fwrite(magic, 1, sizeof(magic), t->fp);
+        fwrite(&version, sizeof(version), 1, t->fp);
+        fwrite(&interval, sizeof(interval), 1, t->fp);
+        fwrite(&elf_hash, sizeof(elf_hash), 1, t->fp);
+    }}
+}}
+
+static inline void trace_fini(Tracer* t) {{
+    if (!t) return;
+    if (t->fp) {{
+        fclose(t->fp);
+        t->fp = NULL;
+    }}
+    fprintf(stderr, "golden: %llu trace points recorded\n", (unsigned long long)t->points);
+}}
+
+/* Golden trace point: block entry PC and register checksum */
+static inline void trace_golden(Tracer* t, uint64_t pc, uint64_t checksum) {{
+    t->points++;
+    if (t->fp) {{
+        uint64_t record[2] = {{ pc, checksum }};
+        fwrite(record, sizeof(record), 1, t->fp);
+    }}
+}}
+
+/* Block entry */
+static inline void trace_block(Tracer* t, {rtype} pc) {{}}
+
+/* Instruction dispatch */
+static inline void trace_pc(Tracer* t, {rtype} pc, uint16_t op) {{}}
+static inline void trace_opcode(Tracer* t, {rtype} pc, uint16_t op, uint32_t opcode) {{}}
+
+/* Register access */
+static inline void trace_reg_read(Tracer* t, {rtype} pc, uint16_t op, uint8_t reg, {rtype} value) {{}}
+static inline void trace_reg_write(Tracer* t, {rtype} pc, uint16_t op, uint8_t reg, {rtype} value) {{}}
+
+/* Memory reads */
+static inline void trace_mem_read_byte(Tracer* t, {rtype} pc, uint16_t op, {rtype} addr, uint8_t value) {{}}
+static inline void trace_mem_read_halfword(Tracer* t, {rtype} pc, uint16_t op, {rtype} addr, uint16_t value) {{}}
+static inline void trace_mem_read_word(Tracer* t, {rtype} pc, uint16_t op, {rtype} addr, uint32_t value) {{}}
+static inline void trace_mem_read_dword(Tracer* t, {rtype} pc, uint16_t op, {rtype} addr, uint64_t value) {{}}
+
+/* Memory writes */
+static inline void trace_mem_write_byte(Tracer* t, {rtype} pc, uint16_t op, {rtype} addr, uint8_t value) {{}}
+static inline void trace_mem_write_halfword(Tracer* t, {rtype} pc, uint16_t op, {rtype} addr, uint16_t value) {{}}
+static inline void trace_mem_write_word(Tracer* t, {rtype} pc, uint16_t op, {rtype} addr, uint32_t value) {{}}
+static inline void trace_mem_write_dword(Tracer* t, {rtype} pc, uint16_t op, {rtype} addr, uint64_t value) {{}}
+
+/* Control flow */
+static inline void trace_branch_taken(Tracer* t, {rtype} pc, uint16_t op, {rtype} target) {{}}
+static inline void trace_branch_not_taken(Tracer* t, {rtype} pc, uint16_t op, {rtype} target) {{}}
+
+/* CSR access */
+static inline void trace_csr_read(Tracer* t, {rtype} pc, uint16_t op, uint16_t csr, {rtype} value) {{}}
+static inline void trace_csr_write(Tracer* t, {rtype} pc, uint16_t op, uint16_t csr, {rtype} value) {{}}
+"#
+    )
+}
@@ -1,29 +1,60 @@
 //! Built-in tracer header generators.
 
+use std::ops::Range;
+
 use rvr_ir::Xlen;
 
-use super::tracer::TracerKind;
+use super::config::CDialect;
+use super::tracer::{TracerKind, block_profile_slots};
 
+mod binary_trace;
+mod block_profile;
 mod buffered_diff;
+mod coverage;
 mod debug;
 mod diff;
 mod dynamic;
 mod ffi;
+mod golden;
 mod none;
+mod page_access;
 mod preflight;
 mod spike;
 mod stats;
 
-pub fn gen_tracer_header<X: Xlen>(kind: TracerKind) -> String {
+pub fn gen_tracer_header<X: Xlen>(
+    kind: TracerKind,
+    page_shift: u32,
+    sample_interval: u32,
+    memory_bits: u8,
+    text: &Range<u64>,
+    elf_hash: u64,
+    dialect: CDialect,
+) -> String {
     match kind {
         TracerKind::None => none::gen_tracer_none::<X>(),
         TracerKind::Preflight => preflight::gen_tracer_preflight::<X>(),
-        TracerKind::Stats => stats::gen_tracer_stats::<X>(),
+        TracerKind::Stats => stats::gen_tracer_stats::<X>(dialect),
         TracerKind::Ffi => ffi::gen_tracer_ffi::<X>(),
         TracerKind::Dynamic => dynamic::gen_tracer_dynamic::<X>(),
         TracerKind::Debug => debug::gen_tracer_debug::<X>(),
         TracerKind::Spike => spike::gen_tracer_spike::<X>(),
-        TracerKind::Diff => diff::gen_tracer_diff::<X>(),
-        TracerKind::BufferedDiff => buffered_diff::gen_tracer_buffered_diff::<X>(),
+        TracerKind::Diff => diff::gen_tracer_diff::<X>(dialect),
+        TracerKind::BufferedDiff => {
+            buffered_diff::gen_tracer_buffered_diff::<X>(sample_interval, dialect)
+        }
+        TracerKind::PageAccess => {
+            page_access::gen_tracer_page_access::<X>(page_shift, memory_bits, dialect)
+        }
+        TracerKind::BlockProfile => block_profile::gen_tracer_block_profile::<X>(
+            text.start,
+            block_profile_slots(text),
+            dialect,
+        ),
+        TracerKind::BinaryTrace => binary_trace::gen_tracer_binary_trace::<X>(elf_hash, dialect),
+        TracerKind::Coverage => {
+            coverage::gen_tracer_coverage::<X>(text.start, block_profile_slots(text), dialect)
+        }
+        TracerKind::Golden => golden::gen_tracer_golden::<X>(sample_interval, elf_hash, dialect),
     }
 }
@@ -0,0 +1,185 @@
+//! Page access tracer header generation.
+
+use rvr_ir::Xlen;
+
+use super::super::config::CDialect;
+use super::super::signature::reg_type;
+use super::super::tracer::{page_bitmap_words, page_count};
+
+#[allow(clippy::too_many_lines)]
+pub fn gen_tracer_page_access<X: Xlen>(
+    page_shift: u32,
+    memory_bits: u8,
+    dialect: CDialect,
+) -> String {
+    let rtype = reg_type::<X>();
+    let page_mask = page_count(page_shift, memory_bits) - 1;
+    let words = page_bitmap_words(page_shift, memory_bits);
+    let constants = [
+        dialect.constant("int", "PAGE_ACCESS_SHIFT", &page_shift.to_string(), false),
+        dialect.constant(
+            "uint64_t",
+            "PAGE_ACCESS_MASK",
+            &format!("{page_mask:#x}ull"),
+            false,
+        ),
+        dialect.constant("uint64_t", "PAGE_ACCESS_WORDS", &words.to_string(), false),
+    ]
+    .join("\n");
+
+    format!(
+        r"/* Page access tracer - records which guest pages are read and written.
+ *
+ * Three host-allocated bitmaps of PAGE_ACCESS_WORDS words, one bit per page:
+ * - read: pages read at least once
+ * - written: pages written at least once
+ * - read_before_write: pages whose first read came before any write
+ *
+ * Writes set their bit unconditionally; reads only do work the first time a
+ * page is read, so the steady-state cost is one load and test per access.
+ */
+#pragma once
+
+#include <stdint.h>
+
+{constants}
+
+typedef struct Tracer {{
+    uint64_t* read;
+    uint64_t* written;
+    uint64_t* read_before_write;
+}} Tracer;
+
+static inline void trace_init(Tracer* t) {{
+    (void)t;
+}}
+
+static inline void trace_fini(Tracer* t) {{
+    (void)t;
+}}
+
+__attribute__((always_inline))
+static inline void page_access_read_page(Tracer* t, uint64_t page) {{
+    page &= PAGE_ACCESS_MASK;
+    uint64_t word = page >> 6;
+    uint64_t bit = 1ull << (page & 63);
+    if (__builtin_expect((t->read[word] & bit) == 0, 0)) {{
+        t->read[word] |= bit;
+        if ((t->written[word] & bit) == 0) {{
+            t->read_before_write[word] |= bit;
+        }}
+    }}
+}}
+
+__attribute__((always_inline))
+static inline void page_access_write_page(Tracer* t, uint64_t page) {{
+    page &= PAGE_ACCESS_MASK;
+    t->written[page >> 6] |= 1ull << (page & 63);
+}}
+
+/* Accesses may straddle a page boundary: mark the pages of the first and last byte */
+__attribute__((always_inline))
+static inline void page_access_read(Tracer* t, uint64_t addr, uint64_t size) {{
+    uint64_t first = addr >> PAGE_ACCESS_SHIFT;
+    uint64_t last = (addr + size - 1) >> PAGE_ACCESS_SHIFT;
+    page_access_read_page(t, first);
+    if (__builtin_expect(last != first, 0)) {{
+        page_access_read_page(t, last);
+    }}
+}}
+
+__attribute__((always_inline))
+static inline void page_access_write(Tracer* t, uint64_t addr, uint64_t size) {{
+    uint64_t first = addr >> PAGE_ACCESS_SHIFT;
+    uint64_t last = (addr + size - 1) >> PAGE_ACCESS_SHIFT;
+    page_access_write_page(t, first);
+    if (__builtin_expect(last != first, 0)) {{
+        page_access_write_page(t, last);
+    }}
+}}
+
+/* Block entry */
+static inline void trace_block(Tracer* t, {rtype} pc) {{
+    (void)t; (void)pc;
+}}
+
+/* Instruction dispatch */
+static inline void trace_pc(Tracer* t, {rtype} pc, uint16_t op) {{
+    (void)t; (void)pc; (void)op;
+}}
+
+static inline void trace_opcode(Tracer* t, {rtype} pc, uint16_t op, uint32_t opcode) {{
+    (void)t; (void)pc; (void)op; (void)opcode;
+}}
+
+/* Register access */
+static inline void trace_reg_read(Tracer* t, {rtype} pc, uint16_t op, uint8_t reg, {rtype} value) {{
+    (void)t; (void)pc; (void)op; (void)reg; (void)value;
+}}
+
+static inline void trace_reg_write(Tracer* t, {rtype} pc, uint16_t op, uint8_t reg, {rtype} value) {{
+    (void)t; (void)pc; (void)op; (void)reg; (void)value;
+}}
+
+/* Memory reads */
+static inline void trace_mem_read_byte(Tracer* t, {rtype} pc, uint16_t op, {rtype} addr, uint8_t value) {{
+    (void)pc; (void)op; (void)value;
+    page_access_read(t, addr, 1);
+}}
+
+static inline void trace_mem_read_halfword(Tracer* t, {rtype} pc, uint16_t op, {rtype} addr, uint16_t value) {{
+    (void)pc; (void)op; (void)value;
+    page_access_read(t, addr, 2);
+}}
+
+static inline void trace_mem_read_word(Tracer* t, {rtype} pc, uint16_t op, {rtype} addr, uint32_t value) {{
+    (void)pc; (void)op; (void)value;
+    page_access_read(t, addr, 4);
+}}
+
+static inline void trace_mem_read_dword(Tracer* t, {rtype} pc, uint16_t op, {rtype} addr, uint64_t value) {{
+    (void)pc; (void)op; (void)value;
+    page_access_read(t, addr, 8);
+}}
+
+/* Memory writes */
+static inline void trace_mem_write_byte(Tracer* t, {rtype} pc, uint16_t op, {rtype} addr, uint8_t value) {{
+    (void)pc; (void)op; (void)value;
+    page_access_write(t, addr, 1);
+}}
+
+static inline void trace_mem_write_halfword(Tracer* t, {rtype} pc, uint16_t op, {rtype} addr, uint16_t value) {{
+    (void)pc; (void)op; (void)value;
+    page_access_write(t, addr, 2);
+}}
+
+static inline void trace_mem_write_word(Tracer* t, {rtype} pc, uint16_t op, {rtype} addr, uint32_t value) {{
+    (void)pc; (void)op; (void)value;
+    page_access_write(t, addr, 4);
+}}
+
+static inline void trace_mem_write_dword(Tracer* t, {rtype} pc, uint16_t op, {rtype} addr, uint64_t value) {{
+    (void)pc; (void)op; (void)value;
+    page_access_write(t, addr, 8);
+}}
+
+/* Control flow */
+static inline void trace_branch_taken(Tracer* t, {rtype} pc, uint16_t op, {rtype} target) {{
+    (void)t; (void)pc; (void)op; (void)target;
+}}
+
+static inline void trace_branch_not_taken(Tracer* t, {rtype} pc, uint16_t op, {rtype} target) {{
+    (void)t; (void)pc; (void)op; (void)target;
+}}
+
+/* CSR access */
+static inline void trace_csr_read(Tracer* t, {rtype} pc, uint16_t op, uint16_t csr, {rtype} value) {{
+    (void)t; (void)pc; (void)op; (void)csr; (void)value;
+}}
+
+static inline void trace_csr_write(Tracer* t, {rtype} pc, uint16_t op, uint16_t csr, {rtype} value) {{
+    (void)t; (void)pc; (void)op; (void)csr; (void)value;
+}}
+"
+    )
+}
@@ -25,7 +25,9 @@ pub fn gen_tracer_spike<X: Xlen>() -> String {
         r#"
         /* Spike-compatible tracer - outputs in Spike's --log-commits format.
          *
-         * Format: core   0: 3 0x<PC> (0x<OPCODE>) [x<RD> 0x<VALUE>] [mem 0x<ADDR>]
+         * Format: core   0: 3 0x<PC> (0x<OPCODE>) [x<RD> 0x<VALUE>] [c<CSR>_csr 0x<VALUE>] [mem 0x<ADDR>]
+         *
+         * CSR numbers are decimal, as Spike prints them; rvr does not name them.
          *
          * Set RVR_TRACE_FILE environment variable to specify output file.
          * Default: /tmp/rvr_trace.log
@@ -43,9 +45,12 @@ pub fn gen_tracer_spike<X: Xlen>() -> String {
             uint8_t pending_rd;
             {rtype} pending_rd_value;
             {rtype} pending_mem_addr;
+            uint16_t pending_csr;
+            {rtype} pending_csr_value;
             uint8_t has_pending;
             uint8_t has_rd;
             uint8_t has_mem;
+            uint8_t has_csr;
             uint64_t count;
         }} Tracer;
         
@@ -60,6 +65,11 @@ pub fn gen_tracer_spike<X: Xlen>() -> String {
                         (unsigned)t->pending_rd, {val_cast}t->pending_rd_value);
             }}
         
+            if (t->has_csr) {{
+                fprintf(t->fp, " c%u_csr 0x{val_fmt}",
+                        (unsigned)t->pending_csr, {val_cast}t->pending_csr_value);
+            }}
+        
             if (t->has_mem) {{
                 fprintf(t->fp, " mem 0x{val_fmt}", {val_cast}t->pending_mem_addr);
             }}
@@ -68,6 +78,7 @@ pub fn gen_tracer_spike<X: Xlen>() -> String {
             t->has_pending = 0;
             t->has_rd = 0;
             t->has_mem = 0;
+            t->has_csr = 0;
         }}
         
         static inline void trace_init(Tracer* t) {{
@@ -78,6 +89,7 @@ pub fn gen_tracer_spike<X: Xlen>() -> String {
             t->has_pending = 0;
             t->has_rd = 0;
             t->has_mem = 0;
+            t->has_csr = 0;
             t->count = 0;
         }}
         
@@ -105,6 +117,7 @@ pub fn gen_tracer_spike<X: Xlen>() -> String {
             t->has_pending = 1;
             t->has_rd = 0;
             t->has_mem = 0;
+            t->has_csr = 0;
             t->count++;
         }}
         
@@ -193,7 +206,10 @@ pub fn gen_tracer_spike<X: Xlen>() -> String {
         }}
         
         static inline void trace_csr_write(Tracer* t, {rtype} pc, uint16_t op, uint16_t csr, {rtype} value) {{
-            (void)t; (void)pc; (void)op; (void)csr; (void)value;
+            (void)pc; (void)op;
+            t->pending_csr = csr;
+            t->pending_csr_value = value;
+            t->has_csr = 1;
         }}
         "#,
     )
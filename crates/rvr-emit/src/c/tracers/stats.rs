@@ -3,16 +3,26 @@
 use rvr_ir::Xlen;
 use rvr_isa::REG_ABI_NAMES;
 
+use super::super::config::CDialect;
 use super::super::signature::reg_type;
 
 #[allow(clippy::too_many_lines)]
-pub fn gen_tracer_stats<X: Xlen>() -> String {
+pub fn gen_tracer_stats<X: Xlen>(dialect: CDialect) -> String {
     let reg_names = REG_ABI_NAMES
         .iter()
         .map(|n| format!("\"{n}\""))
         .collect::<Vec<_>>()
         .join(", ");
     let rtype = reg_type::<X>();
+    let page_bitmap_words = dialect.constant("size_t", "PAGE_BITMAP_WORDS", "16384", false);
+    let page_shift = dialect.constant("int", "PAGE_SHIFT", "12", false);
+    let addr_bitmap_bytes = dialect.constant("uint64_t", "ADDR_BITMAP_BYTES", "1ULL << 29", false);
+    let addr_bitmap_words = dialect.constant(
+        "size_t",
+        "ADDR_BITMAP_WORDS",
+        "ADDR_BITMAP_BYTES / 8",
+        false,
+    );
 
     format!(
         r#"
@@ -31,12 +41,12 @@ pub fn gen_tracer_stats<X: Xlen>() -> String {
         }};
         
         /* Page bitmap: 4GB / 4KB pages / 64 bits = 16384 words = 128KB */
-        constexpr size_t PAGE_BITMAP_WORDS = 16384;
-        constexpr int PAGE_SHIFT = 12;
+        {page_bitmap_words}
+        {page_shift}
         
         /* Address bitmap: 4GB addresses / 8 bits = 512MB (allocated externally) */
-        constexpr uint64_t ADDR_BITMAP_BYTES = 1ULL << 29;
-        constexpr size_t ADDR_BITMAP_WORDS = ADDR_BITMAP_BYTES / 8;
+        {addr_bitmap_bytes}
+        {addr_bitmap_words}
         
         typedef struct Tracer {{
             uint64_t blocks;
@@ -0,0 +1,244 @@
+//! Vector extension runtime.
+//!
+//! Vector instructions (see `rvr_isa::VExtension`) lift to calls into
+//! `rv_vsetvl`, `rv_vle`, `rv_vse` and the arithmetic helpers generated here
+//! into `<base>_vector.c`. `vl` and `vtype` live in their `csrs` slots and
+//! the registers in `RvState::vregs`, `VLEN / 8` bytes each. The helpers
+//! loop over the active elements one at a time: they exist so
+//! autovectorized code runs, not to make it fast. Tail and masked-off
+//! elements are left undisturbed, which satisfies both agnostic and
+//! undisturbed policies.
+//!
+//! Element memory accesses go through `phys_addr`, so they wrap or trap like
+//! scalar accesses, but tracers and code-write checks do not see them.
+
+use std::fmt::Write;
+
+use rvr_ir::Xlen;
+
+use super::namespace::namespaced;
+use super::signature::{MEMORY_PLACEHOLDER, memory_ref, reg_type};
+
+/// `vl` CSR.
+pub const CSR_VL: u32 = 0xC20;
+/// `vtype` CSR.
+pub const CSR_VTYPE: u32 = 0xC21;
+/// `vlenb` CSR (VLEN in bytes, read-only).
+pub const CSR_VLENB: u32 = 0xC22;
+
+/// Element-wise integer operations: name, result of `a` (the `vs2`
+/// element) and `b` (the `vs1` element or scalar), and whether it reads `a`.
+const VECTOR_OPS: [(&str, &str, bool); 5] = [
+    ("vadd", "a + b", true),
+    ("vand", "a & b", true),
+    ("vor", "a | b", true),
+    ("vxor", "a ^ b", true),
+    ("vmv", "b", false),
+];
+
+const VECTOR_PRELUDE: &str = r"
+/* Bytes per element of the current vtype (SEW / 8) */
+static inline uint32_t sew_bytes(const RvState* restrict state) {
+    return 1u << ((state->csrs[CSR_VTYPE] >> 3) & 7);
+}
+
+/* Byte offset of element i of the register group starting at reg */
+static inline size_t elem_offset(reg_t reg, reg_t i, uint32_t bytes) {
+    return ((size_t)reg * RV_VLENB + (size_t)i * bytes) % (32 * RV_VLENB);
+}
+
+/* Elements are little-endian, like the guest and the host */
+static inline uint64_t get_elem(const RvState* restrict state, reg_t reg, reg_t i,
+                                uint32_t bytes) {
+    uint64_t value = 0;
+    memcpy(&value, state->vregs + elem_offset(reg, i, bytes), bytes);
+    return value;
+}
+
+static inline void set_elem(RvState* restrict state, reg_t reg, reg_t i, uint32_t bytes,
+                            uint64_t value) {
+    memcpy(state->vregs + elem_offset(reg, i, bytes), &value, bytes);
+}
+
+/* Whether element i is inactive: masked (vm = 0) and its v0 bit clear */
+static inline int masked_off(const RvState* restrict state, reg_t vm, reg_t i) {
+    return !vm && !((state->vregs[i / 8] >> (i % 8)) & 1);
+}
+
+/* Set vtype and vl = min(avl, VLMAX), or keep vl (clamped) if keep.
+ * Unsupported vtypes set vill and vl = 0, so vector instructions do nothing. */
+reg_t {prefix}rv_vsetvl(RvState* restrict state, reg_t avl, reg_t vtype, reg_t keep) {
+    uint32_t vsew = (vtype >> 3) & 7;
+    uint32_t vlmul = vtype & 7;
+    reg_t vlmax = 0;
+    if ((vtype >> 8) == 0 && vsew <= 3 && vlmul != 4 && !(vlmul > 4 && vsew + 5 > vlmul)) {
+        reg_t per_reg = (reg_t)RV_VLEN >> (3 + vsew);
+        vlmax = vlmul < 4 ? per_reg << vlmul : per_reg >> (8 - vlmul);
+    }
+    if (vlmax == 0) {
+        /* vill, the top bit of vtype */
+        state->csrs[CSR_VTYPE] = (reg_t)1 << (XLEN - 1);
+        state->csrs[CSR_VL] = 0;
+        return 0;
+    }
+    reg_t vl = keep ? state->csrs[CSR_VL] : avl;
+    state->csrs[CSR_VTYPE] = vtype;
+    state->csrs[CSR_VL] = vl < vlmax ? vl : vlmax;
+    return state->csrs[CSR_VL];
+}
+
+void {prefix}rv_vle(RvState* restrict state, reg_t vd, reg_t addr, reg_t eew, reg_t vm) {
+    const uint8_t* mem = {memory};
+    for (reg_t i = 0; i < state->csrs[CSR_VL]; i++) {
+        if (masked_off(state, vm, i)) {
+            continue;
+        }
+        uint64_t value = 0;
+        for (reg_t b = 0; b < eew; b++) {
+            value |= (uint64_t)mem[phys_addr(addr + i * eew + b)] << (8 * b);
+        }
+        set_elem(state, vd, i, (uint32_t)eew, value);
+    }
+}
+
+void {prefix}rv_vse(RvState* restrict state, reg_t vs3, reg_t addr, reg_t eew, reg_t vm) {
+    uint8_t* mem = {memory};
+    for (reg_t i = 0; i < state->csrs[CSR_VL]; i++) {
+        if (masked_off(state, vm, i)) {
+            continue;
+        }
+        uint64_t value = get_elem(state, vs3, i, (uint32_t)eew);
+        for (reg_t b = 0; b < eew; b++) {
+            mem[phys_addr(addr + i * eew + b)] = (uint8_t)(value >> (8 * b));
+        }
+    }
+}
+
+/* Whole-register move of nr registers, regardless of vl and vtype */
+void {prefix}rv_vmvr(RvState* restrict state, reg_t vd, reg_t vs2, reg_t nr) {
+    memmove(state->vregs + vd * RV_VLENB, state->vregs + vs2 * RV_VLENB, nr * RV_VLENB);
+}
+";
+
+/// `rv_<name>_vv` and `rv_<name>_vx` for an element-wise operation.
+fn push_vector_op(s: &mut String, prefix: &str, name: &str, expr: &str, reads_vs2: bool) {
+    let read_a = if reads_vs2 {
+        "        uint64_t a = get_elem(state, vs2, i, bytes);\n"
+    } else {
+        "        (void)vs2;\n"
+    };
+    for (suffix, src, read_b) in [
+        (
+            "vv",
+            "vs1",
+            "        uint64_t b = get_elem(state, vs1, i, bytes);\n",
+        ),
+        ("vx", "x", ""),
+    ] {
+        let scalar = if read_b.is_empty() {
+            // Sign-extended from XLEN, then truncated to SEW
+            "    uint64_t b = (uint64_t)(int64_t)(sreg_t)x;\n"
+        } else {
+            ""
+        };
+        let _ = write!(
+            s,
+            r"
+void {prefix}rv_{name}_{suffix}(RvState* restrict state, reg_t vd, reg_t vs2, reg_t {src}, reg_t vm) {{
+    uint32_t bytes = sew_bytes(state);
+{scalar}    for (reg_t i = 0; i < state->csrs[CSR_VL]; i++) {{
+        if (masked_off(state, vm, i)) {{
+            continue;
+        }}
+{read_a}{read_b}        set_elem(state, vd, i, bytes, {expr});
+    }}
+}}
+"
+        );
+    }
+}
+
+/// Generate the vector runtime source (`<base>_vector.c`).
+#[must_use]
+pub fn gen_vector_source<X: Xlen>(base_name: &str, fixed_addresses: bool, prefix: &str) -> String {
+    let rtype = reg_type::<X>();
+    let stype = if X::VALUE == 32 { "int32_t" } else { "int64_t" };
+    let mem_ref = memory_ref(fixed_addresses);
+    let mut s = format!(
+        r#"#include "{base_name}.h"
+#include <stddef.h>
+#include <stdint.h>
+#include <string.h>
+
+/* Vector extension (RVV subset) on state->vregs */
+
+typedef {rtype} reg_t;
+typedef {stype} sreg_t;
+{prelude}"#,
+        prelude = namespaced(VECTOR_PRELUDE, prefix).replace(MEMORY_PLACEHOLDER, mem_ref)
+    );
+    for (name, expr, reads_vs2) in VECTOR_OPS {
+        push_vector_op(&mut s, prefix, name, expr, reads_vs2);
+    }
+    s
+}
+
+/// Runtime declarations for the header.
+pub(super) fn gen_vector_declarations<X: Xlen>(prefix: &str) -> String {
+    let rtype = reg_type::<X>();
+    let mut s = format!(
+        r"/* Vector extension runtime (see the _vector.c source) */
+{rtype} {prefix}rv_vsetvl(RvState* restrict state, {rtype} avl, {rtype} vtype, {rtype} keep);
+void {prefix}rv_vle(RvState* restrict state, {rtype} vd, {rtype} addr, {rtype} eew, {rtype} vm);
+void {prefix}rv_vse(RvState* restrict state, {rtype} vs3, {rtype} addr, {rtype} eew, {rtype} vm);
+void {prefix}rv_vmvr(RvState* restrict state, {rtype} vd, {rtype} vs2, {rtype} nr);
+"
+    );
+    for (name, _, _) in VECTOR_OPS {
+        for (suffix, src) in [("vv", "vs1"), ("vx", "x")] {
+            let _ = writeln!(
+                s,
+                "void {prefix}rv_{name}_{suffix}(RvState* restrict state, {rtype} vd, {rtype} vs2, \
+                 {rtype} {src}, {rtype} vm);"
+            );
+        }
+    }
+    s.push('\n');
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use rvr_ir::{Rv32, Rv64};
+    use rvr_isa::VECTOR_RUNTIME_FNS;
+
+    use super::*;
+    use crate::c::PREFIX_PLACEHOLDER;
+    use crate::c::signature::MEMORY_FIXED_REF;
+
+    #[test]
+    fn test_gen_vector_source() {
+        let src = gen_vector_source::<Rv64>("rv64", false, "");
+        assert!(src.starts_with("#include \"rv64.h\"\n"));
+        assert!(src.contains("typedef int64_t sreg_t;"));
+        assert!(src.contains("    const uint8_t* mem = state->memory;"));
+        assert!(!src.contains(MEMORY_PLACEHOLDER));
+        assert!(src.contains(
+            "        uint64_t a = get_elem(state, vs2, i, bytes);\n        uint64_t b = \
+             get_elem(state, vs1, i, bytes);\n        set_elem(state, vd, i, bytes, a ^ b);"
+        ));
+
+        let prefixed = gen_vector_source::<Rv64>("rv64", false, "p_");
+        let decls = gen_vector_declarations::<Rv64>("p_");
+        for name in VECTOR_RUNTIME_FNS {
+            let def = format!(" p_{name}(RvState* restrict state, ");
+            assert!(prefixed.contains(&def), "{name} is not defined");
+            assert!(decls.contains(&def), "{name} is not declared");
+        }
+        assert!(!prefixed.contains(PREFIX_PLACEHOLDER));
+
+        let fixed = gen_vector_source::<Rv32>("rv32", true, "");
+        assert!(fixed.contains("typedef uint32_t reg_t;"));
+        assert!(fixed.contains(&format!("    uint8_t* mem = {MEMORY_FIXED_REF};")));
+    }
+}
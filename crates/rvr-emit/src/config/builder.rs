@@ -0,0 +1,467 @@
+//! `with_*` builder methods of [`EmitConfig`].
+
+use std::path::PathBuf;
+
+use rvr_ir::Xlen;
+use rvr_isa::LrScModel;
+use rvr_isa::syscalls::{BareMetalConfig, SyscallPolicy};
+
+use super::{
+    AddressMode, CDialect, Compiler, CompilerLauncher, Compression, CustomCsr, DispatchMode,
+    EmitConfig, FixedAddressConfig, HotRegsMode, InstretMode, LiftErrorMode, SuspendGranularity,
+    SyscallMode,
+};
+use crate::c::TracerConfig;
+use crate::hooks::FunctionHook;
+
+impl<X: Xlen> EmitConfig<X> {
+    /// Set address translation mode.
+    #[must_use]
+    pub const fn with_address_mode(mut self, mode: AddressMode) -> Self {
+        self.address_mode = mode;
+        self
+    }
+
+    /// Set lift error handling.
+    #[must_use]
+    pub const fn with_on_lift_error(mut self, mode: LiftErrorMode) -> Self {
+        self.on_lift_error = mode;
+        self
+    }
+
+    /// Set global or per-function hot register sets.
+    #[must_use]
+    pub const fn with_hot_regs_mode(mut self, mode: HotRegsMode) -> Self {
+        self.hot_regs_mode = mode;
+        self
+    }
+
+    /// Set dispatch table layout.
+    #[must_use]
+    pub const fn with_dispatch_mode(mut self, mode: DispatchMode) -> Self {
+        self.dispatch_mode = mode;
+        self
+    }
+
+    /// Store the flat dispatch table as self-relative offsets.
+    #[must_use]
+    pub const fn with_dispatch_table_relative(mut self, enabled: bool) -> Self {
+        self.flags.set_dispatch_table_relative(enabled);
+        self
+    }
+
+    /// Enable or disable dispatch table prefetches (see
+    /// `prefetch_dispatch`).
+    #[must_use]
+    pub const fn with_prefetch_dispatch(mut self, enabled: bool) -> Self {
+        self.flags.set_prefetch_dispatch(enabled);
+        self
+    }
+
+    /// Enable or disable huge-page placement of the dispatch table (see
+    /// `dispatch_table_hugepages`).
+    #[must_use]
+    pub const fn with_dispatch_table_hugepages(mut self, enabled: bool) -> Self {
+        self.flags.set_dispatch_table_hugepages(enabled);
+        self
+    }
+
+    /// Enable or disable the deterministic guest clock (see
+    /// `deterministic_clock`).
+    #[must_use]
+    pub const fn with_deterministic_clock(mut self, enabled: bool) -> Self {
+        self.flags.set_deterministic_clock(enabled);
+        self
+    }
+
+    /// Enable or disable interpreting blocks that fail to compile (see
+    /// `interpret_failed_blocks`).
+    #[must_use]
+    pub const fn with_interpret_failed_blocks(mut self, enabled: bool) -> Self {
+        self.flags.set_interpret_failed_blocks(enabled);
+        self
+    }
+
+    /// Set tracer configuration.
+    #[must_use]
+    pub fn with_tracer(mut self, config: TracerConfig) -> Self {
+        self.tracer_config = config;
+        self
+    }
+
+    /// Set instret mode.
+    #[must_use]
+    pub const fn with_instret_mode(mut self, mode: InstretMode) -> Self {
+        self.instret_mode = mode;
+        self
+    }
+
+    /// Set where `InstretMode::Suspend` checks the instret limit.
+    #[must_use]
+    pub const fn with_suspend_granularity(mut self, granularity: SuspendGranularity) -> Self {
+        self.suspend_granularity = granularity;
+        self
+    }
+
+    /// Set tohost enabled.
+    #[must_use]
+    pub const fn with_tohost(mut self, enabled: bool) -> Self {
+        self.flags.set_htif_enabled(enabled);
+        self
+    }
+
+    /// Set HTIF verbose (print guest stdout).
+    #[must_use]
+    pub const fn with_htif_verbose(mut self, verbose: bool) -> Self {
+        self.flags.set_htif_verbose(verbose);
+        self
+    }
+
+    /// Set the HTIF poll watchdog limit (0 disables it).
+    #[must_use]
+    pub const fn with_htif_poll_limit(mut self, limit: u64) -> Self {
+        self.htif_poll_limit = limit;
+        self
+    }
+
+    /// Set the golden trace file to embed and check against.
+    #[must_use]
+    pub fn with_embedded_golden(mut self, path: Option<PathBuf>) -> Self {
+        self.embedded_golden = path;
+        self
+    }
+
+    /// Set the blocks to run in the block interpreter, by start PC.
+    #[must_use]
+    pub fn with_interpret_blocks(mut self, pcs: impl IntoIterator<Item = u64>) -> Self {
+        self.interpret_blocks = pcs.into_iter().collect();
+        self
+    }
+
+    /// Set C compiler.
+    #[must_use]
+    pub fn with_compiler(mut self, compiler: Compiler) -> Self {
+        self.compiler = compiler;
+        self
+    }
+
+    /// Set the command prefixed to compile rules (e.g. ccache).
+    #[must_use]
+    pub fn with_compiler_launcher(mut self, launcher: CompilerLauncher) -> Self {
+        self.compiler_launcher = launcher;
+        self
+    }
+
+    /// Set the estimated compile cost per C part file.
+    #[must_use]
+    pub const fn with_target_part_cost(mut self, cost: usize) -> Self {
+        self.target_part_cost = cost;
+        self
+    }
+
+    /// Set the optimization level failed C part files are retried at
+    /// (`None` to not retry).
+    #[must_use]
+    pub const fn with_fallback_opt_level(mut self, level: Option<u8>) -> Self {
+        self.fallback_opt_level = level;
+        self
+    }
+
+    /// Set the C dialect of the generated code.
+    #[must_use]
+    pub const fn with_c_dialect(mut self, dialect: CDialect) -> Self {
+        self.c_dialect = dialect;
+        self
+    }
+
+    /// Set `emit_line_info` (for #line directives).
+    #[must_use]
+    pub const fn with_line_info(mut self, enabled: bool) -> Self {
+        self.flags.set_emit_line_info(enabled);
+        self
+    }
+
+    /// Set syscall mode.
+    #[must_use]
+    pub const fn with_syscall_mode(mut self, mode: SyscallMode) -> Self {
+        self.syscall_mode = mode;
+        self
+    }
+
+    /// Restrict Linux-mode syscalls to `policy`.
+    #[must_use]
+    pub fn with_syscall_policy(mut self, policy: Option<SyscallPolicy>) -> Self {
+        self.syscall_policy = policy;
+        self
+    }
+
+    /// Map bare-metal ECALLs to built-in actions.
+    #[must_use]
+    pub fn with_baremetal_ecalls(mut self, ecalls: Option<BareMetalConfig>) -> Self {
+        self.baremetal_ecalls = ecalls;
+        self
+    }
+
+    /// Set fixed addresses for state and memory.
+    ///
+    /// When enabled, state/memory are accessed via compile-time constant addresses
+    /// instead of function arguments. Requires runtime to map at these addresses.
+    #[must_use]
+    pub fn with_fixed_addresses(mut self, config: FixedAddressConfig) -> Self {
+        self.fixed_addresses = Some(config);
+        // Re-compute hot registers since fixed_addresses affects the calculation
+        self.init_hot_regs(crate::c::config::default_total_slots());
+        self
+    }
+
+    /// Set the load bias for position-independent ELFs.
+    ///
+    /// Exported as `RV_LOAD_BIAS` so the runner loads the ELF at the same
+    /// addresses.
+    #[must_use]
+    pub const fn with_load_bias(mut self, bias: u64) -> Self {
+        self.load_bias = Some(bias);
+        self
+    }
+
+    /// Decode the extensions of the ISA string `isa` (e.g. `rv64imac`),
+    /// whatever the ELF's attributes say.
+    #[must_use]
+    pub fn with_isa(mut self, isa: impl Into<String>) -> Self {
+        self.isa = Some(isa.into());
+        self
+    }
+
+    /// Cap the heap at `size` bytes above the initial program break.
+    ///
+    /// Checked against the image at lift time; `brk`/`mmap` fail beyond it.
+    #[must_use]
+    pub const fn with_heap(mut self, size: u64) -> Self {
+        self.heap_size = Some(size);
+        self
+    }
+
+    /// Reserve `size` bytes of stack below `__stack_top` (or the end of
+    /// memory).
+    #[must_use]
+    pub const fn with_stack_size(mut self, size: u64) -> Self {
+        self.stack_size = Some(size);
+        self
+    }
+
+    /// Serve anonymous `mmap`/`munmap`/`mremap` from a `size`-byte arena at
+    /// the top of the heap.
+    #[must_use]
+    pub const fn with_mmap_size(mut self, size: u64) -> Self {
+        self.mmap_size = Some(size);
+        self
+    }
+
+    /// Keep `size` bytes below a planned stack as its guard (0 for none).
+    #[must_use]
+    pub const fn with_stack_guard(mut self, size: u64) -> Self {
+        self.stack_guard = size;
+        self
+    }
+
+    /// Enable perf mode (disables instret and CSR reads).
+    #[must_use]
+    pub const fn with_perf_mode(mut self, enabled: bool) -> Self {
+        self.perf_mode = enabled;
+        if enabled {
+            self.instret_mode = InstretMode::Off;
+        }
+        self
+    }
+
+    /// Enable or disable superblock formation.
+    ///
+    /// Superblocks merge fall-through blocks after branches for better performance,
+    /// but prevent dispatch to mid-block addresses. Disable for differential testing.
+    #[must_use]
+    pub const fn with_superblock(mut self, enabled: bool) -> Self {
+        self.enable_superblock = enabled;
+        self
+    }
+
+    /// Enable or disable dead register write elimination (see `optimize_ir`).
+    #[must_use]
+    pub const fn with_optimize_ir(mut self, enabled: bool) -> Self {
+        self.flags.set_optimize_ir(enabled);
+        self
+    }
+
+    /// Enable or disable traps on stores into recompiled code (see
+    /// `detect_code_writes`).
+    #[must_use]
+    pub const fn with_detect_code_writes(mut self, enabled: bool) -> Self {
+        self.flags.set_detect_code_writes(enabled);
+        self
+    }
+
+    /// Enable or disable cancellation checks on backward edges (see
+    /// `check_cancel`).
+    #[must_use]
+    pub const fn with_check_cancel(mut self, enabled: bool) -> Self {
+        self.flags.set_check_cancel(enabled);
+        self
+    }
+
+    /// Enable or disable outlined cold paths (see `outline_cold_paths`).
+    #[must_use]
+    pub const fn with_outline_cold_paths(mut self, enabled: bool) -> Self {
+        self.flags.set_outline_cold_paths(enabled);
+        self
+    }
+
+    /// Enable or disable jump site reports (see `report_jump_sites`).
+    #[must_use]
+    pub const fn with_report_jump_sites(mut self, enabled: bool) -> Self {
+        self.flags.set_report_jump_sites(enabled);
+        self
+    }
+
+    /// Enable or disable IR verification in release builds (see
+    /// `verify_ir`).
+    #[must_use]
+    pub const fn with_verify_ir(mut self, enabled: bool) -> Self {
+        self.flags.set_verify_ir(enabled);
+        self
+    }
+
+    /// Enable or disable the static archive and embedding header (see
+    /// `static_archive`).
+    #[must_use]
+    pub const fn with_static_archive(mut self, enabled: bool) -> Self {
+        self.flags.set_static_archive(enabled);
+        self
+    }
+
+    /// Set the maximum instructions per emitted block.
+    ///
+    /// Very large blocks compile slowly as single C functions; past the limit
+    /// a block ends and falls through to the next one, passing hot registers
+    /// like any other block transfer.
+    #[must_use]
+    pub const fn with_superblock_max_instrs(mut self, max_instrs: usize) -> Self {
+        self.superblock_max_instrs = max_instrs;
+        self
+    }
+
+    /// Set the maximum basic blocks merged into one emitted block.
+    #[must_use]
+    pub const fn with_superblock_max_blocks(mut self, max_blocks: usize) -> Self {
+        self.superblock_max_blocks = max_blocks;
+        self
+    }
+
+    /// Enable or disable identical-block deduplication (C backend).
+    ///
+    /// Blocks whose emitted bodies match are compiled once; the others become
+    /// aliases of that function. Only blocks without PC-dependent constants
+    /// can match, so the output behaves the same either way.
+    #[must_use]
+    pub const fn with_dedup_blocks(mut self, enabled: bool) -> Self {
+        self.flags.set_dedup_blocks(enabled);
+        self
+    }
+
+    /// Fail the lift on instructions that would trap instead of listing them.
+    #[must_use]
+    pub const fn with_strict_decode(mut self, enabled: bool) -> Self {
+        self.flags.set_strict_decode(enabled);
+        self
+    }
+
+    /// Lower ARM64 AMOs to LSE instructions (`ldadd`, `swp`, ...) instead of
+    /// exclusive load/store loops. Needs an ARMv8.1 host.
+    #[must_use]
+    pub const fn with_arm64_lse(mut self, enabled: bool) -> Self {
+        self.flags.set_arm64_use_lse(enabled);
+        self
+    }
+
+    /// Initialize guest memory lazily from a segment image.
+    ///
+    /// Segment data is written to `<name>.segments` next to the library
+    /// instead of being embedded in it, and the runner maps the image
+    /// copy-on-write into guest memory rather than copying every byte.
+    #[must_use]
+    pub const fn with_lazy_segment_init(mut self, enabled: bool) -> Self {
+        self.flags.set_lazy_segment_init(enabled);
+        self
+    }
+
+    /// Run the guest's `memcpy`, `memset` and `memcmp` (found by symbol
+    /// name) as native helpers on guest memory (C backend).
+    ///
+    /// The function's first instruction becomes a call to the helper and a
+    /// return, so the call retires one instruction and only `a0` changes;
+    /// the caller-saved registers the guest loop would have clobbered keep
+    /// their values. Tracing is refused, since the traces would change.
+    #[must_use]
+    pub const fn with_native_mem_intrinsics(mut self, enabled: bool) -> Self {
+        self.flags.set_native_mem_intrinsics(enabled);
+        self
+    }
+
+    /// Map host code back to guest PCs (C backend).
+    ///
+    /// Each guest instruction is preceded by `#line <n> "guest_pc.map"`
+    /// instead of its source location, and the library is built with line
+    /// tables. Line `n` of the `guest_pc.map` sidecar names the guest PC, so
+    /// a host debugger or profiler shows guest PCs as source lines and
+    /// `rvr addr2pc` resolves a host address. See `GuestPcLines`.
+    #[must_use]
+    pub const fn with_guest_pc_map(mut self, enabled: bool) -> Self {
+        self.flags.set_emit_guest_pc_map(enabled);
+        self
+    }
+
+    /// Set the custom CSRs (C backend).
+    ///
+    /// Hook CSRs shadowing a counter (`cycle`, `instret` and their high
+    /// halves) are ignored; the counters keep their built-in behavior.
+    #[must_use]
+    pub fn with_custom_csrs(mut self, csrs: Vec<CustomCsr>) -> Self {
+        self.custom_csrs = csrs;
+        self
+    }
+
+    /// Set the guest functions replaced by hooks (see `function_hooks`).
+    #[must_use]
+    pub fn with_function_hooks(mut self, hooks: Vec<FunctionHook>) -> Self {
+        self.function_hooks = hooks;
+        self
+    }
+
+    /// Set the vector register length in bits (see `vlen`).
+    #[must_use]
+    pub const fn with_vlen(mut self, vlen: u32) -> Self {
+        self.vlen = vlen;
+        self
+    }
+
+    /// Compress embedded segment data (see [`Compression`]).
+    #[must_use]
+    pub const fn with_compress_segments(mut self, compression: Option<Compression>) -> Self {
+        self.compress_segments = compression;
+        self
+    }
+
+    /// Set the LR/SC reservation model (see [`LrScModel`]).
+    #[must_use]
+    pub const fn with_lrsc_model(mut self, model: LrScModel) -> Self {
+        self.lrsc_model = model;
+        self
+    }
+
+    /// Set the thread count for CFG analysis and lifting (0 = rayon default).
+    ///
+    /// The emitted code does not depend on this setting.
+    #[must_use]
+    pub const fn with_analysis_jobs(mut self, jobs: usize) -> Self {
+        self.analysis_jobs = jobs;
+        self
+    }
+}
@@ -0,0 +1,119 @@
+//! [`EmitConfig::fingerprint`], the cache key of a configuration.
+
+use std::fmt::Write;
+
+use rvr_ir::Xlen;
+
+use super::{EmitConfig, FINGERPRINT_VERSION};
+
+impl<X: Xlen> EmitConfig<X> {
+    /// Canonical rendering of every setting that affects the generated code,
+    /// one `name=value` line per field, for use as a cache key.
+    ///
+    /// `analysis_jobs`, `compiler_launcher` and `target_part_cost` only change
+    /// how the work is split and run, and are left out.
+    /// Files named by the config (a tracer header path) are not read; their
+    /// contents are up to the caller.
+    #[must_use]
+    pub fn fingerprint(&self) -> String {
+        // Destructured so that a new field cannot be added without deciding
+        // whether it belongs in the fingerprint
+        let Self {
+            num_regs,
+            hot_regs,
+            hot_regs_mode,
+            backend,
+            analysis_mode,
+            analysis_jobs: _,
+            dispatch_mode,
+            address_mode,
+            on_lift_error,
+            instret_mode,
+            suspend_granularity,
+            flags,
+            memory_bits,
+            layout,
+            load_bias,
+            isa,
+            heap_size,
+            stack_size,
+            mmap_size,
+            stack_guard,
+            tracer_config,
+            compiler,
+            compiler_launcher: _,
+            target_part_cost: _,
+            fallback_opt_level,
+            c_dialect,
+            syscall_mode,
+            syscall_policy,
+            baremetal_ecalls,
+            export_functions,
+            fixed_addresses,
+            perf_mode,
+            enable_superblock,
+            superblock_max_instrs,
+            superblock_max_blocks,
+            custom_csrs,
+            function_hooks,
+            symbol_prefix,
+            vlen,
+            compress_segments,
+            lrsc_model,
+            htif_poll_limit,
+            embedded_golden,
+            interpret_blocks,
+            _marker: _,
+        } = self;
+        let fields: [(&str, &dyn std::fmt::Debug); 43] = [
+            ("version", &FINGERPRINT_VERSION),
+            ("xlen", &X::VALUE),
+            ("num_regs", num_regs),
+            ("hot_regs", hot_regs),
+            ("hot_regs_mode", hot_regs_mode),
+            ("backend", backend),
+            ("analysis_mode", analysis_mode),
+            ("dispatch_mode", dispatch_mode),
+            ("address_mode", address_mode),
+            ("on_lift_error", on_lift_error),
+            ("instret_mode", instret_mode),
+            ("suspend_granularity", suspend_granularity),
+            ("flags", flags),
+            ("memory_bits", memory_bits),
+            ("layout", layout),
+            ("load_bias", load_bias),
+            ("isa", isa),
+            ("heap_size", heap_size),
+            ("stack_size", stack_size),
+            ("mmap_size", mmap_size),
+            ("stack_guard", stack_guard),
+            ("tracer_config", tracer_config),
+            ("compiler", compiler),
+            ("fallback_opt_level", fallback_opt_level),
+            ("c_dialect", c_dialect),
+            ("syscall_mode", syscall_mode),
+            ("syscall_policy", syscall_policy),
+            ("baremetal_ecalls", baremetal_ecalls),
+            ("export_functions", export_functions),
+            ("fixed_addresses", fixed_addresses),
+            ("perf_mode", perf_mode),
+            ("enable_superblock", enable_superblock),
+            ("superblock_max_instrs", superblock_max_instrs),
+            ("superblock_max_blocks", superblock_max_blocks),
+            ("custom_csrs", custom_csrs),
+            ("function_hooks", function_hooks),
+            ("symbol_prefix", symbol_prefix),
+            ("vlen", vlen),
+            ("compress_segments", compress_segments),
+            ("lrsc_model", lrsc_model),
+            ("htif_poll_limit", htif_poll_limit),
+            ("embedded_golden", embedded_golden),
+            ("interpret_blocks", interpret_blocks),
+        ];
+        let mut out = String::new();
+        for (name, value) in fields {
+            let _ = writeln!(out, "{name}={value:?}");
+        }
+        out
+    }
+}
@@ -0,0 +1,245 @@
+//! Boolean codegen options, packed into [`EmitFlags`].
+
+/// Codegen feature flags for emitters.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EmitFlags(u32);
+
+impl EmitFlags {
+    const EMIT_COMMENTS: u32 = 1 << 0;
+    const EMIT_LINE_INFO: u32 = 1 << 1;
+    const HTIF_ENABLED: u32 = 1 << 2;
+    const HTIF_VERBOSE: u32 = 1 << 3;
+    const DEDUP_BLOCKS: u32 = 1 << 4;
+    const STRICT_DECODE: u32 = 1 << 5;
+    const ARM64_USE_LSE: u32 = 1 << 6;
+    const OPTIMIZE_IR: u32 = 1 << 7;
+    const LAZY_SEGMENT_INIT: u32 = 1 << 8;
+    const NATIVE_MEM_INTRINSICS: u32 = 1 << 9;
+    const GUEST_PC_MAP: u32 = 1 << 10;
+    const DETECT_CODE_WRITES: u32 = 1 << 11;
+    const DISPATCH_TABLE_RELATIVE: u32 = 1 << 12;
+    const CHECK_CANCEL: u32 = 1 << 13;
+    const STATIC_ARCHIVE: u32 = 1 << 14;
+    const OUTLINE_COLD_PATHS: u32 = 1 << 15;
+    const REPORT_JUMP_SITES: u32 = 1 << 16;
+    const VERIFY_IR: u32 = 1 << 17;
+    const PREFETCH_DISPATCH: u32 = 1 << 18;
+    const DISPATCH_TABLE_HUGEPAGES: u32 = 1 << 19;
+    const DETERMINISTIC_CLOCK: u32 = 1 << 20;
+    const INTERPRET_FAILED_BLOCKS: u32 = 1 << 21;
+
+    #[must_use]
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    const fn contains(self, mask: u32) -> bool {
+        (self.0 & mask) != 0
+    }
+
+    const fn set(&mut self, mask: u32, enabled: bool) {
+        if enabled {
+            self.0 |= mask;
+        } else {
+            self.0 &= !mask;
+        }
+    }
+
+    #[must_use]
+    pub const fn emit_comments(self) -> bool {
+        self.contains(Self::EMIT_COMMENTS)
+    }
+
+    pub const fn set_emit_comments(&mut self, enabled: bool) {
+        self.set(Self::EMIT_COMMENTS, enabled);
+    }
+
+    #[must_use]
+    pub const fn emit_line_info(self) -> bool {
+        self.contains(Self::EMIT_LINE_INFO)
+    }
+
+    pub const fn set_emit_line_info(&mut self, enabled: bool) {
+        self.set(Self::EMIT_LINE_INFO, enabled);
+    }
+
+    #[must_use]
+    pub const fn htif_enabled(self) -> bool {
+        self.contains(Self::HTIF_ENABLED)
+    }
+
+    pub const fn set_htif_enabled(&mut self, enabled: bool) {
+        self.set(Self::HTIF_ENABLED, enabled);
+    }
+
+    #[must_use]
+    pub const fn htif_verbose(self) -> bool {
+        self.contains(Self::HTIF_VERBOSE)
+    }
+
+    pub const fn set_htif_verbose(&mut self, enabled: bool) {
+        self.set(Self::HTIF_VERBOSE, enabled);
+    }
+
+    #[must_use]
+    pub const fn dedup_blocks(self) -> bool {
+        self.contains(Self::DEDUP_BLOCKS)
+    }
+
+    pub const fn set_dedup_blocks(&mut self, enabled: bool) {
+        self.set(Self::DEDUP_BLOCKS, enabled);
+    }
+
+    #[must_use]
+    pub const fn strict_decode(self) -> bool {
+        self.contains(Self::STRICT_DECODE)
+    }
+
+    pub const fn set_strict_decode(&mut self, enabled: bool) {
+        self.set(Self::STRICT_DECODE, enabled);
+    }
+
+    #[must_use]
+    pub const fn arm64_use_lse(self) -> bool {
+        self.contains(Self::ARM64_USE_LSE)
+    }
+
+    pub const fn set_arm64_use_lse(&mut self, enabled: bool) {
+        self.set(Self::ARM64_USE_LSE, enabled);
+    }
+
+    #[must_use]
+    pub const fn optimize_ir(self) -> bool {
+        self.contains(Self::OPTIMIZE_IR)
+    }
+
+    pub const fn set_optimize_ir(&mut self, enabled: bool) {
+        self.set(Self::OPTIMIZE_IR, enabled);
+    }
+
+    #[must_use]
+    pub const fn lazy_segment_init(self) -> bool {
+        self.contains(Self::LAZY_SEGMENT_INIT)
+    }
+
+    pub const fn set_lazy_segment_init(&mut self, enabled: bool) {
+        self.set(Self::LAZY_SEGMENT_INIT, enabled);
+    }
+
+    #[must_use]
+    pub const fn native_mem_intrinsics(self) -> bool {
+        self.contains(Self::NATIVE_MEM_INTRINSICS)
+    }
+
+    pub const fn set_native_mem_intrinsics(&mut self, enabled: bool) {
+        self.set(Self::NATIVE_MEM_INTRINSICS, enabled);
+    }
+
+    #[must_use]
+    pub const fn emit_guest_pc_map(self) -> bool {
+        self.contains(Self::GUEST_PC_MAP)
+    }
+
+    pub const fn set_emit_guest_pc_map(&mut self, enabled: bool) {
+        self.set(Self::GUEST_PC_MAP, enabled);
+    }
+
+    #[must_use]
+    pub const fn detect_code_writes(self) -> bool {
+        self.contains(Self::DETECT_CODE_WRITES)
+    }
+
+    pub const fn set_detect_code_writes(&mut self, enabled: bool) {
+        self.set(Self::DETECT_CODE_WRITES, enabled);
+    }
+
+    #[must_use]
+    pub const fn dispatch_table_relative(self) -> bool {
+        self.contains(Self::DISPATCH_TABLE_RELATIVE)
+    }
+
+    pub const fn set_dispatch_table_relative(&mut self, enabled: bool) {
+        self.set(Self::DISPATCH_TABLE_RELATIVE, enabled);
+    }
+
+    #[must_use]
+    pub const fn check_cancel(self) -> bool {
+        self.contains(Self::CHECK_CANCEL)
+    }
+
+    pub const fn set_check_cancel(&mut self, enabled: bool) {
+        self.set(Self::CHECK_CANCEL, enabled);
+    }
+
+    #[must_use]
+    pub const fn static_archive(self) -> bool {
+        self.contains(Self::STATIC_ARCHIVE)
+    }
+
+    pub const fn set_static_archive(&mut self, enabled: bool) {
+        self.set(Self::STATIC_ARCHIVE, enabled);
+    }
+
+    #[must_use]
+    pub const fn outline_cold_paths(self) -> bool {
+        self.contains(Self::OUTLINE_COLD_PATHS)
+    }
+
+    pub const fn set_outline_cold_paths(&mut self, enabled: bool) {
+        self.set(Self::OUTLINE_COLD_PATHS, enabled);
+    }
+
+    #[must_use]
+    pub const fn report_jump_sites(self) -> bool {
+        self.contains(Self::REPORT_JUMP_SITES)
+    }
+
+    pub const fn set_report_jump_sites(&mut self, enabled: bool) {
+        self.set(Self::REPORT_JUMP_SITES, enabled);
+    }
+
+    #[must_use]
+    pub const fn verify_ir(self) -> bool {
+        self.contains(Self::VERIFY_IR)
+    }
+
+    pub const fn set_verify_ir(&mut self, enabled: bool) {
+        self.set(Self::VERIFY_IR, enabled);
+    }
+
+    #[must_use]
+    pub const fn prefetch_dispatch(self) -> bool {
+        self.contains(Self::PREFETCH_DISPATCH)
+    }
+
+    pub const fn set_prefetch_dispatch(&mut self, enabled: bool) {
+        self.set(Self::PREFETCH_DISPATCH, enabled);
+    }
+
+    #[must_use]
+    pub const fn dispatch_table_hugepages(self) -> bool {
+        self.contains(Self::DISPATCH_TABLE_HUGEPAGES)
+    }
+
+    pub const fn set_dispatch_table_hugepages(&mut self, enabled: bool) {
+        self.set(Self::DISPATCH_TABLE_HUGEPAGES, enabled);
+    }
+
+    #[must_use]
+    pub const fn deterministic_clock(self) -> bool {
+        self.contains(Self::DETERMINISTIC_CLOCK)
+    }
+
+    pub const fn set_deterministic_clock(&mut self, enabled: bool) {
+        self.set(Self::DETERMINISTIC_CLOCK, enabled);
+    }
+
+    #[must_use]
+    pub const fn interpret_failed_blocks(self) -> bool {
+        self.contains(Self::INTERPRET_FAILED_BLOCKS)
+    }
+
+    pub const fn set_interpret_failed_blocks(&mut self, enabled: bool) {
+        self.set(Self::INTERPRET_FAILED_BLOCKS, enabled);
+    }
+}
@@ -0,0 +1,731 @@
+//! Emit configuration.
+//!
+//! Code generation configuration including hot register selection,
+//! instret handling, and platform-specific defaults.
+
+use std::collections::BTreeSet;
+use std::marker::PhantomData;
+use std::path::PathBuf;
+
+use rvr_cfg::{BlockLimits, DEFAULT_SUPERBLOCK_DEPTH, DEFAULT_SUPERBLOCK_MAX_INSTRS};
+use rvr_ir::{RegAccesses, Xlen};
+use rvr_isa::LrScModel;
+use rvr_isa::syscalls::{BareMetalConfig, SyscallPolicy};
+
+use crate::arm64;
+use crate::c::{TracerConfig, config as c_config};
+use crate::hooks::FunctionHook;
+use crate::x86;
+use crate::{DEFAULT_STACK_GUARD, LayoutProfile};
+
+mod builder;
+mod fingerprint;
+mod flags;
+mod modes;
+
+// Import Compiler for convenience (used in EmitConfig)
+pub use c_config::{CDialect, Compiler, CompilerLauncher};
+pub use flags::EmitFlags;
+pub use modes::{
+    AddressMode, AnalysisMode, Backend, Compression, CsrMode, CustomCsr, DEFAULT_COMPRESS_MIN_SIZE,
+    DispatchMode, FixedAddressConfig, HotRegsMode, InstretMode, LiftErrorMode, SuspendGranularity,
+    SyscallMode,
+};
+
+/// Version of the [`EmitConfig::fingerprint`] format. Bump it whenever the
+/// rendering of a field changes, so that old fingerprints stop matching.
+pub const FINGERPRINT_VERSION: u32 = 1;
+
+/// Default vector register length in bits.
+pub const DEFAULT_VLEN: u32 = 128;
+
+/// Default estimated compile cost per C part file (see
+/// [`EmitConfig::target_part_cost`]).
+pub const DEFAULT_TARGET_PART_COST: usize = 16384;
+
+/// Default optimization level parts that fail to compile are retried at
+/// (see [`EmitConfig::fallback_opt_level`]).
+pub const DEFAULT_FALLBACK_OPT_LEVEL: u8 = 1;
+
+/// Default number of unchanged `fromhost` polls before an HTIF guest stops
+/// (see [`EmitConfig::htif_poll_limit`]).
+pub const DEFAULT_HTIF_POLL_LIMIT: u64 = 1 << 20;
+
+/// Instructions a dynamic jump's target register must be final ahead of
+/// the jump for [`EmitConfig::prefetch_dispatch`] to prefetch its dispatch
+/// table slot; closer writes leave the load no time to hide.
+pub const DISPATCH_PREFETCH_DISTANCE: usize = 3;
+
+/// Alignment of the dispatch table, so neighbouring slots share a cache
+/// line.
+pub const DISPATCH_TABLE_ALIGN: u32 = 64;
+
+/// Alignment of the dispatch table in its own section, so the host can
+/// back it with one huge page (see [`EmitConfig::dispatch_table_hugepages`]).
+pub const DISPATCH_TABLE_HUGEPAGE_ALIGN: u32 = 0x20_0000;
+
+/// Largest supported vector register length in bits (matches
+/// `rvr_state::MAX_VLEN`).
+pub const MAX_VLEN: u32 = 1024;
+
+/// Whether the vector runtime supports registers of `vlen` bits.
+#[must_use]
+pub const fn valid_vlen(vlen: u32) -> bool {
+    vlen.is_power_of_two() && vlen >= 64 && vlen <= MAX_VLEN
+}
+
+/// Number of registers for I extension.
+pub const NUM_REGS_I: usize = 32;
+/// Number of registers for E extension.
+pub const NUM_REGS_E: usize = 16;
+
+/// Get platform-specific default total slots for a given backend.
+#[must_use]
+pub const fn default_total_slots_for_backend(backend: Backend) -> usize {
+    match backend {
+        Backend::C => c_config::default_total_slots(),
+        Backend::X86Asm => x86::HOT_REG_SLOTS,
+        Backend::ARM64Asm => arm64::HOT_REG_SLOTS,
+    }
+}
+
+/// Register priority order for hot register selection.
+/// Higher priority registers are chosen first when slots are limited.
+/// x0 (zero) is excluded since it's always 0.
+pub const REG_PRIORITY: [u8; 31] = [
+    // Highest priority - used constantly
+    1, // ra
+    2, // sp
+    // Function arguments (a0-a7)
+    10, 11, 12, 13, 14, 15, 16, 17, // a0-a7
+    // Temporaries (t0-t2)
+    5, 6, 7, // t0-t2
+    // Temporaries (t3-t6)
+    28, 29, 30, 31, // t3-t6
+    // Saved registers (s0-s1)
+    8, 9, // s0-s1
+    // Saved registers (s2-s11)
+    18, 19, 20, 21, 22, 23, 24, 25, 26, 27, // s2-s11
+    // Lowest priority - rarely used
+    3, // gp
+    4, // tp
+];
+
+/// Code generation configuration.
+#[derive(Clone, Debug)]
+pub struct EmitConfig<X: Xlen> {
+    /// Number of registers: 32 for I extension, 16 for E extension.
+    pub num_regs: usize,
+    /// Registers passed as arguments (hot registers).
+    pub hot_regs: Vec<u8>,
+    /// Global or per-function hot register sets (C backend).
+    pub hot_regs_mode: HotRegsMode,
+    /// Code generation backend (C or x86 assembly).
+    pub backend: Backend,
+    /// Analysis mode (full CFG or linear scan).
+    pub analysis_mode: AnalysisMode,
+    /// Threads for CFG analysis and lifting (0 = rayon default).
+    pub analysis_jobs: usize,
+    /// Dispatch table layout (C backend).
+    pub dispatch_mode: DispatchMode,
+    /// Address translation mode.
+    pub address_mode: AddressMode,
+    /// Lift error handling (abort or quarantine failing blocks).
+    pub on_lift_error: LiftErrorMode,
+    /// Instruction retirement mode.
+    pub instret_mode: InstretMode,
+    /// Where `InstretMode::Suspend` checks the instret limit.
+    pub suspend_granularity: SuspendGranularity,
+    /// Code generation feature flags.
+    pub flags: EmitFlags,
+    /// Memory address bits (default 32).
+    pub memory_bits: u8,
+    /// Address-space layout the guest was checked against (optional).
+    pub layout: Option<LayoutProfile>,
+    /// Load bias for position-independent (`ET_DYN`) ELFs (optional; the
+    /// ELF loader's default if unset).
+    pub load_bias: Option<u64>,
+    /// ISA string selecting the decoded extensions (optional; the ELF's
+    /// `Tag_RISCV_arch` attribute, or every supported extension, if unset).
+    pub isa: Option<String>,
+    /// Heap size in bytes (optional; see `MemoryLayout`).
+    pub heap_size: Option<u64>,
+    /// Stack size in bytes (optional; see `MemoryLayout`).
+    pub stack_size: Option<u64>,
+    /// Anonymous mmap arena size in bytes (optional; see `MemoryLayout`).
+    pub mmap_size: Option<u64>,
+    /// Guard bytes below a planned stack (see `MemoryLayout`).
+    pub stack_guard: u64,
+    /// Tracer configuration.
+    pub tracer_config: TracerConfig,
+    /// C compiler to use.
+    pub compiler: Compiler,
+    /// Command prefixed to compile rules in the generated Makefile (C
+    /// backend), e.g. ccache.
+    pub compiler_launcher: CompilerLauncher,
+    /// Estimated compile cost per C part file: one per IR statement and
+    /// terminator. Parts are balanced around it so no part compiles much
+    /// longer than the others (C backend).
+    pub target_part_cost: usize,
+    /// Optimization level (`-O<n>`) C part files that fail to compile, e.g.
+    /// when the compiler runs out of memory, are retried at (C backend;
+    /// `None` fails the build at once).
+    pub fallback_opt_level: Option<u8>,
+    /// C dialect of the generated code (C backend).
+    pub c_dialect: CDialect,
+    /// Syscall handling mode.
+    pub syscall_mode: SyscallMode,
+    /// Capability policy restricting Linux-mode syscalls (unrestricted if unset).
+    pub syscall_policy: Option<SyscallPolicy>,
+    /// Bare-metal ECALLs mapped to built-in actions (exit on every ECALL if
+    /// unset).
+    pub baremetal_ecalls: Option<BareMetalConfig>,
+    /// Export functions mode: compiled for calling exported functions rather than running from entry point.
+    pub export_functions: bool,
+    /// Fixed addresses for state and memory (optional).
+    /// When set, state/memory are not passed as arguments but accessed via constant addresses.
+    pub fixed_addresses: Option<FixedAddressConfig>,
+    /// Perf mode: disable instret/CSR reads for benchmarking.
+    pub perf_mode: bool,
+    /// Enable superblock formation (merging fall-through blocks after branches).
+    /// Disable for differential testing to ensure dispatch works at all block boundaries.
+    pub enable_superblock: bool,
+    /// Maximum instructions per emitted block; longer blocks and superblocks
+    /// are split at a block boundary.
+    pub superblock_max_instrs: usize,
+    /// Maximum basic blocks merged into one emitted block.
+    pub superblock_max_blocks: usize,
+    /// Custom CSRs (C backend). Unconfigured CSRs other than the counters
+    /// read and write their `RvState::csrs` slot.
+    pub custom_csrs: Vec<CustomCsr>,
+    /// Guest functions replaced by hooks, by symbol name. Symbols the ELF
+    /// does not define are ignored.
+    pub function_hooks: Vec<FunctionHook>,
+    /// Prefix for every global C symbol (C backend): block functions, the
+    /// dispatch table, `rv_execute_from` and the `RV_*` metadata. Empty
+    /// unless several programs share one library.
+    pub symbol_prefix: String,
+    /// Vector register length in bits: a power of two from 64 to
+    /// [`MAX_VLEN`]. Only used by libraries with vector instructions.
+    pub vlen: u32,
+    /// Compression of embedded segment data (C backend; plain if unset).
+    pub compress_segments: Option<Compression>,
+    /// How LR/SC pairs behave (see [`LrScModel`]).
+    pub lrsc_model: LrScModel,
+    /// `fromhost` loads an HTIF guest may make without `fromhost` changing
+    /// or a new request before it stops with `ExitCause::HtifStall` (0
+    /// disables the watchdog).
+    pub htif_poll_limit: u64,
+    /// Golden trace file (from the golden tracer) whose register checksums
+    /// the library checks at block entries, stopping with
+    /// `ExitCause::GoldenMismatch` where they differ (C backend).
+    pub embedded_golden: Option<PathBuf>,
+    /// Blocks, by start PC, run by the block interpreter instead of being
+    /// compiled (C backend).
+    pub interpret_blocks: BTreeSet<u64>,
+    _marker: PhantomData<X>,
+}
+
+impl<X: Xlen> Default for EmitConfig<X> {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+impl<X: Xlen> EmitConfig<X> {
+    /// Create base config without hot registers (internal use).
+    fn base(num_regs: usize) -> Self {
+        let mut flags = EmitFlags::empty();
+        flags.set_emit_comments(true);
+        flags.set_emit_line_info(true);
+        flags.set_htif_enabled(false);
+        flags.set_htif_verbose(false);
+        flags.set_optimize_ir(true);
+        flags.set_detect_code_writes(true);
+
+        Self {
+            num_regs,
+            hot_regs: Vec::new(),
+            hot_regs_mode: HotRegsMode::default(),
+            backend: Backend::default(),
+            analysis_mode: AnalysisMode::default(),
+            analysis_jobs: 0,
+            dispatch_mode: DispatchMode::default(),
+            address_mode: AddressMode::default(),
+            on_lift_error: LiftErrorMode::default(),
+            instret_mode: InstretMode::Count,
+            suspend_granularity: SuspendGranularity::default(),
+            flags,
+            memory_bits: 32,
+            layout: None,
+            load_bias: None,
+            isa: None,
+            heap_size: None,
+            stack_size: None,
+            mmap_size: None,
+            stack_guard: DEFAULT_STACK_GUARD,
+            tracer_config: TracerConfig::none(),
+            compiler: Compiler::default(),
+            compiler_launcher: CompilerLauncher::default(),
+            target_part_cost: DEFAULT_TARGET_PART_COST,
+            fallback_opt_level: Some(DEFAULT_FALLBACK_OPT_LEVEL),
+            c_dialect: CDialect::default(),
+            syscall_mode: SyscallMode::default(),
+            syscall_policy: None,
+            baremetal_ecalls: None,
+            export_functions: false,
+            fixed_addresses: None,
+            perf_mode: false,
+            enable_superblock: true, // Enabled by default for performance
+            superblock_max_instrs: DEFAULT_SUPERBLOCK_MAX_INSTRS,
+            superblock_max_blocks: DEFAULT_SUPERBLOCK_DEPTH,
+            custom_csrs: Vec::new(),
+            function_hooks: Vec::new(),
+            symbol_prefix: String::new(),
+            vlen: DEFAULT_VLEN,
+            compress_segments: None,
+            lrsc_model: LrScModel::default(),
+            htif_poll_limit: DEFAULT_HTIF_POLL_LIMIT,
+            embedded_golden: None,
+            interpret_blocks: BTreeSet::new(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Create config with specified register count and platform-optimized hot registers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_regs` is not a supported register count.
+    #[must_use]
+    pub fn new(num_regs: usize) -> Self {
+        assert!(num_regs == NUM_REGS_I || num_regs == NUM_REGS_E);
+        let mut config = Self::base(num_regs);
+        config.init_hot_regs(c_config::default_total_slots());
+        config
+    }
+
+    /// Create config with platform-optimized defaults.
+    ///
+    /// This initializes hot registers based on platform-specific total slots
+    /// and the given tracer configuration.
+    #[must_use]
+    pub fn with_defaults(num_regs: usize, total_slots: usize, tracer_config: TracerConfig) -> Self {
+        let mut config = Self::base(num_regs);
+        config.flags.set_optimize_ir(tracer_config.is_none());
+        config.tracer_config = tracer_config;
+        config.init_hot_regs(total_slots);
+        config
+    }
+
+    /// Create config with standard platform defaults.
+    #[must_use]
+    pub fn standard() -> Self {
+        Self::with_defaults(
+            NUM_REGS_I,
+            c_config::default_total_slots(),
+            TracerConfig::none(),
+        )
+    }
+
+    /// Initialize hot register list with the specified number of hot registers.
+    ///
+    /// Only includes registers that exist (< `num_regs`) for E extension support.
+    fn init_hot_regs_count(&mut self, num_hot_regs: usize) {
+        self.hot_regs.clear();
+
+        let mut count = 0;
+        for &reg in &REG_PRIORITY {
+            if count >= num_hot_regs {
+                break;
+            }
+            // Skip registers that don't exist in E extension
+            if (reg as usize) < self.num_regs {
+                self.hot_regs.push(reg);
+                count += 1;
+            }
+        }
+    }
+
+    /// Initialize hot register list from total argument slots (C backend).
+    ///
+    /// For C backend: subtracts fixed slots (state, memory, instret) from total.
+    /// Only includes registers that exist (< `num_regs`) for E extension support.
+    pub fn init_hot_regs(&mut self, total_slots: usize) {
+        let num_hot_regs = c_config::compute_num_hot_regs(
+            total_slots,
+            self.instret_mode,
+            &self.tracer_config,
+            self.fixed_addresses.is_some(),
+        );
+        self.init_hot_regs_count(num_hot_regs);
+    }
+
+    /// Re-initialize hot registers based on the current backend.
+    ///
+    /// For C backend: uses platform-specific argument slots minus fixed slots.
+    /// For x86/ARM64 backends: uses all available GPRs (state/memory use dedicated regs).
+    pub fn reinit_hot_regs_for_backend(&mut self) {
+        match self.backend {
+            Backend::C => {
+                let total_slots = c_config::default_total_slots();
+                self.init_hot_regs(total_slots);
+            }
+            Backend::X86Asm => {
+                // x86 uses dedicated registers for state (rbx) and memory (r15),
+                // so all hot reg slots are available for RISC-V registers
+                self.init_hot_regs_count(x86::HOT_REG_SLOTS);
+            }
+            Backend::ARM64Asm => {
+                // ARM64 uses dedicated registers for state (x19) and memory (x20),
+                // so all hot reg slots are available for RISC-V registers
+                self.init_hot_regs_count(arm64::HOT_REG_SLOTS);
+            }
+        }
+    }
+
+    /// Resize the register file (16 for RVE), keeping the hot register count.
+    ///
+    /// Hot registers that no longer exist are dropped and their slots go to
+    /// the next registers in priority order, so an explicit `hot_regs` list
+    /// survives as far as it can.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_regs` is not a supported register count.
+    pub fn set_num_regs(&mut self, num_regs: usize) {
+        assert!(num_regs == NUM_REGS_I || num_regs == NUM_REGS_E);
+        let slots = self.hot_regs.len();
+        self.num_regs = num_regs;
+        self.hot_regs.retain(|&reg| (reg as usize) < num_regs);
+        for &reg in &REG_PRIORITY {
+            if self.hot_regs.len() >= slots {
+                break;
+            }
+            if (reg as usize) < num_regs && !self.hot_regs.contains(&reg) {
+                self.hot_regs.push(reg);
+            }
+        }
+    }
+
+    /// Refill the hot register slots with the most accessed registers.
+    ///
+    /// `accesses` is indexed by register number. Registers that are never
+    /// accessed, and ties, fall back to `REG_PRIORITY` order. The number of
+    /// hot registers is unchanged.
+    pub fn rank_hot_regs(&mut self, accesses: &RegAccesses) {
+        let slots = self.hot_regs.len();
+        let mut ranked: Vec<u8> = REG_PRIORITY
+            .iter()
+            .copied()
+            .filter(|&reg| self.is_valid_reg(reg))
+            .collect();
+        // Stable sort keeps priority order among equal counts
+        ranked.sort_by_key(|&reg| std::cmp::Reverse(accesses[reg as usize]));
+        ranked.truncate(slots);
+        self.hot_regs = ranked;
+    }
+
+    /// Hot registers for a function with static register `accesses`
+    /// (`HotRegsMode::PerFunction`).
+    ///
+    /// Fills as many slots as `hot_regs` has with the most accessed
+    /// registers. Ties, and slots the function leaves unused, go to
+    /// registers of `hot_regs`, which cost no glue at transfers. Registers
+    /// shared with `hot_regs` keep their slot, so their argument position
+    /// is the same on both sides of a transfer.
+    #[must_use]
+    pub fn function_hot_regs(&self, accesses: &RegAccesses) -> Vec<u8> {
+        let slots = self.hot_regs.len();
+        let mut ranked: Vec<u8> = REG_PRIORITY
+            .iter()
+            .copied()
+            .filter(|&reg| self.is_valid_reg(reg))
+            .collect();
+        ranked.sort_by_key(|&reg| {
+            (
+                std::cmp::Reverse(accesses[reg as usize]),
+                !self.hot_regs.contains(&reg),
+            )
+        });
+        ranked.truncate(slots);
+        let mut extra = ranked
+            .iter()
+            .copied()
+            .filter(|reg| !self.hot_regs.contains(reg));
+        self.hot_regs
+            .iter()
+            .filter_map(|&reg| {
+                if ranked.contains(&reg) {
+                    Some(reg)
+                } else {
+                    extra.next()
+                }
+            })
+            .collect()
+    }
+
+    /// Check if register index is valid.
+    #[must_use]
+    pub const fn is_valid_reg(&self, reg: u8) -> bool {
+        (reg as usize) < self.num_regs
+    }
+
+    /// Check if register is in hot list.
+    #[must_use]
+    pub fn is_hot_reg(&self, reg: u8) -> bool {
+        reg != 0 && self.hot_regs.contains(&reg)
+    }
+
+    /// Number of hot registers.
+    #[must_use]
+    pub const fn num_hot_regs(&self) -> usize {
+        self.hot_regs.len()
+    }
+
+    /// Check if tracing is enabled.
+    #[must_use]
+    pub const fn has_tracing(&self) -> bool {
+        !self.tracer_config.is_none()
+    }
+
+    /// Check if emit comments is enabled.
+    #[must_use]
+    pub const fn emit_comments(&self) -> bool {
+        self.flags.emit_comments()
+    }
+
+    /// Check if emit line info is enabled.
+    #[must_use]
+    pub const fn emit_line_info(&self) -> bool {
+        self.flags.emit_line_info()
+    }
+
+    /// Check if identical-block deduplication is enabled.
+    #[must_use]
+    pub const fn dedup_blocks(&self) -> bool {
+        self.flags.dedup_blocks()
+    }
+
+    /// Check if instructions that would trap fail the lift.
+    #[must_use]
+    pub const fn strict_decode(&self) -> bool {
+        self.flags.strict_decode()
+    }
+
+    /// Check if ARM64 AMOs use LSE instructions instead of exclusive loops.
+    #[must_use]
+    pub const fn arm64_use_lse(&self) -> bool {
+        self.flags.arm64_use_lse()
+    }
+
+    /// Check if the flat dispatch table holds 32-bit offsets from the table
+    /// instead of function pointers, so it needs no dynamic relocations (C
+    /// backend; the asm backends' jump tables always hold offsets).
+    #[must_use]
+    pub const fn dispatch_table_relative(&self) -> bool {
+        self.flags.dispatch_table_relative()
+    }
+
+    /// Check if indirect jumps prefetch their dispatch table slot as soon
+    /// as the target register is final, when that is at least
+    /// [`DISPATCH_PREFETCH_DISTANCE`] instructions before the jump (flat
+    /// dispatch, C and ARM64 backends). Off by default.
+    #[must_use]
+    pub const fn prefetch_dispatch(&self) -> bool {
+        self.flags.prefetch_dispatch()
+    }
+
+    /// Check if the dispatch table is placed in its own 2 MiB aligned
+    /// section so the host can back it with a huge page, and the shared
+    /// object is linked with 2 MiB segment alignment (flat dispatch, C and
+    /// ARM64 backends). The table is 64-byte aligned either way. Off by
+    /// default.
+    #[must_use]
+    pub const fn dispatch_table_hugepages(&self) -> bool {
+        self.flags.dispatch_table_hugepages()
+    }
+
+    /// Check if Linux `clock_gettime` is served inline from the `time` CSR
+    /// (one nanosecond per retired instruction) instead of the host clock
+    /// (RV64 only). Off by default.
+    #[must_use]
+    pub const fn deterministic_clock(&self) -> bool {
+        self.flags.deterministic_clock()
+    }
+
+    /// Check if blocks whose C fails to compile, or whose IR fails
+    /// verification, are retried in the block interpreter (C backend). Off
+    /// by default.
+    #[must_use]
+    pub const fn interpret_failed_blocks(&self) -> bool {
+        self.flags.interpret_failed_blocks()
+    }
+
+    /// Linker flags the dispatch table placement needs: 2 MiB segment
+    /// alignment with `dispatch_table_hugepages`, none otherwise.
+    #[must_use]
+    pub const fn dispatch_table_ldflags(&self) -> &'static [&'static str] {
+        if self.dispatch_table_hugepages() {
+            &[
+                "-Wl,-z,max-page-size=0x200000",
+                "-Wl,-z,common-page-size=0x200000",
+            ]
+        } else {
+            &[]
+        }
+    }
+
+    /// Check if guest memory is mapped from a segment image instead of
+    /// copied from the ELF.
+    #[must_use]
+    pub const fn lazy_segment_init(&self) -> bool {
+        self.flags.lazy_segment_init()
+    }
+
+    /// Check if the guest's `memcpy`/`memset`/`memcmp` run as native
+    /// helpers instead of recompiled guest code (C backend).
+    #[must_use]
+    pub const fn native_mem_intrinsics(&self) -> bool {
+        self.flags.native_mem_intrinsics()
+    }
+
+    /// Check if the program links the syscall runtime (`syscalls.c`):
+    /// Linux syscalls, or bare-metal ECALLs mapped to runtime actions.
+    #[must_use]
+    pub fn syscall_runtime(&self) -> bool {
+        self.syscall_mode
+            .links_runtime(self.baremetal_ecalls.as_ref())
+    }
+
+    /// Check if generated code maps back to guest PCs through
+    /// `guest_pc.map` line directives (C backend).
+    #[must_use]
+    pub const fn emit_guest_pc_map(&self) -> bool {
+        self.flags.emit_guest_pc_map()
+    }
+
+    /// Check if dead register writes are removed from lifted blocks (C
+    /// backend). On by default unless tracing, and ignored when tracing:
+    /// trace hooks observe every register write. Also ignored with
+    /// `InstretMode::PerInstruction` or a guest PC map, where a debugger can
+    /// stop between instructions.
+    #[must_use]
+    pub const fn optimize_ir(&self) -> bool {
+        self.flags.optimize_ir()
+    }
+
+    /// Check if guest stores into recompiled code trap with
+    /// `RV_CODE_WRITE_TRAP` instead of leaving the stale recompiled code
+    /// running (C backend). On by default, and ignored with
+    /// [`AddressMode::Unchecked`], which skips every address check.
+    #[must_use]
+    pub const fn detect_code_writes(&self) -> bool {
+        self.flags.detect_code_writes() && !matches!(self.address_mode, AddressMode::Unchecked)
+    }
+
+    /// Check if backward jumps and branches stop when the host sets
+    /// `RvState::cancel_requested` (C backend). Off by default.
+    #[must_use]
+    pub const fn check_cancel(&self) -> bool {
+        self.flags.check_cancel()
+    }
+
+    /// Check if trap, exit and suspension paths are emitted as calls to
+    /// shared `cold` helpers instead of inline (C backend, clang dialect).
+    /// Off by default.
+    #[must_use]
+    pub const fn outline_cold_paths(&self) -> bool {
+        self.flags.outline_cold_paths()
+    }
+
+    /// Check if dynamic jumps test their dispatch lookup and report the
+    /// jump site, target and source register on a miss (C backend). Off
+    /// by default: a miss then enters `rv_trap`, which knows none of them.
+    #[must_use]
+    pub const fn report_jump_sites(&self) -> bool {
+        self.flags.report_jump_sites()
+    }
+
+    /// Check if the lifted IR is checked for broken invariants in release
+    /// builds too. Debug builds always check it. Off by default.
+    #[must_use]
+    pub const fn verify_ir(&self) -> bool {
+        self.flags.verify_ir()
+    }
+
+    /// Check if the build also produces `lib<name>.a` and `rv_embed.h`
+    /// for hosts that link the program instead of loading the shared
+    /// library (C backend). Off by default.
+    #[must_use]
+    pub const fn static_archive(&self) -> bool {
+        self.flags.static_archive()
+    }
+
+    /// Numbers of the custom CSRs in [`CsrMode::Hook`] mode, sorted and
+    /// deduplicated. Numbers wider than 12 bits are ignored.
+    #[must_use]
+    pub fn hook_csrs(&self) -> Vec<u16> {
+        let mut csrs: Vec<u16> = self
+            .custom_csrs
+            .iter()
+            .filter(|csr| csr.mode == CsrMode::Hook && csr.number <= 0xFFF)
+            .map(|csr| csr.number)
+            .collect();
+        csrs.sort_unstable();
+        csrs.dedup();
+        csrs
+    }
+
+    /// Heap end enforced by `brk`/`mmap`, if the layout caps the heap.
+    #[must_use]
+    pub const fn heap_limit(&self) -> Option<u64> {
+        match self.layout {
+            Some(layout) => {
+                let regions = layout.spec().regions;
+                if regions.guard.limits_heap() {
+                    Some(regions.heap.end)
+                } else {
+                    None
+                }
+            }
+            None => None,
+        }
+    }
+
+    /// Check if HTIF is enabled.
+    #[must_use]
+    pub const fn htif_enabled(&self) -> bool {
+        self.flags.htif_enabled()
+    }
+
+    /// Check if HTIF verbose is enabled.
+    #[must_use]
+    pub const fn htif_verbose(&self) -> bool {
+        self.flags.htif_verbose()
+    }
+
+    /// Block size limits for the CFG transforms.
+    #[must_use]
+    pub const fn block_limits(&self) -> BlockLimits {
+        BlockLimits {
+            max_instrs: self.superblock_max_instrs,
+            max_blocks: self.superblock_max_blocks,
+        }
+    }
+
+    /// Check if fixed addresses are enabled.
+    #[must_use]
+    pub const fn has_fixed_addresses(&self) -> bool {
+        self.fixed_addresses.is_some()
+    }
+
+    /// Bytes per register based on XLEN.
+    #[must_use]
+    pub const fn reg_bytes(&self) -> usize {
+        X::REG_BYTES
+    }
+}
+
+#[cfg(test)]
+mod tests;
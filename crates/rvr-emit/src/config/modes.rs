@@ -0,0 +1,327 @@
+//! Emit modes: instret, syscalls, CSRs, address checks, backends, dispatch
+//! and segment compression.
+
+use rvr_isa::syscalls::BareMetalConfig;
+
+/// Instruction retirement counting mode.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InstretMode {
+    /// No instruction counting.
+    Off,
+    /// Count instructions but don't suspend.
+    #[default]
+    Count,
+    /// Count instructions and suspend at limit (checked at block boundaries).
+    ///
+    /// The C backend checks the limit on entry to every emitted block, and
+    /// its code only loops by re-entering a block, so a run stops at most one
+    /// block (a superblock plus any inlined branch target) past the limit.
+    /// The x86 and ARM64 backends check at the end of every basic block.
+    /// `EmitConfig::suspend_granularity` adds checks on back-edges.
+    Suspend,
+    /// Count instructions and suspend at limit (checked after every instruction).
+    PerInstruction,
+}
+
+impl InstretMode {
+    #[must_use]
+    pub fn counts(&self) -> bool {
+        *self != Self::Off
+    }
+
+    #[must_use]
+    pub const fn suspends(&self) -> bool {
+        matches!(self, Self::Suspend | Self::PerInstruction)
+    }
+
+    /// True if suspension check is emitted after every instruction.
+    #[must_use]
+    pub fn per_instruction(&self) -> bool {
+        *self == Self::PerInstruction
+    }
+
+    /// Convert to C constant value for `RV_INSTRET_MODE` export.
+    #[must_use]
+    pub const fn as_c_mode(&self) -> u32 {
+        match self {
+            Self::Off => 0,
+            Self::Count => 1,
+            Self::Suspend => 2,
+            Self::PerInstruction => 3,
+        }
+    }
+}
+
+/// Where `InstretMode::Suspend` checks the instret limit.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SuspendGranularity {
+    /// Check on entry to every block.
+    ///
+    /// The C backend checks at block function entry and before each tail
+    /// call; the x86 and ARM64 backends check at the end of every basic
+    /// block of their linear instruction stream.
+    #[default]
+    BlockEntry,
+    /// Also check on loop back-edges (a static target at or before the
+    /// branch), so a run stops at most the longest acyclic path of a
+    /// superblock past the limit.
+    ///
+    /// Adds checks where a back-edge stays inside one C function: a side
+    /// exit or a taken branch whose target block is inlined. The assembly
+    /// backends gain nothing, as every back-edge already ends a block.
+    BackEdges,
+}
+
+impl SuspendGranularity {
+    /// True if back-edges get their own suspension check.
+    #[must_use]
+    pub fn back_edges(self) -> bool {
+        self == Self::BackEdges
+    }
+}
+
+/// Syscall handling mode for ECALL instructions.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SyscallMode {
+    /// Bare-metal syscalls (exit, or `EmitConfig::baremetal_ecalls`).
+    #[default]
+    BareMetal,
+    /// Linux-style syscalls (brk/mmap/read/write, etc).
+    Linux,
+}
+
+impl SyscallMode {
+    /// Check if programs in this mode with bare-metal `ecalls` link the
+    /// syscall runtime (`syscalls.c`).
+    #[must_use]
+    pub fn links_runtime(self, ecalls: Option<&BareMetalConfig>) -> bool {
+        self == Self::Linux || ecalls.is_some_and(BareMetalConfig::needs_runtime)
+    }
+}
+
+/// How a custom CSR in `EmitConfig::custom_csrs` is backed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CsrMode {
+    /// Backed by its slot in `RvState::csrs`.
+    #[default]
+    Storage,
+    /// Reads and writes call `rv_csr_read`/`rv_csr_write`, which forward to
+    /// the host's CSR hooks and fall back to the `RvState::csrs` slot when
+    /// none are installed.
+    Hook,
+}
+
+/// A CSR outside the standard counters that guests use to talk to the host.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CustomCsr {
+    /// CSR number (12 bits).
+    pub number: u16,
+    /// How reads and writes are served.
+    pub mode: CsrMode,
+}
+
+impl CustomCsr {
+    /// Create a custom CSR.
+    #[must_use]
+    pub const fn new(number: u16, mode: CsrMode) -> Self {
+        Self { number, mode }
+    }
+
+    /// CSR backed by its `RvState::csrs` slot.
+    #[must_use]
+    pub const fn storage(number: u16) -> Self {
+        Self::new(number, CsrMode::Storage)
+    }
+
+    /// CSR whose reads and writes call the host's CSR hooks.
+    #[must_use]
+    pub const fn hook(number: u16) -> Self {
+        Self::new(number, CsrMode::Hook)
+    }
+}
+
+/// Address translation mode for memory accesses.
+///
+/// Controls how guest virtual addresses are translated to physical addresses
+/// in the emulator's memory buffer.
+///
+/// # Address Translation Semantics
+///
+/// | Mode      | Mask Address | Bounds Check | Trap on OOB |
+/// |-----------|--------------|--------------|-------------|
+/// | Unchecked | No           | No           | No (guards) |
+/// | Wrap      | Yes          | No           | No          |
+/// | Bounds    | Yes          | Yes          | Yes         |
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AddressMode {
+    /// Assume valid + passthrough. Guard pages catch OOB at runtime.
+    Unchecked,
+    /// Mask addresses to memory size (addresses wrap at boundary).
+    /// Matches RISC-V sv39/sv48 address translation behavior.
+    ///
+    /// One `and` per access and no branch, so it stays memory safe at close
+    /// to Unchecked speed. Unlike Bounds, an out-of-range access silently
+    /// aliases into guest memory instead of trapping.
+    #[default]
+    Wrap,
+    /// Bounds check + trap + mask. Explicit trap on invalid addresses.
+    Bounds,
+}
+
+impl AddressMode {
+    /// Lowercase mode name, matching the CLI spelling.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Unchecked => "unchecked",
+            Self::Wrap => "wrap",
+            Self::Bounds => "bounds",
+        }
+    }
+
+    /// Whether addresses should be masked to memory size.
+    ///
+    /// True for Wrap and Bounds modes. C emitters use `& MASK`, x86 uses `and`.
+    #[must_use]
+    pub const fn needs_mask(self) -> bool {
+        matches!(self, Self::Wrap | Self::Bounds)
+    }
+
+    /// Whether addresses should be bounds-checked before access.
+    ///
+    /// True for Bounds mode only. C emitters use `if (out_of_bounds) trap()`,
+    /// x86 uses `cmp; jbe ok; jmp trap; ok:`.
+    #[must_use]
+    pub fn needs_bounds_check(self) -> bool {
+        self == Self::Bounds
+    }
+
+    /// Whether addresses are assumed valid (for optimizer hints).
+    ///
+    /// True for Unchecked mode. C emitters emit an assumption the optimizer
+    /// can use (see `Compiler::assume`).
+    #[must_use]
+    pub fn assumes_valid(self) -> bool {
+        self == Self::Unchecked
+    }
+}
+
+/// What to do when a block fails to lift.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LiftErrorMode {
+    /// Fail the whole compile on the first lift error.
+    #[default]
+    Abort,
+    /// Replace the failing block with a trap stub and keep compiling.
+    /// The stub raises a quarantined-block fault if it is ever executed.
+    Quarantine,
+}
+
+/// Code generation backend.
+///
+/// Controls the output format of the recompiler.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Backend {
+    /// Emit C code, compile with clang/gcc.
+    #[default]
+    C,
+    /// Emit x86-64 assembly, compile with gcc/as.
+    X86Asm,
+    /// Emit ARM64 assembly, compile with gcc/as.
+    ARM64Asm,
+}
+
+/// Dispatch table layout for indirect jumps (C backend).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DispatchMode {
+    /// One flat table with a slot for every 2-byte PC in the text range.
+    /// Constant-time lookup, but one dynamic relocation per slot.
+    #[default]
+    Flat,
+    /// One table per function holding only its block entries, plus a sorted
+    /// function table. Lookup is two binary searches; relocations scale with
+    /// the number of blocks rather than the size of the text section.
+    PerFunction,
+}
+
+/// Where hot register sets are chosen (C backend).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HotRegsMode {
+    /// One set, `EmitConfig::hot_regs`, for the whole program.
+    #[default]
+    Global,
+    /// Each function gets the registers it accesses most, in as many slots
+    /// as `EmitConfig::hot_regs`. Blocks still enter each other through
+    /// `hot_regs`; transfers between functions with different sets spill and
+    /// reload the registers that differ.
+    PerFunction,
+}
+
+/// Default [`Compression::Lz4`] threshold: smaller segments stay plain.
+pub const DEFAULT_COMPRESS_MIN_SIZE: usize = 64 * 1024;
+
+/// Compression of the segment data embedded in the library (C backend).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    /// LZ4 blocks for segments of at least `min_size` bytes, decoded into
+    /// guest memory by `rv_init_memory`. Smaller segments stay plain arrays.
+    Lz4 { min_size: usize },
+}
+
+impl Compression {
+    /// LZ4 with the default threshold.
+    #[must_use]
+    pub const fn lz4() -> Self {
+        Self::Lz4 {
+            min_size: DEFAULT_COMPRESS_MIN_SIZE,
+        }
+    }
+
+    /// Smallest segment that is compressed.
+    #[must_use]
+    pub const fn min_size(self) -> usize {
+        match self {
+            Self::Lz4 { min_size } => min_size,
+        }
+    }
+}
+
+/// Analysis mode for the compilation pipeline.
+///
+/// Controls how much CFG analysis is performed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AnalysisMode {
+    /// Full CFG analysis: block merging, absorption, optimizations.
+    /// Best for C backend where LLVM benefits from larger functions.
+    #[default]
+    FullCfg,
+    /// Basic mode: decode instructions, mark jump targets, no block merging.
+    /// Faster compilation, sufficient for x86 backend.
+    Basic,
+}
+
+/// Fixed address configuration for state and memory.
+///
+/// When enabled, state and memory are accessed via compile-time constant addresses
+/// instead of being passed as function arguments. This frees up argument registers
+/// for hot values but requires the runtime to map memory at these exact addresses.
+///
+/// Default addresses are chosen to minimize collision with typical ASLR mappings:
+/// - Above 4GB mark (avoid 32-bit conflicts)
+/// - Below typical mmap regions (~0x7f... on Linux)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FixedAddressConfig {
+    /// Fixed address for `RvState` struct.
+    pub state_addr: u64,
+    /// Fixed address for guest memory base.
+    pub memory_addr: u64,
+}
+
+impl Default for FixedAddressConfig {
+    fn default() -> Self {
+        Self {
+            state_addr: 0x10_0000_0000,  // 64 GB
+            memory_addr: 0x20_0000_0000, // 128 GB
+        }
+    }
+}
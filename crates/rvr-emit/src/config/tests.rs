@@ -0,0 +1,250 @@
+use super::*;
+use crate::hooks::HookKind;
+use rvr_ir::{Rv32, Rv64};
+
+#[test]
+fn test_default_config() {
+    let config = EmitConfig::<Rv64>::default();
+    assert_eq!(config.num_regs, 32);
+    // default() now initializes hot registers (same as standard())
+    assert!(!config.hot_regs.is_empty());
+    assert!(config.instret_mode.counts());
+    assert_eq!(config.on_lift_error, LiftErrorMode::Abort);
+}
+
+#[test]
+fn test_standard_config() {
+    let config = EmitConfig::<Rv64>::standard();
+    assert_eq!(config.num_regs, 32);
+    assert!(!config.hot_regs.is_empty());
+}
+
+#[test]
+fn test_hot_regs_init() {
+    let mut config = EmitConfig::<Rv64>::new(32);
+    config.instret_mode = InstretMode::Count;
+    config.init_hot_regs(10);
+    // 10 slots - 3 (state + memory + instret) = 7 hot regs
+    assert_eq!(config.hot_regs.len(), 7);
+    // First should be ra (1)
+    assert_eq!(config.hot_regs[0], 1);
+    // Second should be sp (2)
+    assert_eq!(config.hot_regs[1], 2);
+}
+
+#[test]
+fn test_hot_regs_no_instret() {
+    let mut config = EmitConfig::<Rv64>::new(32);
+    config.instret_mode = InstretMode::Off;
+    config.init_hot_regs(10);
+    // 10 slots - 2 (state + memory) = 8 hot regs
+    assert_eq!(config.hot_regs.len(), 8);
+}
+
+#[test]
+fn test_is_hot_reg() {
+    let mut config = EmitConfig::<Rv64>::new(32);
+    config.hot_regs = vec![1, 2, 10];
+    assert!(config.is_hot_reg(1));
+    assert!(config.is_hot_reg(2));
+    assert!(config.is_hot_reg(10));
+    assert!(!config.is_hot_reg(0)); // x0 is never hot
+    assert!(!config.is_hot_reg(3));
+}
+
+#[test]
+fn test_set_num_regs_refills_hot_regs() {
+    let mut config = EmitConfig::<Rv32>::new(32);
+    config.hot_regs = vec![1, 2, 10, 16, 28];
+    config.set_num_regs(NUM_REGS_E);
+    assert_eq!(config.num_regs, 16);
+    // x16 and x28 are gone; a1 and a2 are next in priority
+    assert_eq!(config.hot_regs, vec![1, 2, 10, 11, 12]);
+
+    let mut config = EmitConfig::<Rv32>::new(32);
+    let slots = config.num_hot_regs();
+    config.set_num_regs(NUM_REGS_E);
+    assert_eq!(config.num_hot_regs(), slots);
+    assert!(config.hot_regs.iter().all(|&reg| config.is_valid_reg(reg)));
+}
+
+#[test]
+fn test_rank_hot_regs() {
+    let mut config = EmitConfig::<Rv64>::new(32);
+    config.hot_regs = vec![1, 2, 10, 11];
+    let mut accesses = [0; 32];
+    accesses[20] = 50; // s4
+    accesses[10] = 40; // a0
+    accesses[0] = 100; // x0 is never hot
+    config.rank_hot_regs(&accesses);
+    // Unaccessed registers fill the rest in priority order
+    assert_eq!(config.hot_regs, vec![20, 10, 1, 2]);
+
+    let mut config = EmitConfig::<Rv32>::new(32);
+    config.set_num_regs(NUM_REGS_E);
+    config.hot_regs = vec![1, 2];
+    accesses[20] = 0;
+    accesses[28] = 90; // t3 does not exist in RVE
+    config.rank_hot_regs(&accesses);
+    assert_eq!(config.hot_regs, vec![10, 1]);
+}
+
+#[test]
+fn test_function_hot_regs() {
+    let mut config = EmitConfig::<Rv64>::new(32);
+    config.hot_regs = vec![1, 2, 10, 11];
+    let mut accesses = [0; 32];
+    accesses[11] = 30; // a1
+    accesses[5] = 20; // t0
+    accesses[2] = 20; // sp
+    // Shared registers keep their slots and t0 takes a0's; ra wins the
+    // last slot over the equally unused t1
+    assert_eq!(config.function_hot_regs(&accesses), vec![1, 2, 5, 11]);
+    // Slots the function leaves free keep the global registers
+    assert_eq!(config.function_hot_regs(&[0; 32]), config.hot_regs);
+}
+
+type Change = fn(&mut EmitConfig<Rv64>);
+
+/// One change per fingerprinted field.
+fn fingerprint_changes() -> [(&'static str, Change); 41] {
+    [
+        ("num_regs", |c| c.num_regs = NUM_REGS_E),
+        ("hot_regs", |c| c.hot_regs.clear()),
+        ("hot_regs_mode", |c| {
+            c.hot_regs_mode = HotRegsMode::PerFunction;
+        }),
+        ("backend", |c| c.backend = Backend::X86Asm),
+        ("analysis_mode", |c| c.analysis_mode = AnalysisMode::Basic),
+        ("dispatch_mode", |c| {
+            c.dispatch_mode = DispatchMode::PerFunction;
+        }),
+        ("address_mode", |c| c.address_mode = AddressMode::Bounds),
+        ("on_lift_error", |c| {
+            c.on_lift_error = LiftErrorMode::Quarantine;
+        }),
+        ("instret_mode", |c| c.instret_mode = InstretMode::Off),
+        ("suspend_granularity", |c| {
+            c.suspend_granularity = SuspendGranularity::BackEdges;
+        }),
+        ("flags", |c| c.flags.set_dedup_blocks(true)),
+        ("memory_bits", |c| c.memory_bits = 30),
+        ("layout", |c| c.layout = Some(LayoutProfile::Baremetal)),
+        ("load_bias", |c| c.load_bias = Some(0x1_0000)),
+        ("isa", |c| c.isa = Some("rv64imac".into())),
+        ("heap_size", |c| c.heap_size = Some(1 << 20)),
+        ("stack_size", |c| c.stack_size = Some(1 << 20)),
+        ("mmap_size", |c| c.mmap_size = Some(1 << 20)),
+        ("stack_guard", |c| c.stack_guard = 0),
+        ("tracer_config", |c| {
+            c.tracer_config = TracerConfig::builtin(crate::c::TracerKind::Stats);
+        }),
+        ("compiler", |c| c.compiler = Compiler::gcc()),
+        ("fallback_opt_level", |c| c.fallback_opt_level = None),
+        ("c_dialect", |c| c.c_dialect = CDialect::Portable),
+        ("syscall_mode", |c| c.syscall_mode = SyscallMode::Linux),
+        ("syscall_policy", |c| {
+            c.syscall_policy = Some(SyscallPolicy::new());
+        }),
+        ("baremetal_ecalls", |c| {
+            c.baremetal_ecalls = Some(BareMetalConfig::new());
+        }),
+        ("export_functions", |c| c.export_functions = true),
+        ("fixed_addresses", |c| {
+            c.fixed_addresses = Some(FixedAddressConfig::default());
+        }),
+        ("perf_mode", |c| c.perf_mode = true),
+        ("enable_superblock", |c| c.enable_superblock = false),
+        ("superblock_max_instrs", |c| c.superblock_max_instrs = 7),
+        ("superblock_max_blocks", |c| c.superblock_max_blocks = 7),
+        ("custom_csrs", |c| {
+            c.custom_csrs = vec![CustomCsr::hook(0x800)];
+        }),
+        ("function_hooks", |c| {
+            c.function_hooks = vec![FunctionHook::new("checksum", HookKind::Nop)];
+        }),
+        ("symbol_prefix", |c| c.symbol_prefix = "p0_".into()),
+        ("vlen", |c| c.vlen = 256),
+        ("compress_segments", |c| {
+            c.compress_segments = Some(Compression::lz4());
+        }),
+        ("lrsc_model", |c| c.lrsc_model = LrScModel::AlwaysSucceed),
+        ("htif_poll_limit", |c| c.htif_poll_limit = 0),
+        ("embedded_golden", |c| {
+            c.embedded_golden = Some("golden.bin".into());
+        }),
+        ("interpret_blocks", |c| c.interpret_blocks = [0x1000].into()),
+    ]
+}
+
+#[test]
+fn test_fingerprint_covers_every_field() {
+    let base = EmitConfig::<Rv64>::default();
+    // A new field fails to compile here until it gets a change below
+    let EmitConfig {
+        num_regs: _,
+        hot_regs: _,
+        hot_regs_mode: _,
+        backend: _,
+        analysis_mode: _,
+        analysis_jobs: _,
+        dispatch_mode: _,
+        address_mode: _,
+        on_lift_error: _,
+        instret_mode: _,
+        suspend_granularity: _,
+        flags: _,
+        memory_bits: _,
+        layout: _,
+        load_bias: _,
+        isa: _,
+        heap_size: _,
+        stack_size: _,
+        mmap_size: _,
+        stack_guard: _,
+        tracer_config: _,
+        compiler: _,
+        compiler_launcher: _,
+        target_part_cost: _,
+        fallback_opt_level: _,
+        c_dialect: _,
+        syscall_mode: _,
+        syscall_policy: _,
+        baremetal_ecalls: _,
+        export_functions: _,
+        fixed_addresses: _,
+        perf_mode: _,
+        enable_superblock: _,
+        superblock_max_instrs: _,
+        superblock_max_blocks: _,
+        custom_csrs: _,
+        function_hooks: _,
+        symbol_prefix: _,
+        vlen: _,
+        compress_segments: _,
+        lrsc_model: _,
+        htif_poll_limit: _,
+        embedded_golden: _,
+        interpret_blocks: _,
+        _marker: _,
+    } = &base;
+    for (field, change) in fingerprint_changes() {
+        let mut config = base.clone();
+        change(&mut config);
+        assert_ne!(config.fingerprint(), base.fingerprint(), "{field}");
+    }
+}
+
+#[test]
+fn test_fingerprint_ignores_build_settings() {
+    let base = EmitConfig::<Rv64>::default();
+    let mut config = base.clone();
+    config.analysis_jobs = 8;
+    config.compiler_launcher = CompilerLauncher::None;
+    config.target_part_cost = 1;
+    assert_eq!(config.fingerprint(), base.fingerprint());
+    assert_ne!(
+        EmitConfig::<Rv32>::default().fingerprint(),
+        base.fingerprint()
+    );
+}
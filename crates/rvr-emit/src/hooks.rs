@@ -0,0 +1,89 @@
+//! Guest function hooks (`EmitConfig::function_hooks`).
+//!
+//! A hooked guest function is found by symbol name and its entry
+//! instruction is lifted as the hook instead: a call into the host
+//! ([`HookKind::HostCall`]), a guest stop ([`HookKind::Replace`]) or nothing
+//! ([`HookKind::Nop`]), followed by a return through `ra` where the guest
+//! keeps running. The CFG does not descend into the body past the entry.
+
+use crate::c::sanitize_c_identifier;
+
+/// Prefix of the generated C function a [`HookKind::HostCall`] entry calls.
+pub const HOOK_FN_PREFIX: &str = "rv_hook_";
+
+/// What a hooked guest function does instead of its body.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HookKind {
+    /// Call the host's hook for the symbol with the argument registers
+    /// a0..a5, which it may change, then return to `ra` (C backend).
+    HostCall,
+    /// Stop the guest with this exit code.
+    Replace(u8),
+    /// Return to `ra` at once.
+    Nop,
+}
+
+/// A guest function, by symbol name, replaced by a hook.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FunctionHook {
+    /// Guest symbol name.
+    pub symbol: String,
+    /// What the function does instead.
+    pub kind: HookKind,
+}
+
+impl FunctionHook {
+    /// Create a hook for `symbol`.
+    #[must_use]
+    pub fn new(symbol: impl Into<String>, kind: HookKind) -> Self {
+        Self {
+            symbol: symbol.into(),
+            kind,
+        }
+    }
+
+    /// Name of the generated C function a host-call entry calls,
+    /// `rv_hook_<symbol>` with non-identifier characters replaced by `_`.
+    #[must_use]
+    pub fn fn_name(&self) -> String {
+        format!("{HOOK_FN_PREFIX}{}", sanitize_c_identifier(&self.symbol))
+    }
+
+    /// Check if the symbol can be passed to the host as a C string literal:
+    /// printable ASCII without quotes or backslashes.
+    #[must_use]
+    pub fn has_literal_symbol(&self) -> bool {
+        !self.symbol.is_empty()
+            && self
+                .symbol
+                .bytes()
+                .all(|b| b.is_ascii_graphic() && b != b'"' && b != b'\\')
+    }
+}
+
+/// Check if the C function `name` is a function hook, which flushes the
+/// hot registers before the call and reloads them after.
+#[must_use]
+pub fn is_hook_fn(name: &str) -> bool {
+    name.starts_with(HOOK_FN_PREFIX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fn_name() {
+        let hook = FunctionHook::new("sha256.compress", HookKind::HostCall);
+        assert_eq!(hook.fn_name(), "rv_hook_sha256_compress");
+        assert!(is_hook_fn(&hook.fn_name()));
+        assert!(!is_hook_fn("rv_memcpy"));
+    }
+
+    #[test]
+    fn test_literal_symbol() {
+        assert!(FunctionHook::new("_ZN3foo3barE", HookKind::Nop).has_literal_symbol());
+        assert!(!FunctionHook::new("a\"b", HookKind::Nop).has_literal_symbol());
+        assert!(!FunctionHook::new("", HookKind::Nop).has_literal_symbol());
+    }
+}
@@ -6,11 +6,23 @@
 /// HTIF tohost address - writes here signal exit or syscall.
 pub const TOHOST_ADDR: u64 = 0x8000_1000;
 
-/// HTIF fromhost address - used for syscall acknowledgment.
-pub const FROMHOST_ADDR: u64 = 0x8000_1008;
+/// HTIF fromhost address - set to 1 when a syscall completes.
+///
+/// riscv-tests place `tohost` and `fromhost` in `.tohost` with `.align 6`,
+/// so `fromhost` is the next 64-byte line.
+pub const FROMHOST_ADDR: u64 = TOHOST_ADDR + 0x40;
+
+/// HTIF syscall number for read.
+pub const SYS_READ: u64 = 63;
 
 /// HTIF syscall number for write.
 pub const SYS_WRITE: u64 = 64;
 
+/// HTIF syscall number for fstat.
+pub const SYS_FSTAT: u64 = 80;
+
+/// HTIF syscall number for exit.
+pub const SYS_EXIT: u64 = 93;
+
 /// HTIF file descriptor for stdout.
 pub const STDOUT_FD: u64 = 1;
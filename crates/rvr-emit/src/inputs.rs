@@ -1,6 +1,14 @@
 //! Derived inputs for emission (computed from the ELF/CFG pipeline).
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::sync::Arc;
+
+use rvr_ir::SyntheticBlockInfo;
+
+use crate::AddressMode;
+use crate::c::{GoldenTrace, GuestPcLines};
+use crate::hooks::FunctionHook;
+use crate::memory_layout::{AddrRange, MemoryLayout};
 
 /// Inputs derived from the program/CFG, not from user configuration.
 #[derive(Clone, Debug, Default)]
@@ -13,10 +21,44 @@ pub struct EmitInputs {
     pub pc_end: u64,
     /// Valid block start addresses.
     pub valid_addresses: HashSet<u64>,
+    /// Recompiled code as sorted, disjoint `[start, end)` ranges; stores
+    /// into them trap when `detect_code_writes` is set.
+    pub code_ranges: Vec<(u64, u64)>,
+    /// Whether the program has vector instructions, which call the vector
+    /// runtime (C backend).
+    pub vector: bool,
     /// Absorbed block mapping: `absorbed_pc` -> `merged_block_start`.
     pub absorbed_to_merged: HashMap<u64, u64>,
+    /// Function membership: `block_start` -> `function_entry`.
+    pub block_to_function: HashMap<u64, u64>,
+    /// Hot registers of functions whose set differs from
+    /// `EmitConfig::hot_regs` (`HotRegsMode::PerFunction`):
+    /// `function_entry` -> slots.
+    pub function_hot_regs: HashMap<u64, Vec<u8>>,
+    /// Override helper blocks: `synthetic_pc` -> owning instruction info.
+    pub synthetic_blocks: HashMap<u64, SyntheticBlockInfo>,
+    /// Quarantined blocks: `stub_pc` -> description of the lift error.
+    pub quarantined: BTreeMap<u64, String>,
+    /// Blocks a block profile never entered (emitted as cold functions).
+    pub cold_blocks: HashSet<u64>,
+    /// Exported function symbols as `(symbol, pc)` (export-functions mode).
+    pub exported_functions: Vec<(String, u64)>,
+    /// Guest functions hooked with `HookKind::HostCall` as `(hook, entry
+    /// pc)` (C backend).
+    pub host_hooks: Vec<(FunctionHook, u64)>,
     /// Initial brk value (end of bss section).
     pub initial_brk: u64,
+    /// Content hash of the ELF file, stamped into binary traces.
+    pub elf_hash: u64,
+    /// Planned heap/stack placement, if sizes were configured.
+    pub memory_layout: Option<MemoryLayout>,
+    /// `guest_pc.map` line of each guest instruction (guest PC map mode).
+    pub guest_pc_lines: Arc<GuestPcLines>,
+    /// Golden trace checked at block entries (`EmitConfig::embedded_golden`).
+    pub golden: Option<Arc<GoldenTrace>>,
+    /// Blocks run by the block interpreter (`EmitConfig::interpret_blocks`
+    /// and blocks that failed to compile), by start PC.
+    pub interpreted: BTreeSet<u64>,
 }
 
 impl EmitInputs {
@@ -29,8 +71,22 @@ impl EmitInputs {
             text_start: entry_point,
             pc_end,
             valid_addresses: HashSet::new(),
+            code_ranges: Vec::new(),
+            vector: false,
             absorbed_to_merged: HashMap::new(),
+            block_to_function: HashMap::new(),
+            function_hot_regs: HashMap::new(),
+            synthetic_blocks: HashMap::new(),
+            quarantined: BTreeMap::new(),
+            cold_blocks: HashSet::new(),
+            exported_functions: Vec::new(),
+            host_hooks: Vec::new(),
             initial_brk: 0,
+            elf_hash: 0,
+            memory_layout: None,
+            guest_pc_lines: Arc::default(),
+            golden: None,
+            interpreted: BTreeSet::new(),
         }
     }
 
@@ -48,12 +104,44 @@ impl EmitInputs {
         self
     }
 
+    /// Set the content hash of the ELF file.
+    #[must_use]
+    pub const fn with_elf_hash(mut self, elf_hash: u64) -> Self {
+        self.elf_hash = elf_hash;
+        self
+    }
+
+    /// Set the planned heap/stack placement.
+    #[must_use]
+    pub fn with_memory_layout(mut self, layout: Option<MemoryLayout>) -> Self {
+        self.memory_layout = layout;
+        self
+    }
+
+    /// Stack guard that stores are checked against: the planned one, unless
+    /// `address_mode` performs no checks.
+    #[must_use]
+    pub fn checked_stack_guard(&self, address_mode: AddressMode) -> Option<AddrRange> {
+        self.memory_layout
+            .as_ref()
+            .and_then(|layout| layout.stack_guard)
+            .filter(|_| address_mode.needs_mask())
+    }
+
     /// Check if address is valid (either directly or via absorbed mapping).
     #[must_use]
     pub fn is_valid_address(&self, pc: u64) -> bool {
         self.valid_addresses.contains(&pc) || self.absorbed_to_merged.contains_key(&pc)
     }
 
+    /// Hot registers of the function holding the block at `pc`, if they
+    /// differ from `EmitConfig::hot_regs`.
+    #[must_use]
+    pub fn block_hot_regs(&self, pc: u64) -> Option<&[u8]> {
+        let function = self.block_to_function.get(&pc)?;
+        self.function_hot_regs.get(function).map(Vec::as_slice)
+    }
+
     /// Resolve an address to its actual target (handles absorbed blocks).
     #[must_use]
     pub fn resolve_address(&self, pc: u64) -> u64 {
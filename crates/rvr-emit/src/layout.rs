@@ -7,6 +7,101 @@ use rvr_ir::Xlen;
 
 use crate::config::EmitConfig;
 
+/// Version of the `RvState` layout, exported by every library as
+/// `RV_STATE_LAYOUT_VERSION`.
+///
+/// Bump it (and `ABI_VERSION`) whenever a field moves or changes meaning.
+pub const STATE_LAYOUT_VERSION: u32 = 5;
+
+/// Why execution stopped, as written to `RvState::exit_cause`.
+///
+/// `exit_info` holds the auxiliary word noted on each cause.
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExitCause {
+    /// Guest exit (exit syscall or `Terminator::Exit`), or not stopped.
+    Guest = 0,
+    /// HTIF `tohost` exit; `exit_info` is the written value shifted right by
+    /// one (the riscv-tests test number, 0 on pass).
+    Htif = 1,
+    /// The host runtime stopped the guest on a request it cannot serve;
+    /// `exit_info` is the request (the unsupported HTIF syscall number).
+    HostStop = 2,
+    /// Illegal or unsupported instruction.
+    IllegalInstruction = 3,
+    /// Jump to an address with no block; `exit_info` is the target, or 0
+    /// if unknown.
+    InvalidTarget = 4,
+    /// Store into recompiled code; `exit_info` is the store address.
+    CodeWrite = 5,
+    /// Store into the stack guard; `exit_info` is the store address.
+    StackOverflow = 6,
+    /// Load or store outside guest memory (bounds-checked asm code).
+    MemoryFault = 7,
+    /// Dynamic jump to a target the dispatch table lacks (C backend); `pc`
+    /// is the jump site, `exit_info` the target and `exit_code` the
+    /// register the target was computed from.
+    UnresolvedJump = 8,
+    /// An HTIF guest kept polling `fromhost` without it changing (see
+    /// `EmitConfig::htif_poll_limit`); `pc` is the polling load and
+    /// `exit_info` the `tohost` value.
+    HtifStall = 9,
+    /// Registers at a block entry differ from the embedded golden trace
+    /// (see `EmitConfig::embedded_golden`); `pc` is the block and
+    /// `exit_info` the index of the golden trace point.
+    GoldenMismatch = 10,
+}
+
+/// Trap entry labels of the asm backends and the cause each records.
+/// `asm_trap` (invalid jump target) comes last and falls through to the
+/// shared stop.
+pub const ASM_TRAP_ENTRIES: [(&str, ExitCause); 3] = [
+    ("asm_trap_illegal", ExitCause::IllegalInstruction),
+    ("asm_trap_memory", ExitCause::MemoryFault),
+    ("asm_trap", ExitCause::InvalidTarget),
+];
+
+impl ExitCause {
+    /// Every cause, in code order.
+    pub const ALL: [Self; 11] = [
+        Self::Guest,
+        Self::Htif,
+        Self::HostStop,
+        Self::IllegalInstruction,
+        Self::InvalidTarget,
+        Self::CodeWrite,
+        Self::StackOverflow,
+        Self::MemoryFault,
+        Self::UnresolvedJump,
+        Self::HtifStall,
+        Self::GoldenMismatch,
+    ];
+
+    /// Cause for a raw `exit_cause` value, if it is known.
+    #[must_use]
+    pub fn from_raw(raw: u32) -> Option<Self> {
+        Self::ALL.into_iter().find(|cause| *cause as u32 == raw)
+    }
+
+    /// Name of the cause's `RvExitCause` enumerator in the generated C header.
+    #[must_use]
+    pub const fn c_name(self) -> &'static str {
+        match self {
+            Self::Guest => "RV_EXIT_GUEST",
+            Self::Htif => "RV_EXIT_HTIF",
+            Self::HostStop => "RV_EXIT_HOST_STOP",
+            Self::IllegalInstruction => "RV_EXIT_ILLEGAL_INSTRUCTION",
+            Self::InvalidTarget => "RV_EXIT_INVALID_TARGET",
+            Self::CodeWrite => "RV_EXIT_CODE_WRITE",
+            Self::StackOverflow => "RV_EXIT_STACK_OVERFLOW",
+            Self::MemoryFault => "RV_EXIT_MEMORY_FAULT",
+            Self::UnresolvedJump => "RV_EXIT_UNRESOLVED_JUMP",
+            Self::HtifStall => "RV_EXIT_HTIF_STALL",
+            Self::GoldenMismatch => "RV_EXIT_GOLDEN_MISMATCH",
+        }
+    }
+}
+
 /// `RvState` field offsets.
 ///
 /// All offsets are in bytes from the start of the struct.
@@ -34,13 +129,21 @@ pub struct RvStateLayout {
     pub offset_has_exited: usize,
     /// Offset of `exit_code`.
     pub offset_exit_code: usize,
+    /// Offset of `cancel_requested` (u8, set by the host to stop the guest).
+    pub offset_cancel_requested: usize,
+    /// Offset of `exit_cause` (u32, an [`ExitCause`]).
+    pub offset_exit_cause: usize,
+    /// Offset of `exit_info` (u64, auxiliary word of the exit cause).
+    pub offset_exit_info: usize,
     /// Offset of brk.
     pub offset_brk: usize,
     /// Offset of `start_brk`.
     pub offset_start_brk: usize,
     /// Offset of memory pointer.
     pub offset_memory: usize,
-    /// Offset of tracer field (immediately after memory pointer).
+    /// Offset of the guest stdio hooks pointer.
+    pub offset_io: usize,
+    /// Offset of tracer field (immediately after the io pointer).
     pub offset_tracer: usize,
 }
 
@@ -81,17 +184,20 @@ impl RvStateLayout {
         // Execution control (packed booleans)
         let offset_has_exited = offset_reservation_valid + 1;
         let offset_exit_code = offset_has_exited + 1;
-        let offset_pad0 = offset_exit_code + 1;
+        let offset_cancel_requested = offset_exit_code + 1;
 
-        // Align to 8 bytes for brk
-        let brk_align_offset = offset_pad0 + 1;
-        let brk_padding = (8 - (brk_align_offset % 8)) % 8;
-        let offset_brk = brk_align_offset + brk_padding;
+        // Structured exit reason: u32 cause, then a u64 aligned to 8 bytes
+        let offset_exit_cause = (offset_cancel_requested + 1 + 3) & !3;
+        let offset_exit_info = (offset_exit_cause + 4 + 7) & !7;
+
+        // brk is 8-byte aligned after exit_info
+        let offset_brk = offset_exit_info + 8;
         let offset_start_brk = offset_brk + reg_bytes;
 
-        // Memory pointer
+        // Memory and stdio hook pointers
         let offset_memory = offset_start_brk + reg_bytes;
-        let offset_tracer = offset_memory + 8;
+        let offset_io = offset_memory + 8;
+        let offset_tracer = offset_io + 8;
 
         Self {
             reg_bytes,
@@ -105,9 +211,13 @@ impl RvStateLayout {
             offset_reservation_valid,
             offset_has_exited,
             offset_exit_code,
+            offset_cancel_requested,
+            offset_exit_cause,
+            offset_exit_info,
             offset_brk,
             offset_start_brk,
             offset_memory,
+            offset_io,
             offset_tracer,
         }
     }
@@ -135,6 +245,13 @@ mod tests {
         assert_eq!(layout.offset_pc, 32 * 8); // 256
         // After pc (8 bytes), instret should be at 264 (already aligned)
         assert_eq!(layout.offset_instret, 264);
+        assert_eq!(layout.offset_cancel_requested, 283);
+        assert_eq!(layout.offset_exit_cause, 284);
+        assert_eq!(layout.offset_exit_info, 288);
+        assert_eq!(layout.offset_brk, 296);
+        assert_eq!(layout.offset_memory, 312);
+        assert_eq!(layout.offset_io, 320);
+        assert_eq!(layout.offset_tracer, 328);
     }
 
     #[test]
@@ -149,6 +266,27 @@ mod tests {
         // After pc (4 bytes), instret needs 8-byte alignment
         // 128 + 4 = 132, align to 8 -> 136
         assert_eq!(layout.offset_instret, 136);
+        // reservation_addr 144, then the status bytes at 148..152
+        assert_eq!(layout.offset_exit_cause, 152);
+        assert_eq!(layout.offset_exit_info, 160);
+        assert_eq!(layout.offset_brk, 168);
+    }
+
+    #[test]
+    fn test_exit_cause_round_trips() {
+        assert_eq!(
+            ExitCause::from_raw(ExitCause::Htif as u32),
+            Some(ExitCause::Htif)
+        );
+        assert_eq!(
+            ExitCause::from_raw(ExitCause::StackOverflow as u32),
+            Some(ExitCause::StackOverflow)
+        );
+        assert_eq!(
+            ExitCause::from_raw(ExitCause::UnresolvedJump as u32),
+            Some(ExitCause::UnresolvedJump)
+        );
+        assert_eq!(ExitCause::from_raw(u32::MAX), None);
     }
 
     #[test]
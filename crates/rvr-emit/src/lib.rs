@@ -5,15 +5,21 @@
 //! - `x86` - x86-64 assembly emission (experimental)
 //! - `arm64` - ARM64 assembly emission (experimental)
 
+mod abi;
 mod config;
+mod hooks;
 pub mod htif;
 mod inputs;
 mod layout;
+mod memory_layout;
 
 pub mod arm64;
 pub mod c;
 pub mod x86;
 
+pub use abi::{ABI_HEADER, ABI_INFO_SYMBOL, ABI_VERSION, AbiFeature, RvAbiInfo, gen_abi_header};
 pub use config::*;
+pub use hooks::{FunctionHook, HOOK_FN_PREFIX, HookKind, is_hook_fn};
 pub use inputs::*;
-pub use layout::RvStateLayout;
+pub use layout::{ExitCause, RvStateLayout, STATE_LAYOUT_VERSION};
+pub use memory_layout::*;
@@ -0,0 +1,902 @@
+//! Guest address-space layout profiles.
+//!
+//! A profile fixes the address mode, memory size and the regions a guest's
+//! code, data, stack and heap must occupy. Compiling with a profile checks the
+//! ELF against it and records the regions in the library, so the runner can
+//! check the ELF it is handed as well. The matching linker scripts ship with
+//! `rvr-rt`.
+
+use std::fmt;
+
+use thiserror::Error;
+
+use crate::AddressMode;
+
+/// Memory address bits of both named profiles.
+const PROFILE_MEMORY_BITS: u8 = 32;
+
+/// `rvr-rt` default `link.x`: one RAM region in the lower half of the 32-bit
+/// space (so zero- and sign-extension agree), stack at the top.
+const BAREMETAL_RAM: AddrRange = AddrRange::new(0x4000_0000, 0x5000_0000);
+/// Stack reserved below `__stack_top` by the baremetal script.
+const BAREMETAL_STACK_SIZE: u64 = 0x10_0000;
+
+/// zkVM-style RV32: stack below the program, separated by a guard gap, and
+/// memory ending at the usual prover limit.
+const ZKVM_STACK: AddrRange = AddrRange::new(0x0000_1000, 0x0020_0000);
+const ZKVM_GUARD_GAP: u64 = 0x1000;
+const ZKVM_CODE_END: u64 = 0x1000_0000;
+const ZKVM_MEMORY_END: u64 = 0x7800_0000;
+
+/// Words in `LayoutRegions::to_words`.
+pub const LAYOUT_REGION_WORDS: usize = 8;
+
+/// Half-open guest address range `[start, end)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AddrRange {
+    /// First address.
+    pub start: u64,
+    /// One past the last address.
+    pub end: u64,
+}
+
+impl AddrRange {
+    /// Create the range `[start, end)`.
+    #[must_use]
+    pub const fn new(start: u64, end: u64) -> Self {
+        Self { start, end }
+    }
+
+    /// Whether `other` lies entirely within this range.
+    #[must_use]
+    pub const fn contains_range(&self, other: &Self) -> bool {
+        self.start <= other.start && other.end <= self.end
+    }
+
+    /// Bytes between the two ranges, or `None` if they overlap.
+    #[must_use]
+    pub const fn gap_to(&self, other: &Self) -> Option<u64> {
+        if self.end <= other.start {
+            Some(other.start - self.end)
+        } else if other.end <= self.start {
+            Some(self.start - other.end)
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for AddrRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{:#x}, {:#x})", self.start, self.end)
+    }
+}
+
+/// How the stack and heap are kept apart.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GuardPolicy {
+    /// Regions only must not overlap; `brk` may grow to the end of memory.
+    #[default]
+    None,
+    /// Keep this many unused bytes between the stack and every other
+    /// region, and stop `brk`/`mmap` at the end of the heap region.
+    Gap(u64),
+}
+
+impl GuardPolicy {
+    /// Minimum distance between the stack and other regions.
+    #[must_use]
+    pub const fn min_gap(self) -> u64 {
+        match self {
+            Self::None => 0,
+            Self::Gap(gap) => gap,
+        }
+    }
+
+    /// Whether the heap is capped at the end of its region.
+    #[must_use]
+    pub const fn limits_heap(self) -> bool {
+        matches!(self, Self::Gap(_))
+    }
+}
+
+/// Regions a guest image must occupy.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LayoutRegions {
+    /// Executable segments.
+    pub code: AddrRange,
+    /// Non-executable segments.
+    pub data: AddrRange,
+    /// Stack; `__stack_top` must lie in `(start, end]`.
+    pub stack: AddrRange,
+    /// Heap; the initial program break must lie in `[start, end]`.
+    pub heap: AddrRange,
+    /// Stack/heap guard policy.
+    pub guard: GuardPolicy,
+}
+
+/// A complete address-space layout.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LayoutSpec {
+    /// Required XLEN, if the layout only makes sense for one.
+    pub xlen: Option<u8>,
+    /// Memory address bits.
+    pub memory_bits: u8,
+    /// Address translation mode.
+    pub address_mode: AddressMode,
+    /// Expected code/data/stack/heap placement.
+    pub regions: LayoutRegions,
+}
+
+/// Named address-space layout.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LayoutProfile {
+    /// `rvr-rt`'s default `link.x` (any XLEN, wrapping addresses).
+    Baremetal,
+    /// zkVM-style RV32 with bounds-checked addresses and a stack guard gap.
+    Rv32ZkVm,
+    /// User-defined layout.
+    Custom(LayoutSpec),
+}
+
+impl LayoutProfile {
+    /// Profile name, as recorded in the compiled library.
+    #[must_use]
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Baremetal => "baremetal",
+            Self::Rv32ZkVm => "rv32-zkvm",
+            Self::Custom(_) => "custom",
+        }
+    }
+
+    /// The layout this profile stands for.
+    #[must_use]
+    pub const fn spec(&self) -> LayoutSpec {
+        match self {
+            Self::Baremetal => {
+                let stack =
+                    AddrRange::new(BAREMETAL_RAM.end - BAREMETAL_STACK_SIZE, BAREMETAL_RAM.end);
+                let program = AddrRange::new(BAREMETAL_RAM.start, stack.start);
+                LayoutSpec {
+                    xlen: None,
+                    memory_bits: PROFILE_MEMORY_BITS,
+                    address_mode: AddressMode::Wrap,
+                    regions: LayoutRegions {
+                        code: program,
+                        data: program,
+                        stack,
+                        heap: program,
+                        guard: GuardPolicy::None,
+                    },
+                }
+            }
+            Self::Rv32ZkVm => {
+                let program_start = ZKVM_STACK.end + ZKVM_GUARD_GAP;
+                let memory = AddrRange::new(program_start, ZKVM_MEMORY_END);
+                LayoutSpec {
+                    xlen: Some(32),
+                    memory_bits: PROFILE_MEMORY_BITS,
+                    address_mode: AddressMode::Bounds,
+                    regions: LayoutRegions {
+                        code: AddrRange::new(program_start, ZKVM_CODE_END),
+                        data: memory,
+                        stack: ZKVM_STACK,
+                        heap: memory,
+                        guard: GuardPolicy::Gap(ZKVM_GUARD_GAP),
+                    },
+                }
+            }
+            Self::Custom(spec) => *spec,
+        }
+    }
+
+    /// Check a loaded image against this profile.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first mismatch between the profile and the image, or an
+    /// inconsistency within the profile itself.
+    pub fn validate(&self, image: &ImageLayout) -> Result<(), LayoutError> {
+        let spec = self.spec();
+        spec.check()
+            .and_then(|()| {
+                if let Some(expected) = spec.xlen
+                    && expected != image.xlen
+                {
+                    return Err(LayoutMismatch::Xlen {
+                        expected,
+                        actual: image.xlen,
+                    });
+                }
+                spec.regions.validate(image)
+            })
+            .map_err(|mismatch| LayoutError::new(self.name(), mismatch))
+    }
+}
+
+impl LayoutSpec {
+    /// Check that the regions fit in memory and respect the guard policy.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first region that does not.
+    pub fn check(&self) -> Result<(), LayoutMismatch> {
+        let memory_end = 1u64.checked_shl(u32::from(self.memory_bits));
+        let regions = &self.regions;
+        for (region, range) in regions.named() {
+            if range.start >= range.end || memory_end.is_some_and(|end| range.end > end) {
+                return Err(LayoutMismatch::RegionOutsideMemory {
+                    region,
+                    range,
+                    memory_bits: self.memory_bits,
+                });
+            }
+        }
+        for (region, range) in [
+            ("code", regions.code),
+            ("data", regions.data),
+            ("heap", regions.heap),
+        ] {
+            regions.check_guard(region, range)?;
+        }
+        Ok(())
+    }
+}
+
+impl LayoutRegions {
+    /// Code, data, stack and heap as start/end pairs.
+    #[must_use]
+    pub const fn to_words(&self) -> [u64; LAYOUT_REGION_WORDS] {
+        [
+            self.code.start,
+            self.code.end,
+            self.data.start,
+            self.data.end,
+            self.stack.start,
+            self.stack.end,
+            self.heap.start,
+            self.heap.end,
+        ]
+    }
+
+    /// Inverse of `to_words`.
+    #[must_use]
+    pub const fn from_words(words: [u64; LAYOUT_REGION_WORDS], guard: GuardPolicy) -> Self {
+        Self {
+            code: AddrRange::new(words[0], words[1]),
+            data: AddrRange::new(words[2], words[3]),
+            stack: AddrRange::new(words[4], words[5]),
+            heap: AddrRange::new(words[6], words[7]),
+            guard,
+        }
+    }
+
+    /// Regions with their names.
+    const fn named(&self) -> [(&'static str, AddrRange); 4] {
+        [
+            ("code", self.code),
+            ("data", self.data),
+            ("stack", self.stack),
+            ("heap", self.heap),
+        ]
+    }
+
+    /// Check that `range` keeps the guard distance from the stack.
+    const fn check_guard(
+        &self,
+        region: &'static str,
+        range: AddrRange,
+    ) -> Result<(), LayoutMismatch> {
+        let gap = self.guard.min_gap();
+        match range.gap_to(&self.stack) {
+            Some(distance) if distance >= gap => Ok(()),
+            _ => Err(LayoutMismatch::Guard {
+                region,
+                range,
+                stack: self.stack,
+                gap,
+            }),
+        }
+    }
+
+    /// Check segment placement, `__stack_top` and the initial program break.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first mismatch.
+    pub fn validate(&self, image: &ImageLayout) -> Result<(), LayoutMismatch> {
+        for segment in &image.segments {
+            let (kind, region) = if segment.executable {
+                ("code", self.code)
+            } else {
+                ("data", self.data)
+            };
+            if !region.contains_range(&segment.range) {
+                return Err(LayoutMismatch::Segment {
+                    kind,
+                    segment: segment.range,
+                    region,
+                });
+            }
+            self.check_guard(kind, segment.range)?;
+        }
+        if let Some(stack_top) = image.stack_top
+            && !(self.stack.start < stack_top && stack_top <= self.stack.end)
+        {
+            return Err(LayoutMismatch::StackTop {
+                stack_top,
+                stack: self.stack,
+            });
+        }
+        let brk = image.program_break;
+        if !(self.heap.start <= brk && brk <= self.heap.end) {
+            return Err(LayoutMismatch::ProgramBreak {
+                brk,
+                heap: self.heap,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// A loadable segment of a guest image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ImageSegment {
+    /// Virtual address range (including BSS).
+    pub range: AddrRange,
+    /// Whether the segment is executable.
+    pub executable: bool,
+}
+
+/// Placement of a guest image, as checked against a layout.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ImageLayout {
+    /// Image XLEN (32 or 64).
+    pub xlen: u8,
+    /// Loadable segments.
+    pub segments: Vec<ImageSegment>,
+    /// `__stack_top` symbol, if defined.
+    pub stack_top: Option<u64>,
+    /// Initial program break (end of the highest segment).
+    pub program_break: u64,
+}
+
+/// Words in `MemoryLayout::to_words`.
+pub const MEMORY_LAYOUT_WORDS: usize = 8;
+
+/// Stack reserved below `__stack_top` when only an mmap arena is sized.
+pub const DEFAULT_STACK_SIZE: u64 = 8 << 20;
+
+/// Guest page size; mmap arena bounds and mappings are aligned to it.
+const GUEST_PAGE_SIZE: u64 = 4096;
+
+/// Unmapped bytes kept below the stack by default: one guest page.
+pub const DEFAULT_STACK_GUARD: u64 = GUEST_PAGE_SIZE;
+
+/// Heap, stack and mmap arena placement chosen for a guest image.
+///
+/// Planned at lift time from `--heap-size`/`--stack-size`/`--mmap-size`: the
+/// heap starts at the initial program break and the stack ends at
+/// `__stack_top` (or the end of memory). A region without an explicit size
+/// takes the free space next to it. The mmap arena is carved from the top of
+/// the heap. `brk` fails once the heap is exhausted, `mmap` once the arena
+/// (or, without one, the heap) is. A guard range directly below the stack
+/// belongs to no region, so an overflowing stack is caught there instead of
+/// corrupting the heap or loaded data.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MemoryLayout {
+    /// Loadable segments of the image.
+    pub segments: Vec<AddrRange>,
+    /// Heap, grown by `brk`/`mmap`.
+    pub heap: AddrRange,
+    /// Stack; the initial stack pointer is `stack.end`.
+    pub stack: AddrRange,
+    /// Arena for anonymous `mmap`s, managed by the guest runtime.
+    pub mmap: Option<AddrRange>,
+    /// Guard below the stack (ends at `stack.start`), if one is reserved.
+    pub stack_guard: Option<AddrRange>,
+}
+
+impl MemoryLayout {
+    /// Plan the heap, stack and mmap arena for an image.
+    ///
+    /// Returns `None` if no size is set (the heap may then grow up to the
+    /// stack pointer). An arena without a heap or stack size reserves
+    /// `DEFAULT_STACK_SIZE` for the stack and gives the rest to the heap.
+    /// `stack_guard` bytes below the stack are left to the guard (none if
+    /// zero); a stack that takes the free space gives them up from its
+    /// bottom.
+    ///
+    /// # Errors
+    ///
+    /// Returns a mismatch if a region overlaps a segment or another
+    /// region, is empty, or does not fit in memory, or if the arena does not
+    /// fit in the heap.
+    pub fn plan(
+        image: &ImageLayout,
+        memory_bits: u8,
+        heap_size: Option<u64>,
+        stack_size: Option<u64>,
+        mmap_size: Option<u64>,
+        stack_guard: u64,
+    ) -> Result<Option<Self>, LayoutMismatch> {
+        if heap_size.is_none() && stack_size.is_none() && mmap_size.is_none() {
+            return Ok(None);
+        }
+        let stack_size = stack_size
+            .or_else(|| (heap_size.is_none() && mmap_size.is_some()).then_some(DEFAULT_STACK_SIZE));
+        let memory_end = 1u64.checked_shl(u32::from(memory_bits)).unwrap_or(u64::MAX);
+        let segments: Vec<AddrRange> = image.segments.iter().map(|s| s.range).collect();
+        let brk = image.program_break;
+        let stack_top = image.stack_top.unwrap_or(memory_end);
+
+        let explicit_heap = heap_size.map(|size| AddrRange::new(brk, brk.saturating_add(size)));
+        let explicit_stack =
+            stack_size.map(|size| AddrRange::new(stack_top.saturating_sub(size), stack_top));
+        let (heap, stack) = match (explicit_heap, explicit_stack) {
+            (Some(heap), Some(stack)) => (heap, stack),
+            (Some(heap), None) => {
+                let floor = highest_end_below(stack_top, segments.iter().chain([&heap]));
+                let start = floor.saturating_add(stack_guard).min(stack_top);
+                (heap, AddrRange::new(start, stack_top))
+            }
+            (None, Some(stack)) => {
+                let guarded = AddrRange::new(stack.start.saturating_sub(stack_guard), stack.end);
+                let ceiling =
+                    lowest_start_above(brk, segments.iter().chain([&guarded]), memory_end);
+                (AddrRange::new(brk, ceiling), stack)
+            }
+            (None, None) => unreachable!("checked above"),
+        };
+        let guard = AddrRange::new(stack.start.saturating_sub(stack_guard), stack.start);
+        let stack_guard = (guard.start < guard.end).then_some(guard);
+        let (heap, mmap) = match mmap_size {
+            Some(size) => {
+                let (heap, arena) = carve_arena(heap, size)?;
+                (heap, Some(arena))
+            }
+            None => (heap, None),
+        };
+
+        let regions = [("heap", heap), ("stack", stack)];
+        let arena = mmap.map(|range| ("mmap", range));
+        let guard = stack_guard.map(|range| ("stack guard", range));
+        for (region, range) in regions.into_iter().chain(arena).chain(guard) {
+            if let Some(&segment) = segments.iter().find(|s| range.gap_to(s).is_none()) {
+                return Err(LayoutMismatch::Overlap {
+                    region,
+                    range,
+                    other: "segment",
+                    other_range: segment,
+                });
+            }
+        }
+        let stack_regions = std::iter::once(("stack region", stack)).chain(guard);
+        for (other, other_range) in stack_regions {
+            for (region, range) in std::iter::once(("heap", heap)).chain(arena) {
+                if range.gap_to(&other_range).is_none() {
+                    return Err(LayoutMismatch::Overlap {
+                        region,
+                        range,
+                        other,
+                        other_range,
+                    });
+                }
+            }
+        }
+        for (region, range) in regions.into_iter().chain(arena) {
+            if range.start >= range.end || range.end > memory_end {
+                return Err(LayoutMismatch::RegionOutsideMemory {
+                    region,
+                    range,
+                    memory_bits,
+                });
+            }
+        }
+        Ok(Some(Self {
+            segments,
+            heap,
+            stack,
+            mmap,
+            stack_guard,
+        }))
+    }
+
+    /// Heap, stack, mmap arena and stack guard as start/end pairs (absent
+    /// ranges are `0, 0`).
+    #[must_use]
+    pub const fn to_words(&self) -> [u64; MEMORY_LAYOUT_WORDS] {
+        let mmap = or_empty(self.mmap);
+        let guard = or_empty(self.stack_guard);
+        [
+            self.heap.start,
+            self.heap.end,
+            self.stack.start,
+            self.stack.end,
+            mmap.start,
+            mmap.end,
+            guard.start,
+            guard.end,
+        ]
+    }
+
+    /// Inverse of `to_words`, with the segments of the loaded image.
+    #[must_use]
+    pub const fn from_words(words: [u64; MEMORY_LAYOUT_WORDS], segments: Vec<AddrRange>) -> Self {
+        Self {
+            segments,
+            heap: AddrRange::new(words[0], words[1]),
+            stack: AddrRange::new(words[2], words[3]),
+            mmap: non_empty(words[4], words[5]),
+            stack_guard: non_empty(words[6], words[7]),
+        }
+    }
+
+    /// Whether a stack pointer at `sp` has run past the stack into its
+    /// guard.
+    #[must_use]
+    pub fn is_stack_overflow(&self, sp: u64) -> bool {
+        self.stack_guard
+            .is_some_and(|guard| guard.start <= sp && sp < guard.end)
+    }
+}
+
+const fn or_empty(range: Option<AddrRange>) -> AddrRange {
+    match range {
+        Some(range) => range,
+        None => AddrRange::new(0, 0),
+    }
+}
+
+const fn non_empty(start: u64, end: u64) -> Option<AddrRange> {
+    if start < end {
+        Some(AddrRange::new(start, end))
+    } else {
+        None
+    }
+}
+
+impl fmt::Display for MemoryLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for segment in &self.segments {
+            write!(f, "segment {segment}, ")?;
+        }
+        write!(f, "heap {}, stack {}", self.heap, self.stack)?;
+        if let Some(guard) = self.stack_guard {
+            write!(f, ", stack guard {guard}")?;
+        }
+        if let Some(mmap) = self.mmap {
+            write!(f, ", mmap {mmap}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Split a page-aligned arena of `size` bytes off the top of `heap`.
+fn carve_arena(heap: AddrRange, size: u64) -> Result<(AddrRange, AddrRange), LayoutMismatch> {
+    let end = heap.end & !(GUEST_PAGE_SIZE - 1);
+    let start = size
+        .checked_next_multiple_of(GUEST_PAGE_SIZE)
+        .and_then(|size| end.checked_sub(size))
+        .filter(|&start| start >= heap.start && start < end)
+        .ok_or(LayoutMismatch::ArenaExceedsHeap { size, heap })?;
+    Ok((
+        AddrRange::new(heap.start, start),
+        AddrRange::new(start, end),
+    ))
+}
+
+/// Highest range end at or below `addr`, or 0.
+fn highest_end_below<'a>(addr: u64, ranges: impl Iterator<Item = &'a AddrRange>) -> u64 {
+    ranges
+        .map(|range| range.end)
+        .filter(|&end| end <= addr)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Lowest range start at or above `addr`, or `limit`.
+fn lowest_start_above<'a>(
+    addr: u64,
+    ranges: impl Iterator<Item = &'a AddrRange>,
+    limit: u64,
+) -> u64 {
+    ranges
+        .map(|range| range.start)
+        .filter(|&start| start >= addr)
+        .min()
+        .unwrap_or(limit)
+}
+
+/// How an image (or a custom layout) violates a layout.
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum LayoutMismatch {
+    #[error("expected an RV{expected} image, got RV{actual}")]
+    Xlen { expected: u8, actual: u8 },
+    #[error("{region} region {range} is empty or exceeds the {memory_bits}-bit address space")]
+    RegionOutsideMemory {
+        region: &'static str,
+        range: AddrRange,
+        memory_bits: u8,
+    },
+    #[error("{kind} segment {segment} is outside the {kind} region {region}")]
+    Segment {
+        kind: &'static str,
+        segment: AddrRange,
+        region: AddrRange,
+    },
+    #[error("{region} {range} is within {gap:#x} bytes of the stack region {stack}")]
+    Guard {
+        region: &'static str,
+        range: AddrRange,
+        stack: AddrRange,
+        gap: u64,
+    },
+    #[error("__stack_top {stack_top:#x} is outside the stack region {stack}")]
+    StackTop { stack_top: u64, stack: AddrRange },
+    #[error("initial program break {brk:#x} is outside the heap region {heap}")]
+    ProgramBreak { brk: u64, heap: AddrRange },
+    #[error("{region} region {range} overlaps {other} {other_range}")]
+    Overlap {
+        region: &'static str,
+        range: AddrRange,
+        other: &'static str,
+        other_range: AddrRange,
+    },
+    #[error("mmap arena of {size:#x} bytes does not fit in the heap region {heap}")]
+    ArenaExceedsHeap { size: u64, heap: AddrRange },
+}
+
+/// An image that does not match its layout profile.
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+#[error("image does not match layout profile `{profile}`: {mismatch}")]
+pub struct LayoutError {
+    /// Profile name.
+    pub profile: String,
+    /// What did not match.
+    pub mismatch: LayoutMismatch,
+}
+
+impl LayoutError {
+    /// Attach a profile name to a mismatch.
+    #[must_use]
+    pub fn new(profile: impl Into<String>, mismatch: LayoutMismatch) -> Self {
+        Self {
+            profile: profile.into(),
+            mismatch,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(start: u64, end: u64, executable: bool) -> ImageSegment {
+        ImageSegment {
+            range: AddrRange::new(start, end),
+            executable,
+        }
+    }
+
+    fn zkvm_image() -> ImageLayout {
+        ImageLayout {
+            xlen: 32,
+            segments: vec![
+                segment(0x0020_1000, 0x0020_2000, true),
+                segment(0x0020_2000, 0x0020_3000, false),
+            ],
+            stack_top: Some(0x0020_0000),
+            program_break: 0x0020_3000,
+        }
+    }
+
+    #[test]
+    fn test_named_profiles_are_consistent() {
+        for profile in [LayoutProfile::Baremetal, LayoutProfile::Rv32ZkVm] {
+            assert_eq!(profile.spec().check(), Ok(()), "{}", profile.name());
+        }
+    }
+
+    #[test]
+    fn test_zkvm_accepts_matching_image() {
+        assert_eq!(LayoutProfile::Rv32ZkVm.validate(&zkvm_image()), Ok(()));
+    }
+
+    #[test]
+    fn test_zkvm_mismatches() {
+        let check = |image: ImageLayout| {
+            LayoutProfile::Rv32ZkVm
+                .validate(&image)
+                .unwrap_err()
+                .to_string()
+        };
+
+        let mut image = zkvm_image();
+        image.xlen = 64;
+        assert_eq!(
+            check(image),
+            "image does not match layout profile `rv32-zkvm`: expected an RV32 image, got RV64"
+        );
+
+        let mut image = zkvm_image();
+        image.segments[0] = segment(0x1000_0000, 0x1000_1000, true);
+        assert!(check(image).ends_with(
+            "code segment [0x10000000, 0x10001000) is outside the code region [0x201000, 0x10000000)"
+        ));
+
+        let mut image = zkvm_image();
+        image.stack_top = Some(0x0020_1000);
+        assert!(
+            check(image)
+                .ends_with("__stack_top 0x201000 is outside the stack region [0x1000, 0x200000)")
+        );
+
+        let mut image = zkvm_image();
+        image.program_break = ZKVM_MEMORY_END + 1;
+        assert!(check(image).ends_with(
+            "initial program break 0x78000001 is outside the heap region [0x201000, 0x78000000)"
+        ));
+    }
+
+    #[test]
+    fn test_custom_guard_gap() {
+        let mut spec = LayoutProfile::Rv32ZkVm.spec();
+        spec.regions.code.start = ZKVM_STACK.end + 0x800;
+        assert_eq!(
+            LayoutProfile::Custom(spec)
+                .validate(&zkvm_image())
+                .unwrap_err()
+                .to_string(),
+            "image does not match layout profile `custom`: code [0x200800, 0x10000000) is within 0x1000 bytes of the stack region [0x1000, 0x200000)"
+        );
+    }
+
+    #[test]
+    fn test_baremetal_image_without_stack_top() {
+        let image = ImageLayout {
+            xlen: 64,
+            segments: vec![segment(0x4000_0000, 0x4000_1000, true)],
+            stack_top: None,
+            program_break: 0x4000_1000,
+        };
+        assert_eq!(LayoutProfile::Baremetal.validate(&image), Ok(()));
+    }
+
+    fn linux_image() -> ImageLayout {
+        ImageLayout {
+            xlen: 64,
+            segments: vec![
+                segment(0x1_0000, 0x1_1000, true),
+                segment(0x1_1000, 0x1_3000, false),
+            ],
+            stack_top: None,
+            program_break: 0x1_3000,
+        }
+    }
+
+    fn plan(
+        image: &ImageLayout,
+        heap: Option<u64>,
+        stack: Option<u64>,
+        mmap: Option<u64>,
+    ) -> Result<Option<MemoryLayout>, LayoutMismatch> {
+        MemoryLayout::plan(image, 32, heap, stack, mmap, DEFAULT_STACK_GUARD)
+    }
+
+    #[test]
+    fn test_memory_layout_plan() {
+        let image = linux_image();
+        assert_eq!(plan(&image, None, None, None), Ok(None));
+
+        let layout = plan(&image, Some(0x1000), Some(0x10_0000), None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(layout.heap, AddrRange::new(0x1_3000, 0x1_4000));
+        assert_eq!(layout.stack, AddrRange::new(0xfff0_0000, 0x1_0000_0000));
+        assert_eq!(
+            layout.stack_guard,
+            Some(AddrRange::new(0xffef_f000, 0xfff0_0000))
+        );
+        assert_eq!(
+            MemoryLayout::from_words(layout.to_words(), layout.segments.clone()),
+            layout
+        );
+
+        // The unsized region takes the free space next to the other one,
+        // less the guard
+        let layout = plan(&image, Some(0x1000), None, None).unwrap().unwrap();
+        assert_eq!(layout.stack, AddrRange::new(0x1_5000, 0x1_0000_0000));
+        assert_eq!(layout.stack_guard, Some(AddrRange::new(0x1_4000, 0x1_5000)));
+        let layout = plan(&image, None, Some(0x1000), None).unwrap().unwrap();
+        assert_eq!(layout.heap, AddrRange::new(0x1_3000, 0xffff_e000));
+
+        // zkVM-style stack below the program: the heap runs to the end of memory
+        let layout = plan(&zkvm_image(), None, Some(0x1000), None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(layout.stack, AddrRange::new(0x1f_f000, 0x20_0000));
+        assert_eq!(layout.heap, AddrRange::new(0x20_3000, 0x1_0000_0000));
+    }
+
+    #[test]
+    fn test_memory_layout_stack_guard() {
+        let image = linux_image();
+        let layout = MemoryLayout::plan(&image, 32, None, Some(0x1000), None, 0)
+            .unwrap()
+            .unwrap();
+        assert_eq!(layout.stack_guard, None);
+        assert_eq!(layout.heap, AddrRange::new(0x1_3000, 0xffff_f000));
+        assert!(!layout.is_stack_overflow(0xffff_eff0));
+
+        let layout = plan(&image, None, Some(0x1000), None).unwrap().unwrap();
+        assert!(layout.is_stack_overflow(0xffff_eff0));
+        assert!(!layout.is_stack_overflow(0xffff_f000));
+        assert!(!layout.is_stack_overflow(0xffff_dff0));
+        assert!(
+            layout
+                .to_string()
+                .contains("stack guard [0xffffe000, 0xfffff000)")
+        );
+    }
+
+    #[test]
+    fn test_memory_layout_plan_mmap() {
+        let image = linux_image();
+
+        // Arena alone: default stack, arena at the top of the remaining heap
+        let layout = plan(&image, None, None, Some(0x10_0000)).unwrap().unwrap();
+        assert_eq!(layout.stack, AddrRange::new(0xff80_0000, 0x1_0000_0000));
+        assert_eq!(layout.mmap, Some(AddrRange::new(0xff6f_f000, 0xff7f_f000)));
+        assert_eq!(layout.heap, AddrRange::new(0x1_3000, 0xff6f_f000));
+        assert_eq!(
+            MemoryLayout::from_words(layout.to_words(), layout.segments.clone()),
+            layout
+        );
+
+        // Arena size rounds up to whole pages
+        let layout = plan(&image, Some(0x1_0000), None, Some(0x1800))
+            .unwrap()
+            .unwrap();
+        assert_eq!(layout.mmap, Some(AddrRange::new(0x2_1000, 0x2_3000)));
+        assert_eq!(layout.heap, AddrRange::new(0x1_3000, 0x2_1000));
+
+        assert_eq!(
+            plan(&image, Some(0x1000), None, Some(0x2000))
+                .unwrap_err()
+                .to_string(),
+            "mmap arena of 0x2000 bytes does not fit in the heap region [0x13000, 0x14000)"
+        );
+    }
+
+    #[test]
+    fn test_memory_layout_plan_errors() {
+        let image = linux_image();
+        let plan = |heap, stack| {
+            MemoryLayout::plan(&image, 20, heap, stack, None, DEFAULT_STACK_GUARD)
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            plan(Some(0x10_0000), None),
+            "heap region [0x13000, 0x113000) overlaps stack region [0x14000, 0x100000)"
+        );
+        assert_eq!(
+            plan(Some(0x8_0000), Some(0x8_0000)),
+            "heap region [0x13000, 0x93000) overlaps stack region [0x80000, 0x100000)"
+        );
+        assert_eq!(
+            plan(Some(0x1000), Some(0xe_c000)),
+            "heap region [0x13000, 0x14000) overlaps stack guard [0x13000, 0x14000)"
+        );
+        assert_eq!(
+            plan(None, Some(0xf_0000)),
+            "stack region [0x10000, 0x100000) overlaps segment [0x10000, 0x11000)"
+        );
+        assert_eq!(
+            plan(Some(0), None),
+            "heap region [0x13000, 0x13000) is empty or exceeds the 20-bit address space"
+        );
+    }
+}
@@ -63,8 +63,12 @@ impl<X: Xlen> X86Emitter<X> {
     }
 
     /// Emit a PC label.
+    ///
+    /// PC labels are jump and dispatch targets (including resuming after a
+    /// suspend), so the cold register cache is not valid past them.
     pub(super) fn emit_pc_label(&mut self, pc: u64) {
         let _ = writeln!(self.asm, "asm_pc_{pc:x}:");
+        self.cold_cache = None;
     }
 
     /// Emit a raw line (no indentation).
@@ -456,16 +460,40 @@ impl<X: Xlen> X86Emitter<X> {
         let _ = tmp_dword;
     }
 
-    /// Get stack offset for a temp slot (relative to current %rsp).
-    pub(super) const fn temp_slot_offset(idx: u8) -> Option<usize> {
+    /// Get stack offset for a temp slot or a live spill slot (relative to current %rsp).
+    pub(super) const fn temp_slot_offset(&self, idx: u8) -> Option<usize> {
         let idx = idx as usize;
-        if idx < Self::TEMP_SLOTS {
+        if idx < Self::TEMP_SLOTS + self.spill_depth as usize {
             Some(idx * Self::TEMP_SLOT_BYTES)
         } else {
             None
         }
     }
 
+    /// Allocate a spill slot above the IR temp slots for nested binary ops.
+    /// The frame grows to the deepest nesting seen (see `frame_bytes`).
+    pub(super) fn alloc_spill_slot(&mut self) -> u8 {
+        let idx = u8::try_from(Self::TEMP_SLOTS + self.spill_depth as usize)
+            .expect("expression nesting exceeds the spill slot range");
+        self.spill_depth += 1;
+        self.max_spill_depth = self.max_spill_depth.max(self.spill_depth);
+        idx
+    }
+
+    /// Release the most recently allocated spill slot.
+    pub(super) const fn release_spill_slot(&mut self) {
+        if self.spill_depth > 0 {
+            self.spill_depth -= 1;
+        }
+    }
+
+    /// Stack bytes reserved below the callee-saved registers: alignment
+    /// padding, IR temp slots and spill slots, keeping %rsp 16-byte aligned.
+    pub(super) const fn frame_bytes(&self) -> usize {
+        let spill_slots = (self.max_spill_depth as usize).next_multiple_of(2);
+        8 + Self::TEMP_STACK_BYTES + spill_slots * Self::TEMP_SLOT_BYTES
+    }
+
     // ========================================================================
     // AT&T syntax helpers
     // ========================================================================
@@ -489,11 +517,11 @@ impl<X: Xlen> X86Emitter<X> {
         if X::VALUE == 32 { "ecx" } else { "rcx" }
     }
 
-    /// Third temp register (rdi/edi) - available after prologue.
+    /// Third temp register (rdx/edx), never a hot register.
     /// Use for parallel operations or when temp1/temp2 are busy.
     /// Particularly useful when temp2 (rcx) is needed for shift count.
     pub(super) const fn temp3() -> &'static str {
-        if X::VALUE == 32 { "edi" } else { "rdi" }
+        if X::VALUE == 32 { "edx" } else { "rdx" }
     }
 
     /// Get the dword-sized version of a temp register.
@@ -704,6 +732,8 @@ impl<X: Xlen> X86Emitter<X> {
                 offset,
                 reserved::STATE_PTR
             ));
+            // Reading the operands (e.g. `addi t0, t0, -1`) may have cached the old value
+            self.cold_cache_invalidate(rv_reg);
         }
     }
 
@@ -738,7 +768,7 @@ impl<X: Xlen> X86Emitter<X> {
                 self.emitf(format!("cmpq %{temp}, %rdx"));
             }
             self.emitf(format!("je {ok_label}"));
-            self.emit("jmp asm_trap");
+            self.emit("jmp asm_trap_memory");
             self.emit_label(&ok_label);
         }
 
@@ -0,0 +1,52 @@
+//! HTIF (Host-Target Interface) code generation for x86-64 assembly.
+//!
+//! Generates the `fromhost` poll watchdog: word and doubleword register
+//! loads from `fromhost` call `htif_poll` (from the generated HTIF runtime),
+//! which stops the guest with `HtifStall` once it has polled
+//! `htif_poll_limit` times without an answer.
+
+use rvr_ir::{Expr, Xlen};
+
+use super::X86Emitter;
+use super::registers::reserved;
+use crate::htif::FROMHOST_ADDR;
+
+impl<X: Xlen> X86Emitter<X> {
+    /// Whether a register load of `width` bytes gets the `fromhost` poll
+    /// check, as in the C backend.
+    pub(super) const fn htif_polls(&self, width: u8) -> bool {
+        self.config.htif_enabled() && self.config.htif_poll_limit > 0 && (width == 4 || width == 8)
+    }
+
+    /// Emit the `fromhost` poll check before a register load from
+    /// `base + offset`.
+    ///
+    /// If the address matches `FROMHOST_ADDR` (as `(uint32_t)addr`, like the
+    /// C backend), calls `htif_poll(state)`. When that reports a stall (it
+    /// sets the exit fields), stores the PC of the load, leaves instret at
+    /// the instructions retired before it and branches to `asm_exit`.
+    pub(super) fn emit_htif_poll(&mut self, base: &Expr<X>, offset: i16) {
+        let not_fromhost = self.next_label("not_fromhost");
+        self.emit_expr_as_addr(base);
+        if offset != 0 {
+            self.emitf(format!("leaq {offset}(%rax), %rax"));
+        }
+        self.emitf(format!("cmpl $0x{FROMHOST_ADDR:x}, %eax"));
+        self.emitf(format!("jne {not_fromhost}"));
+
+        self.save_hot_regs_to_state();
+        self.emitf(format!("movq %{}, %rdi", reserved::STATE_PTR));
+        self.emit("call htif_poll");
+        self.restore_hot_regs_from_state();
+        self.emit("testb %al, %al");
+        self.emitf(format!("jz {not_fromhost}"));
+
+        self.emit_store_next_pc_imm(self.current_pc);
+        if self.config.instret_mode.counts() && !self.config.instret_mode.per_instruction() {
+            // The load's instruction was counted on entry but never retires
+            self.emitf(format!("subq $1, %{}", reserved::INSTRET));
+        }
+        self.emit("jmp asm_exit");
+        self.emit_label(&not_fromhost);
+    }
+}
@@ -1,6 +1,9 @@
 //! IR expression lowering for x86-64.
 
-mod ops;
+mod ops_binary;
+mod ops_div;
+mod ops_unary;
+mod spill;
 
 use rvr_ir::{BinaryOp, Expr, InstrIR, ReadExpr, Terminator, TernaryOp, UnaryOp, Xlen};
 
@@ -8,11 +11,18 @@ use crate::x86::X86Emitter;
 use crate::x86::registers::reserved;
 
 impl<X: Xlen> X86Emitter<X> {
-    /// Emit an expression for use as a 64-bit address.
+    /// Emit an expression for use as a 64-bit address in rax.
     /// For RV32, ensures the result is zero-extended to 64-bit.
-    pub(super) fn emit_expr_as_addr(&mut self, expr: &Expr<X>) -> String {
+    pub(crate) fn emit_expr_as_addr(&mut self, expr: &Expr<X>) -> String {
         match expr {
-            Expr::Read(ReadExpr::Reg(reg)) => self.load_rv_as_addr(*reg, "rax"),
+            Expr::Read(ReadExpr::Reg(reg)) => {
+                // Hot registers come back as themselves; callers address via rax
+                let addr = self.load_rv_as_addr(*reg, "rax");
+                if addr != "rax" {
+                    self.emitf(format!("movq %{addr}, %rax"));
+                }
+                "rax".to_string()
+            }
             Expr::Imm(val) => {
                 let v = X::to_u64(*val);
                 if X::VALUE == 32 {
@@ -43,7 +53,7 @@ impl<X: Xlen> X86Emitter<X> {
         }
     }
 
-    fn emit_store_next_pc_imm(&mut self, next_pc: u64) {
+    pub(crate) fn emit_store_next_pc_imm(&mut self, next_pc: u64) {
         let pc_offset = self.layout.offset_pc;
         if X::VALUE == 32 {
             self.emitf(format!(
@@ -94,6 +104,8 @@ impl<X: Xlen> X86Emitter<X> {
         ));
         self.emitf(format!("cmpq %rdx, %{}", reserved::INSTRET));
         self.emitf(format!("jb {continue_label}"));
+        // Only the exit path below may load into the cold cache
+        let cold_cache = self.cold_cache;
 
         match &instr.terminator {
             Terminator::Fall { target } => {
@@ -176,6 +188,7 @@ impl<X: Xlen> X86Emitter<X> {
             }
         }
 
+        self.cold_cache = cold_cache;
         self.emit_label(&continue_label);
     }
 
@@ -198,6 +211,8 @@ impl<X: Xlen> X86Emitter<X> {
         ));
         self.emitf(format!("cmpq %rdx, %{}", reserved::INSTRET));
         self.emitf(format!("jb {continue_label}"));
+        // Only the exit path below may load into the cold cache
+        let cold_cache = self.cold_cache;
 
         match &instr.terminator {
             Terminator::Fall { target } => {
@@ -278,6 +293,7 @@ impl<X: Xlen> X86Emitter<X> {
             Terminator::Exit { .. } | Terminator::Trap { .. } => {}
         }
 
+        self.cold_cache = cold_cache;
         self.emit_label(&continue_label);
     }
 
@@ -344,7 +360,7 @@ impl<X: Xlen> X86Emitter<X> {
         if v == 0 {
             self.emitf(format!("xor{suffix} %{dest}, %{dest}"));
         } else if X::VALUE == 32 {
-            let v32 = i32::try_from(v).unwrap_or(0);
+            let v32 = u32::try_from(v).unwrap_or(0).cast_signed();
             self.emitf(format!("movl ${v32}, %{dest}"));
         } else if v > 0x7fff_ffff {
             self.emitf(format!("movabsq $0x{v:x}, %{dest}"));
@@ -405,7 +421,7 @@ impl<X: Xlen> X86Emitter<X> {
         let suffix = Self::suffix();
         let instret_off = self.layout.offset_instret;
         match csr {
-            0xC00 | 0xC02 | 0xB00 | 0xB02 => {
+            0xC00 | 0xC01 | 0xC02 | 0xB00 | 0xB02 => {
                 if self.config.instret_mode.counts() {
                     if X::VALUE == 32 {
                         self.emitf(format!(
@@ -424,7 +440,7 @@ impl<X: Xlen> X86Emitter<X> {
                     ));
                 }
             }
-            0xC80 | 0xC82 | 0xB80 | 0xB82 if X::VALUE == 32 => {
+            0xC80 | 0xC81 | 0xC82 | 0xB80 | 0xB82 if X::VALUE == 32 => {
                 if self.config.instret_mode.counts() {
                     self.emitf(format!("movq %{}, %rdx", reserved::INSTRET));
                     self.emit("shrq $32, %rdx");
@@ -506,7 +522,7 @@ impl<X: Xlen> X86Emitter<X> {
 
     fn emit_expr_temp(&mut self, idx: u8, dest: &str) -> String {
         let suffix = Self::suffix();
-        if let Some(offset) = Self::temp_slot_offset(idx) {
+        if let Some(offset) = self.temp_slot_offset(idx) {
             if X::VALUE == 32 {
                 self.emitf(format!("movl {}(%rsp), %{}", offset, Self::reg_dword(dest)));
             } else {
@@ -554,14 +570,34 @@ impl<X: Xlen> X86Emitter<X> {
         let suffix = Self::suffix();
         let temp1 = Self::temp1();
         let temp2 = Self::temp2();
-        let cond_reg = self.emit_expr(cond, temp1);
-        self.emitf(format!("movq %{cond_reg}, %rdx"));
+        let temp3 = Self::temp3();
+        // Compound values may clobber temp3, so the condition waits in a
+        // spill slot while they are evaluated
+        let spill = if spill::is_leaf(then_val) && spill::is_leaf(else_val) {
+            None
+        } else {
+            Some(self.alloc_spill_slot())
+        };
+        if let Some(slot) = spill {
+            self.emit_write_temp(slot, cond, temp1);
+        } else {
+            let cond_reg = self.emit_expr(cond, temp1);
+            self.emitf(format!("mov{suffix} %{cond_reg}, %{temp3}"));
+        }
         let then_reg = self.emit_expr(then_val, temp1);
         if then_reg != temp1 {
             self.emitf(format!("mov{suffix} %{then_reg}, %{temp1}"));
         }
         let else_reg = self.emit_expr(else_val, temp2);
-        self.emit("testq %rdx, %rdx");
+        if let Some(slot) = spill {
+            let offset = self
+                .temp_slot_offset(slot)
+                .expect("spill slot is live until released");
+            self.emitf(format!("cmp{suffix} $0, {offset}(%rsp)"));
+            self.release_spill_slot();
+        } else {
+            self.emitf(format!("test{suffix} %{temp3}, %{temp3}"));
+        }
         if X::VALUE == 32 {
             self.emitf(format!(
                 "cmovzl %{}, %{}",
@@ -1,4 +1,4 @@
-use super::{BinaryOp, Expr, ReadExpr, UnaryOp, X86Emitter, Xlen, reserved};
+use super::{BinaryOp, Expr, ReadExpr, X86Emitter, Xlen, reserved};
 
 struct BinaryEmitCtx<'a, X: Xlen> {
     right: &'a Expr<X>,
@@ -27,6 +27,18 @@ impl<X: Xlen> X86Emitter<X> {
             return result;
         }
 
+        self.emit_binary_op_ordered(op, left, right, dest)
+    }
+
+    /// Load `left` into temp1 and apply `op` with a right operand whose
+    /// evaluation leaves temp1 alone.
+    pub(super) fn emit_binary_op_with_left(
+        &mut self,
+        op: BinaryOp,
+        left: &Expr<X>,
+        right: &Expr<X>,
+        dest: &str,
+    ) -> String {
         let temp1 = Self::temp1();
         let temp2 = Self::temp2();
         let suffix = Self::suffix();
@@ -231,7 +243,7 @@ impl<X: Xlen> X86Emitter<X> {
             }
         } else {
             let temp3 = Self::temp3();
-            let right_reg = self.emit_expr(right, temp3);
+            let right_reg = self.emit_expr(Self::strip_count_mask(right, is_word), temp3);
             if right_reg != "rcx" && right_reg != "ecx" && right_reg != "cl" {
                 self.emitf(format!("movl %{}, %ecx", Self::reg_dword(&right_reg)));
             }
@@ -249,6 +261,24 @@ impl<X: Xlen> X86Emitter<X> {
         Some(dest.to_string())
     }
 
+    /// The shift count without the lifter's `& (XLEN - 1)`, which x86
+    /// shifts apply themselves; evaluating the mask would clobber temp1.
+    pub(super) fn strip_count_mask(right: &Expr<X>, is_word: bool) -> &Expr<X> {
+        let count_mask = if is_word || X::VALUE == 32 {
+            0x1f
+        } else {
+            0x3f
+        };
+        match right {
+            Expr::Binary {
+                op: BinaryOp::And,
+                left,
+                right: mask,
+            } if matches!(**mask, Expr::Imm(m) if X::to_u64(m) == count_mask) => left,
+            _ => right,
+        }
+    }
+
     fn emit_binary_word(
         &mut self,
         op: BinaryOp,
@@ -262,7 +292,9 @@ impl<X: Xlen> X86Emitter<X> {
         let x86_op = if op == BinaryOp::AddW { "addl" } else { "subl" };
 
         if let Expr::Imm(imm) = right {
-            let v = i32::try_from(X::to_u64(*imm)).unwrap_or(0);
+            let v = u32::try_from(X::to_u64(*imm) & u64::from(u32::MAX))
+                .unwrap_or(0)
+                .cast_signed();
             self.emitf(format!("{x86_op} ${v}, %eax"));
         } else {
             let right_reg = self.emit_expr(right, temp2);
@@ -287,7 +319,12 @@ impl<X: Xlen> X86Emitter<X> {
             format!("${imm_i64}")
         } else {
             let r = self.emit_expr(right, temp2);
-            format!("%{r}")
+            if r != temp2 && Self::reads_right_from_temp2(op) {
+                self.emitf(format!("mov{suffix} %{r}, %{temp2}"));
+                format!("%{temp2}")
+            } else {
+                format!("%{r}")
+            }
         };
         let ctx = BinaryEmitCtx {
             right,
@@ -449,55 +486,30 @@ impl<X: Xlen> X86Emitter<X> {
                 if right_is_imm {
                     self.emitf(format!("mov{suffix} {right_val}, %{temp2}"));
                 }
-                if X::VALUE == 32 {
-                    self.emitf(format!("mull %{}", Self::reg_dword(temp2)));
-                    self.emit("testl %eax, %eax");
-                    let done = self.next_label("mulhsu_done");
-                    self.emitf(format!("jns {done}"));
-                    self.emitf(format!("subl %{}, %edx", Self::reg_dword(temp2)));
-                    self.emit_label(&done);
-                    self.emitf(format!("movl %edx, %{dest}"));
+                // Unsigned high product, less rs2 if rs1 is negative; the
+                // sign is tested before `mul` overwrites rax
+                let (acc, high) = if X::VALUE == 32 {
+                    ("eax", "edx")
                 } else {
-                    self.emitf(format!("mulq %{temp2}"));
-                    self.emit("testq %rax, %rax");
-                    let done = self.next_label("mulhsu_done");
-                    self.emitf(format!("jns {done}"));
-                    self.emitf(format!("subq %{temp2}, %rdx"));
-                    self.emit_label(&done);
-                    self.emitf(format!("movq %rdx, %{dest}"));
-                }
+                    ("rax", "rdx")
+                };
+                let negative = self.next_label("mulhsu_neg");
+                let done = self.next_label("mulhsu_done");
+                self.emitf(format!("test{suffix} %{acc}, %{acc}"));
+                self.emitf(format!("js {negative}"));
+                self.emitf(format!("mul{suffix} %{temp2}"));
+                self.emitf(format!("jmp {done}"));
+                self.emit_label(&negative);
+                self.emitf(format!("mul{suffix} %{temp2}"));
+                self.emitf(format!("sub{suffix} %{temp2}, %{high}"));
+                self.emit_label(&done);
+                self.emitf(format!("mov{suffix} %{high}, %{dest}"));
                 Some(dest.to_string())
             }
             _ => None,
         }
     }
 
-    fn emit_binary_div_rem(
-        &mut self,
-        op: BinaryOp,
-        right_is_imm: bool,
-        right_val: &str,
-        temp1: &str,
-        temp2: &str,
-        dest: &str,
-    ) -> Option<String> {
-        match op {
-            BinaryOp::Div => Some(self.emit_div_signed(right_is_imm, right_val, temp2, dest)),
-            BinaryOp::DivU => Some(self.emit_div_unsigned(right_is_imm, right_val, temp2, dest)),
-            BinaryOp::Rem => {
-                Some(self.emit_rem_signed(right_is_imm, right_val, temp1, temp2, dest))
-            }
-            BinaryOp::RemU => {
-                Some(self.emit_rem_unsigned(right_is_imm, right_val, temp1, temp2, dest))
-            }
-            BinaryOp::DivW => Some(self.emit_divw_signed(right_is_imm, right_val, temp2, dest)),
-            BinaryOp::DivUW => Some(self.emit_divw_unsigned(right_is_imm, right_val, temp2, dest)),
-            BinaryOp::RemW => Some(self.emit_remw_signed(right_is_imm, right_val, temp2, dest)),
-            BinaryOp::RemUW => Some(self.emit_remw_unsigned(right_is_imm, right_val, temp2, dest)),
-            _ => None,
-        }
-    }
-
     fn emit_binary_compare(
         &mut self,
         op: BinaryOp,
@@ -521,6 +533,26 @@ impl<X: Xlen> X86Emitter<X> {
         Some(dest.to_string())
     }
 
+    /// Check if `op` takes its right operand in temp2, as the x86
+    /// multiply and divide forms with implicit operands do.
+    const fn reads_right_from_temp2(op: BinaryOp) -> bool {
+        matches!(
+            op,
+            BinaryOp::MulW
+                | BinaryOp::MulH
+                | BinaryOp::MulHU
+                | BinaryOp::MulHSU
+                | BinaryOp::Div
+                | BinaryOp::DivU
+                | BinaryOp::Rem
+                | BinaryOp::RemU
+                | BinaryOp::DivW
+                | BinaryOp::DivUW
+                | BinaryOp::RemW
+                | BinaryOp::RemUW
+        )
+    }
+
     fn finish_binary(&mut self, dest: &str, temp1: &str, suffix: &str) -> String {
         if dest != temp1 {
             self.emitf(format!("mov{suffix} %{temp1}, %{dest}"));
@@ -528,304 +560,6 @@ impl<X: Xlen> X86Emitter<X> {
         dest.to_string()
     }
 
-    // Division helpers
-    fn emit_div_signed(
-        &mut self,
-        right_is_imm: bool,
-        right_val: &str,
-        temp2: &str,
-        dest: &str,
-    ) -> String {
-        let suffix = Self::suffix();
-        if right_is_imm {
-            self.emitf(format!("mov{suffix} {right_val}, %{temp2}"));
-        }
-        let skip = self.next_label("div_skip");
-        let done = self.next_label("div_done");
-
-        self.emitf(format!("test{suffix} %{temp2}, %{temp2}"));
-        self.emitf(format!("jnz {skip}"));
-        self.emitf(format!("mov{suffix} $-1, %{dest}"));
-        self.emitf(format!("jmp {done}"));
-        self.emit_label(&skip);
-
-        let no_ov = self.next_label("div_no_ov");
-        if X::VALUE == 32 {
-            self.emit("cmpl $0x80000000, %eax");
-            self.emitf(format!("jne {no_ov}"));
-            self.emitf(format!("cmpl $-1, %{}", Self::reg_dword(temp2)));
-            self.emitf(format!("jne {no_ov}"));
-            self.emitf(format!("movl $0x80000000, %{dest}"));
-            self.emitf(format!("jmp {done}"));
-            self.emit_label(&no_ov);
-            self.emit("cdq");
-            self.emitf(format!("idivl %{}", Self::reg_dword(temp2)));
-            self.emitf(format!("movl %eax, %{dest}"));
-        } else {
-            self.emit("movabsq $0x8000000000000000, %rdx");
-            self.emit("cmpq %rdx, %rax");
-            self.emitf(format!("jne {no_ov}"));
-            self.emitf(format!("cmpq $-1, %{temp2}"));
-            self.emitf(format!("jne {no_ov}"));
-            self.emitf(format!("movq %rdx, %{dest}"));
-            self.emitf(format!("jmp {done}"));
-            self.emit_label(&no_ov);
-            self.emit("cqo");
-            self.emitf(format!("idivq %{temp2}"));
-            self.emitf(format!("movq %rax, %{dest}"));
-        }
-        self.emit_label(&done);
-        dest.to_string()
-    }
-
-    fn emit_div_unsigned(
-        &mut self,
-        right_is_imm: bool,
-        right_val: &str,
-        temp2: &str,
-        dest: &str,
-    ) -> String {
-        let suffix = Self::suffix();
-        if right_is_imm {
-            self.emitf(format!("mov{suffix} {right_val}, %{temp2}"));
-        }
-        let do_div = self.next_label("divu_do");
-        let done = self.next_label("divu_done");
-
-        self.emitf(format!("test{suffix} %{temp2}, %{temp2}"));
-        self.emitf(format!("jnz {do_div}"));
-        self.emitf(format!("mov{suffix} $-1, %{dest}"));
-        self.emitf(format!("jmp {done}"));
-        self.emit_label(&do_div);
-        self.emit("xorl %edx, %edx");
-        if X::VALUE == 32 {
-            self.emitf(format!("divl %{}", Self::reg_dword(temp2)));
-            self.emitf(format!("movl %eax, %{dest}"));
-        } else {
-            self.emitf(format!("divq %{temp2}"));
-            self.emitf(format!("movq %rax, %{dest}"));
-        }
-        self.emit_label(&done);
-        dest.to_string()
-    }
-
-    fn emit_rem_signed(
-        &mut self,
-        right_is_imm: bool,
-        right_val: &str,
-        temp1: &str,
-        temp2: &str,
-        dest: &str,
-    ) -> String {
-        let suffix = Self::suffix();
-        if right_is_imm {
-            self.emitf(format!("mov{suffix} {right_val}, %{temp2}"));
-        }
-        let skip = self.next_label("rem_skip");
-        let done = self.next_label("rem_done");
-
-        self.emitf(format!("test{suffix} %{temp2}, %{temp2}"));
-        self.emitf(format!("jnz {skip}"));
-        self.emitf(format!("mov{suffix} %{temp1}, %{dest}"));
-        self.emitf(format!("jmp {done}"));
-        self.emit_label(&skip);
-
-        let no_ov = self.next_label("rem_no_ov");
-        if X::VALUE == 32 {
-            self.emit("cmpl $0x80000000, %eax");
-            self.emitf(format!("jne {no_ov}"));
-            self.emitf(format!("cmpl $-1, %{}", Self::reg_dword(temp2)));
-            self.emitf(format!("jne {no_ov}"));
-            self.emitf(format!("xorl %{dest}, %{dest}"));
-            self.emitf(format!("jmp {done}"));
-            self.emit_label(&no_ov);
-            self.emit("cdq");
-            self.emitf(format!("idivl %{}", Self::reg_dword(temp2)));
-            self.emitf(format!("movl %edx, %{dest}"));
-        } else {
-            self.emit("movabsq $0x8000000000000000, %rdx");
-            self.emit("cmpq %rdx, %rax");
-            self.emitf(format!("jne {no_ov}"));
-            self.emitf(format!("cmpq $-1, %{temp2}"));
-            self.emitf(format!("jne {no_ov}"));
-            self.emitf(format!("xorq %{dest}, %{dest}"));
-            self.emitf(format!("jmp {done}"));
-            self.emit_label(&no_ov);
-            self.emit("cqo");
-            self.emitf(format!("idivq %{temp2}"));
-            self.emitf(format!("movq %rdx, %{dest}"));
-        }
-        self.emit_label(&done);
-        dest.to_string()
-    }
-
-    fn emit_rem_unsigned(
-        &mut self,
-        right_is_imm: bool,
-        right_val: &str,
-        temp1: &str,
-        temp2: &str,
-        dest: &str,
-    ) -> String {
-        let suffix = Self::suffix();
-        if right_is_imm {
-            self.emitf(format!("mov{suffix} {right_val}, %{temp2}"));
-        }
-        let do_div = self.next_label("remu_do");
-        let done = self.next_label("remu_done");
-
-        self.emitf(format!("test{suffix} %{temp2}, %{temp2}"));
-        self.emitf(format!("jnz {do_div}"));
-        self.emitf(format!("mov{suffix} %{temp1}, %{dest}"));
-        self.emitf(format!("jmp {done}"));
-        self.emit_label(&do_div);
-        self.emit("xorl %edx, %edx");
-        if X::VALUE == 32 {
-            self.emitf(format!("divl %{}", Self::reg_dword(temp2)));
-            self.emitf(format!("movl %edx, %{dest}"));
-        } else {
-            self.emitf(format!("divq %{temp2}"));
-            self.emitf(format!("movq %rdx, %{dest}"));
-        }
-        self.emit_label(&done);
-        dest.to_string()
-    }
-
-    fn emit_divw_signed(
-        &mut self,
-        right_is_imm: bool,
-        right_val: &str,
-        temp2: &str,
-        dest: &str,
-    ) -> String {
-        if right_is_imm {
-            self.emitf(format!("movl {right_val}, %{}", Self::reg_dword(temp2)));
-        }
-        let skip = self.next_label("divw_skip");
-        let done = self.next_label("divw_done");
-
-        self.emitf(format!(
-            "testl %{}, %{}",
-            Self::reg_dword(temp2),
-            Self::reg_dword(temp2)
-        ));
-        self.emitf(format!("jnz {skip}"));
-        self.emitf(format!("movq $-1, %{dest}"));
-        self.emitf(format!("jmp {done}"));
-        self.emit_label(&skip);
-
-        let no_ov = self.next_label("divw_no_ov");
-        self.emit("cmpl $0x80000000, %eax");
-        self.emitf(format!("jne {no_ov}"));
-        self.emitf(format!("cmpl $-1, %{}", Self::reg_dword(temp2)));
-        self.emitf(format!("jne {no_ov}"));
-        self.emit("movl $0x80000000, %eax");
-        self.emitf(format!("movslq %eax, %{dest}"));
-        self.emitf(format!("jmp {done}"));
-        self.emit_label(&no_ov);
-        self.emit("cdq");
-        self.emitf(format!("idivl %{}", Self::reg_dword(temp2)));
-        self.emitf(format!("movslq %eax, %{dest}"));
-        self.emit_label(&done);
-        dest.to_string()
-    }
-
-    fn emit_divw_unsigned(
-        &mut self,
-        right_is_imm: bool,
-        right_val: &str,
-        temp2: &str,
-        dest: &str,
-    ) -> String {
-        if right_is_imm {
-            self.emitf(format!("movl {right_val}, %{}", Self::reg_dword(temp2)));
-        }
-        let do_div = self.next_label("divuw_do");
-        let done = self.next_label("divuw_done");
-
-        self.emitf(format!(
-            "testl %{}, %{}",
-            Self::reg_dword(temp2),
-            Self::reg_dword(temp2)
-        ));
-        self.emitf(format!("jnz {do_div}"));
-        self.emitf(format!("movq $-1, %{dest}"));
-        self.emitf(format!("jmp {done}"));
-        self.emit_label(&do_div);
-        self.emit("xorl %edx, %edx");
-        self.emitf(format!("divl %{}", Self::reg_dword(temp2)));
-        self.emitf(format!("movslq %eax, %{dest}"));
-        self.emit_label(&done);
-        dest.to_string()
-    }
-
-    fn emit_remw_signed(
-        &mut self,
-        right_is_imm: bool,
-        right_val: &str,
-        temp2: &str,
-        dest: &str,
-    ) -> String {
-        if right_is_imm {
-            self.emitf(format!("movl {right_val}, %{}", Self::reg_dword(temp2)));
-        }
-        let skip = self.next_label("remw_skip");
-        let done = self.next_label("remw_done");
-
-        self.emitf(format!(
-            "testl %{}, %{}",
-            Self::reg_dword(temp2),
-            Self::reg_dword(temp2)
-        ));
-        self.emitf(format!("jnz {skip}"));
-        self.emitf(format!("movslq %eax, %{dest}"));
-        self.emitf(format!("jmp {done}"));
-        self.emit_label(&skip);
-
-        let no_ov = self.next_label("remw_no_ov");
-        self.emit("cmpl $0x80000000, %eax");
-        self.emitf(format!("jne {no_ov}"));
-        self.emitf(format!("cmpl $-1, %{}", Self::reg_dword(temp2)));
-        self.emitf(format!("jne {no_ov}"));
-        self.emitf(format!("xorq %{dest}, %{dest}"));
-        self.emitf(format!("jmp {done}"));
-        self.emit_label(&no_ov);
-        self.emit("cdq");
-        self.emitf(format!("idivl %{}", Self::reg_dword(temp2)));
-        self.emitf(format!("movslq %edx, %{dest}"));
-        self.emit_label(&done);
-        dest.to_string()
-    }
-
-    fn emit_remw_unsigned(
-        &mut self,
-        right_is_imm: bool,
-        right_val: &str,
-        temp2: &str,
-        dest: &str,
-    ) -> String {
-        if right_is_imm {
-            self.emitf(format!("movl {right_val}, %{}", Self::reg_dword(temp2)));
-        }
-        let do_div = self.next_label("remuw_do");
-        let done = self.next_label("remuw_done");
-
-        self.emitf(format!(
-            "testl %{}, %{}",
-            Self::reg_dword(temp2),
-            Self::reg_dword(temp2)
-        ));
-        self.emitf(format!("jnz {do_div}"));
-        self.emitf(format!("movslq %eax, %{dest}"));
-        self.emitf(format!("jmp {done}"));
-        self.emit_label(&do_div);
-        self.emit("xorl %edx, %edx");
-        self.emitf(format!("divl %{}", Self::reg_dword(temp2)));
-        self.emitf(format!("movslq %edx, %{dest}"));
-        self.emit_label(&done);
-        dest.to_string()
-    }
-
     pub(crate) fn emit_extern_call(&mut self, fn_name: &str, args: &[Expr<X>]) -> String {
         self.save_hot_regs_to_state();
 
@@ -861,7 +595,7 @@ impl<X: Xlen> X86Emitter<X> {
                 Expr::Imm(val) => {
                     let v = X::to_u64(*val);
                     if X::VALUE == 32 {
-                        let v32 = i32::try_from(v).unwrap_or(0);
+                        let v32 = u32::try_from(v).unwrap_or(0).cast_signed();
                         self.emitf(format!("movl ${v32}, %{arg_reg}"));
                     } else if v > 0x7fff_ffff {
                         self.emitf(format!("movabsq $0x{v:x}, %{arg_reg}"));
@@ -890,168 +624,4 @@ impl<X: Xlen> X86Emitter<X> {
             "rax".to_string()
         }
     }
-
-    /// Emit a unary operation.
-    pub(super) fn emit_unary_op(&mut self, op: UnaryOp, inner: &Expr<X>, dest: &str) -> String {
-        let temp1 = Self::temp1();
-        let suffix = Self::suffix();
-        let inner_reg = self.emit_expr(inner, temp1);
-        if inner_reg != temp1 {
-            self.emitf(format!("mov{suffix} %{inner_reg}, %{temp1}"));
-        }
-
-        if matches!(op, UnaryOp::Neg | UnaryOp::Not) {
-            match op {
-                UnaryOp::Neg => self.emitf(format!("neg{suffix} %{temp1}")),
-                UnaryOp::Not => self.emitf(format!("not{suffix} %{temp1}")),
-                _ => {}
-            }
-            return self.finish_unary(dest, temp1, suffix);
-        }
-
-        if let Some(result) = self.emit_unary_extend(op, dest, temp1) {
-            return result;
-        }
-        if let Some(result) = self.emit_unary_bitcount(op, dest, temp1, suffix) {
-            return result;
-        }
-        if let Some(result) = self.emit_unary_misc(op, dest, temp1, suffix) {
-            return result;
-        }
-
-        self.emit_comment(&format!("unary op {op:?} simplified"));
-        self.finish_unary(dest, temp1, suffix)
-    }
-
-    fn emit_unary_extend(&mut self, op: UnaryOp, dest: &str, temp1: &str) -> Option<String> {
-        match op {
-            UnaryOp::Sext8 => {
-                if X::VALUE == 32 {
-                    self.emitf(format!("movsbl %al, %{dest}"));
-                } else {
-                    self.emitf(format!("movsbq %al, %{dest}"));
-                }
-                Some(dest.to_string())
-            }
-            UnaryOp::Sext16 => {
-                if X::VALUE == 32 {
-                    self.emitf(format!("movswl %ax, %{dest}"));
-                } else {
-                    self.emitf(format!("movswq %ax, %{dest}"));
-                }
-                Some(dest.to_string())
-            }
-            UnaryOp::Sext32 => {
-                self.emitf(format!("movslq %eax, %{dest}"));
-                Some(dest.to_string())
-            }
-            UnaryOp::Zext8 => {
-                self.emitf(format!("movzbl %al, %{}", Self::reg_dword(dest)));
-                Some(dest.to_string())
-            }
-            UnaryOp::Zext16 => {
-                self.emitf(format!("movzwl %ax, %{}", Self::reg_dword(dest)));
-                Some(dest.to_string())
-            }
-            UnaryOp::Zext32 => {
-                self.emit("movl %eax, %eax");
-                if dest != temp1 {
-                    self.emitf(format!("movq %{temp1}, %{dest}"));
-                }
-                Some(dest.to_string())
-            }
-            _ => None,
-        }
-    }
-
-    fn emit_unary_bitcount(
-        &mut self,
-        op: UnaryOp,
-        dest: &str,
-        temp1: &str,
-        suffix: &str,
-    ) -> Option<String> {
-        match op {
-            UnaryOp::Clz => {
-                let zero_label = self.next_label("clz_zero");
-                let done_label = self.next_label("clz_done");
-                if X::VALUE == 32 {
-                    self.emit("testl %eax, %eax");
-                    self.emitf(format!("jz {zero_label}"));
-                    self.emit("bsrl %eax, %eax");
-                    self.emit("xorl $31, %eax");
-                    self.emitf(format!("jmp {done_label}"));
-                    self.emit_label(&zero_label);
-                    self.emit("movl $32, %eax");
-                } else {
-                    self.emit("testq %rax, %rax");
-                    self.emitf(format!("jz {zero_label}"));
-                    self.emit("bsrq %rax, %rax");
-                    self.emit("xorq $63, %rax");
-                    self.emitf(format!("jmp {done_label}"));
-                    self.emit_label(&zero_label);
-                    self.emit("movq $64, %rax");
-                }
-                self.emit_label(&done_label);
-                Some(self.finish_unary(dest, temp1, suffix))
-            }
-            UnaryOp::Ctz => {
-                let zero_label = self.next_label("ctz_zero");
-                let done_label = self.next_label("ctz_done");
-                if X::VALUE == 32 {
-                    self.emit("testl %eax, %eax");
-                    self.emitf(format!("jz {zero_label}"));
-                    self.emit("bsfl %eax, %eax");
-                    self.emitf(format!("jmp {done_label}"));
-                    self.emit_label(&zero_label);
-                    self.emit("movl $32, %eax");
-                } else {
-                    self.emit("testq %rax, %rax");
-                    self.emitf(format!("jz {zero_label}"));
-                    self.emit("bsfq %rax, %rax");
-                    self.emitf(format!("jmp {done_label}"));
-                    self.emit_label(&zero_label);
-                    self.emit("movq $64, %rax");
-                }
-                self.emit_label(&done_label);
-                Some(self.finish_unary(dest, temp1, suffix))
-            }
-            _ => None,
-        }
-    }
-
-    fn emit_unary_misc(
-        &mut self,
-        op: UnaryOp,
-        dest: &str,
-        temp1: &str,
-        suffix: &str,
-    ) -> Option<String> {
-        match op {
-            UnaryOp::Cpop => {
-                if X::VALUE == 32 {
-                    self.emit("popcntl %eax, %eax");
-                } else {
-                    self.emit("popcntq %rax, %rax");
-                }
-                Some(self.finish_unary(dest, temp1, suffix))
-            }
-            UnaryOp::Rev8 => {
-                if X::VALUE == 32 {
-                    self.emit("bswapl %eax");
-                } else {
-                    self.emit("bswapq %rax");
-                }
-                Some(self.finish_unary(dest, temp1, suffix))
-            }
-            _ => None,
-        }
-    }
-
-    fn finish_unary(&mut self, dest: &str, temp1: &str, suffix: &str) -> String {
-        if dest != temp1 {
-            self.emitf(format!("mov{suffix} %{temp1}, %{dest}"));
-        }
-        dest.to_string()
-    }
 }
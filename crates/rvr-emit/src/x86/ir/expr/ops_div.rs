@@ -0,0 +1,330 @@
+//! Division and remainder lowering.
+
+use super::{BinaryOp, X86Emitter, Xlen};
+
+impl<X: Xlen> X86Emitter<X> {
+    /// Emit the M extension's division and remainder ops, which RISC-V
+    /// defines for a zero divisor and signed overflow.
+    pub(super) fn emit_binary_div_rem(
+        &mut self,
+        op: BinaryOp,
+        right_is_imm: bool,
+        right_val: &str,
+        temp1: &str,
+        temp2: &str,
+        dest: &str,
+    ) -> Option<String> {
+        match op {
+            BinaryOp::Div => Some(self.emit_div_signed(right_is_imm, right_val, temp2, dest)),
+            BinaryOp::DivU => Some(self.emit_div_unsigned(right_is_imm, right_val, temp2, dest)),
+            BinaryOp::Rem => {
+                Some(self.emit_rem_signed(right_is_imm, right_val, temp1, temp2, dest))
+            }
+            BinaryOp::RemU => {
+                Some(self.emit_rem_unsigned(right_is_imm, right_val, temp1, temp2, dest))
+            }
+            BinaryOp::DivW => Some(self.emit_divw_signed(right_is_imm, right_val, temp2, dest)),
+            BinaryOp::DivUW => Some(self.emit_divw_unsigned(right_is_imm, right_val, temp2, dest)),
+            BinaryOp::RemW => Some(self.emit_remw_signed(right_is_imm, right_val, temp2, dest)),
+            BinaryOp::RemUW => Some(self.emit_remw_unsigned(right_is_imm, right_val, temp2, dest)),
+            _ => None,
+        }
+    }
+
+    fn emit_div_signed(
+        &mut self,
+        right_is_imm: bool,
+        right_val: &str,
+        temp2: &str,
+        dest: &str,
+    ) -> String {
+        let suffix = Self::suffix();
+        if right_is_imm {
+            self.emitf(format!("mov{suffix} {right_val}, %{temp2}"));
+        }
+        let skip = self.next_label("div_skip");
+        let done = self.next_label("div_done");
+
+        self.emitf(format!("test{suffix} %{temp2}, %{temp2}"));
+        self.emitf(format!("jnz {skip}"));
+        self.emitf(format!("mov{suffix} $-1, %{dest}"));
+        self.emitf(format!("jmp {done}"));
+        self.emit_label(&skip);
+
+        let no_ov = self.next_label("div_no_ov");
+        if X::VALUE == 32 {
+            self.emit("cmpl $0x80000000, %eax");
+            self.emitf(format!("jne {no_ov}"));
+            self.emitf(format!("cmpl $-1, %{}", Self::reg_dword(temp2)));
+            self.emitf(format!("jne {no_ov}"));
+            self.emitf(format!("movl $0x80000000, %{dest}"));
+            self.emitf(format!("jmp {done}"));
+            self.emit_label(&no_ov);
+            self.emit("cdq");
+            self.emitf(format!("idivl %{}", Self::reg_dword(temp2)));
+            self.emitf(format!("movl %eax, %{dest}"));
+        } else {
+            self.emit("movabsq $0x8000000000000000, %rdx");
+            self.emit("cmpq %rdx, %rax");
+            self.emitf(format!("jne {no_ov}"));
+            self.emitf(format!("cmpq $-1, %{temp2}"));
+            self.emitf(format!("jne {no_ov}"));
+            self.emitf(format!("movq %rdx, %{dest}"));
+            self.emitf(format!("jmp {done}"));
+            self.emit_label(&no_ov);
+            self.emit("cqo");
+            self.emitf(format!("idivq %{temp2}"));
+            self.emitf(format!("movq %rax, %{dest}"));
+        }
+        self.emit_label(&done);
+        dest.to_string()
+    }
+
+    fn emit_div_unsigned(
+        &mut self,
+        right_is_imm: bool,
+        right_val: &str,
+        temp2: &str,
+        dest: &str,
+    ) -> String {
+        let suffix = Self::suffix();
+        if right_is_imm {
+            self.emitf(format!("mov{suffix} {right_val}, %{temp2}"));
+        }
+        let do_div = self.next_label("divu_do");
+        let done = self.next_label("divu_done");
+
+        self.emitf(format!("test{suffix} %{temp2}, %{temp2}"));
+        self.emitf(format!("jnz {do_div}"));
+        self.emitf(format!("mov{suffix} $-1, %{dest}"));
+        self.emitf(format!("jmp {done}"));
+        self.emit_label(&do_div);
+        self.emit("xorl %edx, %edx");
+        if X::VALUE == 32 {
+            self.emitf(format!("divl %{}", Self::reg_dword(temp2)));
+            self.emitf(format!("movl %eax, %{dest}"));
+        } else {
+            self.emitf(format!("divq %{temp2}"));
+            self.emitf(format!("movq %rax, %{dest}"));
+        }
+        self.emit_label(&done);
+        dest.to_string()
+    }
+
+    fn emit_rem_signed(
+        &mut self,
+        right_is_imm: bool,
+        right_val: &str,
+        temp1: &str,
+        temp2: &str,
+        dest: &str,
+    ) -> String {
+        let suffix = Self::suffix();
+        if right_is_imm {
+            self.emitf(format!("mov{suffix} {right_val}, %{temp2}"));
+        }
+        let skip = self.next_label("rem_skip");
+        let done = self.next_label("rem_done");
+
+        self.emitf(format!("test{suffix} %{temp2}, %{temp2}"));
+        self.emitf(format!("jnz {skip}"));
+        self.emitf(format!("mov{suffix} %{temp1}, %{dest}"));
+        self.emitf(format!("jmp {done}"));
+        self.emit_label(&skip);
+
+        let no_ov = self.next_label("rem_no_ov");
+        if X::VALUE == 32 {
+            self.emit("cmpl $0x80000000, %eax");
+            self.emitf(format!("jne {no_ov}"));
+            self.emitf(format!("cmpl $-1, %{}", Self::reg_dword(temp2)));
+            self.emitf(format!("jne {no_ov}"));
+            self.emitf(format!("xorl %{dest}, %{dest}"));
+            self.emitf(format!("jmp {done}"));
+            self.emit_label(&no_ov);
+            self.emit("cdq");
+            self.emitf(format!("idivl %{}", Self::reg_dword(temp2)));
+            self.emitf(format!("movl %edx, %{dest}"));
+        } else {
+            self.emit("movabsq $0x8000000000000000, %rdx");
+            self.emit("cmpq %rdx, %rax");
+            self.emitf(format!("jne {no_ov}"));
+            self.emitf(format!("cmpq $-1, %{temp2}"));
+            self.emitf(format!("jne {no_ov}"));
+            self.emitf(format!("xorq %{dest}, %{dest}"));
+            self.emitf(format!("jmp {done}"));
+            self.emit_label(&no_ov);
+            self.emit("cqo");
+            self.emitf(format!("idivq %{temp2}"));
+            self.emitf(format!("movq %rdx, %{dest}"));
+        }
+        self.emit_label(&done);
+        dest.to_string()
+    }
+
+    fn emit_rem_unsigned(
+        &mut self,
+        right_is_imm: bool,
+        right_val: &str,
+        temp1: &str,
+        temp2: &str,
+        dest: &str,
+    ) -> String {
+        let suffix = Self::suffix();
+        if right_is_imm {
+            self.emitf(format!("mov{suffix} {right_val}, %{temp2}"));
+        }
+        let do_div = self.next_label("remu_do");
+        let done = self.next_label("remu_done");
+
+        self.emitf(format!("test{suffix} %{temp2}, %{temp2}"));
+        self.emitf(format!("jnz {do_div}"));
+        self.emitf(format!("mov{suffix} %{temp1}, %{dest}"));
+        self.emitf(format!("jmp {done}"));
+        self.emit_label(&do_div);
+        self.emit("xorl %edx, %edx");
+        if X::VALUE == 32 {
+            self.emitf(format!("divl %{}", Self::reg_dword(temp2)));
+            self.emitf(format!("movl %edx, %{dest}"));
+        } else {
+            self.emitf(format!("divq %{temp2}"));
+            self.emitf(format!("movq %rdx, %{dest}"));
+        }
+        self.emit_label(&done);
+        dest.to_string()
+    }
+
+    fn emit_divw_signed(
+        &mut self,
+        right_is_imm: bool,
+        right_val: &str,
+        temp2: &str,
+        dest: &str,
+    ) -> String {
+        if right_is_imm {
+            self.emitf(format!("movl {right_val}, %{}", Self::reg_dword(temp2)));
+        }
+        let skip = self.next_label("divw_skip");
+        let done = self.next_label("divw_done");
+
+        self.emitf(format!(
+            "testl %{}, %{}",
+            Self::reg_dword(temp2),
+            Self::reg_dword(temp2)
+        ));
+        self.emitf(format!("jnz {skip}"));
+        self.emitf(format!("movq $-1, %{dest}"));
+        self.emitf(format!("jmp {done}"));
+        self.emit_label(&skip);
+
+        let no_ov = self.next_label("divw_no_ov");
+        self.emit("cmpl $0x80000000, %eax");
+        self.emitf(format!("jne {no_ov}"));
+        self.emitf(format!("cmpl $-1, %{}", Self::reg_dword(temp2)));
+        self.emitf(format!("jne {no_ov}"));
+        self.emit("movl $0x80000000, %eax");
+        self.emitf(format!("movslq %eax, %{dest}"));
+        self.emitf(format!("jmp {done}"));
+        self.emit_label(&no_ov);
+        self.emit("cdq");
+        self.emitf(format!("idivl %{}", Self::reg_dword(temp2)));
+        self.emitf(format!("movslq %eax, %{dest}"));
+        self.emit_label(&done);
+        dest.to_string()
+    }
+
+    fn emit_divw_unsigned(
+        &mut self,
+        right_is_imm: bool,
+        right_val: &str,
+        temp2: &str,
+        dest: &str,
+    ) -> String {
+        if right_is_imm {
+            self.emitf(format!("movl {right_val}, %{}", Self::reg_dword(temp2)));
+        }
+        let do_div = self.next_label("divuw_do");
+        let done = self.next_label("divuw_done");
+
+        self.emitf(format!(
+            "testl %{}, %{}",
+            Self::reg_dword(temp2),
+            Self::reg_dword(temp2)
+        ));
+        self.emitf(format!("jnz {do_div}"));
+        self.emitf(format!("movq $-1, %{dest}"));
+        self.emitf(format!("jmp {done}"));
+        self.emit_label(&do_div);
+        self.emit("xorl %edx, %edx");
+        self.emitf(format!("divl %{}", Self::reg_dword(temp2)));
+        self.emitf(format!("movslq %eax, %{dest}"));
+        self.emit_label(&done);
+        dest.to_string()
+    }
+
+    fn emit_remw_signed(
+        &mut self,
+        right_is_imm: bool,
+        right_val: &str,
+        temp2: &str,
+        dest: &str,
+    ) -> String {
+        if right_is_imm {
+            self.emitf(format!("movl {right_val}, %{}", Self::reg_dword(temp2)));
+        }
+        let skip = self.next_label("remw_skip");
+        let done = self.next_label("remw_done");
+
+        self.emitf(format!(
+            "testl %{}, %{}",
+            Self::reg_dword(temp2),
+            Self::reg_dword(temp2)
+        ));
+        self.emitf(format!("jnz {skip}"));
+        self.emitf(format!("movslq %eax, %{dest}"));
+        self.emitf(format!("jmp {done}"));
+        self.emit_label(&skip);
+
+        let no_ov = self.next_label("remw_no_ov");
+        self.emit("cmpl $0x80000000, %eax");
+        self.emitf(format!("jne {no_ov}"));
+        self.emitf(format!("cmpl $-1, %{}", Self::reg_dword(temp2)));
+        self.emitf(format!("jne {no_ov}"));
+        self.emitf(format!("xorq %{dest}, %{dest}"));
+        self.emitf(format!("jmp {done}"));
+        self.emit_label(&no_ov);
+        self.emit("cdq");
+        self.emitf(format!("idivl %{}", Self::reg_dword(temp2)));
+        self.emitf(format!("movslq %edx, %{dest}"));
+        self.emit_label(&done);
+        dest.to_string()
+    }
+
+    fn emit_remw_unsigned(
+        &mut self,
+        right_is_imm: bool,
+        right_val: &str,
+        temp2: &str,
+        dest: &str,
+    ) -> String {
+        if right_is_imm {
+            self.emitf(format!("movl {right_val}, %{}", Self::reg_dword(temp2)));
+        }
+        let do_div = self.next_label("remuw_do");
+        let done = self.next_label("remuw_done");
+
+        self.emitf(format!(
+            "testl %{}, %{}",
+            Self::reg_dword(temp2),
+            Self::reg_dword(temp2)
+        ));
+        self.emitf(format!("jnz {do_div}"));
+        self.emitf(format!("movslq %eax, %{dest}"));
+        self.emitf(format!("jmp {done}"));
+        self.emit_label(&do_div);
+        self.emit("xorl %edx, %edx");
+        self.emitf(format!("divl %{}", Self::reg_dword(temp2)));
+        self.emitf(format!("movslq %edx, %{dest}"));
+        self.emit_label(&done);
+        dest.to_string()
+    }
+}
@@ -0,0 +1,168 @@
+use super::{Expr, UnaryOp, X86Emitter, Xlen};
+
+impl<X: Xlen> X86Emitter<X> {
+    /// Emit a unary operation.
+    pub(super) fn emit_unary_op(&mut self, op: UnaryOp, inner: &Expr<X>, dest: &str) -> String {
+        let temp1 = Self::temp1();
+        let suffix = Self::suffix();
+        let inner_reg = self.emit_expr(inner, temp1);
+        if inner_reg != temp1 {
+            self.emitf(format!("mov{suffix} %{inner_reg}, %{temp1}"));
+        }
+
+        if matches!(op, UnaryOp::Neg | UnaryOp::Not) {
+            match op {
+                UnaryOp::Neg => self.emitf(format!("neg{suffix} %{temp1}")),
+                UnaryOp::Not => self.emitf(format!("not{suffix} %{temp1}")),
+                _ => {}
+            }
+            return self.finish_unary(dest, temp1, suffix);
+        }
+
+        if let Some(result) = self.emit_unary_extend(op, dest, temp1) {
+            return result;
+        }
+        if let Some(result) = self.emit_unary_bitcount(op, dest, temp1, suffix) {
+            return result;
+        }
+        if let Some(result) = self.emit_unary_misc(op, dest, temp1, suffix) {
+            return result;
+        }
+
+        self.emit_comment(&format!("unary op {op:?} simplified"));
+        self.finish_unary(dest, temp1, suffix)
+    }
+
+    fn emit_unary_extend(&mut self, op: UnaryOp, dest: &str, temp1: &str) -> Option<String> {
+        match op {
+            UnaryOp::Sext8 => {
+                if X::VALUE == 32 {
+                    self.emitf(format!("movsbl %al, %{dest}"));
+                } else {
+                    self.emitf(format!("movsbq %al, %{dest}"));
+                }
+                Some(dest.to_string())
+            }
+            UnaryOp::Sext16 => {
+                if X::VALUE == 32 {
+                    self.emitf(format!("movswl %ax, %{dest}"));
+                } else {
+                    self.emitf(format!("movswq %ax, %{dest}"));
+                }
+                Some(dest.to_string())
+            }
+            UnaryOp::Sext32 if X::VALUE == 32 => Some(self.finish_unary(dest, temp1, "l")),
+            UnaryOp::Sext32 => {
+                self.emitf(format!("movslq %eax, %{dest}"));
+                Some(dest.to_string())
+            }
+            UnaryOp::Zext8 => {
+                self.emitf(format!("movzbl %al, %{}", Self::reg_dword(dest)));
+                Some(dest.to_string())
+            }
+            UnaryOp::Zext16 => {
+                self.emitf(format!("movzwl %ax, %{}", Self::reg_dword(dest)));
+                Some(dest.to_string())
+            }
+            UnaryOp::Zext32 => {
+                self.emit("movl %eax, %eax");
+                if dest != temp1 {
+                    self.emitf(format!("movq %{temp1}, %{dest}"));
+                }
+                Some(dest.to_string())
+            }
+            _ => None,
+        }
+    }
+
+    fn emit_unary_bitcount(
+        &mut self,
+        op: UnaryOp,
+        dest: &str,
+        temp1: &str,
+        suffix: &str,
+    ) -> Option<String> {
+        match op {
+            UnaryOp::Clz => {
+                let zero_label = self.next_label("clz_zero");
+                let done_label = self.next_label("clz_done");
+                if X::VALUE == 32 {
+                    self.emit("testl %eax, %eax");
+                    self.emitf(format!("jz {zero_label}"));
+                    self.emit("bsrl %eax, %eax");
+                    self.emit("xorl $31, %eax");
+                    self.emitf(format!("jmp {done_label}"));
+                    self.emit_label(&zero_label);
+                    self.emit("movl $32, %eax");
+                } else {
+                    self.emit("testq %rax, %rax");
+                    self.emitf(format!("jz {zero_label}"));
+                    self.emit("bsrq %rax, %rax");
+                    self.emit("xorq $63, %rax");
+                    self.emitf(format!("jmp {done_label}"));
+                    self.emit_label(&zero_label);
+                    self.emit("movq $64, %rax");
+                }
+                self.emit_label(&done_label);
+                Some(self.finish_unary(dest, temp1, suffix))
+            }
+            UnaryOp::Ctz => {
+                let zero_label = self.next_label("ctz_zero");
+                let done_label = self.next_label("ctz_done");
+                if X::VALUE == 32 {
+                    self.emit("testl %eax, %eax");
+                    self.emitf(format!("jz {zero_label}"));
+                    self.emit("bsfl %eax, %eax");
+                    self.emitf(format!("jmp {done_label}"));
+                    self.emit_label(&zero_label);
+                    self.emit("movl $32, %eax");
+                } else {
+                    self.emit("testq %rax, %rax");
+                    self.emitf(format!("jz {zero_label}"));
+                    self.emit("bsfq %rax, %rax");
+                    self.emitf(format!("jmp {done_label}"));
+                    self.emit_label(&zero_label);
+                    self.emit("movq $64, %rax");
+                }
+                self.emit_label(&done_label);
+                Some(self.finish_unary(dest, temp1, suffix))
+            }
+            _ => None,
+        }
+    }
+
+    fn emit_unary_misc(
+        &mut self,
+        op: UnaryOp,
+        dest: &str,
+        temp1: &str,
+        suffix: &str,
+    ) -> Option<String> {
+        match op {
+            UnaryOp::Cpop => {
+                if X::VALUE == 32 {
+                    self.emit("popcntl %eax, %eax");
+                } else {
+                    self.emit("popcntq %rax, %rax");
+                }
+                Some(self.finish_unary(dest, temp1, suffix))
+            }
+            UnaryOp::Rev8 => {
+                if X::VALUE == 32 {
+                    self.emit("bswapl %eax");
+                } else {
+                    self.emit("bswapq %rax");
+                }
+                Some(self.finish_unary(dest, temp1, suffix))
+            }
+            _ => None,
+        }
+    }
+
+    fn finish_unary(&mut self, dest: &str, temp1: &str, suffix: &str) -> String {
+        if dest != temp1 {
+            self.emitf(format!("mov{suffix} %{temp1}, %{dest}"));
+        }
+        dest.to_string()
+    }
+}
@@ -0,0 +1,59 @@
+//! Operand ordering and spilling for binary ops whose right operand would
+//! clobber the left one.
+
+use super::{BinaryOp, Expr, ReadExpr, X86Emitter, Xlen};
+
+impl<X: Xlen> X86Emitter<X> {
+    /// Emit a binary op whose left operand is kept live across the right one.
+    pub(super) fn emit_binary_op_ordered(
+        &mut self,
+        op: BinaryOp,
+        left: &Expr<X>,
+        right: &Expr<X>,
+        dest: &str,
+    ) -> String {
+        // The left operand waits in temp1 while the right one is evaluated,
+        // which a compound right operand clobbers: evaluate it first instead,
+        // swapping commutative operands or parking it in a spill slot
+        let (left, right) = if is_commutative(op) && is_leaf(left) && !is_leaf(right) {
+            (right, left)
+        } else {
+            (left, right)
+        };
+        if !Self::needs_spill(op, right) {
+            return self.emit_binary_op_with_left(op, left, right, dest);
+        }
+        let slot = self.alloc_spill_slot();
+        self.emit_write_temp(slot, right, Self::temp1());
+        let result = self.emit_binary_op_with_left(op, left, &Expr::temp(slot), dest);
+        self.release_spill_slot();
+        result
+    }
+
+    /// Whether evaluating `right` would clobber temp1; shift counts are
+    /// evaluated without the mask that x86 shifts apply themselves.
+    fn needs_spill(op: BinaryOp, right: &Expr<X>) -> bool {
+        let right = match op {
+            BinaryOp::Sll | BinaryOp::Srl | BinaryOp::Sra => Self::strip_count_mask(right, false),
+            BinaryOp::SllW | BinaryOp::SrlW | BinaryOp::SraW => Self::strip_count_mask(right, true),
+            _ => right,
+        };
+        !is_leaf(right)
+    }
+}
+
+const fn is_commutative(op: BinaryOp) -> bool {
+    matches!(
+        op,
+        BinaryOp::Add | BinaryOp::And | BinaryOp::Or | BinaryOp::Xor | BinaryOp::Mul
+    )
+}
+
+/// An immediate, register or temp read, which evaluates into its
+/// destination alone.
+pub(super) const fn is_leaf<X: Xlen>(expr: &Expr<X>) -> bool {
+    matches!(
+        expr,
+        Expr::Imm(_) | Expr::Read(ReadExpr::Reg(_) | ReadExpr::Temp(_))
+    )
+}
@@ -1,4 +1,4 @@
-use rvr_ir::{Stmt, WriteTarget, Xlen};
+use rvr_ir::{Expr, ReadExpr, Stmt, WriteTarget, Xlen};
 
 use crate::x86::X86Emitter;
 use crate::x86::registers::reserved;
@@ -31,6 +31,19 @@ impl<X: Xlen> X86Emitter<X> {
         temp2: &str,
         suffix: &str,
     ) {
+        if let (
+            WriteTarget::Reg(_),
+            Expr::Read(ReadExpr::Mem {
+                base,
+                offset,
+                width,
+                ..
+            }),
+        ) = (target, value)
+            && self.htif_polls(*width)
+        {
+            self.emit_htif_poll(base, *offset);
+        }
         match target {
             WriteTarget::Reg(reg) => self.emit_write_reg(*reg, value, temp1),
             WriteTarget::Mem {
@@ -52,7 +65,6 @@ impl<X: Xlen> X86Emitter<X> {
         if reg == 0 {
             return;
         }
-        self.cold_cache_invalidate(reg);
         if let Some(x86_reg) = self.reg_map.get(reg) {
             let val_reg = self.emit_expr(value, x86_reg);
             if val_reg != x86_reg {
@@ -129,9 +141,9 @@ impl<X: Xlen> X86Emitter<X> {
         ));
     }
 
-    fn emit_write_temp(&mut self, idx: u8, value: &rvr_ir::Expr<X>, temp1: &str) {
+    pub(super) fn emit_write_temp(&mut self, idx: u8, value: &rvr_ir::Expr<X>, temp1: &str) {
         let val_reg = self.emit_expr(value, temp1);
-        if let Some(offset) = Self::temp_slot_offset(idx) {
+        if let Some(offset) = self.temp_slot_offset(idx) {
             if X::VALUE == 32 {
                 self.emitf(format!(
                     "movl %{}, {}(%rsp)",
@@ -195,12 +207,15 @@ impl<X: Xlen> X86Emitter<X> {
         if !else_stmts.is_empty() {
             self.emitf(format!("jmp {end_label}"));
         }
+        // Each label joins paths that may have cached different registers
         self.emit_label(&else_label);
+        self.cold_cache = None;
         for s in else_stmts {
             self.emit_stmt(s);
         }
         if !else_stmts.is_empty() {
             self.emit_label(&end_label);
+            self.cold_cache = None;
         }
     }
 
@@ -1,4 +1,4 @@
-use rvr_ir::{InstrIR, Terminator, Xlen};
+use rvr_ir::{InstrIR, Terminator, Xlen, block_ends};
 
 use super::stmt_writes_to_exited;
 use crate::x86::X86Emitter;
@@ -71,7 +71,7 @@ impl<X: Xlen> X86Emitter<X> {
             }
             Terminator::Trap { message } => {
                 self.emit_comment(&format!("trap: {message}"));
-                self.emit("jmp asm_trap");
+                self.emit("jmp asm_trap_illegal");
             }
         }
     }
@@ -79,6 +79,7 @@ impl<X: Xlen> X86Emitter<X> {
     /// Emit a single instruction from IR.
     pub(super) fn emit_instruction(&mut self, instr: &InstrIR<X>, is_last: bool, fall_pc: u64) {
         let pc = X::to_u64(instr.pc);
+        self.current_pc = pc;
         self.emit_trace_pc(pc, instr.raw);
         if !self.config.instret_mode.per_instruction() {
             self.emit_instret_increment(1, pc);
@@ -94,19 +95,20 @@ impl<X: Xlen> X86Emitter<X> {
         // If the instruction might set has_exited, check and branch to asm_exit
         if might_exit {
             let has_exited_off = self.layout.offset_has_exited;
-            let suffix = Self::suffix();
             self.emitf(format!(
-                "cmpb $0, {}({})",
+                "cmpb $0, {}(%{})",
                 has_exited_off,
                 reserved::STATE_PTR
             ));
-            self.emit(&format!("jne{suffix} asm_exit"));
+            self.emit("jne asm_exit");
         }
 
         if self.config.instret_mode.per_instruction() {
             self.emit_instret_post_check(instr, fall_pc, pc);
         }
 
+        // Every back-edge ends a block, so `SuspendGranularity::BackEdges`
+        // needs no checks beyond the block ends
         if is_last
             && self.config.instret_mode.suspends()
             && !self.config.instret_mode.per_instruction()
@@ -132,6 +134,7 @@ impl<X: Xlen> X86Emitter<X> {
 
     /// Emit code for a linear instruction stream.
     pub fn emit_instructions(&mut self, instrs: &[InstrIR<X>]) {
+        let ends = block_ends(instrs);
         self.emit_raw("# Generated code instructions");
         self.emit_blank();
         for (i, instr) in instrs.iter().enumerate() {
@@ -139,12 +142,17 @@ impl<X: Xlen> X86Emitter<X> {
             if self.label_pcs.contains(&pc) {
                 self.emit_pc_label(pc);
             }
+            if self.config.emit_guest_pc_map()
+                && let Some(loc) = self.inputs.guest_pc_lines.asm_loc(pc)
+            {
+                self.emit(&loc);
+            }
             let fall_pc = if i + 1 < instrs.len() {
                 X::to_u64(instrs[i + 1].pc)
             } else {
                 pc + u64::from(instr.size)
             };
-            self.emit_instruction(instr, true, fall_pc);
+            self.emit_instruction(instr, ends[i], fall_pc);
         }
     }
 }
@@ -10,11 +10,13 @@
 //! - `dispatch` - Jump table and dispatch logic
 //! - `prologue` - Header, prologue, epilogue, runtime wrapper
 //! - `instructions` - RISC-V instruction emission
+//! - `htif` - HTIF `fromhost` poll watchdog
 //! - `ir` - IR translation (expressions, statements, terminators)
 //! - `registers` - Register mapping
 
 mod dispatch;
 mod emitter;
+mod htif;
 mod instructions;
 mod ir;
 mod prologue;
@@ -51,6 +53,12 @@ pub struct X86Emitter<X: Xlen> {
     pub(self) label_counter: usize,
     /// Cached cold register (RV reg number) stored in `COLD_CACHE`.
     pub(self) cold_cache: Option<u8>,
+    /// Number of spill slots in use by nested binary ops.
+    pub(self) spill_depth: u8,
+    /// Deepest spill nesting so far, which sizes the stack frame.
+    pub(self) max_spill_depth: u8,
+    /// PC of the instruction being emitted.
+    pub(self) current_pc: u64,
 }
 
 impl<X: Xlen> X86Emitter<X> {
@@ -79,6 +87,9 @@ impl<X: Xlen> X86Emitter<X> {
             memory_mask,
             label_counter: 0,
             cold_cache: None,
+            spill_depth: 0,
+            max_spill_depth: 0,
+            current_pc: 0,
         }
     }
 
@@ -151,8 +162,22 @@ mod tests {
             valid_addresses: [0x8000_0000u64, 0x8000_0004, 0x8000_0008]
                 .into_iter()
                 .collect(),
+            code_ranges: Vec::new(),
+            vector: false,
             absorbed_to_merged: std::collections::HashMap::new(),
+            block_to_function: std::collections::HashMap::new(),
+            function_hot_regs: std::collections::HashMap::new(),
+            synthetic_blocks: std::collections::HashMap::new(),
+            quarantined: std::collections::BTreeMap::new(),
+            cold_blocks: std::collections::HashSet::new(),
+            exported_functions: Vec::new(),
             initial_brk: 0x8000_1000,
+            elf_hash: 0,
+            memory_layout: None,
+            guest_pc_lines: std::sync::Arc::default(),
+            host_hooks: Vec::new(),
+            golden: None,
+            interpreted: std::collections::BTreeSet::new(),
         }
     }
 
@@ -197,6 +222,76 @@ mod tests {
         assert!(asm.contains("asm_trap:"));
     }
 
+    #[test]
+    fn test_runtime_wrapper_keeps_state_across_asm_run() {
+        let config = EmitConfig::<Rv64>::default();
+        let mut emitter = X86Emitter::new(config, test_inputs());
+        emitter.emit_runtime_wrapper();
+        let asm = emitter.assembly();
+        // rdi holds a hot guest register when asm_run returns
+        let after_call = asm.split("call asm_run").nth(1).unwrap();
+        assert!(!after_call.contains("(%rdi)"));
+        assert!(after_call.contains("(%rbx), %eax"));
+    }
+
+    #[test]
+    fn test_exit_syscall_checks_has_exited() {
+        use rvr_ir::{Expr, InstrIR, Stmt, Terminator};
+
+        let config = EmitConfig::<Rv64>::default();
+        let mut emitter = X86Emitter::new(config, test_inputs());
+        let ecall = InstrIR::new(
+            0x8000_0000,
+            4,
+            0,
+            0,
+            vec![Stmt::write_exited(Expr::imm(1))],
+            Terminator::fall(0x8000_0004),
+        );
+        emitter.generate_instructions(&[ecall]);
+        let check = format!(
+            "cmpb $0, {}(%rbx)\n    jne asm_exit\n",
+            emitter.layout().offset_has_exited
+        );
+        assert!(emitter.assembly().contains(&check));
+    }
+
+    #[test]
+    fn test_htif_poll_before_fromhost_load() {
+        use rvr_ir::{Expr, InstrIR, Stmt, Terminator};
+
+        let load = InstrIR::new(
+            0x8000_0000,
+            4,
+            0,
+            0,
+            vec![Stmt::write_reg(6, Expr::mem(Expr::reg(8), 0x40, 8, false))],
+            Terminator::fall(0x8000_0004),
+        );
+        for (limit, polls) in [(1000, true), (0, false)] {
+            let config = EmitConfig::<Rv64>::default()
+                .with_tohost(true)
+                .with_htif_poll_limit(limit);
+            let mut emitter = X86Emitter::new(config, test_inputs());
+            emitter.generate_instructions(std::slice::from_ref(&load));
+            let asm = emitter.assembly();
+            assert_eq!(asm.contains("call htif_poll"), polls, "limit {limit}");
+            assert_eq!(asm.contains("cmpl $0x80001040, %eax"), polls);
+        }
+    }
+
+    #[test]
+    fn test_cold_cache_invalidated_by_store() {
+        let config = EmitConfig::<Rv64>::default();
+        let mut emitter = X86Emitter::new(config, test_inputs());
+        // x5 is cold: the first read caches it, the store must drop the cache
+        assert!(emitter.rv_reg(5).is_none());
+        let cached = emitter.load_rv_to_temp(5, "rax");
+        emitter.store_to_rv(5, "rax");
+        assert_eq!(emitter.load_rv_to_temp(5, "rax"), cached);
+        assert_eq!(emitter.assembly().matches("movq 40(%rbx)").count(), 2);
+    }
+
     #[test]
     fn test_emit_jump_table() {
         let config = EmitConfig::<Rv64>::default();
@@ -225,6 +320,77 @@ mod tests {
         assert_eq!(layout.reg_offset(1), 8);
     }
 
+    /// Assembly for the single instruction `x5 = value`.
+    fn emit_write_x5<X: Xlen>(value: rvr_ir::Expr<X>) -> String {
+        use rvr_ir::{Stmt, Terminator};
+
+        let mut emitter = X86Emitter::new(EmitConfig::<X>::default(), test_inputs());
+        let instr = InstrIR::new(
+            X::from_u64(0x8000_0000),
+            4,
+            0,
+            0,
+            vec![Stmt::write_reg(5, value)],
+            Terminator::fall(X::from_u64(0x8000_0004)),
+        );
+        emitter.generate_instructions(&[instr]);
+        emitter.assembly().to_string()
+    }
+
+    #[test]
+    fn test_temp3_is_not_a_hot_register() {
+        use registers::{AVAILABLE_REGS, AVAILABLE_REGS_32};
+
+        assert!(!AVAILABLE_REGS.contains(&X86Emitter::<Rv64>::temp3()));
+        assert!(!AVAILABLE_REGS_32.contains(&X86Emitter::<rvr_ir::Rv32>::temp3()));
+    }
+
+    #[test]
+    fn test_compound_right_operand_is_spilled() {
+        use rvr_ir::Expr;
+
+        // ror x5, x6, x7: both shifts go through temp1
+        let (mask, xlen) = (Expr::imm(63), Expr::imm(64));
+        let asm = emit_write_x5::<Rv64>(Expr::or(
+            Expr::srl(Expr::reg(6), Expr::and(Expr::reg(7), mask.clone())),
+            Expr::sll(Expr::reg(6), Expr::and(Expr::sub(xlen, Expr::reg(7)), mask)),
+        ));
+        let spill = asm.find("movq %rax, 64(%rsp)").unwrap();
+        let reload = asm.find("movq 64(%rsp), %rcx").unwrap();
+        assert!(spill < reload);
+        assert!(asm.contains(".set FRAME_BYTES, 88"));
+    }
+
+    #[test]
+    fn test_spill_area_grows_with_nesting() {
+        use rvr_ir::Expr;
+
+        // Each level's compound right operand takes another spill slot
+        let mut value = Expr::reg(7);
+        for _ in 0..6 {
+            value = Expr::sub(Expr::reg(6), Expr::xor(Expr::reg(6), value));
+        }
+        let asm = emit_write_x5::<Rv64>(value);
+        assert!(asm.contains("movq 104(%rsp), %rcx"));
+        assert!(asm.contains(".set FRAME_BYTES, 120"));
+    }
+
+    #[test]
+    fn test_addw_negative_immediate() {
+        use rvr_ir::Expr;
+
+        let asm = emit_write_x5::<Rv64>(Expr::addw(Expr::reg(6), Expr::imm(u64::MAX)));
+        assert!(asm.contains("addl $-1, %eax"));
+    }
+
+    #[test]
+    fn test_rv32_immediate_with_sign_bit() {
+        use rvr_ir::Expr;
+
+        let asm = emit_write_x5::<rvr_ir::Rv32>(Expr::imm(0x8000_0000));
+        assert!(asm.contains("movl $-2147483648, %"), "{asm}");
+    }
+
     #[test]
     fn test_full_generation() {
         let config = EmitConfig::<Rv64>::default();
@@ -2,9 +2,11 @@
 
 use rvr_ir::Xlen;
 
-use super::X86Emitter;
 use super::registers::reserved;
-use crate::c::TracerKind;
+use super::{HOT_REG_SLOTS, X86Emitter};
+use crate::abi::{ABI_INFO_SYMBOL, AbiFeature, RvAbiInfo};
+use crate::c::{TracerKind, asm_file_directive};
+use crate::layout::{ASM_TRAP_ENTRIES, STATE_LAYOUT_VERSION};
 
 impl<X: Xlen> X86Emitter<X> {
     /// Emit the assembly file header.
@@ -30,6 +32,14 @@ impl<X: Xlen> X86Emitter<X> {
             ".set EXIT_CODE_OFFSET, {}",
             self.layout.offset_exit_code
         ));
+        self.emitf(format!(
+            ".set EXIT_CAUSE_OFFSET, {}",
+            self.layout.offset_exit_cause
+        ));
+        self.emitf(format!(
+            ".set EXIT_INFO_OFFSET, {}",
+            self.layout.offset_exit_info
+        ));
         self.emitf(format!(".set MEMORY_OFFSET, {}", self.layout.offset_memory));
 
         // Fixed address constants if enabled
@@ -38,6 +48,10 @@ impl<X: Xlen> X86Emitter<X> {
             self.emitf(format!(".set RV_MEMORY_ADDR, 0x{:x}", fixed.memory_addr));
         }
 
+        if self.config.emit_guest_pc_map() {
+            self.emit_raw(&asm_file_directive());
+        }
+
         self.emit_blank();
     }
 
@@ -60,9 +74,9 @@ impl<X: Xlen> X86Emitter<X> {
         self.emit("pushq %r13");
         self.emit("pushq %r14");
         self.emit("pushq %r15");
-        let stack_bytes = 8 + Self::TEMP_STACK_BYTES;
-        self.emit_comment("Align stack to 16 bytes and reserve temp slots");
-        self.emitf(format!("subq ${stack_bytes}, %rsp"));
+        // FRAME_BYTES is set by the epilogue, once the spill depth is known
+        self.emit_comment("Align stack to 16 bytes and reserve temp and spill slots");
+        self.emit("subq $FRAME_BYTES, %rsp");
         self.emit_blank();
 
         self.emit_comment("Setup pointers");
@@ -134,8 +148,8 @@ impl<X: Xlen> X86Emitter<X> {
         self.emit_blank();
 
         self.emit_comment("Restore stack and callee-saved registers");
-        let stack_bytes = 8 + Self::TEMP_STACK_BYTES;
-        self.emitf(format!("addq ${stack_bytes}, %rsp"));
+        self.emitf(format!(".set FRAME_BYTES, {}", self.frame_bytes()));
+        self.emit("addq $FRAME_BYTES, %rsp");
         self.emit("popq %r15");
         self.emit("popq %r14");
         self.emit("popq %r13");
@@ -145,8 +159,22 @@ impl<X: Xlen> X86Emitter<X> {
         self.emit("ret");
         self.emit_blank();
 
-        self.emit_label("asm_trap");
-        self.emit_comment("Trap handler - set exit flag and exit (exit_code stays 0)");
+        self.emit_comment("Trap entries - record the cause, then stop");
+        let exit_cause = self.layout.offset_exit_cause;
+        for (i, (label, cause)) in ASM_TRAP_ENTRIES.iter().enumerate() {
+            self.emit_label(label);
+            self.emitf(format!(
+                "movl ${}, {}(%{})",
+                *cause as u32,
+                exit_cause,
+                reserved::STATE_PTR
+            ));
+            if i + 1 < ASM_TRAP_ENTRIES.len() {
+                self.emit("jmp asm_trap_stop");
+            }
+        }
+        self.emit_label("asm_trap_stop");
+        self.emit_comment("Set exit flag and exit (exit_code stays 0)");
         let has_exited = self.layout.offset_has_exited;
         self.emitf(format!("movb $1, {}(%{})", has_exited, reserved::STATE_PTR));
         self.emit("jmp asm_exit");
@@ -177,12 +205,17 @@ impl<X: Xlen> X86Emitter<X> {
             self.emitf(format!("movq {memory_offset}(%rdi), %rsi"));
         }
 
-        // Call asm_run
+        // rdi and rsi are hot guest registers inside asm_run, so keep the state
+        // pointer in callee-saved rbx across the call (the push also aligns
+        // the stack for asm_run's prologue)
+        self.emit("pushq %rbx");
+        self.emit("movq %rdi, %rbx");
         self.emit("call asm_run");
 
         // Return has_exited
         let has_exited_offset = self.layout.offset_has_exited;
-        self.emitf(format!("movzbl {has_exited_offset}(%rdi), %eax"));
+        self.emitf(format!("movzbl {has_exited_offset}(%rbx), %eax"));
+        self.emit("popq %rbx");
         self.emit("ret");
         self.emit_blank();
     }
@@ -192,6 +225,15 @@ impl<X: Xlen> X86Emitter<X> {
         self.emit_raw(".section .rodata");
         self.emit_blank();
 
+        // rv_abi_info (asm code never polls cancel_requested)
+        let abi = RvAbiInfo::new(&self.config).without(AbiFeature::CheckCancel);
+        let words: Vec<String> = abi.words().iter().map(u32::to_string).collect();
+        self.emit_raw(".balign 4");
+        self.emit_raw(&format!(".global {ABI_INFO_SYMBOL}"));
+        self.emit_label(ABI_INFO_SYMBOL);
+        self.emitf(format!(".long {}", words.join(", ")));
+        self.emit_blank();
+
         // RV_TRACER_KIND
         let tracer_kind = self
             .config
@@ -218,6 +260,18 @@ impl<X: Xlen> X86Emitter<X> {
         self.emitf(format!(".long {instret_mode}"));
         self.emit_blank();
 
+        // RV_NUM_REGS
+        let num_regs = self.config.num_regs;
+        self.emit_raw(".global RV_NUM_REGS");
+        self.emit_label("RV_NUM_REGS");
+        self.emitf(format!(".long {num_regs}"));
+        self.emit_blank();
+
+        self.emit_raw(".global RV_STATE_LAYOUT_VERSION");
+        self.emit_label("RV_STATE_LAYOUT_VERSION");
+        self.emitf(format!(".long {STATE_LAYOUT_VERSION}"));
+        self.emit_blank();
+
         // Fixed addresses (if enabled)
         if let Some(fixed) = self.config.fixed_addresses {
             self.emit_raw(".global RV_FIXED_STATE_ADDR");
@@ -230,5 +284,33 @@ impl<X: Xlen> X86Emitter<X> {
             self.emitf(format!(".quad 0x{:x}", fixed.memory_addr));
             self.emit_blank();
         }
+
+        // Load bias (position-independent ELFs only)
+        if let Some(bias) = self.config.load_bias {
+            self.emit_raw(".global RV_LOAD_BIAS");
+            self.emit_label("RV_LOAD_BIAS");
+            self.emitf(format!(".quad 0x{bias:x}"));
+            self.emit_blank();
+        }
+
+        if self.config.lazy_segment_init() {
+            self.emit_raw(".global RV_LAZY_SEGMENTS");
+            self.emit_label("RV_LAZY_SEGMENTS");
+            self.emitf(".long 1");
+            self.emit_blank();
+        }
+
+        // Guest register in each hot register slot (0 if unused), for
+        // debuggers that follow the host code with the guest PC map
+        if self.config.emit_guest_pc_map() {
+            let slots: Vec<String> = (0..HOT_REG_SLOTS)
+                .map(|slot| self.config.hot_regs.get(slot).copied().unwrap_or(0))
+                .map(|reg| reg.to_string())
+                .collect();
+            self.emit_raw(".global RV_HOT_REGS");
+            self.emit_label("RV_HOT_REGS");
+            self.emitf(format!(".byte {}", slots.join(", ")));
+            self.emit_blank();
+        }
     }
 }
@@ -1,5 +1,7 @@
 //! Basic block IR.
 
+use std::collections::HashSet;
+
 use crate::xlen::Xlen;
 
 use crate::instr::InstrIR;
@@ -52,3 +54,52 @@ impl<X: Xlen> BlockIR<X> {
         self.instructions.is_empty()
     }
 }
+
+/// Which instructions of a linear stream end a basic block.
+///
+/// A block ends at control flow, at a fall-through anywhere but the next
+/// instruction, before a static branch or jump target, and at the end of
+/// the stream.
+pub fn block_ends<X: Xlen>(instrs: &[InstrIR<X>]) -> Vec<bool> {
+    let targets: HashSet<u64> = instrs
+        .iter()
+        .flat_map(|instr| instr.terminator.static_targets())
+        .map(X::to_u64)
+        .collect();
+    instrs
+        .iter()
+        .enumerate()
+        .map(|(i, instr)| {
+            let Some(next) = instrs.get(i + 1) else {
+                return true;
+            };
+            let next_pc = X::to_u64(next.pc);
+            instr.terminator.is_control_flow()
+                || targets.contains(&next_pc)
+                || instr
+                    .terminator
+                    .fall_target()
+                    .is_some_and(|target| X::to_u64(target) != next_pc)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::Expr;
+    use crate::xlen::Rv64;
+
+    #[test]
+    fn test_block_ends() {
+        let instr = |pc, terminator| InstrIR::<Rv64>::new(pc, 4, 0, 0, Vec::new(), terminator);
+        let instrs = [
+            instr(0x1000, Terminator::fall(0x1004)),
+            instr(0x1004, Terminator::fall(0x1008)),
+            instr(0x1008, Terminator::branch(Expr::reg(5), 0x1004)),
+            instr(0x100c, Terminator::fall(0x1000)),
+            instr(0x1010, Terminator::fall(0x1014)),
+        ];
+        assert_eq!(block_ends(&instrs), [true, false, true, true, true]);
+    }
+}
@@ -0,0 +1,359 @@
+//! Dead register write elimination.
+//!
+//! Within a block, a register write that is overwritten before anything reads
+//! it is dropped, as are writes of a register to itself and pure writes to x0.
+//! Liveness is computed backwards over the block. Every register is live at
+//! the end of the block and wherever guest state can be observed:
+//! - side exits (a branch in the middle of a superblock) and any other
+//!   terminator that leaves the block;
+//! - extern calls and C variables (syscalls, CSR helpers), which may read any
+//!   register;
+//! - exit flag writes, after which the block returns early;
+//! - memory accesses that can trap or exit, per [`TrappingAccesses`].
+//!
+//! Writes whose value calls out or reads memory or a CSR (hook CSRs call the
+//! host) are kept, so no side effect or trap is removed. Trace hooks observe
+//! every register write, so traced builds must not run the pass.
+
+use crate::block::BlockIR;
+use crate::expr::{Expr, ReadExpr};
+use crate::stmt::{Stmt, WriteTarget};
+use crate::terminator::Terminator;
+use crate::xlen::Xlen;
+
+/// Set of registers, one bit per index.
+type RegSet = u32;
+
+const ALL_REGS: RegSet = RegSet::MAX;
+
+/// Memory accesses that can end a block early, leaving guest state
+/// observable at the access.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TrappingAccesses {
+    /// No access traps: unchecked or wrapped addresses and no store checks.
+    #[default]
+    None,
+    /// Stores can trap or exit: HTIF `tohost`, code-write detection or the
+    /// stack guard.
+    Stores,
+    /// Loads and stores can trap or exit: bounds-checked addresses or the
+    /// HTIF `fromhost` poll watchdog.
+    All,
+}
+
+impl TrappingAccesses {
+    const fn loads(self) -> bool {
+        matches!(self, Self::All)
+    }
+
+    const fn stores(self) -> bool {
+        !matches!(self, Self::None)
+    }
+}
+
+const fn reg_bit(reg: u8) -> RegSet {
+    match (1 as RegSet).checked_shl(reg as u32) {
+        Some(bit) => bit,
+        None => 0,
+    }
+}
+
+/// Remove dead register writes from `block`, returning how many were removed.
+///
+/// `traps` are the memory accesses that can end the block early.
+pub fn eliminate_dead_reg_writes<X: Xlen>(
+    block: &mut BlockIR<X>,
+    traps: TrappingAccesses,
+) -> usize {
+    let last = block.instructions.len().saturating_sub(1);
+    // Registers overwritten later in the block before being read or observed
+    let mut dead: RegSet = 0;
+    let mut removed = 0;
+    for (idx, instr) in block.instructions.iter_mut().enumerate().rev() {
+        // Jumps before the last instruction only join absorbed ranges
+        let internal = idx < last
+            && matches!(
+                instr.terminator,
+                Terminator::Fall { .. } | Terminator::Jump { .. }
+            );
+        if !internal {
+            dead = 0;
+        }
+        removed += eliminate_in_stmts(&mut instr.statements, &mut dead, traps);
+    }
+    removed
+}
+
+/// Backward pass over one instruction's statements.
+fn eliminate_in_stmts<X: Xlen>(
+    stmts: &mut Vec<Stmt<X>>,
+    dead: &mut RegSet,
+    traps: TrappingAccesses,
+) -> usize {
+    let mut removed = 0;
+    for i in (0..stmts.len()).rev() {
+        if let Stmt::Write {
+            target: WriteTarget::Reg(reg),
+            value,
+        } = &stmts[i]
+        {
+            let bit = reg_bit(*reg);
+            let unobserved = *dead & bit != 0 || *reg == 0 || is_self_move(*reg, value);
+            if unobserved && is_pure(value) {
+                stmts.remove(i);
+                removed += 1;
+                continue;
+            }
+            *dead |= bit;
+            *dead &= !expr_uses(value, traps);
+        } else {
+            *dead &= !stmt_uses(&stmts[i], traps);
+        }
+    }
+    removed
+}
+
+const fn is_self_move<X: Xlen>(reg: u8, value: &Expr<X>) -> bool {
+    matches!(value, Expr::Read(ReadExpr::Reg(src)) if *src == reg)
+}
+
+/// True if evaluating `expr` has no effect besides its value.
+fn is_pure<X: Xlen>(expr: &Expr<X>) -> bool {
+    match expr {
+        Expr::Read(ReadExpr::Mem { .. } | ReadExpr::MemAddr { .. } | ReadExpr::Csr(_))
+        | Expr::Var(_)
+        | Expr::ExternCall { .. } => false,
+        Expr::Imm(_) | Expr::PcConst(_) | Expr::Read(_) => true,
+        Expr::Unary { expr, .. } => is_pure(expr),
+        Expr::Binary { left, right, .. } => is_pure(left) && is_pure(right),
+        Expr::Ternary {
+            first,
+            second,
+            third,
+            ..
+        } => is_pure(first) && is_pure(second) && is_pure(third),
+    }
+}
+
+/// Registers `expr` reads, or all of them if it can observe guest state.
+fn expr_uses<X: Xlen>(expr: &Expr<X>, traps: TrappingAccesses) -> RegSet {
+    match expr {
+        Expr::Imm(_) | Expr::PcConst(_) => 0,
+        Expr::Var(_) | Expr::ExternCall { .. } => ALL_REGS,
+        Expr::Read(read) => match read {
+            ReadExpr::Reg(reg) => reg_bit(*reg),
+            ReadExpr::Mem { .. } | ReadExpr::MemAddr { .. } if traps.loads() => ALL_REGS,
+            ReadExpr::Mem { base, .. } => expr_uses(base, traps),
+            ReadExpr::MemAddr { addr, .. } => expr_uses(addr, traps),
+            _ => 0,
+        },
+        Expr::Unary { expr, .. } => expr_uses(expr, traps),
+        Expr::Binary { left, right, .. } => expr_uses(left, traps) | expr_uses(right, traps),
+        Expr::Ternary {
+            first,
+            second,
+            third,
+            ..
+        } => expr_uses(first, traps) | expr_uses(second, traps) | expr_uses(third, traps),
+    }
+}
+
+/// Registers a statement reads, or all of them if guest state can be
+/// observed during it. Writes nested in an `If` are conditional, so they do
+/// not make a register dead.
+fn stmt_uses<X: Xlen>(stmt: &Stmt<X>, traps: TrappingAccesses) -> RegSet {
+    match stmt {
+        Stmt::Write { target, value } => match target {
+            WriteTarget::Exited => ALL_REGS,
+            WriteTarget::Mem { .. } if traps.stores() => ALL_REGS,
+            WriteTarget::Mem { base, .. } => expr_uses(base, traps) | expr_uses(value, traps),
+            _ => expr_uses(value, traps),
+        },
+        Stmt::If {
+            cond,
+            then_stmts,
+            else_stmts,
+        } => then_stmts
+            .iter()
+            .chain(else_stmts)
+            .fold(expr_uses(cond, traps), |uses, stmt| {
+                uses | stmt_uses(stmt, traps)
+            }),
+        Stmt::ExternCall { .. } => ALL_REGS,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instr::InstrIR;
+    use crate::xlen::Rv64;
+
+    const T0: u8 = 5;
+    const A0: u8 = 10;
+    const A1: u8 = 11;
+
+    fn block(instrs: Vec<(Vec<Stmt<Rv64>>, Terminator<Rv64>)>) -> BlockIR<Rv64> {
+        let mut block = BlockIR::new(0x1000);
+        let mut pc = 0x1000;
+        for (stmts, terminator) in instrs {
+            block.push(InstrIR::new(pc, 4, 0, 0, stmts, terminator));
+            pc += 4;
+        }
+        block
+    }
+
+    fn fall() -> Terminator<Rv64> {
+        Terminator::Fall { target: None }
+    }
+
+    fn write_count(block: &BlockIR<Rv64>) -> usize {
+        block
+            .instructions
+            .iter()
+            .map(|instr| instr.statements.len())
+            .sum()
+    }
+
+    #[test]
+    fn test_overwritten_write_is_removed() {
+        let mut b = block(vec![
+            (vec![Stmt::write_reg(A0, Expr::imm(1))], fall()),
+            (vec![Stmt::write_reg(A0, Expr::imm(2))], fall()),
+            (vec![Stmt::write_reg(A1, Expr::reg(A0))], fall()),
+        ]);
+        assert_eq!(eliminate_dead_reg_writes(&mut b, TrappingAccesses::None), 1);
+        assert!(b.instructions[0].statements.is_empty());
+        assert_eq!(write_count(&b), 2);
+    }
+
+    #[test]
+    fn test_read_keeps_write() {
+        let mut b = block(vec![
+            (vec![Stmt::write_reg(A0, Expr::imm(1))], fall()),
+            (vec![Stmt::write_reg(A1, Expr::reg(A0))], fall()),
+            (vec![Stmt::write_reg(A0, Expr::imm(2))], fall()),
+        ]);
+        assert_eq!(eliminate_dead_reg_writes(&mut b, TrappingAccesses::None), 0);
+    }
+
+    #[test]
+    fn test_write_live_at_block_end() {
+        let mut b = block(vec![(vec![Stmt::write_reg(A0, Expr::imm(1))], fall())]);
+        assert_eq!(eliminate_dead_reg_writes(&mut b, TrappingAccesses::None), 0);
+    }
+
+    #[test]
+    fn test_self_move_is_removed() {
+        let mut b = block(vec![(vec![Stmt::write_reg(A0, Expr::reg(A0))], fall())]);
+        assert_eq!(eliminate_dead_reg_writes(&mut b, TrappingAccesses::None), 1);
+    }
+
+    #[test]
+    fn test_side_exit_is_barrier() {
+        let mut b = block(vec![
+            (vec![Stmt::write_reg(A0, Expr::imm(1))], fall()),
+            (
+                Vec::new(),
+                Terminator::branch(Expr::ne(Expr::reg(A1), Expr::imm(0)), 0x2000),
+            ),
+            (vec![Stmt::write_reg(A0, Expr::imm(2))], fall()),
+        ]);
+        assert_eq!(eliminate_dead_reg_writes(&mut b, TrappingAccesses::None), 0);
+    }
+
+    #[test]
+    fn test_internal_jump_is_not_barrier() {
+        let mut b = block(vec![
+            (
+                vec![Stmt::write_reg(A0, Expr::imm(1))],
+                Terminator::jump(0x1004),
+            ),
+            (vec![Stmt::write_reg(A0, Expr::imm(2))], fall()),
+        ]);
+        assert_eq!(eliminate_dead_reg_writes(&mut b, TrappingAccesses::None), 1);
+    }
+
+    #[test]
+    fn test_extern_call_is_barrier() {
+        let mut b = block(vec![
+            (vec![Stmt::write_reg(A0, Expr::imm(1))], fall()),
+            (vec![Stmt::extern_call("rv_ecall", Vec::new())], fall()),
+            (vec![Stmt::write_reg(A0, Expr::imm(2))], fall()),
+        ]);
+        assert_eq!(eliminate_dead_reg_writes(&mut b, TrappingAccesses::None), 0);
+    }
+
+    #[test]
+    fn test_impure_value_is_kept() {
+        let mut b = block(vec![
+            (
+                vec![Stmt::write_reg(A0, Expr::mem(Expr::reg(A1), 0, 4, true))],
+                fall(),
+            ),
+            (vec![Stmt::write_reg(A0, Expr::imm(2))], fall()),
+        ]);
+        assert_eq!(eliminate_dead_reg_writes(&mut b, TrappingAccesses::None), 0);
+    }
+
+    #[test]
+    fn test_csr_read_is_kept() {
+        let mut b = block(vec![
+            (vec![Stmt::write_reg(A0, Expr::csr(0x8c0))], fall()),
+            (vec![Stmt::write_reg(A0, Expr::imm(2))], fall()),
+        ]);
+        assert_eq!(eliminate_dead_reg_writes(&mut b, TrappingAccesses::None), 0);
+    }
+
+    #[test]
+    fn test_store_is_barrier_only_if_stores_trap() {
+        let instrs = || {
+            vec![
+                (vec![Stmt::write_reg(A0, Expr::imm(1))], fall()),
+                (
+                    vec![Stmt::write_mem(Expr::reg(A1), 0, Expr::imm(0), 8)],
+                    fall(),
+                ),
+                (vec![Stmt::write_reg(A0, Expr::imm(2))], fall()),
+            ]
+        };
+        let removed = |traps| eliminate_dead_reg_writes(&mut block(instrs()), traps);
+        assert_eq!(removed(TrappingAccesses::None), 1);
+        assert_eq!(removed(TrappingAccesses::Stores), 0);
+        assert_eq!(removed(TrappingAccesses::All), 0);
+    }
+
+    #[test]
+    fn test_load_is_barrier_only_if_loads_trap() {
+        // li t0, 1; lw a0, 0(a1); li t0, 2
+        let instrs = || {
+            vec![
+                (vec![Stmt::write_reg(T0, Expr::imm(1))], fall()),
+                (
+                    vec![Stmt::write_reg(A0, Expr::mem(Expr::reg(A1), 0, 4, true))],
+                    fall(),
+                ),
+                (vec![Stmt::write_reg(T0, Expr::imm(2))], fall()),
+            ]
+        };
+        let removed = |traps| eliminate_dead_reg_writes(&mut block(instrs()), traps);
+        assert_eq!(removed(TrappingAccesses::None), 1);
+        assert_eq!(removed(TrappingAccesses::Stores), 1);
+        assert_eq!(removed(TrappingAccesses::All), 0);
+    }
+
+    #[test]
+    fn test_conditional_write_does_not_kill() {
+        let mut b = block(vec![
+            (vec![Stmt::write_reg(A0, Expr::imm(1))], fall()),
+            (
+                vec![Stmt::if_then(
+                    Expr::reg(A1),
+                    vec![Stmt::write_reg(A0, Expr::imm(2))],
+                )],
+                fall(),
+            ),
+        ]);
+        assert_eq!(eliminate_dead_reg_writes(&mut b, TrappingAccesses::None), 0);
+    }
+}
@@ -0,0 +1,376 @@
+//! Multi-block override expansions.
+//!
+//! An instruction override may lower one guest instruction into a primary
+//! `InstrIR` plus auxiliary helper blocks (bounded loops, multi-step syscall
+//! sequences). Helper blocks live at synthetic PCs: odd addresses at the top of
+//! the address space that guest code can never jump to and the dispatch table
+//! never covers. Helper blocks do not fire per-instruction trace hooks; register
+//! and memory hooks inside them report the synthetic PC, which consumers can
+//! recognise with [`is_synthetic_pc`].
+
+use std::collections::HashMap;
+
+use crate::block::BlockIR;
+use crate::instr::InstrIR;
+use crate::stmt::Stmt;
+use crate::terminator::Terminator;
+use crate::xlen::Xlen;
+
+/// Number of synthetic PC slots reserved at the top of the address space.
+pub const SYNTHETIC_PC_SLOTS: u64 = 1 << 16;
+
+/// Highest address representable at this register width.
+const fn address_top<X: Xlen>() -> u64 {
+    if X::VALUE == 64 {
+        u64::MAX
+    } else {
+        u32::MAX as u64
+    }
+}
+
+/// Synthetic PC for the given slot.
+#[must_use]
+pub fn synthetic_pc<X: Xlen>(slot: u64) -> X::Reg {
+    debug_assert!(slot < SYNTHETIC_PC_SLOTS);
+    X::from_u64(address_top::<X>() - 2 * slot)
+}
+
+/// Check whether `pc` lies in the reserved synthetic range.
+#[must_use]
+pub const fn is_synthetic_pc<X: Xlen>(pc: u64) -> bool {
+    let top = address_top::<X>();
+    pc & 1 == 1 && pc <= top && top - pc < 2 * SYNTHETIC_PC_SLOTS
+}
+
+/// Slot index of a synthetic PC.
+const fn synthetic_slot<X: Xlen>(pc: u64) -> Option<u64> {
+    if is_synthetic_pc::<X>(pc) {
+        Some((address_top::<X>() - pc) / 2)
+    } else {
+        None
+    }
+}
+
+/// Instret accounting policy for helper blocks.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HelperInstret {
+    /// Only the primary instruction retires; helper blocks are free.
+    #[default]
+    Uncounted,
+    /// Every executed helper block retires one instruction.
+    PerBlock,
+}
+
+/// Auxiliary block emitted alongside an override's primary instruction.
+///
+/// A `Fall` terminator, or a `Branch` fall-through, without a target continues
+/// at the instruction after the overridden one.
+#[derive(Clone, Debug)]
+pub struct HelperBlock<X: Xlen> {
+    /// Statements executed by the block.
+    pub statements: Vec<Stmt<X>>,
+    /// Control flow out of the block.
+    pub terminator: Terminator<X>,
+}
+
+impl<X: Xlen> HelperBlock<X> {
+    /// Create a helper block.
+    pub const fn new(statements: Vec<Stmt<X>>, terminator: Terminator<X>) -> Self {
+        Self {
+            statements,
+            terminator,
+        }
+    }
+}
+
+/// Result of lifting one instruction: the primary IR plus optional helper blocks.
+///
+/// Terminators reference helper `idx` through [`OverrideExpansion::helper_target`];
+/// the pipeline relocates those labels into its synthetic PC range.
+#[derive(Clone, Debug)]
+pub struct OverrideExpansion<X: Xlen> {
+    /// IR for the guest instruction slot.
+    pub primary: InstrIR<X>,
+    /// Helper blocks, addressed by index.
+    pub helpers: Vec<HelperBlock<X>>,
+    /// Instret policy for the helper blocks.
+    pub instret: HelperInstret,
+}
+
+impl<X: Xlen> OverrideExpansion<X> {
+    /// Create an expansion with no helper blocks.
+    pub const fn new(primary: InstrIR<X>) -> Self {
+        Self {
+            primary,
+            helpers: Vec::new(),
+            instret: HelperInstret::Uncounted,
+        }
+    }
+
+    /// Append a helper block.
+    #[must_use]
+    pub fn with_helper(mut self, helper: HelperBlock<X>) -> Self {
+        self.helpers.push(helper);
+        self
+    }
+
+    /// Set the instret policy for helper blocks.
+    #[must_use]
+    pub const fn with_instret(mut self, instret: HelperInstret) -> Self {
+        self.instret = instret;
+        self
+    }
+
+    /// Check whether the expansion has helper blocks.
+    pub const fn has_helpers(&self) -> bool {
+        !self.helpers.is_empty()
+    }
+
+    /// Jump target label for helper `idx`.
+    #[must_use]
+    pub fn helper_target(idx: usize) -> X::Reg {
+        synthetic_pc::<X>(idx as u64)
+    }
+
+    /// Guest PCs that the primary instruction or a helper block can continue at.
+    pub fn exit_targets(&self) -> Vec<u64> {
+        let next_pc = X::to_u64(self.primary.next_pc());
+        let terminators = std::iter::once(&self.primary.terminator)
+            .chain(self.helpers.iter().map(|h| &h.terminator));
+        let mut targets = Vec::new();
+        for term in terminators {
+            targets.extend(term.static_targets().into_iter().map(X::to_u64));
+            match term {
+                Terminator::Fall { .. } | Terminator::Branch { .. } => {
+                    targets.push(term.fall_target().map_or(next_pc, X::to_u64));
+                }
+                _ => {}
+            }
+        }
+        targets.retain(|&pc| !is_synthetic_pc::<X>(pc));
+        targets.sort_unstable();
+        targets.dedup();
+        targets
+    }
+
+    /// Relocate helper labels to slots starting at `base` and build helper blocks.
+    fn relocate(self, base: u64) -> (InstrIR<X>, Vec<BlockIR<X>>) {
+        let next_pc = self.primary.next_pc();
+        let relocate = |pc: X::Reg| {
+            synthetic_slot::<X>(X::to_u64(pc)).map_or(pc, |slot| synthetic_pc::<X>(base + slot))
+        };
+
+        let mut primary = self.primary;
+        relocate_terminator(&mut primary.terminator, relocate);
+
+        let blocks = self
+            .helpers
+            .into_iter()
+            .enumerate()
+            .map(|(idx, helper)| {
+                let mut terminator = helper.terminator;
+                match &mut terminator {
+                    Terminator::Fall { target: fall } | Terminator::Branch { fall, .. } => {
+                        fall.get_or_insert(next_pc);
+                    }
+                    _ => {}
+                }
+                relocate_terminator(&mut terminator, relocate);
+                let pc = synthetic_pc::<X>(base + idx as u64);
+                let mut block = BlockIR::new(pc);
+                block.push(InstrIR::new(
+                    pc,
+                    0,
+                    primary.op,
+                    primary.raw,
+                    helper.statements,
+                    terminator,
+                ));
+                block
+            })
+            .collect();
+
+        (primary, blocks)
+    }
+}
+
+fn relocate_terminator<X: Xlen>(term: &mut Terminator<X>, relocate: impl Fn(X::Reg) -> X::Reg) {
+    match term {
+        Terminator::Fall { target } => *target = target.map(&relocate),
+        Terminator::Jump { target } => *target = relocate(*target),
+        Terminator::JumpDyn { resolved, .. } => {
+            for target in resolved.iter_mut().flatten() {
+                *target = relocate(*target);
+            }
+        }
+        Terminator::Branch { target, fall, .. } => {
+            *target = relocate(*target);
+            *fall = fall.map(&relocate);
+        }
+        Terminator::Exit { .. } | Terminator::Trap { .. } => {}
+    }
+}
+
+/// Metadata for a synthetic helper block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SyntheticBlockInfo {
+    /// PC of the guest instruction whose override produced the block.
+    pub owner_pc: u64,
+    /// Instret policy of the owning expansion.
+    pub instret: HelperInstret,
+}
+
+impl SyntheticBlockInfo {
+    /// Check whether executing the block retires an instruction.
+    #[must_use]
+    pub fn retires(&self) -> bool {
+        self.instret == HelperInstret::PerBlock
+    }
+}
+
+/// Allocator that places helper blocks into the synthetic PC range.
+///
+/// An instruction lifted more than once (tail duplication, superblocks) reuses
+/// the slots assigned on first placement, so its helpers are emitted once.
+#[derive(Debug)]
+pub struct SyntheticBlocks<X: Xlen> {
+    bases: HashMap<u64, u64>,
+    next_slot: u64,
+    blocks: Vec<BlockIR<X>>,
+    info: HashMap<u64, SyntheticBlockInfo>,
+}
+
+impl<X: Xlen> Default for SyntheticBlocks<X> {
+    fn default() -> Self {
+        Self {
+            bases: HashMap::new(),
+            next_slot: 0,
+            blocks: Vec::new(),
+            info: HashMap::new(),
+        }
+    }
+}
+
+impl<X: Xlen> SyntheticBlocks<X> {
+    /// Place an expansion and return its relocated primary instruction.
+    ///
+    /// Returns `None` when the synthetic PC range is exhausted.
+    pub fn place(&mut self, expansion: OverrideExpansion<X>) -> Option<InstrIR<X>> {
+        if !expansion.has_helpers() {
+            return Some(expansion.primary);
+        }
+
+        let owner_pc = X::to_u64(expansion.primary.pc);
+        if let Some(&base) = self.bases.get(&owner_pc) {
+            return Some(expansion.relocate(base).0);
+        }
+
+        let base = self.next_slot;
+        let end = base + expansion.helpers.len() as u64;
+        if end > SYNTHETIC_PC_SLOTS {
+            return None;
+        }
+        self.next_slot = end;
+        self.bases.insert(owner_pc, base);
+
+        let instret = expansion.instret;
+        let (primary, blocks) = expansion.relocate(base);
+        for block in blocks {
+            self.info.insert(
+                X::to_u64(block.start_pc),
+                SyntheticBlockInfo { owner_pc, instret },
+            );
+            self.blocks.push(block);
+        }
+        Some(primary)
+    }
+
+    /// Number of placed helper blocks.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Check whether no helper blocks have been placed.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    /// Consume the allocator, returning helper blocks and their metadata.
+    #[must_use]
+    pub fn into_parts(self) -> (Vec<BlockIR<X>>, HashMap<u64, SyntheticBlockInfo>) {
+        (self.blocks, self.info)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Expr, Rv32, Rv64};
+
+    fn copy_expansion(pc: u64) -> OverrideExpansion<Rv64> {
+        let primary = InstrIR::new(
+            pc,
+            4,
+            0,
+            0,
+            Vec::new(),
+            Terminator::jump(OverrideExpansion::<Rv64>::helper_target(0)),
+        );
+        OverrideExpansion::new(primary)
+            .with_helper(HelperBlock::new(
+                Vec::new(),
+                Terminator::branch(
+                    Expr::ne(Expr::reg(12), Expr::imm(0)),
+                    OverrideExpansion::<Rv64>::helper_target(1),
+                ),
+            ))
+            .with_helper(HelperBlock::new(
+                Vec::new(),
+                Terminator::jump(OverrideExpansion::<Rv64>::helper_target(0)),
+            ))
+    }
+
+    #[test]
+    fn test_synthetic_pc_range() {
+        assert!(is_synthetic_pc::<Rv64>(u64::MAX));
+        assert!(is_synthetic_pc::<Rv32>(u64::from(u32::MAX)));
+        assert!(!is_synthetic_pc::<Rv64>(0x8000_0000));
+        assert!(!is_synthetic_pc::<Rv64>(u64::MAX - 1));
+        assert!(!is_synthetic_pc::<Rv32>(u64::MAX));
+        let last = synthetic_pc::<Rv32>(SYNTHETIC_PC_SLOTS - 1);
+        assert!(is_synthetic_pc::<Rv32>(u64::from(last)));
+        assert!(!is_synthetic_pc::<Rv32>(u64::from(last) - 2));
+    }
+
+    #[test]
+    fn test_place_relocates_and_reuses_slots() {
+        let mut synthetic = SyntheticBlocks::<Rv64>::default();
+        let first = synthetic.place(copy_expansion(0x1000)).unwrap();
+        let second = synthetic.place(copy_expansion(0x2000)).unwrap();
+        let again = synthetic.place(copy_expansion(0x1000)).unwrap();
+        assert_eq!(synthetic.len(), 4);
+
+        let (blocks, info) = synthetic.into_parts();
+        assert!(matches!(first.terminator, Terminator::Jump { target } if target == u64::MAX));
+        assert!(matches!(second.terminator, Terminator::Jump { target } if target == u64::MAX - 4));
+        assert!(matches!(again.terminator, Terminator::Jump { target } if target == u64::MAX));
+
+        // Helper 0 of the second expansion branches to its own helper 1 and
+        // falls back to the guest instruction after the owner.
+        let helper = &blocks[2].instructions[0];
+        assert!(matches!(
+            helper.terminator,
+            Terminator::Branch { target, fall: Some(0x2004), .. } if target == u64::MAX - 6
+        ));
+        assert_eq!(info[&(u64::MAX - 4)].owner_pc, 0x2000);
+        assert!(!info[&(u64::MAX - 4)].retires());
+    }
+
+    #[test]
+    fn test_exit_targets_exclude_helpers() {
+        let expansion = copy_expansion(0x1000);
+        assert_eq!(expansion.exit_targets(), vec![0x1004]);
+    }
+}
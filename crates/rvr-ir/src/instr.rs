@@ -5,7 +5,7 @@ use crate::terminator::Terminator;
 use crate::xlen::Xlen;
 
 /// Source location for debug info (#line directives).
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub struct SourceLoc {
     /// Source file name.
     pub file: String,
@@ -5,16 +5,22 @@
 
 mod block;
 mod builder;
+mod dead_writes;
+mod expansion;
 mod expr;
 mod instr;
+mod reg_access;
 mod stmt;
 mod terminator;
 mod xlen;
 
 pub use block::*;
 pub use builder::*;
+pub use dead_writes::*;
+pub use expansion::*;
 pub use expr::*;
 pub use instr::*;
+pub use reg_access::*;
 pub use stmt::*;
 pub use terminator::*;
 pub use xlen::*;
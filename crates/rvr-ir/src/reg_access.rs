@@ -0,0 +1,397 @@
+//! Static register access counts.
+//!
+//! Counts how often each register appears in a block's IR, as a read or a
+//! write target. Weighted by how often the block runs, this ranks registers
+//! for the hot register slots.
+//!
+//! Also finds the register a dynamic jump takes its target from, for
+//! reports of jumps to unknown targets, and how early in its block the
+//! target is known, for dispatch table prefetches.
+
+use crate::block::BlockIR;
+use crate::expr::{Expr, ReadExpr};
+use crate::instr::InstrIR;
+use crate::stmt::{Stmt, WriteTarget};
+use crate::terminator::Terminator;
+use crate::xlen::Xlen;
+
+/// Register accesses, indexed by register number.
+pub type RegAccesses = [u64; 32];
+
+/// Count the register reads and writes in `block`, including those in
+/// terminator expressions. Accesses to `x0` are not counted.
+pub fn reg_accesses<X: Xlen>(block: &BlockIR<X>) -> RegAccesses {
+    let mut counts = [0; 32];
+    for instr in &block.instructions {
+        for stmt in &instr.statements {
+            count_stmt(stmt, &mut counts);
+        }
+        match &instr.terminator {
+            Terminator::JumpDyn { addr, .. } => count_expr(addr, &mut counts),
+            Terminator::Branch { cond, .. } => count_expr(cond, &mut counts),
+            Terminator::Exit { code } => count_expr(code, &mut counts),
+            Terminator::Fall { .. } | Terminator::Jump { .. } | Terminator::Trap { .. } => {}
+        }
+    }
+    counts[0] = 0;
+    counts
+}
+
+fn count_reg(reg: u8, counts: &mut RegAccesses) {
+    if let Some(count) = counts.get_mut(usize::from(reg)) {
+        *count += 1;
+    }
+}
+
+fn count_stmt<X: Xlen>(stmt: &Stmt<X>, counts: &mut RegAccesses) {
+    match stmt {
+        Stmt::Write { target, value } => {
+            match target {
+                WriteTarget::Reg(reg) => count_reg(*reg, counts),
+                WriteTarget::Mem { base, .. } => count_expr(base, counts),
+                _ => {}
+            }
+            count_expr(value, counts);
+        }
+        Stmt::If {
+            cond,
+            then_stmts,
+            else_stmts,
+        } => {
+            count_expr(cond, counts);
+            for stmt in then_stmts.iter().chain(else_stmts) {
+                count_stmt(stmt, counts);
+            }
+        }
+        Stmt::ExternCall { args, .. } => {
+            for arg in args {
+                count_expr(arg, counts);
+            }
+        }
+    }
+}
+
+fn count_expr<X: Xlen>(expr: &Expr<X>, counts: &mut RegAccesses) {
+    match expr {
+        Expr::Imm(_) | Expr::PcConst(_) | Expr::Var(_) => {}
+        Expr::Read(read) => match read {
+            ReadExpr::Reg(reg) => count_reg(*reg, counts),
+            ReadExpr::Mem { base, .. } => count_expr(base, counts),
+            ReadExpr::MemAddr { addr, .. } => count_expr(addr, counts),
+            _ => {}
+        },
+        Expr::Unary { expr, .. } => count_expr(expr, counts),
+        Expr::Binary { left, right, .. } => {
+            count_expr(left, counts);
+            count_expr(right, counts);
+        }
+        Expr::Ternary {
+            first,
+            second,
+            third,
+            ..
+        } => {
+            count_expr(first, counts);
+            count_expr(second, counts);
+            count_expr(third, counts);
+        }
+        Expr::ExternCall { args, .. } => {
+            for arg in args {
+                count_expr(arg, counts);
+            }
+        }
+    }
+}
+
+/// Register the target of `instr`'s dynamic jump is computed from.
+///
+/// That is the first register its address reads, directly or through a
+/// temporary the instruction wrote (`jalr ra, 0(ra)`). `None` without a
+/// dynamic jump or a register.
+pub fn jump_source_reg<X: Xlen>(instr: &InstrIR<X>) -> Option<u8> {
+    let Terminator::JumpDyn { addr, .. } = &instr.terminator else {
+        return None;
+    };
+    match first_read(addr)? {
+        ReadExpr::Reg(reg) => Some(*reg),
+        ReadExpr::Temp(temp) => instr.statements.iter().find_map(|stmt| match stmt {
+            Stmt::Write {
+                target: WriteTarget::Temp(idx),
+                value,
+            } if idx == temp => match first_read(value)? {
+                ReadExpr::Reg(reg) => Some(*reg),
+                _ => None,
+            },
+            _ => None,
+        }),
+        _ => None,
+    }
+}
+
+/// Earliest point where the target of the dynamic jump closing `instrs`
+/// is known.
+///
+/// `instrs` is a straight-line run such as a block. Returns the index of
+/// the instruction before which every register the target address reads
+/// holds its final value, and that address.
+///
+/// `None` unless the address reads only registers and constants, nothing
+/// between the point and the jump can change them (register writes, extern
+/// calls, control flow), and at least `min_distance` instructions run in
+/// between.
+pub fn early_jump_target<X: Xlen>(
+    instrs: &[InstrIR<X>],
+    min_distance: usize,
+) -> Option<(usize, &Expr<X>)> {
+    let (jump, body) = instrs.split_last()?;
+    let Terminator::JumpDyn { addr, .. } = &jump.terminator else {
+        return None;
+    };
+    let mut regs = 0u32;
+    if !plain_reg_reads(addr, &mut regs) || jump.statements.iter().any(|s| clobbers(s, regs)) {
+        return None;
+    }
+    let start = body
+        .iter()
+        .rposition(|instr| {
+            !matches!(instr.terminator, Terminator::Fall { .. })
+                || instr.statements.iter().any(|s| clobbers(s, regs))
+        })
+        .map_or(0, |last| last + 1);
+    (body.len() - start >= min_distance).then_some((start, addr))
+}
+
+/// Collect the registers `expr` reads into the mask `regs`. False if it
+/// reads anything else that may change (memory, CSRs, temporaries).
+fn plain_reg_reads<X: Xlen>(expr: &Expr<X>, regs: &mut u32) -> bool {
+    match expr {
+        Expr::Imm(_) | Expr::PcConst(_) => true,
+        Expr::Read(ReadExpr::Reg(reg)) if *reg < 32 => {
+            *regs |= 1 << reg;
+            true
+        }
+        Expr::Unary { expr, .. } => plain_reg_reads(expr, regs),
+        Expr::Binary { left, right, .. } => {
+            plain_reg_reads(left, regs) && plain_reg_reads(right, regs)
+        }
+        Expr::Ternary {
+            first,
+            second,
+            third,
+            ..
+        } => {
+            plain_reg_reads(first, regs)
+                && plain_reg_reads(second, regs)
+                && plain_reg_reads(third, regs)
+        }
+        Expr::Read(_) | Expr::Var(_) | Expr::ExternCall { .. } => false,
+    }
+}
+
+/// True if `stmt` may write a register in the mask `regs`. Extern calls
+/// may write any.
+fn clobbers<X: Xlen>(stmt: &Stmt<X>, regs: u32) -> bool {
+    match stmt {
+        Stmt::Write {
+            target: WriteTarget::Reg(reg),
+            ..
+        } => *reg < 32 && regs & (1 << reg) != 0,
+        Stmt::Write { .. } => false,
+        Stmt::If {
+            then_stmts,
+            else_stmts,
+            ..
+        } => then_stmts
+            .iter()
+            .chain(else_stmts)
+            .any(|s| clobbers(s, regs)),
+        Stmt::ExternCall { .. } => true,
+    }
+}
+
+/// First register or temporary read in `expr`, depth first.
+fn first_read<X: Xlen>(expr: &Expr<X>) -> Option<&ReadExpr<X>> {
+    match expr {
+        Expr::Read(read @ (ReadExpr::Reg(_) | ReadExpr::Temp(_))) => Some(read),
+        Expr::Read(ReadExpr::Mem { base, .. }) => first_read(base),
+        Expr::Read(ReadExpr::MemAddr { addr, .. }) => first_read(addr),
+        Expr::Imm(_) | Expr::PcConst(_) | Expr::Var(_) | Expr::Read(_) => None,
+        Expr::Unary { expr, .. } => first_read(expr),
+        Expr::Binary { left, right, .. } => first_read(left).or_else(|| first_read(right)),
+        Expr::Ternary {
+            first,
+            second,
+            third,
+            ..
+        } => first_read(first)
+            .or_else(|| first_read(second))
+            .or_else(|| first_read(third)),
+        Expr::ExternCall { args, .. } => args.iter().find_map(first_read),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::xlen::Rv64;
+
+    const SP: u8 = 2;
+    const A0: u8 = 10;
+    const A1: u8 = 11;
+
+    #[test]
+    fn test_reg_accesses() {
+        let mut block = BlockIR::<Rv64>::new(0x1000);
+        block.push(InstrIR::new(
+            0x1000,
+            4,
+            0,
+            0,
+            vec![
+                Stmt::write_reg(A0, Expr::add(Expr::reg(A0), Expr::reg(0))),
+                Stmt::write_mem(Expr::reg(SP), 8, Expr::reg(A1), 8),
+            ],
+            Terminator::fall(0x1004),
+        ));
+        block.push(InstrIR::new(
+            0x1004,
+            4,
+            0,
+            0,
+            Vec::new(),
+            Terminator::branch(Expr::eq(Expr::reg(A0), Expr::reg(A1)), 0x1000),
+        ));
+
+        let counts = reg_accesses(&block);
+        assert_eq!(counts[A0 as usize], 3);
+        assert_eq!(counts[A1 as usize], 2);
+        assert_eq!(counts[SP as usize], 1);
+        assert_eq!(counts[0], 0);
+        assert_eq!(counts.iter().sum::<u64>(), 6);
+    }
+
+    #[test]
+    fn test_jump_source_reg() {
+        let jalr = |statements, addr| {
+            InstrIR::<Rv64>::new(0x1000, 4, 0, 0, statements, Terminator::jump_dyn(addr))
+        };
+        let target = |base| Expr::and(Expr::add(base, Expr::imm(8)), Expr::imm(!1));
+        assert_eq!(
+            jump_source_reg(&jalr(Vec::new(), target(Expr::reg(A1)))),
+            Some(A1)
+        );
+        // rd == rs1 reads the base through a temporary
+        let through_temp = jalr(
+            vec![
+                Stmt::write_temp(0, Expr::reg(SP)),
+                Stmt::write_reg(SP, Expr::imm(0x1004)),
+            ],
+            target(Expr::temp(0)),
+        );
+        assert_eq!(jump_source_reg(&through_temp), Some(SP));
+        assert_eq!(jump_source_reg(&jalr(Vec::new(), Expr::imm(0x2000))), None);
+        let fall = InstrIR::<Rv64>::new(0x1000, 4, 0, 0, Vec::new(), Terminator::fall(0x1004));
+        assert_eq!(jump_source_reg(&fall), None);
+    }
+
+    /// Statements and terminator of one instruction.
+    type Body = (Vec<Stmt<Rv64>>, Terminator<Rv64>);
+
+    #[test]
+    fn test_early_jump_target() {
+        let addi = |rd| Stmt::write_reg(rd, Expr::add(Expr::reg(rd), Expr::imm(1)));
+        let nop = || Vec::new();
+        let branch = || Terminator::branch(Expr::eq(Expr::reg(A0), Expr::imm(0)), 0x1000);
+        let jalr = |addr| {
+            (
+                vec![Stmt::write_reg(1, Expr::imm(0x1004))],
+                Terminator::jump_dyn(addr),
+            )
+        };
+        let to_a1 = || Expr::and(Expr::add(Expr::reg(A1), Expr::imm(8)), Expr::imm(!1));
+        let fall = Terminator::fall;
+        // (instructions ahead of the jump, jump address, expected start)
+        let cases: [(Vec<Body>, Expr<Rv64>, Option<usize>); 7] = [
+            // Written first, then three unrelated instructions
+            (
+                vec![
+                    (vec![addi(A1)], fall(0)),
+                    (vec![addi(A0)], fall(0)),
+                    (nop(), fall(0)),
+                    (vec![addi(SP)], fall(0)),
+                ],
+                to_a1(),
+                Some(1),
+            ),
+            // Never written in the block
+            (
+                vec![(nop(), fall(0)), (nop(), fall(0)), (nop(), fall(0))],
+                to_a1(),
+                Some(0),
+            ),
+            // Written too close to the jump
+            (
+                vec![
+                    (nop(), fall(0)),
+                    (nop(), fall(0)),
+                    (vec![addi(A1)], fall(0)),
+                ],
+                to_a1(),
+                None,
+            ),
+            // A side exit in between
+            (
+                vec![(nop(), fall(0)), (nop(), fall(0)), (nop(), branch())],
+                to_a1(),
+                None,
+            ),
+            // An extern call may write any register
+            (
+                vec![
+                    (nop(), fall(0)),
+                    (vec![Stmt::extern_call("f", Vec::new())], fall(0)),
+                    (nop(), fall(0)),
+                    (nop(), fall(0)),
+                    (nop(), fall(0)),
+                ],
+                to_a1(),
+                Some(2),
+            ),
+            // Loaded targets are not known early
+            (
+                vec![(nop(), fall(0)), (nop(), fall(0)), (nop(), fall(0))],
+                Expr::mem_u(Expr::reg(A1), 8),
+                None,
+            ),
+            // rd == rs1 reads the base through a temporary
+            (
+                vec![(nop(), fall(0)), (nop(), fall(0)), (nop(), fall(0))],
+                Expr::temp(0),
+                None,
+            ),
+        ];
+        for (index, (body, addr, expected)) in cases.into_iter().enumerate() {
+            let mut block = BlockIR::<Rv64>::new(0x1000);
+            let (statements, terminator) = jalr(addr);
+            for (pc, (statements, terminator)) in (0x1000..)
+                .step_by(4)
+                .zip(body.into_iter().chain([(statements, terminator)]))
+            {
+                block.push(InstrIR::new(pc, 4, 0, 0, statements, terminator));
+            }
+            let start = early_jump_target(&block.instructions, 3).map(|(start, _)| start);
+            assert_eq!(start, expected, "case {index}");
+        }
+        // Writing the target register in the jump itself
+        let mut block = BlockIR::<Rv64>::new(0x1000);
+        block.push(InstrIR::new(
+            0x1000,
+            4,
+            0,
+            0,
+            vec![addi(A1)],
+            Terminator::jump_dyn(to_a1()),
+        ));
+        assert!(early_jump_target(&block.instructions, 0).is_none());
+    }
+}
@@ -0,0 +1,433 @@
+//! Example: Lowering a custom instruction into helper blocks.
+//!
+//! This example adds a hypothetical `memcpy.b rd, rs1, rs2` instruction that
+//! copies `rs2` bytes from `rs1` to `rd`. Instead of calling out to the
+//! runtime, an `InstructionOverride` expands it into a bounded byte-copy loop:
+//! the primary instruction jumps into a loop header helper block, which either
+//! runs the copy body helper or falls through to the next guest instruction.
+//!
+//! Run with: `cargo run --example memcpy_expansion -p rvr-isa`
+
+use rvr_ir::{
+    Expr, HelperBlock, HelperInstret, InstrIR, OverrideExpansion, Rv64, Stmt, SyntheticBlocks,
+    Terminator, Xlen,
+};
+use rvr_isa::{
+    DecodedInstr, ExtensionRegistry, InstrArgs, InstructionExtension, InstructionOverride, OpClass,
+    OpId, OpInfo,
+};
+
+// Custom opcode for memcpy.b
+const EXT_XMEMCPY: u8 = 129;
+const OP_MEMCPY_B: OpId = OpId::new(EXT_XMEMCPY, 0);
+
+/// Custom R-type encoding for memcpy.b:
+///   31-25: funct7 = 0b0000010 (identifies memcpy.b)
+///   24-20: rs2 (byte count register)
+///   19-15: rs1 (source pointer register)
+///   14-12: funct3 = 0b000
+///   11-7:  rd (destination pointer register)
+///   6-0:   opcode = 0b0001011 (custom-0)
+const CUSTOM_0_OPCODE: u32 = 0b000_1011;
+const MEMCPY_FUNCT3: u32 = 0b000;
+const MEMCPY_FUNCT7: u32 = 0b000_0010;
+
+/// Helper block indices within the expansion.
+const LOOP_HEAD: usize = 0;
+const LOOP_BODY: usize = 1;
+
+/// Decoder for `memcpy.b`.
+///
+/// Lifting without the override traps, since the instruction only has
+/// meaning as a helper-block expansion.
+pub struct MemcpyExtension;
+
+impl<X: Xlen> InstructionExtension<X> for MemcpyExtension {
+    fn name(&self) -> &'static str {
+        "Xmemcpy"
+    }
+
+    fn ext_id(&self) -> u8 {
+        EXT_XMEMCPY
+    }
+
+    fn decode32(&self, raw: u32, pc: X::Reg) -> Option<DecodedInstr<X>> {
+        let opcode = raw & 0x7F;
+        let funct3 = (raw >> 12) & 0x7;
+        let funct7 = (raw >> 25) & 0x7F;
+        if opcode != CUSTOM_0_OPCODE || funct3 != MEMCPY_FUNCT3 || funct7 != MEMCPY_FUNCT7 {
+            return None;
+        }
+
+        let rd = ((raw >> 7) & 0x1F) as u8;
+        let rs1 = ((raw >> 15) & 0x1F) as u8;
+        let rs2 = ((raw >> 20) & 0x1F) as u8;
+        Some(DecodedInstr::new(
+            OP_MEMCPY_B,
+            pc,
+            4,
+            raw,
+            InstrArgs::R { rd, rs1, rs2 },
+        ))
+    }
+
+    fn lift(&self, instr: &DecodedInstr<X>) -> InstrIR<X> {
+        InstrIR::new(
+            instr.pc,
+            instr.size,
+            instr.opid.pack(),
+            instr.raw,
+            Vec::new(),
+            Terminator::trap("memcpy.b requires the memcpy override"),
+        )
+    }
+
+    fn disasm(&self, instr: &DecodedInstr<X>) -> String {
+        match &instr.args {
+            InstrArgs::R { rd, rs1, rs2 } => format!("memcpy.b x{rd}, x{rs1}, x{rs2}"),
+            _ => "memcpy.b ???".to_string(),
+        }
+    }
+
+    fn op_info(&self, opid: OpId) -> Option<OpInfo> {
+        (opid == OP_MEMCPY_B).then_some(OpInfo {
+            opid: OP_MEMCPY_B,
+            name: "memcpy.b",
+            class: OpClass::Other,
+            size_hint: 4,
+        })
+    }
+}
+
+/// Override that expands `memcpy.b` into a byte-copy loop.
+pub struct MemcpyLoop {
+    /// Instret policy for the loop blocks.
+    pub instret: HelperInstret,
+}
+
+impl<X: Xlen> InstructionOverride<X> for MemcpyLoop {
+    fn lift(
+        &self,
+        instr: &DecodedInstr<X>,
+        _default_lift: &dyn Fn(&DecodedInstr<X>) -> InstrIR<X>,
+    ) -> InstrIR<X> {
+        InstrIR::new(
+            instr.pc,
+            instr.size,
+            instr.opid.pack(),
+            instr.raw,
+            Vec::new(),
+            Terminator::jump(OverrideExpansion::<X>::helper_target(LOOP_HEAD)),
+        )
+    }
+
+    fn expand(
+        &self,
+        instr: &DecodedInstr<X>,
+        default_lift: &dyn Fn(&DecodedInstr<X>) -> InstrIR<X>,
+    ) -> OverrideExpansion<X> {
+        let InstrArgs::R { rd, rs1, rs2 } = instr.args else {
+            return OverrideExpansion::new(default_lift(instr));
+        };
+        let one = Expr::imm(X::from_u64(1));
+
+        // while (rs2 != 0) { *rd++ = *rs1++; rs2--; }
+        let head = HelperBlock::new(
+            Vec::new(),
+            Terminator::branch(
+                Expr::ne(Expr::reg(rs2), Expr::imm(X::from_u64(0))),
+                OverrideExpansion::<X>::helper_target(LOOP_BODY),
+            ),
+        );
+        let body = HelperBlock::new(
+            vec![
+                Stmt::write_mem(Expr::reg(rd), 0, Expr::mem_u(Expr::reg(rs1), 1), 1),
+                Stmt::write_reg(rd, Expr::add(Expr::reg(rd), one.clone())),
+                Stmt::write_reg(rs1, Expr::add(Expr::reg(rs1), one.clone())),
+                Stmt::write_reg(rs2, Expr::sub(Expr::reg(rs2), one)),
+            ],
+            Terminator::jump(OverrideExpansion::<X>::helper_target(LOOP_HEAD)),
+        );
+
+        OverrideExpansion::new(self.lift(instr, default_lift))
+            .with_helper(head)
+            .with_helper(body)
+            .with_instret(self.instret)
+    }
+}
+
+/// Encode a memcpy.b instruction.
+fn encode_memcpy_b(rd: u8, rs1: u8, rs2: u8) -> [u8; 4] {
+    let raw: u32 = CUSTOM_0_OPCODE
+        | ((u32::from(rd) & 0x1F) << 7)
+        | (MEMCPY_FUNCT3 << 12)
+        | ((u32::from(rs1) & 0x1F) << 15)
+        | ((u32::from(rs2) & 0x1F) << 20)
+        | (MEMCPY_FUNCT7 << 25);
+    raw.to_le_bytes()
+}
+
+fn registry(instret: HelperInstret) -> ExtensionRegistry<Rv64> {
+    ExtensionRegistry::<Rv64>::standard()
+        .with_extension(MemcpyExtension)
+        .with_override(OP_MEMCPY_B, MemcpyLoop { instret })
+}
+
+fn main() {
+    println!("=== memcpy.b Helper Block Example ===\n");
+
+    let registry = registry(HelperInstret::Uncounted);
+
+    // memcpy.b a0, a1, a2
+    let bytes = encode_memcpy_b(10, 11, 12);
+    let instr = registry
+        .decode(&bytes, 0x1000u64)
+        .expect("Failed to decode memcpy.b");
+    println!("Disassembly: {}", registry.disasm(&instr));
+
+    let expansion = registry.lift_expanded(&instr);
+    assert_eq!(expansion.helpers.len(), 2);
+    assert_eq!(expansion.exit_targets(), vec![0x1004]);
+
+    // Place helpers at synthetic PCs the way the pipeline does
+    let mut synthetic = SyntheticBlocks::<Rv64>::default();
+    let primary = synthetic
+        .place(expansion)
+        .expect("synthetic PC range exhausted");
+    println!("\nPrimary @ {:#x}: {:?}", primary.pc, primary.terminator);
+    let (blocks, _) = synthetic.into_parts();
+    for block in &blocks {
+        let ir = &block.instructions[0];
+        println!(
+            "Helper  @ {:#x}: {} stmts, {:?}",
+            ir.pc,
+            ir.statements.len(),
+            ir.terminator
+        );
+    }
+
+    println!("\n=== All assertions passed! ===");
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use rvr_ir::{BinaryOp, ReadExpr, WriteTarget};
+
+    use super::*;
+
+    const REG_T0: u8 = 5;
+    const REG_A0: u8 = 10;
+    const REG_A1: u8 = 11;
+    const REG_A2: u8 = 12;
+
+    /// Minimal IR interpreter covering the statements used by the tests.
+    #[derive(Clone, Default)]
+    struct Machine {
+        regs: [u64; 32],
+        mem: HashMap<u64, u8>,
+        instret: u64,
+    }
+
+    impl Machine {
+        fn load_byte(&self, addr: u64, signed: bool) -> u64 {
+            let byte = self.mem.get(&addr).copied().unwrap_or(0);
+            if signed {
+                i64::from(byte.cast_signed()).cast_unsigned()
+            } else {
+                u64::from(byte)
+            }
+        }
+
+        fn eval(&self, expr: &Expr<Rv64>) -> u64 {
+            match expr {
+                Expr::Imm(value) => *value,
+                Expr::Read(ReadExpr::Reg(reg)) => self.regs[usize::from(*reg)],
+                Expr::Read(ReadExpr::Mem {
+                    base,
+                    offset,
+                    width: 1,
+                    signed,
+                }) => self.load_byte(
+                    self.eval(base).wrapping_add_signed(i64::from(*offset)),
+                    *signed,
+                ),
+                Expr::Read(ReadExpr::MemAddr {
+                    addr,
+                    width: 1,
+                    signed,
+                }) => self.load_byte(self.eval(addr), *signed),
+                Expr::Binary { op, left, right } => {
+                    let (left, right) = (self.eval(left), self.eval(right));
+                    match op {
+                        BinaryOp::Add => left.wrapping_add(right),
+                        BinaryOp::Sub => left.wrapping_sub(right),
+                        BinaryOp::Ne => u64::from(left != right),
+                        _ => panic!("unsupported binary op {op:?}"),
+                    }
+                }
+                _ => panic!("unsupported expression {expr:?}"),
+            }
+        }
+
+        fn exec(&mut self, stmts: &[Stmt<Rv64>]) {
+            for stmt in stmts {
+                let Stmt::Write { target, value } = stmt else {
+                    panic!("unsupported statement {stmt:?}");
+                };
+                let value = self.eval(value);
+                match target {
+                    WriteTarget::Reg(0) | WriteTarget::ResAddr | WriteTarget::ResValid => {}
+                    WriteTarget::Reg(reg) => self.regs[usize::from(*reg)] = value,
+                    WriteTarget::Mem {
+                        base,
+                        offset,
+                        width: 1,
+                    } => {
+                        let addr = self.eval(base).wrapping_add_signed(i64::from(*offset));
+                        self.mem.insert(addr, value.to_le_bytes()[0]);
+                    }
+                    _ => panic!("unsupported write target {target:?}"),
+                }
+            }
+        }
+
+        /// Run an expanded instruction until control leaves its helper blocks.
+        fn run_expansion(&mut self, expansion: OverrideExpansion<Rv64>) {
+            let mut synthetic = SyntheticBlocks::default();
+            let primary = synthetic.place(expansion).unwrap();
+            let exit_pc = primary.next_pc();
+            let (blocks, info) = synthetic.into_parts();
+            let mut code: HashMap<u64, InstrIR<Rv64>> = blocks
+                .into_iter()
+                .map(|mut block| (block.start_pc, block.instructions.remove(0)))
+                .collect();
+            code.insert(primary.pc, primary.clone());
+
+            let mut pc = primary.pc;
+            while pc != exit_pc {
+                let ir = &code[&pc];
+                self.exec(&ir.statements);
+                if info.get(&pc).is_none_or(|block| block.retires()) {
+                    self.instret += 1;
+                }
+                pc = match &ir.terminator {
+                    Terminator::Jump { target } => *target,
+                    Terminator::Branch {
+                        cond, target, fall, ..
+                    } => {
+                        if self.eval(cond) == 0 {
+                            fall.unwrap()
+                        } else {
+                            *target
+                        }
+                    }
+                    Terminator::Fall { target } => target.unwrap_or_else(|| ir.next_pc()),
+                    term => panic!("unexpected terminator {term:?}"),
+                };
+            }
+        }
+
+        /// Run straight-line guest code.
+        fn run_linear(&mut self, registry: &ExtensionRegistry<Rv64>, code: &[u32]) {
+            for (idx, raw) in code.iter().enumerate() {
+                let pc = 0x2000 + 4 * idx as u64;
+                let instr = registry.decode(&raw.to_le_bytes(), pc).unwrap();
+                let ir = registry.lift(&instr);
+                self.exec(&ir.statements);
+                self.instret += 1;
+            }
+        }
+    }
+
+    fn i_type(opcode: u32, rd: u8, rs1: u8, imm: i32) -> u32 {
+        (imm.cast_unsigned() << 20) | (u32::from(rs1) << 15) | (u32::from(rd) << 7) | opcode
+    }
+
+    fn sb(rs1: u8, rs2: u8) -> u32 {
+        (u32::from(rs2) << 20) | (u32::from(rs1) << 15) | 0b010_0011
+    }
+
+    /// Equivalent unrolled copy: `lb t0, 0(a1); sb t0, 0(a0); addi a0/a1/a2`.
+    fn unrolled_copy(len: usize) -> Vec<u32> {
+        let step = [
+            i_type(0b000_0011, REG_T0, REG_A1, 0),
+            sb(REG_A0, REG_T0),
+            i_type(0b001_0011, REG_A0, REG_A0, 1),
+            i_type(0b001_0011, REG_A1, REG_A1, 1),
+            i_type(0b001_0011, REG_A2, REG_A2, -1),
+        ];
+        step.repeat(len)
+    }
+
+    fn setup(len: u64) -> Machine {
+        let mut machine = Machine::default();
+        machine.regs[usize::from(REG_A0)] = 0x8000;
+        machine.regs[usize::from(REG_A1)] = 0x4000;
+        machine.regs[usize::from(REG_A2)] = len;
+        for i in 0..len {
+            machine.mem.insert(0x4000 + i, (i * 7 + 3).to_le_bytes()[0]);
+        }
+        machine
+    }
+
+    fn expand(instret: HelperInstret) -> OverrideExpansion<Rv64> {
+        let registry = registry(instret);
+        let instr = registry
+            .decode(&encode_memcpy_b(REG_A0, REG_A1, REG_A2), 0x1000u64)
+            .unwrap();
+        registry.lift_expanded(&instr)
+    }
+
+    #[test]
+    fn test_memcpy_matches_straight_line_copy() {
+        let standard = ExtensionRegistry::<Rv64>::standard();
+        for len in [0, 1, 5, 16] {
+            let mut looped = setup(len);
+            looped.run_expansion(expand(HelperInstret::Uncounted));
+
+            let mut linear = setup(len);
+            linear.run_linear(&standard, &unrolled_copy(len as usize));
+
+            assert_eq!(looped.mem, linear.mem, "len={len}");
+            for reg in [REG_A0, REG_A1, REG_A2] {
+                let reg = usize::from(reg);
+                assert_eq!(looped.regs[reg], linear.regs[reg], "len={len} x{reg}");
+            }
+            assert_eq!(looped.regs[usize::from(REG_A2)], 0);
+        }
+    }
+
+    #[test]
+    fn test_memcpy_instret_policy() {
+        let len = 4;
+
+        let mut uncounted = setup(len);
+        uncounted.run_expansion(expand(HelperInstret::Uncounted));
+        assert_eq!(uncounted.instret, 1);
+
+        // Primary, then one head per iteration plus the final check, one body per byte
+        let mut per_block = setup(len);
+        per_block.run_expansion(expand(HelperInstret::PerBlock));
+        assert_eq!(per_block.instret, 1 + (len + 1) + len);
+    }
+
+    #[test]
+    fn test_memcpy_lift_without_expansion_keeps_primary() {
+        let registry = registry(HelperInstret::Uncounted);
+        let instr = registry
+            .decode(&encode_memcpy_b(REG_A0, REG_A1, REG_A2), 0x1000u64)
+            .unwrap();
+        assert!(registry.may_expand(instr.opid));
+
+        let ir = registry.lift(&instr);
+        let expansion = registry.lift_expanded(&instr);
+        assert!(matches!(ir.terminator, Terminator::Jump { target } if target == u64::MAX));
+        assert!(matches!(
+            expansion.primary.terminator,
+            Terminator::Jump { target } if target == u64::MAX
+        ));
+        assert_eq!(registry.disasm(&instr), "memcpy.b x10, x11, x12");
+    }
+}
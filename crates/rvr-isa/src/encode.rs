@@ -1,4 +1,4 @@
-//! Immediate encoding/decoding helpers for RISC-V instructions.
+//! Instruction and immediate encoding/decoding helpers for RISC-V instructions.
 
 /// Decode I-type immediate (bits [31:20] sign-extended).
 #[must_use]
@@ -93,6 +93,77 @@ pub const fn decode_opcode(instr: u32) -> u8 {
     (instr & 0x7F) as u8
 }
 
+/// Encode an R-type instruction.
+#[must_use]
+#[inline]
+pub const fn encode_r(opcode: u8, rd: u8, funct3: u8, rs1: u8, rs2: u8, funct7: u8) -> u32 {
+    ((funct7 as u32 & 0x7F) << 25)
+        | ((rs2 as u32 & 0x1F) << 20)
+        | ((rs1 as u32 & 0x1F) << 15)
+        | ((funct3 as u32 & 0x7) << 12)
+        | ((rd as u32 & 0x1F) << 7)
+        | (opcode as u32 & 0x7F)
+}
+
+/// Encode an I-type instruction (12-bit signed immediate).
+#[must_use]
+#[inline]
+pub const fn encode_i(opcode: u8, rd: u8, funct3: u8, rs1: u8, imm: i32) -> u32 {
+    ((imm.cast_unsigned() & 0xFFF) << 20)
+        | ((rs1 as u32 & 0x1F) << 15)
+        | ((funct3 as u32 & 0x7) << 12)
+        | ((rd as u32 & 0x1F) << 7)
+        | (opcode as u32 & 0x7F)
+}
+
+/// Encode an S-type instruction (12-bit signed immediate).
+#[must_use]
+#[inline]
+pub const fn encode_s(opcode: u8, funct3: u8, rs1: u8, rs2: u8, imm: i32) -> u32 {
+    let imm = imm.cast_unsigned();
+    (((imm >> 5) & 0x7F) << 25)
+        | ((rs2 as u32 & 0x1F) << 20)
+        | ((rs1 as u32 & 0x1F) << 15)
+        | ((funct3 as u32 & 0x7) << 12)
+        | ((imm & 0x1F) << 7)
+        | (opcode as u32 & 0x7F)
+}
+
+/// Encode a B-type instruction (13-bit signed, 2-byte aligned offset).
+#[must_use]
+#[inline]
+pub const fn encode_b(opcode: u8, funct3: u8, rs1: u8, rs2: u8, offset: i32) -> u32 {
+    let imm = offset.cast_unsigned();
+    (((imm >> 12) & 0x1) << 31)
+        | (((imm >> 5) & 0x3F) << 25)
+        | ((rs2 as u32 & 0x1F) << 20)
+        | ((rs1 as u32 & 0x1F) << 15)
+        | ((funct3 as u32 & 0x7) << 12)
+        | (((imm >> 1) & 0xF) << 8)
+        | (((imm >> 11) & 0x1) << 7)
+        | (opcode as u32 & 0x7F)
+}
+
+/// Encode a J-type instruction (21-bit signed, 2-byte aligned offset).
+#[must_use]
+#[inline]
+pub const fn encode_j(opcode: u8, rd: u8, offset: i32) -> u32 {
+    let imm = offset.cast_unsigned();
+    (((imm >> 20) & 0x1) << 31)
+        | (((imm >> 1) & 0x3FF) << 21)
+        | (((imm >> 11) & 0x1) << 20)
+        | (((imm >> 12) & 0xFF) << 12)
+        | ((rd as u32 & 0x1F) << 7)
+        | (opcode as u32 & 0x7F)
+}
+
+/// Encode a U-type instruction (`imm` is the upper 20 bits, unshifted).
+#[must_use]
+#[inline]
+pub const fn encode_u(opcode: u8, rd: u8, imm: u32) -> u32 {
+    ((imm & 0xF_FFFF) << 12) | ((rd as u32 & 0x1F) << 7) | (opcode as u32 & 0x7F)
+}
+
 /// Sign extend from 8 bits to 64 bits.
 #[must_use]
 #[inline]
@@ -143,6 +214,41 @@ mod tests {
         assert_eq!(decode_j_imm(instr), 0);
     }
 
+    #[test]
+    fn test_encode_roundtrip() {
+        // ADDI x1, x2, 100
+        assert_eq!(encode_i(0x13, 1, 0, 2, 100), 0x0641_0093);
+        // ADDI x1, x0, -1
+        assert_eq!(encode_i(0x13, 1, 0, 0, -1), 0xFFF0_0093);
+
+        // SB x6, -8(x11)
+        let sb = encode_s(0x23, 0, 11, 6, -8);
+        assert_eq!(decode_s_imm(sb), -8);
+        assert_eq!(decode_rs1(sb), 11);
+        assert_eq!(decode_rs2(sb), 6);
+
+        // LUI x11, 0x80001
+        let lui = encode_u(0x37, 11, 0x80001);
+        assert_eq!(decode_u_imm(lui).cast_unsigned(), 0x8000_1000);
+        assert_eq!(decode_rd(lui), 11);
+
+        // ADD x11, x11, x5
+        let add = encode_r(0x33, 11, 0, 11, 5, 0);
+        assert_eq!(add, 0x0055_85B3);
+        assert_eq!(decode_funct7(add), 0);
+
+        // BEQ x10, x0, -12
+        let beq = encode_b(0x63, 0, 10, 0, -12);
+        assert_eq!(decode_b_imm(beq), -12);
+        assert_eq!(decode_rs1(beq), 10);
+
+        // JAL x1, 0x7FE
+        let jal = encode_j(0x6F, 1, 0x7FE);
+        assert_eq!(decode_j_imm(jal), 0x7FE);
+        assert_eq!(decode_j_imm(encode_j(0x6F, 0, -0x10_0000)), -0x10_0000);
+        assert_eq!(decode_rd(jal), 1);
+    }
+
     #[test]
     fn test_field_extraction() {
         // ADDI x1, x2, 100 -> rd=1, rs1=2
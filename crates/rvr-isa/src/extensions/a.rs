@@ -416,10 +416,11 @@ fn lift_a<X: Xlen>(args: &InstrArgs, opid: crate::OpId) -> (Vec<Stmt<X>>, Termin
 
             if opid == OP_SC_W || opid == OP_SC_D {
                 // SC: Conditional store based on reservation
-                // cond = res_valid && (res_addr == rs1)
+                // cond = (res_addr == rs1) && res_valid; res_valid is 0 or 1,
+                // and the simple operand goes right for the asm backends
                 let cond = Expr::and(
-                    Expr::ne(Expr::res_valid(), Expr::imm(X::from_u64(0))),
                     Expr::eq(Expr::res_addr(), Expr::read(rs1)),
+                    Expr::res_valid(),
                 );
                 // If valid: store, write 0 to rd, clear reservation
                 let then_stmts = vec![
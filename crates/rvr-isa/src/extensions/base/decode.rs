@@ -49,7 +49,7 @@ pub(super) fn decode_32bit<X: Xlen>(instr: u32, pc: X::Reg) -> Option<DecodedIns
         0x63 => decode_branch(funct3, rs1, rs2, instr)?,
         0x03 => decode_load::<X>(funct3, rd, rs1, instr)?,
         0x23 => decode_store::<X>(funct3, rs1, rs2, instr)?,
-        0x13 => decode_op_imm(funct3, funct7, rd, rs1, instr)?,
+        0x13 => decode_op_imm::<X>(funct3, funct7, rd, rs1, instr)?,
         0x1B if X::VALUE == 64 => decode_op_imm_32(funct3, funct7, rd, rs1, instr)?,
         0x33 if funct7 != 0x01 => decode_op(funct3, funct7, rd, rs1, rs2)?,
         0x3B if X::VALUE == 64 && funct7 != 0x01 => decode_op_32(funct3, funct7, rd, rs1, rs2)?,
@@ -114,7 +114,7 @@ const fn decode_store<X: Xlen>(
     Some((op, InstrArgs::S { rs1, rs2, imm }))
 }
 
-const fn decode_op_imm(
+const fn decode_op_imm<X: Xlen>(
     funct3: u8,
     funct7: u8,
     rd: u8,
@@ -123,6 +123,10 @@ const fn decode_op_imm(
 ) -> Option<(OpId, InstrArgs)> {
     let imm = decode_i_imm(instr);
     let shamt = (instr >> 20) & 0x3F;
+    // shamt[5] is reserved on RV32
+    if (funct3 == 1 || funct3 == 5) && X::VALUE == 32 && shamt & 0x20 != 0 {
+        return None;
+    }
     let op = match funct3 {
         0 => OP_ADDI,
         1 if (funct7 & 0xFE) == 0 => OP_SLLI,
@@ -6,6 +6,7 @@ use super::{
     OP_SRAIW, OP_SRAW, OP_SRL, OP_SRLI, OP_SRLIW, OP_SRLW, OP_SUB, OP_SUBW, OP_SW, OP_XOR, OP_XORI,
     OpId, Stmt, Terminator, Xlen,
 };
+use crate::invalidate_reservation;
 
 pub(super) fn lift_base<X: Xlen>(
     args: &InstrArgs,
@@ -243,11 +244,14 @@ fn lift_store<X: Xlen>(args: &InstrArgs, width: u8) -> (Vec<Stmt<X>>, Terminator
         InstrArgs::S { rs1, rs2, imm } => {
             let base = Expr::read(*rs1);
             let offset = i16::try_from(*imm).expect("store offset fits i16");
+            let addr = Expr::add(
+                base.clone(),
+                Expr::imm(X::from_u64(i64::from(offset).cast_unsigned())),
+            );
             (
                 vec![
                     Stmt::write_mem(base, offset, Expr::read(*rs2), width),
-                    // Clear reservation on any store (spurious failure is allowed).
-                    Stmt::write_res_valid(Expr::imm(X::from_u64(0))),
+                    invalidate_reservation(addr),
                 ],
                 Terminator::Fall { target: None },
             )
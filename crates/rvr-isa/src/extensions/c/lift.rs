@@ -5,6 +5,7 @@ use super::{
     OP_C_NOP, OP_C_OR, OP_C_SD, OP_C_SDSP, OP_C_SLLI, OP_C_SRAI, OP_C_SRLI, OP_C_SUB, OP_C_SUBW,
     OP_C_SW, OP_C_SWSP, OP_C_XOR, Stmt, Terminator, Xlen,
 };
+use crate::invalidate_reservation;
 
 pub(super) fn lift_c<X: Xlen>(
     args: &InstrArgs,
@@ -182,11 +183,14 @@ fn lift_store<X: Xlen>(args: &InstrArgs, width: u8) -> (Vec<Stmt<X>>, Terminator
         InstrArgs::S { rs1, rs2, imm } => {
             let base = Expr::read(*rs1);
             let offset = i16::try_from(*imm).expect("store offset fits i16");
+            let addr = Expr::add(
+                base.clone(),
+                Expr::imm(X::from_u64(i64::from(offset).cast_unsigned())),
+            );
             (
                 vec![
                     Stmt::write_mem(base, offset, Expr::read(*rs2), width),
-                    // Clear reservation on any store (spurious failure is allowed).
-                    Stmt::write_res_valid(Expr::imm(X::from_u64(0))),
+                    invalidate_reservation(addr),
                 ],
                 Terminator::Fall { target: None },
             )
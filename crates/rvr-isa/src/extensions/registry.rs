@@ -0,0 +1,457 @@
+//! Registry of the extensions and overrides used to decode and lift.
+
+use std::collections::HashMap;
+
+use rvr_ir::{InstrIR, OverrideExpansion, Terminator, Xlen};
+
+use super::{
+    AExtension, BaseExtension, CExtension, InstructionExtension, InstructionOverride, LiftSource,
+    MExtension, OP_ECALL, VExtension, ZbaExtension, ZbbExtension, ZbkbExtension, ZbsExtension,
+    ZcbExtension, ZcmpExtension, ZicondExtension, ZicsrExtension, ZifenceiExtension,
+};
+use crate::syscalls::{BareMetalHandler, SyscallHandler};
+use crate::{DecodedInstr, EXT_ZCB, EXT_ZCMP, OpId, OpInfo};
+
+/// Registry for RISC-V instruction set extensions.
+///
+/// Chains multiple extensions and dispatches decode/lift/disasm to the appropriate one.
+/// Extensions are tried in order; C extension should be first to handle compressed instructions.
+///
+/// Supports per-`OpId` overrides for custom instruction handling.
+///
+/// # Building a Registry
+///
+/// Use the builder pattern to construct a registry with specific extensions:
+///
+/// ```ignore
+/// use rvr_isa::{ExtensionRegistry, Rv64};
+///
+/// // Minimal: just base I extension
+/// let minimal = ExtensionRegistry::<Rv64>::base();
+///
+/// // Common embedded: I + M + C
+/// let embedded = ExtensionRegistry::<Rv64>::base()
+///     .with_m()
+///     .with_c();
+///
+/// // Full Linux userspace: I + M + A + C + Zicsr
+/// let linux = ExtensionRegistry::<Rv64>::base()
+///     .with_m()
+///     .with_a()
+///     .with_c()
+///     .with_zicsr();
+///
+/// // All standard extensions
+/// let full = ExtensionRegistry::<Rv64>::standard();
+/// ```
+///
+/// # Extension Order
+///
+/// Extensions are tried in registration order during decode. The C extension
+/// should be added first (via `with_c()`) to handle 16-bit compressed instructions
+/// before 32-bit decoders see them.
+pub struct ExtensionRegistry<X: Xlen> {
+    extensions: Vec<Box<dyn InstructionExtension<X>>>,
+    overrides: HashMap<OpId, Box<dyn InstructionOverride<X>>>,
+    syscall_handler: Box<dyn SyscallHandler<X>>,
+}
+
+impl<X: Xlen> ExtensionRegistry<X> {
+    /// Create a new registry with the given extensions.
+    #[must_use]
+    pub fn new(extensions: Vec<Box<dyn InstructionExtension<X>>>) -> Self {
+        Self {
+            extensions,
+            overrides: HashMap::new(),
+            syscall_handler: Box::new(BareMetalHandler),
+        }
+    }
+
+    /// Create a registry with just the base I extension.
+    ///
+    /// This is the minimal RISC-V configuration. Use builder methods
+    /// to add more extensions:
+    ///
+    /// ```ignore
+    /// let registry = ExtensionRegistry::<Rv64>::base()
+    ///     .with_m()    // Integer multiply/divide
+    ///     .with_c();   // Compressed instructions
+    /// ```
+    #[must_use]
+    pub fn base() -> Self {
+        Self {
+            extensions: vec![Box::new(BaseExtension)],
+            overrides: HashMap::new(),
+            syscall_handler: Box::new(BareMetalHandler),
+        }
+    }
+
+    /// Create a registry with all standard RISC-V extensions.
+    ///
+    /// Includes: I, M, A, C, Zcb, Zcmp, Zicsr, Zifencei, Zba, Zbb, Zbs, Zbkb, Zicond, V.
+    ///
+    /// Order: Zcmp, Zcb and C (compressed first), then I, M, A, Zicsr, Zifencei, Zba, Zbb, Zbs,
+    /// Zbkb, Zicond, V.
+    #[must_use]
+    pub fn standard() -> Self {
+        Self::base()
+            .with_c() // C first (handles 16-bit instructions)
+            .with_zcb()
+            .with_zcmp()
+            .with_m()
+            .with_a()
+            .with_zicsr()
+            .with_zifencei()
+            .with_zba()
+            .with_zbb()
+            .with_zbs()
+            .with_zbkb()
+            .with_zicond()
+            .with_v()
+    }
+
+    /// Create an empty registry (no extensions).
+    ///
+    /// Useful for testing or building a completely custom extension set.
+    #[must_use]
+    pub fn empty() -> Self {
+        Self {
+            extensions: Vec::new(),
+            overrides: HashMap::new(),
+            syscall_handler: Box::new(BareMetalHandler),
+        }
+    }
+
+    // =========================================================================
+    // Standard extension builder methods
+    // =========================================================================
+
+    /// Add M extension (integer multiply/divide).
+    ///
+    /// Instructions: MUL, MULH, MULHSU, MULHU, DIV, DIVU, REM, REMU,
+    /// and W variants for RV64.
+    #[must_use]
+    pub fn with_m(self) -> Self {
+        self.with_extension(MExtension)
+    }
+
+    /// Add A extension (atomic operations).
+    ///
+    /// Instructions: LR.W, SC.W, AMO*.W, and D variants for RV64.
+    #[must_use]
+    pub fn with_a(self) -> Self {
+        self.with_extension(AExtension)
+    }
+
+    /// Add C extension (compressed 16-bit instructions).
+    ///
+    /// **Important**: Should be added first (before other extensions) so
+    /// compressed instructions are decoded before 32-bit decoders see them.
+    ///
+    /// Instructions: C.LW, C.SW, C.ADDI, C.JAL, C.J, etc.
+    #[must_use]
+    pub fn with_c(mut self) -> Self {
+        // Insert C at the front for correct decode order, after Zcb and Zcmp
+        // whose encodings C decodes as illegal
+        let after = self
+            .extensions
+            .iter()
+            .take_while(|ext| matches!(ext.ext_id(), EXT_ZCB | EXT_ZCMP))
+            .count();
+        self.extensions.insert(after, Box::new(CExtension));
+        self
+    }
+
+    /// Add Zcb extension (simple compressed instructions).
+    ///
+    /// Inserted ahead of C. Instructions: C.LBU, C.LHU, C.LH, C.SB, C.SH,
+    /// C.ZEXT.B/H/W, C.SEXT.B/H, C.NOT, C.MUL.
+    #[must_use]
+    pub fn with_zcb(mut self) -> Self {
+        self.extensions.insert(0, Box::new(ZcbExtension));
+        self
+    }
+
+    /// Add Zcmp extension (compressed push/pop and paired moves).
+    ///
+    /// Inserted ahead of C. Instructions: CM.PUSH, CM.POP, CM.POPRET,
+    /// CM.POPRETZ, CM.MVSA01, CM.MVA01S.
+    #[must_use]
+    pub fn with_zcmp(mut self) -> Self {
+        self.extensions.insert(0, Box::new(ZcmpExtension));
+        self
+    }
+
+    /// Add Zicsr extension (CSR instructions).
+    ///
+    /// Instructions: CSRRW, CSRRS, CSRRC, CSRRWI, CSRRSI, CSRRCI.
+    #[must_use]
+    pub fn with_zicsr(self) -> Self {
+        self.with_extension(ZicsrExtension)
+    }
+
+    /// Add Zifencei extension (instruction-fetch fence).
+    ///
+    /// Instructions: FENCE.I.
+    #[must_use]
+    pub fn with_zifencei(self) -> Self {
+        self.with_extension(ZifenceiExtension)
+    }
+
+    /// Add Zba extension (address generation).
+    ///
+    /// Instructions: SH1ADD, SH2ADD, SH3ADD, ADD.UW, SH*ADD.UW, SLLI.UW.
+    #[must_use]
+    pub fn with_zba(self) -> Self {
+        self.with_extension(ZbaExtension)
+    }
+
+    /// Add Zbb extension (basic bit manipulation).
+    ///
+    /// Instructions: ANDN, ORN, XNOR, CLZ, CTZ, CPOP, MAX, MIN, SEXT, ZEXT,
+    /// ROL, ROR, ORC.B, REV8.
+    #[must_use]
+    pub fn with_zbb(self) -> Self {
+        self.with_extension(ZbbExtension)
+    }
+
+    /// Add Zbs extension (single-bit operations).
+    ///
+    /// Instructions: BCLR, BEXT, BINV, BSET and immediate variants.
+    #[must_use]
+    pub fn with_zbs(self) -> Self {
+        self.with_extension(ZbsExtension)
+    }
+
+    /// Add Zbkb extension (bit manipulation for cryptography).
+    ///
+    /// Instructions: BREV8, PACK, PACKH, PACKW, ZIP, UNZIP.
+    #[must_use]
+    pub fn with_zbkb(self) -> Self {
+        self.with_extension(ZbkbExtension)
+    }
+
+    /// Add Zicond extension (conditional operations).
+    ///
+    /// Instructions: CZERO.EQZ, CZERO.NEZ.
+    #[must_use]
+    pub fn with_zicond(self) -> Self {
+        self.with_extension(ZicondExtension)
+    }
+
+    /// Add V extension (vector operations), the subset compilers emit for
+    /// copy and fill loops.
+    ///
+    /// Instructions: VSETVLI, VSETIVLI, VSETVL, VLE*.V, VSE*.V, VADD, VAND,
+    /// VOR, VXOR, VMV.V.*, VMV*R.V.
+    #[must_use]
+    pub fn with_v(self) -> Self {
+        self.with_extension(VExtension)
+    }
+
+    // =========================================================================
+    // Generic extension and override methods
+    // =========================================================================
+
+    /// Add a custom extension to the registry.
+    ///
+    /// Extensions are appended to the end of the decode chain.
+    /// For the C extension, use `with_c()` which inserts at the front.
+    #[must_use]
+    pub fn with_extension(mut self, ext: impl InstructionExtension<X> + 'static) -> Self {
+        self.extensions.push(Box::new(ext));
+        self
+    }
+
+    /// Register an override for a specific `OpId`.
+    ///
+    /// When the given `OpId` is lifted, the override's `lift()` method is called
+    /// instead of the standard extension lift.
+    #[must_use]
+    pub fn with_override(
+        mut self,
+        opid: OpId,
+        handler: impl InstructionOverride<X> + 'static,
+    ) -> Self {
+        self.overrides.insert(opid, Box::new(handler));
+        self
+    }
+
+    /// Register multiple overrides at once.
+    #[must_use]
+    pub fn with_overrides(
+        mut self,
+        overrides: HashMap<OpId, Box<dyn InstructionOverride<X>>>,
+    ) -> Self {
+        self.overrides.extend(overrides);
+        self
+    }
+
+    /// Set the syscall handler for ECALL instructions.
+    ///
+    /// The syscall handler is called when an ECALL instruction is lifted,
+    /// unless an explicit override for `OP_ECALL` is registered.
+    ///
+    /// Default: `RiscvTestsHandler` (exits with a0 as exit code).
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use rvr_isa::{ExtensionRegistry, syscalls::LinuxHandler};
+    /// use rvr_ir::Rv64;
+    ///
+    /// let registry = ExtensionRegistry::<Rv64>::standard()
+    ///     .with_syscall_handler(LinuxHandler::default());
+    /// ```
+    #[must_use]
+    pub fn with_syscall_handler(mut self, handler: impl SyscallHandler<X> + 'static) -> Self {
+        self.syscall_handler = Box::new(handler);
+        self
+    }
+
+    /// Check if there are any overrides registered.
+    #[inline]
+    #[must_use]
+    pub fn has_overrides(&self) -> bool {
+        !self.overrides.is_empty()
+    }
+
+    /// Get all registered extensions.
+    #[must_use]
+    pub fn extensions(&self) -> &[Box<dyn InstructionExtension<X>>] {
+        &self.extensions
+    }
+
+    /// Decode an instruction using registered extensions.
+    pub fn decode(&self, bytes: &[u8], pc: X::Reg) -> Option<DecodedInstr<X>> {
+        for ext in &self.extensions {
+            if let Some(instr) = ext.decode(bytes, pc) {
+                return Some(instr);
+            }
+        }
+        None
+    }
+
+    /// Lift an instruction using the appropriate extension.
+    ///
+    /// Order of precedence:
+    /// 1. Explicit override for the `OpId` (highest priority)
+    /// 2. Syscall handler for ECALL instructions
+    /// 3. Default extension lift
+    pub fn lift(&self, instr: &DecodedInstr<X>) -> InstrIR<X> {
+        // Check for explicit override (highest priority)
+        if let Some(handler) = self.overrides.get(&instr.opid) {
+            let default_lift = |i: &DecodedInstr<X>| self.lift_without_override(i);
+            return handler.lift(instr, &default_lift);
+        }
+
+        self.lift_without_override(instr)
+    }
+
+    /// Lift an instruction, allowing overrides to emit helper blocks.
+    ///
+    /// Same precedence as [`Self::lift`], but consults
+    /// [`InstructionOverride::expand`] and [`SyscallHandler::expand_ecall`].
+    pub fn lift_expanded(&self, instr: &DecodedInstr<X>) -> OverrideExpansion<X> {
+        if let Some(handler) = self.overrides.get(&instr.opid) {
+            let default_lift = |i: &DecodedInstr<X>| self.lift_without_override(i);
+            return handler.expand(instr, &default_lift);
+        }
+
+        if instr.opid == OP_ECALL {
+            return self.syscall_handler.expand_ecall(instr);
+        }
+
+        OverrideExpansion::new(self.lift_default(instr))
+    }
+
+    /// Check whether lifting `opid` may produce helper blocks.
+    #[must_use]
+    pub fn may_expand(&self, opid: OpId) -> bool {
+        opid == OP_ECALL || self.overrides.contains_key(&opid)
+    }
+
+    /// Check whether `opid` has a lifter (override, syscall handler, or
+    /// extension). Without one, lifting falls back to a trap.
+    #[must_use]
+    pub fn can_lift(&self, opid: OpId) -> bool {
+        self.may_expand(opid) || self.extensions.iter().any(|ext| ext.ext_id() == opid.ext)
+    }
+
+    /// Which lifter [`Self::lift`] uses for `opid`.
+    #[must_use]
+    pub fn lift_source(&self, opid: OpId) -> LiftSource {
+        if self.overrides.contains_key(&opid) {
+            return LiftSource::Override;
+        }
+        if opid == OP_ECALL {
+            return LiftSource::SyscallHandler;
+        }
+        self.extensions
+            .iter()
+            .find(|ext| ext.ext_id() == opid.ext)
+            .map_or(LiftSource::Unsupported, |ext| {
+                LiftSource::Extension(ext.name())
+            })
+    }
+
+    /// Lift without checking overrides (for syscall handler and default).
+    fn lift_without_override(&self, instr: &DecodedInstr<X>) -> InstrIR<X> {
+        // ECALL is handled by the syscall handler
+        if instr.opid == OP_ECALL {
+            return self.syscall_handler.handle_ecall(instr);
+        }
+
+        self.lift_default(instr)
+    }
+
+    /// Default lift implementation (no override).
+    fn lift_default(&self, instr: &DecodedInstr<X>) -> InstrIR<X> {
+        for ext in &self.extensions {
+            if ext.ext_id() == instr.opid.ext {
+                return ext.lift(instr);
+            }
+        }
+        // No extension handles this - return trap
+        InstrIR::new(
+            instr.pc,
+            instr.size,
+            instr.opid.pack(),
+            instr.raw,
+            Vec::new(),
+            Terminator::trap("unhandled extension"),
+        )
+    }
+
+    /// Disassemble an instruction.
+    pub fn disasm(&self, instr: &DecodedInstr<X>) -> String {
+        for ext in &self.extensions {
+            if ext.ext_id() == instr.opid.ext {
+                return ext.disasm(instr);
+            }
+        }
+        format!("??? (ext={})", instr.opid.ext)
+    }
+
+    /// Get metadata for an instruction by `OpId`.
+    ///
+    /// Tries all extensions since some extensions (like Zicsr) handle multiple `ext_ids`.
+    #[must_use]
+    pub fn op_info(&self, opid: OpId) -> Option<OpInfo> {
+        for ext in &self.extensions {
+            if let Some(info) = ext.op_info(opid) {
+                return Some(info);
+            }
+        }
+        None
+    }
+}
+
+impl<X: Xlen> Default for ExtensionRegistry<X> {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+/// Type alias for backward compatibility.
+pub type CompositeDecoder<X> = ExtensionRegistry<X>;
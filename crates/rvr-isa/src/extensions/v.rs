@@ -0,0 +1,810 @@
+//! V extension (vector operations, RVV 1.0 subset) - decode, lift, disasm.
+//!
+//! Instructions: vsetvli, vsetivli, vsetvl, vle{8,16,32,64}.v, vse{8,16,32,64}.v,
+//! vadd, vand, vor, vxor (.vv, .vx, .vi), vmv.v.{v,x,i}, vmv{1,2,4,8}r.v
+//!
+//! This is the subset compilers emit for copy and fill loops. Other vector
+//! encodings do not decode, so they show up as decode diagnostics instead of
+//! being misread. Vector instructions lift to calls into the C runtime
+//! (`rv_vsetvl`, `rv_vle`, ...), which keeps `vl`, `vtype` and the vector
+//! registers in `RvState`.
+
+use rvr_ir::{Expr, InstrIR, Stmt, Terminator, Xlen};
+
+use super::InstructionExtension;
+use crate::{
+    DecodedInstr, EXT_V, InstrArgs, OpClass, OpId, OpInfo,
+    encode::{decode_funct3, decode_rd, decode_rs1, decode_rs2},
+    reg_name,
+};
+
+// Instruction OpIds
+pub const OP_VSETVLI: OpId = OpId::new(EXT_V, 0);
+pub const OP_VSETIVLI: OpId = OpId::new(EXT_V, 1);
+pub const OP_VSETVL: OpId = OpId::new(EXT_V, 2);
+pub const OP_VLE8: OpId = OpId::new(EXT_V, 3);
+pub const OP_VLE16: OpId = OpId::new(EXT_V, 4);
+pub const OP_VLE32: OpId = OpId::new(EXT_V, 5);
+pub const OP_VLE64: OpId = OpId::new(EXT_V, 6);
+pub const OP_VSE8: OpId = OpId::new(EXT_V, 7);
+pub const OP_VSE16: OpId = OpId::new(EXT_V, 8);
+pub const OP_VSE32: OpId = OpId::new(EXT_V, 9);
+pub const OP_VSE64: OpId = OpId::new(EXT_V, 10);
+pub const OP_VADD_VV: OpId = OpId::new(EXT_V, 11);
+pub const OP_VADD_VX: OpId = OpId::new(EXT_V, 12);
+pub const OP_VADD_VI: OpId = OpId::new(EXT_V, 13);
+pub const OP_VAND_VV: OpId = OpId::new(EXT_V, 14);
+pub const OP_VAND_VX: OpId = OpId::new(EXT_V, 15);
+pub const OP_VAND_VI: OpId = OpId::new(EXT_V, 16);
+pub const OP_VOR_VV: OpId = OpId::new(EXT_V, 17);
+pub const OP_VOR_VX: OpId = OpId::new(EXT_V, 18);
+pub const OP_VOR_VI: OpId = OpId::new(EXT_V, 19);
+pub const OP_VXOR_VV: OpId = OpId::new(EXT_V, 20);
+pub const OP_VXOR_VX: OpId = OpId::new(EXT_V, 21);
+pub const OP_VXOR_VI: OpId = OpId::new(EXT_V, 22);
+pub const OP_VMV_V_V: OpId = OpId::new(EXT_V, 23);
+pub const OP_VMV_V_X: OpId = OpId::new(EXT_V, 24);
+pub const OP_VMV_V_I: OpId = OpId::new(EXT_V, 25);
+pub const OP_VMV1R: OpId = OpId::new(EXT_V, 26);
+pub const OP_VMV2R: OpId = OpId::new(EXT_V, 27);
+pub const OP_VMV4R: OpId = OpId::new(EXT_V, 28);
+pub const OP_VMV8R: OpId = OpId::new(EXT_V, 29);
+
+/// C runtime functions vector instructions lift to.
+pub const VECTOR_RUNTIME_FNS: &[&str] = &[
+    "rv_vsetvl",
+    "rv_vle",
+    "rv_vse",
+    "rv_vadd_vv",
+    "rv_vadd_vx",
+    "rv_vand_vv",
+    "rv_vand_vx",
+    "rv_vor_vv",
+    "rv_vor_vx",
+    "rv_vxor_vv",
+    "rv_vxor_vx",
+    "rv_vmv_vv",
+    "rv_vmv_vx",
+    "rv_vmvr",
+];
+
+// Opcodes
+const OPCODE_LOAD_FP: u32 = 0b000_0111;
+const OPCODE_STORE_FP: u32 = 0b010_0111;
+const OPCODE_OP_V: u32 = 0b101_0111;
+
+// OP-V funct3 categories
+const FUNCT3_OPIVV: u8 = 0b000;
+const FUNCT3_OPIVI: u8 = 0b011;
+const FUNCT3_OPIVX: u8 = 0b100;
+const FUNCT3_OPCFG: u8 = 0b111;
+
+// OP-V funct6
+const FUNCT6_VADD: u32 = 0b00_0000;
+const FUNCT6_VAND: u32 = 0b00_1001;
+const FUNCT6_VOR: u32 = 0b00_1010;
+const FUNCT6_VXOR: u32 = 0b00_1011;
+const FUNCT6_VMV: u32 = 0b01_0111;
+const FUNCT6_VMVR: u32 = 0b10_0111;
+
+/// Element widths of unit-stride loads and stores, by width field.
+const WIDTH_EEW: [(u8, u8); 4] = [(0b000, 1), (0b101, 2), (0b110, 4), (0b111, 8)];
+
+/// V extension (vector operations).
+pub struct VExtension;
+
+impl<X: Xlen> InstructionExtension<X> for VExtension {
+    fn name(&self) -> &'static str {
+        "V"
+    }
+
+    fn ext_id(&self) -> u8 {
+        EXT_V
+    }
+
+    fn decode32(&self, raw: u32, pc: X::Reg) -> Option<DecodedInstr<X>> {
+        let (opid, args) = match raw & 0x7F {
+            OPCODE_OP_V => decode_op_v(raw)?,
+            OPCODE_LOAD_FP | OPCODE_STORE_FP => decode_unit_stride(raw)?,
+            _ => return None,
+        };
+        Some(DecodedInstr::new(opid, pc, 4, raw, args))
+    }
+
+    fn lift(&self, instr: &DecodedInstr<X>) -> InstrIR<X> {
+        let stmts = match instr.opid {
+            OP_VSETVLI | OP_VSETIVLI | OP_VSETVL => lift_vset(instr),
+            OP_VLE8 | OP_VLE16 | OP_VLE32 | OP_VLE64 => lift_mem(instr, "rv_vle"),
+            OP_VSE8 | OP_VSE16 | OP_VSE32 | OP_VSE64 => lift_mem(instr, "rv_vse"),
+            OP_VMV1R | OP_VMV2R | OP_VMV4R | OP_VMV8R => lift_vmvr(instr),
+            opid => arith_fn(opid).and_then(|(name, scalar)| lift_arith(instr, name, scalar)),
+        };
+        let Some(stmts) = stmts else {
+            return InstrIR::new(
+                instr.pc,
+                instr.size,
+                instr.opid.pack(),
+                instr.raw,
+                Vec::new(),
+                Terminator::trap("unknown V opid"),
+            );
+        };
+        InstrIR::new(
+            instr.pc,
+            instr.size,
+            instr.opid.pack(),
+            instr.raw,
+            stmts,
+            Terminator::Fall { target: None },
+        )
+    }
+
+    fn disasm(&self, instr: &DecodedInstr<X>) -> String {
+        let Some(mnemonic) = v_mnemonic(instr.opid) else {
+            return "???".to_string();
+        };
+        match &instr.args {
+            InstrArgs::I { rd, rs1, imm } => format!(
+                "{mnemonic} {}, {}, {}",
+                reg_name(*rd),
+                reg_name(*rs1),
+                vtype_name(u32::try_from(*imm).unwrap_or(u32::MAX))
+            ),
+            InstrArgs::CsrI { rd, imm, csr } => format!(
+                "{mnemonic} {}, {imm}, {}",
+                reg_name(*rd),
+                vtype_name(u32::from(*csr))
+            ),
+            InstrArgs::R { rd, rs1, rs2 } => format!(
+                "{mnemonic} {}, {}, {}",
+                reg_name(*rd),
+                reg_name(*rs1),
+                reg_name(*rs2)
+            ),
+            InstrArgs::Custom(fields) => disasm_vector(instr.opid, mnemonic, fields),
+            _ => format!("{mnemonic} ???"),
+        }
+    }
+
+    fn op_info(&self, opid: OpId) -> Option<OpInfo> {
+        OP_INFO_V.iter().find(|info| info.opid == opid).copied()
+    }
+}
+
+// === Decode helpers ===
+
+/// Decode an OP-V instruction: configuration, integer arithmetic and moves.
+fn decode_op_v(raw: u32) -> Option<(OpId, InstrArgs)> {
+    let funct3 = decode_funct3(raw);
+    let rd = decode_rd(raw);
+    let rs1 = decode_rs1(raw);
+    if funct3 == FUNCT3_OPCFG {
+        return decode_vset(raw, rd, rs1);
+    }
+
+    let funct6 = raw >> 26;
+    let vm = (raw >> 25) & 1;
+    let vs2 = u32::from(decode_rs2(raw));
+    let src = match funct3 {
+        FUNCT3_OPIVV | FUNCT3_OPIVX => u32::from(rs1),
+        // simm5, sign-extended
+        FUNCT3_OPIVI => ((i32::from(rs1) << 27) >> 27).cast_unsigned(),
+        _ => return None,
+    };
+    let form = match funct3 {
+        FUNCT3_OPIVV => 0,
+        FUNCT3_OPIVX => 1,
+        _ => 2,
+    };
+    let opid = match funct6 {
+        FUNCT6_VADD => [OP_VADD_VV, OP_VADD_VX, OP_VADD_VI][form],
+        FUNCT6_VAND => [OP_VAND_VV, OP_VAND_VX, OP_VAND_VI][form],
+        FUNCT6_VOR => [OP_VOR_VV, OP_VOR_VX, OP_VOR_VI][form],
+        FUNCT6_VXOR => [OP_VXOR_VV, OP_VXOR_VX, OP_VXOR_VI][form],
+        // vm=0 is vmerge, which is not supported
+        FUNCT6_VMV if vm == 1 && vs2 == 0 => [OP_VMV_V_V, OP_VMV_V_X, OP_VMV_V_I][form],
+        FUNCT6_VMVR if vm == 1 && funct3 == FUNCT3_OPIVI => {
+            let (opid, nr) = match rs1 {
+                0 => (OP_VMV1R, 1),
+                1 => (OP_VMV2R, 2),
+                3 => (OP_VMV4R, 4),
+                7 => (OP_VMV8R, 8),
+                _ => return None,
+            };
+            // Register groups must be aligned to their size
+            if u32::from(rd) % nr != 0 || vs2 % nr != 0 {
+                return None;
+            }
+            return Some((opid, InstrArgs::Custom(Box::new([u32::from(rd), vs2, nr]))));
+        }
+        _ => return None,
+    };
+    Some((
+        opid,
+        InstrArgs::Custom(Box::new([u32::from(rd), vs2, src, vm])),
+    ))
+}
+
+/// Decode vsetvli, vsetivli or vsetvl.
+///
+/// They write `rd` like other instructions, so their arguments use the
+/// standard shapes: vsetivli's AVL immediate and `vtype` ride in `CsrI`.
+fn decode_vset(raw: u32, rd: u8, rs1: u8) -> Option<(OpId, InstrArgs)> {
+    if raw >> 31 == 0 {
+        let zimm = i32::try_from((raw >> 20) & 0x7FF).ok()?;
+        return Some((OP_VSETVLI, InstrArgs::I { rd, rs1, imm: zimm }));
+    }
+    if raw >> 30 == 0b11 {
+        let zimm = u16::try_from((raw >> 20) & 0x3FF).ok()?;
+        return Some((
+            OP_VSETIVLI,
+            InstrArgs::CsrI {
+                rd,
+                imm: rs1,
+                csr: zimm,
+            },
+        ));
+    }
+    if raw >> 25 == 0b100_0000 {
+        let rs2 = decode_rs2(raw);
+        return Some((OP_VSETVL, InstrArgs::R { rd, rs1, rs2 }));
+    }
+    None
+}
+
+/// Decode a unit-stride vector load or store.
+///
+/// Segment, strided, indexed, whole-register and fault-only-first forms do
+/// not decode.
+fn decode_unit_stride(raw: u32) -> Option<(OpId, InstrArgs)> {
+    let width = decode_funct3(raw);
+    let (_, eew) = WIDTH_EEW.iter().find(|&&(w, _)| w == width)?;
+    // nf, mew, mop and lumop/sumop must all be zero
+    let fields = (raw >> 26) & 0x3F;
+    let umop = decode_rs2(raw);
+    if fields != 0 || umop != 0 {
+        return None;
+    }
+    let idx = eew.trailing_zeros() as usize;
+    let opid = if raw & 0x7F == OPCODE_LOAD_FP {
+        [OP_VLE8, OP_VLE16, OP_VLE32, OP_VLE64][idx]
+    } else {
+        [OP_VSE8, OP_VSE16, OP_VSE32, OP_VSE64][idx]
+    };
+    let vm = (raw >> 25) & 1;
+    Some((
+        opid,
+        InstrArgs::Custom(Box::new([
+            u32::from(decode_rd(raw)),
+            u32::from(decode_rs1(raw)),
+            u32::from(*eew),
+            vm,
+        ])),
+    ))
+}
+
+// === Lift helpers ===
+
+fn imm<X: Xlen>(value: u32) -> Expr<X> {
+    Expr::imm(X::from_u64(u64::from(value)))
+}
+
+/// Call `rv_vsetvl(state, avl, vtype, keep)` and write the new `vl` to `rd`.
+fn lift_vset<X: Xlen>(instr: &DecodedInstr<X>) -> Option<Vec<Stmt<X>>> {
+    let (rd, avl, vtype) = match instr.args {
+        InstrArgs::I { rd, rs1, imm: zimm } => (rd, rs1, imm(u32::try_from(zimm).ok()?)),
+        InstrArgs::R { rd, rs1, rs2 } => (rd, rs1, Expr::reg(rs2)),
+        InstrArgs::CsrI {
+            rd,
+            imm: uimm,
+            csr: zimm,
+        } => {
+            let args = vec![
+                Expr::var("state"),
+                imm(u32::from(uimm)),
+                imm(u32::from(zimm)),
+                imm(0),
+            ];
+            return Some(vec![vset_call(rd, args)]);
+        }
+        _ => return None,
+    };
+    // rs1 = x0 requests VLMAX, or with rd = x0 too, keeps the current vl
+    let (avl, keep) = match (avl, rd) {
+        (0, 0) => (imm(0), 1),
+        (0, _) => (Expr::imm(X::from_u64(u64::MAX)), 0),
+        (rs1, _) => (Expr::reg(rs1), 0),
+    };
+    let args = vec![Expr::var("state"), avl, vtype, imm(keep)];
+    Some(vec![vset_call(rd, args)])
+}
+
+fn vset_call<X: Xlen>(rd: u8, args: Vec<Expr<X>>) -> Stmt<X> {
+    if rd == 0 {
+        return Stmt::extern_call("rv_vsetvl", args);
+    }
+    let width = u8::try_from(X::REG_BYTES * 8).expect("register width fits u8");
+    Stmt::write_reg(rd, Expr::extern_call("rv_vsetvl", args, width))
+}
+
+/// `<name>(state, vd, addr, eew, vm)` for a unit-stride load or store.
+fn lift_mem<X: Xlen>(instr: &DecodedInstr<X>, name: &str) -> Option<Vec<Stmt<X>>> {
+    let InstrArgs::Custom(fields) = &instr.args else {
+        return None;
+    };
+    let &[vd, rs1, eew, vm] = fields.as_ref() else {
+        return None;
+    };
+    let args = vec![
+        Expr::var("state"),
+        imm(vd),
+        Expr::reg(u8::try_from(rs1).ok()?),
+        imm(eew),
+        imm(vm),
+    ];
+    Some(vec![Stmt::extern_call(name, args)])
+}
+
+/// Runtime function of an arithmetic opid, and whether its source is a
+/// scalar (register or immediate) rather than a vector register.
+const fn arith_fn(opid: OpId) -> Option<(&'static str, bool)> {
+    Some(match opid {
+        OP_VADD_VV => ("rv_vadd_vv", false),
+        OP_VADD_VX | OP_VADD_VI => ("rv_vadd_vx", true),
+        OP_VAND_VV => ("rv_vand_vv", false),
+        OP_VAND_VX | OP_VAND_VI => ("rv_vand_vx", true),
+        OP_VOR_VV => ("rv_vor_vv", false),
+        OP_VOR_VX | OP_VOR_VI => ("rv_vor_vx", true),
+        OP_VXOR_VV => ("rv_vxor_vv", false),
+        OP_VXOR_VX | OP_VXOR_VI => ("rv_vxor_vx", true),
+        OP_VMV_V_V => ("rv_vmv_vv", false),
+        OP_VMV_V_X | OP_VMV_V_I => ("rv_vmv_vx", true),
+        _ => return None,
+    })
+}
+
+/// `<name>(state, vd, vs2, src, vm)`; `.vi` forms pass the immediate as the
+/// scalar operand of the `.vx` function.
+fn lift_arith<X: Xlen>(instr: &DecodedInstr<X>, name: &str, scalar: bool) -> Option<Vec<Stmt<X>>> {
+    let InstrArgs::Custom(fields) = &instr.args else {
+        return None;
+    };
+    let &[vd, vs2, src, vm] = fields.as_ref() else {
+        return None;
+    };
+    let src = if is_vi(instr.opid) {
+        Expr::imm(X::from_u64(i64::from(src.cast_signed()).cast_unsigned()))
+    } else if scalar {
+        Expr::reg(u8::try_from(src).ok()?)
+    } else {
+        imm(src)
+    };
+    let args = vec![Expr::var("state"), imm(vd), imm(vs2), src, imm(vm)];
+    Some(vec![Stmt::extern_call(name, args)])
+}
+
+/// Whether `opid` takes a 5-bit signed immediate.
+const fn is_vi(opid: OpId) -> bool {
+    matches!(
+        opid,
+        OP_VADD_VI | OP_VAND_VI | OP_VOR_VI | OP_VXOR_VI | OP_VMV_V_I
+    )
+}
+
+/// `rv_vmvr(state, vd, vs2, nr)`.
+fn lift_vmvr<X: Xlen>(instr: &DecodedInstr<X>) -> Option<Vec<Stmt<X>>> {
+    let InstrArgs::Custom(fields) = &instr.args else {
+        return None;
+    };
+    let &[vd, vs2, nr] = fields.as_ref() else {
+        return None;
+    };
+    let args = vec![Expr::var("state"), imm(vd), imm(vs2), imm(nr)];
+    Some(vec![Stmt::extern_call("rv_vmvr", args)])
+}
+
+// === Disasm helpers ===
+
+/// `e32, m1, ta, ma` for a `vtype` immediate.
+fn vtype_name(vtype: u32) -> String {
+    if vtype >> 8 != 0 {
+        return format!("{vtype:#x}");
+    }
+    let sew = match (vtype >> 3) & 7 {
+        0 => "e8",
+        1 => "e16",
+        2 => "e32",
+        3 => "e64",
+        _ => return format!("{vtype:#x}"),
+    };
+    let lmul = match vtype & 7 {
+        0 => "m1",
+        1 => "m2",
+        2 => "m4",
+        3 => "m8",
+        5 => "mf8",
+        6 => "mf4",
+        7 => "mf2",
+        _ => return format!("{vtype:#x}"),
+    };
+    let ta = if vtype & 0x40 != 0 { "ta" } else { "tu" };
+    let ma = if vtype & 0x80 != 0 { "ma" } else { "mu" };
+    format!("{sew}, {lmul}, {ta}, {ma}")
+}
+
+fn disasm_vector(opid: OpId, mnemonic: &str, fields: &[u32]) -> String {
+    let mask = |vm: u32| if vm == 0 { ", v0.t" } else { "" };
+    let reg = |r: u32| reg_name(u8::try_from(r).unwrap_or(0));
+    match (opid, fields) {
+        (_, &[vd, rs1, _, vm]) if (OP_VLE8.idx..=OP_VSE64.idx).contains(&opid.idx) => {
+            format!("{mnemonic} v{vd}, ({}){}", reg(rs1), mask(vm))
+        }
+        (_, &[vd, vs2, _]) => format!("{mnemonic} v{vd}, v{vs2}"),
+        (OP_VMV_V_V, &[vd, _, vs1, _]) => format!("{mnemonic} v{vd}, v{vs1}"),
+        (OP_VMV_V_X, &[vd, _, rs1, _]) => format!("{mnemonic} v{vd}, {}", reg(rs1)),
+        (OP_VMV_V_I, &[vd, _, simm, _]) => format!("{mnemonic} v{vd}, {}", simm.cast_signed()),
+        (_, &[vd, vs2, src, vm]) => {
+            let src = match arith_fn(opid) {
+                Some((_, false)) => format!("v{src}"),
+                _ if is_vi(opid) => src.cast_signed().to_string(),
+                _ => reg(src).to_string(),
+            };
+            format!("{mnemonic} v{vd}, v{vs2}, {src}{}", mask(vm))
+        }
+        _ => format!("{mnemonic} ???"),
+    }
+}
+
+/// Table-driven `OpInfo` for V extension.
+const OP_INFO_V: &[OpInfo] = &[
+    OpInfo {
+        opid: OP_VSETVLI,
+        name: "vsetvli",
+        class: OpClass::Csr,
+        size_hint: 4,
+    },
+    OpInfo {
+        opid: OP_VSETIVLI,
+        name: "vsetivli",
+        class: OpClass::Csr,
+        size_hint: 4,
+    },
+    OpInfo {
+        opid: OP_VSETVL,
+        name: "vsetvl",
+        class: OpClass::Csr,
+        size_hint: 4,
+    },
+    OpInfo {
+        opid: OP_VLE8,
+        name: "vle8.v",
+        class: OpClass::Load,
+        size_hint: 4,
+    },
+    OpInfo {
+        opid: OP_VLE16,
+        name: "vle16.v",
+        class: OpClass::Load,
+        size_hint: 4,
+    },
+    OpInfo {
+        opid: OP_VLE32,
+        name: "vle32.v",
+        class: OpClass::Load,
+        size_hint: 4,
+    },
+    OpInfo {
+        opid: OP_VLE64,
+        name: "vle64.v",
+        class: OpClass::Load,
+        size_hint: 4,
+    },
+    OpInfo {
+        opid: OP_VSE8,
+        name: "vse8.v",
+        class: OpClass::Store,
+        size_hint: 4,
+    },
+    OpInfo {
+        opid: OP_VSE16,
+        name: "vse16.v",
+        class: OpClass::Store,
+        size_hint: 4,
+    },
+    OpInfo {
+        opid: OP_VSE32,
+        name: "vse32.v",
+        class: OpClass::Store,
+        size_hint: 4,
+    },
+    OpInfo {
+        opid: OP_VSE64,
+        name: "vse64.v",
+        class: OpClass::Store,
+        size_hint: 4,
+    },
+    OpInfo {
+        opid: OP_VADD_VV,
+        name: "vadd.vv",
+        class: OpClass::Alu,
+        size_hint: 4,
+    },
+    OpInfo {
+        opid: OP_VADD_VX,
+        name: "vadd.vx",
+        class: OpClass::Alu,
+        size_hint: 4,
+    },
+    OpInfo {
+        opid: OP_VADD_VI,
+        name: "vadd.vi",
+        class: OpClass::Alu,
+        size_hint: 4,
+    },
+    OpInfo {
+        opid: OP_VAND_VV,
+        name: "vand.vv",
+        class: OpClass::Alu,
+        size_hint: 4,
+    },
+    OpInfo {
+        opid: OP_VAND_VX,
+        name: "vand.vx",
+        class: OpClass::Alu,
+        size_hint: 4,
+    },
+    OpInfo {
+        opid: OP_VAND_VI,
+        name: "vand.vi",
+        class: OpClass::Alu,
+        size_hint: 4,
+    },
+    OpInfo {
+        opid: OP_VOR_VV,
+        name: "vor.vv",
+        class: OpClass::Alu,
+        size_hint: 4,
+    },
+    OpInfo {
+        opid: OP_VOR_VX,
+        name: "vor.vx",
+        class: OpClass::Alu,
+        size_hint: 4,
+    },
+    OpInfo {
+        opid: OP_VOR_VI,
+        name: "vor.vi",
+        class: OpClass::Alu,
+        size_hint: 4,
+    },
+    OpInfo {
+        opid: OP_VXOR_VV,
+        name: "vxor.vv",
+        class: OpClass::Alu,
+        size_hint: 4,
+    },
+    OpInfo {
+        opid: OP_VXOR_VX,
+        name: "vxor.vx",
+        class: OpClass::Alu,
+        size_hint: 4,
+    },
+    OpInfo {
+        opid: OP_VXOR_VI,
+        name: "vxor.vi",
+        class: OpClass::Alu,
+        size_hint: 4,
+    },
+    OpInfo {
+        opid: OP_VMV_V_V,
+        name: "vmv.v.v",
+        class: OpClass::Alu,
+        size_hint: 4,
+    },
+    OpInfo {
+        opid: OP_VMV_V_X,
+        name: "vmv.v.x",
+        class: OpClass::Alu,
+        size_hint: 4,
+    },
+    OpInfo {
+        opid: OP_VMV_V_I,
+        name: "vmv.v.i",
+        class: OpClass::Alu,
+        size_hint: 4,
+    },
+    OpInfo {
+        opid: OP_VMV1R,
+        name: "vmv1r.v",
+        class: OpClass::Alu,
+        size_hint: 4,
+    },
+    OpInfo {
+        opid: OP_VMV2R,
+        name: "vmv2r.v",
+        class: OpClass::Alu,
+        size_hint: 4,
+    },
+    OpInfo {
+        opid: OP_VMV4R,
+        name: "vmv4r.v",
+        class: OpClass::Alu,
+        size_hint: 4,
+    },
+    OpInfo {
+        opid: OP_VMV8R,
+        name: "vmv8r.v",
+        class: OpClass::Alu,
+        size_hint: 4,
+    },
+];
+
+/// Get mnemonic for V instruction.
+#[must_use]
+pub fn v_mnemonic(opid: OpId) -> Option<&'static str> {
+    OP_INFO_V
+        .iter()
+        .find(|info| info.opid == opid)
+        .map(|info| info.name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rvr_ir::{ReadExpr, Rv64};
+
+    fn decode(raw: u32) -> Option<DecodedInstr<Rv64>> {
+        InstructionExtension::<Rv64>::decode32(&VExtension, raw, 0u64)
+    }
+
+    fn disasm(raw: u32) -> String {
+        let instr = decode(raw).unwrap();
+        InstructionExtension::<Rv64>::disasm(&VExtension, &instr)
+    }
+
+    fn op_v(funct6: u32, vm: u32, vs2: u32, src: u32, funct3: u8, vd: u32) -> u32 {
+        OPCODE_OP_V
+            | (vd << 7)
+            | (u32::from(funct3) << 12)
+            | (src << 15)
+            | (vs2 << 20)
+            | (vm << 25)
+            | (funct6 << 26)
+    }
+
+    #[test]
+    fn test_vset_decode() {
+        // vsetvli a0, a1, e8, m8, ta, ma
+        let instr = decode(0x0C35_F557).unwrap();
+        assert_eq!(instr.opid, OP_VSETVLI);
+        assert_eq!(
+            instr.args,
+            InstrArgs::I {
+                rd: 10,
+                rs1: 11,
+                imm: 0xC3
+            }
+        );
+        // vsetivli zero, 16, e32, m1, tu, mu
+        let instr = decode(0xC108_7057).unwrap();
+        assert_eq!(instr.opid, OP_VSETIVLI);
+        assert_eq!(
+            instr.args,
+            InstrArgs::CsrI {
+                rd: 0,
+                imm: 16,
+                csr: 0x10
+            }
+        );
+        // vsetvl a0, a1, a2
+        assert_eq!(decode(0x80C5_F557).unwrap().opid, OP_VSETVL);
+        // Reserved encoding between vsetvl and vsetivli
+        assert!(decode(0x82C5_F557).is_none());
+    }
+
+    #[test]
+    fn test_unit_stride_decode() {
+        // vle8.v v8, (a0)
+        let instr = decode(0x0205_0407).unwrap();
+        assert_eq!(instr.opid, OP_VLE8);
+        assert_eq!(instr.args, InstrArgs::Custom(Box::new([8, 10, 1, 1])));
+        // vse64.v v8, (a1), v0.t
+        let instr = decode(0x0005_F427).unwrap();
+        assert_eq!(instr.opid, OP_VSE64);
+        assert_eq!(instr.args, InstrArgs::Custom(Box::new([8, 11, 8, 0])));
+        // vlse8.v (strided), vle8ff.v, vl1re8.v and flw do not decode
+        assert!(decode(0x0ac5_0407).is_none());
+        assert!(decode(0x0305_0407).is_none());
+        assert!(decode(0x0285_0407).is_none());
+        assert!(decode(0x0005_2407).is_none());
+    }
+
+    #[test]
+    fn test_arith_decode() {
+        let vadd_vi = op_v(FUNCT6_VADD, 1, 2, 0x1D, FUNCT3_OPIVI, 1);
+        let instr = decode(vadd_vi).unwrap();
+        assert_eq!(instr.opid, OP_VADD_VI);
+        assert_eq!(
+            instr.args,
+            InstrArgs::Custom(Box::new([1, 2, (-3i32).cast_unsigned(), 1]))
+        );
+        let vxor_vx = op_v(FUNCT6_VXOR, 0, 2, 10, FUNCT3_OPIVX, 1);
+        assert_eq!(decode(vxor_vx).unwrap().opid, OP_VXOR_VX);
+        let vmv_v_x = op_v(FUNCT6_VMV, 1, 0, 10, FUNCT3_OPIVX, 4);
+        assert_eq!(decode(vmv_v_x).unwrap().opid, OP_VMV_V_X);
+        // vmerge.vxm
+        assert!(decode(op_v(FUNCT6_VMV, 0, 2, 10, FUNCT3_OPIVX, 4)).is_none());
+        let vmv2r = op_v(FUNCT6_VMVR, 1, 4, 1, FUNCT3_OPIVI, 2);
+        assert_eq!(decode(vmv2r).unwrap().opid, OP_VMV2R);
+        // Misaligned register group
+        assert!(decode(op_v(FUNCT6_VMVR, 1, 3, 1, FUNCT3_OPIVI, 2)).is_none());
+        // vsub.vv is not supported
+        assert!(decode(op_v(0b00_0010, 1, 2, 3, FUNCT3_OPIVV, 1)).is_none());
+    }
+
+    #[test]
+    fn test_disasm() {
+        assert_eq!(disasm(0x0C35_F557), "vsetvli a0, a1, e8, m8, ta, ma");
+        assert_eq!(disasm(0xC108_7057), "vsetivli zero, 16, e32, m1, tu, mu");
+        assert_eq!(disasm(0x0205_0407), "vle8.v v8, (a0)");
+        assert_eq!(disasm(0x0005_F427), "vse64.v v8, (a1), v0.t");
+        assert_eq!(
+            disasm(op_v(FUNCT6_VADD, 1, 2, 0x1D, FUNCT3_OPIVI, 1)),
+            "vadd.vi v1, v2, -3"
+        );
+        assert_eq!(
+            disasm(op_v(FUNCT6_VAND, 0, 2, 3, FUNCT3_OPIVV, 1)),
+            "vand.vv v1, v2, v3, v0.t"
+        );
+        assert_eq!(
+            disasm(op_v(FUNCT6_VMV, 1, 0, 10, FUNCT3_OPIVX, 4)),
+            "vmv.v.x v4, a0"
+        );
+        assert_eq!(
+            disasm(op_v(FUNCT6_VMVR, 1, 2, 0, FUNCT3_OPIVI, 1)),
+            "vmv1r.v v1, v2"
+        );
+    }
+
+    #[test]
+    fn test_lift_vsetvli() {
+        let instr = decode(0x0C35_F557).unwrap();
+        let ir = InstructionExtension::<Rv64>::lift(&VExtension, &instr);
+        let [Stmt::Write { value, .. }] = ir.statements.as_slice() else {
+            panic!("expected a write to rd: {:?}", ir.statements);
+        };
+        let Expr::ExternCall { name, args, .. } = value else {
+            panic!("expected a call: {value:?}");
+        };
+        assert_eq!(name, "rv_vsetvl");
+        assert!(matches!(args[1], Expr::Read(ReadExpr::Reg(11))));
+        assert!(matches!(args[2], Expr::Imm(0xC3)));
+
+        // vsetvli zero, zero keeps vl and writes no register
+        let instr = decode(0x0C30_7057).unwrap();
+        let ir = InstructionExtension::<Rv64>::lift(&VExtension, &instr);
+        let [Stmt::ExternCall { args, .. }] = ir.statements.as_slice() else {
+            panic!("expected a call: {:?}", ir.statements);
+        };
+        assert!(matches!(args[3], Expr::Imm(1)));
+    }
+
+    #[test]
+    fn test_lift_arith_immediate() {
+        let instr = decode(op_v(FUNCT6_VOR, 1, 2, 0x1D, FUNCT3_OPIVI, 1)).unwrap();
+        let ir = InstructionExtension::<Rv64>::lift(&VExtension, &instr);
+        let [Stmt::ExternCall { fn_name, args }] = ir.statements.as_slice() else {
+            panic!("expected a call: {:?}", ir.statements);
+        };
+        assert_eq!(fn_name, "rv_vor_vx");
+        assert!(matches!(args[3], Expr::Imm(0xFFFF_FFFF_FFFF_FFFD)));
+    }
+
+    #[test]
+    fn test_op_info() {
+        let info = InstructionExtension::<Rv64>::op_info(&VExtension, OP_VSE32).unwrap();
+        assert_eq!(info.name, "vse32.v");
+        assert_eq!(info.class, OpClass::Store);
+        assert!(OP_INFO_V.iter().all(|info| info.opid.ext == EXT_V));
+    }
+}
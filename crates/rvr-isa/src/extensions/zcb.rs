@@ -0,0 +1,402 @@
+//! Zcb extension (simple compressed instructions) - decode, lift, disasm.
+//!
+//! Instructions: c.lbu, c.lhu, c.lh, c.sb, c.sh, c.zext.b, c.sext.b,
+//! c.zext.h, c.sext.h, c.zext.w, c.not, c.mul
+//!
+//! Each lifts to the IR of its uncompressed equivalent.
+
+use rvr_ir::{InstrIR, Terminator, Xlen};
+
+use super::{
+    BaseExtension, InstructionExtension, MExtension, OP_ADD_UW, OP_ANDI, OP_LBU, OP_LH, OP_LHU,
+    OP_MUL, OP_SB, OP_SEXT_B, OP_SEXT_H, OP_SH, OP_XORI, OP_ZEXT_H, ZbaExtension, ZbbExtension,
+};
+use crate::{DecodedInstr, EXT_ZCB, InstrArgs, OpClass, OpId, OpInfo, REG_ZERO, reg_name};
+
+// Instruction OpIds
+pub const OP_C_LBU: OpId = OpId::new(EXT_ZCB, 0);
+pub const OP_C_LHU: OpId = OpId::new(EXT_ZCB, 1);
+pub const OP_C_LH: OpId = OpId::new(EXT_ZCB, 2);
+pub const OP_C_SB: OpId = OpId::new(EXT_ZCB, 3);
+pub const OP_C_SH: OpId = OpId::new(EXT_ZCB, 4);
+pub const OP_C_ZEXT_B: OpId = OpId::new(EXT_ZCB, 5);
+pub const OP_C_SEXT_B: OpId = OpId::new(EXT_ZCB, 6);
+pub const OP_C_ZEXT_H: OpId = OpId::new(EXT_ZCB, 7);
+pub const OP_C_SEXT_H: OpId = OpId::new(EXT_ZCB, 8);
+pub const OP_C_ZEXT_W: OpId = OpId::new(EXT_ZCB, 9); // RV64
+pub const OP_C_NOT: OpId = OpId::new(EXT_ZCB, 10);
+pub const OP_C_MUL: OpId = OpId::new(EXT_ZCB, 11);
+
+// Encoding constants
+const QUADRANT_0: u16 = 0b00;
+const QUADRANT_1: u16 = 0b01;
+const FUNCT3_ZCB: u16 = 0b100;
+/// Bits [12:10] of the quadrant 0 loads and stores.
+const FUNCT3_LBU: u16 = 0b000;
+const FUNCT3_LH: u16 = 0b001;
+const FUNCT3_SB: u16 = 0b010;
+const FUNCT3_SH: u16 = 0b011;
+/// Bits [12:10] of the quadrant 1 arithmetic.
+const FUNCT3_ARITH: u16 = 0b111;
+/// Bits [6:5] of the quadrant 1 arithmetic.
+const FUNCT2_MUL: u16 = 0b10;
+const FUNCT2_UNARY: u16 = 0b11;
+/// Bits [4:2] of the unary operations.
+const UNARY_ZEXT_B: u16 = 0b000;
+const UNARY_SEXT_B: u16 = 0b001;
+const UNARY_ZEXT_H: u16 = 0b010;
+const UNARY_SEXT_H: u16 = 0b011;
+const UNARY_ZEXT_W: u16 = 0b100;
+const UNARY_NOT: u16 = 0b101;
+/// `x8`, the first register a 3-bit compressed register field names.
+const CREG_BASE: u8 = 8;
+const BYTE_MASK: i32 = 0xFF;
+const ALL_ONES: i32 = -1;
+const INSTR_SIZE: u8 = 2;
+
+/// Zcb extension (simple compressed instructions).
+///
+/// Must be registered before the C extension, which decodes Zcb's reserved
+/// encodings as illegal instructions.
+pub struct ZcbExtension;
+
+impl<X: Xlen> InstructionExtension<X> for ZcbExtension {
+    fn name(&self) -> &'static str {
+        "Zcb"
+    }
+
+    fn ext_id(&self) -> u8 {
+        EXT_ZCB
+    }
+
+    fn decode16(&self, raw: u16, pc: X::Reg) -> Option<DecodedInstr<X>> {
+        if (raw >> 13) & 0x7 != FUNCT3_ZCB {
+            return None;
+        }
+        let (opid, args) = match raw & 0x3 {
+            QUADRANT_0 => decode_q0(raw)?,
+            QUADRANT_1 => decode_q1::<X>(raw)?,
+            _ => return None,
+        };
+        Some(DecodedInstr::new(
+            opid,
+            pc,
+            INSTR_SIZE,
+            u32::from(raw),
+            args,
+        ))
+    }
+
+    fn lift(&self, instr: &DecodedInstr<X>) -> InstrIR<X> {
+        let args = instr.args.clone();
+        let (rd, rs2) = match instr.args {
+            InstrArgs::I { rd, .. } => (rd, REG_ZERO),
+            InstrArgs::R { rd, rs2, .. } => (rd, rs2),
+            _ => (REG_ZERO, REG_ZERO),
+        };
+        let unary = |opid, imm| (opid, InstrArgs::I { rd, rs1: rd, imm });
+        match instr.opid {
+            OP_C_LBU => lift_as(&BaseExtension, instr, OP_LBU, args),
+            OP_C_LHU => lift_as(&BaseExtension, instr, OP_LHU, args),
+            OP_C_LH => lift_as(&BaseExtension, instr, OP_LH, args),
+            OP_C_SB => lift_as(&BaseExtension, instr, OP_SB, args),
+            OP_C_SH => lift_as(&BaseExtension, instr, OP_SH, args),
+            OP_C_ZEXT_B => {
+                let (opid, args) = unary(OP_ANDI, BYTE_MASK);
+                lift_as(&BaseExtension, instr, opid, args)
+            }
+            OP_C_NOT => {
+                let (opid, args) = unary(OP_XORI, ALL_ONES);
+                lift_as(&BaseExtension, instr, opid, args)
+            }
+            OP_C_SEXT_B => lift_as(&ZbbExtension, instr, OP_SEXT_B, args),
+            OP_C_ZEXT_H => lift_as(&ZbbExtension, instr, OP_ZEXT_H, args),
+            OP_C_SEXT_H => lift_as(&ZbbExtension, instr, OP_SEXT_H, args),
+            // zext.w is add.uw rd, rd, zero
+            OP_C_ZEXT_W => {
+                let args = InstrArgs::R {
+                    rd,
+                    rs1: rd,
+                    rs2: REG_ZERO,
+                };
+                lift_as(&ZbaExtension, instr, OP_ADD_UW, args)
+            }
+            OP_C_MUL => {
+                let args = InstrArgs::R { rd, rs1: rd, rs2 };
+                lift_as(&MExtension, instr, OP_MUL, args)
+            }
+            _ => InstrIR::new(
+                instr.pc,
+                instr.size,
+                instr.opid.pack(),
+                instr.raw,
+                Vec::new(),
+                Terminator::trap("unknown Zcb opid"),
+            ),
+        }
+    }
+
+    fn disasm(&self, instr: &DecodedInstr<X>) -> String {
+        let mnemonic = zcb_mnemonic(instr.opid).unwrap_or("???");
+        match instr.args {
+            InstrArgs::I { rd, rs1, imm } if is_memory(instr.opid) => {
+                format!("{mnemonic} {}, {imm}({})", reg_name(rd), reg_name(rs1))
+            }
+            InstrArgs::S { rs1, rs2, imm } => {
+                format!("{mnemonic} {}, {imm}({})", reg_name(rs2), reg_name(rs1))
+            }
+            InstrArgs::I { rd, .. } => format!("{mnemonic} {}", reg_name(rd)),
+            InstrArgs::R { rd, rs2, .. } => {
+                format!("{mnemonic} {}, {}", reg_name(rd), reg_name(rs2))
+            }
+            _ => format!("{mnemonic} ???"),
+        }
+    }
+
+    fn op_info(&self, opid: OpId) -> Option<OpInfo> {
+        OP_INFO_ZCB.iter().find(|info| info.opid == opid).copied()
+    }
+}
+
+/// The `x8`-`x15` register a 3-bit field at `shift` names.
+const fn creg(raw: u16, shift: u32) -> u8 {
+    ((raw >> shift) & 0x7) as u8 + CREG_BASE
+}
+
+/// Quadrant 0: byte and halfword loads and stores.
+fn decode_q0(raw: u16) -> Option<(OpId, InstrArgs)> {
+    let rs1 = creg(raw, 7);
+    let rd = creg(raw, 2);
+    let bit5 = i32::from((raw >> 5) & 1);
+    let bit6 = (raw >> 6) & 1;
+    // Byte offsets are uimm[1:0] = {bit 5, bit 6}; halfword ones uimm[1] = bit 5
+    let byte_offset = (bit5 << 1) | i32::from(bit6);
+    let half_offset = bit5 << 1;
+    let load = |opid, imm| Some((opid, InstrArgs::I { rd, rs1, imm }));
+    let store = |opid, imm| Some((opid, InstrArgs::S { rs1, rs2: rd, imm }));
+    match (raw >> 10) & 0x7 {
+        FUNCT3_LBU => load(OP_C_LBU, byte_offset),
+        FUNCT3_LH if bit6 == 0 => load(OP_C_LHU, half_offset),
+        FUNCT3_LH => load(OP_C_LH, half_offset),
+        FUNCT3_SB => store(OP_C_SB, byte_offset),
+        FUNCT3_SH if bit6 == 0 => store(OP_C_SH, half_offset),
+        _ => None,
+    }
+}
+
+/// Quadrant 1: unary operations and `c.mul`.
+const fn decode_q1<X: Xlen>(raw: u16) -> Option<(OpId, InstrArgs)> {
+    if (raw >> 10) & 0x7 != FUNCT3_ARITH {
+        return None;
+    }
+    let rd = creg(raw, 7);
+    match (raw >> 5) & 0x3 {
+        FUNCT2_MUL => Some((
+            OP_C_MUL,
+            InstrArgs::R {
+                rd,
+                rs1: rd,
+                rs2: creg(raw, 2),
+            },
+        )),
+        FUNCT2_UNARY => {
+            let opid = match (raw >> 2) & 0x7 {
+                UNARY_ZEXT_B => OP_C_ZEXT_B,
+                UNARY_SEXT_B => OP_C_SEXT_B,
+                UNARY_ZEXT_H => OP_C_ZEXT_H,
+                UNARY_SEXT_H => OP_C_SEXT_H,
+                UNARY_ZEXT_W if X::VALUE == 64 => OP_C_ZEXT_W,
+                UNARY_NOT => OP_C_NOT,
+                _ => return None,
+            };
+            Some((
+                opid,
+                InstrArgs::I {
+                    rd,
+                    rs1: rd,
+                    imm: 0,
+                },
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// Lift `instr` as the uncompressed `opid` with `args`, keeping its own
+/// `OpId` for tracing.
+fn lift_as<X: Xlen>(
+    ext: &dyn InstructionExtension<X>,
+    instr: &DecodedInstr<X>,
+    opid: OpId,
+    args: InstrArgs,
+) -> InstrIR<X> {
+    let equivalent = DecodedInstr::new(opid, instr.pc, instr.size, instr.raw, args);
+    let mut ir = ext.lift(&equivalent);
+    ir.op = instr.opid.pack();
+    ir
+}
+
+const fn is_memory(opid: OpId) -> bool {
+    matches!(opid, OP_C_LBU | OP_C_LHU | OP_C_LH)
+}
+
+/// Table-driven `OpInfo` for Zcb extension.
+const OP_INFO_ZCB: &[OpInfo] = &[
+    OpInfo {
+        opid: OP_C_LBU,
+        name: "c.lbu",
+        class: OpClass::Load,
+        size_hint: 2,
+    },
+    OpInfo {
+        opid: OP_C_LHU,
+        name: "c.lhu",
+        class: OpClass::Load,
+        size_hint: 2,
+    },
+    OpInfo {
+        opid: OP_C_LH,
+        name: "c.lh",
+        class: OpClass::Load,
+        size_hint: 2,
+    },
+    OpInfo {
+        opid: OP_C_SB,
+        name: "c.sb",
+        class: OpClass::Store,
+        size_hint: 2,
+    },
+    OpInfo {
+        opid: OP_C_SH,
+        name: "c.sh",
+        class: OpClass::Store,
+        size_hint: 2,
+    },
+    OpInfo {
+        opid: OP_C_ZEXT_B,
+        name: "c.zext.b",
+        class: OpClass::Alu,
+        size_hint: 2,
+    },
+    OpInfo {
+        opid: OP_C_SEXT_B,
+        name: "c.sext.b",
+        class: OpClass::Alu,
+        size_hint: 2,
+    },
+    OpInfo {
+        opid: OP_C_ZEXT_H,
+        name: "c.zext.h",
+        class: OpClass::Alu,
+        size_hint: 2,
+    },
+    OpInfo {
+        opid: OP_C_SEXT_H,
+        name: "c.sext.h",
+        class: OpClass::Alu,
+        size_hint: 2,
+    },
+    OpInfo {
+        opid: OP_C_ZEXT_W,
+        name: "c.zext.w",
+        class: OpClass::Alu,
+        size_hint: 2,
+    },
+    OpInfo {
+        opid: OP_C_NOT,
+        name: "c.not",
+        class: OpClass::Alu,
+        size_hint: 2,
+    },
+    OpInfo {
+        opid: OP_C_MUL,
+        name: "c.mul",
+        class: OpClass::Mul,
+        size_hint: 2,
+    },
+];
+
+/// Get mnemonic for Zcb instruction.
+#[must_use]
+pub fn zcb_mnemonic(opid: OpId) -> Option<&'static str> {
+    OP_INFO_ZCB
+        .iter()
+        .find(|info| info.opid == opid)
+        .map(|info| info.name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rvr_ir::{Rv32, Rv64};
+
+    fn decode<X: Xlen>(raw: u16) -> Option<DecodedInstr<X>> {
+        InstructionExtension::<X>::decode16(&ZcbExtension, raw, X::from_u64(0))
+    }
+
+    fn disasm(raw: u16) -> String {
+        let instr = decode::<Rv64>(raw).unwrap();
+        InstructionExtension::<Rv64>::disasm(&ZcbExtension, &instr)
+    }
+
+    #[test]
+    fn test_decode_loads_and_stores() {
+        // c.lbu a0, 3(a1)
+        assert_eq!(disasm(0x81e8), "c.lbu a0, 3(a1)");
+        // c.lhu a0, 2(a1)
+        assert_eq!(disasm(0x85a8), "c.lhu a0, 2(a1)");
+        // c.lh a0, 2(a1)
+        assert_eq!(disasm(0x85e8), "c.lh a0, 2(a1)");
+        // c.sb a0, 1(a1)
+        assert_eq!(disasm(0x89c8), "c.sb a0, 1(a1)");
+        // c.sh a0, 0(a1)
+        assert_eq!(disasm(0x8d88), "c.sh a0, 0(a1)");
+        // c.sh with bit 6 set is reserved
+        assert!(decode::<Rv64>(0x8dc8).is_none());
+    }
+
+    #[test]
+    fn test_decode_arithmetic() {
+        assert_eq!(disasm(0x9d61), "c.zext.b a0");
+        assert_eq!(disasm(0x9d65), "c.sext.b a0");
+        assert_eq!(disasm(0x9d69), "c.zext.h a0");
+        assert_eq!(disasm(0x9d6d), "c.sext.h a0");
+        assert_eq!(disasm(0x9d71), "c.zext.w a0");
+        assert_eq!(disasm(0x9d75), "c.not a0");
+        assert_eq!(disasm(0x9d4d), "c.mul a0, a1");
+        // c.zext.w is RV64 only; bits [4:2] above c.not are reserved
+        assert!(decode::<Rv32>(0x9d71).is_none());
+        assert!(decode::<Rv64>(0x9d79).is_none());
+    }
+
+    #[test]
+    fn test_lift_matches_uncompressed() {
+        let instr = decode::<Rv64>(0x9d75).unwrap();
+        let ir = InstructionExtension::<Rv64>::lift(&ZcbExtension, &instr);
+        let xori = DecodedInstr::new(
+            OP_XORI,
+            0u64,
+            INSTR_SIZE,
+            instr.raw,
+            InstrArgs::I {
+                rd: 10,
+                rs1: 10,
+                imm: ALL_ONES,
+            },
+        );
+        let expected = InstructionExtension::<Rv64>::lift(&BaseExtension, &xori);
+        assert_eq!(
+            format!("{:?}", ir.statements),
+            format!("{:?}", expected.statements)
+        );
+        assert_eq!(ir.op, OP_C_NOT.pack());
+        assert_eq!(ir.size, INSTR_SIZE);
+    }
+
+    #[test]
+    fn test_op_info() {
+        let info = InstructionExtension::<Rv64>::op_info(&ZcbExtension, OP_C_SB).unwrap();
+        assert_eq!(info.name, "c.sb");
+        assert_eq!(info.class, OpClass::Store);
+    }
+}
@@ -0,0 +1,482 @@
+//! Zcmp extension (compressed push/pop and paired moves) - decode, lift, disasm.
+//!
+//! Instructions: cm.push, cm.pop, cm.popretz, cm.popret, cm.mvsa01, cm.mva01s
+//!
+//! Push and pop lift to the loads or stores of their register list plus the
+//! `sp` adjustment; `cm.popret` and `cm.popretz` then return through `ra`.
+
+use rvr_ir::{Expr, InstrIR, Stmt, Terminator, Xlen};
+
+use super::InstructionExtension;
+use crate::{
+    DecodedInstr, EXT_ZCMP, InstrArgs, OpClass, OpId, OpInfo, REG_A0, REG_A1, REG_RA, REG_S0,
+    REG_S1, REG_S2, REG_S3, REG_S4, REG_S5, REG_S6, REG_S7, REG_S8, REG_S9, REG_S10, REG_S11,
+    REG_SP, invalidate_reservation, reg_name,
+};
+
+// Instruction OpIds
+pub const OP_CM_PUSH: OpId = OpId::new(EXT_ZCMP, 0);
+pub const OP_CM_POP: OpId = OpId::new(EXT_ZCMP, 1);
+pub const OP_CM_POPRETZ: OpId = OpId::new(EXT_ZCMP, 2);
+pub const OP_CM_POPRET: OpId = OpId::new(EXT_ZCMP, 3);
+pub const OP_CM_MVSA01: OpId = OpId::new(EXT_ZCMP, 4);
+pub const OP_CM_MVA01S: OpId = OpId::new(EXT_ZCMP, 5);
+
+// Encoding constants
+const QUADRANT_2: u16 = 0b10;
+const FUNCT3_ZCMP: u16 = 0b101;
+/// Bits [12:8] of the push and pop instructions.
+const FUNCT5_PUSH: u16 = 0b11000;
+const FUNCT5_POP: u16 = 0b11010;
+const FUNCT5_POPRETZ: u16 = 0b11100;
+const FUNCT5_POPRET: u16 = 0b11110;
+/// Bits [12:10] of the paired moves.
+const FUNCT3_MOVE: u16 = 0b011;
+/// Bits [6:5] of the paired moves.
+const FUNCT2_MVSA01: u16 = 0b01;
+const FUNCT2_MVA01S: u16 = 0b11;
+/// Register lists below this are reserved.
+const RLIST_MIN: u8 = 4;
+/// The register list naming `ra` and `s0`-`s11` (`s10` never goes alone).
+const RLIST_ALL: u8 = 15;
+/// `sp` stays 16-byte aligned, and `spimm` counts extra 16-byte units.
+const STACK_ALIGN: u32 = 16;
+const INSTR_SIZE: u8 = 2;
+
+/// Registers a register list can name, in list order.
+const RLIST_REGS: [u8; 13] = [
+    REG_RA, REG_S0, REG_S1, REG_S2, REG_S3, REG_S4, REG_S5, REG_S6, REG_S7, REG_S8, REG_S9,
+    REG_S10, REG_S11,
+];
+
+/// Registers the 3-bit `r1s'`/`r2s'` fields name.
+const SREGS: [u8; 8] = [
+    REG_S0, REG_S1, REG_S2, REG_S3, REG_S4, REG_S5, REG_S6, REG_S7,
+];
+
+/// Zcmp extension (compressed push/pop and paired moves).
+///
+/// Must be registered before the C extension, which decodes Zcmp's encodings
+/// (those of `c.fsdsp`) as illegal instructions.
+pub struct ZcmpExtension;
+
+impl<X: Xlen> InstructionExtension<X> for ZcmpExtension {
+    fn name(&self) -> &'static str {
+        "Zcmp"
+    }
+
+    fn ext_id(&self) -> u8 {
+        EXT_ZCMP
+    }
+
+    fn decode16(&self, raw: u16, pc: X::Reg) -> Option<DecodedInstr<X>> {
+        if raw & 0x3 != QUADRANT_2 || (raw >> 13) & 0x7 != FUNCT3_ZCMP {
+            return None;
+        }
+        let (opid, args) = match (raw >> 8) & 0x1F {
+            FUNCT5_PUSH => (OP_CM_PUSH, decode_stack::<X>(raw)?),
+            FUNCT5_POP => (OP_CM_POP, decode_stack::<X>(raw)?),
+            FUNCT5_POPRETZ => (OP_CM_POPRETZ, decode_stack::<X>(raw)?),
+            FUNCT5_POPRET => (OP_CM_POPRET, decode_stack::<X>(raw)?),
+            _ => decode_move(raw)?,
+        };
+        Some(DecodedInstr::new(
+            opid,
+            pc,
+            INSTR_SIZE,
+            u32::from(raw),
+            args,
+        ))
+    }
+
+    fn lift(&self, instr: &DecodedInstr<X>) -> InstrIR<X> {
+        let (stmts, term) = match (instr.opid, &instr.args) {
+            (OP_CM_PUSH, InstrArgs::Custom(fields)) => (
+                lift_push::<X>(rlist(fields), fields[1]),
+                Terminator::Fall { target: None },
+            ),
+            (OP_CM_POP, InstrArgs::Custom(fields)) => (
+                lift_pop::<X>(rlist(fields), fields[1]),
+                Terminator::Fall { target: None },
+            ),
+            (OP_CM_POPRETZ | OP_CM_POPRET, InstrArgs::Custom(fields)) => {
+                let mut stmts = lift_pop::<X>(rlist(fields), fields[1]);
+                if instr.opid == OP_CM_POPRETZ {
+                    stmts.push(Stmt::write_reg(REG_A0, Expr::imm(X::from_u64(0))));
+                }
+                (stmts, Terminator::jump_dyn(Expr::read(REG_RA)))
+            }
+            (OP_CM_MVSA01, InstrArgs::Custom(fields)) => (
+                vec![
+                    Stmt::write_reg(sreg(fields, 0), Expr::read(REG_A0)),
+                    Stmt::write_reg(sreg(fields, 1), Expr::read(REG_A1)),
+                ],
+                Terminator::Fall { target: None },
+            ),
+            (OP_CM_MVA01S, InstrArgs::Custom(fields)) => (
+                vec![
+                    Stmt::write_reg(REG_A0, Expr::read(sreg(fields, 0))),
+                    Stmt::write_reg(REG_A1, Expr::read(sreg(fields, 1))),
+                ],
+                Terminator::Fall { target: None },
+            ),
+            _ => (Vec::new(), Terminator::trap("unknown Zcmp opid")),
+        };
+        InstrIR::new(
+            instr.pc,
+            instr.size,
+            instr.opid.pack(),
+            instr.raw,
+            stmts,
+            term,
+        )
+    }
+
+    fn disasm(&self, instr: &DecodedInstr<X>) -> String {
+        let mnemonic = zcmp_mnemonic(instr.opid).unwrap_or("???");
+        let InstrArgs::Custom(fields) = &instr.args else {
+            return format!("{mnemonic} ???");
+        };
+        match instr.opid {
+            OP_CM_PUSH => format!("{mnemonic} {}, -{}", format_rlist(rlist(fields)), fields[1]),
+            OP_CM_MVSA01 | OP_CM_MVA01S => format!(
+                "{mnemonic} {}, {}",
+                reg_name(sreg(fields, 0)),
+                reg_name(sreg(fields, 1))
+            ),
+            _ => format!("{mnemonic} {}, {}", format_rlist(rlist(fields)), fields[1]),
+        }
+    }
+
+    fn op_info(&self, opid: OpId) -> Option<OpInfo> {
+        OP_INFO_ZCMP.iter().find(|info| info.opid == opid).copied()
+    }
+}
+
+/// Registers a push/pop register list saves or restores: `ra`, then
+/// `s0` onwards. Empty for the reserved lists.
+#[must_use]
+pub fn rlist_regs(rlist: u8) -> &'static [u8] {
+    let count = match rlist {
+        RLIST_ALL => RLIST_REGS.len(),
+        RLIST_MIN..RLIST_ALL => usize::from(rlist - RLIST_MIN + 1),
+        _ => 0,
+    };
+    &RLIST_REGS[..count]
+}
+
+/// Bytes a push/pop moves `sp` by: the register list rounded up to the
+/// stack alignment, plus `spimm` more aligned units.
+#[must_use]
+pub fn stack_adjustment<X: Xlen>(rlist: u8, spimm: u32) -> u32 {
+    let bytes = u32::try_from(rlist_regs(rlist).len() * X::REG_BYTES).unwrap_or(0);
+    bytes.next_multiple_of(STACK_ALIGN) + spimm * STACK_ALIGN
+}
+
+/// Registers a Zcmp instruction writes, a bit per register; 0 for other
+/// instructions.
+#[must_use]
+pub fn zcmp_written_regs(opid: OpId, args: &InstrArgs) -> u32 {
+    let InstrArgs::Custom(fields) = args else {
+        return 0;
+    };
+    let bit = |reg: u8| 1u32 << reg;
+    let popped = || {
+        rlist_regs(rlist(fields))
+            .iter()
+            .fold(bit(REG_SP), |mask, &reg| mask | bit(reg))
+    };
+    match opid {
+        OP_CM_PUSH => bit(REG_SP),
+        OP_CM_POP | OP_CM_POPRET => popped(),
+        OP_CM_POPRETZ => popped() | bit(REG_A0),
+        OP_CM_MVSA01 => bit(sreg(fields, 0)) | bit(sreg(fields, 1)),
+        OP_CM_MVA01S => bit(REG_A0) | bit(REG_A1),
+        _ => 0,
+    }
+}
+
+/// `[rlist, stack adjustment]` of a push/pop; `None` for reserved lists.
+fn decode_stack<X: Xlen>(raw: u16) -> Option<InstrArgs> {
+    let rlist = ((raw >> 4) & 0xF) as u8;
+    if rlist < RLIST_MIN {
+        return None;
+    }
+    let spimm = u32::from((raw >> 2) & 0x3);
+    Some(InstrArgs::Custom(Box::new([
+        u32::from(rlist),
+        stack_adjustment::<X>(rlist, spimm),
+    ])))
+}
+
+/// `cm.mvsa01`/`cm.mva01s` with `[r1s, r2s]` as register numbers.
+fn decode_move(raw: u16) -> Option<(OpId, InstrArgs)> {
+    if (raw >> 10) & 0x7 != FUNCT3_MOVE {
+        return None;
+    }
+    let opid = match (raw >> 5) & 0x3 {
+        FUNCT2_MVSA01 => OP_CM_MVSA01,
+        FUNCT2_MVA01S => OP_CM_MVA01S,
+        _ => return None,
+    };
+    let r1s = SREGS[usize::from((raw >> 7) & 0x7)];
+    let r2s = SREGS[usize::from((raw >> 2) & 0x7)];
+    // Both halves of a0/a1 going to one register is reserved
+    if opid == OP_CM_MVSA01 && r1s == r2s {
+        return None;
+    }
+    Some((
+        opid,
+        InstrArgs::Custom(Box::new([u32::from(r1s), u32::from(r2s)])),
+    ))
+}
+
+fn rlist(fields: &[u32]) -> u8 {
+    u8::try_from(fields[0]).unwrap_or(0)
+}
+
+fn sreg(fields: &[u32], idx: usize) -> u8 {
+    u8::try_from(fields[idx]).unwrap_or(0)
+}
+
+fn reg_width<X: Xlen>() -> u8 {
+    u8::try_from(X::REG_BYTES).expect("register width fits u8")
+}
+
+/// Store the list below `sp`, highest register first, then move `sp` down.
+fn lift_push<X: Xlen>(rlist: u8, stack_adj: u32) -> Vec<Stmt<X>> {
+    let width = reg_width::<X>();
+    let mut stmts = Vec::new();
+    for (slot, &reg) in (1i16..).zip(rlist_regs(rlist).iter().rev()) {
+        let offset = -slot * i16::from(width);
+        let sp = Expr::read(REG_SP);
+        let addr = Expr::add(
+            sp.clone(),
+            Expr::imm(X::from_u64(i64::from(offset).cast_unsigned())),
+        );
+        stmts.push(Stmt::write_mem(sp, offset, Expr::read(reg), width));
+        stmts.push(invalidate_reservation(addr));
+    }
+    stmts.push(Stmt::write_reg(
+        REG_SP,
+        Expr::sub(
+            Expr::read(REG_SP),
+            Expr::imm(X::from_u64(u64::from(stack_adj))),
+        ),
+    ));
+    stmts
+}
+
+/// Load the list from the top of the frame, highest register first, then
+/// move `sp` up.
+fn lift_pop<X: Xlen>(rlist: u8, stack_adj: u32) -> Vec<Stmt<X>> {
+    let width = reg_width::<X>();
+    let top = i16::try_from(stack_adj).expect("stack adjustment fits i16");
+    let mut stmts = Vec::new();
+    for (slot, &reg) in (1i16..).zip(rlist_regs(rlist).iter().rev()) {
+        let offset = top - slot * i16::from(width);
+        let value = Expr::mem(Expr::read(REG_SP), offset, width, false);
+        stmts.push(Stmt::write_reg(reg, value));
+    }
+    stmts.push(Stmt::write_reg(
+        REG_SP,
+        Expr::add(
+            Expr::read(REG_SP),
+            Expr::imm(X::from_u64(u64::from(stack_adj))),
+        ),
+    ));
+    stmts
+}
+
+/// `{ra}`, `{ra, s0}` or `{ra, s0-sN}`.
+fn format_rlist(rlist: u8) -> String {
+    let names = match rlist_regs(rlist) {
+        [] => String::new(),
+        [ra] => reg_name(*ra).to_string(),
+        [ra, s0] => format!("{}, {}", reg_name(*ra), reg_name(*s0)),
+        [ra, s0, .., last] => {
+            format!("{}, {}-{}", reg_name(*ra), reg_name(*s0), reg_name(*last))
+        }
+    };
+    format!("{{{names}}}")
+}
+
+/// Table-driven `OpInfo` for Zcmp extension.
+const OP_INFO_ZCMP: &[OpInfo] = &[
+    OpInfo {
+        opid: OP_CM_PUSH,
+        name: "cm.push",
+        class: OpClass::Store,
+        size_hint: 2,
+    },
+    OpInfo {
+        opid: OP_CM_POP,
+        name: "cm.pop",
+        class: OpClass::Load,
+        size_hint: 2,
+    },
+    OpInfo {
+        opid: OP_CM_POPRETZ,
+        name: "cm.popretz",
+        class: OpClass::JumpIndirect,
+        size_hint: 2,
+    },
+    OpInfo {
+        opid: OP_CM_POPRET,
+        name: "cm.popret",
+        class: OpClass::JumpIndirect,
+        size_hint: 2,
+    },
+    OpInfo {
+        opid: OP_CM_MVSA01,
+        name: "cm.mvsa01",
+        class: OpClass::Alu,
+        size_hint: 2,
+    },
+    OpInfo {
+        opid: OP_CM_MVA01S,
+        name: "cm.mva01s",
+        class: OpClass::Alu,
+        size_hint: 2,
+    },
+];
+
+/// Get mnemonic for Zcmp instruction.
+#[must_use]
+pub fn zcmp_mnemonic(opid: OpId) -> Option<&'static str> {
+    OP_INFO_ZCMP
+        .iter()
+        .find(|info| info.opid == opid)
+        .map(|info| info.name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rvr_ir::{Rv32, Rv64};
+
+    /// A push/pop with `funct5` in bits [12:8].
+    const fn stack_op(funct5: u16, rlist: u16, spimm: u16) -> u16 {
+        (FUNCT3_ZCMP << 13) | (funct5 << 8) | (rlist << 4) | (spimm << 2) | QUADRANT_2
+    }
+
+    const fn push(rlist: u16, spimm: u16) -> u16 {
+        stack_op(FUNCT5_PUSH, rlist, spimm)
+    }
+
+    const fn pop(rlist: u16, spimm: u16) -> u16 {
+        stack_op(FUNCT5_POP, rlist, spimm)
+    }
+
+    fn decode<X: Xlen>(raw: u16) -> Option<DecodedInstr<X>> {
+        InstructionExtension::<X>::decode16(&ZcmpExtension, raw, X::from_u64(0))
+    }
+
+    fn disasm<X: Xlen>(raw: u16) -> String {
+        let instr = decode::<X>(raw).unwrap();
+        InstructionExtension::<X>::disasm(&ZcmpExtension, &instr)
+    }
+
+    #[test]
+    fn test_push_register_lists_rv32() {
+        assert_eq!(disasm::<Rv32>(push(4, 0)), "cm.push {ra}, -16");
+        assert_eq!(disasm::<Rv32>(push(5, 0)), "cm.push {ra, s0}, -16");
+        assert_eq!(disasm::<Rv32>(push(6, 0)), "cm.push {ra, s0-s1}, -16");
+        assert_eq!(disasm::<Rv32>(push(7, 0)), "cm.push {ra, s0-s2}, -16");
+        assert_eq!(disasm::<Rv32>(push(8, 0)), "cm.push {ra, s0-s3}, -32");
+        assert_eq!(disasm::<Rv32>(push(9, 0)), "cm.push {ra, s0-s4}, -32");
+        assert_eq!(disasm::<Rv32>(push(10, 0)), "cm.push {ra, s0-s5}, -32");
+        assert_eq!(disasm::<Rv32>(push(11, 0)), "cm.push {ra, s0-s6}, -32");
+        assert_eq!(disasm::<Rv32>(push(12, 0)), "cm.push {ra, s0-s7}, -48");
+        assert_eq!(disasm::<Rv32>(push(13, 0)), "cm.push {ra, s0-s8}, -48");
+        assert_eq!(disasm::<Rv32>(push(14, 0)), "cm.push {ra, s0-s9}, -48");
+        assert_eq!(disasm::<Rv32>(push(15, 0)), "cm.push {ra, s0-s11}, -64");
+    }
+
+    #[test]
+    fn test_push_register_lists_rv64() {
+        assert_eq!(disasm::<Rv64>(push(4, 0)), "cm.push {ra}, -16");
+        assert_eq!(disasm::<Rv64>(push(5, 0)), "cm.push {ra, s0}, -16");
+        assert_eq!(disasm::<Rv64>(push(6, 0)), "cm.push {ra, s0-s1}, -32");
+        assert_eq!(disasm::<Rv64>(push(7, 0)), "cm.push {ra, s0-s2}, -32");
+        assert_eq!(disasm::<Rv64>(push(8, 0)), "cm.push {ra, s0-s3}, -48");
+        assert_eq!(disasm::<Rv64>(push(9, 0)), "cm.push {ra, s0-s4}, -48");
+        assert_eq!(disasm::<Rv64>(push(10, 0)), "cm.push {ra, s0-s5}, -64");
+        assert_eq!(disasm::<Rv64>(push(11, 0)), "cm.push {ra, s0-s6}, -64");
+        assert_eq!(disasm::<Rv64>(push(12, 0)), "cm.push {ra, s0-s7}, -80");
+        assert_eq!(disasm::<Rv64>(push(13, 0)), "cm.push {ra, s0-s8}, -80");
+        assert_eq!(disasm::<Rv64>(push(14, 0)), "cm.push {ra, s0-s9}, -96");
+        assert_eq!(disasm::<Rv64>(push(15, 0)), "cm.push {ra, s0-s11}, -112");
+    }
+
+    #[test]
+    fn test_pop_register_lists() {
+        let mask = |raw| {
+            let instr = decode::<Rv32>(raw).unwrap();
+            zcmp_written_regs(instr.opid, &instr.args)
+        };
+        let sp = 1 << REG_SP;
+        let ra = 1 << REG_RA;
+        // s0 and s1 are x8-x9, s2-s11 are x18-x27
+        assert_eq!(mask(pop(4, 0)), sp | ra);
+        assert_eq!(mask(pop(5, 0)), sp | ra | 0x100);
+        assert_eq!(mask(pop(6, 0)), sp | ra | 0x300);
+        assert_eq!(mask(pop(7, 0)), sp | ra | 0x300 | 0x4_0000);
+        assert_eq!(mask(pop(8, 0)), sp | ra | 0x300 | 0xC_0000);
+        assert_eq!(mask(pop(9, 0)), sp | ra | 0x300 | 0x1C_0000);
+        assert_eq!(mask(pop(10, 0)), sp | ra | 0x300 | 0x3C_0000);
+        assert_eq!(mask(pop(11, 0)), sp | ra | 0x300 | 0x7C_0000);
+        assert_eq!(mask(pop(12, 0)), sp | ra | 0x300 | 0xFC_0000);
+        assert_eq!(mask(pop(13, 0)), sp | ra | 0x300 | 0x1FC_0000);
+        assert_eq!(mask(pop(14, 0)), sp | ra | 0x300 | 0x3FC_0000);
+        assert_eq!(mask(pop(15, 0)), sp | ra | 0x300 | 0xFFC_0000);
+    }
+
+    #[test]
+    fn test_reserved_register_lists() {
+        assert!(decode::<Rv64>(push(0, 0)).is_none());
+        assert!(decode::<Rv64>(push(3, 0)).is_none());
+        assert!(decode::<Rv64>(pop(3, 0)).is_none());
+        assert!(rlist_regs(3).is_empty());
+    }
+
+    #[test]
+    fn test_decode_pops_and_moves() {
+        assert_eq!(disasm::<Rv32>(pop(5, 1)), "cm.pop {ra, s0}, 32");
+        assert_eq!(
+            disasm::<Rv64>(stack_op(FUNCT5_POPRET, 6, 3)),
+            "cm.popret {ra, s0-s1}, 80"
+        );
+        assert_eq!(
+            disasm::<Rv32>(stack_op(FUNCT5_POPRETZ, 4, 0)),
+            "cm.popretz {ra}, 16"
+        );
+        // cm.mvsa01 s0, s2 / cm.mva01s s1, s7
+        assert_eq!(disasm::<Rv32>(0xac2a), "cm.mvsa01 s0, s2");
+        assert_eq!(disasm::<Rv32>(0xacfe), "cm.mva01s s1, s7");
+        // cm.mvsa01 with r1s' == r2s' is reserved
+        assert!(decode::<Rv32>(0xac22).is_none());
+    }
+
+    #[test]
+    fn test_lift_push_and_popret() {
+        let instr = decode::<Rv32>(push(6, 0)).unwrap();
+        let ir = InstructionExtension::<Rv32>::lift(&ZcmpExtension, &instr);
+        // A store and a reservation check per register, then the sp update
+        assert_eq!(ir.statements.len(), 3 * 2 + 1);
+        assert!(matches!(ir.terminator, Terminator::Fall { .. }));
+
+        let instr = decode::<Rv32>(stack_op(FUNCT5_POPRETZ, 6, 0)).unwrap();
+        let ir = InstructionExtension::<Rv32>::lift(&ZcmpExtension, &instr);
+        // Three loads, a0 = 0 and the sp update
+        assert_eq!(ir.statements.len(), 3 + 2);
+        assert!(ir.terminator.is_control_flow());
+    }
+
+    #[test]
+    fn test_op_info() {
+        let info = InstructionExtension::<Rv64>::op_info(&ZcmpExtension, OP_CM_POPRET).unwrap();
+        assert_eq!(info.name, "cm.popret");
+        assert_eq!(info.class, OpClass::JumpIndirect);
+    }
+}
@@ -24,7 +24,12 @@ pub const CSR_INSTRET: u16 = 0xC02;
 pub const CSR_CYCLEH: u16 = 0xC80;
 pub const CSR_TIMEH: u16 = 0xC81;
 pub const CSR_INSTRETH: u16 = 0xC82;
+pub const CSR_MCYCLE: u16 = 0xB00;
+pub const CSR_MINSTRET: u16 = 0xB02;
+pub const CSR_MCYCLEH: u16 = 0xB80;
+pub const CSR_MINSTRETH: u16 = 0xB82;
 pub const CSR_MISA: u16 = 0x301;
+pub const CSR_MTVAL: u16 = 0x343;
 pub const CSR_MVENDORID: u16 = 0xF11;
 pub const CSR_MARCHID: u16 = 0xF12;
 pub const CSR_MIMPID: u16 = 0xF13;
@@ -54,7 +59,12 @@ pub const fn csr_name(csr: u16) -> &'static str {
         0xC80 => "cycleh",
         0xC81 => "timeh",
         0xC82 => "instreth",
+        0xB00 => "mcycle",
+        0xB02 => "minstret",
+        0xB80 => "mcycleh",
+        0xB82 => "minstreth",
         0x301 => "misa",
+        0x343 => "mtval",
         0xF11 => "mvendorid",
         0xF12 => "marchid",
         0xF13 => "mimpid",
@@ -63,6 +73,27 @@ pub const fn csr_name(csr: u16) -> &'static str {
     }
 }
 
+/// Value of counter CSR `csr` on an `xlen`-bit hart that has retired
+/// `instret` instructions, or `None` if `csr` is not a counter.
+///
+/// Time is deterministic: `cycle`, `time` and `instret` (and the machine
+/// aliases) all count retired instructions. RV32 reads the low 32 bits
+/// and the upper half through the `*h` CSRs, which only exist on RV32.
+#[must_use]
+pub const fn counter_csr_value(csr: u16, instret: u64, xlen: u8) -> Option<u64> {
+    let rv32 = xlen == 32;
+    match csr {
+        CSR_CYCLE | CSR_TIME | CSR_INSTRET | CSR_MCYCLE | CSR_MINSTRET if rv32 => {
+            Some(instret & u32::MAX as u64)
+        }
+        CSR_CYCLE | CSR_TIME | CSR_INSTRET | CSR_MCYCLE | CSR_MINSTRET => Some(instret),
+        CSR_CYCLEH | CSR_TIMEH | CSR_INSTRETH | CSR_MCYCLEH | CSR_MINSTRETH if rv32 => {
+            Some(instret >> 32)
+        }
+        _ => None,
+    }
+}
+
 /// Zicsr extension (CSR instructions).
 pub struct ZicsrExtension;
 
@@ -194,15 +225,49 @@ fn lift_zicsr<X: Xlen>(args: &InstrArgs, opid: crate::OpId) -> (Vec<Stmt<X>>, Te
     }
 }
 
+/// Check whether `csr` is read-only (address bits 11:10 set).
+const fn is_read_only_csr(csr: u16) -> bool {
+    csr >> 10 == 0b11
+}
+
+/// Lift a CSR access: `rd = csr`, then `csr = update(old, src)` if `src` is
+/// set.
+///
+/// The CSR is read at most once, since hook CSR reads call out to the host,
+/// and `src` is evaluated before `rd` is written (`src_reads_rd` saves it to
+/// a temp first). Writing a read-only CSR is an illegal instruction; this is
+/// how `unimp` (`csrrw x0, cycle, x0`) traps.
+fn lift_csr_access<X: Xlen>(
+    rd: u8,
+    csr: u16,
+    src: Option<Expr<X>>,
+    src_reads_rd: bool,
+    update: impl FnOnce(Expr<X>, Expr<X>) -> Expr<X>,
+) -> (Vec<Stmt<X>>, Terminator<X>) {
+    if src.is_some() && is_read_only_csr(csr) {
+        return (Vec::new(), Terminator::trap("write to read-only CSR"));
+    }
+    let mut stmts = Vec::new();
+    match src {
+        None if rd != 0 => stmts.push(Stmt::write_reg(rd, Expr::csr(csr))),
+        None => {}
+        Some(src) if rd == 0 => stmts.push(Stmt::write_csr(csr, update(Expr::csr(csr), src))),
+        Some(mut src) => {
+            if src_reads_rd {
+                stmts.push(Stmt::write_temp(0, src));
+                src = Expr::temp(0);
+            }
+            stmts.push(Stmt::write_reg(rd, Expr::csr(csr)));
+            stmts.push(Stmt::write_csr(csr, update(Expr::read(rd), src)));
+        }
+    }
+    (stmts, Terminator::Fall { target: None })
+}
+
 fn lift_csrrw<X: Xlen>(args: &InstrArgs) -> (Vec<Stmt<X>>, Terminator<X>) {
     match args {
         InstrArgs::Csr { rd, rs1, csr } => {
-            let mut stmts = Vec::new();
-            if *rd != 0 {
-                stmts.push(Stmt::write_reg(*rd, Expr::csr(*csr)));
-            }
-            stmts.push(Stmt::write_csr(*csr, Expr::read(*rs1)));
-            (stmts, Terminator::Fall { target: None })
+            lift_csr_access(*rd, *csr, Some(Expr::read(*rs1)), rd == rs1, |_, src| src)
         }
         _ => (Vec::new(), Terminator::trap("invalid args")),
     }
@@ -211,15 +276,8 @@ fn lift_csrrw<X: Xlen>(args: &InstrArgs) -> (Vec<Stmt<X>>, Terminator<X>) {
 fn lift_csrrs<X: Xlen>(args: &InstrArgs) -> (Vec<Stmt<X>>, Terminator<X>) {
     match args {
         InstrArgs::Csr { rd, rs1, csr } => {
-            let mut stmts = Vec::new();
-            let old_val = Expr::csr(*csr);
-            if *rd != 0 {
-                stmts.push(Stmt::write_reg(*rd, old_val.clone()));
-            }
-            if *rs1 != 0 {
-                stmts.push(Stmt::write_csr(*csr, Expr::or(old_val, Expr::read(*rs1))));
-            }
-            (stmts, Terminator::Fall { target: None })
+            let src = (*rs1 != 0).then(|| Expr::read(*rs1));
+            lift_csr_access(*rd, *csr, src, rd == rs1, Expr::or)
         }
         _ => (Vec::new(), Terminator::trap("invalid args")),
     }
@@ -228,18 +286,10 @@ fn lift_csrrs<X: Xlen>(args: &InstrArgs) -> (Vec<Stmt<X>>, Terminator<X>) {
 fn lift_csrrc<X: Xlen>(args: &InstrArgs) -> (Vec<Stmt<X>>, Terminator<X>) {
     match args {
         InstrArgs::Csr { rd, rs1, csr } => {
-            let mut stmts = Vec::new();
-            let old_val = Expr::csr(*csr);
-            if *rd != 0 {
-                stmts.push(Stmt::write_reg(*rd, old_val.clone()));
-            }
-            if *rs1 != 0 {
-                stmts.push(Stmt::write_csr(
-                    *csr,
-                    Expr::and(old_val, Expr::not(Expr::read(*rs1))),
-                ));
-            }
-            (stmts, Terminator::Fall { target: None })
+            let src = (*rs1 != 0).then(|| Expr::read(*rs1));
+            lift_csr_access(*rd, *csr, src, rd == rs1, |old, src| {
+                Expr::and(old, Expr::not(src))
+            })
         }
         _ => (Vec::new(), Terminator::trap("invalid args")),
     }
@@ -248,15 +298,8 @@ fn lift_csrrc<X: Xlen>(args: &InstrArgs) -> (Vec<Stmt<X>>, Terminator<X>) {
 fn lift_csrrwi<X: Xlen>(args: &InstrArgs) -> (Vec<Stmt<X>>, Terminator<X>) {
     match args {
         InstrArgs::CsrI { rd, imm, csr } => {
-            let mut stmts = Vec::new();
-            if *rd != 0 {
-                stmts.push(Stmt::write_reg(*rd, Expr::csr(*csr)));
-            }
-            stmts.push(Stmt::write_csr(
-                *csr,
-                Expr::imm(X::from_u64(u64::from(*imm))),
-            ));
-            (stmts, Terminator::Fall { target: None })
+            let src = Expr::imm(X::from_u64(u64::from(*imm)));
+            lift_csr_access(*rd, *csr, Some(src), false, |_, src| src)
         }
         _ => (Vec::new(), Terminator::trap("invalid args")),
     }
@@ -265,18 +308,8 @@ fn lift_csrrwi<X: Xlen>(args: &InstrArgs) -> (Vec<Stmt<X>>, Terminator<X>) {
 fn lift_csrrsi<X: Xlen>(args: &InstrArgs) -> (Vec<Stmt<X>>, Terminator<X>) {
     match args {
         InstrArgs::CsrI { rd, imm, csr } => {
-            let mut stmts = Vec::new();
-            let old_val = Expr::csr(*csr);
-            if *rd != 0 {
-                stmts.push(Stmt::write_reg(*rd, old_val.clone()));
-            }
-            if *imm != 0 {
-                stmts.push(Stmt::write_csr(
-                    *csr,
-                    Expr::or(old_val, Expr::imm(X::from_u64(u64::from(*imm)))),
-                ));
-            }
-            (stmts, Terminator::Fall { target: None })
+            let src = (*imm != 0).then(|| Expr::imm(X::from_u64(u64::from(*imm))));
+            lift_csr_access(*rd, *csr, src, false, Expr::or)
         }
         _ => (Vec::new(), Terminator::trap("invalid args")),
     }
@@ -285,19 +318,119 @@ fn lift_csrrsi<X: Xlen>(args: &InstrArgs) -> (Vec<Stmt<X>>, Terminator<X>) {
 fn lift_csrrci<X: Xlen>(args: &InstrArgs) -> (Vec<Stmt<X>>, Terminator<X>) {
     match args {
         InstrArgs::CsrI { rd, imm, csr } => {
-            let mut stmts = Vec::new();
-            let old_val = Expr::csr(*csr);
-            if *rd != 0 {
-                stmts.push(Stmt::write_reg(*rd, old_val.clone()));
-            }
-            if *imm != 0 {
-                stmts.push(Stmt::write_csr(
-                    *csr,
-                    Expr::and(old_val, Expr::not(Expr::imm(X::from_u64(u64::from(*imm))))),
-                ));
-            }
-            (stmts, Terminator::Fall { target: None })
+            let src = (*imm != 0).then(|| Expr::imm(X::from_u64(u64::from(*imm))));
+            lift_csr_access(*rd, *csr, src, false, |old, src| {
+                Expr::and(old, Expr::not(src))
+            })
         }
         _ => (Vec::new(), Terminator::trap("invalid args")),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rvr_ir::{ReadExpr, Rv64, WriteTarget};
+
+    const CSR: u16 = 0x8c0;
+
+    fn lift_ir(csr: u16, funct3: u8, rd: u8, rs1: u8) -> InstrIR<Rv64> {
+        let raw = 0x73
+            | (u32::from(rd) << 7)
+            | (u32::from(funct3) << 12)
+            | (u32::from(rs1) << 15)
+            | (u32::from(csr) << 20);
+        let ext = ZicsrExtension;
+        let instr = InstructionExtension::<Rv64>::decode32(&ext, raw, 0u64).unwrap();
+        InstructionExtension::<Rv64>::lift(&ext, &instr)
+    }
+
+    fn lift(funct3: u8, rd: u8, rs1: u8) -> Vec<Stmt<Rv64>> {
+        lift_ir(CSR, funct3, rd, rs1).statements
+    }
+
+    fn csr_reads(expr: &Expr<Rv64>) -> usize {
+        match expr {
+            Expr::Read(ReadExpr::Csr(_)) => 1,
+            Expr::Unary { expr, .. } => csr_reads(expr),
+            Expr::Binary { left, right, .. } => csr_reads(left) + csr_reads(right),
+            _ => 0,
+        }
+    }
+
+    fn total_csr_reads(stmts: &[Stmt<Rv64>]) -> usize {
+        stmts
+            .iter()
+            .map(|stmt| match stmt {
+                Stmt::Write { value, .. } => csr_reads(value),
+                _ => 0,
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_csrrs_reads_csr_once() {
+        // csrrs a0, csr, a1
+        let stmts = lift(2, 10, 11);
+        assert_eq!(stmts.len(), 2);
+        assert_eq!(total_csr_reads(&stmts), 1);
+    }
+
+    #[test]
+    fn test_csrw_does_not_read() {
+        // csrrw x0, csr, a1
+        let stmts = lift(1, 0, 11);
+        assert_eq!(stmts.len(), 1);
+        assert_eq!(total_csr_reads(&stmts), 0);
+    }
+
+    #[test]
+    fn test_csrrw_swap_saves_source() {
+        // csrrw a0, csr, a0: the CSR gets the old a0
+        let stmts = lift(1, 10, 10);
+        assert!(matches!(
+            &stmts[0],
+            Stmt::Write {
+                target: WriteTarget::Temp(0),
+                value: Expr::Read(ReadExpr::Reg(10)),
+            }
+        ));
+        assert!(matches!(
+            &stmts[2],
+            Stmt::Write {
+                target: WriteTarget::Csr(CSR),
+                value: Expr::Read(ReadExpr::Temp(0)),
+            }
+        ));
+    }
+
+    #[test]
+    fn test_csrr_only_reads() {
+        // csrrs a0, csr, x0
+        let stmts = lift(2, 10, 0);
+        assert_eq!(stmts.len(), 1);
+        assert_eq!(total_csr_reads(&stmts), 1);
+    }
+
+    #[test]
+    fn test_read_only_csr_write_traps() {
+        // unimp = csrrw x0, cycle, x0
+        let unimp = lift_ir(CSR_CYCLE, 1, 0, 0);
+        assert!(matches!(unimp.terminator, Terminator::Trap { .. }));
+        assert!(unimp.statements.is_empty());
+        // rdcycle a0 = csrrs a0, cycle, x0 does not write
+        let rdcycle = lift_ir(CSR_CYCLE, 2, 10, 0);
+        assert!(matches!(rdcycle.terminator, Terminator::Fall { .. }));
+    }
+
+    #[test]
+    fn test_counter_csr_value() {
+        let instret = 0x1_0000_0002;
+        assert_eq!(counter_csr_value(CSR_TIME, instret, 64), Some(instret));
+        assert_eq!(counter_csr_value(CSR_TIMEH, instret, 64), None);
+        assert_eq!(counter_csr_value(CSR_CYCLE, instret, 32), Some(2));
+        assert_eq!(counter_csr_value(CSR_TIMEH, instret, 32), Some(1));
+        assert_eq!(counter_csr_value(CSR_MINSTRETH, instret, 32), Some(1));
+        assert_eq!(counter_csr_value(CSR_MISA, instret, 32), None);
+    }
+}
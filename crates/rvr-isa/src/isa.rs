@@ -0,0 +1,322 @@
+//! RISC-V ISA strings, as in `-march` and the ELF `Tag_RISCV_arch` attribute.
+//!
+//! An ISA string is `rv32` or `rv64`, a base (`i`, `e` or `g`), single-letter
+//! extensions, then `_`-separated multi-letter extensions, each optionally
+//! followed by a version (`2p1` is 2.1): `rv64imac_zicsr_zifencei`,
+//! `rv64i2p1_m2p0_a2p1_c2p0_zicsr2p0`.
+
+use thiserror::Error;
+
+use crate::{ExtensionRegistry, Xlen};
+
+/// Extensions `g` stands for.
+const G_EXTENSIONS: &[&str] = &["i", "m", "a", "f", "d", "zicsr", "zifencei"];
+
+/// Extensions `b` stands for.
+const B_EXTENSIONS: &[&str] = &["zba", "zbb", "zbs"];
+
+/// Extensions the registry has no decoder of their own for, but whose
+/// instructions another supported extension decodes (subsets such as
+/// `zmmul` of `m`) or which only add meaning to existing encodings
+/// (counters read with Zicsr, hints).
+const COVERED_EXTENSIONS: &[(&str, &str)] = &[
+    ("zmmul", "m"),
+    ("zaamo", "a"),
+    ("zalrsc", "a"),
+    ("zca", "c"),
+    ("zicntr", "zicsr"),
+    ("zihpm", "zicsr"),
+    ("zihintpause", "i"),
+    ("zihintntl", "i"),
+    ("zve32x", "v"),
+    ("zve32f", "v"),
+    ("zve64x", "v"),
+    ("zve64f", "v"),
+    ("zve64d", "v"),
+    ("zvl32b", "v"),
+    ("zvl64b", "v"),
+    ("zvl128b", "v"),
+];
+
+/// Extensions with a decoder in [`ExtensionRegistry`].
+const SUPPORTED_EXTENSIONS: &[&str] = &[
+    "i", "e", "m", "a", "c", "zcb", "zcmp", "zicsr", "zifencei", "zba", "zbb", "zbs", "zbkb",
+    "zicond", "v",
+];
+
+/// ISA string parse errors.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum IsaError {
+    #[error("ISA string '{0}' does not start with rv32 or rv64")]
+    InvalidXlen(String),
+    #[error("ISA string '{0}' does not name a base ISA (i, e or g)")]
+    MissingBase(String),
+    #[error("ISA string '{isa}' has a malformed extension '{extension}'")]
+    InvalidExtension { isa: String, extension: String },
+}
+
+/// The extensions an ISA string names.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IsaString {
+    /// 32 or 64.
+    pub xlen: u8,
+    /// Extension names in string order, lowercase and without versions.
+    /// `g` and `b` are expanded, and an ISA spec 2.0 base `i` (which still
+    /// included them) adds `zicsr` and `zifencei`.
+    pub extensions: Vec<String>,
+}
+
+impl IsaString {
+    /// Parse an ISA string.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the string does not start with `rv32` or `rv64`
+    /// and a base ISA, or an extension name is empty or not alphanumeric.
+    pub fn parse(isa: &str) -> Result<Self, IsaError> {
+        let lower = isa.trim().to_ascii_lowercase();
+        let (xlen, rest) = if let Some(rest) = lower.strip_prefix("rv32") {
+            (32, rest)
+        } else if let Some(rest) = lower.strip_prefix("rv64") {
+            (64, rest)
+        } else {
+            return Err(IsaError::InvalidXlen(isa.to_string()));
+        };
+        if !rest.starts_with(['i', 'e', 'g']) {
+            return Err(IsaError::MissingBase(isa.to_string()));
+        }
+
+        let mut parsed = Self {
+            xlen,
+            extensions: Vec::new(),
+        };
+        let invalid = |extension: &str| IsaError::InvalidExtension {
+            isa: isa.to_string(),
+            extension: extension.to_string(),
+        };
+        // Each token is single-letter extensions, each with an optional
+        // version, possibly ending in a multi-letter extension
+        for token in rest.split('_').filter(|token| !token.is_empty()) {
+            let mut chars = token;
+            while let Some(letter) = chars.chars().next() {
+                if matches!(letter, 'z' | 's' | 'x') {
+                    parsed.push_multi(chars).ok_or_else(|| invalid(chars))?;
+                    break;
+                }
+                if !letter.is_ascii_lowercase() {
+                    return Err(invalid(chars));
+                }
+                let (version, tail) = split_version(&chars[1..]);
+                parsed.push_single(letter, version);
+                chars = tail;
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// Whether the string names `extension`.
+    #[must_use]
+    pub fn has(&self, extension: &str) -> bool {
+        self.extensions.iter().any(|e| e == extension)
+    }
+
+    /// Extensions rvr does not decode, in string order.
+    ///
+    /// Instructions of these trap (or, if their encodings overlap a
+    /// supported extension, are misread).
+    #[must_use]
+    pub fn unsupported(&self) -> Vec<&str> {
+        self.extensions
+            .iter()
+            .map(String::as_str)
+            .filter(|e| {
+                !SUPPORTED_EXTENSIONS.contains(e)
+                    && !COVERED_EXTENSIONS.iter().any(|(name, _)| name == e)
+            })
+            .collect()
+    }
+
+    /// Whether `extension`, or one it covers, is named.
+    fn selects(&self, extension: &str) -> bool {
+        self.has(extension)
+            || COVERED_EXTENSIONS
+                .iter()
+                .any(|&(name, by)| by == extension && self.has(name))
+    }
+
+    fn push(&mut self, extension: &str) {
+        if !self.has(extension) {
+            self.extensions.push(extension.to_string());
+        }
+    }
+
+    fn push_single(&mut self, letter: char, version: Option<&str>) {
+        match letter {
+            'g' => G_EXTENSIONS.iter().for_each(|e| self.push(e)),
+            'b' => B_EXTENSIONS.iter().for_each(|e| self.push(e)),
+            'i' => {
+                self.push("i");
+                if matches!(version, Some("2" | "2p0")) {
+                    self.push("zicsr");
+                    self.push("zifencei");
+                }
+            }
+            _ => self.push(letter.encode_utf8(&mut [0; 4])),
+        }
+    }
+
+    /// Add a multi-letter extension; `None` if it is malformed.
+    fn push_multi(&mut self, token: &str) -> Option<()> {
+        let name = strip_version(token);
+        if name.len() < 2 || !name.bytes().all(|b| b.is_ascii_alphanumeric()) {
+            return None;
+        }
+        self.push(name);
+        Some(())
+    }
+}
+
+/// Split a leading version (`2`, `2p1`) off `s`.
+fn split_version(s: &str) -> (Option<&str>, &str) {
+    let major = s.bytes().take_while(u8::is_ascii_digit).count();
+    if major == 0 {
+        return (None, s);
+    }
+    let rest = &s[major..];
+    let minor = rest
+        .strip_prefix('p')
+        .map_or(0, |r| r.bytes().take_while(u8::is_ascii_digit).count());
+    let len = if minor > 0 { major + 1 + minor } else { major };
+    (Some(&s[..len]), &s[len..])
+}
+
+/// `token` without a trailing version (`zicsr2p0` is `zicsr`).
+fn strip_version(token: &str) -> &str {
+    let trimmed = token.trim_end_matches(|c: char| c.is_ascii_digit());
+    if trimmed.len() == token.len() {
+        return token;
+    }
+    // A minor version: strip `p<digits>`, then the major version
+    if let Some(major) = trimmed.strip_suffix('p')
+        && major.ends_with(|c: char| c.is_ascii_digit())
+    {
+        return major.trim_end_matches(|c: char| c.is_ascii_digit());
+    }
+    trimmed
+}
+
+/// An [`ExtensionRegistry`] builder method.
+type Builder<X> = fn(ExtensionRegistry<X>) -> ExtensionRegistry<X>;
+
+impl<X: Xlen> ExtensionRegistry<X> {
+    /// Create a registry with the extensions of `isa` that rvr implements.
+    ///
+    /// Extensions rvr does not implement are left out; see
+    /// [`IsaString::unsupported`].
+    #[must_use]
+    pub fn for_isa(isa: &IsaString) -> Self {
+        let mut registry = Self::base();
+        if isa.selects("c") {
+            registry = registry.with_c();
+        }
+        let builders: [(&str, Builder<X>); 12] = [
+            ("zcb", Self::with_zcb),
+            ("zcmp", Self::with_zcmp),
+            ("m", Self::with_m),
+            ("a", Self::with_a),
+            ("zicsr", Self::with_zicsr),
+            ("zifencei", Self::with_zifencei),
+            ("zba", Self::with_zba),
+            ("zbb", Self::with_zbb),
+            ("zbs", Self::with_zbs),
+            ("zbkb", Self::with_zbkb),
+            ("zicond", Self::with_zicond),
+            ("v", Self::with_v),
+        ];
+        for (extension, with) in builders {
+            if isa.selects(extension) {
+                registry = with(registry);
+            }
+        }
+        registry
+    }
+
+    /// Names of the registered extensions, in decode order.
+    #[must_use]
+    pub fn extension_names(&self) -> Vec<&'static str> {
+        self.extensions().iter().map(|ext| ext.name()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Rv64;
+
+    fn parse(isa: &str) -> Vec<String> {
+        IsaString::parse(isa).unwrap().extensions
+    }
+
+    #[test]
+    fn test_parse_isa_strings() {
+        assert_eq!(
+            parse("rv64imac_zicsr_zifencei"),
+            ["i", "m", "a", "c", "zicsr", "zifencei"]
+        );
+        assert_eq!(
+            parse("rv64i2p1_m2p0_a2p1_c2p0_zicsr2p0_zmmul1p0_zve32x1p0"),
+            ["i", "m", "a", "c", "zicsr", "zmmul", "zve32x"]
+        );
+        assert_eq!(
+            parse("RV32GC_zba"),
+            ["i", "m", "a", "f", "d", "zicsr", "zifencei", "c", "zba"]
+        );
+        assert_eq!(parse("rv32e"), ["e"]);
+        assert_eq!(parse("rv32i2p0_m2p0"), ["i", "zicsr", "zifencei", "m"]);
+        assert_eq!(parse("rv64ib"), ["i", "zba", "zbb", "zbs"]);
+        assert_eq!(IsaString::parse("rv32i").unwrap().xlen, 32);
+
+        assert!(matches!(
+            IsaString::parse("rv128i"),
+            Err(IsaError::InvalidXlen(_))
+        ));
+        assert!(matches!(
+            IsaString::parse("rv64m"),
+            Err(IsaError::MissingBase(_))
+        ));
+        assert!(matches!(
+            IsaString::parse("rv64i_z!"),
+            Err(IsaError::InvalidExtension { .. })
+        ));
+    }
+
+    #[test]
+    fn test_unsupported_extensions() {
+        let isa = IsaString::parse("rv64gcv_zaamo_zicntr_zbc").unwrap();
+        assert_eq!(isa.unsupported(), ["f", "d", "zbc"]);
+        // A VLEN above the default 128 must be configured, not assumed
+        let isa = IsaString::parse("rv64gc_zve64x_zvl128b_zvl256b").unwrap();
+        assert_eq!(isa.unsupported(), ["f", "d", "zvl256b"]);
+    }
+
+    #[test]
+    fn test_registry_for_isa() {
+        let isa = IsaString::parse("rv64i_zmmul_zca_zicsr_zbb").unwrap();
+        let registry = ExtensionRegistry::<Rv64>::for_isa(&isa);
+        assert_eq!(registry.extension_names(), ["C", "I", "M", "Zicsr", "Zbb"]);
+
+        // Zcb and Zcmp decode ahead of C, which reads their encodings as illegal
+        let isa = IsaString::parse("rv32imc_zcb_zcmp").unwrap();
+        assert_eq!(
+            ExtensionRegistry::<Rv64>::for_isa(&isa).extension_names(),
+            ["Zcmp", "Zcb", "C", "I", "M"]
+        );
+
+        let all =
+            IsaString::parse("rv64imacv_zcb_zcmp_zicsr_zifencei_zba_zbb_zbs_zbkb_zicond").unwrap();
+        assert_eq!(
+            ExtensionRegistry::<Rv64>::for_isa(&all).extension_names(),
+            ExtensionRegistry::<Rv64>::standard().extension_names()
+        );
+    }
+}
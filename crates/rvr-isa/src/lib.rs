@@ -6,11 +6,15 @@
 
 mod encode;
 pub mod extensions;
+mod isa;
+mod lrsc;
 pub mod syscalls;
 mod types;
 
 pub use encode::*;
 pub use extensions::*;
+pub use isa::{IsaError, IsaString};
+pub use lrsc::{LrScModel, RESERVATION_GRANULE, invalidate_reservation};
 pub use types::*;
 
 /// Decode an instruction using the standard RISC-V extensions.
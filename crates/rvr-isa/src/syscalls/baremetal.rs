@@ -1,12 +1,26 @@
 //! Bare-metal ECALL handling.
 //!
-//! Treat ECALL as exit with a0 as the exit code, matching riscv-tests.
+//! By default ECALL exits with a0 as the exit code, matching riscv-tests.
+//! A [`BareMetalConfig`] instead maps syscall numbers to built-in actions,
+//! for firmware that uses ECALL for host services.
 
-use rvr_ir::{Expr, InstrIR, Terminator, Xlen};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use rvr_ir::{Expr, HelperBlock, InstrIR, OverrideExpansion, Stmt, Terminator, Xlen};
 
 use crate::{DecodedInstr, REG_A0};
 
-use super::table::SyscallHandler;
+use super::table::{SyscallAbi, SyscallHandler};
+
+/// `ENOSYS`, returned by a host call the host did not register.
+const ENOSYS: i64 = 38;
+
+/// Argument registers passed to a host call (a0..a5).
+const HOST_CALL_ARGS: u8 = 6;
+
+/// Trap message of an ECALL with an unmapped syscall number.
+const UNMAPPED_TRAP: &str = "unmapped ECALL";
 
 /// Bare-metal handler (exit with a0).
 #[derive(Debug, Clone, Copy, Default)]
@@ -28,6 +42,256 @@ impl<X: Xlen> SyscallHandler<X> for BareMetalHandler {
 /// Backwards-compatible name for riscv-tests behavior.
 pub type RiscvTestsHandler = BareMetalHandler;
 
+/// Built-in behavior of a mapped bare-metal ECALL.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EcallAction {
+    /// Exit the program with a0 as exit code.
+    Exit,
+    /// Write the low byte of a0 to the host's stdout.
+    PutChar,
+    /// Return the retired instruction count in a0.
+    GetInstret,
+    /// Call host syscall `n` registered with the runner, with arguments
+    /// a0..a5 and the result in a0 (`-ENOSYS` if the host has none).
+    HostCall(u64),
+}
+
+/// What an ECALL whose number is not mapped does.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UnmappedEcall {
+    /// Exit with a0 as exit code, like [`BareMetalHandler`].
+    Exit,
+    /// Stop with an illegal-instruction trap at the ECALL (C backend).
+    #[default]
+    Trap,
+}
+
+/// Bare-metal ECALLs mapped by syscall number to built-in actions.
+///
+/// Parses from `NUM=ACTION` pairs separated by commas, where `ACTION` is
+/// `exit`, `putchar`, `instret`, `host` (host syscall `NUM`) or `host:N`:
+///
+/// ```
+/// use rvr_isa::syscalls::{BareMetalConfig, EcallAction};
+///
+/// let config: BareMetalConfig = "1=putchar,93=exit".parse().unwrap();
+/// assert_eq!(config.action(1), Some(EcallAction::PutChar));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BareMetalConfig {
+    abi: SyscallAbi,
+    ecalls: BTreeMap<u64, EcallAction>,
+    unmapped: UnmappedEcall,
+}
+
+impl Default for BareMetalConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BareMetalConfig {
+    /// Config with no mapped ECALLs that traps on every ECALL.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            abi: SyscallAbi::Standard,
+            ecalls: BTreeMap::new(),
+            unmapped: UnmappedEcall::Trap,
+        }
+    }
+
+    /// Map syscall number `num` to `action`.
+    #[must_use]
+    pub fn with_ecall(mut self, num: u64, action: EcallAction) -> Self {
+        self.ecalls.insert(num, action);
+        self
+    }
+
+    /// Set what unmapped syscall numbers do (default: trap).
+    #[must_use]
+    pub const fn with_unmapped(mut self, unmapped: UnmappedEcall) -> Self {
+        self.unmapped = unmapped;
+        self
+    }
+
+    /// Set the register holding the syscall number (default: a7).
+    #[must_use]
+    pub const fn with_abi(mut self, abi: SyscallAbi) -> Self {
+        self.abi = abi;
+        self
+    }
+
+    /// Action mapped to syscall number `num`, if any.
+    #[must_use]
+    pub fn action(&self, num: u64) -> Option<EcallAction> {
+        self.ecalls.get(&num).copied()
+    }
+
+    /// What unmapped syscall numbers do.
+    #[must_use]
+    pub const fn unmapped(&self) -> UnmappedEcall {
+        self.unmapped
+    }
+
+    /// True if a mapped action calls the syscall runtime (`rv_putchar`,
+    /// host syscalls), which then has to be linked in.
+    #[must_use]
+    pub fn needs_runtime(&self) -> bool {
+        self.ecalls
+            .values()
+            .any(|action| matches!(action, EcallAction::PutChar | EcallAction::HostCall(_)))
+    }
+
+    /// Statements performing `action`.
+    fn action_stmts<X: Xlen>(action: EcallAction) -> Vec<Stmt<X>> {
+        let width = u8::try_from(X::REG_BYTES * 8).expect("register width fits u8");
+        match action {
+            EcallAction::Exit => exit_stmts(),
+            EcallAction::PutChar => vec![Stmt::extern_call(
+                "rv_putchar",
+                vec![Expr::var("state"), Expr::read(REG_A0)],
+            )],
+            EcallAction::GetInstret => vec![Stmt::write_reg(REG_A0, Expr::instret())],
+            EcallAction::HostCall(num) => {
+                let num = Expr::imm(X::from_u64(num));
+                vec![Stmt::if_then_else(
+                    Expr::ne(
+                        Expr::extern_call(
+                            "rv_has_host_syscall",
+                            vec![Expr::var("state"), num.clone()],
+                            width,
+                        ),
+                        Expr::imm(X::from_u64(0)),
+                    ),
+                    vec![Stmt::write_reg(
+                        REG_A0,
+                        Expr::extern_call(
+                            "rv_host_syscall",
+                            [Expr::var("state"), num]
+                                .into_iter()
+                                .chain((REG_A0..REG_A0 + HOST_CALL_ARGS).map(Expr::read))
+                                .collect(),
+                            width,
+                        ),
+                    )],
+                    vec![Stmt::write_reg(
+                        REG_A0,
+                        Expr::imm(X::from_u64((-ENOSYS).cast_unsigned())),
+                    )],
+                )]
+            }
+        }
+    }
+}
+
+/// Statements exiting with a0 as exit code.
+fn exit_stmts<X: Xlen>() -> Vec<Stmt<X>> {
+    vec![
+        Stmt::write_exited(Expr::imm(X::from_u64(1))),
+        Stmt::write_exit_code(Expr::read(REG_A0)),
+    ]
+}
+
+impl<X: Xlen> SyscallHandler<X> for BareMetalConfig {
+    fn handle_ecall(&self, instr: &DecodedInstr<X>) -> InstrIR<X> {
+        self.expand_ecall(instr).primary
+    }
+
+    fn expand_ecall(&self, instr: &DecodedInstr<X>) -> OverrideExpansion<X> {
+        let sys_num = Expr::read(self.abi.syscall_reg());
+        let num_eq = |num: u64| Expr::eq(sys_num.clone(), Expr::imm(X::from_u64(num)));
+        let mapped = self.ecalls.keys().map(|&num| num_eq(num)).reduce(Expr::or);
+        let ir = |stmts, terminator| {
+            InstrIR::new(
+                instr.pc,
+                instr.size,
+                instr.opid.pack(),
+                instr.raw,
+                stmts,
+                terminator,
+            )
+        };
+
+        let Some(mapped) = mapped else {
+            let terminator = match self.unmapped {
+                UnmappedEcall::Exit => Terminator::exit(Expr::read(REG_A0)),
+                UnmappedEcall::Trap => Terminator::trap(UNMAPPED_TRAP),
+            };
+            return OverrideExpansion::new(ir(Vec::new(), terminator));
+        };
+
+        // Each action leaves the syscall number register alone, so at most
+        // one of the checks runs
+        let mut stmts: Vec<Stmt<X>> = self
+            .ecalls
+            .iter()
+            .map(|(&num, &action)| Stmt::if_then(num_eq(num), Self::action_stmts(action)))
+            .collect();
+        let next_pc = instr.pc + X::Reg::from(u32::from(instr.size));
+        // The comparisons are 0 or 1, so test for zero (`not` is bitwise)
+        let unmapped = Expr::eq(mapped, Expr::imm(X::from_u64(0)));
+        match self.unmapped {
+            UnmappedEcall::Exit => {
+                stmts.push(Stmt::if_then(unmapped, exit_stmts()));
+                OverrideExpansion::new(ir(stmts, Terminator::fall(next_pc)))
+            }
+            UnmappedEcall::Trap => {
+                let terminator = Terminator::branch_with_fall(
+                    unmapped,
+                    OverrideExpansion::<X>::helper_target(0),
+                    next_pc,
+                );
+                OverrideExpansion::new(ir(stmts, terminator)).with_helper(HelperBlock::new(
+                    Vec::new(),
+                    Terminator::trap(UNMAPPED_TRAP),
+                ))
+            }
+        }
+    }
+}
+
+impl FromStr for EcallAction {
+    type Err = String;
+
+    /// `exit`, `putchar`, `instret` or `host:N`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "exit" => Ok(Self::Exit),
+            "putchar" => Ok(Self::PutChar),
+            "instret" => Ok(Self::GetInstret),
+            _ => s
+                .strip_prefix("host:")
+                .and_then(|num| num.parse().ok())
+                .map(Self::HostCall)
+                .ok_or_else(|| {
+                    format!("unknown ECALL action '{s}' (exit, putchar, instret, host[:N])")
+                }),
+        }
+    }
+}
+
+impl FromStr for BareMetalConfig {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',').try_fold(Self::new(), |config, pair| {
+            let (num, action) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("expected NUM=ACTION, got '{pair}'"))?;
+            let num: u64 = num
+                .trim()
+                .parse()
+                .map_err(|_| format!("invalid syscall number '{num}'"))?;
+            let action = match action.trim() {
+                "host" => EcallAction::HostCall(num),
+                action => action.parse()?,
+            };
+            Ok(config.with_ecall(num, action))
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -52,4 +316,61 @@ mod tests {
 
         assert!(matches!(ir.terminator, Terminator::Exit { .. }));
     }
+
+    #[test]
+    fn test_config_parses_actions() {
+        let config: BareMetalConfig = "1=putchar, 2=instret,93=exit,7=host,8=host:9"
+            .parse()
+            .unwrap();
+        assert_eq!(config.action(1), Some(EcallAction::PutChar));
+        assert_eq!(config.action(2), Some(EcallAction::GetInstret));
+        assert_eq!(config.action(93), Some(EcallAction::Exit));
+        assert_eq!(config.action(7), Some(EcallAction::HostCall(7)));
+        assert_eq!(config.action(8), Some(EcallAction::HostCall(9)));
+        assert_eq!(config.action(3), None);
+        assert_eq!(config.unmapped(), UnmappedEcall::Trap);
+        assert!(config.needs_runtime());
+
+        assert!("1".parse::<BareMetalConfig>().is_err());
+        assert!("x=exit".parse::<BareMetalConfig>().is_err());
+        assert!("1=print".parse::<BareMetalConfig>().is_err());
+    }
+
+    #[test]
+    fn test_config_traps_unmapped_in_helper() {
+        let config = BareMetalConfig::new()
+            .with_ecall(1, EcallAction::PutChar)
+            .with_ecall(93, EcallAction::Exit);
+        let expansion = config.expand_ecall(&make_ecall_instr());
+
+        let stmts = format!("{:?}", expansion.primary.statements);
+        assert!(stmts.contains("rv_putchar"), "{stmts}");
+        assert!(matches!(
+            expansion.primary.terminator,
+            Terminator::Branch {
+                fall: Some(0x1004),
+                ..
+            }
+        ));
+        let [helper] = expansion.helpers.as_slice() else {
+            panic!("expected one trap helper: {:?}", expansion.helpers);
+        };
+        assert!(matches!(helper.terminator, Terminator::Trap { .. }));
+    }
+
+    #[test]
+    fn test_config_exits_unmapped() {
+        let config = BareMetalConfig::new()
+            .with_ecall(2, EcallAction::GetInstret)
+            .with_unmapped(UnmappedEcall::Exit);
+        let expansion = config.expand_ecall(&make_ecall_instr());
+
+        assert!(!expansion.has_helpers());
+        assert!(!config.needs_runtime());
+        assert!(matches!(
+            expansion.primary.terminator,
+            Terminator::Fall { .. }
+        ));
+        assert_eq!(expansion.primary.statements.len(), 2);
+    }
 }
@@ -1,9 +1,10 @@
 //! Linux-style syscall table.
 
-use rvr_ir::{InstrIR, Xlen};
+use rvr_ir::{Expr, InstrIR, Stmt, Xlen};
 
-use crate::DecodedInstr;
+use crate::{CSR_TIME, DecodedInstr, REG_A0, REG_A1};
 
+use super::policy::SyscallPolicy;
 use super::table::{SyscallAbi, SyscallHandler, SyscallTable};
 
 /// Known Linux syscall numbers (RISC-V ABI).
@@ -13,26 +14,37 @@ pub mod syscall_nr {
     pub const SYS_OPENAT: u64 = 56;
     pub const SYS_CLOSE: u64 = 57;
     pub const SYS_GETDENTS64: u64 = 61;
+    pub const SYS_LSEEK: u64 = 62;
     pub const SYS_READ: u64 = 63;
     pub const SYS_WRITE: u64 = 64;
+    pub const SYS_READV: u64 = 65;
+    pub const SYS_WRITEV: u64 = 66;
     pub const SYS_PREAD64: u64 = 67;
+    pub const SYS_NEWFSTATAT: u64 = 79;
     pub const SYS_FSTAT: u64 = 80;
     pub const SYS_EXIT: u64 = 93;
     pub const SYS_EXIT_GROUP: u64 = 94;
     pub const SYS_SET_TID_ADDRESS: u64 = 96;
+    pub const SYS_FUTEX: u64 = 98;
     pub const SYS_SETPRIORITY: u64 = 99;
     pub const SYS_SCHED_SETSCHEDULER: u64 = 119;
     pub const SYS_SCHED_GETSCHEDULER: u64 = 120;
     pub const SYS_SCHED_GETPARAM: u64 = 121;
     pub const SYS_SCHED_GET_PRIORITY_MAX: u64 = 125;
     pub const SYS_SCHED_GET_PRIORITY_MIN: u64 = 126;
+    pub const SYS_KILL: u64 = 129;
+    pub const SYS_TKILL: u64 = 130;
     pub const SYS_TGKILL: u64 = 131;
+    pub const SYS_SIGALTSTACK: u64 = 132;
+    pub const SYS_RT_SIGACTION: u64 = 134;
+    pub const SYS_RT_SIGPROCMASK: u64 = 135;
     pub const SYS_GETPID: u64 = 172;
     pub const SYS_GETTID: u64 = 178;
     pub const SYS_SYSINFO: u64 = 179;
     pub const SYS_BRK: u64 = 214;
     pub const SYS_MUNMAP: u64 = 215;
     pub const SYS_MREMAP: u64 = 216;
+    pub const SYS_CLONE: u64 = 220;
     pub const SYS_MMAP: u64 = 222;
     pub const SYS_MPROTECT: u64 = 226;
     pub const SYS_MADVISE: u64 = 233;
@@ -42,12 +54,36 @@ pub mod syscall_nr {
     pub const SYS_RSEQ: u64 = 293;
     pub const SYS_CLOCK_GETTIME: u64 = 113;
     pub const SYS_CLOCK_GETTIME64: u64 = 403;
+    pub const SYS_CLONE3: u64 = 435;
 }
 
+/// `-EAGAIN`, returned by `clone`: there are no threads to spawn.
+const EAGAIN: i64 = 11;
+/// The one process/thread id of the guest.
+const GUEST_TID: i64 = 1;
+/// Bytes of a `sigset_t`, the `sa_mask` of a `struct sigaction` and the
+/// `oldset` of `rt_sigprocmask`.
+const SIGSET_BYTES: u8 = 8;
+/// Nanoseconds per second, for the deterministic `clock_gettime`.
+const NANOS_PER_SEC: u64 = 1_000_000_000;
+
 /// Linux syscall handler using a default syscall table.
+///
+/// The guest is a single thread: `futex` never blocks (a wait on a
+/// matching word times out with `ETIMEDOUT` if it has a timeout and
+/// otherwise fails with `EDEADLK`), signal setup is
+/// accepted and ignored (old actions and masks read back as zero, the
+/// defaults), `kill`/`tkill`/`tgkill` exit with 128 + signal if the signal
+/// terminates by default and otherwise return 0, and `clone`/`clone3` fail
+/// with `EAGAIN` so thread spawns report an error instead of running a
+/// second thread on shared state. Files are reached
+/// only through directories the host preopened: `openat`, `read(v)`,
+/// `write(v)`, `lseek`, `close` and `fstat(at)` on guest fds above 2 go to
+/// the host's fd table.
 #[derive(Clone, Debug)]
 pub struct LinuxHandler {
     table: SyscallTable,
+    deterministic_clock: bool,
 }
 
 impl LinuxHandler {
@@ -55,8 +91,29 @@ impl LinuxHandler {
     pub fn new(abi: SyscallAbi) -> Self {
         Self {
             table: linux_table(abi),
+            deterministic_clock: false,
+        }
+    }
+
+    /// Restrict the handler to `policy` (see [`SyscallTable::with_policy`]).
+    #[must_use]
+    pub fn with_policy(self, policy: &SyscallPolicy) -> Self {
+        Self {
+            table: self.table.with_policy(policy),
+            ..self
         }
     }
+
+    /// Serve `clock_gettime` inline from the `time` CSR (one nanosecond
+    /// per retired instruction) instead of the host clock, so runs are
+    /// reproducible.
+    ///
+    /// RV64 only: RV32 guests keep the host clock.
+    #[must_use]
+    pub const fn with_deterministic_clock(mut self) -> Self {
+        self.deterministic_clock = true;
+        self
+    }
 }
 
 impl Default for LinuxHandler {
@@ -67,52 +124,86 @@ impl Default for LinuxHandler {
 
 impl<X: Xlen> SyscallHandler<X> for LinuxHandler {
     fn handle_ecall(&self, instr: &DecodedInstr<X>) -> InstrIR<X> {
-        self.table.handle_ecall(instr)
+        self.table.build_ir(instr, self)
+    }
+
+    fn inline_lift(&self, num: u64) -> Option<Vec<Stmt<X>>> {
+        use syscall_nr::{SYS_CLOCK_GETTIME, SYS_CLOCK_GETTIME64};
+        let is_clock = matches!(num, SYS_CLOCK_GETTIME | SYS_CLOCK_GETTIME64);
+        (self.deterministic_clock && is_clock && X::VALUE == 64).then(deterministic_clock_gettime)
     }
 }
 
+/// `clock_gettime(clk_id, tp)` with `time` as nanoseconds: fills the
+/// `timespec` at a1 (`tv_sec` at +0, `tv_nsec` at +8) and returns 0.
+fn deterministic_clock_gettime<X: Xlen>() -> Vec<Stmt<X>> {
+    let nanos = || Expr::csr(CSR_TIME);
+    let per_sec = || Expr::imm(X::from_u64(NANOS_PER_SEC));
+    vec![
+        Stmt::write_mem(Expr::read(REG_A1), 0, Expr::divu(nanos(), per_sec()), 8),
+        Stmt::write_mem(Expr::read(REG_A1), 8, Expr::remu(nanos(), per_sec()), 8),
+        Stmt::write_reg(REG_A0, Expr::imm(X::from_u64(0))),
+    ]
+}
+
 fn linux_table(abi: SyscallAbi) -> SyscallTable {
     use syscall_nr::{
-        SYS_BRK, SYS_CLOCK_GETTIME, SYS_CLOCK_GETTIME64, SYS_CLOSE, SYS_EXIT, SYS_EXIT_GROUP,
-        SYS_FCNTL, SYS_FSTAT, SYS_GETCWD, SYS_GETDENTS64, SYS_GETPID, SYS_GETRANDOM, SYS_GETTID,
-        SYS_MADVISE, SYS_MMAP, SYS_MPROTECT, SYS_MREMAP, SYS_MUNMAP, SYS_OPENAT, SYS_PREAD64,
-        SYS_PRLIMIT64, SYS_READ, SYS_RISCV_HWPROBE, SYS_RSEQ, SYS_SCHED_GET_PRIORITY_MAX,
-        SYS_SCHED_GET_PRIORITY_MIN, SYS_SCHED_GETPARAM, SYS_SCHED_GETSCHEDULER,
-        SYS_SCHED_SETSCHEDULER, SYS_SET_TID_ADDRESS, SYS_SETPRIORITY, SYS_SYSINFO, SYS_TGKILL,
-        SYS_WRITE,
+        SYS_BRK, SYS_CLOCK_GETTIME, SYS_CLOCK_GETTIME64, SYS_CLONE, SYS_CLONE3, SYS_CLOSE,
+        SYS_EXIT, SYS_EXIT_GROUP, SYS_FCNTL, SYS_FSTAT, SYS_FUTEX, SYS_GETCWD, SYS_GETDENTS64,
+        SYS_GETPID, SYS_GETRANDOM, SYS_GETTID, SYS_KILL, SYS_LSEEK, SYS_MADVISE, SYS_MMAP,
+        SYS_MPROTECT, SYS_MREMAP, SYS_MUNMAP, SYS_NEWFSTATAT, SYS_OPENAT, SYS_PREAD64,
+        SYS_PRLIMIT64, SYS_READ, SYS_READV, SYS_RISCV_HWPROBE, SYS_RSEQ, SYS_RT_SIGACTION,
+        SYS_RT_SIGPROCMASK, SYS_SCHED_GET_PRIORITY_MAX, SYS_SCHED_GET_PRIORITY_MIN,
+        SYS_SCHED_GETPARAM, SYS_SCHED_GETSCHEDULER, SYS_SCHED_SETSCHEDULER, SYS_SET_TID_ADDRESS,
+        SYS_SETPRIORITY, SYS_SIGALTSTACK, SYS_SYSINFO, SYS_TGKILL, SYS_TKILL, SYS_WRITE,
+        SYS_WRITEV,
     };
     SyscallTable::new(abi)
+        .with_host_syscalls()
         .with_exit(SYS_EXIT)
         .with_exit(SYS_EXIT_GROUP)
+        .with_exit_signal(SYS_KILL, 1)
+        .with_exit_signal(SYS_TKILL, 1)
+        .with_exit_signal(SYS_TGKILL, 2)
         .with_runtime(SYS_WRITE, "rv_sys_write", 3)
         .with_runtime(SYS_READ, "rv_sys_read", 3)
+        .with_runtime(SYS_WRITEV, "rv_sys_writev", 3)
+        .with_runtime(SYS_READV, "rv_sys_readv", 3)
+        .with_runtime(SYS_OPENAT, "rv_sys_openat", 4)
+        .with_runtime(SYS_CLOSE, "rv_sys_close", 1)
+        .with_runtime(SYS_LSEEK, "rv_sys_lseek", 3)
         .with_runtime(SYS_BRK, "rv_sys_brk", 1)
         .with_runtime(SYS_MMAP, "rv_sys_mmap", 6)
+        .with_runtime(SYS_MUNMAP, "rv_sys_munmap", 2)
+        .with_runtime(SYS_MREMAP, "rv_sys_mremap", 5)
         .with_runtime(SYS_FSTAT, "rv_sys_fstat", 2)
+        .with_runtime(SYS_NEWFSTATAT, "rv_sys_fstatat", 4)
         .with_runtime(SYS_GETRANDOM, "rv_sys_getrandom", 3)
         .with_runtime(SYS_CLOCK_GETTIME, "rv_sys_clock_gettime", 2)
         .with_runtime(SYS_CLOCK_GETTIME64, "rv_sys_clock_gettime", 2)
+        .with_runtime(SYS_FUTEX, "rv_sys_futex", 4)
         .with_return(SYS_RISCV_HWPROBE, -38)
         .with_return(SYS_RSEQ, -38)
-        .with_return(SYS_SET_TID_ADDRESS, 1)
-        .with_return(SYS_GETPID, 1)
-        .with_return(SYS_GETTID, 1)
+        .with_return(SYS_SET_TID_ADDRESS, GUEST_TID)
+        .with_return(SYS_GETPID, GUEST_TID)
+        .with_return(SYS_GETTID, GUEST_TID)
+        .with_return(SYS_SIGALTSTACK, 0)
+        // `oldact`: `sa_handler`, `sa_flags` and `sa_mask`
+        .with_zeroed_output(SYS_RT_SIGACTION, 2, 2, SIGSET_BYTES)
+        .with_zeroed_output(SYS_RT_SIGPROCMASK, 2, 0, SIGSET_BYTES)
+        .with_return(SYS_CLONE, -EAGAIN)
+        .with_return(SYS_CLONE3, -EAGAIN)
         .with_return(SYS_SCHED_GET_PRIORITY_MAX, 99)
         .with_return(SYS_SCHED_GET_PRIORITY_MIN, 1)
         .with_return(SYS_GETCWD, -1)
-        .with_return(SYS_OPENAT, -1)
         .with_return(SYS_SYSINFO, -1)
-        .with_return(SYS_MREMAP, -1)
         .with_return(SYS_FCNTL, -1)
-        .with_return(SYS_CLOSE, 0)
         .with_return(SYS_GETDENTS64, -1)
         .with_return(SYS_PREAD64, -1)
         .with_return(SYS_SETPRIORITY, -1)
         .with_return(SYS_SCHED_SETSCHEDULER, -1)
         .with_return(SYS_SCHED_GETSCHEDULER, -1)
         .with_return(SYS_SCHED_GETPARAM, -1)
-        .with_return(SYS_TGKILL, -1)
-        .with_return(SYS_MUNMAP, 0)
         .with_return(SYS_MPROTECT, 0)
         .with_return(SYS_MADVISE, 0)
         .with_return(SYS_PRLIMIT64, -1)
@@ -143,4 +234,38 @@ mod tests {
         assert!(matches!(ir.terminator, rvr_ir::Terminator::Fall { .. }));
         assert!(!ir.statements.is_empty());
     }
+
+    #[test]
+    fn test_deterministic_clock_inlines_clock_gettime() {
+        use rvr_ir::Rv32;
+
+        let rv32_instr = DecodedInstr::<Rv32> {
+            pc: 0x1000,
+            opid: OP_ECALL,
+            size: 4,
+            raw: 0,
+            args: InstrArgs::None,
+        };
+        // (deterministic, clock call on RV64, clock call on RV32)
+        let cases = [(false, true, true), (true, false, true)];
+        for (deterministic, rv64_call, rv32_call) in cases {
+            let mut handler = LinuxHandler::default();
+            if deterministic {
+                handler = handler.with_deterministic_clock();
+            }
+            let rv64 = format!("{:?}", handler.handle_ecall(&make_ecall_instr()).statements);
+            let rv32 = format!("{:?}", handler.handle_ecall(&rv32_instr).statements);
+            assert_eq!(
+                rv64.contains("rv_sys_clock_gettime"),
+                rv64_call,
+                "{deterministic}"
+            );
+            assert_eq!(
+                rv32.contains("rv_sys_clock_gettime"),
+                rv32_call,
+                "{deterministic}"
+            );
+            assert_eq!(rv64.contains(&format!("Csr({CSR_TIME})")), !rv64_call);
+        }
+    }
 }
@@ -1,9 +1,11 @@
 //! Syscall/ECALL handling for RISC-V.
 //!
 //! Provides a small, table-driven mechanism for lowering ECALL to IR.
-//! The default handler matches riscv-tests semantics (exit with a0).
+//! The default handler matches riscv-tests semantics (exit with a0); a
+//! [`BareMetalConfig`] maps bare-metal syscall numbers to built-in actions.
 //! Linux-style syscalls are handled via a syscall table that dispatches
-//! to runtime C helpers (`rv_sys_*`).
+//! to runtime C helpers (`rv_sys_*`), optionally restricted by a
+//! [`SyscallPolicy`].
 //!
 //! # Usage
 //!
@@ -17,8 +19,12 @@
 
 mod baremetal;
 mod linux;
+mod policy;
 mod table;
 
-pub use baremetal::{BareMetalHandler, RiscvTestsHandler};
+pub use baremetal::{
+    BareMetalConfig, BareMetalHandler, EcallAction, RiscvTestsHandler, UnmappedEcall,
+};
 pub use linux::{LinuxHandler, syscall_nr};
+pub use policy::{EPERM, SyscallPolicy};
 pub use table::{SyscallAbi, SyscallAction, SyscallEntry, SyscallHandler, SyscallTable};
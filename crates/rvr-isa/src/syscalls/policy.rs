@@ -0,0 +1,60 @@
+//! Capability policy for Linux-style syscalls.
+
+use std::collections::BTreeSet;
+
+/// `EPERM`, returned for syscalls the policy denies.
+pub const EPERM: i64 = 1;
+
+/// Syscalls a guest may make, and the directories it may open files under.
+///
+/// Applied at lift time: every syscall outside the policy compiles to an
+/// immediate `-EPERM`, as does any number the syscall table does not know.
+/// Exit syscalls are always allowed. Preopens are guest-visible directory
+/// paths the host binds to host directories at run time; path-taking
+/// syscalls are checked against them by the host before anything is opened.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SyscallPolicy {
+    allowed: BTreeSet<u64>,
+    preopens: Vec<String>,
+}
+
+impl SyscallPolicy {
+    /// Policy allowing only the exit syscalls.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow syscall `num`.
+    #[must_use]
+    pub fn allow(mut self, num: u64) -> Self {
+        self.allowed.insert(num);
+        self
+    }
+
+    /// Allow every syscall in `nums`.
+    #[must_use]
+    pub fn allow_all(mut self, nums: impl IntoIterator<Item = u64>) -> Self {
+        self.allowed.extend(nums);
+        self
+    }
+
+    /// Let the guest open files under `guest_path` (an absolute path).
+    #[must_use]
+    pub fn with_preopen(mut self, guest_path: impl Into<String>) -> Self {
+        self.preopens.push(guest_path.into());
+        self
+    }
+
+    /// True if syscall `num` is allowed.
+    #[must_use]
+    pub fn allows(&self, num: u64) -> bool {
+        self.allowed.contains(&num)
+    }
+
+    /// Guest paths of the preopened directories, in order.
+    #[must_use]
+    pub fn preopens(&self) -> &[String] {
+        &self.preopens
+    }
+}
@@ -1,11 +1,13 @@
 //! Table-driven syscall lowering.
 
-use rvr_ir::{Expr, InstrIR, Stmt, Terminator, Xlen};
+use rvr_ir::{Expr, InstrIR, OverrideExpansion, Stmt, Terminator, Xlen};
 
 use crate::{DecodedInstr, REG_A0, REG_A7, REG_T0};
 
+use super::policy::{EPERM, SyscallPolicy};
+
 /// Syscall ABI for the syscall number register.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum SyscallAbi {
     /// Standard RISC-V ABI: syscall number in a7.
     Standard,
@@ -25,9 +27,28 @@ impl SyscallAbi {
 }
 
 /// Trait for handling ECALL instructions.
+///
+/// Handlers cannot declare a narrower clobber set: a runtime call spills and
+/// reloads every hot register.
 pub trait SyscallHandler<X: Xlen>: Send + Sync {
     /// Generate IR for an ECALL instruction.
     fn handle_ecall(&self, instr: &DecodedInstr<X>) -> InstrIR<X>;
+
+    /// Generate IR for an ECALL instruction, optionally with helper blocks.
+    ///
+    /// Defaults to wrapping [`Self::handle_ecall`] with no helpers.
+    fn expand_ecall(&self, instr: &DecodedInstr<X>) -> OverrideExpansion<X> {
+        OverrideExpansion::new(self.handle_ecall(instr))
+    }
+
+    /// IR serving syscall `num` inline instead of calling its runtime
+    /// function, for handlers simple enough to lower directly.
+    ///
+    /// Defaults to `None`: the runtime function is called.
+    fn inline_lift(&self, num: u64) -> Option<Vec<Stmt<X>>> {
+        let _ = num;
+        None
+    }
 }
 
 /// Syscall action for a syscall table entry.
@@ -35,10 +56,85 @@ pub trait SyscallHandler<X: Xlen>: Send + Sync {
 pub enum SyscallAction {
     /// Exit the program with a0 as exit code.
     Exit,
+    /// Deliver the signal in argument register `a{arg}` with its default
+    /// action: exit with 128 + signal, as a shell reports it, if that
+    /// terminates the process. Signal 0 and signals ignored or stopping by
+    /// default return 0; invalid signals return `-EINVAL`.
+    ExitSignal { arg: u8 },
     /// Call a runtime function with arguments a0..a5.
     Runtime { name: &'static str, args: u8 },
     /// Return a fixed value in a0 (may be negative).
     ReturnConst(i64),
+    /// Return 0 after zeroing the output struct at the pointer in `a{arg}`,
+    /// if non-null: `regs` register-sized fields followed by `bytes` bytes.
+    ZeroOutput { arg: u8, regs: u8, bytes: u8 },
+}
+
+/// Exit code base of a guest killed by a signal (128 + signal number).
+const SIGNAL_EXIT_BASE: u64 = 128;
+
+/// Highest Linux signal number.
+const SIGNAL_MAX: u64 = 64;
+
+/// Signals ignored (`SIGCHLD`, `SIGURG`, `SIGWINCH`) or stopping and
+/// continuing the process (`SIGCONT`..`SIGTTOU`) by default, as inclusive
+/// ranges.
+const NON_TERMINATING_SIGNALS: [(u64, u64); 2] = [(17, 23), (28, 28)];
+
+/// `-EINVAL`, returned for a signal above [`SIGNAL_MAX`].
+const EINVAL: i64 = 22;
+
+impl SyscallAction {
+    /// True for actions that exit the program.
+    #[must_use]
+    pub const fn is_exit(self) -> bool {
+        matches!(self, Self::Exit | Self::ExitSignal { .. })
+    }
+
+    /// Statements of an exit action, run when its syscall is made.
+    fn exit_stmts<X: Xlen>(self) -> Vec<Stmt<X>> {
+        let imm = |value: u64| Expr::imm(X::from_u64(value));
+        let exit = |code: Expr<X>| vec![Stmt::write_exited(imm(1)), Stmt::write_exit_code(code)];
+        let Self::ExitSignal { arg } = self else {
+            return exit(Expr::read(REG_A0));
+        };
+        let signal = || Expr::read(REG_A0 + arg);
+        // Unsigned `signal - lo < len` tests lo <= signal < lo + len
+        let in_range = |lo: u64, hi: u64| Expr::ltu(Expr::sub(signal(), imm(lo)), imm(hi - lo + 1));
+        let terminates = NON_TERMINATING_SIGNALS
+            .iter()
+            .fold(in_range(1, SIGNAL_MAX), |acc, &(lo, hi)| {
+                Expr::and(acc, Expr::eq(in_range(lo, hi), imm(0)))
+            });
+        let ret = Expr::select(
+            Expr::ltu(imm(SIGNAL_MAX), signal()),
+            imm((-EINVAL).cast_unsigned()),
+            imm(0),
+        );
+        vec![Stmt::if_then_else(
+            terminates,
+            exit(Expr::add(imm(SIGNAL_EXIT_BASE), signal())),
+            vec![Stmt::write_reg(REG_A0, ret)],
+        )]
+    }
+
+    /// Statements of a [`ZeroOutput`](Self::ZeroOutput) action.
+    fn zero_output_stmts<X: Xlen>(arg: u8, regs: u8, bytes: u8) -> Vec<Stmt<X>> {
+        let width = u8::try_from(X::REG_BYTES).expect("register size fits u8");
+        let size = u16::from(regs) * u16::from(width) + u16::from(bytes);
+        let ptr = Expr::read(REG_A0 + arg);
+        let stores = (0..size)
+            .step_by(usize::from(width))
+            .map(|offset| {
+                let offset = i16::try_from(offset).expect("output struct fits i16");
+                Stmt::write_mem(ptr.clone(), offset, Expr::imm(X::from_u64(0)), width)
+            })
+            .collect();
+        vec![
+            Stmt::if_then(Expr::ne(ptr, Expr::imm(X::from_u64(0))), stores),
+            Stmt::write_reg(REG_A0, Expr::imm(X::from_u64(0))),
+        ]
+    }
 }
 
 /// Syscall table entry.
@@ -57,6 +153,14 @@ impl SyscallEntry {
         }
     }
 
+    #[must_use]
+    pub const fn exit_signal(num: u64, arg: u8) -> Self {
+        Self {
+            num,
+            action: SyscallAction::ExitSignal { arg },
+        }
+    }
+
     #[must_use]
     pub const fn runtime(num: u64, name: &'static str, args: u8) -> Self {
         Self {
@@ -72,6 +176,14 @@ impl SyscallEntry {
             action: SyscallAction::ReturnConst(value),
         }
     }
+
+    #[must_use]
+    pub const fn zero_output(num: u64, arg: u8, regs: u8, bytes: u8) -> Self {
+        Self {
+            num,
+            action: SyscallAction::ZeroOutput { arg, regs, bytes },
+        }
+    }
 }
 
 /// Table-driven syscall handler.
@@ -80,6 +192,7 @@ pub struct SyscallTable {
     abi: SyscallAbi,
     entries: Vec<SyscallEntry>,
     default_error: i64,
+    host_syscalls: bool,
 }
 
 impl SyscallTable {
@@ -90,6 +203,7 @@ impl SyscallTable {
             abi,
             entries: Vec::new(),
             default_error: -38, // ENOSYS
+            host_syscalls: false,
         }
     }
 
@@ -106,7 +220,15 @@ impl SyscallTable {
         self.with_entry(SyscallEntry::exit(num))
     }
 
-    /// Add a runtime syscall entry.
+    /// Add a syscall entry that delivers the signal in `a{arg}`, exiting if
+    /// its default action terminates the process.
+    #[must_use]
+    pub fn with_exit_signal(self, num: u64, arg: u8) -> Self {
+        self.with_entry(SyscallEntry::exit_signal(num, arg))
+    }
+
+    /// Add a runtime syscall entry. The C backend links `name` under the
+    /// program's symbol prefix.
     #[must_use]
     pub fn with_runtime(self, num: u64, name: &'static str, args: u8) -> Self {
         self.with_entry(SyscallEntry::runtime(num, name, args))
@@ -118,6 +240,22 @@ impl SyscallTable {
         self.with_entry(SyscallEntry::ret(num, value))
     }
 
+    /// Add a syscall entry that returns 0 after zeroing the output struct
+    /// at the pointer in `a{arg}`, if non-null: `regs` register-sized fields
+    /// followed by `bytes` bytes.
+    #[must_use]
+    pub fn with_zeroed_output(self, num: u64, arg: u8, regs: u8, bytes: u8) -> Self {
+        self.with_entry(SyscallEntry::zero_output(num, arg, regs, bytes))
+    }
+
+    /// Check `rv_has_host_syscall` before the table and send syscalls the
+    /// host registered to `rv_host_syscall` (a0..a5) instead.
+    #[must_use]
+    pub const fn with_host_syscalls(mut self) -> Self {
+        self.host_syscalls = true;
+        self
+    }
+
     /// Set the default error code for unknown syscalls (default: -38 = ENOSYS).
     #[must_use]
     pub const fn default_error(mut self, code: i64) -> Self {
@@ -125,35 +263,43 @@ impl SyscallTable {
         self
     }
 
-    pub(crate) fn build_ir<X: Xlen>(&self, instr: &DecodedInstr<X>) -> InstrIR<X> {
+    /// Restrict the table to `policy`: entries it denies and unknown
+    /// syscalls return `-EPERM` without reaching their handler.
+    ///
+    /// Host syscalls are still checked first; the host chose to serve them.
+    #[must_use]
+    pub fn with_policy(mut self, policy: &SyscallPolicy) -> Self {
+        for entry in &mut self.entries {
+            if !entry.action.is_exit() && !policy.allows(entry.num) {
+                entry.action = SyscallAction::ReturnConst(-EPERM);
+            }
+        }
+        self.default_error(-EPERM)
+    }
+
+    /// Lower an ECALL through the table, asking `hooks` for inline
+    /// lowerings of runtime entries.
+    pub(crate) fn build_ir<X: Xlen, H: SyscallHandler<X> + ?Sized>(
+        &self,
+        instr: &DecodedInstr<X>,
+        hooks: &H,
+    ) -> InstrIR<X> {
         let sys_reg = self.abi.syscall_reg();
         let sys_num = Expr::read(sys_reg);
         let a7_eq = |num: u64| Expr::eq(sys_num.clone(), Expr::imm(X::from_u64(num)));
+        let width = u8::try_from(X::REG_BYTES * 8).expect("register width fits u8");
 
         let mut entries = self.entries.clone();
         entries.sort_by_key(|e| e.num);
 
-        let mut exit_entries = Vec::new();
-        let mut non_exit_entries = Vec::new();
-
-        for entry in entries {
-            match entry.action {
-                SyscallAction::Exit => exit_entries.push(entry),
-                _ => non_exit_entries.push(entry),
-            }
-        }
+        let (exit_entries, non_exit_entries): (Vec<_>, Vec<_>) =
+            entries.into_iter().partition(|e| e.action.is_exit());
 
         let mut stmts = Vec::new();
 
-        // Exit syscalls: set exit flag and exit code (a0)
+        // Exit syscalls: set exit flag and exit code (a0, or 128 + signal)
         for entry in &exit_entries {
-            stmts.push(Stmt::if_then(
-                a7_eq(entry.num),
-                vec![
-                    Stmt::write_exited(Expr::imm(X::from_u64(1))),
-                    Stmt::write_exit_code(Expr::read(REG_A0)),
-                ],
-            ));
+            stmts.push(Stmt::if_then(a7_eq(entry.num), entry.action.exit_stmts()));
         }
 
         // Default error return
@@ -166,23 +312,18 @@ impl SyscallTable {
         for entry in non_exit_entries.iter().rev() {
             dispatch = match entry.action {
                 SyscallAction::Runtime { name, args } => {
-                    let mut call_args = Vec::with_capacity((args as usize) + 1);
-                    call_args.push(Expr::var("state"));
-                    for i in 0..args {
-                        call_args.push(Expr::read(REG_A0 + i));
-                    }
-                    Stmt::if_then_else(
-                        a7_eq(entry.num),
+                    let body = hooks.inline_lift(entry.num).unwrap_or_else(|| {
+                        let mut call_args = Vec::with_capacity((args as usize) + 1);
+                        call_args.push(Expr::var("state"));
+                        for i in 0..args {
+                            call_args.push(Expr::read(REG_A0 + i));
+                        }
                         vec![Stmt::write_reg(
                             REG_A0,
-                            Expr::extern_call(
-                                name,
-                                call_args,
-                                u8::try_from(X::REG_BYTES * 8).expect("register width fits u8"),
-                            ),
-                        )],
-                        vec![dispatch],
-                    )
+                            Expr::extern_call(name, call_args, width),
+                        )]
+                    });
+                    Stmt::if_then_else(a7_eq(entry.num), body, vec![dispatch])
                 }
                 SyscallAction::ReturnConst(value) => Stmt::if_then_else(
                     a7_eq(entry.num),
@@ -192,7 +333,12 @@ impl SyscallTable {
                     )],
                     vec![dispatch],
                 ),
-                SyscallAction::Exit => dispatch,
+                SyscallAction::ZeroOutput { arg, regs, bytes } => Stmt::if_then_else(
+                    a7_eq(entry.num),
+                    SyscallAction::zero_output_stmts(arg, regs, bytes),
+                    vec![dispatch],
+                ),
+                SyscallAction::Exit | SyscallAction::ExitSignal { .. } => dispatch,
             };
         }
 
@@ -204,10 +350,36 @@ impl SyscallTable {
                 for entry in exit_entries.iter().skip(1) {
                     is_exit = Expr::or(is_exit, a7_eq(entry.num));
                 }
-                stmts.push(Stmt::if_then(Expr::not(is_exit), vec![dispatch]));
+                let not_exit = Expr::eq(is_exit, Expr::imm(X::from_u64(0)));
+                stmts.push(Stmt::if_then(not_exit, vec![dispatch]));
             }
         }
 
+        if self.host_syscalls {
+            stmts = vec![Stmt::if_then_else(
+                Expr::ne(
+                    Expr::extern_call(
+                        "rv_has_host_syscall",
+                        vec![Expr::var("state"), sys_num.clone()],
+                        width,
+                    ),
+                    Expr::imm(X::from_u64(0)),
+                ),
+                vec![Stmt::write_reg(
+                    REG_A0,
+                    Expr::extern_call(
+                        "rv_host_syscall",
+                        std::iter::once(Expr::var("state"))
+                            .chain(std::iter::once(sys_num))
+                            .chain((REG_A0..REG_A0 + 6).map(Expr::read))
+                            .collect(),
+                        width,
+                    ),
+                )],
+                stmts,
+            )];
+        }
+
         let next_pc = instr.pc + X::Reg::from(u32::from(instr.size));
         InstrIR::new(
             instr.pc,
@@ -222,7 +394,7 @@ impl SyscallTable {
 
 impl<X: Xlen> SyscallHandler<X> for SyscallTable {
     fn handle_ecall(&self, instr: &DecodedInstr<X>) -> InstrIR<X> {
-        self.build_ir(instr)
+        self.build_ir(instr, self)
     }
 }
 
@@ -230,7 +402,7 @@ impl<X: Xlen> SyscallHandler<X> for SyscallTable {
 mod tests {
     use super::*;
     use crate::{DecodedInstr, InstrArgs, OP_ECALL};
-    use rvr_ir::{Rv64, WriteTarget};
+    use rvr_ir::{BinaryOp, Rv64, WriteTarget};
 
     fn make_ecall_instr() -> DecodedInstr<Rv64> {
         DecodedInstr {
@@ -278,4 +450,197 @@ mod tests {
         assert!(!ir.statements.is_empty());
         assert!(has_exit_write(&ir.statements));
     }
+
+    #[test]
+    fn test_exit_signal_adds_signal_base() {
+        let handler = SyscallTable::new(SyscallAbi::Standard).with_exit_signal(131, 2);
+
+        let ir = handler.handle_ecall(&make_ecall_instr());
+        assert!(has_exit_write(&ir.statements));
+        let stmts = format!("{:?}", ir.statements);
+        assert!(stmts.contains(&format!("{SIGNAL_EXIT_BASE}")), "{stmts}");
+
+        // Exits only if the signal terminates; otherwise a0 is the result
+        let [Stmt::If { then_stmts, .. }] = ir.statements.as_slice() else {
+            panic!("expected one exit entry: {stmts}");
+        };
+        let [
+            Stmt::If {
+                then_stmts: kill,
+                else_stmts: ignore,
+                ..
+            },
+        ] = then_stmts.as_slice()
+        else {
+            panic!("expected a default action check: {stmts}");
+        };
+        assert!(has_exit_write(kill));
+        assert!(!has_exit_write(ignore));
+        assert!(matches!(
+            ignore.as_slice(),
+            [Stmt::Write {
+                target: WriteTarget::Reg(REG_A0),
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn test_exit_entries_skip_dispatch() {
+        let handler = SyscallTable::new(SyscallAbi::Standard)
+            .with_exit_signal(129, 1)
+            .with_return(134, 0);
+
+        let ir = handler.handle_ecall(&make_ecall_instr());
+        let [_, Stmt::If { cond, .. }] = ir.statements.as_slice() else {
+            panic!(
+                "expected an exit entry and the dispatch: {:?}",
+                ir.statements
+            );
+        };
+        // A logical, not bitwise, negation of the exit check
+        assert!(
+            matches!(cond, Expr::Binary { op: BinaryOp::Eq, right, .. } if matches!(**right, Expr::Imm(0))),
+            "{cond:?}"
+        );
+    }
+
+    /// `(offset, width)` of every memory write in `stmts`.
+    fn mem_writes<X: Xlen>(stmts: &[Stmt<X>]) -> Vec<(i16, u8)> {
+        let mut out = Vec::new();
+        for stmt in stmts {
+            match stmt {
+                Stmt::Write {
+                    target: WriteTarget::Mem { offset, width, .. },
+                    ..
+                } => out.push((*offset, *width)),
+                Stmt::If {
+                    then_stmts,
+                    else_stmts,
+                    ..
+                } => {
+                    out.extend(mem_writes(then_stmts));
+                    out.extend(mem_writes(else_stmts));
+                }
+                _ => {}
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn test_zero_output_clears_struct() {
+        use rvr_ir::Rv32;
+
+        let handler = SyscallTable::new(SyscallAbi::Standard).with_zeroed_output(134, 2, 2, 8);
+        let rv64 = handler.handle_ecall(&make_ecall_instr());
+        assert_eq!(mem_writes(&rv64.statements), [(0, 8), (8, 8), (16, 8)]);
+
+        let rv32 = handler.handle_ecall(&DecodedInstr::<Rv32> {
+            pc: 0x1000,
+            opid: OP_ECALL,
+            size: 4,
+            raw: 0,
+            args: InstrArgs::None,
+        });
+        assert_eq!(
+            mem_writes(&rv32.statements),
+            [(0, 4), (4, 4), (8, 4), (12, 4)]
+        );
+        // Only through a non-null pointer
+        let stmts = format!("{:?}", rv64.statements);
+        assert!(stmts.contains("op: Ne"), "{stmts}");
+    }
+
+    #[test]
+    fn test_host_syscalls_checked_first() {
+        let handler = SyscallTable::new(SyscallAbi::Standard)
+            .with_host_syscalls()
+            .with_exit(93)
+            .with_runtime(64, "rv_sys_write", 3);
+
+        let ir = handler.handle_ecall(&make_ecall_instr());
+        let [
+            Stmt::If {
+                then_stmts,
+                else_stmts,
+                ..
+            },
+        ] = ir.statements.as_slice()
+        else {
+            panic!("expected one host syscall check: {:?}", ir.statements);
+        };
+        let host_call = format!("{then_stmts:?}");
+        assert!(host_call.contains("rv_host_syscall"), "{host_call}");
+        assert!(!has_exit_write(then_stmts));
+        assert!(has_exit_write(else_stmts));
+    }
+
+    #[test]
+    fn test_policy_denies_with_eperm() {
+        let policy = SyscallPolicy::new().allow(64);
+        let handler = SyscallTable::new(SyscallAbi::Standard)
+            .with_exit(93)
+            .with_runtime(63, "rv_sys_read", 3)
+            .with_runtime(64, "rv_sys_write", 3)
+            .with_policy(&policy);
+
+        let ir = handler.handle_ecall(&make_ecall_instr());
+        let stmts = format!("{:?}", ir.statements);
+        assert!(stmts.contains("rv_sys_write"), "{stmts}");
+        assert!(!stmts.contains("rv_sys_read"), "{stmts}");
+        assert!(has_exit_write(&ir.statements));
+        assert_eq!(handler.default_error, -EPERM);
+    }
+
+    /// Number of `name` extern call expressions in `stmts`.
+    fn call_count(stmts: &[Stmt<Rv64>], name: &str) -> usize {
+        fn walk(expr: &Expr<Rv64>, name: &str) -> usize {
+            match expr {
+                Expr::ExternCall { name: n, .. } if n == name => 1,
+                Expr::Binary { left, right, .. } => walk(left, name) + walk(right, name),
+                _ => 0,
+            }
+        }
+        stmts
+            .iter()
+            .map(|stmt| match stmt {
+                Stmt::Write { value, .. } => walk(value, name),
+                Stmt::If {
+                    cond,
+                    then_stmts,
+                    else_stmts,
+                } => walk(cond, name) + call_count(then_stmts, name) + call_count(else_stmts, name),
+                Stmt::ExternCall { .. } => 0,
+            })
+            .sum()
+    }
+
+    /// Hooks that inline getrandom.
+    struct Hooks;
+
+    impl SyscallHandler<Rv64> for Hooks {
+        fn handle_ecall(&self, _instr: &DecodedInstr<Rv64>) -> InstrIR<Rv64> {
+            unreachable!()
+        }
+
+        fn inline_lift(&self, num: u64) -> Option<Vec<Stmt<Rv64>>> {
+            (num == 278).then(|| vec![Stmt::write_reg(REG_A0, Expr::imm(0))])
+        }
+    }
+
+    #[test]
+    fn test_inline_lift_replaces_runtime_call() {
+        let table = SyscallTable::new(SyscallAbi::Standard)
+            .with_runtime(63, "rv_sys_read", 3)
+            .with_runtime(278, "rv_sys_getrandom", 3);
+        let own = table.build_ir(&make_ecall_instr(), &table).statements;
+        let hooked = table.build_ir(&make_ecall_instr(), &Hooks).statements;
+        // (name, calls with the table's own hooks, calls with `Hooks`)
+        let cases = [("rv_sys_read", 1, 1), ("rv_sys_getrandom", 1, 0)];
+        for (name, expected_own, expected_hooked) in cases {
+            assert_eq!(call_count(&own, name), expected_own, "{name}");
+            assert_eq!(call_count(&hooked, name), expected_hooked, "{name}");
+        }
+    }
 }
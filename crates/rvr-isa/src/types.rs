@@ -67,6 +67,23 @@ pub enum InstrArgs {
     Custom(Box<[u32]>),
 }
 
+impl InstrArgs {
+    /// Highest integer register the arguments name, if any.
+    ///
+    /// Custom arguments are opaque and report `None`.
+    #[must_use]
+    pub fn max_reg(&self) -> Option<u8> {
+        match *self {
+            Self::R { rd, rs1, rs2 } | Self::Amo { rd, rs1, rs2, .. } => Some(rd.max(rs1).max(rs2)),
+            Self::R4 { rd, rs1, rs2, rs3 } => Some(rd.max(rs1).max(rs2).max(rs3)),
+            Self::I { rd, rs1, .. } | Self::Csr { rd, rs1, .. } => Some(rd.max(rs1)),
+            Self::S { rs1, rs2, .. } | Self::B { rs1, rs2, .. } => Some(rs1.max(rs2)),
+            Self::U { rd, .. } | Self::J { rd, .. } | Self::CsrI { rd, .. } => Some(rd),
+            Self::None | Self::Custom(_) => None,
+        }
+    }
+}
+
 /// Compact instruction identifier (2 bytes).
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
 pub struct OpId {
@@ -153,6 +170,9 @@ pub const EXT_ZBB: u8 = 7;
 pub const EXT_ZBS: u8 = 8;
 pub const EXT_ZBKB: u8 = 9;
 pub const EXT_ZICOND: u8 = 10;
+pub const EXT_V: u8 = 11;
+pub const EXT_ZCB: u8 = 12;
+pub const EXT_ZCMP: u8 = 13;
 
 // Number of registers
 pub const NUM_REGS_I: usize = 32;
@@ -237,4 +257,21 @@ mod tests {
         assert_eq!(op.ext, EXT_I);
         assert_eq!(op.idx, 5);
     }
+
+    #[test]
+    fn test_max_reg() {
+        let store = InstrArgs::S {
+            rs1: 2,
+            rs2: 18,
+            imm: -4,
+        };
+        assert_eq!(store.max_reg(), Some(18));
+        let csri = InstrArgs::CsrI {
+            rd: 5,
+            imm: 31,
+            csr: 0x300,
+        };
+        assert_eq!(csri.max_reg(), Some(5));
+        assert_eq!(InstrArgs::None.max_reg(), None);
+    }
 }
@@ -1,12 +1,36 @@
+use std::path::PathBuf;
+use std::{env, fs};
+
+/// Linker script per layout profile (see `LayoutProfile` in rvr-emit).
+const LAYOUTS: [(&str, &str); 2] = [("baremetal", "link.x"), ("rv32-zkvm", "link-rv32-zkvm.x")];
+
 fn main() {
-    // Note: Linker script (link.x) and linker flags must be set in the
-    // final binary's .cargo/config.toml, not here. Cargo's rustc-link-arg
-    // from library dependencies doesn't propagate to final binary linking.
-    //
-    // Example .cargo/config.toml for RISC-V projects using rvr-rt:
+    // The selected script is copied to OUT_DIR as `link.x`. Link search paths
+    // propagate to the final binary, but link args do not, so the binary
+    // still passes the script itself, e.g. in .cargo/config.toml:
     //
     // [target.rv64i]
-    // rustflags = ["-C", "link-arg=-T<path>/link.x", "-C", "link-arg=--gc-sections"]
-
+    // rustflags = ["-C", "link-arg=-Tlink.x", "-C", "link-arg=--gc-sections"]
     println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=RVR_LAYOUT");
+    for (_, script) in LAYOUTS {
+        println!("cargo:rerun-if-changed={script}");
+    }
+
+    let layout = env::var("RVR_LAYOUT").unwrap_or_else(|_| {
+        let default = if env::var_os("CARGO_FEATURE_LAYOUT_RV32_ZKVM").is_some() {
+            "rv32-zkvm"
+        } else {
+            "baremetal"
+        };
+        default.to_string()
+    });
+    let Some((_, script)) = LAYOUTS.iter().find(|(name, _)| *name == layout) else {
+        let names: Vec<_> = LAYOUTS.iter().map(|(name, _)| *name).collect();
+        panic!("unknown RVR_LAYOUT `{layout}` (expected one of: {})", names.join(", "));
+    };
+
+    let out_dir = PathBuf::from(env::var_os("OUT_DIR").expect("OUT_DIR is set by cargo"));
+    fs::copy(script, out_dir.join("link.x")).expect("copy linker script");
+    println!("cargo:rustc-link-search={}", out_dir.display());
 }
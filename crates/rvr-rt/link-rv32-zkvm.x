@@ -0,0 +1,49 @@
+/* Layout profile "rv32-zkvm": zkVM-style RV32.
+ *
+ * The stack grows down from 0x200000, followed by a 4 KiB guard gap and the
+ * program. Code must end below 0x10000000; memory ends at 0x78000000.
+ */
+MEMORY
+{
+  STACK : ORIGIN = 0x00001000, LENGTH = 0x001FF000
+  RAM   : ORIGIN = 0x00201000, LENGTH = 0x77DFF000
+}
+
+ENTRY(_start)
+
+SECTIONS
+{
+  .text : {
+    *(.text._start)
+    *(.text .text.*)
+  } > RAM
+
+  ASSERT(. <= 0x10000000, "rv32-zkvm: code extends past the code region")
+
+  .rodata : {
+    *(.rodata .rodata.*)
+  } > RAM
+
+  /* Guest tests registered with `rvr_test!` (read by `rvr test guest`) */
+  .rvr_tests : {
+    KEEP(*(.rvr_tests))
+  } > RAM
+
+  .data : {
+    *(.data .data.*)
+    PROVIDE(__global_pointer$ = . + 0x800);
+  } > RAM
+
+  .bss : {
+    __bss_start = .;
+    *(.bss .bss.*)
+    *(COMMON)
+    __bss_end = .;
+  } > RAM
+
+  __stack_top = ORIGIN(STACK) + LENGTH(STACK);
+
+  /DISCARD/ : {
+    *(.eh_frame)
+  }
+}
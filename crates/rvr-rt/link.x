@@ -1,3 +1,4 @@
+/* Layout profile "baremetal": the top 1 MiB of RAM is reserved for the stack. */
 MEMORY
 {
   /* Use lower half of 32-bit address space so zero-extension = sign-extension */
@@ -17,6 +18,11 @@ SECTIONS
     *(.rodata .rodata.*)
   } > RAM
 
+  /* Guest tests registered with `rvr_test!` (read by `rvr test guest`) */
+  .rvr_tests : {
+    KEEP(*(.rvr_tests))
+  } > RAM
+
   .data : {
     *(.data .data.*)
     PROVIDE(__global_pointer$ = . + 0x800);
@@ -30,6 +36,8 @@ SECTIONS
   } > RAM
 
   __stack_top = ORIGIN(RAM) + LENGTH(RAM);
+  __stack_size = 1M;
+  ASSERT(__bss_end <= __stack_top - __stack_size, "baremetal: program overlaps the stack")
 
   /DISCARD/ : {
     *(.eh_frame)
@@ -9,9 +9,18 @@
 //!   - `panic-halt`: Infinite loop (safe, debugger-friendly)
 //!   - `panic-trap`: Illegal instruction (exit_code=1 via trap)
 //!   - `panic-abort`: Exit via ecall with code 1
+//!   - `panic-htif`: Write the message via HTIF, then exit with code 1
+//!
+//! - **Guest tests** (`test` feature): `rvr_test!` registers test functions
+//!   for the `guest_test` runner, with a panic handler that records the message
+//!   (instead of a `panic-*` feature)
 //!
 //! - **Allocator** (`alloc` feature): Bump allocator with const-generic heap size
 //!
+//! - **Trace records** (`trace` feature): `rvr_rt::trace` re-exports
+//!   `rvr-trace-format`, so guest instrumentation can write diff entries the
+//!   host reads like those of its buffered diff tracer
+//!
 //! - **Critical section** (`critical-section` feature): Single-threaded critical
 //!   section via mstatus CSR
 //!
@@ -46,8 +55,27 @@
 //! | `panic-halt` | Panic handler that loops forever |
 //! | `panic-trap` | Panic handler that executes `unimp` (exit_code=1) |
 //! | `panic-abort` | Panic handler that calls exit syscall with code 1 |
+//! | `panic-htif` | Panic handler that writes the message via HTIF, then exits with code 1 |
+//! | `test` | `rvr_test!` guest tests; panic handler records the message and exits with code 101 |
 //! | `alloc` | Bump allocator (`BumpAlloc<N>`) |
+//! | `trace` | Tracer record layouts (`trace::DiffEntry`, `trace::DiffWriter`) |
 //! | `critical-section` | Critical section implementation for `critical-section` crate |
+//! | `layout-rv32-zkvm` | Link with the `rv32-zkvm` layout profile script instead of the default |
+//!
+//! # Linker Scripts
+//!
+//! The build script copies the linker script for the selected layout profile
+//! to its output directory as `link.x` and adds it to the link search path, so
+//! binaries link with `-C link-arg=-Tlink.x`. `RVR_LAYOUT=<profile>` picks the
+//! profile at build time and overrides the feature:
+//!
+//! | Profile | Script | Layout |
+//! |---------|--------|--------|
+//! | `baremetal` (default) | `link.x` | RAM at `0x40000000`, 256 MiB, top 1 MiB stack |
+//! | `rv32-zkvm` | `link-rv32-zkvm.x` | Stack below `0x200000`, program from `0x201000` |
+//!
+//! Compile with the matching `rvr` layout profile (`--layout`) to have the
+//! recompiler check the ELF against it.
 
 #![no_std]
 
@@ -59,6 +87,12 @@ mod entry;
 #[cfg(any(feature = "panic-halt", feature = "panic-trap", feature = "panic-abort", feature = "panic-htif"))]
 mod panic;
 
+// Guest unit tests module
+#[cfg(feature = "test")]
+mod test;
+#[cfg(feature = "test")]
+pub use test::{PANIC_EXIT_CODE, TestCase};
+
 // Allocator module
 #[cfg(feature = "alloc")]
 mod alloc;
@@ -68,3 +102,7 @@ pub use alloc::BumpAlloc;
 // Critical section module
 #[cfg(feature = "critical-section")]
 mod critical;
+
+// Tracer record layouts
+#[cfg(feature = "trace")]
+pub use rvr_trace_format as trace;
@@ -0,0 +1,160 @@
+//! Guest unit tests for the `guest_test` runner.
+//!
+//! Rust's test harness needs `std`, so guest tests register themselves with
+//! [`rvr_test!`](crate::rvr_test) instead: each test becomes an entry in the
+//! `.rvr_tests` link section, which the host reads from the ELF and calls
+//! one by one from a fresh state.
+//!
+//! A panicking test writes its message to `RVR_TEST_PANIC_MSG` (length in
+//! `RVR_TEST_PANIC_LEN`) and exits with [`PANIC_EXIT_CODE`], so the host
+//! can report it. This replaces the `panic-*` handlers.
+
+use core::fmt::{self, Write};
+use core::panic::PanicInfo;
+
+#[cfg(any(
+    feature = "panic-halt",
+    feature = "panic-trap",
+    feature = "panic-abort",
+    feature = "panic-htif"
+))]
+compile_error!("Feature `test` provides the panic handler; disable the `panic-*` features");
+
+/// Exit code of a panicking test (as with Rust's own test harness).
+pub const PANIC_EXIT_CODE: u8 = 101;
+
+/// Capacity of the panic message buffer; longer messages are truncated.
+const PANIC_MSG_CAPACITY: usize = 1024;
+
+#[unsafe(no_mangle)]
+static mut RVR_TEST_PANIC_MSG: [u8; PANIC_MSG_CAPACITY] = [0; PANIC_MSG_CAPACITY];
+
+#[unsafe(no_mangle)]
+static mut RVR_TEST_PANIC_LEN: usize = 0;
+
+/// A registered test: one `.rvr_tests` entry.
+///
+/// Laid out as three words (name pointer, name length, function), which is
+/// the format the host parses. Create with [`rvr_test!`](crate::rvr_test).
+#[repr(C)]
+pub struct TestCase {
+    name: *const u8,
+    name_len: usize,
+    func: extern "C" fn(),
+}
+
+// Only read by the host; the guest never dereferences `name`.
+unsafe impl Sync for TestCase {}
+
+impl TestCase {
+    /// Test entry for `func` named `name`.
+    pub const fn new(name: &'static str, func: extern "C" fn()) -> Self {
+        Self {
+            name: name.as_ptr(),
+            name_len: name.len(),
+            func,
+        }
+    }
+}
+
+/// Define test functions and register them in the `.rvr_tests` section.
+///
+/// Tests take no arguments and pass by returning; they fail by panicking.
+/// Each test is named `module::path::function`.
+///
+/// ```ignore
+/// rvr_rt::rvr_test! {
+///     fn adds() {
+///         assert_eq!(1 + 1, 2);
+///     }
+///
+///     fn parses() {
+///         assert!(parse("42").is_ok());
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! rvr_test {
+    ($($(#[$attr:meta])* fn $name:ident() $body:block)*) => {
+        $(
+            $(#[$attr])*
+            extern "C" fn $name() $body
+
+            const _: () = {
+                #[used]
+                #[unsafe(link_section = ".rvr_tests")]
+                static TEST: $crate::TestCase = $crate::TestCase::new(
+                    concat!(module_path!(), "::", stringify!($name)),
+                    $name,
+                );
+            };
+        )*
+    };
+}
+
+/// Appends to the panic message buffer, truncating at its capacity.
+struct PanicMessage {
+    len: usize,
+}
+
+impl Write for PanicMessage {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let n = s.len().min(PANIC_MSG_CAPACITY - self.len);
+        // Single hart, and the panic handler never returns: no other access
+        unsafe {
+            let dst = (&raw mut RVR_TEST_PANIC_MSG).cast::<u8>().add(self.len);
+            core::ptr::copy_nonoverlapping(s.as_ptr(), dst, n);
+        }
+        self.len += n;
+        Ok(())
+    }
+}
+
+/// Panic handler that records the message and exits with
+/// [`PANIC_EXIT_CODE`].
+#[panic_handler]
+fn panic_test(info: &PanicInfo) -> ! {
+    let mut message = PanicMessage { len: 0 };
+    let _ = write!(message, "{info}");
+    unsafe { (&raw mut RVR_TEST_PANIC_LEN).write_volatile(message.len) };
+    exit(PANIC_EXIT_CODE)
+}
+
+/// Exit via the exit syscall (93).
+fn exit(code: u8) -> ! {
+    #[cfg(all(
+        any(target_arch = "riscv32", target_arch = "riscv64"),
+        not(target_feature = "e")
+    ))]
+    unsafe {
+        // Standard ABI: syscall number in a7
+        core::arch::asm!(
+            "li a7, 93", // syscall 93 = exit
+            "ecall",
+            in("a0") usize::from(code),
+            options(noreturn)
+        );
+    }
+
+    #[cfg(all(
+        any(target_arch = "riscv32", target_arch = "riscv64"),
+        target_feature = "e"
+    ))]
+    unsafe {
+        // RVE ABI: syscall number in t0
+        core::arch::asm!(
+            "li t0, 93", // syscall 93 = exit
+            "ecall",
+            in("a0") usize::from(code),
+            options(noreturn)
+        );
+    }
+
+    #[cfg(not(any(target_arch = "riscv32", target_arch = "riscv64")))]
+    {
+        let _ = code;
+        loop {
+            core::hint::spin_loop();
+        }
+    }
+}
@@ -0,0 +1,329 @@
+//! On-demand commit of reserved guest memory.
+//!
+//! [`GuardedMemory::reserve`](crate::GuardedMemory::reserve) maps the usable
+//! region `PROT_NONE` and registers it here. A process-wide `SIGSEGV` handler
+//! makes the faulting [`COMMIT_CHUNK_SIZE`] chunk of a registered region
+//! readable and writable and resumes the access, so pages are only charged
+//! against the commit limit once the guest touches them. Faults anywhere else
+//! (guard pages included) go to the handler that was installed before, so
+//! they still crash.
+//!
+//! Pages the owner mapped with a protection of its own (host buffers shared
+//! with the guest) are pinned: commits skip them, and faults on them crash
+//! like faults outside any region.
+//!
+//! Only Linux gets the handler; elsewhere [`Reservation::register`] returns
+//! `None` and memory is committed eagerly.
+
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicU64, AtomicUsize, Ordering};
+
+/// Granularity of commits made by the fault handler.
+pub const COMMIT_CHUNK_SIZE: usize = 1 << 16;
+
+/// Maximum number of reservations live at once.
+const MAX_RESERVATIONS: usize = 64;
+
+/// Registered region, readable from the signal handler without locks.
+struct Slot {
+    /// Set while a [`Reservation`] owns the slot.
+    claimed: AtomicBool,
+    /// Start of the region, 0 while unregistered.
+    base: AtomicUsize,
+    len: AtomicUsize,
+    page_size: AtomicUsize,
+    /// Committed-page bitmap of the owning reservation.
+    pages: AtomicPtr<AtomicU64>,
+    /// Pinned-page bitmap of the owning reservation.
+    pinned: AtomicPtr<AtomicU64>,
+}
+
+impl Slot {
+    const fn new() -> Self {
+        Self {
+            claimed: AtomicBool::new(false),
+            base: AtomicUsize::new(0),
+            len: AtomicUsize::new(0),
+            page_size: AtomicUsize::new(0),
+            pages: AtomicPtr::new(std::ptr::null_mut()),
+            pinned: AtomicPtr::new(std::ptr::null_mut()),
+        }
+    }
+}
+
+static SLOTS: [Slot; MAX_RESERVATIONS] = [const { Slot::new() }; MAX_RESERVATIONS];
+
+/// A region whose pages are committed on first touch.
+///
+/// Tracks which pages are readable and writable, one bit per host page.
+/// Whoever changes the protection of part of the region (remapping it
+/// `PROT_NONE`, mapping a file over it) updates the bitmap to match.
+pub struct Reservation {
+    slot: usize,
+    base: usize,
+    len: usize,
+    page_size: usize,
+    pages: Box<[AtomicU64]>,
+    pinned: Box<[AtomicU64]>,
+}
+
+impl Reservation {
+    /// Register the `PROT_NONE` region at `base` for on-demand commit.
+    ///
+    /// Returns `None` if the fault handler cannot be installed or too many
+    /// regions are registered; the caller then commits the region eagerly.
+    pub fn register(base: *mut u8, len: usize, page_size: usize) -> Option<Self> {
+        if !handler::install() {
+            return None;
+        }
+        let words = len.div_ceil(page_size).div_ceil(64);
+        let pages: Box<[AtomicU64]> = (0..words).map(|_| AtomicU64::new(0)).collect();
+        let pinned: Box<[AtomicU64]> = (0..words).map(|_| AtomicU64::new(0)).collect();
+        let slot = SLOTS.iter().position(|slot| {
+            slot.claimed
+                .compare_exchange(false, true, Ordering::AcqRel, Ordering::Relaxed)
+                .is_ok()
+        })?;
+        let entry = &SLOTS[slot];
+        entry.len.store(len, Ordering::Relaxed);
+        entry.page_size.store(page_size, Ordering::Relaxed);
+        entry
+            .pages
+            .store(pages.as_ptr().cast_mut(), Ordering::Relaxed);
+        entry
+            .pinned
+            .store(pinned.as_ptr().cast_mut(), Ordering::Relaxed);
+        // Publishes the fields above to the handler
+        entry.base.store(base as usize, Ordering::Release);
+        Some(Self {
+            slot,
+            base: base as usize,
+            len,
+            page_size,
+            pages,
+            pinned,
+        })
+    }
+
+    /// Make `len` bytes at `offset` readable and writable now.
+    ///
+    /// The range is widened to whole pages. Pinned pages are left as they
+    /// are.
+    pub fn commit(&self, offset: usize, len: usize) -> nix::Result<()> {
+        let start = offset - offset % self.page_size;
+        let end = offset.saturating_add(len).min(self.len);
+        if end <= start {
+            return Ok(());
+        }
+        if commit_unpinned(
+            self.base,
+            self.page_size,
+            &self.pages,
+            &self.pinned,
+            start..end,
+        ) {
+            Ok(())
+        } else {
+            Err(nix::Error::last())
+        }
+    }
+
+    /// Record the pages overlapping `len` bytes at `offset` as committed or not.
+    pub fn mark(&self, offset: usize, len: usize, committed: bool) {
+        mark_pages(&self.pages, self.page_size, offset, len, committed);
+    }
+
+    /// Record the pages overlapping `len` bytes at `offset` as pinned or not.
+    ///
+    /// Pinned pages are mapped by the owner with a protection of their own;
+    /// neither [`commit`](Self::commit) nor the fault handler touches them.
+    pub fn pin(&self, offset: usize, len: usize, pinned: bool) {
+        mark_pages(&self.pinned, self.page_size, offset, len, pinned);
+    }
+
+    /// Record every page as uncommitted and unpinned.
+    pub fn reset(&self) {
+        for word in self.pages.iter().chain(&*self.pinned) {
+            word.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Committed byte ranges, relative to the start of the region, in
+    /// address order with adjacent ranges merged.
+    pub fn committed_ranges(&self) -> Vec<Range<usize>> {
+        let num_pages = self.len.div_ceil(self.page_size);
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+        for page in 0..num_pages {
+            if self.pages[page / 64].load(Ordering::Relaxed) & (1 << (page % 64)) == 0 {
+                continue;
+            }
+            let start = page * self.page_size;
+            let end = (start + self.page_size).min(self.len);
+            match ranges.last_mut() {
+                Some(last) if last.end == start => last.end = end,
+                _ => ranges.push(start..end),
+            }
+        }
+        ranges
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        let entry = &SLOTS[self.slot];
+        entry.base.store(0, Ordering::Release);
+        entry.pages.store(std::ptr::null_mut(), Ordering::Relaxed);
+        entry.pinned.store(std::ptr::null_mut(), Ordering::Relaxed);
+        entry.claimed.store(false, Ordering::Release);
+    }
+}
+
+fn mark_pages(pages: &[AtomicU64], page_size: usize, offset: usize, len: usize, committed: bool) {
+    if len == 0 {
+        return;
+    }
+    let first = offset / page_size;
+    let last = (offset + len - 1) / page_size;
+    for page in first..=last {
+        let (word, bit) = (page / 64, 1u64 << (page % 64));
+        if committed {
+            pages[word].fetch_or(bit, Ordering::Relaxed);
+        } else {
+            pages[word].fetch_and(!bit, Ordering::Relaxed);
+        }
+    }
+}
+
+fn is_marked(pages: &[AtomicU64], page: usize) -> bool {
+    pages
+        .get(page / 64)
+        .is_some_and(|word| word.load(Ordering::Relaxed) & (1 << (page % 64)) != 0)
+}
+
+/// Make the unpinned pages of `range` (relative to `base`, starting on a
+/// page) readable and writable and record them as committed.
+///
+/// Called from the signal handler: only atomics and `mprotect`.
+fn commit_unpinned(
+    base: usize,
+    page_size: usize,
+    pages: &[AtomicU64],
+    pinned: &[AtomicU64],
+    range: Range<usize>,
+) -> bool {
+    let commit = |start: usize, end: usize| {
+        if end <= start {
+            return true;
+        }
+        let prot = nix::libc::PROT_READ | nix::libc::PROT_WRITE;
+        if unsafe { nix::libc::mprotect((base + start) as *mut _, end - start, prot) } != 0 {
+            return false;
+        }
+        if !pages.is_empty() {
+            mark_pages(pages, page_size, start, end - start, true);
+        }
+        true
+    };
+    let mut run_start = range.start;
+    for page_start in range.clone().step_by(page_size) {
+        if is_marked(pinned, page_start / page_size) {
+            if !commit(run_start, page_start) {
+                return false;
+            }
+            run_start = (page_start + page_size).min(range.end);
+        }
+    }
+    commit(run_start, range.end)
+}
+
+/// Commit the chunk containing `addr` if it lies in a registered region.
+///
+/// Called from the signal handler: only atomics and `mprotect`.
+#[cfg(target_os = "linux")]
+fn commit_fault(addr: usize) -> bool {
+    for entry in &SLOTS {
+        let base = entry.base.load(Ordering::Acquire);
+        if base == 0 || addr < base {
+            continue;
+        }
+        let len = entry.len.load(Ordering::Relaxed);
+        let offset = addr - base;
+        if offset >= len {
+            continue;
+        }
+        let page_size = entry.page_size.load(Ordering::Relaxed);
+        let words = len.div_ceil(page_size).div_ceil(64);
+        let bitmap = |ptr: *mut AtomicU64| {
+            if ptr.is_null() {
+                &[][..]
+            } else {
+                unsafe { std::slice::from_raw_parts(ptr.cast_const(), words) }
+            }
+        };
+        let pages = bitmap(entry.pages.load(Ordering::Relaxed));
+        let pinned = bitmap(entry.pinned.load(Ordering::Relaxed));
+        if is_marked(pinned, offset / page_size) {
+            return false;
+        }
+        let chunk = COMMIT_CHUNK_SIZE.max(page_size);
+        let start = offset - offset % chunk;
+        let end = start + chunk.min(len - start);
+        return commit_unpinned(base, page_size, pages, pinned, start..end);
+    }
+    false
+}
+
+#[cfg(target_os = "linux")]
+mod handler {
+    use std::ffi::c_void;
+    use std::sync::OnceLock;
+
+    use nix::libc;
+    use nix::sys::signal::{SaFlags, SigAction, SigHandler, SigSet, Signal, sigaction};
+
+    /// Action that was installed for `SIGSEGV` before ours.
+    static PREVIOUS: OnceLock<SigAction> = OnceLock::new();
+    static INSTALLED: OnceLock<bool> = OnceLock::new();
+
+    /// Install the fault handler once per process; `false` if that failed.
+    pub fn install() -> bool {
+        *INSTALLED.get_or_init(|| {
+            let action = SigAction::new(
+                SigHandler::SigAction(handle_fault),
+                SaFlags::SA_SIGINFO | SaFlags::SA_ONSTACK,
+                SigSet::empty(),
+            );
+            unsafe { sigaction(Signal::SIGSEGV, &action) }
+                .is_ok_and(|previous| PREVIOUS.set(previous).is_ok())
+        })
+    }
+
+    extern "C" fn handle_fault(
+        signal: libc::c_int,
+        info: *mut libc::siginfo_t,
+        context: *mut c_void,
+    ) {
+        let addr = unsafe { (*info).si_addr() } as usize;
+        if super::commit_fault(addr) {
+            return;
+        }
+        match PREVIOUS.get().map(SigAction::handler) {
+            Some(SigHandler::SigAction(previous)) => previous(signal, info, context),
+            Some(SigHandler::Handler(previous)) => previous(signal),
+            // Put the default (or ignore) action back; the access faults again
+            // on return and the kernel delivers it.
+            _ => {
+                let default = SigAction::new(SigHandler::SigDfl, SaFlags::empty(), SigSet::empty());
+                let previous = PREVIOUS.get().unwrap_or(&default);
+                let _ = unsafe { sigaction(Signal::SIGSEGV, previous) };
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod handler {
+    pub const fn install() -> bool {
+        false
+    }
+}
@@ -0,0 +1,182 @@
+//! Host I/O hooks for guest stdio and custom CSRs.
+//!
+//! The generated `rv_sys_read`/`rv_sys_write` call through `RvState::io` when
+//! it is non-null, and fall back to the host's stdio otherwise. Custom CSRs
+//! compiled in hook mode call `csr_read`/`csr_write` when set, and use their
+//! `RvState::csrs` slot otherwise. Linux syscalls whose number is listed in
+//! `syscall_nums` go to `syscall` instead of the built-in handling.
+//! `open`/`close`/`seek`/`stat` are set when the host preopened
+//! directories; the generated `rv_sys_openat` hands every guest path to
+//! `open` for the host to check, and guest fds above 2 then go through
+//! `read`/`write` (and `readv`/`writev`, one call per iovec) too.
+//! Guest functions hooked with `HookKind::HostCall` call `hook` by name.
+
+use std::ffi::{c_char, c_void};
+
+/// Reads up to `len` bytes for guest fd `fd` into `buf`.
+///
+/// Returns the number of bytes read (0 at EOF) or a negative errno.
+pub type GuestReadFn =
+    unsafe extern "C" fn(ctx: *mut c_void, fd: u32, buf: *mut u8, len: usize) -> i64;
+
+/// Writes `len` bytes from `buf` to guest fd `fd`.
+///
+/// Returns the number of bytes written or a negative errno.
+pub type GuestWriteFn =
+    unsafe extern "C" fn(ctx: *mut c_void, fd: u32, buf: *const u8, len: usize) -> i64;
+
+/// Returns the value the guest reads from hook CSR `csr`.
+pub type GuestCsrReadFn = unsafe extern "C" fn(ctx: *mut c_void, csr: u32) -> u64;
+
+/// Called when the guest writes `value` to hook CSR `csr`.
+pub type GuestCsrWriteFn = unsafe extern "C" fn(ctx: *mut c_void, csr: u32, value: u64);
+
+/// Handles host syscall `num` with arguments `args` (a0..a5, zero-extended).
+///
+/// `memory` is guest memory (`memory_size` bytes, indexed by guest address).
+/// Returns the value for a0.
+pub type GuestSyscallFn = unsafe extern "C" fn(
+    ctx: *mut c_void,
+    num: u64,
+    args: *const u64,
+    memory: *mut u8,
+    memory_size: u64,
+) -> i64;
+
+/// Opens NUL-terminated guest `path` relative to guest fd `dirfd`.
+///
+/// The host checks the path against its preopened directories first.
+/// Returns the new guest fd or a negative errno.
+pub type GuestOpenFn = unsafe extern "C" fn(
+    ctx: *mut c_void,
+    dirfd: i32,
+    path: *const c_char,
+    flags: u64,
+    mode: u64,
+) -> i64;
+
+/// Closes guest fd `fd`; returns 0 or a negative errno.
+pub type GuestCloseFn = unsafe extern "C" fn(ctx: *mut c_void, fd: u32) -> i64;
+
+/// Moves the offset of guest fd `fd` as `lseek` with `whence` does.
+///
+/// Returns the new offset or a negative errno.
+pub type GuestSeekFn =
+    unsafe extern "C" fn(ctx: *mut c_void, fd: u32, offset: i64, whence: u32) -> i64;
+
+/// Fills `out` for guest fd `dirfd` (`path` null), or for NUL-terminated
+/// `path` relative to it, as `fstatat` with `flags` does.
+///
+/// The host checks the path against its preopened directories first.
+/// Returns 0 or a negative errno.
+pub type GuestStatFn = unsafe extern "C" fn(
+    ctx: *mut c_void,
+    dirfd: i32,
+    path: *const c_char,
+    flags: u32,
+    out: *mut GuestStat,
+) -> i64;
+
+/// File metadata the host reports for guest `fstat`/`fstatat`.
+///
+/// The generated code writes it into the guest's `struct stat`; every
+/// field the host does not report (ids, link count, times) reads as 0.
+///
+/// Matches C struct:
+/// ```c
+/// typedef struct RvStat {
+///     uint64_t size;
+///     uint32_t mode;
+///     uint32_t blksize;
+///     uint64_t blocks;
+/// } RvStat;
+/// ```
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GuestStat {
+    /// Size in bytes.
+    pub size: u64,
+    /// File type and permission bits (`st_mode`).
+    pub mode: u32,
+    /// Preferred I/O block size (`st_blksize`).
+    pub blksize: u32,
+    /// Allocated 512-byte blocks (`st_blocks`).
+    pub blocks: u64,
+}
+
+/// Runs the host hook for guest function `name` (NUL-terminated).
+///
+/// `args` holds a0..a5 (zero-extended); the hook may change them and the
+/// guest gets them back. `memory` is guest memory as for [`GuestSyscallFn`].
+/// Returns 0, or nonzero if no hook serves `name`.
+pub type GuestHookFn = unsafe extern "C" fn(
+    ctx: *mut c_void,
+    name: *const c_char,
+    args: *mut u64,
+    memory: *mut u8,
+    memory_size: u64,
+) -> i32;
+
+/// Host hook table.
+///
+/// Matches C struct:
+/// ```c
+/// typedef struct RvIo {
+///     void* ctx;
+///     int64_t (*read)(void* ctx, uint32_t fd, uint8_t* buf, size_t len);
+///     int64_t (*write)(void* ctx, uint32_t fd, const uint8_t* buf, size_t len);
+///     void* csr_ctx;
+///     uint64_t (*csr_read)(void* ctx, uint32_t csr);
+///     void (*csr_write)(void* ctx, uint32_t csr, uint64_t value);
+///     void* syscall_ctx;
+///     const uint64_t* syscall_nums;
+///     uint64_t syscall_count;
+///     int64_t (*syscall)(void* ctx, uint64_t num, const uint64_t* args,
+///                        uint8_t* memory, uint64_t memory_size);
+///     int64_t (*open)(void* ctx, int32_t dirfd, const char* path, uint64_t flags,
+///                     uint64_t mode);
+///     int64_t (*close)(void* ctx, uint32_t fd);
+///     void* hook_ctx;
+///     int32_t (*hook)(void* ctx, const char* name, uint64_t* args, uint8_t* memory,
+///                     uint64_t memory_size);
+///     int64_t (*seek)(void* ctx, uint32_t fd, int64_t offset, uint32_t whence);
+///     int64_t (*stat)(void* ctx, int32_t dirfd, const char* path, uint32_t flags,
+///                     RvStat* out);
+/// } RvIo;
+/// ```
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct GuestIo {
+    /// Opaque context passed to both hooks.
+    pub ctx: *mut c_void,
+    /// Called for guest reads from fd 0 (and opened files).
+    pub read: GuestReadFn,
+    /// Called for guest writes to fd 1 and 2 (and opened files).
+    pub write: GuestWriteFn,
+    /// Opaque context passed to the CSR hooks.
+    pub csr_ctx: *mut c_void,
+    /// Called for guest reads of hook CSRs.
+    pub csr_read: Option<GuestCsrReadFn>,
+    /// Called for guest writes to hook CSRs.
+    pub csr_write: Option<GuestCsrWriteFn>,
+    /// Opaque context passed to the syscall hook.
+    pub syscall_ctx: *mut c_void,
+    /// Syscall numbers served by `syscall`, sorted ascending.
+    pub syscall_nums: *const u64,
+    /// Length of `syscall_nums`.
+    pub syscall_count: u64,
+    /// Called for guest syscalls listed in `syscall_nums`.
+    pub syscall: Option<GuestSyscallFn>,
+    /// Called for guest `openat` (with `ctx`); unset without preopens.
+    pub open: Option<GuestOpenFn>,
+    /// Called for guest `close` of fds above 2 (with `ctx`).
+    pub close: Option<GuestCloseFn>,
+    /// Opaque context passed to the function hook.
+    pub hook_ctx: *mut c_void,
+    /// Called at the entry of guest functions hooked with `HookKind::HostCall`.
+    pub hook: Option<GuestHookFn>,
+    /// Called for guest `lseek` of fds above 2 (with `ctx`).
+    pub seek: Option<GuestSeekFn>,
+    /// Called for guest `fstat`/`fstatat` (with `ctx`); unset without preopens.
+    pub stat: Option<GuestStatFn>,
+}
@@ -24,29 +24,46 @@
 //! let state = Rv64StateWith::<PreflightTracer<Rv64>>::new();
 //! ```
 
+mod commit;
+mod io;
 mod memory;
+mod mmap;
 mod state;
 mod suspender;
 mod tracer;
 
-pub use memory::{DEFAULT_MEMORY_SIZE, FixedMemory, GUARD_SIZE, GuardedMemory, MemoryError};
+pub use io::{
+    GuestCloseFn, GuestCsrReadFn, GuestCsrWriteFn, GuestHookFn, GuestIo, GuestOpenFn, GuestReadFn,
+    GuestSeekFn, GuestStat, GuestStatFn, GuestSyscallFn, GuestWriteFn,
+};
+pub use memory::{
+    DEFAULT_MEMORY_SIZE, FixedMemory, GUARD_SIZE, GuardedMemory, HostBuffer, MemoryError,
+    MemorySnapshot, page_size,
+};
+pub use mmap::{MMAP_MAX_REGIONS, MmapRegions};
 pub use state::{
-    ExecutionStatus, NUM_CSRS, NUM_REGS_E, NUM_REGS_I, Rv32EState, Rv32State, Rv32StateWith,
-    Rv64EState, Rv64State, Rv64StateWith, RvState,
+    ExecutionStatus, MAX_VLEN, NUM_CSRS, NUM_REGS_E, NUM_REGS_I, Rv32EState, Rv32State,
+    Rv32StateWith, Rv64EState, Rv64State, Rv64StateWith, RvState, StateSnapshot, VREGS_BYTES,
 };
 pub use suspender::{InstretSuspender, SuspenderState};
 // TODO: avoid reexports - add to agents.md
+/// `no_std` tracer record layouts, shared with guest-side tooling.
+pub use rvr_trace_format as trace_format;
 pub use tracer::{
+    BlockProfileTracer,
     BufferedDiffIterator,
     BufferedDiffTracer,
     CountingTracer,
+    CoverageTracer,
     DebugTracer,
     DiffEntry,
     DiffTracer,
     DynamicTracer,
     FfiTracer,
     FfiTracerPtr,
+    GoldenTracer,
     NoopTracer,
+    PageAccessTracer,
     PreflightTracer,
     StatsTracer,
     // Behavior trait and implementations
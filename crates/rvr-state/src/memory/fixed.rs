@@ -0,0 +1,77 @@
+use nix::sys::mman::{MapFlags, ProtFlags, mmap_anonymous, munmap};
+use std::ffi::c_void;
+use std::ptr::NonNull;
+
+use super::{MemoryError, map_fixed_flags};
+
+// TODO: why both fixed memory and new_at_fixed
+// GuardedMemory and FixedMemory intentionally coexist:
+// - GuardedMemory provides guard pages for OOB detection in normal runtime use.
+// - FixedMemory maps at explicit addresses for fixed-address execution mode.
+/// Fixed-address memory region (without guard pages).
+///
+/// Used for allocating state at a specific address for the fixed-addresses feature.
+pub struct FixedMemory {
+    addr: NonNull<c_void>,
+    size: usize,
+}
+
+impl FixedMemory {
+    /// Allocate memory at a specific fixed address.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if mmap fails or the fixed address is unavailable.
+    pub fn new(fixed_addr: u64, size: usize) -> Result<Self, MemoryError> {
+        use nix::errno::Errno;
+        use std::num::NonZeroUsize;
+
+        if size == 0 {
+            return Err(MemoryError::InvalidSize(size));
+        }
+
+        let addr = usize::try_from(fixed_addr).map_err(|_| MemoryError::InvalidSize(size))?;
+        let addr_nz = NonZeroUsize::new(addr).ok_or(MemoryError::InvalidSize(size))?;
+        let size_nz = NonZeroUsize::new(size).ok_or(MemoryError::InvalidSize(size))?;
+
+        let region = unsafe {
+            mmap_anonymous(
+                Some(addr_nz),
+                size_nz,
+                ProtFlags::PROT_READ | ProtFlags::PROT_WRITE,
+                MapFlags::MAP_PRIVATE | map_fixed_flags(),
+            )
+            .map_err(|e| {
+                if e == Errno::EEXIST {
+                    MemoryError::FixedAddressUnavailable(fixed_addr)
+                } else {
+                    MemoryError::MmapFailed(e)
+                }
+            })?
+        };
+
+        Ok(Self { addr: region, size })
+    }
+
+    /// Returns pointer to the memory region.
+    #[must_use]
+    pub const fn as_ptr(&self) -> *mut u8 {
+        self.addr.as_ptr().cast::<u8>()
+    }
+
+    /// Returns the size of the memory region.
+    #[must_use]
+    pub const fn size(&self) -> usize {
+        self.size
+    }
+}
+
+impl Drop for FixedMemory {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = munmap(self.addr, self.size);
+        }
+    }
+}
+
+unsafe impl Send for FixedMemory {}
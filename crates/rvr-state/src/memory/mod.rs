@@ -0,0 +1,608 @@
+//! Guarded memory allocation with mmap.
+//!
+//! Provides a memory region with guard pages on each side to catch
+//! buffer overflows/underflows at the OS level.
+//!
+//! Guarded memory can also be frozen into a [`MemorySnapshot`]. The usable
+//! region is then mapped copy-on-write over the snapshot file, so restoring
+//! only has to discard the pages the guest dirtied since.
+//!
+//! Sub-ranges of the usable region can likewise be mapped copy-on-write over
+//! a file ([`GuardedMemory::map_file`]), so large initialized data is paged in
+//! on first touch instead of copied up front.
+//!
+//! A [`HostBuffer`] can be mapped shared into the usable region
+//! ([`GuardedMemory::map_shared`]), so the guest reads host data in place
+//! instead of the host copying it in.
+//!
+//! [`GuardedMemory::reserve`] only reserves the usable region and commits
+//! pages as the guest touches them from a `SIGSEGV` handler, so a 4GB guest
+//! address space costs nothing until it is used, even with overcommit
+//! disabled.
+
+use nix::sys::mman::{MapFlags, ProtFlags, mmap, mmap_anonymous, mprotect, munmap};
+use nix::unistd::{SysconfVar, sysconf};
+use std::ffi::c_void;
+use std::fs::File;
+use std::num::NonZeroUsize;
+use std::ops::Range;
+use std::ptr::NonNull;
+use thiserror::Error;
+
+use crate::commit::Reservation;
+
+mod fixed;
+mod shared;
+mod snapshot;
+#[cfg(test)]
+mod tests;
+
+pub use fixed::FixedMemory;
+pub use shared::HostBuffer;
+pub use snapshot::MemorySnapshot;
+
+use shared::SharedMapping;
+
+/// Guard page size (16KB, must be >= page size and cover max load/store offset).
+pub const GUARD_SIZE: usize = 1 << 14;
+
+/// Get flags for fixed-address mmap that fails if address is already mapped.
+///
+/// Uses `MAP_FIXED_NOREPLACE` on Linux (safer - returns EEXIST if address is taken).
+/// Falls back to `MAP_FIXED` on macOS/BSD (will unmap existing mappings).
+#[cfg(target_os = "linux")]
+const fn map_fixed_flags() -> MapFlags {
+    MapFlags::MAP_FIXED_NOREPLACE
+}
+
+#[cfg(not(target_os = "linux"))]
+fn map_fixed_flags() -> MapFlags {
+    MapFlags::MAP_FIXED
+}
+
+/// Default memory size (4GB).
+pub const DEFAULT_MEMORY_SIZE: usize = 1 << 32;
+
+/// Fallback when the host page size cannot be queried.
+const DEFAULT_PAGE_SIZE: usize = 1 << 12;
+
+/// Host page size: the granularity of [`GuardedMemory::map_file`].
+#[must_use]
+pub fn page_size() -> usize {
+    sysconf(SysconfVar::PAGE_SIZE)
+        .ok()
+        .flatten()
+        .and_then(|size| usize::try_from(size).ok())
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+}
+
+/// Memory allocation error.
+#[derive(Debug, Error)]
+pub enum MemoryError {
+    #[error("mmap failed: {0}")]
+    MmapFailed(#[from] nix::Error),
+
+    #[error("invalid memory size: {0}")]
+    InvalidSize(usize),
+
+    #[error("fixed address {0:#x} is not available (already mapped or reserved)")]
+    FixedAddressUnavailable(u64),
+
+    #[error("snapshot I/O failed: {0}")]
+    SnapshotIo(#[from] std::io::Error),
+
+    #[error("snapshot size {snapshot} does not match memory size {memory}")]
+    SnapshotSizeMismatch { snapshot: usize, memory: usize },
+
+    #[error(
+        "file mapping at memory offset {offset:#x} (file offset {file_offset:#x}, {len:#x} bytes) is not aligned to the {page_size:#x}-byte page size"
+    )]
+    UnalignedMapping {
+        offset: usize,
+        file_offset: u64,
+        len: usize,
+        page_size: usize,
+    },
+
+    #[error("file mapping at {offset:#x} ({len:#x} bytes) is outside memory of size {memory:#x}")]
+    MappingOutOfRange {
+        offset: usize,
+        len: usize,
+        memory: usize,
+    },
+
+    #[error("commit at {offset:#x} ({len:#x} bytes) is outside memory of size {memory:#x}")]
+    CommitOutOfRange {
+        offset: usize,
+        len: usize,
+        memory: usize,
+    },
+
+    #[error("shared mapping at {offset:#x} ({len:#x} bytes) overlaps the one at {existing:#x}")]
+    SharedMappingOverlap {
+        offset: usize,
+        len: usize,
+        existing: usize,
+    },
+
+    #[error("no shared mapping at {0:#x}")]
+    NoSharedMapping(usize),
+}
+
+/// Memory region with guard pages.
+///
+/// Allocates `[GUARD][MEMORY][GUARD]` with the guard pages protected as `PROT_NONE`.
+/// Any access to guard pages will cause a segfault, catching buffer overflows.
+///
+/// Memory from [`new`](Self::new) is committed up front; memory from
+/// [`reserve`](Self::reserve) is committed on first touch.
+pub struct GuardedMemory {
+    /// Pointer to the start of the entire region (including first guard).
+    region: NonNull<c_void>,
+    /// Total size including both guard pages.
+    total_size: usize,
+    /// Size of the usable memory region.
+    memory_size: usize,
+    /// Committed pages of the usable region, if it is committed on demand.
+    reservation: Option<Reservation>,
+    /// Host buffers mapped over the usable region, by offset.
+    shared: Vec<SharedMapping>,
+}
+
+impl GuardedMemory {
+    /// Allocate a new guarded memory region.
+    ///
+    /// # Arguments
+    ///
+    /// * `memory_size` - Size of the usable memory region in bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if mmap fails.
+    pub fn new(memory_size: usize) -> Result<Self, MemoryError> {
+        let memory = Self::map_region(memory_size)?;
+        memory.protect_usable(ProtFlags::PROT_READ | ProtFlags::PROT_WRITE)?;
+        Ok(memory)
+    }
+
+    /// Reserve a guarded memory region whose pages are committed on first
+    /// touch.
+    ///
+    /// Only pages the guest (or the host, through [`as_ptr`](Self::as_ptr))
+    /// touches count against the commit limit and the resident set. If the
+    /// fault handler that commits them cannot be installed, the region is
+    /// committed up front as with [`new`](Self::new).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if mmap fails.
+    pub fn reserve(memory_size: usize) -> Result<Self, MemoryError> {
+        let mut memory = Self::map_region(memory_size)?;
+        memory.reservation = Reservation::register(memory.as_ptr(), memory_size, page_size());
+        if memory.reservation.is_none() {
+            memory.protect_usable(ProtFlags::PROT_READ | ProtFlags::PROT_WRITE)?;
+        }
+        Ok(memory)
+    }
+
+    /// Map `[GUARD][MEMORY][GUARD]` entirely `PROT_NONE`.
+    fn map_region(memory_size: usize) -> Result<Self, MemoryError> {
+        if memory_size == 0 {
+            return Err(MemoryError::InvalidSize(memory_size));
+        }
+
+        let total_size = memory_size
+            .checked_add(2 * GUARD_SIZE)
+            .ok_or(MemoryError::InvalidSize(memory_size))?;
+        let total_size_nz =
+            NonZeroUsize::new(total_size).ok_or(MemoryError::InvalidSize(memory_size))?;
+
+        // Allocate entire region as PROT_NONE
+        let region = unsafe {
+            mmap_anonymous(
+                None,
+                total_size_nz,
+                ProtFlags::PROT_NONE,
+                MapFlags::MAP_PRIVATE | MapFlags::MAP_NORESERVE,
+            )?
+        };
+
+        Ok(Self {
+            region,
+            total_size,
+            memory_size,
+            reservation: None,
+            shared: Vec::new(),
+        })
+    }
+
+    /// Set the protection of the whole usable region.
+    fn protect_usable(&self, prot: ProtFlags) -> Result<(), MemoryError> {
+        let memory_start = unsafe { NonNull::new_unchecked(self.as_ptr().cast::<c_void>()) };
+        unsafe { mprotect(memory_start, self.memory_size, prot)? };
+        Ok(())
+    }
+
+    /// Create with default memory size (4GB).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if memory allocation fails.
+    pub fn with_default_size() -> Result<Self, MemoryError> {
+        Self::new(DEFAULT_MEMORY_SIZE)
+    }
+
+    /// Allocate memory at a specific fixed address.
+    ///
+    /// Uses `MAP_FIXED_NOREPLACE` to ensure the address is available.
+    /// The usable memory starts at `fixed_addr`, with a guard page before it.
+    ///
+    /// # Arguments
+    ///
+    /// * `fixed_addr` - The address where the usable memory should start.
+    /// * `memory_size` - Size of the usable memory region in bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if mmap fails or the address is already in use.
+    pub fn new_at_fixed(fixed_addr: u64, memory_size: usize) -> Result<Self, MemoryError> {
+        use nix::errno::Errno;
+        use std::num::NonZeroUsize;
+
+        if memory_size == 0 {
+            return Err(MemoryError::InvalidSize(memory_size));
+        }
+
+        let total_size = memory_size
+            .checked_add(2 * GUARD_SIZE)
+            .ok_or(MemoryError::InvalidSize(memory_size))?;
+        let total_size_nz =
+            NonZeroUsize::new(total_size).ok_or(MemoryError::InvalidSize(memory_size))?;
+
+        // Region starts at (fixed_addr - GUARD_SIZE) to place usable memory at fixed_addr
+        let region_start = usize::try_from(fixed_addr.saturating_sub(GUARD_SIZE as u64))
+            .map_err(|_| MemoryError::InvalidSize(memory_size))?;
+
+        // Use MAP_FIXED_NOREPLACE to fail if address is already mapped
+        // This is safer than MAP_FIXED which would silently unmap existing mappings
+        let region = unsafe {
+            mmap_anonymous(
+                Some(NonZeroUsize::new(region_start).ok_or(MemoryError::InvalidSize(memory_size))?),
+                total_size_nz,
+                ProtFlags::PROT_NONE,
+                MapFlags::MAP_PRIVATE | MapFlags::MAP_NORESERVE | map_fixed_flags(),
+            )
+            .map_err(|e| {
+                if e == Errno::EEXIST {
+                    MemoryError::FixedAddressUnavailable(fixed_addr)
+                } else {
+                    MemoryError::MmapFailed(e)
+                }
+            })?
+        };
+
+        // Make middle portion readable/writable
+        let memory_start = unsafe {
+            NonNull::new_unchecked(
+                region
+                    .as_ptr()
+                    .cast::<u8>()
+                    .add(GUARD_SIZE)
+                    .cast::<c_void>(),
+            )
+        };
+        unsafe {
+            mprotect(
+                memory_start,
+                memory_size,
+                ProtFlags::PROT_READ | ProtFlags::PROT_WRITE,
+            )?;
+        }
+
+        Ok(Self {
+            region,
+            total_size,
+            memory_size,
+            reservation: None,
+            shared: Vec::new(),
+        })
+    }
+
+    /// Returns pointer to usable memory (after first guard page).
+    #[must_use]
+    pub const fn as_ptr(&self) -> *mut u8 {
+        unsafe { self.region.as_ptr().cast::<u8>().add(GUARD_SIZE) }
+    }
+
+    /// Returns the size of the usable memory region.
+    #[must_use]
+    pub const fn size(&self) -> usize {
+        self.memory_size
+    }
+
+    /// Whether pages are committed on first touch rather than up front.
+    #[must_use]
+    pub const fn commits_on_demand(&self) -> bool {
+        self.reservation.is_some()
+    }
+
+    /// Commit `len` bytes at `offset` now rather than on first touch.
+    ///
+    /// Saves the faults for ranges that are known to be used, such as loaded
+    /// segments. Does nothing for memory committed up front.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the range is outside the usable region or the
+    /// `mprotect` fails.
+    pub fn commit(&self, offset: usize, len: usize) -> Result<(), MemoryError> {
+        if offset
+            .checked_add(len)
+            .is_none_or(|end| end > self.memory_size)
+        {
+            return Err(MemoryError::CommitOutOfRange {
+                offset,
+                len,
+                memory: self.memory_size,
+            });
+        }
+        if let Some(reservation) = &self.reservation {
+            reservation.commit(offset, len)?;
+        }
+        Ok(())
+    }
+
+    /// Committed byte ranges of the usable region, as offsets from
+    /// [`as_ptr`](Self::as_ptr).
+    ///
+    /// Memory committed up front is a single range.
+    #[must_use]
+    pub fn committed_ranges(&self) -> Vec<Range<usize>> {
+        self.reservation.as_ref().map_or_else(
+            || std::iter::once(0..self.memory_size).collect(),
+            Reservation::committed_ranges,
+        )
+    }
+
+    /// Bytes of the usable region currently resident in host memory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `mincore` fails.
+    pub fn resident_bytes(&self) -> Result<usize, MemoryError> {
+        let page_size = page_size();
+        let mut resident = 0;
+        for range in self.committed_ranges() {
+            let mut pages = vec![0u8; range.len().div_ceil(page_size)];
+            let ret = unsafe {
+                nix::libc::mincore(
+                    self.as_ptr().add(range.start).cast(),
+                    range.len(),
+                    pages.as_mut_ptr().cast(),
+                )
+            };
+            if ret != 0 {
+                return Err(MemoryError::MmapFailed(nix::Error::last()));
+            }
+            let last = range.len() - (pages.len() - 1) * page_size;
+            for (idx, &page) in pages.iter().enumerate() {
+                if page & 1 != 0 {
+                    resident += if idx + 1 == pages.len() {
+                        last
+                    } else {
+                        page_size
+                    };
+                }
+            }
+        }
+        Ok(resident)
+    }
+
+    /// Zero the entire memory region.
+    ///
+    /// Memory committed on demand, or with host buffers mapped over it, is
+    /// discarded instead of written.
+    pub fn clear(&mut self) {
+        if (self.reservation.is_some() || !self.shared.is_empty()) && self.discard().is_ok() {
+            return;
+        }
+        unsafe {
+            std::ptr::write_bytes(self.as_ptr(), 0, self.memory_size);
+        }
+    }
+
+    /// Protection of freshly mapped pages: none when they are committed on
+    /// demand.
+    const fn fresh_prot(&self) -> ProtFlags {
+        if self.reservation.is_some() {
+            ProtFlags::PROT_NONE
+        } else {
+            ProtFlags::PROT_READ.union(ProtFlags::PROT_WRITE)
+        }
+    }
+
+    /// Replace the usable region with fresh zero pages.
+    ///
+    /// Unlike [`clear`](Self::clear) this does not touch every page, and it
+    /// also drops file mappings made by [`map_file`](Self::map_file) or a
+    /// snapshot. Host buffers from [`map_shared`](Self::map_shared) are mapped
+    /// again. The base address does not change. Memory committed on demand is
+    /// decommitted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the remap fails.
+    pub fn discard(&mut self) -> Result<(), MemoryError> {
+        let addr = NonZeroUsize::new(self.as_ptr() as usize)
+            .ok_or(MemoryError::InvalidSize(self.memory_size))?;
+        let len = NonZeroUsize::new(self.memory_size)
+            .ok_or(MemoryError::InvalidSize(self.memory_size))?;
+
+        // MAP_FIXED deliberately replaces our own mapping; guard pages are untouched.
+        unsafe {
+            mmap_anonymous(
+                Some(addr),
+                len,
+                self.fresh_prot(),
+                MapFlags::MAP_PRIVATE | MapFlags::MAP_FIXED | MapFlags::MAP_NORESERVE,
+            )?;
+        }
+        if let Some(reservation) = &self.reservation {
+            reservation.reset();
+        }
+        self.remap_shared()
+    }
+
+    /// Map `len` bytes of `file` at `file_offset` copy-on-write over memory
+    /// at `offset`.
+    ///
+    /// Pages are read from the file on first touch; guest writes land in
+    /// private pages and never reach the file. The rest of the region is left
+    /// as is, and [`discard`](Self::discard) or a restore replaces the
+    /// mapping again.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `offset`, `file_offset` or `len` is not a multiple
+    /// of [`page_size`], if the range is outside the usable region, or if the
+    /// mmap fails.
+    pub fn map_file(
+        &mut self,
+        offset: usize,
+        len: usize,
+        file: &File,
+        file_offset: u64,
+    ) -> Result<(), MemoryError> {
+        let page_size = page_size();
+        if !offset.is_multiple_of(page_size)
+            || !len.is_multiple_of(page_size)
+            || !file_offset.is_multiple_of(page_size as u64)
+        {
+            return Err(MemoryError::UnalignedMapping {
+                offset,
+                file_offset,
+                len,
+                page_size,
+            });
+        }
+        let out_of_range = MemoryError::MappingOutOfRange {
+            offset,
+            len,
+            memory: self.memory_size,
+        };
+        if offset
+            .checked_add(len)
+            .is_none_or(|end| end > self.memory_size)
+        {
+            return Err(out_of_range);
+        }
+        let Some(len) = NonZeroUsize::new(len) else {
+            return Ok(());
+        };
+        let addr = NonZeroUsize::new(self.as_ptr() as usize + offset).ok_or(out_of_range)?;
+        let file_offset =
+            i64::try_from(file_offset).map_err(|_| MemoryError::UnalignedMapping {
+                offset,
+                file_offset,
+                len: len.get(),
+                page_size,
+            })?;
+
+        // MAP_FIXED replaces only the carved-out pages of our own mapping.
+        unsafe {
+            mmap(
+                Some(addr),
+                len,
+                self.fresh_prot(),
+                MapFlags::MAP_PRIVATE | MapFlags::MAP_FIXED | MapFlags::MAP_NORESERVE,
+                file,
+                file_offset,
+            )?;
+        }
+        if let Some(reservation) = &self.reservation {
+            reservation.mark(offset, len.get(), false);
+        }
+        Ok(())
+    }
+
+    /// Copy data into memory at the given offset.
+    ///
+    /// # Safety
+    ///
+    /// Caller must ensure `offset + data.len() <= self.size()`.
+    pub unsafe fn copy_from(&mut self, offset: usize, data: &[u8]) {
+        debug_assert!(offset + data.len() <= self.memory_size);
+        unsafe {
+            std::ptr::copy_nonoverlapping(data.as_ptr(), self.as_ptr().add(offset), data.len());
+        }
+    }
+
+    /// Read a byte from memory.
+    ///
+    /// # Safety
+    ///
+    /// Caller must ensure `offset < self.size()`.
+    #[must_use]
+    pub unsafe fn read_u8(&self, offset: usize) -> u8 {
+        debug_assert!(offset < self.memory_size);
+        unsafe { *self.as_ptr().add(offset) }
+    }
+
+    /// Write a byte to memory.
+    ///
+    /// # Safety
+    ///
+    /// Caller must ensure `offset < self.size()`.
+    pub unsafe fn write_u8(&mut self, offset: usize, value: u8) {
+        debug_assert!(offset < self.memory_size);
+        unsafe { *self.as_ptr().add(offset) = value };
+    }
+}
+
+/// Create an anonymous file to hold snapshot contents or a host buffer.
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+fn anonymous_file() -> Result<File, MemoryError> {
+    use nix::sys::memfd::{MemFdCreateFlag, memfd_create};
+
+    let fd = memfd_create(c"rvr-snapshot", MemFdCreateFlag::MFD_CLOEXEC)?;
+    Ok(File::from(fd))
+}
+
+/// Create an anonymous file to hold snapshot contents or a host buffer.
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "freebsd")))]
+fn anonymous_file() -> Result<File, MemoryError> {
+    use nix::fcntl::OFlag;
+    use nix::sys::mman::{shm_open, shm_unlink};
+    use nix::sys::stat::Mode;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+    // POSIX shm names are global; unlink right away so only the fd remains.
+    let name = format!(
+        "/rvr-snapshot-{}-{}",
+        std::process::id(),
+        NEXT_ID.fetch_add(1, Ordering::Relaxed)
+    );
+    let fd = shm_open(
+        name.as_str(),
+        OFlag::O_RDWR | OFlag::O_CREAT | OFlag::O_EXCL,
+        Mode::S_IRUSR | Mode::S_IWUSR,
+    )?;
+    shm_unlink(name.as_str())?;
+    Ok(File::from(fd))
+}
+
+impl Drop for GuardedMemory {
+    fn drop(&mut self) {
+        // Unregister first so the fault handler never sees the address range
+        // after it is unmapped.
+        drop(self.reservation.take());
+        unsafe {
+            let _ = munmap(self.region, self.total_size);
+        }
+    }
+}
+
+// GuardedMemory is Send but not Sync (contains raw pointer)
+unsafe impl Send for GuardedMemory {}
@@ -0,0 +1,262 @@
+use nix::sys::mman::{MapFlags, ProtFlags, mmap, mmap_anonymous, munmap};
+use std::ffi::c_void;
+use std::fs::File;
+use std::num::NonZeroUsize;
+use std::ptr::NonNull;
+use std::sync::Arc;
+
+use super::{GuardedMemory, MemoryError, anonymous_file, page_size};
+
+/// Host buffer mapped over part of the usable region.
+pub struct SharedMapping {
+    offset: usize,
+    len: usize,
+    file: Arc<File>,
+    writable: bool,
+}
+
+/// Host memory that can be mapped into guest memory
+/// ([`GuardedMemory::map_shared`]).
+///
+/// Backed by an anonymous file, so the same pages can be mapped a second time
+/// at a guest address: the host fills the buffer in place and the guest reads
+/// it without a copy.
+pub struct HostBuffer {
+    file: Arc<File>,
+    ptr: NonNull<c_void>,
+    len: usize,
+}
+
+impl HostBuffer {
+    /// Allocate a zeroed buffer of `len` bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `len` is zero or the buffer cannot be created.
+    pub fn new(len: usize) -> Result<Self, MemoryError> {
+        let size = NonZeroUsize::new(len).ok_or(MemoryError::InvalidSize(len))?;
+        let file = anonymous_file()?;
+        file.set_len(len as u64)?;
+        let ptr = unsafe {
+            mmap(
+                None,
+                size,
+                ProtFlags::PROT_READ | ProtFlags::PROT_WRITE,
+                MapFlags::MAP_SHARED,
+                &file,
+                0,
+            )?
+        };
+        Ok(Self {
+            file: Arc::new(file),
+            ptr,
+            len,
+        })
+    }
+
+    /// Allocate a buffer holding a copy of `data`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `data` is empty or the buffer cannot be created.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, MemoryError> {
+        let mut buffer = Self::new(data.len())?;
+        buffer.as_mut_slice().copy_from_slice(data);
+        Ok(buffer)
+    }
+
+    /// Size of the buffer in bytes.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the buffer is empty (never true for a created buffer).
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Contents of the buffer, including guest writes to writable mappings.
+    #[must_use]
+    pub const fn as_slice(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr().cast::<u8>(), self.len) }
+    }
+
+    /// Contents of the buffer, for the host to fill in.
+    #[must_use]
+    pub const fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe { std::slice::from_raw_parts_mut(self.ptr.as_ptr().cast::<u8>(), self.len) }
+    }
+}
+
+impl Drop for HostBuffer {
+    fn drop(&mut self) {
+        // Guest mappings keep the file alive on their own.
+        unsafe {
+            let _ = munmap(self.ptr, self.len);
+        }
+    }
+}
+
+// HostBuffer is Send but not Sync (contains raw pointer)
+unsafe impl Send for HostBuffer {}
+
+impl std::fmt::Debug for HostBuffer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HostBuffer")
+            .field("len", &self.len)
+            .finish_non_exhaustive()
+    }
+}
+
+impl GuardedMemory {
+    /// Map `buffer` shared over memory at `offset`.
+    ///
+    /// The guest then reads the host's pages in place: host writes to the
+    /// buffer are visible to the guest and, if `writable`, guest writes are
+    /// visible in the buffer. A guest write to a read-only mapping faults.
+    /// The mapping stays in place across [`clear`](Self::clear),
+    /// [`discard`](Self::discard), snapshots and restores (the buffer is not
+    /// part of a snapshot) until [`unmap_shared`](Self::unmap_shared).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `offset` or the buffer length is not a multiple of
+    /// [`page_size`], if the range is outside the usable region or overlaps
+    /// another shared mapping, or if the mmap fails.
+    pub fn map_shared(
+        &mut self,
+        offset: usize,
+        buffer: &HostBuffer,
+        writable: bool,
+    ) -> Result<(), MemoryError> {
+        self.add_shared(SharedMapping {
+            offset,
+            len: buffer.len(),
+            file: Arc::clone(&buffer.file),
+            writable,
+        })
+    }
+
+    /// Map the host buffer `other` has at `offset` at the same offset here,
+    /// with the same permissions, e.g. to carry it over to a new memory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `other` has no buffer at `offset`, or for the
+    /// reasons [`map_shared`](Self::map_shared) fails.
+    pub fn map_shared_from(&mut self, other: &Self, offset: usize) -> Result<(), MemoryError> {
+        let mapping = other
+            .shared
+            .iter()
+            .find(|m| m.offset == offset)
+            .ok_or(MemoryError::NoSharedMapping(offset))?;
+        self.add_shared(SharedMapping {
+            file: Arc::clone(&mapping.file),
+            ..*mapping
+        })
+    }
+
+    fn add_shared(&mut self, mapping: SharedMapping) -> Result<(), MemoryError> {
+        let SharedMapping { offset, len, .. } = mapping;
+        let page_size = page_size();
+        if !offset.is_multiple_of(page_size) || !len.is_multiple_of(page_size) {
+            return Err(MemoryError::UnalignedMapping {
+                offset,
+                file_offset: 0,
+                len,
+                page_size,
+            });
+        }
+        let end = offset
+            .checked_add(len)
+            .filter(|&end| end <= self.memory_size)
+            .ok_or(MemoryError::MappingOutOfRange {
+                offset,
+                len,
+                memory: self.memory_size,
+            })?;
+        if let Some(existing) = self
+            .shared
+            .iter()
+            .find(|m| offset < m.offset + m.len && m.offset < end)
+        {
+            return Err(MemoryError::SharedMappingOverlap {
+                offset,
+                len,
+                existing: existing.offset,
+            });
+        }
+        self.map_shared_pages(&mapping)?;
+        self.shared.push(mapping);
+        Ok(())
+    }
+
+    /// Remove the host buffer mapped at `offset`, leaving fresh zero pages.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no buffer is mapped at `offset` or the remap fails.
+    pub fn unmap_shared(&mut self, offset: usize) -> Result<(), MemoryError> {
+        let idx = self
+            .shared
+            .iter()
+            .position(|m| m.offset == offset)
+            .ok_or(MemoryError::NoSharedMapping(offset))?;
+        let mapping = self.shared.remove(idx);
+        let addr = NonZeroUsize::new(self.as_ptr() as usize + offset)
+            .ok_or(MemoryError::NoSharedMapping(offset))?;
+        let len = NonZeroUsize::new(mapping.len).ok_or(MemoryError::NoSharedMapping(offset))?;
+
+        // MAP_FIXED replaces only the buffer's pages of our own mapping.
+        unsafe {
+            mmap_anonymous(
+                Some(addr),
+                len,
+                self.fresh_prot(),
+                MapFlags::MAP_PRIVATE | MapFlags::MAP_FIXED | MapFlags::MAP_NORESERVE,
+            )?;
+        }
+        if let Some(reservation) = &self.reservation {
+            reservation.pin(offset, mapping.len, false);
+        }
+        Ok(())
+    }
+
+    /// Map the shared buffers again after the usable region was replaced.
+    pub(super) fn remap_shared(&self) -> Result<(), MemoryError> {
+        self.shared
+            .iter()
+            .try_for_each(|mapping| self.map_shared_pages(mapping))
+    }
+
+    fn map_shared_pages(&self, mapping: &SharedMapping) -> Result<(), MemoryError> {
+        let addr = NonZeroUsize::new(self.as_ptr() as usize + mapping.offset)
+            .ok_or(MemoryError::InvalidSize(self.memory_size))?;
+        let len = NonZeroUsize::new(mapping.len).ok_or(MemoryError::InvalidSize(mapping.len))?;
+        let prot = if mapping.writable {
+            ProtFlags::PROT_READ | ProtFlags::PROT_WRITE
+        } else {
+            ProtFlags::PROT_READ
+        };
+
+        // The fault handler must not make a read-only buffer writable.
+        if let Some(reservation) = &self.reservation {
+            reservation.pin(mapping.offset, mapping.len, true);
+            reservation.mark(mapping.offset, mapping.len, false);
+        }
+        // MAP_FIXED replaces only the buffer's pages of our own mapping.
+        unsafe {
+            mmap(
+                Some(addr),
+                len,
+                prot,
+                MapFlags::MAP_SHARED | MapFlags::MAP_FIXED,
+                mapping.file.as_ref(),
+                0,
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,134 @@
+use nix::sys::mman::{MapFlags, mmap};
+use std::fs::File;
+use std::num::NonZeroUsize;
+use std::os::unix::fs::FileExt;
+use std::sync::Arc;
+
+use super::{GuardedMemory, MemoryError, anonymous_file};
+
+/// Granularity for copying memory into a snapshot file (all-zero chunks are skipped).
+pub(super) const SNAPSHOT_CHUNK_SIZE: usize = 1 << 16;
+
+/// Frozen guest memory contents created by [`GuardedMemory::snapshot`].
+///
+/// Cheap to clone; clones share the same backing file. A snapshot can be
+/// restored into any [`GuardedMemory`] of the same size.
+#[derive(Clone)]
+pub struct MemorySnapshot {
+    file: Arc<File>,
+    size: usize,
+}
+
+impl MemorySnapshot {
+    /// Returns the size of the captured memory region.
+    #[must_use]
+    pub const fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Read captured memory at `offset` into `buf`, as it was when the
+    /// snapshot was taken.
+    ///
+    /// Returns the number of bytes read, which is short if the range runs
+    /// past the end of the snapshot.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading the snapshot file fails.
+    pub fn read_at(&self, offset: usize, buf: &mut [u8]) -> Result<usize, MemoryError> {
+        if offset >= self.size {
+            return Ok(0);
+        }
+        let len = buf.len().min(self.size - offset);
+        self.file.read_exact_at(&mut buf[..len], offset as u64)?;
+        Ok(len)
+    }
+}
+
+impl std::fmt::Debug for MemorySnapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MemorySnapshot")
+            .field("size", &self.size)
+            .finish_non_exhaustive()
+    }
+}
+
+impl GuardedMemory {
+    /// Freeze the current contents into a snapshot.
+    ///
+    /// Copies the non-zero parts of memory into an anonymous file, then maps the
+    /// usable region copy-on-write over it. Subsequent writes land in private
+    /// pages, which [`restore`](Self::restore) discards. The base address does
+    /// not change, so pointers into guest memory stay valid.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the snapshot file cannot be created or mapped.
+    pub fn snapshot(&mut self) -> Result<MemorySnapshot, MemoryError> {
+        let file = anonymous_file()?;
+        file.set_len(self.memory_size as u64)?;
+
+        // Uncommitted pages are zero, so only committed ranges are scanned.
+        for range in self.committed_ranges() {
+            // SAFETY: committed ranges are mapped readable.
+            let memory =
+                unsafe { std::slice::from_raw_parts(self.as_ptr().add(range.start), range.len()) };
+            for (idx, chunk) in memory.chunks(SNAPSHOT_CHUNK_SIZE).enumerate() {
+                // Fold instead of `any` so the scan vectorizes; untouched pages read as zero.
+                if chunk.iter().fold(0, |acc, &b| acc | b) != 0 {
+                    let offset = range.start + idx * SNAPSHOT_CHUNK_SIZE;
+                    file.write_all_at(chunk, offset as u64)?;
+                }
+            }
+        }
+
+        let snapshot = MemorySnapshot {
+            file: Arc::new(file),
+            size: self.memory_size,
+        };
+        self.map_snapshot(&snapshot)?;
+        Ok(snapshot)
+    }
+
+    /// Restore memory to the contents of a snapshot.
+    ///
+    /// Remaps the usable region copy-on-write over the snapshot file. The cost
+    /// is proportional to the pages touched since the last snapshot or restore,
+    /// not to the memory size.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the snapshot size differs or the remap fails.
+    pub fn restore(&mut self, snapshot: &MemorySnapshot) -> Result<(), MemoryError> {
+        self.map_snapshot(snapshot)
+    }
+
+    fn map_snapshot(&self, snapshot: &MemorySnapshot) -> Result<(), MemoryError> {
+        if snapshot.size != self.memory_size {
+            return Err(MemoryError::SnapshotSizeMismatch {
+                snapshot: snapshot.size,
+                memory: self.memory_size,
+            });
+        }
+        let addr = NonZeroUsize::new(self.as_ptr() as usize)
+            .ok_or(MemoryError::InvalidSize(self.memory_size))?;
+        let len = NonZeroUsize::new(self.memory_size)
+            .ok_or(MemoryError::InvalidSize(self.memory_size))?;
+
+        // MAP_FIXED deliberately replaces our own mapping; guard pages are untouched.
+        unsafe {
+            mmap(
+                Some(addr),
+                len,
+                self.fresh_prot(),
+                MapFlags::MAP_PRIVATE | MapFlags::MAP_FIXED | MapFlags::MAP_NORESERVE,
+                snapshot.file.as_ref(),
+                0,
+            )?;
+        }
+        if let Some(reservation) = &self.reservation {
+            reservation.reset();
+        }
+        self.remap_shared()
+    }
+}
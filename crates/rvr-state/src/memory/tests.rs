@@ -0,0 +1,340 @@
+use std::os::unix::fs::FileExt;
+
+use super::snapshot::SNAPSHOT_CHUNK_SIZE;
+use super::*;
+
+#[test]
+fn test_guarded_memory_alloc() {
+    let mem = GuardedMemory::new(4096).expect("allocation should succeed");
+    assert_eq!(mem.size(), 4096);
+    assert!(!mem.as_ptr().is_null());
+}
+
+#[test]
+fn test_guarded_memory_read_write() {
+    let mut mem = GuardedMemory::new(4096).expect("allocation should succeed");
+
+    unsafe {
+        mem.write_u8(0, 0xAB);
+        mem.write_u8(4095, 0xCD);
+        assert_eq!(mem.read_u8(0), 0xAB);
+        assert_eq!(mem.read_u8(4095), 0xCD);
+    }
+}
+
+#[test]
+fn test_guarded_memory_copy() {
+    let mut mem = GuardedMemory::new(4096).expect("allocation should succeed");
+    let data = [1u8, 2, 3, 4, 5];
+
+    unsafe {
+        mem.copy_from(100, &data);
+        assert_eq!(mem.read_u8(100), 1);
+        assert_eq!(mem.read_u8(104), 5);
+    }
+}
+
+#[test]
+fn test_guarded_memory_clear() {
+    let mut mem = GuardedMemory::new(4096).expect("allocation should succeed");
+
+    unsafe {
+        mem.write_u8(0, 0xFF);
+        mem.write_u8(100, 0xFF);
+    }
+
+    mem.clear();
+
+    unsafe {
+        assert_eq!(mem.read_u8(0), 0);
+        assert_eq!(mem.read_u8(100), 0);
+    }
+}
+
+#[test]
+fn test_guarded_memory_snapshot_restore() {
+    let size = 4 * SNAPSHOT_CHUNK_SIZE;
+    let mut mem = GuardedMemory::new(size).expect("allocation should succeed");
+    let base = mem.as_ptr();
+
+    unsafe {
+        mem.write_u8(0, 0x11);
+        mem.write_u8(size - 1, 0x22);
+    }
+    let snapshot = mem.snapshot().expect("snapshot should succeed");
+    assert_eq!(mem.as_ptr(), base);
+
+    for _ in 0..2 {
+        unsafe {
+            assert_eq!(mem.read_u8(0), 0x11);
+            assert_eq!(mem.read_u8(size - 1), 0x22);
+            mem.write_u8(0, 0xAA);
+            mem.write_u8(SNAPSHOT_CHUNK_SIZE, 0xBB);
+        }
+        mem.restore(&snapshot).expect("restore should succeed");
+        unsafe {
+            assert_eq!(mem.read_u8(0), 0x11);
+            assert_eq!(mem.read_u8(SNAPSHOT_CHUNK_SIZE), 0);
+        }
+    }
+}
+
+#[test]
+fn test_memory_snapshot_read_at() {
+    let size = 2 * SNAPSHOT_CHUNK_SIZE;
+    let mut mem = GuardedMemory::new(size).expect("allocation should succeed");
+    unsafe {
+        mem.write_u8(1, 0x11);
+        mem.write_u8(size - 1, 0x22);
+    }
+    let snapshot = mem.snapshot().expect("snapshot should succeed");
+    unsafe { mem.write_u8(1, 0xAA) };
+
+    let mut buf = [0xFF; 4];
+    assert_eq!(snapshot.read_at(0, &mut buf).unwrap(), 4);
+    assert_eq!(buf, [0, 0x11, 0, 0]);
+    assert_eq!(snapshot.read_at(size - 2, &mut buf).unwrap(), 2);
+    assert_eq!(buf[..2], [0, 0x22]);
+    assert_eq!(snapshot.read_at(size, &mut buf).unwrap(), 0);
+}
+
+#[test]
+fn test_guarded_memory_restore_size_mismatch() {
+    let mut small = GuardedMemory::new(4096).expect("allocation should succeed");
+    let mut large = GuardedMemory::new(8192).expect("allocation should succeed");
+    let snapshot = small.snapshot().expect("snapshot should succeed");
+    assert!(matches!(
+        large.restore(&snapshot),
+        Err(MemoryError::SnapshotSizeMismatch { .. })
+    ));
+}
+
+#[test]
+fn test_guarded_memory_map_file() {
+    let page = page_size();
+    let mut mem = GuardedMemory::new(4 * page).expect("allocation should succeed");
+    let file = anonymous_file().expect("file should be created");
+    file.set_len(3 * page as u64).unwrap();
+    file.write_all_at(&[0x11, 0x22], page as u64).unwrap();
+
+    unsafe { mem.write_u8(0, 0xFF) };
+    mem.map_file(page, 2 * page, &file, page as u64)
+        .expect("map should succeed");
+    unsafe {
+        assert_eq!(mem.read_u8(0), 0xFF);
+        assert_eq!(mem.read_u8(page), 0x11);
+        assert_eq!(mem.read_u8(page + 1), 0x22);
+        // Copy-on-write: the file keeps its contents
+        mem.write_u8(page, 0xAA);
+    }
+    let mut byte = [0u8];
+    file.read_exact_at(&mut byte, page as u64).unwrap();
+    assert_eq!(byte, [0x11]);
+
+    mem.discard().expect("discard should succeed");
+    unsafe {
+        assert_eq!(mem.read_u8(0), 0);
+        assert_eq!(mem.read_u8(page), 0);
+    }
+}
+
+#[test]
+fn test_guarded_memory_map_file_rejects_bad_ranges() {
+    let page = page_size();
+    let mut mem = GuardedMemory::new(2 * page).expect("allocation should succeed");
+    let file = anonymous_file().expect("file should be created");
+    file.set_len(2 * page as u64).unwrap();
+
+    assert!(matches!(
+        mem.map_file(1, page, &file, 0),
+        Err(MemoryError::UnalignedMapping { .. })
+    ));
+    assert!(matches!(
+        mem.map_file(0, page, &file, 1),
+        Err(MemoryError::UnalignedMapping { .. })
+    ));
+    assert!(matches!(
+        mem.map_file(page, 2 * page, &file, 0),
+        Err(MemoryError::MappingOutOfRange { .. })
+    ));
+}
+
+#[test]
+fn test_reserved_memory_commits_on_first_touch() {
+    let size = DEFAULT_MEMORY_SIZE;
+    let mut mem = GuardedMemory::reserve(size).expect("reservation should succeed");
+    assert!(mem.commits_on_demand());
+    assert!(mem.committed_ranges().is_empty());
+
+    unsafe {
+        mem.write_u8(size - 1, 0xAB);
+        assert_eq!(mem.read_u8(size - 1), 0xAB);
+        assert_eq!(mem.read_u8(size - 2), 0);
+    }
+    let chunk = crate::commit::COMMIT_CHUNK_SIZE;
+    let committed = mem.committed_ranges();
+    assert_eq!(committed.len(), 1);
+    assert_eq!(committed[0], size - chunk..size);
+    let resident = mem.resident_bytes().expect("mincore should succeed");
+    assert!(resident > 0 && resident <= chunk);
+
+    mem.commit(0, 1).expect("commit should succeed");
+    assert_eq!(mem.committed_ranges()[0], 0..page_size());
+
+    mem.clear();
+    assert!(mem.committed_ranges().is_empty());
+    unsafe { assert_eq!(mem.read_u8(size - 1), 0) };
+}
+
+#[test]
+fn test_reserved_memory_snapshot_restore() {
+    let size = 4 * SNAPSHOT_CHUNK_SIZE;
+    let mut mem = GuardedMemory::reserve(size).expect("reservation should succeed");
+    unsafe { mem.write_u8(size - 1, 0x22) };
+    let snapshot = mem.snapshot().expect("snapshot should succeed");
+    assert!(mem.committed_ranges().is_empty());
+
+    unsafe {
+        assert_eq!(mem.read_u8(size - 1), 0x22);
+        mem.write_u8(0, 0xAA);
+    }
+    mem.restore(&snapshot).expect("restore should succeed");
+    unsafe {
+        assert_eq!(mem.read_u8(0), 0);
+        assert_eq!(mem.read_u8(size - 1), 0x22);
+    }
+}
+
+#[test]
+fn test_reserved_memory_guard_page_faults() {
+    use nix::sys::signal::Signal;
+    use nix::sys::wait::{WaitStatus, waitpid};
+    use nix::unistd::{ForkResult, fork};
+
+    let mem = GuardedMemory::reserve(4096).expect("reservation should succeed");
+    // A fault in the child must not be mistaken for an on-demand commit.
+    match unsafe { fork() }.expect("fork should succeed") {
+        ForkResult::Child => unsafe {
+            mem.as_ptr().sub(1).write_volatile(1);
+            nix::libc::_exit(0);
+        },
+        ForkResult::Parent { child } => {
+            let status = waitpid(child, None).expect("waitpid should succeed");
+            assert!(
+                matches!(status, WaitStatus::Signaled(_, Signal::SIGSEGV, _)),
+                "guard page write should crash, got {status:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_shared_mapping() {
+    let page = page_size();
+    let mut mem = GuardedMemory::new(8 * page).expect("allocation should succeed");
+    let mut buffer = HostBuffer::from_bytes(&vec![0x5A; 2 * page]).expect("buffer");
+    mem.map_shared(2 * page, &buffer, true)
+        .expect("map should succeed");
+
+    unsafe {
+        assert_eq!(mem.read_u8(2 * page), 0x5A);
+        assert_eq!(mem.read_u8(4 * page - 1), 0x5A);
+        assert_eq!(mem.read_u8(4 * page), 0);
+        mem.write_u8(2 * page + 1, 0xA5);
+    }
+    assert_eq!(buffer.as_slice()[1], 0xA5);
+    buffer.as_mut_slice()[2] = 0x11;
+    unsafe { assert_eq!(mem.read_u8(2 * page + 2), 0x11) };
+
+    // Resets leave the buffer mapped and its contents alone
+    mem.clear();
+    unsafe { assert_eq!(mem.read_u8(2 * page), 0x5A) };
+    let snapshot = mem.snapshot().expect("snapshot should succeed");
+    mem.restore(&snapshot).expect("restore should succeed");
+    unsafe { assert_eq!(mem.read_u8(2 * page + 1), 0xA5) };
+
+    // Another memory maps the same pages
+    let mut other = GuardedMemory::new(8 * page).expect("allocation should succeed");
+    other
+        .map_shared_from(&mem, 2 * page)
+        .expect("map from should succeed");
+    unsafe { assert_eq!(other.read_u8(2 * page + 1), 0xA5) };
+    assert!(matches!(
+        other.map_shared_from(&mem, page),
+        Err(MemoryError::NoSharedMapping(_))
+    ));
+
+    mem.unmap_shared(2 * page).expect("unmap should succeed");
+    unsafe { assert_eq!(mem.read_u8(2 * page), 0) };
+    unsafe { assert_eq!(other.read_u8(2 * page), 0x5A) };
+    assert_eq!(buffer.as_slice()[0], 0x5A);
+    assert!(matches!(
+        mem.unmap_shared(2 * page),
+        Err(MemoryError::NoSharedMapping(_))
+    ));
+}
+
+#[test]
+fn test_shared_mapping_rejects_bad_ranges() {
+    let page = page_size();
+    let mut mem = GuardedMemory::new(4 * page).expect("allocation should succeed");
+    let buffer = HostBuffer::new(2 * page).expect("buffer");
+    assert!(matches!(
+        mem.map_shared(1, &buffer, false),
+        Err(MemoryError::UnalignedMapping { .. })
+    ));
+    let odd = HostBuffer::new(page + 1).expect("buffer");
+    assert!(matches!(
+        mem.map_shared(0, &odd, false),
+        Err(MemoryError::UnalignedMapping { .. })
+    ));
+    assert!(matches!(
+        mem.map_shared(3 * page, &buffer, false),
+        Err(MemoryError::MappingOutOfRange { .. })
+    ));
+    mem.map_shared(0, &buffer, false)
+        .expect("map should succeed");
+    assert!(matches!(
+        mem.map_shared(page, &buffer, false),
+        Err(MemoryError::SharedMappingOverlap { existing: 0, .. })
+    ));
+    assert!(HostBuffer::new(0).is_err());
+}
+
+#[test]
+fn test_reserved_memory_read_only_mapping_faults() {
+    use nix::sys::signal::Signal;
+    use nix::sys::wait::{WaitStatus, waitpid};
+    use nix::unistd::{ForkResult, fork};
+
+    let page = page_size();
+    let mut mem = GuardedMemory::reserve(4 * page).expect("reservation should succeed");
+    let buffer = HostBuffer::from_bytes(&vec![7; page]).expect("buffer");
+    mem.map_shared(page, &buffer, false)
+        .expect("map should succeed");
+    // Committing the chunk around the buffer must not make it writable
+    unsafe {
+        mem.write_u8(0, 1);
+        assert_eq!(mem.read_u8(page), 7);
+    }
+    match unsafe { fork() }.expect("fork should succeed") {
+        ForkResult::Child => unsafe {
+            mem.as_ptr().add(page).write_volatile(1);
+            nix::libc::_exit(0);
+        },
+        ForkResult::Parent { child } => {
+            let status = waitpid(child, None).expect("waitpid should succeed");
+            assert!(
+                matches!(status, WaitStatus::Signaled(_, Signal::SIGSEGV, _)),
+                "read-only buffer write should crash, got {status:?}"
+            );
+        }
+    }
+    assert_eq!(buffer.as_slice()[0], 7);
+}
+
+#[test]
+fn test_guarded_memory_invalid_size() {
+    let result = GuardedMemory::new(0);
+    assert!(result.is_err());
+}
@@ -0,0 +1,61 @@
+//! Anonymous mmap arena state.
+//!
+//! The generated `rv_sys_mmap`/`rv_sys_munmap`/`rv_sys_mremap` keep the live
+//! mappings here, inside `RvState`, so suspending or snapshotting a guest
+//! keeps its allocator consistent with its memory.
+
+/// Maximum number of live mappings; adjacent mappings are merged, and
+/// operations that would need more fail with ENOMEM.
+pub const MMAP_MAX_REGIONS: usize = 128;
+
+/// Live mappings in the mmap arena, sorted and non-adjacent.
+///
+/// Matches C struct:
+/// ```c
+/// typedef struct RvMmap {
+///     uint32_t count;
+///     uint32_t _pad;
+///     uint64_t regions[RV_MMAP_MAX_REGIONS][2];
+/// } RvMmap;
+/// ```
+#[repr(C)]
+#[derive(Clone, Debug)]
+pub struct MmapRegions {
+    count: u32,
+    _pad: u32,
+    regions: [[u64; 2]; MMAP_MAX_REGIONS],
+}
+
+impl Default for MmapRegions {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            _pad: 0,
+            regions: [[0; 2]; MMAP_MAX_REGIONS],
+        }
+    }
+}
+
+impl MmapRegions {
+    /// Live mappings as `[start, end)` pairs, lowest first.
+    #[must_use]
+    pub fn regions(&self) -> &[[u64; 2]] {
+        &self.regions[..self.count as usize]
+    }
+
+    /// Unmap everything.
+    pub const fn clear(&mut self) {
+        self.count = 0;
+    }
+
+    /// Replace the live mappings, e.g. from a snapshot.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are more than `MMAP_MAX_REGIONS` mappings.
+    pub fn set(&mut self, regions: &[[u64; 2]]) {
+        assert!(regions.len() <= MMAP_MAX_REGIONS, "too many mmap regions");
+        self.regions[..regions.len()].copy_from_slice(regions);
+        self.count = u32::try_from(regions.len()).expect("bounded by MMAP_MAX_REGIONS");
+    }
+}
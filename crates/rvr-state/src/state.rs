@@ -4,6 +4,8 @@
 
 use rvr_ir::Xlen;
 
+use crate::io::GuestIo;
+use crate::mmap::MmapRegions;
 use crate::suspender::SuspenderState;
 use crate::tracer::TracerState;
 
@@ -19,6 +21,14 @@ pub enum ExecutionStatus {
 /// Number of CSRs.
 pub const NUM_CSRS: usize = 4096;
 
+/// Largest supported vector register length in bits.
+pub const MAX_VLEN: usize = 1024;
+
+/// Bytes of vector register storage: 32 registers of [`MAX_VLEN`] bits.
+///
+/// Libraries compiled with a smaller VLEN use the front of the array.
+pub const VREGS_BYTES: usize = 32 * MAX_VLEN / 8;
+
 /// Number of registers for I extension (32 GPRs).
 pub const NUM_REGS_I: usize = 32;
 
@@ -47,11 +57,19 @@ pub const NUM_REGS_E: usize = 16;
 /// offset ?:     has_exited (u8, legacy execution-status byte)
 /// offset ?:     exit_code (u8, legacy result payload byte)
 /// offset ?:     _pad1 (u8)
+/// offset ?:     exit_cause (u32)
+/// offset ?:     exit_info (u64)
 /// offset ?:     brk
 /// offset ?:     start_brk
 /// offset ?:     memory (*mut u8)          (cold - rarely used in hot paths)
+/// offset ?:     io (*mut GuestIo)         (cold - stdio syscalls and hook CSRs)
 /// offset ?:     tracer (only when T != ())
 /// offset ?:     csrs[4096]                (cold - huge array at end)
+/// offset ?:     mmap                      (cold - only used by mmap syscalls)
+/// offset ?:     vregs[VREGS_BYTES]        (cold - only used by vector code)
+/// offset ?:     trace_countdown (u64)     (only used by sampled tracers)
+/// offset ?:     htif_polls, htif_fromhost, htif_syscall (u64, HTIF watchdog)
+/// offset ?:     golden_countdown, golden_pos (u64, golden trace points)
 /// ```
 #[repr(C)]
 pub struct RvState<
@@ -93,8 +111,17 @@ pub struct RvState<
     /// as a status-specific payload.
     pub exit_code: u8,
 
-    /// Alignment padding (for brk alignment).
-    _pad0: u8,
+    /// Set by the host to stop the guest at its next backward jump or
+    /// branch (libraries compiled with `check_cancel`; ignored otherwise).
+    pub cancel_requested: u8,
+
+    /// Why execution stopped (`rvr_emit::ExitCause` code; 0 for a guest
+    /// exit or while running).
+    pub exit_cause: u32,
+
+    /// Auxiliary word of `exit_cause`: the faulting address, jump target or
+    /// HTIF test number.
+    pub exit_info: u64,
 
     /// Current heap break.
     pub brk: X::Reg,
@@ -105,11 +132,43 @@ pub struct RvState<
     /// Guest memory pointer (cold - rarely accessed in hot paths).
     pub memory: *mut u8,
 
+    /// Host hooks (guest stdio, hook CSRs), or null to use the host's stdio
+    /// and the hook CSRs' slots.
+    pub io: *mut GuestIo,
+
     /// Tracer state (ZST when T = (), real struct when tracing).
     pub tracer: T,
 
     /// Control and status registers (cold - huge array, rarely used).
     pub csrs: [X::Reg; NUM_CSRS],
+
+    /// Anonymous mmap arena mappings (cold - only used by mmap syscalls).
+    pub mmap: MmapRegions,
+
+    /// Vector registers `v0..v31`, each `VLEN / 8` bytes (cold - only used
+    /// by vector instructions). `vl` and `vtype` live in `csrs`.
+    pub vregs: [u8; VREGS_BYTES],
+
+    /// Instructions until a sampled tracer's hooks see the next one (only
+    /// used by sampled tracers). Zero samples the next instruction.
+    pub trace_countdown: u64,
+
+    /// `fromhost` loads since `fromhost` last changed or the guest made an
+    /// HTIF request (only used by the HTIF poll watchdog).
+    pub htif_polls: u64,
+
+    /// `fromhost` as the HTIF poll watchdog last saw it.
+    pub htif_fromhost: u64,
+
+    /// Number of the last HTIF syscall the guest requested (0 if none).
+    pub htif_syscall: u64,
+
+    /// Block entries until the next golden trace point (only used when
+    /// capturing or checking a golden trace). Zero samples the next entry.
+    pub golden_countdown: u64,
+
+    /// Golden trace points passed so far, the index of the next one.
+    pub golden_pos: u64,
 }
 
 impl<X: Xlen, T: TracerState, S: SuspenderState, const NUM_REGS: usize> RvState<X, T, S, NUM_REGS> {
@@ -139,12 +198,23 @@ impl<X: Xlen, T: TracerState, S: SuspenderState, const NUM_REGS: usize> Default
             reservation_valid: 0,
             has_exited: 0,
             exit_code: 0,
-            _pad0: 0,
+            cancel_requested: 0,
+            exit_cause: 0,
+            exit_info: 0,
             brk: X::from_u64(0),
             start_brk: X::from_u64(0),
             memory: std::ptr::null_mut(),
+            io: std::ptr::null_mut(),
             tracer: T::default(),
             csrs: [X::from_u64(0); NUM_CSRS],
+            mmap: MmapRegions::default(),
+            vregs: [0; VREGS_BYTES],
+            trace_countdown: 0,
+            htif_polls: 0,
+            htif_fromhost: 0,
+            htif_syscall: 0,
+            golden_countdown: 0,
+            golden_pos: 0,
         }
     }
 }
@@ -181,6 +251,16 @@ impl<X: Xlen, T: TracerState, S: SuspenderState, const NUM_REGS: usize> RvState<
         self.reservation_valid = 0;
         self.has_exited = 0;
         self.exit_code = 0;
+        self.exit_cause = 0;
+        self.exit_info = 0;
+        self.cancel_requested = 0;
+        self.mmap.clear();
+        self.trace_countdown = 0;
+        self.htif_polls = 0;
+        self.htif_fromhost = 0;
+        self.htif_syscall = 0;
+        self.golden_countdown = 0;
+        self.golden_pos = 0;
     }
 
     /// Legacy helper: true when the execution-status byte is non-zero.
@@ -196,7 +276,6 @@ impl<X: Xlen, T: TracerState, S: SuspenderState, const NUM_REGS: usize> RvState<
     /// Get the execution status.
     pub const fn execution_status(&self) -> ExecutionStatus {
         match self.has_exited {
-            0 => ExecutionStatus::Running,
             1 => ExecutionStatus::Terminated,
             2 => ExecutionStatus::Suspended,
             3 => ExecutionStatus::Trapped,
@@ -234,15 +313,38 @@ impl<X: Xlen, T: TracerState, S: SuspenderState, const NUM_REGS: usize> RvState<
         self.is_terminated() && self.result_code() == 0
     }
 
+    /// Get the raw exit cause code.
+    pub const fn exit_cause(&self) -> u32 {
+        self.exit_cause
+    }
+
+    /// Get the auxiliary word of the exit cause.
+    pub const fn exit_info(&self) -> u64 {
+        self.exit_info
+    }
+
+    /// Number of the last HTIF syscall the guest requested (0 if none).
+    pub const fn htif_syscall(&self) -> u64 {
+        self.htif_syscall
+    }
+
+    /// Pointer to `cancel_requested`, for setting it from another thread.
+    pub const fn cancel_flag(&mut self) -> *mut u8 {
+        &raw mut self.cancel_requested
+    }
+
     /// Set the raw execution-status and result payload bytes together.
     pub const fn set_execution_state(&mut self, status: ExecutionStatus, result: u8) {
         self.has_exited = status as u8;
         self.exit_code = result;
     }
 
-    /// Clear execution status and payload to allow further execution.
+    /// Clear execution status, payload and exit cause to allow further
+    /// execution.
     pub const fn clear_exit(&mut self) {
         self.set_execution_state(ExecutionStatus::Running, 0);
+        self.exit_cause = 0;
+        self.exit_info = 0;
     }
 
     /// Get the instruction count.
@@ -281,6 +383,97 @@ impl<X: Xlen, T: TracerState, S: SuspenderState, const NUM_REGS: usize> RvState<
     pub const fn memory(&self) -> *mut u8 {
         self.memory
     }
+
+    /// Set host hooks (null restores the host's stdio and CSR slots).
+    pub const fn set_io(&mut self, io: *mut GuestIo) {
+        self.io = io;
+    }
+
+    /// Capture the architectural state.
+    ///
+    /// Host-side fields (memory and I/O pointers, tracer, suspender) are not
+    /// captured.
+    #[must_use]
+    pub fn capture(&self) -> StateSnapshot {
+        StateSnapshot {
+            regs: self.regs.iter().map(|&r| X::to_u64(r)).collect(),
+            pc: X::to_u64(self.pc),
+            instret: self.instret,
+            reservation_addr: X::to_u64(self.reservation_addr),
+            reservation_valid: self.reservation_valid,
+            has_exited: self.has_exited,
+            exit_code: self.exit_code,
+            exit_cause: self.exit_cause,
+            exit_info: self.exit_info,
+            brk: X::to_u64(self.brk),
+            start_brk: X::to_u64(self.start_brk),
+            csrs: self.csrs.iter().map(|&c| X::to_u64(c)).collect(),
+            mmap: self.mmap.regions().to_vec(),
+            vregs: self.vregs.into(),
+        }
+    }
+
+    /// Restore architectural state captured by [`capture`](Self::capture).
+    ///
+    /// Host-side fields (memory and I/O pointers, tracer, suspender) are left
+    /// untouched.
+    pub fn restore(&mut self, snapshot: &StateSnapshot) {
+        debug_assert_eq!(snapshot.regs.len(), NUM_REGS);
+        for (reg, &value) in self.regs.iter_mut().zip(&snapshot.regs) {
+            *reg = X::from_u64(value);
+        }
+        self.pc = X::from_u64(snapshot.pc);
+        self.instret = snapshot.instret;
+        self.reservation_addr = X::from_u64(snapshot.reservation_addr);
+        self.reservation_valid = snapshot.reservation_valid;
+        self.has_exited = snapshot.has_exited;
+        self.exit_code = snapshot.exit_code;
+        self.exit_cause = snapshot.exit_cause;
+        self.exit_info = snapshot.exit_info;
+        self.brk = X::from_u64(snapshot.brk);
+        self.start_brk = X::from_u64(snapshot.start_brk);
+        for (csr, &value) in self.csrs.iter_mut().zip(snapshot.csrs.iter()) {
+            *csr = X::from_u64(value);
+        }
+        self.mmap.set(&snapshot.mmap);
+        self.vregs.copy_from_slice(&snapshot.vregs);
+    }
+}
+
+/// Architectural state captured by [`RvState::capture`].
+///
+/// Register values are widened to `u64` so a snapshot can be held without
+/// knowing the XLEN of the state it came from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StateSnapshot {
+    /// General-purpose registers.
+    pub regs: Vec<u64>,
+    /// Program counter.
+    pub pc: u64,
+    /// Instructions retired.
+    pub instret: u64,
+    /// LR/SC reservation address.
+    pub reservation_addr: u64,
+    /// LR/SC reservation valid flag.
+    pub reservation_valid: u8,
+    /// Execution-status byte.
+    pub has_exited: u8,
+    /// Result payload byte.
+    pub exit_code: u8,
+    /// Exit cause code.
+    pub exit_cause: u32,
+    /// Auxiliary word of the exit cause.
+    pub exit_info: u64,
+    /// Current heap break.
+    pub brk: u64,
+    /// Initial heap break.
+    pub start_brk: u64,
+    /// Control and status registers.
+    pub csrs: Box<[u64]>,
+    /// Live mmap arena mappings as `[start, end)` pairs.
+    pub mmap: Vec<[u64; 2]>,
+    /// Vector registers.
+    pub vregs: Box<[u8]>,
 }
 
 /// Type alias for RV32I state (32-bit, 32 registers, no tracer, no suspender).
@@ -323,13 +516,21 @@ mod tests {
         assert_eq!(offset_of!(Rv64State, reservation_valid), 280);
         assert_eq!(offset_of!(Rv64State, has_exited), 281);
         assert_eq!(offset_of!(Rv64State, exit_code), 282);
-        assert_eq!(offset_of!(Rv64State, _pad0), 283);
-        assert_eq!(offset_of!(Rv64State, brk), 288); // aligned to 8
-        assert_eq!(offset_of!(Rv64State, start_brk), 296);
-        assert_eq!(offset_of!(Rv64State, memory), 304);
-        // Tracer ZST is here at 312, adds 0 bytes
-        assert_eq!(offset_of!(Rv64State, csrs), 312);
-        assert_eq!(size_of::<Rv64State>(), 312 + 4096 * 8); // 33080
+        assert_eq!(offset_of!(Rv64State, cancel_requested), 283);
+        assert_eq!(offset_of!(Rv64State, exit_cause), 284);
+        assert_eq!(offset_of!(Rv64State, exit_info), 288);
+        assert_eq!(offset_of!(Rv64State, brk), 296);
+        assert_eq!(offset_of!(Rv64State, start_brk), 304);
+        assert_eq!(offset_of!(Rv64State, memory), 312);
+        assert_eq!(offset_of!(Rv64State, io), 320);
+        // Tracer ZST is here at 328, adds 0 bytes
+        assert_eq!(offset_of!(Rv64State, csrs), 328);
+        assert_eq!(offset_of!(Rv64State, mmap), 328 + 4096 * 8); // 33096
+        assert_eq!(offset_of!(Rv64State, vregs), 33096 + 8 + 128 * 16); // 35152
+        assert_eq!(offset_of!(Rv64State, trace_countdown), 35152 + VREGS_BYTES); // 39248
+        assert_eq!(offset_of!(Rv64State, htif_polls), 39248 + 8);
+        assert_eq!(offset_of!(Rv64State, golden_countdown), 39256 + 3 * 8);
+        assert_eq!(size_of::<Rv64State>(), 39280 + 2 * 8);
     }
 
     #[test]
@@ -340,6 +541,9 @@ mod tests {
         // pc is 4 bytes for RV32, instret is u64 needing 8-byte alignment
         // So there's 4 bytes of implicit padding after pc
         assert_eq!(offset_of!(RvState<Rv32, (), (), 32>, instret), 136); // 128 + 4 (pc) + 4 (padding) = 136
+        assert_eq!(offset_of!(RvState<Rv32, (), (), 32>, exit_cause), 152);
+        assert_eq!(offset_of!(RvState<Rv32, (), (), 32>, exit_info), 160);
+        assert_eq!(offset_of!(RvState<Rv32, (), (), 32>, brk), 168);
     }
 
     #[test]
@@ -357,11 +561,14 @@ mod tests {
         let tracer_size = size_of::<PreflightTracer<Rv64>>();
         assert_eq!(tracer_size, 32);
 
-        // Tracer offset is at memory + 8
-        assert_eq!(offset_of!(StateWithTracer, tracer), 312);
+        // Tracer offset is at memory + 16 (after the io pointer)
+        assert_eq!(offset_of!(StateWithTracer, tracer), 328);
         // CSRs come after tracer
-        assert_eq!(offset_of!(StateWithTracer, csrs), 312 + 32); // 344
-        assert_eq!(size_of::<StateWithTracer>(), 344 + 4096 * 8); // 33112
+        assert_eq!(offset_of!(StateWithTracer, csrs), 328 + 32); // 360
+        assert_eq!(
+            size_of::<StateWithTracer>(),
+            360 + 4096 * 8 + 8 + 128 * 16 + VREGS_BYTES + 8 + 3 * 8 + 2 * 8
+        ); // 39328
     }
 
     #[test]
@@ -394,6 +601,9 @@ mod tests {
         state.instret = 100;
         state.has_exited = 1;
         state.exit_code = 42;
+        state.exit_cause = 1;
+        state.exit_info = 0x1000;
+        state.mmap.set(&[[0x1000, 0x2000]]);
 
         state.reset();
 
@@ -401,6 +611,39 @@ mod tests {
         assert_eq!(state.instret(), 0);
         assert!(!state.has_exited());
         assert_eq!(state.exit_code(), 0);
+        assert_eq!(state.exit_cause(), 0);
+        assert_eq!(state.exit_info(), 0);
+        assert!(state.mmap.regions().is_empty());
+    }
+
+    #[test]
+    fn test_state_capture_restore() {
+        let mut state = Rv32State::new();
+        state.set_reg(5, 0xdead_beef);
+        state.set_pc(0x8000_0000);
+        state.instret = 7;
+        state.csrs[0x300] = 0x1800;
+        state.mmap.set(&[[0x1000, 0x3000], [0x8000, 0x9000]]);
+        state.vregs[16] = 0xAB;
+        let snapshot = state.capture();
+
+        state.set_reg(5, 0);
+        state.set_pc(0);
+        state.instret = 99;
+        state.csrs[0x300] = 0;
+        state.mmap.clear();
+        state.vregs[16] = 0;
+        state.set_execution_state(ExecutionStatus::Terminated, 3);
+
+        state.restore(&snapshot);
+        assert_eq!(state.get_reg(5), 0xdead_beef);
+        assert_eq!(state.pc(), 0x8000_0000);
+        assert_eq!(state.instret(), 7);
+        assert_eq!(state.csrs[0x300], 0x1800);
+        assert_eq!(state.mmap.regions(), [[0x1000, 0x3000], [0x8000, 0x9000]]);
+        assert_eq!(state.vregs[16], 0xAB);
+        assert!(state.is_running());
+        assert_eq!(state.capture(), snapshot);
     }
 
     #[test]
@@ -18,8 +18,9 @@ mod state;
 
 // Re-export state types
 pub use state::{
-    BufferedDiffIterator, BufferedDiffTracer, DebugTracer, DiffEntry, DiffTracer, DynamicTracer,
-    FfiTracer, PreflightTracer, StatsTracer, TracerState,
+    BlockProfileTracer, BufferedDiffIterator, BufferedDiffTracer, CoverageTracer, DebugTracer,
+    DiffEntry, DiffTracer, DynamicTracer, FfiTracer, GoldenTracer, PageAccessTracer,
+    PreflightTracer, StatsTracer, TracerState,
 };
 
 // Re-export FFI types
@@ -8,6 +8,7 @@
 
 // TODO: split into separate files
 use rvr_ir::Xlen;
+use rvr_trace_format::kind;
 
 /// Marker trait for FFI-safe tracer state.
 ///
@@ -22,7 +23,7 @@ pub trait TracerState: Default + Copy {
 
 // No tracer - zero-sized type, adds nothing to struct
 impl TracerState for () {
-    const KIND: u32 = 0;
+    const KIND: u32 = kind::NONE;
 }
 
 /// Preflight tracer state - records execution for replay/proofs.
@@ -63,7 +64,7 @@ impl<X: Xlen> Default for PreflightTracer<X> {
 }
 
 impl<X: Xlen> TracerState for PreflightTracer<X> {
-    const KIND: u32 = 1;
+    const KIND: u32 = kind::PREFLIGHT;
 }
 
 impl<X: Xlen> PreflightTracer<X> {
@@ -99,7 +100,7 @@ pub struct StatsTracer {
 }
 
 impl TracerState for StatsTracer {
-    const KIND: u32 = 2;
+    const KIND: u32 = kind::STATS;
 }
 
 impl StatsTracer {
@@ -127,7 +128,7 @@ pub struct FfiTracer {
 }
 
 impl TracerState for FfiTracer {
-    const KIND: u32 = 3;
+    const KIND: u32 = kind::FFI;
 }
 
 impl FfiTracer {
@@ -154,7 +155,7 @@ pub struct DynamicTracer<X: Xlen> {
 }
 
 impl<X: Xlen> TracerState for DynamicTracer<X> {
-    const KIND: u32 = 4;
+    const KIND: u32 = kind::DYNAMIC;
 }
 
 /// Debug tracer state - writes PCs to file for debugging.
@@ -188,372 +189,190 @@ impl Default for DebugTracer {
 }
 
 impl TracerState for DebugTracer {
-    const KIND: u32 = 5;
+    const KIND: u32 = kind::DEBUG;
 }
 
-/// Diff tracer state - captures single-instruction state for differential testing.
+/// Diff tracer state for `X`; see [`rvr_trace_format::DiffTracer`].
+pub type DiffTracer<X> = rvr_trace_format::DiffTracer<<X as Xlen>::Reg>;
+
+/// Buffered diff tracer entry for `X`; see [`rvr_trace_format::DiffEntry`].
+pub type DiffEntry<X> = rvr_trace_format::DiffEntry<<X as Xlen>::Reg>;
+
+/// Buffered diff tracer state for `X`; see
+/// [`rvr_trace_format::BufferedDiffTracer`].
+pub type BufferedDiffTracer<X> = rvr_trace_format::BufferedDiffTracer<<X as Xlen>::Reg>;
+
+/// Iterator over the entries of a [`BufferedDiffTracer`].
+pub type BufferedDiffIterator<'a, X> = rvr_trace_format::BufferedDiffIterator<'a, <X as Xlen>::Reg>;
+
+impl<R: Copy + Default> TracerState for rvr_trace_format::DiffTracer<R> {
+    const KIND: u32 = kind::DIFF;
+}
+
+impl<R: Copy + Default> TracerState for rvr_trace_format::BufferedDiffTracer<R> {
+    const KIND: u32 = kind::BUFFERED_DIFF;
+}
+
+/// Page access tracer state - read/written page bitmaps.
 ///
-/// Uses bounded memory (~48 bytes for RV64). Only stores the most recent
-/// instruction's effects. State is cleared on `trace_pc` and accumulated during
-/// the instruction.
+/// Each pointer is a host-allocated bitmap with one bit per guest page.
 ///
-/// Matches C struct generated by `gen_tracer_diff`:
+/// Matches C struct generated by `gen_tracer_page_access`:
 /// ```c
 /// typedef struct Tracer {
-///     uint64_t pc;
-///     uint32_t opcode;
-///     uint8_t rd;
-///     uint64_t rd_value;
-///     uint64_t mem_addr;
-///     uint64_t mem_value;
-///     uint8_t mem_width;
-///     uint8_t is_write;
-///     uint8_t has_rd;
-///     uint8_t has_mem;
-///     uint8_t valid;
+///     uint64_t* read;
+///     uint64_t* written;
+///     uint64_t* read_before_write;
 /// } Tracer;
 /// ```
 #[repr(C)]
 #[derive(Clone, Copy, Debug)]
-pub struct DiffTracer<X: Xlen> {
-    /// Program counter.
-    pub pc: X::Reg,
-    /// Raw instruction opcode.
-    pub opcode: u32,
-    /// Destination register (0 = none/x0).
-    pub rd: u8,
-    // Padding to align rd_value (3 bytes on 64-bit)
-    _pad0: [u8; 3],
-    /// Value written to rd.
-    pub rd_value: X::Reg,
-    /// Memory address accessed.
-    pub mem_addr: X::Reg,
-    /// Memory value read/written.
-    pub mem_value: X::Reg,
-    /// Memory access width (1/2/4/8 bytes).
-    pub mem_width: u8,
-    /// 1 = store, 0 = load.
-    pub is_write: u8,
-    /// Non-zero if register was written.
-    pub has_rd: u8,
-    /// Non-zero if memory was accessed.
-    pub has_mem: u8,
-    /// Non-zero if instruction was traced.
-    pub valid: u8,
-    // Padding to align struct (3 bytes on 64-bit)
-    _pad1: [u8; 3],
-}
-
-impl<X: Xlen> Default for DiffTracer<X> {
+pub struct PageAccessTracer {
+    /// Pages read at least once.
+    pub read: *mut u64,
+    /// Pages written at least once.
+    pub written: *mut u64,
+    /// Pages whose first read came before any write.
+    pub read_before_write: *mut u64,
+}
+
+impl Default for PageAccessTracer {
     fn default() -> Self {
         Self {
-            pc: X::from_u64(0),
-            opcode: 0,
-            rd: 0,
-            _pad0: [0; 3],
-            rd_value: X::from_u64(0),
-            mem_addr: X::from_u64(0),
-            mem_value: X::from_u64(0),
-            mem_width: 0,
-            is_write: 0,
-            has_rd: 0,
-            has_mem: 0,
-            valid: 0,
-            _pad1: [0; 3],
+            read: std::ptr::null_mut(),
+            written: std::ptr::null_mut(),
+            read_before_write: std::ptr::null_mut(),
         }
     }
 }
 
-impl<X: Xlen> TracerState for DiffTracer<X> {
-    const KIND: u32 = 7;
+impl TracerState for PageAccessTracer {
+    const KIND: u32 = kind::PAGE_ACCESS;
 }
 
-impl<X: Xlen> DiffTracer<X> {
-    /// Reset the tracer state (called by `trace_pc` in C).
-    pub const fn reset(&mut self) {
-        self.valid = 0;
-        self.has_rd = 0;
-        self.has_mem = 0;
-    }
-
-    /// Check if the tracer captured valid instruction state.
-    pub const fn is_valid(&self) -> bool {
-        self.valid != 0
-    }
-
-    /// Get the destination register if one was written (None for x0 or no write).
-    pub const fn get_rd(&self) -> Option<u8> {
-        if self.has_rd != 0 && self.rd != 0 {
-            Some(self.rd)
-        } else {
-            None
-        }
-    }
-
-    /// Get the value written to rd if applicable.
-    pub fn get_rd_value(&self) -> Option<u64> {
-        if self.has_rd != 0 && self.rd != 0 {
-            Some(X::to_u64(self.rd_value))
-        } else {
-            None
-        }
-    }
-
-    /// Check if memory was accessed.
-    pub const fn has_mem_access(&self) -> bool {
-        self.has_mem != 0
-    }
-
-    /// Get memory access info if applicable.
-    pub fn get_mem_access(&self) -> Option<(u64, u64, u8, bool)> {
-        if self.has_mem != 0 {
-            Some((
-                X::to_u64(self.mem_addr),
-                X::to_u64(self.mem_value),
-                self.mem_width,
-                self.is_write != 0,
-            ))
-        } else {
-            None
-        }
+impl PageAccessTracer {
+    /// Setup with the three bitmaps.
+    pub const fn setup(&mut self, read: *mut u64, written: *mut u64, read_before_write: *mut u64) {
+        self.read = read;
+        self.written = written;
+        self.read_before_write = read_before_write;
     }
 }
 
-/// Single instruction entry for buffered diff tracer.
+/// Block profile tracer state - per-block entry counters.
 ///
-/// Matches C struct:
+/// `counts` is a host-allocated array with one counter per 2-byte slot of
+/// the dispatch range (`RV_TRACER_PROFILE_SLOTS` entries).
+///
+/// Matches C struct generated by `gen_tracer_block_profile`:
 /// ```c
-/// typedef struct DiffEntry {
-///     uint64_t pc;
-///     uint32_t opcode;
-///     uint8_t rd;
-///     uint8_t has_rd;
-///     uint8_t has_mem;
-///     uint8_t is_write;
-///     uint64_t rd_value;
-///     uint64_t mem_addr;
-///     uint64_t mem_value;
-///     uint8_t mem_width;
-///     uint8_t _pad[7];
-/// } DiffEntry;
+/// typedef struct Tracer {
+///     uint64_t* counts;
+/// } Tracer;
 /// ```
 #[repr(C)]
 #[derive(Clone, Copy, Debug)]
-pub struct DiffEntry<X: Xlen> {
-    pub pc: X::Reg,
-    pub opcode: u32,
-    pub rd: u8,
-    pub has_rd: u8,
-    pub has_mem: u8,
-    pub is_write: u8,
-    pub rd_value: X::Reg,
-    pub mem_addr: X::Reg,
-    pub mem_value: X::Reg,
-    pub mem_width: u8,
-    _pad: [u8; 7],
-}
-
-impl<X: Xlen> Default for DiffEntry<X> {
+pub struct BlockProfileTracer {
+    /// Entry count per dispatch slot.
+    pub counts: *mut u64,
+}
+
+impl Default for BlockProfileTracer {
     fn default() -> Self {
         Self {
-            pc: X::from_u64(0),
-            opcode: 0,
-            rd: 0,
-            has_rd: 0,
-            has_mem: 0,
-            is_write: 0,
-            rd_value: X::from_u64(0),
-            mem_addr: X::from_u64(0),
-            mem_value: X::from_u64(0),
-            mem_width: 0,
-            _pad: [0; 7],
+            counts: std::ptr::null_mut(),
         }
     }
 }
 
-impl<X: Xlen> DiffEntry<X> {
-    /// Get the destination register if one was written (None for x0 or no write).
-    pub const fn get_rd(&self) -> Option<u8> {
-        if self.has_rd != 0 && self.rd != 0 {
-            Some(self.rd)
-        } else {
-            None
-        }
-    }
-
-    /// Get the value written to rd if applicable.
-    pub fn get_rd_value(&self) -> Option<u64> {
-        if self.has_rd != 0 && self.rd != 0 {
-            Some(X::to_u64(self.rd_value))
-        } else {
-            None
-        }
-    }
+impl TracerState for BlockProfileTracer {
+    const KIND: u32 = kind::BLOCK_PROFILE;
+}
 
-    /// Get memory access info if applicable.
-    pub fn get_mem_access(&self) -> Option<(u64, u64, u8, bool)> {
-        if self.has_mem != 0 {
-            Some((
-                X::to_u64(self.mem_addr),
-                X::to_u64(self.mem_value),
-                self.mem_width,
-                self.is_write != 0,
-            ))
-        } else {
-            None
-        }
+impl BlockProfileTracer {
+    /// Setup with the counter array.
+    pub const fn setup(&mut self, counts: *mut u64) {
+        self.counts = counts;
     }
 }
 
-/// Buffered diff tracer state - ring buffer of instruction entries.
+/// Coverage tracer state.
 ///
-/// Matches C struct:
+/// `flags` is a host-allocated array with one byte per 2-byte slot of the
+/// dispatch range (`RV_TRACER_PROFILE_SLOTS` entries). Each byte collects
+/// the executed, branch-taken and branch-not-taken bits of its instruction.
+///
+/// Matches C struct generated by `gen_tracer_coverage`:
 /// ```c
 /// typedef struct Tracer {
-///     DiffEntry* buffer;
-///     uint32_t capacity;
-///     uint32_t head;
-///     uint32_t count;
-///     uint32_t dropped;
-///     DiffEntry current;
-///     uint8_t current_valid;
-///     uint8_t _pad[7];
+///     uint8_t* flags;
 /// } Tracer;
 /// ```
 #[repr(C)]
 #[derive(Clone, Copy, Debug)]
-pub struct BufferedDiffTracer<X: Xlen> {
-    pub buffer: *mut DiffEntry<X>,
-    pub capacity: u32,
-    pub head: u32,
-    pub count: u32,
-    pub dropped: u32,
-    pub current: DiffEntry<X>,
-    pub current_valid: u8,
-    _pad: [u8; 7],
-}
-
-impl<X: Xlen> Default for BufferedDiffTracer<X> {
+pub struct CoverageTracer {
+    /// Coverage flags per dispatch slot.
+    pub flags: *mut u8,
+}
+
+impl Default for CoverageTracer {
     fn default() -> Self {
         Self {
-            buffer: std::ptr::null_mut(),
-            capacity: 0,
-            head: 0,
-            count: 0,
-            dropped: 0,
-            current: DiffEntry::default(),
-            current_valid: 0,
-            _pad: [0; 7],
+            flags: std::ptr::null_mut(),
         }
     }
 }
 
-impl<X: Xlen> TracerState for BufferedDiffTracer<X> {
-    const KIND: u32 = 8;
-}
-
-impl<X: Xlen> BufferedDiffTracer<X> {
-    /// Setup with provided buffer.
-    pub const fn setup(&mut self, buffer: *mut DiffEntry<X>, capacity: u32) {
-        self.buffer = buffer;
-        self.capacity = capacity;
-        self.head = 0;
-        self.count = 0;
-        self.dropped = 0;
-        self.current_valid = 0;
-    }
-
-    /// Number of entries captured.
-    pub const fn len(&self) -> usize {
-        self.count as usize
-    }
-
-    /// Check if any entries were dropped due to overflow.
-    pub const fn has_overflow(&self) -> bool {
-        self.dropped > 0
-    }
-
-    /// Number of entries dropped due to overflow.
-    pub const fn dropped_count(&self) -> u32 {
-        self.dropped
-    }
-
-    /// Check if buffer is empty.
-    pub const fn is_empty(&self) -> bool {
-        self.count == 0
-    }
-
-    /// Get entry at index (0 = oldest entry).
-    ///
-    /// Returns None if index is out of bounds or buffer is null.
-    pub fn get(&self, index: usize) -> Option<&DiffEntry<X>> {
-        if self.buffer.is_null() || index >= self.count as usize {
-            return None;
-        }
-        // Ring buffer: oldest entry is at (head - count) % capacity
-        let start = if self.count >= self.capacity {
-            self.head as usize
-        } else {
-            0
-        };
-        let actual_idx = (start + index) % self.capacity as usize;
-        // SAFETY: index is bounds-checked above, buffer is non-null
-        unsafe { Some(&*self.buffer.add(actual_idx)) }
-    }
-
-    /// Iterate over all captured entries in order (oldest first).
-    pub const fn iter(&self) -> BufferedDiffIterator<'_, X> {
-        BufferedDiffIterator {
-            tracer: self,
-            index: 0,
-        }
-    }
-
-    /// Reset the tracer state (keeps buffer allocation).
-    pub const fn reset(&mut self) {
-        self.head = 0;
-        self.count = 0;
-        self.dropped = 0;
-        self.current_valid = 0;
-    }
+impl TracerState for CoverageTracer {
+    const KIND: u32 = kind::COVERAGE;
 }
 
-impl<'a, X: Xlen> IntoIterator for &'a BufferedDiffTracer<X> {
-    type Item = &'a DiffEntry<X>;
-    type IntoIter = BufferedDiffIterator<'a, X>;
-
-    fn into_iter(self) -> Self::IntoIter {
-        self.iter()
+impl CoverageTracer {
+    /// Setup with the flag array.
+    pub const fn setup(&mut self, flags: *mut u8) {
+        self.flags = flags;
     }
 }
 
-/// Iterator over buffered diff entries.
-pub struct BufferedDiffIterator<'a, X: Xlen> {
-    tracer: &'a BufferedDiffTracer<X>,
-    index: usize,
+/// Golden tracer state - writes block entry register checksums to a
+/// golden trace file.
+///
+/// Matches C struct generated by `gen_tracer_golden`:
+/// ```c
+/// typedef struct Tracer {
+///     FILE* fp;
+///     uint64_t points;
+/// } Tracer;
+/// ```
+///
+/// The FILE* is managed by C code (`trace_init` opens, `trace_fini` closes).
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct GoldenTracer {
+    /// File pointer (managed by C code, NULL until `trace_init` called).
+    pub fp: *mut std::ffi::c_void,
+    /// Count of recorded golden trace points.
+    pub points: u64,
 }
 
-impl<'a, X: Xlen> Iterator for BufferedDiffIterator<'a, X> {
-    type Item = &'a DiffEntry<X>;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        let entry = self.tracer.get(self.index)?;
-        self.index += 1;
-        Some(entry)
-    }
-
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        let remaining = self.tracer.len().saturating_sub(self.index);
-        (remaining, Some(remaining))
+impl Default for GoldenTracer {
+    fn default() -> Self {
+        Self {
+            fp: std::ptr::null_mut(),
+            points: 0,
+        }
     }
 }
 
-impl<X: Xlen> ExactSizeIterator for BufferedDiffIterator<'_, X> {}
+impl TracerState for GoldenTracer {
+    const KIND: u32 = kind::GOLDEN;
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use rvr_ir::Rv64;
+    use rvr_ir::{Rv32, Rv64};
+    use rvr_trace_format::{DiffWriter, Word};
     use std::mem::size_of;
 
     #[test]
@@ -585,14 +404,21 @@ mod tests {
         assert_eq!(size_of::<DebugTracer>(), 16);
     }
 
+    #[test]
+    fn test_golden_layout() {
+        // 8 (ptr) + 8 (u64) = 16 bytes
+        assert_eq!(size_of::<GoldenTracer>(), 16);
+    }
+
     #[test]
     fn test_diff_layout() {
         use std::mem::offset_of;
 
         // pc: 8 + opcode: 4 + rd: 1 + pad: 3 + rd_value: 8 +
         // mem_addr: 8 + mem_value: 8 + mem_width: 1 + is_write: 1 +
-        // has_rd: 1 + has_mem: 1 + valid: 1 + pad: 3 = 48 bytes
-        assert_eq!(size_of::<DiffTracer<Rv64>>(), 48);
+        // has_rd: 1 + has_mem: 1 + valid: 1 + has_csr: 1 + csr: 2 +
+        // csr_value: 8 = 56 bytes
+        assert_eq!(size_of::<DiffTracer<Rv64>>(), 56);
 
         // Verify field offsets match C struct layout
         assert_eq!(offset_of!(DiffTracer<Rv64>, pc), 0);
@@ -606,6 +432,9 @@ mod tests {
         assert_eq!(offset_of!(DiffTracer<Rv64>, has_rd), 42);
         assert_eq!(offset_of!(DiffTracer<Rv64>, has_mem), 43);
         assert_eq!(offset_of!(DiffTracer<Rv64>, valid), 44);
+        assert_eq!(offset_of!(DiffTracer<Rv64>, has_csr), 45);
+        assert_eq!(offset_of!(DiffTracer<Rv64>, csr), 46);
+        assert_eq!(offset_of!(DiffTracer<Rv64>, csr_value), 48);
     }
 
     #[test]
@@ -617,6 +446,27 @@ mod tests {
         assert_eq!(<DebugTracer as TracerState>::KIND, 5);
         assert_eq!(<DiffTracer<Rv64> as TracerState>::KIND, 7);
         assert_eq!(<BufferedDiffTracer<Rv64> as TracerState>::KIND, 8);
+        assert_eq!(<PageAccessTracer as TracerState>::KIND, 9);
+        assert_eq!(<BlockProfileTracer as TracerState>::KIND, 10);
+        assert_eq!(<CoverageTracer as TracerState>::KIND, 12);
+    }
+
+    #[test]
+    fn test_page_access_layout() {
+        // 3 * 8 (ptr) = 24 bytes
+        assert_eq!(size_of::<PageAccessTracer>(), 24);
+    }
+
+    #[test]
+    fn test_block_profile_layout() {
+        // 8 (ptr) = 8 bytes
+        assert_eq!(size_of::<BlockProfileTracer>(), 8);
+    }
+
+    #[test]
+    fn test_coverage_layout() {
+        // 8 (ptr) = 8 bytes
+        assert_eq!(size_of::<CoverageTracer>(), 8);
     }
 
     #[test]
@@ -624,8 +474,9 @@ mod tests {
         use std::mem::offset_of;
 
         // pc: 8 + opcode: 4 + rd: 1 + has_rd: 1 + has_mem: 1 + is_write: 1 +
-        // rd_value: 8 + mem_addr: 8 + mem_value: 8 + mem_width: 1 + pad: 7 = 48 bytes
-        assert_eq!(size_of::<DiffEntry<Rv64>>(), 48);
+        // rd_value: 8 + mem_addr: 8 + mem_value: 8 + mem_width: 1 + has_csr: 1 +
+        // csr: 2 + pad: 4 + csr_value: 8 + instret: 8 = 64 bytes
+        assert_eq!(size_of::<DiffEntry<Rv64>>(), 64);
 
         // Verify field offsets match C struct layout
         assert_eq!(offset_of!(DiffEntry<Rv64>, pc), 0);
@@ -638,6 +489,15 @@ mod tests {
         assert_eq!(offset_of!(DiffEntry<Rv64>, mem_addr), 24);
         assert_eq!(offset_of!(DiffEntry<Rv64>, mem_value), 32);
         assert_eq!(offset_of!(DiffEntry<Rv64>, mem_width), 40);
+        assert_eq!(offset_of!(DiffEntry<Rv64>, has_csr), 41);
+        assert_eq!(offset_of!(DiffEntry<Rv64>, csr), 42);
+        assert_eq!(offset_of!(DiffEntry<Rv64>, csr_value), 48);
+        assert_eq!(offset_of!(DiffEntry<Rv64>, instret), 56);
+
+        // RV32: 4-byte registers, instret stays 8-aligned
+        assert_eq!(offset_of!(DiffEntry<Rv32>, csr_value), 32);
+        assert_eq!(offset_of!(DiffEntry<Rv32>, instret), 40);
+        assert_eq!(size_of::<DiffEntry<Rv32>>(), 48);
     }
 
     #[test]
@@ -645,8 +505,8 @@ mod tests {
         use std::mem::offset_of;
 
         // buffer: 8 + capacity: 4 + head: 4 + count: 4 + dropped: 4 +
-        // current: 48 + current_valid: 1 + pad: 7 = 80 bytes
-        assert_eq!(size_of::<BufferedDiffTracer<Rv64>>(), 80);
+        // current: 64 + current_valid: 1 + pad: 7 + instret: 8 = 104 bytes
+        assert_eq!(size_of::<BufferedDiffTracer<Rv64>>(), 104);
 
         // Verify field offsets
         assert_eq!(offset_of!(BufferedDiffTracer<Rv64>, buffer), 0);
@@ -655,7 +515,8 @@ mod tests {
         assert_eq!(offset_of!(BufferedDiffTracer<Rv64>, count), 16);
         assert_eq!(offset_of!(BufferedDiffTracer<Rv64>, dropped), 20);
         assert_eq!(offset_of!(BufferedDiffTracer<Rv64>, current), 24);
-        assert_eq!(offset_of!(BufferedDiffTracer<Rv64>, current_valid), 72);
+        assert_eq!(offset_of!(BufferedDiffTracer<Rv64>, current_valid), 88);
+        assert_eq!(offset_of!(BufferedDiffTracer<Rv64>, instret), 96);
     }
 
     #[test]
@@ -684,4 +545,52 @@ mod tests {
         let pcs: Vec<u64> = tracer.iter().map(|e| e.pc).collect();
         assert_eq!(pcs, vec![0x1000, 0x1004]);
     }
+
+    /// Entries a guest wrote with `DiffWriter` read back through the host
+    /// tracer, oldest first, for `pushed` entries into `capacity` slots.
+    fn check_guest_entries<X: Xlen>(capacity: usize, pushed: u32)
+    where
+        X::Reg: Word,
+    {
+        let size = DiffEntry::<X>::SIZE;
+        let mut bytes = vec![0; capacity * size + size / 2];
+        let mut writer = DiffWriter::<X::Reg>::new(&mut bytes);
+        let entry = |i: u32| DiffEntry::<X> {
+            pc: X::from_u64(0x1000 + 4 * u64::from(i)),
+            opcode: 0x13,
+            rd: 5,
+            has_rd: 1,
+            rd_value: X::from_u64(u64::from(i) * 3),
+            has_mem: u8::from(i.is_multiple_of(2)),
+            mem_addr: X::from_u64(0x8000),
+            mem_width: 8,
+            instret: u64::from(i),
+            ..DiffEntry::<X>::default()
+        };
+        for i in 0..pushed {
+            writer.push(&entry(i));
+        }
+        let (head, count, dropped) = (writer.head(), writer.count(), writer.dropped());
+
+        let mut entries: Vec<DiffEntry<X>> = bytes
+            .chunks_exact(size)
+            .map(DiffEntry::<X>::decode)
+            .collect();
+        let mut tracer = BufferedDiffTracer::<X>::default();
+        tracer.setup(entries.as_mut_ptr(), u32::try_from(capacity).unwrap());
+        (tracer.head, tracer.count, tracer.dropped) = (head, count, dropped);
+
+        let expected: Vec<_> = (pushed.saturating_sub(count)..pushed).map(entry).collect();
+        let read: Vec<_> = tracer.iter().copied().collect();
+        assert_eq!(read, expected, "RV{} {capacity}/{pushed}", X::VALUE);
+        assert_eq!(tracer.dropped_count(), pushed.saturating_sub(count));
+    }
+
+    #[test]
+    fn test_guest_written_entries() {
+        for (capacity, pushed) in [(4, 0), (4, 3), (4, 4), (4, 10), (1, 2)] {
+            check_guest_entries::<Rv32>(capacity, pushed);
+            check_guest_entries::<Rv64>(capacity, pushed);
+        }
+    }
 }
@@ -0,0 +1,665 @@
+//! Diff tracer records: the single-instruction [`DiffTracer`] and the ring
+//! buffer of [`DiffEntry`] records of [`BufferedDiffTracer`].
+
+use core::marker::PhantomData;
+use core::mem::{align_of, offset_of, size_of};
+
+/// Register-sized record field: `u32` on RV32, `u64` on RV64.
+pub trait Word: Copy + Default + Into<u64> + sealed::Sealed {
+    /// Write the little-endian bytes to the start of `out`.
+    fn write_le(self, out: &mut [u8]);
+
+    /// Read from the little-endian bytes at the start of `bytes`.
+    fn read_le(bytes: &[u8]) -> Self;
+}
+
+mod sealed {
+    pub trait Sealed {}
+    impl Sealed for u32 {}
+    impl Sealed for u64 {}
+}
+
+impl Word for u32 {
+    fn write_le(self, out: &mut [u8]) {
+        out[..size_of::<Self>()].copy_from_slice(&self.to_le_bytes());
+    }
+
+    fn read_le(bytes: &[u8]) -> Self {
+        let mut le = [0; size_of::<Self>()];
+        le.copy_from_slice(&bytes[..size_of::<Self>()]);
+        Self::from_le_bytes(le)
+    }
+}
+
+impl Word for u64 {
+    fn write_le(self, out: &mut [u8]) {
+        out[..size_of::<Self>()].copy_from_slice(&self.to_le_bytes());
+    }
+
+    fn read_le(bytes: &[u8]) -> Self {
+        let mut le = [0; size_of::<Self>()];
+        le.copy_from_slice(&bytes[..size_of::<Self>()]);
+        Self::from_le_bytes(le)
+    }
+}
+
+/// Diff tracer state - captures single-instruction state for differential testing.
+///
+/// Uses bounded memory (~56 bytes for RV64). Only stores the most recent
+/// instruction's effects. State is cleared on `trace_pc` and accumulated during
+/// the instruction.
+///
+/// Matches C struct generated by `gen_tracer_diff`:
+/// ```c
+/// typedef struct Tracer {
+///     uint64_t pc;
+///     uint32_t opcode;
+///     uint8_t rd;
+///     uint64_t rd_value;
+///     uint64_t mem_addr;
+///     uint64_t mem_value;
+///     uint8_t mem_width;
+///     uint8_t is_write;
+///     uint8_t has_rd;
+///     uint8_t has_mem;
+///     uint8_t valid;
+///     uint8_t has_csr;
+///     uint16_t csr;
+///     uint64_t csr_value;
+/// } Tracer;
+/// ```
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DiffTracer<R> {
+    /// Program counter.
+    pub pc: R,
+    /// Raw instruction opcode.
+    pub opcode: u32,
+    /// Destination register (0 = none/x0).
+    pub rd: u8,
+    /// Padding to align `rd_value` (3 bytes on 64-bit).
+    pub pad: [u8; 3],
+    /// Value written to rd.
+    pub rd_value: R,
+    /// Memory address accessed.
+    pub mem_addr: R,
+    /// Memory value read/written.
+    pub mem_value: R,
+    /// Memory access width (1/2/4/8 bytes).
+    pub mem_width: u8,
+    /// 1 = store, 0 = load.
+    pub is_write: u8,
+    /// Non-zero if register was written.
+    pub has_rd: u8,
+    /// Non-zero if memory was accessed.
+    pub has_mem: u8,
+    /// Non-zero if instruction was traced.
+    pub valid: u8,
+    /// Non-zero if a CSR was written.
+    pub has_csr: u8,
+    /// CSR written.
+    pub csr: u16,
+    /// Value written to the CSR.
+    pub csr_value: R,
+}
+
+impl<R: Copy + Into<u64>> DiffTracer<R> {
+    /// Reset the tracer state (called by `trace_pc` in C).
+    pub const fn reset(&mut self) {
+        self.valid = 0;
+        self.has_rd = 0;
+        self.has_mem = 0;
+        self.has_csr = 0;
+    }
+
+    /// Check if the tracer captured valid instruction state.
+    pub const fn is_valid(&self) -> bool {
+        self.valid != 0
+    }
+
+    /// Get the destination register if one was written (None for x0 or no write).
+    pub const fn get_rd(&self) -> Option<u8> {
+        if self.has_rd != 0 && self.rd != 0 {
+            Some(self.rd)
+        } else {
+            None
+        }
+    }
+
+    /// Get the value written to rd if applicable.
+    pub fn get_rd_value(&self) -> Option<u64> {
+        if self.has_rd != 0 && self.rd != 0 {
+            Some(self.rd_value.into())
+        } else {
+            None
+        }
+    }
+
+    /// Check if memory was accessed.
+    pub const fn has_mem_access(&self) -> bool {
+        self.has_mem != 0
+    }
+
+    /// Get memory access info if applicable.
+    pub fn get_mem_access(&self) -> Option<(u64, u64, u8, bool)> {
+        if self.has_mem != 0 {
+            Some((
+                self.mem_addr.into(),
+                self.mem_value.into(),
+                self.mem_width,
+                self.is_write != 0,
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Get the CSR write (csr, value) if one was recorded.
+    pub fn get_csr_write(&self) -> Option<(u16, u64)> {
+        if self.has_csr != 0 {
+            Some((self.csr, self.csr_value.into()))
+        } else {
+            None
+        }
+    }
+}
+
+/// Single instruction entry for buffered diff tracer.
+///
+/// Matches C struct:
+/// ```c
+/// typedef struct DiffEntry {
+///     uint64_t pc;
+///     uint32_t opcode;
+///     uint8_t rd;
+///     uint8_t has_rd;
+///     uint8_t has_mem;
+///     uint8_t is_write;
+///     uint64_t rd_value;
+///     uint64_t mem_addr;
+///     uint64_t mem_value;
+///     uint8_t mem_width;
+///     uint8_t has_csr;
+///     uint16_t csr;
+///     uint8_t _pad[4];
+///     uint64_t csr_value;
+///     uint64_t instret;
+/// } DiffEntry;
+/// ```
+///
+/// `instret` counts the instructions before this one since the tracer was
+/// set up. A sampled tracer records one instruction in `sample_interval`,
+/// so consecutive entries are that many instructions apart.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DiffEntry<R> {
+    /// Program counter.
+    pub pc: R,
+    /// Raw instruction opcode.
+    pub opcode: u32,
+    /// Destination register (0 = none/x0).
+    pub rd: u8,
+    /// Non-zero if register was written.
+    pub has_rd: u8,
+    /// Non-zero if memory was accessed.
+    pub has_mem: u8,
+    /// 1 = store, 0 = load.
+    pub is_write: u8,
+    /// Value written to rd.
+    pub rd_value: R,
+    /// Memory address accessed.
+    pub mem_addr: R,
+    /// Memory value read/written.
+    pub mem_value: R,
+    /// Memory access width (1/2/4/8 bytes).
+    pub mem_width: u8,
+    /// Non-zero if a CSR was written.
+    pub has_csr: u8,
+    /// CSR written.
+    pub csr: u16,
+    /// Padding to align `csr_value`.
+    pub pad: [u8; 4],
+    /// Value written to the CSR.
+    pub csr_value: R,
+    /// Instructions before this one.
+    pub instret: u64,
+}
+
+impl<R: Copy + Into<u64>> DiffEntry<R> {
+    /// Get the destination register if one was written (None for x0 or no write).
+    pub const fn get_rd(&self) -> Option<u8> {
+        if self.has_rd != 0 && self.rd != 0 {
+            Some(self.rd)
+        } else {
+            None
+        }
+    }
+
+    /// Get the value written to rd if applicable.
+    pub fn get_rd_value(&self) -> Option<u64> {
+        if self.has_rd != 0 && self.rd != 0 {
+            Some(self.rd_value.into())
+        } else {
+            None
+        }
+    }
+
+    /// Get memory access info if applicable.
+    pub fn get_mem_access(&self) -> Option<(u64, u64, u8, bool)> {
+        if self.has_mem != 0 {
+            Some((
+                self.mem_addr.into(),
+                self.mem_value.into(),
+                self.mem_width,
+                self.is_write != 0,
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Get the CSR write (csr, value) if one was recorded.
+    pub fn get_csr_write(&self) -> Option<(u16, u64)> {
+        if self.has_csr != 0 {
+            Some((self.csr, self.csr_value.into()))
+        } else {
+            None
+        }
+    }
+}
+
+impl<R: Word> DiffEntry<R> {
+    /// Size of an entry in the C ring buffer.
+    pub const SIZE: usize = size_of::<Self>();
+
+    /// Write the entry as it lies in the C ring buffer to the first
+    /// [`SIZE`](Self::SIZE) bytes of `out`, with zeroed padding.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `out` is shorter than [`SIZE`](Self::SIZE).
+    pub fn encode(&self, out: &mut [u8]) {
+        let out = &mut out[..Self::SIZE];
+        out.fill(0);
+        self.pc.write_le(&mut out[offset_of!(Self, pc)..]);
+        out[offset_of!(Self, opcode)..][..4].copy_from_slice(&self.opcode.to_le_bytes());
+        out[offset_of!(Self, rd)] = self.rd;
+        out[offset_of!(Self, has_rd)] = self.has_rd;
+        out[offset_of!(Self, has_mem)] = self.has_mem;
+        out[offset_of!(Self, is_write)] = self.is_write;
+        self.rd_value
+            .write_le(&mut out[offset_of!(Self, rd_value)..]);
+        self.mem_addr
+            .write_le(&mut out[offset_of!(Self, mem_addr)..]);
+        self.mem_value
+            .write_le(&mut out[offset_of!(Self, mem_value)..]);
+        out[offset_of!(Self, mem_width)] = self.mem_width;
+        out[offset_of!(Self, has_csr)] = self.has_csr;
+        out[offset_of!(Self, csr)..][..2].copy_from_slice(&self.csr.to_le_bytes());
+        self.csr_value
+            .write_le(&mut out[offset_of!(Self, csr_value)..]);
+        out[offset_of!(Self, instret)..][..8].copy_from_slice(&self.instret.to_le_bytes());
+    }
+
+    /// Read an entry [`encode`](Self::encode) wrote.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes` is shorter than [`SIZE`](Self::SIZE).
+    #[must_use]
+    pub fn decode(bytes: &[u8]) -> Self {
+        let bytes = &bytes[..Self::SIZE];
+        let mut opcode = [0; 4];
+        opcode.copy_from_slice(&bytes[offset_of!(Self, opcode)..][..4]);
+        let mut csr = [0; 2];
+        csr.copy_from_slice(&bytes[offset_of!(Self, csr)..][..2]);
+        let mut instret = [0; 8];
+        instret.copy_from_slice(&bytes[offset_of!(Self, instret)..][..8]);
+        Self {
+            pc: R::read_le(&bytes[offset_of!(Self, pc)..]),
+            opcode: u32::from_le_bytes(opcode),
+            rd: bytes[offset_of!(Self, rd)],
+            has_rd: bytes[offset_of!(Self, has_rd)],
+            has_mem: bytes[offset_of!(Self, has_mem)],
+            is_write: bytes[offset_of!(Self, is_write)],
+            rd_value: R::read_le(&bytes[offset_of!(Self, rd_value)..]),
+            mem_addr: R::read_le(&bytes[offset_of!(Self, mem_addr)..]),
+            mem_value: R::read_le(&bytes[offset_of!(Self, mem_value)..]),
+            mem_width: bytes[offset_of!(Self, mem_width)],
+            has_csr: bytes[offset_of!(Self, has_csr)],
+            csr: u16::from_le_bytes(csr),
+            pad: [0; 4],
+            csr_value: R::read_le(&bytes[offset_of!(Self, csr_value)..]),
+            instret: u64::from_le_bytes(instret),
+        }
+    }
+}
+
+/// Buffered diff tracer state - ring buffer of instruction entries.
+///
+/// Matches C struct:
+/// ```c
+/// typedef struct Tracer {
+///     DiffEntry* buffer;
+///     uint32_t capacity;
+///     uint32_t head;
+///     uint32_t count;
+///     uint32_t dropped;
+///     DiffEntry current;
+///     uint8_t current_valid;
+///     uint8_t _pad[7];
+///     uint64_t instret;
+/// } Tracer;
+/// ```
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct BufferedDiffTracer<R> {
+    /// Ring buffer of `capacity` entries.
+    pub buffer: *mut DiffEntry<R>,
+    /// Entries the buffer holds.
+    pub capacity: u32,
+    /// Slot of the next entry.
+    pub head: u32,
+    /// Valid entries, at most `capacity`.
+    pub count: u32,
+    /// Entries overwritten after the buffer filled up.
+    pub dropped: u32,
+    /// Entry of the instruction in progress.
+    pub current: DiffEntry<R>,
+    /// Non-zero if `current` has data.
+    pub current_valid: u8,
+    /// Padding to align `instret`.
+    pub pad: [u8; 7],
+    /// Instructions seen since setup, including those a sampled tracer
+    /// skipped.
+    pub instret: u64,
+}
+
+impl<R: Default> Default for BufferedDiffTracer<R> {
+    fn default() -> Self {
+        Self {
+            buffer: core::ptr::null_mut(),
+            capacity: 0,
+            head: 0,
+            count: 0,
+            dropped: 0,
+            current: DiffEntry::default(),
+            current_valid: 0,
+            pad: [0; 7],
+            instret: 0,
+        }
+    }
+}
+
+impl<R> BufferedDiffTracer<R> {
+    /// Setup with provided buffer.
+    pub const fn setup(&mut self, buffer: *mut DiffEntry<R>, capacity: u32) {
+        self.buffer = buffer;
+        self.capacity = capacity;
+        self.head = 0;
+        self.count = 0;
+        self.dropped = 0;
+        self.current_valid = 0;
+        self.instret = 0;
+    }
+
+    /// Number of entries captured.
+    pub const fn len(&self) -> usize {
+        self.count as usize
+    }
+
+    /// Check if any entries were dropped due to overflow.
+    pub const fn has_overflow(&self) -> bool {
+        self.dropped > 0
+    }
+
+    /// Number of entries dropped due to overflow.
+    pub const fn dropped_count(&self) -> u32 {
+        self.dropped
+    }
+
+    /// Check if buffer is empty.
+    pub const fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Get entry at index (0 = oldest entry).
+    ///
+    /// Returns None if index is out of bounds or buffer is null.
+    pub fn get(&self, index: usize) -> Option<&DiffEntry<R>> {
+        if self.buffer.is_null() || index >= self.count as usize {
+            return None;
+        }
+        // Ring buffer: oldest entry is at (head - count) % capacity
+        let start = if self.count >= self.capacity {
+            self.head as usize
+        } else {
+            0
+        };
+        let actual_idx = (start + index) % self.capacity as usize;
+        // SAFETY: index is bounds-checked above, buffer is non-null
+        unsafe { Some(&*self.buffer.add(actual_idx)) }
+    }
+
+    /// Iterate over all captured entries in order (oldest first).
+    pub const fn iter(&self) -> BufferedDiffIterator<'_, R> {
+        BufferedDiffIterator {
+            tracer: self,
+            index: 0,
+        }
+    }
+
+    /// Reset the tracer state (keeps buffer allocation and the instruction
+    /// count, so entries after a reset continue its instret).
+    pub const fn reset(&mut self) {
+        self.head = 0;
+        self.count = 0;
+        self.dropped = 0;
+        self.current_valid = 0;
+    }
+}
+
+impl<'a, R> IntoIterator for &'a BufferedDiffTracer<R> {
+    type Item = &'a DiffEntry<R>;
+    type IntoIter = BufferedDiffIterator<'a, R>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over buffered diff entries.
+///
+/// Entries of a sampled tracer are not consecutive instructions; use
+/// [`DiffEntry::instret`] to tell how far apart they are.
+pub struct BufferedDiffIterator<'a, R> {
+    tracer: &'a BufferedDiffTracer<R>,
+    index: usize,
+}
+
+impl<'a, R> Iterator for BufferedDiffIterator<'a, R> {
+    type Item = &'a DiffEntry<R>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry = self.tracer.get(self.index)?;
+        self.index += 1;
+        Some(entry)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.tracer.len().saturating_sub(self.index);
+        (remaining, Some(remaining))
+    }
+}
+
+impl<R> ExactSizeIterator for BufferedDiffIterator<'_, R> {}
+
+/// Writer of a buffered diff ring buffer into bytes, for guests.
+///
+/// Entries go to the slots the C tracer would use, in the layout of
+/// [`DiffEntry`], so the host reads a copy of the buffer through a
+/// [`BufferedDiffTracer`] with this writer's `capacity`, `head`, `count`
+/// and `dropped`.
+#[derive(Debug)]
+pub struct DiffWriter<'a, R> {
+    buffer: &'a mut [u8],
+    capacity: u32,
+    head: u32,
+    count: u32,
+    dropped: u32,
+    _reg: PhantomData<R>,
+}
+
+impl<'a, R: Word> DiffWriter<'a, R> {
+    /// Ring buffer of as many entries as fit in `buffer`.
+    pub fn new(buffer: &'a mut [u8]) -> Self {
+        let capacity = u32::try_from(buffer.len() / DiffEntry::<R>::SIZE).unwrap_or(u32::MAX);
+        Self {
+            buffer,
+            capacity,
+            head: 0,
+            count: 0,
+            dropped: 0,
+            _reg: PhantomData,
+        }
+    }
+
+    /// Append `entry`, overwriting the oldest one if the buffer is full.
+    /// Without room for any entry, it is dropped.
+    pub fn push(&mut self, entry: &DiffEntry<R>) {
+        if self.capacity == 0 {
+            self.dropped = self.dropped.wrapping_add(1);
+            return;
+        }
+        let start = self.head as usize * DiffEntry::<R>::SIZE;
+        entry.encode(&mut self.buffer[start..]);
+        self.head = (self.head + 1) % self.capacity;
+        if self.count < self.capacity {
+            self.count += 1;
+        } else {
+            self.dropped = self.dropped.wrapping_add(1);
+        }
+    }
+
+    /// Entries the buffer holds.
+    #[must_use]
+    pub const fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    /// Slot of the next entry.
+    #[must_use]
+    pub const fn head(&self) -> u32 {
+        self.head
+    }
+
+    /// Valid entries, at most `capacity`.
+    #[must_use]
+    pub const fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Entries overwritten after the buffer filled up.
+    #[must_use]
+    pub const fn dropped(&self) -> u32 {
+        self.dropped
+    }
+}
+
+// FFI layouts the generated C structs are checked against
+const _: () = {
+    assert!(size_of::<DiffTracer<u64>>() == 56);
+    assert!(align_of::<DiffTracer<u64>>() == 8);
+    assert!(offset_of!(DiffTracer<u64>, rd_value) == 16);
+    assert!(offset_of!(DiffTracer<u64>, mem_width) == 40);
+    assert!(offset_of!(DiffTracer<u64>, csr) == 46);
+    assert!(offset_of!(DiffTracer<u64>, csr_value) == 48);
+    assert!(size_of::<DiffTracer<u32>>() == 36);
+    assert!(align_of::<DiffTracer<u32>>() == 4);
+    assert!(offset_of!(DiffTracer<u32>, rd_value) == 12);
+    assert!(offset_of!(DiffTracer<u32>, mem_width) == 24);
+    assert!(offset_of!(DiffTracer<u32>, csr) == 30);
+    assert!(offset_of!(DiffTracer<u32>, csr_value) == 32);
+
+    assert!(size_of::<DiffEntry<u64>>() == 64);
+    assert!(align_of::<DiffEntry<u64>>() == 8);
+    assert!(offset_of!(DiffEntry<u64>, rd_value) == 16);
+    assert!(offset_of!(DiffEntry<u64>, mem_width) == 40);
+    assert!(offset_of!(DiffEntry<u64>, csr) == 42);
+    assert!(offset_of!(DiffEntry<u64>, csr_value) == 48);
+    assert!(offset_of!(DiffEntry<u64>, instret) == 56);
+    // RV32: 4-byte registers, instret stays 8-aligned
+    assert!(size_of::<DiffEntry<u32>>() == 48);
+    assert!(align_of::<DiffEntry<u32>>() == 8);
+    assert!(offset_of!(DiffEntry<u32>, rd_value) == 12);
+    assert!(offset_of!(DiffEntry<u32>, mem_width) == 24);
+    assert!(offset_of!(DiffEntry<u32>, csr) == 26);
+    assert!(offset_of!(DiffEntry<u32>, csr_value) == 32);
+    assert!(offset_of!(DiffEntry<u32>, instret) == 40);
+};
+
+// The tracer holds a host pointer, so its layout is only fixed per host
+#[cfg(target_pointer_width = "64")]
+const _: () = {
+    assert!(size_of::<BufferedDiffTracer<u64>>() == 104);
+    assert!(offset_of!(BufferedDiffTracer<u64>, capacity) == 8);
+    assert!(offset_of!(BufferedDiffTracer<u64>, dropped) == 20);
+    assert!(offset_of!(BufferedDiffTracer<u64>, current) == 24);
+    assert!(offset_of!(BufferedDiffTracer<u64>, current_valid) == 88);
+    assert!(offset_of!(BufferedDiffTracer<u64>, instret) == 96);
+    assert!(size_of::<BufferedDiffTracer<u32>>() == 88);
+    assert!(offset_of!(BufferedDiffTracer<u32>, current) == 24);
+    assert!(offset_of!(BufferedDiffTracer<u32>, current_valid) == 72);
+    assert!(offset_of!(BufferedDiffTracer<u32>, instret) == 80);
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry<R: Word + From<u32>>(pc: u32) -> DiffEntry<R> {
+        DiffEntry {
+            pc: R::from(pc),
+            opcode: 0x00b5_0533,
+            rd: 10,
+            has_rd: 1,
+            rd_value: R::from(pc ^ 0xffff_0000),
+            mem_addr: R::from(0x2000),
+            mem_width: 4,
+            has_csr: 1,
+            csr: 0x300,
+            csr_value: R::from(0x80),
+            instret: u64::from(pc) << 32,
+            ..DiffEntry::default()
+        }
+    }
+
+    fn check_encode<R: Word + From<u32> + core::fmt::Debug + Eq>() {
+        let entry = entry::<R>(0x8000_1234);
+        let mut bytes = [0xaa; 80];
+        entry.encode(&mut bytes);
+        assert_eq!(DiffEntry::<R>::decode(&bytes), entry);
+        assert_eq!(bytes[..4], 0x8000_1234u32.to_le_bytes());
+        assert!(bytes[DiffEntry::<R>::SIZE..].iter().all(|&b| b == 0xaa));
+        // Padding is zeroed
+        let pad = offset_of!(DiffEntry<R>, pad);
+        assert_eq!(bytes[pad..offset_of!(DiffEntry<R>, csr_value)], [0; 4]);
+    }
+
+    #[test]
+    fn test_diff_entry_encode() {
+        check_encode::<u32>();
+        check_encode::<u64>();
+    }
+
+    #[test]
+    fn test_diff_writer_without_room() {
+        let mut bytes = [0; 47];
+        let mut writer = DiffWriter::<u32>::new(&mut bytes);
+        writer.push(&entry(0x1000));
+        assert_eq!(
+            (writer.capacity(), writer.count(), writer.dropped()),
+            (0, 0, 1)
+        );
+    }
+}
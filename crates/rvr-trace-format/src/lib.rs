@@ -0,0 +1,119 @@
+//! Layouts of the records rvr tracers write, shared by the host and guests.
+//!
+//! The generated C tracers, the host runner (`rvr-state`) and guest-side
+//! instrumentation (`rvr-rt`, feature `trace`) all read or write these
+//! records, so they live in this `no_std` crate without dependencies.
+//! Register-sized fields are generic over the register type: `u32` for
+//! RV32 and `u64` for RV64. Const assertions pin the layouts the C emitter
+//! checks the generated structs against.
+//!
+//! A guest writes diff entries with [`DiffWriter`] into a byte buffer laid
+//! out like the C ring buffer, and the host reads it back through
+//! [`BufferedDiffTracer`].
+
+#![no_std]
+
+mod diff;
+
+pub use diff::{BufferedDiffIterator, BufferedDiffTracer, DiffEntry, DiffTracer, DiffWriter, Word};
+
+/// `RV_TRACER_KIND` ids of the built-in tracers.
+pub mod kind {
+    /// No tracer.
+    pub const NONE: u32 = 0;
+    /// Preflight tracer.
+    pub const PREFLIGHT: u32 = 1;
+    /// Stats tracer.
+    pub const STATS: u32 = 2;
+    /// FFI tracer.
+    pub const FFI: u32 = 3;
+    /// Dynamic tracer.
+    pub const DYNAMIC: u32 = 4;
+    /// Debug tracer.
+    pub const DEBUG: u32 = 5;
+    /// Spike-format tracer.
+    pub const SPIKE: u32 = 6;
+    /// Single-instruction diff tracer.
+    pub const DIFF: u32 = 7;
+    /// Buffered diff tracer.
+    pub const BUFFERED_DIFF: u32 = 8;
+    /// Page access tracer.
+    pub const PAGE_ACCESS: u32 = 9;
+    /// Block profile tracer.
+    pub const BLOCK_PROFILE: u32 = 10;
+    /// Binary trace tracer.
+    pub const BINARY_TRACE: u32 = 11;
+    /// Coverage tracer.
+    pub const COVERAGE: u32 = 12;
+    /// Golden trace tracer.
+    pub const GOLDEN: u32 = 13;
+    /// A custom tracer header.
+    pub const CUSTOM: u32 = 255;
+}
+
+/// Magic bytes opening a binary trace file.
+pub const BINARY_TRACE_MAGIC: [u8; 8] = *b"RVRTRACE";
+
+/// Binary trace format version, bumped on any record or header change.
+pub const BINARY_TRACE_VERSION: u32 = 1;
+
+/// Size of the binary trace header: magic, version (u32), xlen (u32) and
+/// ELF hash (u64).
+pub const BINARY_TRACE_HEADER_SIZE: usize = 24;
+
+/// Binary trace record flag: `rd`/`rd_value` are valid.
+pub const TRACE_FLAG_RD: u8 = 1 << 0;
+/// Binary trace record flag: `mem_addr` is valid.
+pub const TRACE_FLAG_MEM: u8 = 1 << 1;
+/// Binary trace record flag: `csr`/`csr_value` are valid.
+pub const TRACE_FLAG_CSR: u8 = 1 << 2;
+
+/// Size of one binary trace record for `xlen`: instret (u64), four
+/// XLEN-wide fields (pc, `rd_value`, `mem_addr`, `csr_value`), opcode (u32),
+/// csr (u16), rd (u8) and flags (u8).
+#[must_use]
+pub const fn binary_trace_record_size(xlen: u8) -> usize {
+    let fixed = size_of::<u64>() + size_of::<u32>() + size_of::<u16>() + 2 * size_of::<u8>();
+    fixed + 4 * (xlen as usize / u8::BITS as usize)
+}
+
+/// Magic bytes opening a golden trace file.
+pub const GOLDEN_TRACE_MAGIC: [u8; 8] = *b"RVRGOLDN";
+
+/// Golden trace format version, bumped on any record, header or checksum
+/// change.
+pub const GOLDEN_TRACE_VERSION: u32 = 1;
+
+/// Size of the golden trace header: magic, version (u32), block sample
+/// interval (u32) and ELF hash (u64).
+pub const GOLDEN_TRACE_HEADER_SIZE: usize = 24;
+
+/// Size of one golden trace record: block entry PC (u64) and register
+/// checksum (u64).
+pub const GOLDEN_TRACE_RECORD_SIZE: usize = 16;
+
+/// Register checksum before any register is folded in.
+pub const GOLDEN_CHECKSUM_SEED: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// Multiplier of [`golden_mix`].
+pub const GOLDEN_CHECKSUM_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Rotation of [`golden_mix`], so high register bits reach the low bits of
+/// later steps.
+pub const GOLDEN_CHECKSUM_ROTATE: u32 = 29;
+
+/// Fold one register, zero-extended to 64 bits, into a golden checksum.
+/// Block entries fold `x1` up to the last register in order.
+#[must_use]
+pub const fn golden_mix(sum: u64, value: u64) -> u64 {
+    (sum ^ value)
+        .wrapping_mul(GOLDEN_CHECKSUM_PRIME)
+        .rotate_left(GOLDEN_CHECKSUM_ROTATE)
+}
+
+/// Coverage flag: the instruction in the slot executed.
+pub const COVERAGE_EXECUTED: u8 = 1 << 0;
+/// Coverage flag: the conditional branch in the slot was taken.
+pub const COVERAGE_TAKEN: u8 = 1 << 1;
+/// Coverage flag: the conditional branch in the slot fell through.
+pub const COVERAGE_NOT_TAKEN: u8 = 1 << 2;
@@ -0,0 +1,216 @@
+#![feature(test)]
+//! Address translation cost on load/store-heavy guests, per address mode.
+//!
+//! `memcpy` copies [`COPY_BYTES`] with doubleword loads and stores
+//! [`COPY_PASSES`] times; `qsort` fills [`SORT_LEN`] doublewords with xorshift
+//! values, quicksorts them and checks the order. Set `RVR_BENCH_BACKEND=x86`
+//! (or `arm64`) to bench an assembly backend.
+
+extern crate test;
+
+use std::path::{Path, PathBuf};
+
+use rvr::test_support::guest::{
+    ECALL, FUNCT3_BGEU, FUNCT3_BLTU, FUNCT3_BNE, FUNCT3_SLLI, FUNCT3_SRLI, FUNCT3_XOR,
+    OPCODE_BRANCH, OPCODE_JAL, OPCODE_JALR, OPCODE_OP, OPCODE_OP_IMM, addi, code, ld, li, lui, sd,
+};
+use rvr::{AddressMode, CompileOptions, Runner};
+use rvr_elf::{ElfWriter, PF_R, PF_X, STT_NOTYPE};
+use rvr_emit::Backend;
+use rvr_isa::{
+    REG_A0, REG_A1, REG_A2, REG_A7, REG_RA, REG_S1, REG_SP, REG_T0, REG_T1, REG_T2, REG_T3, REG_T4,
+    REG_T5, REG_T6, REG_ZERO, Rv64, encode_b, encode_i, encode_j, encode_r,
+};
+use test::Bencher;
+
+/// Bytes copied per `memcpy` pass.
+const COPY_BYTES: u64 = 0x1_0000;
+/// `memcpy` passes per guest run.
+const COPY_PASSES: u64 = 256;
+/// Doublewords sorted per `qsort` run.
+const SORT_LEN: u64 = 0x1_0000;
+
+const START: u64 = 0x1000;
+/// Source buffer of `memcpy` and array of `qsort`.
+const DATA: u64 = 0x10_0000;
+/// Destination buffer of `memcpy`.
+const COPY_DST: u64 = 0x20_0000;
+const STACK_TOP: u64 = 0x80_0000;
+const EXIT_SYSCALL: u64 = 93;
+
+/// Branch `insns` instructions away.
+const fn branch(funct3: u8, rs1: u8, rs2: u8, insns: i32) -> u32 {
+    encode_b(OPCODE_BRANCH, funct3, rs1, rs2, insns * 4)
+}
+
+/// Jump `insns` instructions away, linking `rd`.
+const fn jal(rd: u8, insns: i32) -> u32 {
+    encode_j(OPCODE_JAL, rd, insns * 4)
+}
+
+/// Exit with `code`.
+const fn exit(code: u64) -> [u32; 3] {
+    [li(REG_A0, code), li(REG_A7, EXIT_SYSCALL), ECALL]
+}
+
+fn memcpy_text() -> Vec<u32> {
+    let mut text = vec![
+        li(REG_S1, COPY_PASSES),
+        // pass:
+        lui(REG_A0, DATA),
+        lui(REG_A1, COPY_DST),
+        lui(REG_A2, DATA + COPY_BYTES),
+        // copy:
+        ld(REG_T0, REG_A0, 0),
+        sd(REG_T0, REG_A1, 0),
+        addi(REG_A0, REG_A0, 8),
+        addi(REG_A1, REG_A1, 8),
+        branch(FUNCT3_BNE, REG_A0, REG_A2, -4),
+        addi(REG_S1, REG_S1, -1),
+        branch(FUNCT3_BNE, REG_S1, REG_ZERO, -9),
+    ];
+    text.extend(exit(0));
+    text
+}
+
+/// Fill, sort and check; exits 1 if the array is out of order.
+fn qsort_text() -> Vec<u32> {
+    let end = DATA + SORT_LEN * 8;
+    let mut text = vec![
+        lui(REG_A0, DATA),
+        lui(REG_A1, end),
+        li(REG_T0, 1),
+        // fill: xorshift64
+        encode_i(OPCODE_OP_IMM, REG_T1, FUNCT3_SLLI, REG_T0, 13),
+        encode_r(OPCODE_OP, REG_T0, FUNCT3_XOR, REG_T0, REG_T1, 0),
+        encode_i(OPCODE_OP_IMM, REG_T1, FUNCT3_SRLI, REG_T0, 7),
+        encode_r(OPCODE_OP, REG_T0, FUNCT3_XOR, REG_T0, REG_T1, 0),
+        encode_i(OPCODE_OP_IMM, REG_T1, FUNCT3_SLLI, REG_T0, 17),
+        encode_r(OPCODE_OP, REG_T0, FUNCT3_XOR, REG_T0, REG_T1, 0),
+        sd(REG_T0, REG_A0, 0),
+        addi(REG_A0, REG_A0, 8),
+        branch(FUNCT3_BNE, REG_A0, REG_A1, -8),
+        lui(REG_A0, DATA),
+        addi(REG_A1, REG_A1, -8),
+        jal(REG_RA, 15),
+        lui(REG_A0, DATA),
+        lui(REG_A1, end),
+        addi(REG_A1, REG_A1, -8),
+        // check:
+        ld(REG_T0, REG_A0, 0),
+        ld(REG_T1, REG_A0, 8),
+        branch(FUNCT3_BLTU, REG_T1, REG_T0, 6),
+        addi(REG_A0, REG_A0, 8),
+        branch(FUNCT3_BNE, REG_A0, REG_A1, -4),
+    ];
+    text.extend(exit(0));
+    text.extend(exit(1));
+    text.extend(QUICKSORT);
+    text
+}
+
+/// Lomuto quicksort of the doublewords from a0 to a1 (inclusive), recursing
+/// on the left part and looping on the right one.
+const QUICKSORT: [u32; 29] = [
+    branch(FUNCT3_BGEU, REG_A0, REG_A1, 28),
+    addi(REG_SP, REG_SP, -32),
+    sd(REG_RA, REG_SP, 0),
+    sd(REG_A1, REG_SP, 8),
+    ld(REG_T2, REG_A1, 0),
+    addi(REG_T3, REG_A0, 0),
+    addi(REG_T4, REG_A0, 0),
+    // partition:
+    branch(FUNCT3_BGEU, REG_T4, REG_A1, 9),
+    ld(REG_T5, REG_T4, 0),
+    branch(FUNCT3_BGEU, REG_T5, REG_T2, 5),
+    ld(REG_T6, REG_T3, 0),
+    sd(REG_T5, REG_T3, 0),
+    sd(REG_T6, REG_T4, 0),
+    addi(REG_T3, REG_T3, 8),
+    addi(REG_T4, REG_T4, 8),
+    jal(REG_ZERO, -8),
+    // place the pivot and sort the left part
+    ld(REG_T5, REG_T3, 0),
+    sd(REG_T2, REG_T3, 0),
+    sd(REG_T5, REG_A1, 0),
+    sd(REG_T3, REG_SP, 16),
+    addi(REG_A1, REG_T3, -8),
+    jal(REG_RA, -21),
+    ld(REG_T3, REG_SP, 16),
+    addi(REG_A0, REG_T3, 8),
+    ld(REG_A1, REG_SP, 8),
+    ld(REG_RA, REG_SP, 0),
+    addi(REG_SP, REG_SP, 32),
+    jal(REG_ZERO, -27),
+    encode_i(OPCODE_JALR, REG_ZERO, 0, REG_RA, 0),
+];
+
+fn guest_elf(text: &[u32]) -> Vec<u8> {
+    let text = code(text);
+    let size = text.len() as u64;
+    ElfWriter::<Rv64>::new(START)
+        .with_segment(START, PF_R | PF_X, text)
+        .with_function("_start", START, size)
+        .with_symbol("__stack_top", STACK_TOP, STT_NOTYPE)
+        .build()
+}
+
+fn backend() -> Backend {
+    match std::env::var("RVR_BENCH_BACKEND").as_deref() {
+        Ok("x86" | "x86_64") => Backend::X86Asm,
+        Ok("arm64" | "aarch64") => Backend::ARM64Asm,
+        _ => Backend::C,
+    }
+}
+
+/// Compile `text` with `mode` into a temp dir and load it.
+fn load(dir: &Path, text: &[u32], mode: AddressMode) -> Runner {
+    let elf: PathBuf = dir.join("guest.elf");
+    std::fs::write(&elf, guest_elf(text)).expect("write ELF");
+    let out = dir.join("out");
+    let options = CompileOptions::new()
+        .with_backend(backend())
+        .with_address_mode(mode)
+        .with_quiet(true);
+    rvr::compile_with_options(&elf, &out, &options).expect("compile");
+    Runner::load(&out, &elf).expect("load runner")
+}
+
+fn bench_guest(b: &mut Bencher, text: &[u32], mode: AddressMode) {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let mut runner = load(temp.path(), text, mode);
+    b.iter(|| {
+        let result = runner.run().expect("run guest");
+        assert_eq!(result.exit_code, 0);
+    });
+}
+
+#[bench]
+fn bench_memcpy_unchecked(b: &mut Bencher) {
+    bench_guest(b, &memcpy_text(), AddressMode::Unchecked);
+}
+
+#[bench]
+fn bench_memcpy_wrap(b: &mut Bencher) {
+    bench_guest(b, &memcpy_text(), AddressMode::Wrap);
+}
+
+#[bench]
+fn bench_memcpy_bounds(b: &mut Bencher) {
+    bench_guest(b, &memcpy_text(), AddressMode::Bounds);
+}
+
+#[bench]
+fn bench_qsort_unchecked(b: &mut Bencher) {
+    bench_guest(b, &qsort_text(), AddressMode::Unchecked);
+}
+
+#[bench]
+fn bench_qsort_wrap(b: &mut Bencher) {
+    bench_guest(b, &qsort_text(), AddressMode::Wrap);
+}
+
+#[bench]
+fn bench_qsort_bounds(b: &mut Bencher) {
+    bench_guest(b, &qsort_text(), AddressMode::Bounds);
+}
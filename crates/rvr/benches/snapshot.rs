@@ -0,0 +1,52 @@
+#![feature(test)]
+//! Snapshot restore latency on a 1 GiB guest memory with a sparse dirty set.
+//!
+//! Each iteration dirties 4% of the pages and then resets memory, which is
+//! what a fuzzing loop pays per input. Compare `restore` against `clear` to see
+//! the cost of copy-on-write restore versus touching the whole region.
+
+extern crate test;
+
+use rvr_state::GuardedMemory;
+use test::Bencher;
+
+/// Guest memory size (1 GiB).
+const MEMORY_SIZE: usize = 1 << 30;
+/// Host page size used to spread writes.
+const PAGE_SIZE: usize = 4096;
+/// Dirty one page out of every `DIRTY_STRIDE` (4%).
+const DIRTY_STRIDE: usize = 25;
+
+fn populated_memory() -> GuardedMemory {
+    let mut memory = GuardedMemory::new(MEMORY_SIZE).expect("failed to allocate guest memory");
+    // Give the snapshot real contents: one quarter of memory is initialized.
+    for offset in (0..MEMORY_SIZE / 4).step_by(PAGE_SIZE) {
+        unsafe { memory.write_u8(offset, 1) };
+    }
+    memory
+}
+
+fn dirty_pages(memory: &mut GuardedMemory) {
+    for offset in (0..MEMORY_SIZE).step_by(PAGE_SIZE * DIRTY_STRIDE) {
+        unsafe { memory.write_u8(offset, 0xA5) };
+    }
+}
+
+#[bench]
+fn bench_snapshot_restore(b: &mut Bencher) {
+    let mut memory = populated_memory();
+    let snapshot = memory.snapshot().expect("failed to snapshot memory");
+    b.iter(|| {
+        dirty_pages(&mut memory);
+        memory.restore(&snapshot).expect("failed to restore memory");
+    });
+}
+
+#[bench]
+fn bench_full_clear(b: &mut Bencher) {
+    let mut memory = populated_memory();
+    b.iter(|| {
+        dirty_pages(&mut memory);
+        memory.clear();
+    });
+}
@@ -64,6 +64,9 @@ fn bench_output_dir(
         .join(suffix)
 }
 
+/// mmap arena for Linux-syscall benchmarks.
+const BENCH_MMAP_SIZE: u64 = 64 << 20;
+
 fn compile_options(info: &BenchmarkInfo, backend: Backend) -> CompileOptions {
     let mut options = CompileOptions::new()
         .with_compiler(Compiler::default())
@@ -77,7 +80,9 @@ fn compile_options(info: &BenchmarkInfo, backend: Backend) -> CompileOptions {
             options = options.with_htif(true);
         }
         BenchmarkSource::Libriscv | BenchmarkSource::Coremark => {
-            options = options.with_syscall_mode(SyscallMode::Linux);
+            options = options
+                .with_syscall_mode(SyscallMode::Linux)
+                .with_mmap_size(BENCH_MMAP_SIZE);
         }
         _ => {}
     }
@@ -37,56 +37,56 @@ pub const BENCHMARKS: &[BenchmarkInfo] = &[
     BenchmarkInfo {
         name: "towers",
         uses_exports: false,
-        default_archs: "rv64i",
+        default_archs: "rv32i,rv64i",
         source: BenchmarkSource::RiscvTests,
     },
     BenchmarkInfo {
         name: "qsort",
         uses_exports: false,
-        default_archs: "rv64i",
+        default_archs: "rv32i,rv64i",
         source: BenchmarkSource::RiscvTests,
     },
     BenchmarkInfo {
         name: "rsort",
         uses_exports: false,
-        default_archs: "rv64i",
+        default_archs: "rv32i,rv64i",
         source: BenchmarkSource::RiscvTests,
     },
     BenchmarkInfo {
         name: "median",
         uses_exports: false,
-        default_archs: "rv64i",
+        default_archs: "rv32i,rv64i",
         source: BenchmarkSource::RiscvTests,
     },
     BenchmarkInfo {
         name: "multiply",
         uses_exports: false,
-        default_archs: "rv64i",
+        default_archs: "rv32i,rv64i",
         source: BenchmarkSource::RiscvTests,
     },
     BenchmarkInfo {
         name: "vvadd",
         uses_exports: false,
-        default_archs: "rv64i",
+        default_archs: "rv32i,rv64i",
         source: BenchmarkSource::RiscvTests,
     },
     BenchmarkInfo {
         name: "memcpy",
         uses_exports: false,
-        default_archs: "rv64i",
+        default_archs: "rv32i,rv64i",
         source: BenchmarkSource::RiscvTests,
     },
     BenchmarkInfo {
         name: "dhrystone",
         uses_exports: false,
-        default_archs: "rv64i",
+        default_archs: "rv32i,rv64i",
         source: BenchmarkSource::RiscvTests,
     },
     // libriscv benchmarks (use Linux syscalls, not HTIF)
     BenchmarkInfo {
         name: "fib",
         uses_exports: false,
-        default_archs: "rv64i",
+        default_archs: "rv32i,rv64i",
         source: BenchmarkSource::Libriscv,
     },
     BenchmarkInfo {
@@ -99,7 +99,7 @@ pub const BENCHMARKS: &[BenchmarkInfo] = &[
     BenchmarkInfo {
         name: "coremark",
         uses_exports: false,
-        default_archs: "rv64i",
+        default_archs: "rv32i,rv64i",
         source: BenchmarkSource::Coremark,
     },
     // polkavm benchmarks
@@ -0,0 +1,104 @@
+#![feature(test)]
+//! ECALL round-trip latency: a Linux guest making [`NULL_SYSCALLS`] syscalls
+//! that do no work, then exiting.
+//!
+//! `brk(0)` calls into the `rv_sys_brk` runtime, so it pays for spilling and
+//! reloading hot registers around the call; `getpid` is lowered inline.
+//! Divide ns/iter by [`NULL_SYSCALLS`] for the per-call cost. Set
+//! `RVR_BENCH_BACKEND=x86` (or `arm64`) to bench an assembly backend.
+
+extern crate test;
+
+use std::path::{Path, PathBuf};
+
+use rvr::{CompileOptions, Runner, SyscallMode};
+use rvr_elf::{ElfWriter, PF_R, PF_X, STT_NOTYPE};
+use rvr_emit::Backend;
+use rvr_isa::{
+    REG_A0, REG_A7, REG_T1, REG_ZERO, Rv64, encode_b, encode_i, encode_u,
+    syscalls::syscall_nr::{SYS_BRK, SYS_EXIT, SYS_GETPID},
+};
+use test::Bencher;
+
+/// Syscalls per guest run.
+const NULL_SYSCALLS: u32 = 10_000_000;
+
+const OPCODE_LUI: u8 = 0b011_0111;
+const OPCODE_OP_IMM: u8 = 0b001_0011;
+const OPCODE_BRANCH: u8 = 0b110_0011;
+const OPCODE_SYSTEM: u8 = 0b111_0011;
+const FUNCT3_BNE: u8 = 0b001;
+const ECALL: u32 = encode_i(OPCODE_SYSTEM, REG_ZERO, 0, REG_ZERO, 0);
+
+const START: u64 = 0x1000;
+const STACK_TOP: u64 = 0x10_0000;
+
+fn li(rd: u8, imm: u64) -> u32 {
+    encode_i(OPCODE_OP_IMM, rd, 0, REG_ZERO, i32::try_from(imm).unwrap())
+}
+
+/// Loop `NULL_SYSCALLS` times over `a0 = 0; ecall(num)`, then exit 0.
+fn guest_elf(num: u64) -> Vec<u8> {
+    let hi = NULL_SYSCALLS >> 12;
+    let lo = i32::try_from(NULL_SYSCALLS & 0xfff).unwrap();
+    let text = [
+        encode_u(OPCODE_LUI, REG_T1, hi),
+        encode_i(OPCODE_OP_IMM, REG_T1, 0, REG_T1, lo),
+        // loop:
+        li(REG_A7, num),
+        li(REG_A0, 0),
+        ECALL,
+        encode_i(OPCODE_OP_IMM, REG_T1, 0, REG_T1, -1),
+        encode_b(OPCODE_BRANCH, FUNCT3_BNE, REG_T1, REG_ZERO, -16),
+        li(REG_A0, 0),
+        li(REG_A7, SYS_EXIT),
+        ECALL,
+    ];
+    let text: Vec<u8> = text.iter().flat_map(|i| i.to_le_bytes()).collect();
+    let size = text.len() as u64;
+    ElfWriter::<Rv64>::new(START)
+        .with_segment(START, PF_R | PF_X, text)
+        .with_function("_start", START, size)
+        .with_symbol("__stack_top", STACK_TOP, STT_NOTYPE)
+        .build()
+}
+
+fn backend() -> Backend {
+    match std::env::var("RVR_BENCH_BACKEND").as_deref() {
+        Ok("x86" | "x86_64") => Backend::X86Asm,
+        Ok("arm64" | "aarch64") => Backend::ARM64Asm,
+        _ => Backend::C,
+    }
+}
+
+/// Compile the guest for `num` into a temp dir and load it.
+fn load(dir: &Path, num: u64) -> Runner {
+    let elf: PathBuf = dir.join("null.elf");
+    std::fs::write(&elf, guest_elf(num)).expect("write ELF");
+    let out = dir.join("out");
+    let options = CompileOptions::new()
+        .with_backend(backend())
+        .with_syscall_mode(SyscallMode::Linux)
+        .with_quiet(true);
+    rvr::compile_with_options(&elf, &out, &options).expect("compile");
+    Runner::load(&out, &elf).expect("load runner")
+}
+
+fn bench_null_syscall(b: &mut Bencher, num: u64) {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let mut runner = load(temp.path(), num);
+    b.iter(|| {
+        let result = runner.run().expect("run guest");
+        assert_eq!(result.exit_code, 0);
+    });
+}
+
+#[bench]
+fn bench_brk_runtime_call(b: &mut Bencher) {
+    bench_null_syscall(b, SYS_BRK);
+}
+
+#[bench]
+fn bench_getpid_inline(b: &mut Bencher) {
+    bench_null_syscall(b, SYS_GETPID);
+}
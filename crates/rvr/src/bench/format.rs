@@ -0,0 +1,314 @@
+use crate::{RunPhases, RunResultWithPerf};
+
+use super::{HostResult, u64_to_f64};
+
+/// Format a number with SI suffix (K, M, B).
+#[must_use]
+pub fn format_num(n: u64) -> String {
+    if n >= 1_000_000_000 {
+        let whole = n / 1_000_000_000;
+        let frac = (n % 1_000_000_000) / 10_000_000;
+        format!("{whole}.{frac:02}B")
+    } else if n >= 1_000_000 {
+        let whole = n / 1_000_000;
+        let frac = (n % 1_000_000) / 10_000;
+        format!("{whole}.{frac:02}M")
+    } else if n >= 1_000 {
+        let whole = n / 1_000;
+        let frac = (n % 1_000) / 10;
+        format!("{whole}.{frac:02}K")
+    } else {
+        n.to_string()
+    }
+}
+
+/// Calculate overhead ratio (`vm_time` / `host_time`).
+#[must_use]
+pub fn calc_overhead(vm_time: f64, host_time: f64) -> Option<f64> {
+    if host_time > 0.0 {
+        Some(vm_time / host_time)
+    } else {
+        None
+    }
+}
+
+/// Format overhead as "X.Xx".
+#[must_use]
+pub fn format_overhead(oh: Option<f64>) -> String {
+    oh.map_or_else(|| "-".to_string(), |v| format!("{v:.1}x"))
+}
+
+/// Format IPC value.
+#[must_use]
+pub fn format_ipc(ipc: Option<f64>) -> String {
+    ipc.map_or_else(|| "-".to_string(), |v| format!("{v:.2}"))
+}
+
+/// Format branch miss rate as percentage.
+#[must_use]
+pub fn format_branch_miss(rate: Option<f64>) -> String {
+    rate.map_or_else(|| "-".to_string(), |v| format!("{v:.2}%"))
+}
+
+/// Format speed value with appropriate unit (`MIPS` or `BIPS`).
+/// Input is in `MIPS` (millions of instructions per second).
+#[must_use]
+pub fn format_speed(mips: f64) -> String {
+    if mips <= 0.0 {
+        "-".to_string()
+    } else if mips >= 1000.0 {
+        // BIPS = billions of instructions per second
+        format!("{:.2} BIPS", mips / 1000.0)
+    } else if mips >= 1.0 {
+        format!("{mips:.0} MIPS")
+    } else {
+        // Sub-MIPS: show with decimals
+        format!("{mips:.2} MIPS")
+    }
+}
+
+/// Format speed for shell parsing (underscore instead of space).
+#[must_use]
+pub fn format_speed_shell(mips: f64) -> String {
+    if mips <= 0.0 {
+        "-".to_string()
+    } else if mips >= 1000.0 {
+        format!("{:.2}_BIPS", mips / 1000.0)
+    } else if mips >= 1.0 {
+        format!("{mips:.0}_MIPS")
+    } else {
+        format!("{mips:.2}_MIPS")
+    }
+}
+
+/// Format a byte count with a binary unit (KiB, MiB, GiB).
+#[must_use]
+pub fn format_bytes(bytes: Option<u64>) -> String {
+    let Some(bytes) = bytes else {
+        return "-".to_string();
+    };
+    let value = u64_to_f64(bytes);
+    if bytes >= 1 << 30 {
+        format!("{:.2}GiB", value / f64::from(1u32 << 30))
+    } else if bytes >= 1 << 20 {
+        format!("{:.1}MiB", value / f64::from(1u32 << 20))
+    } else if bytes >= 1 << 10 {
+        format!("{:.0}KiB", value / f64::from(1u32 << 10))
+    } else {
+        format!("{bytes}B")
+    }
+}
+
+/// Format time value with appropriate unit (s, ms, us, ns).
+/// Input is in seconds.
+#[must_use]
+pub fn format_time(secs: f64) -> String {
+    if secs <= 0.0 {
+        "-".to_string()
+    } else if secs >= 1.0 {
+        format!("{secs:.2}s")
+    } else if secs >= 0.001 {
+        format!("{:.2}ms", secs * 1000.0)
+    } else if secs >= 0.000_001 {
+        format!("{:.2}us", secs * 1_000_000.0)
+    } else {
+        format!("{:.2}ns", secs * 1_000_000_000.0)
+    }
+}
+
+// ============================================================================
+// Table output
+// ============================================================================
+
+/// Row in a benchmark results table.
+#[derive(Debug, Clone)]
+pub struct TableRow {
+    /// Row label (arch name or `host`).
+    pub label: String,
+    /// Instruction count (guest instret), None for host.
+    pub instret: Option<u64>,
+    /// Host instructions executed.
+    pub host_instrs: Option<u64>,
+    /// Host instructions per guest instruction.
+    pub instrs_per_guest: Option<f64>,
+    /// Execution time in seconds.
+    pub time_secs: Option<f64>,
+    /// Wall-clock breakdown of the run, None for host.
+    pub phases: Option<RunPhases>,
+    /// Overhead compared to host (`vm_time` / `host_time`).
+    pub overhead: Option<f64>,
+    /// Speed in MIPS (guest MIPS), None for host.
+    pub mips: Option<f64>,
+    /// Guest memory resident at the end of the run, None for host.
+    pub resident_bytes: Option<u64>,
+    /// Instructions per cycle (host IPC).
+    pub ipc: Option<f64>,
+    /// Branch miss rate as percentage.
+    pub branch_miss_rate: Option<f64>,
+    /// Error message if benchmark failed.
+    pub error: Option<String>,
+}
+
+impl TableRow {
+    /// Create a row for the host baseline.
+    #[must_use]
+    pub fn host(label: &str, result: &HostResult) -> Self {
+        let (ipc, branch_miss_rate, host_instrs) =
+            result.perf.as_ref().map_or((None, None, None), |p| {
+                (p.ipc(), p.branch_miss_rate(), p.instructions)
+            });
+
+        Self {
+            label: label.to_string(),
+            instret: None,
+            host_instrs,
+            instrs_per_guest: None,
+            time_secs: result.time_secs,
+            phases: None,
+            overhead: Some(1.0),
+            mips: None,
+            resident_bytes: None,
+            ipc,
+            branch_miss_rate,
+            error: None,
+        }
+    }
+
+    /// Create a row for a VM backend.
+    #[must_use]
+    pub fn backend(label: &str, result: &RunResultWithPerf, host_time: Option<f64>) -> Self {
+        let overhead = host_time.and_then(|ht| calc_overhead(result.result.time_secs, ht));
+        let (ipc, branch_miss_rate, host_instrs) =
+            result.perf.as_ref().map_or((None, None, None), |p| {
+                (p.ipc(), p.branch_miss_rate(), p.instructions)
+            });
+
+        // Calculate host instructions per guest instruction
+        let instrs_per_guest =
+            host_instrs.map(|hi| u64_to_f64(hi) / u64_to_f64(result.result.instret));
+
+        Self {
+            label: label.to_string(),
+            instret: Some(result.result.instret),
+            host_instrs,
+            instrs_per_guest,
+            time_secs: Some(result.result.time_secs),
+            phases: Some(result.result.phases),
+            overhead,
+            mips: Some(result.result.mips),
+            resident_bytes: result.result.resident_bytes,
+            ipc,
+            branch_miss_rate,
+            error: None,
+        }
+    }
+
+    /// Create an error row.
+    #[must_use]
+    pub fn error(label: &str, error: String) -> Self {
+        Self {
+            label: label.to_string(),
+            instret: None,
+            host_instrs: None,
+            instrs_per_guest: None,
+            time_secs: None,
+            phases: None,
+            overhead: None,
+            mips: None,
+            resident_bytes: None,
+            ipc: None,
+            branch_miss_rate: None,
+            error: Some(error),
+        }
+    }
+}
+
+/// Format host instructions per guest instruction.
+#[must_use]
+pub fn format_instrs_per_guest(ipg: Option<f64>) -> String {
+    ipg.map_or_else(|| "-".to_string(), |v| format!("{v:.1}x"))
+}
+
+/// Print markdown table header for benchmark results.
+pub fn print_bench_header(name: &str, description: &str, runs: usize) {
+    println!("## {name}");
+    println!();
+    println!("*{description} | runs: {runs}*");
+    println!();
+    println!(
+        "| {:<14} | {:>10} | {:>10} | {:>9} | {:>10} | {:>10} | {:>10} | {:>10} | {:>6} | {:>12} | {:>9} | {:>5} | {:>11} |",
+        "Backend",
+        "Instret",
+        "Host Ops",
+        "Ops/Guest",
+        "Load",
+        "Init",
+        "Time",
+        "Teardown",
+        "OH",
+        "Speed",
+        "Memory",
+        "IPC",
+        "Branch Miss"
+    );
+    println!(
+        "|{:-<16}|{:-<12}|{:-<12}|{:-<11}|{:-<12}|{:-<12}|{:-<12}|{:-<12}|{:-<8}|{:-<14}|{:-<11}|{:-<7}|{:-<13}|",
+        "", "", "", "", "", "", "", "", "", "", "", "", ""
+    );
+}
+
+/// Print a table row.
+pub fn print_table_row(row: &TableRow) {
+    if let Some(ref err) = row.error {
+        // Truncate error to fit in Speed column (12 chars)
+        let err_display = if err.len() > 12 {
+            format!("{err}...", err = &err[..9])
+        } else {
+            err.clone()
+        };
+        println!(
+            "| {:<14} | {:>10} | {:>10} | {:>9} | {:>10} | {:>10} | {:>10} | {:>10} | {:>6} | {:>12} | {:>9} | {:>5} | {:>11} |",
+            row.label, "-", "-", "-", "-", "-", "-", "-", "-", err_display, "-", "-", "-"
+        );
+        return;
+    }
+
+    let instret = row.instret.map_or_else(|| "-".to_string(), format_num);
+    let host_instrs = row.host_instrs.map_or_else(|| "-".to_string(), format_num);
+    let instrs_per_guest = format_instrs_per_guest(row.instrs_per_guest);
+    let phase = |secs: fn(&RunPhases) -> f64| {
+        row.phases
+            .as_ref()
+            .map_or_else(|| "-".to_string(), |phases| format_time(secs(phases)))
+    };
+    let load = phase(|phases| phases.load_secs);
+    let init = phase(|phases| phases.init_secs);
+    let teardown = phase(|phases| phases.teardown_secs);
+    let time = row.time_secs.map_or_else(|| "-".to_string(), format_time);
+    let overhead = format_overhead(row.overhead);
+    let speed = row.mips.map_or_else(|| "-".to_string(), format_speed);
+    let memory = format_bytes(row.resident_bytes);
+    let ipc = format_ipc(row.ipc);
+    let branch_miss = format_branch_miss(row.branch_miss_rate);
+
+    println!(
+        "| {:<14} | {:>10} | {:>10} | {:>9} | {:>10} | {:>10} | {:>10} | {:>10} | {:>6} | {:>12} | {:>9} | {:>5} | {:>11} |",
+        row.label,
+        instret,
+        host_instrs,
+        instrs_per_guest,
+        load,
+        init,
+        time,
+        teardown,
+        overhead,
+        speed,
+        memory,
+        ipc,
+        branch_miss
+    );
+}
+
+// ============================================================================
+// JSON results
+// ============================================================================
@@ -1,7 +1,8 @@
 //! Benchmarking utilities.
 //!
 //! Provides functions to benchmark compiled RISC-V programs with optional
-//! hardware performance counter collection.
+//! hardware performance counter collection, and the JSON results format
+//! (`BenchReport`) that CI compares across commits.
 
 use std::path::Path;
 use std::process::Command;
@@ -10,7 +11,22 @@ use std::time::Instant;
 use rvr_isa::{REG_GP, REG_RA, REG_SP};
 
 use crate::perf::HostPerfCounters;
-use crate::{PerfCounters, RunResultWithPerf, Runner};
+use crate::{PerfCounters, RunPhases, RunResult, RunResultWithPerf, Runner};
+
+mod format;
+mod report;
+#[cfg(test)]
+mod tests;
+
+pub use format::{
+    TableRow, calc_overhead, format_branch_miss, format_bytes, format_instrs_per_guest, format_ipc,
+    format_num, format_overhead, format_speed, format_speed_shell, format_time, print_bench_header,
+    print_table_row,
+};
+pub use report::{
+    BENCH_REPORT_VERSION, BenchDelta, BenchRecord, BenchReport, PerfRecord, compare_reports,
+    rvr_commit,
+};
 
 /// RISC-V architecture variant.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -200,11 +216,16 @@ fn run_bench_library_inner(
     // Set up perf counters
     let mut perf_group = crate::perf::PerfGroup::new();
 
-    // Run benchmark N times
-    let mut total_time = 0.0;
+    // Run benchmark N times, summing each phase
+    let mut total = RunPhases {
+        load_secs: runner.take_load_secs(),
+        ..RunPhases::default()
+    };
     let mut total_instret = 0u64;
 
     for _ in 0..runs {
+        let start = Instant::now();
+
         // Load segments and reset state for each run
         runner.prepare();
 
@@ -219,7 +240,7 @@ fn run_bench_library_inner(
         // Set return address to 0 - rv_trap handles it
         runner.set_register(REG_RA as usize, 0);
 
-        // Run initialize() (not timed)
+        // Run initialize() (counted as init)
         runner
             .execute_from(init_addr)
             .map_err(|e| format!("initialize() failed: {e}"))?;
@@ -230,6 +251,7 @@ fn run_bench_library_inner(
 
         // Record instret before run() to calculate delta
         let instret_before = runner.instret();
+        total.init_secs += start.elapsed().as_secs_f64();
 
         if let Some(ref mut group) = perf_group {
             let _ = group.reset();
@@ -244,26 +266,29 @@ fn run_bench_library_inner(
             let _ = group.disable();
         }
 
-        total_time += elapsed.as_secs_f64();
+        let start = Instant::now();
+        total.execute_secs += elapsed.as_secs_f64();
         total_instret += instret_after - instret_before;
 
         // Clear exit flag for next iteration
         runner.clear_exit();
+        total.teardown_secs += start.elapsed().as_secs_f64();
     }
 
     let runs_u64 = u64::try_from(runs).unwrap_or(u64::MAX);
-    let avg_time = total_time / u64_to_f64(runs_u64);
+    let count = u64_to_f64(runs_u64);
     let avg_instret = total_instret / runs_u64;
-    let mips = (u64_to_f64(avg_instret) / avg_time) / 1_000_000.0;
+    let phases = RunPhases {
+        load_secs: total.load_secs / count,
+        init_secs: total.init_secs / count,
+        execute_secs: total.execute_secs / count,
+        teardown_secs: total.teardown_secs / count,
+    };
 
     let perf = perf_group.as_mut().and_then(crate::perf::PerfGroup::read);
 
-    let result = crate::RunResult {
-        exit_code: 0,
-        instret: avg_instret,
-        time_secs: avg_time,
-        mips,
-    };
+    let result =
+        RunResult::new(0, avg_instret, phases).with_resident_bytes(runner.resident_bytes());
 
     Ok(RunResultWithPerf { result, perf })
 }
@@ -285,6 +310,7 @@ pub fn run_host(host_bin: &Path, runs: usize) -> Result<HostResult, String> {
     let mut total_instructions = 0u64;
     let mut total_branches = 0u64;
     let mut total_branch_misses = 0u64;
+    let mut total_dtlb_misses = None;
 
     // Get initial snapshot for delta tracking
     let mut prev_snapshot = perf_counters
@@ -321,6 +347,9 @@ pub fn run_host(host_bin: &Path, runs: usize) -> Result<HostResult, String> {
             total_instructions += delta.instructions.unwrap_or(0);
             total_branches += delta.branches.unwrap_or(0);
             total_branch_misses += delta.branch_misses.unwrap_or(0);
+            if let Some(misses) = delta.dtlb_misses {
+                *total_dtlb_misses.get_or_insert(0) += misses;
+            }
             prev_snapshot = counters.read();
         }
     }
@@ -332,6 +361,7 @@ pub fn run_host(host_bin: &Path, runs: usize) -> Result<HostResult, String> {
         instructions: Some(total_instructions / runs_u64),
         branches: Some(total_branches / runs_u64),
         branch_misses: Some(total_branch_misses / runs_u64),
+        dtlb_misses: total_dtlb_misses.map(|misses: u64| misses / runs_u64),
     });
 
     Ok(HostResult {
@@ -349,301 +379,3 @@ fn u64_to_f64(value: u64) -> f64 {
     let lo = u32::try_from(value & 0xFFFF_FFFF).unwrap_or(u32::MAX);
     f64::from(hi) * 4_294_967_296.0 + f64::from(lo)
 }
-
-/// Format a number with SI suffix (K, M, B).
-#[must_use]
-pub fn format_num(n: u64) -> String {
-    if n >= 1_000_000_000 {
-        let whole = n / 1_000_000_000;
-        let frac = (n % 1_000_000_000) / 10_000_000;
-        format!("{whole}.{frac:02}B")
-    } else if n >= 1_000_000 {
-        let whole = n / 1_000_000;
-        let frac = (n % 1_000_000) / 10_000;
-        format!("{whole}.{frac:02}M")
-    } else if n >= 1_000 {
-        let whole = n / 1_000;
-        let frac = (n % 1_000) / 10;
-        format!("{whole}.{frac:02}K")
-    } else {
-        n.to_string()
-    }
-}
-
-/// Calculate overhead ratio (`vm_time` / `host_time`).
-#[must_use]
-pub fn calc_overhead(vm_time: f64, host_time: f64) -> Option<f64> {
-    if host_time > 0.0 {
-        Some(vm_time / host_time)
-    } else {
-        None
-    }
-}
-
-/// Format overhead as "X.Xx".
-#[must_use]
-pub fn format_overhead(oh: Option<f64>) -> String {
-    oh.map_or_else(|| "-".to_string(), |v| format!("{v:.1}x"))
-}
-
-/// Format IPC value.
-#[must_use]
-pub fn format_ipc(ipc: Option<f64>) -> String {
-    ipc.map_or_else(|| "-".to_string(), |v| format!("{v:.2}"))
-}
-
-/// Format branch miss rate as percentage.
-#[must_use]
-pub fn format_branch_miss(rate: Option<f64>) -> String {
-    rate.map_or_else(|| "-".to_string(), |v| format!("{v:.2}%"))
-}
-
-/// Format speed value with appropriate unit (`MIPS` or `BIPS`).
-/// Input is in `MIPS` (millions of instructions per second).
-#[must_use]
-pub fn format_speed(mips: f64) -> String {
-    if mips <= 0.0 {
-        "-".to_string()
-    } else if mips >= 1000.0 {
-        // BIPS = billions of instructions per second
-        format!("{:.2} BIPS", mips / 1000.0)
-    } else if mips >= 1.0 {
-        format!("{mips:.0} MIPS")
-    } else {
-        // Sub-MIPS: show with decimals
-        format!("{mips:.2} MIPS")
-    }
-}
-
-/// Format speed for shell parsing (underscore instead of space).
-#[must_use]
-pub fn format_speed_shell(mips: f64) -> String {
-    if mips <= 0.0 {
-        "-".to_string()
-    } else if mips >= 1000.0 {
-        format!("{:.2}_BIPS", mips / 1000.0)
-    } else if mips >= 1.0 {
-        format!("{mips:.0}_MIPS")
-    } else {
-        format!("{mips:.2}_MIPS")
-    }
-}
-
-/// Format time value with appropriate unit (s, ms, us, ns).
-/// Input is in seconds.
-#[must_use]
-pub fn format_time(secs: f64) -> String {
-    if secs <= 0.0 {
-        "-".to_string()
-    } else if secs >= 1.0 {
-        format!("{secs:.2}s")
-    } else if secs >= 0.001 {
-        format!("{:.2}ms", secs * 1000.0)
-    } else if secs >= 0.000_001 {
-        format!("{:.2}us", secs * 1_000_000.0)
-    } else {
-        format!("{:.2}ns", secs * 1_000_000_000.0)
-    }
-}
-
-// ============================================================================
-// Table output
-// ============================================================================
-
-/// Row in a benchmark results table.
-#[derive(Debug, Clone)]
-pub struct TableRow {
-    /// Row label (arch name or `host`).
-    pub label: String,
-    /// Instruction count (guest instret), None for host.
-    pub instret: Option<u64>,
-    /// Host instructions executed.
-    pub host_instrs: Option<u64>,
-    /// Host instructions per guest instruction.
-    pub instrs_per_guest: Option<f64>,
-    /// Execution time in seconds.
-    pub time_secs: Option<f64>,
-    /// Overhead compared to host (`vm_time` / `host_time`).
-    pub overhead: Option<f64>,
-    /// Speed in MIPS (guest MIPS), None for host.
-    pub mips: Option<f64>,
-    /// Instructions per cycle (host IPC).
-    pub ipc: Option<f64>,
-    /// Branch miss rate as percentage.
-    pub branch_miss_rate: Option<f64>,
-    /// Error message if benchmark failed.
-    pub error: Option<String>,
-}
-
-impl TableRow {
-    /// Create a row for the host baseline.
-    #[must_use]
-    pub fn host(label: &str, result: &HostResult) -> Self {
-        let (ipc, branch_miss_rate, host_instrs) =
-            result.perf.as_ref().map_or((None, None, None), |p| {
-                (p.ipc(), p.branch_miss_rate(), p.instructions)
-            });
-
-        Self {
-            label: label.to_string(),
-            instret: None,
-            host_instrs,
-            instrs_per_guest: None,
-            time_secs: result.time_secs,
-            overhead: Some(1.0),
-            mips: None,
-            ipc,
-            branch_miss_rate,
-            error: None,
-        }
-    }
-
-    /// Create a row for a VM backend.
-    #[must_use]
-    pub fn backend(label: &str, result: &RunResultWithPerf, host_time: Option<f64>) -> Self {
-        let overhead = host_time.and_then(|ht| calc_overhead(result.result.time_secs, ht));
-        let (ipc, branch_miss_rate, host_instrs) =
-            result.perf.as_ref().map_or((None, None, None), |p| {
-                (p.ipc(), p.branch_miss_rate(), p.instructions)
-            });
-
-        // Calculate host instructions per guest instruction
-        let instrs_per_guest =
-            host_instrs.map(|hi| u64_to_f64(hi) / u64_to_f64(result.result.instret));
-
-        Self {
-            label: label.to_string(),
-            instret: Some(result.result.instret),
-            host_instrs,
-            instrs_per_guest,
-            time_secs: Some(result.result.time_secs),
-            overhead,
-            mips: Some(result.result.mips),
-            ipc,
-            branch_miss_rate,
-            error: None,
-        }
-    }
-
-    /// Create an error row.
-    #[must_use]
-    pub fn error(label: &str, error: String) -> Self {
-        Self {
-            label: label.to_string(),
-            instret: None,
-            host_instrs: None,
-            instrs_per_guest: None,
-            time_secs: None,
-            overhead: None,
-            mips: None,
-            ipc: None,
-            branch_miss_rate: None,
-            error: Some(error),
-        }
-    }
-}
-
-/// Format host instructions per guest instruction.
-#[must_use]
-pub fn format_instrs_per_guest(ipg: Option<f64>) -> String {
-    ipg.map_or_else(|| "-".to_string(), |v| format!("{v:.1}x"))
-}
-
-/// Print markdown table header for benchmark results.
-pub fn print_bench_header(name: &str, description: &str, runs: usize) {
-    println!("## {name}");
-    println!();
-    println!("*{description} | runs: {runs}*");
-    println!();
-    println!(
-        "| {:<14} | {:>10} | {:>10} | {:>9} | {:>10} | {:>6} | {:>12} | {:>5} | {:>11} |",
-        "Backend", "Instret", "Host Ops", "Ops/Guest", "Time", "OH", "Speed", "IPC", "Branch Miss"
-    );
-    println!(
-        "|{:-<16}|{:-<12}|{:-<12}|{:-<11}|{:-<12}|{:-<8}|{:-<14}|{:-<7}|{:-<13}|",
-        "", "", "", "", "", "", "", "", ""
-    );
-}
-
-/// Print a table row.
-pub fn print_table_row(row: &TableRow) {
-    if let Some(ref err) = row.error {
-        // Truncate error to fit in Speed column (12 chars)
-        let err_display = if err.len() > 12 {
-            format!("{err}...", err = &err[..9])
-        } else {
-            err.clone()
-        };
-        println!(
-            "| {:<14} | {:>10} | {:>10} | {:>9} | {:>10} | {:>6} | {:>12} | {:>5} | {:>11} |",
-            row.label, "-", "-", "-", "-", "-", err_display, "-", "-"
-        );
-        return;
-    }
-
-    let instret = row.instret.map_or_else(|| "-".to_string(), format_num);
-    let host_instrs = row.host_instrs.map_or_else(|| "-".to_string(), format_num);
-    let instrs_per_guest = format_instrs_per_guest(row.instrs_per_guest);
-    let time = row.time_secs.map_or_else(|| "-".to_string(), format_time);
-    let overhead = format_overhead(row.overhead);
-    let speed = row.mips.map_or_else(|| "-".to_string(), format_speed);
-    let ipc = format_ipc(row.ipc);
-    let branch_miss = format_branch_miss(row.branch_miss_rate);
-
-    println!(
-        "| {:<14} | {:>10} | {:>10} | {:>9} | {:>10} | {:>6} | {:>12} | {:>5} | {:>11} |",
-        row.label, instret, host_instrs, instrs_per_guest, time, overhead, speed, ipc, branch_miss
-    );
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_arch_parse() {
-        assert_eq!(Arch::parse("rv32i"), Some(Arch::Rv32i));
-        assert_eq!(Arch::parse("RV64E"), Some(Arch::Rv64e));
-        assert_eq!(Arch::parse("invalid"), None);
-    }
-
-    #[test]
-    fn test_arch_list_parse() {
-        let archs = Arch::parse_list("rv32i,rv64e").unwrap();
-        assert_eq!(archs, vec![Arch::Rv32i, Arch::Rv64e]);
-    }
-
-    #[test]
-    fn test_format_num() {
-        assert_eq!(format_num(500), "500");
-        assert_eq!(format_num(1500), "1.50K");
-        assert_eq!(format_num(1_500_000), "1.50M");
-        assert_eq!(format_num(7_920_000_000), "7.92B");
-    }
-
-    #[test]
-    fn test_calc_overhead() {
-        assert_eq!(calc_overhead(2.0, 1.0), Some(2.0));
-        assert_eq!(calc_overhead(1.5, 0.5), Some(3.0));
-        assert_eq!(calc_overhead(1.0, 0.0), None);
-    }
-
-    #[test]
-    fn test_format_overhead() {
-        assert_eq!(format_overhead(Some(2.5)), "2.5x");
-        assert_eq!(format_overhead(Some(10.0)), "10.0x");
-        assert_eq!(format_overhead(None), "-");
-    }
-
-    #[test]
-    fn test_format_speed() {
-        assert_eq!(format_speed(0.0), "-");
-        assert_eq!(format_speed(-1.0), "-");
-        assert_eq!(format_speed(0.5), "0.50 MIPS");
-        assert_eq!(format_speed(100.0), "100 MIPS");
-        assert_eq!(format_speed(999.0), "999 MIPS");
-        assert_eq!(format_speed(1000.0), "1.00 BIPS");
-        assert_eq!(format_speed(3861.0), "3.86 BIPS");
-        assert_eq!(format_speed(8609.0), "8.61 BIPS");
-    }
-}
@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{PerfCounters, RunPhases, RunResultWithPerf};
+
+use super::{HostResult, calc_overhead, u64_to_f64};
+
+/// Layout version of `BenchReport`; bumped on incompatible changes.
+pub const BENCH_REPORT_VERSION: u32 = 1;
+
+/// Git commit the rvr binary was built from, if it was built in a checkout.
+#[must_use]
+pub const fn rvr_commit() -> Option<&'static str> {
+    option_env!("RVR_GIT_COMMIT")
+}
+
+/// Machine-readable benchmark results, one record per benchmark/arch/backend.
+///
+/// Field names are part of the format: rename only with a version bump.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchReport {
+    /// `BENCH_REPORT_VERSION` of the writer.
+    pub version: u32,
+    /// Git commit of the rvr that produced the results.
+    pub rvr_commit: Option<String>,
+    /// Results in run order.
+    pub results: Vec<BenchRecord>,
+}
+
+impl Default for BenchReport {
+    fn default() -> Self {
+        Self {
+            version: BENCH_REPORT_VERSION,
+            rvr_commit: rvr_commit().map(str::to_string),
+            results: Vec::new(),
+        }
+    }
+}
+
+impl BenchReport {
+    /// Read a report written by `write`.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read, is not a report, or has
+    /// a newer layout version.
+    pub fn read(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+        let report: Self = serde_json::from_str(&text)
+            .map_err(|e| format!("invalid bench report {}: {e}", path.display()))?;
+        if report.version > BENCH_REPORT_VERSION {
+            return Err(format!(
+                "bench report {} has version {}, expected at most {BENCH_REPORT_VERSION}",
+                path.display(),
+                report.version
+            ));
+        }
+        Ok(report)
+    }
+
+    /// Write the report as pretty-printed JSON.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be written.
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        let text = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("failed to serialize bench report: {e}"))?;
+        std::fs::write(path, text + "\n")
+            .map_err(|e| format!("failed to write {}: {e}", path.display()))
+    }
+}
+
+/// One benchmark on one arch and backend.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchRecord {
+    /// Benchmark name.
+    pub benchmark: String,
+    /// Guest architecture (`rv64i`, ...).
+    pub arch: String,
+    /// Backend (`c`, `x86`, `arm64`).
+    pub backend: String,
+    /// Guest instructions retired (0 with instret counting off).
+    #[serde(default)]
+    pub instret: Option<u64>,
+    /// Average wall-clock time per run.
+    #[serde(default)]
+    pub time_secs: Option<f64>,
+    /// Guest MIPS.
+    #[serde(default)]
+    pub mips: Option<f64>,
+    /// Average wall-clock breakdown per run (`time_secs` is the execute
+    /// phase).
+    #[serde(default)]
+    pub phases: Option<RunPhases>,
+    /// Time relative to the native host build (`time_secs` / host time).
+    #[serde(default)]
+    pub host_overhead: Option<f64>,
+    /// Host performance counters of the run.
+    #[serde(default)]
+    pub perf: Option<PerfRecord>,
+    /// Guest memory resident at the end of the run.
+    #[serde(default)]
+    pub resident_bytes: Option<u64>,
+    /// Time to compile the ELF, if it was compiled for this run.
+    #[serde(default)]
+    pub compile_time_secs: Option<f64>,
+    /// Error message if the benchmark failed.
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl BenchRecord {
+    /// Record a successful run, with the host baseline if one ran.
+    #[must_use]
+    pub fn new(
+        benchmark: &str,
+        arch: &str,
+        backend: &str,
+        result: &RunResultWithPerf,
+        host: Option<&HostResult>,
+    ) -> Self {
+        let host_time = host.and_then(|host| host.time_secs);
+        Self {
+            instret: Some(result.result.instret),
+            time_secs: Some(result.result.time_secs),
+            mips: Some(result.result.mips),
+            phases: Some(result.result.phases),
+            host_overhead: host_time.and_then(|ht| calc_overhead(result.result.time_secs, ht)),
+            perf: result.perf.as_ref().map(PerfRecord::from),
+            resident_bytes: result.result.resident_bytes,
+            ..Self::failed(benchmark, arch, backend, None)
+        }
+    }
+
+    /// Record a benchmark that failed to build or run (`error`), or an empty
+    /// record to fill in.
+    #[must_use]
+    pub fn failed(benchmark: &str, arch: &str, backend: &str, error: Option<String>) -> Self {
+        Self {
+            benchmark: benchmark.to_string(),
+            arch: arch.to_string(),
+            backend: backend.to_string(),
+            instret: None,
+            time_secs: None,
+            mips: None,
+            phases: None,
+            host_overhead: None,
+            perf: None,
+            resident_bytes: None,
+            compile_time_secs: None,
+            error,
+        }
+    }
+
+    fn key(&self) -> (&str, &str, &str) {
+        (&self.benchmark, &self.arch, &self.backend)
+    }
+}
+
+/// Host performance counters of a run.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PerfRecord {
+    /// Host CPU cycles.
+    pub cycles: Option<u64>,
+    /// Host instructions executed.
+    pub instructions: Option<u64>,
+    /// Host instructions per cycle.
+    pub ipc: Option<f64>,
+    /// Branch miss rate as a percentage.
+    pub branch_miss_rate: Option<f64>,
+    /// Branch misses.
+    #[serde(default)]
+    pub branch_misses: Option<u64>,
+    /// Data TLB read misses.
+    #[serde(default)]
+    pub dtlb_misses: Option<u64>,
+}
+
+impl From<&PerfCounters> for PerfRecord {
+    fn from(perf: &PerfCounters) -> Self {
+        Self {
+            cycles: perf.cycles,
+            instructions: perf.instructions,
+            ipc: perf.ipc(),
+            branch_miss_rate: perf.branch_miss_rate(),
+            branch_misses: perf.branch_misses,
+            dtlb_misses: perf.dtlb_misses,
+        }
+    }
+}
+
+/// Time of one benchmark in two reports.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchDelta {
+    /// Benchmark name.
+    pub benchmark: String,
+    /// Guest architecture.
+    pub arch: String,
+    /// Backend.
+    pub backend: String,
+    /// Time in the old report.
+    pub old_time_secs: f64,
+    /// Time in the new report.
+    pub new_time_secs: f64,
+    /// Host performance counters in the old report.
+    pub old_perf: Option<PerfRecord>,
+    /// Host performance counters in the new report.
+    pub new_perf: Option<PerfRecord>,
+}
+
+impl BenchDelta {
+    /// Relative change in time, in percent (positive is slower).
+    #[must_use]
+    pub fn change_percent(&self) -> f64 {
+        (self.new_time_secs / self.old_time_secs - 1.0) * 100.0
+    }
+
+    /// Relative change in branch misses, in percent, if both reports
+    /// counted them.
+    #[must_use]
+    pub fn branch_misses_change_percent(&self) -> Option<f64> {
+        self.counter_change_percent(|perf| perf.branch_misses)
+    }
+
+    /// Relative change in data TLB misses, in percent, if both reports
+    /// counted them.
+    #[must_use]
+    pub fn dtlb_misses_change_percent(&self) -> Option<f64> {
+        self.counter_change_percent(|perf| perf.dtlb_misses)
+    }
+
+    fn counter_change_percent(&self, counter: impl Fn(&PerfRecord) -> Option<u64>) -> Option<f64> {
+        let old = counter(self.old_perf.as_ref()?)?;
+        let new = counter(self.new_perf.as_ref()?)?;
+        (old > 0).then(|| (u64_to_f64(new) / u64_to_f64(old) - 1.0) * 100.0)
+    }
+
+    /// Whether the new run is more than `threshold_percent` slower.
+    #[must_use]
+    pub fn is_regression(&self, threshold_percent: f64) -> bool {
+        self.change_percent() > threshold_percent
+    }
+}
+
+/// Pair up the records both reports timed, in the order of `new`.
+///
+/// Records are matched by benchmark, arch and backend; ones without a time
+/// in either report (failed, or only in one) are skipped.
+#[must_use]
+pub fn compare_reports(old: &BenchReport, new: &BenchReport) -> Vec<BenchDelta> {
+    let old_records: HashMap<_, _> = old
+        .results
+        .iter()
+        .filter_map(|record| Some((record.key(), (record.time_secs?, record.perf))))
+        .collect();
+    new.results
+        .iter()
+        .filter_map(|record| {
+            let &(old_time_secs, old_perf) = old_records.get(&record.key())?;
+            let new_time_secs = record.time_secs?;
+            (old_time_secs > 0.0).then(|| BenchDelta {
+                benchmark: record.benchmark.clone(),
+                arch: record.arch.clone(),
+                backend: record.backend.clone(),
+                old_time_secs,
+                new_time_secs,
+                old_perf,
+                new_perf: record.perf,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,146 @@
+use super::*;
+
+#[test]
+fn test_arch_parse() {
+    assert_eq!(Arch::parse("rv32i"), Some(Arch::Rv32i));
+    assert_eq!(Arch::parse("RV64E"), Some(Arch::Rv64e));
+    assert_eq!(Arch::parse("invalid"), None);
+}
+
+#[test]
+fn test_arch_list_parse() {
+    let archs = Arch::parse_list("rv32i,rv64e").unwrap();
+    assert_eq!(archs, vec![Arch::Rv32i, Arch::Rv64e]);
+}
+
+#[test]
+fn test_format_num() {
+    assert_eq!(format_num(500), "500");
+    assert_eq!(format_num(1500), "1.50K");
+    assert_eq!(format_num(1_500_000), "1.50M");
+    assert_eq!(format_num(7_920_000_000), "7.92B");
+}
+
+#[test]
+fn test_calc_overhead() {
+    assert_eq!(calc_overhead(2.0, 1.0), Some(2.0));
+    assert_eq!(calc_overhead(1.5, 0.5), Some(3.0));
+    assert_eq!(calc_overhead(1.0, 0.0), None);
+}
+
+#[test]
+fn test_format_overhead() {
+    assert_eq!(format_overhead(Some(2.5)), "2.5x");
+    assert_eq!(format_overhead(Some(10.0)), "10.0x");
+    assert_eq!(format_overhead(None), "-");
+}
+
+#[test]
+fn test_format_speed() {
+    assert_eq!(format_speed(0.0), "-");
+    assert_eq!(format_speed(-1.0), "-");
+    assert_eq!(format_speed(0.5), "0.50 MIPS");
+    assert_eq!(format_speed(100.0), "100 MIPS");
+    assert_eq!(format_speed(999.0), "999 MIPS");
+    assert_eq!(format_speed(1000.0), "1.00 BIPS");
+    assert_eq!(format_speed(3861.0), "3.86 BIPS");
+    assert_eq!(format_speed(8609.0), "8.61 BIPS");
+}
+
+#[test]
+fn test_format_bytes() {
+    assert_eq!(format_bytes(None), "-");
+    assert_eq!(format_bytes(Some(512)), "512B");
+    assert_eq!(format_bytes(Some(64 << 10)), "64KiB");
+    assert_eq!(format_bytes(Some(200 << 20)), "200.0MiB");
+    assert_eq!(format_bytes(Some(4 << 30)), "4.00GiB");
+}
+
+fn timed(benchmark: &str, time_secs: f64) -> BenchRecord {
+    BenchRecord {
+        time_secs: Some(time_secs),
+        ..BenchRecord::failed(benchmark, "rv64i", "c", None)
+    }
+}
+
+#[test]
+fn test_bench_report_json_roundtrip() {
+    let mut record = timed("towers", 0.5);
+    record.perf = Some(PerfRecord::from(&PerfCounters {
+        cycles: Some(200),
+        instructions: Some(300),
+        branches: Some(10),
+        branch_misses: Some(1),
+        dtlb_misses: None,
+    }));
+    let report = BenchReport {
+        version: BENCH_REPORT_VERSION,
+        rvr_commit: Some("abc123".to_string()),
+        results: vec![
+            record,
+            BenchRecord::failed("qsort", "rv32i", "x86", Some("boom".into())),
+        ],
+    };
+
+    let json = serde_json::to_string(&report).unwrap();
+    assert!(json.contains("\"time_secs\":0.5"));
+    assert!(json.contains("\"ipc\":1.5"));
+    assert_eq!(serde_json::from_str::<BenchReport>(&json).unwrap(), report);
+
+    // Optional fields may be left out
+    let minimal = r#"{"version":1,"rvr_commit":null,"results":[{"benchmark":"b","arch":"rv64i","backend":"c"}]}"#;
+    let parsed: BenchReport = serde_json::from_str(minimal).unwrap();
+    assert_eq!(
+        parsed.results[0],
+        BenchRecord::failed("b", "rv64i", "c", None)
+    );
+}
+
+#[test]
+fn test_compare_reports() {
+    let old = BenchReport {
+        results: vec![timed("a", 1.0), timed("b", 1.0), timed("gone", 1.0)],
+        ..BenchReport::default()
+    };
+    let new = BenchReport {
+        results: vec![
+            timed("b", 1.04),
+            timed("a", 1.10),
+            timed("new", 1.0),
+            BenchRecord::failed("gone", "rv64i", "c", Some("boom".into())),
+        ],
+        ..BenchReport::default()
+    };
+
+    let deltas = compare_reports(&old, &new);
+
+    let names: Vec<_> = deltas.iter().map(|d| d.benchmark.as_str()).collect();
+    assert_eq!(names, vec!["b", "a"]);
+    assert!(!deltas[0].is_regression(5.0));
+    assert!(deltas[1].is_regression(5.0));
+    assert!((deltas[1].change_percent() - 10.0).abs() < 1e-9);
+    assert_eq!(deltas[1].branch_misses_change_percent(), None);
+}
+
+#[test]
+fn test_compare_reports_counter_changes() {
+    let counted = |branch_misses, dtlb_misses| BenchRecord {
+        perf: Some(PerfRecord::from(&PerfCounters {
+            branch_misses,
+            dtlb_misses,
+            ..PerfCounters::default()
+        })),
+        ..timed("a", 1.0)
+    };
+    let report = |record| BenchReport {
+        results: vec![record],
+        ..BenchReport::default()
+    };
+    let deltas = compare_reports(
+        &report(counted(Some(200), Some(50))),
+        &report(counted(Some(150), None)),
+    );
+    assert_eq!(deltas[0].branch_misses_change_percent(), Some(-25.0));
+    // The new run's PMU had no dTLB counter
+    assert_eq!(deltas[0].dtlb_misses_change_percent(), None);
+}
@@ -0,0 +1,156 @@
+//! Compare Wrap and Bounds address modes on ELFs and negative-test fixtures.
+//!
+//! Builds run through the `rvr` binary next to this one (`--rvr` overrides it).
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
+
+use clap::Parser;
+use rvr::test_support::address_modes::{self, AddressModeHarness};
+use rvr_emit::c::DEFAULT_CLANG_COMMAND;
+
+#[derive(Parser, Debug)]
+#[command(name = "address_mode_diff")]
+#[command(about = "Compare Wrap and Bounds address modes on ELFs and negative-test fixtures")]
+struct Args {
+    /// ELF files or directories of ELF files (e.g. bin/riscv-tests)
+    #[arg(value_name = "PATH")]
+    paths: Vec<PathBuf>,
+
+    /// Run every ELF instead of sampling (nightly matrix)
+    #[arg(long)]
+    full: bool,
+
+    /// Rotation slot used for sampling (default: day-based)
+    #[arg(long)]
+    rotation: Option<u64>,
+
+    /// Skip the built-in negative-test fixtures
+    #[arg(long)]
+    no_fixtures: bool,
+
+    /// Output directory for compiled code (default: temp dir)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// C compiler command
+    #[arg(long, default_value = DEFAULT_CLANG_COMMAND)]
+    cc: String,
+
+    /// Per-run timeout in seconds
+    #[arg(long, default_value = "10")]
+    timeout: u64,
+
+    /// rvr binary to build with (default: the one next to this binary)
+    #[arg(long, value_name = "PATH")]
+    rvr: Option<PathBuf>,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let compiler = match args.cc.parse::<rvr::Compiler>() {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("Error: invalid compiler: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let rvr_exe = match args.rvr.map_or_else(sibling_rvr, Ok) {
+        Ok(path) => path,
+        Err(e) => {
+            eprintln!("Error: failed to locate rvr executable: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let output_dir = args.output.unwrap_or_else(|| {
+        let temp = tempfile::tempdir().expect("failed to create temp dir");
+        temp.keep()
+    });
+    eprintln!("Output: {}", output_dir.display());
+
+    let harness = AddressModeHarness::new(rvr_exe, &output_dir)
+        .with_compiler(compiler)
+        .with_timeout(Duration::from_secs(args.timeout));
+
+    let mut failures = 0usize;
+
+    if !args.no_fixtures {
+        for fixture in address_modes::fixtures() {
+            let label = fixture.regression.map_or("fixture", |_| "regression");
+            match harness.check_fixture(&fixture) {
+                Ok(()) => eprintln!("PASS {label} {}", fixture.name),
+                Err(e) => {
+                    eprintln!("FAIL {label} {}: {e}", fixture.name);
+                    failures += 1;
+                }
+            }
+        }
+    }
+
+    let mut cases = Vec::new();
+    for path in &args.paths {
+        if let Err(e) = collect_elfs(path, &mut cases) {
+            eprintln!("Error: failed to read {}: {e}", path.display());
+            return ExitCode::FAILURE;
+        }
+    }
+    cases.sort();
+    let full = args.full || address_modes::full_matrix_requested();
+    let slot = args.rotation.unwrap_or_else(address_modes::rotation_slot);
+    let selected = address_modes::sample_suite(&cases, slot, full);
+    if !full {
+        eprintln!(
+            "Sampling {} of {} ELFs (rotation slot {slot}/{})",
+            selected.len(),
+            cases.len(),
+            address_modes::ROTATION_PERIOD
+        );
+    }
+
+    for elf in &selected {
+        let name = elf.file_name().and_then(|n| n.to_str()).unwrap_or("elf");
+        match harness
+            .compare_elf(elf, name)
+            .and_then(|comparison| comparison.check())
+        {
+            Ok(()) => eprintln!("PASS {name}"),
+            Err(e) => {
+                eprintln!("FAIL {name}: {e}");
+                failures += 1;
+            }
+        }
+    }
+
+    if failures > 0 {
+        eprintln!("{failures} failure(s)");
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// The `rvr` binary built next to this one.
+fn sibling_rvr() -> std::io::Result<PathBuf> {
+    let exe = std::env::current_exe()?;
+    let rvr = exe.with_file_name(format!("rvr{}", std::env::consts::EXE_SUFFIX));
+    if rvr.is_file() {
+        Ok(rvr)
+    } else {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("{} not found; build it or pass --rvr", rvr.display()),
+        ))
+    }
+}
+
+fn collect_elfs(path: &Path, out: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    if path.is_file() {
+        out.push(path.to_path_buf());
+        return Ok(());
+    }
+    for entry in std::fs::read_dir(path)? {
+        collect_elfs(&entry?.path(), out)?;
+    }
+    Ok(())
+}
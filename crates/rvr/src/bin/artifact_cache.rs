@@ -0,0 +1,56 @@
+//! Inspect or clear the cache of compiled outputs (`$RVR_CACHE_DIR`, default
+//! `~/.cache/rvr/artifacts`).
+
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand};
+use rvr::{ArtifactCache, CacheStats};
+
+#[derive(Parser, Debug)]
+#[command(name = "artifact_cache")]
+#[command(about = "Manage the cache of compiled outputs")]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Show the number and size of cached builds
+    Stats,
+    /// Remove all cached builds
+    Clear,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    match args.command {
+        Command::Stats => with_cache(ArtifactCache::stats, "cached"),
+        Command::Clear => with_cache(ArtifactCache::clear, "removed"),
+    }
+}
+
+/// Run `op` on the default cache and print its stats.
+fn with_cache(op: fn(&ArtifactCache) -> std::io::Result<CacheStats>, verb: &str) -> ExitCode {
+    let Some(cache) = ArtifactCache::from_env() else {
+        eprintln!("no cache directory: set RVR_CACHE_DIR or HOME");
+        return ExitCode::FAILURE;
+    };
+    match op(&cache) {
+        Ok(stats) => {
+            // Display only; a rounded size is fine
+            #[allow(clippy::cast_precision_loss)]
+            let mib = stats.bytes as f64 / (1024.0 * 1024.0);
+            println!(
+                "{}: {} builds {verb} ({mib:.1} MiB)",
+                cache.dir().display(),
+                stats.entries
+            );
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("cannot access cache {}: {e}", cache.dir().display());
+            ExitCode::FAILURE
+        }
+    }
+}
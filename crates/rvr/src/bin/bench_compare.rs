@@ -0,0 +1,104 @@
+//! Compare two `bench_report --json` results; exits non-zero on a regression.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::Parser;
+use rvr::bench::{BenchReport, compare_reports, format_time};
+
+#[derive(Parser, Debug)]
+#[command(name = "bench_compare")]
+#[command(about = "Compare two bench_report --json results; exits non-zero on a regression")]
+struct Args {
+    /// Baseline results
+    #[arg(value_name = "OLD_JSON")]
+    old: PathBuf,
+
+    /// Results to check
+    #[arg(value_name = "NEW_JSON")]
+    new: PathBuf,
+
+    /// Slowdown in percent above which a benchmark counts as a regression
+    #[arg(long, default_value = "5")]
+    threshold: f64,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let threshold = args.threshold;
+    let (old, new) = match (BenchReport::read(&args.old), BenchReport::read(&args.new)) {
+        (Ok(old), Ok(new)) => (old, new),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("failed to read bench report: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let commit = |report: &BenchReport| report.rvr_commit.clone().unwrap_or_else(|| "?".into());
+    println!("{} -> {}", commit(&old), commit(&new));
+    println!(
+        "{:<24} {:<6} {:<6} {:>10} {:>10} {:>8} {:>9} {:>9}",
+        "Benchmark", "Arch", "Backend", "Old", "New", "Change", "Br-miss", "dTLB-miss"
+    );
+    let counter_change = |change: Option<f64>| {
+        change.map_or_else(|| "-".to_string(), |change| format!("{change:+.1}%"))
+    };
+
+    let deltas = compare_reports(&old, &new);
+    let mut regressions = 0;
+    for delta in &deltas {
+        let regressed = delta.is_regression(threshold);
+        regressions += usize::from(regressed);
+        println!(
+            "{:<24} {:<6} {:<6} {:>10} {:>10} {:>+7.1}% {:>9} {:>9}{}",
+            delta.benchmark,
+            delta.arch,
+            delta.backend,
+            format_time(delta.old_time_secs),
+            format_time(delta.new_time_secs),
+            delta.change_percent(),
+            counter_change(delta.branch_misses_change_percent()),
+            counter_change(delta.dtlb_misses_change_percent()),
+            if regressed { "  REGRESSION" } else { "" }
+        );
+    }
+
+    // Results that stopped producing a time are regressions too
+    let failed: Vec<_> = new
+        .results
+        .iter()
+        .filter(|record| record.error.is_some())
+        .filter(|record| {
+            old.results.iter().any(|prev| {
+                prev.time_secs.is_some()
+                    && (&prev.benchmark, &prev.arch, &prev.backend)
+                        == (&record.benchmark, &record.arch, &record.backend)
+            })
+        })
+        .collect();
+    for record in &failed {
+        println!(
+            "{:<24} {:<6} {:<6} failed: {}",
+            record.benchmark,
+            record.arch,
+            record.backend,
+            record.error.as_deref().unwrap_or_default()
+        );
+    }
+
+    println!();
+    let total = regressions + failed.len();
+    if total == 0 {
+        println!(
+            "{} benchmarks compared, no regressions above {threshold}%",
+            deltas.len()
+        );
+        ExitCode::SUCCESS
+    } else {
+        println!(
+            "{total} of {} benchmarks regressed above {threshold}%",
+            deltas.len() + failed.len()
+        );
+        ExitCode::FAILURE
+    }
+}
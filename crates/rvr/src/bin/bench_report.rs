@@ -4,10 +4,11 @@ use std::fmt::Write as FmtWrite;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::time::Instant;
 
 use clap::Parser;
-use rvr::bench::{self, Arch};
-use rvr::{AddressMode, CompileOptions, Compiler, InstretMode, SyscallMode};
+use rvr::bench::{self, Arch, BenchRecord, BenchReport};
+use rvr::{AddressMode, CompileOptions, Compiler, InstretMode, RunResult, SyscallMode};
 use rvr_emit::{Backend, c::DEFAULT_CLANG_COMMAND};
 
 #[path = "../../benches/support/mod.rs"]
@@ -21,6 +22,8 @@ use bench_support::{
 #[derive(Parser, Debug)]
 #[command(name = "bench_report")]
 #[command(about = "Generate BENCHMARKS.md from cargo bench helpers")]
+// Independent command-line switches, one bool each
+#[allow(clippy::struct_excessive_bools)]
 struct Args {
     /// Backend to use for compilation
     #[arg(long, value_parser = ["c", "x86", "arm64"], default_value = "c")]
@@ -49,6 +52,20 @@ struct Args {
     /// Enable perf mode (disable instret, enable perf)
     #[arg(long)]
     perf: bool,
+
+    /// Compile with dispatch table prefetches (`--prefetch-dispatch`)
+    #[arg(long)]
+    prefetch_dispatch: bool,
+
+    /// Compile with a huge-page aligned dispatch table
+    /// (`--dispatch-table-hugepages`)
+    #[arg(long)]
+    dispatch_table_hugepages: bool,
+
+    /// Also write machine-readable results (`rvr::bench::BenchReport`) here,
+    /// for `bench_compare`
+    #[arg(long)]
+    json: Option<PathBuf>,
 }
 
 fn parse_backend(arg: &str) -> Backend {
@@ -79,12 +96,21 @@ fn bench_output_dir(
     info: &BenchmarkInfo,
     arch: Arch,
     backend: Backend,
+    args: &Args,
 ) -> PathBuf {
-    let suffix = match backend {
+    let mut suffix = match backend {
         Backend::C => "base",
         Backend::X86Asm => "x86",
         Backend::ARM64Asm => "arm64",
-    };
+    }
+    .to_string();
+    // Variants get their own builds, so A/B runs need no recompile
+    if args.prefetch_dispatch {
+        suffix.push_str("-prefetch");
+    }
+    if args.dispatch_table_hugepages {
+        suffix.push_str("-hugepages");
+    }
     project_dir
         .join("target/benchmarks")
         .join(info.name)
@@ -98,6 +124,8 @@ fn compile_options(info: &BenchmarkInfo, backend: Backend, args: &Args) -> Compi
         .with_backend(backend)
         .with_export_functions(info.uses_exports)
         .with_address_mode(AddressMode::Wrap)
+        .with_prefetch_dispatch(args.prefetch_dispatch)
+        .with_dispatch_table_hugepages(args.dispatch_table_hugepages)
         .with_quiet(true);
 
     match info.source {
@@ -142,14 +170,16 @@ fn ensure_elf(
     }
 }
 
+/// Output dir of the compiled benchmark, and the compile time if it was
+/// compiled now.
 fn ensure_compiled(
     project_dir: &Path,
     info: &BenchmarkInfo,
     arch: Arch,
     backend: Backend,
     args: &Args,
-) -> Result<PathBuf, String> {
-    let out_dir = bench_output_dir(project_dir, info, arch, backend);
+) -> Result<(PathBuf, Option<f64>), String> {
+    let out_dir = bench_output_dir(project_dir, info, arch, backend, args);
     let so_path = out_dir.join(format!(
         "lib{}.{}",
         info.name,
@@ -160,15 +190,31 @@ fn ensure_compiled(
         }
     ));
     if so_path.exists() && !should_recompile(args) {
-        return Ok(out_dir);
+        return Ok((out_dir, None));
     }
 
     let elf_path = ensure_elf(project_dir, info, arch, args)?;
     std::fs::create_dir_all(&out_dir).map_err(|e| format!("failed to create output dir: {e}"))?;
     let options = compile_options(info, backend, args);
+    let start = Instant::now();
     rvr::compile_with_options(&elf_path, &out_dir, &options)
         .map_err(|e| format!("compile failed: {e}"))?;
-    Ok(out_dir)
+    Ok((out_dir, Some(start.elapsed().as_secs_f64())))
+}
+
+/// Run the native build of a benchmark (`bin/host/<name>`), if there is one.
+fn run_host_baseline(
+    project_dir: &Path,
+    info: &BenchmarkInfo,
+    runs: usize,
+) -> Option<bench::HostResult> {
+    let host_bin = project_dir.join("bin/host").join(info.name);
+    if !host_bin.exists() {
+        return None;
+    }
+    bench::run_host(&host_bin, runs)
+        .map_err(|err| eprintln!("{} (host) failed: {}", info.name, err))
+        .ok()
 }
 
 fn collect_system_info() -> Vec<(String, String)> {
@@ -224,7 +270,7 @@ fn collect_system_info() -> Vec<(String, String)> {
     info
 }
 
-fn render_markdown(rows: &[(String, String, String, f64, f64)]) -> String {
+fn render_markdown(rows: &[(String, String, String, RunResult)]) -> String {
     let mut out = String::new();
     out.push_str("# Benchmarks\n\n");
 
@@ -235,12 +281,24 @@ fn render_markdown(rows: &[(String, String, String, f64, f64)]) -> String {
     out.push('\n');
 
     out.push_str("## Results\n\n");
-    out.push_str("| Benchmark | Arch | Backend | Time (s) | MIPS |\n");
-    out.push_str("|---|---|---|---:|---:|\n");
-    for (name, arch, backend, time, mips) in rows {
+    out.push_str(
+        "| Benchmark | Arch | Backend | Load (s) | Init (s) | Reset (s) | Time (s) | Teardown (s) | MIPS | Memory |\n",
+    );
+    out.push_str("|---|---|---|---:|---:|---:|---:|---:|---:|---:|\n");
+    for (name, arch, backend, result) in rows {
+        let phases = &result.phases;
+        let reset = result
+            .reset_secs
+            .map_or_else(|| "-".to_string(), |secs| format!("{secs:.6}"));
         let _ = writeln!(
             out,
-            "| {name} | {arch} | {backend} | {time:.6} | {mips:.2} |"
+            "| {name} | {arch} | {backend} | {:.6} | {:.6} | {reset} | {:.6} | {:.6} | {:.2} | {} |",
+            phases.load_secs,
+            phases.init_secs,
+            result.time_secs,
+            phases.teardown_secs,
+            result.mips,
+            bench::format_bytes(result.resident_bytes)
         );
     }
 
@@ -258,42 +316,64 @@ fn main() {
         let _ = bench_support::registry::find_benchmark(filter);
     }
 
+    let backend_label = match backend {
+        Backend::C => "c",
+        Backend::X86Asm => "x86",
+        Backend::ARM64Asm => "arm64",
+    };
     let mut rows = Vec::new();
+    let mut report = BenchReport::default();
 
     for info in bench_support::registry::BENCHMARKS {
         if !filter_match(info.name, args.filter.as_deref()) {
             continue;
         }
+        let host = args
+            .json
+            .as_ref()
+            .and_then(|_| run_host_baseline(&project_dir, info, runs));
         let archs = Arch::parse_list(info.default_archs).unwrap_or_else(|_| vec![Arch::Rv64i]);
         for arch in archs {
-            let out_dir = match ensure_compiled(&project_dir, info, arch, backend, &args) {
-                Ok(dir) => dir,
-                Err(err) => {
-                    eprintln!("{} ({}) skipped: {}", info.name, arch.as_str(), err);
-                    continue;
-                }
+            let failed = |err: String| {
+                BenchRecord::failed(info.name, arch.as_str(), backend_label, Some(err))
             };
+            let (out_dir, compile_time_secs) =
+                match ensure_compiled(&project_dir, info, arch, backend, &args) {
+                    Ok(compiled) => compiled,
+                    Err(err) => {
+                        eprintln!("{} ({}) skipped: {}", info.name, arch.as_str(), err);
+                        report.results.push(failed(err));
+                        continue;
+                    }
+                };
             let elf_path = project_dir.join("bin").join(arch.as_str()).join(info.name);
             let result = match bench::run_bench_auto(&out_dir, &elf_path, runs) {
                 Ok((result, _)) => result,
                 Err(err) => {
                     eprintln!("{} ({}) failed: {}", info.name, arch.as_str(), err);
+                    report.results.push(BenchRecord {
+                        compile_time_secs,
+                        ..failed(err)
+                    });
                     continue;
                 }
             };
 
-            let backend_label = match backend {
-                Backend::C => "c",
-                Backend::X86Asm => "x86",
-                Backend::ARM64Asm => "arm64",
-            };
-
+            report.results.push(BenchRecord {
+                compile_time_secs,
+                ..BenchRecord::new(
+                    info.name,
+                    arch.as_str(),
+                    backend_label,
+                    &result,
+                    host.as_ref(),
+                )
+            });
             rows.push((
                 info.name.to_string(),
                 arch.as_str().to_string(),
                 backend_label.to_string(),
-                result.result.time_secs,
-                result.result.mips,
+                result.result,
             ));
         }
     }
@@ -305,4 +385,12 @@ fn main() {
         std::process::exit(1);
     }
     println!("wrote {}", out_path.display());
+
+    if let Some(json_path) = &args.json {
+        if let Err(err) = report.write(json_path) {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+        println!("wrote {}", json_path.display());
+    }
 }
@@ -0,0 +1,323 @@
+//! Run random instruction sequences on two backends and report divergences.
+//!
+//! Each program is built from a seed (program i uses `--seed` + i), so a
+//! divergence is reproduced by rerunning its seed with `-n 1`.
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use clap::{Parser, ValueEnum};
+use rvr::test_support::diff::{self, CompareResult, Divergence};
+use rvr::test_support::fuzz::{self, FuzzCase, FuzzConfig};
+use rvr::{Rv32, Rv64};
+use rvr_emit::Backend;
+use rvr_emit::c::DEFAULT_CLANG_COMMAND;
+
+#[derive(Parser, Debug)]
+#[command(name = "fuzz")]
+#[command(about = "Run random instruction sequences on two backends and report divergences")]
+// Independent command-line switches, one bool each
+#[allow(clippy::struct_excessive_bools)]
+struct Args {
+    /// Seed of the first program; program i uses seed + i (default: from the clock)
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Number of programs to generate
+    #[arg(short = 'n', long, default_value = "100")]
+    iterations: u64,
+
+    /// Random instructions per program
+    #[arg(long, default_value = "64")]
+    length: usize,
+
+    /// Extensions to draw instructions from
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_value = fuzz::DEFAULT_EXTENSIONS,
+        value_parser = fuzz::parse_extension
+    )]
+    ext: Vec<u8>,
+
+    /// Register width of the generated programs (32 or 64)
+    #[arg(long, default_value = "64", value_parser = parse_xlen)]
+    xlen: u8,
+
+    /// Reference backend
+    #[arg(long = "ref", value_enum, default_value = "c")]
+    ref_backend: BackendArg,
+
+    /// Test backend (default: the host's assembly backend)
+    #[arg(long = "test", value_enum)]
+    test_backend: Option<BackendArg>,
+
+    /// C compiler command
+    #[arg(long, default_value = DEFAULT_CLANG_COMMAND)]
+    cc: String,
+
+    /// Also compare memory values when available
+    #[arg(long)]
+    strict_mem: bool,
+
+    /// Directory for the ELF and listing of diverging programs
+    #[arg(short, long, default_value = ".")]
+    output: PathBuf,
+
+    /// Keep generating after a divergence
+    #[arg(long)]
+    keep_going: bool,
+}
+
+/// Backend a program runs on.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum BackendArg {
+    /// Spike (reference only)
+    Spike,
+    /// C backend
+    C,
+    /// ARM64 backend
+    Arm64,
+    /// x86 backend
+    X86,
+}
+
+/// Parse a register width: 32 or 64.
+fn parse_xlen(arg: &str) -> Result<u8, String> {
+    match arg {
+        "32" => Ok(32),
+        "64" => Ok(64),
+        _ => Err(format!("invalid XLEN '{arg}': expected 32 or 64")),
+    }
+}
+
+/// `None` is Spike.
+const fn fuzz_backend(arg: BackendArg) -> Option<Backend> {
+    match arg {
+        BackendArg::Spike => None,
+        BackendArg::C => Some(Backend::C),
+        BackendArg::Arm64 => Some(Backend::ARM64Asm),
+        BackendArg::X86 => Some(Backend::X86Asm),
+    }
+}
+
+const fn host_backend() -> Backend {
+    if cfg!(target_arch = "aarch64") {
+        Backend::ARM64Asm
+    } else if cfg!(target_arch = "x86_64") {
+        Backend::X86Asm
+    } else {
+        Backend::C
+    }
+}
+
+/// Name of `backend` as `--ref`/`--test` take it.
+const fn backend_arg(backend: Option<Backend>) -> &'static str {
+    match backend {
+        None => "spike",
+        Some(Backend::ARM64Asm) => "arm64",
+        Some(Backend::X86Asm) => "x86",
+        Some(_) => "c",
+    }
+}
+
+fn resolve_fuzz_backends(
+    args: &Args,
+    config: &FuzzConfig,
+) -> Result<(Option<Backend>, Backend), String> {
+    let reference = fuzz_backend(args.ref_backend);
+    let test = match args.test_backend.map(fuzz_backend) {
+        Some(Some(backend)) => backend,
+        Some(None) => return Err("Spike can only be used as reference backend".to_string()),
+        None => host_backend(),
+    };
+    if reference == Some(test) {
+        return Err(format!("reference and test backends are both {test:?}"));
+    }
+    if reference.is_none() && diff::find_spike().is_none() {
+        return Err("Spike not found in PATH".to_string());
+    }
+    if config.ops(args.xlen).is_empty() {
+        return Err(format!("no instructions to fuzz for rv{}", args.xlen));
+    }
+    Ok((reference, test))
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let config = FuzzConfig::new(args.length, args.ext.clone());
+    let (reference, test) = match resolve_fuzz_backends(&args, &config) {
+        Ok(pair) => pair,
+        Err(message) => {
+            eprintln!("{message}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let compiler: rvr::Compiler = match args.cc.parse() {
+        Ok(compiler) => compiler,
+        Err(e) => {
+            eprintln!("invalid compiler: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let work = match tempfile::tempdir() {
+        Ok(work) => work,
+        Err(e) => {
+            eprintln!("failed to create work dir: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let first_seed = args.seed.unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs())
+    });
+    let isa = config.spike_isa(args.xlen);
+    let flags = format!(
+        "--ref {} --test {} --xlen {} --ext {} --length {}",
+        backend_arg(reference),
+        backend_arg(Some(test)),
+        args.xlen,
+        config.extension_list(),
+        config.length,
+    );
+    eprintln!(
+        "Fuzzing {} programs from seed {first_seed}: {flags}",
+        args.iterations
+    );
+
+    let mut divergences = 0u64;
+    let mut programs = 0u64;
+    for i in 0..args.iterations {
+        let seed = first_seed.wrapping_add(i);
+        let case = if args.xlen == 32 {
+            fuzz::generate::<Rv32>(&config, seed)
+        } else {
+            fuzz::generate::<Rv64>(&config, seed)
+        };
+        let dir = work.path().join(seed.to_string());
+        let outcome = run_fuzz_case(
+            &case,
+            &dir,
+            reference,
+            test,
+            &compiler,
+            &isa,
+            args.strict_mem,
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+        programs += 1;
+
+        let mark = match outcome {
+            Ok(CompareResult {
+                divergence: None, ..
+            }) => continue,
+            Ok(CompareResult {
+                divergence: Some(div),
+                ..
+            }) => {
+                print_divergence(&div);
+                Some(div.actual.pc)
+            }
+            Err(message) => {
+                eprintln!();
+                eprintln!("ERROR (seed {seed}): {message}");
+                None
+            }
+        };
+        divergences += 1;
+        save_reproducer(&case, mark, &args.output, &flags);
+        if !args.keep_going {
+            break;
+        }
+    }
+
+    eprintln!();
+    eprintln!("{programs} programs, {divergences} divergences");
+    if divergences == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn run_fuzz_case(
+    case: &FuzzCase,
+    dir: &Path,
+    reference: Option<Backend>,
+    test: Backend,
+    compiler: &rvr::Compiler,
+    isa: &str,
+    strict_mem: bool,
+) -> Result<CompareResult, String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("failed to create {}: {e}", dir.display()))?;
+    let elf = dir.join("fuzz.elf");
+    std::fs::write(&elf, &case.elf).map_err(|e| format!("failed to write ELF: {e}"))?;
+    let config = diff::CompareConfig {
+        strict_reg_writes: true,
+        strict_mem_access: strict_mem,
+        strict_csrs: false,
+    };
+    let max_instrs = u64::try_from(case.max_instrs()).ok();
+
+    let test_dir = dir.join("test");
+    diff::compile_for_diff(&elf, &test_dir, test, compiler)?;
+    let mut test_exec = diff::InProcessExecutor::new(&test_dir, &elf)
+        .map_err(|e| format!("failed to load test executor: {e}"))?;
+    let result = if let Some(backend) = reference {
+        let ref_dir = dir.join("ref");
+        diff::compile_for_diff(&elf, &ref_dir, backend, compiler)?;
+        let mut ref_exec = diff::InProcessExecutor::new(&ref_dir, &elf)
+            .map_err(|e| format!("failed to load reference executor: {e}"))?;
+        diff::compare_lockstep(&mut ref_exec, &mut test_exec, &config, max_instrs)
+    } else {
+        let mut spike = diff::SpikeExecutor::start(&elf, isa, case.entry)
+            .map_err(|e| format!("failed to start Spike: {e}"))?;
+        diff::compare_lockstep(&mut spike, &mut test_exec, &config, max_instrs)
+    };
+    Ok(result)
+}
+
+fn print_divergence(div: &Divergence) {
+    eprintln!();
+    eprintln!("DIVERGENCE at instruction {}: {}", div.index, div.kind);
+    for (label, state) in [("Expected", &div.expected), ("Actual", &div.actual)] {
+        eprint!(
+            "  {label}: pc 0x{:x} opcode 0x{:08x}",
+            state.pc, state.opcode
+        );
+        if let (Some(rd), Some(val)) = (state.rd, state.rd_value) {
+            eprint!(" x{rd} = 0x{val:x}");
+        }
+        if let Some(addr) = state.mem_addr {
+            eprint!(" mem 0x{addr:x}");
+        }
+        eprintln!();
+    }
+}
+
+/// Write `fuzz-<seed>.elf` and `fuzz-<seed>.S` and print the listing.
+fn save_reproducer(case: &FuzzCase, mark: Option<u64>, output: &Path, flags: &str) {
+    let listing = case.listing(mark);
+    eprintln!();
+    eprint!("{listing}");
+
+    let base = output.join(format!("fuzz-{}", case.seed));
+    let elf = base.with_extension("elf");
+    let written = std::fs::create_dir_all(output)
+        .and_then(|()| std::fs::write(&elf, &case.elf))
+        .and_then(|()| std::fs::write(base.with_extension("S"), &listing));
+    match written {
+        Ok(()) => {
+            eprintln!();
+            eprintln!("Reproducer: {}", elf.display());
+            eprintln!(
+                "Rerun: cargo run --release --bin fuzz -- --seed {} -n 1 {flags}",
+                case.seed
+            );
+        }
+        Err(e) => eprintln!("failed to save reproducer {}: {e}", elf.display()),
+    }
+}
@@ -0,0 +1,155 @@
+//! Run a guest's `rvr_test!` unit tests under rvr.
+//!
+//! The library must be compiled with `--export-functions` (and `--instret
+//! suspend` for `--max-insns`).
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::Parser;
+use rvr::{GuestTestOptions, GuestTestReport, TestOutcome};
+
+#[derive(Parser, Debug)]
+#[command(name = "guest_test")]
+#[command(about = "Run a guest's rvr_test! unit tests under rvr")]
+struct Args {
+    /// Directory containing the compiled shared library
+    #[arg(value_name = "LIB_DIR")]
+    lib_dir: PathBuf,
+
+    /// Path to the ELF file
+    #[arg(value_name = "ELF_PATH")]
+    elf_path: PathBuf,
+
+    /// Run only tests whose name contains one of these
+    #[arg(value_name = "FILTER")]
+    filters: Vec<String>,
+
+    /// Match filters against the whole test name
+    #[arg(long)]
+    exact: bool,
+
+    /// List the tests instead of running them
+    #[arg(long)]
+    list: bool,
+
+    /// Maximum instructions per test before it times out (requires --instret suspend at compile time)
+    #[arg(long)]
+    max_insns: Option<u64>,
+
+    /// Number of tests to run in parallel (0 = auto)
+    #[arg(short = 'j', long, default_value = "1")]
+    jobs: usize,
+
+    /// Memory size as power of 2 (e.g., 30 = 1 GiB, 32 = 4 GiB)
+    #[arg(long, default_value = "32")]
+    memory_bits: u8,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let options = GuestTestOptions {
+        filters: args.filters,
+        exact: args.exact,
+        max_insns: args.max_insns,
+        jobs: args.jobs,
+        memory_size: 1usize << args.memory_bits,
+    };
+    if args.list {
+        list(&args.elf_path, &options)
+    } else {
+        match rvr::run_guest_tests(&args.lib_dir, &args.elf_path, &options) {
+            Ok(report) => {
+                print_report(&report);
+                if report.success() {
+                    ExitCode::SUCCESS
+                } else {
+                    ExitCode::FAILURE
+                }
+            }
+            Err(err) => {
+                eprintln!(
+                    "failed to run guest tests in {}: {err}",
+                    args.lib_dir.display()
+                );
+                ExitCode::FAILURE
+            }
+        }
+    }
+}
+
+/// Print the tests `options` selects, like `cargo test -- --list`.
+fn list(elf_path: &std::path::Path, options: &GuestTestOptions) -> ExitCode {
+    match rvr::list_guest_tests(elf_path) {
+        Ok(tests) => {
+            let tests: Vec<_> = tests
+                .iter()
+                .filter(|test| options.matches(&test.name))
+                .collect();
+            for test in &tests {
+                println!("{}: test", test.name);
+            }
+            println!();
+            println!("{} tests", tests.len());
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!(
+                "failed to list guest tests in {}: {err}",
+                elf_path.display()
+            );
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Print results in the format of Rust's test harness.
+fn print_report(report: &GuestTestReport) {
+    println!();
+    println!("running {} tests", report.results.len());
+    for result in &report.results {
+        let verdict = match result.outcome {
+            TestOutcome::Passed => "ok",
+            TestOutcome::Failed { .. } => "FAILED",
+            TestOutcome::TimedOut => "TIMEOUT",
+        };
+        println!("test {} ... {verdict}", result.test.name);
+    }
+
+    let failures: Vec<_> = report
+        .results
+        .iter()
+        .filter(|result| result.outcome != TestOutcome::Passed)
+        .collect();
+    if !failures.is_empty() {
+        println!();
+        println!("failures:");
+        for result in &failures {
+            println!();
+            println!("---- {} ----", result.test.name);
+            match &result.outcome {
+                TestOutcome::Failed { message } => println!("{message}"),
+                _ => println!(
+                    "exceeded the instruction limit ({} instructions)",
+                    result.instret
+                ),
+            }
+        }
+        println!();
+        println!("failures:");
+        for result in &failures {
+            println!("    {}", result.test.name);
+        }
+    }
+
+    println!();
+    println!(
+        "test result: {}. {} passed; {} failed; {} timed out; {} filtered out; finished in {:.2}s",
+        if report.success() { "ok" } else { "FAILED" },
+        report.passed(),
+        report.failed(),
+        report.timed_out(),
+        report.filtered_out,
+        report.time.as_secs_f64()
+    );
+}
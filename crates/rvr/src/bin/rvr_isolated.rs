@@ -0,0 +1,18 @@
+//! Child process of `Runner::spawn_isolated`.
+
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+fn main() {
+    rvr::serve_isolated()
+}
+
+#[cfg(not(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+)))]
+fn main() {
+    eprintln!("rvr-isolated: isolated runners need Linux on x86_64 or aarch64");
+    std::process::exit(1);
+}
@@ -0,0 +1,82 @@
+//! Print a binary trace (`--tracer binary-trace`) with disassembly.
+
+use std::fmt::Write as _;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::Parser;
+use rvr::test_support::trace::{TraceEntry, TraceReader};
+use rvr::{Rv32, Rv64};
+use rvr_isa::{Xlen, op_mnemonic};
+
+const HEX_DIGIT_BITS: usize = 4;
+
+#[derive(Parser, Debug)]
+#[command(name = "trace_dump")]
+#[command(about = "Print a binary trace with disassembly")]
+struct Args {
+    /// Binary trace file
+    #[arg(value_name = "FILE")]
+    file: PathBuf,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    match dump(&args.file) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("cannot read binary trace {}: {e}", args.file.display());
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn dump(file: &std::path::Path) -> io::Result<()> {
+    let mut reader = TraceReader::open(file)?;
+    let header = *reader.header();
+    let mut out = BufWriter::new(io::stdout().lock());
+    writeln!(
+        out,
+        "# binary trace v{} rv{} elf {:016x}",
+        header.version, header.xlen, header.elf_hash
+    )?;
+    while let Some(record) = reader.read_record()? {
+        let line = format_entry(&record.entry, header.xlen);
+        writeln!(out, "{:>10} {line}", record.instret)?;
+    }
+    out.flush()
+}
+
+/// One entry as `pc (opcode) mnemonic` followed by its register, memory
+/// and CSR effects, in Spike's notation.
+fn format_entry(entry: &TraceEntry, xlen: u8) -> String {
+    // Hex digits of an XLEN-wide value
+    let width = usize::from(xlen) / HEX_DIGIT_BITS;
+    let mnemonic = if xlen == Rv32::VALUE {
+        mnemonic::<Rv32>(entry)
+    } else {
+        mnemonic::<Rv64>(entry)
+    };
+    let mut line = format!(
+        "0x{:0width$x} (0x{:08x}) {mnemonic:<10}",
+        entry.pc, entry.opcode
+    );
+    if let (Some(rd), Some(value)) = (entry.rd, entry.rd_value) {
+        let _ = write!(line, " x{rd:<2} 0x{value:0width$x}");
+    }
+    if let Some(addr) = entry.mem_addr {
+        let _ = write!(line, " mem 0x{addr:0width$x}");
+    }
+    if let (Some(csr), Some(value)) = (entry.csr, entry.csr_value) {
+        let _ = write!(line, " c{csr} 0x{value:0width$x}");
+    }
+    line.trim_end().to_string()
+}
+
+/// Mnemonic of the entry's instruction, or `unknown` if it does not decode.
+fn mnemonic<X: Xlen>(entry: &TraceEntry) -> &'static str {
+    rvr_isa::decode::<X>(&entry.opcode.to_le_bytes(), X::from_u64(entry.pc))
+        .map_or("unknown", |instr| op_mnemonic(instr.opid.pack()))
+}
@@ -0,0 +1,535 @@
+//! Build driver of generated C projects.
+//!
+//! `make -k` builds every object it can. Objects that fail, e.g. because
+//! the compiler ran out of memory or crashed on one large part, are deleted
+//! and retried at `EmitConfig::fallback_opt_level`, so one part does not
+//! cost the whole build. Parts that still fail are reported with the guest
+//! functions they hold. Compile times come from the stamps the object rules
+//! touch before compiling (see `COMPILE_STAMP_SUFFIX`).
+//!
+//! Make's output is read line by line as it runs: lines are logged as they
+//! arrive, and the `COMPILED_MARKER` lines of the object rules are counted
+//! against a dry run's plan to report compile progress.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+use std::time::SystemTime;
+
+use rvr_emit::c::{COMPILE_STAMP_SUFFIX, COMPILED_MARKER, CompilerIdentity, OPT_VAR};
+use rvr_emit::{Backend, EmitConfig};
+use rvr_isa::Xlen;
+use tracing::{debug, error, info, info_span, warn};
+
+use crate::progress::{CompilePhase, ProgressFn};
+use crate::{Error, Result};
+
+/// Compiler output of a compiler killed or out of memory.
+const OOM_SIGNATURES: [&str; 6] = [
+    "killed signal terminated program",
+    "out of memory",
+    "cannot allocate memory",
+    "std::bad_alloc",
+    "virtual memory exhausted",
+    "] killed",
+];
+
+/// Compiler output of a compiler crash or internal compiler error.
+const CRASH_SIGNATURES: [&str; 4] = [
+    "internal compiler error",
+    "please submit a bug report",
+    "command failed due to signal",
+    "segmentation fault",
+];
+
+/// Marker of make's line for a failed target (`make: *** [Makefile:30: a.o] Error 1`).
+const MAKE_FAILURE: &str = "*** [";
+
+/// Guest functions listed per failed part.
+const MAX_LISTED_FUNCTIONS: usize = 8;
+
+/// Why a C part file failed to compile.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PartFailureKind {
+    /// The compiler ran out of memory or was killed.
+    OutOfMemory,
+    /// The compiler crashed (internal compiler error or signal).
+    CompilerCrash,
+    /// The compiler rejected the code.
+    Error,
+}
+
+impl PartFailureKind {
+    /// What to try next.
+    const fn hint(self) -> &'static str {
+        match self {
+            Self::OutOfMemory => {
+                "lower --part-cost to split the part, or -j to compile fewer parts at once"
+            }
+            Self::CompilerCrash => "try another compiler (--cc) or lower --part-cost",
+            Self::Error => "see the compiler output in the log",
+        }
+    }
+}
+
+impl fmt::Display for PartFailureKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfMemory => write!(f, "compiler out of memory"),
+            Self::CompilerCrash => write!(f, "compiler crash"),
+            Self::Error => write!(f, "compile error"),
+        }
+    }
+}
+
+/// A C source file that failed to compile.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PartFailure {
+    /// Source file, relative to the output directory.
+    pub file: String,
+    /// Failure category.
+    pub kind: PartFailureKind,
+    /// First compiler error (or make's report of the target).
+    pub message: String,
+    /// Guest functions whose first block is in the file.
+    pub functions: Vec<String>,
+}
+
+impl fmt::Display for PartFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}", self.file, self.kind)?;
+        if !self.functions.is_empty() {
+            let listed = &self.functions[..self.functions.len().min(MAX_LISTED_FUNCTIONS)];
+            write!(f, "; functions {}", listed.join(", "))?;
+            if self.functions.len() > listed.len() {
+                write!(f, " and {} more", self.functions.len() - listed.len())?;
+            }
+        }
+        write!(f, "): {}; {}", self.message, self.kind.hint())
+    }
+}
+
+/// Compile time of one C source file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PartTime {
+    /// Source file, relative to the output directory.
+    pub file: String,
+    /// Wall-clock seconds.
+    pub secs: f64,
+}
+
+/// Result of a successful build.
+#[derive(Clone, Debug, Default)]
+pub struct BuildOutcome {
+    /// Files compiled by this build, slowest first.
+    pub part_times: Vec<PartTime>,
+    /// Files that failed at the regular optimization level and built at
+    /// the fallback level.
+    pub retried: Vec<PartFailure>,
+}
+
+/// One build of a generated Makefile.
+pub struct MakeBuild<'a> {
+    pub dir: &'a Path,
+    pub jobs: usize,
+    pub quiet: bool,
+    /// Make targets, e.g. `shared`.
+    pub targets: Vec<&'static str>,
+    /// `-O` level failed objects are retried at.
+    pub fallback_opt_level: Option<u8>,
+    /// Guest functions by part file name, for failure reports.
+    pub part_functions: &'a HashMap<String, Vec<String>>,
+    /// Callback receiving compiled object counts (optional).
+    pub progress: Option<&'a ProgressFn>,
+}
+
+impl MakeBuild<'_> {
+    /// Run the build, retrying failed objects once at the fallback level.
+    ///
+    /// # Errors
+    /// Returns `PartsFailed` if objects still fail, or `CompilationFailed`
+    /// if make fails otherwise (e.g. linking).
+    pub fn run(&self) -> Result<BuildOutcome> {
+        let _span =
+            info_span!("compile_c", dir = %self.dir.display(), jobs = self.job_count()).entered();
+        let makefile_path = self.dir.join("Makefile");
+        if !makefile_path.exists() {
+            error!(path = %makefile_path.display(), "Makefile not found");
+            return Err(Error::CompilationFailed("Makefile not found".to_string()));
+        }
+
+        let started = SystemTime::now();
+        let output = self.make(&self.targets, true, None)?;
+        let retried = if output.status.success() {
+            Vec::new()
+        } else {
+            let failures = self.failures(&output);
+            if failures.is_empty() {
+                return Err(make_error(self.dir, &output));
+            }
+            self.remove_objects(&failures);
+            let level = self
+                .fallback_opt_level
+                .filter(|_| self.retryable(&failures));
+            let Some(level) = level else {
+                return Err(Error::PartsFailed {
+                    failures,
+                    fallback_opt_level: None,
+                });
+            };
+            for failure in &failures {
+                warn!(%failure, "retrying at -O{level}");
+            }
+            let objects: Vec<String> = failures.iter().map(|f| object_name(&f.file)).collect();
+            let objects: Vec<&str> = objects.iter().map(String::as_str).collect();
+            let retry = self.make(&objects, true, Some(level))?;
+            if !retry.status.success() {
+                let failures = self.failures(&retry);
+                if failures.is_empty() {
+                    return Err(make_error(self.dir, &retry));
+                }
+                self.remove_objects(&failures);
+                return Err(Error::PartsFailed {
+                    failures,
+                    fallback_opt_level: Some(level),
+                });
+            }
+            let output = self.make(&self.targets, false, None)?;
+            if !output.status.success() {
+                return Err(make_error(self.dir, &output));
+            }
+            failures
+        };
+
+        let part_times = compile_times(self.dir, started);
+        if let Some(part) = part_times.first() {
+            info!(file = %part.file, secs = format!("{:.2}", part.secs), "slowest part");
+        }
+        Ok(BuildOutcome {
+            part_times,
+            retried,
+        })
+    }
+
+    /// Parallel jobs of make (`jobs`, or the CPU count minus two if 0).
+    fn job_count(&self) -> usize {
+        if self.jobs == 0 {
+            num_cpus::get().saturating_sub(2).max(1)
+        } else {
+            self.jobs
+        }
+    }
+
+    /// Make command for `targets`, with `-k` if `keep_going`, and the
+    /// optimization level overridden if `opt_level` is set.
+    fn command(&self, targets: &[&str], keep_going: bool, opt_level: Option<u8>) -> Command {
+        let mut cmd = Command::new("make");
+        cmd.arg("-C")
+            .arg(self.dir)
+            .arg("-j")
+            .arg(self.job_count().to_string());
+        if keep_going {
+            cmd.arg("-k");
+        }
+        if let Some(level) = opt_level {
+            cmd.arg(format!("{OPT_VAR}=-O{level}"));
+        }
+        cmd.args(targets);
+        cmd
+    }
+
+    /// Run make on `targets` (see `command`), streaming its output.
+    fn make(&self, targets: &[&str], keep_going: bool, opt_level: Option<u8>) -> Result<Output> {
+        debug!(?targets, ?opt_level, "running make");
+        let total = match self.progress {
+            Some(_) => self.planned_objects(targets, opt_level),
+            None => 0,
+        };
+        self.stream(self.command(targets, keep_going, opt_level), total)
+            .map_err(|e| {
+                error!(error = %e, "failed to run make");
+                Error::CompilationFailed(format!("Failed to run make: {e}"))
+            })
+    }
+
+    /// Objects a make run on `targets` would compile, from a dry run.
+    fn planned_objects(&self, targets: &[&str], opt_level: Option<u8>) -> usize {
+        let mut cmd = self.command(targets, true, opt_level);
+        cmd.arg("-n").stdin(Stdio::null()).stderr(Stdio::null());
+        let marker = format!("echo {COMPILED_MARKER} ");
+        cmd.output().map_or(0, |output| {
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .filter(|line| line.trim_start().starts_with(&marker))
+                .count()
+        })
+    }
+
+    /// Run `cmd`, logging its output and reporting compiled objects out of
+    /// `total` as they finish. The output is also collected for the
+    /// failure diagnosis.
+    fn stream(&self, mut cmd: Command, total: usize) -> std::io::Result<Output> {
+        let mut child = cmd
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let (Some(stdout), Some(stderr)) = (child.stdout.take(), child.stderr.take()) else {
+            return Err(std::io::Error::other("make output is not piped"));
+        };
+        let marker = format!("{COMPILED_MARKER} ");
+        let mut compiled = 0;
+        if let Some(progress) = self.progress {
+            progress.report(CompilePhase::Cc, compiled, total);
+        }
+        let (stdout, stderr) = std::thread::scope(|scope| {
+            let stderr = scope.spawn(|| read_lines(stderr, |line| debug!("{line}")));
+            let stdout = read_lines(stdout, |line| {
+                if line.starts_with(&marker) {
+                    compiled += 1;
+                    if let Some(progress) = self.progress {
+                        progress.report(CompilePhase::Cc, compiled, total.max(compiled));
+                    }
+                } else if !self.quiet {
+                    debug!("{line}");
+                }
+            });
+            let stderr = stderr
+                .join()
+                .unwrap_or_else(|_| Err(std::io::Error::other("make stderr reader panicked")));
+            (stdout, stderr)
+        });
+        let status = child.wait()?;
+        Ok(Output {
+            status,
+            stdout: stdout?,
+            stderr: stderr?,
+        })
+    }
+
+    /// Objects make reported as failed, diagnosed from its output.
+    fn failures(&self, output: &Output) -> Vec<PartFailure> {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let objects = failed_objects(&stderr);
+        let single = objects.len() == 1;
+        objects
+            .into_iter()
+            .map(|object| {
+                let file = format!("{}.c", object.trim_end_matches(".o"));
+                let lines: Vec<&str> = stderr
+                    .lines()
+                    .filter(|line| single || line.contains(&file) || line.contains(&object))
+                    .collect();
+                let (kind, message) = diagnose(&lines);
+                let functions = self.part_functions.get(&file).cloned().unwrap_or_default();
+                PartFailure {
+                    file,
+                    kind,
+                    message,
+                    functions,
+                }
+            })
+            .collect()
+    }
+
+    /// Whether every failed source is in the build directory, so its object
+    /// can be rebuilt on its own (not so for multi-program libraries).
+    fn retryable(&self, failures: &[PartFailure]) -> bool {
+        failures.iter().all(|f| self.dir.join(&f.file).exists())
+    }
+
+    /// Delete the objects and stamps of failed files, so a retry starts clean.
+    fn remove_objects(&self, failures: &[PartFailure]) {
+        for failure in failures {
+            let object = object_name(&failure.file);
+            let stamp = format!("{object}{COMPILE_STAMP_SUFFIX}");
+            for name in [object, stamp] {
+                let path = self.dir.join(name);
+                if let Err(e) = std::fs::remove_file(&path)
+                    && e.kind() != std::io::ErrorKind::NotFound
+                {
+                    warn!(path = %path.display(), error = %e, "cannot remove failed object");
+                }
+            }
+        }
+    }
+}
+
+/// Read `pipe` to its end, passing each line to `on_line` as it arrives,
+/// and return everything read.
+/// Probe the compiler `config` builds with, failing before lifting if it
+/// cannot build the backend's output: C code of `config.c_dialect`, linked
+/// with the compiler's linker unless building a static archive.
+pub fn check_compiler<X: Xlen>(config: &EmitConfig<X>) -> Result<CompilerIdentity> {
+    let compiler = &config.compiler;
+    let identity = match config.backend {
+        Backend::C => compiler.check(config.c_dialect, !config.static_archive())?,
+        Backend::X86Asm | Backend::ARM64Asm => compiler.probe()?,
+    };
+    debug!(compiler = %compiler, identity = %identity, "probed compiler");
+    Ok(identity)
+}
+
+fn read_lines(pipe: impl Read, mut on_line: impl FnMut(&str)) -> std::io::Result<Vec<u8>> {
+    let mut reader = BufReader::new(pipe);
+    let mut all = Vec::new();
+    let mut line = Vec::new();
+    while reader.read_until(b'\n', &mut line)? > 0 {
+        on_line(String::from_utf8_lossy(&line).trim_end());
+        all.append(&mut line);
+    }
+    Ok(all)
+}
+
+/// Object file of source `file`.
+fn object_name(file: &str) -> String {
+    format!("{}.o", file.trim_end_matches(".c"))
+}
+
+/// Object targets of make's failure lines, in order of appearance.
+fn failed_objects(stderr: &str) -> Vec<String> {
+    let mut objects: Vec<String> = Vec::new();
+    for line in stderr.lines() {
+        let Some(start) = line.find(MAKE_FAILURE) else {
+            continue;
+        };
+        let rest = &line[start + MAKE_FAILURE.len()..];
+        let Some(end) = rest.find(']') else {
+            continue;
+        };
+        // GNU make 4 prefixes the target with `Makefile:<line>: `
+        let target = rest[..end].rsplit(": ").next().unwrap_or_default();
+        let is_object = Path::new(target).extension().is_some_and(|ext| ext == "o");
+        if is_object && !objects.iter().any(|o| o == target) {
+            objects.push(target.to_string());
+        }
+    }
+    objects
+}
+
+/// Failure category and the most telling line of a failed object's output.
+fn diagnose(lines: &[&str]) -> (PartFailureKind, String) {
+    let matching = |signatures: &[&str]| {
+        lines.iter().find(|line| {
+            let line = line.to_lowercase();
+            signatures.iter().any(|signature| line.contains(signature))
+        })
+    };
+    let (kind, line) = matching(&OOM_SIGNATURES)
+        .map(|line| (PartFailureKind::OutOfMemory, Some(line)))
+        .or_else(|| {
+            matching(&CRASH_SIGNATURES).map(|line| (PartFailureKind::CompilerCrash, Some(line)))
+        })
+        .unwrap_or_else(|| {
+            let line = lines
+                .iter()
+                .find(|line| line.contains("error:"))
+                .or_else(|| lines.iter().find(|line| line.contains(MAKE_FAILURE)));
+            (PartFailureKind::Error, line)
+        });
+    let message = line.map_or("unknown error", |line| line.trim());
+    (kind, message.to_string())
+}
+
+/// Error of a make run that failed outside any object.
+fn make_error(dir: &Path, output: &Output) -> Error {
+    let code = output.status.code().unwrap_or(-1);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    // Log full output for debugging
+    if !stderr.is_empty() {
+        error!(exit_code = code, dir = %dir.display(), stderr = %stderr, "make failed");
+    } else if !stdout.is_empty() {
+        error!(exit_code = code, dir = %dir.display(), stdout = %stdout, "make failed");
+    } else {
+        error!(exit_code = code, dir = %dir.display(), "make failed");
+    }
+    // Include the first compiler error (else the first line) in the
+    // error message for quick visibility
+    let first_error = stderr
+        .lines()
+        .find(|line| line.contains("error:"))
+        .or_else(|| stderr.lines().next())
+        .or_else(|| stdout.lines().next())
+        .unwrap_or("unknown error");
+    Error::CompilationFailed(format!("make failed: {first_error}"))
+}
+
+/// Compile times of the objects in `dir` built since `started`, slowest
+/// first.
+fn compile_times(dir: &Path, started: SystemTime) -> Vec<PartTime> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    let mut times: Vec<PartTime> = entries
+        .filter_map(std::result::Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let object = name.strip_suffix(COMPILE_STAMP_SUFFIX)?;
+            let start = modified(&entry.path()).filter(|&start| start >= started)?;
+            let end = modified(&dir.join(object))?;
+            Some(PartTime {
+                file: format!("{}.c", object.trim_end_matches(".o")),
+                secs: end.duration_since(start).ok()?.as_secs_f64(),
+            })
+        })
+        .collect();
+    times.sort_by(|a, b| b.secs.total_cmp(&a.secs).then_with(|| a.file.cmp(&b.file)));
+    times
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failed_objects() {
+        let stderr = "g_part1.c:3:2: error: #error injected\n\
+                      make: *** [Makefile:31: g_part1.o] Error 1\n\
+                      make: *** [g_part4.o] Killed\n\
+                      make: *** [Makefile:22: libg.so] Error 1\n\
+                      make: *** Waiting for unfinished jobs....\n";
+        assert_eq!(failed_objects(stderr), ["g_part1.o", "g_part4.o"]);
+    }
+
+    #[test]
+    fn test_diagnose() {
+        let (kind, message) = diagnose(&[
+            "g_part1.c: In function 'B_1000':",
+            "g_part1.c:3:2: error: #error injected",
+        ]);
+        assert_eq!(kind, PartFailureKind::Error);
+        assert_eq!(message, "g_part1.c:3:2: error: #error injected");
+
+        let (kind, message) = diagnose(&[
+            "gcc: fatal error: Killed signal terminated program cc1",
+            "make: *** [Makefile:31: g_part1.o] Error 1",
+        ]);
+        assert_eq!(kind, PartFailureKind::OutOfMemory);
+        assert_eq!(
+            message,
+            "gcc: fatal error: Killed signal terminated program cc1"
+        );
+
+        let (kind, _) = diagnose(&["g_part1.c:9:1: internal compiler error: in expand_expr"]);
+        assert_eq!(kind, PartFailureKind::CompilerCrash);
+    }
+
+    #[test]
+    fn test_part_failure_display() {
+        let failure = PartFailure {
+            file: "g_part1.c".to_string(),
+            kind: PartFailureKind::OutOfMemory,
+            message: "Killed".to_string(),
+            functions: (0..=MAX_LISTED_FUNCTIONS)
+                .map(|i| format!("f{i}"))
+                .collect(),
+        };
+        let text = failure.to_string();
+        assert!(text.starts_with("g_part1.c (compiler out of memory; functions f0, f1,"));
+        assert!(text.contains("f7 and 1 more): Killed; lower --part-cost"));
+    }
+}
@@ -0,0 +1,346 @@
+//! Persistent cache of compiled output directories.
+//!
+//! Lifting and building a large guest takes seconds to minutes, but the
+//! output only depends on the ELF, the effective [`EmitConfig`] and the
+//! toolchain. With `CompileOptions::with_cache` set, as the CLI does,
+//! [`compile_with_report`](crate::compile_with_report) looks the build up
+//! before lifting and, on a hit, copies the cached files into the
+//! output directory instead; after a successful build it stores them.
+//!
+//! An entry is a directory `<elf hash>-<inputs hash>` under the cache
+//! directory holding the output files and a small JSON file with the parts
+//! of the report that are not on disk. Entries are staged in a temporary
+//! directory and renamed into place, so a concurrent compile never sees a
+//! partial entry.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use rvr_emit::EmitConfig;
+use rvr_emit::c::{DedupStats, TracerSource, content_hash};
+use rvr_isa::Xlen;
+use serde::{Deserialize, Serialize};
+
+/// Environment variable overriding the cache directory.
+pub const CACHE_DIR_ENV: &str = "RVR_CACHE_DIR";
+/// File in each entry describing the build.
+const ENTRY_FILE: &str = "rvr-cache-entry.json";
+/// Prefix of entries still being written.
+const STAGING_PREFIX: &str = ".staging-";
+
+/// Report fields of a cached build that are not in its files.
+#[derive(Debug, Serialize, Deserialize)]
+struct EntryInfo {
+    /// File name of the shared library.
+    library: String,
+    dedup_groups: usize,
+    dedup_aliases: usize,
+    dedup_bytes_saved: usize,
+}
+
+/// A build restored from the cache.
+#[derive(Clone, Debug)]
+pub struct CachedBuild {
+    /// Shared library in the output directory.
+    pub library: PathBuf,
+    /// Deduplication results of the original build.
+    pub dedup: DedupStats,
+}
+
+/// Entry count and size of a cache directory.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Cached builds.
+    pub entries: usize,
+    /// Bytes of all cached files.
+    pub bytes: u64,
+}
+
+/// Directory of cached compiled outputs.
+#[derive(Clone, Debug)]
+pub struct ArtifactCache {
+    dir: PathBuf,
+}
+
+impl ArtifactCache {
+    /// Cache in `dir`.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Cache in [`default_dir`](Self::default_dir), if there is one.
+    #[must_use]
+    pub fn from_env() -> Option<Self> {
+        Self::default_dir().map(Self::new)
+    }
+
+    /// `$RVR_CACHE_DIR`, else `$XDG_CACHE_HOME/rvr/artifacts`, else
+    /// `~/.cache/rvr/artifacts`.
+    #[must_use]
+    pub fn default_dir() -> Option<PathBuf> {
+        let non_empty = |name| std::env::var_os(name).filter(|dir| !dir.is_empty());
+        if let Some(dir) = non_empty(CACHE_DIR_ENV) {
+            return Some(PathBuf::from(dir));
+        }
+        let base = non_empty("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))?;
+        Some(base.join("rvr").join("artifacts"))
+    }
+
+    /// Cache directory.
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Key of the build of `elf` with `config` into `output_dir`.
+    ///
+    /// Besides the ELF and [`EmitConfig::fingerprint`], the key covers what
+    /// else ends up in the output: the library name (the directory name),
+    /// the compiler's `--version`, the contents of the block profile, of a
+    /// tracer header file and of an embedded golden trace, the extra entry
+    /// points, and the rvr binary itself. Returns `None` if
+    /// any of them cannot be read, leaving the build uncached.
+    #[must_use]
+    pub fn key<X: Xlen>(
+        config: &EmitConfig<X>,
+        elf: &[u8],
+        output_dir: &Path,
+        profile: Option<&Path>,
+        entry_points: &[u64],
+    ) -> Option<String> {
+        let mut inputs = config.fingerprint().into_bytes();
+        let mut add = |name: &str, value: &[u8]| {
+            inputs.extend_from_slice(name.as_bytes());
+            inputs.push(b'=');
+            inputs.extend_from_slice(value);
+            inputs.push(0);
+        };
+        let name = output_dir.file_name().map(|n| n.to_string_lossy());
+        add("output", name.as_deref().unwrap_or_default().as_bytes());
+        add("rvr", build_id()?.as_bytes());
+        let cc = config.compiler.probe().ok()?;
+        add("cc", format!("{cc}\n{}", cc.version_output).as_bytes());
+        if let Some(path) = profile {
+            add("profile", &fs::read(path).ok()?);
+        }
+        if !entry_points.is_empty() {
+            let bytes: Vec<u8> = entry_points
+                .iter()
+                .flat_map(|pc| pc.to_le_bytes())
+                .collect();
+            add("entry_points", &bytes);
+        }
+        if let TracerSource::File { path, .. } = &config.tracer_config.source {
+            add("tracer", &fs::read(path).ok()?);
+        }
+        if let Some(path) = &config.embedded_golden {
+            add("golden", &fs::read(path).ok()?);
+        }
+        Some(format!(
+            "{:016x}-{:016x}",
+            content_hash(elf),
+            content_hash(&inputs)
+        ))
+    }
+
+    /// Path of the entry for `key`.
+    #[must_use]
+    pub fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(key)
+    }
+
+    /// Copy the entry for `key` into `output_dir`. Returns `None` on a miss.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from reading the entry or writing `output_dir`.
+    pub fn restore(&self, key: &str, output_dir: &Path) -> io::Result<Option<CachedBuild>> {
+        let entry = self.path(key);
+        let info = match fs::read_to_string(entry.join(ENTRY_FILE)) {
+            Ok(data) => serde_json::from_str::<EntryInfo>(&data).map_err(io::Error::other)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        fs::create_dir_all(output_dir)?;
+        for (path, name) in files(&entry)? {
+            if name != ENTRY_FILE {
+                fs::copy(&path, output_dir.join(&name))?;
+            }
+        }
+        Ok(Some(CachedBuild {
+            library: output_dir.join(info.library),
+            dedup: DedupStats {
+                groups: info.dedup_groups,
+                aliases: info.dedup_aliases,
+                bytes_saved: info.dedup_bytes_saved,
+            },
+        }))
+    }
+
+    /// Store the files of `output_dir`, built into `library`, under `key`.
+    /// An existing entry is kept.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from reading `output_dir` or writing the cache.
+    pub fn store(
+        &self,
+        key: &str,
+        output_dir: &Path,
+        library: &Path,
+        dedup: DedupStats,
+    ) -> io::Result<()> {
+        let entry = self.path(key);
+        if entry.exists() {
+            return Ok(());
+        }
+        let library = library
+            .file_name()
+            .ok_or_else(|| io::Error::other("library path has no file name"))?;
+        fs::create_dir_all(&self.dir)?;
+        let staging = tempfile::Builder::new()
+            .prefix(STAGING_PREFIX)
+            .tempdir_in(&self.dir)?;
+        for (path, name) in files(output_dir)? {
+            fs::copy(&path, staging.path().join(name))?;
+        }
+        let info = EntryInfo {
+            library: library.to_string_lossy().into_owned(),
+            dedup_groups: dedup.groups,
+            dedup_aliases: dedup.aliases,
+            dedup_bytes_saved: dedup.bytes_saved,
+        };
+        let data = serde_json::to_string_pretty(&info).map_err(io::Error::other)?;
+        fs::write(staging.path().join(ENTRY_FILE), data + "\n")?;
+        // Another compile may have stored the same build meanwhile
+        if let Err(e) = fs::rename(staging.path(), &entry)
+            && !entry.exists()
+        {
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Number and size of the cached builds.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from listing the cache directory.
+    pub fn stats(&self) -> io::Result<CacheStats> {
+        let mut stats = CacheStats::default();
+        for entry in self.entries()? {
+            stats.entries += 1;
+            stats.bytes += files(&entry)?
+                .iter()
+                .filter_map(|(path, _)| fs::metadata(path).ok())
+                .map(|meta| meta.len())
+                .sum::<u64>();
+        }
+        Ok(stats)
+    }
+
+    /// Remove every cached build, returning what was removed.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from listing or removing entries.
+    pub fn clear(&self) -> io::Result<CacheStats> {
+        let stats = self.stats()?;
+        for entry in self.entries()? {
+            fs::remove_dir_all(entry)?;
+        }
+        Ok(stats)
+    }
+
+    /// Entry directories (not ones being staged).
+    fn entries(&self) -> io::Result<Vec<PathBuf>> {
+        let dir = match fs::read_dir(&self.dir) {
+            Ok(dir) => dir,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut entries = Vec::new();
+        for entry in dir {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() && !entry.file_name().to_string_lossy().starts_with('.')
+            {
+                entries.push(entry.path());
+            }
+        }
+        Ok(entries)
+    }
+}
+
+/// Regular files directly in `dir`, with their names.
+fn files(dir: &Path) -> io::Result<Vec<(PathBuf, String)>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_file() {
+            files.push((
+                entry.path(),
+                entry.file_name().to_string_lossy().into_owned(),
+            ));
+        }
+    }
+    Ok(files)
+}
+
+/// Identity of the running rvr: its version plus the size and modification
+/// time of the executable, so a rebuilt rvr does not reuse old output.
+fn build_id() -> Option<String> {
+    let meta = fs::metadata(std::env::current_exe().ok()?).ok()?;
+    let modified = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some(format!(
+        "{} {} {}",
+        env!("CARGO_PKG_VERSION"),
+        meta.len(),
+        modified.as_nanos()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(dir: &Path, name: &str, data: &[u8]) {
+        fs::create_dir_all(dir).unwrap();
+        fs::write(dir.join(name), data).unwrap();
+    }
+
+    #[test]
+    fn test_store_restore_roundtrip() {
+        let temp = tempfile::tempdir().unwrap();
+        let cache = ArtifactCache::new(temp.path().join("cache"));
+        let built = temp.path().join("built");
+        write(&built, "libbuilt.so", b"library");
+        write(&built, "built_part0.c", b"source");
+        let dedup = DedupStats {
+            groups: 1,
+            aliases: 2,
+            bytes_saved: 3,
+        };
+
+        let out = temp.path().join("out");
+        assert!(cache.restore("k", &out).unwrap().is_none());
+        cache
+            .store("k", &built, &built.join("libbuilt.so"), dedup)
+            .unwrap();
+        let restored = cache.restore("k", &out).unwrap().unwrap();
+        assert_eq!(restored.library, out.join("libbuilt.so"));
+        assert_eq!(restored.dedup, dedup);
+        assert_eq!(fs::read(out.join("built_part0.c")).unwrap(), b"source");
+        assert!(!out.join(ENTRY_FILE).exists());
+
+        let stats = cache.stats().unwrap();
+        assert_eq!(stats.entries, 1);
+        assert!(stats.bytes >= 13);
+        assert_eq!(cache.clear().unwrap(), stats);
+        assert_eq!(cache.stats().unwrap(), CacheStats::default());
+        assert!(cache.restore("k", &out).unwrap().is_none());
+    }
+}
@@ -0,0 +1,148 @@
+//! Argument groups shared by several commands.
+
+use std::path::PathBuf;
+
+use rvr::{
+    BareMetalConfig, CompilerLauncher, DEFAULT_FALLBACK_OPT_LEVEL, DEFAULT_TARGET_PART_COST,
+};
+use rvr_cfg::{DEFAULT_SUPERBLOCK_DEPTH, DEFAULT_SUPERBLOCK_MAX_INSTRS};
+use rvr_emit::c::DEFAULT_TRACER_PAGE_SIZE;
+
+use super::{TracerKindArg, UnmappedEcallArg, parse_env_var, parse_size};
+
+/// Tracer configuration arguments.
+#[derive(clap::Args, Clone, Debug)]
+pub struct TracerArgs {
+    /// Tracer kind (built-in).
+    #[arg(long, value_enum, default_value = "none")]
+    pub tracer: TracerKindArg,
+
+    /// Custom tracer header path (overrides --tracer).
+    #[arg(long)]
+    pub tracer_header: Option<PathBuf>,
+
+    /// Inline custom tracer header content (overrides --tracer).
+    #[arg(long)]
+    pub tracer_inline: Option<String>,
+
+    /// Passed vars for the tracer (e.g. ptr:data, `index:data_idx`).
+    #[arg(long = "tracer-pass", value_name = "KIND:NAME", action = clap::ArgAction::Append)]
+    pub tracer_pass: Vec<String>,
+
+    /// Page size in bytes for the page access tracer (power of two).
+    #[arg(long, default_value_t = DEFAULT_TRACER_PAGE_SIZE)]
+    pub tracer_page_size: u64,
+
+    /// Trace one instruction in N (C backend); sampled traces have gaps and
+    /// cannot be diffed in lockstep. The golden tracer samples one block
+    /// entry in N instead.
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    pub tracer_sample_interval: u32,
+}
+
+/// Guest environment variables (Linux syscall mode).
+#[derive(clap::Args, Clone, Debug)]
+pub struct GuestEnvArgs {
+    /// Set a guest environment variable (repeatable; overrides --env-file)
+    #[arg(long = "env", value_name = "KEY=VALUE", value_parser = parse_env_var, action = clap::ArgAction::Append)]
+    pub vars: Vec<(String, String)>,
+
+    /// Set the guest environment variables of a dotenv file
+    #[arg(long, value_name = "FILE")]
+    pub env_file: Option<PathBuf>,
+}
+
+/// Block size limits for merging and superblock formation.
+#[derive(clap::Args, Clone, Copy, Debug)]
+pub struct SuperblockArgs {
+    /// Maximum guest instructions per emitted block; longer blocks and
+    /// superblocks are split at a block boundary.
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_SUPERBLOCK_MAX_INSTRS)]
+    pub superblock_max_instrs: usize,
+
+    /// Maximum basic blocks merged into one emitted block.
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_SUPERBLOCK_DEPTH)]
+    pub superblock_max_blocks: usize,
+}
+
+/// Splitting and compiling of the generated C part files.
+#[derive(clap::Args, Clone, Debug)]
+pub struct PartArgs {
+    /// Target estimated compile cost (IR statements) per C part file;
+    /// parts are balanced around it so `make -j` finishes evenly.
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_TARGET_PART_COST)]
+    pub part_cost: usize,
+
+    /// Optimization level part files that fail to compile (e.g. the
+    /// compiler runs out of memory on one) are retried at
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_FALLBACK_OPT_LEVEL)]
+    pub fallback_opt_level: u8,
+
+    /// Fail the build at once when a part file fails to compile
+    #[arg(long)]
+    pub no_fallback: bool,
+
+    /// Command prefixed to compile rules: `auto` (ccache or sccache if on
+    /// PATH), `none`, or a command such as `sccache`.
+    #[arg(long, value_name = "CMD", default_value = "auto")]
+    pub compiler_launcher: CompilerLauncher,
+}
+
+impl PartArgs {
+    /// Level failed parts are retried at, if any.
+    pub const fn fallback_opt_level(&self) -> Option<u8> {
+        if self.no_fallback {
+            None
+        } else {
+            Some(self.fallback_opt_level)
+        }
+    }
+}
+
+/// Bare-metal ECALLs mapped to built-in actions.
+#[derive(clap::Args, Clone, Debug)]
+pub struct EcallArgs {
+    /// Map bare-metal ECALL numbers (in a7) to actions instead of exiting
+    /// on every ECALL: `exit`, `putchar`, `instret`, `host` or `host:N`
+    /// (host syscall), e.g. `1=putchar,93=exit`
+    #[arg(long = "ecall", value_name = "NUM=ACTION,...")]
+    pub ecalls: Option<BareMetalConfig>,
+
+    /// What ECALL numbers not mapped by --ecall do
+    #[arg(long, value_enum, default_value = "trap")]
+    pub ecall_unmapped: UnmappedEcallArg,
+}
+
+impl EcallArgs {
+    /// The ECALL mapping, if any.
+    #[must_use]
+    pub fn config(&self) -> Option<BareMetalConfig> {
+        self.ecalls
+            .clone()
+            .map(|ecalls| ecalls.with_unmapped(self.ecall_unmapped.into()))
+    }
+}
+
+/// Heap, stack and mmap arena sizes (checked against the ELF at lift time).
+#[derive(clap::Args, Clone, Copy, Debug)]
+pub struct MemoryLayoutArgs {
+    /// Heap size above the initial program break; brk/mmap fail with ENOMEM
+    /// beyond it (bytes, hex or K/M/G suffix).
+    #[arg(long = "heap-size", value_name = "SIZE", value_parser = parse_size)]
+    pub heap: Option<u64>,
+
+    /// Stack size below `__stack_top` or the end of memory (bytes, hex or
+    /// K/M/G suffix).
+    #[arg(long = "stack-size", value_name = "SIZE", value_parser = parse_size)]
+    pub stack: Option<u64>,
+
+    /// Arena at the top of the heap for anonymous mmap/munmap/mremap (bytes,
+    /// hex or K/M/G suffix).
+    #[arg(long = "mmap-size", value_name = "SIZE", value_parser = parse_size)]
+    pub mmap: Option<u64>,
+
+    /// Guard below the stack; stores into it fail the run as a stack
+    /// overflow (bytes, hex or K/M/G suffix; 0 for none).
+    #[arg(long = "stack-guard", value_name = "SIZE", value_parser = parse_size)]
+    pub stack_guard: Option<u64>,
+}
@@ -0,0 +1,285 @@
+//! Arguments of `rvr compile`.
+
+use std::path::PathBuf;
+
+use rvr_emit::DEFAULT_VLEN;
+
+use super::{
+    AddressModeArg, AnalysisModeArg, BackendArg, CDialectArg, DispatchModeArg, EcallArgs,
+    HotRegsModeArg, InstretModeArg, LayoutArg, LiftErrorModeArg, LrScModelArg, MemoryLayoutArgs,
+    PartArgs, SuperblockArgs, SuspendGranularityArg, SyscallModeArg, TracerArgs, parse_pc,
+    parse_size, parse_vlen,
+};
+
+/// Arguments of `rvr compile`.
+#[derive(clap::Args, Clone, Debug)]
+// Independent command-line switches, one bool each
+#[allow(clippy::struct_excessive_bools)]
+pub struct CompileArgs {
+    /// Input ELF file
+    #[arg(value_name = "ELF")]
+    pub input: PathBuf,
+
+    /// Output directory
+    #[arg(short, long, default_value = "output")]
+    pub output: PathBuf,
+
+    /// Code generation backend
+    #[arg(long, value_enum, default_value = "c")]
+    pub backend: BackendArg,
+
+    /// Analysis mode (auto = CFG for C, linear for asm)
+    #[arg(long, value_enum, default_value = "auto")]
+    pub analysis: AnalysisModeArg,
+
+    /// Address translation mode
+    #[arg(long, value_enum, default_value = "wrap")]
+    pub address_mode: AddressModeArg,
+
+    /// Dispatch table layout for dynamic jumps (C backend)
+    #[arg(long, value_enum, default_value = "flat")]
+    pub dispatch: DispatchModeArg,
+
+    /// Hot register sets: one for the program, or one per function
+    /// with spill glue between functions (C backend)
+    #[arg(long, value_enum, default_value = "global")]
+    pub hot_regs: HotRegsModeArg,
+
+    /// Store the flat dispatch table as offsets from the table instead of
+    /// function pointers, so it needs no dynamic relocations (C backend)
+    #[arg(long)]
+    pub relative_dispatch_table: bool,
+
+    /// Prefetch an indirect jump's dispatch table slot as soon as its
+    /// target register is final (C flat dispatch and ARM64 backends)
+    #[arg(long)]
+    pub prefetch_dispatch: bool,
+
+    /// Place the dispatch table in its own 2 MiB aligned section for
+    /// huge-page backing (C flat dispatch and ARM64 backends)
+    #[arg(long)]
+    pub dispatch_table_hugepages: bool,
+
+    /// Serve Linux `clock_gettime` from the retired instruction count
+    /// instead of the host clock (RV64)
+    #[arg(long)]
+    pub deterministic_clock: bool,
+
+    /// C dialect of the generated code (C backend; GCC older than 15
+    /// gets portable C)
+    #[arg(long, value_enum, default_value = "clang")]
+    pub c_dialect: CDialectArg,
+
+    /// Enable HTIF (Host-Target Interface) for riscv-tests
+    #[arg(long)]
+    pub htif: bool,
+
+    /// Print guest HTIF writes to stdout/stderr (otherwise discarded
+    /// unless the runner redirects them)
+    #[arg(long, requires = "htif")]
+    pub htif_verbose: bool,
+
+    /// Stop an HTIF guest that polls `fromhost` this many times without
+    /// it changing (0 disables the watchdog)
+    #[arg(long, requires = "htif", default_value_t = rvr_emit::DEFAULT_HTIF_POLL_LIMIT)]
+    pub htif_poll_limit: u64,
+
+    /// Instruction retirement mode
+    #[arg(long, value_enum, default_value = "count")]
+    pub instret: InstretModeArg,
+
+    /// Where `--instret suspend` checks the instret limit
+    #[arg(long, value_enum, default_value = "block-entry")]
+    pub suspend_granularity: SuspendGranularityArg,
+
+    /// Syscall handling mode
+    #[arg(long, value_enum, default_value = "baremetal")]
+    pub syscalls: SyscallModeArg,
+
+    #[command(flatten)]
+    pub ecalls: EcallArgs,
+
+    /// Perf mode (disable instret and CSR reads)
+    #[arg(long)]
+    pub perf: bool,
+
+    /// Disable superblock formation (keeps blocks at natural boundaries).
+    /// Useful for differential testing where dispatch to all block entries is needed.
+    #[arg(long)]
+    pub no_superblock: bool,
+
+    #[command(flatten)]
+    pub superblock: SuperblockArgs,
+
+    #[command(flatten)]
+    pub parts: PartArgs,
+
+    #[command(flatten)]
+    pub memory_layout: MemoryLayoutArgs,
+
+    /// Emit blocks with identical bodies once, as aliases (C backend)
+    #[arg(long)]
+    pub dedup_blocks: bool,
+
+    /// Disable dead register write elimination on the lifted IR
+    #[arg(long)]
+    pub no_optimize_ir: bool,
+
+    /// Let guest stores into recompiled code through instead of failing
+    /// the run (the recompiled code keeps running the original bytes)
+    #[arg(long)]
+    pub no_code_write_check: bool,
+
+    /// Stop at backward jumps and branches when the host cancels or a
+    /// run timeout expires (C backend)
+    #[arg(long)]
+    pub check_cancel: bool,
+
+    /// Emit trap, exit and suspension paths as calls to shared cold
+    /// helpers instead of inline in every block (C backend)
+    #[arg(long)]
+    pub outline_cold_paths: bool,
+
+    /// Check every dynamic jump's dispatch lookup and report the jump
+    /// site, target and source register when it has no block, for `rvr
+    /// run --collect-jump-targets` (C backend; costs a compare per jump)
+    #[arg(long)]
+    pub report_jump_sites: bool,
+
+    /// Also build a static archive and an embedding header, for hosts
+    /// that link the program instead of loading it (C backend, no LTO)
+    #[arg(long)]
+    pub static_archive: bool,
+
+    /// Prefix the generated global symbols, so several programs can be
+    /// linked into one host (C backend)
+    #[arg(long, value_name = "PREFIX")]
+    pub symbol_prefix: Option<String>,
+
+    /// What to do when a block fails to lift.
+    /// With quarantine, the compile exits with code 3 if any block was stubbed.
+    #[arg(long, value_enum, default_value = "abort")]
+    pub on_lift_error: LiftErrorModeArg,
+
+    /// Fail on any instruction that would trap (undecodable or without a
+    /// lifter), including code the CFG does not reach
+    #[arg(long)]
+    pub strict_decode: bool,
+
+    /// Lower AMOs to LSE instructions instead of exclusive loops
+    /// (ARM64 backend, needs ARMv8.1)
+    #[arg(long)]
+    pub arm64_lse: bool,
+
+    /// LR/SC model: track the reservation, or let every SC succeed
+    #[arg(long, value_enum, default_value = "address-reservation")]
+    pub lrsc: LrScModelArg,
+
+    /// Write segment data to <name>.segments and map it into guest
+    /// memory on first touch instead of copying it at startup
+    #[arg(long)]
+    pub lazy_segments: bool,
+
+    /// Embed segments of at least this many bytes (default 64K) as LZ4
+    /// blocks in the library (C backend)
+    #[arg(
+        long,
+        value_name = "MIN_SIZE",
+        num_args = 0..=1,
+        default_missing_value = "64K",
+        value_parser = parse_size
+    )]
+    pub compress_segments: Option<u64>,
+
+    /// Run the guest's memcpy/memset/memcmp (by symbol name) as native
+    /// helpers on guest memory (C backend, no tracer)
+    #[arg(long)]
+    pub native_mem_intrinsics: bool,
+
+    /// Build line tables mapping host code to guest PCs through a
+    /// `guest_pc.map` sidecar, for perf/gdb and `rvr addr2pc` (C and
+    /// assembly backends)
+    #[arg(long)]
+    pub guest_pc_map: bool,
+
+    /// Write `size_report.tsv` attributing guest instructions, emitted C
+    /// and compiled host bytes to each guest function (C backend)
+    #[arg(long)]
+    pub report: bool,
+
+    /// Recompile with the block counts of a previous run (from `rvr run
+    /// --profile-counts`): hints branches, marks blocks that never ran
+    /// cold and picks hot registers by use (C backend)
+    #[arg(long, value_name = "FILE")]
+    pub profile: Option<PathBuf>,
+
+    /// Embed a golden trace (from a run compiled with `--tracer golden`)
+    /// and stop where the register checksums diverge from it (C backend)
+    #[arg(long, value_name = "FILE")]
+    pub embed_golden: Option<PathBuf>,
+
+    /// Run the block starting at this PC in the block interpreter instead
+    /// of compiling it (C backend; repeatable)
+    #[arg(long, value_name = "PC", value_parser = parse_pc)]
+    pub interpret_block: Vec<u64>,
+
+    /// Interpret the blocks of C parts that fail to compile and build
+    /// again (C backend)
+    #[arg(long)]
+    pub interpret_failed_blocks: bool,
+
+    /// Lift the dynamic jump targets collected by `rvr run
+    /// --collect-jump-targets` as extra entry points
+    #[arg(long, value_name = "FILE")]
+    pub jump_targets: Option<PathBuf>,
+
+    /// Address-space layout profile. Checks the ELF against the profile
+    /// and overrides --address-mode with the profile's mode.
+    #[arg(long, value_enum)]
+    pub layout: Option<LayoutArg>,
+
+    /// Always lift and build, neither reusing nor storing a cached build
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// Number of parallel compile jobs (0 = auto)
+    #[arg(short = 'j', long, default_value = "0")]
+    pub jobs: usize,
+
+    /// Threads for CFG analysis and lifting (0 = auto)
+    #[arg(long, default_value = "0")]
+    pub analysis_jobs: usize,
+
+    /// C compiler command (e.g., clang, clang-20, gcc-13)
+    #[arg(long)]
+    pub cc: Option<String>,
+
+    /// Linker to use (e.g., lld, lld-20). Auto-derived from --cc if not specified.
+    #[arg(long)]
+    pub linker: Option<String>,
+
+    /// Use fixed addresses for state and memory (experimental).
+    /// Format: "`STATE_ADDR,MEMORY_ADDR`" (hex) or "default" for default addresses.
+    /// Requires runtime to map memory at these addresses.
+    #[arg(long, value_name = "ADDRS")]
+    pub fixed_addresses: Option<String>,
+
+    /// Load address for position-independent (PIE) ELFs (hex; default 0x10000).
+    /// Ignored for non-PIE executables.
+    #[arg(long, value_name = "ADDR", value_parser = parse_pc)]
+    pub load_bias: Option<u64>,
+
+    /// ISA string selecting the extensions to decode (e.g.
+    /// `rv64imac_zicsr_zba`). Overrides the ELF's .riscv.attributes; without
+    /// either, all supported extensions are decoded.
+    #[arg(long, value_name = "ISA")]
+    pub isa: Option<String>,
+
+    /// Vector register length in bits for V extension code (power of two
+    /// from 64 to 1024).
+    #[arg(long, value_name = "BITS", default_value_t = DEFAULT_VLEN, value_parser = parse_vlen)]
+    pub vlen: u32,
+
+    #[command(flatten)]
+    pub tracer: TracerArgs,
+}
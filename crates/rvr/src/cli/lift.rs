@@ -0,0 +1,137 @@
+//! Arguments of `rvr lift`.
+
+use std::ops::Range;
+use std::path::PathBuf;
+
+use rvr_emit::DEFAULT_VLEN;
+
+use super::{
+    AddressModeArg, AnalysisModeArg, BackendArg, CDialectArg, DispatchModeArg, EcallArgs,
+    HotRegsModeArg, InstretModeArg, MemoryLayoutArgs, PartArgs, SuperblockArgs,
+    SuspendGranularityArg, SyscallModeArg, TracerArgs, parse_pc, parse_pc_range, parse_vlen,
+};
+
+/// Arguments of `rvr lift`.
+#[derive(clap::Args, Clone, Debug)]
+// Independent command-line switches, one bool each
+#[allow(clippy::struct_excessive_bools)]
+pub struct LiftArgs {
+    /// Input ELF file
+    #[arg(value_name = "ELF")]
+    pub input: PathBuf,
+
+    /// Output directory
+    #[arg(short, long, default_value = "output")]
+    pub output: PathBuf,
+
+    /// Code generation backend
+    #[arg(long, value_enum, default_value = "c")]
+    pub backend: BackendArg,
+
+    /// Analysis mode (auto = CFG for C, linear for asm)
+    #[arg(long, value_enum, default_value = "auto")]
+    pub analysis: AnalysisModeArg,
+
+    /// Address translation mode
+    #[arg(long, value_enum, default_value = "wrap")]
+    pub address_mode: AddressModeArg,
+
+    /// Dispatch table layout for dynamic jumps (C backend)
+    #[arg(long, value_enum, default_value = "flat")]
+    pub dispatch: DispatchModeArg,
+
+    /// Hot register sets: one for the program, or one per function
+    /// with spill glue between functions (C backend)
+    #[arg(long, value_enum, default_value = "global")]
+    pub hot_regs: HotRegsModeArg,
+
+    /// C dialect of the generated code (C backend; GCC older than 15
+    /// gets portable C)
+    #[arg(long, value_enum, default_value = "clang")]
+    pub c_dialect: CDialectArg,
+
+    /// Enable HTIF (Host-Target Interface) for riscv-tests
+    #[arg(long)]
+    pub htif: bool,
+
+    /// Print guest HTIF writes to stdout/stderr (otherwise discarded
+    /// unless the runner redirects them)
+    #[arg(long, requires = "htif")]
+    pub htif_verbose: bool,
+
+    /// Emit #line directives with source locations (requires debug info in ELF)
+    #[arg(long, default_value = "true")]
+    pub line_info: bool,
+
+    /// Instruction retirement mode
+    #[arg(long, value_enum, default_value = "count")]
+    pub instret: InstretModeArg,
+
+    /// Where `--instret suspend` checks the instret limit
+    #[arg(long, value_enum, default_value = "block-entry")]
+    pub suspend_granularity: SuspendGranularityArg,
+
+    /// Syscall handling mode
+    #[arg(long, value_enum, default_value = "baremetal")]
+    pub syscalls: SyscallModeArg,
+
+    #[command(flatten)]
+    pub ecalls: EcallArgs,
+
+    /// Perf mode (disable instret and CSR reads)
+    #[arg(long)]
+    pub perf: bool,
+
+    /// Emit blocks with identical bodies once, as aliases (C backend)
+    #[arg(long)]
+    pub dedup_blocks: bool,
+
+    /// Disable dead register write elimination on the lifted IR
+    #[arg(long)]
+    pub no_optimize_ir: bool,
+
+    #[command(flatten)]
+    pub superblock: SuperblockArgs,
+
+    #[command(flatten)]
+    pub parts: PartArgs,
+
+    #[command(flatten)]
+    pub memory_layout: MemoryLayoutArgs,
+
+    /// Use fixed addresses for state and memory (experimental).
+    /// Format: "`STATE_ADDR,MEMORY_ADDR`" (hex) or "default" for default addresses.
+    /// Requires runtime to map memory at these addresses.
+    #[arg(long, value_name = "ADDRS")]
+    pub fixed_addresses: Option<String>,
+
+    /// Load address for position-independent (PIE) ELFs (hex; default 0x10000).
+    /// Ignored for non-PIE executables.
+    #[arg(long, value_name = "ADDR", value_parser = parse_pc)]
+    pub load_bias: Option<u64>,
+
+    /// ISA string selecting the extensions to decode (e.g.
+    /// `rv64imac_zicsr_zba`). Overrides the ELF's .riscv.attributes; without
+    /// either, all supported extensions are decoded.
+    #[arg(long, value_name = "ISA")]
+    pub isa: Option<String>,
+
+    /// Vector register length in bits for V extension code (power of two
+    /// from 64 to 1024).
+    #[arg(long, value_name = "BITS", default_value_t = DEFAULT_VLEN, value_parser = parse_vlen)]
+    pub vlen: u32,
+
+    /// Lift only these functions, as one C file for reading; jumps out
+    /// of them become trap stubs and the output is not runnable (C
+    /// backend)
+    #[arg(long, value_name = "SYMBOLS", value_delimiter = ',')]
+    pub only: Vec<String>,
+
+    /// Lift only the blocks starting in these PC ranges, like `--only`
+    /// (e.g. 0x1000-0x2000)
+    #[arg(long, value_name = "RANGE", value_delimiter = ',', value_parser = parse_pc_range)]
+    pub only_range: Vec<Range<u64>>,
+
+    #[command(flatten)]
+    pub tracer: TracerArgs,
+}
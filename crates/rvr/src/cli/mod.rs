@@ -0,0 +1,310 @@
+//! CLI definitions and argument types.
+
+mod args;
+mod compile;
+mod lift;
+mod parse;
+mod subcommands;
+mod values;
+
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand, ValueEnum};
+
+pub use args::*;
+pub use compile::*;
+pub use lift::*;
+pub use parse::*;
+pub use subcommands::*;
+pub use values::*;
+
+/// Exit code for success.
+pub const EXIT_SUCCESS: i32 = 0;
+/// Exit code for failure.
+pub const EXIT_FAILURE: i32 = 1;
+/// Exit code for a compile that succeeded with quarantined blocks.
+pub const EXIT_QUARANTINED: i32 = 3;
+
+#[derive(Parser)]
+#[command(name = "rvr")]
+#[command(about = "RISC-V Recompiler - compiles ELF to native code via C")]
+#[command(version)]
+pub struct Cli {
+    /// Show metrics summary after execution
+    #[arg(long, global = true)]
+    pub metrics: bool,
+
+    /// Enable verbose output (sets `RUST_LOG=debug`)
+    #[arg(short, long, global = true)]
+    pub verbose: bool,
+
+    /// Suppress output (only show errors)
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub silent: bool,
+
+    #[command(subcommand)]
+    pub command: Commands,
+}
+
+#[derive(Subcommand)]
+pub enum Commands {
+    /// Compile an ELF file to a shared library
+    Compile(CompileArgs),
+    /// Lift an ELF file to C source (without compiling)
+    Lift(LiftArgs),
+    /// Summarize an ELF (XLEN, ABI, segments, symbols) without compiling
+    /// it, or explain how the block containing a PC is lifted and emitted
+    Inspect {
+        /// Input ELF file
+        #[arg(value_name = "ELF")]
+        input: PathBuf,
+
+        /// Guest PC to explain (hex, e.g. 0x80001234)
+        #[arg(long, value_name = "PC", value_parser = parse_pc)]
+        explain: Option<u64>,
+
+        /// Linearly decode the code segments and print an extension histogram
+        #[arg(long, conflicts_with = "explain")]
+        decode: bool,
+
+        /// Summary output format
+        #[arg(long, value_enum, default_value = "text", conflicts_with = "explain")]
+        format: OutputFormat,
+
+        /// Guest memory size as power of 2; segments past it are flagged
+        #[arg(long, default_value = "32")]
+        memory_bits: u8,
+
+        /// Load address for position-independent (PIE) ELFs (hex; default 0x10000).
+        /// Ignored for non-PIE executables.
+        #[arg(long, value_name = "ADDR", value_parser = parse_pc)]
+        load_bias: Option<u64>,
+
+        /// Analysis mode (auto = CFG for C, linear for asm)
+        #[arg(long, value_enum, default_value = "auto")]
+        analysis: AnalysisModeArg,
+
+        /// Address translation mode
+        #[arg(long, value_enum, default_value = "wrap")]
+        address_mode: AddressModeArg,
+
+        /// Instruction retirement mode
+        #[arg(long, value_enum, default_value = "count")]
+        instret: InstretModeArg,
+
+        /// Syscall handling mode
+        #[arg(long, value_enum, default_value = "baremetal")]
+        syscalls: SyscallModeArg,
+
+        /// Disable superblock formation (keeps blocks at natural boundaries).
+        #[arg(long)]
+        no_superblock: bool,
+
+        #[command(flatten)]
+        tracer: TracerArgs,
+    },
+    /// Print the guest PC of a host address in a library compiled with
+    /// --guest-pc-map
+    Addr2pc {
+        /// Compiled shared library
+        #[arg(value_name = "LIB")]
+        library: PathBuf,
+
+        /// Address in the library file (hex, e.g. 0x1a2b0)
+        #[arg(value_name = "HOST_ADDR", value_parser = parse_pc)]
+        host_addr: u64,
+    },
+    /// Run a compiled shared library
+    Run {
+        /// Directory containing the compiled shared library
+        #[arg(value_name = "LIB_DIR")]
+        lib_dir: PathBuf,
+
+        /// Path to the ELF file
+        #[arg(value_name = "ELF_PATH")]
+        elf_path: PathBuf,
+
+        /// Output format
+        #[arg(long, value_enum, default_value = "text")]
+        format: OutputFormat,
+
+        /// Number of runs (for averaging)
+        #[arg(long, default_value = "1")]
+        runs: usize,
+
+        /// Memory size as power of 2 (e.g., 30 = 1 GiB, 32 = 4 GiB)
+        #[arg(long, default_value = "32")]
+        memory_bits: u8,
+
+        /// Maximum instructions to execute before stopping (requires --instret suspend at compile time)
+        #[arg(long)]
+        max_insns: Option<u64>,
+
+        /// Call a function by name instead of running from entry point (requires --export-functions at compile time)
+        #[arg(long)]
+        call: Option<String>,
+
+        /// Start GDB server on specified address (e.g., :1234 or 127.0.0.1:1234)
+        #[arg(long)]
+        gdb: Option<String>,
+
+        /// Load state from file before execution
+        #[arg(long)]
+        load_state: Option<PathBuf>,
+
+        /// Save state to file after execution
+        #[arg(long)]
+        save_state: Option<PathBuf>,
+
+        /// Feed the guest's stdin from a file
+        #[arg(long, value_name = "FILE")]
+        stdin: Option<PathBuf>,
+
+        /// Write the guest's stdout to a file
+        #[arg(long, value_name = "FILE")]
+        stdout: Option<PathBuf>,
+
+        /// Write the guest's stderr to a file
+        #[arg(long, value_name = "FILE")]
+        stderr: Option<PathBuf>,
+
+        #[command(flatten)]
+        env: GuestEnvArgs,
+
+        /// Record every host input the guest consumes (reads, opens, stats,
+        /// clock and getrandom), with its command line and environment, to
+        /// this file for --replay; written even if the run fails
+        #[arg(long, value_name = "FILE", conflicts_with_all = ["gdb", "debug", "runs", "replay"])]
+        record: Option<PathBuf>,
+
+        /// Feed the guest the inputs recorded with --record instead of
+        /// touching host files, stdin or the clock, failing if it diverges
+        #[arg(long, value_name = "FILE", conflicts_with_all = ["gdb", "debug", "runs"])]
+        replay: Option<PathBuf>,
+
+        /// Write a folded-stack block profile (flamegraph input) and print the
+        /// hottest blocks (requires --tracer block-profile at compile time)
+        #[arg(long, value_name = "FILE", conflicts_with_all = ["gdb", "debug"])]
+        profile: Option<PathBuf>,
+
+        /// Save the block entry counts for `rvr compile --profile` (requires
+        /// --tracer block-profile at compile time)
+        #[arg(long, value_name = "FILE", conflicts_with_all = ["gdb", "debug"])]
+        profile_counts: Option<PathBuf>,
+
+        /// Write guest line and branch coverage as an lcov tracefile for
+        /// genhtml (requires --tracer coverage at compile time)
+        #[arg(long, value_name = "FILE", conflicts_with_all = ["gdb", "debug"])]
+        coverage: Option<PathBuf>,
+
+        /// Sample the guest every --profile-host-interval instructions and
+        /// print host cost (cycles, or time without perf counters) per guest
+        /// function next to its guest instructions; JSON with --format json
+        /// (requires --instret suspend at compile time)
+        #[arg(long, conflicts_with_all = ["gdb", "debug", "call", "runs"])]
+        profile_host: bool,
+
+        /// Guest instructions between --profile-host samples
+        #[arg(long, value_name = "N", default_value_t = rvr::DEFAULT_HOST_SAMPLE_INTERVAL)]
+        profile_host_interval: u64,
+
+        /// Print the guest call stack to stderr if the program traps
+        #[arg(long, conflicts_with_all = ["gdb", "debug", "runs"])]
+        backtrace: bool,
+
+        /// On an unresolved dynamic jump, add its site and target to this
+        /// JSON file for `rvr compile --jump-targets` (needs a library
+        /// compiled with --report-jump-sites)
+        #[arg(long, value_name = "FILE", conflicts_with_all = ["gdb", "debug", "runs"])]
+        collect_jump_targets: Option<PathBuf>,
+
+        /// Interactive debugger mode (requires --instret suspend at compile time)
+        #[arg(long, conflicts_with_all = ["gdb", "runs"])]
+        debug: bool,
+    },
+    /// Compile an ELF into a temporary directory and run it at once,
+    /// exiting with the guest's exit code
+    Exec {
+        /// Input ELF file
+        #[arg(value_name = "ELF")]
+        input: PathBuf,
+
+        /// Syscall handling mode
+        #[arg(long, value_enum, default_value = "baremetal")]
+        syscalls: SyscallModeArg,
+
+        /// Instruction retirement mode
+        #[arg(long, value_enum, default_value = "count")]
+        instret: InstretModeArg,
+
+        /// C compiler command (e.g., clang, clang-20, gcc-13)
+        #[arg(long)]
+        cc: Option<String>,
+
+        /// Hide the C compiler's progress output
+        #[arg(long)]
+        quiet: bool,
+
+        #[command(flatten)]
+        env: GuestEnvArgs,
+
+        /// Arguments for the guest after `--` (its `argv[0]` is the ELF path)
+        #[arg(last = true, value_name = "GUEST_ARGS")]
+        args: Vec<String>,
+    },
+    /// Build Rust project to RISC-V ELF
+    Build {
+        /// Path to Rust project (directory with Cargo.toml)
+        #[arg(value_name = "PATH", default_value = ".")]
+        path: PathBuf,
+
+        /// Target architectures (comma-separated: rv32i,rv32e,rv64i,rv64e)
+        #[arg(short, long, default_value = "rv64i")]
+        target: String,
+
+        /// Output directory for ELF binaries (default: bin/{arch}/{name})
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Output binary name (default: crate name from Cargo.toml)
+        #[arg(short, long)]
+        name: Option<String>,
+
+        /// Rust toolchain to use (default: nightly)
+        #[arg(long, default_value = "nightly")]
+        toolchain: String,
+
+        /// Additional features to enable
+        #[arg(long)]
+        features: Option<String>,
+
+        /// Build in release mode (default: true)
+        #[arg(long, default_value = "true")]
+        release: bool,
+
+        /// Show the exact cargo command being run
+        #[arg(short, long)]
+        verbose: bool,
+    },
+    /// Developer utilities
+    Dev {
+        #[command(subcommand)]
+        command: DevCommands,
+    },
+}
+
+/// Output format for run command.
+#[derive(Clone, Copy, Debug, ValueEnum, Default)]
+pub enum OutputFormat {
+    /// Human-readable output (default)
+    #[default]
+    Text,
+    /// Raw key-value output (for scripting)
+    Raw,
+    /// JSON output
+    Json,
+}
+
+// ============================================================================
+// Tracer configuration helpers
+// ============================================================================
@@ -0,0 +1,170 @@
+//! Value parsers and conversions for CLI arguments.
+
+use std::ops::Range;
+
+use rvr::FixedAddressConfig;
+use rvr_emit::c::{PassedVar, TracerConfig};
+use rvr_emit::{MAX_VLEN, valid_vlen};
+
+use super::TracerArgs;
+
+/// Parse passed vars from CLI arguments.
+pub fn parse_passed_vars(items: &[String]) -> Result<Vec<PassedVar>, String> {
+    let mut vars = Vec::new();
+    for item in items {
+        let mut parts = item.splitn(2, ':');
+        let kind = parts.next().unwrap_or("");
+        let name = parts.next().unwrap_or("");
+        if name.is_empty() {
+            return Err(format!("invalid tracer var '{item}', expected KIND:NAME"));
+        }
+        let var = match kind {
+            "ptr" => PassedVar::ptr(name),
+            "index" => PassedVar::index(name),
+            "value" => PassedVar::value(name),
+            _ => {
+                return Err(format!(
+                    "invalid tracer var kind '{kind}', expected ptr/index/value"
+                ));
+            }
+        };
+        vars.push(var);
+    }
+    Ok(vars)
+}
+
+/// Build tracer configuration from CLI arguments.
+pub fn build_tracer_config(args: &TracerArgs) -> Result<TracerConfig, String> {
+    let passed_vars = parse_passed_vars(&args.tracer_pass)?;
+    let interval = args.tracer_sample_interval;
+    if interval == 0 {
+        return Err("tracer sample interval must be at least 1".to_string());
+    }
+
+    if args.tracer_header.is_some() && args.tracer_inline.is_some() {
+        return Err("only one of --tracer-header or --tracer-inline may be used".to_string());
+    }
+
+    if let Some(path) = &args.tracer_header {
+        let name = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("custom");
+        return Ok(
+            TracerConfig::custom_file(name, path, passed_vars).with_sample_interval(interval)
+        );
+    }
+
+    if let Some(inline) = &args.tracer_inline {
+        return Ok(TracerConfig::custom_inline("inline", inline, passed_vars)
+            .with_sample_interval(interval));
+    }
+
+    if !args.tracer_page_size.is_power_of_two() {
+        return Err(format!(
+            "tracer page size must be a power of two, got {}",
+            args.tracer_page_size
+        ));
+    }
+
+    let mut config = TracerConfig::builtin(args.tracer.into())
+        .with_page_size(args.tracer_page_size)
+        .with_sample_interval(interval);
+    if !passed_vars.is_empty() {
+        config = config.with_passed_vars(passed_vars);
+    }
+    Ok(config)
+}
+
+/// Parse a guest PC (hex, with or without `0x`).
+pub fn parse_pc(arg: &str) -> Result<u64, String> {
+    let digits = arg
+        .strip_prefix("0x")
+        .or_else(|| arg.strip_prefix("0X"))
+        .unwrap_or(arg);
+    u64::from_str_radix(digits, 16).map_err(|e| format!("invalid PC '{arg}': {e}"))
+}
+
+/// Parse a guest environment variable `KEY=VALUE`.
+pub fn parse_env_var(arg: &str) -> Result<(String, String), String> {
+    match arg.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("invalid variable '{arg}': expected KEY=VALUE")),
+    }
+}
+
+/// Parse a guest PC range `START-END` (hex, end exclusive).
+pub fn parse_pc_range(arg: &str) -> Result<Range<u64>, String> {
+    let (start, end) = arg
+        .split_once('-')
+        .ok_or_else(|| format!("invalid PC range '{arg}': expected START-END"))?;
+    let (start, end) = (parse_pc(start.trim())?, parse_pc(end.trim())?);
+    if start >= end {
+        return Err(format!("invalid PC range '{arg}': empty"));
+    }
+    Ok(start..end)
+}
+
+/// Parse a size in bytes: decimal with an optional binary `K`/`M`/`G`
+/// suffix, or hex with `0x`.
+pub fn parse_size(arg: &str) -> Result<u64, String> {
+    if let Some(hex) = arg.strip_prefix("0x").or_else(|| arg.strip_prefix("0X")) {
+        return u64::from_str_radix(hex, 16).map_err(|e| format!("invalid size '{arg}': {e}"));
+    }
+    let (digits, shift) = match arg.as_bytes().last() {
+        Some(b'k' | b'K') => (&arg[..arg.len() - 1], 10),
+        Some(b'm' | b'M') => (&arg[..arg.len() - 1], 20),
+        Some(b'g' | b'G') => (&arg[..arg.len() - 1], 30),
+        _ => (arg, 0),
+    };
+    let value: u64 = digits
+        .parse()
+        .map_err(|e| format!("invalid size '{arg}': {e}"))?;
+    value
+        .checked_mul(1 << shift)
+        .ok_or_else(|| format!("size '{arg}' is too large"))
+}
+
+/// Parse a vector register length in bits.
+pub fn parse_vlen(arg: &str) -> Result<u32, String> {
+    let vlen: u32 = arg
+        .parse()
+        .map_err(|e| format!("invalid VLEN '{arg}': {e}"))?;
+    if !valid_vlen(vlen) {
+        return Err(format!(
+            "invalid VLEN '{arg}': expected a power of two from 64 to {MAX_VLEN}"
+        ));
+    }
+    Ok(vlen)
+}
+
+/// Parse fixed addresses from CLI argument.
+///
+/// Accepts:
+/// - "default" - use default addresses (64GB, 128GB)
+/// - "`STATE_ADDR,MEMORY_ADDR`" - hex addresses (e.g., "0x1000000000,0x2000000000")
+pub fn parse_fixed_addresses(arg: &str) -> Result<FixedAddressConfig, String> {
+    let arg = arg.trim();
+
+    if arg.eq_ignore_ascii_case("default") {
+        return Ok(FixedAddressConfig::default());
+    }
+
+    let parts: Vec<&str> = arg.split(',').collect();
+    if parts.len() != 2 {
+        return Err("expected format: STATE_ADDR,MEMORY_ADDR (hex) or 'default'".to_string());
+    }
+
+    let parse_hex = |s: &str| -> Result<u64, String> {
+        let s = s.trim().trim_start_matches("0x").trim_start_matches("0X");
+        u64::from_str_radix(s, 16).map_err(|e| format!("invalid hex address: {e}"))
+    };
+
+    let state_addr = parse_hex(parts[0])?;
+    let memory_addr = parse_hex(parts[1])?;
+
+    Ok(FixedAddressConfig {
+        state_addr,
+        memory_addr,
+    })
+}
@@ -0,0 +1,127 @@
+//! Subcommands of `rvr dev`.
+
+use std::path::PathBuf;
+
+use clap::Subcommand;
+use rvr_emit::c::DEFAULT_CLANG_COMMAND;
+
+use super::{
+    DiffBackendArg, DiffExecutorArg, DiffGranularityArg, DiffModeArg, TraceReferenceArg, parse_size,
+};
+
+#[derive(Subcommand)]
+pub enum DevCommands {
+    /// Trace comparison between rvr and Spike or QEMU (differential testing)
+    Trace {
+        /// Path to ELF binary
+        elf: PathBuf,
+
+        /// Output directory for compiled rvr code (default: temp dir)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// C compiler command
+        #[arg(long, default_value = DEFAULT_CLANG_COMMAND)]
+        cc: String,
+
+        /// Stop on first difference
+        #[arg(long)]
+        stop_on_first: bool,
+
+        /// ISA string for Spike (auto-detected if not specified)
+        #[arg(long)]
+        isa: Option<String>,
+
+        /// Timeout in seconds
+        #[arg(long, default_value = "60")]
+        timeout: u64,
+
+        /// Reference simulator
+        #[arg(long, value_enum, default_value = "spike")]
+        reference: TraceReferenceArg,
+
+        /// QEMU execlog plugin and options (for `--reference qemu`)
+        #[arg(long, default_value = "libexeclog.so,reg=*")]
+        qemu_plugin: String,
+    },
+    /// Lockstep differential execution between backends
+    Diff {
+        /// Comparison mode
+        #[arg(value_enum)]
+        mode: DiffModeArg,
+
+        /// Path to ELF binary
+        elf: PathBuf,
+
+        /// Reference backend (overrides mode)
+        #[arg(long = "ref", value_enum)]
+        ref_backend: Option<DiffBackendArg>,
+
+        /// Test backend (overrides mode)
+        #[arg(long = "test", value_enum)]
+        test_backend: Option<DiffBackendArg>,
+
+        /// Comparison granularity
+        #[arg(short, long, value_enum, default_value = "instruction")]
+        granularity: DiffGranularityArg,
+
+        /// How to step the test backend
+        #[arg(long, value_enum, default_value = "in-process")]
+        executor: DiffExecutorArg,
+
+        /// Maximum instructions to compare
+        #[arg(short = 'n', long)]
+        max_instrs: Option<u64>,
+
+        /// Output directory for compiled code (default: temp dir)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Use pre-compiled reference from this directory
+        #[arg(long)]
+        ref_dir: Option<PathBuf>,
+
+        /// Use pre-compiled test from this directory
+        #[arg(long)]
+        test_dir: Option<PathBuf>,
+
+        /// C compiler command
+        #[arg(long, default_value = DEFAULT_CLANG_COMMAND)]
+        cc: String,
+
+        /// ISA string for Spike (auto-detected if not specified)
+        #[arg(long)]
+        isa: Option<String>,
+
+        /// Also compare memory values when available
+        #[arg(long)]
+        strict_mem: bool,
+
+        /// Also compare clock counters (cycle, time), which differ between
+        /// backends
+        #[arg(long)]
+        strict_csrs: bool,
+
+        /// Re-run Spike instead of replaying its cached trace
+        #[arg(long)]
+        refresh_ref: bool,
+
+        /// Directory of cached Spike traces (default: ~/.cache/rvr/ref-traces)
+        #[arg(long, value_name = "DIR")]
+        ref_cache: Option<PathBuf>,
+
+        /// Size cap of the Spike trace cache; least recently used traces are
+        /// evicted beyond it (bytes, hex or K/M/G suffix)
+        #[arg(long, value_name = "SIZE", value_parser = parse_size, default_value = "8G")]
+        ref_cache_size: u64,
+
+        /// Compare every N instructions (checkpoint granularity only: sampled
+        /// traces cannot be compared in lockstep)
+        #[arg(long, value_name = "N")]
+        sample_interval: Option<u64>,
+    },
+}
+
+// ============================================================================
+// Argument types with conversions
+// ============================================================================
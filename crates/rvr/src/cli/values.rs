@@ -0,0 +1,372 @@
+//! `ValueEnum` arguments and their conversions to library types.
+
+use clap::ValueEnum;
+use rvr::test_support::trace::TraceFormat;
+use rvr::{
+    AddressMode, CDialect, DispatchMode, HotRegsMode, InstretMode, LayoutProfile, LiftErrorMode,
+    LrScModel, SuspendGranularity, SyscallMode, UnmappedEcall,
+};
+use rvr_emit::c::TracerKind;
+
+/// Instruction retirement counting mode.
+#[derive(Clone, Copy, Debug, ValueEnum, Default)]
+pub enum InstretModeArg {
+    /// No instruction counting
+    Off,
+    /// Count instructions
+    #[default]
+    Count,
+    /// Count and suspend at limit (checked at block boundaries)
+    Suspend,
+    /// Count and suspend at limit (checked after every instruction)
+    PerInstruction,
+}
+
+impl From<InstretModeArg> for InstretMode {
+    fn from(arg: InstretModeArg) -> Self {
+        match arg {
+            InstretModeArg::Off => Self::Off,
+            InstretModeArg::Count => Self::Count,
+            InstretModeArg::Suspend => Self::Suspend,
+            InstretModeArg::PerInstruction => Self::PerInstruction,
+        }
+    }
+}
+
+/// Where `--instret suspend` checks the instret limit.
+#[derive(Clone, Copy, Debug, ValueEnum, Default)]
+pub enum SuspendGranularityArg {
+    /// Check on entry to every block
+    #[default]
+    BlockEntry,
+    /// Also check on loop back-edges inside a block
+    BackEdges,
+}
+
+impl From<SuspendGranularityArg> for SuspendGranularity {
+    fn from(arg: SuspendGranularityArg) -> Self {
+        match arg {
+            SuspendGranularityArg::BlockEntry => Self::BlockEntry,
+            SuspendGranularityArg::BackEdges => Self::BackEdges,
+        }
+    }
+}
+
+/// Tracer kind argument.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum TracerKindArg {
+    None,
+    Preflight,
+    Stats,
+    Ffi,
+    Dynamic,
+    Debug,
+    Spike,
+    Diff,
+    BufferedDiff,
+    PageAccess,
+    BlockProfile,
+    BinaryTrace,
+    Coverage,
+    Golden,
+}
+
+impl From<TracerKindArg> for TracerKind {
+    fn from(arg: TracerKindArg) -> Self {
+        match arg {
+            TracerKindArg::None => Self::None,
+            TracerKindArg::Preflight => Self::Preflight,
+            TracerKindArg::Stats => Self::Stats,
+            TracerKindArg::Ffi => Self::Ffi,
+            TracerKindArg::Dynamic => Self::Dynamic,
+            TracerKindArg::Debug => Self::Debug,
+            TracerKindArg::Spike => Self::Spike,
+            TracerKindArg::Diff => Self::Diff,
+            TracerKindArg::BufferedDiff => Self::BufferedDiff,
+            TracerKindArg::PageAccess => Self::PageAccess,
+            TracerKindArg::BlockProfile => Self::BlockProfile,
+            TracerKindArg::BinaryTrace => Self::BinaryTrace,
+            TracerKindArg::Coverage => Self::Coverage,
+            TracerKindArg::Golden => Self::Golden,
+        }
+    }
+}
+
+/// Syscall handling mode.
+#[derive(Clone, Copy, Debug, ValueEnum, Default)]
+pub enum SyscallModeArg {
+    /// Bare-metal syscalls (exit, or the --ecall map).
+    #[default]
+    Baremetal,
+    /// Linux-style syscalls (brk/mmap/read/write, etc).
+    Linux,
+}
+
+impl From<SyscallModeArg> for SyscallMode {
+    fn from(arg: SyscallModeArg) -> Self {
+        match arg {
+            SyscallModeArg::Baremetal => Self::BareMetal,
+            SyscallModeArg::Linux => Self::Linux,
+        }
+    }
+}
+
+/// Behavior of unmapped bare-metal ECALLs.
+#[derive(Clone, Copy, Debug, ValueEnum, Default)]
+pub enum UnmappedEcallArg {
+    /// Exit with a0 as exit code.
+    Exit,
+    /// Stop with an illegal-instruction trap (C backend).
+    #[default]
+    Trap,
+}
+
+impl From<UnmappedEcallArg> for UnmappedEcall {
+    fn from(arg: UnmappedEcallArg) -> Self {
+        match arg {
+            UnmappedEcallArg::Exit => Self::Exit,
+            UnmappedEcallArg::Trap => Self::Trap,
+        }
+    }
+}
+
+/// Address translation mode for memory accesses.
+#[derive(Clone, Copy, Debug, ValueEnum, Default)]
+pub enum AddressModeArg {
+    /// Assume valid + passthrough (guard pages catch OOB)
+    Unchecked,
+    /// Mask to memory size, no branch (matches sv39; alias: mask)
+    #[default]
+    #[value(alias = "mask")]
+    Wrap,
+    /// Bounds check + trap (explicit errors)
+    Bounds,
+}
+
+impl From<AddressModeArg> for AddressMode {
+    fn from(arg: AddressModeArg) -> Self {
+        match arg {
+            AddressModeArg::Unchecked => Self::Unchecked,
+            AddressModeArg::Wrap => Self::Wrap,
+            AddressModeArg::Bounds => Self::Bounds,
+        }
+    }
+}
+
+/// Dispatch table layout for dynamic jumps.
+#[derive(Clone, Copy, Debug, ValueEnum, Default)]
+pub enum DispatchModeArg {
+    /// One table slot per 2-byte text offset (fastest lookup)
+    #[default]
+    Flat,
+    /// Per-function tables with only block entries (smaller binaries)
+    PerFunction,
+}
+
+impl From<DispatchModeArg> for DispatchMode {
+    fn from(arg: DispatchModeArg) -> Self {
+        match arg {
+            DispatchModeArg::Flat => Self::Flat,
+            DispatchModeArg::PerFunction => Self::PerFunction,
+        }
+    }
+}
+
+/// Hot register sets.
+#[derive(Clone, Copy, Debug, ValueEnum, Default)]
+pub enum HotRegsModeArg {
+    /// One set for the whole program
+    #[default]
+    Global,
+    /// Each function's most accessed registers
+    PerFunction,
+}
+
+impl From<HotRegsModeArg> for HotRegsMode {
+    fn from(arg: HotRegsModeArg) -> Self {
+        match arg {
+            HotRegsModeArg::Global => Self::Global,
+            HotRegsModeArg::PerFunction => Self::PerFunction,
+        }
+    }
+}
+
+/// C dialect of the generated code.
+#[derive(Clone, Copy, Debug, ValueEnum, Default)]
+pub enum CDialectArg {
+    /// C23 with clang extensions: musttail chains, `preserve_none` (fastest)
+    #[default]
+    Clang,
+    /// Plain C11 with a trampoline loop (any C compiler, slower)
+    Portable,
+}
+
+impl From<CDialectArg> for CDialect {
+    fn from(arg: CDialectArg) -> Self {
+        match arg {
+            CDialectArg::Clang => Self::Clang,
+            CDialectArg::Portable => Self::Portable,
+        }
+    }
+}
+
+/// LR/SC reservation model.
+#[derive(Clone, Copy, Debug, ValueEnum, Default)]
+pub enum LrScModelArg {
+    /// SC fails unless a matching LR reservation is still valid (spec)
+    #[default]
+    AddressReservation,
+    /// SC always succeeds; stores skip the reservation check (faster)
+    AlwaysSucceed,
+}
+
+impl From<LrScModelArg> for LrScModel {
+    fn from(arg: LrScModelArg) -> Self {
+        match arg {
+            LrScModelArg::AddressReservation => Self::AddressReservation,
+            LrScModelArg::AlwaysSucceed => Self::AlwaysSucceed,
+        }
+    }
+}
+
+/// What to do when a block fails to lift.
+#[derive(Clone, Copy, Debug, ValueEnum, Default)]
+pub enum LiftErrorModeArg {
+    /// Fail the compile (default)
+    #[default]
+    Abort,
+    /// Replace the block with a trap stub and keep compiling
+    Quarantine,
+}
+
+impl From<LiftErrorModeArg> for LiftErrorMode {
+    fn from(arg: LiftErrorModeArg) -> Self {
+        match arg {
+            LiftErrorModeArg::Abort => Self::Abort,
+            LiftErrorModeArg::Quarantine => Self::Quarantine,
+        }
+    }
+}
+
+/// Address-space layout profile.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum LayoutArg {
+    /// rvr-rt default link.x
+    Baremetal,
+    /// zkVM-style RV32 (bounds-checked, stack below the program)
+    Rv32Zkvm,
+}
+
+impl From<LayoutArg> for LayoutProfile {
+    fn from(arg: LayoutArg) -> Self {
+        match arg {
+            LayoutArg::Baremetal => Self::Baremetal,
+            LayoutArg::Rv32Zkvm => Self::Rv32ZkVm,
+        }
+    }
+}
+
+/// Code generation backend.
+#[derive(Clone, Copy, Debug, ValueEnum, Default)]
+pub enum BackendArg {
+    /// Emit C code, compile with clang/gcc (default)
+    #[default]
+    C,
+    /// Emit x86-64 assembly, compile with gcc/as (experimental)
+    X86,
+    /// Emit ARM64 assembly, compile with gcc/as (experimental)
+    Arm64,
+}
+
+impl From<BackendArg> for rvr_emit::Backend {
+    fn from(arg: BackendArg) -> Self {
+        match arg {
+            BackendArg::C => Self::C,
+            BackendArg::X86 => Self::X86Asm,
+            BackendArg::Arm64 => Self::ARM64Asm,
+        }
+    }
+}
+
+/// Reference simulator for trace comparison.
+#[derive(Clone, Copy, Debug, ValueEnum, Default)]
+pub enum TraceReferenceArg {
+    /// Spike `--log-commits` (default)
+    #[default]
+    Spike,
+    /// QEMU user mode with the execlog plugin
+    Qemu,
+}
+
+impl From<TraceReferenceArg> for TraceFormat {
+    fn from(arg: TraceReferenceArg) -> Self {
+        match arg {
+            TraceReferenceArg::Spike => Self::Spike,
+            TraceReferenceArg::Qemu => Self::QemuExeclog,
+        }
+    }
+}
+
+/// Differential execution mode.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum DiffModeArg {
+    /// Spike (reference) vs C backend
+    SpikeC,
+    /// Spike (reference) vs ARM64 backend
+    SpikeArm64,
+    /// C backend vs ARM64 backend
+    CArm64,
+}
+
+/// Differential execution backend.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum DiffBackendArg {
+    /// Spike (reference only)
+    Spike,
+    /// C backend
+    C,
+    /// ARM64 backend
+    Arm64,
+    /// x86 backend
+    X86,
+}
+
+/// Differential execution granularity.
+#[derive(Clone, Copy, Debug, ValueEnum, Default)]
+pub enum DiffGranularityArg {
+    /// Compare after every instruction
+    #[default]
+    Instruction,
+    /// Compare at block boundaries
+    Block,
+    /// Compare by block, drill down on divergence
+    Hybrid,
+    /// Fast checkpoint comparison (compare PC+registers every 1M instructions,
+    /// or every --sample-interval)
+    Checkpoint,
+    /// Pure C comparison (generates standalone C program, no Rust FFI)
+    PureC,
+}
+
+/// How differential execution steps the test backend.
+#[derive(Clone, Copy, Debug, ValueEnum, Default)]
+pub enum DiffExecutorArg {
+    /// Load the library in process and step it with the diff tracer
+    #[default]
+    InProcess,
+    /// Single-step the host's assembly backend under ptrace, without a
+    /// tracer (instruction granularity only)
+    Ptrace,
+}
+
+/// Analysis mode for the compilation pipeline.
+#[derive(Clone, Copy, Debug, ValueEnum, Default)]
+pub enum AnalysisModeArg {
+    /// Auto: CFG for C backend, linear scan for asm backends (default)
+    #[default]
+    Auto,
+    /// Full CFG analysis with block merging and optimizations
+    Cfg,
+    /// Linear scan: decode instructions without block merging (faster)
+    Linear,
+}
@@ -2,71 +2,88 @@
 
 use std::path::Path;
 
-use rvr::{CompileOptions, Compiler};
+use rvr::{CompileOptions, Compiler, Compression, FilterSpec, JumpTargets};
 use rvr_emit::Backend;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 use crate::cli::{
-    AddressModeArg, AnalysisModeArg, BackendArg, EXIT_FAILURE, EXIT_SUCCESS, InstretModeArg,
-    SyscallModeArg, TracerArgs, build_tracer_config, parse_fixed_addresses,
+    AnalysisModeArg, CompileArgs, EXIT_FAILURE, EXIT_QUARANTINED, EXIT_SUCCESS, LiftArgs,
+    MemoryLayoutArgs, build_tracer_config, parse_fixed_addresses,
 };
+use crate::terminal::Spinner;
 
 /// Handle the `compile` command.
-#[allow(clippy::too_many_arguments)]
-pub fn cmd_compile(
-    input: &Path,
-    output: &Path,
-    backend: BackendArg,
-    analysis: AnalysisModeArg,
-    address_mode: AddressModeArg,
-    htif: bool,
-    instret: InstretModeArg,
-    syscalls: SyscallModeArg,
-    perf: bool,
-    no_superblock: bool,
-    jobs: usize,
-    cc: Option<&str>,
-    linker: Option<&str>,
-    fixed_addresses: Option<&str>,
-    tracer: &TracerArgs,
-) -> i32 {
-    info!(input = %input.display(), output = %output.display(), "compiling");
-
-    let tracer_config = match build_tracer_config(tracer) {
-        Ok(config) => config,
-        Err(err) => {
-            error!(error = %err, "invalid tracer configuration");
-            return EXIT_FAILURE;
-        }
+pub fn cmd_compile(args: &CompileArgs, progress: bool) -> i32 {
+    info!(input = %args.input.display(), output = %args.output.display(), "compiling");
+
+    let Some(mut options) = compile_options(args) else {
+        return EXIT_FAILURE;
     };
 
-    let backend: Backend = backend.into();
+    let spinner = progress.then(|| Spinner::new("Compiling"));
+    if let Some(spinner) = &spinner {
+        let set_message = spinner.message_setter();
+        options = options.with_progress(Box::new(move |progress| {
+            set_message(format!("Compiling: {progress}"));
+        }));
+    }
 
-    let mut options = CompileOptions::new()
-        .with_backend(backend)
-        .with_address_mode(address_mode.into())
-        .with_htif(htif)
-        .with_instret_mode(instret.into())
-        .with_syscall_mode(syscalls.into())
-        .with_tracer_config(tracer_config)
-        .with_superblock(!no_superblock)
-        .with_jobs(jobs);
-    match analysis {
-        AnalysisModeArg::Auto => {
-            options = options.with_analysis_mode_auto(true);
+    let result = rvr::compile_with_report(&args.input, &args.output, &options);
+    drop(spinner);
+    if let Ok(report) = &result
+        && let Some(layout) = &report.memory_layout
+    {
+        info!(%layout, "memory layout");
+    }
+    match result {
+        Ok(report) if !report.quarantined.is_empty() => {
+            for failure in &report.quarantined {
+                warn!(
+                    pc = format!("{:#x}", failure.block_pc),
+                    "quarantined: {failure}"
+                );
+            }
+            warn!(
+                output = %report.library.display(),
+                blocks = report.quarantined.len(),
+                "done with quarantined blocks"
+            );
+            EXIT_QUARANTINED
         }
-        AnalysisModeArg::Cfg => {
-            options = options.with_analysis_mode(rvr_emit::AnalysisMode::FullCfg);
+        Ok(report) => {
+            info!(output = %report.library.display(), compiler = %report.compiler, "done");
+            EXIT_SUCCESS
         }
-        AnalysisModeArg::Linear => {
-            options = options.with_analysis_mode(rvr_emit::AnalysisMode::Basic);
+        Err(e) => {
+            error!(error = %e, "compilation failed");
+            EXIT_FAILURE
         }
     }
-    if perf {
-        options = options.with_perf_mode(true);
+}
+
+/// Compile options for `args`, or none (with the error logged) if an
+/// argument is invalid.
+fn compile_options(args: &CompileArgs) -> Option<CompileOptions> {
+    let tracer_config = match build_tracer_config(&args.tracer) {
+        Ok(config) => config,
+        Err(err) => {
+            error!(error = %err, "invalid tracer configuration");
+            return None;
+        }
+    };
+    let mut options = flag_options(args).with_tracer_config(tracer_config);
+
+    if let Some(path) = &args.jump_targets {
+        match load_jump_targets(&args.input, path) {
+            Ok(targets) => options = options.with_extra_entry_points(targets),
+            Err(e) => {
+                error!(error = %e, path = %path.display(), "failed to read jump targets");
+                return None;
+            }
+        }
     }
 
-    if let Some(addrs) = fixed_addresses {
+    if let Some(addrs) = &args.fixed_addresses {
         match parse_fixed_addresses(addrs) {
             Ok(config) => {
                 info!(
@@ -78,55 +95,117 @@ pub fn cmd_compile(
             }
             Err(e) => {
                 error!(error = %e, "invalid fixed addresses");
-                return EXIT_FAILURE;
+                return None;
             }
         }
     }
 
-    let options = if let Some(cc) = cc {
-        let mut compiler: Compiler = cc.parse().unwrap_or_else(|e| {
-            error!(error = %e, "invalid compiler");
-            std::process::exit(EXIT_FAILURE);
-        });
-        if let Some(ld) = linker {
+    if let Some(cc) = &args.cc {
+        let mut compiler: Compiler = match cc.parse() {
+            Ok(compiler) => compiler,
+            Err(e) => {
+                error!(error = %e, "invalid compiler");
+                return None;
+            }
+        };
+        if let Some(ld) = &args.linker {
             compiler = compiler.with_linker(ld);
         }
-        options.with_compiler(compiler)
-    } else {
-        options
-    };
+        options = options.with_compiler(compiler);
+    }
+    Some(options)
+}
 
-    match rvr::compile_with_options(input, output, &options) {
-        Ok(path) => {
-            info!(output = %path.display(), "done");
-            EXIT_SUCCESS
+/// Compile options for the arguments of `args` that cannot be invalid.
+fn flag_options(args: &CompileArgs) -> CompileOptions {
+    let mut options = CompileOptions::new()
+        .with_backend(args.backend.into())
+        .with_address_mode(args.address_mode.into())
+        .with_dispatch_mode(args.dispatch.into())
+        .with_hot_regs_mode(args.hot_regs.into())
+        .with_dispatch_table_relative(args.relative_dispatch_table)
+        .with_prefetch_dispatch(args.prefetch_dispatch)
+        .with_dispatch_table_hugepages(args.dispatch_table_hugepages)
+        .with_deterministic_clock(args.deterministic_clock)
+        .with_c_dialect(args.c_dialect.into())
+        .with_htif(args.htif)
+        .with_htif_verbose(args.htif_verbose)
+        .with_htif_poll_limit(args.htif_poll_limit)
+        .with_instret_mode(args.instret.into())
+        .with_suspend_granularity(args.suspend_granularity.into())
+        .with_syscall_mode(args.syscalls.into())
+        .with_superblock(!args.no_superblock)
+        .with_superblock_max_instrs(args.superblock.superblock_max_instrs)
+        .with_superblock_max_blocks(args.superblock.superblock_max_blocks)
+        .with_target_part_cost(args.parts.part_cost)
+        .with_fallback_opt_level(args.parts.fallback_opt_level())
+        .with_compiler_launcher(args.parts.compiler_launcher.clone())
+        .with_dedup_blocks(args.dedup_blocks)
+        .with_optimize_ir(!args.no_optimize_ir)
+        .with_detect_code_writes(!args.no_code_write_check)
+        .with_check_cancel(args.check_cancel)
+        .with_outline_cold_paths(args.outline_cold_paths)
+        .with_report_jump_sites(args.report_jump_sites)
+        .with_static_archive(args.static_archive)
+        .with_symbol_prefix(args.symbol_prefix.as_deref().unwrap_or_default())
+        .with_on_lift_error(args.on_lift_error.into())
+        .with_strict_decode(args.strict_decode)
+        .with_arm64_lse(args.arm64_lse)
+        .with_lrsc_model(args.lrsc.into())
+        .with_lazy_segment_init(args.lazy_segments)
+        .with_compress_segments(args.compress_segments.map(|min_size| Compression::Lz4 {
+            min_size: usize::try_from(min_size).unwrap_or(usize::MAX),
+        }))
+        .with_native_mem_intrinsics(args.native_mem_intrinsics)
+        .with_guest_pc_map(args.guest_pc_map)
+        .with_size_report(args.report)
+        .with_cache(!args.no_cache)
+        .with_jobs(args.jobs)
+        .with_analysis_jobs(args.analysis_jobs)
+        .with_vlen(args.vlen)
+        .with_interpret_blocks(args.interpret_block.iter().copied())
+        .with_interpret_failed_blocks(args.interpret_failed_blocks);
+    match args.analysis {
+        AnalysisModeArg::Auto => {
+            options = options.with_analysis_mode_auto(true);
         }
-        Err(e) => {
-            error!(error = %e, "compilation failed");
-            EXIT_FAILURE
+        AnalysisModeArg::Cfg => {
+            options = options.with_analysis_mode(rvr_emit::AnalysisMode::FullCfg);
+        }
+        AnalysisModeArg::Linear => {
+            options = options.with_analysis_mode(rvr_emit::AnalysisMode::Basic);
         }
     }
+    if args.perf {
+        options = options.with_perf_mode(true);
+    }
+    if let Some(ecalls) = args.ecalls.config() {
+        options = options.with_baremetal_ecalls(ecalls);
+    }
+    if let Some(bias) = args.load_bias {
+        options = options.with_load_bias(bias);
+    }
+    if let Some(isa) = &args.isa {
+        options = options.with_isa(isa);
+    }
+    options = with_memory_layout(options, args.memory_layout);
+    if let Some(layout) = args.layout {
+        options = options.with_layout(layout.into());
+    }
+    if let Some(path) = &args.profile {
+        options = options.with_profile(path);
+    }
+    if let Some(path) = &args.embed_golden {
+        options = options.with_embedded_golden(path);
+    }
+    options
 }
 
 /// Handle the `lift` command.
-#[allow(clippy::too_many_arguments)]
-pub fn cmd_lift(
-    input: &Path,
-    output: &Path,
-    backend: BackendArg,
-    analysis: AnalysisModeArg,
-    address_mode: AddressModeArg,
-    htif: bool,
-    line_info: bool,
-    instret: InstretModeArg,
-    syscalls: SyscallModeArg,
-    perf: bool,
-    fixed_addresses: Option<&str>,
-    tracer: &TracerArgs,
-) -> i32 {
-    info!(input = %input.display(), output = %output.display(), "lifting");
-
-    let tracer_config = match build_tracer_config(tracer) {
+pub fn cmd_lift(args: &LiftArgs) -> i32 {
+    info!(input = %args.input.display(), output = %args.output.display(), "lifting");
+
+    let tracer_config = match build_tracer_config(&args.tracer) {
         Ok(config) => config,
         Err(err) => {
             error!(error = %err, "invalid tracer configuration");
@@ -134,17 +213,30 @@ pub fn cmd_lift(
         }
     };
 
-    let backend: Backend = backend.into();
+    let backend: Backend = args.backend.into();
 
     let mut options = CompileOptions::new()
         .with_backend(backend)
-        .with_address_mode(address_mode.into())
-        .with_htif(htif)
-        .with_line_info(line_info)
-        .with_instret_mode(instret.into())
-        .with_syscall_mode(syscalls.into())
-        .with_tracer_config(tracer_config);
-    match analysis {
+        .with_address_mode(args.address_mode.into())
+        .with_dispatch_mode(args.dispatch.into())
+        .with_hot_regs_mode(args.hot_regs.into())
+        .with_c_dialect(args.c_dialect.into())
+        .with_htif(args.htif)
+        .with_htif_verbose(args.htif_verbose)
+        .with_line_info(args.line_info)
+        .with_instret_mode(args.instret.into())
+        .with_suspend_granularity(args.suspend_granularity.into())
+        .with_syscall_mode(args.syscalls.into())
+        .with_tracer_config(tracer_config)
+        .with_superblock_max_instrs(args.superblock.superblock_max_instrs)
+        .with_superblock_max_blocks(args.superblock.superblock_max_blocks)
+        .with_target_part_cost(args.parts.part_cost)
+        .with_fallback_opt_level(args.parts.fallback_opt_level())
+        .with_compiler_launcher(args.parts.compiler_launcher.clone())
+        .with_dedup_blocks(args.dedup_blocks)
+        .with_optimize_ir(!args.no_optimize_ir)
+        .with_vlen(args.vlen);
+    match args.analysis {
         AnalysisModeArg::Auto => {
             options = options.with_analysis_mode_auto(true);
         }
@@ -155,11 +247,27 @@ pub fn cmd_lift(
             options = options.with_analysis_mode(rvr_emit::AnalysisMode::Basic);
         }
     }
-    if perf {
+    if args.perf {
         options = options.with_perf_mode(true);
     }
+    if let Some(ecalls) = args.ecalls.config() {
+        options = options.with_baremetal_ecalls(ecalls);
+    }
+    if let Some(bias) = args.load_bias {
+        options = options.with_load_bias(bias);
+    }
+    if let Some(isa) = &args.isa {
+        options = options.with_isa(isa);
+    }
+    options = with_memory_layout(options, args.memory_layout);
+    if !args.only.is_empty() || !args.only_range.is_empty() {
+        options = options.with_filter(FilterSpec {
+            symbols: args.only.clone(),
+            ranges: args.only_range.clone(),
+        });
+    }
 
-    if let Some(addrs) = fixed_addresses {
+    if let Some(addrs) = &args.fixed_addresses {
         match parse_fixed_addresses(addrs) {
             Ok(config) => {
                 info!(
@@ -176,7 +284,7 @@ pub fn cmd_lift(
         }
     }
 
-    match rvr::lift_to_c_with_options(input, output, &options) {
+    match rvr::lift_to_c_with_options(&args.input, &args.output, &options) {
         Ok(path) => {
             info!(output = %path.display(), "done");
             EXIT_SUCCESS
@@ -187,3 +295,34 @@ pub fn cmd_lift(
         }
     }
 }
+
+/// Targets of the jumps collected in `path`, or none with a warning if they
+/// were collected from another ELF than `input`.
+fn load_jump_targets(input: &Path, path: &Path) -> rvr::Result<Vec<u64>> {
+    let targets = JumpTargets::load(path)?;
+    if !targets.matches(&std::fs::read(input)?) {
+        warn!(
+            path = %path.display(),
+            "jump targets were collected from a different ELF, ignoring them"
+        );
+        return Ok(Vec::new());
+    }
+    Ok(targets.targets())
+}
+
+/// Apply `--heap-size`/`--stack-size`/`--mmap-size`/`--stack-guard`.
+const fn with_memory_layout(mut options: CompileOptions, args: MemoryLayoutArgs) -> CompileOptions {
+    if let Some(size) = args.heap {
+        options = options.with_heap(size);
+    }
+    if let Some(size) = args.stack {
+        options = options.with_stack_size(size);
+    }
+    if let Some(size) = args.mmap {
+        options = options.with_mmap_size(size);
+    }
+    if let Some(size) = args.stack_guard {
+        options = options.with_stack_guard(size);
+    }
+    options
+}
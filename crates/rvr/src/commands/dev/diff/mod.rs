@@ -8,6 +8,7 @@ use rvr_emit::Backend;
 
 use crate::cli::{EXIT_FAILURE, EXIT_SUCCESS};
 use pure_c::run_pure_c_comparison;
+use rvr::test_support::diff::executor::Executor;
 use rvr::test_support::{diff, trace};
 
 #[derive(Clone, Copy, Debug)]
@@ -78,17 +79,56 @@ fn resolve_diff_backends(
     Ok((ref_backend, test_backend))
 }
 
+/// The ptrace executor single-steps the host's own assembly backend, one
+/// instruction at a time.
+fn check_executor(
+    executor: DiffExecutorArg,
+    test_backend: DiffBackend,
+    modes: CompareModes,
+) -> Result<(), String> {
+    if matches!(executor, DiffExecutorArg::InProcess) {
+        return Ok(());
+    }
+    if !test_backend
+        .as_backend()
+        .is_some_and(diff::backend_supports_ptrace)
+    {
+        return Err(format!(
+            "Error: the ptrace executor needs the host's assembly backend, not {test_backend:?}"
+        ));
+    }
+    if modes.use_block_comparison || modes.use_checkpoint_comparison || modes.use_pure_c {
+        return Err("Error: the ptrace executor needs --granularity instruction".to_string());
+    }
+    Ok(())
+}
+
 fn should_skip_test(name: &str) -> bool {
     matches!(name, "rv32ui-p-fence_i" | "rv64ui-p-fence_i")
 }
 
 const CHECKPOINT_INTERVAL: u64 = 1_000_000;
 
+/// Instructions between checkpoints: `sample_interval` if given, which only
+/// checkpoint comparison can honor; every other mode is lockstep.
+fn checkpoint_interval(sample_interval: Option<u64>, modes: CompareModes) -> Result<u64, String> {
+    match sample_interval {
+        None => Ok(CHECKPOINT_INTERVAL),
+        Some(0) => Err("Error: --sample-interval must be at least 1".to_string()),
+        Some(_) if !modes.use_checkpoint_comparison => Err(
+            "Error: --sample-interval needs --granularity checkpoint with two compiled backends; \
+             lockstep comparison cannot skip instructions"
+                .to_string(),
+        ),
+        Some(interval) => Ok(interval),
+    }
+}
+
 // ============================================================================
 // Differential Execution Command
 // ============================================================================
 
-use crate::cli::{DiffBackendArg, DiffGranularityArg, DiffModeArg};
+use crate::cli::{DiffBackendArg, DiffExecutorArg, DiffGranularityArg, DiffModeArg};
 
 pub struct DiffCompareArgs<'a> {
     pub mode: DiffModeArg,
@@ -96,6 +136,7 @@ pub struct DiffCompareArgs<'a> {
     pub test_backend: Option<DiffBackendArg>,
     pub elf_path: &'a PathBuf,
     pub granularity_arg: DiffGranularityArg,
+    pub executor: DiffExecutorArg,
     pub max_instrs: Option<u64>,
     pub output_dir: Option<PathBuf>,
     pub ref_dir: Option<PathBuf>,
@@ -103,6 +144,11 @@ pub struct DiffCompareArgs<'a> {
     pub cc: &'a str,
     pub isa: Option<String>,
     pub strict_mem: bool,
+    pub strict_csrs: bool,
+    pub refresh_ref: bool,
+    pub ref_cache: Option<PathBuf>,
+    pub ref_cache_size: u64,
+    pub sample_interval: Option<u64>,
 }
 
 const fn granularity_from_arg(arg: DiffGranularityArg) -> diff::DiffGranularity {
@@ -231,12 +277,17 @@ struct DiffContext<'a> {
     compiler: &'a rvr::Compiler,
     ref_backend: DiffBackend,
     test_backend: DiffBackend,
+    executor: DiffExecutorArg,
     ref_dir: Option<PathBuf>,
     test_dir: Option<PathBuf>,
     max_instrs: Option<u64>,
     strict_mem: bool,
+    strict_csrs: bool,
     isa: &'a str,
     entry_point: u64,
+    ref_cache: diff::TraceCache,
+    refresh_ref: bool,
+    checkpoint_interval: u64,
 }
 
 fn run_pure_c(ctx: &DiffContext<'_>, cc: &str) -> diff::CompareResult {
@@ -280,6 +331,7 @@ fn run_block_comparison(ctx: &DiffContext<'_>) -> Result<diff::CompareResult, St
     let config = diff::CompareConfig {
         strict_reg_writes: true,
         strict_mem_access: ctx.strict_mem,
+        strict_csrs: ctx.strict_csrs,
     };
 
     let mut block_exec = diff::BufferedInProcessExecutor::new(&block_dir, ctx.elf_path)
@@ -296,7 +348,10 @@ fn run_block_comparison(ctx: &DiffContext<'_>) -> Result<diff::CompareResult, St
 }
 
 fn run_checkpoint_comparison(ctx: &DiffContext<'_>) -> Result<diff::CompareResult, String> {
-    eprintln!("Using checkpoint comparison (1M instruction intervals)");
+    eprintln!(
+        "Using checkpoint comparison ({} instruction intervals)",
+        ctx.checkpoint_interval
+    );
 
     let ref_dir = if let Some(dir) = ctx.ref_dir.clone() {
         dir
@@ -335,7 +390,7 @@ fn run_checkpoint_comparison(ctx: &DiffContext<'_>) -> Result<diff::CompareResul
     Ok(diff::compare_checkpoint(
         &mut ref_runner,
         &mut test_runner,
-        CHECKPOINT_INTERVAL,
+        ctx.checkpoint_interval,
         ctx.max_instrs,
     ))
 }
@@ -358,7 +413,11 @@ fn run_instruction_comparison(ctx: &DiffContext<'_>) -> Result<diff::CompareResu
     } else if let Some(backend) = ctx.test_backend.as_backend() {
         let dir = ctx.output_dir.join("test");
         eprintln!("Compiling test ({backend:?})...");
-        diff::compile_for_diff(ctx.elf_path, &dir, backend, ctx.compiler)
+        let compile = match ctx.executor {
+            DiffExecutorArg::InProcess => diff::compile_for_diff,
+            DiffExecutorArg::Ptrace => diff::compile_for_ptrace,
+        };
+        compile(ctx.elf_path, &dir, backend, ctx.compiler)
             .map_err(|err| format!("Error: {err}"))?;
         dir
     } else {
@@ -369,29 +428,37 @@ fn run_instruction_comparison(ctx: &DiffContext<'_>) -> Result<diff::CompareResu
     let config = diff::CompareConfig {
         strict_reg_writes: true,
         strict_mem_access: ctx.strict_mem,
+        strict_csrs: ctx.strict_csrs,
     };
 
     match ctx.ref_backend {
         DiffBackend::Spike => {
-            let mut spike = diff::SpikeExecutor::start(ctx.elf_path, ctx.isa, ctx.entry_point)
-                .map_err(|e| format!("Error starting Spike: {e}"))?;
-            let mut test = diff::InProcessExecutor::new(&test_compiled_dir, ctx.elf_path)
-                .map_err(|e| format!("Error loading test executor: {e}"))?;
-            Ok(diff::compare_lockstep(
-                &mut spike,
-                &mut test,
-                &config,
-                ctx.max_instrs,
-            ))
+            let mut spike = diff::CachedTraceExecutor::open(
+                ctx.ref_cache.clone(),
+                ctx.elf_path,
+                ctx.isa,
+                ctx.entry_point,
+                ctx.refresh_ref,
+            )
+            .map_err(|e| format!("Error starting Spike: {e}"))?;
+            log_trace_source(&spike);
+            let mut test = test_executor(ctx, &test_compiled_dir)?;
+            let result = diff::compare_lockstep(&mut spike, &mut *test, &config, ctx.max_instrs);
+            let path = spike.path().to_path_buf();
+            match spike.finish() {
+                Ok(true) => eprintln!("Cached Spike trace: {}", path.display()),
+                Ok(false) => {}
+                Err(e) => eprintln!("Warning: failed to cache Spike trace: {e}"),
+            }
+            Ok(result)
         }
         DiffBackend::Backend(_) => {
             let mut reference = diff::InProcessExecutor::new(&ref_compiled_dir, ctx.elf_path)
                 .map_err(|e| format!("Error loading reference executor: {e}"))?;
-            let mut test = diff::InProcessExecutor::new(&test_compiled_dir, ctx.elf_path)
-                .map_err(|e| format!("Error loading test executor: {e}"))?;
+            let mut test = test_executor(ctx, &test_compiled_dir)?;
             Ok(diff::compare_lockstep(
                 &mut reference,
-                &mut test,
+                &mut *test,
                 &config,
                 ctx.max_instrs,
             ))
@@ -399,6 +466,51 @@ fn run_instruction_comparison(ctx: &DiffContext<'_>) -> Result<diff::CompareResu
     }
 }
 
+/// Executor stepping the test library in `dir`.
+fn test_executor(ctx: &DiffContext<'_>, dir: &Path) -> Result<Box<dyn Executor>, String> {
+    match ctx.executor {
+        DiffExecutorArg::InProcess => diff::InProcessExecutor::new(dir, ctx.elf_path)
+            .map(|executor| Box::new(executor) as Box<dyn Executor>)
+            .map_err(|e| format!("Error loading test executor: {e}")),
+        DiffExecutorArg::Ptrace => ptrace_executor(dir, ctx.elf_path),
+    }
+}
+
+#[cfg(all(
+    target_os = "linux",
+    target_env = "gnu",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+fn ptrace_executor(dir: &Path, elf_path: &Path) -> Result<Box<dyn Executor>, String> {
+    diff::PtraceExecutor::new(dir, elf_path)
+        .map(|executor| Box::new(executor) as Box<dyn Executor>)
+        .map_err(|e| format!("Error starting ptrace executor: {e}"))
+}
+
+#[cfg(not(all(
+    target_os = "linux",
+    target_env = "gnu",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+)))]
+fn ptrace_executor(_dir: &Path, _elf_path: &Path) -> Result<Box<dyn Executor>, String> {
+    Err("Error: the ptrace executor needs Linux on x86_64 or aarch64".to_string())
+}
+
+fn log_trace_source(spike: &diff::CachedTraceExecutor) {
+    match spike.source() {
+        diff::TraceSource::Cached { records, complete } => eprintln!(
+            "Replaying cached Spike trace ({records} instructions{}): {}",
+            if complete {
+                ""
+            } else {
+                ", Spike resumes after"
+            },
+            spike.path().display()
+        ),
+        diff::TraceSource::Spike => eprintln!("Running Spike (trace will be cached)"),
+    }
+}
+
 fn report_result(result: &diff::CompareResult, output_dir: &Path) -> i32 {
     eprintln!();
     result.divergence.as_ref().map_or_else(
@@ -418,6 +530,9 @@ fn report_result(result: &diff::CompareResult, output_dir: &Path) -> i32 {
             if let Some(addr) = div.expected.mem_addr {
                 eprintln!("  mem 0x{addr:016x}");
             }
+            if let (Some(csr), Some(val)) = (div.expected.csr, div.expected.csr_value) {
+                eprintln!("  csr 0x{csr:03x} = 0x{val:016x}");
+            }
             eprintln!();
             eprintln!("Actual:");
             eprintln!("  PC: 0x{:016x}", div.actual.pc);
@@ -428,6 +543,9 @@ fn report_result(result: &diff::CompareResult, output_dir: &Path) -> i32 {
             if let Some(addr) = div.actual.mem_addr {
                 eprintln!("  mem 0x{addr:016x}");
             }
+            if let (Some(csr), Some(val)) = (div.actual.csr, div.actual.csr_value) {
+                eprintln!("  csr 0x{csr:03x} = 0x{val:016x}");
+            }
             eprintln!();
             eprintln!("Output: {}", output_dir.display());
             EXIT_FAILURE
@@ -440,6 +558,7 @@ fn run_comparison(
     cc: &str,
     modes: CompareModes,
 ) -> Result<diff::CompareResult, String> {
+    check_executor(ctx.executor, ctx.test_backend, modes)?;
     if modes.use_pure_c {
         Ok(run_pure_c(ctx, cc))
     } else if modes.use_block_comparison {
@@ -459,6 +578,7 @@ pub fn diff_compare(args: DiffCompareArgs<'_>) -> i32 {
         test_backend,
         elf_path,
         granularity_arg,
+        executor,
         max_instrs,
         output_dir,
         ref_dir,
@@ -466,6 +586,11 @@ pub fn diff_compare(args: DiffCompareArgs<'_>) -> i32 {
         cc,
         isa,
         strict_mem,
+        strict_csrs,
+        refresh_ref,
+        ref_cache,
+        ref_cache_size,
+        sample_interval,
     } = args;
     let granularity = granularity_from_arg(granularity_arg);
 
@@ -494,6 +619,9 @@ pub fn diff_compare(args: DiffCompareArgs<'_>) -> i32 {
     log_header(elf_path, mode, granularity, &isa, entry_point, max_instrs);
 
     let output_dir = create_output_dir(output_dir);
+    let ref_cache_dir = ref_cache
+        .or_else(diff::TraceCache::default_dir)
+        .unwrap_or_else(|| output_dir.join("ref-traces"));
 
     let (ref_backend, test_backend) = match resolve_diff_backends(mode, ref_backend, test_backend) {
         Ok(pair) => pair,
@@ -512,6 +640,13 @@ pub fn diff_compare(args: DiffCompareArgs<'_>) -> i32 {
     }
 
     let modes = determine_compare_modes(granularity, ref_backend, test_backend);
+    let checkpoint_interval = match checkpoint_interval(sample_interval, modes) {
+        Ok(interval) => interval,
+        Err(message) => {
+            eprintln!("{message}");
+            return EXIT_FAILURE;
+        }
+    };
     let compiler = match resolve_compiler(cc) {
         Ok(compiler) => compiler,
         Err(message) => {
@@ -526,12 +661,17 @@ pub fn diff_compare(args: DiffCompareArgs<'_>) -> i32 {
         compiler: &compiler,
         ref_backend,
         test_backend,
+        executor,
         ref_dir,
         test_dir,
         max_instrs,
         strict_mem,
+        strict_csrs,
         isa: &isa,
         entry_point,
+        ref_cache: diff::TraceCache::new(ref_cache_dir, ref_cache_size),
+        refresh_ref,
+        checkpoint_interval,
     };
 
     let result = match run_comparison(&ctx, cc, modes) {
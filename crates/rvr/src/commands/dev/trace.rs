@@ -1,29 +1,44 @@
 use std::path::{Path, PathBuf};
 
 use crate::cli::{EXIT_FAILURE, EXIT_SUCCESS};
-use rvr::test_support::trace;
+use rvr::test_support::trace::{self, TraceFormat};
+
+pub struct TraceCompareArgs<'a> {
+    pub elf_path: &'a Path,
+    pub output_dir: Option<PathBuf>,
+    pub cc: &'a str,
+    pub isa: Option<String>,
+    pub timeout: u64,
+    pub stop_on_first: bool,
+    pub reference: TraceFormat,
+    pub qemu_plugin: &'a str,
+}
+
+/// Compare instruction traces between rvr and a reference simulator.
+pub fn trace_compare(args: TraceCompareArgs<'_>) -> i32 {
+    let TraceCompareArgs {
+        elf_path,
+        output_dir,
+        cc,
+        isa,
+        timeout,
+        stop_on_first,
+        reference,
+        qemu_plugin,
+    } = args;
 
-/// Compare instruction traces between rvr and Spike.
-pub fn trace_compare(
-    elf_path: &Path,
-    output_dir: Option<PathBuf>,
-    cc: &str,
-    isa: Option<String>,
-    timeout: u64,
-    stop_on_first: bool,
-) -> i32 {
     let test_name = elf_path.file_name().and_then(|n| n.to_str()).unwrap_or("");
     if should_skip_trace(test_name) {
         return EXIT_SUCCESS;
     }
 
-    let spike_path = match resolve_spike_path() {
-        Ok(path) => path,
+    let isa = match resolve_isa(elf_path, isa) {
+        Ok(i) => i,
         Err(code) => return code,
     };
 
-    let isa = match resolve_isa(elf_path, isa) {
-        Ok(i) => i,
+    let reference_path = match resolve_reference_path(reference, &isa) {
+        Ok(path) => path,
         Err(code) => return code,
     };
 
@@ -36,17 +51,36 @@ pub fn trace_compare(
     };
 
     let output_dir = prepare_output_dir(output_dir);
-    log_trace_setup(elf_path, &isa, entry_point, &spike_path, &output_dir);
+    log_trace_setup(
+        elf_path,
+        &isa,
+        entry_point,
+        reference,
+        &reference_path,
+        &output_dir,
+    );
 
-    if let Err(code) = compile_rvr_trace(elf_path, &output_dir, cc) {
+    if let Err(code) = compile_rvr_trace(elf_path, &output_dir, cc, reference) {
         return code;
     }
 
-    let spike_trace_path =
-        match run_spike(&spike_path, elf_path, &isa, &output_dir, timeout, test_name) {
-            Ok(path) => path,
-            Err(code) => return code,
-        };
+    let reference_trace_path = match reference {
+        TraceFormat::Spike => run_spike(
+            &reference_path,
+            elf_path,
+            &isa,
+            &output_dir,
+            timeout,
+            test_name,
+        ),
+        TraceFormat::QemuExeclog => {
+            run_qemu(&reference_path, elf_path, qemu_plugin, &output_dir, timeout)
+        }
+    };
+    let reference_trace_path = match reference_trace_path {
+        Ok(path) => path,
+        Err(code) => return code,
+    };
 
     let rvr_trace_path = match run_rvr(elf_path, &output_dir, timeout) {
         Ok(path) => path,
@@ -54,8 +88,9 @@ pub fn trace_compare(
     };
 
     compare_and_report(
-        &spike_trace_path,
+        &reference_trace_path,
         &rvr_trace_path,
+        reference,
         entry_point,
         stop_on_first,
         &output_dir,
@@ -63,24 +98,26 @@ pub fn trace_compare(
 }
 
 fn should_skip_trace(test_name: &str) -> bool {
-    if should_skip(test_name) {
+    let skip = should_skip(test_name);
+    if skip {
         eprintln!("SKIP: {test_name} (not compatible with static recompilation)");
-        return true;
-    }
-    if test_name.contains("lrsc") {
-        eprintln!("SKIP: {test_name} (LR/SC trace is nondeterministic)");
-        return true;
     }
-    false
+    skip
 }
 
-fn resolve_spike_path() -> Result<PathBuf, i32> {
-    let Some(spike_path) = trace::find_spike() else {
-        eprintln!("Error: Spike not found in PATH");
-        eprintln!("Install from https://github.com/riscv-software-src/riscv-isa-sim");
-        return Err(EXIT_FAILURE);
-    };
-    Ok(spike_path)
+fn resolve_reference_path(reference: TraceFormat, isa: &str) -> Result<PathBuf, i32> {
+    match reference {
+        TraceFormat::Spike => trace::find_spike().ok_or_else(|| {
+            eprintln!("Error: Spike not found in PATH");
+            eprintln!("Install from https://github.com/riscv-software-src/riscv-isa-sim");
+            EXIT_FAILURE
+        }),
+        TraceFormat::QemuExeclog => trace::find_qemu(isa).ok_or_else(|| {
+            eprintln!("Error: QEMU user-mode emulator not found in PATH");
+            eprintln!("Install qemu-user (qemu-riscv32/qemu-riscv64) with plugin support");
+            EXIT_FAILURE
+        }),
+    }
 }
 
 fn resolve_isa(elf_path: &Path, isa: Option<String>) -> Result<String, i32> {
@@ -109,22 +146,29 @@ fn log_trace_setup(
     elf_path: &Path,
     isa: &str,
     entry_point: u64,
-    spike_path: &Path,
+    reference: TraceFormat,
+    reference_path: &Path,
     output_dir: &Path,
 ) {
     eprintln!("ELF: {}", elf_path.display());
     eprintln!("ISA: {isa}");
     eprintln!("Entry: 0x{entry_point:x}");
-    eprintln!("Spike: {}", spike_path.display());
+    eprintln!("Reference ({reference}): {}", reference_path.display());
     eprintln!("Output: {}", output_dir.display());
     eprintln!();
 }
 
-fn compile_rvr_trace(elf_path: &Path, output_dir: &Path, cc: &str) -> Result<(), i32> {
+fn compile_rvr_trace(
+    elf_path: &Path,
+    output_dir: &Path,
+    cc: &str,
+    reference: TraceFormat,
+) -> Result<(), i32> {
     use std::process::Command;
 
     eprintln!("Step 1: Compiling with rvr (spike tracer)...");
-    let compile_status = Command::new("./target/release/rvr")
+    let mut compile_cmd = Command::new("./target/release/rvr");
+    compile_cmd
         .arg("compile")
         .arg(elf_path)
         .arg("-o")
@@ -132,8 +176,12 @@ fn compile_rvr_trace(elf_path: &Path, output_dir: &Path, cc: &str) -> Result<(),
         .arg("--tracer")
         .arg("spike")
         .arg("--cc")
-        .arg(cc)
-        .status();
+        .arg(cc);
+    // QEMU user mode runs Linux ELFs: match its syscall ABI
+    if reference == TraceFormat::QemuExeclog {
+        compile_cmd.arg("--syscalls").arg("linux");
+    }
+    let compile_status = compile_cmd.status();
 
     match compile_status {
         Ok(status) if status.success() => Ok(()),
@@ -194,6 +242,46 @@ fn run_spike(
     }
 }
 
+fn run_qemu(
+    qemu_path: &Path,
+    elf_path: &Path,
+    plugin: &str,
+    output_dir: &Path,
+    timeout: u64,
+) -> Result<PathBuf, i32> {
+    use std::process::Command;
+    use std::time::Duration;
+
+    eprintln!("Step 2: Running QEMU (execlog)...");
+    let qemu_trace_path = output_dir.join("qemu_trace.log");
+    let mut qemu_cmd = Command::new(qemu_path);
+    qemu_cmd
+        .arg("-plugin")
+        .arg(plugin)
+        .arg("-d")
+        .arg("plugin")
+        .arg("-D")
+        .arg(&qemu_trace_path)
+        .arg(elf_path);
+    let qemu_status = trace::run_command_with_timeout(&mut qemu_cmd, Duration::from_secs(timeout));
+
+    match qemu_status {
+        Ok(status) if status.success() => Ok(qemu_trace_path),
+        Ok(status) => {
+            eprintln!("Error: QEMU failed with exit code {:?}", status.code());
+            Err(EXIT_FAILURE)
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::TimedOut => {
+            eprintln!("Error: QEMU timed out after {timeout}s");
+            Err(EXIT_FAILURE)
+        }
+        Err(e) => {
+            eprintln!("Error: failed to run QEMU: {e}");
+            Err(EXIT_FAILURE)
+        }
+    }
+}
+
 fn run_rvr(elf_path: &Path, output_dir: &Path, timeout: u64) -> Result<PathBuf, i32> {
     use std::process::Command;
     use std::time::Duration;
@@ -226,18 +314,20 @@ fn run_rvr(elf_path: &Path, output_dir: &Path, timeout: u64) -> Result<PathBuf,
 }
 
 fn compare_and_report(
-    spike_trace_path: &Path,
+    reference_trace_path: &Path,
     rvr_trace_path: &Path,
+    reference: TraceFormat,
     entry_point: u64,
     stop_on_first: bool,
     output_dir: &Path,
 ) -> i32 {
     eprintln!("Step 4: Comparing traces...");
 
-    let spike_trace = match trace::parse_trace_file(spike_trace_path) {
+    let reference_trace = match trace::parse_trace_file_with_format(reference_trace_path, reference)
+    {
         Ok(t) => t,
         Err(e) => {
-            eprintln!("Error parsing Spike trace: {e}");
+            eprintln!("Error parsing {reference} trace: {e}");
             return EXIT_FAILURE;
         }
     };
@@ -250,30 +340,28 @@ fn compare_and_report(
         }
     };
 
-    eprintln!("Spike trace: {} entries", spike_trace.len());
+    eprintln!("{reference} trace: {} entries", reference_trace.len());
     eprintln!("rvr trace: {} entries", rvr_trace.len());
 
-    let (spike_aligned, rvr_aligned) =
-        trace::align_traces_at(&spike_trace, &rvr_trace, entry_point);
+    let (reference_aligned, rvr_aligned) =
+        trace::align_traces_at(&reference_trace, &rvr_trace, entry_point);
     eprintln!(
-        "After alignment: Spike={}, rvr={}",
-        spike_aligned.len(),
+        "After alignment: {reference}={}, rvr={}",
+        reference_aligned.len(),
         rvr_aligned.len()
     );
 
     let config = trace::CompareConfig {
-        entry_point,
-        strict_reg_writes: true,
-        strict_mem_access: false,
         stop_on_first,
+        ..trace::CompareConfig::for_format(reference, entry_point)
     };
-    let result = trace::compare_traces_with_config(&spike_aligned, &rvr_aligned, &config);
+    let result = trace::compare_traces_with_config(&reference_aligned, &rvr_aligned, &config);
 
     eprintln!();
     if let Some(div) = &result.divergence {
         eprintln!("DIVERGENCE at instruction {}: {}", div.index, div.kind);
         eprintln!();
-        eprintln!("Expected (Spike):");
+        eprintln!("Expected ({reference}):");
         eprintln!("  PC: 0x{:016x}", div.expected.pc);
         eprintln!("  Opcode: 0x{:08x}", div.expected.opcode);
         if let (Some(rd), Some(val)) = (div.expected.rd, div.expected.rd_value) {
@@ -282,6 +370,9 @@ fn compare_and_report(
         if let Some(addr) = div.expected.mem_addr {
             eprintln!("  mem 0x{addr:016x}");
         }
+        if let (Some(csr), Some(val)) = (div.expected.csr, div.expected.csr_value) {
+            eprintln!("  csr 0x{csr:03x} = 0x{val:016x}");
+        }
         eprintln!();
         eprintln!("Actual (rvr):");
         eprintln!("  PC: 0x{:016x}", div.actual.pc);
@@ -292,6 +383,9 @@ fn compare_and_report(
         if let Some(addr) = div.actual.mem_addr {
             eprintln!("  mem 0x{addr:016x}");
         }
+        if let (Some(csr), Some(val)) = (div.actual.csr, div.actual.csr_value) {
+            eprintln!("  csr 0x{csr:03x} = 0x{val:016x}");
+        }
         eprintln!();
         eprintln!("Output: {}", output_dir.display());
         EXIT_FAILURE
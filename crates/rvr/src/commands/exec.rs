@@ -0,0 +1,91 @@
+//! Exec command: compile into a temporary directory and run at once.
+
+use std::path::Path;
+
+use rvr::{CompileOptions, Compiler, Runner};
+use tempfile::TempDir;
+use tracing::{debug, error};
+
+use crate::cli::{EXIT_FAILURE, GuestEnvArgs, InstretModeArg, SyscallModeArg};
+use crate::commands::run::with_guest_env;
+
+/// Name of the output directory inside the temporary directory; fixed so
+/// repeated runs of the same ELF hit the artifact cache.
+const OUTPUT_NAME: &str = "exec";
+
+/// Handle the `exec` command.
+///
+/// Compiles through the artifact cache when one is configured, so running
+/// the same ELF again skips lifting and building. The temporary directory
+/// is removed once the guest ran, and kept (with its path logged) when
+/// compiling, loading or running fails.
+pub fn cmd_exec(
+    input: &Path,
+    syscalls: SyscallModeArg,
+    instret: InstretModeArg,
+    cc: Option<&str>,
+    quiet: bool,
+    env: &GuestEnvArgs,
+    args: &[String],
+) -> i32 {
+    let mut options = CompileOptions::new()
+        .with_syscall_mode(syscalls.into())
+        .with_instret_mode(instret.into())
+        .with_cache(true)
+        .with_quiet(quiet);
+    if let Some(cc) = cc {
+        match cc.parse::<Compiler>() {
+            Ok(compiler) => options = options.with_compiler(compiler),
+            Err(e) => {
+                error!(error = %e, "invalid compiler");
+                return EXIT_FAILURE;
+            }
+        }
+    }
+
+    let dir = match tempfile::Builder::new().prefix("rvr-exec-").tempdir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            error!(error = %e, "failed to create a temporary directory");
+            return EXIT_FAILURE;
+        }
+    };
+    let output = dir.path().join(OUTPUT_NAME);
+    debug!(input = %input.display(), output = %output.display(), "compiling");
+
+    if let Err(e) = rvr::compile_with_options(input, &output, &options) {
+        error!(error = %e, "compilation failed");
+        return keep(dir);
+    }
+    let runner = match Runner::load(&output, input) {
+        Ok(runner) => runner,
+        Err(e) => {
+            error!(error = %e, "failed to load library");
+            return keep(dir);
+        }
+    };
+    let argv0 = input.display().to_string();
+    let runner = runner.with_args(std::iter::once(argv0).chain(args.iter().cloned()));
+    let mut runner = match with_guest_env(runner, env) {
+        Ok(runner) => runner,
+        Err(e) => {
+            error!(error = %e, "failed to set the guest environment");
+            return EXIT_FAILURE;
+        }
+    };
+    match runner.run() {
+        Ok(result) => i32::from(result.exit_code),
+        Err(e) => {
+            error!(error = %e, "execution failed");
+            keep(dir)
+        }
+    }
+}
+
+/// Keep `dir` for debugging and log where it is; returns the failure exit
+/// code.
+fn keep(dir: TempDir) -> i32 {
+    let path = dir.keep();
+    error!(path = %path.display(), "kept the compiled output");
+    EXIT_FAILURE
+}
@@ -0,0 +1,181 @@
+//! Inspect command.
+
+use std::path::Path;
+
+use rvr::{CompileOptions, ElfSummary, InspectOptions, guest_pc_at};
+use tracing::error;
+
+use crate::cli::{
+    AddressModeArg, AnalysisModeArg, EXIT_FAILURE, EXIT_SUCCESS, InstretModeArg, OutputFormat,
+    SyscallModeArg, TracerArgs, build_tracer_config,
+};
+
+/// Handle the `inspect` command without `--explain`: summarize the ELF.
+pub fn cmd_summary(input: &Path, options: &InspectOptions, format: OutputFormat) -> i32 {
+    let summary = match std::fs::read(input)
+        .map_err(|e| e.to_string())
+        .and_then(|data| rvr::inspect_elf(&data, options).map_err(|e| e.to_string()))
+    {
+        Ok(summary) => summary,
+        Err(err) => {
+            error!(error = %err, path = %input.display(), "cannot inspect ELF");
+            return EXIT_FAILURE;
+        }
+    };
+
+    match format {
+        OutputFormat::Text => print_summary_text(&summary),
+        OutputFormat::Raw => print_summary_raw(&summary),
+        OutputFormat::Json => match serde_json::to_string(&summary) {
+            Ok(json) => println!("{json}"),
+            Err(err) => {
+                error!(error = %err, "cannot serialize summary");
+                return EXIT_FAILURE;
+            }
+        },
+    }
+    EXIT_SUCCESS
+}
+
+fn print_summary_text(summary: &ElfSummary) {
+    let rvc = if summary.rvc { " (RVC)" } else { "" };
+    println!("XLEN:         {}", summary.xlen);
+    println!("ABI:          {}{rvc}", summary.abi);
+    println!("ISA:          {}", summary.arch.as_deref().unwrap_or("-"));
+    println!("Entry point:  {:#x}", summary.entry_point);
+    if summary.load_bias != 0 {
+        println!("Load bias:    {:#x}", summary.load_bias);
+    }
+    println!(
+        "Symbols:      {} ({} functions)",
+        summary.symbols, summary.functions
+    );
+    println!("Code bytes:   {}", summary.code_bytes);
+
+    println!();
+    println!(
+        "{:<18}  {:<18}  {:>10}  {:>10}  PERM  CODE",
+        "START", "END", "FILE", "MEM"
+    );
+    for seg in &summary.segments {
+        println!(
+            "{:<18}  {:<18}  {:>10}  {:>10}  {:<4}  {}",
+            format!("{:#x}", seg.start),
+            format!("{:#x}", seg.end),
+            seg.file_size,
+            seg.mem_size,
+            seg.permissions,
+            if seg.code { "yes" } else { "no" }
+        );
+    }
+
+    if let Some(decode) = &summary.decode {
+        println!();
+        println!("{:<10}  {:>10}", "EXTENSION", "INSTRS");
+        for (name, count) in &decode.extensions {
+            println!("{name:<10}  {count:>10}");
+        }
+        println!("{:<10}  {:>10}", "undecoded", decode.undecoded_slots);
+    }
+
+    if !summary.warnings.is_empty() {
+        println!();
+        for warning in &summary.warnings {
+            println!("warning: {warning}");
+        }
+    }
+}
+
+fn print_summary_raw(summary: &ElfSummary) {
+    println!("xlen: {}", summary.xlen);
+    println!("abi: {}", summary.abi);
+    println!("rvc: {}", summary.rvc);
+    println!("arch: {}", summary.arch.as_deref().unwrap_or(""));
+    println!("entry_point: {:#x}", summary.entry_point);
+    println!("load_bias: {:#x}", summary.load_bias);
+    println!("symbols: {}", summary.symbols);
+    println!("functions: {}", summary.functions);
+    println!("code_bytes: {}", summary.code_bytes);
+    for seg in &summary.segments {
+        println!(
+            "segment: {:#x} {:#x} {} {} {}",
+            seg.start, seg.end, seg.file_size, seg.mem_size, seg.permissions
+        );
+    }
+    if let Some(decode) = &summary.decode {
+        for (name, count) in &decode.extensions {
+            println!("extension: {name} {count}");
+        }
+        println!("undecoded_slots: {}", decode.undecoded_slots);
+    }
+    println!("warnings: {}", summary.warnings.len());
+}
+
+/// Handle the `inspect` command: explain the block containing `pc`.
+#[allow(clippy::too_many_arguments)]
+pub fn cmd_inspect(
+    input: &Path,
+    pc: u64,
+    load_bias: Option<u64>,
+    analysis: AnalysisModeArg,
+    address_mode: AddressModeArg,
+    instret: InstretModeArg,
+    syscalls: SyscallModeArg,
+    no_superblock: bool,
+    tracer: &TracerArgs,
+) -> i32 {
+    let tracer_config = match build_tracer_config(tracer) {
+        Ok(config) => config,
+        Err(err) => {
+            error!(error = %err, "invalid tracer configuration");
+            return EXIT_FAILURE;
+        }
+    };
+
+    let mut options = CompileOptions::new()
+        .with_address_mode(address_mode.into())
+        .with_instret_mode(instret.into())
+        .with_syscall_mode(syscalls.into())
+        .with_superblock(!no_superblock)
+        .with_tracer_config(tracer_config);
+    if let Some(bias) = load_bias {
+        options = options.with_load_bias(bias);
+    }
+    options = match analysis {
+        AnalysisModeArg::Auto => options.with_analysis_mode_auto(true),
+        AnalysisModeArg::Cfg => options.with_analysis_mode(rvr_emit::AnalysisMode::FullCfg),
+        AnalysisModeArg::Linear => options.with_analysis_mode(rvr_emit::AnalysisMode::Basic),
+    };
+
+    match rvr::explain_with_options(input, pc, &options) {
+        Ok(explanation) => {
+            print!("{explanation}");
+            EXIT_SUCCESS
+        }
+        Err(e) => {
+            error!(error = %e, "explain failed");
+            EXIT_FAILURE
+        }
+    }
+}
+
+/// Handle the `addr2pc` command: print the guest PC of `host_addr`.
+pub fn cmd_addr2pc(library: &Path, host_addr: u64) -> i32 {
+    match guest_pc_at(library, host_addr) {
+        Ok(Some(pc)) => {
+            println!("{pc:#x}");
+            EXIT_SUCCESS
+        }
+        Ok(None) => {
+            error!(
+                addr = format!("{host_addr:#x}"),
+                "address is not in guest code"
+            );
+            EXIT_FAILURE
+        }
+        Err(e) => {
+            error!(error = %e, library = %library.display(), "cannot resolve address");
+            EXIT_FAILURE
+        }
+    }
+}
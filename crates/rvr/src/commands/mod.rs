@@ -5,93 +5,75 @@
 mod build;
 mod compile;
 mod dev;
+mod exec;
+mod inspect;
 mod run;
 
+use std::io::IsTerminal;
+
 use crate::cli::{Cli, Commands, DevCommands, OutputFormat};
 
 /// Dispatch CLI command to the appropriate handler.
 pub fn run_command(cli: &Cli) -> i32 {
     match &cli.command {
-        Commands::Compile { .. } => handle_compile(cli),
-        Commands::Lift { .. } => handle_lift(cli),
+        Commands::Compile(args) => compile::cmd_compile(
+            args,
+            !cli.verbose && !cli.silent && std::io::stderr().is_terminal(),
+        ),
+        Commands::Lift(args) => compile::cmd_lift(args),
+        Commands::Inspect { .. } => handle_inspect(cli),
+        Commands::Addr2pc { library, host_addr } => inspect::cmd_addr2pc(library, *host_addr),
         Commands::Run { .. } => handle_run(cli),
+        Commands::Exec {
+            input,
+            syscalls,
+            instret,
+            cc,
+            quiet,
+            env,
+            args,
+        } => exec::cmd_exec(input, *syscalls, *instret, cc.as_deref(), *quiet, env, args),
         Commands::Build { .. } => handle_build(cli),
         Commands::Dev { command } => handle_dev(command),
     }
 }
 
-fn handle_compile(cli: &Cli) -> i32 {
-    let Commands::Compile {
+fn handle_inspect(cli: &Cli) -> i32 {
+    let Commands::Inspect {
         input,
-        output,
-        backend,
+        explain,
+        decode,
+        format,
+        memory_bits,
+        load_bias,
         analysis,
         address_mode,
-        htif,
         instret,
         syscalls,
-        perf,
         no_superblock,
-        jobs,
-        cc,
-        linker,
-        fixed_addresses,
         tracer,
     } = &cli.command
     else {
-        unreachable!("compile command variant mismatch");
+        unreachable!("inspect command variant mismatch");
     };
 
-    compile::cmd_compile(
-        input,
-        output,
-        *backend,
-        *analysis,
-        *address_mode,
-        *htif,
-        *instret,
-        *syscalls,
-        *perf,
-        *no_superblock,
-        *jobs,
-        cc.as_deref(),
-        linker.as_deref(),
-        fixed_addresses.as_deref(),
-        tracer,
-    )
-}
-
-fn handle_lift(cli: &Cli) -> i32 {
-    let Commands::Lift {
-        input,
-        output,
-        backend,
-        analysis,
-        address_mode,
-        htif,
-        line_info,
-        instret,
-        syscalls,
-        perf,
-        fixed_addresses,
-        tracer,
-    } = &cli.command
-    else {
-        unreachable!("lift command variant mismatch");
+    let Some(pc) = *explain else {
+        let options = rvr::InspectOptions {
+            memory_bits: *memory_bits,
+            load_bias: *load_bias,
+            decode: *decode,
+        };
+        return inspect::cmd_summary(input, &options, *format);
     };
-
-    compile::cmd_lift(
+    inspect::cmd_inspect(
         input,
-        output,
-        *backend,
+        pc,
+        *load_bias,
         *analysis,
         *address_mode,
-        *htif,
-        *line_info,
         *instret,
         *syscalls,
-        *perf,
-        fixed_addresses.as_deref(),
+        *no_superblock,
         tracer,
     )
 }
@@ -108,6 +90,19 @@ fn handle_run(cli: &Cli) -> i32 {
         gdb,
         load_state,
         save_state,
+        stdin,
+        stdout,
+        stderr,
+        env,
+        record,
+        replay,
+        profile,
+        profile_counts,
+        coverage,
+        profile_host,
+        profile_host_interval,
+        backtrace,
+        collect_jump_targets,
         debug,
     } = &cli.command
     else {
@@ -125,6 +120,17 @@ fn handle_run(cli: &Cli) -> i32 {
         gdb.as_deref(),
         load_state.as_ref(),
         save_state.as_ref(),
+        [stdin.as_ref(), stdout.as_ref(), stderr.as_ref()],
+        env,
+        [record.as_ref(), replay.as_ref()],
+        &run::RunReports {
+            profile: profile.as_ref(),
+            profile_counts: profile_counts.as_ref(),
+            coverage: coverage.as_ref(),
+        },
+        profile_host.then_some(*profile_host_interval),
+        *backtrace,
+        collect_jump_targets.as_ref(),
         *debug,
     )
 }
@@ -166,20 +172,25 @@ fn handle_dev(command: &DevCommands) -> i32 {
             stop_on_first,
             isa,
             timeout,
-        } => dev::trace_compare(
-            elf,
-            output.clone(),
+            reference,
+            qemu_plugin,
+        } => dev::trace_compare(dev::TraceCompareArgs {
+            elf_path: elf,
+            output_dir: output.clone(),
             cc,
-            isa.clone(),
-            *timeout,
-            *stop_on_first,
-        ),
+            isa: isa.clone(),
+            timeout: *timeout,
+            stop_on_first: *stop_on_first,
+            reference: (*reference).into(),
+            qemu_plugin,
+        }),
         DevCommands::Diff {
             mode,
             elf,
             ref_backend,
             test_backend,
             granularity,
+            executor,
             max_instrs,
             output,
             ref_dir,
@@ -187,12 +198,18 @@ fn handle_dev(command: &DevCommands) -> i32 {
             cc,
             isa,
             strict_mem,
+            strict_csrs,
+            refresh_ref,
+            ref_cache,
+            ref_cache_size,
+            sample_interval,
         } => dev::diff_compare(dev::DiffCompareArgs {
             mode: *mode,
             ref_backend: *ref_backend,
             test_backend: *test_backend,
             elf_path: elf,
             granularity_arg: *granularity,
+            executor: *executor,
             max_instrs: *max_instrs,
             output_dir: output.clone(),
             ref_dir: ref_dir.clone(),
@@ -200,6 +217,11 @@ fn handle_dev(command: &DevCommands) -> i32 {
             cc,
             isa: isa.clone(),
             strict_mem: *strict_mem,
+            strict_csrs: *strict_csrs,
+            refresh_ref: *refresh_ref,
+            ref_cache: ref_cache.clone(),
+            ref_cache_size: *ref_cache_size,
+            sample_interval: *sample_interval,
         }),
     }
 }
@@ -209,51 +231,128 @@ fn handle_dev(command: &DevCommands) -> i32 {
 // ============================================================================
 
 /// Print a single run result.
-pub fn print_single_result(format: OutputFormat, result: &rvr::RunResult) {
+pub fn print_single_result(
+    format: OutputFormat,
+    result: &rvr::RunResult,
+    layout: Option<&rvr::MemoryLayout>,
+) {
     match format {
         OutputFormat::Text => {
             println!("Exit code: {}", result.exit_code);
+            if !matches!(result.exit_reason, rvr::ExitReason::Exited(_)) {
+                println!("Exit reason: {}", result.exit_reason);
+            }
             println!("Instructions: {}", result.instret);
             println!("Time: {:.6}s", result.time_secs);
             println!("Speed: {}", rvr::bench::format_speed(result.mips));
+            print_phases(&result.phases, "Phases");
+            println!(
+                "Total: {:.6}s ({})",
+                result.total_secs(),
+                rvr::bench::format_speed(result.total_mips())
+            );
+            if let Some(bytes) = result.resident_bytes {
+                println!("Memory: {}", rvr::bench::format_bytes(Some(bytes)));
+            }
+            if let Some(layout) = layout {
+                println!("Memory layout: {layout}");
+            }
         }
         OutputFormat::Raw => {
             println!("instret: {}", result.instret);
             println!("time: {:.6}", result.time_secs);
             println!("speed: {}", rvr::bench::format_speed_shell(result.mips));
+            print_phases_raw(&result.phases);
+            println!("total_time: {:.6}", result.total_secs());
+            if let Some(bytes) = result.resident_bytes {
+                println!("resident_bytes: {bytes}");
+            }
         }
-        OutputFormat::Json => {
-            result.print_json();
-        }
+        OutputFormat::Json => match layout {
+            Some(layout) => println!(
+                r#"{{"instret":{},"time":{:.6},"mips":{:.2},"exit_code":{},"phases":{},"total_time":{:.6},"total_mips":{:.2},"resident_bytes":{},"memory_layout":{}}}"#,
+                result.instret,
+                result.time_secs,
+                result.mips,
+                result.exit_code,
+                result.phases.to_json(),
+                result.total_secs(),
+                result.total_mips(),
+                result.resident_bytes_json(),
+                memory_layout_json(layout)
+            ),
+            None => result.print_json(),
+        },
     }
 }
 
+/// `{"segments":[[start,end],...],"heap":[start,end],"stack":[start,end]}`.
+fn memory_layout_json(layout: &rvr::MemoryLayout) -> String {
+    let range = |range: &rvr::AddrRange| format!("[{},{}]", range.start, range.end);
+    let segments: Vec<String> = layout.segments.iter().map(range).collect();
+    format!(
+        r#"{{"segments":[{}],"heap":{},"stack":{},"mmap":{}}}"#,
+        segments.join(","),
+        range(&layout.heap),
+        range(&layout.stack),
+        layout
+            .mmap
+            .as_ref()
+            .map_or_else(|| "null".to_string(), range)
+    )
+}
+
 /// Print averaged result from multiple runs.
-pub fn print_multi_result(
-    format: OutputFormat,
-    runs: usize,
-    first: &rvr::RunResult,
-    avg_time: f64,
-    avg_mips: f64,
-) {
+pub fn print_multi_result(format: OutputFormat, runs: usize, avg: &rvr::RunResult) {
     match format {
         OutputFormat::Text => {
             println!("Runs: {runs}");
-            println!("Exit code: {}", first.exit_code);
-            println!("Instructions: {}", first.instret);
-            println!("Avg time: {avg_time:.6}s");
-            println!("Avg speed: {}", rvr::bench::format_speed(avg_mips));
+            println!("Exit code: {}", avg.exit_code);
+            println!("Instructions: {}", avg.instret);
+            println!("Avg time: {:.6}s", avg.time_secs);
+            println!("Avg speed: {}", rvr::bench::format_speed(avg.mips));
+            print_phases(&avg.phases, "Avg phases");
+            if let Some(reset) = avg.reset_secs {
+                println!("Reset after first run: {reset:.6}s");
+            }
         }
         OutputFormat::Raw => {
-            println!("instret: {}", first.instret);
-            println!("time: {avg_time:.6}");
-            println!("speed: {}", rvr::bench::format_speed_shell(avg_mips));
+            println!("instret: {}", avg.instret);
+            println!("time: {:.6}", avg.time_secs);
+            println!("speed: {}", rvr::bench::format_speed_shell(avg.mips));
+            print_phases_raw(&avg.phases);
+            if let Some(reset) = avg.reset_secs {
+                println!("reset: {reset:.6}");
+            }
         }
         OutputFormat::Json => {
             println!(
-                r#"{{"runs":{},"instret":{},"avg_time":{:.6},"avg_mips":{:.2},"exit_code":{}}}"#,
-                runs, first.instret, avg_time, avg_mips, first.exit_code
+                r#"{{"runs":{},"instret":{},"avg_time":{:.6},"avg_mips":{:.2},"exit_code":{},"avg_phases":{},"reset":{}}}"#,
+                runs,
+                avg.instret,
+                avg.time_secs,
+                avg.mips,
+                avg.exit_code,
+                avg.phases.to_json(),
+                avg.reset_secs
+                    .map_or_else(|| "null".to_string(), |secs| format!("{secs:.6}"))
             );
         }
     }
 }
+
+/// Print the phase breakdown of a run as text.
+fn print_phases(phases: &rvr::RunPhases, label: &str) {
+    println!(
+        "{label}: load {:.6}s, init {:.6}s, execute {:.6}s, teardown {:.6}s",
+        phases.load_secs, phases.init_secs, phases.execute_secs, phases.teardown_secs
+    );
+}
+
+/// Print the phase breakdown of a run as raw key-value lines.
+fn print_phases_raw(phases: &rvr::RunPhases) {
+    println!("load: {:.6}", phases.load_secs);
+    println!("init: {:.6}", phases.init_secs);
+    println!("execute: {:.6}", phases.execute_secs);
+    println!("teardown: {:.6}", phases.teardown_secs);
+}
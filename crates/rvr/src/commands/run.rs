@@ -1,20 +1,68 @@
 //! Run command.
 
-use std::io::{self, BufRead, Write};
-use std::path::PathBuf;
+use std::fs::File;
+use std::io::{self, BufRead, BufWriter, Write};
+use std::path::{Path, PathBuf};
 
 use tracing::{error, info, warn};
 
-use crate::cli::{EXIT_FAILURE, EXIT_SUCCESS, OutputFormat};
+use crate::cli::{EXIT_FAILURE, EXIT_SUCCESS, GuestEnvArgs, OutputFormat};
 use crate::commands::{print_multi_result, print_single_result};
 
-fn usize_to_f64(value: usize) -> f64 {
-    let value = u64::try_from(value).unwrap_or(u64::MAX);
-    let hi = u32::try_from(value >> 32).unwrap_or(u32::MAX);
-    let lo = u32::try_from(value & 0xFFFF_FFFF).unwrap_or(u32::MAX);
-    f64::from(hi) * 4_294_967_296.0 + f64::from(lo)
+/// Blocks shown in the `--profile` hottest-blocks report.
+const PROFILE_REPORT_BLOCKS: usize = 20;
+/// Functions shown in the `--profile-host` report.
+const HOST_PROFILE_REPORT_FUNCTIONS: usize = 20;
+
+/// Files written from the tracer data of a run.
+pub struct RunReports<'a> {
+    /// Folded-stack block profile (`--profile`).
+    pub profile: Option<&'a PathBuf>,
+    /// Block entry counts for a profile-guided recompile (`--profile-counts`).
+    pub profile_counts: Option<&'a PathBuf>,
+    /// lcov coverage tracefile (`--coverage`).
+    pub coverage: Option<&'a PathBuf>,
 }
 
+impl RunReports<'_> {
+    /// Check that `runner` was compiled with the tracers the reports need.
+    fn check(&self, runner: &rvr::Runner) -> bool {
+        if self.profile.is_some() && runner.block_profile().is_none() {
+            warn!("--profile requires library compiled with --tracer block-profile");
+            return false;
+        }
+        if self.profile_counts.is_some() && runner.block_profile().is_none() {
+            warn!("--profile-counts requires library compiled with --tracer block-profile");
+            return false;
+        }
+        if self.coverage.is_some() && runner.coverage().is_none() {
+            warn!("--coverage requires library compiled with --tracer coverage");
+            return false;
+        }
+        true
+    }
+
+    /// Write the requested reports, logging the first failure.
+    fn write(&self, runner: &rvr::Runner, elf_path: &Path) -> bool {
+        let writers: [(Option<&PathBuf>, ReportWriter, &str); 3] = [
+            (self.profile, write_block_profile, "block profile"),
+            (self.profile_counts, write_profile_counts, "block counts"),
+            (self.coverage, write_coverage, "coverage"),
+        ];
+        for (path, write, what) in writers {
+            if let Some(path) = path
+                && let Err(e) = write(runner, elf_path, path)
+            {
+                error!(error = %e, path = %path.display(), "failed to write {what}");
+                return false;
+            }
+        }
+        true
+    }
+}
+
+type ReportWriter = fn(&rvr::Runner, &Path, &Path) -> rvr::Result<()>;
+
 /// Handle the `run` command.
 #[allow(clippy::too_many_arguments)]
 pub fn cmd_run(
@@ -28,16 +76,34 @@ pub fn cmd_run(
     gdb_addr: Option<&str>,
     load_state_path: Option<&PathBuf>,
     save_state_path: Option<&PathBuf>,
+    stdio_paths: [Option<&PathBuf>; 3],
+    env: &GuestEnvArgs,
+    [record_path, replay_path]: [Option<&PathBuf>; 2],
+    reports: &RunReports<'_>,
+    profile_host: Option<u64>,
+    backtrace: bool,
+    collect_jump_targets: Option<&PathBuf>,
     debug_mode: bool,
 ) -> i32 {
     let memory_size = 1usize << memory_bits;
-    let mut runner = match rvr::Runner::load_with_memory(lib_dir, elf_path, memory_size) {
+    let runner = match rvr::Runner::load_with_memory(lib_dir, elf_path, memory_size) {
         Ok(r) => r,
         Err(e) => {
             error!(error = %e, path = %lib_dir.display(), "failed to load library");
             return EXIT_FAILURE;
         }
     };
+    let mut runner = match with_guest_env(runner, env) {
+        Ok(r) => r,
+        Err(e) => {
+            error!(error = %e, "failed to set the guest environment");
+            return EXIT_FAILURE;
+        }
+    };
+
+    if !setup_inputs(&mut runner, stdio_paths, [record_path, replay_path]) {
+        return EXIT_FAILURE;
+    }
 
     // Load state from file if specified
     if let Some(path) = load_state_path {
@@ -52,14 +118,8 @@ pub fn cmd_run(
         }
     }
 
-    // Set up instruction limit if specified
-    if let Some(limit) = max_insns {
-        if runner.supports_suspend() {
-            runner.set_target_instret(limit);
-        } else {
-            warn!("--max-insns requires library compiled with --instret suspend");
-            return EXIT_FAILURE;
-        }
+    if !check_features(&mut runner, max_insns, reports, profile_host) {
+        return EXIT_FAILURE;
     }
 
     // If --gdb is specified, start GDB server instead of running normally
@@ -89,28 +149,27 @@ pub fn cmd_run(
             }
         }
     }
+    // Host cost sampling
+    else if let Some(interval) = profile_host {
+        run_host_profile(&mut runner, elf_path, format, interval)
+    }
     // Normal execution
     else if runs <= 1 {
-        match runner.run() {
-            Ok(result) => {
-                print_single_result(format, &result);
-                i32::from(result.exit_code)
-            }
-            Err(e) => {
-                error!(error = %e, "execution failed");
-                EXIT_FAILURE
-            }
-        }
+        run_once(
+            &mut runner,
+            elf_path,
+            format,
+            backtrace,
+            collect_jump_targets,
+        )
     } else {
         match runner.run_multiple(runs) {
             Ok(results) => {
-                let runs_f64 = usize_to_f64(runs);
-                let avg_time: f64 = results.iter().map(|r| r.time_secs).sum::<f64>() / runs_f64;
-                let avg_mips: f64 = results.iter().map(|r| r.mips).sum::<f64>() / runs_f64;
-                let first = &results[0];
-
-                print_multi_result(format, runs, first, avg_time, avg_mips);
-                i32::from(first.exit_code)
+                let Some(avg) = rvr::RunResult::average(&results) else {
+                    return EXIT_FAILURE;
+                };
+                print_multi_result(format, runs, &avg);
+                i32::from(avg.exit_code)
             }
             Err(e) => {
                 error!(error = %e, "execution failed");
@@ -119,6 +178,17 @@ pub fn cmd_run(
         }
     };
 
+    // Keep the inputs of failed runs too, to replay the failure
+    if let Some(path) = record_path
+        && !save_recording(&mut runner, path)
+    {
+        return EXIT_FAILURE;
+    }
+
+    if !reports.write(&runner, elf_path) {
+        return EXIT_FAILURE;
+    }
+
     // Save state to file if specified
     if let Some(path) = save_state_path {
         match runner.save_state(path) {
@@ -135,6 +205,239 @@ pub fn cmd_run(
     exit_code
 }
 
+/// Check that the library was compiled with what the requested options
+/// need, setting up the instruction limit.
+fn check_features(
+    runner: &mut rvr::Runner,
+    max_insns: Option<u64>,
+    reports: &RunReports<'_>,
+    profile_host: Option<u64>,
+) -> bool {
+    if let Some(limit) = max_insns {
+        if !runner.supports_suspend() {
+            warn!("--max-insns requires library compiled with --instret suspend");
+            return false;
+        }
+        runner.set_target_instret(limit);
+    }
+    if !reports.check(runner) {
+        return false;
+    }
+    if profile_host.is_some() && !runner.supports_suspend() {
+        warn!("--profile-host requires library compiled with --instret suspend");
+        return false;
+    }
+    true
+}
+
+/// Redirect guest stdio and set up input recording or replay.
+fn setup_inputs(
+    runner: &mut rvr::Runner,
+    stdio_paths: [Option<&PathBuf>; 3],
+    [record_path, replay_path]: [Option<&PathBuf>; 2],
+) -> bool {
+    if let Err((e, path)) = redirect_stdio(runner, stdio_paths) {
+        error!(error = %e, path = %path.display(), "failed to open guest stdio file");
+        return false;
+    }
+    if let Some(path) = replay_path {
+        let replayed =
+            rvr::InputRecording::load(path).and_then(|recording| runner.replay_inputs(recording));
+        if let Err(e) = replayed {
+            error!(error = %e, path = %path.display(), "failed to replay inputs");
+            return false;
+        }
+    }
+    if record_path.is_some() {
+        runner.record_inputs();
+    }
+    true
+}
+
+/// Save the inputs recorded during the run, if recording was enabled.
+fn save_recording(runner: &mut rvr::Runner, path: &Path) -> bool {
+    let Some(recording) = runner.take_recording() else {
+        return true;
+    };
+    if let Err(e) = recording.save(path) {
+        error!(error = %e, path = %path.display(), "failed to save input recording");
+        return false;
+    }
+    info!(path = %path.display(), inputs = recording.len(), "saved input recording");
+    true
+}
+
+/// Run once from the entry point, printing a backtrace or collecting the
+/// jump target if the run fails.
+fn run_once(
+    runner: &mut rvr::Runner,
+    elf_path: &Path,
+    format: OutputFormat,
+    backtrace: bool,
+    collect_jump_targets: Option<&PathBuf>,
+) -> i32 {
+    match runner.run() {
+        Ok(result) => {
+            print_single_result(format, &result, runner.memory_layout());
+            if backtrace && runner.is_trapped() {
+                print_backtrace(runner);
+            }
+            i32::from(result.exit_code)
+        }
+        Err(e) => {
+            error!(error = %e, "execution failed");
+            if let rvr::RunError::UnresolvedJump { site, target, .. } = e {
+                eprintln!("jump site: {}", runner.jump_site(site));
+                if let Some(path) = collect_jump_targets
+                    && let Err(e) = collect_jump_target(elf_path, path, site, target)
+                {
+                    error!(error = %e, path = %path.display(), "failed to save jump targets");
+                }
+            }
+            EXIT_FAILURE
+        }
+    }
+}
+
+/// Run once while sampling host cost every `interval` guest instructions.
+fn run_host_profile(
+    runner: &mut rvr::Runner,
+    elf_path: &Path,
+    format: OutputFormat,
+    interval: u64,
+) -> i32 {
+    match runner.run_host_profile(interval) {
+        Ok((result, samples)) => {
+            print_single_result(format, &result, runner.memory_layout());
+            if let Err(e) = report_host_profile(runner, elf_path, format, &samples) {
+                error!(error = %e, "failed to report host profile");
+                return EXIT_FAILURE;
+            }
+            i32::from(result.exit_code)
+        }
+        Err(e) => {
+            error!(error = %e, "execution failed");
+            EXIT_FAILURE
+        }
+    }
+}
+
+/// Print the guest call stack of a trapped run to stderr.
+fn print_backtrace(runner: &rvr::Runner) {
+    let mut stderr = io::stderr().lock();
+    let _ = writeln!(
+        stderr,
+        "guest trapped at {:#x}, backtrace:",
+        runner.get_pc()
+    );
+    for (depth, frame) in runner.backtrace().iter().enumerate() {
+        let _ = writeln!(stderr, "  #{depth:<2} {frame}");
+    }
+}
+
+/// Add the jump from `site` to `target` to the jump targets at `path`.
+fn collect_jump_target(elf_path: &Path, path: &Path, site: u64, target: u64) -> rvr::Result<()> {
+    let mut targets = rvr::JumpTargets::load_or_new(path, &std::fs::read(elf_path)?)?;
+    if targets.insert(site, target) {
+        targets.save(path)?;
+        info!(
+            path = %path.display(),
+            jumps = targets.jumps().count(),
+            "saved jump target, recompile with --jump-targets"
+        );
+    }
+    Ok(())
+}
+
+/// Write the folded block profile to `path` and the hottest blocks to stderr.
+fn write_block_profile(runner: &rvr::Runner, elf_path: &Path, path: &Path) -> rvr::Result<()> {
+    let counts = runner.block_profile().unwrap_or_default();
+    let addr2line = rvr::Compiler::default().addr2line();
+    let profile = rvr::BlockProfile::resolve(elf_path, runner.load_bias(), &counts, &addr2line)?;
+    let mut file = BufWriter::new(File::create(path)?);
+    profile.write_folded(&mut file)?;
+    file.flush()?;
+    profile.write_hottest(io::stderr().lock(), PROFILE_REPORT_BLOCKS)?;
+    Ok(())
+}
+
+/// Save the block entry counts to `path` for a profile-guided recompile.
+fn write_profile_counts(runner: &rvr::Runner, elf_path: &Path, path: &Path) -> rvr::Result<()> {
+    let counts = runner.block_profile().unwrap_or_default();
+    let profile = rvr::ProfileCounts::new(&std::fs::read(elf_path)?, &counts);
+    let mut file = BufWriter::new(File::create(path)?);
+    profile.write(&mut file)?;
+    file.flush()?;
+    info!(path = %path.display(), blocks = counts.len(), "saved block counts");
+    Ok(())
+}
+
+/// Write guest line and branch coverage to `path` as an lcov tracefile.
+fn write_coverage(runner: &rvr::Runner, elf_path: &Path, path: &Path) -> rvr::Result<()> {
+    let flags = runner.coverage().unwrap_or_default();
+    let addr2line = rvr::Compiler::default().addr2line();
+    let coverage = rvr::Coverage::resolve(elf_path, runner.load_bias(), &flags, &addr2line)?;
+    // lcov test names are identifiers
+    let test_name: String = elf_path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let mut file = BufWriter::new(File::create(path)?);
+    coverage.write_lcov(&mut file, &test_name)?;
+    file.flush()?;
+    info!(path = %path.display(), files = coverage.files().count(), "saved coverage");
+    Ok(())
+}
+
+/// Print the host cost per guest function: as JSON on stdout with
+/// `--format json`, else as a table on stderr.
+fn report_host_profile(
+    runner: &rvr::Runner,
+    elf_path: &Path,
+    format: OutputFormat,
+    samples: &rvr::HostSamples,
+) -> rvr::Result<()> {
+    let profile = rvr::HostProfile::resolve(elf_path, runner.load_bias(), samples)?;
+    if matches!(format, OutputFormat::Json) {
+        println!("{}", profile.to_json().map_err(io::Error::other)?);
+    } else {
+        profile.write_table(io::stderr().lock(), HOST_PROFILE_REPORT_FUNCTIONS)?;
+    }
+    Ok(())
+}
+
+/// Set the guest environment of `env` on `runner`, `--env` after
+/// `--env-file` so it wins.
+pub fn with_guest_env(
+    mut runner: rvr::Runner,
+    env: &GuestEnvArgs,
+) -> Result<rvr::Runner, rvr::RunError> {
+    if let Some(path) = &env.env_file {
+        runner = runner.with_env_file(path)?;
+    }
+    Ok(runner.with_env(&env.vars))
+}
+
+/// Redirect guest stdin, stdout and stderr to the given files.
+fn redirect_stdio<'a>(
+    runner: &mut rvr::Runner,
+    [stdin, stdout, stderr]: [Option<&'a PathBuf>; 3],
+) -> Result<(), (io::Error, &'a PathBuf)> {
+    if let Some(path) = stdin {
+        runner.set_stdin(File::open(path).map_err(|e| (e, path))?);
+    }
+    if let Some(path) = stdout {
+        runner.set_stdout(File::create(path).map_err(|e| (e, path))?);
+    }
+    if let Some(path) = stderr {
+        runner.set_stderr(File::create(path).map_err(|e| (e, path))?);
+    }
+    Ok(())
+}
+
 /// Run with GDB server.
 fn cmd_run_gdb(runner: rvr::Runner, addr: &str) -> i32 {
     use rvr::gdb::GdbServer;
@@ -0,0 +1,725 @@
+use std::path::PathBuf;
+
+use rvr_emit::c::TracerConfig;
+use rvr_emit::{
+    AddressMode, AnalysisMode, Backend, CDialect, Compiler, CompilerLauncher, Compression,
+    CustomCsr, DispatchMode, FixedAddressConfig, FunctionHook, HookKind, HotRegsMode, InstretMode,
+    LayoutProfile, LiftErrorMode, SuspendGranularity, SyscallMode,
+};
+use rvr_isa::LrScModel;
+use rvr_isa::syscalls::{BareMetalConfig, SyscallPolicy};
+
+use crate::FilterSpec;
+use crate::progress::{CompileProgress, ProgressFn};
+
+use super::CompileOptions;
+
+impl CompileOptions {
+    /// Set code generation backend.
+    #[must_use]
+    pub const fn with_backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Set analysis mode.
+    #[must_use]
+    pub const fn with_analysis_mode(mut self, mode: AnalysisMode) -> Self {
+        self.analysis_mode = mode;
+        self.flags.set_analysis_mode_auto(false);
+        self
+    }
+
+    /// Use backend defaults for analysis mode (CFG for C, linear for asm).
+    #[must_use]
+    pub const fn with_analysis_mode_auto(mut self, enabled: bool) -> Self {
+        self.flags.set_analysis_mode_auto(enabled);
+        self
+    }
+
+    /// Set address translation mode.
+    #[must_use]
+    pub const fn with_address_mode(mut self, mode: AddressMode) -> Self {
+        self.address_mode = mode;
+        self
+    }
+
+    /// Set dispatch table layout.
+    #[must_use]
+    pub const fn with_dispatch_mode(mut self, mode: DispatchMode) -> Self {
+        self.dispatch_mode = mode;
+        self
+    }
+
+    /// Set global or per-function hot register sets.
+    #[must_use]
+    pub const fn with_hot_regs_mode(mut self, mode: HotRegsMode) -> Self {
+        self.hot_regs_mode = mode;
+        self
+    }
+
+    /// Store the flat dispatch table as 32-bit offsets from the table
+    /// instead of function pointers (C backend).
+    ///
+    /// The table then needs no dynamic relocation per entry, is half the
+    /// size on 64-bit hosts, and costs one add per indirect jump.
+    #[must_use]
+    pub const fn with_dispatch_table_relative(mut self, enabled: bool) -> Self {
+        self.flags.set_dispatch_table_relative(enabled);
+        self
+    }
+
+    /// Prefetch the dispatch table slot of an indirect jump as soon as its
+    /// target register is final, when that is a few instructions ahead of
+    /// the jump (C flat dispatch and ARM64 backends).
+    #[must_use]
+    pub const fn with_prefetch_dispatch(mut self, enabled: bool) -> Self {
+        self.flags.set_prefetch_dispatch(enabled);
+        self
+    }
+
+    /// Place the dispatch table in its own 2 MiB aligned section and link
+    /// with 2 MiB segment alignment, so the host can back it with a huge
+    /// page (C flat dispatch and ARM64 backends).
+    #[must_use]
+    pub const fn with_dispatch_table_hugepages(mut self, enabled: bool) -> Self {
+        self.flags.set_dispatch_table_hugepages(enabled);
+        self
+    }
+
+    /// Serve Linux `clock_gettime` inline from the retired instruction
+    /// count instead of the host clock, so runs are reproducible (RV64).
+    #[must_use]
+    pub const fn with_deterministic_clock(mut self, enabled: bool) -> Self {
+        self.flags.set_deterministic_clock(enabled);
+        self
+    }
+
+    /// Set HTIF enabled.
+    #[must_use]
+    pub const fn with_htif(mut self, enabled: bool) -> Self {
+        self.flags.set_htif(enabled);
+        self
+    }
+
+    /// Set HTIF verbose (print guest stdout).
+    #[must_use]
+    pub const fn with_htif_verbose(mut self, verbose: bool) -> Self {
+        self.flags.set_htif_verbose(verbose);
+        self
+    }
+
+    /// Stop an HTIF guest with `ExitReason::HtifStall` once it polls
+    /// `fromhost` `limit` times without it changing or a new request (C
+    /// backend; 0 disables the watchdog).
+    #[must_use]
+    pub const fn with_htif_poll_limit(mut self, limit: u64) -> Self {
+        self.htif_poll_limit = limit;
+        self
+    }
+
+    /// Set `line_info` enabled (for `#line` directives).
+    #[must_use]
+    pub const fn with_line_info(mut self, enabled: bool) -> Self {
+        self.flags.set_line_info(enabled);
+        self
+    }
+
+    /// Set instret mode.
+    #[must_use]
+    pub const fn with_instret_mode(mut self, mode: InstretMode) -> Self {
+        self.instret_mode = mode;
+        self
+    }
+
+    /// Set where `InstretMode::Suspend` checks the instret limit.
+    #[must_use]
+    pub const fn with_suspend_granularity(mut self, granularity: SuspendGranularity) -> Self {
+        self.suspend_granularity = granularity;
+        self
+    }
+
+    /// Set number of parallel compile jobs (0 = auto-detect).
+    #[must_use]
+    pub const fn with_jobs(mut self, jobs: usize) -> Self {
+        self.jobs = jobs;
+        self
+    }
+
+    /// Set threads for CFG analysis and lifting (0 = rayon default).
+    ///
+    /// The generated code does not depend on this.
+    #[must_use]
+    pub const fn with_analysis_jobs(mut self, jobs: usize) -> Self {
+        self.analysis_jobs = jobs;
+        self
+    }
+
+    /// Set tracer configuration.
+    #[must_use]
+    pub fn with_tracer_config(mut self, tracer_config: TracerConfig) -> Self {
+        self.tracer_config = tracer_config;
+        self
+    }
+
+    /// Set syscall handling mode.
+    #[must_use]
+    pub const fn with_syscall_mode(mut self, mode: SyscallMode) -> Self {
+        self.syscall_mode = mode;
+        self
+    }
+
+    /// Restrict Linux-mode syscalls to `policy`.
+    ///
+    /// Syscalls outside the policy compile to an immediate `-EPERM`, so
+    /// their host-side handling is not even linked in. The policy's
+    /// preopens are the only guest paths `Runner::with_preopened_dir`
+    /// accepts; every guest `openat` is checked against them at run time.
+    #[must_use]
+    pub fn with_syscall_policy(mut self, policy: SyscallPolicy) -> Self {
+        self.syscall_policy = Some(policy);
+        self
+    }
+
+    /// Map bare-metal ECALLs (by the number in a7) to built-in actions
+    /// such as exit, putchar or a host syscall, instead of exiting on every
+    /// ECALL. Unmapped numbers trap unless the config says to exit.
+    ///
+    /// Takes effect with `SyscallMode::BareMetal`. Traps need the C backend.
+    #[must_use]
+    pub fn with_baremetal_ecalls(mut self, ecalls: BareMetalConfig) -> Self {
+        self.baremetal_ecalls = Some(ecalls);
+        self
+    }
+
+    /// Set the C compiler to use.
+    #[must_use]
+    pub fn with_compiler(mut self, compiler: Compiler) -> Self {
+        self.compiler = compiler;
+        self
+    }
+
+    /// Set the command prefixed to compile rules, such as `ccache`.
+    #[must_use]
+    pub fn with_compiler_launcher(mut self, launcher: CompilerLauncher) -> Self {
+        self.compiler_launcher = launcher;
+        self
+    }
+
+    /// Set the target estimated compile cost per C part file.
+    ///
+    /// Smaller parts build in parallel and rebuild less after a change,
+    /// at the cost of more compiler invocations.
+    #[must_use]
+    pub const fn with_target_part_cost(mut self, cost: usize) -> Self {
+        self.target_part_cost = cost;
+        self
+    }
+
+    /// Set the optimization level C part files that fail to compile (the
+    /// compiler ran out of memory or crashed) are retried at, or `None` to
+    /// fail the build at once (default: `-O1`).
+    #[must_use]
+    pub const fn with_fallback_opt_level(mut self, level: Option<u8>) -> Self {
+        self.fallback_opt_level = level;
+        self
+    }
+
+    /// Suppress compilation output.
+    #[must_use]
+    pub const fn with_quiet(mut self, quiet: bool) -> Self {
+        self.flags.set_quiet(quiet);
+        self
+    }
+
+    /// Enable export functions mode for calling exported functions.
+    ///
+    /// When enabled, all function symbols are added as CFG entry points,
+    /// and `RV_EXPORT_FUNCTIONS` metadata is set in the compiled library.
+    #[must_use]
+    pub const fn with_export_functions(mut self, enabled: bool) -> Self {
+        self.flags.set_export_functions(enabled);
+        self
+    }
+
+    /// Set fixed addresses for state and memory.
+    ///
+    /// When enabled, state/memory are accessed via compile-time constant addresses
+    /// instead of function arguments. Requires runtime to map at these addresses.
+    #[must_use]
+    pub const fn with_fixed_addresses(mut self, config: FixedAddressConfig) -> Self {
+        self.fixed_addresses = Some(config);
+        self
+    }
+
+    /// Enable perf mode (disable instret/CSR reads).
+    #[must_use]
+    pub const fn with_perf_mode(mut self, enabled: bool) -> Self {
+        self.flags.set_perf_mode(enabled);
+        if enabled {
+            self.instret_mode = InstretMode::Off;
+        }
+        self
+    }
+
+    /// Enable or disable superblock formation.
+    ///
+    /// Superblocks merge fall-through blocks after branches for better performance,
+    /// but prevent dispatch to mid-block addresses. Disable for differential testing.
+    #[must_use]
+    pub const fn with_superblock(mut self, enabled: bool) -> Self {
+        self.flags.set_enable_superblock(enabled);
+        self
+    }
+
+    /// Enable or disable traps on guest stores into recompiled code (on by
+    /// default; `AddressMode::Unchecked` builds never check).
+    ///
+    /// A guest that rewrites its own text would otherwise keep running the
+    /// code recompiled from the original bytes. With the check, the store
+    /// fails the run with `RunError::CodeWrite` instead.
+    #[must_use]
+    pub const fn with_detect_code_writes(mut self, enabled: bool) -> Self {
+        self.flags.set_detect_code_writes(enabled);
+        self
+    }
+
+    /// Enable or disable a cancel check on backward jumps and branches
+    /// (C backend, off by default).
+    ///
+    /// The check lets [`Runner::run_with_timeout`](crate::Runner::run_with_timeout)
+    /// and [`CancelHandle`](crate::CancelHandle) stop libraries compiled
+    /// without `InstretMode::Suspend`, for the cost of a flag test per loop
+    /// iteration.
+    #[must_use]
+    pub const fn with_check_cancel(mut self, enabled: bool) -> Self {
+        self.flags.set_check_cancel(enabled);
+        self
+    }
+
+    /// Enable or disable outlined cold paths (C backend, clang dialect; off
+    /// by default).
+    ///
+    /// Traps, exits and suspensions tail-call shared `cold` helpers that
+    /// record the stop and save the hot registers, so hot blocks keep only
+    /// the check and a jump.
+    #[must_use]
+    pub const fn with_outline_cold_paths(mut self, enabled: bool) -> Self {
+        self.flags.set_outline_cold_paths(enabled);
+        self
+    }
+
+    /// Enable or disable dead register write elimination on the lifted IR
+    /// (on by default; traced, per-instruction suspend and guest PC map
+    /// builds never run it).
+    ///
+    /// Within a block, register writes that are overwritten before being
+    /// read are dropped; side exits, extern calls and exits are barriers.
+    #[must_use]
+    pub const fn with_optimize_ir(mut self, enabled: bool) -> Self {
+        self.flags.set_optimize_ir(enabled);
+        self
+    }
+
+    /// Set the maximum instructions per emitted block.
+    ///
+    /// Longer blocks and superblocks are split at a block boundary, bounding
+    /// the size of any single C function.
+    #[must_use]
+    pub const fn with_superblock_max_instrs(mut self, max_instrs: usize) -> Self {
+        self.superblock_max_instrs = max_instrs;
+        self
+    }
+
+    /// Set the maximum basic blocks merged into one emitted block.
+    #[must_use]
+    pub const fn with_superblock_max_blocks(mut self, max_blocks: usize) -> Self {
+        self.superblock_max_blocks = max_blocks;
+        self
+    }
+
+    /// Enable or disable identical-block deduplication (C backend).
+    ///
+    /// Blocks with identical emitted bodies are compiled once and aliased;
+    /// the counts are in `CompileReport::dedup`.
+    #[must_use]
+    pub const fn with_dedup_blocks(mut self, enabled: bool) -> Self {
+        self.flags.set_dedup_blocks(enabled);
+        self
+    }
+
+    /// Fail the compile on any instruction that would trap.
+    ///
+    /// Instructions that no extension lifts or that do not decode (e.g. RVV
+    /// from autovectorization) are otherwise only listed in a warning and in
+    /// `PipelineStats::unsupported`, and reachable ones go through
+    /// `on_lift_error`. With it, the compile fails with
+    /// `Error::UnsupportedInstructions` listing all of them.
+    #[must_use]
+    pub const fn with_strict_decode(mut self, enabled: bool) -> Self {
+        self.flags.set_strict_decode(enabled);
+        self
+    }
+
+    /// Lower AMOs to LSE instructions on the ARM64 backend.
+    ///
+    /// By default AMOs are exclusive load/store retry loops, which run on
+    /// any ARM64 host; LSE (`ldadd`, `swp`, ...) needs an ARMv8.1 host.
+    #[must_use]
+    pub const fn with_arm64_lse(mut self, enabled: bool) -> Self {
+        self.flags.set_arm64_lse(enabled);
+        self
+    }
+
+    /// Set how LR/SC pairs behave (see [`LrScModel`]).
+    ///
+    /// The default tracks the reservation so SC fails as the spec allows;
+    /// `AlwaysSucceed` drops the tracking, including the check on every
+    /// store, for single-hart guests that do not rely on SC failing.
+    #[must_use]
+    pub const fn with_lrsc_model(mut self, model: LrScModel) -> Self {
+        self.lrsc_model = model;
+        self
+    }
+
+    /// Map guest memory from a segment image instead of copying the ELF
+    /// segments at startup.
+    ///
+    /// Segment data is written to `<name>.segments` next to the library and
+    /// paged in copy-on-write on first touch, so startup cost and resident
+    /// memory no longer scale with the data the guest never reads. The image
+    /// must ship with the library.
+    #[must_use]
+    pub const fn with_lazy_segment_init(mut self, enabled: bool) -> Self {
+        self.flags.set_lazy_segment_init(enabled);
+        self
+    }
+
+    /// Run the guest's `memcpy`, `memset` and `memcmp` as native helpers
+    /// (C backend).
+    ///
+    /// The functions are found by symbol name; each call retires as one
+    /// instruction and changes only `a0`. Compiling with a tracer fails,
+    /// since the trace would change.
+    #[must_use]
+    pub const fn with_native_mem_intrinsics(mut self, enabled: bool) -> Self {
+        self.flags.set_native_mem_intrinsics(enabled);
+        self
+    }
+
+    /// Map host code back to guest PCs (C and assembly backends).
+    ///
+    /// The library gets line tables pointing into a `guest_pc.map` sidecar
+    /// naming one guest instruction per line, so `perf`, `gdb` and
+    /// `rvr addr2pc` show guest PCs. Replaces source `#line` directives.
+    #[must_use]
+    pub const fn with_guest_pc_map(mut self, enabled: bool) -> Self {
+        self.flags.set_guest_pc_map(enabled);
+        self
+    }
+
+    /// Write `size_report.tsv` attributing code size to guest functions (C
+    /// backend).
+    ///
+    /// Lists guest instructions, emitted C and part file per function, plus
+    /// compiled host bytes once the library is built (see [`SizeReport`]).
+    /// Builds with a report skip the artifact cache.
+    #[must_use]
+    pub const fn with_size_report(mut self, enabled: bool) -> Self {
+        self.flags.set_size_report(enabled);
+        self
+    }
+
+    /// Set what to do when a block fails to lift.
+    ///
+    /// `Quarantine` replaces the block with a trap stub and keeps compiling;
+    /// the stubs are listed in the `CompileReport`.
+    #[must_use]
+    pub const fn with_on_lift_error(mut self, mode: LiftErrorMode) -> Self {
+        self.on_lift_error = mode;
+        self
+    }
+
+    /// Compile for a layout profile.
+    ///
+    /// Sets the profile's address mode and memory size, checks the ELF's
+    /// segments, `__stack_top` and initial program break against it (failing
+    /// with `Error::Layout`), and records it for the runner to check at load.
+    #[must_use]
+    pub const fn with_layout(mut self, layout: LayoutProfile) -> Self {
+        self.address_mode = layout.spec().address_mode;
+        self.layout = Some(layout);
+        self
+    }
+
+    /// Load position-independent (`ET_DYN`) ELFs at `bias` instead of
+    /// `rvr_elf::DEFAULT_LOAD_BIAS`.
+    ///
+    /// The bias is recorded in the library so the runner loads the ELF at
+    /// the same addresses. It must be a multiple of the segment alignment;
+    /// ELFs linked at fixed addresses ignore it.
+    #[must_use]
+    pub const fn with_load_bias(mut self, bias: u64) -> Self {
+        self.load_bias = Some(bias);
+        self
+    }
+
+    /// Set the C dialect of the generated code.
+    ///
+    /// With `CDialect::Clang` (the default) and a GCC too old for
+    /// `musttail` as the compiler, portable code is emitted instead.
+    #[must_use]
+    pub const fn with_c_dialect(mut self, dialect: CDialect) -> Self {
+        self.c_dialect = dialect;
+        self
+    }
+
+    /// Decode the extensions of the ISA string `isa` (e.g.
+    /// `rv64imac_zicsr_zba`) instead of those in the ELF's
+    /// `.riscv.attributes`.
+    ///
+    /// Without either, every supported extension is decoded. An invalid
+    /// string fails the lift.
+    #[must_use]
+    pub fn with_isa(mut self, isa: impl Into<String>) -> Self {
+        self.isa = Some(isa.into());
+        self
+    }
+
+    /// Cap the heap at `size` bytes above the initial program break.
+    ///
+    /// The heap and stack are checked against the ELF's segments and
+    /// `memory_bits` at lift time; `brk` and `mmap` fail (ENOMEM) at the end
+    /// of the heap instead of running into the stack. The chosen layout is
+    /// in `CompileReport::memory_layout` and `Runner::memory_layout`.
+    #[must_use]
+    pub const fn with_heap(mut self, size: u64) -> Self {
+        self.heap_size = Some(size);
+        self
+    }
+
+    /// Reserve `size` bytes of stack below `__stack_top` (or the end of
+    /// memory); see `with_heap`.
+    #[must_use]
+    pub const fn with_stack_size(mut self, size: u64) -> Self {
+        self.stack_size = Some(size);
+        self
+    }
+
+    /// Serve anonymous `mmap`, `munmap` and `mremap` from a `size`-byte
+    /// arena carved from the top of the heap (Linux syscalls only).
+    ///
+    /// Mappings are page-aligned and zero-filled, freed ranges are reused,
+    /// and the allocator state lives in `RvState`, so it is suspended and
+    /// snapshotted with the guest. At most `rvr_state::MMAP_MAX_REGIONS`
+    /// disjoint mappings can be live. File-backed mappings and `MAP_FIXED`
+    /// outside the arena fail with EINVAL. Without a heap or stack size the
+    /// stack defaults to 8 MiB.
+    #[must_use]
+    pub const fn with_mmap_size(mut self, size: u64) -> Self {
+        self.mmap_size = Some(size);
+        self
+    }
+
+    /// Keep `size` bytes below a planned stack as a guard (default
+    /// `DEFAULT_STACK_GUARD`, 0 for none).
+    ///
+    /// The guard belongs to no region. With `Wrap` or `Bounds` addresses a
+    /// store into it fails the run with `RunError::StackOverflow`; with
+    /// `Unchecked` addresses the runner reports the overflow if the guest
+    /// traps with its stack pointer in the guard.
+    #[must_use]
+    pub const fn with_stack_guard(mut self, size: u64) -> Self {
+        self.stack_guard = size;
+        self
+    }
+
+    /// Set the vector register length in bits (default `DEFAULT_VLEN`).
+    ///
+    /// Guests built for a minimum VLEN (`zvl256b` and up) need at least that
+    /// much. Compiling vector code fails unless it is a power of two from 64
+    /// to `MAX_VLEN`.
+    #[must_use]
+    pub const fn with_vlen(mut self, vlen: u32) -> Self {
+        self.vlen = vlen;
+        self
+    }
+
+    /// Compress the segment data embedded in the library (C backend).
+    ///
+    /// Segments of at least the threshold are stored as LZ4 blocks, which
+    /// keeps the generated source and the library small for guests with
+    /// large data sections; `rv_init_memory` decodes them into guest
+    /// memory. Ignored with lazy segment init, which embeds no data.
+    #[must_use]
+    pub const fn with_compress_segments(mut self, compression: Option<Compression>) -> Self {
+        self.compress_segments = compression;
+        self
+    }
+
+    /// Set the custom CSRs guests use to talk to the host (C backend).
+    ///
+    /// Storage CSRs are backed by their `RvState::csrs` slot, which
+    /// `Runner::get_csr`/`set_csr` read and write. Hook CSRs call the hook
+    /// installed with `Runner::set_csr_hook` instead. Other CSRs keep their
+    /// current behavior.
+    #[must_use]
+    pub fn with_custom_csrs(mut self, csrs: Vec<CustomCsr>) -> Self {
+        self.custom_csrs = csrs;
+        self
+    }
+
+    /// Replace the guest function `symbol` with a hook.
+    ///
+    /// The function's entry does `kind` instead of running the body:
+    /// `HookKind::HostCall` runs the handler registered with
+    /// `Runner::register_hook` and returns to `ra` (C backend),
+    /// `HookKind::Replace` stops the guest with an exit code and
+    /// `HookKind::Nop` returns at once. CFG analysis does not descend into
+    /// the body. A later hook for the same symbol replaces an earlier one;
+    /// symbols the ELF does not define are ignored.
+    #[must_use]
+    pub fn with_function_hook(mut self, symbol: impl Into<String>, kind: HookKind) -> Self {
+        let hook = FunctionHook::new(symbol, kind);
+        self.function_hooks.retain(|h| h.symbol != hook.symbol);
+        self.function_hooks.push(hook);
+        self
+    }
+
+    /// Recompile with a block profile (C backend).
+    ///
+    /// `path` holds the counts of a run of a library compiled with the block
+    /// profile tracer (`rvr run --profile-counts`). Branches get hints towards
+    /// the hotter successor, blocks that were never entered are emitted as
+    /// cold functions, and the hot register slots go to the registers
+    /// accessed most at run time. A profile collected from a different ELF
+    /// is ignored with a warning.
+    ///
+    /// Branches inside a superblock fall through into the rest of it, which
+    /// has a count only if the profiled build was compiled without
+    /// superblocks (`with_superblock(false)`).
+    #[must_use]
+    pub fn with_profile(mut self, path: impl Into<PathBuf>) -> Self {
+        self.profile = Some(path.into());
+        self
+    }
+
+    /// Embed the golden trace at `path` and check against it (C backend).
+    ///
+    /// `path` holds the register checksums of a run of a library compiled
+    /// with the golden tracer (`--tracer golden`). The library recomputes
+    /// them at the same block entries and stops with
+    /// `ExitReason::GoldenMismatch` at the first that differs. Compile with
+    /// the block shaping options of the golden run, so the blocks match.
+    #[must_use]
+    pub fn with_embedded_golden(mut self, path: impl Into<PathBuf>) -> Self {
+        self.embedded_golden = Some(path.into());
+        self
+    }
+
+    /// Run the blocks starting at `pcs` in the block interpreter instead of
+    /// compiling them (C backend).
+    ///
+    /// The interpreter executes the original instructions and continues at
+    /// the next block through the dispatch table: slow, but a block the C
+    /// compiler chokes on no longer sinks the compile. Blocks of system,
+    /// CSR, vector or hooked instructions cannot be interpreted.
+    #[must_use]
+    pub fn with_interpret_blocks(mut self, pcs: impl IntoIterator<Item = u64>) -> Self {
+        self.interpret_blocks.extend(pcs);
+        self
+    }
+
+    /// Interpret the blocks of C part files that fail to compile, even at
+    /// the fallback optimization level, and build again (C backend).
+    #[must_use]
+    pub const fn with_interpret_failed_blocks(mut self, enabled: bool) -> Self {
+        self.flags.set_interpret_failed_blocks(enabled);
+        self
+    }
+
+    /// Treat `targets` as extra entry points when building the CFG.
+    ///
+    /// Every address gets a block that dynamic jumps can dispatch to. Feed
+    /// it the targets of unresolved dynamic jumps collected with
+    /// `rvr run --collect-jump-targets` (see [`JumpTargets`](crate::JumpTargets)).
+    #[must_use]
+    pub fn with_extra_entry_points(mut self, targets: impl IntoIterator<Item = u64>) -> Self {
+        self.extra_entry_points.extend(targets);
+        self
+    }
+
+    /// Enable or disable jump site reports (C backend; off by default).
+    ///
+    /// Each dynamic jump checks its dispatch lookup and, on a miss, stops
+    /// with [`RunError::UnresolvedJump`](crate::RunError::UnresolvedJump)
+    /// naming the jump site, target and source register. Costs a compare
+    /// per dynamic jump and keeps blocks ending in one from deduplicating;
+    /// without it a miss is a plain invalid-target trap.
+    #[must_use]
+    pub const fn with_report_jump_sites(mut self, enabled: bool) -> Self {
+        self.flags.set_report_jump_sites(enabled);
+        self
+    }
+
+    /// Lift only the functions and PC ranges `filter` selects (C backend).
+    ///
+    /// Jumps out of the selection become trap stubs, and the C is emitted
+    /// as one part file without segment data: it compiles standalone for
+    /// reading or `clang -S`, but the program cannot run. Only
+    /// [`lift_to_c_with_options`] applies it; compiling a library with a
+    /// filter is an error.
+    #[must_use]
+    pub fn with_filter(mut self, filter: FilterSpec) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Enable the artifact cache (default: disabled; the CLI enables it
+    /// unless `--no-cache` is given).
+    ///
+    /// A compile whose ELF, effective config and toolchain match an earlier
+    /// successful one copies its output instead of lifting and building
+    /// again. Builds with quarantined blocks are not cached.
+    #[must_use]
+    pub const fn with_cache(mut self, enabled: bool) -> Self {
+        self.flags.set_cache(enabled);
+        self
+    }
+
+    /// Keep the artifact cache in `dir` instead of the default directory.
+    #[must_use]
+    pub fn with_cache_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.cache_dir = Some(dir.into());
+        self
+    }
+
+    /// Also build a static archive `lib<name>.a` with an embedding header
+    /// `rv_embed.h` (C backend, off by default), for hosts that link the
+    /// program instead of loading it (see
+    /// [`Runner::load_embedded`](crate::Runner::load_embedded)). Disables
+    /// LTO.
+    #[must_use]
+    pub const fn with_static_archive(mut self, enabled: bool) -> Self {
+        self.flags.set_static_archive(enabled);
+        self
+    }
+
+    /// Prefix the generated global symbols with `prefix` (C backend), so
+    /// several programs can be linked into one host. Must be empty or a C
+    /// identifier.
+    #[must_use]
+    pub fn with_symbol_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.symbol_prefix = prefix.into();
+        self
+    }
+
+    /// Report progress to `callback` as the compile moves through its
+    /// phases (see [`CompileProgress`]). The callback may be called from
+    /// several threads.
+    #[must_use]
+    pub fn with_progress(mut self, callback: Box<dyn Fn(CompileProgress) + Send + Sync>) -> Self {
+        self.progress = Some(ProgressFn::new(callback));
+        self
+    }
+}
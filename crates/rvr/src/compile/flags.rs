@@ -0,0 +1,288 @@
+/// Toggle flags for compile options.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CompileFlags(u32);
+
+impl CompileFlags {
+    const ANALYSIS_MODE_AUTO: u32 = 1 << 0;
+    const HTIF: u32 = 1 << 1;
+    const HTIF_VERBOSE: u32 = 1 << 2;
+    const LINE_INFO: u32 = 1 << 3;
+    const EXPORT_FUNCTIONS: u32 = 1 << 4;
+    const QUIET: u32 = 1 << 5;
+    const PERF_MODE: u32 = 1 << 6;
+    const SUPERBLOCK: u32 = 1 << 7;
+    const DEDUP_BLOCKS: u32 = 1 << 8;
+    const STRICT_DECODE: u32 = 1 << 9;
+    const ARM64_LSE: u32 = 1 << 10;
+    const OPTIMIZE_IR: u32 = 1 << 11;
+    const LAZY_SEGMENT_INIT: u32 = 1 << 12;
+    const NATIVE_MEM_INTRINSICS: u32 = 1 << 13;
+    const CACHE: u32 = 1 << 14;
+    const GUEST_PC_MAP: u32 = 1 << 15;
+    const SIZE_REPORT: u32 = 1 << 16;
+    const DETECT_CODE_WRITES: u32 = 1 << 17;
+    const DISPATCH_TABLE_RELATIVE: u32 = 1 << 18;
+    const CHECK_CANCEL: u32 = 1 << 19;
+    const STATIC_ARCHIVE: u32 = 1 << 20;
+    const OUTLINE_COLD_PATHS: u32 = 1 << 21;
+    const REPORT_JUMP_SITES: u32 = 1 << 22;
+    const PREFETCH_DISPATCH: u32 = 1 << 23;
+    const DISPATCH_TABLE_HUGEPAGES: u32 = 1 << 24;
+    const DETERMINISTIC_CLOCK: u32 = 1 << 25;
+    const INTERPRET_FAILED_BLOCKS: u32 = 1 << 26;
+
+    const fn set_flag(&mut self, flag: u32, enabled: bool) {
+        if enabled {
+            self.0 |= flag;
+        } else {
+            self.0 &= !flag;
+        }
+    }
+
+    const fn has_flag(self, flag: u32) -> bool {
+        (self.0 & flag) != 0
+    }
+
+    #[must_use]
+    pub const fn analysis_mode_auto(self) -> bool {
+        self.has_flag(Self::ANALYSIS_MODE_AUTO)
+    }
+
+    pub const fn set_analysis_mode_auto(&mut self, enabled: bool) {
+        self.set_flag(Self::ANALYSIS_MODE_AUTO, enabled);
+    }
+
+    #[must_use]
+    pub const fn htif(self) -> bool {
+        self.has_flag(Self::HTIF)
+    }
+
+    pub const fn set_htif(&mut self, enabled: bool) {
+        self.set_flag(Self::HTIF, enabled);
+    }
+
+    #[must_use]
+    pub const fn htif_verbose(self) -> bool {
+        self.has_flag(Self::HTIF_VERBOSE)
+    }
+
+    pub const fn set_htif_verbose(&mut self, enabled: bool) {
+        self.set_flag(Self::HTIF_VERBOSE, enabled);
+    }
+
+    #[must_use]
+    pub const fn line_info(self) -> bool {
+        self.has_flag(Self::LINE_INFO)
+    }
+
+    pub const fn set_line_info(&mut self, enabled: bool) {
+        self.set_flag(Self::LINE_INFO, enabled);
+    }
+
+    #[must_use]
+    pub const fn export_functions(self) -> bool {
+        self.has_flag(Self::EXPORT_FUNCTIONS)
+    }
+
+    pub const fn set_export_functions(&mut self, enabled: bool) {
+        self.set_flag(Self::EXPORT_FUNCTIONS, enabled);
+    }
+
+    #[must_use]
+    pub const fn quiet(self) -> bool {
+        self.has_flag(Self::QUIET)
+    }
+
+    pub const fn set_quiet(&mut self, enabled: bool) {
+        self.set_flag(Self::QUIET, enabled);
+    }
+
+    #[must_use]
+    pub const fn perf_mode(self) -> bool {
+        self.has_flag(Self::PERF_MODE)
+    }
+
+    pub const fn set_perf_mode(&mut self, enabled: bool) {
+        self.set_flag(Self::PERF_MODE, enabled);
+    }
+
+    #[must_use]
+    pub const fn enable_superblock(self) -> bool {
+        self.has_flag(Self::SUPERBLOCK)
+    }
+
+    pub const fn set_enable_superblock(&mut self, enabled: bool) {
+        self.set_flag(Self::SUPERBLOCK, enabled);
+    }
+
+    #[must_use]
+    pub const fn dedup_blocks(self) -> bool {
+        self.has_flag(Self::DEDUP_BLOCKS)
+    }
+
+    pub const fn set_dedup_blocks(&mut self, enabled: bool) {
+        self.set_flag(Self::DEDUP_BLOCKS, enabled);
+    }
+
+    #[must_use]
+    pub const fn strict_decode(self) -> bool {
+        self.has_flag(Self::STRICT_DECODE)
+    }
+
+    pub const fn set_strict_decode(&mut self, enabled: bool) {
+        self.set_flag(Self::STRICT_DECODE, enabled);
+    }
+
+    #[must_use]
+    pub const fn arm64_lse(self) -> bool {
+        self.has_flag(Self::ARM64_LSE)
+    }
+
+    pub const fn set_arm64_lse(&mut self, enabled: bool) {
+        self.set_flag(Self::ARM64_LSE, enabled);
+    }
+
+    #[must_use]
+    pub const fn optimize_ir(self) -> bool {
+        self.has_flag(Self::OPTIMIZE_IR)
+    }
+
+    pub const fn set_optimize_ir(&mut self, enabled: bool) {
+        self.set_flag(Self::OPTIMIZE_IR, enabled);
+    }
+
+    #[must_use]
+    pub const fn lazy_segment_init(self) -> bool {
+        self.has_flag(Self::LAZY_SEGMENT_INIT)
+    }
+
+    pub const fn set_lazy_segment_init(&mut self, enabled: bool) {
+        self.set_flag(Self::LAZY_SEGMENT_INIT, enabled);
+    }
+
+    #[must_use]
+    pub const fn native_mem_intrinsics(self) -> bool {
+        self.has_flag(Self::NATIVE_MEM_INTRINSICS)
+    }
+
+    pub const fn set_native_mem_intrinsics(&mut self, enabled: bool) {
+        self.set_flag(Self::NATIVE_MEM_INTRINSICS, enabled);
+    }
+
+    #[must_use]
+    pub const fn cache(self) -> bool {
+        self.has_flag(Self::CACHE)
+    }
+
+    pub const fn set_cache(&mut self, enabled: bool) {
+        self.set_flag(Self::CACHE, enabled);
+    }
+
+    #[must_use]
+    pub const fn guest_pc_map(self) -> bool {
+        self.has_flag(Self::GUEST_PC_MAP)
+    }
+
+    pub const fn set_guest_pc_map(&mut self, enabled: bool) {
+        self.set_flag(Self::GUEST_PC_MAP, enabled);
+    }
+
+    #[must_use]
+    pub const fn size_report(self) -> bool {
+        self.has_flag(Self::SIZE_REPORT)
+    }
+
+    pub const fn set_size_report(&mut self, enabled: bool) {
+        self.set_flag(Self::SIZE_REPORT, enabled);
+    }
+
+    #[must_use]
+    pub const fn detect_code_writes(self) -> bool {
+        self.has_flag(Self::DETECT_CODE_WRITES)
+    }
+
+    pub const fn set_detect_code_writes(&mut self, enabled: bool) {
+        self.set_flag(Self::DETECT_CODE_WRITES, enabled);
+    }
+
+    #[must_use]
+    pub const fn dispatch_table_relative(self) -> bool {
+        self.has_flag(Self::DISPATCH_TABLE_RELATIVE)
+    }
+
+    pub const fn set_dispatch_table_relative(&mut self, enabled: bool) {
+        self.set_flag(Self::DISPATCH_TABLE_RELATIVE, enabled);
+    }
+
+    #[must_use]
+    pub const fn check_cancel(self) -> bool {
+        self.has_flag(Self::CHECK_CANCEL)
+    }
+
+    pub const fn set_check_cancel(&mut self, enabled: bool) {
+        self.set_flag(Self::CHECK_CANCEL, enabled);
+    }
+
+    #[must_use]
+    pub const fn static_archive(self) -> bool {
+        self.has_flag(Self::STATIC_ARCHIVE)
+    }
+
+    pub const fn set_static_archive(&mut self, enabled: bool) {
+        self.set_flag(Self::STATIC_ARCHIVE, enabled);
+    }
+
+    #[must_use]
+    pub const fn outline_cold_paths(self) -> bool {
+        self.has_flag(Self::OUTLINE_COLD_PATHS)
+    }
+
+    pub const fn set_outline_cold_paths(&mut self, enabled: bool) {
+        self.set_flag(Self::OUTLINE_COLD_PATHS, enabled);
+    }
+
+    #[must_use]
+    pub const fn report_jump_sites(self) -> bool {
+        self.has_flag(Self::REPORT_JUMP_SITES)
+    }
+
+    pub const fn set_report_jump_sites(&mut self, enabled: bool) {
+        self.set_flag(Self::REPORT_JUMP_SITES, enabled);
+    }
+
+    #[must_use]
+    pub const fn prefetch_dispatch(self) -> bool {
+        self.has_flag(Self::PREFETCH_DISPATCH)
+    }
+
+    pub const fn set_prefetch_dispatch(&mut self, enabled: bool) {
+        self.set_flag(Self::PREFETCH_DISPATCH, enabled);
+    }
+
+    #[must_use]
+    pub const fn dispatch_table_hugepages(self) -> bool {
+        self.has_flag(Self::DISPATCH_TABLE_HUGEPAGES)
+    }
+
+    pub const fn set_dispatch_table_hugepages(&mut self, enabled: bool) {
+        self.set_flag(Self::DISPATCH_TABLE_HUGEPAGES, enabled);
+    }
+
+    #[must_use]
+    pub const fn deterministic_clock(self) -> bool {
+        self.has_flag(Self::DETERMINISTIC_CLOCK)
+    }
+
+    pub const fn set_deterministic_clock(&mut self, enabled: bool) {
+        self.set_flag(Self::DETERMINISTIC_CLOCK, enabled);
+    }
+
+    #[must_use]
+    pub const fn interpret_failed_blocks(self) -> bool {
+        self.has_flag(Self::INTERPRET_FAILED_BLOCKS)
+    }
+
+    pub const fn set_interpret_failed_blocks(&mut self, enabled: bool) {
+        self.set_flag(Self::INTERPRET_FAILED_BLOCKS, enabled);
+    }
+}
@@ -0,0 +1,649 @@
+use std::path::{Path, PathBuf};
+
+use rvr_cfg::{DEFAULT_SUPERBLOCK_DEPTH, DEFAULT_SUPERBLOCK_MAX_INSTRS};
+use rvr_elf::ElfImage;
+use rvr_emit::c::{CompilerIdentity, DedupStats, GCC_MUSTTAIL_VERSION, TracerConfig};
+use rvr_emit::{
+    AddressMode, AnalysisMode, Backend, CDialect, Compiler, CompilerLauncher, Compression,
+    CustomCsr, DEFAULT_FALLBACK_OPT_LEVEL, DEFAULT_HTIF_POLL_LIMIT, DEFAULT_STACK_GUARD,
+    DEFAULT_TARGET_PART_COST, DEFAULT_VLEN, DispatchMode, EmitConfig, FixedAddressConfig,
+    FunctionHook, HotRegsMode, InstretMode, LayoutProfile, LiftErrorMode, MemoryLayout,
+    SuspendGranularity, SyscallMode,
+};
+use rvr_isa::syscalls::{BareMetalConfig, SyscallPolicy};
+use rvr_isa::{LrScModel, Rv32, Rv64, Xlen};
+use tracing::{info, warn};
+
+use crate::build::{PartFailure, PartTime};
+use crate::cache::ArtifactCache;
+use crate::layout::image_layout;
+use crate::programs::is_valid_name;
+use crate::progress::ProgressFn;
+use crate::quarantine::LiftFailure;
+use crate::size_report::SizeReport;
+use crate::{Error, Explanation, FilterSpec, Recompiler, Result};
+
+mod builder;
+mod flags;
+
+pub use flags::CompileFlags;
+
+/// Options for compile/lift operations.
+#[derive(Clone, Debug)]
+pub struct CompileOptions {
+    /// Code generation backend.
+    pub backend: Backend,
+    /// Analysis mode (full CFG or linear scan).
+    pub analysis_mode: AnalysisMode,
+    /// Address translation mode.
+    pub address_mode: AddressMode,
+    /// Dispatch table layout (C backend).
+    pub dispatch_mode: DispatchMode,
+    /// Global or per-function hot register sets (C backend).
+    pub hot_regs_mode: HotRegsMode,
+    /// Instruction retirement mode.
+    pub instret_mode: InstretMode,
+    /// Where `InstretMode::Suspend` checks the instret limit.
+    pub suspend_granularity: SuspendGranularity,
+    /// Number of parallel compile jobs (0 = auto-detect based on CPU count).
+    pub jobs: usize,
+    /// Threads for CFG analysis and lifting (0 = rayon default).
+    pub analysis_jobs: usize,
+    /// Tracer configuration.
+    pub tracer_config: TracerConfig,
+    /// Syscall handling mode.
+    pub syscall_mode: SyscallMode,
+    /// Capability policy for Linux-mode syscalls (optional).
+    pub syscall_policy: Option<SyscallPolicy>,
+    /// Bare-metal ECALLs mapped to built-in actions.
+    pub baremetal_ecalls: Option<BareMetalConfig>,
+    /// C compiler to use.
+    pub compiler: Compiler,
+    /// Command prefixed to compile rules, such as a compiler cache (C backend).
+    pub compiler_launcher: CompilerLauncher,
+    /// Target estimated compile cost per C part file (C backend).
+    pub target_part_cost: usize,
+    /// Optimization level C part files that fail to compile are retried at
+    /// (C backend; `None` to not retry).
+    pub fallback_opt_level: Option<u8>,
+    /// C dialect of the generated code (C backend).
+    pub c_dialect: CDialect,
+    /// Fixed addresses for state and memory (optional).
+    /// When set, state/memory are accessed via compile-time constant addresses.
+    pub fixed_addresses: Option<FixedAddressConfig>,
+    /// What to do when a block fails to lift.
+    pub on_lift_error: LiftErrorMode,
+    /// Address-space layout to check the ELF against (optional).
+    pub layout: Option<LayoutProfile>,
+    /// Load bias for position-independent ELFs (optional).
+    pub load_bias: Option<u64>,
+    /// ISA string overriding the ELF's arch attribute (optional).
+    pub isa: Option<String>,
+    /// Heap size in bytes (optional).
+    pub heap_size: Option<u64>,
+    /// Stack size in bytes (optional).
+    pub stack_size: Option<u64>,
+    /// Anonymous mmap arena size in bytes (optional).
+    pub mmap_size: Option<u64>,
+    /// Guard bytes below a planned stack.
+    pub stack_guard: u64,
+    /// Vector register length in bits.
+    pub vlen: u32,
+    /// Compression of embedded segment data (C backend, optional).
+    pub compress_segments: Option<Compression>,
+    /// LR/SC reservation model.
+    pub lrsc_model: LrScModel,
+    /// Unchanged `fromhost` polls before an HTIF guest stops (0 disables
+    /// the watchdog).
+    pub htif_poll_limit: u64,
+    /// Maximum instructions per emitted block.
+    pub superblock_max_instrs: usize,
+    /// Maximum basic blocks merged into one emitted block.
+    pub superblock_max_blocks: usize,
+    /// Custom CSRs (C backend).
+    pub custom_csrs: Vec<CustomCsr>,
+    /// Guest functions replaced by hooks.
+    pub function_hooks: Vec<FunctionHook>,
+    /// Block profile to recompile with (C backend, optional).
+    pub profile: Option<PathBuf>,
+    /// Golden trace to embed and check against (C backend, optional).
+    pub embedded_golden: Option<PathBuf>,
+    /// Blocks run by the block interpreter instead of being compiled, by
+    /// start PC (C backend).
+    pub interpret_blocks: Vec<u64>,
+    /// Guest addresses lifted as extra entry points, such as dynamic jump
+    /// targets the static analysis missed.
+    pub extra_entry_points: Vec<u64>,
+    /// Functions and PC ranges a lift is restricted to (C backend,
+    /// optional).
+    pub filter: Option<FilterSpec>,
+    /// Artifact cache directory (optional; `ArtifactCache::default_dir` if
+    /// unset).
+    pub cache_dir: Option<PathBuf>,
+    /// Prefix of the generated global symbols (C backend, empty by default).
+    pub symbol_prefix: String,
+    /// Callback receiving compile progress (optional).
+    pub progress: Option<ProgressFn>,
+    /// Compile-time flags for toggles and optional features.
+    pub flags: CompileFlags,
+}
+
+/// Result of a successful compile.
+#[derive(Clone, Debug)]
+pub struct CompileReport {
+    /// Path to the compiled shared library.
+    pub library: PathBuf,
+    /// Blocks replaced by trap stubs under `LiftErrorMode::Quarantine`.
+    pub quarantined: Vec<LiftFailure>,
+    /// Identical blocks emitted as aliases (`with_dedup_blocks`).
+    pub dedup: DedupStats,
+    /// Heap, stack and mmap arena placement (`with_heap`/`with_stack_size`/
+    /// `with_mmap_size`).
+    pub memory_layout: Option<MemoryLayout>,
+    /// Code size per guest function (`with_size_report`).
+    pub size_report: Option<SizeReport>,
+    /// Compile time of each C file this build compiled, slowest first
+    /// (C backend; empty for cached builds).
+    pub part_times: Vec<PartTime>,
+    /// C part files that failed to compile and were built at the fallback
+    /// optimization level (`with_fallback_opt_level`).
+    pub retried_parts: Vec<PartFailure>,
+    /// The compiler the library was built with.
+    pub compiler: CompilerIdentity,
+}
+
+impl Default for CompileOptions {
+    fn default() -> Self {
+        let mut flags = CompileFlags::default();
+        flags.set_analysis_mode_auto(true);
+        flags.set_line_info(true);
+        flags.set_enable_superblock(true);
+        flags.set_optimize_ir(true);
+        flags.set_detect_code_writes(true);
+        Self {
+            backend: Backend::default(),
+            analysis_mode: AnalysisMode::default(),
+            address_mode: AddressMode::default(),
+            dispatch_mode: DispatchMode::default(),
+            hot_regs_mode: HotRegsMode::default(),
+            instret_mode: InstretMode::default(),
+            suspend_granularity: SuspendGranularity::default(),
+            jobs: 0,
+            analysis_jobs: 0,
+            tracer_config: TracerConfig::default(),
+            syscall_mode: SyscallMode::default(),
+            syscall_policy: None,
+            baremetal_ecalls: None,
+            compiler: Compiler::default(),
+            compiler_launcher: CompilerLauncher::default(),
+            target_part_cost: DEFAULT_TARGET_PART_COST,
+            fallback_opt_level: Some(DEFAULT_FALLBACK_OPT_LEVEL),
+            c_dialect: CDialect::default(),
+            fixed_addresses: None,
+            on_lift_error: LiftErrorMode::default(),
+            layout: None,
+            load_bias: None,
+            isa: None,
+            heap_size: None,
+            stack_size: None,
+            mmap_size: None,
+            stack_guard: DEFAULT_STACK_GUARD,
+            vlen: DEFAULT_VLEN,
+            compress_segments: None,
+            lrsc_model: LrScModel::default(),
+            htif_poll_limit: DEFAULT_HTIF_POLL_LIMIT,
+            superblock_max_instrs: DEFAULT_SUPERBLOCK_MAX_INSTRS,
+            superblock_max_blocks: DEFAULT_SUPERBLOCK_DEPTH,
+            custom_csrs: Vec::new(),
+            function_hooks: Vec::new(),
+            profile: None,
+            embedded_golden: None,
+            interpret_blocks: Vec::new(),
+            extra_entry_points: Vec::new(),
+            filter: None,
+            cache_dir: None,
+            symbol_prefix: String::new(),
+            progress: None,
+            flags,
+        }
+    }
+}
+
+impl CompileOptions {
+    /// Create default options.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply options to `EmitConfig`.
+    fn apply<X: Xlen>(&self, config: &mut EmitConfig<X>) {
+        config.backend = self.backend;
+        config.analysis_mode = if self.flags.analysis_mode_auto() {
+            match self.backend {
+                Backend::C => AnalysisMode::FullCfg,
+                _ => AnalysisMode::Basic,
+            }
+        } else {
+            self.analysis_mode
+        };
+        config.address_mode = self.address_mode;
+        config.dispatch_mode = self.dispatch_mode;
+        config.hot_regs_mode = self.hot_regs_mode;
+        config
+            .flags
+            .set_dispatch_table_relative(self.flags.dispatch_table_relative());
+        config
+            .flags
+            .set_prefetch_dispatch(self.flags.prefetch_dispatch());
+        config
+            .flags
+            .set_dispatch_table_hugepages(self.flags.dispatch_table_hugepages());
+        config
+            .flags
+            .set_deterministic_clock(self.flags.deterministic_clock());
+        config.flags.set_htif_enabled(self.flags.htif());
+        config.flags.set_htif_verbose(self.flags.htif_verbose());
+        config.htif_poll_limit = self.htif_poll_limit;
+        config.embedded_golden.clone_from(&self.embedded_golden);
+        config.interpret_blocks = self.interpret_blocks.iter().copied().collect();
+        config
+            .flags
+            .set_interpret_failed_blocks(self.flags.interpret_failed_blocks());
+        config.flags.set_emit_line_info(self.flags.line_info());
+        config.instret_mode = self.instret_mode;
+        config.suspend_granularity = self.suspend_granularity;
+        config.tracer_config = self.tracer_config.clone();
+        config.compiler = self.compiler.clone();
+        config.compiler_launcher = self.compiler_launcher.clone();
+        config.target_part_cost = self.target_part_cost;
+        config.fallback_opt_level = self.fallback_opt_level;
+        config.c_dialect = resolve_c_dialect(self.c_dialect, self.backend, &self.compiler);
+        config.syscall_mode = self.syscall_mode;
+        config.syscall_policy.clone_from(&self.syscall_policy);
+        config.baremetal_ecalls.clone_from(&self.baremetal_ecalls);
+        config.fixed_addresses = self.fixed_addresses;
+        config.perf_mode = self.flags.perf_mode();
+        config.enable_superblock = self.flags.enable_superblock();
+        config.superblock_max_instrs = self.superblock_max_instrs;
+        config.superblock_max_blocks = self.superblock_max_blocks;
+        config.custom_csrs.clone_from(&self.custom_csrs);
+        config.function_hooks.clone_from(&self.function_hooks);
+        config
+            .flags
+            .set_optimize_ir(self.flags.optimize_ir() && self.tracer_config.is_none());
+        config.flags.set_dedup_blocks(self.flags.dedup_blocks());
+        config.flags.set_strict_decode(self.flags.strict_decode());
+        config.flags.set_arm64_use_lse(self.flags.arm64_lse());
+        config
+            .flags
+            .set_lazy_segment_init(self.flags.lazy_segment_init());
+        config
+            .flags
+            .set_native_mem_intrinsics(self.flags.native_mem_intrinsics());
+        config
+            .flags
+            .set_emit_guest_pc_map(self.flags.guest_pc_map());
+        config
+            .flags
+            .set_detect_code_writes(self.flags.detect_code_writes());
+        config.flags.set_check_cancel(self.flags.check_cancel());
+        config
+            .flags
+            .set_outline_cold_paths(self.flags.outline_cold_paths());
+        config
+            .flags
+            .set_report_jump_sites(self.flags.report_jump_sites());
+        config.flags.set_static_archive(self.flags.static_archive());
+        config.symbol_prefix.clone_from(&self.symbol_prefix);
+        config.on_lift_error = self.on_lift_error;
+        config.analysis_jobs = self.analysis_jobs;
+        config.layout = self.layout;
+        config.load_bias = self.load_bias;
+        config.isa.clone_from(&self.isa);
+        config.heap_size = self.heap_size;
+        config.stack_size = self.stack_size;
+        config.mmap_size = self.mmap_size;
+        config.stack_guard = self.stack_guard;
+        config.vlen = self.vlen;
+        config.compress_segments = self.compress_segments;
+        config.lrsc_model = self.lrsc_model;
+        if let Some(layout) = self.layout {
+            config.memory_bits = layout.spec().memory_bits;
+        }
+        if self.flags.perf_mode() {
+            config.instret_mode = InstretMode::Off;
+        }
+        // Re-compute hot registers based on backend (x86 has different slot count than C)
+        config.reinit_hot_regs_for_backend();
+    }
+
+    /// Check if line info is enabled.
+    #[must_use]
+    pub const fn has_line_info(&self) -> bool {
+        self.flags.line_info()
+    }
+
+    #[must_use]
+    pub const fn quiet(&self) -> bool {
+        self.flags.quiet()
+    }
+
+    #[must_use]
+    pub const fn export_functions(&self) -> bool {
+        self.flags.export_functions()
+    }
+
+    #[must_use]
+    pub const fn cache(&self) -> bool {
+        self.flags.cache()
+    }
+
+    #[must_use]
+    pub const fn size_report(&self) -> bool {
+        self.flags.size_report()
+    }
+
+    /// Artifact cache to use, if enabled and a directory is known.
+    fn artifact_cache(&self) -> Option<ArtifactCache> {
+        if !self.cache() || self.size_report() {
+            return None;
+        }
+        self.cache_dir
+            .clone()
+            .map(ArtifactCache::new)
+            .or_else(ArtifactCache::from_env)
+    }
+}
+
+/// The C dialect to emit for `compiler`.
+///
+/// Clang code needs `musttail`, so GCC older than [`GCC_MUSTTAIL_VERSION`]
+/// gets portable code instead, with a warning.
+fn resolve_c_dialect(dialect: CDialect, backend: Backend, compiler: &Compiler) -> CDialect {
+    if dialect != CDialect::Clang || backend != Backend::C || compiler.is_clang() {
+        return dialect;
+    }
+    match compiler.probe() {
+        Ok(identity) if identity.is_gcc_older_than(GCC_MUSTTAIL_VERSION) => {
+            warn!(
+                compiler = %compiler,
+                version = %identity,
+                "GCC older than {GCC_MUSTTAIL_VERSION} cannot build clang C, emitting portable C"
+            );
+            CDialect::Portable
+        }
+        _ => dialect,
+    }
+}
+
+/// Compile an ELF file, auto-detecting XLEN from the ELF header.
+///
+/// # Errors
+/// Returns an error if the ELF cannot be read or compilation fails.
+pub fn compile(elf_path: &Path, output_dir: &Path) -> Result<PathBuf> {
+    let options = CompileOptions::default();
+    compile_with_options(elf_path, output_dir, &options)
+}
+
+/// Compile an ELF file with options, auto-detecting XLEN from the ELF header.
+///
+/// # Errors
+/// Returns an error if the ELF cannot be read or compilation fails.
+pub fn compile_with_options(
+    elf_path: &Path,
+    output_dir: &Path,
+    options: &CompileOptions,
+) -> Result<PathBuf> {
+    compile_with_report(elf_path, output_dir, options).map(|report| report.library)
+}
+
+/// Compile an ELF file with options, reporting quarantined blocks.
+///
+/// Goes through the artifact cache unless disabled (see
+/// `CompileOptions::with_cache`).
+///
+/// # Errors
+/// Returns an error if the ELF cannot be read or compilation fails.
+pub fn compile_with_report(
+    elf_path: &Path,
+    output_dir: &Path,
+    options: &CompileOptions,
+) -> Result<CompileReport> {
+    check_unfiltered(options)?;
+    let data = std::fs::read(elf_path)?;
+    let xlen = rvr_elf::get_elf_xlen(&data)?;
+
+    dispatch_by_xlen(
+        xlen,
+        || compile_cached::<Rv32>(elf_path, &data, output_dir, options),
+        || compile_cached::<Rv64>(elf_path, &data, output_dir, options),
+    )
+}
+
+/// Restore the build from the artifact cache, or compile and store it.
+fn compile_cached<X: Xlen>(
+    elf_path: &Path,
+    data: &[u8],
+    output_dir: &Path,
+    options: &CompileOptions,
+) -> Result<CompileReport> {
+    if !options.symbol_prefix.is_empty() && !is_valid_name(&options.symbol_prefix) {
+        return Err(Error::InvalidSymbolPrefix(options.symbol_prefix.clone()));
+    }
+    let mut config = EmitConfig::<X>::default();
+    options.apply(&mut config);
+    let recompiler = Recompiler::<X>::new(config)
+        .with_quiet(options.quiet())
+        .with_export_functions(options.export_functions())
+        .with_profile(options.profile.clone())
+        .with_extra_entry_points(options.extra_entry_points.clone())
+        .with_size_report(options.size_report())
+        .with_progress(options.progress.clone());
+    let config = recompiler.config();
+
+    let cache = options.artifact_cache().and_then(|cache| {
+        let key = ArtifactCache::key(
+            config,
+            data,
+            output_dir,
+            options.profile.as_deref(),
+            &options.extra_entry_points,
+        )?;
+        Some((cache, key))
+    });
+    if let Some((cache, key)) = &cache {
+        match cache.restore(key, output_dir) {
+            Ok(Some(build)) => {
+                info!(key = %key, "using cached build");
+                let image = ElfImage::<X>::parse_with_load_bias(data, config.load_bias)?;
+                return Ok(CompileReport {
+                    library: build.library,
+                    quarantined: Vec::new(),
+                    dedup: build.dedup,
+                    memory_layout: MemoryLayout::plan(
+                        &image_layout(&image),
+                        config.memory_bits,
+                        config.heap_size,
+                        config.stack_size,
+                        config.mmap_size,
+                        config.stack_guard,
+                    )?,
+                    size_report: None,
+                    part_times: Vec::new(),
+                    retried_parts: Vec::new(),
+                    // Probed for the cache key
+                    compiler: config.compiler.probe()?,
+                });
+            }
+            Ok(None) => {}
+            Err(e) => warn!(error = %e, key = %key, "cannot restore cached build, rebuilding"),
+        }
+    }
+
+    let report = recompiler.compile_with_report(elf_path, output_dir, options.jobs)?;
+    // A quarantined build is kept out so that its failures are reported again
+    if let Some((cache, key)) = &cache
+        && report.quarantined.is_empty()
+        && let Err(e) = cache.store(key, output_dir, &report.library, report.dedup)
+    {
+        warn!(error = %e, dir = %cache.dir().display(), "cannot store build in the artifact cache");
+    }
+    Ok(report)
+}
+
+/// Compile an ELF file once per address mode, sharing a single lift.
+///
+/// `options.address_mode` is ignored; each entry of `modes` is emitted into
+/// `output_root/<mode>`. Returns the library paths in the order of `modes`.
+///
+/// # Errors
+/// Returns an error if the ELF cannot be read or any compilation fails.
+pub fn compile_address_modes(
+    elf_path: &Path,
+    output_root: &Path,
+    options: &CompileOptions,
+    modes: &[AddressMode],
+) -> Result<Vec<PathBuf>> {
+    check_unfiltered(options)?;
+    let data = std::fs::read(elf_path)?;
+    let xlen = rvr_elf::get_elf_xlen(&data)?;
+
+    dispatch_by_xlen(
+        xlen,
+        || {
+            let mut config = EmitConfig::<Rv32>::default();
+            options.apply(&mut config);
+            let recompiler = Recompiler::<Rv32>::new(config)
+                .with_quiet(options.quiet())
+                .with_export_functions(options.export_functions())
+                .with_profile(options.profile.clone())
+                .with_extra_entry_points(options.extra_entry_points.clone())
+                .with_progress(options.progress.clone());
+            recompiler.compile_address_modes(elf_path, output_root, modes, options.jobs)
+        },
+        || {
+            let mut config = EmitConfig::<Rv64>::default();
+            options.apply(&mut config);
+            let recompiler = Recompiler::<Rv64>::new(config)
+                .with_quiet(options.quiet())
+                .with_export_functions(options.export_functions())
+                .with_profile(options.profile.clone())
+                .with_extra_entry_points(options.extra_entry_points.clone())
+                .with_progress(options.progress.clone());
+            recompiler.compile_address_modes(elf_path, output_root, modes, options.jobs)
+        },
+    )
+}
+
+/// Fail for options with a lift filter: filtered programs do not run.
+fn check_unfiltered(options: &CompileOptions) -> Result<()> {
+    if options.filter.is_some() {
+        return Err(Error::InvalidFilter(
+            "filtered lifts are not runnable, lift them instead".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Lift an ELF file to C source code, auto-detecting XLEN.
+///
+/// # Errors
+/// Returns an error if the ELF cannot be read or lifting fails.
+pub fn lift_to_c(elf_path: &Path, output_dir: &Path) -> Result<PathBuf> {
+    let options = CompileOptions::default();
+    lift_to_c_with_options(elf_path, output_dir, &options)
+}
+
+/// Lift an ELF file to C source code with options, auto-detecting XLEN.
+///
+/// # Errors
+/// Returns an error if the ELF cannot be read or lifting fails.
+pub fn lift_to_c_with_options(
+    elf_path: &Path,
+    output_dir: &Path,
+    options: &CompileOptions,
+) -> Result<PathBuf> {
+    let data = std::fs::read(elf_path)?;
+    let xlen = rvr_elf::get_elf_xlen(&data)?;
+
+    dispatch_by_xlen(
+        xlen,
+        || {
+            let mut config = EmitConfig::<Rv32>::default();
+            options.apply(&mut config);
+            let recompiler = Recompiler::<Rv32>::new(config)
+                .with_export_functions(options.export_functions())
+                .with_profile(options.profile.clone())
+                .with_extra_entry_points(options.extra_entry_points.clone())
+                .with_filter(options.filter.clone())
+                .with_size_report(options.size_report());
+            recompiler.lift(elf_path, output_dir)
+        },
+        || {
+            let mut config = EmitConfig::<Rv64>::default();
+            options.apply(&mut config);
+            let recompiler = Recompiler::<Rv64>::new(config)
+                .with_export_functions(options.export_functions())
+                .with_profile(options.profile.clone())
+                .with_extra_entry_points(options.extra_entry_points.clone())
+                .with_filter(options.filter.clone())
+                .with_size_report(options.size_report());
+            recompiler.lift(elf_path, output_dir)
+        },
+    )
+}
+
+/// Explain how the block containing `pc` is lifted and emitted, auto-detecting XLEN.
+///
+/// # Errors
+/// Returns an error if the ELF cannot be read, lifting fails, or no lifted
+/// block contains `pc`.
+pub fn explain_with_options(
+    elf_path: &Path,
+    pc: u64,
+    options: &CompileOptions,
+) -> Result<Explanation> {
+    let data = std::fs::read(elf_path)?;
+    let xlen = rvr_elf::get_elf_xlen(&data)?;
+
+    dispatch_by_xlen(
+        xlen,
+        || {
+            let mut config = EmitConfig::<Rv32>::default();
+            options.apply(&mut config);
+            let recompiler = Recompiler::<Rv32>::new(config)
+                .with_export_functions(options.export_functions())
+                .with_profile(options.profile.clone())
+                .with_extra_entry_points(options.extra_entry_points.clone());
+            recompiler.explain(elf_path, pc)
+        },
+        || {
+            let mut config = EmitConfig::<Rv64>::default();
+            options.apply(&mut config);
+            let recompiler = Recompiler::<Rv64>::new(config)
+                .with_export_functions(options.export_functions())
+                .with_profile(options.profile.clone())
+                .with_extra_entry_points(options.extra_entry_points.clone());
+            recompiler.explain(elf_path, pc)
+        },
+    )
+}
+
+fn dispatch_by_xlen<R>(
+    xlen: u8,
+    rv32: impl FnOnce() -> Result<R>,
+    rv64: impl FnOnce() -> Result<R>,
+) -> Result<R> {
+    match xlen {
+        32 => rv32(),
+        64 => rv64(),
+        _ => {
+            warn!(xlen = xlen, "unsupported XLEN (expected 32 or 64)");
+            Err(Error::XlenMismatch {
+                expected: 32,
+                actual: xlen,
+            })
+        }
+    }
+}
@@ -0,0 +1,339 @@
+//! Guest source coverage and lcov export.
+//!
+//! Turns the per-instruction flags of the coverage tracer
+//! ([`Runner::coverage`](crate::Runner::coverage)) into line and branch
+//! coverage of the guest source, and writes it as an lcov tracefile
+//! (`DA`/`BRDA` records) for genhtml.
+//!
+//! Flags are kept per instruction, so every source line merged into a
+//! superblock or tail-duplicated block is covered on its own.
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::path::Path;
+
+use rvr_elf::{DebugInfo, ElfImage};
+use rvr_emit::c::{COVERAGE_EXECUTED, COVERAGE_NOT_TAKEN, COVERAGE_TAKEN};
+use rvr_ir::SourceLoc;
+use rvr_isa::{Rv32, Rv64, Xlen};
+use tracing::warn;
+
+use crate::inspect::code_segments;
+use crate::{Recompiler, Result};
+
+/// Smallest instruction size; undecodable bytes are skipped in these steps.
+const MIN_INSTR_SIZE: usize = 2;
+
+/// One guest instruction that coverage can be reported for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct CodeInstr {
+    pc: u64,
+    is_branch: bool,
+}
+
+/// Coverage of one conditional branch.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BranchCoverage {
+    /// Guest PC of the branch.
+    pub pc: u64,
+    /// The branch executed at least once.
+    pub executed: bool,
+    /// The branch was taken at least once.
+    pub taken: bool,
+    /// The branch fell through at least once.
+    pub not_taken: bool,
+}
+
+/// Line and branch coverage of one source file.
+#[derive(Clone, Debug, Default)]
+struct FileCoverage {
+    /// Whether any instruction of each line executed.
+    lines: BTreeMap<u32, bool>,
+    /// Branches of each line, in PC order.
+    branches: BTreeMap<u32, Vec<BranchCoverage>>,
+}
+
+/// Line and branch coverage of the guest source.
+#[derive(Clone, Debug, Default)]
+pub struct Coverage {
+    files: BTreeMap<String, FileCoverage>,
+}
+
+impl Coverage {
+    /// Map `(pc, flags)` pairs onto the source of the ELF at `elf_path`.
+    ///
+    /// Every instruction of the ELF's code segments with line info is
+    /// reported, so lines and branches that never ran show up as uncovered.
+    /// `load_bias` places a position-independent ELF as the runner did (see
+    /// [`Runner::load_bias`](crate::Runner::load_bias)). Line info comes
+    /// from `addr2line_cmd`; if it fails, a warning is logged and the
+    /// report is empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the ELF cannot be read or parsed, or its ISA
+    /// attribute names no extension rvr decodes.
+    pub fn resolve(
+        elf_path: &Path,
+        load_bias: Option<u64>,
+        flags: &[(u64, u8)],
+        addr2line_cmd: &str,
+    ) -> Result<Self> {
+        let data = std::fs::read(elf_path)?;
+        if rvr_elf::get_elf_xlen(&data)? == Rv32::VALUE {
+            let image = ElfImage::<Rv32>::parse_with_load_bias(&data, load_bias)?;
+            Self::resolve_image(&image, elf_path, flags, addr2line_cmd)
+        } else {
+            let image = ElfImage::<Rv64>::parse_with_load_bias(&data, load_bias)?;
+            Self::resolve_image(&image, elf_path, flags, addr2line_cmd)
+        }
+    }
+
+    /// Like [`resolve`](Self::resolve), with source locations from
+    /// `location` (by guest PC) instead of the ELF's debug info.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the ELF cannot be read or parsed, or its ISA
+    /// attribute names no extension rvr decodes.
+    pub fn resolve_with_locations(
+        elf_path: &Path,
+        load_bias: Option<u64>,
+        flags: &[(u64, u8)],
+        location: impl Fn(u64) -> Option<SourceLoc>,
+    ) -> Result<Self> {
+        let data = std::fs::read(elf_path)?;
+        let instrs = if rvr_elf::get_elf_xlen(&data)? == Rv32::VALUE {
+            code_instrs(&ElfImage::<Rv32>::parse_with_load_bias(&data, load_bias)?)?
+        } else {
+            code_instrs(&ElfImage::<Rv64>::parse_with_load_bias(&data, load_bias)?)?
+        };
+        Ok(Self::from_flags(&instrs, flags, location))
+    }
+
+    fn resolve_image<X: Xlen>(
+        image: &ElfImage<X>,
+        elf_path: &Path,
+        flags: &[(u64, u8)],
+        addr2line_cmd: &str,
+    ) -> Result<Self> {
+        let instrs = code_instrs(image)?;
+        // addr2line sees the unrelocated file
+        let bias = image.load_bias;
+        let addresses: Vec<u64> = instrs.iter().map(|instr| instr.pc - bias).collect();
+        let debug_info = DebugInfo::load(&elf_path.to_string_lossy(), &addresses, addr2line_cmd)
+            .unwrap_or_else(|e| {
+                warn!(error = %e, "failed to load debug info, coverage has no lines");
+                DebugInfo::new()
+            });
+        Ok(Self::from_flags(&instrs, flags, |pc| {
+            debug_info.get(pc - bias).cloned()
+        }))
+    }
+
+    /// Build coverage from the code's instructions, their flags and a
+    /// location lookup. Instructions without a valid location are dropped.
+    fn from_flags(
+        instrs: &[CodeInstr],
+        flags: &[(u64, u8)],
+        location: impl Fn(u64) -> Option<SourceLoc>,
+    ) -> Self {
+        let flags: BTreeMap<u64, u8> = flags.iter().copied().collect();
+        let mut files: BTreeMap<String, FileCoverage> = BTreeMap::new();
+        for instr in instrs {
+            let Some(loc) = location(instr.pc).filter(SourceLoc::is_valid) else {
+                continue;
+            };
+            let flag = flags.get(&instr.pc).copied().unwrap_or(0);
+            let executed = flag & COVERAGE_EXECUTED != 0;
+            let file = files.entry(loc.file).or_default();
+            *file.lines.entry(loc.line).or_default() |= executed;
+            if instr.is_branch {
+                file.branches
+                    .entry(loc.line)
+                    .or_default()
+                    .push(BranchCoverage {
+                        pc: instr.pc,
+                        executed,
+                        taken: flag & COVERAGE_TAKEN != 0,
+                        not_taken: flag & COVERAGE_NOT_TAKEN != 0,
+                    });
+            }
+        }
+        Self { files }
+    }
+
+    /// Source files with coverage, in name order.
+    pub fn files(&self) -> impl Iterator<Item = &str> {
+        self.files.keys().map(String::as_str)
+    }
+
+    /// Whether `line` of `file` executed (`None` if it has no code).
+    #[must_use]
+    pub fn line_hit(&self, file: &str, line: u32) -> Option<bool> {
+        self.files.get(file)?.lines.get(&line).copied()
+    }
+
+    /// Branches on `line` of `file`, in PC order.
+    #[must_use]
+    pub fn branches(&self, file: &str, line: u32) -> &[BranchCoverage] {
+        self.files
+            .get(file)
+            .and_then(|file| file.branches.get(&line))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Write an lcov tracefile named `test_name`, one record per file.
+    ///
+    /// Each conditional branch is an lcov block with two branches: 0 is
+    /// the taken edge, 1 the fall-through. Counts are 1 or 0 (the tracer
+    /// records whether, not how often), and `-` if the branch never ran.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from writing to `w`.
+    pub fn write_lcov(&self, mut w: impl Write, test_name: &str) -> io::Result<()> {
+        for (name, file) in &self.files {
+            writeln!(w, "TN:{test_name}")?;
+            writeln!(w, "SF:{name}")?;
+            let mut branches_found = 0;
+            let mut branches_hit = 0;
+            for (line, branches) in &file.branches {
+                for (block, branch) in branches.iter().enumerate() {
+                    for (edge, hit) in [(0, branch.taken), (1, branch.not_taken)] {
+                        let count = if branch.executed {
+                            u8::from(hit).to_string()
+                        } else {
+                            "-".to_string()
+                        };
+                        writeln!(w, "BRDA:{line},{block},{edge},{count}")?;
+                        branches_found += 1;
+                        branches_hit += usize::from(hit);
+                    }
+                }
+            }
+            writeln!(w, "BRF:{branches_found}")?;
+            writeln!(w, "BRH:{branches_hit}")?;
+            for (line, &hit) in &file.lines {
+                writeln!(w, "DA:{line},{}", u8::from(hit))?;
+            }
+            writeln!(w, "LF:{}", file.lines.len())?;
+            writeln!(w, "LH:{}", file.lines.values().filter(|&&hit| hit).count())?;
+            writeln!(w, "end_of_record")?;
+        }
+        Ok(())
+    }
+}
+
+/// Linearly decode the code segments, skipping bytes that do not decode.
+fn code_instrs<X: Xlen>(image: &ElfImage<X>) -> Result<Vec<CodeInstr>> {
+    let registry = Recompiler::<X>::with_defaults().extension_registry(image)?;
+    let code = code_segments(image);
+    let mut instrs = Vec::new();
+    for (seg, _) in image.memory_segments.iter().zip(&code).filter(|(_, c)| **c) {
+        let start = X::to_u64(seg.virtual_start);
+        let mut offset = 0;
+        while offset + MIN_INSTR_SIZE <= seg.data.len() {
+            let pc = start + offset as u64;
+            let Some(instr) = registry.decode(&seg.data[offset..], X::from_u64(pc)) else {
+                offset += MIN_INSTR_SIZE;
+                continue;
+            };
+            instrs.push(CodeInstr {
+                pc,
+                is_branch: registry.lift(&instr).terminator.is_branch(),
+            });
+            offset += usize::from(instr.size);
+        }
+    }
+    Ok(instrs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILE: &str = "guest.c";
+
+    /// Lines 1-4: a branch on line 2 that only fell through, and line 4
+    /// that never ran.
+    fn sample() -> Coverage {
+        let instrs = [
+            CodeInstr {
+                pc: 0x1000,
+                is_branch: false,
+            },
+            CodeInstr {
+                pc: 0x1004,
+                is_branch: true,
+            },
+            CodeInstr {
+                pc: 0x1008,
+                is_branch: false,
+            },
+            CodeInstr {
+                pc: 0x100c,
+                is_branch: false,
+            },
+            CodeInstr {
+                pc: 0x1010,
+                is_branch: false,
+            },
+        ];
+        let flags = [
+            (0x1000, COVERAGE_EXECUTED),
+            (0x1004, COVERAGE_EXECUTED | COVERAGE_NOT_TAKEN),
+            (0x1008, COVERAGE_EXECUTED),
+        ];
+        Coverage::from_flags(&instrs, &flags, |pc| {
+            // 0x1010 has no line info
+            let line = u32::try_from((pc - 0x1000) / 4).ok()? + 1;
+            (line <= 4).then(|| SourceLoc::new(FILE, line, "main"))
+        })
+    }
+
+    #[test]
+    fn test_line_and_branch_coverage() {
+        let coverage = sample();
+        assert_eq!(coverage.files().collect::<Vec<_>>(), [FILE]);
+        assert_eq!(coverage.line_hit(FILE, 1), Some(true));
+        assert_eq!(coverage.line_hit(FILE, 4), Some(false));
+        assert_eq!(coverage.line_hit(FILE, 5), None);
+        assert_eq!(
+            coverage.branches(FILE, 2),
+            [BranchCoverage {
+                pc: 0x1004,
+                executed: true,
+                taken: false,
+                not_taken: true,
+            }]
+        );
+        assert!(coverage.branches(FILE, 1).is_empty());
+    }
+
+    #[test]
+    fn test_write_lcov() {
+        let mut out = Vec::new();
+        sample().write_lcov(&mut out, "guest").unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "TN:guest\nSF:guest.c\nBRDA:2,0,0,0\nBRDA:2,0,1,1\nBRF:2\nBRH:1\n\
+             DA:1,1\nDA:2,1\nDA:3,1\nDA:4,0\nLF:4\nLH:3\nend_of_record\n"
+        );
+    }
+
+    #[test]
+    fn test_unexecuted_branch_is_dash() {
+        let instrs = [CodeInstr {
+            pc: 0x1000,
+            is_branch: true,
+        }];
+        let coverage =
+            Coverage::from_flags(&instrs, &[], |_| Some(SourceLoc::new(FILE, 7, "main")));
+        let mut out = Vec::new();
+        coverage.write_lcov(&mut out, "guest").unwrap();
+        let lcov = String::from_utf8(out).unwrap();
+        assert!(lcov.contains("BRDA:7,0,0,-\nBRDA:7,0,1,-\n"));
+        assert!(lcov.contains("BRH:0\nDA:7,0\n"));
+    }
+}
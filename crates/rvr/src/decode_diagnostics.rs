@@ -0,0 +1,484 @@
+//! Unsupported-instruction diagnostics.
+//!
+//! An instruction no extension lifts compiles to a trap, and bytes that do
+//! not decode get no block at all; either way the guest only fails once it
+//! gets there. This pass lists both at lift time: everything reachable from
+//! the lifted code, plus every instruction inside a sized function symbol
+//! (which catches code the CFG did not reach, e.g. behind indirect calls).
+//! For RVE images it also lists instructions naming x16-x31, which the
+//! 16-entry register file does not have.
+//! `EmitConfig::strict_decode` turns any finding into a compile error.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Write as _};
+
+use rvr_cfg::InstructionTable;
+use rvr_elf::{DebugInfo, ElfImage, STT_FUNC};
+use rvr_ir::SourceLoc;
+use rvr_isa::{ExtensionRegistry, Xlen, op_mnemonic};
+
+use crate::quarantine::{LiftFailureKind, guess_extension};
+
+/// Hint shown when any finding looks like an RVV instruction.
+const VECTOR_HINT: &str = "RVV instructions usually come from autovectorization (-O2/-O3 with \
+     a V-enabled -march), and rvr lifts only vsetvl/vsetvli/vsetivli, unit-stride loads and stores, \
+     vadd/vand/vor/vxor/vmv and vmv<n>r.v; rebuild with a -march without `v`, or with \
+     -fno-vectorize -fno-slp-vectorize (clang) / -fno-tree-vectorize (gcc)";
+/// Hint shown when an RVE image names registers above x15.
+const RVE_HINT: &str = "the ELF is flagged RVE (EF_RISCV_RVE) but uses x16-x31; rebuild all of \
+     it, libraries included, with an E -march/-mabi (e.g. rv32e/ilp32e)";
+/// Extension reported for registers outside the RVE register file.
+const EXT_RVE: &str = "RVE";
+
+// Major opcodes and fields used to guess mnemonics.
+const OPCODE_MASK: u32 = 0x7F;
+const OPCODE_LOAD_FP: u32 = 0x07;
+const OPCODE_MISC_MEM: u32 = 0x0F;
+const OPCODE_STORE_FP: u32 = 0x27;
+const OPCODE_AMO: u32 = 0x2F;
+const OPCODE_FMADD: u32 = 0x43;
+const OPCODE_FMSUB: u32 = 0x47;
+const OPCODE_FNMSUB: u32 = 0x4B;
+const OPCODE_FNMADD: u32 = 0x4F;
+const OPCODE_OP_FP: u32 = 0x53;
+const OPCODE_OP_V: u32 = 0x57;
+const OPCODE_SYSTEM: u32 = 0x73;
+/// `funct3` of OP-V configuration instructions (`vsetvli` and friends).
+const FUNCT3_OPCFG: u32 = 0b111;
+
+/// An instruction that would fall through to the trap path.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DecodeDiagnostic {
+    /// Whether the bytes did not decode or decoded without a lifter.
+    pub kind: LiftFailureKind,
+    /// PC of the instruction.
+    pub pc: u64,
+    /// Raw bytes at `pc` (one instruction).
+    pub raw: Vec<u8>,
+    /// Best guess at the mnemonic, from the encoding.
+    pub mnemonic: Option<String>,
+    /// Best guess at the extension the bytes belong to.
+    pub extension: Option<&'static str>,
+    /// Function symbol containing `pc`, if any.
+    pub symbol: Option<String>,
+    /// Source line, once debug info is loaded.
+    pub source: Option<SourceLoc>,
+}
+
+impl DecodeDiagnostic {
+    fn new<X: Xlen>(kind: LiftFailureKind, pc: u64, raw: Vec<u8>, image: &ElfImage<X>) -> Self {
+        Self {
+            kind,
+            pc,
+            mnemonic: guess_mnemonic(&raw),
+            extension: guess_extension(&raw),
+            raw,
+            symbol: image.function_containing(pc).map(str::to_string),
+            source: None,
+        }
+    }
+}
+
+impl fmt::Display for DecodeDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {:#x}", self.kind, self.pc)?;
+        if let Some(mnemonic) = &self.mnemonic {
+            write!(f, " ({mnemonic}?)")?;
+        }
+        if let Some(symbol) = &self.symbol {
+            write!(f, " in {symbol}")?;
+        }
+        if let Some(source) = &self.source {
+            write!(f, " at {}:{}", source.file, source.line)?;
+        }
+        Ok(())
+    }
+}
+
+/// Find instructions that would trap, sorted by PC.
+///
+/// `reached` are PCs the lifted code executes or transfers to; sized
+/// function symbols are swept as well. Instructions naming a register at or
+/// above `num_regs` are reported too.
+pub fn find_decode_diagnostics<X: Xlen>(
+    instr_table: &InstructionTable<X>,
+    registry: &ExtensionRegistry<X>,
+    image: &ElfImage<X>,
+    num_regs: usize,
+    reached: impl IntoIterator<Item = u64>,
+) -> Vec<DecodeDiagnostic> {
+    let check = |pc| diagnose(instr_table, registry, image, num_regs, pc);
+    let mut found = BTreeMap::new();
+    for pc in reached {
+        if let Some(diagnostic) = check(pc) {
+            found.entry(pc).or_insert(diagnostic);
+        }
+    }
+
+    let functions = image
+        .symbols
+        .iter()
+        .filter(|s| s.sym_type == STT_FUNC && X::to_u64(s.size) > 0);
+    for symbol in functions {
+        let start = X::to_u64(symbol.value).max(instr_table.base_address());
+        let end = (X::to_u64(symbol.value) + X::to_u64(symbol.size)).min(instr_table.end_address());
+        let mut pc = start;
+        while pc < end {
+            let diagnostic = check(pc);
+            pc += instr_table.get_at_pc(pc).map_or_else(
+                || diagnostic.as_ref().map_or(2, |d| d.raw.len() as u64),
+                |instr| u64::from(instr.size),
+            );
+            if let Some(diagnostic) = diagnostic {
+                found.entry(diagnostic.pc).or_insert(diagnostic);
+            }
+        }
+    }
+
+    found.into_values().collect()
+}
+
+/// Diagnose the instruction at `pc`, if it would trap or name a register
+/// the register file does not have.
+fn diagnose<X: Xlen>(
+    instr_table: &InstructionTable<X>,
+    registry: &ExtensionRegistry<X>,
+    image: &ElfImage<X>,
+    num_regs: usize,
+    pc: u64,
+) -> Option<DecodeDiagnostic> {
+    if pc < instr_table.base_address() || pc >= instr_table.end_address() {
+        return None;
+    }
+    if let Some(instr) = instr_table.get_at_pc(pc) {
+        let raw = instr.raw.to_le_bytes()[..usize::from(instr.size)].to_vec();
+        if registry.can_lift(instr.opid) {
+            if instr
+                .args
+                .max_reg()
+                .is_none_or(|reg| usize::from(reg) < num_regs)
+            {
+                return None;
+            }
+            return Some(DecodeDiagnostic {
+                mnemonic: Some(op_mnemonic(instr.opid.pack()).to_string()),
+                extension: Some(EXT_RVE),
+                ..DecodeDiagnostic::new(LiftFailureKind::RegisterOutOfRange, pc, raw, image)
+            });
+        }
+        return Some(DecodeDiagnostic::new(
+            LiftFailureKind::UnhandledExtension,
+            pc,
+            raw,
+            image,
+        ));
+    }
+    let &first = image.bytes_at(pc, 1)?.first()?;
+    let raw = image.bytes_at(pc, instr_len(first))?;
+    Some(DecodeDiagnostic::new(
+        LiftFailureKind::Undecodable,
+        pc,
+        raw.to_vec(),
+        image,
+    ))
+}
+
+/// Encoded length of the instruction starting with `first`.
+const fn instr_len(first: u8) -> usize {
+    if first & 0b11 == 0b11 { 4 } else { 2 }
+}
+
+/// Attach source lines from `debug_info` to diagnostics.
+pub fn attach_sources(diagnostics: &mut [DecodeDiagnostic], debug_info: &DebugInfo) {
+    for diagnostic in diagnostics {
+        diagnostic.source = debug_info.get(diagnostic.pc).cloned();
+    }
+}
+
+/// Hint for the most likely cause of `diagnostics`, if one is known.
+#[must_use]
+pub fn hint(diagnostics: &[DecodeDiagnostic]) -> Option<&'static str> {
+    let has = |ext| diagnostics.iter().any(|d| d.extension == Some(ext));
+    if has(EXT_RVE) {
+        Some(RVE_HINT)
+    } else {
+        has("V").then_some(VECTOR_HINT)
+    }
+}
+
+/// Render `diagnostics` as a table, with a per-extension summary and hint.
+#[must_use]
+pub fn format_table(diagnostics: &[DecodeDiagnostic]) -> String {
+    let mut by_extension: BTreeMap<&str, usize> = BTreeMap::new();
+    for diagnostic in diagnostics {
+        *by_extension
+            .entry(diagnostic.extension.unwrap_or("unknown"))
+            .or_default() += 1;
+    }
+    let summary: Vec<String> = by_extension
+        .iter()
+        .map(|(ext, count)| format!("{count} {ext}"))
+        .collect();
+
+    let mut out = format!(
+        "{} unsupported instruction(s) ({})\n",
+        diagnostics.len(),
+        summary.join(", ")
+    );
+    let _ = writeln!(
+        out,
+        "  {:<18} {:<12} {:<14} {:<16} {:<24} SOURCE",
+        "PC", "BYTES", "MNEMONIC", "EXTENSION", "FUNCTION"
+    );
+    for diagnostic in diagnostics {
+        let bytes: Vec<String> = diagnostic.raw.iter().map(|b| format!("{b:02x}")).collect();
+        let source = diagnostic
+            .source
+            .as_ref()
+            .map_or_else(|| "-".to_string(), |s| format!("{}:{}", s.file, s.line));
+        let _ = writeln!(
+            out,
+            "  {:<18} {:<12} {:<14} {:<16} {:<24} {source}",
+            format!("{:#x}", diagnostic.pc),
+            bytes.join(" "),
+            diagnostic.mnemonic.as_deref().unwrap_or("?"),
+            diagnostic.extension.unwrap_or("?"),
+            diagnostic.symbol.as_deref().unwrap_or("-"),
+        );
+    }
+    if let Some(hint) = hint(diagnostics) {
+        let _ = writeln!(out, "hint: {hint}");
+    }
+    out.pop();
+    out
+}
+
+/// Guess the mnemonic of `raw` from its encoding.
+///
+/// Covers the extensions rvr does not (fully) lift (V, F/D, A, Zifencei, CSRs and
+/// privileged instructions); returns `None` for anything else.
+fn guess_mnemonic(raw: &[u8]) -> Option<String> {
+    let word = u32::from_le_bytes(raw.try_into().ok()?);
+    let funct3 = (word >> 12) & 0b111;
+    match word & OPCODE_MASK {
+        OPCODE_OP_V => Some(guess_vector_op(word, funct3)),
+        OPCODE_LOAD_FP | OPCODE_STORE_FP => Some(guess_fp_mem(word, funct3)),
+        OPCODE_FMADD | OPCODE_FMSUB | OPCODE_FNMSUB | OPCODE_FNMADD => {
+            let name = match word & OPCODE_MASK {
+                OPCODE_FMADD => "fmadd",
+                OPCODE_FMSUB => "fmsub",
+                OPCODE_FNMSUB => "fnmsub",
+                _ => "fnmadd",
+            };
+            Some(format!("{name}{}", fp_suffix(word >> 25)))
+        }
+        OPCODE_OP_FP => guess_fp_op(word, funct3),
+        OPCODE_AMO => guess_amo(word, funct3),
+        OPCODE_MISC_MEM if funct3 == 0b001 => Some("fence.i".to_string()),
+        OPCODE_SYSTEM => guess_system(word, funct3),
+        _ => None,
+    }
+}
+
+/// OP-V: configuration, or arithmetic with its operand form.
+fn guess_vector_op(word: u32, funct3: u32) -> String {
+    if funct3 == FUNCT3_OPCFG {
+        return match word >> 30 {
+            0b00 | 0b01 => "vsetvli",
+            0b11 => "vsetivli",
+            _ => "vsetvl",
+        }
+        .to_string();
+    }
+    let funct6 = word >> 26;
+    // OPIVV, OPFVV, OPMVV, OPIVI, OPIVX, OPFVF, OPMVX
+    let form = ["vv", "vv", "vv", "vi", "vx", "vf", "vx"][funct3 as usize];
+    let name = match (funct3, funct6) {
+        (0b000 | 0b011 | 0b100, 0x00) => "vadd",
+        (0b000 | 0b100, 0x02) => "vsub",
+        (0b000 | 0b011 | 0b100, 0x09) => "vand",
+        (0b000 | 0b011 | 0b100, 0x0a) => "vor",
+        (0b000 | 0b011 | 0b100, 0x0b) => "vxor",
+        (0b011 | 0b100, 0x0e) => "vslideup",
+        (0b011 | 0b100, 0x0f) => "vslidedown",
+        (0b000 | 0b011 | 0b100, 0x17) if word & (1 << 25) != 0 => {
+            return format!("vmv.v.{}", &form[1..]);
+        }
+        (0b000 | 0b011 | 0b100, 0x17) => return format!("vmerge.{form}m"),
+        (0b000 | 0b011 | 0b100, 0x18) => "vmseq",
+        (0b010, 0x00) => return "vredsum.vs".to_string(),
+        (0b010, 0x10) => return "vmv.x.s".to_string(),
+        (0b110, 0x10) => return "vmv.s.x".to_string(),
+        (0b010 | 0b110, 0x25) => "vmul",
+        (0b010 | 0b110, 0x2d) => "vmacc",
+        (0b001 | 0b101, 0x00) => "vfadd",
+        (0b001 | 0b101, 0x02) => "vfsub",
+        (0b001 | 0b101, 0x24) => "vfmul",
+        (0b001 | 0b101, 0x2c) => "vfmacc",
+        (0b001, 0x01) => return "vfredusum.vs".to_string(),
+        _ => return format!("v?(funct6={funct6:#x}).{form}"),
+    };
+    format!("{name}.{form}")
+}
+
+/// LOAD-FP/STORE-FP: scalar FP loads and stores, or vector memory ops.
+fn guess_fp_mem(word: u32, funct3: u32) -> String {
+    let store = word & OPCODE_MASK == OPCODE_STORE_FP;
+    let eew = match funct3 {
+        0b000 => 8,
+        0b101 => 16,
+        0b110 => 32,
+        0b111 => 64,
+        0b001 => return if store { "fsh" } else { "flh" }.to_string(),
+        0b010 => return if store { "fsw" } else { "flw" }.to_string(),
+        0b011 => return if store { "fsd" } else { "fld" }.to_string(),
+        _ => return if store { "fsq" } else { "flq" }.to_string(),
+    };
+    let op = if store { "vs" } else { "vl" };
+    // mop: unit-stride, indexed-unordered, strided, indexed-ordered
+    match (word >> 26) & 0b11 {
+        0b00 => format!("{op}e{eew}.v"),
+        0b01 => format!("{op}uxei{eew}.v"),
+        0b10 => format!("{op}se{eew}.v"),
+        _ => format!("{op}oxei{eew}.v"),
+    }
+}
+
+/// OP-FP, by `funct5` and format.
+fn guess_fp_op(word: u32, funct3: u32) -> Option<String> {
+    let suffix = fp_suffix(word >> 25);
+    let name = match word >> 27 {
+        0x00 => "fadd",
+        0x01 => "fsub",
+        0x02 => "fmul",
+        0x03 => "fdiv",
+        0x0b => "fsqrt",
+        0x04 => ["fsgnj", "fsgnjn", "fsgnjx"]
+            .get(funct3 as usize)
+            .copied()?,
+        0x05 => ["fmin", "fmax"].get(funct3 as usize).copied()?,
+        0x08 => "fcvt",
+        0x14 => ["fle", "flt", "feq"].get(funct3 as usize).copied()?,
+        0x18 => "fcvt.w",
+        0x1a => return Some(format!("fcvt{suffix}.w")),
+        0x1c if funct3 == 0b001 => "fclass",
+        0x1c => return Some(format!("fmv.x{}", fmv_width(suffix))),
+        0x1e => return Some(format!("fmv{}.x", fmv_width(suffix))),
+        _ => return None,
+    };
+    Some(format!("{name}{suffix}"))
+}
+
+/// Format suffix of an F/D instruction from its `fmt` field.
+const fn fp_suffix(fmt: u32) -> &'static str {
+    match fmt & 0b11 {
+        0b00 => ".s",
+        0b01 => ".d",
+        0b10 => ".h",
+        _ => ".q",
+    }
+}
+
+/// Width suffix of `fmv` between integer and FP registers (`.w` for single).
+fn fmv_width(suffix: &'static str) -> &'static str {
+    if suffix == ".s" { ".w" } else { suffix }
+}
+
+/// AMO, by `funct5` and width.
+fn guess_amo(word: u32, funct3: u32) -> Option<String> {
+    let width = match funct3 {
+        0b010 => ".w",
+        0b011 => ".d",
+        _ => return None,
+    };
+    let name = match word >> 27 {
+        0x00 => "amoadd",
+        0x01 => "amoswap",
+        0x02 => "lr",
+        0x03 => "sc",
+        0x04 => "amoxor",
+        0x08 => "amoor",
+        0x0c => "amoand",
+        0x10 => "amomin",
+        0x14 => "amomax",
+        0x18 => "amominu",
+        0x1c => "amomaxu",
+        _ => return None,
+    };
+    Some(format!("{name}{width}"))
+}
+
+/// SYSTEM: CSR accesses and privileged instructions.
+fn guess_system(word: u32, funct3: u32) -> Option<String> {
+    let name = match (funct3, word) {
+        (0b001, _) => "csrrw",
+        (0b010, _) => "csrrs",
+        (0b011, _) => "csrrc",
+        (0b101, _) => "csrrwi",
+        (0b110, _) => "csrrsi",
+        (0b111, _) => "csrrci",
+        (0b000, 0x1020_0073) => "sret",
+        (0b000, 0x3020_0073) => "mret",
+        (0b000, 0x1050_0073) => "wfi",
+        (0b000, _) if word >> 25 == 0x09 => "sfence.vma",
+        _ => return None,
+    };
+    Some(name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mnemonic(word: u32) -> Option<String> {
+        guess_mnemonic(&word.to_le_bytes())
+    }
+
+    #[test]
+    fn test_guess_mnemonic() {
+        assert_eq!(mnemonic(0x0105_72D7).as_deref(), Some("vsetvli"));
+        assert_eq!(mnemonic(0x0205_6007).as_deref(), Some("vle32.v"));
+        assert_eq!(mnemonic(0x0205_7027).as_deref(), Some("vse64.v"));
+        // vadd.vv v1, v2, v3
+        assert_eq!(mnemonic(0x0221_80D7).as_deref(), Some("vadd.vv"));
+        // fadd.d f0, f0, f0
+        assert_eq!(mnemonic(0x0200_7053).as_deref(), Some("fadd.d"));
+        assert_eq!(mnemonic(0x0005_2007).as_deref(), Some("flw"));
+        // amoadd.w a0, a1, (a2)
+        assert_eq!(mnemonic(0x00B6_252F).as_deref(), Some("amoadd.w"));
+        assert_eq!(mnemonic(0x3020_0073).as_deref(), Some("mret"));
+        // addi is lifted, so there is nothing to guess
+        assert_eq!(mnemonic(0x0000_0013), None);
+        assert_eq!(guess_mnemonic(&[0x01, 0x00]), None);
+    }
+
+    #[test]
+    fn test_format_table() {
+        let diagnostic = DecodeDiagnostic {
+            kind: LiftFailureKind::Undecodable,
+            pc: 0x8000_0014,
+            raw: vec![0xD7, 0x72, 0x05, 0x01],
+            mnemonic: Some("vsetvli".to_string()),
+            extension: Some("V"),
+            symbol: Some("vec_kernel".to_string()),
+            source: Some(SourceLoc::new("kernel.c", 12, "vec_kernel")),
+        };
+        assert_eq!(
+            diagnostic.to_string(),
+            "undecodable instruction at 0x80000014 (vsetvli?) in vec_kernel at kernel.c:12"
+        );
+
+        let table = format_table(std::slice::from_ref(&diagnostic));
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines[0], "1 unsupported instruction(s) (1 V)");
+        assert!(lines[2].contains("d7 72 05 01"), "{table}");
+        assert!(lines[2].ends_with("kernel.c:12"), "{table}");
+        assert!(lines[3].starts_with("hint: "), "{table}");
+
+        let fp = DecodeDiagnostic {
+            extension: Some("F/D"),
+            ..diagnostic
+        };
+        assert_eq!(hint(&[fp]), None);
+    }
+}
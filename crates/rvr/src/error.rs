@@ -1,5 +1,12 @@
+use std::fmt::Write;
+
 use thiserror::Error;
 
+use crate::build::PartFailure;
+use crate::decode_diagnostics::{DecodeDiagnostic, format_table};
+use crate::pipeline::IrViolation;
+use crate::quarantine::LiftFailure;
+
 /// Recompiler errors.
 #[derive(Error, Debug)]
 pub enum Error {
@@ -11,12 +18,71 @@ pub enum Error {
     XlenMismatch { expected: u8, actual: u8 },
     #[error("Compilation failed: {0}")]
     CompilationFailed(String),
+    #[error(transparent)]
+    Compiler(#[from] rvr_emit::c::CompilerError),
+    #[error("Compilation failed: {}", format_part_failures(.failures, *.fallback_opt_level))]
+    PartsFailed {
+        failures: Vec<PartFailure>,
+        /// Level the parts were retried at, if they were.
+        fallback_opt_level: Option<u8>,
+    },
     #[error("No program loaded")]
     NoProgramLoaded,
     #[error("No code segment containing entry point 0x{0:x}")]
     NoCodeSegment(u64),
     #[error("CFG not built: call build_cfg before {0}")]
     CfgNotBuilt(&'static str),
+    #[error("No lifted block contains 0x{0:x}")]
+    NoBlockAtPc(u64),
+    #[error("Lift failed: {0}")]
+    LiftFailed(Box<LiftFailure>),
+    #[error("Invalid IR: {}", format_violations(.0))]
+    InvalidIr(Vec<IrViolation>),
+    #[error("Strict decode: {}", format_table(.0))]
+    UnsupportedInstructions(Vec<DecodeDiagnostic>),
+    #[error(transparent)]
+    Layout(#[from] rvr_emit::LayoutError),
+    #[error("Invalid heap/stack layout: {0}")]
+    MemoryLayout(#[from] rvr_emit::LayoutMismatch),
+    #[error("Invalid block profile {0}")]
+    InvalidProfile(String),
+    #[error("Invalid golden trace {0}")]
+    InvalidGolden(String),
+    #[error("Invalid jump target file {0}")]
+    InvalidJumpTargets(String),
+    #[error("Invalid lift filter: {0}")]
+    InvalidFilter(String),
+    #[error("Invalid program list: {0}")]
+    InvalidProgram(String),
+    #[error("Invalid symbol prefix '{0}': not a C identifier")]
+    InvalidSymbolPrefix(String),
+    #[error("Invalid ISA: {0}")]
+    InvalidIsa(#[from] rvr_isa::IsaError),
+    #[error("Invalid synthetic program: {0}")]
+    InvalidSynthetic(String),
+    #[error("Debug info: {0}")]
+    DebugInfo(String),
+}
+
+/// One line per failed part, after a summary of the attempts.
+fn format_part_failures(failures: &[PartFailure], fallback_opt_level: Option<u8>) -> String {
+    let mut out = format!("{} part file(s) failed to compile", failures.len());
+    if let Some(level) = fallback_opt_level {
+        let _ = write!(out, " at the default level and at -O{level}");
+    }
+    for failure in failures {
+        let _ = write!(out, "\n  {failure}");
+    }
+    out
+}
+
+/// One line per violation, after their count.
+fn format_violations(violations: &[IrViolation]) -> String {
+    let mut out = format!("{} violation(s)", violations.len());
+    for violation in violations {
+        let _ = write!(out, "\n  {violation}");
+    }
+    out
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
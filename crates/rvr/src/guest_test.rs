@@ -0,0 +1,380 @@
+//! Guest unit tests.
+//!
+//! Guest crates register tests with `rvr_rt::rvr_test!`, which places them
+//! in the `.rvr_tests` link section. [`run_guest_tests`] reads that section
+//! from the ELF and calls each test through the library's return trampoline
+//! (so the library must be compiled with `--export-functions`), starting
+//! from a freshly loaded image every time.
+//!
+//! A test passes by returning. It fails if it panics (the `rvr-rt` `test`
+//! panic handler records the message and exits with code 101), exits, or
+//! traps; with an instruction limit (`--instret suspend` libraries), a test
+//! that runs past the limit times out.
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use rvr_elf::{ElfImage, GuestTest, get_elf_xlen};
+use rvr_isa::{Rv32, Rv64};
+use rvr_state::DEFAULT_MEMORY_SIZE;
+use thiserror::Error;
+use tracing::debug;
+
+use crate::{ExitReason, RunError, Runner};
+
+/// Exit code of a panicking test (`rvr_rt::PANIC_EXIT_CODE`).
+pub const PANIC_EXIT_CODE: u8 = 101;
+/// Guest buffer holding the panic message.
+const PANIC_MSG_SYMBOL: &str = "RVR_TEST_PANIC_MSG";
+/// Guest word holding the panic message length.
+const PANIC_LEN_SYMBOL: &str = "RVR_TEST_PANIC_LEN";
+/// Upper bound on the panic message read from the guest.
+const MAX_PANIC_MSG_LEN: usize = 4096;
+
+/// Guest test errors (failing tests are results, not errors).
+#[derive(Debug, Error)]
+pub enum GuestTestError {
+    #[error(transparent)]
+    Run(#[from] RunError),
+    #[error("ELF error: {0}")]
+    Elf(#[from] rvr_elf::ElfError),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("library has no call trampoline; compile with --export-functions")]
+    NoReturnTrampoline,
+    #[error("instruction limit requires a library compiled with --instret suspend")]
+    SuspendUnsupported,
+}
+
+/// How a guest test ended.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TestOutcome {
+    /// The test returned.
+    Passed,
+    /// The test panicked, exited or trapped.
+    Failed {
+        /// Panic message, or why the test stopped.
+        message: String,
+    },
+    /// The test reached the instruction limit.
+    TimedOut,
+}
+
+/// Result of one guest test.
+#[derive(Clone, Debug)]
+pub struct GuestTestResult {
+    /// The test.
+    pub test: GuestTest,
+    /// How it ended.
+    pub outcome: TestOutcome,
+    /// Instructions retired by the test.
+    pub instret: u64,
+    /// Wall-clock time of the test.
+    pub time: Duration,
+}
+
+/// Results of a guest test run, in test order.
+#[derive(Clone, Debug, Default)]
+pub struct GuestTestReport {
+    /// Per-test results.
+    pub results: Vec<GuestTestResult>,
+    /// Tests skipped by the filters.
+    pub filtered_out: usize,
+    /// Wall-clock time of the whole run.
+    pub time: Duration,
+}
+
+impl GuestTestReport {
+    /// Number of passed tests.
+    #[must_use]
+    pub fn passed(&self) -> usize {
+        self.count(|outcome| matches!(outcome, TestOutcome::Passed))
+    }
+
+    /// Number of failed tests (not counting timeouts).
+    #[must_use]
+    pub fn failed(&self) -> usize {
+        self.count(|outcome| matches!(outcome, TestOutcome::Failed { .. }))
+    }
+
+    /// Number of timed-out tests.
+    #[must_use]
+    pub fn timed_out(&self) -> usize {
+        self.count(|outcome| matches!(outcome, TestOutcome::TimedOut))
+    }
+
+    /// Whether every test that ran passed.
+    #[must_use]
+    pub fn success(&self) -> bool {
+        self.passed() == self.results.len()
+    }
+
+    fn count(&self, pred: impl Fn(&TestOutcome) -> bool) -> usize {
+        self.results.iter().filter(|r| pred(&r.outcome)).count()
+    }
+}
+
+/// Options for [`run_guest_tests`].
+#[derive(Clone, Debug)]
+pub struct GuestTestOptions {
+    /// Run only tests whose name contains one of these (all if empty).
+    pub filters: Vec<String>,
+    /// Match filters against the whole name instead of a substring.
+    pub exact: bool,
+    /// Instruction limit per test.
+    pub max_insns: Option<u64>,
+    /// Tests to run in parallel, each on its own runner (0 = one per CPU).
+    pub jobs: usize,
+    /// Guest memory size per runner.
+    pub memory_size: usize,
+}
+
+impl Default for GuestTestOptions {
+    fn default() -> Self {
+        Self {
+            filters: Vec::new(),
+            exact: false,
+            max_insns: None,
+            jobs: 1,
+            memory_size: DEFAULT_MEMORY_SIZE,
+        }
+    }
+}
+
+impl GuestTestOptions {
+    /// Create default options: every test, no limit, one job.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a name filter.
+    #[must_use]
+    pub fn with_filter(mut self, filter: impl Into<String>) -> Self {
+        self.filters.push(filter.into());
+        self
+    }
+
+    /// Match filters exactly.
+    #[must_use]
+    pub const fn with_exact(mut self, exact: bool) -> Self {
+        self.exact = exact;
+        self
+    }
+
+    /// Set the instruction limit per test.
+    #[must_use]
+    pub const fn with_max_insns(mut self, max_insns: Option<u64>) -> Self {
+        self.max_insns = max_insns;
+        self
+    }
+
+    /// Set the number of parallel jobs (0 = one per CPU).
+    #[must_use]
+    pub const fn with_jobs(mut self, jobs: usize) -> Self {
+        self.jobs = jobs;
+        self
+    }
+
+    /// Set the guest memory size per runner.
+    #[must_use]
+    pub const fn with_memory_size(mut self, memory_size: usize) -> Self {
+        self.memory_size = memory_size;
+        self
+    }
+
+    /// Whether the test `name` passes the filters.
+    #[must_use]
+    pub fn matches(&self, name: &str) -> bool {
+        self.filters.is_empty()
+            || self.filters.iter().any(|filter| {
+                if self.exact {
+                    name == filter
+                } else {
+                    name.contains(filter.as_str())
+                }
+            })
+    }
+}
+
+/// Guest tests registered in the ELF at `elf_path`.
+///
+/// # Errors
+/// Returns an error if the ELF cannot be read or its test section is malformed.
+pub fn list_guest_tests(elf_path: &Path) -> Result<Vec<GuestTest>, GuestTestError> {
+    let data = std::fs::read(elf_path)?;
+    let tests = if get_elf_xlen(&data)? == 32 {
+        ElfImage::<Rv32>::parse(&data)?.guest_tests()?
+    } else {
+        ElfImage::<Rv64>::parse(&data)?.guest_tests()?
+    };
+    Ok(tests)
+}
+
+/// Run the guest tests in `elf_path` against the library in `lib_dir`.
+///
+/// # Errors
+/// Returns an error if the tests cannot be listed or a runner cannot be set
+/// up; test failures are reported in the [`GuestTestReport`].
+pub fn run_guest_tests(
+    lib_dir: &Path,
+    elf_path: &Path,
+    options: &GuestTestOptions,
+) -> Result<GuestTestReport, GuestTestError> {
+    let start = Instant::now();
+    let all = list_guest_tests(elf_path)?;
+    let total = all.len();
+    let tests: Vec<GuestTest> = all
+        .into_iter()
+        .filter(|test| options.matches(&test.name))
+        .collect();
+    let filtered_out = total - tests.len();
+
+    let runner = load_runner(lib_dir, elf_path, options)?;
+    let jobs = if runner.has_fixed_addresses() {
+        1
+    } else if options.jobs == 0 {
+        std::thread::available_parallelism().map_or(1, std::num::NonZero::get)
+    } else {
+        options.jobs
+    }
+    .clamp(1, tests.len().max(1));
+
+    let results = if jobs == 1 {
+        let mut runner = runner;
+        tests
+            .into_iter()
+            .map(|test| run_test(&mut runner, test, options.max_insns))
+            .collect::<Result<Vec<_>, _>>()?
+    } else {
+        drop(runner);
+        run_parallel(lib_dir, elf_path, &tests, options, jobs)?
+    };
+
+    Ok(GuestTestReport {
+        results,
+        filtered_out,
+        time: start.elapsed(),
+    })
+}
+
+/// Run `tests` on `jobs` threads, each with its own runner.
+fn run_parallel(
+    lib_dir: &Path,
+    elf_path: &Path,
+    tests: &[GuestTest],
+    options: &GuestTestOptions,
+    jobs: usize,
+) -> Result<Vec<GuestTestResult>, GuestTestError> {
+    let next = AtomicUsize::new(0);
+    let slots: Vec<Mutex<Option<GuestTestResult>>> =
+        tests.iter().map(|_| Mutex::new(None)).collect();
+    let (lib_dir, elf_path): (PathBuf, PathBuf) = (lib_dir.into(), elf_path.into());
+
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..jobs)
+            .map(|_| {
+                scope.spawn(|| -> Result<(), GuestTestError> {
+                    let mut runner = load_runner(&lib_dir, &elf_path, options)?;
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(test) = tests.get(index) else {
+                            return Ok(());
+                        };
+                        let result = run_test(&mut runner, test.clone(), options.max_insns)?;
+                        *slots[index].lock().expect("result slot poisoned") = Some(result);
+                    }
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .try_for_each(|worker| worker.join().expect("test worker panicked"))
+    })?;
+
+    Ok(slots
+        .into_iter()
+        .map(|slot| {
+            slot.into_inner()
+                .expect("result slot poisoned")
+                .expect("every test ran")
+        })
+        .collect())
+}
+
+fn load_runner(
+    lib_dir: &Path,
+    elf_path: &Path,
+    options: &GuestTestOptions,
+) -> Result<Runner, GuestTestError> {
+    let runner = Runner::load_with_memory(lib_dir, elf_path, options.memory_size)?;
+    if !runner.has_export_functions() {
+        return Err(GuestTestError::NoReturnTrampoline);
+    }
+    if options.max_insns.is_some() && !runner.supports_suspend() {
+        return Err(GuestTestError::SuspendUnsupported);
+    }
+    Ok(runner)
+}
+
+/// Call `test` from a fresh entry state.
+fn run_test(
+    runner: &mut Runner,
+    test: GuestTest,
+    max_insns: Option<u64>,
+) -> Result<GuestTestResult, GuestTestError> {
+    let return_pc = runner
+        .prepare_call()
+        .ok_or(GuestTestError::NoReturnTrampoline)?;
+    if let Some(limit) = max_insns {
+        runner.set_target_instret(limit);
+    }
+
+    debug!(name = %test.name, addr = format!("{:#x}", test.addr), "running guest test");
+    let start = Instant::now();
+    let outcome = match runner.execute_from(test.addr) {
+        Ok(_) if runner.has_exited() => TestOutcome::Failed {
+            message: "exited with code 0 before returning".to_string(),
+        },
+        Ok(_) if runner.get_pc() == return_pc => TestOutcome::Passed,
+        // Stopped without exiting or returning: suspended at the limit
+        Ok(_) => TestOutcome::TimedOut,
+        Err(RunError::ExecutionError(ExitReason::Exited(PANIC_EXIT_CODE))) => TestOutcome::Failed {
+            message: panic_message(runner).unwrap_or_else(|| "panicked".to_string()),
+        },
+        Err(RunError::ExecutionError(reason)) => TestOutcome::Failed {
+            message: reason.to_string(),
+        },
+        Err(err @ RunError::QuarantinedBlock { .. }) => TestOutcome::Failed {
+            message: err.to_string(),
+        },
+        Err(err) => return Err(err.into()),
+    };
+
+    Ok(GuestTestResult {
+        test,
+        outcome,
+        instret: runner.instret(),
+        time: start.elapsed(),
+    })
+}
+
+/// Message recorded by the `rvr-rt` test panic handler.
+fn panic_message(runner: &Runner) -> Option<String> {
+    let msg_addr = runner.lookup_symbol(PANIC_MSG_SYMBOL)?;
+    let len_addr = runner.lookup_symbol(PANIC_LEN_SYMBOL)?;
+    let mut len = [0u8; 8];
+    let word = usize::from(runner.xlen() / 8);
+    if runner.read_memory(len_addr, &mut len[..word]) != word {
+        return None;
+    }
+    let len = usize::try_from(u64::from_le_bytes(len))
+        .ok()?
+        .min(MAX_PANIC_MSG_LEN);
+    let mut msg = vec![0u8; len];
+    let read = runner.read_memory(msg_addr, &mut msg);
+    msg.truncate(read);
+    Some(String::from_utf8_lossy(&msg).into_owned())
+}
@@ -0,0 +1,288 @@
+//! Host cost per guest function.
+//!
+//! [`Runner::run_host_profile`](crate::Runner::run_host_profile) suspends
+//! the guest every few thousand instructions and charges the host cost of
+//! each interval to the function the guest suspended in. Next to each
+//! function's share of guest instructions, its share of host cost shows
+//! where the recompiled code expands the most.
+//!
+//! The whole interval is charged to that one function, so the shares are
+//! only meaningful for functions that run for many instructions per call
+//! (loops, not small leaf calls): calls shorter than the interval are
+//! charged to whichever function the interval ends in. Shorten the interval
+//! to resolve them, at the cost of a suspension per interval.
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::Path;
+
+use rvr_elf::ElfImage;
+use rvr_isa::{Rv32, Rv64, Xlen};
+use serde::Serialize;
+
+use crate::Result;
+
+/// Guest instructions between samples unless configured otherwise.
+pub const DEFAULT_HOST_SAMPLE_INTERVAL: u64 = 10_000;
+
+/// Name for samples outside any known function.
+const UNKNOWN_SYMBOL: &str = "[unknown]";
+
+/// Percent scale of the shares.
+const PERCENT: f64 = 100.0;
+/// Resolution of the shares (parts of the total).
+const SHARE_SCALE: u32 = 1_000_000;
+
+/// Unit of [`HostSample::cost`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HostCostUnit {
+    /// Host CPU cycles from perf counters.
+    Cycles,
+    /// Wall-clock nanoseconds, when perf counters are unavailable.
+    Nanos,
+}
+
+impl HostCostUnit {
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Cycles => "cycles",
+            Self::Nanos => "ns",
+        }
+    }
+}
+
+/// One sampling interval.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HostSample {
+    /// Guest PC the interval ended at.
+    pub pc: u64,
+    /// Guest instructions retired in the interval.
+    pub instructions: u64,
+    /// Host cost of the interval.
+    pub cost: u64,
+}
+
+/// Samples of one [`Runner::run_host_profile`](crate::Runner::run_host_profile) run.
+#[derive(Clone, Debug)]
+pub struct HostSamples {
+    /// Unit of the sample costs.
+    pub unit: HostCostUnit,
+    /// Samples in execution order.
+    pub samples: Vec<HostSample>,
+}
+
+/// Host cost and guest instructions of one guest function.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct HostFunction {
+    /// Function symbol, or `[unknown]`.
+    pub symbol: String,
+    /// Samples that ended in the function.
+    pub samples: u64,
+    /// Guest instructions of those samples.
+    pub instructions: u64,
+    /// Host cost of those samples.
+    pub cost: u64,
+    /// Share of the total host cost, in percent.
+    pub host_percent: f64,
+    /// Share of the guest instructions, in percent.
+    pub guest_percent: f64,
+}
+
+impl HostFunction {
+    /// Host share over guest share: above 1 the function costs more per
+    /// guest instruction than the program's average.
+    #[must_use]
+    pub fn expansion(&self) -> Option<f64> {
+        (self.guest_percent > 0.0).then(|| self.host_percent / self.guest_percent)
+    }
+}
+
+/// Host cost per guest function, costliest first.
+#[derive(Clone, Debug, Serialize)]
+pub struct HostProfile {
+    /// Unit of the costs.
+    pub unit: HostCostUnit,
+    /// Total host cost.
+    pub cost: u64,
+    /// Total guest instructions.
+    pub instructions: u64,
+    /// Functions, costliest first.
+    pub functions: Vec<HostFunction>,
+}
+
+impl HostProfile {
+    /// Attribute `samples` to the functions of the ELF at `elf_path`.
+    ///
+    /// `load_bias` places a position-independent ELF as the runner did (see
+    /// [`Runner::load_bias`](crate::Runner::load_bias)).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the ELF cannot be read or parsed.
+    pub fn resolve(elf_path: &Path, load_bias: Option<u64>, samples: &HostSamples) -> Result<Self> {
+        let data = std::fs::read(elf_path)?;
+        if rvr_elf::get_elf_xlen(&data)? == Rv32::VALUE {
+            let image = ElfImage::<Rv32>::parse_with_load_bias(&data, load_bias)?;
+            Ok(Self::resolve_image(&image, samples))
+        } else {
+            let image = ElfImage::<Rv64>::parse_with_load_bias(&data, load_bias)?;
+            Ok(Self::resolve_image(&image, samples))
+        }
+    }
+
+    fn resolve_image<X: Xlen>(image: &ElfImage<X>, samples: &HostSamples) -> Self {
+        Self::from_samples(samples, |pc| {
+            image.function_containing(pc).map(str::to_string)
+        })
+    }
+
+    /// Build a profile from samples and a symbol lookup.
+    fn from_samples(samples: &HostSamples, symbol: impl Fn(u64) -> Option<String>) -> Self {
+        let mut by_symbol: HashMap<String, (u64, u64, u64)> = HashMap::new();
+        for sample in &samples.samples {
+            let name = symbol(sample.pc).unwrap_or_else(|| UNKNOWN_SYMBOL.to_string());
+            let entry = by_symbol.entry(name).or_default();
+            entry.0 += 1;
+            entry.1 += sample.instructions;
+            entry.2 += sample.cost;
+        }
+        let cost: u64 = by_symbol.values().map(|&(_, _, cost)| cost).sum();
+        let instructions: u64 = by_symbol.values().map(|&(_, insns, _)| insns).sum();
+        let mut functions: Vec<HostFunction> = by_symbol
+            .into_iter()
+            .map(|(symbol, (count, insns, fn_cost))| HostFunction {
+                symbol,
+                samples: count,
+                instructions: insns,
+                cost: fn_cost,
+                host_percent: share(fn_cost, cost),
+                guest_percent: share(insns, instructions),
+            })
+            .collect();
+        functions.sort_by(|a, b| b.cost.cmp(&a.cost).then_with(|| a.symbol.cmp(&b.symbol)));
+        Self {
+            unit: samples.unit,
+            cost,
+            instructions,
+            functions,
+        }
+    }
+
+    /// Write the `limit` costliest functions as a text table.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from writing to `w`.
+    pub fn write_table(&self, mut w: impl Write, limit: usize) -> io::Result<()> {
+        let unit = self.unit.name();
+        writeln!(
+            w,
+            "Host cost per guest function ({} of {}, {} {unit}, {} instructions)",
+            limit.min(self.functions.len()),
+            self.functions.len(),
+            self.cost,
+            self.instructions
+        )?;
+        writeln!(
+            w,
+            "{:>7}  {:>7}  {:>9}  {:>16}  function",
+            "host%", "guest%", "expansion", unit
+        )?;
+        for function in self.functions.iter().take(limit) {
+            let expansion = function
+                .expansion()
+                .map_or_else(|| "-".to_string(), |x| format!("{x:.2}x"));
+            writeln!(
+                w,
+                "{:>6.1}%  {:>6.1}%  {expansion:>9}  {:>16}  {}",
+                function.host_percent, function.guest_percent, function.cost, function.symbol
+            )?;
+        }
+        Ok(())
+    }
+
+    /// The profile as one line of JSON.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialization fails.
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(self)
+    }
+}
+
+/// `part / total` in percent.
+fn share(part: u64, total: u64) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    let scaled = u128::from(part) * u128::from(SHARE_SCALE) / u128::from(total);
+    f64::from(u32::try_from(scaled).unwrap_or(SHARE_SCALE)) / f64::from(SHARE_SCALE) * PERCENT
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAIN: u64 = 0x1000;
+    const HOT: u64 = 0x2000;
+
+    fn samples() -> HostSamples {
+        let sample = |pc, cost| HostSample {
+            pc,
+            instructions: 100,
+            cost,
+        };
+        HostSamples {
+            unit: HostCostUnit::Cycles,
+            samples: vec![
+                sample(MAIN, 100),
+                sample(HOT, 600),
+                sample(HOT, 200),
+                sample(0, 100),
+            ],
+        }
+    }
+
+    fn profile() -> HostProfile {
+        HostProfile::from_samples(&samples(), |pc| {
+            [(MAIN, "main"), (HOT, "hot")]
+                .iter()
+                .find(|&&(start, _)| start == pc)
+                .map(|&(_, name)| name.to_string())
+        })
+    }
+
+    #[test]
+    fn test_host_profile_attribution() {
+        let profile = profile();
+        assert_eq!((profile.cost, profile.instructions), (1000, 400));
+        let names: Vec<&str> = profile
+            .functions
+            .iter()
+            .map(|f| f.symbol.as_str())
+            .collect();
+        assert_eq!(names, ["hot", UNKNOWN_SYMBOL, "main"]);
+        let hot = &profile.functions[0];
+        assert_eq!((hot.samples, hot.instructions, hot.cost), (2, 200, 800));
+        assert!((hot.host_percent - 80.0).abs() < 1e-9);
+        assert!((hot.guest_percent - 50.0).abs() < 1e-9);
+        assert!((hot.expansion().unwrap() - 1.6).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_host_profile_reports() {
+        let profile = profile();
+        let mut out = Vec::new();
+        profile.write_table(&mut out, 1).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.starts_with("Host cost per guest function (1 of 3, 1000 cycles, 400"));
+        assert!(text.contains("  80.0%    50.0%      1.60x"), "{text}");
+        assert!(!text.contains("main"));
+
+        let json = profile.to_json().unwrap();
+        assert!(json.starts_with(r#"{"unit":"cycles","cost":1000,"instructions":400,"#));
+        assert!(json.contains(r#""symbol":"hot","samples":2"#));
+    }
+}
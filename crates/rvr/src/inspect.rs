@@ -0,0 +1,334 @@
+//! ELF summaries for `rvr inspect`.
+//!
+//! Reports what rvr sees in an image (XLEN, ABI, entry point, segments,
+//! symbols) without lifting or compiling it, and flags images that would
+//! fail or misbehave later: an entry point outside executable code, segments
+//! reaching past guest memory, an RVE flag that disagrees with the ISA
+//! attribute, and extensions rvr does not decode.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use rvr_elf::{
+    EF_RISCV_FLOAT_ABI_DOUBLE, EF_RISCV_FLOAT_ABI_QUAD, EF_RISCV_FLOAT_ABI_SINGLE, ElfError,
+    ElfImage, MemorySegment, PF_R, PF_W, PF_X, STT_FUNC,
+};
+use rvr_isa::{ExtensionRegistry, IsaString, Rv32, Rv64, Xlen};
+use serde::Serialize;
+
+/// Mask of the float ABI bits in `e_flags`.
+const EF_RISCV_FLOAT_ABI_MASK: u32 = 0x6;
+
+/// What to inspect.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InspectOptions {
+    /// Guest memory size as a power of two; segments past it are flagged.
+    pub memory_bits: u8,
+    /// Load bias for position-independent images (see
+    /// [`ElfImage::parse_with_load_bias`]).
+    pub load_bias: Option<u64>,
+    /// Linearly decode executable segments for an extension histogram.
+    pub decode: bool,
+}
+
+impl Default for InspectOptions {
+    fn default() -> Self {
+        Self {
+            memory_bits: 32,
+            load_bias: None,
+            decode: false,
+        }
+    }
+}
+
+/// Summary of an ELF image.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ElfSummary {
+    /// 32 or 64.
+    pub xlen: u8,
+    /// ABI from `e_flags`, e.g. `lp64d` or `ilp32e`.
+    pub abi: String,
+    /// Whether `e_flags` has `EF_RISCV_RVC`.
+    pub rvc: bool,
+    /// ISA string from `.riscv.attributes`, if present.
+    pub arch: Option<String>,
+    /// Entry point, after the load bias.
+    pub entry_point: u64,
+    /// Load bias applied to a position-independent image (0 otherwise).
+    pub load_bias: u64,
+    /// Loadable segments in program header order.
+    pub segments: Vec<SegmentSummary>,
+    /// Number of symbols.
+    pub symbols: usize,
+    /// Number of function symbols.
+    pub functions: usize,
+    /// File bytes of the segments rvr would decode as code.
+    pub code_bytes: u64,
+    /// Extension histogram, if decoding was requested.
+    pub decode: Option<DecodeSummary>,
+    /// Problems found in the image.
+    pub warnings: Vec<InspectWarning>,
+}
+
+/// A loadable segment.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct SegmentSummary {
+    /// First address.
+    pub start: u64,
+    /// End address (exclusive), including BSS.
+    pub end: u64,
+    /// Bytes backed by the file.
+    pub file_size: u64,
+    /// Bytes in memory, including BSS.
+    pub mem_size: u64,
+    /// Permissions as `rwx`, with `-` for missing ones.
+    pub permissions: String,
+    /// Whether rvr decodes the segment as code (`PF_X`, or executable
+    /// sections when no segment has `PF_X`).
+    pub code: bool,
+}
+
+/// Result of a linear decode of the code segments.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct DecodeSummary {
+    /// Decoded instructions per extension name.
+    pub extensions: BTreeMap<String, usize>,
+    /// 2-byte slots no selected extension decodes (data, padding, or
+    /// instructions of unsupported extensions).
+    pub undecoded_slots: usize,
+}
+
+/// A problem found in an image.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum InspectWarning {
+    /// The entry point is outside every code segment; compiling fails with
+    /// [`Error::NoCodeSegment`](crate::Error::NoCodeSegment).
+    EntryNotInCode { entry_point: u64 },
+    /// A segment reaches past guest memory into the guard region.
+    GuardOverlap {
+        start: u64,
+        end: u64,
+        memory_size: u64,
+    },
+    /// `EF_RISCV_RVE` disagrees with the base of the ISA attribute.
+    RveMismatch { rve_flag: bool, arch: String },
+    /// The ISA attribute names extensions rvr does not decode.
+    UnsupportedExtensions { extensions: Vec<String> },
+}
+
+impl fmt::Display for InspectWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EntryNotInCode { entry_point } => {
+                write!(
+                    f,
+                    "entry point {entry_point:#x} is not in an executable segment"
+                )
+            }
+            Self::GuardOverlap {
+                start,
+                end,
+                memory_size,
+            } => write!(
+                f,
+                "segment {start:#x}..{end:#x} extends past guest memory ({memory_size:#x} bytes) into the guard region"
+            ),
+            Self::RveMismatch {
+                rve_flag: true,
+                arch,
+            } => {
+                write!(f, "ELF is flagged RVE but the ISA attribute is {arch}")
+            }
+            Self::RveMismatch {
+                rve_flag: false,
+                arch,
+            } => write!(
+                f,
+                "ISA attribute {arch} is RVE but the ELF is not flagged RVE"
+            ),
+            Self::UnsupportedExtensions { extensions } => write!(
+                f,
+                "ISA uses extensions rvr does not implement: {}",
+                extensions.join(",")
+            ),
+        }
+    }
+}
+
+/// Parse an ELF file of either XLEN and summarize it.
+///
+/// # Errors
+///
+/// Returns an error if the ELF cannot be parsed.
+pub fn inspect_elf(data: &[u8], options: &InspectOptions) -> Result<ElfSummary, ElfError> {
+    if rvr_elf::get_elf_xlen(data)? == Rv32::VALUE {
+        let image = ElfImage::<Rv32>::parse_with_load_bias(data, options.load_bias)?;
+        Ok(inspect_image(&image, options))
+    } else {
+        let image = ElfImage::<Rv64>::parse_with_load_bias(data, options.load_bias)?;
+        Ok(inspect_image(&image, options))
+    }
+}
+
+/// Summarize a parsed image.
+#[must_use]
+pub fn inspect_image<X: Xlen>(image: &ElfImage<X>, options: &InspectOptions) -> ElfSummary {
+    let code = code_segments(image);
+    let entry_point = X::to_u64(image.entry_point);
+    let arch = image.arch_attributes().and_then(|a| a.arch.clone());
+    let isa = arch.as_deref().and_then(|arch| IsaString::parse(arch).ok());
+
+    let mut warnings = Vec::new();
+    let in_code = image
+        .memory_segments
+        .iter()
+        .zip(&code)
+        .any(|(seg, &is_code)| is_code && contains(seg, entry_point));
+    if !in_code {
+        warnings.push(InspectWarning::EntryNotInCode { entry_point });
+    }
+    let memory_size = 1u64.checked_shl(options.memory_bits.into()).unwrap_or(0);
+    for seg in &image.memory_segments {
+        let (start, end) = (X::to_u64(seg.virtual_start), X::to_u64(seg.virtual_end));
+        if memory_size != 0 && end > memory_size {
+            warnings.push(InspectWarning::GuardOverlap {
+                start,
+                end,
+                memory_size,
+            });
+        }
+    }
+    if let (Some(isa), Some(arch)) = (&isa, &arch)
+        && isa.has("e") != image.is_rve()
+    {
+        warnings.push(InspectWarning::RveMismatch {
+            rve_flag: image.is_rve(),
+            arch: arch.clone(),
+        });
+    }
+    if let Some(isa) = &isa {
+        let unsupported = isa.unsupported();
+        if !unsupported.is_empty() {
+            warnings.push(InspectWarning::UnsupportedExtensions {
+                extensions: unsupported.into_iter().map(str::to_string).collect(),
+            });
+        }
+    }
+
+    let decode = options.decode.then(|| {
+        let registry = isa
+            .as_ref()
+            .map_or_else(ExtensionRegistry::standard, ExtensionRegistry::for_isa);
+        decode_histogram(image, &code, &registry)
+    });
+
+    ElfSummary {
+        xlen: X::VALUE,
+        abi: abi_name::<X>(image),
+        rvc: image.is_rvc(),
+        arch,
+        entry_point,
+        load_bias: image.load_bias,
+        segments: image
+            .memory_segments
+            .iter()
+            .zip(&code)
+            .map(|(seg, &is_code)| SegmentSummary {
+                start: X::to_u64(seg.virtual_start),
+                end: X::to_u64(seg.virtual_end),
+                file_size: seg.filesz(),
+                mem_size: seg.memsz(),
+                permissions: permissions(seg.flags),
+                code: is_code,
+            })
+            .collect(),
+        symbols: image.symbols.len(),
+        functions: image
+            .symbols
+            .iter()
+            .filter(|s| s.sym_type == STT_FUNC)
+            .count(),
+        code_bytes: image
+            .memory_segments
+            .iter()
+            .zip(&code)
+            .filter(|&(_, &is_code)| is_code)
+            .map(|(seg, _)| seg.filesz())
+            .sum(),
+        decode,
+        warnings,
+    }
+}
+
+/// Which segments are decoded as code, as the pipeline picks them: `PF_X`
+/// segments, or segments with executable sections if none has `PF_X`.
+pub fn code_segments<X: Xlen>(image: &ElfImage<X>) -> Vec<bool> {
+    let segments = &image.memory_segments;
+    if segments.iter().any(MemorySegment::is_executable) {
+        segments.iter().map(MemorySegment::is_executable).collect()
+    } else {
+        segments
+            .iter()
+            .map(|seg| seg.has_executable_sections(&image.sections))
+            .collect()
+    }
+}
+
+fn contains<X: Xlen>(seg: &MemorySegment<X>, addr: u64) -> bool {
+    (X::to_u64(seg.virtual_start)..X::to_u64(seg.virtual_end)).contains(&addr)
+}
+
+/// Decode each code segment front to back, skipping 2 bytes past anything
+/// that does not decode.
+fn decode_histogram<X: Xlen>(
+    image: &ElfImage<X>,
+    code: &[bool],
+    registry: &ExtensionRegistry<X>,
+) -> DecodeSummary {
+    let mut summary = DecodeSummary::default();
+    for (seg, _) in image.memory_segments.iter().zip(code).filter(|(_, c)| **c) {
+        let start = X::to_u64(seg.virtual_start);
+        let mut offset = 0;
+        while offset + 2 <= seg.data.len() {
+            let pc = X::from_u64(start + offset as u64);
+            let Some(instr) = registry.decode(&seg.data[offset..], pc) else {
+                summary.undecoded_slots += 1;
+                offset += 2;
+                continue;
+            };
+            let name = registry
+                .extensions()
+                .iter()
+                .find(|ext| ext.ext_id() == instr.opid.ext)
+                .map_or("?", |ext| ext.name());
+            *summary.extensions.entry(name.to_string()).or_default() += 1;
+            offset += usize::from(instr.size);
+        }
+    }
+    summary
+}
+
+/// ABI name from the class, float ABI and RVE bits of `e_flags`.
+fn abi_name<X: Xlen>(image: &ElfImage<X>) -> String {
+    let base = if X::VALUE == 32 { "ilp32" } else { "lp64" };
+    let suffix = if image.is_rve() {
+        "e"
+    } else {
+        match image.e_flags & EF_RISCV_FLOAT_ABI_MASK {
+            EF_RISCV_FLOAT_ABI_SINGLE => "f",
+            EF_RISCV_FLOAT_ABI_DOUBLE => "d",
+            EF_RISCV_FLOAT_ABI_QUAD => "q",
+            _ => "",
+        }
+    };
+    format!("{base}{suffix}")
+}
+
+/// `rwx` with `-` for missing permissions.
+fn permissions(flags: u32) -> String {
+    [(PF_R, 'r'), (PF_W, 'w'), (PF_X, 'x')]
+        .iter()
+        .map(|&(flag, c)| if flags & flag == 0 { '-' } else { c })
+        .collect()
+}
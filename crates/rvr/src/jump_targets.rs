@@ -0,0 +1,203 @@
+//! Dynamic jump targets collected at run time.
+//!
+//! Static analysis cannot resolve every jump table. A run that stops on an
+//! unresolved dynamic jump records its site and target in a
+//! [`JumpTargets`] file (`rvr run --collect-jump-targets`), and a recompile
+//! with [`CompileOptions::with_extra_entry_points`](crate::CompileOptions::with_extra_entry_points)
+//! (`rvr compile --jump-targets`) makes the targets entry points. The
+//! generated code cannot continue past a missing target, so each run adds
+//! at most one; recompile and rerun until the program runs through.
+
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use rvr_emit::c::content_hash;
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, Result};
+
+/// Layout version of the file.
+const JUMP_TARGETS_VERSION: u32 = 1;
+
+/// Unresolved dynamic jumps collected from the runs of one ELF, saved as
+/// JSON:
+///
+/// ```json
+/// {
+///   "version": 1,
+///   "elf": "0123456789abcdef",
+///   "jumps": [{ "site": "0x10078", "target": "0x10200" }]
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct JumpTargets {
+    elf_hash: u64,
+    jumps: BTreeSet<(u64, u64)>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JumpTargetsFile {
+    version: u32,
+    elf: String,
+    jumps: Vec<JumpFile>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JumpFile {
+    site: String,
+    target: String,
+}
+
+impl JumpTargets {
+    /// No jumps yet, for the ELF `elf` (its file contents).
+    #[must_use]
+    pub fn new(elf: &[u8]) -> Self {
+        Self {
+            elf_hash: content_hash(elf),
+            jumps: BTreeSet::new(),
+        }
+    }
+
+    /// Read a saved file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a jump target
+    /// file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        Self::parse(&text)
+            .map_err(|message| Error::InvalidJumpTargets(format!("{}: {message}", path.display())))
+    }
+
+    /// Read the file at `path` to add to, starting over if it does not
+    /// exist or was collected from another ELF.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an existing file cannot be read or parsed.
+    pub fn load_or_new(path: &Path, elf: &[u8]) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::new(elf));
+        }
+        let targets = Self::load(path)?;
+        Ok(if targets.matches(elf) {
+            targets
+        } else {
+            Self::new(elf)
+        })
+    }
+
+    fn parse(text: &str) -> std::result::Result<Self, String> {
+        let file: JumpTargetsFile = serde_json::from_str(text).map_err(|e| e.to_string())?;
+        if file.version > JUMP_TARGETS_VERSION {
+            return Err(format!(
+                "version {}, expected at most {JUMP_TARGETS_VERSION}",
+                file.version
+            ));
+        }
+        let hex = |value: &str| {
+            u64::from_str_radix(value.trim_start_matches("0x"), 16)
+                .map_err(|_| format!("invalid address '{value}'"))
+        };
+        let jumps = file
+            .jumps
+            .iter()
+            .map(|jump| Ok((hex(&jump.site)?, hex(&jump.target)?)))
+            .collect::<std::result::Result<_, String>>()?;
+        Ok(Self {
+            elf_hash: hex(&file.elf)?,
+            jumps,
+        })
+    }
+
+    /// Write the file [`load`](Self::load) reads.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let file = JumpTargetsFile {
+            version: JUMP_TARGETS_VERSION,
+            elf: format!("{:016x}", self.elf_hash),
+            jumps: self
+                .jumps
+                .iter()
+                .map(|&(site, target)| JumpFile {
+                    site: format!("{site:#x}"),
+                    target: format!("{target:#x}"),
+                })
+                .collect(),
+        };
+        let text = serde_json::to_string_pretty(&file).map_err(std::io::Error::other)?;
+        std::fs::write(path, text + "\n")?;
+        Ok(())
+    }
+
+    /// Record a jump from `site` to `target`. Returns false if it was
+    /// already recorded.
+    pub fn insert(&mut self, site: u64, target: u64) -> bool {
+        self.jumps.insert((site, target))
+    }
+
+    /// True if the jumps were collected from the ELF `elf`.
+    #[must_use]
+    pub fn matches(&self, elf: &[u8]) -> bool {
+        self.elf_hash == content_hash(elf)
+    }
+
+    /// The recorded `(site, target)` pairs, by site.
+    pub fn jumps(&self) -> impl Iterator<Item = (u64, u64)> + '_ {
+        self.jumps.iter().copied()
+    }
+
+    /// The distinct targets, ascending.
+    #[must_use]
+    pub fn targets(&self) -> Vec<u64> {
+        let targets: BTreeSet<u64> = self.jumps.iter().map(|&(_, target)| target).collect();
+        targets.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jump_targets_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("jumps.json");
+        let mut targets = JumpTargets::load_or_new(&path, b"elf").unwrap();
+        assert!(targets.insert(0x1010, 0x2000));
+        assert!(targets.insert(0x1000, 0x2000));
+        assert!(!targets.insert(0x1010, 0x2000));
+        targets.save(&path).unwrap();
+
+        let loaded = JumpTargets::load_or_new(&path, b"elf").unwrap();
+        assert_eq!(loaded, targets);
+        assert_eq!(
+            loaded.jumps().collect::<Vec<_>>(),
+            [(0x1000, 0x2000), (0x1010, 0x2000)]
+        );
+        assert_eq!(loaded.targets(), [0x2000]);
+        // Jumps of another ELF are dropped
+        let other = JumpTargets::load_or_new(&path, b"other").unwrap();
+        assert_eq!(other.jumps().count(), 0);
+    }
+
+    #[test]
+    fn test_jump_targets_parse_errors() {
+        let cases = [
+            ("7", "invalid type"),
+            (r#"{"version": 2, "elf": "0", "jumps": []}"#, "version 2"),
+            (
+                r#"{"version": 1, "elf": "0", "jumps": [{"site": "0x1", "target": "zz"}]}"#,
+                "invalid address 'zz'",
+            ),
+        ];
+        for (text, expected) in cases {
+            let err = JumpTargets::parse(text).unwrap_err();
+            assert!(err.contains(expected), "{text}: {err}");
+        }
+    }
+}
@@ -0,0 +1,50 @@
+//! Guest image placement for layout profile checks.
+
+use rvr_elf::{ElfError, ElfImage};
+use rvr_emit::{AddrRange, ImageLayout, ImageSegment};
+use rvr_isa::{Rv32, Rv64, Xlen};
+
+/// Symbol the runtime points `sp` at.
+pub const STACK_TOP_SYMBOL: &str = "__stack_top";
+
+/// Segments, `__stack_top` and initial program break of `image`.
+#[must_use]
+pub fn image_layout<X: Xlen>(image: &ElfImage<X>) -> ImageLayout {
+    ImageLayout {
+        xlen: X::VALUE,
+        segments: image
+            .memory_segments
+            .iter()
+            .map(|segment| ImageSegment {
+                range: AddrRange::new(
+                    X::to_u64(segment.virtual_start),
+                    X::to_u64(segment.virtual_end),
+                ),
+                executable: segment.is_executable()
+                    || segment.has_executable_sections(&image.sections),
+            })
+            .collect(),
+        stack_top: image.lookup_symbol(STACK_TOP_SYMBOL),
+        program_break: X::to_u64(image.get_initial_program_break()),
+    }
+}
+
+/// Parse an ELF file of either XLEN and describe its placement.
+///
+/// Position-independent ELFs are placed at `load_bias` (see
+/// [`ElfImage::parse_with_load_bias`]).
+///
+/// # Errors
+///
+/// Returns an error if the ELF cannot be parsed.
+pub fn elf_layout(data: &[u8], load_bias: Option<u64>) -> Result<ImageLayout, ElfError> {
+    if rvr_elf::get_elf_xlen(data)? == Rv32::VALUE {
+        Ok(image_layout(&ElfImage::<Rv32>::parse_with_load_bias(
+            data, load_bias,
+        )?))
+    } else {
+        Ok(image_layout(&ElfImage::<Rv64>::parse_with_load_bias(
+            data, load_bias,
+        )?))
+    }
+}
@@ -33,8 +33,12 @@
 //! // Free memory
 //! void rv_free_memory(RvState* state);
 //!
-//! // Dispatch table for dynamic jumps
+//! // Dispatch table for dynamic jumps (`DispatchMode::Flat`; `int32_t`
+//! // offsets from the table with `dispatch_table_relative`)
 //! extern const rv_fn dispatch_table[];
+//!
+//! // Per-function block lookup (`DispatchMode::PerFunction`)
+//! rv_fn dispatch_lookup(uint64_t pc);
 //! ```
 //!
 //! ## State Structure
@@ -197,11 +201,27 @@
 //! common extensions (I, M, A, C, Zicsr, Zifencei, Zba, Zbb, Zbs, Zbkb, Zicond).
 
 // Modules
+mod build;
+mod cache;
 mod compile;
+mod coverage;
+mod decode_diagnostics;
 mod error;
+mod guest_test;
+mod host_profile;
+mod inspect;
+mod jump_targets;
+mod layout;
+mod pc_map;
 mod pipeline;
+mod profile;
+mod programs;
+mod progress;
+mod quarantine;
 mod recompiler;
 mod runner;
+mod segment_image;
+mod size_report;
 
 pub mod bench;
 pub mod build_utils;
@@ -214,20 +234,68 @@ pub mod test_support;
 mod tests;
 
 // Re-exports from internal modules
+pub use build::{PartFailure, PartFailureKind, PartTime};
+pub use cache::{ArtifactCache, CACHE_DIR_ENV, CacheStats, CachedBuild};
 pub use compile::{
-    CompileOptions, compile, compile_with_options, lift_to_c, lift_to_c_with_options,
+    CompileOptions, CompileReport, compile, compile_address_modes, compile_with_options,
+    compile_with_report, explain_with_options, lift_to_c, lift_to_c_with_options,
 };
+pub use coverage::{BranchCoverage, Coverage};
+pub use decode_diagnostics::DecodeDiagnostic;
 pub use error::{Error, Result};
-pub use pipeline::{Pipeline, PipelineStats};
+pub use guest_test::{
+    GuestTestError, GuestTestOptions, GuestTestReport, GuestTestResult, PANIC_EXIT_CODE,
+    TestOutcome, list_guest_tests, run_guest_tests,
+};
+pub use host_profile::{
+    DEFAULT_HOST_SAMPLE_INTERVAL, HostCostUnit, HostFunction, HostProfile, HostSample, HostSamples,
+};
+pub use inspect::{
+    DecodeSummary, ElfSummary, InspectOptions, InspectWarning, SegmentSummary, inspect_elf,
+    inspect_image,
+};
+pub use jump_targets::JumpTargets;
+pub use layout::{elf_layout, image_layout};
+pub use pc_map::{guest_pc_at, guest_pc_entries};
+pub use pipeline::{
+    BLOCK_SIZE_BUCKETS, BlockSizeHistogram, CLine, ExplainedInstr, Explanation, FilterSpec,
+    FunctionHotRegs, IrRule, IrViolation, Operand, Pipeline, PipelineStats, SyntheticProgram,
+    TerminatorResolution,
+};
+pub use profile::{BlockProfile, ProfileCounts, ProfiledBlock};
+pub use programs::{PROGRAMS_MANIFEST, ProgramEntry, ProgramManifest};
+pub use progress::{CompilePhase, CompileProgress, ProgressFn};
+pub use quarantine::{LiftFailure, LiftFailureKind};
 pub use recompiler::Recompiler;
-pub use runner::{PerfCounters, RunError, RunResult, RunResultWithPerf, Runner};
+pub use runner::{
+    CancelHandle, CsrHook, ExitReason, Frame, GuestContext, HookFn, InputRecording, JumpSite,
+    PageAccessLog, PerfCounters, RunError, RunPhases, RunResult, RunResultWithPerf, Runner,
+    RvEmbedInfo, RvEmbedSymbol, Snapshot, SyscallFn, TrapCause,
+};
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+pub use runner::{
+    HELPER_ENV, IsolatedRunner, IsolatedSnapshot, IsolatedState, IsolationOptions, serve_isolated,
+};
+pub use size_report::{FunctionSize, SIZE_REPORT, SizeReport};
 
 // Re-exports from dependencies
-pub use rvr_elf::{ElfImage, get_elf_xlen};
-pub use rvr_emit::c::TracerConfig;
+pub use rvr_cfg::BlockTransform;
+pub use rvr_elf::{DEFAULT_LOAD_BIAS, ElfImage, GuestTest, get_elf_xlen};
+pub use rvr_emit::c::{CArtifacts, DedupStats, TracerConfig};
 pub use rvr_emit::{
-    AddressMode, AnalysisMode, Backend, Compiler, EmitConfig, FixedAddressConfig, InstretMode,
-    SyscallMode,
+    AddrRange, AddressMode, AnalysisMode, Backend, CDialect, Compiler, CompilerLauncher,
+    Compression, CsrMode, CustomCsr, DEFAULT_FALLBACK_OPT_LEVEL, DEFAULT_STACK_GUARD,
+    DEFAULT_TARGET_PART_COST, DispatchMode, EmitConfig, FixedAddressConfig, FunctionHook,
+    GuardPolicy, HookKind, HotRegsMode, ImageLayout, ImageSegment, InstretMode, LayoutError,
+    LayoutMismatch, LayoutProfile, LayoutRegions, LayoutSpec, LiftErrorMode, MemoryLayout,
+    SuspendGranularity, SyscallMode,
+};
+pub use rvr_isa::extensions::{
+    CSR_CYCLE, CSR_CYCLEH, CSR_INSTRET, CSR_INSTRETH, CSR_TIME, CSR_TIMEH, counter_csr_value,
 };
-pub use rvr_isa::extensions::{CSR_CYCLE, CSR_INSTRET, CSR_TIME};
-pub use rvr_isa::{Rv32, Rv64, Xlen};
+pub use rvr_isa::syscalls::{BareMetalConfig, EcallAction, SyscallPolicy, UnmappedEcall};
+pub use rvr_isa::{LiftSource, LrScModel, Rv32, Rv64, Xlen};
+pub use rvr_state::HostBuffer;
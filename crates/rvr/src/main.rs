@@ -31,7 +31,8 @@ fn main() {
         "rvr=error"
     } else {
         match &cli.command {
-            Commands::Dev { .. } => "rvr=warn",
+            // Exec shares stdout with the guest
+            Commands::Dev { .. } | Commands::Exec { .. } => "rvr=warn",
             _ => "rvr=info",
         }
     };
@@ -67,6 +67,11 @@ pub fn init() {
         Unit::Count,
         "Total host branch mispredictions"
     );
+    describe_counter!(
+        "rvr_host_dtlb_misses_total",
+        Unit::Count,
+        "Total host data TLB read misses"
+    );
     describe_counter!("rvr_tests_passed_total", Unit::Count, "Total tests passed");
     describe_counter!("rvr_tests_failed_total", Unit::Count, "Total tests failed");
     describe_counter!(
@@ -137,6 +142,9 @@ pub fn record_run(arch: &str, result: &RunResult, perf: Option<&PerfCounters>) {
         if let Some(m) = p.branch_misses {
             counter!("rvr_host_branch_misses_total", &labels).absolute(m);
         }
+        if let Some(m) = p.dtlb_misses {
+            counter!("rvr_host_dtlb_misses_total", &labels).absolute(m);
+        }
         if let Some(ipc) = p.ipc() {
             gauge!("rvr_host_ipc", &labels).set(ipc);
         }
@@ -0,0 +1,124 @@
+//! Resolving host addresses in a compiled library to guest PCs.
+//!
+//! A library compiled with
+//! [`CompileOptions::with_guest_pc_map`](crate::CompileOptions::with_guest_pc_map)
+//! has line tables whose "source" is the `guest_pc.map` sidecar next to it,
+//! one guest instruction per line.
+
+use std::path::Path;
+use std::process::Command;
+
+use rvr_elf::DebugInfo;
+use rvr_emit::c::{GUEST_PC_MAP, guest_pc_from_map};
+
+use crate::{Compiler, Error, Result};
+
+/// Guest PC of the code at `host_addr` in `library`.
+///
+/// `host_addr` is an address in the library file (as `perf` and `objdump`
+/// report it), not a relocated runtime address. Returns `None` if the
+/// address is not in code emitted for a guest instruction.
+///
+/// # Errors
+///
+/// Returns an error if `guest_pc.map` cannot be read from the library's
+/// directory or `llvm-addr2line` fails.
+pub fn guest_pc_at(library: &Path, host_addr: u64) -> Result<Option<u64>> {
+    let dir = library.parent().unwrap_or_else(|| Path::new("."));
+    let map = std::fs::read_to_string(dir.join(GUEST_PC_MAP))?;
+    let addr2line = Compiler::default().addr2line();
+    let debug_info = DebugInfo::load(&library.to_string_lossy(), &[host_addr], &addr2line)
+        .map_err(Error::DebugInfo)?;
+    Ok(debug_info
+        .get(host_addr)
+        .filter(|loc| Path::new(&loc.file).file_name() == Some(GUEST_PC_MAP.as_ref()))
+        .and_then(|loc| guest_pc_from_map(&map, loc.line)))
+}
+
+/// Entry address (in the library file) and guest PC of every guest
+/// instruction in an assembly-backend `library`, from its line table.
+///
+/// # Errors
+///
+/// Returns an error if `guest_pc.map` cannot be read from the library's
+/// directory or `llvm-dwarfdump` fails.
+pub fn guest_pc_entries(library: &Path) -> Result<Vec<(u64, u64)>> {
+    let dir = library.parent().unwrap_or_else(|| Path::new("."));
+    let map = std::fs::read_to_string(dir.join(GUEST_PC_MAP))?;
+    let output = Command::new(Compiler::default().dwarfdump())
+        .arg("--debug-line")
+        .arg(library)
+        .output()?;
+    if !output.status.success() {
+        return Err(Error::DebugInfo(format!(
+            "llvm-dwarfdump failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    let dump = String::from_utf8_lossy(&output.stdout);
+    Ok(guest_pc_map_rows(&dump)
+        .into_iter()
+        .filter_map(|(addr, line)| Some((addr, guest_pc_from_map(&map, line)?)))
+        .collect())
+}
+
+/// `(address, line)` rows of a `llvm-dwarfdump --debug-line` dump whose file
+/// is `guest_pc.map`.
+fn guest_pc_map_rows(dump: &str) -> Vec<(u64, u32)> {
+    let mut map_files = Vec::new();
+    let mut file_index: Option<u32> = None;
+    let mut rows = Vec::new();
+    for line in dump.lines().map(str::trim) {
+        if line.starts_with("debug_line[") {
+            map_files.clear();
+        } else if let Some(rest) = line.strip_prefix("file_names[") {
+            file_index = rest.split(']').next().and_then(|n| n.trim().parse().ok());
+        } else if let Some(name) = line.strip_prefix("name:") {
+            if name.trim().trim_matches('"') == GUEST_PC_MAP {
+                map_files.extend(file_index);
+            }
+        } else if line.starts_with("0x") && !line.contains("end_sequence") {
+            let mut fields = line.split_whitespace();
+            let addr = fields
+                .next()
+                .and_then(|a| u64::from_str_radix(&a[2..], 16).ok());
+            let row_line = fields.next().and_then(|l| l.parse().ok());
+            let file = fields.nth(1).and_then(|f| f.parse::<u32>().ok());
+            if let (Some(addr), Some(row_line), Some(file)) = (addr, row_line, file)
+                && map_files.contains(&file)
+            {
+                rows.push((addr, row_line));
+            }
+        }
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DUMP: &str = r#"
+debug_line[0x00000000]
+Line table prologue:
+include_directories[  0] = "/tmp"
+file_names[  0]:
+           name: "lib.s"
+      dir_index: 0
+file_names[  1]:
+           name: "guest_pc.map"
+      dir_index: 0
+
+Address            Line   Column File   ISA Discriminator OpIndex Flags
+------------------ ------ ------ ------ --- ------------- ------- -------------
+0x0000000000001000      7      0      0   0             0       0  is_stmt
+0x0000000000001010      1      0      1   0             0       0  is_stmt
+0x0000000000001018      2      0      1   0             0       0  is_stmt
+0x0000000000001020      2      0      1   0             0       0  is_stmt end_sequence
+"#;
+
+    #[test]
+    fn test_guest_pc_map_rows() {
+        assert_eq!(guest_pc_map_rows(DUMP), vec![(0x1010, 1), (0x1018, 2)]);
+    }
+}
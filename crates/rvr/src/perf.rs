@@ -12,9 +12,24 @@ use crate::PerfCounters;
 #[cfg(target_os = "linux")]
 mod inner {
     use super::PerfCounters;
-    use perf_event::events::Hardware;
+    use perf_event::events::{Cache, CacheOp, CacheResult, Hardware, WhichCache};
     use perf_event::{Builder, Counter, Group};
 
+    /// Data TLB read misses. Not every PMU has it, so it is optional.
+    const DTLB_READ_MISSES: Cache = Cache {
+        which: WhichCache::DTLB,
+        operation: CacheOp::READ,
+        result: CacheResult::MISS,
+    };
+
+    fn dtlb_counter(inherit: bool) -> Option<Counter> {
+        Builder::new()
+            .kind(DTLB_READ_MISSES)
+            .inherit(inherit)
+            .build()
+            .ok()
+    }
+
     /// Perf counter group for in-process measurement (used by Runner).
     pub struct PerfGroup {
         group: Group,
@@ -22,6 +37,8 @@ mod inner {
         instructions: Counter,
         branches: Counter,
         branch_misses: Counter,
+        // Outside the group, so a PMU short of counters still counts the rest
+        dtlb_misses: Option<Counter>,
     }
 
     impl PerfGroup {
@@ -56,6 +73,7 @@ mod inner {
                 instructions,
                 branches,
                 branch_misses,
+                dtlb_misses: dtlb_counter(false),
             })
         }
 
@@ -64,6 +82,9 @@ mod inner {
         /// # Errors
         /// Returns an error if perf counters cannot be enabled.
         pub fn enable(&mut self) -> std::io::Result<()> {
+            if let Some(counter) = &mut self.dtlb_misses {
+                counter.enable()?;
+            }
             self.group.enable()
         }
 
@@ -72,7 +93,11 @@ mod inner {
         /// # Errors
         /// Returns an error if perf counters cannot be disabled.
         pub fn disable(&mut self) -> std::io::Result<()> {
-            self.group.disable()
+            self.group.disable()?;
+            if let Some(counter) = &mut self.dtlb_misses {
+                counter.disable()?;
+            }
+            Ok(())
         }
 
         /// Reset perf counters.
@@ -80,6 +105,9 @@ mod inner {
         /// # Errors
         /// Returns an error if perf counters cannot be reset.
         pub fn reset(&mut self) -> std::io::Result<()> {
+            if let Some(counter) = &mut self.dtlb_misses {
+                counter.reset()?;
+            }
             self.group.reset()
         }
 
@@ -90,6 +118,7 @@ mod inner {
                 instructions: counts.get(&self.instructions).copied(),
                 branches: counts.get(&self.branches).copied(),
                 branch_misses: counts.get(&self.branch_misses).copied(),
+                dtlb_misses: self.dtlb_misses.as_mut().and_then(|c| c.read().ok()),
             })
         }
     }
@@ -104,6 +133,7 @@ mod inner {
         instructions: Counter,
         branches: Counter,
         branch_misses: Counter,
+        dtlb_misses: Option<Counter>,
     }
 
     impl HostPerfCounters {
@@ -135,6 +165,7 @@ mod inner {
                 instructions,
                 branches,
                 branch_misses,
+                dtlb_misses: dtlb_counter(true),
             })
         }
 
@@ -147,6 +178,9 @@ mod inner {
             self.instructions.enable()?;
             self.branches.enable()?;
             self.branch_misses.enable()?;
+            if let Some(counter) = &mut self.dtlb_misses {
+                counter.enable()?;
+            }
             Ok(())
         }
 
@@ -159,6 +193,9 @@ mod inner {
             self.instructions.disable()?;
             self.branches.disable()?;
             self.branch_misses.disable()?;
+            if let Some(counter) = &mut self.dtlb_misses {
+                counter.disable()?;
+            }
             Ok(())
         }
 
@@ -168,6 +205,7 @@ mod inner {
                 instructions: self.instructions.read().ok(),
                 branches: self.branches.read().ok(),
                 branch_misses: self.branch_misses.read().ok(),
+                dtlb_misses: self.dtlb_misses.as_mut().and_then(|c| c.read().ok()),
             }
         }
 
@@ -197,6 +235,11 @@ mod inner {
                     (Some(c), None) => Some(c),
                     _ => None,
                 },
+                dtlb_misses: match (curr.dtlb_misses, prev.dtlb_misses) {
+                    (Some(c), Some(p)) => Some(c.saturating_sub(p)),
+                    (Some(c), None) => Some(c),
+                    _ => None,
+                },
             }
         }
     }
@@ -1,865 +0,0 @@
-//! Recompilation pipeline - ELF → CFG → IR → C.
-
-use std::collections::HashMap;
-use std::path::Path;
-
-use rvr_cfg::{BlockTable, InstructionTable};
-use rvr_elf::{DebugInfo, ElfImage, MemorySegment as ElfMemorySegment};
-use rvr_emit::arm64::Arm64Emitter;
-use rvr_emit::c::{
-    CProject, HeaderConfig, HtifConfig, MemorySegment as CMemorySegment, SyscallsConfig,
-    gen_header, gen_htif_header, gen_htif_source, gen_syscalls_source, gen_tracer_header,
-};
-use rvr_emit::x86::X86Emitter;
-use rvr_emit::{
-    AnalysisMode, Backend, EmitConfig, EmitInputs, NUM_REGS_E, NUM_REGS_I, SyscallMode,
-};
-use rvr_ir::{BlockIR, InstrIR};
-use rvr_isa::{ExtensionRegistry, Xlen};
-use tracing::{debug, info, info_span, trace_span, warn};
-
-use crate::{Error, Result};
-
-fn u64_to_f64(value: u64) -> f64 {
-    let hi = u32::try_from(value >> 32).unwrap_or(u32::MAX);
-    let lo = u32::try_from(value & 0xFFFF_FFFF).unwrap_or(u32::MAX);
-    f64::from(hi) * 4_294_967_296.0 + f64::from(lo)
-}
-
-fn usize_to_f64(value: usize) -> f64 {
-    let value_u64 = u64::try_from(value).unwrap_or(u64::MAX);
-    u64_to_f64(value_u64)
-}
-
-/// Recompilation pipeline.
-pub struct Pipeline<X: Xlen> {
-    /// ELF image.
-    image: ElfImage<X>,
-    /// Emit configuration.
-    config: EmitConfig<X>,
-    /// Block table (from CFG analysis).
-    block_table: Option<BlockTable<X>>,
-    /// Instruction table (for linear emission).
-    instruction_table: Option<InstructionTable<X>>,
-    /// Lifted IR blocks (keyed by start PC).
-    ir_blocks: HashMap<u64, BlockIR<X>>,
-    /// Lifted IR instructions (linear order).
-    ir_instructions: Vec<InstrIR<X>>,
-    /// Extension registry for decoding and lifting.
-    registry: ExtensionRegistry<X>,
-    /// Extra entry points (e.g., exported function addresses).
-    extra_entry_points: Vec<u64>,
-}
-
-impl<X: Xlen> Pipeline<X> {
-    fn adjust_config_for_image(image: &ElfImage<X>, config: &mut EmitConfig<X>) {
-        if image.is_rve() {
-            debug!(num_regs = NUM_REGS_E, "RVE mode detected");
-            config.num_regs = NUM_REGS_E;
-            config.hot_regs.retain(|&r| (r as usize) < NUM_REGS_E);
-        } else {
-            config.num_regs = NUM_REGS_I;
-        }
-    }
-
-    /// Create a new pipeline with standard extensions.
-    pub fn new(image: ElfImage<X>, config: EmitConfig<X>) -> Self {
-        let mut config = config;
-        debug!(
-            entry_point = format!("{:#x}", X::to_u64(image.entry_point)),
-            segments = image.memory_segments.len(),
-            "loaded ELF"
-        );
-        Self::adjust_config_for_image(&image, &mut config);
-        Self {
-            image,
-            config,
-            block_table: None,
-            instruction_table: None,
-            ir_blocks: HashMap::new(),
-            ir_instructions: Vec::new(),
-            registry: ExtensionRegistry::standard(),
-            extra_entry_points: Vec::new(),
-        }
-    }
-
-    /// Create a new pipeline with custom extension registry.
-    pub fn with_registry(
-        image: ElfImage<X>,
-        config: EmitConfig<X>,
-        registry: ExtensionRegistry<X>,
-    ) -> Self {
-        let mut config = config;
-        debug!(
-            entry_point = format!("{:#x}", X::to_u64(image.entry_point)),
-            segments = image.memory_segments.len(),
-            "loaded ELF"
-        );
-        Self::adjust_config_for_image(&image, &mut config);
-        Self {
-            image,
-            config,
-            block_table: None,
-            instruction_table: None,
-            ir_blocks: HashMap::new(),
-            ir_instructions: Vec::new(),
-            registry,
-            extra_entry_points: Vec::new(),
-        }
-    }
-
-    /// Get reference to ELF image.
-    pub const fn image(&self) -> &ElfImage<X> {
-        &self.image
-    }
-
-    /// Get reference to emit config.
-    pub const fn config(&self) -> &EmitConfig<X> {
-        &self.config
-    }
-
-    /// Get mutable reference to emit config.
-    pub const fn config_mut(&mut self) -> &mut EmitConfig<X> {
-        &mut self.config
-    }
-
-    /// Get reference to block table (if built).
-    pub const fn block_table(&self) -> Option<&BlockTable<X>> {
-        self.block_table.as_ref()
-    }
-
-    /// Get reference to lifted IR blocks.
-    pub const fn ir_blocks(&self) -> &HashMap<u64, BlockIR<X>> {
-        &self.ir_blocks
-    }
-
-    /// Add extra entry points (e.g., exported function addresses).
-    ///
-    /// These addresses will be treated as additional function entry points
-    /// during CFG analysis, ensuring blocks are generated for them.
-    /// Must be called before `build_cfg`.
-    pub fn add_extra_entry_points(&mut self, entry_points: &[u64]) {
-        self.extra_entry_points.extend(entry_points.iter().copied());
-    }
-
-    /// Add function symbols from the ELF as extra entry points.
-    ///
-    /// This is useful for benchmarks where exported functions like `initialize`
-    /// and `run` need to be callable independently.
-    pub fn add_function_symbols_as_entry_points(&mut self) {
-        use rvr_elf::STT_FUNC;
-        let entry_points: Vec<u64> = self
-            .image
-            .symbols
-            .iter()
-            .filter(|s| s.sym_type == STT_FUNC && !s.name.is_empty())
-            .map(|s| X::to_u64(s.value))
-            .collect();
-        self.extra_entry_points.extend(entry_points);
-    }
-
-    fn collect_exec_segments(&self, entry_pc: u64) -> Result<Vec<&ElfMemorySegment<X>>> {
-        let mut exec_segments: Vec<_> = self
-            .image
-            .memory_segments
-            .iter()
-            .filter(|seg| seg.is_executable())
-            .collect();
-
-        if exec_segments.is_empty() {
-            // TODO: Fix upstream riscv-tests/benchmarks/common/test.ld to use FLAGS(5) instead of FLAGS(SHF_ALLOC | SHF_EXECINSTR)
-            exec_segments = self
-                .image
-                .memory_segments
-                .iter()
-                .filter(|seg| seg.has_executable_sections(&self.image.sections))
-                .collect();
-
-            if !exec_segments.is_empty() {
-                debug!(
-                    "No segments have PF_X flag, but found {} segment(s) with executable sections (SHF_EXECINSTR). \
-                     This is likely due to a buggy linker script using section flags instead of program header flags.",
-                    exec_segments.len()
-                );
-            }
-        }
-
-        if exec_segments.is_empty() {
-            return Err(Error::NoCodeSegment(entry_pc));
-        }
-
-        let entry_in_exec = exec_segments.iter().any(|seg| {
-            let start = X::to_u64(seg.virtual_start);
-            let end = X::to_u64(seg.virtual_end);
-            entry_pc >= start && entry_pc < end
-        });
-        if !entry_in_exec {
-            return Err(Error::NoCodeSegment(entry_pc));
-        }
-
-        Ok(exec_segments)
-    }
-
-    fn exec_segments_range(
-        exec_segments: &[&ElfMemorySegment<X>],
-        entry_pc: u64,
-    ) -> Result<(u64, u64)> {
-        let base_address = exec_segments
-            .iter()
-            .map(|seg| X::to_u64(seg.virtual_start))
-            .min()
-            .ok_or(Error::NoCodeSegment(entry_pc))?;
-        let end_address = exec_segments
-            .iter()
-            .map(|seg| X::to_u64(seg.virtual_end))
-            .max()
-            .ok_or(Error::NoCodeSegment(entry_pc))?;
-        Ok((base_address, end_address))
-    }
-
-    fn decode_exec_segments(
-        &self,
-        exec_segments: &[&ElfMemorySegment<X>],
-        instr_table: &mut InstructionTable<X>,
-    ) {
-        let _span = trace_span!("decode_instructions").entered();
-        for seg in exec_segments {
-            let seg_start = X::to_u64(seg.virtual_start);
-            instr_table.populate_segment(&seg.data, seg_start, &self.registry);
-        }
-    }
-
-    fn add_extra_entry_points_to_table(&self, instr_table: &mut InstructionTable<X>) {
-        if !self.extra_entry_points.is_empty() {
-            debug!(
-                count = self.extra_entry_points.len(),
-                "adding extra entry points"
-            );
-            instr_table.add_entry_points(self.extra_entry_points.iter().copied());
-        }
-    }
-
-    fn add_ro_segments_to_table(&self, instr_table: &mut InstructionTable<X>) {
-        for seg in &self.image.memory_segments {
-            if seg.is_readonly() {
-                let seg_start = X::to_u64(seg.virtual_start);
-                let seg_end = X::to_u64(seg.virtual_end);
-                instr_table.add_ro_segment(seg_start, seg_end, seg.data.clone());
-            }
-        }
-    }
-
-    fn insns_per_block(num_instructions: usize, num_blocks: usize) -> f64 {
-        if num_blocks == 0 {
-            return 0.0;
-        }
-        usize_to_f64(num_instructions) / usize_to_f64(num_blocks)
-    }
-
-    fn build_cfg_for_c(&mut self, instr_table: InstructionTable<X>, num_instructions: usize) {
-        let (mut block_table, blocks_before) = match self.config.analysis_mode {
-            AnalysisMode::FullCfg => {
-                let _span = trace_span!("cfg_analysis").entered();
-                let table = BlockTable::from_instruction_table(instr_table, &self.registry);
-                let before = table.len();
-                (table, before)
-            }
-            AnalysisMode::Basic => {
-                debug!("Basic mode: using linear blocks (no CFG analysis)");
-                let table = BlockTable::linear(instr_table);
-                let before = table.len();
-                (table, before)
-            }
-        };
-
-        let (absorbed, tail_duplicated, superblocked) = match self.config.analysis_mode {
-            AnalysisMode::FullCfg => {
-                let _span = trace_span!("block_transforms").entered();
-                if self.config.enable_superblock {
-                    block_table.optimize(&self.registry)
-                } else {
-                    let merged = block_table.merge_blocks(&self.registry);
-                    let tail_duped =
-                        block_table.tail_duplicate(rvr_cfg::DEFAULT_TAIL_DUP_SIZE, &self.registry);
-                    block_table.fix_stale_mappings();
-                    (merged, tail_duped, 0)
-                }
-            }
-            AnalysisMode::Basic => (0, 0, 0),
-        };
-
-        let num_blocks = block_table.len();
-        let insns_per_block = Self::insns_per_block(num_instructions, num_blocks);
-
-        info!(
-            instructions = num_instructions,
-            blocks = num_blocks,
-            insns_per_block = format!("{:.1}", insns_per_block),
-            analysis_mode = ?self.config.analysis_mode,
-            "built CFG"
-        );
-
-        if absorbed > 0 || tail_duplicated > 0 || superblocked > 0 {
-            info!(
-                before = blocks_before,
-                absorbed = absorbed,
-                tail_duplicated = tail_duplicated,
-                superblocked = superblocked,
-                "block transforms"
-            );
-        }
-
-        self.block_table = Some(block_table);
-        self.instruction_table = None;
-    }
-
-    fn build_cfg_for_asm(&mut self, instr_table: InstructionTable<X>, num_instructions: usize) {
-        if self.config.analysis_mode == AnalysisMode::FullCfg {
-            let _span = trace_span!("cfg_analysis").entered();
-            let block_table =
-                BlockTable::from_instruction_table(instr_table.clone(), &self.registry);
-            let num_blocks = block_table.len();
-            let insns_per_block = Self::insns_per_block(num_instructions, num_blocks);
-            info!(
-                instructions = num_instructions,
-                blocks = num_blocks,
-                insns_per_block = format!("{:.1}", insns_per_block),
-                analysis_mode = ?self.config.analysis_mode,
-                "built CFG (linear emission)"
-            );
-            self.block_table = Some(block_table);
-        } else {
-            info!(
-                instructions = num_instructions,
-                analysis_mode = ?self.config.analysis_mode,
-                "built instruction table"
-            );
-            self.block_table = None;
-        }
-        self.instruction_table = Some(instr_table);
-    }
-
-    /// Build CFG: creates `InstructionTable` → `BlockTable` with optimizations.
-    ///
-    /// Builds `InstructionTable` from ALL executable segments, not just the entry segment.
-    ///
-    /// # Errors
-    ///
-    /// Returns `Error::NoCodeSegment` if there are no executable segments or
-    /// the entry point is not within any executable segment.
-    pub fn build_cfg(&mut self) -> Result<()> {
-        let _span = info_span!("build_cfg").entered();
-
-        let entry_pc = X::to_u64(self.image.entry_point);
-        let exec_segments = self.collect_exec_segments(entry_pc)?;
-        let (base_address, end_address) = Self::exec_segments_range(&exec_segments, entry_pc)?;
-
-        debug!(
-            base_address = format!("{:#x}", base_address),
-            end_address = format!("{:#x}", end_address),
-            "address range"
-        );
-
-        // Create InstructionTable spanning all executable segments
-        let mut instr_table = InstructionTable::new(base_address, end_address, entry_pc);
-        self.decode_exec_segments(&exec_segments, &mut instr_table);
-        self.add_extra_entry_points_to_table(&mut instr_table);
-        self.add_ro_segments_to_table(&mut instr_table);
-
-        let num_instructions = instr_table.valid_indices().count();
-
-        if self.config.backend == Backend::C {
-            self.build_cfg_for_c(instr_table, num_instructions);
-        } else {
-            self.build_cfg_for_asm(instr_table, num_instructions);
-        }
-        Ok(())
-    }
-
-    /// Lift all blocks to IR using `BlockTable`.
-    ///
-    /// # Errors
-    ///
-    /// Returns `Error::CfgNotBuilt` if `build_cfg` has not been called.
-    pub fn lift_to_ir(&mut self) -> Result<()> {
-        let _span = info_span!("lift_to_ir").entered();
-
-        let block_table = self
-            .block_table
-            .as_ref()
-            .ok_or(Error::CfgNotBuilt("lift_to_ir"))?;
-
-        // Collect block info first to avoid borrow issues
-        let blocks_info: Vec<_> = block_table.iter().map(|b| (b.start, b.end)).collect();
-        let continuations = block_table.block_continuations.clone();
-
-        // Lift each block from BlockTable, following continuations
-        for (start, end) in blocks_info {
-            let conts = continuations.get(&start);
-            let conts = match self.config.backend {
-                Backend::C => conts,
-                _ => None,
-            };
-            if let Some(block_ir) = self.lift_block_with_continuations(start, end, conts) {
-                self.ir_blocks.insert(start, block_ir);
-            }
-        }
-
-        debug!(blocks = self.ir_blocks.len(), "lifted to IR");
-
-        Ok(())
-    }
-
-    /// Lift all instructions to IR in linear order (no CFG).
-    ///
-    /// # Errors
-    ///
-    /// Returns `Error::CfgNotBuilt` if `build_cfg` has not been called.
-    pub fn lift_to_ir_linear(&mut self) -> Result<()> {
-        let _span = info_span!("lift_to_ir_linear").entered();
-
-        let instr_table = self
-            .instruction_table
-            .as_ref()
-            .ok_or(Error::CfgNotBuilt("lift_to_ir_linear"))?;
-
-        self.ir_instructions.clear();
-        for (_, instr) in instr_table.valid_instructions() {
-            let instr_ir = self.registry.lift(instr);
-            self.ir_instructions.push(instr_ir);
-        }
-
-        debug!(instructions = self.ir_instructions.len(), "lifted to IR");
-
-        Ok(())
-    }
-
-    /// Lift all instructions to IR as single-instruction blocks.
-    ///
-    /// Creates one block per instruction, useful for per-instruction stepping
-    /// where mid-block resume is needed. Each instruction becomes its own block
-    /// with the dispatch table having an entry for every instruction PC.
-    ///
-    /// # Errors
-    ///
-    /// Returns `Error::CfgNotBuilt` if `build_cfg` has not been called.
-    pub fn lift_to_ir_as_single_blocks(&mut self) -> Result<()> {
-        let _span = info_span!("lift_to_ir_as_single_blocks").entered();
-
-        // For C backend, instruction_table is stored inside block_table
-        // For other backends, it's stored directly in self.instruction_table
-        let block_table = self
-            .block_table
-            .as_ref()
-            .ok_or(Error::CfgNotBuilt("lift_to_ir_as_single_blocks"))?;
-
-        self.ir_blocks.clear();
-        for (_, instr) in block_table.instruction_table().valid_instructions() {
-            let instr_ir = self.registry.lift(instr);
-            let pc = X::to_u64(instr_ir.pc);
-            let end_pc = pc + u64::from(instr_ir.size);
-            let mut block = BlockIR::new(instr_ir.pc);
-            block.end_pc = X::from_u64(end_pc);
-            block.instructions.push(instr_ir);
-            self.ir_blocks.insert(pc, block);
-        }
-
-        debug!(
-            blocks = self.ir_blocks.len(),
-            "lifted to IR as single-instruction blocks"
-        );
-
-        Ok(())
-    }
-
-    /// Load debug info and attach source locations to instructions.
-    ///
-    /// Must be called after `lift_to_ir()`. Uses llvm-addr2line to resolve
-    /// instruction addresses to source `<file:line:function>`.
-    ///
-    /// # Arguments
-    ///
-    /// * `elf_path` - Path to the ELF file (needed by addr2line).
-    ///
-    /// # Errors
-    ///
-    /// Returns `Error::CfgNotBuilt` if `lift_to_ir` has not been called.
-    /// Logs a warning if addr2line fails but does not return an error.
-    pub fn load_debug_info(&mut self, elf_path: &str) -> Result<()> {
-        let _span = info_span!("load_debug_info").entered();
-
-        if self.ir_blocks.is_empty() {
-            return Err(Error::CfgNotBuilt("load_debug_info"));
-        }
-
-        // Collect all instruction PCs
-        let addresses: Vec<u64> = self
-            .ir_blocks
-            .values()
-            .flat_map(|block| block.instructions.iter().map(|ir| X::to_u64(ir.pc)))
-            .collect();
-
-        debug!(addresses = addresses.len(), "resolving debug info");
-
-        // Load debug info via addr2line (version derived from compiler)
-        let addr2line_cmd = self.config.compiler.addr2line();
-        let debug_info = match DebugInfo::load(elf_path, &addresses, &addr2line_cmd) {
-            Ok(info) => info,
-            Err(e) => {
-                warn!(error = %e, "failed to load debug info");
-                return Ok(());
-            }
-        };
-
-        if debug_info.is_empty() {
-            debug!("no debug info found");
-            return Ok(());
-        }
-
-        info!(locations = debug_info.len(), "loaded debug info");
-
-        // Attach source locations to instructions
-        for block in self.ir_blocks.values_mut() {
-            for instr in &mut block.instructions {
-                let pc = X::to_u64(instr.pc);
-                if let Some(loc) = debug_info.get(pc) {
-                    instr.set_source_loc(loc.clone());
-                }
-            }
-        }
-
-        Ok(())
-    }
-
-    /// Lift a single block with continuations (absorbed blocks).
-    fn lift_block_with_continuations(
-        &self,
-        start: u64,
-        end: u64,
-        continuations: Option<&Vec<(u64, u64)>>,
-    ) -> Option<BlockIR<X>> {
-        let block_table = self.block_table.as_ref()?;
-        let instr_table = block_table.instruction_table();
-
-        let mut block = BlockIR::new(X::from_u64(start));
-
-        // Build list of ranges to lift: main block + continuations
-        let mut ranges = vec![(start, end)];
-        if let Some(conts) = continuations {
-            ranges.extend(conts.iter().copied());
-        }
-
-        // Lift all ranges
-        for (range_idx, (range_start, range_end)) in ranges.iter().enumerate() {
-            let is_last_range = range_idx == ranges.len() - 1;
-            let mut pc = *range_start;
-
-            while pc < *range_end {
-                // Get decoded instruction from table
-                let Some(instr) = instr_table.get_at_pc(pc) else {
-                    break;
-                };
-
-                let size = u64::from(instr.size);
-
-                // Lift to IR
-                let instr_ir = self.registry.lift(instr);
-
-                // Check if this is a control flow terminator
-                let is_terminator = instr_ir.terminator.is_control_flow();
-
-                block.push(instr_ir);
-                pc += size;
-
-                // Only stop at terminator if this is the LAST range
-                // (Terminators in absorbed ranges are internal jumps/falls)
-                if is_terminator && is_last_range {
-                    break;
-                }
-            }
-        }
-
-        // If block doesn't end with a terminator, add fall-through
-        if !block.is_empty() {
-            let last_term = &block.instructions.last().unwrap().terminator;
-            if !last_term.is_control_flow() {
-                // Mark the fall-through target - use end of last range
-                let final_pc = ranges.last().map_or(end, |(_, e)| *e);
-                if let Some(last_instr) = block.instructions.last_mut() {
-                    last_instr.terminator = rvr_ir::Terminator::Fall {
-                        target: Some(X::from_u64(final_pc)),
-                    };
-                }
-            }
-        }
-
-        if block.is_empty() { None } else { Some(block) }
-    }
-
-    /// Emit C code to output directory using `CProject`.
-    ///
-    /// # Errors
-    ///
-    /// Returns `Error::CfgNotBuilt` if `build_cfg` has not been called.
-    /// Returns `Error::Io` if file writing fails.
-    pub fn emit_c(&mut self, output_dir: &Path, base_name: &str) -> Result<()> {
-        let _span = info_span!("emit_c").entered();
-
-        let block_table = self
-            .block_table
-            .as_ref()
-            .ok_or(Error::CfgNotBuilt("emit_c"))?;
-
-        let entry_point = X::to_u64(self.image.entry_point);
-
-        // Convert memory segments
-        let segments: Vec<CMemorySegment> = self
-            .image
-            .memory_segments
-            .iter()
-            .map(|seg| {
-                let mem_len =
-                    usize::try_from(X::to_u64(seg.virtual_end) - X::to_u64(seg.virtual_start))
-                        .map_err(|_| {
-                            Error::CompilationFailed(
-                                "memory segment size does not fit in host usize".to_string(),
-                            )
-                        })?;
-                Ok(CMemorySegment::new(
-                    X::to_u64(seg.virtual_start),
-                    seg.data.len(),
-                    mem_len,
-                    seg.data.clone(),
-                ))
-            })
-            .collect::<Result<Vec<_>>>()?;
-
-        // Compute text_start (minimum block address) and pc_end (maximum end address) from blocks
-        let text_start = self
-            .ir_blocks
-            .values()
-            .map(|b| X::to_u64(b.start_pc))
-            .min()
-            .unwrap_or(entry_point);
-        let pc_end = self
-            .ir_blocks
-            .values()
-            .map(|b| X::to_u64(b.end_pc))
-            .max()
-            .unwrap_or(0);
-
-        // Get absorbed_to_merged mapping from BlockTable
-        let absorbed_to_merged = block_table.absorbed_to_merged.clone();
-
-        // Get taken_inlines mapping from BlockTable
-        let taken_inlines = block_table.taken_inlines.clone();
-
-        // Build derived emission inputs
-        let initial_brk = X::to_u64(self.image.get_initial_program_break());
-        let mut inputs = EmitInputs::new(entry_point, pc_end)
-            .with_text_start(text_start)
-            .with_initial_brk(initial_brk);
-        inputs
-            .valid_addresses
-            .extend(self.ir_blocks.keys().copied());
-        inputs.absorbed_to_merged = absorbed_to_merged;
-
-        // Create CProject with block transform mappings
-        // Note: compiler is already in self.config, no need to call with_compiler
-        let project = CProject::new(output_dir, base_name, self.config.clone())
-            .with_inputs(inputs)
-            .with_taken_inlines(taken_inlines)
-            .with_segments(segments);
-
-        // Collect blocks sorted by start PC
-        let mut blocks: Vec<&BlockIR<X>> = self.ir_blocks.values().collect();
-        blocks.sort_by_key(|b| X::to_u64(b.start_pc));
-
-        // Clone blocks for write_all (which takes owned)
-        let owned_blocks: Vec<BlockIR<X>> = blocks.into_iter().cloned().collect();
-
-        // Write all files
-        project.write_all(&owned_blocks)?;
-
-        Ok(())
-    }
-
-    fn instruction_range(&self, entry_point: u64) -> (u64, u64) {
-        if self.ir_instructions.is_empty() {
-            return (entry_point, 0);
-        }
-
-        let text_start = self
-            .ir_instructions
-            .iter()
-            .map(|ir| X::to_u64(ir.pc))
-            .min()
-            .unwrap_or(entry_point);
-        let pc_end = self
-            .ir_instructions
-            .iter()
-            .map(|ir| X::to_u64(ir.pc) + u64::from(ir.size))
-            .max()
-            .unwrap_or(0);
-        (text_start, pc_end)
-    }
-
-    /// Emit x86-64 assembly to output directory.
-    ///
-    /// # Errors
-    ///
-    /// Returns `Error::CfgNotBuilt` if `build_cfg` has not been called.
-    /// Returns `Error::Io` if file writing fails.
-    pub fn emit_x86(&mut self, output_dir: &Path, base_name: &str) -> Result<()> {
-        let _span = info_span!("emit_x86").entered();
-
-        if self.ir_instructions.is_empty() {
-            return Err(Error::CfgNotBuilt("emit_x86"));
-        }
-
-        let entry_point = X::to_u64(self.image.entry_point);
-
-        // Build emission inputs
-        let (text_start, pc_end) = self.instruction_range(entry_point);
-        let initial_brk = X::to_u64(self.image.get_initial_program_break());
-        let mut inputs = EmitInputs::new(entry_point, pc_end)
-            .with_text_start(text_start)
-            .with_initial_brk(initial_brk);
-        for instr in &self.ir_instructions {
-            inputs.valid_addresses.insert(X::to_u64(instr.pc));
-        }
-
-        // Create x86 emitter
-        let mut emitter = X86Emitter::new(self.config.clone(), inputs.clone());
-
-        // Generate assembly
-        emitter.generate_instructions(&self.ir_instructions);
-
-        // Create output directory
-        std::fs::create_dir_all(output_dir)?;
-
-        // Write assembly file
-        let asm_path = output_dir.join(format!("{base_name}.s"));
-        emitter.write_asm(&asm_path)?;
-
-        self.write_asm_syscalls_support(output_dir, base_name, &inputs)?;
-
-        info!(output = %asm_path.display(), "wrote x86 assembly");
-
-        Ok(())
-    }
-
-    /// Emit ARM64 assembly to output directory.
-    ///
-    /// # Errors
-    ///
-    /// Returns `Error::CfgNotBuilt` if `build_cfg` has not been called.
-    /// Returns `Error::Io` if file writing fails.
-    pub fn emit_arm64(&mut self, output_dir: &Path, base_name: &str) -> Result<()> {
-        let _span = info_span!("emit_arm64").entered();
-
-        if self.ir_instructions.is_empty() {
-            return Err(Error::CfgNotBuilt("emit_arm64"));
-        }
-
-        let entry_point = X::to_u64(self.image.entry_point);
-
-        // Build emission inputs
-        let (text_start, pc_end) = self.instruction_range(entry_point);
-        let initial_brk = X::to_u64(self.image.get_initial_program_break());
-        let mut inputs = EmitInputs::new(entry_point, pc_end)
-            .with_text_start(text_start)
-            .with_initial_brk(initial_brk);
-        for instr in &self.ir_instructions {
-            inputs.valid_addresses.insert(X::to_u64(instr.pc));
-        }
-
-        // Create ARM64 emitter
-        let mut emitter = Arm64Emitter::new(self.config.clone(), inputs.clone());
-
-        // Generate assembly
-        emitter.generate_instructions(&self.ir_instructions);
-
-        // Create output directory
-        std::fs::create_dir_all(output_dir)?;
-
-        // Write assembly file
-        let asm_path = output_dir.join(format!("{base_name}.s"));
-        emitter.write_asm(&asm_path)?;
-
-        self.write_asm_syscalls_support(output_dir, base_name, &inputs)?;
-
-        info!(output = %asm_path.display(), "wrote ARM64 assembly");
-
-        Ok(())
-    }
-
-    fn write_asm_syscalls_support(
-        &self,
-        output_dir: &Path,
-        base_name: &str,
-        inputs: &EmitInputs,
-    ) -> Result<()> {
-        // HTIF support can be used with any syscall mode (for riscv-tests benchmarks)
-        if self.config.htif_enabled() {
-            // Write header file (needed for HTIF source to compile)
-            let header_cfg = HeaderConfig::new(base_name, &self.config, inputs, Vec::new());
-            let header = gen_header::<X>(&header_cfg);
-            std::fs::write(output_dir.join(format!("{base_name}.h")), header)?;
-
-            let htif_cfg =
-                HtifConfig::new(base_name, true).with_verbose(self.config.htif_verbose());
-            let htif_header = gen_htif_header::<X>(&htif_cfg);
-            std::fs::write(output_dir.join(format!("{base_name}_htif.h")), htif_header)?;
-            let htif_source = gen_htif_source::<X>(&htif_cfg);
-            std::fs::write(output_dir.join(format!("{base_name}_htif.c")), htif_source)?;
-        }
-
-        // Linux syscall mode requires additional support files
-        if self.config.syscall_mode != SyscallMode::Linux {
-            return Ok(());
-        }
-
-        // Write header if not already written for HTIF
-        if !self.config.htif_enabled() {
-            let header_cfg = HeaderConfig::new(base_name, &self.config, inputs, Vec::new());
-            let header = gen_header::<X>(&header_cfg);
-            std::fs::write(output_dir.join(format!("{base_name}.h")), header)?;
-        }
-
-        if !self.config.tracer_config.is_none() {
-            let tracer_header = gen_tracer_header::<X>(&self.config.tracer_config)?;
-            std::fs::write(output_dir.join("rv_tracer.h"), tracer_header)?;
-        }
-
-        let syscalls_cfg = SyscallsConfig::new(base_name, self.config.fixed_addresses.is_some());
-        let syscalls_src = gen_syscalls_source::<X>(&syscalls_cfg);
-        std::fs::write(
-            output_dir.join(format!("{base_name}_syscalls.c")),
-            syscalls_src,
-        )?;
-
-        Ok(())
-    }
-
-    /// Get statistics.
-    pub fn stats(&self) -> PipelineStats {
-        let block_table = self.block_table.as_ref();
-        PipelineStats {
-            num_blocks: self.ir_blocks.len(),
-            num_basic_blocks: block_table.map_or(0, BlockTable::len),
-            num_absorbed: block_table.map_or(0, |b| b.absorbed_to_merged.len()),
-        }
-    }
-}
-
-/// Pipeline statistics.
-#[derive(Debug, Default)]
-pub struct PipelineStats {
-    /// Number of lifted IR blocks.
-    pub num_blocks: usize,
-    /// Number of basic blocks from CFG analysis.
-    pub num_basic_blocks: usize,
-    /// Number of blocks absorbed (merged/tail-duped).
-    pub num_absorbed: usize,
-}
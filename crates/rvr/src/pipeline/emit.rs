@@ -0,0 +1,456 @@
+//! Emitting the lifted IR as C or assembly.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use rvr_emit::arm64::Arm64Emitter;
+use rvr_emit::c::{
+    CArtifacts, CProject, GUEST_PC_MAP, GuestPcLines, HeaderConfig, HtifConfig,
+    MemorySegment as CMemorySegment, PartProgress, SyscallsConfig, gen_header, gen_htif_header,
+    gen_htif_source, gen_syscalls_source, gen_tracer_header,
+};
+use rvr_emit::x86::X86Emitter;
+use rvr_emit::{ABI_HEADER, EmitInputs, gen_abi_header};
+use rvr_ir::BlockIR;
+use rvr_isa::Xlen;
+use tracing::{info, info_span};
+
+use super::Pipeline;
+use crate::progress::CompilePhase;
+use crate::segment_image::{segment_image_path, write_segment_image};
+use crate::{Error, Result};
+
+impl<X: Xlen> Pipeline<X> {
+    /// Emit C code to output directory using `CProject`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::CfgNotBuilt` if `build_cfg` has not been called.
+    /// Returns `Error::Io` if file writing fails.
+    pub fn emit_c(&mut self, output_dir: &Path, base_name: &str) -> Result<()> {
+        let _span = info_span!("emit_c").entered();
+
+        let project = self.c_project(output_dir, base_name)?;
+
+        // Write all files
+        let stats = project.write_all(&self.sorted_blocks())?;
+        if self.config.dedup_blocks() {
+            info!(
+                groups = stats.dedup.groups,
+                aliases = stats.dedup.aliases,
+                bytes_saved = stats.dedup.bytes_saved,
+                "deduplicated identical blocks"
+            );
+        }
+        self.dedup = stats.dedup;
+        info!(
+            parts = stats.partitions,
+            unchanged = stats.unchanged_partitions,
+            "emitted C parts"
+        );
+        self.c_parts = stats.partitions;
+        self.unchanged_c_parts = stats.unchanged_partitions;
+        self.emitted_blocks = stats.blocks;
+
+        self.write_segment_image(output_dir, base_name)
+    }
+
+    /// Render the C project in memory instead of writing it.
+    ///
+    /// Holds the same files `emit_c` writes into an empty directory, except
+    /// the segment image of `lazy_segment_init`; the Makefile is included
+    /// but optional for hosts that compile the sources themselves.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::CfgNotBuilt` if `build_cfg` has not been called.
+    pub fn emit_c_artifacts(&self, base_name: &str) -> Result<CArtifacts> {
+        let _span = info_span!("emit_c_artifacts").entered();
+        let project = self.c_project(Path::new(""), base_name)?;
+        Ok(project.render_all(&self.sorted_blocks())?)
+    }
+
+    /// Lifted blocks sorted by start PC.
+    fn sorted_blocks(&self) -> Vec<BlockIR<X>> {
+        let mut blocks: Vec<BlockIR<X>> = self.ir_blocks.values().cloned().collect();
+        blocks.sort_by_key(|b| X::to_u64(b.start_pc));
+        blocks
+    }
+
+    /// Build the C project for the lifted blocks.
+    pub(super) fn c_project(&self, output_dir: &Path, base_name: &str) -> Result<CProject<X>> {
+        // Synthetic programs have blocks but no block table
+        if self.block_table.is_none() && self.ir_blocks.is_empty() {
+            return Err(Error::CfgNotBuilt("emit_c"));
+        }
+        let block_table = self.block_table.as_ref();
+
+        let entry_point = X::to_u64(self.image.entry_point);
+
+        // Compute text_start (minimum block address) and pc_end (maximum end address)
+        // from guest blocks; helper blocks and filter stubs live outside the
+        // dispatch range
+        let guest_blocks = || {
+            self.ir_blocks.values().filter(|b| {
+                let pc = X::to_u64(b.start_pc);
+                !self.synthetic_blocks.contains_key(&pc)
+                    && !self.filter_stubs.as_ref().is_some_and(|s| s.contains(&pc))
+            })
+        };
+        let text_start = guest_blocks()
+            .map(|b| X::to_u64(b.start_pc))
+            .min()
+            .unwrap_or(entry_point);
+        let pc_end = guest_blocks()
+            .map(|b| X::to_u64(b.end_pc))
+            .max()
+            .unwrap_or(0);
+
+        // Get absorbed_to_merged mapping from BlockTable
+        let absorbed_to_merged = block_table
+            .map(|table| table.absorbed_to_merged.clone())
+            .unwrap_or_default();
+
+        // Get taken_inlines mapping from BlockTable; interpreted blocks are
+        // never inlined into compiled ones
+        let interpreted = self.interpreted_blocks()?;
+        let mut taken_inlines = block_table
+            .map(|table| table.taken_inlines.clone())
+            .unwrap_or_default();
+        taken_inlines.retain(|_, (inline_start, _)| !interpreted.contains(inline_start));
+
+        // Build derived emission inputs
+        let initial_brk = X::to_u64(self.image.get_initial_program_break());
+        let mut inputs = EmitInputs::new(entry_point, pc_end)
+            .with_text_start(text_start)
+            .with_initial_brk(initial_brk)
+            .with_elf_hash(self.elf_hash)
+            .with_memory_layout(self.memory_layout()?);
+        inputs
+            .valid_addresses
+            .extend(self.ir_blocks.keys().copied());
+        if self.config.detect_code_writes() {
+            inputs.code_ranges = self.code_ranges(entry_point);
+        }
+        inputs.vector = self
+            .ir_blocks
+            .values()
+            .flat_map(|b| &b.instructions)
+            .any(super::lift::is_vector);
+        inputs.absorbed_to_merged = absorbed_to_merged;
+        if let Some(table) = block_table {
+            inputs
+                .block_to_function
+                .extend(table.block_to_function.iter().map(|(&b, &f)| (b, f)));
+        }
+        inputs
+            .function_hot_regs
+            .extend(self.selected_function_hot_regs());
+        inputs.synthetic_blocks.clone_from(&self.synthetic_blocks);
+        inputs.cold_blocks.clone_from(&self.cold_blocks);
+        inputs.host_hooks = self.host_hooks();
+        inputs.golden = self.load_golden()?;
+        inputs.interpreted = interpreted;
+        if self.config.emit_guest_pc_map() {
+            inputs.guest_pc_lines = Arc::new(GuestPcLines::new(
+                self.ir_blocks.values(),
+                &self.synthetic_blocks,
+            ));
+        }
+        inputs.quarantined = self
+            .quarantined
+            .iter()
+            .map(|f| (f.block_pc, f.to_string()))
+            .collect();
+        if self.config.export_functions {
+            inputs.exported_functions = self
+                .function_symbols()
+                .filter(|&(_, pc)| inputs.is_valid_address(pc))
+                .map(|(name, pc)| (name.to_string(), pc))
+                .collect();
+        }
+
+        // Create CProject with block transform mappings
+        // Note: compiler is already in self.config, no need to call with_compiler
+        let on_part = self.progress.clone().map(|progress| -> PartProgress {
+            Arc::new(move |done, total| progress.report(CompilePhase::Emit, done, total))
+        });
+        let project = CProject::new(output_dir, base_name, self.config.clone())
+            .with_inputs(inputs)
+            .with_taken_inlines(taken_inlines)
+            .with_part_progress(on_part);
+        // Lazily initialized segments live in the segment image, not the
+        // library; filtered programs do not run
+        if self.config.lazy_segment_init() || self.filter_stubs.is_some() {
+            return Ok(project);
+        }
+        Ok(project.with_segments(self.c_segments()?))
+    }
+
+    /// Recompiled code: each code segment clipped to the guest blocks lifted
+    /// from it, so data sharing a writable segment with the text is not
+    /// included.
+    fn code_ranges(&self, entry_point: u64) -> Vec<(u64, u64)> {
+        let Ok(segments) = self.collect_exec_segments(entry_point) else {
+            return Vec::new();
+        };
+        let mut ranges: Vec<(u64, u64)> = segments
+            .iter()
+            .filter_map(|seg| {
+                let segment = X::to_u64(seg.virtual_start)..X::to_u64(seg.virtual_end);
+                let (start, end) = self
+                    .ir_blocks
+                    .values()
+                    .filter(|b| !self.synthetic_blocks.contains_key(&X::to_u64(b.start_pc)))
+                    .map(|b| (X::to_u64(b.start_pc), X::to_u64(b.end_pc)))
+                    .filter(|(start, _)| segment.contains(start))
+                    .reduce(|(lo, hi), (start, end)| (lo.min(start), hi.max(end)))?;
+                Some((start, end))
+            })
+            .collect();
+        ranges.sort_unstable();
+        ranges
+    }
+
+    /// Memory segments to embed in the C project.
+    fn c_segments(&self) -> Result<Vec<CMemorySegment>> {
+        self.image
+            .memory_segments
+            .iter()
+            .map(|seg| {
+                let mem_len =
+                    usize::try_from(X::to_u64(seg.virtual_end) - X::to_u64(seg.virtual_start))
+                        .map_err(|_| {
+                            Error::CompilationFailed(
+                                "memory segment size does not fit in host usize".to_string(),
+                            )
+                        })?;
+                Ok(CMemorySegment::new(
+                    X::to_u64(seg.virtual_start),
+                    seg.data.len(),
+                    mem_len,
+                    seg.data.clone(),
+                ))
+            })
+            .collect()
+    }
+
+    /// Write the segment image the runner maps guest memory from, when
+    /// `lazy_segment_init` is set.
+    ///
+    /// Reports how much of the segment data maps on any host; the partial
+    /// pages at segment edges are copied at load instead.
+    fn write_segment_image(&self, output_dir: &Path, base_name: &str) -> Result<()> {
+        if !self.config.lazy_segment_init() {
+            return Ok(());
+        }
+        let path = segment_image_path(output_dir, base_name);
+        let stats = write_segment_image(&path, &self.image)?;
+        info!(
+            path = %path.display(),
+            mapped_bytes = stats.mapped_bytes,
+            copied_bytes = stats.copied_bytes,
+            "wrote segment image"
+        );
+        Ok(())
+    }
+
+    fn instruction_range(&self, entry_point: u64) -> (u64, u64) {
+        if self.ir_instructions.is_empty() {
+            return (entry_point, 0);
+        }
+
+        let text_start = self
+            .ir_instructions
+            .iter()
+            .map(|ir| X::to_u64(ir.pc))
+            .min()
+            .unwrap_or(entry_point);
+        let pc_end = self
+            .ir_instructions
+            .iter()
+            .map(|ir| X::to_u64(ir.pc) + u64::from(ir.size))
+            .max()
+            .unwrap_or(0);
+        (text_start, pc_end)
+    }
+
+    /// Emit x86-64 assembly to output directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::CfgNotBuilt` if `build_cfg` has not been called.
+    /// Returns `Error::Io` if file writing fails.
+    pub fn emit_x86(&mut self, output_dir: &Path, base_name: &str) -> Result<()> {
+        let _span = info_span!("emit_x86").entered();
+
+        if self.ir_instructions.is_empty() {
+            return Err(Error::CfgNotBuilt("emit_x86"));
+        }
+
+        let entry_point = X::to_u64(self.image.entry_point);
+
+        // Build emission inputs
+        let (text_start, pc_end) = self.instruction_range(entry_point);
+        let initial_brk = X::to_u64(self.image.get_initial_program_break());
+        let mut inputs = EmitInputs::new(entry_point, pc_end)
+            .with_text_start(text_start)
+            .with_initial_brk(initial_brk)
+            .with_elf_hash(self.elf_hash)
+            .with_memory_layout(self.memory_layout()?);
+        for instr in &self.ir_instructions {
+            inputs.valid_addresses.insert(X::to_u64(instr.pc));
+        }
+        self.add_asm_guest_pc_lines(&mut inputs);
+
+        // Create x86 emitter
+        let mut emitter = X86Emitter::new(self.config.clone(), inputs.clone());
+
+        // Generate assembly
+        emitter.generate_instructions(&self.ir_instructions);
+
+        // Create output directory
+        std::fs::create_dir_all(output_dir)?;
+
+        // Write assembly file
+        let asm_path = output_dir.join(format!("{base_name}.s"));
+        emitter.write_asm(&asm_path)?;
+        std::fs::write(output_dir.join(ABI_HEADER), gen_abi_header(&self.config))?;
+
+        self.write_asm_syscalls_support(output_dir, base_name, &inputs)?;
+        self.write_segment_image(output_dir, base_name)?;
+        if self.config.emit_guest_pc_map() {
+            std::fs::write(
+                output_dir.join(GUEST_PC_MAP),
+                inputs.guest_pc_lines.render(),
+            )?;
+        }
+
+        info!(output = %asm_path.display(), "wrote x86 assembly");
+
+        Ok(())
+    }
+
+    /// Emit ARM64 assembly to output directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::CfgNotBuilt` if `build_cfg` has not been called.
+    /// Returns `Error::Io` if file writing fails.
+    pub fn emit_arm64(&mut self, output_dir: &Path, base_name: &str) -> Result<()> {
+        let _span = info_span!("emit_arm64").entered();
+
+        if self.ir_instructions.is_empty() {
+            return Err(Error::CfgNotBuilt("emit_arm64"));
+        }
+
+        let entry_point = X::to_u64(self.image.entry_point);
+
+        // Build emission inputs
+        let (text_start, pc_end) = self.instruction_range(entry_point);
+        let initial_brk = X::to_u64(self.image.get_initial_program_break());
+        let mut inputs = EmitInputs::new(entry_point, pc_end)
+            .with_text_start(text_start)
+            .with_initial_brk(initial_brk)
+            .with_elf_hash(self.elf_hash)
+            .with_memory_layout(self.memory_layout()?);
+        for instr in &self.ir_instructions {
+            inputs.valid_addresses.insert(X::to_u64(instr.pc));
+        }
+        self.add_asm_guest_pc_lines(&mut inputs);
+
+        // Create ARM64 emitter
+        let mut emitter = Arm64Emitter::new(self.config.clone(), inputs.clone());
+
+        // Generate assembly
+        emitter.generate_instructions(&self.ir_instructions);
+
+        // Create output directory
+        std::fs::create_dir_all(output_dir)?;
+
+        // Write assembly file
+        let asm_path = output_dir.join(format!("{base_name}.s"));
+        emitter.write_asm(&asm_path)?;
+        std::fs::write(output_dir.join(ABI_HEADER), gen_abi_header(&self.config))?;
+
+        self.write_asm_syscalls_support(output_dir, base_name, &inputs)?;
+        self.write_segment_image(output_dir, base_name)?;
+        if self.config.emit_guest_pc_map() {
+            std::fs::write(
+                output_dir.join(GUEST_PC_MAP),
+                inputs.guest_pc_lines.render(),
+            )?;
+        }
+
+        info!(output = %asm_path.display(), "wrote ARM64 assembly");
+
+        Ok(())
+    }
+
+    /// Number the linear instruction stream for the guest PC map.
+    fn add_asm_guest_pc_lines(&self, inputs: &mut EmitInputs) {
+        if self.config.emit_guest_pc_map() {
+            inputs.guest_pc_lines = Arc::new(GuestPcLines::for_instructions(
+                &self.ir_instructions,
+                &HashMap::new(),
+            ));
+        }
+    }
+
+    fn write_asm_syscalls_support(
+        &self,
+        output_dir: &Path,
+        base_name: &str,
+        inputs: &EmitInputs,
+    ) -> Result<()> {
+        // HTIF support can be used with any syscall mode (for riscv-tests benchmarks)
+        if self.config.htif_enabled() {
+            // Write header file (needed for HTIF source to compile)
+            let header_cfg = HeaderConfig::new(base_name, &self.config, inputs, Vec::new());
+            let header = gen_header::<X>(&header_cfg);
+            std::fs::write(output_dir.join(format!("{base_name}.h")), header)?;
+
+            let htif_cfg = HtifConfig::new(base_name, true)
+                .with_dialect(self.config.c_dialect)
+                .with_verbose(self.config.htif_verbose())
+                .with_poll_limit(self.config.htif_poll_limit);
+            let htif_header = gen_htif_header::<X>(&htif_cfg);
+            std::fs::write(output_dir.join(format!("{base_name}_htif.h")), htif_header)?;
+            let htif_source = gen_htif_source::<X>(&htif_cfg);
+            std::fs::write(output_dir.join(format!("{base_name}_htif.c")), htif_source)?;
+        }
+
+        // The syscall runtime requires additional support files
+        if !self.config.syscall_runtime() {
+            return Ok(());
+        }
+
+        // Write header if not already written for HTIF
+        if !self.config.htif_enabled() {
+            let header_cfg = HeaderConfig::new(base_name, &self.config, inputs, Vec::new());
+            let header = gen_header::<X>(&header_cfg);
+            std::fs::write(output_dir.join(format!("{base_name}.h")), header)?;
+        }
+
+        if !self.config.tracer_config.is_none() {
+            let tracer_header = gen_tracer_header::<X>(
+                &self.config.tracer_config,
+                self.config.memory_bits,
+                &(inputs.text_start..inputs.pc_end),
+                inputs.elf_hash,
+                self.config.c_dialect,
+            )?;
+            std::fs::write(output_dir.join("rv_tracer.h"), tracer_header)?;
+        }
+
+        let syscalls_cfg = SyscallsConfig::new(base_name, self.config.fixed_addresses.is_some());
+        let syscalls_src = gen_syscalls_source::<X>(&syscalls_cfg);
+        std::fs::write(
+            output_dir.join(format!("{base_name}_syscalls.c")),
+            syscalls_src,
+        )?;
+
+        Ok(())
+    }
+}
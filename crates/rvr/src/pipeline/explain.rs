@@ -0,0 +1,423 @@
+//! Explaining how the C for a block was produced.
+//!
+//! Collects what the pipeline already knows about one block: how each
+//! instruction was decoded and lifted, which block transforms shaped it, how
+//! its terminator was resolved, which operands live in hot registers, and the
+//! emitted C mapped back to guest instructions.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+use rvr_cfg::BlockTransform;
+use rvr_emit::c::CEmitter;
+use rvr_ir::{BlockIR, Terminator};
+use rvr_isa::{InstrArgs, LiftSource, Xlen, reg_name};
+
+use super::Pipeline;
+use crate::{Error, Result};
+
+/// How a block's terminator was resolved.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TerminatorResolution {
+    /// Falls through to the next block.
+    Fall { target: Option<u64> },
+    /// Jumps to a static target.
+    Direct { target: u64 },
+    /// Conditional branch; `inlined` if the taken path is inlined.
+    Branch {
+        taken: u64,
+        fall: Option<u64>,
+        inlined: bool,
+    },
+    /// Indirect jump whose targets CFG analysis recovered.
+    Recovered { targets: Vec<u64> },
+    /// Indirect jump CFG analysis could not resolve.
+    Unresolved,
+    /// Exits the program.
+    Exit,
+    /// Traps with a message.
+    Trap { message: String },
+}
+
+impl fmt::Display for TerminatorResolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Fall {
+                target: Some(target),
+            } => write!(f, "fall through to {target:#x}"),
+            Self::Fall { target: None } => write!(f, "fall through"),
+            Self::Direct { target } => write!(f, "direct jump to {target:#x}"),
+            Self::Branch {
+                taken,
+                fall,
+                inlined,
+            } => {
+                write!(f, "branch to {taken:#x}")?;
+                if *inlined {
+                    write!(f, " (taken path inlined)")?;
+                }
+                if let Some(fall) = fall {
+                    write!(f, ", else {fall:#x}")?;
+                }
+                Ok(())
+            }
+            Self::Recovered { targets } => {
+                write!(f, "indirect jump, {} targets recovered:", targets.len())?;
+                for target in targets {
+                    write!(f, " {target:#x}")?;
+                }
+                Ok(())
+            }
+            Self::Unresolved => write!(f, "indirect jump, unresolved (dispatch table)"),
+            Self::Exit => write!(f, "exit"),
+            Self::Trap { message } => write!(f, "trap: {message}"),
+        }
+    }
+}
+
+/// A register operand of a guest instruction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Operand {
+    /// Register index.
+    pub reg: u8,
+    /// Whether the register is passed in a hot register.
+    pub hot: bool,
+}
+
+/// A guest instruction of an explained block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExplainedInstr {
+    /// Instruction PC.
+    pub pc: u64,
+    /// Raw instruction bits.
+    pub raw: u32,
+    /// Size in bytes.
+    pub size: u8,
+    /// Disassembly.
+    pub disasm: String,
+    /// Extension that decoded the instruction.
+    pub extension: &'static str,
+    /// Lifter that produced the IR.
+    pub lifted_by: LiftSource,
+    /// Helper blocks the lifter emitted alongside the instruction.
+    pub helper_blocks: usize,
+    /// Register operands (excluding `x0`).
+    pub operands: Vec<Operand>,
+}
+
+/// A line of emitted C.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CLine {
+    /// Guest instruction the line was emitted for (`None` for block framing).
+    pub pc: Option<u64>,
+    /// Line text.
+    pub text: String,
+}
+
+/// Everything the pipeline decided for the block containing a PC.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Explanation {
+    /// PC that was asked about.
+    pub pc: u64,
+    /// Block start.
+    pub block_start: u64,
+    /// Block end (exclusive; absorbed blocks may lie elsewhere).
+    pub block_end: u64,
+    /// Instructions in emission order.
+    pub instructions: Vec<ExplainedInstr>,
+    /// Block transforms that absorbed other blocks into this one.
+    pub transforms: Vec<BlockTransform>,
+    /// Lift error if the block was quarantined.
+    pub quarantined: Option<String>,
+    /// Terminator resolution.
+    pub terminator: TerminatorResolution,
+    /// Hot registers.
+    pub hot_regs: Vec<u8>,
+    /// Emitted C (empty for non-C backends).
+    pub code: Vec<CLine>,
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "block {:#x}..{:#x} (contains {:#x})",
+            self.block_start, self.block_end, self.pc
+        )?;
+        if let Some(error) = &self.quarantined {
+            writeln!(f, "quarantined: {error}")?;
+        }
+
+        writeln!(f, "\ninstructions:")?;
+        for instr in &self.instructions {
+            writeln!(
+                f,
+                "  {:#x}: {:0width$x}  {}",
+                instr.pc,
+                instr.raw,
+                instr.disasm,
+                width = usize::from(instr.size) * 2
+            )?;
+            write!(
+                f,
+                "      decoded by {}, lifted by {}",
+                instr.extension, instr.lifted_by
+            )?;
+            if instr.helper_blocks > 0 {
+                write!(f, " with {} helper blocks", instr.helper_blocks)?;
+            }
+            let operands: Vec<_> = instr
+                .operands
+                .iter()
+                .map(|op| {
+                    format!(
+                        "{} ({})",
+                        reg_name(op.reg),
+                        if op.hot { "hot" } else { "cold" }
+                    )
+                })
+                .collect();
+            if !operands.is_empty() {
+                write!(f, "; operands: {}", operands.join(", "))?;
+            }
+            writeln!(f)?;
+        }
+
+        writeln!(f, "\ntransforms:")?;
+        if self.transforms.is_empty() {
+            writeln!(f, "  none")?;
+        }
+        for transform in &self.transforms {
+            let (kind, (start, end)) = match transform {
+                BlockTransform::Merged { .. } => ("merged", transform.range()),
+                BlockTransform::TailDuplicated { .. } => ("tail-duplicated", transform.range()),
+                BlockTransform::Superblock { .. } => ("superblock", transform.range()),
+            };
+            writeln!(f, "  {kind} {start:#x}..{end:#x}")?;
+        }
+
+        writeln!(f, "\nterminator: {}", self.terminator)?;
+        let hot: Vec<_> = self.hot_regs.iter().map(|&reg| reg_name(reg)).collect();
+        writeln!(f, "hot registers: {}", hot.join(" "))?;
+
+        if !self.code.is_empty() {
+            writeln!(f, "\nemitted C:")?;
+            for line in &self.code {
+                match line.pc {
+                    Some(pc) => writeln!(f, "{pc:>12x} | {}", line.text)?,
+                    None => writeln!(f, "{:>12} | {}", "", line.text)?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Register operands of a decoded instruction, excluding `x0`.
+fn operand_regs(args: &InstrArgs) -> Vec<u8> {
+    let regs: &[u8] = match args {
+        InstrArgs::R { rd, rs1, rs2 } | InstrArgs::Amo { rd, rs1, rs2, .. } => &[*rd, *rs1, *rs2],
+        InstrArgs::R4 { rd, rs1, rs2, rs3 } => &[*rd, *rs1, *rs2, *rs3],
+        InstrArgs::I { rd, rs1, .. } | InstrArgs::Csr { rd, rs1, .. } => &[*rd, *rs1],
+        InstrArgs::S { rs1, rs2, .. } | InstrArgs::B { rs1, rs2, .. } => &[*rs1, *rs2],
+        InstrArgs::U { rd, .. } | InstrArgs::J { rd, .. } | InstrArgs::CsrI { rd, .. } => &[*rd],
+        InstrArgs::None | InstrArgs::Custom(_) => &[],
+    };
+    let mut unique = Vec::new();
+    for &reg in regs {
+        if reg != 0 && !unique.contains(&reg) {
+            unique.push(reg);
+        }
+    }
+    unique
+}
+
+/// Split rendered C into lines, each tagged with the instruction it came from.
+fn annotate_lines(code: &str, origins: &[(usize, Option<u64>)]) -> Vec<CLine> {
+    let mut offset = 0;
+    code.trim_end()
+        .lines()
+        .map(|text| {
+            let pc = origins
+                .iter()
+                .take_while(|&&(start, _)| start <= offset)
+                .last()
+                .and_then(|&(_, pc)| pc);
+            offset += text.len() + 1;
+            CLine {
+                pc,
+                text: text.to_string(),
+            }
+        })
+        .collect()
+}
+
+impl<X: Xlen> Pipeline<X> {
+    /// Explain how the block containing `pc` was lifted and emitted.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::CfgNotBuilt` if the IR has not been lifted, and
+    /// `Error::NoBlockAtPc` if no lifted block contains `pc`.
+    pub fn explain(&self, pc: u64) -> Result<Explanation> {
+        let block_table = self
+            .block_table
+            .as_ref()
+            .filter(|_| !self.ir_blocks.is_empty())
+            .ok_or(Error::CfgNotBuilt("explain"))?;
+        let block = self.block_containing(pc).ok_or(Error::NoBlockAtPc(pc))?;
+        let block_start = X::to_u64(block.start_pc);
+        let instr_table = block_table.instruction_table();
+
+        let mut helper_blocks: HashMap<u64, usize> = HashMap::new();
+        for info in self.synthetic_blocks.values() {
+            *helper_blocks.entry(info.owner_pc).or_default() += 1;
+        }
+
+        let instructions = block
+            .instructions
+            .iter()
+            .filter_map(|ir| instr_table.get_at_pc(X::to_u64(ir.pc)))
+            .map(|decoded| {
+                let pc = X::to_u64(decoded.pc);
+                ExplainedInstr {
+                    pc,
+                    raw: decoded.raw,
+                    size: decoded.size,
+                    disasm: self.registry.disasm(decoded),
+                    extension: self
+                        .registry
+                        .extensions()
+                        .iter()
+                        .find(|ext| ext.ext_id() == decoded.opid.ext)
+                        .map_or("?", |ext| ext.name()),
+                    lifted_by: self.registry.lift_source(decoded.opid),
+                    helper_blocks: helper_blocks.get(&pc).copied().unwrap_or(0),
+                    operands: operand_regs(&decoded.args)
+                        .into_iter()
+                        .map(|reg| Operand {
+                            reg,
+                            hot: self.config.is_hot_reg(reg),
+                        })
+                        .collect(),
+                }
+            })
+            .collect();
+
+        let last = block.instructions.last().ok_or(Error::NoBlockAtPc(pc))?;
+        let last_pc = X::to_u64(last.pc);
+        let terminator = match &last.terminator {
+            Terminator::Fall { target } => TerminatorResolution::Fall {
+                target: target.map(X::to_u64),
+            },
+            Terminator::Jump { target } => TerminatorResolution::Direct {
+                target: X::to_u64(*target),
+            },
+            Terminator::Branch { target, fall, .. } => TerminatorResolution::Branch {
+                taken: X::to_u64(*target),
+                fall: fall.map(X::to_u64),
+                inlined: block_table.taken_inlines.contains_key(&last_pc),
+            },
+            Terminator::JumpDyn { .. } if block_table.unresolved_jumps.contains(&last_pc) => {
+                TerminatorResolution::Unresolved
+            }
+            Terminator::JumpDyn { resolved, .. } => {
+                let mut targets: Vec<u64> = resolved.as_ref().map_or_else(
+                    || {
+                        block_table
+                            .successors
+                            .get(&last_pc)
+                            .map(|s| s.iter().copied().collect())
+                            .unwrap_or_default()
+                    },
+                    |resolved| resolved.iter().map(|&t| X::to_u64(t)).collect(),
+                );
+                targets.sort_unstable();
+                TerminatorResolution::Recovered { targets }
+            }
+            Terminator::Exit { .. } => TerminatorResolution::Exit,
+            Terminator::Trap { message } => TerminatorResolution::Trap {
+                message: message.clone(),
+            },
+        };
+
+        Ok(Explanation {
+            pc,
+            block_start,
+            block_end: X::to_u64(block.end_pc),
+            instructions,
+            transforms: block_table.transforms(block_start).to_vec(),
+            quarantined: self
+                .quarantined
+                .iter()
+                .find(|failure| failure.block_pc == block_start)
+                .map(ToString::to_string),
+            terminator,
+            hot_regs: self.config.hot_regs.clone(),
+            code: self.explain_c(block)?,
+        })
+    }
+
+    /// Lifted guest block containing `pc`, preferring the nearest start.
+    fn block_containing(&self, pc: u64) -> Option<&BlockIR<X>> {
+        self.ir_blocks
+            .values()
+            .filter(|block| {
+                !self
+                    .synthetic_blocks
+                    .contains_key(&X::to_u64(block.start_pc))
+            })
+            .filter(|block| {
+                block.instructions.iter().any(|ir| {
+                    let start = X::to_u64(ir.pc);
+                    (start..start + u64::from(ir.size)).contains(&pc)
+                })
+            })
+            .min_by_key(|block| {
+                let start = X::to_u64(block.start_pc);
+                (start > pc, pc.abs_diff(start))
+            })
+    }
+
+    /// Render `block` as `emit_c` would, tagging each line with its instruction.
+    fn explain_c(&self, block: &BlockIR<X>) -> Result<Vec<CLine>> {
+        if self.config.backend != rvr_emit::Backend::C {
+            return Ok(Vec::new());
+        }
+        let project = self.c_project(Path::new("."), "explain")?;
+        let block_map: HashMap<u64, &BlockIR<X>> = self
+            .ir_blocks
+            .iter()
+            .map(|(&pc, block)| (pc, block))
+            .collect();
+        let mut emitter = CEmitter::new(project.config.clone(), project.inputs.clone());
+        let code = project.render_block(&mut emitter, block, &block_map)?;
+        Ok(annotate_lines(&code, emitter.origins()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_operand_regs() {
+        let args = InstrArgs::R {
+            rd: 5,
+            rs1: 5,
+            rs2: 0,
+        };
+        assert_eq!(operand_regs(&args), [5]);
+        assert!(operand_regs(&InstrArgs::None).is_empty());
+    }
+
+    #[test]
+    fn test_annotate_lines() {
+        let code = "void B(void) {\n    a;\n    b;\n}\n\n";
+        let lines = annotate_lines(code, &[(15, Some(0x10)), (22, Some(0x14)), (29, None)]);
+        let pcs: Vec<_> = lines.iter().map(|line| line.pc).collect();
+        assert_eq!(pcs, [None, Some(0x10), Some(0x14), None]);
+        assert_eq!(lines[1].text, "    a;");
+    }
+}
@@ -0,0 +1,200 @@
+//! Filtered lifts for reading the C of a few functions.
+//!
+//! [`Pipeline::apply_filter`] keeps the lifted blocks of the selected
+//! functions and PC ranges and drops the rest. Static jumps and branches
+//! out of the selection land on trap stubs that stop with "left filtered
+//! region", dynamic jumps dispatch through a table of the kept blocks
+//! only, and the project is emitted as one part file without segment
+//! data. The C still compiles standalone, but the program is not runnable
+//! end to end: filtered lifts are for reading or `clang -S`.
+
+use std::collections::HashSet;
+use std::ops::Range;
+
+use rvr_emit::Backend;
+use rvr_ir::{BlockIR, Terminator};
+use rvr_isa::Xlen;
+use tracing::info;
+
+use super::Pipeline;
+use crate::quarantine::trap_stub;
+use crate::{Error, Result};
+
+/// Trap message of the stubs standing in for dropped blocks.
+const LEFT_FILTERED_REGION: &str = "left filtered region";
+
+/// Functions and PC ranges a filtered lift keeps.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FilterSpec {
+    /// Function symbols whose blocks are kept.
+    pub symbols: Vec<String>,
+    /// PC ranges whose blocks are kept, by block start.
+    pub ranges: Vec<Range<u64>>,
+}
+
+impl FilterSpec {
+    /// Empty filter; add functions or ranges to select anything.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also keep the blocks of function `symbol`.
+    #[must_use]
+    pub fn with_symbol(mut self, symbol: impl Into<String>) -> Self {
+        self.symbols.push(symbol.into());
+        self
+    }
+
+    /// Also keep the blocks starting in `range`.
+    #[must_use]
+    pub fn with_range(mut self, range: Range<u64>) -> Self {
+        self.ranges.push(range);
+        self
+    }
+
+    /// Whether the filter selects nothing.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.symbols.is_empty() && self.ranges.is_empty()
+    }
+}
+
+impl<X: Xlen> Pipeline<X> {
+    /// Keep only the blocks `filter` selects (C backend).
+    ///
+    /// Call after lifting and before `emit_c`. A block is kept if it starts
+    /// in one of the ranges or in the extent of one of the functions, or
+    /// CFG analysis assigned it to one of the functions. See the module
+    /// docs for what the emitted project can and cannot do.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidFilter` for another backend, an empty
+    /// filter, a name without a function symbol, or a filter that keeps no
+    /// lifted block.
+    pub fn apply_filter(&mut self, filter: &FilterSpec) -> Result<()> {
+        if self.config.backend != Backend::C {
+            return Err(Error::InvalidFilter(
+                "only the C backend can be filtered".to_string(),
+            ));
+        }
+        if filter.is_empty() {
+            return Err(Error::InvalidFilter(
+                "no functions or ranges given".to_string(),
+            ));
+        }
+        let mut ranges = filter.ranges.clone();
+        let mut entries = HashSet::new();
+        for name in &filter.symbols {
+            let (start, end) = self
+                .image
+                .lookup_function_range(name)
+                .ok_or_else(|| Error::InvalidFilter(format!("no function symbol '{name}'")))?;
+            entries.insert(start);
+            ranges.push(start..end);
+        }
+
+        let block_to_function = self.block_table.as_ref().map(|t| &t.block_to_function);
+        let selected = |pc: u64| {
+            ranges.iter().any(|range| range.contains(&pc))
+                || block_to_function
+                    .and_then(|map| map.get(&pc))
+                    .is_some_and(|entry| entries.contains(entry))
+        };
+        let mut kept: HashSet<u64> = self
+            .ir_blocks
+            .keys()
+            .copied()
+            .filter(|pc| !self.synthetic_blocks.contains_key(pc) && selected(*pc))
+            .collect();
+        if kept.is_empty() {
+            return Err(Error::InvalidFilter(
+                "the filter keeps no lifted block".to_string(),
+            ));
+        }
+        // Override helpers go with the block of their guest instruction
+        let helpers: Vec<u64> = self
+            .synthetic_blocks
+            .iter()
+            .filter(|(_, info)| {
+                kept.iter().any(|pc| {
+                    let end = X::to_u64(self.ir_blocks[pc].end_pc);
+                    (*pc..end).contains(&info.owner_pc)
+                })
+            })
+            .map(|(&pc, _)| pc)
+            .collect();
+        kept.extend(helpers);
+
+        let functions = kept
+            .iter()
+            .filter_map(|pc| block_to_function.and_then(|map| map.get(pc)))
+            .collect::<HashSet<_>>()
+            .len();
+        // Jump targets that name a dropped block, directly or absorbed
+        let mut dropped: HashSet<u64> = self
+            .ir_blocks
+            .keys()
+            .copied()
+            .filter(|pc| !kept.contains(pc))
+            .collect();
+        self.ir_blocks.retain(|pc, _| kept.contains(pc));
+        self.synthetic_blocks.retain(|pc, _| kept.contains(pc));
+        self.cold_blocks.retain(|pc| kept.contains(pc));
+        if let Some(table) = &mut self.block_table {
+            table.absorbed_to_merged.retain(|&pc, merged| {
+                let keep = kept.contains(merged);
+                if !keep {
+                    dropped.insert(pc);
+                }
+                keep
+            });
+        }
+
+        let exits: HashSet<u64> = self
+            .ir_blocks
+            .values()
+            .flat_map(static_targets)
+            .filter(|target| dropped.contains(target))
+            .collect();
+        for &pc in &exits {
+            self.ir_blocks
+                .insert(pc, trap_stub(pc, LEFT_FILTERED_REGION));
+        }
+
+        // One part file, and no segment data: the program does not run
+        self.config.target_part_cost = usize::MAX;
+        info!(
+            functions,
+            blocks = kept.len(),
+            stubs = exits.len(),
+            "filtered lifted blocks"
+        );
+        self.filter_stubs = Some(exits);
+        Ok(())
+    }
+}
+
+/// Static successors of the instructions of `block`, and the block end if
+/// the last one falls through without a target.
+fn static_targets<X: Xlen>(block: &BlockIR<X>) -> Vec<u64> {
+    let mut targets = Vec::new();
+    for instr in &block.instructions {
+        match &instr.terminator {
+            Terminator::Fall {
+                target: Some(target),
+            }
+            | Terminator::Jump { target } => targets.push(*target),
+            Terminator::Branch { target, fall, .. } => {
+                targets.push(*target);
+                targets.extend(*fall);
+            }
+            _ => {}
+        }
+    }
+    if matches!(block.terminator(), Some(Terminator::Fall { target: None })) {
+        targets.push(block.end_pc);
+    }
+    targets.into_iter().map(X::to_u64).collect()
+}
@@ -0,0 +1,53 @@
+//! Embedded golden traces (`EmitConfig::embedded_golden`).
+//!
+//! A golden trace written by the golden tracer is read at emission and
+//! compiled into the library, which checks the register checksums at the
+//! sampled block entries against it (see `rvr_emit::c::GoldenTrace`).
+
+use std::sync::Arc;
+
+use rvr_emit::Backend;
+use rvr_emit::c::GoldenTrace;
+use rvr_isa::Xlen;
+
+use super::Pipeline;
+use crate::{Error, Result};
+
+impl<X: Xlen> Pipeline<X> {
+    /// Check that an embedded golden trace can be checked by the backend.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::CompilationFailed` if a golden trace is embedded with
+    /// a non-C backend.
+    pub(super) fn check_golden(&self) -> Result<()> {
+        if self.config.embedded_golden.is_some() && self.config.backend != Backend::C {
+            return Err(Error::CompilationFailed(
+                "embedded golden traces require the C backend".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Read the golden trace to embed, if one is configured.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Io` if the file cannot be read and
+    /// `Error::InvalidGolden` if it is not a golden trace of this ELF.
+    pub(super) fn load_golden(&self) -> Result<Option<Arc<GoldenTrace>>> {
+        let Some(path) = &self.config.embedded_golden else {
+            return Ok(None);
+        };
+        let invalid =
+            |message: String| Error::InvalidGolden(format!("{}: {message}", path.display()));
+        let trace = GoldenTrace::parse(&std::fs::read(path)?).map_err(invalid)?;
+        if trace.elf_hash != self.elf_hash {
+            return Err(invalid(format!(
+                "captured from another ELF (hash {:#018x}, expected {:#018x})",
+                trace.elf_hash, self.elf_hash
+            )));
+        }
+        Ok(Some(Arc::new(trace)))
+    }
+}
@@ -0,0 +1,126 @@
+//! Guest function hooks (`EmitConfig::function_hooks`).
+//!
+//! Hooked functions are found by symbol name before CFG analysis, which
+//! treats their entry as a return and so does not descend into the body.
+//! The entry instruction is then lifted as the hook: a call to the
+//! generated `rv_hook_<symbol>` and a return through `ra`, a guest exit, or
+//! just the return. The body is still lifted where something else (a code
+//! pointer in data, a jump from elsewhere) reaches it.
+
+use rvr_cfg::InstructionTable;
+use rvr_emit::{Backend, FunctionHook, HookKind};
+use rvr_ir::{Expr, InstrIR, Stmt, Terminator};
+use rvr_isa::{DecodedInstr, REG_RA, Xlen};
+use tracing::{debug, info};
+
+use super::Pipeline;
+use crate::{Error, Result};
+
+impl<X: Xlen> Pipeline<X> {
+    /// Resolve the configured hooks to the functions' entry PCs and mark
+    /// the functions hooked in `instr_table`. Symbols the ELF does not
+    /// define are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::CompilationFailed` if a host-call hook is used with
+    /// a non-C backend or its symbol cannot be passed to the host.
+    pub(super) fn find_function_hooks(
+        &mut self,
+        instr_table: &mut InstructionTable<X>,
+    ) -> Result<()> {
+        self.function_hooks.clear();
+        for hook in &self.config.function_hooks {
+            if hook.kind == HookKind::HostCall {
+                if self.config.backend != Backend::C {
+                    return Err(Error::CompilationFailed(format!(
+                        "function hook `{}` calls the host, which requires the C backend",
+                        hook.symbol
+                    )));
+                }
+                if !hook.has_literal_symbol() {
+                    return Err(Error::CompilationFailed(format!(
+                        "function hook symbol {:?} is not printable ASCII",
+                        hook.symbol
+                    )));
+                }
+            }
+            let Some((entry, end)) = self.image.lookup_function_range(&hook.symbol) else {
+                debug!(symbol = hook.symbol, "hooked function not found");
+                continue;
+            };
+            debug!(
+                symbol = hook.symbol,
+                pc = format!("{entry:#x}"),
+                kind = ?hook.kind,
+                "function hook"
+            );
+            instr_table.add_hooked_function(entry, end);
+            self.function_hooks.insert(entry, hook.clone());
+        }
+        info!(hooked = self.function_hooks.len(), "function hooks");
+        Ok(())
+    }
+
+    /// Hooks served by the host as `(hook, entry pc)`, sorted by PC.
+    pub(super) fn host_hooks(&self) -> Vec<(FunctionHook, u64)> {
+        let mut hooks: Vec<(FunctionHook, u64)> = self
+            .function_hooks
+            .iter()
+            .filter(|(_, hook)| hook.kind == HookKind::HostCall)
+            .map(|(&pc, hook)| (hook.clone(), pc))
+            .collect();
+        hooks.sort_unstable_by_key(|&(_, pc)| pc);
+        hooks
+    }
+}
+
+/// The entry instruction of a hooked function.
+pub(super) fn hook_instr<X: Xlen>(hook: &FunctionHook, instr: &DecodedInstr<X>) -> InstrIR<X> {
+    let ret = Terminator::jump_dyn(Expr::and(Expr::read(REG_RA), Expr::imm(X::from_u64(!1u64))));
+    let (statements, terminator) = match hook.kind {
+        HookKind::HostCall => (
+            vec![Stmt::extern_call(&hook.fn_name(), vec![Expr::var("state")])],
+            ret,
+        ),
+        HookKind::Replace(code) => (
+            Vec::new(),
+            Terminator::exit(Expr::imm(X::from_u64(u64::from(code)))),
+        ),
+        HookKind::Nop => (Vec::new(), ret),
+    };
+    InstrIR::new(
+        instr.pc,
+        instr.size,
+        instr.opid.pack(),
+        instr.raw,
+        statements,
+        terminator,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use rvr_isa::{InstrArgs, OpId, Rv64};
+
+    use super::*;
+
+    #[test]
+    fn test_hook_instr() {
+        let instr = DecodedInstr::<Rv64>::new(OpId::new(0, 0), 0x1000, 4, 0, InstrArgs::None);
+        let host = hook_instr(&FunctionHook::new("checksum", HookKind::HostCall), &instr);
+        let [Stmt::ExternCall { fn_name, args }] = host.statements.as_slice() else {
+            panic!("expected one call: {:?}", host.statements);
+        };
+        assert_eq!(fn_name, "rv_hook_checksum");
+        assert_eq!(args.len(), 1);
+        assert!(host.terminator.is_dyn_jump());
+
+        let nop = hook_instr(&FunctionHook::new("checksum", HookKind::Nop), &instr);
+        assert!(nop.statements.is_empty());
+        assert!(nop.terminator.is_dyn_jump());
+
+        let replace = hook_instr(&FunctionHook::new("abort", HookKind::Replace(3)), &instr);
+        assert!(matches!(replace.terminator, Terminator::Exit { .. }));
+    }
+}
@@ -0,0 +1,121 @@
+//! Per-function hot register selection (`HotRegsMode::PerFunction`).
+//!
+//! The global hot registers suit the program on average, but leaf math
+//! wants argument and temporary registers while call-heavy code wants
+//! `ra`/`sp`/`s0`/`s1`. Each CFG function instead gets the registers its
+//! blocks access most, in the same number of slots (see
+//! `EmitConfig::function_hot_regs`). The C backend spills and reloads the
+//! registers that differ where control moves between functions.
+
+use std::collections::BTreeMap;
+
+use rvr_emit::HotRegsMode;
+use rvr_ir::{RegAccesses, reg_accesses};
+use rvr_isa::{Xlen, reg_name};
+use tracing::{debug, info};
+
+use super::Pipeline;
+
+/// Hot registers selected for one function.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FunctionHotRegs {
+    /// Entry PC (from CFG analysis).
+    pub entry: u64,
+    /// ELF symbol of the function, if any.
+    pub name: Option<String>,
+    /// Selected hot registers, in slot order.
+    pub hot_regs: Vec<u8>,
+    /// Predicted spills: static register accesses left to the state struct.
+    pub spills: u64,
+    /// Predicted spills with the global hot registers.
+    pub global_spills: u64,
+}
+
+impl FunctionHotRegs {
+    /// Whether the selection differs from the global hot registers.
+    #[must_use]
+    pub fn is_own_set(&self, global: &[u8]) -> bool {
+        self.hot_regs != global
+    }
+}
+
+/// Static accesses to registers outside `hot_regs`.
+fn state_accesses(accesses: &RegAccesses, hot_regs: &[u8]) -> u64 {
+    accesses
+        .iter()
+        .enumerate()
+        .skip(1)
+        .filter(|&(reg, _)| !hot_regs.iter().any(|&hot| usize::from(hot) == reg))
+        .map(|(_, &count)| count)
+        .sum()
+}
+
+impl<X: Xlen> Pipeline<X> {
+    /// Hot registers per function as `HotRegsMode::PerFunction` selects
+    /// them, by entry PC.
+    ///
+    /// Empty before `build_cfg`, and for linear analysis, which has no
+    /// functions.
+    pub fn function_hot_regs(&self) -> Vec<FunctionHotRegs> {
+        let Some(table) = self.block_table.as_ref() else {
+            return Vec::new();
+        };
+        let mut accesses: BTreeMap<u64, RegAccesses> = BTreeMap::new();
+        for (pc, block) in &self.ir_blocks {
+            let Some(&entry) = table.block_to_function.get(pc) else {
+                continue;
+            };
+            let function = accesses.entry(entry).or_insert([0; 32]);
+            for (reg, count) in reg_accesses(block).into_iter().enumerate() {
+                function[reg] = function[reg].saturating_add(count);
+            }
+        }
+        accesses
+            .into_iter()
+            .map(|(entry, accesses)| {
+                let hot_regs = self.config.function_hot_regs(&accesses);
+                FunctionHotRegs {
+                    entry,
+                    name: self.image.function_containing(entry).map(str::to_string),
+                    spills: state_accesses(&accesses, &hot_regs),
+                    global_spills: state_accesses(&accesses, &self.config.hot_regs),
+                    hot_regs,
+                }
+            })
+            .collect()
+    }
+
+    /// Hot registers of the functions whose selection differs from the
+    /// global one, for `EmitInputs::function_hot_regs`; empty in
+    /// `HotRegsMode::Global`.
+    pub(super) fn selected_function_hot_regs(&self) -> Vec<(u64, Vec<u8>)> {
+        if self.config.hot_regs_mode != HotRegsMode::PerFunction {
+            return Vec::new();
+        }
+        let functions = self.function_hot_regs();
+        for function in &functions {
+            let names: Vec<_> = function.hot_regs.iter().map(|&reg| reg_name(reg)).collect();
+            debug!(
+                entry = format!("{:#x}", function.entry),
+                name = function.name.as_deref().unwrap_or("?"),
+                hot_regs = ?names,
+                spills = function.spills,
+                global_spills = function.global_spills,
+                "function hot registers"
+            );
+        }
+        let global = &self.config.hot_regs;
+        info!(
+            functions = functions.len(),
+            own_sets = functions.iter().filter(|f| f.is_own_set(global)).count(),
+            spills = functions.iter().map(|f| f.spills).sum::<u64>(),
+            global_spills = functions.iter().map(|f| f.global_spills).sum::<u64>(),
+            "selected per-function hot registers"
+        );
+        functions
+            .into_iter()
+            .filter(|f| f.is_own_set(global))
+            .map(|f| (f.entry, f.hot_regs))
+            .collect()
+    }
+}
@@ -0,0 +1,94 @@
+//! Interpreted blocks (`EmitConfig::interpret_blocks`).
+//!
+//! A block the C compiler cannot handle need not sink the whole compile: it
+//! can be run by the block interpreter instead (see
+//! `rvr_emit::c::gen_interp_source`), which executes the original
+//! instruction bits and continues at the next block through the dispatch
+//! table. Only blocks of plain extension instructions qualify; see
+//! [`Pipeline::interpret_error`].
+
+use std::collections::{BTreeSet, HashSet};
+
+use rvr_emit::Backend;
+use rvr_emit::c::{first_uninterpretable, partition_file_name};
+use rvr_isa::{LiftSource, OpId, Xlen, op_mnemonic};
+
+use super::Pipeline;
+use crate::{Error, Result};
+
+impl<X: Xlen> Pipeline<X> {
+    /// Check that interpreted blocks can be run by the backend.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::CompilationFailed` if blocks are interpreted with a
+    /// non-C backend.
+    pub(super) fn check_interpreted(&self) -> Result<()> {
+        let interprets =
+            !self.config.interpret_blocks.is_empty() || self.config.interpret_failed_blocks();
+        if interprets && self.config.backend != Backend::C {
+            return Err(Error::CompilationFailed(
+                "interpreted blocks require the C backend".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Why the block at `pc` cannot be interpreted, if it cannot.
+    #[must_use]
+    pub fn interpret_error(&self, pc: u64) -> Option<String> {
+        let Some(block) = self.ir_blocks.get(&pc) else {
+            return Some("not the start of a lifted block".to_string());
+        };
+        if self.synthetic_blocks.contains_key(&pc) {
+            return Some("an override helper block".to_string());
+        }
+        if self.config.tracer_config.has_passed_vars() {
+            return Some("the tracer passes variables between blocks".to_string());
+        }
+        let overridden = block.instructions.iter().find(|instr| {
+            let [ext, idx] = instr.op.to_be_bytes();
+            !matches!(
+                self.registry.lift_source(OpId::new(ext, idx)),
+                LiftSource::Extension(_)
+            )
+        });
+        overridden
+            .or_else(|| first_uninterpretable(block))
+            .map(|instr| {
+                format!(
+                    "{} at {:#x} is not supported by the interpreter",
+                    op_mnemonic(instr.op),
+                    X::to_u64(instr.pc)
+                )
+            })
+    }
+
+    /// Blocks the last `emit_c` wrote into the part files `files` (named by
+    /// `partition_file_name`).
+    #[must_use]
+    pub fn blocks_in_parts(&self, base_name: &str, files: &HashSet<&str>) -> Vec<u64> {
+        self.emitted_blocks
+            .iter()
+            .filter(|block| files.contains(partition_file_name(base_name, block.part).as_str()))
+            .map(|block| block.pc)
+            .collect()
+    }
+
+    /// Blocks to interpret, checked to be interpretable.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::CompilationFailed` naming the first configured block
+    /// that cannot be interpreted.
+    pub(super) fn interpreted_blocks(&self) -> Result<BTreeSet<u64>> {
+        for &pc in &self.config.interpret_blocks {
+            if let Some(reason) = self.interpret_error(pc) {
+                return Err(Error::CompilationFailed(format!(
+                    "block {pc:#x} cannot be interpreted: {reason}"
+                )));
+            }
+        }
+        Ok(self.config.interpret_blocks.clone())
+    }
+}
@@ -0,0 +1,118 @@
+//! Native memory intrinsics (`EmitConfig::native_mem_intrinsics`).
+//!
+//! The guest's `memcpy`, `memset` and `memcmp` are found by symbol name and
+//! their first instruction is lifted as `a0 = rv_memcpy(state, a0, a1, a2)`
+//! (and so on) followed by a return through `ra`. The rest of the function
+//! is still lifted but no longer reached through its entry. Replacing the
+//! instruction rather than the block keeps the replacement wherever the CFG
+//! copies the entry, e.g. into a tail-duplicated `tail memset`.
+
+use rvr_emit::Backend;
+use rvr_emit::c::MemIntrinsic;
+use rvr_ir::{Expr, InstrIR, OverrideExpansion, Stmt, Terminator};
+use rvr_isa::{DecodedInstr, REG_A0, REG_RA, Xlen};
+use tracing::{debug, info};
+
+use super::Pipeline;
+use super::hooks::hook_instr;
+use crate::{Error, Result};
+
+impl<X: Xlen> Pipeline<X> {
+    /// Find the functions to replace, if `native_mem_intrinsics` is set.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::CompilationFailed` if intrinsics are requested with a
+    /// non-C backend or with tracing, whose traces would change.
+    pub(super) fn find_mem_intrinsics(&mut self) -> Result<()> {
+        self.mem_intrinsics.clear();
+        if !self.config.native_mem_intrinsics() {
+            return Ok(());
+        }
+        if self.config.backend != Backend::C {
+            return Err(Error::CompilationFailed(
+                "native memory intrinsics require the C backend".to_string(),
+            ));
+        }
+        if self.config.has_tracing() {
+            return Err(Error::CompilationFailed(
+                "native memory intrinsics would change the trace; disable the tracer".to_string(),
+            ));
+        }
+        for intrinsic in MemIntrinsic::ALL {
+            if let Some(pc) = self.image.lookup_function(intrinsic.symbol()) {
+                debug!(
+                    symbol = intrinsic.symbol(),
+                    pc = format!("{pc:#x}"),
+                    "native intrinsic"
+                );
+                self.mem_intrinsics.insert(pc, intrinsic);
+            }
+        }
+        info!(
+            replaced = self.mem_intrinsics.len(),
+            "native memory intrinsics"
+        );
+        Ok(())
+    }
+
+    /// Lift `instr` under the configured LR/SC model, replacing the entry
+    /// of a hooked function or a native intrinsic.
+    pub(super) fn lift_instr(&self, instr: &DecodedInstr<X>) -> OverrideExpansion<X> {
+        if let Some(hook) = self.function_hooks.get(&X::to_u64(instr.pc)) {
+            return OverrideExpansion::new(hook_instr(hook, instr));
+        }
+        if let Some(&intrinsic) = self.mem_intrinsics.get(&X::to_u64(instr.pc)) {
+            return OverrideExpansion::new(intrinsic_call(intrinsic, instr));
+        }
+        let mut expansion = self.registry.lift_expanded(instr);
+        let model = self.config.lrsc_model;
+        model.apply(&mut expansion.primary.statements);
+        for helper in &mut expansion.helpers {
+            model.apply(&mut helper.statements);
+        }
+        expansion
+    }
+}
+
+/// `a0 = <helper>(state, a0, a1, a2)`, then return to `ra`.
+fn intrinsic_call<X: Xlen>(intrinsic: MemIntrinsic, instr: &DecodedInstr<X>) -> InstrIR<X> {
+    let args = std::iter::once(Expr::var("state"))
+        .chain((REG_A0..REG_A0 + 3).map(Expr::read))
+        .collect();
+    let width = u8::try_from(X::REG_BYTES * 8).expect("register width fits u8");
+    InstrIR::new(
+        instr.pc,
+        instr.size,
+        instr.opid.pack(),
+        instr.raw,
+        vec![Stmt::write_reg(
+            REG_A0,
+            Expr::extern_call(intrinsic.runtime_fn(), args, width),
+        )],
+        Terminator::jump_dyn(Expr::and(Expr::read(REG_RA), Expr::imm(X::from_u64(!1u64)))),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use rvr_isa::{InstrArgs, OpId, Rv64};
+
+    use super::*;
+
+    #[test]
+    fn test_intrinsic_call() {
+        let instr = DecodedInstr::<Rv64>::new(OpId::new(0, 0), 0x1000, 2, 0, InstrArgs::None);
+        let ir = intrinsic_call(MemIntrinsic::Memcmp, &instr);
+        assert_eq!((ir.pc, ir.size), (0x1000, 2));
+        let [Stmt::Write { value, .. }] = ir.statements.as_slice() else {
+            panic!("expected one write: {:?}", ir.statements);
+        };
+        let Expr::ExternCall { name, args, .. } = value else {
+            panic!("expected an extern call: {value:?}");
+        };
+        assert_eq!(name, "rv_memcmp");
+        assert_eq!(args.len(), 4);
+        assert!(ir.terminator.is_dyn_jump());
+    }
+}
@@ -0,0 +1,547 @@
+//! Lifting the block table to IR.
+//!
+//! Instructions are lifted in parallel; override helper blocks are then placed
+//! sequentially in block order, so synthetic PCs (and the emitted code) do not
+//! depend on the number of threads.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use rayon::prelude::*;
+use rvr_cfg::{ParallelTime, timed};
+use rvr_emit::{AddressMode, Backend, LiftErrorMode, MAX_VLEN, valid_vlen};
+use rvr_ir::{
+    BlockIR, InstrIR, OverrideExpansion, SyntheticBlocks, Terminator, TrappingAccesses,
+    eliminate_dead_reg_writes,
+};
+use rvr_isa::{EXT_V, Xlen};
+use tracing::{debug, info, info_span, warn};
+
+use super::{Pipeline, helpers_unsupported};
+use crate::decode_diagnostics::{find_decode_diagnostics, format_table};
+use crate::progress::{CompilePhase, ProgressFn};
+use crate::quarantine::{find_lift_failures, quarantine_blocks};
+use crate::{Error, Result};
+
+/// A block lifted to expansions whose helper blocks are not placed yet.
+struct LiftedBlock<X: Xlen> {
+    start: u64,
+    /// Fall-through target if the block does not end in control flow.
+    fall_through: u64,
+    expansions: Vec<OverrideExpansion<X>>,
+}
+
+impl<X: Xlen> Pipeline<X> {
+    /// Lift all blocks to IR using `BlockTable`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::CfgNotBuilt` if `build_cfg` has not been called.
+    /// Returns `Error::CompilationFailed` if override helper blocks are used
+    /// with a non-C backend or exhaust the synthetic PC range, native
+    /// memory intrinsics are used with tracing, the tracer is sampled or a
+    /// golden trace embedded with a non-C backend, or vector instructions
+    /// are used with a non-C backend or an unsupported VLEN.
+    /// Returns `Error::UnsupportedInstructions` if an instruction would trap
+    /// and `strict_decode` is set.
+    /// Returns `Error::LiftFailed` if a block fails to lift and
+    /// `on_lift_error` is `Abort`.
+    /// Returns `Error::InvalidIr` if the lifted IR breaks an invariant (see
+    /// [`verify_ir`](Self::verify_ir)), checked in debug builds and with
+    /// `EmitConfig::verify_ir`.
+    pub fn lift_to_ir(&mut self) -> Result<()> {
+        let _span = info_span!("lift_to_ir").entered();
+        let started = Instant::now();
+        self.find_mem_intrinsics()?;
+        self.check_sampled_tracer()?;
+        self.check_golden()?;
+        self.check_interpreted()?;
+
+        let block_table = self
+            .block_table
+            .as_ref()
+            .ok_or(Error::CfgNotBuilt("lift_to_ir"))?;
+
+        // Lift each block from BlockTable, following continuations
+        let use_continuations = self.config.backend == Backend::C;
+        let (lifted, time) = self.on_analysis_pool(|| {
+            timed_par_map(&block_table.blocks, self.progress.as_ref(), |block| {
+                let conts = block_table
+                    .block_continuations
+                    .get(&block.start)
+                    .filter(|_| use_continuations);
+                self.lift_block_with_continuations(block.start, block.end, conts)
+            })
+        });
+        self.parallel_time += time;
+
+        let mut synthetic = SyntheticBlocks::default();
+        for lifted in lifted.into_iter().flatten() {
+            let start = lifted.start;
+            if let Some(block_ir) = self.place_block(lifted, &mut synthetic)? {
+                self.ir_blocks.insert(start, block_ir);
+            }
+        }
+        self.insert_synthetic_blocks(synthetic)?;
+        let reached = reached_pcs(self.ir_blocks.values().flat_map(|b| &b.instructions));
+        self.diagnose_decode(reached)?;
+        self.check_vector(self.ir_blocks.values().flat_map(|b| &b.instructions))?;
+        self.handle_lift_failures()?;
+        self.eliminate_dead_writes();
+        if cfg!(debug_assertions) || self.config.verify_ir() {
+            self.verify_ir().map_err(Error::InvalidIr)?;
+        }
+
+        debug!(blocks = self.ir_blocks.len(), "lifted to IR");
+        self.record_lift_time(started.elapsed());
+
+        Ok(())
+    }
+
+    /// Lift all instructions to IR in linear order (no CFG).
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::CfgNotBuilt` if `build_cfg` has not been called.
+    /// Returns `Error::CompilationFailed` if an override emits helper blocks,
+    /// native memory intrinsics are requested, the tracer is sampled, a
+    /// golden trace is embedded or vector instructions are used, which only
+    /// the C backend supports.
+    /// Returns `Error::UnsupportedInstructions` if an instruction would trap
+    /// and `strict_decode` is set.
+    pub fn lift_to_ir_linear(&mut self) -> Result<()> {
+        let _span = info_span!("lift_to_ir_linear").entered();
+        let started = Instant::now();
+        self.find_mem_intrinsics()?;
+        self.check_sampled_tracer()?;
+        self.check_golden()?;
+        self.check_interpreted()?;
+
+        let instr_table = self
+            .instruction_table
+            .as_ref()
+            .ok_or(Error::CfgNotBuilt("lift_to_ir_linear"))?;
+
+        let instrs: Vec<_> = instr_table.valid_instructions().map(|(_, i)| i).collect();
+        let (expansions, time) = self.on_analysis_pool(|| {
+            timed_par_map(&instrs, self.progress.as_ref(), |instr| {
+                self.lift_instr(instr)
+            })
+        });
+        self.parallel_time += time;
+
+        self.ir_instructions.clear();
+        for expansion in expansions {
+            if expansion.has_helpers() {
+                return Err(helpers_unsupported(&expansion));
+            }
+            self.ir_instructions.push(expansion.primary);
+        }
+        self.diagnose_decode(reached_pcs(self.ir_instructions.iter()))?;
+        self.check_vector(self.ir_instructions.iter())?;
+
+        debug!(instructions = self.ir_instructions.len(), "lifted to IR");
+        self.record_lift_time(started.elapsed());
+
+        Ok(())
+    }
+
+    /// Lift all instructions to IR as single-instruction blocks.
+    ///
+    /// Creates one block per instruction, useful for per-instruction stepping
+    /// where mid-block resume is needed. Each instruction becomes its own block
+    /// with the dispatch table having an entry for every instruction PC.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::CfgNotBuilt` if `build_cfg` has not been called.
+    /// Returns `Error::CompilationFailed` if override helper blocks exhaust
+    /// the synthetic PC range, native memory intrinsics are used with
+    /// tracing, the tracer is sampled or a golden trace embedded with a
+    /// non-C backend, or vector instructions are used with a non-C backend
+    /// or an unsupported VLEN.
+    /// Returns `Error::UnsupportedInstructions` if an instruction would trap
+    /// and `strict_decode` is set.
+    /// Returns `Error::LiftFailed` if an instruction fails to lift and
+    /// `on_lift_error` is `Abort`.
+    pub fn lift_to_ir_as_single_blocks(&mut self) -> Result<()> {
+        let _span = info_span!("lift_to_ir_as_single_blocks").entered();
+        let started = Instant::now();
+        self.find_mem_intrinsics()?;
+        self.check_sampled_tracer()?;
+        self.check_golden()?;
+        self.check_interpreted()?;
+
+        // For C backend, instruction_table is stored inside block_table
+        // For other backends, it's stored directly in self.instruction_table
+        let block_table = self
+            .block_table
+            .as_ref()
+            .ok_or(Error::CfgNotBuilt("lift_to_ir_as_single_blocks"))?;
+
+        let instrs: Vec<_> = block_table
+            .instruction_table()
+            .valid_instructions()
+            .map(|(_, i)| i)
+            .collect();
+        let (expansions, time) = self.on_analysis_pool(|| {
+            timed_par_map(&instrs, self.progress.as_ref(), |instr| {
+                self.lift_instr(instr)
+            })
+        });
+        self.parallel_time += time;
+
+        self.ir_blocks.clear();
+        let mut synthetic = SyntheticBlocks::default();
+        for expansion in expansions {
+            let instr_ir = self.place_expansion(expansion, &mut synthetic)?;
+            let pc = X::to_u64(instr_ir.pc);
+            let end_pc = pc + u64::from(instr_ir.size);
+            let mut block = BlockIR::new(instr_ir.pc);
+            block.end_pc = X::from_u64(end_pc);
+            block.instructions.push(instr_ir);
+            self.ir_blocks.insert(pc, block);
+        }
+        self.insert_synthetic_blocks(synthetic)?;
+        let reached = reached_pcs(self.ir_blocks.values().flat_map(|b| &b.instructions));
+        self.diagnose_decode(reached)?;
+        self.check_vector(self.ir_blocks.values().flat_map(|b| &b.instructions))?;
+        self.handle_lift_failures()?;
+        self.eliminate_dead_writes();
+
+        debug!(
+            blocks = self.ir_blocks.len(),
+            "lifted to IR as single-instruction blocks"
+        );
+        self.record_lift_time(started.elapsed());
+
+        Ok(())
+    }
+
+    /// Record the lifting time and log the parallel speedup.
+    fn record_lift_time(&mut self, elapsed: Duration) {
+        self.lift_time = elapsed;
+        let stats = self.stats();
+        info!(
+            jobs = stats.analysis_jobs,
+            cfg_ms = stats.cfg_time.as_millis(),
+            lift_ms = stats.lift_time.as_millis(),
+            speedup = format!("{:.2}", stats.analysis_speedup()),
+            "parallel CFG analysis and lifting"
+        );
+        debug!(
+            max = stats.block_sizes.max,
+            histogram = %stats.block_sizes,
+            "block sizes"
+        );
+    }
+
+    /// Place an override expansion, relocating its helper blocks.
+    fn place_expansion(
+        &self,
+        expansion: OverrideExpansion<X>,
+        synthetic: &mut SyntheticBlocks<X>,
+    ) -> Result<InstrIR<X>> {
+        if expansion.has_helpers() && self.config.backend != Backend::C {
+            return Err(helpers_unsupported(&expansion));
+        }
+        let pc = X::to_u64(expansion.primary.pc);
+        synthetic.place(expansion).ok_or_else(|| {
+            Error::CompilationFailed(format!(
+                "override helper blocks at {pc:#x} exhaust the synthetic PC range"
+            ))
+        })
+    }
+
+    /// Report instructions that would trap, failing under `strict_decode`.
+    fn diagnose_decode(&mut self, reached: Vec<u64>) -> Result<()> {
+        let instr_table = match (&self.block_table, &self.instruction_table) {
+            (Some(block_table), _) => block_table.instruction_table(),
+            (None, Some(instr_table)) => instr_table,
+            (None, None) => return Ok(()),
+        };
+        self.unsupported = find_decode_diagnostics(
+            instr_table,
+            &self.registry,
+            &self.image,
+            self.config.num_regs,
+            reached,
+        );
+        if self.unsupported.is_empty() {
+            return Ok(());
+        }
+        if self.config.strict_decode() {
+            return Err(Error::UnsupportedInstructions(std::mem::take(
+                &mut self.unsupported,
+            )));
+        }
+        warn!("{}", format_table(&self.unsupported));
+        Ok(())
+    }
+
+    /// Check that a sampled tracer, if any, can be emitted: only the C
+    /// backend gates the hooks.
+    fn check_sampled_tracer(&self) -> Result<()> {
+        if self.config.tracer_config.is_sampled() && self.config.backend != Backend::C {
+            return Err(Error::CompilationFailed(
+                "tracer sampling requires the C backend".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Check that vector instructions, if any, can be emitted: their runtime
+    /// is C and sized for at most `MAX_VLEN`.
+    fn check_vector<'a>(&self, mut instrs: impl Iterator<Item = &'a InstrIR<X>>) -> Result<()>
+    where
+        X: 'a,
+    {
+        if !instrs.any(is_vector) {
+            return Ok(());
+        }
+        if self.config.backend != Backend::C {
+            return Err(Error::CompilationFailed(
+                "vector instructions require the C backend".to_string(),
+            ));
+        }
+        let vlen = self.config.vlen;
+        if !valid_vlen(vlen) {
+            return Err(Error::CompilationFailed(format!(
+                "VLEN {vlen} is not a power of two from 64 to {MAX_VLEN}"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Abort on, or quarantine, blocks that failed to lift.
+    fn handle_lift_failures(&mut self) -> Result<()> {
+        let Some(block_table) = self.block_table.as_ref() else {
+            return Ok(());
+        };
+        let failures = find_lift_failures(
+            &self.ir_blocks,
+            &self.synthetic_blocks,
+            block_table.instruction_table(),
+            &self.registry,
+            &self.image,
+        );
+        if failures.is_empty() {
+            return Ok(());
+        }
+        if self.config.on_lift_error == LiftErrorMode::Abort {
+            return Err(Error::LiftFailed(Box::new(failures[0].clone())));
+        }
+
+        quarantine_blocks(&mut self.ir_blocks, &failures);
+        self.quarantined = failures;
+        Ok(())
+    }
+
+    /// Drop register writes that are overwritten before being read
+    /// (`optimize_ir`). Traced builds keep every write for the trace hooks,
+    /// and builds that can stop or be inspected between instructions (per
+    /// instruction suspension, guest PC line tables for debuggers) keep them
+    /// so the guest state is exact there; accesses that can trap or exit are
+    /// barriers.
+    fn eliminate_dead_writes(&mut self) {
+        if !self.config.optimize_ir()
+            || self.config.has_tracing()
+            || self.config.instret_mode.per_instruction()
+            || self.config.emit_guest_pc_map()
+        {
+            return;
+        }
+        let traps = self.trapping_accesses();
+        self.dead_writes_removed = self
+            .ir_blocks
+            .values_mut()
+            .map(|block| eliminate_dead_reg_writes(block, traps))
+            .sum();
+        debug!(
+            removed = self.dead_writes_removed,
+            "eliminated dead register writes"
+        );
+    }
+
+    /// Memory accesses the emitted code can trap or exit at: bounds-checked
+    /// loads and stores, loads polling `fromhost` under the HTIF watchdog,
+    /// and stores into `tohost`, recompiled code or the stack guard.
+    fn trapping_accesses(&self) -> TrappingAccesses {
+        let mode = self.config.address_mode;
+        if mode == AddressMode::Bounds
+            || (self.config.htif_enabled() && self.config.htif_poll_limit > 0)
+        {
+            TrappingAccesses::All
+        } else if self.config.htif_enabled()
+            || self.config.detect_code_writes()
+            || (self.config.stack_guard != 0 && mode.needs_mask())
+        {
+            TrappingAccesses::Stores
+        } else {
+            TrappingAccesses::None
+        }
+    }
+
+    /// Add placed helper blocks to the lifted IR.
+    fn insert_synthetic_blocks(&mut self, synthetic: SyntheticBlocks<X>) -> Result<()> {
+        let (blocks, info) = synthetic.into_parts();
+        for block in blocks {
+            let pc = X::to_u64(block.start_pc);
+            if self.ir_blocks.insert(pc, block).is_some() {
+                return Err(Error::CompilationFailed(format!(
+                    "override helper block at {pc:#x} collides with guest code"
+                )));
+            }
+        }
+        if !info.is_empty() {
+            debug!(helpers = info.len(), "placed override helper blocks");
+        }
+        self.synthetic_blocks = info;
+        Ok(())
+    }
+
+    /// Lift a single block with continuations (absorbed blocks).
+    fn lift_block_with_continuations(
+        &self,
+        start: u64,
+        end: u64,
+        continuations: Option<&Vec<(u64, u64)>>,
+    ) -> Option<LiftedBlock<X>> {
+        let block_table = self.block_table.as_ref()?;
+        let instr_table = block_table.instruction_table();
+
+        // Build list of ranges to lift: main block + continuations
+        let mut ranges = vec![(start, end)];
+        if let Some(conts) = continuations {
+            ranges.extend(conts.iter().copied());
+        }
+
+        // Lift all ranges
+        let mut expansions = Vec::new();
+        'ranges: for (range_idx, (range_start, range_end)) in ranges.iter().enumerate() {
+            let is_last_range = range_idx == ranges.len() - 1;
+            let mut pc = *range_start;
+
+            while pc < *range_end {
+                // Get decoded instruction from table
+                let Some(instr) = instr_table.get_at_pc(pc) else {
+                    break;
+                };
+
+                let mut expansion = self.lift_instr(instr);
+                if let Some(targets) = block_table.resolved_jumps.get(&pc)
+                    && let Terminator::JumpDyn { resolved, .. } = &mut expansion.primary.terminator
+                {
+                    *resolved = Some(targets.iter().map(|&t| X::from_u64(t)).collect());
+                }
+
+                // Check if this is a control flow terminator
+                let is_terminator = expansion.primary.terminator.is_control_flow();
+                let is_replaced =
+                    self.mem_intrinsics.contains_key(&pc) || self.function_hooks.contains_key(&pc);
+
+                expansions.push(expansion);
+                pc += u64::from(instr.size);
+
+                // An intrinsic's or hook's return always ends the block: the
+                // ranges after it belong to the replaced body
+                if is_replaced {
+                    break 'ranges;
+                }
+                // Only stop at terminator if this is the LAST range
+                // (Terminators in absorbed ranges are internal jumps/falls)
+                if is_terminator && is_last_range {
+                    break;
+                }
+            }
+        }
+
+        (!expansions.is_empty()).then(|| LiftedBlock {
+            start,
+            // Mark the fall-through target - use end of last range
+            fall_through: ranges.last().map_or(end, |(_, e)| *e),
+            expansions,
+        })
+    }
+
+    /// Place a lifted block's helper blocks and assemble its IR.
+    fn place_block(
+        &self,
+        lifted: LiftedBlock<X>,
+        synthetic: &mut SyntheticBlocks<X>,
+    ) -> Result<Option<BlockIR<X>>> {
+        let mut block = BlockIR::new(X::from_u64(lifted.start));
+        for expansion in lifted.expansions {
+            block.push(self.place_expansion(expansion, synthetic)?);
+        }
+
+        // If block doesn't end with a terminator, add fall-through
+        if let Some(last_instr) = block.instructions.last_mut()
+            && !last_instr.terminator.is_control_flow()
+        {
+            last_instr.terminator = Terminator::Fall {
+                target: Some(X::from_u64(lifted.fall_through)),
+            };
+        }
+
+        Ok(if block.is_empty() { None } else { Some(block) })
+    }
+}
+
+/// Whether `ir` is a vector (`V` extension) instruction.
+pub(super) fn is_vector<X: Xlen>(ir: &InstrIR<X>) -> bool {
+    ir.op >> 8 == u16::from(EXT_V)
+}
+
+/// PCs that `instrs` execute or transfer control to directly.
+fn reached_pcs<'a, X: Xlen + 'a>(instrs: impl Iterator<Item = &'a InstrIR<X>>) -> Vec<u64> {
+    let mut pcs = Vec::new();
+    for ir in instrs {
+        let pc = X::to_u64(ir.pc);
+        pcs.push(pc);
+        pcs.extend(ir.terminator.static_targets().into_iter().map(X::to_u64));
+        if matches!(
+            ir.terminator,
+            Terminator::Fall { .. } | Terminator::Branch { .. }
+        ) {
+            let next = ir.terminator.fall_target().map(X::to_u64);
+            pcs.push(next.unwrap_or_else(|| pc + u64::from(ir.size)));
+        }
+    }
+    pcs
+}
+
+/// Map `items` in parallel, preserving order and timing each item, and
+/// report lift progress as items finish.
+fn timed_par_map<T: Sync, R: Send>(
+    items: &[T],
+    progress: Option<&ProgressFn>,
+    f: impl Fn(&T) -> R + Sync,
+) -> (Vec<R>, ParallelTime) {
+    let done = AtomicUsize::new(0);
+    let (results, wall) = timed(|| {
+        items
+            .par_iter()
+            .map(|item| {
+                let result = timed(|| f(item));
+                if let Some(progress) = progress {
+                    let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+                    progress.step(CompilePhase::Lift, done, items.len());
+                }
+                result
+            })
+            .collect::<Vec<_>>()
+    });
+    let mut time = ParallelTime {
+        wall,
+        ..ParallelTime::default()
+    };
+    let results = results
+        .into_iter()
+        .map(|(result, work)| {
+            time.work += work;
+            result
+        })
+        .collect();
+    (results, time)
+}
@@ -0,0 +1,678 @@
+//! Recompilation pipeline - ELF → CFG → IR → C.
+
+mod emit;
+mod explain;
+mod filter;
+mod golden;
+mod hooks;
+mod hot_regs;
+mod interp;
+mod intrinsics;
+mod lift;
+mod profile;
+mod stats;
+mod synthetic;
+mod verify;
+
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use rvr_cfg::{BlockTable, InstructionTable, ParallelTime, timed};
+use rvr_elf::{DebugInfo, ElfImage, MemorySegment as ElfMemorySegment};
+use rvr_emit::c::{DedupStats, EmittedBlock, MemIntrinsic};
+use rvr_emit::{
+    AnalysisMode, Backend, EmitConfig, FunctionHook, MemoryLayout, NUM_REGS_E, NUM_REGS_I,
+};
+use rvr_ir::{BlockIR, InstrIR, OverrideExpansion, SyntheticBlockInfo};
+use rvr_isa::{ExtensionRegistry, Xlen};
+use tracing::{debug, info, info_span, trace_span, warn};
+
+pub use explain::{CLine, ExplainedInstr, Explanation, Operand, TerminatorResolution};
+pub use filter::FilterSpec;
+pub use hot_regs::FunctionHotRegs;
+pub use stats::{BLOCK_SIZE_BUCKETS, BlockSizeHistogram, PipelineStats};
+pub use synthetic::SyntheticProgram;
+pub use verify::{IrRule, IrViolation};
+
+use crate::decode_diagnostics::{DecodeDiagnostic, attach_sources};
+use crate::layout::image_layout;
+use crate::progress::{CompilePhase, ProgressFn};
+use crate::quarantine::LiftFailure;
+use crate::size_report::SizeReport;
+use crate::{Error, Result};
+
+pub fn u64_to_f64(value: u64) -> f64 {
+    let hi = u32::try_from(value >> 32).unwrap_or(u32::MAX);
+    let lo = u32::try_from(value & 0xFFFF_FFFF).unwrap_or(u32::MAX);
+    f64::from(hi) * 4_294_967_296.0 + f64::from(lo)
+}
+
+pub fn usize_to_f64(value: usize) -> f64 {
+    let value_u64 = u64::try_from(value).unwrap_or(u64::MAX);
+    u64_to_f64(value_u64)
+}
+
+fn helpers_unsupported<X: Xlen>(expansion: &OverrideExpansion<X>) -> Error {
+    Error::CompilationFailed(format!(
+        "override at {:#x} emits helper blocks, which require the C backend",
+        X::to_u64(expansion.primary.pc)
+    ))
+}
+
+/// Recompilation pipeline.
+pub struct Pipeline<X: Xlen> {
+    /// ELF image.
+    image: ElfImage<X>,
+    /// Emit configuration.
+    config: EmitConfig<X>,
+    /// Block table (from CFG analysis).
+    block_table: Option<BlockTable<X>>,
+    /// Instruction table (for linear emission).
+    instruction_table: Option<InstructionTable<X>>,
+    /// Lifted IR blocks (keyed by start PC).
+    ir_blocks: HashMap<u64, BlockIR<X>>,
+    /// Helper blocks from override expansions (keyed by synthetic PC).
+    synthetic_blocks: HashMap<u64, SyntheticBlockInfo>,
+    /// Lifted IR instructions (linear order).
+    ir_instructions: Vec<InstrIR<X>>,
+    /// Extension registry for decoding and lifting.
+    registry: ExtensionRegistry<X>,
+    /// Extra entry points (e.g., exported function addresses).
+    extra_entry_points: Vec<u64>,
+    /// Blocks replaced by trap stubs under `LiftErrorMode::Quarantine`.
+    quarantined: Vec<LiftFailure>,
+    /// Dead register writes removed from the lifted blocks (`optimize_ir`).
+    dead_writes_removed: usize,
+    /// Blocks the applied block profile never entered.
+    cold_blocks: HashSet<u64>,
+    /// Content hash of the ELF file (0 if unknown).
+    elf_hash: u64,
+    /// Entry PCs of the functions replaced by native intrinsics.
+    mem_intrinsics: HashMap<u64, MemIntrinsic>,
+    /// Entry PCs of the hooked functions.
+    function_hooks: HashMap<u64, FunctionHook>,
+    /// Instructions that lift to a trap or do not decode.
+    unsupported: Vec<DecodeDiagnostic>,
+    /// Threads used for CFG analysis and lifting.
+    analysis_threads: usize,
+    /// Wall-clock time of `build_cfg`.
+    cfg_time: Duration,
+    /// Wall-clock time of lifting to IR.
+    lift_time: Duration,
+    /// Time spent in the parallel parts of CFG analysis and lifting.
+    parallel_time: ParallelTime,
+    /// Identical blocks merged by the last `emit_c`.
+    dedup: DedupStats,
+    /// C part files of the last `emit_c`.
+    c_parts: usize,
+    /// C part files the last `emit_c` left untouched.
+    unchanged_c_parts: usize,
+    /// C written per block by the last `emit_c`.
+    emitted_blocks: Vec<EmittedBlock>,
+    /// Callback receiving CFG, lift and emit progress (optional).
+    progress: Option<ProgressFn>,
+    /// Trap stubs added by `apply_filter`; `Some` once blocks are filtered.
+    filter_stubs: Option<HashSet<u64>>,
+}
+
+impl<X: Xlen> Pipeline<X> {
+    fn adjust_config_for_image(image: &ElfImage<X>, config: &mut EmitConfig<X>) {
+        if image.is_rve() {
+            debug!(num_regs = NUM_REGS_E, "RVE mode detected");
+            config.set_num_regs(NUM_REGS_E);
+        } else {
+            config.set_num_regs(NUM_REGS_I);
+        }
+    }
+
+    /// Create a new pipeline with standard extensions.
+    pub fn new(image: ElfImage<X>, config: EmitConfig<X>) -> Self {
+        let mut config = config;
+        debug!(
+            entry_point = format!("{:#x}", X::to_u64(image.entry_point)),
+            segments = image.memory_segments.len(),
+            "loaded ELF"
+        );
+        Self::adjust_config_for_image(&image, &mut config);
+        Self {
+            image,
+            config,
+            block_table: None,
+            instruction_table: None,
+            ir_blocks: HashMap::new(),
+            synthetic_blocks: HashMap::new(),
+            ir_instructions: Vec::new(),
+            registry: ExtensionRegistry::standard(),
+            extra_entry_points: Vec::new(),
+            quarantined: Vec::new(),
+            dead_writes_removed: 0,
+            cold_blocks: HashSet::new(),
+            elf_hash: 0,
+            mem_intrinsics: HashMap::new(),
+            function_hooks: HashMap::new(),
+            unsupported: Vec::new(),
+            analysis_threads: 0,
+            cfg_time: Duration::ZERO,
+            lift_time: Duration::ZERO,
+            parallel_time: ParallelTime::default(),
+            dedup: DedupStats::default(),
+            c_parts: 0,
+            unchanged_c_parts: 0,
+            emitted_blocks: Vec::new(),
+            progress: None,
+            filter_stubs: None,
+        }
+    }
+
+    /// Create a new pipeline with custom extension registry.
+    pub fn with_registry(
+        image: ElfImage<X>,
+        config: EmitConfig<X>,
+        registry: ExtensionRegistry<X>,
+    ) -> Self {
+        let mut config = config;
+        debug!(
+            entry_point = format!("{:#x}", X::to_u64(image.entry_point)),
+            segments = image.memory_segments.len(),
+            "loaded ELF"
+        );
+        Self::adjust_config_for_image(&image, &mut config);
+        Self {
+            image,
+            config,
+            block_table: None,
+            instruction_table: None,
+            ir_blocks: HashMap::new(),
+            synthetic_blocks: HashMap::new(),
+            ir_instructions: Vec::new(),
+            registry,
+            extra_entry_points: Vec::new(),
+            quarantined: Vec::new(),
+            dead_writes_removed: 0,
+            cold_blocks: HashSet::new(),
+            elf_hash: 0,
+            mem_intrinsics: HashMap::new(),
+            function_hooks: HashMap::new(),
+            unsupported: Vec::new(),
+            analysis_threads: 0,
+            cfg_time: Duration::ZERO,
+            lift_time: Duration::ZERO,
+            parallel_time: ParallelTime::default(),
+            dedup: DedupStats::default(),
+            c_parts: 0,
+            unchanged_c_parts: 0,
+            emitted_blocks: Vec::new(),
+            progress: None,
+            filter_stubs: None,
+        }
+    }
+
+    /// Report CFG, lift and emit progress to `progress`.
+    pub fn set_progress(&mut self, progress: Option<ProgressFn>) {
+        self.progress = progress;
+    }
+
+    /// Report `done` of `total` units of `phase`, if anyone listens.
+    fn report(&self, phase: CompilePhase, done: usize, total: usize) {
+        crate::progress::report(self.progress.as_ref(), phase, done, total);
+    }
+
+    /// Blocks quarantined during lifting, sorted by PC.
+    pub fn quarantined(&self) -> &[LiftFailure] {
+        &self.quarantined
+    }
+
+    /// Unsupported instructions found during lifting, sorted by PC.
+    pub fn unsupported(&self) -> &[DecodeDiagnostic] {
+        &self.unsupported
+    }
+
+    /// Heap, stack and mmap arena placement for the configured sizes, if
+    /// any.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::MemoryLayout` if the regions overlap the image or
+    /// each other, or do not fit in memory.
+    pub fn memory_layout(&self) -> Result<Option<MemoryLayout>> {
+        Ok(MemoryLayout::plan(
+            &image_layout(&self.image),
+            self.config.memory_bits,
+            self.config.heap_size,
+            self.config.stack_size,
+            self.config.mmap_size,
+            self.config.stack_guard,
+        )?)
+    }
+
+    /// Get reference to ELF image.
+    pub const fn image(&self) -> &ElfImage<X> {
+        &self.image
+    }
+
+    /// Get reference to emit config.
+    pub const fn config(&self) -> &EmitConfig<X> {
+        &self.config
+    }
+
+    /// Get mutable reference to emit config.
+    pub const fn config_mut(&mut self) -> &mut EmitConfig<X> {
+        &mut self.config
+    }
+
+    /// Get reference to block table (if built).
+    pub const fn block_table(&self) -> Option<&BlockTable<X>> {
+        self.block_table.as_ref()
+    }
+
+    /// Get reference to lifted IR blocks.
+    pub const fn ir_blocks(&self) -> &HashMap<u64, BlockIR<X>> {
+        &self.ir_blocks
+    }
+
+    /// Add extra entry points (e.g., exported function addresses).
+    ///
+    /// These addresses will be treated as additional function entry points
+    /// during CFG analysis, ensuring blocks are generated for them.
+    /// Must be called before `build_cfg`.
+    pub fn add_extra_entry_points(&mut self, entry_points: &[u64]) {
+        self.extra_entry_points.extend(entry_points.iter().copied());
+    }
+
+    /// Record the content hash of the ELF file the image was parsed from.
+    pub const fn set_elf_hash(&mut self, elf_hash: u64) {
+        self.elf_hash = elf_hash;
+    }
+
+    /// Add function symbols from the ELF as extra entry points.
+    ///
+    /// This is useful for benchmarks where exported functions like `initialize`
+    /// and `run` need to be callable independently.
+    pub fn add_function_symbols_as_entry_points(&mut self) {
+        let entry_points: Vec<u64> = self.function_symbols().map(|(_, pc)| pc).collect();
+        self.extra_entry_points.extend(entry_points);
+    }
+
+    /// Add guest test functions (the `.rvr_tests` section) as extra entry
+    /// points, so the `guest_test` runner can call them.
+    ///
+    /// # Errors
+    /// Returns an error if the test section is malformed.
+    pub fn add_guest_tests_as_entry_points(&mut self) -> Result<()> {
+        let tests = self.image.guest_tests()?;
+        self.extra_entry_points
+            .extend(tests.iter().map(|test| test.addr));
+        Ok(())
+    }
+
+    /// Named function symbols as `(name, address)`.
+    fn function_symbols(&self) -> impl Iterator<Item = (&str, u64)> {
+        use rvr_elf::STT_FUNC;
+        self.image
+            .symbols
+            .iter()
+            .filter(|s| s.sym_type == STT_FUNC && !s.name.is_empty())
+            .map(|s| (s.name.as_str(), X::to_u64(s.value)))
+    }
+
+    fn collect_exec_segments(&self, entry_pc: u64) -> Result<Vec<&ElfMemorySegment<X>>> {
+        let mut exec_segments: Vec<_> = self
+            .image
+            .memory_segments
+            .iter()
+            .filter(|seg| seg.is_executable())
+            .collect();
+
+        if exec_segments.is_empty() {
+            // TODO: Fix upstream riscv-tests/benchmarks/common/test.ld to use FLAGS(5) instead of FLAGS(SHF_ALLOC | SHF_EXECINSTR)
+            exec_segments = self
+                .image
+                .memory_segments
+                .iter()
+                .filter(|seg| seg.has_executable_sections(&self.image.sections))
+                .collect();
+
+            if !exec_segments.is_empty() {
+                debug!(
+                    "No segments have PF_X flag, but found {} segment(s) with executable sections (SHF_EXECINSTR). \
+                     This is likely due to a buggy linker script using section flags instead of program header flags.",
+                    exec_segments.len()
+                );
+            }
+        }
+
+        if exec_segments.is_empty() {
+            return Err(Error::NoCodeSegment(entry_pc));
+        }
+
+        let entry_in_exec = exec_segments.iter().any(|seg| {
+            let start = X::to_u64(seg.virtual_start);
+            let end = X::to_u64(seg.virtual_end);
+            entry_pc >= start && entry_pc < end
+        });
+        if !entry_in_exec {
+            return Err(Error::NoCodeSegment(entry_pc));
+        }
+
+        Ok(exec_segments)
+    }
+
+    fn exec_segments_range(
+        exec_segments: &[&ElfMemorySegment<X>],
+        entry_pc: u64,
+    ) -> Result<(u64, u64)> {
+        let base_address = exec_segments
+            .iter()
+            .map(|seg| X::to_u64(seg.virtual_start))
+            .min()
+            .ok_or(Error::NoCodeSegment(entry_pc))?;
+        let end_address = exec_segments
+            .iter()
+            .map(|seg| X::to_u64(seg.virtual_end))
+            .max()
+            .ok_or(Error::NoCodeSegment(entry_pc))?;
+        Ok((base_address, end_address))
+    }
+
+    fn decode_exec_segments(
+        &self,
+        exec_segments: &[&ElfMemorySegment<X>],
+        instr_table: &mut InstructionTable<X>,
+    ) {
+        let _span = trace_span!("decode_instructions").entered();
+        for seg in exec_segments {
+            let seg_start = X::to_u64(seg.virtual_start);
+            instr_table.populate_segment(&seg.data, seg_start, &self.registry);
+        }
+    }
+
+    fn add_extra_entry_points_to_table(&self, instr_table: &mut InstructionTable<X>) {
+        if !self.extra_entry_points.is_empty() {
+            debug!(
+                count = self.extra_entry_points.len(),
+                "adding extra entry points"
+            );
+            instr_table.add_entry_points(self.extra_entry_points.iter().copied());
+        }
+    }
+
+    fn add_ro_segments_to_table(&self, instr_table: &mut InstructionTable<X>) {
+        for seg in &self.image.memory_segments {
+            if seg.is_readonly() {
+                let seg_start = X::to_u64(seg.virtual_start);
+                let seg_end = X::to_u64(seg.virtual_end);
+                instr_table.add_ro_segment(seg_start, seg_end, seg.data.clone());
+            }
+        }
+    }
+
+    /// Run `f` on a pool with `analysis_jobs` threads (the global pool if 0).
+    fn on_analysis_pool<R: Send>(&self, f: impl FnOnce() -> R + Send) -> R {
+        let jobs = self.config.analysis_jobs;
+        if jobs == 0 {
+            return f();
+        }
+        match rayon::ThreadPoolBuilder::new().num_threads(jobs).build() {
+            Ok(pool) => pool.install(f),
+            Err(e) => {
+                warn!(error = %e, jobs, "failed to build analysis thread pool, using default");
+                f()
+            }
+        }
+    }
+
+    fn insns_per_block(num_instructions: usize, num_blocks: usize) -> f64 {
+        if num_blocks == 0 {
+            return 0.0;
+        }
+        usize_to_f64(num_instructions) / usize_to_f64(num_blocks)
+    }
+
+    fn build_cfg_for_c(&mut self, instr_table: InstructionTable<X>, num_instructions: usize) {
+        let (mut block_table, blocks_before) = match self.config.analysis_mode {
+            AnalysisMode::FullCfg => {
+                let _span = trace_span!("cfg_analysis").entered();
+                let table = self.on_analysis_pool(|| {
+                    BlockTable::from_instruction_table(instr_table, &self.registry)
+                });
+                let before = table.len();
+                (table, before)
+            }
+            AnalysisMode::Basic => {
+                debug!("Basic mode: using linear blocks (no CFG analysis)");
+                let table = BlockTable::linear(instr_table);
+                let before = table.len();
+                (table, before)
+            }
+        };
+
+        let (absorbed, tail_duplicated, superblocked) = match self.config.analysis_mode {
+            AnalysisMode::FullCfg => {
+                let _span = trace_span!("block_transforms").entered();
+                let limits = self.config.block_limits();
+                if self.config.enable_superblock {
+                    block_table.optimize(limits, &self.registry)
+                } else {
+                    block_table.split_long_blocks(limits.max_instrs);
+                    let merged = block_table.merge_blocks(limits, &self.registry);
+                    let tail_duped =
+                        block_table.tail_duplicate(rvr_cfg::DEFAULT_TAIL_DUP_SIZE, &self.registry);
+                    block_table.fix_stale_mappings();
+                    (merged, tail_duped, 0)
+                }
+            }
+            AnalysisMode::Basic => (0, 0, 0),
+        };
+
+        let num_blocks = block_table.len();
+        let insns_per_block = Self::insns_per_block(num_instructions, num_blocks);
+
+        info!(
+            instructions = num_instructions,
+            blocks = num_blocks,
+            insns_per_block = format!("{:.1}", insns_per_block),
+            resolved_jumps = block_table.resolved_jumps.len(),
+            unresolved_jumps = block_table.unresolved_jumps.len(),
+            analysis_mode = ?self.config.analysis_mode,
+            "built CFG"
+        );
+
+        if absorbed > 0 || tail_duplicated > 0 || superblocked > 0 {
+            info!(
+                before = blocks_before,
+                absorbed = absorbed,
+                tail_duplicated = tail_duplicated,
+                superblocked = superblocked,
+                "block transforms"
+            );
+        }
+
+        self.block_table = Some(block_table);
+        self.instruction_table = None;
+    }
+
+    fn build_cfg_for_asm(&mut self, instr_table: InstructionTable<X>, num_instructions: usize) {
+        if self.config.analysis_mode == AnalysisMode::FullCfg {
+            let _span = trace_span!("cfg_analysis").entered();
+            let block_table = self.on_analysis_pool(|| {
+                BlockTable::from_instruction_table(instr_table.clone(), &self.registry)
+            });
+            let num_blocks = block_table.len();
+            let insns_per_block = Self::insns_per_block(num_instructions, num_blocks);
+            info!(
+                instructions = num_instructions,
+                blocks = num_blocks,
+                insns_per_block = format!("{:.1}", insns_per_block),
+                analysis_mode = ?self.config.analysis_mode,
+                "built CFG (linear emission)"
+            );
+            self.block_table = Some(block_table);
+        } else {
+            info!(
+                instructions = num_instructions,
+                analysis_mode = ?self.config.analysis_mode,
+                "built instruction table"
+            );
+            self.block_table = None;
+        }
+        self.instruction_table = Some(instr_table);
+    }
+
+    /// Build CFG: creates `InstructionTable` → `BlockTable` with optimizations.
+    ///
+    /// Builds `InstructionTable` from ALL executable segments, not just the entry segment.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::NoCodeSegment` if there are no executable segments or
+    /// the entry point is not within any executable segment.
+    /// Returns `Error::CompilationFailed` if a function hook calls the host
+    /// with a non-C backend or has a symbol it cannot pass to the host.
+    pub fn build_cfg(&mut self) -> Result<()> {
+        let _span = info_span!("build_cfg").entered();
+        self.report(CompilePhase::Cfg, 0, 0);
+
+        let entry_pc = X::to_u64(self.image.entry_point);
+        let exec_segments = self.collect_exec_segments(entry_pc)?;
+        let (base_address, end_address) = Self::exec_segments_range(&exec_segments, entry_pc)?;
+
+        debug!(
+            base_address = format!("{:#x}", base_address),
+            end_address = format!("{:#x}", end_address),
+            "address range"
+        );
+
+        // Create InstructionTable spanning all executable segments
+        let mut instr_table = InstructionTable::new(base_address, end_address, entry_pc);
+        self.decode_exec_segments(&exec_segments, &mut instr_table);
+        self.add_extra_entry_points_to_table(&mut instr_table);
+        self.add_ro_segments_to_table(&mut instr_table);
+        self.find_function_hooks(&mut instr_table)?;
+
+        let num_instructions = instr_table.valid_indices().count();
+
+        let ((), elapsed) = timed(|| {
+            if self.config.backend == Backend::C {
+                self.build_cfg_for_c(instr_table, num_instructions);
+            } else {
+                self.build_cfg_for_asm(instr_table, num_instructions);
+            }
+        });
+        self.cfg_time = elapsed;
+        self.analysis_threads = self.on_analysis_pool(rayon::current_num_threads);
+        if let Some(block_table) = &self.block_table {
+            self.parallel_time = block_table.analysis_time;
+        }
+        let blocks = self
+            .block_table
+            .as_ref()
+            .map_or(num_instructions, |table| table.blocks.len());
+        self.report(CompilePhase::Cfg, blocks, blocks);
+        Ok(())
+    }
+
+    /// Load debug info and attach source locations to instructions.
+    ///
+    /// Must be called after `lift_to_ir()`. Uses llvm-addr2line to resolve
+    /// instruction addresses to source `<file:line:function>`.
+    ///
+    /// # Arguments
+    ///
+    /// * `elf_path` - Path to the ELF file (needed by addr2line).
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::CfgNotBuilt` if `lift_to_ir` has not been called.
+    /// Logs a warning if addr2line fails but does not return an error.
+    pub fn load_debug_info(&mut self, elf_path: &str) -> Result<()> {
+        let _span = info_span!("load_debug_info").entered();
+
+        if self.ir_blocks.is_empty() {
+            return Err(Error::CfgNotBuilt("load_debug_info"));
+        }
+
+        // Collect all guest instruction PCs (helper blocks have no source location)
+        let addresses: Vec<u64> = self
+            .ir_blocks
+            .values()
+            .flat_map(|block| block.instructions.iter().map(|ir| X::to_u64(ir.pc)))
+            .filter(|pc| !self.synthetic_blocks.contains_key(pc))
+            .chain(self.unsupported.iter().map(|d| d.pc))
+            .collect();
+
+        debug!(addresses = addresses.len(), "resolving debug info");
+
+        // Load debug info via addr2line (version derived from compiler)
+        let addr2line_cmd = self.config.compiler.addr2line();
+        let debug_info = match DebugInfo::load(elf_path, &addresses, &addr2line_cmd) {
+            Ok(info) => info,
+            Err(e) => {
+                warn!(error = %e, "failed to load debug info");
+                return Ok(());
+            }
+        };
+
+        if debug_info.is_empty() {
+            debug!("no debug info found");
+            return Ok(());
+        }
+
+        info!(locations = debug_info.len(), "loaded debug info");
+
+        attach_sources(&mut self.unsupported, &debug_info);
+        for diagnostic in self.unsupported.iter().filter(|d| d.source.is_some()) {
+            warn!(pc = format!("{:#x}", diagnostic.pc), "{diagnostic}");
+        }
+
+        // Attach source locations to instructions
+        for block in self.ir_blocks.values_mut() {
+            for instr in &mut block.instructions {
+                let pc = X::to_u64(instr.pc);
+                if let Some(loc) = debug_info.get(pc) {
+                    instr.set_source_loc(loc.clone());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Code size per guest function of the last `emit_c` (empty before it).
+    pub fn size_report(&self) -> SizeReport {
+        let block_to_function = self.block_table.as_ref().map(|t| &t.block_to_function);
+        SizeReport::new(
+            &self.image,
+            |pc| block_to_function.and_then(|m| m.get(&pc).copied()),
+            &self.ir_blocks,
+            &self.emitted_blocks,
+        )
+    }
+
+    /// Get statistics.
+    pub fn stats(&self) -> PipelineStats {
+        let block_table = self.block_table.as_ref();
+        PipelineStats {
+            num_blocks: self.ir_blocks.len(),
+            num_basic_blocks: block_table.map_or(0, BlockTable::len),
+            num_absorbed: block_table.map_or(0, |b| b.absorbed_to_merged.len()),
+            num_quarantined: self.quarantined.len(),
+            resolved_jumps: block_table.map_or(0, |b| b.resolved_jumps.len()),
+            unresolved_jumps: block_table.map_or(0, |b| b.unresolved_jumps.len()),
+            dead_writes_removed: self.dead_writes_removed,
+            unsupported: self.unsupported.clone(),
+            analysis_jobs: self.analysis_threads,
+            cfg_time: self.cfg_time,
+            lift_time: self.lift_time,
+            parallel_time: self.parallel_time,
+            dedup: self.dedup,
+            c_parts: self.c_parts,
+            unchanged_c_parts: self.unchanged_c_parts,
+            block_sizes: BlockSizeHistogram::from_sizes(
+                self.ir_blocks
+                    .values()
+                    .map(|block| block.instructions.len()),
+            ),
+            extensions: self.registry.extension_names(),
+        }
+    }
+}
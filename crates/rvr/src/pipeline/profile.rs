@@ -0,0 +1,94 @@
+//! Profile-guided emission.
+//!
+//! A block profile from a previous run (see [`ProfileCounts`]) steers the C
+//! backend three ways:
+//! - conditional branches get a `likely`/`unlikely` hint towards the hotter
+//!   successor, so clang lays the hot edge out as the fallthrough;
+//! - blocks that were never entered are emitted `__attribute__((cold))`,
+//!   which moves them to the cold text section;
+//! - the hot register slots go to the registers accessed most at run time
+//!   (static accesses weighted by block entries) instead of the static
+//!   priority order. Blocks tail-call each other with the hot registers as
+//!   arguments, so the choice is per program, not per function.
+
+use rvr_ir::{BranchHint, RegAccesses, Terminator, reg_accesses};
+use rvr_isa::Xlen;
+use tracing::info;
+
+use super::Pipeline;
+use crate::ProfileCounts;
+
+/// A branch is hinted when one successor is entered this many times as
+/// often as the other.
+const HINT_RATIO: u64 = 4;
+
+impl<X: Xlen> Pipeline<X> {
+    /// Apply a block profile to the lifted blocks (C backend).
+    ///
+    /// Call after lifting and before `emit_c`. The profile must come from
+    /// the same ELF; see [`ProfileCounts::matches`].
+    pub fn apply_profile(&mut self, profile: &ProfileCounts) {
+        // Entries of the block at `pc`, if it is a block here or was one in
+        // the profiled build
+        let weight = |pc: u64| match profile.count(pc) {
+            0 => self
+                .ir_blocks
+                .get(&pc)
+                .map(|block| profile.hottest(pc..X::to_u64(block.end_pc))),
+            count => Some(count),
+        };
+
+        let mut hints = Vec::new();
+        let mut accesses: RegAccesses = [0; 32];
+        for (&pc, block) in &self.ir_blocks {
+            let entries = weight(pc).unwrap_or(0);
+            if entries == 0 {
+                if !self.synthetic_blocks.contains_key(&pc) {
+                    self.cold_blocks.insert(pc);
+                }
+                continue;
+            }
+            for (reg, count) in reg_accesses(block).into_iter().enumerate() {
+                accesses[reg] = accesses[reg].saturating_add(count.saturating_mul(entries));
+            }
+            for (idx, instr) in block.instructions.iter().enumerate() {
+                let Terminator::Branch { target, fall, .. } = &instr.terminator else {
+                    continue;
+                };
+                let fall = X::to_u64(fall.unwrap_or_else(|| instr.next_pc()));
+                // A side exit falls through into the rest of its superblock,
+                // which has no count unless the profiled build split there
+                let (Some(taken), Some(not_taken)) = (weight(X::to_u64(*target)), weight(fall))
+                else {
+                    continue;
+                };
+                let hint = if taken > not_taken.saturating_mul(HINT_RATIO) {
+                    BranchHint::Taken
+                } else if not_taken > taken.saturating_mul(HINT_RATIO) {
+                    BranchHint::NotTaken
+                } else {
+                    continue;
+                };
+                hints.push((pc, idx, hint));
+            }
+        }
+
+        let hinted = hints.len();
+        for (pc, idx, hint) in hints {
+            if let Some(Terminator::Branch { hint: slot, .. }) = self
+                .ir_blocks
+                .get_mut(&pc)
+                .map(|block| &mut block.instructions[idx].terminator)
+            {
+                *slot = hint;
+            }
+        }
+        self.config.rank_hot_regs(&accesses);
+        info!(
+            cold_blocks = self.cold_blocks.len(),
+            hinted_branches = hinted,
+            hot_regs = ?self.config.hot_regs,
+            "applied block profile"
+        );
+    }
+}
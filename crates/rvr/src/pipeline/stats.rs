@@ -0,0 +1,124 @@
+//! Pipeline statistics: block sizes and analysis timings.
+
+use std::time::Duration;
+
+use rvr_cfg::ParallelTime;
+use rvr_emit::c::DedupStats;
+
+use crate::decode_diagnostics::DecodeDiagnostic;
+
+/// Number of [`BlockSizeHistogram`] buckets.
+pub const BLOCK_SIZE_BUCKETS: usize = 16;
+
+/// Lifted block sizes in instructions, in power-of-two buckets.
+///
+/// Bucket `i` counts blocks of `2^i..2^(i+1)` instructions; the last bucket
+/// also holds every larger block.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BlockSizeHistogram {
+    /// Block count per bucket.
+    pub counts: [usize; BLOCK_SIZE_BUCKETS],
+    /// Size of the largest block.
+    pub max: usize,
+}
+
+impl BlockSizeHistogram {
+    /// Histogram of `sizes`.
+    #[must_use]
+    pub fn from_sizes(sizes: impl IntoIterator<Item = usize>) -> Self {
+        let mut histogram = Self::default();
+        for size in sizes {
+            histogram.counts[Self::bucket(size)] += 1;
+            histogram.max = histogram.max.max(size);
+        }
+        histogram
+    }
+
+    /// Bucket holding blocks of `size` instructions.
+    #[must_use]
+    pub const fn bucket(size: usize) -> usize {
+        let log2 = if size > 1 { size.ilog2() as usize } else { 0 };
+        if log2 < BLOCK_SIZE_BUCKETS {
+            log2
+        } else {
+            BLOCK_SIZE_BUCKETS - 1
+        }
+    }
+
+    /// Non-empty buckets as `(min_size, count)`.
+    pub fn buckets(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.counts
+            .iter()
+            .enumerate()
+            .filter(|&(_, &count)| count > 0)
+            .map(|(i, &count)| (1 << i, count))
+    }
+}
+
+impl std::fmt::Display for BlockSizeHistogram {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, (min_size, count)) in self.buckets().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{min_size}+:{count}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Pipeline statistics.
+#[derive(Clone, Debug, Default)]
+pub struct PipelineStats {
+    /// Number of lifted IR blocks.
+    pub num_blocks: usize,
+    /// Number of basic blocks from CFG analysis.
+    pub num_basic_blocks: usize,
+    /// Number of blocks absorbed (merged/tail-duped).
+    pub num_absorbed: usize,
+    /// Number of blocks replaced by trap stubs.
+    pub num_quarantined: usize,
+    /// Indirect jumps with proven targets, emitted as direct jumps.
+    pub resolved_jumps: usize,
+    /// Indirect jumps left to the dispatch table.
+    pub unresolved_jumps: usize,
+    /// Register writes removed as dead (`EmitConfig::optimize_ir`).
+    pub dead_writes_removed: usize,
+    /// Instructions that lift to a trap or do not decode, sorted by PC.
+    pub unsupported: Vec<DecodeDiagnostic>,
+    /// Threads used for CFG analysis and lifting.
+    pub analysis_jobs: usize,
+    /// Wall-clock time of CFG construction.
+    pub cfg_time: Duration,
+    /// Wall-clock time of lifting to IR.
+    pub lift_time: Duration,
+    /// Wall-clock and single-thread time of the parallel phases.
+    pub parallel_time: ParallelTime,
+    /// Identical blocks merged when emitting C (`dedup_blocks`).
+    pub dedup: DedupStats,
+    /// C part files of the last emission.
+    pub c_parts: usize,
+    /// C part files left untouched because an earlier emission into the same
+    /// directory wrote the same source; make does not rebuild them.
+    pub unchanged_c_parts: usize,
+    /// Sizes of the lifted blocks, after merging and superblock formation.
+    pub block_sizes: BlockSizeHistogram,
+    /// Extensions the decoder was built with, in decode order.
+    pub extensions: Vec<&'static str>,
+}
+
+impl PipelineStats {
+    /// Speedup of CFG construction and lifting over a single thread.
+    ///
+    /// Estimated by replacing the wall-clock time of the parallel phases with
+    /// their summed per-task time.
+    #[must_use]
+    pub fn analysis_speedup(&self) -> f64 {
+        let total = self.cfg_time + self.lift_time;
+        if total.is_zero() {
+            return 1.0;
+        }
+        let serial = total.saturating_sub(self.parallel_time.wall) + self.parallel_time.work;
+        serial.as_secs_f64() / total.as_secs_f64()
+    }
+}
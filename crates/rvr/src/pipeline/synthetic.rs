@@ -0,0 +1,247 @@
+//! Programs assembled from IR instead of lifted from an ELF.
+//!
+//! A [`SyntheticProgram`] is a set of IR blocks (built with
+//! [`IRBuilder`](rvr_ir::IRBuilder)), an entry point and optional data
+//! segments. [`Pipeline::from_program`] takes it straight to emission: no
+//! ELF is parsed and no CFG is built, so successors come from the
+//! terminators and the dispatch table covers exactly the given blocks.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use rvr_elf::{ElfImage, MemorySegment, PF_R, PF_W};
+use rvr_emit::EmitConfig;
+use rvr_ir::{BlockIR, InstrIR, Terminator};
+use rvr_isa::Xlen;
+
+use super::Pipeline;
+use crate::{Error, Result};
+
+/// A guest program built from IR blocks.
+///
+/// Memory starts zeroed, with each data segment copied in. Static targets
+/// must be blocks of the program or marked external; a jump to an external
+/// target leaves the generated code the way a jump out of a lifted image
+/// does.
+#[derive(Clone, Debug)]
+pub struct SyntheticProgram<X: Xlen> {
+    blocks: HashMap<u64, BlockIR<X>>,
+    entry: u64,
+    data: BTreeMap<u64, Vec<u8>>,
+    external: HashSet<u64>,
+}
+
+impl<X: Xlen> SyntheticProgram<X> {
+    /// Create an empty program starting at `entry`.
+    #[must_use]
+    pub fn new(entry: u64) -> Self {
+        Self::from_blocks(HashMap::new(), entry)
+    }
+
+    /// Create a program from blocks keyed by start PC.
+    #[must_use]
+    pub fn from_blocks(blocks: HashMap<u64, BlockIR<X>>, entry: u64) -> Self {
+        Self {
+            blocks,
+            entry,
+            data: BTreeMap::new(),
+            external: HashSet::new(),
+        }
+    }
+
+    /// Add a block, replacing any block with the same start PC.
+    #[must_use]
+    pub fn with_block(mut self, block: BlockIR<X>) -> Self {
+        self.blocks.insert(X::to_u64(block.start_pc), block);
+        self
+    }
+
+    /// Add a data segment copied to `addr` before the program starts.
+    #[must_use]
+    pub fn with_data(mut self, addr: u64, data: Vec<u8>) -> Self {
+        self.data.insert(addr, data);
+        self
+    }
+
+    /// Allow static jumps to `pc` without a block there.
+    #[must_use]
+    pub fn with_external_target(mut self, pc: u64) -> Self {
+        self.external.insert(pc);
+        self
+    }
+
+    /// Entry point.
+    #[must_use]
+    pub const fn entry(&self) -> u64 {
+        self.entry
+    }
+
+    /// Blocks keyed by start PC.
+    #[must_use]
+    pub const fn blocks(&self) -> &HashMap<u64, BlockIR<X>> {
+        &self.blocks
+    }
+
+    /// Check that the blocks form a program the backends can emit.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidSynthetic` if the entry point is not a block,
+    /// a block is empty, keyed by another PC, not contiguous or overlaps
+    /// another block, or a static target is neither a block nor external.
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |reason: String| Err(Error::InvalidSynthetic(reason));
+        if !self.blocks.contains_key(&self.entry) {
+            return invalid(format!("entry point {:#x} is not a block", self.entry));
+        }
+        for (&pc, block) in &self.blocks {
+            if X::to_u64(block.start_pc) != pc {
+                return invalid(format!(
+                    "block {:#x} is keyed by {pc:#x}",
+                    X::to_u64(block.start_pc)
+                ));
+            }
+        }
+        let mut prev_end = 0;
+        for block in self.sorted_blocks() {
+            let start = X::to_u64(block.start_pc);
+            if block.instructions.is_empty() {
+                return invalid(format!("block {start:#x} is empty"));
+            }
+            if start < prev_end {
+                return invalid(format!("block {start:#x} overlaps the block before it"));
+            }
+            let mut pc = start;
+            for instr in &block.instructions {
+                if X::to_u64(instr.pc) != pc {
+                    return invalid(format!(
+                        "block {start:#x} has an instruction at {:#x}, expected {pc:#x}",
+                        X::to_u64(instr.pc)
+                    ));
+                }
+                pc = X::to_u64(instr.next_pc());
+            }
+            if X::to_u64(block.end_pc) != pc {
+                return invalid(format!("block {start:#x} ends at {pc:#x}, not its end_pc"));
+            }
+            prev_end = pc;
+
+            for target in successors(block) {
+                if !self.blocks.contains_key(&target) && !self.external.contains(&target) {
+                    return invalid(format!(
+                        "block {start:#x} jumps to {target:#x}, which is neither a block nor external"
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Image the runner and emitters see: the entry point and data
+    /// segments, no sections or symbols.
+    #[must_use]
+    pub fn image(&self) -> ElfImage<X> {
+        ElfImage {
+            entry_point: X::from_u64(self.entry),
+            e_flags: 0,
+            memory_segments: self
+                .data
+                .iter()
+                .map(|(&addr, data)| MemorySegment {
+                    virtual_start: X::from_u64(addr),
+                    virtual_end: X::from_u64(addr + data.len() as u64),
+                    data: data.clone(),
+                    flags: PF_R | PF_W,
+                })
+                .collect(),
+            sections: Vec::new(),
+            symbols: Vec::new(),
+            load_bias: 0,
+            attributes: None,
+            relocations: Vec::new(),
+        }
+    }
+
+    fn sorted_blocks(&self) -> Vec<&BlockIR<X>> {
+        let mut blocks: Vec<&BlockIR<X>> = self.blocks.values().collect();
+        blocks.sort_by_key(|block| X::to_u64(block.start_pc));
+        blocks
+    }
+
+    /// Instructions in PC order for the assembly backends, which lay
+    /// blocks out back to back: falling off a block is made an explicit
+    /// jump, since the next block need not be adjacent.
+    fn linear_instructions(&self) -> Vec<InstrIR<X>> {
+        let mut instrs = Vec::new();
+        for block in self.sorted_blocks() {
+            let last = block.instructions.len() - 1;
+            for (idx, instr) in block.instructions.iter().enumerate() {
+                let mut instr = instr.clone();
+                if idx == last {
+                    let next = instr.next_pc();
+                    match &mut instr.terminator {
+                        Terminator::Fall { target } => *target = target.or(Some(next)),
+                        Terminator::Branch { fall, .. } => *fall = fall.or(Some(next)),
+                        _ => {}
+                    }
+                }
+                instrs.push(instr);
+            }
+        }
+        instrs
+    }
+}
+
+/// Static successors of `block`: jump, branch and resolved dynamic targets
+/// of every instruction, plus the fall-through of the last one.
+fn successors<X: Xlen>(block: &BlockIR<X>) -> Vec<u64> {
+    let mut targets = Vec::new();
+    for instr in &block.instructions {
+        targets.extend(instr.terminator.static_targets().into_iter().map(X::to_u64));
+    }
+    if let Some(last) = block.instructions.last()
+        && matches!(
+            last.terminator,
+            Terminator::Fall { .. } | Terminator::Branch { .. }
+        )
+    {
+        let fall = last
+            .terminator
+            .fall_target()
+            .unwrap_or_else(|| last.next_pc());
+        targets.push(X::to_u64(fall));
+    }
+    targets
+}
+
+impl<X: Xlen> Pipeline<X> {
+    /// Create a pipeline for IR blocks keyed by start PC, without an ELF.
+    ///
+    /// See [`Pipeline::from_program`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidSynthetic` if the blocks do not validate.
+    pub fn from_ir_blocks(
+        blocks: HashMap<u64, BlockIR<X>>,
+        entry: u64,
+        config: EmitConfig<X>,
+    ) -> Result<Self> {
+        Self::from_program(SyntheticProgram::from_blocks(blocks, entry), config)
+    }
+
+    /// Create a pipeline for a synthetic program, ready to emit.
+    ///
+    /// The blocks stand in for both `build_cfg` and lifting, so no CFG
+    /// transforms or IR optimizations are applied to them.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidSynthetic` if the program does not validate.
+    pub fn from_program(program: SyntheticProgram<X>, config: EmitConfig<X>) -> Result<Self> {
+        program.validate()?;
+        let mut pipeline = Self::new(program.image(), config);
+        pipeline.ir_instructions = program.linear_instructions();
+        pipeline.ir_blocks = program.blocks;
+        Ok(pipeline)
+    }
+}
@@ -0,0 +1,479 @@
+//! Invariant checks on the lifted IR.
+//!
+//! Lifting, override expansion and dead write elimination all rewrite the
+//! IR; a bug in any of them tends to show up as wrong guest behavior far
+//! from its cause. [`Pipeline::verify_ir`] checks each block for the
+//! invariants the emitters rely on and reports the instructions that break
+//! them. `lift_to_ir` runs it in debug builds and with
+//! `EmitConfig::verify_ir`.
+//!
+//! Static targets outside the lifted code are external: the emitters stop
+//! there with an invalid target error. Dynamic jump targets are resolved at
+//! run time and not checked.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use rvr_ir::{BlockIR, Expr, InstrIR, ReadExpr, Stmt, Terminator, WriteTarget, is_synthetic_pc};
+use rvr_isa::{Xlen, op_mnemonic};
+
+use super::Pipeline;
+
+/// An IR invariant broken by an instruction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IrRule {
+    /// The block has no instructions.
+    EmptyBlock,
+    /// The first instruction is not at the block's start PC.
+    StartPc {
+        /// Start PC of the block.
+        expected: u64,
+    },
+    /// The instruction does not follow the fall-through instruction before
+    /// it.
+    PcGap {
+        /// Next PC of the previous instruction.
+        expected: u64,
+    },
+    /// The block's end PC is not the last instruction's next PC.
+    EndPc {
+        /// Next PC of the last instruction.
+        expected: u64,
+        /// End PC of the block.
+        actual: u64,
+    },
+    /// Instruction size other than 2 or 4 bytes (0 in helper blocks).
+    InstrSize(u8),
+    /// A static target inside the lifted code where no block starts.
+    TargetNotLeader(u64),
+    /// A write to `x0`.
+    ZeroRegWrite,
+    /// A register at or above the register count (16 for RVE).
+    RegOutOfRange(u8),
+    /// A memory access width other than 1, 2, 4 or 8 bytes.
+    MemWidth(u8),
+    /// An extern call without a name, or a call in an expression without a
+    /// return width.
+    ExternCall(String),
+}
+
+impl fmt::Display for IrRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EmptyBlock => write!(f, "empty block"),
+            Self::StartPc { expected } => write!(f, "block starts at {expected:#x}"),
+            Self::PcGap { expected } => write!(f, "gap after fall-through, expected {expected:#x}"),
+            Self::EndPc { expected, actual } => {
+                write!(f, "block ends at {actual:#x}, expected {expected:#x}")
+            }
+            Self::InstrSize(size) => write!(f, "instruction size {size}"),
+            Self::TargetNotLeader(target) => write!(f, "target {target:#x} starts no block"),
+            Self::ZeroRegWrite => write!(f, "write to x0"),
+            Self::RegOutOfRange(reg) => write!(f, "register x{reg} out of range"),
+            Self::MemWidth(width) => write!(f, "memory access width {width}"),
+            Self::ExternCall(name) => write!(f, "malformed extern call '{name}'"),
+        }
+    }
+}
+
+/// A broken IR invariant, at the instruction that breaks it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IrViolation {
+    /// Guest PC of the instruction (block start for an empty block).
+    pub pc: u64,
+    /// Mnemonic of the instruction (empty for an empty block).
+    pub mnemonic: &'static str,
+    /// The violated rule.
+    pub rule: IrRule,
+}
+
+impl fmt::Display for IrViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#x}", self.pc)?;
+        if !self.mnemonic.is_empty() {
+            write!(f, " {}", self.mnemonic)?;
+        }
+        write!(f, ": {}", self.rule)
+    }
+}
+
+impl<X: Xlen> Pipeline<X> {
+    /// Check the lifted blocks for broken IR invariants: contiguous PCs,
+    /// static targets that start a block, no writes to `x0`, registers
+    /// below the register count and well-formed expressions.
+    ///
+    /// Registers out of range at instructions the decode diagnostics list
+    /// (see [`unsupported`](Self::unsupported)) are already reported there.
+    ///
+    /// # Errors
+    ///
+    /// Returns every violation found, by PC.
+    pub fn verify_ir(&self) -> std::result::Result<(), Vec<IrViolation>> {
+        let empty = HashMap::new();
+        let absorbed = self
+            .block_table
+            .as_ref()
+            .map_or(&empty, |table| &table.absorbed_to_merged);
+        let mut violations = verify_blocks(&self.ir_blocks, absorbed, self.config.num_regs);
+        violations.retain(|violation| {
+            !matches!(violation.rule, IrRule::RegOutOfRange(_))
+                || !self.unsupported.iter().any(|d| d.pc == violation.pc)
+        });
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+}
+
+/// Check `blocks`, keyed by start PC. Targets in `absorbed` are reached
+/// through the block they were merged into.
+fn verify_blocks<X: Xlen>(
+    blocks: &HashMap<u64, BlockIR<X>>,
+    absorbed: &HashMap<u64, u64>,
+    num_regs: usize,
+) -> Vec<IrViolation> {
+    let instr_pcs: HashSet<u64> = blocks
+        .values()
+        .flat_map(|block| &block.instructions)
+        .map(|instr| X::to_u64(instr.pc))
+        .collect();
+    let is_internal = |target: u64| {
+        !instr_pcs.contains(&target)
+            || blocks.contains_key(&target)
+            || absorbed.contains_key(&target)
+    };
+
+    let mut starts: Vec<_> = blocks.keys().copied().collect();
+    starts.sort_unstable();
+    let mut violations = Vec::new();
+    for start in starts {
+        let block = &blocks[&start];
+        let mut check = Check {
+            num_regs,
+            rules: Vec::new(),
+        };
+        let Some(first) = block.instructions.first() else {
+            violations.push(IrViolation {
+                pc: start,
+                mnemonic: "",
+                rule: IrRule::EmptyBlock,
+            });
+            continue;
+        };
+        let mut report = |instr: &InstrIR<X>, rules: &mut Vec<IrRule>| {
+            violations.extend(rules.drain(..).map(|rule| IrViolation {
+                pc: X::to_u64(instr.pc),
+                mnemonic: op_mnemonic(instr.op),
+                rule,
+            }));
+        };
+
+        if X::to_u64(first.pc) != X::to_u64(block.start_pc) {
+            check.rules.push(IrRule::StartPc {
+                expected: X::to_u64(block.start_pc),
+            });
+        }
+        let mut prev: Option<&InstrIR<X>> = None;
+        for (i, instr) in block.instructions.iter().enumerate() {
+            let pc = X::to_u64(instr.pc);
+            if let Some(prev) = prev
+                && !prev.terminator.is_control_flow()
+                && pc != X::to_u64(prev.next_pc())
+            {
+                check.rules.push(IrRule::PcGap {
+                    expected: X::to_u64(prev.next_pc()),
+                });
+            }
+            let synthetic = is_synthetic_pc::<X>(pc);
+            if !matches!((instr.size, synthetic), (2 | 4, false) | (0, true)) {
+                check.rules.push(IrRule::InstrSize(instr.size));
+            }
+            for stmt in &instr.statements {
+                check.stmt(stmt);
+            }
+            check.terminator(&instr.terminator);
+            let is_last = i + 1 == block.instructions.len();
+            for target in static_targets(&instr.terminator, is_last) {
+                if !is_internal(target) {
+                    check.rules.push(IrRule::TargetNotLeader(target));
+                }
+            }
+            report(instr, &mut check.rules);
+            prev = Some(instr);
+        }
+
+        let last = prev.unwrap_or(first);
+        if X::to_u64(block.end_pc) != X::to_u64(last.next_pc()) {
+            check.rules.push(IrRule::EndPc {
+                expected: X::to_u64(last.next_pc()),
+                actual: X::to_u64(block.end_pc),
+            });
+            report(last, &mut check.rules);
+        }
+    }
+    violations
+}
+
+/// Static targets of `terminator`. Fall-through targets only leave the
+/// block from its last instruction.
+fn static_targets<X: Xlen>(terminator: &Terminator<X>, is_last: bool) -> Vec<u64> {
+    let fall = terminator.fall_target().filter(|_| is_last);
+    match terminator {
+        Terminator::Jump { target } | Terminator::Branch { target, .. } => std::iter::once(*target)
+            .chain(fall)
+            .map(X::to_u64)
+            .collect(),
+        _ => fall.into_iter().map(X::to_u64).collect(),
+    }
+}
+
+/// Rules broken by the statements and expressions of one instruction.
+struct Check {
+    num_regs: usize,
+    rules: Vec<IrRule>,
+}
+
+impl Check {
+    fn reg(&mut self, reg: u8) {
+        if usize::from(reg) >= self.num_regs {
+            self.rules.push(IrRule::RegOutOfRange(reg));
+        }
+    }
+
+    fn width(&mut self, width: u8) {
+        if !matches!(width, 1 | 2 | 4 | 8) {
+            self.rules.push(IrRule::MemWidth(width));
+        }
+    }
+
+    fn stmt<X: Xlen>(&mut self, stmt: &Stmt<X>) {
+        match stmt {
+            Stmt::Write { target, value } => {
+                match target {
+                    WriteTarget::Reg(0) => self.rules.push(IrRule::ZeroRegWrite),
+                    WriteTarget::Reg(reg) => self.reg(*reg),
+                    WriteTarget::Mem { base, width, .. } => {
+                        self.width(*width);
+                        self.expr(base);
+                    }
+                    _ => {}
+                }
+                self.expr(value);
+            }
+            Stmt::If {
+                cond,
+                then_stmts,
+                else_stmts,
+            } => {
+                self.expr(cond);
+                for stmt in then_stmts.iter().chain(else_stmts) {
+                    self.stmt(stmt);
+                }
+            }
+            Stmt::ExternCall { fn_name, args } => {
+                if fn_name.is_empty() {
+                    self.rules.push(IrRule::ExternCall(fn_name.clone()));
+                }
+                for arg in args {
+                    self.expr(arg);
+                }
+            }
+        }
+    }
+
+    fn terminator<X: Xlen>(&mut self, terminator: &Terminator<X>) {
+        match terminator {
+            Terminator::JumpDyn { addr, .. } => self.expr(addr),
+            Terminator::Branch { cond, .. } => self.expr(cond),
+            Terminator::Exit { code } => self.expr(code),
+            Terminator::Fall { .. } | Terminator::Jump { .. } | Terminator::Trap { .. } => {}
+        }
+    }
+
+    fn expr<X: Xlen>(&mut self, expr: &Expr<X>) {
+        match expr {
+            Expr::Imm(_) | Expr::PcConst(_) | Expr::Var(_) => {}
+            Expr::Read(read) => match read {
+                ReadExpr::Reg(reg) => self.reg(*reg),
+                ReadExpr::Mem { base, width, .. } => {
+                    self.width(*width);
+                    self.expr(base);
+                }
+                ReadExpr::MemAddr { addr, width, .. } => {
+                    self.width(*width);
+                    self.expr(addr);
+                }
+                _ => {}
+            },
+            Expr::Unary { expr, .. } => self.expr(expr),
+            Expr::Binary { left, right, .. } => {
+                self.expr(left);
+                self.expr(right);
+            }
+            Expr::Ternary {
+                first,
+                second,
+                third,
+                ..
+            } => {
+                self.expr(first);
+                self.expr(second);
+                self.expr(third);
+            }
+            Expr::ExternCall {
+                name,
+                args,
+                ret_width,
+                ..
+            } => {
+                if name.is_empty() || *ret_width == 0 {
+                    self.rules.push(IrRule::ExternCall(name.clone()));
+                }
+                for arg in args {
+                    self.expr(arg);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rvr_ir::IRBuilder;
+    use rvr_isa::Rv64;
+
+    use super::*;
+
+    fn block(instrs: Vec<InstrIR<Rv64>>) -> BlockIR<Rv64> {
+        let mut block = BlockIR::new(instrs[0].pc);
+        for instr in instrs {
+            block.push(instr);
+        }
+        block
+    }
+
+    fn addi(pc: u64) -> IRBuilder<Rv64> {
+        IRBuilder::new(pc, 4)
+    }
+
+    #[test]
+    fn test_verify_blocks() {
+        let a0 = Expr::read(10);
+        let cases: Vec<(&str, BlockIR<Rv64>, Vec<IrRule>)> = vec![
+            (
+                "valid",
+                block(vec![
+                    addi(0x1000).write_reg(10, a0.clone()).build_fall(),
+                    addi(0x1004).build_branch(a0.clone(), 0x1000),
+                ]),
+                vec![],
+            ),
+            (
+                "pc gap",
+                block(vec![
+                    addi(0x1000).build_fall(),
+                    addi(0x1008).build_jump(0x1000),
+                ]),
+                vec![IrRule::PcGap { expected: 0x1004 }],
+            ),
+            (
+                "end pc",
+                BlockIR {
+                    end_pc: 0x1010,
+                    ..block(vec![addi(0x1000).build_jump(0x1000)])
+                },
+                vec![IrRule::EndPc {
+                    expected: 0x1004,
+                    actual: 0x1010,
+                }],
+            ),
+            (
+                "size",
+                block(vec![IRBuilder::new(0x1000, 3).build_jump(0x1000)]),
+                vec![IrRule::InstrSize(3)],
+            ),
+            (
+                "target mid-block",
+                block(vec![
+                    addi(0x1000).build_fall(),
+                    addi(0x1004).build_jump(0x1004),
+                ]),
+                vec![IrRule::TargetNotLeader(0x1004)],
+            ),
+            (
+                "external target",
+                block(vec![addi(0x1000).build_jump(0x9000)]),
+                vec![],
+            ),
+            (
+                "x0 write",
+                block(vec![
+                    addi(0x1000)
+                        .stmt(Stmt::write_reg(0, a0.clone()))
+                        .build_jump(0x1000),
+                ]),
+                vec![IrRule::ZeroRegWrite],
+            ),
+            (
+                "rve register",
+                block(vec![
+                    addi(0x1000)
+                        .write_reg(20, Expr::read(17))
+                        .build_jump(0x1000),
+                ]),
+                vec![IrRule::RegOutOfRange(20), IrRule::RegOutOfRange(17)],
+            ),
+            (
+                "memory width",
+                block(vec![
+                    addi(0x1000)
+                        .write_mem(a0.clone(), 0, Expr::mem_u(a0.clone(), 3), 16)
+                        .build_jump(0x1000),
+                ]),
+                vec![IrRule::MemWidth(16), IrRule::MemWidth(3)],
+            ),
+            (
+                "extern call",
+                block(vec![
+                    addi(0x1000)
+                        .extern_call("", vec![])
+                        .write_reg(10, Expr::extern_call("rv_f", vec![], 0))
+                        .build_exit(a0),
+                ]),
+                vec![
+                    IrRule::ExternCall(String::new()),
+                    IrRule::ExternCall("rv_f".to_string()),
+                ],
+            ),
+        ];
+        for (name, block, expected) in cases {
+            let blocks = HashMap::from([(block.start_pc, block)]);
+            let rules: Vec<_> = verify_blocks(&blocks, &HashMap::new(), 16)
+                .into_iter()
+                .map(|violation| violation.rule)
+                .collect();
+            assert_eq!(rules, expected, "{name}");
+        }
+    }
+
+    #[test]
+    fn test_verify_absorbed_target() {
+        let blocks = HashMap::from([(
+            0x1000,
+            block(vec![
+                addi(0x1000).build_fall(),
+                addi(0x1004).build_jump(0x1004),
+            ]),
+        )]);
+        let absorbed = HashMap::from([(0x1004, 0x1000)]);
+        assert!(verify_blocks(&blocks, &absorbed, 32).is_empty());
+
+        let violations = verify_blocks(&blocks, &HashMap::new(), 32);
+        assert_eq!(
+            violations[0].to_string(),
+            format!("0x1004 {}: target 0x1004 starts no block", op_mnemonic(0))
+        );
+    }
+}
@@ -0,0 +1,419 @@
+//! Block profile symbolization and reports.
+//!
+//! Turns the per-block entry counts of the block profile tracer
+//! ([`Runner::block_profile`](crate::Runner::block_profile)) into a
+//! folded-stack file for inferno/flamegraph.pl and a hottest-blocks report,
+//! and saves them as [`ProfileCounts`] for a profile-guided recompile.
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::ops::Range;
+use std::path::Path;
+
+use rvr_elf::{DebugInfo, ElfImage};
+use rvr_emit::c::content_hash;
+use rvr_ir::SourceLoc;
+use rvr_isa::{Rv32, Rv64, Xlen};
+use tracing::warn;
+
+use crate::{Error, Result};
+
+/// Frame name for blocks outside any known function.
+const UNKNOWN_SYMBOL: &str = "[unknown]";
+
+/// Attribution note printed with the hottest-blocks report.
+const ATTRIBUTION_NOTE: &str = "Counts are block entries. Code merged into a block (superblocks, \
+                                merged and tail-duplicated blocks) is attributed to the block's \
+                                entry PC.";
+
+/// First line of a saved block profile.
+const COUNTS_HEADER: &str = "# rvr block profile v1";
+
+/// One profiled block.
+#[derive(Clone, Debug)]
+pub struct ProfiledBlock {
+    /// Guest PC of the block entry.
+    pub pc: u64,
+    /// Number of times the block was entered.
+    pub count: u64,
+    /// Function containing the entry (debug info, then ELF symbols).
+    pub symbol: Option<String>,
+    /// Source location of the entry, if the ELF has line info.
+    pub location: Option<SourceLoc>,
+}
+
+impl ProfiledBlock {
+    fn symbol(&self) -> &str {
+        self.symbol.as_deref().unwrap_or(UNKNOWN_SYMBOL)
+    }
+
+    /// `file:line` of the entry, or its PC without line info.
+    fn frame(&self) -> String {
+        self.location.as_ref().map_or_else(
+            || format!("{:#x}", self.pc),
+            |loc| format!("{}:{}", loc.file, loc.line),
+        )
+    }
+}
+
+/// Symbolized block profile, hottest block first.
+#[derive(Clone, Debug, Default)]
+pub struct BlockProfile {
+    blocks: Vec<ProfiledBlock>,
+}
+
+impl BlockProfile {
+    /// Symbolize `(pc, count)` pairs against the ELF at `elf_path`.
+    ///
+    /// `load_bias` places a position-independent ELF as the runner did (see
+    /// [`Runner::load_bias`](crate::Runner::load_bias)). Line info comes
+    /// from `addr2line_cmd`; if it fails, a warning is logged and blocks
+    /// are reported by symbol and PC only.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the ELF cannot be read or parsed.
+    pub fn resolve(
+        elf_path: &Path,
+        load_bias: Option<u64>,
+        counts: &[(u64, u64)],
+        addr2line_cmd: &str,
+    ) -> Result<Self> {
+        let data = std::fs::read(elf_path)?;
+        if rvr_elf::get_elf_xlen(&data)? == Rv32::VALUE {
+            let image = ElfImage::<Rv32>::parse_with_load_bias(&data, load_bias)?;
+            Ok(Self::resolve_image(&image, elf_path, counts, addr2line_cmd))
+        } else {
+            let image = ElfImage::<Rv64>::parse_with_load_bias(&data, load_bias)?;
+            Ok(Self::resolve_image(&image, elf_path, counts, addr2line_cmd))
+        }
+    }
+
+    fn resolve_image<X: Xlen>(
+        image: &ElfImage<X>,
+        elf_path: &Path,
+        counts: &[(u64, u64)],
+        addr2line_cmd: &str,
+    ) -> Self {
+        // addr2line sees the unrelocated file
+        let bias = image.load_bias;
+        let addresses: Vec<u64> = counts.iter().map(|&(pc, _)| pc - bias).collect();
+        let debug_info = DebugInfo::load(&elf_path.to_string_lossy(), &addresses, addr2line_cmd)
+            .unwrap_or_else(|e| {
+                warn!(error = %e, "failed to load debug info, profile has no line info");
+                DebugInfo::new()
+            });
+
+        Self::from_counts(
+            counts,
+            |pc| image.function_containing(pc).map(str::to_string),
+            |pc| debug_info.get(pc - bias).cloned(),
+        )
+    }
+
+    /// Build a profile from counts and symbol/location lookups.
+    fn from_counts(
+        counts: &[(u64, u64)],
+        symbol: impl Fn(u64) -> Option<String>,
+        location: impl Fn(u64) -> Option<SourceLoc>,
+    ) -> Self {
+        let mut blocks: Vec<ProfiledBlock> = counts
+            .iter()
+            .filter(|&&(_, count)| count != 0)
+            .map(|&(pc, count)| {
+                let location = location(pc);
+                // Debug info names inlined functions; symbols only the outer one
+                let symbol = location
+                    .as_ref()
+                    .map(|loc| loc.function.clone())
+                    .filter(|function| !function.is_empty())
+                    .or_else(|| symbol(pc));
+                ProfiledBlock {
+                    pc,
+                    count,
+                    symbol,
+                    location: location.filter(SourceLoc::is_valid),
+                }
+            })
+            .collect();
+        blocks.sort_by(|a, b| b.count.cmp(&a.count).then(a.pc.cmp(&b.pc)));
+        Self { blocks }
+    }
+
+    /// Profiled blocks, hottest first.
+    #[must_use]
+    pub fn blocks(&self) -> &[ProfiledBlock] {
+        &self.blocks
+    }
+
+    /// Total block entries.
+    #[must_use]
+    pub fn total(&self) -> u64 {
+        self.blocks.iter().map(|block| block.count).sum()
+    }
+
+    /// Write folded stacks (`symbol;file:line count`, one per line).
+    ///
+    /// Blocks without line info use their PC as the frame. Blocks sharing
+    /// a frame are summed.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from writing to `w`.
+    pub fn write_folded(&self, mut w: impl Write) -> io::Result<()> {
+        let mut stacks: BTreeMap<String, u64> = BTreeMap::new();
+        for block in &self.blocks {
+            let stack = format!("{};{}", block.symbol(), block.frame());
+            *stacks.entry(stack).or_default() += block.count;
+        }
+        for (stack, count) in stacks {
+            writeln!(w, "{stack} {count}")?;
+        }
+        Ok(())
+    }
+
+    /// Write the `limit` hottest blocks as a text table.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from writing to `w`.
+    pub fn write_hottest(&self, mut w: impl Write, limit: usize) -> io::Result<()> {
+        let total = self.total();
+        let shown = limit.min(self.blocks.len());
+        writeln!(
+            w,
+            "Hottest blocks ({shown} of {}, {total} entries)",
+            self.blocks.len()
+        )?;
+        writeln!(w, "Note: {ATTRIBUTION_NOTE}")?;
+        writeln!(
+            w,
+            "{:>4}  {:>14}  {:>6}  {:<18}  location",
+            "rank", "count", "%", "pc"
+        )?;
+        for (rank, block) in self.blocks.iter().take(limit).enumerate() {
+            writeln!(
+                w,
+                "{:>4}  {:>14}  {:>6}  {:<18}  {} ({})",
+                rank + 1,
+                block.count,
+                percent(block.count, total),
+                format!("{:#x}", block.pc),
+                block.symbol(),
+                block.frame()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Block entry counts saved for a profile-guided recompile
+/// (see `CompileOptions::with_profile`).
+///
+/// The file holds the counts of [`Runner::block_profile`](crate::Runner::block_profile)
+/// as one `<pc> <count>` line each, after a header with the hash of the ELF
+/// they were collected from:
+///
+/// ```text
+/// # rvr block profile v1
+/// elf 0123456789abcdef
+/// 0x80000000 1
+/// 0x80000010 4096
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProfileCounts {
+    elf_hash: u64,
+    counts: BTreeMap<u64, u64>,
+}
+
+impl ProfileCounts {
+    /// Counts collected from the ELF `elf` (its file contents).
+    #[must_use]
+    pub fn new(elf: &[u8], counts: &[(u64, u64)]) -> Self {
+        Self {
+            elf_hash: content_hash(elf),
+            counts: counts.iter().copied().collect(),
+        }
+    }
+
+    /// Read a saved profile.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a block profile.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        Self::parse(&text)
+            .map_err(|message| Error::InvalidProfile(format!("{}: {message}", path.display())))
+    }
+
+    fn parse(text: &str) -> std::result::Result<Self, String> {
+        let mut lines = text.lines().enumerate().map(|(idx, line)| (idx + 1, line));
+        if lines.next().map(|(_, line)| line.trim()) != Some(COUNTS_HEADER) {
+            return Err(format!("missing `{COUNTS_HEADER}` header"));
+        }
+        let elf_hash = lines
+            .next()
+            .and_then(|(_, line)| line.trim().strip_prefix("elf "))
+            .and_then(|hash| u64::from_str_radix(hash.trim(), 16).ok())
+            .ok_or("missing `elf <hash>` line")?;
+        let mut counts = BTreeMap::new();
+        for (number, line) in lines.filter(|(_, line)| !line.trim().is_empty()) {
+            let entry = line.split_once(' ').and_then(|(pc, count)| {
+                let pc = u64::from_str_radix(pc.trim_start_matches("0x"), 16).ok()?;
+                Some((pc, count.trim().parse::<u64>().ok()?))
+            });
+            let (pc, count) =
+                entry.ok_or_else(|| format!("line {number}: expected `<pc> <count>`"))?;
+            counts.insert(pc, count);
+        }
+        Ok(Self { elf_hash, counts })
+    }
+
+    /// Write the profile in the format [`load`](Self::load) reads.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from writing to `w`.
+    pub fn write(&self, mut w: impl Write) -> io::Result<()> {
+        writeln!(w, "{COUNTS_HEADER}")?;
+        writeln!(w, "elf {:016x}", self.elf_hash)?;
+        for (pc, count) in &self.counts {
+            writeln!(w, "{pc:#x} {count}")?;
+        }
+        Ok(())
+    }
+
+    /// True if the counts were collected from the ELF `elf`.
+    #[must_use]
+    pub fn matches(&self, elf: &[u8]) -> bool {
+        self.elf_hash == content_hash(elf)
+    }
+
+    /// Entries of the block starting at `pc`.
+    #[must_use]
+    pub fn count(&self, pc: u64) -> u64 {
+        self.counts.get(&pc).copied().unwrap_or(0)
+    }
+
+    /// Entries of the hottest block starting in `range`.
+    ///
+    /// Blocks can be split differently than in the profiled build (the
+    /// tracer changes which passes run), so this looks at every block
+    /// starting inside a lifted block, not just at its start.
+    #[must_use]
+    pub fn hottest(&self, range: Range<u64>) -> u64 {
+        self.counts
+            .range(range)
+            .map(|(_, &count)| count)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// True if no block was entered.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.counts.values().all(|&count| count == 0)
+    }
+}
+
+/// `part / total` as a percentage with one decimal.
+fn percent(part: u64, total: u64) -> String {
+    let permille = u128::from(part) * 1000 / u128::from(total.max(1));
+    format!("{}.{}%", permille / 10, permille % 10)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile() -> BlockProfile {
+        let counts = [
+            (0x1000, 5),
+            (0x1010, 0),
+            (0x1020, 90),
+            (0x1030, 5),
+            (0x2000, 1),
+        ];
+        BlockProfile::from_counts(
+            &counts,
+            |pc| (pc < 0x2000).then(|| "main".to_string()),
+            |pc| match pc {
+                0x1000 | 0x1030 => Some(SourceLoc::new("main.c", 3, "")),
+                0x1020 => Some(SourceLoc::new("main.c", 7, "inner")),
+                _ => None,
+            },
+        )
+    }
+
+    #[test]
+    fn test_blocks_sorted_hottest_first() {
+        let profile = profile();
+        let pcs: Vec<u64> = profile.blocks().iter().map(|block| block.pc).collect();
+        assert_eq!(pcs, [0x1020, 0x1000, 0x1030, 0x2000]);
+        assert_eq!(profile.total(), 101);
+        assert_eq!(profile.blocks()[0].symbol.as_deref(), Some("inner"));
+    }
+
+    #[test]
+    fn test_write_folded() {
+        let mut out = Vec::new();
+        profile().write_folded(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "[unknown];0x2000 1\ninner;main.c:7 90\nmain;main.c:3 10\n"
+        );
+    }
+
+    #[test]
+    fn test_write_hottest() {
+        let mut out = Vec::new();
+        profile().write_hottest(&mut out, 2).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0], "Hottest blocks (2 of 4, 101 entries)");
+        assert!(lines[1].contains("attributed to the block's entry PC"));
+        assert!(lines[3].contains("90"));
+        assert!(lines[3].contains("89.1%"));
+        assert!(lines[3].ends_with("inner (main.c:7)"));
+        assert!(lines[4].ends_with("main (main.c:3)"));
+    }
+
+    #[test]
+    fn test_profile_counts_round_trip() {
+        let elf = b"\x7fELF guest";
+        let counts = ProfileCounts::new(elf, &[(0x8000_0000, 1), (0x8000_0010, 4096)]);
+        let mut out = Vec::new();
+        counts.write(&mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.starts_with("# rvr block profile v1\nelf "));
+        assert!(text.ends_with("0x80000000 1\n0x80000010 4096\n"));
+
+        let parsed = ProfileCounts::parse(&text).unwrap();
+        assert_eq!(parsed, counts);
+        assert!(parsed.matches(elf));
+        assert!(!parsed.matches(b"\x7fELF other"));
+        assert_eq!(parsed.count(0x8000_0010), 4096);
+        assert_eq!(parsed.count(0x8000_0004), 0);
+        assert_eq!(parsed.hottest(0x8000_0000..0x8000_0014), 4096);
+        assert_eq!(parsed.hottest(0x8000_0004..0x8000_0010), 0);
+        assert!(!parsed.is_empty());
+        assert!(ProfileCounts::new(elf, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_profile_counts_parse_errors() {
+        assert!(ProfileCounts::parse("main;0x1000 5\n").is_err());
+        assert!(ProfileCounts::parse("# rvr block profile v1\n0x1000 5\n").is_err());
+        let err = ProfileCounts::parse("# rvr block profile v1\nelf 0\n0x1000 x\n").unwrap_err();
+        assert!(err.contains("line 3"), "{err}");
+    }
+
+    #[test]
+    fn test_percent() {
+        assert_eq!(percent(1, 3), "33.3%");
+        assert_eq!(percent(0, 0), "0.0%");
+        assert_eq!(percent(5, 5), "100.0%");
+    }
+}
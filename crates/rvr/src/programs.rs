@@ -0,0 +1,135 @@
+//! Manifest of a multi-program shared library.
+//!
+//! [`Recompiler::compile_many`](crate::Recompiler::compile_many) compiles
+//! several ELFs into one library, each in its own `<name>/` subdirectory
+//! with its symbols prefixed by `<name>_`. The manifest records what a host
+//! needs to load each program from the combined library (see
+//! [`Runner::load_program`](crate::Runner::load_program)).
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// File name of the manifest in the library directory.
+pub const PROGRAMS_MANIFEST: &str = "programs.json";
+
+/// One program in a combined library.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProgramEntry {
+    /// Program name; also the symbol prefix (`<name>_`) and subdirectory.
+    pub name: String,
+    /// Entry PC (load bias applied).
+    pub entry_point: u64,
+    /// Guest memory the program was compiled for, in bytes.
+    pub memory_size: u64,
+    /// Register width (32 or 64).
+    pub xlen: u8,
+    /// Number of general-purpose registers (16 for RV32E/RV64E).
+    pub num_regs: usize,
+}
+
+impl ProgramEntry {
+    /// Prefix of the program's symbols in the library.
+    #[must_use]
+    pub fn symbol_prefix(&self) -> String {
+        symbol_prefix(&self.name)
+    }
+}
+
+/// Programs compiled into one shared library, in compile order.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProgramManifest {
+    pub programs: Vec<ProgramEntry>,
+}
+
+impl ProgramManifest {
+    /// Path of the manifest in `lib_dir`.
+    #[must_use]
+    pub fn path(lib_dir: &Path) -> PathBuf {
+        lib_dir.join(PROGRAMS_MANIFEST)
+    }
+
+    /// Read the manifest from `lib_dir`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a manifest.
+    pub fn load(lib_dir: &Path) -> io::Result<Self> {
+        let data = std::fs::read_to_string(Self::path(lib_dir))?;
+        serde_json::from_str(&data).map_err(io::Error::other)
+    }
+
+    /// Write the manifest to `lib_dir`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn save(&self, lib_dir: &Path) -> io::Result<()> {
+        let data = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        std::fs::write(Self::path(lib_dir), data + "\n")
+    }
+
+    /// The program called `name`.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&ProgramEntry> {
+        self.programs.iter().find(|program| program.name == name)
+    }
+}
+
+/// Symbol prefix of the program called `name`.
+pub fn symbol_prefix(name: &str) -> String {
+    format!("{name}_")
+}
+
+/// Whether `name` can name a program: a C identifier, so the prefixed
+/// symbols are too.
+pub fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = ProgramManifest {
+            programs: vec![
+                ProgramEntry {
+                    name: "fib".to_string(),
+                    entry_point: 0x8000_0000,
+                    memory_size: 1 << 32,
+                    xlen: 64,
+                    num_regs: 32,
+                },
+                ProgramEntry {
+                    name: "sha".to_string(),
+                    entry_point: 0x1_0000,
+                    memory_size: 1 << 24,
+                    xlen: 32,
+                    num_regs: 16,
+                },
+            ],
+        };
+        manifest.save(dir.path()).unwrap();
+        let loaded = ProgramManifest::load(dir.path()).unwrap();
+        assert_eq!(loaded, manifest);
+        assert_eq!(loaded.get("sha").unwrap().symbol_prefix(), "sha_");
+        assert!(loaded.get("missing").is_none());
+    }
+
+    #[test]
+    fn test_is_valid_name() {
+        assert!(is_valid_name("fib"));
+        assert!(is_valid_name("_sha256_v2"));
+        assert!(!is_valid_name(""));
+        assert!(!is_valid_name("2fast"));
+        assert!(!is_valid_name("my-prog"));
+    }
+}
@@ -0,0 +1,169 @@
+//! Compile progress reporting.
+//!
+//! A compile goes through the phases of [`CompilePhase`] in order and
+//! reports counts within each to the callback of
+//! `CompileOptions::with_progress`. Counts can be reported from several
+//! threads at once (lifting is parallel), so the callback is `Send + Sync`.
+
+use std::fmt;
+use std::sync::Arc;
+
+/// Percent steps reported within a phase.
+const PERCENT: usize = 100;
+
+/// Phase of a compile, in order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CompilePhase {
+    /// Reading and parsing the ELF.
+    Parse,
+    /// Building the CFG; counts blocks, known only when analysis finishes.
+    Cfg,
+    /// Lifting blocks to IR.
+    Lift,
+    /// Writing C part files or assembly.
+    Emit,
+    /// Compiling the emitted sources; counts the objects this build
+    /// compiles (unchanged parts are skipped).
+    Cc,
+}
+
+impl CompilePhase {
+    /// Unit counted within the phase.
+    #[must_use]
+    pub const fn unit(self) -> &'static str {
+        match self {
+            Self::Parse => "files",
+            Self::Cfg | Self::Lift => "blocks",
+            Self::Emit | Self::Cc => "parts",
+        }
+    }
+}
+
+impl fmt::Display for CompilePhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Parse => "parse",
+            Self::Cfg => "cfg",
+            Self::Lift => "lift",
+            Self::Emit => "emit",
+            Self::Cc => "cc",
+        })
+    }
+}
+
+/// Progress of a compile: `done` of `total` units of `phase`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CompileProgress {
+    /// Current phase.
+    pub phase: CompilePhase,
+    /// Units finished.
+    pub done: usize,
+    /// Units in the phase (0 while unknown).
+    pub total: usize,
+}
+
+impl CompileProgress {
+    /// Whole percent done, if the total is known.
+    #[must_use]
+    pub fn percent(&self) -> Option<usize> {
+        if self.total == 0 {
+            return None;
+        }
+        Some(self.done.min(self.total) * PERCENT / self.total)
+    }
+}
+
+impl fmt::Display for CompileProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.percent() {
+            Some(percent) => write!(
+                f,
+                "{} {percent}% ({}/{} {})",
+                self.phase,
+                self.done,
+                self.total,
+                self.phase.unit()
+            ),
+            None => write!(f, "{}", self.phase),
+        }
+    }
+}
+
+/// Callback of `CompileOptions::with_progress`.
+#[derive(Clone)]
+pub struct ProgressFn(Arc<dyn Fn(CompileProgress) + Send + Sync>);
+
+impl ProgressFn {
+    /// Wrap `callback`.
+    #[must_use]
+    pub fn new(callback: Box<dyn Fn(CompileProgress) + Send + Sync>) -> Self {
+        Self(Arc::from(callback))
+    }
+
+    /// Report `done` of `total` units of `phase`.
+    pub fn report(&self, phase: CompilePhase, done: usize, total: usize) {
+        (self.0)(CompileProgress { phase, done, total });
+    }
+
+    /// Report `done` of `total` units of `phase` if it is the first count
+    /// of a new whole percent, so a phase reports at most about a hundred
+    /// times however many units it has.
+    pub fn step(&self, phase: CompilePhase, done: usize, total: usize) {
+        let percent = |done: usize| done * PERCENT / total.max(1);
+        if done <= 1 || done >= total || percent(done) != percent(done - 1) {
+            self.report(phase, done, total);
+        }
+    }
+}
+
+impl fmt::Debug for ProgressFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressFn(..)")
+    }
+}
+
+/// Report to `progress`, if set.
+pub fn report(progress: Option<&ProgressFn>, phase: CompilePhase, done: usize, total: usize) {
+    if let Some(progress) = progress {
+        progress.report(phase, done, total);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let progress = CompileProgress {
+            phase: CompilePhase::Lift,
+            done: 1234,
+            total: 2938,
+        };
+        assert_eq!(progress.to_string(), "lift 42% (1234/2938 blocks)");
+        let unknown = CompileProgress {
+            phase: CompilePhase::Cfg,
+            done: 0,
+            total: 0,
+        };
+        assert_eq!(unknown.percent(), None);
+        assert_eq!(unknown.to_string(), "cfg");
+    }
+
+    #[test]
+    fn test_step_reports_each_percent_once() {
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&reported);
+        let progress = ProgressFn::new(Box::new(move |p: CompileProgress| {
+            sink.lock().unwrap().push(p.done);
+        }));
+        let total = 1000;
+        (1..=total).for_each(|done| progress.step(CompilePhase::Lift, done, total));
+        let reported = std::mem::take(&mut *reported.lock().unwrap());
+        assert_eq!(reported.len(), PERCENT + 1);
+        assert_eq!(reported.first(), Some(&1));
+        assert_eq!(reported.last(), Some(&total));
+    }
+}
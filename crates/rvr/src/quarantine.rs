@@ -0,0 +1,289 @@
+//! Lift-error quarantine.
+//!
+//! With `LiftErrorMode::Quarantine`, a block that fails to lift is replaced
+//! by a trap stub instead of failing the compile. The stub exits at its own
+//! PC, and the C backend exports the stub PCs and error descriptions
+//! (`RV_QUARANTINE_PCS` / `RV_QUARANTINE_REASONS`) so the runner can turn
+//! that exit into a [`RunError::QuarantinedBlock`](crate::RunError).
+
+use std::collections::HashMap;
+use std::fmt;
+
+use rvr_cfg::InstructionTable;
+use rvr_elf::ElfImage;
+use rvr_ir::{BlockIR, InstrIR, SyntheticBlockInfo, Terminator};
+use rvr_isa::{ExtensionRegistry, Xlen};
+use tracing::{info, warn};
+
+/// Bytes of context recorded for a failing instruction.
+const RAW_CONTEXT_BYTES: usize = 4;
+/// Size of a stub standing in for undecodable bytes (one instruction slot).
+const STUB_SIZE: u8 = 2;
+/// Packed op id of the stub; names no extension, so it traces as `???`.
+const STUB_OP: u16 = u16::MAX;
+
+// Major opcodes (bits [6:0]) used to guess the extension of failing bytes.
+const OPCODE_MASK: u8 = 0x7F;
+const QUADRANT_MASK: u8 = 0b11;
+const QUADRANT_32BIT: u8 = 0b11;
+const OPCODE_LOAD_FP: u8 = 0x07;
+const OPCODE_MISC_MEM: u8 = 0x0F;
+const OPCODE_STORE_FP: u8 = 0x27;
+const OPCODE_AMO: u8 = 0x2F;
+const OPCODE_FMADD: u8 = 0x43;
+const OPCODE_FMSUB: u8 = 0x47;
+const OPCODE_FNMSUB: u8 = 0x4B;
+const OPCODE_FNMADD: u8 = 0x4F;
+const OPCODE_OP_FP: u8 = 0x53;
+const OPCODE_OP_V: u8 = 0x57;
+const OPCODE_SYSTEM: u8 = 0x73;
+const OPCODE_CUSTOM: [u8; 4] = [0x0B, 0x2B, 0x5B, 0x7B];
+/// LOAD-FP/STORE-FP `width` (funct3) values of vector loads and stores.
+const VECTOR_MEM_WIDTHS: [u8; 4] = [0b000, 0b101, 0b110, 0b111];
+
+/// Why a block failed to lift.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LiftFailureKind {
+    /// A direct jump or branch targets bytes that do not decode.
+    Undecodable,
+    /// An instruction decoded, but no extension lifts it.
+    UnhandledExtension,
+    /// An RVE instruction names a register outside x0-x15.
+    RegisterOutOfRange,
+}
+
+impl fmt::Display for LiftFailureKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Undecodable => write!(f, "undecodable instruction"),
+            Self::UnhandledExtension => write!(f, "unhandled extension"),
+            Self::RegisterOutOfRange => write!(f, "register outside the RVE register file"),
+        }
+    }
+}
+
+/// A block that failed to lift, with enough context to judge reachability.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LiftFailure {
+    /// Failure category.
+    pub kind: LiftFailureKind,
+    /// PC of the block replaced by a trap stub.
+    pub block_pc: u64,
+    /// PC of the offending instruction.
+    pub pc: u64,
+    /// Raw bytes at `pc` (up to one 32-bit instruction).
+    pub raw: Vec<u8>,
+    /// Best guess at the extension the bytes belong to.
+    pub extension: Option<&'static str>,
+    /// Function symbol containing `pc`, if any.
+    pub symbol: Option<String>,
+}
+
+impl fmt::Display for LiftFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {:#x}", self.kind, self.pc)?;
+        if let Some(symbol) = &self.symbol {
+            write!(f, " in {symbol}")?;
+        }
+        write!(f, " (bytes")?;
+        for byte in &self.raw {
+            write!(f, " {byte:02x}")?;
+        }
+        if let Some(ext) = self.extension {
+            write!(f, ", likely {ext}")?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl LiftFailure {
+    fn new<X: Xlen>(
+        kind: LiftFailureKind,
+        block_pc: u64,
+        pc: u64,
+        raw: Vec<u8>,
+        image: &ElfImage<X>,
+    ) -> Self {
+        Self {
+            kind,
+            block_pc,
+            pc,
+            extension: guess_extension(&raw),
+            raw,
+            symbol: image.function_containing(pc).map(str::to_string),
+        }
+    }
+
+    /// Build the trap stub that replaces the failing block.
+    ///
+    /// `end_pc` keeps the replaced block's extent so dispatch ranges are unchanged.
+    pub(crate) fn stub<X: Xlen>(&self, end_pc: Option<u64>) -> BlockIR<X> {
+        let mut block = trap_stub(self.block_pc, &format!("quarantined: {self}"));
+        if let Some(end) = end_pc {
+            block.end_pc = X::from_u64(end);
+        }
+        block
+    }
+}
+
+/// A one-slot block at `pc` that traps with `message`.
+pub fn trap_stub<X: Xlen>(pc: u64, message: &str) -> BlockIR<X> {
+    let start = X::from_u64(pc);
+    let mut block = BlockIR::new(start);
+    block.push(InstrIR::new(
+        start,
+        STUB_SIZE,
+        STUB_OP,
+        0,
+        Vec::new(),
+        Terminator::trap(message),
+    ));
+    block
+}
+
+/// Find lifted blocks that failed to lift, sorted by block PC.
+///
+/// Helper blocks of override expansions are skipped; their guest PCs are
+/// checked through the owning instruction.
+pub fn find_lift_failures<X: Xlen>(
+    blocks: &HashMap<u64, BlockIR<X>>,
+    synthetic: &HashMap<u64, SyntheticBlockInfo>,
+    instr_table: &InstructionTable<X>,
+    registry: &ExtensionRegistry<X>,
+    image: &ElfImage<X>,
+) -> Vec<LiftFailure> {
+    let mut failures: Vec<LiftFailure> = Vec::new();
+    let guest_blocks = blocks.iter().filter(|(pc, _)| !synthetic.contains_key(pc));
+
+    for (&block_pc, block) in guest_blocks {
+        // Instructions that decoded but have no lifter
+        if let Some(instr) = block.instructions.iter().find(|ir| {
+            instr_table
+                .get_at_pc(X::to_u64(ir.pc))
+                .is_some_and(|d| !registry.can_lift(d.opid))
+        }) {
+            let size = usize::from(instr.size);
+            let raw = instr.raw.to_le_bytes()[..size.min(RAW_CONTEXT_BYTES)].to_vec();
+            failures.push(LiftFailure::new(
+                LiftFailureKind::UnhandledExtension,
+                block_pc,
+                X::to_u64(instr.pc),
+                raw,
+                image,
+            ));
+        }
+
+        // Direct control flow into bytes that do not decode
+        let targets = block
+            .instructions
+            .iter()
+            .filter_map(|ir| match &ir.terminator {
+                Terminator::Jump { target } | Terminator::Branch { target, .. } => {
+                    Some(X::to_u64(*target))
+                }
+                _ => None,
+            });
+        for target in targets {
+            if target < instr_table.base_address()
+                || target >= instr_table.end_address()
+                || instr_table.is_valid_pc(target)
+                || blocks.contains_key(&target)
+                || failures.iter().any(|f| f.block_pc == target)
+            {
+                continue;
+            }
+            let Some(raw) = image.bytes_at(target, RAW_CONTEXT_BYTES) else {
+                continue;
+            };
+            failures.push(LiftFailure::new(
+                LiftFailureKind::Undecodable,
+                target,
+                target,
+                raw.to_vec(),
+                image,
+            ));
+        }
+    }
+
+    failures.sort_by_key(|f| f.block_pc);
+    failures
+}
+
+/// Replace each failing block with its trap stub.
+pub fn quarantine_blocks<X: Xlen>(blocks: &mut HashMap<u64, BlockIR<X>>, failures: &[LiftFailure]) {
+    for failure in failures {
+        warn!(
+            pc = format!("{:#x}", failure.block_pc),
+            "quarantined block: {failure}"
+        );
+        let end_pc = blocks.get(&failure.block_pc).map(|b| X::to_u64(b.end_pc));
+        blocks.insert(failure.block_pc, failure.stub(end_pc));
+    }
+    info!(
+        blocks = failures.len(),
+        "quarantined blocks that failed to lift"
+    );
+}
+
+/// Guess which extension `raw` belongs to from its major opcode.
+pub fn guess_extension(raw: &[u8]) -> Option<&'static str> {
+    let &first = raw.first()?;
+    if first & QUADRANT_MASK != QUADRANT_32BIT {
+        return Some("C");
+    }
+    match first & OPCODE_MASK {
+        // Vector loads/stores share the FP opcodes; their widths are 0, 5, 6, 7
+        OPCODE_LOAD_FP | OPCODE_STORE_FP
+            if raw
+                .get(1)
+                .is_some_and(|b| VECTOR_MEM_WIDTHS.contains(&((b >> 4) & 0b111))) =>
+        {
+            Some("V")
+        }
+        OPCODE_LOAD_FP | OPCODE_STORE_FP | OPCODE_FMADD | OPCODE_FMSUB | OPCODE_FNMSUB
+        | OPCODE_FNMADD | OPCODE_OP_FP => Some("F/D"),
+        OPCODE_OP_V => Some("V"),
+        OPCODE_AMO => Some("A"),
+        OPCODE_MISC_MEM => Some("Zifencei"),
+        OPCODE_SYSTEM => Some("Zicsr/privileged"),
+        op if OPCODE_CUSTOM.contains(&op) => Some("custom"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guess_extension() {
+        // fadd.d f0, f0, f0
+        assert_eq!(guess_extension(&[0x53, 0x70, 0x00, 0x02]), Some("F/D"));
+        // vsetvli
+        assert_eq!(guess_extension(&[0x57, 0x70, 0x00, 0x00]), Some("V"));
+        // vle32.v v0, (a0) vs. flw f0, 0(a0)
+        assert_eq!(guess_extension(&[0x07, 0x60, 0x05, 0x02]), Some("V"));
+        assert_eq!(guess_extension(&[0x07, 0x20, 0x05, 0x00]), Some("F/D"));
+        // c.unimp
+        assert_eq!(guess_extension(&[0x00, 0x00]), Some("C"));
+        assert_eq!(guess_extension(&[0x0B, 0x00, 0x00, 0x00]), Some("custom"));
+        assert_eq!(guess_extension(&[0x13, 0x00, 0x00, 0x00]), None);
+        assert_eq!(guess_extension(&[]), None);
+    }
+
+    #[test]
+    fn test_failure_display() {
+        let failure = LiftFailure {
+            kind: LiftFailureKind::Undecodable,
+            block_pc: 0x8000_0014,
+            pc: 0x8000_0014,
+            raw: vec![0x57, 0x70, 0x00, 0x00],
+            extension: Some("V"),
+            symbol: Some("vec_kernel".to_string()),
+        };
+        assert_eq!(
+            failure.to_string(),
+            "undecodable instruction at 0x80000014 in vec_kernel (bytes 57 70 00 00, likely V)"
+        );
+    }
+}
@@ -0,0 +1,324 @@
+//! Assembling and linking the assembly backends' output.
+
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use rvr_emit::Compiler;
+use rvr_emit::c::DEFAULT_CLANG_COMMAND;
+use tracing::{debug, error, info_span};
+
+use crate::{Error, Result};
+
+fn configure_asm_command(cmd: &mut Command, needs_cross: bool, target_triple: &str) {
+    if needs_cross {
+        cmd.args([
+            format!("--target={target_triple}"),
+            "-c".to_string(),
+            "-fPIC".to_string(),
+        ]);
+    } else {
+        cmd.args(["-c", "-fPIC"]);
+    }
+}
+
+fn configure_c_command(cmd: &mut Command, needs_cross: bool, target_triple: &str) {
+    if needs_cross {
+        cmd.args([
+            format!("--target={target_triple}"),
+            "-c".to_string(),
+            "-fPIC".to_string(),
+            "-O2".to_string(),
+            "-std=c23".to_string(),
+        ]);
+    } else {
+        cmd.args(["-c", "-fPIC", "-O2", "-std=c23"]);
+    }
+}
+
+fn assemble_asm(
+    cc: &str,
+    asm_path: &Path,
+    obj_path: &Path,
+    needs_cross: bool,
+    target_triple: &str,
+) -> Result<()> {
+    let mut asm_cmd = Command::new(cc);
+    configure_asm_command(&mut asm_cmd, needs_cross, target_triple);
+    asm_cmd.arg("-o").arg(obj_path).arg(asm_path);
+
+    let asm_output = {
+        let _span = info_span!("assemble").entered();
+        asm_cmd
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output()
+            .map_err(|e| Error::CompilationFailed(format!("Failed to run {cc}: {e}")))?
+    };
+
+    if !asm_output.status.success() {
+        let stderr = String::from_utf8_lossy(&asm_output.stderr);
+        error!(stderr = %stderr, "assembly failed");
+        let first_line = stderr.lines().next().unwrap_or("unknown error");
+        return Err(Error::CompilationFailed(format!(
+            "Assembly failed: {first_line}"
+        )));
+    }
+
+    Ok(())
+}
+
+fn compile_optional_c(
+    cc: &str,
+    output_dir: &Path,
+    base_name: &str,
+    suffix: &str,
+    needs_cross: bool,
+    target_triple: &str,
+) -> Result<Option<std::path::PathBuf>> {
+    let c_path = output_dir.join(format!("{base_name}_{suffix}.c"));
+    if !c_path.exists() {
+        return Ok(None);
+    }
+
+    let obj_path = output_dir.join(format!("{base_name}_{suffix}.o"));
+    let mut cmd = Command::new(cc);
+    configure_c_command(&mut cmd, needs_cross, target_triple);
+    cmd.arg("-I")
+        .arg(output_dir)
+        .arg("-o")
+        .arg(&obj_path)
+        .arg(&c_path);
+
+    let output = {
+        let _span = info_span!("compile_support_c", suffix = suffix).entered();
+        cmd.stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output()
+            .map_err(|e| Error::CompilationFailed(format!("Failed to compile {suffix}: {e}")))?
+    };
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        error!(stderr = %stderr, "{suffix} compilation failed");
+        let first_line = stderr.lines().next().unwrap_or("unknown error");
+        return Err(Error::CompilationFailed(format!(
+            "{suffix} compilation failed: {first_line}"
+        )));
+    }
+
+    Ok(Some(obj_path))
+}
+
+fn link_shared(
+    cc: &str,
+    obj_files: &[std::path::PathBuf],
+    lib_path: &Path,
+    compiler: &Compiler,
+    ldflags: &[&str],
+    needs_cross: bool,
+    target_triple: &str,
+) -> Result<()> {
+    let mut link_cmd = Command::new(cc);
+
+    if needs_cross {
+        link_cmd.args([
+            format!("--target={target_triple}"),
+            "-fuse-ld=lld".to_string(),
+            "-nostdlib".to_string(),
+            "-shared".to_string(),
+            "-Wl,-z,noexecstack".to_string(),
+        ]);
+    } else {
+        link_cmd.args(["-shared", "-Wl,-z,noexecstack"]);
+        if let Some(linker) = compiler.linker() {
+            link_cmd.arg(format!("-fuse-ld={linker}"));
+        }
+    }
+
+    link_cmd.args(ldflags);
+    link_cmd.arg("-o").arg(lib_path);
+    for obj in obj_files {
+        link_cmd.arg(obj);
+    }
+
+    let link_output = {
+        let _span = info_span!("link_shared").entered();
+        link_cmd
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output()
+            .map_err(|e| Error::CompilationFailed(format!("Failed to link: {e}")))?
+    };
+
+    if !link_output.status.success() {
+        let stderr = String::from_utf8_lossy(&link_output.stderr);
+        error!(stderr = %stderr, "linking failed");
+        let first_line = stderr.lines().next().unwrap_or("unknown error");
+        return Err(Error::CompilationFailed(format!(
+            "Linking failed: {first_line}"
+        )));
+    }
+
+    Ok(())
+}
+
+/// Compile x86 assembly to shared library.
+///
+/// On non-x86 hosts, uses clang for cross-compilation with:
+/// - `--target=x86_64-unknown-linux-gnu` for x86 target
+/// - `-fuse-ld=lld` for cross-linking
+/// - `-nostdlib` since generated code is self-contained
+pub fn compile_x86_to_shared(
+    output_dir: &Path,
+    base_name: &str,
+    compiler: &Compiler,
+    quiet: bool,
+) -> Result<()> {
+    let _span = info_span!("compile_x86").entered();
+
+    let asm_path = output_dir.join(format!("{base_name}.s"));
+    let obj_path = output_dir.join(format!("{base_name}.o"));
+    let lib_path = output_dir.join(format!("lib{base_name}.so"));
+
+    if !asm_path.exists() {
+        return Err(Error::CompilationFailed(format!(
+            "Assembly file not found: {}",
+            asm_path.display()
+        )));
+    }
+
+    // Check if we need cross-compilation (non-x86 host)
+    let is_x86_host = cfg!(target_arch = "x86_64") || cfg!(target_arch = "x86");
+    let needs_cross = !is_x86_host;
+
+    let target_triple = "x86_64-unknown-linux-gnu";
+    let cc = if needs_cross {
+        DEFAULT_CLANG_COMMAND
+    } else {
+        compiler.command()
+    };
+
+    debug!(asm = %asm_path.display(), compiler = %cc, cross = %needs_cross, "assembling");
+
+    assemble_asm(cc, &asm_path, &obj_path, needs_cross, target_triple)?;
+
+    debug!(obj = %obj_path.display(), "linking");
+
+    let mut obj_files = vec![obj_path];
+    if let Some(path) = compile_optional_c(
+        cc,
+        output_dir,
+        base_name,
+        "syscalls",
+        needs_cross,
+        target_triple,
+    )? {
+        obj_files.push(path);
+    }
+    if let Some(path) = compile_optional_c(
+        cc,
+        output_dir,
+        base_name,
+        "htif",
+        needs_cross,
+        target_triple,
+    )? {
+        obj_files.push(path);
+    }
+
+    link_shared(
+        cc,
+        &obj_files,
+        &lib_path,
+        compiler,
+        &[],
+        needs_cross,
+        target_triple,
+    )?;
+
+    if !quiet {
+        debug!(lib = %lib_path.display(), cross = %needs_cross, "compiled x86 shared library");
+    }
+
+    Ok(())
+}
+
+/// Compile ARM64 assembly to shared library.
+///
+/// On non-ARM64 hosts, uses clang for cross-compilation with:
+/// - `--target=aarch64-unknown-linux-gnu` for ARM64 target
+/// - `-fuse-ld=lld` for cross-linking
+/// - `-nostdlib` since generated code is self-contained
+pub(super) fn compile_arm64_to_shared(
+    output_dir: &Path,
+    base_name: &str,
+    compiler: &Compiler,
+    ldflags: &[&str],
+    quiet: bool,
+) -> Result<()> {
+    let _span = info_span!("compile_arm64").entered();
+
+    let asm_path = output_dir.join(format!("{base_name}.s"));
+    let obj_path = output_dir.join(format!("{base_name}.o"));
+    let lib_path = output_dir.join(format!("lib{base_name}.so"));
+
+    if !asm_path.exists() {
+        return Err(Error::CompilationFailed(format!(
+            "Assembly file not found: {}",
+            asm_path.display()
+        )));
+    }
+
+    let is_arm64_host = cfg!(target_arch = "aarch64");
+    let needs_cross = !is_arm64_host;
+    let target_triple = "aarch64-unknown-linux-gnu";
+    let cc = if needs_cross {
+        DEFAULT_CLANG_COMMAND
+    } else {
+        compiler.command()
+    };
+
+    debug!(asm = %asm_path.display(), compiler = %cc, cross = %needs_cross, "assembling");
+
+    assemble_asm(cc, &asm_path, &obj_path, needs_cross, target_triple)?;
+
+    debug!(obj = %obj_path.display(), "linking");
+
+    let mut obj_files = vec![obj_path];
+    if let Some(path) = compile_optional_c(
+        cc,
+        output_dir,
+        base_name,
+        "syscalls",
+        needs_cross,
+        target_triple,
+    )? {
+        obj_files.push(path);
+    }
+    if let Some(path) = compile_optional_c(
+        cc,
+        output_dir,
+        base_name,
+        "htif",
+        needs_cross,
+        target_triple,
+    )? {
+        obj_files.push(path);
+    }
+
+    link_shared(
+        cc,
+        &obj_files,
+        &lib_path,
+        compiler,
+        ldflags,
+        needs_cross,
+        target_triple,
+    )?;
+
+    if !quiet {
+        debug!(lib = %lib_path.display(), cross = %needs_cross, "compiled ARM64 shared library");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,62 @@
+//! Interpreting blocks whose C fails to compile
+//! (`EmitConfig::interpret_failed_blocks`).
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use rvr_isa::Xlen;
+use tracing::{info, warn};
+
+use super::Recompiler;
+use crate::{Error, Pipeline, Result};
+
+impl<X: Xlen> Recompiler<X> {
+    /// Re-emit `pipeline` with the blocks of the part files that failed to
+    /// compile (`err`, a `PartsFailed`) interpreted, ready to build again.
+    ///
+    /// Blocks the interpreter cannot run stay compiled, so their part may
+    /// fail again.
+    ///
+    /// # Errors
+    ///
+    /// Returns `err` if no block of the failed parts can be interpreted, and
+    /// errors from emitting.
+    pub(super) fn interpret_failed_parts(
+        &self,
+        pipeline: &mut Pipeline<X>,
+        elf_path: &Path,
+        output_dir: &Path,
+        err: Error,
+    ) -> Result<()> {
+        let Error::PartsFailed { failures, .. } = &err else {
+            return Err(err);
+        };
+        let base_name = output_dir
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("rv");
+        let files: HashSet<&str> = failures.iter().map(|f| f.file.as_str()).collect();
+        let mut blocks = Vec::new();
+        for pc in pipeline.blocks_in_parts(base_name, &files) {
+            match pipeline.interpret_error(pc) {
+                None => blocks.push(pc),
+                Some(reason) => {
+                    warn!(pc = format!("{pc:#x}"), %reason, "cannot interpret block");
+                }
+            }
+        }
+        if blocks.is_empty() {
+            return Err(err);
+        }
+        for failure in failures {
+            warn!(%failure, "interpreting the blocks of the part");
+        }
+        info!(
+            blocks = blocks.len(),
+            "interpreting blocks that failed to compile"
+        );
+        pipeline.config_mut().interpret_blocks.extend(blocks);
+        self.emit(pipeline, Some(elf_path), output_dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,695 @@
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+use rvr_elf::ElfImage;
+use rvr_emit::c::{CProject, content_hash, partition_file_name};
+use rvr_emit::{AddressMode, Backend, Compiler, EmitConfig, SyscallMode};
+use rvr_isa::syscalls::{LinuxHandler, SyscallAbi};
+use rvr_isa::{ExtensionRegistry, IsaString, Xlen};
+use tracing::{debug, info, info_span, warn};
+
+use self::asm::compile_arm64_to_shared;
+pub use self::asm::compile_x86_to_shared;
+
+use crate::build::{BuildOutcome, MakeBuild, check_compiler};
+use crate::layout::image_layout;
+use crate::programs::{ProgramEntry, ProgramManifest, is_valid_name, symbol_prefix};
+use crate::progress::{CompilePhase, ProgressFn, report};
+use crate::{
+    CompileReport, Error, Explanation, FilterSpec, Pipeline, ProfileCounts, Result, SIZE_REPORT,
+    SizeReport, SyntheticProgram,
+};
+
+mod asm;
+mod interp;
+
+/// RISC-V recompiler.
+pub struct Recompiler<X: Xlen> {
+    config: EmitConfig<X>,
+    quiet: bool,
+    export_functions: bool,
+    profile: Option<PathBuf>,
+    extra_entry_points: Vec<u64>,
+    filter: Option<FilterSpec>,
+    size_report: bool,
+    progress: Option<ProgressFn>,
+    _marker: PhantomData<X>,
+}
+
+impl<X: Xlen> Recompiler<X> {
+    /// Create a new recompiler with the given configuration.
+    #[must_use]
+    pub const fn new(config: EmitConfig<X>) -> Self {
+        Self {
+            config,
+            quiet: false,
+            export_functions: false,
+            profile: None,
+            extra_entry_points: Vec::new(),
+            filter: None,
+            size_report: false,
+            progress: None,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Create a recompiler with default configuration.
+    #[must_use]
+    pub fn with_defaults() -> Self {
+        Self::new(EmitConfig::default())
+    }
+
+    /// Set syscall handling mode.
+    #[must_use]
+    pub const fn with_syscall_mode(mut self, mode: SyscallMode) -> Self {
+        self.config.syscall_mode = mode;
+        self
+    }
+
+    /// Set the C compiler to use (clang or gcc).
+    #[must_use]
+    pub fn with_compiler(mut self, compiler: Compiler) -> Self {
+        self.config.compiler = compiler;
+        self
+    }
+
+    /// Suppress compilation output.
+    #[must_use]
+    pub const fn with_quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
+    }
+
+    /// Enable export functions mode for calling exported functions.
+    ///
+    /// When enabled, all function symbols are added as CFG entry points,
+    /// and `RV_EXPORT_FUNCTIONS` metadata is set in the compiled library.
+    #[must_use]
+    pub const fn with_export_functions(mut self, enabled: bool) -> Self {
+        self.export_functions = enabled;
+        self.config.export_functions = enabled;
+        self
+    }
+
+    /// Recompile with the block profile saved at `path` (C backend).
+    ///
+    /// See `CompileOptions::with_profile`.
+    #[must_use]
+    pub fn with_profile(mut self, path: Option<PathBuf>) -> Self {
+        self.profile = path;
+        self
+    }
+
+    /// Add `targets` as extra CFG entry points.
+    ///
+    /// See `CompileOptions::with_extra_entry_points`.
+    #[must_use]
+    pub fn with_extra_entry_points(mut self, targets: Vec<u64>) -> Self {
+        self.extra_entry_points = targets;
+        self
+    }
+
+    /// Lift only the blocks `filter` selects (C backend).
+    ///
+    /// See `CompileOptions::with_filter`; only [`lift`](Self::lift)
+    /// applies it.
+    #[must_use]
+    pub fn with_filter(mut self, filter: Option<FilterSpec>) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Write `size_report.tsv` next to the emitted C (C backend).
+    ///
+    /// See `CompileOptions::with_size_report`.
+    #[must_use]
+    pub const fn with_size_report(mut self, enabled: bool) -> Self {
+        self.size_report = enabled;
+        self
+    }
+
+    /// Report compile progress to `progress`.
+    ///
+    /// See `CompileOptions::with_progress`.
+    #[must_use]
+    pub fn with_progress(mut self, progress: Option<ProgressFn>) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Get the configuration.
+    #[must_use]
+    pub const fn config(&self) -> &EmitConfig<X> {
+        &self.config
+    }
+
+    /// Compile an ELF file to a shared library.
+    ///
+    /// If `jobs` is 0, auto-detects based on CPU count.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from lifting or compiling the output.
+    pub fn compile(
+        &self,
+        elf_path: &Path,
+        output_dir: &Path,
+        jobs: usize,
+    ) -> Result<std::path::PathBuf> {
+        self.compile_with_report(elf_path, output_dir, jobs)
+            .map(|report| report.library)
+    }
+
+    /// Compile an ELF file to a shared library, reporting quarantined blocks.
+    ///
+    /// If `jobs` is 0, auto-detects based on CPU count.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from lifting or compiling the output.
+    pub fn compile_with_report(
+        &self,
+        elf_path: &Path,
+        output_dir: &Path,
+        jobs: usize,
+    ) -> Result<CompileReport> {
+        let _span = info_span!(
+            "compile",
+            backend = ?self.config.backend,
+            output = %output_dir.display()
+        )
+        .entered();
+        let compiler = check_compiler(&self.config)?;
+        // First lift to source (C or x86 assembly)
+        let mut pipeline = self.lift_pipeline(elf_path)?;
+        std::fs::create_dir_all(output_dir)?;
+        self.emit(&mut pipeline, Some(elf_path), output_dir)?;
+        let mut size_report = self.write_size_report(&pipeline, output_dir)?;
+        let (library, build) = match self.build_shared(&pipeline, output_dir, jobs) {
+            Err(err @ Error::PartsFailed { .. }) if self.config.interpret_failed_blocks() => {
+                self.interpret_failed_parts(&mut pipeline, elf_path, output_dir, err)?;
+                size_report = self.write_size_report(&pipeline, output_dir)?;
+                self.build_shared(&pipeline, output_dir, jobs)?
+            }
+            result => result?,
+        };
+        if let Some(report) = &mut size_report {
+            match report.add_host_sizes::<X>(&library, &self.config.symbol_prefix) {
+                Ok(()) => report.write(output_dir)?,
+                Err(e) => warn!(error = %e, "cannot read host code sizes from the library"),
+            }
+        }
+        Ok(CompileReport {
+            library,
+            quarantined: pipeline.quarantined().to_vec(),
+            dedup: pipeline.stats().dedup,
+            memory_layout: pipeline.memory_layout()?,
+            size_report,
+            part_times: build.part_times,
+            retried_parts: build.retried,
+            compiler,
+        })
+    }
+
+    /// Compile an ELF file once per address mode, sharing the lift.
+    ///
+    /// The CFG and IR are built once; each mode is then emitted and compiled
+    /// into its own `output_root/<mode>` directory. Returns the library paths
+    /// in the same order as `modes`.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from lifting, emitting, or compiling any of the modes.
+    pub fn compile_address_modes(
+        &self,
+        elf_path: &Path,
+        output_root: &Path,
+        modes: &[AddressMode],
+        jobs: usize,
+    ) -> Result<Vec<std::path::PathBuf>> {
+        let _span = info_span!(
+            "compile_address_modes",
+            backend = ?self.config.backend,
+            output = %output_root.display()
+        )
+        .entered();
+        check_compiler(&self.config)?;
+        let mut pipeline = self.lift_pipeline(elf_path)?;
+
+        let mut libs = Vec::with_capacity(modes.len());
+        for &mode in modes {
+            let output_dir = output_root.join(mode.as_str());
+            std::fs::create_dir_all(&output_dir)?;
+            pipeline.config_mut().address_mode = mode;
+            self.emit(&mut pipeline, Some(elf_path), &output_dir)?;
+            libs.push(self.build_shared(&pipeline, &output_dir, jobs)?.0);
+        }
+        Ok(libs)
+    }
+
+    /// Compile several ELFs into one shared library (C backend).
+    ///
+    /// Each `(name, elf)` program is emitted into `output_dir/<name>` with
+    /// every generated symbol, dispatch table included, prefixed by
+    /// `<name>_`, so the programs cannot see each other's blocks. A
+    /// top-level Makefile links all of them into `lib<output_dir>.so`, and
+    /// a [`ProgramManifest`] records each program's entry point and memory
+    /// size for [`Runner::load_program`](crate::Runner::load_program).
+    ///
+    /// If `jobs` is 0, auto-detects based on CPU count.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a name is not a C identifier or is repeated, the
+    /// backend is not C, or lifting or compiling any program fails.
+    pub fn compile_many(
+        &self,
+        programs: &[(&str, &Path)],
+        output_dir: &Path,
+        jobs: usize,
+    ) -> Result<PathBuf> {
+        let _span = info_span!("compile_many", output = %output_dir.display()).entered();
+        if self.config.backend != Backend::C {
+            return Err(Error::InvalidProgram(
+                "multi-program libraries need the C backend".to_string(),
+            ));
+        }
+        check_compiler(&self.config)?;
+        let mut names: Vec<String> = Vec::with_capacity(programs.len());
+        for &(name, _) in programs {
+            if !is_valid_name(name) {
+                return Err(Error::InvalidProgram(format!(
+                    "'{name}' is not a C identifier"
+                )));
+            }
+            if names.iter().any(|seen| seen == name) {
+                return Err(Error::InvalidProgram(format!("'{name}' is listed twice")));
+            }
+            names.push(name.to_string());
+        }
+
+        std::fs::create_dir_all(output_dir)?;
+        let mut manifest = ProgramManifest::default();
+        for &(name, elf_path) in programs {
+            let _span = info_span!("program", name).entered();
+            let mut pipeline = self.lift_pipeline(elf_path)?;
+            pipeline.config_mut().symbol_prefix = symbol_prefix(name);
+            let program_dir = output_dir.join(name);
+            std::fs::create_dir_all(&program_dir)?;
+            self.emit(&mut pipeline, Some(elf_path), &program_dir)?;
+            manifest.programs.push(ProgramEntry {
+                name: name.to_string(),
+                entry_point: X::to_u64(pipeline.image().entry_point),
+                memory_size: 1 << pipeline.config().memory_bits,
+                xlen: X::VALUE,
+                num_regs: pipeline.config().num_regs,
+            });
+        }
+
+        let lib_name = output_dir
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("rv");
+        CProject::new(output_dir, lib_name, self.config.clone()).write_library_makefile(&names)?;
+        manifest.save(output_dir)?;
+        MakeBuild {
+            dir: output_dir,
+            jobs,
+            quiet: self.quiet,
+            targets: vec!["shared"],
+            fallback_opt_level: self.config.fallback_opt_level,
+            part_functions: &HashMap::new(),
+            progress: self.progress.as_ref(),
+        }
+        .run()?;
+        Ok(output_dir.join(format!("lib{lib_name}.so")))
+    }
+
+    /// Compile a program built from IR to a shared library, without an ELF.
+    ///
+    /// The recompiler's configuration applies as for an ELF; load the
+    /// result with [`Runner::load_synthetic`](crate::Runner::load_synthetic).
+    /// If `jobs` is 0, auto-detects based on CPU count.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the program does not validate or emitting or
+    /// compiling it fails.
+    pub fn compile_program(
+        &self,
+        program: SyntheticProgram<X>,
+        output_dir: &Path,
+        jobs: usize,
+    ) -> Result<PathBuf> {
+        let _span = info_span!(
+            "compile_program",
+            backend = ?self.config.backend,
+            output = %output_dir.display()
+        )
+        .entered();
+        check_compiler(&self.config)?;
+        let mut pipeline = Pipeline::from_program(program, self.config.clone())?;
+        pipeline.set_progress(self.progress.clone());
+        std::fs::create_dir_all(output_dir)?;
+        self.emit(&mut pipeline, None, output_dir)?;
+        self.build_shared(&pipeline, output_dir, jobs)
+            .map(|(library, _)| library)
+    }
+
+    /// Compile the sources `pipeline` emitted into `output_dir` to a shared
+    /// library.
+    fn build_shared(
+        &self,
+        pipeline: &Pipeline<X>,
+        output_dir: &Path,
+        jobs: usize,
+    ) -> Result<(PathBuf, BuildOutcome)> {
+        let lib_name = output_dir
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("rv");
+
+        // Compile based on backend
+        let mut build = BuildOutcome::default();
+        match self.config.backend {
+            Backend::C => {
+                // Compile C to .so (compiler choice is already in the Makefile via config)
+                let mut targets = vec!["shared"];
+                if self.config.static_archive() {
+                    targets.push("static");
+                }
+                build = MakeBuild {
+                    dir: output_dir,
+                    jobs,
+                    quiet: self.quiet,
+                    targets,
+                    fallback_opt_level: self.config.fallback_opt_level,
+                    part_functions: &part_functions(pipeline, lib_name),
+                    progress: self.progress.as_ref(),
+                }
+                .run()?;
+            }
+            Backend::X86Asm => {
+                // Assemble x86 to .so
+                self.report(CompilePhase::Cc, 0, 1);
+                compile_x86_to_shared(output_dir, lib_name, &self.config.compiler, self.quiet)?;
+                self.report(CompilePhase::Cc, 1, 1);
+            }
+            Backend::ARM64Asm => {
+                // Assemble ARM64 to .so
+                self.report(CompilePhase::Cc, 0, 1);
+                compile_arm64_to_shared(
+                    output_dir,
+                    lib_name,
+                    &self.config.compiler,
+                    self.config.dispatch_table_ldflags(),
+                    self.quiet,
+                )?;
+                self.report(CompilePhase::Cc, 1, 1);
+            }
+        }
+
+        let lib_path = output_dir.join(format!("lib{lib_name}.so"));
+        Ok((lib_path, build))
+    }
+
+    /// Lift an ELF file to source code (C or x86 assembly, depending on backend).
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from parsing or lifting the ELF.
+    pub fn lift(&self, elf_path: &Path, output_dir: &Path) -> Result<std::path::PathBuf> {
+        let _span = info_span!(
+            "lift",
+            backend = ?self.config.backend,
+            input = %elf_path.display(),
+            output = %output_dir.display()
+        )
+        .entered();
+        let mut pipeline = self.lift_pipeline(elf_path)?;
+        if let Some(filter) = &self.filter {
+            pipeline.apply_filter(filter)?;
+        }
+
+        // Create output directory if it doesn't exist
+        std::fs::create_dir_all(output_dir)?;
+
+        let source = self.emit(&mut pipeline, Some(elf_path), output_dir)?;
+        self.write_size_report(&pipeline, output_dir)?;
+        Ok(source)
+    }
+
+    /// Write the size report of an emitted pipeline, if enabled.
+    ///
+    /// Only the C backend records emitted sizes; other backends get none.
+    fn write_size_report(
+        &self,
+        pipeline: &Pipeline<X>,
+        output_dir: &Path,
+    ) -> Result<Option<SizeReport>> {
+        if !self.size_report {
+            return Ok(None);
+        }
+        if self.config.backend != Backend::C {
+            warn!("size reports only cover the C backend, ignoring --report");
+            return Ok(None);
+        }
+        let report = pipeline.size_report();
+        report.write(output_dir)?;
+        info!(
+            functions = report.functions.len(),
+            path = %output_dir.join(SIZE_REPORT).display(),
+            "wrote size report"
+        );
+        Ok(Some(report))
+    }
+
+    /// Explain how the block containing `pc` is lifted and emitted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if lifting fails or no lifted block contains `pc`.
+    pub fn explain(&self, elf_path: &Path, pc: u64) -> Result<Explanation> {
+        let _span = info_span!("explain", input = %elf_path.display()).entered();
+        let pipeline = self.lift_pipeline(elf_path)?;
+        pipeline.explain(pc)
+    }
+
+    /// Extensions to decode `image` with.
+    ///
+    /// The ISA string comes from `EmitConfig::isa` if set, else from the
+    /// ELF's `Tag_RISCV_arch` attribute; without either, every supported
+    /// extension is decoded. Extensions the string names that rvr does not
+    /// implement are warned about here, since their instructions would
+    /// otherwise only show up as traps at whatever PC first runs one.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidIsa` if `EmitConfig::isa` does not parse. A
+    /// malformed attribute is only warned about.
+    pub fn extension_registry(&self, image: &ElfImage<X>) -> Result<ExtensionRegistry<X>> {
+        let isa = if let Some(isa) = &self.config.isa {
+            IsaString::parse(isa)?
+        } else {
+            let Some(arch) = image.arch_attributes().and_then(|a| a.arch.as_deref()) else {
+                debug!("no ISA attribute, decoding all supported extensions");
+                return Ok(ExtensionRegistry::standard());
+            };
+            match IsaString::parse(arch) {
+                Ok(isa) => isa,
+                Err(e) => {
+                    warn!(error = %e, "ignoring ISA attribute, decoding all supported extensions");
+                    return Ok(ExtensionRegistry::standard());
+                }
+            }
+        };
+
+        if isa.xlen != X::VALUE {
+            warn!(
+                isa_xlen = isa.xlen,
+                elf_xlen = X::VALUE,
+                "ISA string does not match the ELF class"
+            );
+        }
+        let unsupported = isa.unsupported();
+        if !unsupported.is_empty() {
+            warn!(
+                extensions = unsupported.join(","),
+                "ISA uses extensions rvr does not implement; their instructions will trap"
+            );
+        }
+        let registry = ExtensionRegistry::for_isa(&isa);
+        info!(
+            extensions = registry.extension_names().join(","),
+            "selected extensions"
+        );
+        Ok(registry)
+    }
+
+    /// Report `done` of `total` units of `phase`, if anyone listens.
+    fn report(&self, phase: CompilePhase, done: usize, total: usize) {
+        report(self.progress.as_ref(), phase, done, total);
+    }
+
+    /// Load an ELF, build its CFG, and lift it to IR.
+    fn lift_pipeline(&self, elf_path: &Path) -> Result<Pipeline<X>> {
+        self.report(CompilePhase::Parse, 0, 1);
+        // Load ELF
+        let data = {
+            let _span = info_span!("load_elf").entered();
+            std::fs::read(elf_path)?
+        };
+        let image = {
+            let _span = info_span!("parse_elf").entered();
+            ElfImage::<X>::parse_with_load_bias(&data, self.config.load_bias)?
+        };
+        self.report(CompilePhase::Parse, 1, 1);
+        if let Some(layout) = &self.config.layout {
+            layout.validate(&image_layout(&image))?;
+        }
+
+        // Build pipeline with syscall handler selection.
+        let registry = self.extension_registry(&image)?;
+        let abi = if image.is_rve() {
+            SyscallAbi::Embedded
+        } else {
+            SyscallAbi::Standard
+        };
+        let registry = match self.config.syscall_mode {
+            SyscallMode::BareMetal => match &self.config.baremetal_ecalls {
+                Some(ecalls) => registry.with_syscall_handler(ecalls.clone().with_abi(abi)),
+                None => registry,
+            },
+            SyscallMode::Linux => {
+                let mut handler = LinuxHandler::new(abi);
+                if self.config.deterministic_clock() {
+                    handler = handler.with_deterministic_clock();
+                }
+                let handler = match &self.config.syscall_policy {
+                    Some(policy) => handler.with_policy(policy),
+                    None => handler,
+                };
+                registry.with_syscall_handler(handler)
+            }
+        };
+        let mut pipeline = {
+            let _span = info_span!("pipeline_init").entered();
+            Pipeline::<X>::with_registry(image, self.config.clone(), registry)
+        };
+        pipeline.set_elf_hash(content_hash(&data));
+        pipeline.set_progress(self.progress.clone());
+        pipeline.memory_layout()?;
+
+        // Add function symbols (and guest tests) as extra entry points if requested
+        if self.export_functions {
+            pipeline.add_function_symbols_as_entry_points();
+            pipeline.add_guest_tests_as_entry_points()?;
+        }
+        pipeline.add_extra_entry_points(&self.extra_entry_points);
+
+        // Build CFG (InstructionTable → BlockTable → optimizations)
+        pipeline.build_cfg()?;
+
+        // Lift to IR
+        // For C backend in per-instruction mode, use single-instruction blocks
+        // to enable mid-block resume (dispatch table entry for every instruction PC)
+        match self.config.backend {
+            Backend::C if self.config.instret_mode.per_instruction() => {
+                pipeline.lift_to_ir_as_single_blocks()?;
+            }
+            Backend::C => pipeline.lift_to_ir()?,
+            _ => pipeline.lift_to_ir_linear()?,
+        }
+
+        if let Some(path) = &self.profile {
+            self.apply_profile(&mut pipeline, path, &data)?;
+        }
+
+        Ok(pipeline)
+    }
+
+    /// Apply the block profile at `path` unless it is stale.
+    ///
+    /// A profile collected from a different ELF is ignored with a warning,
+    /// since its PCs no longer match the code.
+    fn apply_profile(&self, pipeline: &mut Pipeline<X>, path: &Path, elf: &[u8]) -> Result<()> {
+        if self.config.backend != Backend::C {
+            warn!("block profiles only apply to the C backend, ignoring --profile");
+            return Ok(());
+        }
+        let profile = ProfileCounts::load(path)?;
+        if !profile.matches(elf) {
+            warn!(
+                path = %path.display(),
+                "block profile was collected from a different ELF, ignoring it"
+            );
+        } else if profile.is_empty() {
+            warn!(path = %path.display(), "block profile has no entries, ignoring it");
+        } else {
+            let _span = info_span!("apply_profile").entered();
+            pipeline.apply_profile(&profile);
+        }
+        Ok(())
+    }
+
+    /// Emit a lifted pipeline to source code in `output_dir`.
+    ///
+    /// Line info comes from `elf_path`, if there is an ELF.
+    fn emit(
+        &self,
+        pipeline: &mut Pipeline<X>,
+        elf_path: Option<&Path>,
+        output_dir: &Path,
+    ) -> Result<std::path::PathBuf> {
+        let base_name = output_dir
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("rv");
+
+        // Emit based on backend
+        match self.config.backend {
+            Backend::C => {
+                // Load debug info for #line directives (if enabled and ELF has debug info)
+                if self.config.emit_line_info()
+                    && let Some(path_str) = elf_path.and_then(Path::to_str)
+                    && let Err(e) = pipeline.load_debug_info(path_str)
+                {
+                    warn!(error = %e, "failed to load debug info (continuing without #line directives)");
+                }
+
+                pipeline.emit_c(output_dir, base_name)?;
+                Ok(output_dir.join(format!("{base_name}_part0.c")))
+            }
+            Backend::X86Asm => {
+                pipeline.emit_x86(output_dir, base_name)?;
+                self.report(CompilePhase::Emit, 1, 1);
+                Ok(output_dir.join(format!("{base_name}.s")))
+            }
+            Backend::ARM64Asm => {
+                pipeline.emit_arm64(output_dir, base_name)?;
+                self.report(CompilePhase::Emit, 1, 1);
+                Ok(output_dir.join(format!("{base_name}.s")))
+            }
+        }
+    }
+}
+
+/// Guest functions by the part file holding their first block, named by
+/// symbol or entry PC.
+fn part_functions<X: Xlen>(
+    pipeline: &Pipeline<X>,
+    base_name: &str,
+) -> HashMap<String, Vec<String>> {
+    let mut parts: HashMap<String, Vec<String>> = HashMap::new();
+    for function in pipeline.size_report().functions {
+        let name = function
+            .name
+            .unwrap_or_else(|| format!("{:#x}", function.pc));
+        parts
+            .entry(partition_file_name(base_name, function.part))
+            .or_default()
+            .push(name);
+    }
+    parts
+}
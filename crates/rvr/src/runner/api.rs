@@ -1,15 +1,24 @@
 //! Library API loading and types.
 
-use std::ffi::c_void;
+use std::collections::HashMap;
+use std::ffi::{CStr, c_char, c_void};
 
-use libloading::os::unix::{Library, Symbol};
+use rvr_emit::{
+    ABI_INFO_SYMBOL, ABI_VERSION, GuardPolicy, LAYOUT_REGION_WORDS, LayoutRegions,
+    MEMORY_LAYOUT_WORDS,
+};
+use rvr_ir::Xlen;
 use tracing::error;
 
 use super::RunError;
+use super::symbols::Symbols;
 
 /// C API - only the execution function is required.
 pub type RvExecuteFrom = unsafe extern "C" fn(*mut c_void, u64) -> i32;
 
+/// Writes the segment data embedded in the library into guest memory.
+pub type RvInitMemory = unsafe extern "C" fn(*mut c_void);
+
 /// Fixed address configuration loaded from library.
 #[derive(Clone, Copy, Debug)]
 pub struct FixedAddresses {
@@ -17,23 +26,86 @@ pub struct FixedAddresses {
     pub memory_addr: u64,
 }
 
+/// Page bitmap size of the page access tracer.
+#[derive(Clone, Copy, Debug)]
+pub struct PageBitmapSize {
+    /// Log2 of the page size.
+    pub page_shift: u32,
+    /// Words per bitmap.
+    pub words: usize,
+}
+
+/// Slot array of the block profile and coverage tracers.
+#[derive(Clone, Copy, Debug)]
+pub struct ProfileSlots {
+    /// Guest PC of the first slot (the start of the dispatch range).
+    pub base: u64,
+    /// Number of 2-byte slots.
+    pub slots: usize,
+}
+
+/// Host buffers a built-in tracer needs, sized by the compiled library.
+#[derive(Clone, Copy, Debug)]
+pub enum TracerBuffers {
+    /// The tracer needs no host buffers.
+    None,
+    /// Page bitmaps of the page access tracer.
+    PageBitmaps(PageBitmapSize),
+    /// Entry counters of the block profile tracer.
+    BlockCounters(ProfileSlots),
+    /// Per-instruction flags of the coverage tracer.
+    CoverageFlags(ProfileSlots),
+}
+
 /// Minimal API from the generated C code.
 #[derive(Clone, Copy)]
 pub struct RvApi {
     pub execute_from: RvExecuteFrom,
     pub tracer_kind: u32,
     pub export_functions: bool,
+    /// Return address that stops execution (export-functions mode).
+    pub call_return_pc: Option<u64>,
     pub instret_mode: u32,
+    /// Guest register count the library was compiled for (16 for RVE).
+    ///
+    /// `None` for libraries that predate `RV_NUM_REGS`.
+    pub num_regs: Option<u32>,
     pub fixed_addresses: Option<FixedAddresses>,
+    /// Tracer buffer sizes (page access, block profile and coverage tracers).
+    pub tracer_buffers: TracerBuffers,
+    /// Load bias the library was compiled for (position-independent ELFs).
+    pub load_bias: Option<u64>,
+    /// Guest memory is mapped from the library's segment image.
+    pub lazy_segments: bool,
+    /// Backward jumps and branches stop when `RvState::cancel_requested` is set.
+    pub check_cancel: bool,
+    /// Tracer hooks see one instruction in this many (1 for every one).
+    pub sample_interval: u32,
+    /// `rv_init_memory`, if the library embeds segment data.
+    pub init_memory: Option<RvInitMemory>,
 }
 
 impl RvApi {
-    pub unsafe fn load(lib: &Library) -> Result<Self, RunError> {
+    /// Load the API of the program `symbols` looks up.
+    ///
+    /// Fails with `AbiMismatch` if the library was built for another ABI
+    /// (`rv_abi_info`, whose first field is the version, is missing or
+    /// differs).
+    pub unsafe fn load<X: Xlen>(symbols: &Symbols) -> Result<Self, RunError> {
         unsafe {
+            let abi_version = symbols.data_u32(ABI_INFO_SYMBOL);
+            if abi_version != Some(ABI_VERSION) {
+                error!(found = ?abi_version, "ABI version mismatch");
+                return Err(RunError::AbiMismatch {
+                    expected: ABI_VERSION,
+                    found: abi_version,
+                });
+            }
+
             // Load fixed addresses if present
             let fixed_addresses = match (
-                load_data_symbol_u64(lib, b"RV_FIXED_STATE_ADDR"),
-                load_data_symbol_u64(lib, b"RV_FIXED_MEMORY_ADDR"),
+                symbols.data_u64("RV_FIXED_STATE_ADDR"),
+                symbols.data_u64("RV_FIXED_MEMORY_ADDR"),
             ) {
                 (Some(state_addr), Some(memory_addr)) => Some(FixedAddresses {
                     state_addr,
@@ -42,12 +114,32 @@ impl RvApi {
                 _ => None,
             };
 
+            let tracer_kind = symbols.data_u32("RV_TRACER_KIND").unwrap_or(0);
+            let tracer_buffers = match TracerKind::from_raw(tracer_kind) {
+                TracerKind::PageAccess => {
+                    TracerBuffers::PageBitmaps(load_page_bitmap_size(symbols)?)
+                }
+                TracerKind::BlockProfile => {
+                    TracerBuffers::BlockCounters(load_profile_slots(symbols)?)
+                }
+                TracerKind::Coverage => TracerBuffers::CoverageFlags(load_profile_slots(symbols)?),
+                _ => TracerBuffers::None,
+            };
+
             Ok(Self {
-                execute_from: load_symbol(lib, b"rv_execute_from", "rv_execute_from")?,
-                tracer_kind: load_data_symbol(lib, b"RV_TRACER_KIND").unwrap_or(0),
-                export_functions: load_data_symbol(lib, b"RV_EXPORT_FUNCTIONS").unwrap_or(0) != 0,
-                instret_mode: load_data_symbol(lib, b"RV_INSTRET_MODE").unwrap_or(1), // Default to Count
+                execute_from: symbols.get("rv_execute_from")?,
+                tracer_kind,
+                export_functions: symbols.data_u32("RV_EXPORT_FUNCTIONS").unwrap_or(0) != 0,
+                call_return_pc: symbols.data_reg::<X>("RV_CALL_RETURN_PC"),
+                instret_mode: symbols.data_u32("RV_INSTRET_MODE").unwrap_or(1), // Default to Count
+                num_regs: symbols.data_u32("RV_NUM_REGS"),
                 fixed_addresses,
+                tracer_buffers,
+                load_bias: symbols.data_u64("RV_LOAD_BIAS"),
+                lazy_segments: symbols.data_u32("RV_LAZY_SEGMENTS").unwrap_or(0) != 0,
+                check_cancel: symbols.data_u32("RV_CHECK_CANCEL").unwrap_or(0) != 0,
+                sample_interval: symbols.data_u32("RV_TRACER_SAMPLE_INTERVAL").unwrap_or(1),
+                init_memory: symbols.get_optional::<RvInitMemory>("rv_init_memory"),
             })
         }
     }
@@ -59,31 +151,91 @@ impl RvApi {
     }
 }
 
-pub unsafe fn load_symbol<T: Copy>(
-    lib: &Library,
-    symbol: &'static [u8],
-    label: &'static str,
-) -> Result<T, RunError> {
+/// Load the page bitmap size; required for libraries with the page access tracer.
+unsafe fn load_page_bitmap_size(symbols: &Symbols) -> Result<PageBitmapSize, RunError> {
+    unsafe {
+        let page_shift: *const u32 = symbols.get("RV_TRACER_PAGE_SHIFT")?;
+        let words: *const u64 = symbols.get("RV_TRACER_PAGE_WORDS")?;
+        Ok(PageBitmapSize {
+            page_shift: *page_shift,
+            words: usize::try_from(*words).unwrap_or(usize::MAX),
+        })
+    }
+}
+
+/// Load the counter array size; required for libraries with the block profile tracer.
+unsafe fn load_profile_slots(symbols: &Symbols) -> Result<ProfileSlots, RunError> {
+    unsafe {
+        let base: *const u64 = symbols.get("RV_TRACER_PROFILE_BASE")?;
+        let slots: *const u64 = symbols.get("RV_TRACER_PROFILE_SLOTS")?;
+        Ok(ProfileSlots {
+            base: *base,
+            slots: usize::try_from(*slots).unwrap_or(usize::MAX),
+        })
+    }
+}
+
+/// Load the quarantined-block table (`stub_pc` -> lift error), if any.
+pub unsafe fn load_quarantine(symbols: &Symbols) -> HashMap<u64, String> {
+    unsafe {
+        let Some(count) = symbols.data_u32("RV_QUARANTINE_COUNT") else {
+            return HashMap::new();
+        };
+        let (Some(pcs), Some(reasons)) = (
+            symbols.address("RV_QUARANTINE_PCS"),
+            symbols.address("RV_QUARANTINE_REASONS"),
+        ) else {
+            return HashMap::new();
+        };
+        let count = count as usize;
+        let pcs = std::slice::from_raw_parts(pcs.cast::<u64>(), count);
+        let reasons = std::slice::from_raw_parts(reasons.cast::<*const c_char>(), count);
+        pcs.iter()
+            .zip(reasons)
+            .map(|(&pc, &reason)| (pc, CStr::from_ptr(reason).to_string_lossy().into_owned()))
+            .collect()
+    }
+}
+
+/// Load the guest paths the syscall policy allows to preopen, if the
+/// library was compiled with a policy.
+pub unsafe fn load_preopens(symbols: &Symbols) -> Option<Vec<String>> {
     unsafe {
-        let sym: Symbol<T> = lib.get(symbol).map_err(|e| {
-            error!(symbol = label, "symbol not found in library");
-            RunError::SymbolNotFound(label.to_string(), e)
-        })?;
-        Ok(*sym)
+        let count = symbols.data_u32("RV_PREOPEN_COUNT")?;
+        let paths = symbols.address("RV_PREOPEN_PATHS")?;
+        let paths = std::slice::from_raw_parts(paths.cast::<*const c_char>(), count as usize);
+        Some(
+            paths
+                .iter()
+                .map(|&path| CStr::from_ptr(path).to_string_lossy().into_owned())
+                .collect(),
+        )
     }
 }
 
-pub unsafe fn load_data_symbol(lib: &Library, symbol: &'static [u8]) -> Option<u32> {
+/// Load the layout profile name and regions, if the library has one.
+pub unsafe fn load_layout(symbols: &Symbols) -> Option<(String, LayoutRegions)> {
     unsafe {
-        let sym: Symbol<*const u32> = lib.get(symbol).ok()?;
-        Some(**sym)
+        let name = symbols.address("RV_LAYOUT_PROFILE")?.cast::<c_char>();
+        let words = symbols
+            .address("RV_LAYOUT_REGIONS")?
+            .cast::<[u64; LAYOUT_REGION_WORDS]>();
+        let guard = symbols
+            .data_u64("RV_LAYOUT_GUARD")
+            .map_or(GuardPolicy::None, GuardPolicy::Gap);
+        Some((
+            CStr::from_ptr(name).to_string_lossy().into_owned(),
+            LayoutRegions::from_words(*words, guard),
+        ))
     }
 }
 
-pub unsafe fn load_data_symbol_u64(lib: &Library, symbol: &'static [u8]) -> Option<u64> {
+/// Load the planned heap and stack words, if the library has them.
+pub unsafe fn load_memory_layout(symbols: &Symbols) -> Option<[u64; MEMORY_LAYOUT_WORDS]> {
     unsafe {
-        let sym: Symbol<*const u64> = lib.get(symbol).ok()?;
-        Some(**sym)
+        symbols
+            .address("RV_MEMORY_LAYOUT")
+            .map(|words| *words.cast::<[u64; MEMORY_LAYOUT_WORDS]>())
     }
 }
 
@@ -99,6 +251,11 @@ pub enum TracerKind {
     Spike,
     Diff,
     BufferedDiff,
+    PageAccess,
+    BlockProfile,
+    BinaryTrace,
+    Coverage,
+    Golden,
 }
 
 impl TracerKind {
@@ -112,6 +269,11 @@ impl TracerKind {
             6 => Self::Spike,
             7 => Self::Diff,
             8 => Self::BufferedDiff,
+            9 => Self::PageAccess,
+            10 => Self::BlockProfile,
+            11 => Self::BinaryTrace,
+            12 => Self::Coverage,
+            13 => Self::Golden,
             _ => Self::None,
         }
     }
@@ -0,0 +1,130 @@
+//! Guest command line and environment on the initial stack.
+//!
+//! A runner given arguments or environment variables starts each run like
+//! a Linux process: at `sp` are `argc`, the `argv` pointers and a NULL,
+//! the `envp` pointers and a NULL, and an auxiliary vector holding
+//! `AT_RANDOM` and `AT_NULL`. Above them, below the stack top, sit the 16
+//! `AT_RANDOM` bytes and the strings.
+
+/// Stack pointer alignment of the RISC-V psABI.
+const STACK_ALIGN: u64 = 16;
+/// Pointer-sized words besides the `argv` and `envp` pointers: `argc`, the
+/// `argv` and `envp` terminators, and the `AT_RANDOM` and `AT_NULL` auxv
+/// entries (type and value each).
+const FIXED_WORDS: usize = 7;
+/// Auxv type of the pointer to 16 random bytes (libc seeds stack
+/// protectors and pointer guards from them).
+const AT_RANDOM: u64 = 25;
+/// The `AT_RANDOM` bytes: fixed, so runs stay deterministic.
+const RANDOM_BYTES: [u8; 16] = [
+    0x9e, 0x37, 0x79, 0xb9, 0x7f, 0x4a, 0x7c, 0x15, 0xf3, 0x9c, 0xc0, 0x60, 0x5c, 0xed, 0xc8, 0x34,
+];
+
+/// Initial stack of a guest with `xlen`-bit pointers started with `args`
+/// and `env` (`KEY=VALUE` strings) below `stack_top`: the new `sp` and the
+/// bytes from there to `stack_top`.
+pub(super) fn initial_stack(
+    stack_top: u64,
+    xlen: u8,
+    args: &[String],
+    env: &[String],
+) -> (u64, Vec<u8>) {
+    let word = usize::from(xlen / 8);
+    let strings_len: usize = args.iter().chain(env).map(|s| s.len() + 1).sum();
+    let data_len = RANDOM_BYTES.len() + strings_len;
+    let unpadded = (FIXED_WORDS + args.len() + env.len()) * word + data_len;
+    // Padding between the words and the data that aligns `sp`
+    let padding = usize::try_from((stack_top - unpadded as u64) % STACK_ALIGN).unwrap_or(0);
+    let sp = stack_top - (unpadded + padding) as u64;
+
+    let mut stack = Vec::with_capacity(unpadded + padding);
+    let mut push_word = |value: u64| stack.extend_from_slice(&value.to_le_bytes()[..word]);
+    push_word(args.len() as u64);
+    let random = stack_top - data_len as u64;
+    let mut addr = random + RANDOM_BYTES.len() as u64;
+    for strings in [args, env] {
+        for string in strings {
+            push_word(addr);
+            addr += string.len() as u64 + 1;
+        }
+        push_word(0);
+    }
+    push_word(AT_RANDOM);
+    push_word(random);
+    // AT_NULL
+    push_word(0);
+    push_word(0);
+
+    stack.resize(stack.len() + padding, 0);
+    stack.extend_from_slice(&RANDOM_BYTES);
+    for string in args.iter().chain(env) {
+        stack.extend_from_slice(string.as_bytes());
+        stack.push(0);
+    }
+    (sp, stack)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STACK_TOP: u64 = 0x1_0000;
+
+    fn args() -> Vec<String> {
+        vec!["prog".to_string(), "ab".to_string()]
+    }
+
+    fn env() -> Vec<String> {
+        vec!["HOME=/".to_string()]
+    }
+
+    /// Guest string at `addr` in `stack`, which starts at `sp`.
+    fn string_at(stack: &[u8], sp: u64, addr: u64) -> &[u8] {
+        let start = usize::try_from(addr - sp).unwrap();
+        let len = stack[start..].iter().position(|&b| b == 0).unwrap();
+        &stack[start..start + len]
+    }
+
+    /// Check the layout of the stack built for `xlen`.
+    fn check_layout(xlen: u8) {
+        let (sp, stack) = initial_stack(STACK_TOP, xlen, &args(), &env());
+        assert_eq!(sp % STACK_ALIGN, 0);
+        assert_eq!(sp + stack.len() as u64, STACK_TOP);
+        let size = usize::from(xlen / 8);
+        let word = |i: usize| {
+            let mut bytes = [0; 8];
+            bytes[..size].copy_from_slice(&stack[i * size..(i + 1) * size]);
+            u64::from_le_bytes(bytes)
+        };
+        assert_eq!(word(0), 2);
+        assert_eq!(string_at(&stack, sp, word(1)), b"prog");
+        assert_eq!(string_at(&stack, sp, word(2)), b"ab");
+        assert_eq!(word(3), 0);
+        assert_eq!(string_at(&stack, sp, word(4)), b"HOME=/");
+        assert_eq!(word(5), 0);
+        assert_eq!(word(6), AT_RANDOM);
+        let random = usize::try_from(word(7) - sp).unwrap();
+        assert_eq!(stack[random..random + 16], RANDOM_BYTES);
+        assert_eq!([word(8), word(9)], [0; 2]);
+    }
+
+    #[test]
+    fn test_initial_stack_rv64() {
+        check_layout(64);
+    }
+
+    #[test]
+    fn test_initial_stack_rv32() {
+        check_layout(32);
+    }
+
+    #[test]
+    fn test_initial_stack_env_only() {
+        let (sp, stack) = initial_stack(STACK_TOP, 64, &[], &env());
+        assert_eq!(sp % STACK_ALIGN, 0);
+        let word = |i: usize| u64::from_le_bytes(stack[i * 8..(i + 1) * 8].try_into().unwrap());
+        assert_eq!([word(0), word(1)], [0; 2]);
+        assert_eq!(string_at(&stack, sp, word(2)), b"HOME=/");
+        assert_eq!([word(3), word(4)], [0, AT_RANDOM]);
+    }
+}
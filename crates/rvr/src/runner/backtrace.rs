@@ -0,0 +1,222 @@
+//! Guest stack unwinding after a trap.
+//!
+//! [`Runner::backtrace`] walks the guest stack from the trapping PC. Frames
+//! with `.eh_frame` CFI are unwound with it; the rest follow the frame
+//! pointer chain (`ra` at `fp - XLEN`, the caller's `fp` at `fp - 2*XLEN`).
+//! A trap in a leaf that has not saved `ra` yet continues at `ra`.
+
+use std::fmt;
+use std::path::Path;
+
+use rvr_elf::{CallFrameInfo, DebugInfo, ElfImage, RegisterRule, UnwindRow, get_elf_xlen};
+use rvr_ir::{Rv32, Rv64, SourceLoc, Xlen};
+use rvr_isa::{REG_FP, REG_RA, REG_SP};
+use tracing::{debug, warn};
+
+use super::Runner;
+
+/// Frames walked before giving up on a corrupt or cyclic stack.
+const MAX_FRAMES: usize = 64;
+
+/// One frame of a guest backtrace.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    /// Trapping PC for the innermost frame, return address for callers.
+    pub pc: u64,
+    /// Function containing the frame, if the ELF has a symbol for it.
+    pub function: Option<String>,
+    /// Offset of `pc` from the start of `function`.
+    pub offset: u64,
+    /// Source location of the trap or call, if the ELF has debug info.
+    pub location: Option<SourceLoc>,
+}
+
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#x}", self.pc)?;
+        if let Some(function) = &self.function {
+            write!(f, " in {function}+{:#x}", self.offset)?;
+        }
+        if let Some(location) = &self.location {
+            write!(f, " at {}:{}", location.file, location.line)?;
+        }
+        Ok(())
+    }
+}
+
+impl Runner {
+    /// Check if the last run stopped on a guest trap (illegal instruction,
+    /// `unimp`, failed fetch), as opposed to an exit.
+    #[must_use]
+    pub fn is_trapped(&self) -> bool {
+        self.inner.is_trapped()
+    }
+
+    /// Walk the guest stack from the current PC, innermost frame first.
+    ///
+    /// Meant for a trapped run, whose generated code saved the trapping PC
+    /// and registers. Return addresses are resolved to symbol+offset and,
+    /// when the ELF has debug info, to a source line via llvm-addr2line.
+    /// Returns no frames if the ELF cannot be read, or there is none.
+    #[must_use]
+    pub fn backtrace(&self) -> Vec<Frame> {
+        let Some(elf_path) = &self.elf_path else {
+            return Vec::new();
+        };
+        let image = std::fs::read(elf_path)
+            .map_err(|e| e.to_string())
+            .and_then(|data| {
+                let xlen = get_elf_xlen(&data).map_err(|e| e.to_string())?;
+                let load_bias = self.api.load_bias;
+                if xlen == Rv32::VALUE {
+                    ElfImage::<Rv32>::parse_with_load_bias(&data, load_bias)
+                        .map(|image| self.backtrace_image(&image, elf_path))
+                } else {
+                    ElfImage::<Rv64>::parse_with_load_bias(&data, load_bias)
+                        .map(|image| self.backtrace_image(&image, elf_path))
+                }
+                .map_err(|e| e.to_string())
+            });
+        image.unwrap_or_else(|e| {
+            warn!(error = %e, path = %elf_path.display(), "cannot read ELF for backtrace");
+            Vec::new()
+        })
+    }
+
+    fn backtrace_image<X: Xlen>(&self, image: &ElfImage<X>, elf_path: &Path) -> Vec<Frame> {
+        let pcs = self.unwind(image);
+        // Callers are looked up at the call, not the instruction after it
+        let lookups: Vec<u64> = pcs
+            .iter()
+            .enumerate()
+            .map(|(depth, &pc)| if depth == 0 { pc } else { pc.wrapping_sub(1) })
+            .collect();
+
+        // addr2line sees the unrelocated file
+        let bias = image.load_bias;
+        let addresses: Vec<u64> = lookups.iter().map(|&pc| pc.wrapping_sub(bias)).collect();
+        let addr2line = crate::Compiler::default().addr2line();
+        let debug_info = DebugInfo::load(&elf_path.to_string_lossy(), &addresses, &addr2line)
+            .unwrap_or_else(|e| {
+                debug!(error = %e, "no debug info, backtrace has no line info");
+                DebugInfo::new()
+            });
+
+        pcs.iter()
+            .zip(&lookups)
+            .map(|(&pc, &lookup)| {
+                let symbol = image.function_symbol(lookup);
+                Frame {
+                    pc,
+                    function: symbol.map(|s| s.name.clone()),
+                    offset: symbol.map_or(0, |s| pc - X::to_u64(s.value)),
+                    location: debug_info
+                        .get(lookup.wrapping_sub(bias))
+                        .filter(|loc| loc.is_valid())
+                        .cloned(),
+                }
+            })
+            .collect()
+    }
+
+    /// PCs of the frames on the stack, innermost first.
+    fn unwind<X: Xlen>(&self, image: &ElfImage<X>) -> Vec<u64> {
+        let cfi = CallFrameInfo::from_image(image);
+        let mut regs: Vec<u64> = (0..self.num_regs()).map(|r| self.get_register(r)).collect();
+        let mut pc = self.get_pc();
+        let mut pcs = Vec::new();
+        while pcs.len() < MAX_FRAMES {
+            let innermost = pcs.is_empty();
+            pcs.push(pc);
+            let lookup = if innermost { pc } else { pc.wrapping_sub(1) };
+            let caller = cfi
+                .row(lookup)
+                .and_then(|row| self.unwind_cfi::<X>(&row, &regs))
+                .or_else(|| self.unwind_fp(image, innermost, lookup, &regs));
+            let Some((caller_pc, caller_regs)) = caller else {
+                break;
+            };
+            let (sp, caller_sp) = (regs[usize::from(REG_SP)], caller_regs[usize::from(REG_SP)]);
+            // The stack grows down, so callers' frames are at or above ours
+            if caller_pc == 0
+                || !is_text(image, caller_pc)
+                || caller_sp < sp
+                || (caller_sp == sp && caller_pc == pc)
+            {
+                break;
+            }
+            pc = caller_pc;
+            regs = caller_regs;
+        }
+        pcs
+    }
+
+    /// Caller's PC and registers from the CFI row of the frame.
+    fn unwind_cfi<X: Xlen>(&self, row: &UnwindRow, regs: &[u64]) -> Option<(u64, Vec<u64>)> {
+        let cfa = regs
+            .get(usize::from(row.cfa_register))?
+            .wrapping_add_signed(row.cfa_offset);
+        let mut caller = regs.to_vec();
+        for (reg, rule) in row.rules() {
+            let Some(slot) = caller.get_mut(usize::from(reg)) else {
+                continue;
+            };
+            *slot = match rule {
+                RegisterRule::SameValue => continue,
+                RegisterRule::Undefined => 0,
+                RegisterRule::Offset(offset) => {
+                    self.read_word::<X>(cfa.wrapping_add_signed(offset))?
+                }
+                RegisterRule::ValOffset(offset) => cfa.wrapping_add_signed(offset),
+                RegisterRule::Register(from) => *regs.get(usize::from(from))?,
+            };
+        }
+        let ra = *caller.get(usize::from(row.return_address))?;
+        caller[usize::from(REG_SP)] = cfa;
+        Some((ra, caller))
+    }
+
+    /// Caller's PC and registers from the frame pointer chain.
+    fn unwind_fp<X: Xlen>(
+        &self,
+        image: &ElfImage<X>,
+        innermost: bool,
+        pc: u64,
+        regs: &[u64],
+    ) -> Option<(u64, Vec<u64>)> {
+        let word = X::REG_BYTES as u64;
+        let fp = regs[usize::from(REG_FP)];
+        let saved_ra = fp
+            .checked_sub(word)
+            .and_then(|addr| self.read_word::<X>(addr));
+
+        // A leaf (or a prologue) still has its return address in ra, which
+        // then points into another function and is not yet saved at fp
+        let ra = regs[usize::from(REG_RA)];
+        let function = |addr| image.function_symbol(addr).map(|s| X::to_u64(s.value));
+        if innermost && is_text(image, ra) && function(ra) != function(pc) && saved_ra != Some(ra) {
+            return Some((ra, regs.to_vec()));
+        }
+
+        let caller_fp = self.read_word::<X>(fp.checked_sub(2 * word)?)?;
+        let mut caller = regs.to_vec();
+        caller[usize::from(REG_FP)] = caller_fp;
+        caller[usize::from(REG_SP)] = fp;
+        Some((saved_ra?, caller))
+    }
+
+    /// Read an XLEN-sized guest word.
+    fn read_word<X: Xlen>(&self, addr: u64) -> Option<u64> {
+        let mut buf = [0u8; 8];
+        let word = &mut buf[..X::REG_BYTES];
+        (self.read_memory(addr, word) == word.len()).then(|| u64::from_le_bytes(buf))
+    }
+}
+
+/// Check whether `addr` is in an executable segment.
+fn is_text<X: Xlen>(image: &ElfImage<X>, addr: u64) -> bool {
+    image.memory_segments.iter().any(|segment| {
+        segment.is_executable()
+            && (X::to_u64(segment.virtual_start)..X::to_u64(segment.virtual_end)).contains(&addr)
+    })
+}
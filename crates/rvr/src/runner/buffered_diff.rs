@@ -8,9 +8,12 @@ use std::ffi::c_void;
 
 use rvr_elf::ElfImage;
 use rvr_ir::Xlen;
-use rvr_state::{BufferedDiffTracer, DiffEntry, GuardedMemory, InstretSuspender, RvState};
+use rvr_state::{BufferedDiffTracer, DiffEntry, GuardedMemory, GuestIo, InstretSuspender, RvState};
 
 use super::traits::{BufferedDiffEntry, RunnerImpl};
+use crate::segment_image::SegmentImage;
+
+use super::{RunError, Snapshot, init_memory};
 
 /// Default buffer capacity for buffered diff tracer.
 const DEFAULT_BUFFER_CAPACITY: usize = 4096;
@@ -46,7 +49,7 @@ impl<X: Xlen, const NUM_REGS: usize> BufferedDiffRunner<X, NUM_REGS> {
         // Allocate buffer and set up tracer
         let capacity_u32 = u32::try_from(capacity).unwrap_or(u32::MAX);
         let capacity = usize::try_from(capacity_u32).unwrap_or(usize::MAX);
-        let mut buffer = vec![DiffEntry::default(); capacity];
+        let mut buffer = vec![DiffEntry::<X>::default(); capacity];
         state.tracer.setup(buffer.as_mut_ptr(), capacity_u32);
 
         Self {
@@ -69,13 +72,8 @@ impl<X: Xlen, const NUM_REGS: usize> BufferedDiffRunner<X, NUM_REGS> {
 }
 
 impl<X: Xlen, const NUM_REGS: usize> RunnerImpl for BufferedDiffRunner<X, NUM_REGS> {
-    fn load_segments(&mut self) {
-        self.memory.clear();
-        for seg in &self.elf_image.memory_segments {
-            let vaddr = usize::try_from(X::to_u64(seg.virtual_start))
-                .expect("segment address does not fit in host usize");
-            unsafe { self.memory.copy_from(vaddr, &seg.data) };
-        }
+    fn load_segments(&mut self, segments: Option<&SegmentImage>) {
+        init_memory(&mut self.memory, &self.elf_image, segments);
     }
 
     fn reset(&mut self) {
@@ -102,6 +100,26 @@ impl<X: Xlen, const NUM_REGS: usize> RunnerImpl for BufferedDiffRunner<X, NUM_RE
         self.state.has_exited()
     }
 
+    fn is_trapped(&self) -> bool {
+        self.state.is_trapped()
+    }
+
+    fn exit_cause(&self) -> u32 {
+        self.state.exit_cause()
+    }
+
+    fn exit_info(&self) -> u64 {
+        self.state.exit_info()
+    }
+
+    fn htif_syscall(&self) -> u64 {
+        self.state.htif_syscall()
+    }
+
+    fn cancel_flag(&mut self) -> *mut u8 {
+        self.state.cancel_flag()
+    }
+
     fn entry_point(&self) -> u64 {
         X::to_u64(self.elf_image.entry_point)
     }
@@ -171,10 +189,33 @@ impl<X: Xlen, const NUM_REGS: usize> RunnerImpl for BufferedDiffRunner<X, NUM_RE
         self.memory.size()
     }
 
+    fn resident_bytes(&self) -> Option<usize> {
+        self.memory.resident_bytes().ok()
+    }
+
+    fn memory_mut(&mut self) -> &mut GuardedMemory {
+        &mut self.memory
+    }
+
     fn clear_exit(&mut self) {
         self.state.clear_exit();
     }
 
+    fn set_io(&mut self, io: *mut GuestIo) {
+        self.state.set_io(io);
+    }
+
+    fn snapshot(&mut self) -> Result<Snapshot, RunError> {
+        let memory = self.memory.snapshot()?;
+        Ok(Snapshot::new(X::VALUE, self.state.capture(), memory))
+    }
+
+    fn restore(&mut self, snapshot: &Snapshot) -> Result<(), RunError> {
+        self.memory.restore(snapshot.memory())?;
+        self.state.restore(snapshot.state());
+        Ok(())
+    }
+
     fn supports_suspend(&self) -> bool {
         true
     }
@@ -210,6 +251,8 @@ impl<X: Xlen, const NUM_REGS: usize> RunnerImpl for BufferedDiffRunner<X, NUM_RE
             entry.get_rd(),
             entry.get_rd_value(),
             entry.get_mem_access(),
+            entry.get_csr_write(),
+            entry.instret,
         ))
     }
 
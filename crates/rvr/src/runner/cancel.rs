@@ -0,0 +1,233 @@
+//! Run timeouts and cancellation from another thread.
+//!
+//! Libraries compiled with `check_cancel` test `RvState::cancel_requested`
+//! on backward jumps and branches, so the host stops them by setting the
+//! byte. Libraries compiled with `--instret suspend` run in bounded slices
+//! instead, with the deadline and token checked between slices.
+
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tracing::trace;
+
+use super::{RunError, RunPhases, RunResult, Runner};
+
+/// Guest instructions per slice when a timeout is enforced by suspending.
+const CANCEL_SLICE_INSTRUCTIONS: u64 = 1 << 20;
+
+/// `RvState::cancel_requested` of the runner whose run is in progress.
+struct FlagPtr(*mut u8);
+
+// SAFETY: the pointer is only dereferenced under `CancelState::flag`, and
+// the runner nulls it before its state can be freed.
+unsafe impl Send for FlagPtr {}
+
+/// Cancellation shared between a runner and its handles.
+pub(super) struct CancelState {
+    /// A handle asked to stop; consumed by the run it stops.
+    requested: AtomicBool,
+    flag: Mutex<FlagPtr>,
+}
+
+impl Default for CancelState {
+    fn default() -> Self {
+        Self {
+            requested: AtomicBool::new(false),
+            flag: Mutex::new(FlagPtr(std::ptr::null_mut())),
+        }
+    }
+}
+
+impl CancelState {
+    fn request(&self) {
+        self.requested.store(true, Ordering::SeqCst);
+        self.raise();
+    }
+
+    /// Set the state's flag if a run is in progress.
+    fn raise(&self) {
+        let flag = self.flag.lock().unwrap_or_else(PoisonError::into_inner);
+        if !flag.0.is_null() {
+            // SAFETY: non-null only while the runner's state is alive.
+            unsafe { AtomicU8::from_ptr(flag.0).store(1, Ordering::SeqCst) };
+        }
+    }
+
+    /// Point at the state's flag for a run, raising it if a cancel is pending.
+    pub(super) fn arm(&self, ptr: *mut u8) {
+        let mut flag = self.flag.lock().unwrap_or_else(PoisonError::into_inner);
+        let pending = u8::from(self.requested.load(Ordering::SeqCst));
+        // SAFETY: `ptr` points into the runner's state.
+        unsafe { AtomicU8::from_ptr(ptr).store(pending, Ordering::SeqCst) };
+        flag.0 = ptr;
+    }
+
+    /// Stop writing to the state's flag and clear it. Returns whether it
+    /// was raised.
+    pub(super) fn disarm(&self) -> bool {
+        let mut flag = self.flag.lock().unwrap_or_else(PoisonError::into_inner);
+        if flag.0.is_null() {
+            return false;
+        }
+        // SAFETY: non-null only while the runner's state is alive.
+        let raised = unsafe { AtomicU8::from_ptr(flag.0).swap(0, Ordering::SeqCst) } != 0;
+        flag.0 = std::ptr::null_mut();
+        raised
+    }
+
+    /// Consume a pending cancel.
+    pub(super) fn take_request(&self) -> bool {
+        self.requested.swap(false, Ordering::SeqCst)
+    }
+}
+
+/// Handle that cancels a [`Runner`]'s runs from another thread.
+///
+/// A cancel stops the run in progress, or the next run if none is. Runs
+/// stop at the next backward jump or branch (libraries compiled with
+/// `check_cancel`) or slice boundary ([`Runner::run_with_timeout`] on
+/// libraries compiled with `--instret suspend`); a run that exits first
+/// clears it.
+#[derive(Clone)]
+pub struct CancelHandle {
+    shared: Arc<CancelState>,
+}
+
+impl CancelHandle {
+    /// Ask the runner to stop.
+    pub fn cancel(&self) {
+        self.shared.request();
+    }
+
+    /// Check if a cancel is pending.
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.shared.requested.load(Ordering::SeqCst)
+    }
+}
+
+impl Runner {
+    /// Handle for cancelling this runner's runs from another thread.
+    #[must_use]
+    pub fn cancel_handle(&self) -> CancelHandle {
+        CancelHandle {
+            shared: Arc::clone(&self.cancel),
+        }
+    }
+
+    /// Run the program, stopping it after `timeout` of wall-clock time.
+    ///
+    /// Libraries compiled with `check_cancel` are stopped by a watchdog
+    /// thread; libraries compiled with `--instret suspend` run in slices
+    /// of about a million instructions with the deadline checked between
+    /// them. Either way the guest's PC and registers stay inspectable
+    /// after a stop.
+    ///
+    /// # Errors
+    /// Returns `TimedOut` if the deadline passed, `Cancelled` if a
+    /// [`CancelHandle`] stopped the run, `TimeoutUnsupported` if the
+    /// library was compiled with neither option, or an error if execution
+    /// fails.
+    pub fn run_with_timeout(&mut self, timeout: Duration) -> Result<RunResult, RunError> {
+        if self.api.check_cancel {
+            self.run_with_watchdog(timeout)
+        } else if self.supports_suspend() {
+            self.run_in_slices(Instant::now().checked_add(timeout))
+        } else {
+            Err(RunError::TimeoutUnsupported)
+        }
+    }
+
+    /// Run while a watchdog thread raises the cancel flag at the deadline.
+    fn run_with_watchdog(&mut self, timeout: Duration) -> Result<RunResult, RunError> {
+        let shared = Arc::clone(&self.cancel);
+        let timed_out = AtomicBool::new(false);
+        let timed_out = &timed_out;
+        let (done, finished) = mpsc::channel::<()>();
+        let result = thread::scope(|scope| {
+            scope.spawn(move || {
+                if finished.recv_timeout(timeout) == Err(RecvTimeoutError::Timeout) {
+                    timed_out.store(true, Ordering::SeqCst);
+                    shared.raise();
+                }
+            });
+            let result = self.run();
+            drop(done);
+            result
+        })?;
+        if timed_out.load(Ordering::SeqCst) && !self.inner.has_exited() {
+            return Err(RunError::TimedOut {
+                instret_so_far: self.inner.instret(),
+            });
+        }
+        Ok(result)
+    }
+
+    /// Run from the entry point in suspend slices until the guest exits,
+    /// the deadline (if any) passes or a handle cancels.
+    fn run_in_slices(&mut self, deadline: Option<Instant>) -> Result<RunResult, RunError> {
+        let load_secs = self.take_load_secs();
+
+        let start = Instant::now();
+        let limit = self
+            .inner
+            .get_target_instret()
+            .filter(|&target| target != u64::MAX);
+        self.inner.load_segments(self.segments.as_ref());
+        self.inner.reset();
+        self.setup_initial_regs();
+        let mut pc = self.inner.entry_point();
+        let init_secs = start.elapsed().as_secs_f64();
+
+        let start = Instant::now();
+        let stop = loop {
+            let target = self
+                .inner
+                .instret()
+                .saturating_add(CANCEL_SLICE_INSTRUCTIONS);
+            self.inner
+                .set_target_instret(limit.map_or(target, |limit| target.min(limit)));
+            unsafe { (self.api.execute_from)(self.inner.as_void_ptr(), pc) };
+            pc = self.inner.get_pc();
+            let instret = self.inner.instret();
+            if self.inner.has_exited() || limit.is_some_and(|limit| instret >= limit) {
+                break None;
+            }
+            if self.cancel.take_request() {
+                break Some(RunError::Cancelled);
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                break Some(RunError::TimedOut {
+                    instret_so_far: instret,
+                });
+            }
+        };
+        let execute_secs = start.elapsed().as_secs_f64();
+        self.inner.set_target_instret(limit.unwrap_or(u64::MAX));
+        if let Some(err) = stop {
+            trace!(pc = format!("{pc:#x}"), %err, "run stopped");
+            return Err(err);
+        }
+        self.cancel.take_request();
+
+        let start = Instant::now();
+        self.check_quarantine()?;
+        let exit_reason = self.exit_reason();
+        let teardown_secs = start.elapsed().as_secs_f64();
+        Ok(RunResult::new(
+            self.inner.exit_code(),
+            self.inner.instret(),
+            RunPhases {
+                load_secs,
+                init_secs,
+                execute_secs,
+                teardown_secs,
+            },
+        )
+        .with_exit_reason(exit_reason)
+        .with_resident_bytes(self.resident_bytes()))
+    }
+}
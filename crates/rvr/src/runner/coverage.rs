@@ -0,0 +1,192 @@
+//! `CoverageRunner` - runner with coverage tracer for per-instruction coverage flags.
+
+use std::ffi::c_void;
+
+use rvr_elf::ElfImage;
+use rvr_ir::Xlen;
+use rvr_state::{CoverageTracer, GuardedMemory, GuestIo, RvState};
+
+use super::api::ProfileSlots;
+use super::block_profile::nonzero_slots;
+use crate::segment_image::SegmentImage;
+
+use super::{RunError, RunnerImpl, Snapshot, init_memory};
+
+/// Typed runner with coverage tracer (needs flag management).
+pub struct CoverageRunner<X: Xlen, const NUM_REGS: usize> {
+    state: RvState<X, CoverageTracer, (), NUM_REGS>,
+    memory: GuardedMemory,
+    elf_image: ElfImage<X>,
+    base: u64,
+    flags: Vec<u8>,
+}
+
+impl<X: Xlen, const NUM_REGS: usize> CoverageRunner<X, NUM_REGS> {
+    pub fn new(elf_image: ElfImage<X>, memory: GuardedMemory, slots: ProfileSlots) -> Self {
+        let mut state = RvState::new();
+        state.set_memory(memory.as_ptr());
+        let brk = elf_image.get_initial_program_break();
+        state.brk = brk;
+        state.start_brk = brk;
+        Self {
+            state,
+            memory,
+            elf_image,
+            base: slots.base,
+            flags: vec![0u8; slots.slots],
+        }
+    }
+}
+
+impl<X: Xlen, const NUM_REGS: usize> RunnerImpl for CoverageRunner<X, NUM_REGS> {
+    fn load_segments(&mut self, segments: Option<&SegmentImage>) {
+        init_memory(&mut self.memory, &self.elf_image, segments);
+    }
+
+    fn reset(&mut self) {
+        self.state.reset();
+        self.state.set_memory(self.memory.as_ptr());
+        self.flags.fill(0);
+        self.state.tracer.setup(self.flags.as_mut_ptr());
+    }
+
+    fn as_void_ptr(&mut self) -> *mut c_void {
+        self.state.as_void_ptr()
+    }
+
+    fn instret(&self) -> u64 {
+        self.state.instret()
+    }
+
+    fn exit_code(&self) -> u8 {
+        self.state.exit_code()
+    }
+
+    fn has_exited(&self) -> bool {
+        self.state.has_exited()
+    }
+
+    fn is_trapped(&self) -> bool {
+        self.state.is_trapped()
+    }
+
+    fn exit_cause(&self) -> u32 {
+        self.state.exit_cause()
+    }
+
+    fn exit_info(&self) -> u64 {
+        self.state.exit_info()
+    }
+
+    fn htif_syscall(&self) -> u64 {
+        self.state.htif_syscall()
+    }
+
+    fn cancel_flag(&mut self) -> *mut u8 {
+        self.state.cancel_flag()
+    }
+
+    fn entry_point(&self) -> u64 {
+        X::to_u64(self.elf_image.entry_point)
+    }
+
+    fn lookup_symbol(&self, name: &str) -> Option<u64> {
+        self.elf_image.lookup_symbol(name)
+    }
+
+    fn set_register(&mut self, reg: usize, value: u64) {
+        self.state.set_reg(reg, X::from_u64(value));
+    }
+
+    fn get_register(&self, reg: usize) -> u64 {
+        X::to_u64(self.state.get_reg(reg))
+    }
+
+    fn get_pc(&self) -> u64 {
+        X::to_u64(self.state.pc())
+    }
+
+    fn set_pc(&mut self, pc: u64) {
+        self.state.set_pc(X::from_u64(pc));
+    }
+
+    fn get_csr(&self, csr: u16) -> u64 {
+        X::to_u64(self.state.csrs[csr as usize])
+    }
+
+    fn set_csr(&mut self, csr: u16, value: u64) {
+        self.state.csrs[csr as usize] = X::from_u64(value);
+    }
+
+    fn read_memory(&self, addr: u64, buf: &mut [u8]) -> usize {
+        let mem_size = self.memory.size();
+        let Ok(addr) = usize::try_from(addr) else {
+            return 0;
+        };
+        if addr >= mem_size {
+            return 0;
+        }
+        let len = buf.len().min(mem_size - addr);
+        let src = unsafe { std::slice::from_raw_parts(self.memory.as_ptr().add(addr), len) };
+        buf[..len].copy_from_slice(src);
+        len
+    }
+
+    fn write_memory(&mut self, addr: u64, data: &[u8]) -> usize {
+        let mem_size = self.memory.size();
+        let Ok(addr) = usize::try_from(addr) else {
+            return 0;
+        };
+        if addr >= mem_size {
+            return 0;
+        }
+        let len = data.len().min(mem_size - addr);
+        unsafe {
+            std::ptr::copy_nonoverlapping(data.as_ptr(), self.memory.as_ptr().add(addr), len);
+        }
+        len
+    }
+
+    fn num_regs(&self) -> usize {
+        NUM_REGS
+    }
+
+    fn xlen(&self) -> u8 {
+        X::VALUE
+    }
+
+    fn memory_size(&self) -> usize {
+        self.memory.size()
+    }
+
+    fn resident_bytes(&self) -> Option<usize> {
+        self.memory.resident_bytes().ok()
+    }
+
+    fn memory_mut(&mut self) -> &mut GuardedMemory {
+        &mut self.memory
+    }
+
+    fn clear_exit(&mut self) {
+        self.state.clear_exit();
+    }
+
+    fn set_io(&mut self, io: *mut GuestIo) {
+        self.state.set_io(io);
+    }
+
+    fn snapshot(&mut self) -> Result<Snapshot, RunError> {
+        let memory = self.memory.snapshot()?;
+        Ok(Snapshot::new(X::VALUE, self.state.capture(), memory))
+    }
+
+    fn restore(&mut self, snapshot: &Snapshot) -> Result<(), RunError> {
+        self.memory.restore(snapshot.memory())?;
+        self.state.restore(snapshot.state());
+        Ok(())
+    }
+
+    fn coverage(&self) -> Option<Vec<(u64, u8)>> {
+        Some(nonzero_slots(self.base, &self.flags))
+    }
+}
@@ -4,9 +4,11 @@ use std::ffi::c_void;
 
 use rvr_elf::ElfImage;
 use rvr_ir::Xlen;
-use rvr_state::{DiffTracer, GuardedMemory, InstretSuspender, RvState};
+use rvr_state::{DiffTracer, GuardedMemory, GuestIo, InstretSuspender, RvState};
 
-use super::RunnerImpl;
+use crate::segment_image::SegmentImage;
+
+use super::{RunError, RunnerImpl, Snapshot, init_memory};
 
 /// Typed runner with diff tracer and instret suspension for differential testing.
 ///
@@ -37,13 +39,8 @@ impl<X: Xlen, const NUM_REGS: usize> DiffRunner<X, NUM_REGS> {
 }
 
 impl<X: Xlen, const NUM_REGS: usize> RunnerImpl for DiffRunner<X, NUM_REGS> {
-    fn load_segments(&mut self) {
-        self.memory.clear();
-        for seg in &self.elf_image.memory_segments {
-            let vaddr = usize::try_from(X::to_u64(seg.virtual_start))
-                .expect("segment address does not fit in host usize");
-            unsafe { self.memory.copy_from(vaddr, &seg.data) };
-        }
+    fn load_segments(&mut self, segments: Option<&SegmentImage>) {
+        init_memory(&mut self.memory, &self.elf_image, segments);
     }
 
     fn reset(&mut self) {
@@ -68,6 +65,26 @@ impl<X: Xlen, const NUM_REGS: usize> RunnerImpl for DiffRunner<X, NUM_REGS> {
         self.state.has_exited()
     }
 
+    fn is_trapped(&self) -> bool {
+        self.state.is_trapped()
+    }
+
+    fn exit_cause(&self) -> u32 {
+        self.state.exit_cause()
+    }
+
+    fn exit_info(&self) -> u64 {
+        self.state.exit_info()
+    }
+
+    fn htif_syscall(&self) -> u64 {
+        self.state.htif_syscall()
+    }
+
+    fn cancel_flag(&mut self) -> *mut u8 {
+        self.state.cancel_flag()
+    }
+
     fn entry_point(&self) -> u64 {
         X::to_u64(self.elf_image.entry_point)
     }
@@ -137,10 +154,33 @@ impl<X: Xlen, const NUM_REGS: usize> RunnerImpl for DiffRunner<X, NUM_REGS> {
         self.memory.size()
     }
 
+    fn resident_bytes(&self) -> Option<usize> {
+        self.memory.resident_bytes().ok()
+    }
+
+    fn memory_mut(&mut self) -> &mut GuardedMemory {
+        &mut self.memory
+    }
+
     fn clear_exit(&mut self) {
         self.state.clear_exit();
     }
 
+    fn set_io(&mut self, io: *mut GuestIo) {
+        self.state.set_io(io);
+    }
+
+    fn snapshot(&mut self) -> Result<Snapshot, RunError> {
+        let memory = self.memory.snapshot()?;
+        Ok(Snapshot::new(X::VALUE, self.state.capture(), memory))
+    }
+
+    fn restore(&mut self, snapshot: &Snapshot) -> Result<(), RunError> {
+        self.memory.restore(snapshot.memory())?;
+        self.state.restore(snapshot.state());
+        Ok(())
+    }
+
     fn supports_suspend(&self) -> bool {
         true
     }
@@ -182,6 +222,10 @@ impl<X: Xlen, const NUM_REGS: usize> RunnerImpl for DiffRunner<X, NUM_REGS> {
         self.state.tracer.get_mem_access()
     }
 
+    fn diff_traced_csr(&self) -> Option<(u16, u64)> {
+        self.state.tracer.get_csr_write()
+    }
+
     fn diff_tracer_valid(&self) -> bool {
         self.state.tracer.is_valid()
     }
@@ -0,0 +1,129 @@
+//! Guest environment files in dotenv format.
+//!
+//! One `KEY=VALUE` per line, optionally after `export`; blank lines and
+//! lines starting with `#` are skipped. Unquoted values are trimmed and end
+//! at a ` #` comment, single-quoted values are taken literally, and
+//! double-quoted values understand `\n`, `\t`, `\"` and `\\`. Values do not
+//! span lines.
+
+/// Variables of dotenv `text`, in file order.
+pub(super) fn parse_dotenv(text: &str) -> Result<Vec<(String, String)>, String> {
+    let mut vars = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let parsed = line
+            .split_once('=')
+            .ok_or_else(|| "expected KEY=VALUE".to_string())
+            .and_then(|(key, value)| {
+                let key = key.trim();
+                if !is_env_key(key) {
+                    return Err(format!("invalid variable name '{key}'"));
+                }
+                Ok((key.to_string(), parse_value(value.trim())?))
+            });
+        vars.push(parsed.map_err(|reason| format!("line {}: {reason}", index + 1))?);
+    }
+    Ok(vars)
+}
+
+/// Whether `key` is a shell-style variable name.
+fn is_env_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn parse_value(value: &str) -> Result<String, String> {
+    if let Some(quoted) = value.strip_prefix('\'') {
+        let (inner, rest) = quoted
+            .split_once('\'')
+            .ok_or_else(|| "unterminated single quote".to_string())?;
+        check_trailing(rest)?;
+        return Ok(inner.to_string());
+    }
+    if let Some(quoted) = value.strip_prefix('"') {
+        let mut inner = String::new();
+        let mut chars = quoted.chars();
+        loop {
+            match chars.next() {
+                None => return Err("unterminated double quote".to_string()),
+                Some('"') => break,
+                Some('\\') => inner.push(match chars.next() {
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    Some(c @ ('"' | '\\')) => c,
+                    Some(c) => return Err(format!("unknown escape '\\{c}'")),
+                    None => return Err("unterminated double quote".to_string()),
+                }),
+                Some(c) => inner.push(c),
+            }
+        }
+        check_trailing(chars.as_str())?;
+        return Ok(inner);
+    }
+    let value = value.find(" #").map_or(value, |end| &value[..end]);
+    Ok(value.trim_end().to_string())
+}
+
+/// Only a comment may follow a quoted value.
+fn check_trailing(rest: &str) -> Result<(), String> {
+    let rest = rest.trim_start();
+    if rest.is_empty() || rest.starts_with('#') {
+        Ok(())
+    } else {
+        Err(format!("unexpected '{rest}' after quoted value"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| ((*k).to_string(), (*v).to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_dotenv() {
+        let cases: &[(&str, &[(&str, &str)])] = &[
+            ("", &[]),
+            ("# comment\n\nA=1\n", &[("A", "1")]),
+            ("export TZ=UTC", &[("TZ", "UTC")]),
+            (" KEY = spaced value  ", &[("KEY", "spaced value")]),
+            ("A=x # note\nB=y#z", &[("A", "x"), ("B", "y#z")]),
+            ("EMPTY=", &[("EMPTY", "")]),
+            ("A=a=b", &[("A", "a=b")]),
+            ("S='raw \\n # kept'", &[("S", "raw \\n # kept")]),
+            (r#"D="a\tb\n\"q\" \\" # c"#, &[("D", "a\tb\n\"q\" \\")]),
+            ("A=1\nA=2", &[("A", "1"), ("A", "2")]),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(parse_dotenv(text).unwrap(), vars(expected), "{text:?}");
+        }
+    }
+
+    #[test]
+    fn test_parse_dotenv_errors() {
+        let cases = [
+            ("A=1\nnot a pair", "line 2: expected KEY=VALUE"),
+            ("1A=x", "line 1: invalid variable name '1A'"),
+            ("=x", "line 1: invalid variable name ''"),
+            ("A='open", "line 1: unterminated single quote"),
+            ("A=\"open", "line 1: unterminated double quote"),
+            ("A=\"\\x\"", "line 1: unknown escape '\\x'"),
+            ("A='v' tail", "line 1: unexpected 'tail' after quoted value"),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(parse_dotenv(text).unwrap_err(), expected, "{text:?}");
+        }
+    }
+}
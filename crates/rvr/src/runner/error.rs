@@ -2,6 +2,8 @@
 
 use thiserror::Error;
 
+use super::ExitReason;
+
 /// Runner error type.
 #[derive(Debug, Error)]
 pub enum RunError {
@@ -14,9 +16,21 @@ pub enum RunError {
     #[error("ELF file not found: {0}")]
     ElfNotFound(String),
 
+    #[error("program not in library manifest: {0}")]
+    ProgramNotFound(String),
+
     #[error("failed to find symbol '{0}': {1}")]
     SymbolNotFound(String, libloading::Error),
 
+    #[error("symbol not found in embedded program: {0}")]
+    EmbeddedSymbolNotFound(String),
+
+    #[error("embedded programs do not support {0}")]
+    EmbeddedUnsupported(&'static str),
+
+    #[error("embedded program is RV{embedded} but the ELF is RV{elf}")]
+    XlenMismatch { embedded: u32, elf: u8 },
+
     #[error("function not found: {0}")]
     FunctionNotFound(String),
 
@@ -29,12 +43,88 @@ pub enum RunError {
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
 
-    #[error("execution error: exit code {0}")]
-    ExecutionError(u8),
+    #[error("execution error: {0}")]
+    ExecutionError(ExitReason),
+
+    #[error(
+        "library has ABI version {}, runner expects {expected}; recompile it",
+        .found.map_or_else(|| "none".to_string(), |version| version.to_string())
+    )]
+    AbiMismatch { expected: u32, found: Option<u32> },
+
+    #[error(transparent)]
+    LayoutMismatch(#[from] rvr_emit::LayoutError),
+
+    #[error("executed quarantined block at {pc:#x}: {reason}")]
+    QuarantinedBlock { pc: u64, reason: String },
+
+    #[error("guest wrote to recompiled code at pc {pc:#x}, address {addr:#x}")]
+    CodeWrite { pc: u64, addr: u64 },
+
+    #[error("guest stack overflow (sp={sp:#x}, limit={limit:#x}) at pc {pc:#x}")]
+    StackOverflow { pc: u64, sp: u64, limit: u64 },
+
+    #[error(
+        "unresolved dynamic jump at pc {site:#x} to {target:#x} (target in {})",
+        rvr_isa::reg_name(*reg)
+    )]
+    UnresolvedJump { site: u64, target: u64, reg: u8 },
 
     #[error("tracer setup failed: {0}")]
     TracerSetupFailed(String),
 
     #[error("state file error: {0}")]
     StateError(String),
+
+    #[error("segment image {path}: {reason}")]
+    SegmentImage { path: String, reason: String },
+
+    #[error("host buffer at {addr:#x}: {reason}")]
+    HostBuffer { addr: u64, reason: String },
+
+    #[error("reloaded library {what} differs: was {old}, now {new}")]
+    ReloadMismatch {
+        what: &'static str,
+        old: String,
+        new: String,
+    },
+
+    #[error("cannot reload: {0}")]
+    Reload(String),
+
+    #[error("cannot preopen {guest_path}: {reason}")]
+    Preopen { guest_path: String, reason: String },
+
+    #[error("invalid environment file {path}: {reason}")]
+    EnvFile { path: String, reason: String },
+
+    #[error("input recording {path}: {reason}")]
+    InputRecording { path: String, reason: String },
+
+    #[error("replay diverged at input {index}: {reason}")]
+    ReplayDiverged { index: usize, reason: String },
+
+    #[error("library embeds no segment data")]
+    NoEmbeddedSegments,
+
+    #[error("{0} requires a library compiled with --instret suspend")]
+    SuspendRequired(&'static str),
+
+    #[error("run timed out after {instret_so_far} instructions")]
+    TimedOut { instret_so_far: u64 },
+
+    #[error("run cancelled")]
+    Cancelled,
+
+    #[error("run timeouts require a library compiled with --instret suspend or --check-cancel")]
+    TimeoutUnsupported,
+
+    #[error("ptrace: {0}")]
+    Ptrace(String),
+
+    #[error("isolated runner child killed by signal {0}")]
+    ChildCrashed(i32),
+
+    #[error("isolated runner: {0}")]
+    Isolation(String),
 }
@@ -0,0 +1,328 @@
+//! Running the guest: entry, repeated and timed runs, and calls into guest
+//! functions.
+
+use std::time::Instant;
+
+use rvr_isa::{REG_RA, REG_SP};
+use tracing::{debug, trace};
+
+use super::{ExitReason, RunError, RunPhases, RunResult, RunResultWithPerf, Runner, TrapCause};
+
+impl Runner {
+    /// Load segments and reset state for a fresh run.
+    pub fn prepare(&mut self) {
+        self.inner.load_segments(self.segments.as_ref());
+        self.inner.reset();
+    }
+
+    /// Re-initialize guest memory from the segment data embedded in the
+    /// library instead of the ELF, decompressing compressed segments.
+    ///
+    /// The library and ELF normally hold the same bytes, so this is for
+    /// checking what a library embeds; [`prepare`](Self::prepare) and the
+    /// run methods load the ELF again.
+    ///
+    /// # Errors
+    /// Returns `NoEmbeddedSegments` if the library embeds none (lazy
+    /// segment init, or an ELF without loadable data).
+    pub fn load_embedded_segments(&mut self) -> Result<(), RunError> {
+        let init_memory = self.api.init_memory.ok_or(RunError::NoEmbeddedSegments)?;
+        self.inner.memory_mut().clear();
+        unsafe { init_memory(self.inner.as_void_ptr()) };
+        Ok(())
+    }
+
+    /// Execute from a specific address.
+    ///
+    /// # Errors
+    /// Returns an error if execution fails or the runtime reports a failure.
+    pub fn execute_from(&mut self, pc: u64) -> Result<(std::time::Duration, u64), RunError> {
+        let start = Instant::now();
+        unsafe { (self.api.execute_from)(self.inner.as_void_ptr(), pc) };
+        let elapsed = start.elapsed();
+        self.check_quarantine()?;
+        self.check_replay()?;
+        let reason = self.exit_reason();
+        if reason.is_success() {
+            Ok((elapsed, self.inner.instret()))
+        } else {
+            Err(RunError::ExecutionError(reason))
+        }
+    }
+
+    /// Fail with `QuarantinedBlock` if execution stopped in a quarantine
+    /// stub, with `CodeWrite` if it trapped on a store into recompiled
+    /// code, with `UnresolvedJump` if a dynamic jump missed the dispatch
+    /// table, or with `StackOverflow` if it trapped on a store into the
+    /// stack guard or with the stack pointer in it.
+    pub(super) fn check_quarantine(&self) -> Result<(), RunError> {
+        let reason = self.exit_reason();
+        if reason.is_success() {
+            return Ok(());
+        }
+        match reason {
+            ExitReason::Trapped {
+                cause: TrapCause::CodeWrite,
+                pc,
+                addr,
+            } => return Err(RunError::CodeWrite { pc, addr }),
+            // The miss path stores the source register in the payload byte
+            ExitReason::Trapped {
+                cause: TrapCause::UnresolvedJump,
+                pc,
+                addr,
+            } => {
+                return Err(RunError::UnresolvedJump {
+                    site: pc,
+                    target: addr,
+                    reg: self.inner.exit_code(),
+                });
+            }
+            _ => {}
+        }
+        let pc = self.inner.get_pc();
+        if let ExitReason::Trapped { cause, .. } = reason
+            && let Some(layout) = &self.memory_layout
+        {
+            let sp = self.inner.get_register(REG_SP as usize);
+            if cause == TrapCause::StackOverflow || layout.is_stack_overflow(sp) {
+                return Err(RunError::StackOverflow {
+                    pc,
+                    sp,
+                    limit: layout.stack.start,
+                });
+            }
+        }
+        self.quarantine.get(&pc).map_or(Ok(()), |reason| {
+            Err(RunError::QuarantinedBlock {
+                pc,
+                reason: reason.clone(),
+            })
+        })
+    }
+
+    pub(crate) fn reset_and_run_to_instret(
+        &mut self,
+        target_instret: u64,
+    ) -> Result<(std::time::Duration, u64), RunError> {
+        self.inner.load_segments(self.segments.as_ref());
+        self.inner.reset();
+        self.setup_initial_regs();
+        self.inner.set_target_instret(target_instret);
+        self.clear_exit();
+        let entry_point = self.inner.entry_point();
+        self.execute_from(entry_point)
+    }
+
+    /// Run the program and return the result.
+    ///
+    /// # Errors
+    /// Returns an error if execution fails or the runtime reports a failure.
+    pub fn run(&mut self) -> Result<RunResult, RunError> {
+        self.run_timed(None, false)
+    }
+
+    /// Run multiple times.
+    ///
+    /// The library stays loaded across runs; guest memory and state are
+    /// re-initialized for each, memory with
+    /// [`reset_memory_fast`](Self::reset_memory_fast). Only the first result
+    /// includes load time.
+    ///
+    /// # Errors
+    /// Returns an error if execution fails or the runtime reports a failure.
+    pub fn run_multiple(&mut self, count: usize) -> Result<Vec<RunResult>, RunError> {
+        let fast_reset = count > 1;
+        (0..count)
+            .map(|_| self.run_timed(None, fast_reset))
+            .collect()
+    }
+
+    /// Run with hardware performance counters.
+    ///
+    /// # Errors
+    /// Returns an error if execution fails or the runtime reports a failure.
+    pub fn run_with_counters(&mut self) -> Result<RunResultWithPerf, RunError> {
+        let mut perf_group = crate::perf::PerfGroup::new();
+        let result = self.run_timed(perf_group.as_mut(), false)?;
+        let perf = perf_group.as_mut().and_then(crate::perf::PerfGroup::read);
+
+        crate::metrics::record_run("unknown", &result, perf.as_ref());
+
+        Ok(RunResultWithPerf { result, perf })
+    }
+
+    /// Take the load time for the first run's result.
+    pub(crate) fn take_load_secs(&mut self) -> f64 {
+        std::mem::take(&mut self.load_secs)
+    }
+
+    /// Run once from the entry point, timing each phase. `perf` counts the
+    /// execute phase only. With `fast_reset`, memory is reset with
+    /// [`reset_memory_fast`](Self::reset_memory_fast).
+    fn run_timed(
+        &mut self,
+        mut perf: Option<&mut crate::perf::PerfGroup>,
+        fast_reset: bool,
+    ) -> Result<RunResult, RunError> {
+        let load_secs = self.take_load_secs();
+
+        let start = Instant::now();
+        // Save target_instret before reset (reset() disables the suspender)
+        let saved_target = self.inner.get_target_instret();
+
+        if fast_reset {
+            self.reset_memory_fast();
+        } else {
+            self.inner.load_segments(self.segments.as_ref());
+            self.inner.reset();
+        }
+        self.setup_initial_regs();
+
+        // Restore target_instret if it was set
+        if let Some(target) = saved_target
+            && target != u64::MAX
+        {
+            self.inner.set_target_instret(target);
+        }
+        let entry_point = self.inner.entry_point();
+        let init_secs = start.elapsed().as_secs_f64();
+
+        trace!(entry_point = format!("{:#x}", entry_point), "executing");
+
+        if let Some(group) = perf.as_deref_mut() {
+            let _ = group.enable();
+        }
+        let start = Instant::now();
+        self.cancel.arm(self.inner.cancel_flag());
+        unsafe { (self.api.execute_from)(self.inner.as_void_ptr(), entry_point) };
+        let stopped = self.cancel.disarm();
+        let execute_secs = start.elapsed().as_secs_f64();
+        if let Some(group) = perf {
+            let _ = group.disable();
+        }
+        if self.cancel.take_request() && stopped && !self.inner.has_exited() {
+            return Err(RunError::Cancelled);
+        }
+
+        let start = Instant::now();
+        self.check_quarantine()?;
+        self.check_replay()?;
+        let instret = self.inner.instret();
+        let exit_code = self.inner.exit_code();
+        let exit_reason = self.exit_reason();
+        let teardown_secs = start.elapsed().as_secs_f64();
+
+        trace!(
+            instret = instret,
+            exit_code = exit_code,
+            exit_reason = %exit_reason,
+            init_secs = format!("{:.6}", init_secs),
+            execute_secs = format!("{:.6}", execute_secs),
+            "execution complete"
+        );
+
+        Ok(RunResult::new(
+            exit_code,
+            instret,
+            RunPhases {
+                load_secs,
+                init_secs,
+                execute_secs,
+                teardown_secs,
+            },
+        )
+        .with_exit_reason(exit_reason)
+        .with_resident_bytes(self.resident_bytes()))
+    }
+
+    /// Call a guest function by name with the given arguments.
+    /// Call an exported function by name.
+    ///
+    /// # Errors
+    /// Returns an error if the function cannot be resolved or execution fails.
+    pub fn call(&mut self, name: &str, args: &[u64]) -> Result<u64, RunError> {
+        let addr = self
+            .lookup_symbol(name)
+            .ok_or_else(|| RunError::FunctionNotFound(name.to_string()))?;
+        self.call_addr(addr, args)
+    }
+
+    /// Call a guest function by address with the given arguments.
+    /// Call an exported function by address.
+    ///
+    /// # Errors
+    /// Returns an error if execution fails.
+    pub fn call_addr(&mut self, addr: u64, args: &[u64]) -> Result<u64, RunError> {
+        if args.len() > 8 {
+            return Err(RunError::TracerSetupFailed(
+                "too many arguments (max 8)".to_string(),
+            ));
+        }
+
+        self.inner.load_segments(self.segments.as_ref());
+        self.inner.reset();
+
+        // Set up arguments in a0-a7 (registers 10-17)
+        for (i, &arg) in args.iter().enumerate() {
+            self.inner.set_register(10 + i, arg);
+        }
+
+        // Return to the library's trampoline if it has one; otherwise ra = 0
+        // traps when the function returns
+        self.inner
+            .set_register(1, self.api.call_return_pc.unwrap_or(0));
+
+        debug!(addr = format!("{:#x}", addr), "calling guest function");
+        unsafe { (self.api.execute_from)(self.inner.as_void_ptr(), addr) };
+
+        Ok(self.inner.get_register(10))
+    }
+
+    /// Reset to the entry state for calling a function from the host:
+    /// segments reloaded, `gp`/`sp` as at program entry, and `ra` at the
+    /// library's return trampoline.
+    ///
+    /// Returns the trampoline PC, where execution stops once the function
+    /// returns, or `None` if the library has no trampoline (compiled
+    /// without `--export-functions`).
+    pub fn prepare_call(&mut self) -> Option<u64> {
+        let return_pc = self.api.call_return_pc?;
+        self.inner.load_segments(self.segments.as_ref());
+        self.inner.reset();
+        self.setup_initial_regs();
+        self.inner.set_register(REG_RA as usize, return_pc);
+        Some(return_pc)
+    }
+
+    /// Run multiple times with hardware performance counters.
+    ///
+    /// Times and phases are averaged over the runs; the counters cover the
+    /// last run.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from perf counter setup or execution.
+    pub fn run_multiple_with_counters(
+        &mut self,
+        count: usize,
+    ) -> Result<RunResultWithPerf, RunError> {
+        let mut perf_group = crate::perf::PerfGroup::new();
+        let mut results = Vec::with_capacity(count);
+
+        for _ in 0..count {
+            if let Some(ref mut group) = perf_group {
+                let _ = group.reset();
+            }
+            results.push(self.run_timed(perf_group.as_mut(), count > 1)?);
+        }
+
+        let result = RunResult::average(&results)
+            .unwrap_or_else(|| RunResult::new(0, 0, RunPhases::default()));
+        let perf = perf_group.as_mut().and_then(crate::perf::PerfGroup::read);
+
+        crate::metrics::record_run("unknown", &result, perf.as_ref());
+
+        Ok(RunResultWithPerf { result, perf })
+    }
+}
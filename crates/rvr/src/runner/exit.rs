@@ -0,0 +1,253 @@
+//! Why a run stopped, decoded from the exit fields of `RvState`.
+
+use std::fmt;
+
+use rvr_emit::ExitCause;
+use serde::{Deserialize, Serialize};
+
+/// Cause of a guest trap.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TrapCause {
+    /// Illegal or unsupported instruction.
+    IllegalInstruction,
+    /// Jump to an address with no recompiled block.
+    InvalidTarget,
+    /// Store into recompiled code.
+    CodeWrite,
+    /// Store into the stack guard.
+    StackOverflow,
+    /// Load or store outside guest memory.
+    MemoryFault,
+    /// Dynamic jump to a target without a recompiled block.
+    UnresolvedJump,
+}
+
+impl fmt::Display for TrapCause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::IllegalInstruction => "illegal instruction",
+            Self::InvalidTarget => "invalid jump target",
+            Self::CodeWrite => "write to recompiled code",
+            Self::StackOverflow => "stack overflow",
+            Self::MemoryFault => "memory access out of bounds",
+            Self::UnresolvedJump => "unresolved dynamic jump",
+        })
+    }
+}
+
+/// Why execution stopped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExitReason {
+    /// The guest exited with this code.
+    Exited(u8),
+    /// The guest trapped at `pc`. `addr` is the store address or jump
+    /// target of the trap, or 0 if it has none or it is unknown.
+    Trapped {
+        cause: TrapCause,
+        pc: u64,
+        addr: u64,
+    },
+    /// Execution suspended at an instret target and can be resumed.
+    Suspended { instret: u64 },
+    /// A riscv-tests style HTIF exit reported a failure in test `test_num`.
+    HtifFail { test_num: u64 },
+    /// The host runtime stopped the guest on a request it cannot serve.
+    HostStop,
+    /// An HTIF guest polled `fromhost` at `pc` past the watchdog limit
+    /// without it changing. `syscall` is the last HTIF syscall it requested.
+    HtifStall {
+        pc: u64,
+        tohost: u64,
+        fromhost: u64,
+        syscall: Option<u64>,
+    },
+    /// Registers at the entry of the block at `pc` differ from golden trace
+    /// point `index` of the embedded golden trace.
+    GoldenMismatch { pc: u64, index: u64 },
+}
+
+impl ExitReason {
+    /// Decode the exit fields of a stopped guest.
+    ///
+    /// A run that stopped without `exited` set was suspended at `instret`.
+    /// `fromhost` and `syscall` of an HTIF stall are not in the exit fields
+    /// and decode as 0 and `None`.
+    #[must_use]
+    pub fn decode(
+        exited: bool,
+        exit_code: u8,
+        cause: u32,
+        info: u64,
+        pc: u64,
+        instret: u64,
+    ) -> Self {
+        let trapped = |cause| Self::Trapped {
+            cause,
+            pc,
+            addr: info,
+        };
+        match ExitCause::from_raw(cause) {
+            Some(ExitCause::Htif) if info != 0 => Self::HtifFail { test_num: info },
+            Some(ExitCause::HostStop) => Self::HostStop,
+            Some(ExitCause::IllegalInstruction) => trapped(TrapCause::IllegalInstruction),
+            Some(ExitCause::InvalidTarget) => trapped(TrapCause::InvalidTarget),
+            Some(ExitCause::CodeWrite) => trapped(TrapCause::CodeWrite),
+            Some(ExitCause::StackOverflow) => trapped(TrapCause::StackOverflow),
+            Some(ExitCause::MemoryFault) => trapped(TrapCause::MemoryFault),
+            Some(ExitCause::UnresolvedJump) => trapped(TrapCause::UnresolvedJump),
+            Some(ExitCause::HtifStall) => Self::HtifStall {
+                pc,
+                tohost: info,
+                fromhost: 0,
+                syscall: None,
+            },
+            Some(ExitCause::GoldenMismatch) => Self::GoldenMismatch { pc, index: info },
+            _ if !exited => Self::Suspended { instret },
+            _ => Self::Exited(exit_code),
+        }
+    }
+
+    /// True for a guest exit with code 0 (including an HTIF pass) or a
+    /// suspension, the outcomes that are not failures.
+    #[must_use]
+    pub const fn is_success(&self) -> bool {
+        matches!(self, Self::Exited(0) | Self::Suspended { .. })
+    }
+
+    /// The process exit code this reason maps to (1 for traps, host stops,
+    /// HTIF stalls and golden mismatches, the low byte of the test number
+    /// for HTIF failures).
+    #[must_use]
+    pub const fn exit_code(&self) -> u8 {
+        match *self {
+            Self::Exited(code) => code,
+            Self::Suspended { .. } => 0,
+            Self::HtifFail { test_num } => test_num.to_le_bytes()[0],
+            Self::Trapped { .. }
+            | Self::HostStop
+            | Self::HtifStall { .. }
+            | Self::GoldenMismatch { .. } => 1,
+        }
+    }
+}
+
+impl fmt::Display for ExitReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Exited(code) => write!(f, "exited with code {code}"),
+            Self::Trapped { cause, pc, addr } if *addr != 0 => {
+                write!(f, "trapped at pc {pc:#x}: {cause} (address {addr:#x})")
+            }
+            Self::Trapped { cause, pc, .. } => write!(f, "trapped at pc {pc:#x}: {cause}"),
+            Self::Suspended { instret } => write!(f, "suspended at instret {instret}"),
+            Self::HtifFail { test_num } => write!(f, "HTIF test {test_num} failed"),
+            Self::HostStop => f.write_str("stopped by the host"),
+            Self::HtifStall {
+                pc,
+                tohost,
+                fromhost,
+                syscall,
+            } => {
+                write!(
+                    f,
+                    "HTIF handshake stalled: tohost={tohost:#x}, last syscall="
+                )?;
+                match syscall {
+                    Some(num) => write!(f, "{num}")?,
+                    None => f.write_str("none")?,
+                }
+                write!(f, " (fromhost={fromhost:#x}, polling at pc {pc:#x})")
+            }
+            Self::GoldenMismatch { pc, index } => write!(
+                f,
+                "diverged from the golden trace at block {pc:#x} (trace point {index})"
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PC: u64 = 0x1000;
+    const INSTRET: u64 = 7;
+
+    #[test]
+    fn test_decode_guest_exit() {
+        let reason = ExitReason::decode(true, 3, ExitCause::Guest as u32, 0, PC, INSTRET);
+        assert_eq!(reason, ExitReason::Exited(3));
+    }
+
+    #[test]
+    fn test_decode_htif() {
+        let pass = ExitReason::decode(true, 0, ExitCause::Htif as u32, 0, PC, INSTRET);
+        assert_eq!(pass, ExitReason::Exited(0));
+        let fail = ExitReason::decode(true, 5, ExitCause::Htif as u32, 5, PC, INSTRET);
+        assert_eq!(fail, ExitReason::HtifFail { test_num: 5 });
+        assert_eq!(fail.exit_code(), 5);
+    }
+
+    #[test]
+    fn test_decode_trap() {
+        let addr = 0x2000;
+        let reason = ExitReason::decode(true, 1, ExitCause::CodeWrite as u32, addr, PC, INSTRET);
+        assert_eq!(
+            reason,
+            ExitReason::Trapped {
+                cause: TrapCause::CodeWrite,
+                pc: PC,
+                addr,
+            }
+        );
+        assert_eq!(
+            reason.to_string(),
+            "trapped at pc 0x1000: write to recompiled code (address 0x2000)"
+        );
+    }
+
+    #[test]
+    fn test_decode_htif_stall() {
+        let tohost = 0x8000_2000;
+        let reason = ExitReason::decode(true, 1, ExitCause::HtifStall as u32, tohost, PC, INSTRET);
+        assert_eq!(
+            reason,
+            ExitReason::HtifStall {
+                pc: PC,
+                tohost,
+                fromhost: 0,
+                syscall: None,
+            }
+        );
+        assert_eq!(reason.exit_code(), 1);
+        let reported = ExitReason::HtifStall {
+            pc: PC,
+            tohost,
+            fromhost: 0,
+            syscall: Some(64),
+        };
+        assert_eq!(
+            reported.to_string(),
+            "HTIF handshake stalled: tohost=0x80002000, last syscall=64 \
+             (fromhost=0x0, polling at pc 0x1000)"
+        );
+    }
+
+    #[test]
+    fn test_decode_golden_mismatch() {
+        let reason = ExitReason::decode(true, 1, ExitCause::GoldenMismatch as u32, 42, PC, INSTRET);
+        assert_eq!(reason, ExitReason::GoldenMismatch { pc: PC, index: 42 });
+        assert_eq!(reason.exit_code(), 1);
+        assert_eq!(
+            reason.to_string(),
+            "diverged from the golden trace at block 0x1000 (trace point 42)"
+        );
+    }
+
+    #[test]
+    fn test_decode_suspended() {
+        let reason = ExitReason::decode(false, 0, ExitCause::Guest as u32, 0, PC, INSTRET);
+        assert_eq!(reason, ExitReason::Suspended { instret: INSTRET });
+        assert!(reason.is_success());
+    }
+}
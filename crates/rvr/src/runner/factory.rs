@@ -0,0 +1,164 @@
+//! Selection of the [`RunnerImpl`] for a library's tracer and register file.
+
+use rvr_elf::ElfImage;
+use rvr_ir::Xlen;
+use rvr_state::{GoldenTracer, GuardedMemory, NUM_REGS_E, NUM_REGS_I};
+
+use super::block_profile::BlockProfileRunner;
+use super::buffered_diff::BufferedDiffRunner;
+use super::coverage::CoverageRunner;
+use super::debug::DebugRunner;
+use super::diff::DiffRunner;
+use super::fixed::FixedAddrRunner;
+use super::load::library_is_rve;
+use super::page_access::PageAccessRunner;
+use super::preflight::PreflightRunner;
+use super::stats::StatsRunner;
+use super::suspend::SuspendRunner;
+use super::typed::TypedRunner;
+use super::{
+    FixedAddresses, InstretMode, PageBitmapSize, ProfileSlots, RunError, RunnerImpl, TracerBuffers,
+    TracerKind,
+};
+
+/// Create runner implementation with fixed addresses for state and memory.
+pub(super) fn create_fixed_addr_runner<X: Xlen + 'static>(
+    image: ElfImage<X>,
+    num_regs: Option<u32>,
+    fixed: FixedAddresses,
+    memory_size: usize,
+) -> Result<Box<dyn RunnerImpl>, RunError> {
+    if library_is_rve(num_regs, &image) {
+        Ok(Box::new(FixedAddrRunner::<X, NUM_REGS_E>::new(
+            image,
+            fixed,
+            memory_size,
+        )?))
+    } else {
+        Ok(Box::new(FixedAddrRunner::<X, NUM_REGS_I>::new(
+            image,
+            fixed,
+            memory_size,
+        )?))
+    }
+}
+
+/// Create page access runner (bitmaps sized by the compiled library).
+fn create_page_access_runner<X: Xlen + 'static>(
+    image: ElfImage<X>,
+    is_rve: bool,
+    memory: GuardedMemory,
+    bitmaps: PageBitmapSize,
+) -> Box<dyn RunnerImpl> {
+    if is_rve {
+        Box::new(PageAccessRunner::<X, NUM_REGS_E>::new(
+            image, memory, bitmaps,
+        ))
+    } else {
+        Box::new(PageAccessRunner::<X, NUM_REGS_I>::new(
+            image, memory, bitmaps,
+        ))
+    }
+}
+
+/// Create block profile runner (counters sized by the compiled library).
+fn create_block_profile_runner<X: Xlen + 'static>(
+    image: ElfImage<X>,
+    is_rve: bool,
+    memory: GuardedMemory,
+    slots: ProfileSlots,
+) -> Box<dyn RunnerImpl> {
+    if is_rve {
+        Box::new(BlockProfileRunner::<X, NUM_REGS_E>::new(
+            image, memory, slots,
+        ))
+    } else {
+        Box::new(BlockProfileRunner::<X, NUM_REGS_I>::new(
+            image, memory, slots,
+        ))
+    }
+}
+
+/// Create coverage runner (flags sized by the compiled library).
+fn create_coverage_runner<X: Xlen + 'static>(
+    image: ElfImage<X>,
+    is_rve: bool,
+    memory: GuardedMemory,
+    slots: ProfileSlots,
+) -> Box<dyn RunnerImpl> {
+    if is_rve {
+        Box::new(CoverageRunner::<X, NUM_REGS_E>::new(image, memory, slots))
+    } else {
+        Box::new(CoverageRunner::<X, NUM_REGS_I>::new(image, memory, slots))
+    }
+}
+
+/// Create runner implementation based on tracer and instret mode.
+pub(super) fn create_runner_impl<X: Xlen + 'static>(
+    image: ElfImage<X>,
+    num_regs: Option<u32>,
+    tracer_kind: TracerKind,
+    instret_mode: InstretMode,
+    tracer_buffers: TracerBuffers,
+    memory_size: usize,
+) -> Result<Box<dyn RunnerImpl>, RunError> {
+    let memory = GuardedMemory::reserve(memory_size)?;
+    let is_rve = library_is_rve(num_regs, &image);
+
+    // Buffer sizes are only exported by page access, block profile and coverage builds
+    match tracer_buffers {
+        TracerBuffers::PageBitmaps(bitmaps) => {
+            return Ok(create_page_access_runner(image, is_rve, memory, bitmaps));
+        }
+        TracerBuffers::BlockCounters(slots) => {
+            return Ok(create_block_profile_runner(image, is_rve, memory, slots));
+        }
+        TracerBuffers::CoverageFlags(slots) => {
+            return Ok(create_coverage_runner(image, is_rve, memory, slots));
+        }
+        TracerBuffers::None => {}
+    }
+
+    match (tracer_kind, is_rve) {
+        (TracerKind::Preflight, false) => Ok(Box::new(PreflightRunner::<X, NUM_REGS_I>::new(
+            image, memory,
+        ))),
+        (TracerKind::Preflight, true) => Ok(Box::new(PreflightRunner::<X, NUM_REGS_E>::new(
+            image, memory,
+        ))),
+        (TracerKind::Stats, false) => {
+            Ok(Box::new(StatsRunner::<X, NUM_REGS_I>::new(image, memory)))
+        }
+        (TracerKind::Stats, true) => Ok(Box::new(StatsRunner::<X, NUM_REGS_E>::new(image, memory))),
+        (TracerKind::Debug, false) => {
+            Ok(Box::new(DebugRunner::<X, NUM_REGS_I>::new(image, memory)))
+        }
+        (TracerKind::Debug, true) => Ok(Box::new(DebugRunner::<X, NUM_REGS_E>::new(image, memory))),
+        (TracerKind::Diff, false) => Ok(Box::new(DiffRunner::<X, NUM_REGS_I>::new(image, memory))),
+        (TracerKind::Diff, true) => Ok(Box::new(DiffRunner::<X, NUM_REGS_E>::new(image, memory))),
+        (TracerKind::BufferedDiff, false) => Ok(Box::new(
+            BufferedDiffRunner::<X, NUM_REGS_I>::new(image, memory),
+        )),
+        (TracerKind::BufferedDiff, true) => Ok(Box::new(BufferedDiffRunner::<X, NUM_REGS_E>::new(
+            image, memory,
+        ))),
+        (TracerKind::Golden, false) => Ok(Box::new(
+            TypedRunner::<X, GoldenTracer, NUM_REGS_I>::new(image, memory),
+        )),
+        (TracerKind::Golden, true) => Ok(Box::new(
+            TypedRunner::<X, GoldenTracer, NUM_REGS_E>::new(image, memory),
+        )),
+        (_, false) if instret_mode.is_suspend() => {
+            Ok(Box::new(SuspendRunner::<X, NUM_REGS_I>::new(image, memory)))
+        }
+        (_, true) if instret_mode.is_suspend() => {
+            Ok(Box::new(SuspendRunner::<X, NUM_REGS_E>::new(image, memory)))
+        }
+        (_, false) => Ok(Box::new(TypedRunner::<X, (), NUM_REGS_I>::new(
+            image, memory,
+        ))),
+        (_, true) => Ok(Box::new(TypedRunner::<X, (), NUM_REGS_E>::new(
+            image, memory,
+        ))),
+    }
+}
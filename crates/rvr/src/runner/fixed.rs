@@ -4,9 +4,11 @@ use std::ffi::c_void;
 
 use rvr_elf::ElfImage;
 use rvr_ir::Xlen;
-use rvr_state::{FixedMemory, GuardedMemory, RvState};
+use rvr_state::{FixedMemory, GuardedMemory, GuestIo, RvState};
 
-use super::{FixedAddresses, RunError, RunnerImpl};
+use crate::segment_image::SegmentImage;
+
+use super::{FixedAddresses, RunError, RunnerImpl, Snapshot, init_memory};
 
 /// Runner with state and memory allocated at fixed addresses.
 ///
@@ -67,13 +69,8 @@ impl<X: Xlen, const NUM_REGS: usize> FixedAddrRunner<X, NUM_REGS> {
 }
 
 impl<X: Xlen, const NUM_REGS: usize> RunnerImpl for FixedAddrRunner<X, NUM_REGS> {
-    fn load_segments(&mut self) {
-        self.memory.clear();
-        for seg in &self.elf_image.memory_segments {
-            let vaddr = usize::try_from(X::to_u64(seg.virtual_start))
-                .expect("segment address does not fit in host usize");
-            unsafe { self.memory.copy_from(vaddr, &seg.data) };
-        }
+    fn load_segments(&mut self, segments: Option<&SegmentImage>) {
+        init_memory(&mut self.memory, &self.elf_image, segments);
     }
 
     fn reset(&mut self) {
@@ -101,6 +98,26 @@ impl<X: Xlen, const NUM_REGS: usize> RunnerImpl for FixedAddrRunner<X, NUM_REGS>
         self.state().has_exited != 0
     }
 
+    fn is_trapped(&self) -> bool {
+        self.state().is_trapped()
+    }
+
+    fn exit_cause(&self) -> u32 {
+        self.state().exit_cause()
+    }
+
+    fn exit_info(&self) -> u64 {
+        self.state().exit_info()
+    }
+
+    fn htif_syscall(&self) -> u64 {
+        self.state().htif_syscall()
+    }
+
+    fn cancel_flag(&mut self) -> *mut u8 {
+        self.state_mut().cancel_flag()
+    }
+
     fn entry_point(&self) -> u64 {
         X::to_u64(self.elf_image.entry_point)
     }
@@ -131,12 +148,12 @@ impl<X: Xlen, const NUM_REGS: usize> RunnerImpl for FixedAddrRunner<X, NUM_REGS>
         self.state_mut().pc = X::from_u64(pc);
     }
 
-    fn get_csr(&self, _csr: u16) -> u64 {
-        0 // CSRs not supported in fixed-address runner
+    fn get_csr(&self, csr: u16) -> u64 {
+        X::to_u64(self.state().csrs[csr as usize])
     }
 
-    fn set_csr(&mut self, _csr: u16, _value: u64) {
-        // CSRs not supported in fixed-address runner
+    fn set_csr(&mut self, csr: u16, value: u64) {
+        self.state_mut().csrs[csr as usize] = X::from_u64(value);
     }
 
     fn read_memory(&self, addr: u64, buf: &mut [u8]) -> usize {
@@ -176,7 +193,30 @@ impl<X: Xlen, const NUM_REGS: usize> RunnerImpl for FixedAddrRunner<X, NUM_REGS>
         self.memory.size()
     }
 
+    fn resident_bytes(&self) -> Option<usize> {
+        self.memory.resident_bytes().ok()
+    }
+
+    fn memory_mut(&mut self) -> &mut GuardedMemory {
+        &mut self.memory
+    }
+
     fn clear_exit(&mut self) {
         self.state_mut().clear_exit();
     }
+
+    fn set_io(&mut self, io: *mut GuestIo) {
+        self.state_mut().set_io(io);
+    }
+
+    fn snapshot(&mut self) -> Result<Snapshot, RunError> {
+        let memory = self.memory.snapshot()?;
+        Ok(Snapshot::new(X::VALUE, self.state().capture(), memory))
+    }
+
+    fn restore(&mut self, snapshot: &Snapshot) -> Result<(), RunError> {
+        self.memory.restore(snapshot.memory())?;
+        self.state_mut().restore(snapshot.state());
+        Ok(())
+    }
 }
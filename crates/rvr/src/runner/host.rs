@@ -0,0 +1,188 @@
+//! Host-side guest configuration: stdio, CSR and syscall hooks, command
+//! line, environment and preopened directories.
+
+use std::io::{Read as IoRead, Write as IoWrite};
+use std::path::Path;
+
+use super::env;
+use super::io::HostHooks;
+use super::{CsrHook, HookFn, RunError, Runner, SyscallFn};
+
+impl Runner {
+    /// Feed guest reads from fd 0 from `reader` instead of the host's stdin.
+    ///
+    /// Each guest `read` makes a single `Read::read` call, so the guest sees
+    /// the same short reads `reader` returns, and 0 at EOF.
+    pub fn set_stdin(&mut self, reader: impl IoRead + Send + 'static) {
+        self.host_hooks().set_stdin(Box::new(reader));
+        self.install_hooks();
+    }
+
+    /// Send guest writes to fd 1 to `writer` instead of the host's stdout.
+    ///
+    /// `writer` is flushed after every guest `write`.
+    pub fn set_stdout(&mut self, writer: impl IoWrite + Send + 'static) {
+        self.host_hooks().set_stdout(Box::new(writer));
+        self.install_hooks();
+    }
+
+    /// Send guest writes to fd 2 to `writer` instead of the host's stderr.
+    ///
+    /// `writer` is flushed after every guest `write`.
+    pub fn set_stderr(&mut self, writer: impl IoWrite + Send + 'static) {
+        self.host_hooks().set_stderr(Box::new(writer));
+        self.install_hooks();
+    }
+
+    /// Serve guest accesses to custom CSRs compiled in `CsrMode::Hook` mode
+    /// with `hook`.
+    ///
+    /// Until a hook is set, those CSRs read and write their `RvState::csrs`
+    /// slot, like storage CSRs.
+    pub fn set_csr_hook(&mut self, hook: impl CsrHook + 'static) {
+        self.host_hooks().set_csr_hook(Box::new(hook));
+        self.install_hooks();
+    }
+
+    /// Serve guest syscall `num` with `handler`, replacing any earlier
+    /// handler and the built-in handling of `num`.
+    ///
+    /// Takes effect in libraries compiled with `SyscallMode::Linux`, whose
+    /// syscall shim checks the registered numbers before its own table, and
+    /// for bare-metal ECALLs mapped to `EcallAction::HostCall`.
+    /// The handler gets a0..a5 and guest memory through [`GuestContext`]
+    /// and returns the value for a0 (a negative errno on failure, by Linux
+    /// convention). Other syscalls keep their built-in behavior.
+    pub fn register_syscall(&mut self, num: u64, handler: SyscallFn) {
+        self.host_hooks().register_syscall(num, handler);
+        self.install_hooks();
+    }
+
+    /// Serve the guest function `name`, compiled with
+    /// `HookKind::HostCall`, with `handler`, replacing any earlier handler.
+    ///
+    /// The handler gets a0..a5 and guest memory through [`GuestContext`]
+    /// and sets the registers the guest gets back, e.g. the return value in
+    /// a0, with [`GuestContext::set_arg`]; the guest then returns to `ra`.
+    /// Calling a hooked function without a handler stops the guest
+    /// (`ExitCause::HostStop`, the function's entry in `exit_info`).
+    pub fn register_hook(&mut self, name: impl Into<String>, handler: HookFn) {
+        self.host_hooks().register_hook(name.into(), handler);
+        self.install_hooks();
+    }
+
+    /// Start the guest with command line `args` (`argv[0]` first).
+    ///
+    /// Each run puts `argc`, `argv`, the environment (see
+    /// [`with_env`](Self::with_env)) and an auxiliary vector at `sp` the
+    /// way Linux does, for guests whose startup code reads them (libraries
+    /// compiled with `SyscallMode::Linux`). Needs a stack top: a
+    /// `__stack_top` symbol, a layout profile or a planned stack.
+    #[must_use]
+    pub fn with_args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args = args.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Set guest environment variables, replacing earlier values of the
+    /// same keys (which must not contain `=`).
+    ///
+    /// Like [`with_args`](Self::with_args), each run lays out `envp` on the
+    /// initial stack, along with an `AT_RANDOM` auxv entry pointing at 16
+    /// fixed bytes, so `getenv` works in guests compiled with
+    /// `SyscallMode::Linux`. Without arguments, `argc` is 0.
+    #[must_use]
+    pub fn with_env<K: AsRef<str>, V: AsRef<str>>(mut self, vars: &[(K, V)]) -> Self {
+        for (key, value) in vars {
+            let (key, value) = (key.as_ref(), value.as_ref());
+            self.env
+                .retain(|var| var.split_once('=').is_none_or(|(k, _)| k != key));
+            self.env.push(format!("{key}={value}"));
+        }
+        self
+    }
+
+    /// Set the guest environment variables of dotenv file `path` like
+    /// [`with_env`](Self::with_env).
+    ///
+    /// Lines are `KEY=VALUE`, optionally after `export`, with `#` comments
+    /// and single- or double-quoted values.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or a line does not
+    /// parse.
+    pub fn with_env_file(self, path: impl AsRef<Path>) -> Result<Self, RunError> {
+        let path = path.as_ref();
+        let error = |reason: String| RunError::EnvFile {
+            path: path.display().to_string(),
+            reason,
+        };
+        let text = std::fs::read_to_string(path).map_err(|e| error(e.to_string()))?;
+        let vars = env::parse_dotenv(&text).map_err(error)?;
+        Ok(self.with_env(&vars))
+    }
+
+    /// Let the guest open files under `host_path` as guest directory
+    /// `guest_path` (an absolute path), WASI style.
+    ///
+    /// Preopened directories are the guest's fds 3, 4, ... in the order
+    /// they are added. Takes effect in libraries compiled with
+    /// `SyscallMode::Linux`: every guest `openat` is checked against the
+    /// preopens, and paths outside them (including through `..` or
+    /// symlinks) fail with `EPERM`. Without a preopen, guests cannot open
+    /// files at all.
+    ///
+    /// # Errors
+    /// Returns an error if `guest_path` is not absolute, if `host_path` is
+    /// not a directory or cannot be opened, or if the library's syscall
+    /// policy does not list `guest_path`.
+    pub fn with_preopened_dir(
+        mut self,
+        guest_path: impl AsRef<Path>,
+        host_path: impl AsRef<Path>,
+    ) -> Result<Self, RunError> {
+        let guest_path = guest_path.as_ref();
+        let host_path = host_path.as_ref();
+        let error = |reason: &str| RunError::Preopen {
+            guest_path: guest_path.display().to_string(),
+            reason: reason.to_string(),
+        };
+        if !guest_path.is_absolute() {
+            return Err(error("guest path is not absolute"));
+        }
+        if !host_path.is_dir() {
+            return Err(error(&format!(
+                "{} is not a directory",
+                host_path.display()
+            )));
+        }
+        if let Some(allowed) = &self.preopen_paths
+            && !allowed.iter().any(|path| Path::new(path) == guest_path)
+        {
+            return Err(error("not a preopen of the library's syscall policy"));
+        }
+        self.host_hooks()
+            .preopen(guest_path.to_path_buf(), host_path)
+            .map_err(|e| error(&format!("cannot open {}: {e}", host_path.display())))?;
+        self.install_hooks();
+        Ok(self)
+    }
+
+    pub(super) fn host_hooks(&mut self) -> &mut HostHooks {
+        self.hooks.get_or_insert_with(HostHooks::new)
+    }
+
+    pub(super) fn install_hooks(&mut self) {
+        if let Some(hooks) = &mut self.hooks {
+            let mut table = hooks.table();
+            if let Some(journal) = &mut self.journal {
+                table = journal.wrap(table);
+            }
+            self.inner.set_io(table);
+        }
+    }
+}
@@ -0,0 +1,165 @@
+//! Host buffers shared with the guest.
+//!
+//! [`Runner::map_host_buffer`] maps a [`HostBuffer`] over guest memory, so the
+//! guest reads host input in place instead of the host copying it in through
+//! [`Runner::write_memory`]. Host and guest agree on the address out of band:
+//! a constant, a custom syscall or a CSR hook.
+
+use rvr_emit::AddrRange;
+use rvr_state::{HostBuffer, page_size};
+use tracing::debug;
+
+use super::{RunError, Runner};
+
+impl Runner {
+    /// Map `buffer` into guest memory at `guest_addr`.
+    ///
+    /// The guest reads the buffer's pages without a copy: host writes to the
+    /// buffer are visible to the guest and, if `writable`, guest writes show
+    /// up in the buffer. A guest store to a read-only buffer crashes the
+    /// process like a store to a guard page. The mapping stays in place
+    /// across runs, snapshots and restores until
+    /// [`unmap_host_buffer`](Self::unmap_host_buffer) or until the runner is
+    /// dropped.
+    ///
+    /// The range must not overlap the ELF's segments or another buffer, nor
+    /// the stack, heap or mmap arena when the library was compiled with a
+    /// layout or heap and stack sizes. Without those the heap and stack are
+    /// unbounded, and keeping them clear of the buffer is up to the guest.
+    ///
+    /// # Errors
+    /// Returns an error if `guest_addr` or the buffer length is not a
+    /// multiple of the host page size, if the range is outside guest memory
+    /// or overlaps one of the regions above, or if the mapping fails.
+    pub fn map_host_buffer(
+        &mut self,
+        guest_addr: u64,
+        buffer: &HostBuffer,
+        writable: bool,
+    ) -> Result<(), RunError> {
+        let len = buffer.len();
+        let error = |reason: String| RunError::HostBuffer {
+            addr: guest_addr,
+            reason,
+        };
+        let page = page_size();
+        if !guest_addr.is_multiple_of(page as u64) || !len.is_multiple_of(page) {
+            return Err(error(format!(
+                "address and length ({len:#x} bytes) must be multiples of the {page:#x}-byte page size"
+            )));
+        }
+        let range = AddrRange::new(guest_addr, guest_addr.saturating_add(len as u64));
+        let memory_size = self.inner.memory_size();
+        let offset = usize::try_from(guest_addr)
+            .ok()
+            .filter(|_| range.end <= memory_size as u64)
+            .ok_or_else(|| {
+                error(format!(
+                    "{len:#x} bytes do not fit in guest memory of size {memory_size:#x}"
+                ))
+            })?;
+        if let Some((name, region)) = self.overlapped_region(&range) {
+            return Err(error(format!("{range} overlaps the {name} at {region}")));
+        }
+
+        self.inner
+            .memory_mut()
+            .map_shared(offset, buffer, writable)
+            .map_err(|err| error(err.to_string()))?;
+        self.host_buffers.push((range, writable));
+        debug!(
+            addr = format!("{guest_addr:#x}"),
+            len, writable, "mapped host buffer"
+        );
+        Ok(())
+    }
+
+    /// Unmap the host buffer mapped at `guest_addr`, leaving zeroed guest
+    /// memory in its place.
+    ///
+    /// # Errors
+    /// Returns an error if no buffer is mapped at `guest_addr` or guest memory
+    /// cannot be remapped.
+    pub fn unmap_host_buffer(&mut self, guest_addr: u64) -> Result<(), RunError> {
+        let error = |reason: String| RunError::HostBuffer {
+            addr: guest_addr,
+            reason,
+        };
+        let idx = self
+            .host_buffers
+            .iter()
+            .position(|(range, _)| range.start == guest_addr)
+            .ok_or_else(|| error("no buffer is mapped there".to_string()))?;
+        let offset = usize::try_from(guest_addr).map_err(|err| error(err.to_string()))?;
+        self.inner
+            .memory_mut()
+            .unmap_shared(offset)
+            .map_err(|err| error(err.to_string()))?;
+        self.host_buffers.remove(idx);
+        Ok(())
+    }
+
+    /// Map the host buffer `old` has at `range` into this runner's memory
+    /// (see [`reload`](Self::reload)), if it stays clear of this runner's
+    /// regions.
+    pub(super) fn carry_host_buffer(
+        &mut self,
+        old: &mut Self,
+        range: AddrRange,
+    ) -> Result<(), RunError> {
+        let error = |reason: String| RunError::HostBuffer {
+            addr: range.start,
+            reason,
+        };
+        if let Some((name, region)) = self.overlapped_region(&range) {
+            return Err(error(format!("{range} overlaps the {name} at {region}")));
+        }
+        let offset = usize::try_from(range.start).map_err(|err| error(err.to_string()))?;
+        self.inner
+            .memory_mut()
+            .map_shared_from(old.inner.memory_mut(), offset)
+            .map_err(|err| error(err.to_string()))
+    }
+
+    /// The first region a host buffer at `range` would overlap.
+    fn overlapped_region(&self, range: &AddrRange) -> Option<(&'static str, AddrRange)> {
+        self.reserved_regions()
+            .into_iter()
+            .find(|(_, region)| region.start < region.end && region.gap_to(range).is_none())
+    }
+
+    /// Guest regions a host buffer must stay clear of.
+    fn reserved_regions(&self) -> Vec<(&'static str, AddrRange)> {
+        let mut regions: Vec<_> = self
+            .image_segments
+            .iter()
+            .map(|&range| ("segment", range))
+            .collect();
+        regions.extend(
+            self.host_buffers
+                .iter()
+                .map(|&(range, _)| ("host buffer", range)),
+        );
+        if let Some(layout) = &self.layout {
+            regions.extend([("stack", layout.stack), ("heap", layout.heap)]);
+        }
+        if let Some(layout) = &self.memory_layout {
+            regions.extend([("stack", layout.stack), ("heap", layout.heap)]);
+            regions.extend(layout.mmap.map(|range| ("mmap arena", range)));
+            regions.extend(layout.stack_guard.map(|range| ("stack guard", range)));
+        }
+        regions
+    }
+
+    /// Bytes of a `len`-byte write at `addr` before the first read-only host
+    /// buffer it would reach.
+    pub(super) fn writable_len(&self, addr: u64, len: usize) -> usize {
+        let end = addr.saturating_add(len as u64);
+        self.host_buffers
+            .iter()
+            .filter(|&&(range, writable)| !writable && range.start < end && addr < range.end)
+            .map(|(range, _)| range.start.saturating_sub(addr))
+            .min()
+            .map_or(len, |before| usize::try_from(before).unwrap_or(len))
+    }
+}
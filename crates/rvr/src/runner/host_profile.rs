@@ -0,0 +1,137 @@
+//! Host cost sampling: the guest suspends at a fixed instret interval and
+//! each interval's host cost is recorded with the PC it ended at.
+//!
+//! The guest PC is only stored to `RvState` when a block exits or suspends,
+//! so sampling it from another thread would see stale values. Suspending
+//! costs a dispatch per interval, and an interval's whole cost goes to the
+//! PC it ended at: code that runs for at least an interval at a time is
+//! charged its own cost, while functions alternating within one interval
+//! share their cost by where the intervals happen to end.
+
+use std::time::Instant;
+
+use tracing::trace;
+
+use crate::host_profile::{HostCostUnit, HostSample, HostSamples};
+use crate::perf::PerfGroup;
+
+use super::{RunError, RunPhases, RunResult, Runner};
+
+/// Host cost so far: cycles from perf counters when they can be opened,
+/// else wall-clock nanoseconds.
+struct HostClock {
+    perf: Option<PerfGroup>,
+    start: Instant,
+}
+
+impl HostClock {
+    fn start() -> Self {
+        let perf = PerfGroup::new().and_then(|mut group| group.enable().ok().map(|()| group));
+        Self {
+            perf,
+            start: Instant::now(),
+        }
+    }
+
+    const fn unit(&self) -> HostCostUnit {
+        if self.perf.is_some() {
+            HostCostUnit::Cycles
+        } else {
+            HostCostUnit::Nanos
+        }
+    }
+
+    fn now(&mut self) -> u64 {
+        match &mut self.perf {
+            Some(group) => group
+                .read()
+                .and_then(|counters| counters.cycles)
+                .unwrap_or(0),
+            None => u64::try_from(self.start.elapsed().as_nanos()).unwrap_or(u64::MAX),
+        }
+    }
+}
+
+impl Runner {
+    /// Run once from the entry point, suspending every `interval` guest
+    /// instructions to sample the host cost of the interval and the guest
+    /// PC it ended at (see [`crate::HostProfile`] for attributing them).
+    ///
+    /// A target set with [`set_target_instret`](Self::set_target_instret)
+    /// still ends the run. Costs are host cycles when perf counters are
+    /// available, else nanoseconds.
+    ///
+    /// # Errors
+    /// Returns `SuspendRequired` if the library was not compiled with
+    /// `--instret suspend`, or an error if execution fails.
+    pub fn run_host_profile(
+        &mut self,
+        interval: u64,
+    ) -> Result<(RunResult, HostSamples), RunError> {
+        if !self.supports_suspend() {
+            return Err(RunError::SuspendRequired("host profiling"));
+        }
+        let load_secs = self.take_load_secs();
+
+        let start = Instant::now();
+        let limit = self
+            .inner
+            .get_target_instret()
+            .filter(|&target| target != u64::MAX);
+        self.inner.load_segments(self.segments.as_ref());
+        self.inner.reset();
+        self.setup_initial_regs();
+        let mut pc = self.inner.entry_point();
+        let init_secs = start.elapsed().as_secs_f64();
+
+        let interval = interval.max(1);
+        let mut samples = Vec::new();
+        let mut clock = HostClock::start();
+        let start = Instant::now();
+        loop {
+            let before = self.inner.instret();
+            let target = before.saturating_add(interval);
+            self.inner
+                .set_target_instret(limit.map_or(target, |limit| target.min(limit)));
+            let cost = clock.now();
+            unsafe { (self.api.execute_from)(self.inner.as_void_ptr(), pc) };
+            let cost = clock.now().saturating_sub(cost);
+            pc = self.inner.get_pc();
+            let instret = self.inner.instret();
+            samples.push(HostSample {
+                pc,
+                instructions: instret - before,
+                cost,
+            });
+            if self.inner.has_exited() || limit.is_some_and(|limit| instret >= limit) {
+                break;
+            }
+        }
+        let execute_secs = start.elapsed().as_secs_f64();
+        self.inner.set_target_instret(limit.unwrap_or(u64::MAX));
+
+        let start = Instant::now();
+        self.check_quarantine()?;
+        let instret = self.inner.instret();
+        let exit_code = self.inner.exit_code();
+        let teardown_secs = start.elapsed().as_secs_f64();
+        trace!(samples = samples.len(), instret, "host profile complete");
+
+        let result = RunResult::new(
+            exit_code,
+            instret,
+            RunPhases {
+                load_secs,
+                init_secs,
+                execute_secs,
+                teardown_secs,
+            },
+        )
+        .with_resident_bytes(self.resident_bytes());
+        let samples = HostSamples {
+            unit: clock.unit(),
+            samples,
+        };
+        Ok((result, samples))
+    }
+}
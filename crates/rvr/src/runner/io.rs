@@ -0,0 +1,579 @@
+//! Guest stdio redirection, preopened files, custom CSR hooks, host
+//! syscalls and function hooks.
+//!
+//! [`HostHooks`] backs the `RvIo` hook table the generated `rv_sys_read`,
+//! `rv_sys_write`, `rv_sys_openat`, `rv_sys_close`, `rv_sys_lseek`,
+//! `rv_sys_fstat`, `rv_sys_fstatat`, `rv_csr_read`,
+//! `rv_csr_write`, `rv_host_syscall` and `rv_hook_call` call into. Streams
+//! that were not redirected fall back to the host process's own stdio,
+//! guests cannot open files until a directory is preopened, hook CSRs fall
+//! back to their `RvState::csrs` slot until a [`CsrHook`] is set, syscalls
+//! without a registered handler keep their built-in behavior, and hooked
+//! functions without a registered handler stop the guest.
+
+use std::collections::BTreeMap;
+use std::ffi::{CStr, c_char, c_void};
+use std::io::{self, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+
+use rvr_state::{GuestIo, GuestStat};
+
+use super::preopen::{EPERM, GuestFiles};
+
+/// Host side of custom CSRs compiled in `CsrMode::Hook` mode.
+///
+/// Each guest CSR instruction makes at most one `read` and one `write`
+/// call, in that order. Both default to a CSR that reads as 0 and ignores
+/// writes.
+pub trait CsrHook: Send {
+    /// Value the guest reads from `csr`.
+    fn read(&mut self, _csr: u16) -> u64 {
+        0
+    }
+
+    /// Called when the guest writes `value` to `csr`. RV32 values are
+    /// zero-extended.
+    fn write(&mut self, _csr: u16, _value: u64) {}
+}
+
+/// Handler for a host syscall, returning the value for a0.
+pub type SyscallFn = Box<dyn FnMut(&mut GuestContext<'_>) -> i64>;
+
+/// Handler for a guest function hooked with `HookKind::HostCall`; returns
+/// values through [`GuestContext::set_arg`].
+pub type HookFn = Box<dyn FnMut(&mut GuestContext<'_>)>;
+
+/// Arguments and guest memory of a host syscall or function hook.
+///
+/// Memory accessors are bounds-checked against guest memory and return
+/// `None` (or `false`) for ranges outside it.
+pub struct GuestContext<'a> {
+    num: u64,
+    args: [u64; 6],
+    memory: &'a mut [u8],
+}
+
+impl<'a> GuestContext<'a> {
+    /// Context of syscall `num` over guest `memory`.
+    pub(super) const fn new(num: u64, args: [u64; 6], memory: &'a mut [u8]) -> Self {
+        Self { num, args, memory }
+    }
+
+    /// Syscall number (0 for a function hook).
+    #[must_use]
+    pub const fn num(&self) -> u64 {
+        self.num
+    }
+
+    /// Argument register a`index` (0..6); RV32 values are zero-extended.
+    ///
+    /// # Panics
+    /// Panics if `index` is 6 or more.
+    #[must_use]
+    pub const fn arg(&self, index: usize) -> u64 {
+        self.args[index]
+    }
+
+    /// Set argument register a`index` (0..6). A function hook's a0..a5 go
+    /// back to the guest (a0 and a1 hold the return value); a syscall
+    /// handler's are ignored.
+    ///
+    /// # Panics
+    /// Panics if `index` is 6 or more.
+    pub const fn set_arg(&mut self, index: usize, value: u64) {
+        self.args[index] = value;
+    }
+
+    /// Guest memory `[addr, addr + len)`.
+    #[must_use]
+    pub fn memory(&self, addr: u64, len: u64) -> Option<&[u8]> {
+        let range = self.range(addr, len)?;
+        Some(&self.memory[range])
+    }
+
+    /// Guest memory `[addr, addr + len)`, writable.
+    #[must_use]
+    pub fn memory_mut(&mut self, addr: u64, len: u64) -> Option<&mut [u8]> {
+        let range = self.range(addr, len)?;
+        Some(&mut self.memory[range])
+    }
+
+    /// Copy `data` to guest memory at `addr`; writes nothing and returns
+    /// `false` if it does not fit.
+    pub fn write(&mut self, addr: u64, data: &[u8]) -> bool {
+        self.memory_mut(addr, data.len() as u64)
+            .map(|dst| dst.copy_from_slice(data))
+            .is_some()
+    }
+
+    fn range(&self, addr: u64, len: u64) -> Option<std::ops::Range<usize>> {
+        let start = usize::try_from(addr).ok()?;
+        let end = start.checked_add(usize::try_from(len).ok()?)?;
+        (end <= self.memory.len()).then_some(start..end)
+    }
+}
+
+/// `ENOSYS`, returned for a host syscall without a handler.
+const ENOSYS: i64 = 38;
+/// Returned to `rv_hook_call` for a function hook without a handler.
+const NO_HOOK: i32 = 1;
+/// `EIO`, returned when a host stream fails without an OS error code.
+const EIO: i32 = 5;
+/// `EBADF`, returned for fds that are neither stdio nor open files.
+const EBADF: i32 = 9;
+
+/// Host streams backing guest fds 0, 1 and 2, and the files behind the
+/// fds above them.
+#[derive(Default)]
+struct GuestStreams {
+    stdin: Option<Box<dyn Read + Send>>,
+    stdout: Option<Box<dyn Write + Send>>,
+    stderr: Option<Box<dyn Write + Send>>,
+    files: GuestFiles,
+}
+
+impl GuestStreams {
+    /// Read once from the stream behind `fd`; may return fewer bytes than
+    /// `buf` holds, and 0 at EOF.
+    fn read(&mut self, fd: u32, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let result = match (fd, &mut self.stdin) {
+                (0, Some(reader)) => reader.read(buf),
+                (0, None) => io::stdin().read(buf),
+                (1 | 2, _) => return Err(io::Error::from_raw_os_error(EBADF)),
+                _ => self.files.read(fd, buf),
+            };
+            match result {
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                result => return result,
+            }
+        }
+    }
+
+    /// Write all of `buf` to the stream behind `fd` and flush it.
+    fn write(&mut self, fd: u32, buf: &[u8]) -> io::Result<()> {
+        let redirect = match fd {
+            1 => &mut self.stdout,
+            2 => &mut self.stderr,
+            0 => return Err(io::Error::from_raw_os_error(EBADF)),
+            _ => return self.files.write(fd, buf),
+        };
+        match redirect {
+            Some(out) => write_flush(out, buf),
+            None if fd == 1 => write_flush(&mut io::stdout(), buf),
+            None => write_flush(&mut io::stderr(), buf),
+        }
+    }
+}
+
+fn write_flush(out: &mut (impl Write + ?Sized), buf: &[u8]) -> io::Result<()> {
+    out.write_all(buf)?;
+    out.flush()
+}
+
+/// Negative errno for `err`, as returned to the guest.
+fn errno(err: &io::Error) -> i64 {
+    -i64::from(err.raw_os_error().unwrap_or(EIO))
+}
+
+unsafe extern "C" fn guest_read(ctx: *mut c_void, fd: u32, buf: *mut u8, len: usize) -> i64 {
+    let streams = unsafe { &mut *ctx.cast::<GuestStreams>() };
+    let buf = unsafe { std::slice::from_raw_parts_mut(buf, len) };
+    streams
+        .read(fd, buf)
+        .map_or_else(|err| errno(&err), |n| i64::try_from(n).unwrap_or(i64::MAX))
+}
+
+unsafe extern "C" fn guest_open(
+    ctx: *mut c_void,
+    dirfd: i32,
+    path: *const c_char,
+    flags: u64,
+    mode: u64,
+) -> i64 {
+    let streams = unsafe { &mut *ctx.cast::<GuestStreams>() };
+    let Ok(path) = unsafe { CStr::from_ptr(path) }.to_str() else {
+        return -i64::from(EPERM);
+    };
+    streams
+        .files
+        .open(dirfd, path, flags, mode)
+        .map_or_else(|err| errno(&err), i64::from)
+}
+
+unsafe extern "C" fn guest_close(ctx: *mut c_void, fd: u32) -> i64 {
+    let streams = unsafe { &mut *ctx.cast::<GuestStreams>() };
+    streams
+        .files
+        .close(fd)
+        .map_or_else(|err| errno(&err), |()| 0)
+}
+
+unsafe extern "C" fn guest_seek(ctx: *mut c_void, fd: u32, offset: i64, whence: u32) -> i64 {
+    let streams = unsafe { &mut *ctx.cast::<GuestStreams>() };
+    streams.files.seek(fd, offset, whence).map_or_else(
+        |err| errno(&err),
+        |pos| i64::try_from(pos).unwrap_or(i64::MAX),
+    )
+}
+
+/// `fstatat` flag: do not follow a final symlink.
+const AT_SYMLINK_NOFOLLOW: u32 = 0x100;
+
+unsafe extern "C" fn guest_stat(
+    ctx: *mut c_void,
+    dirfd: i32,
+    path: *const c_char,
+    flags: u32,
+    out: *mut GuestStat,
+) -> i64 {
+    let streams = unsafe { &mut *ctx.cast::<GuestStreams>() };
+    let path = if path.is_null() {
+        None
+    } else {
+        let Ok(path) = unsafe { CStr::from_ptr(path) }.to_str() else {
+            return -i64::from(EPERM);
+        };
+        Some(path)
+    };
+    match streams
+        .files
+        .stat(dirfd, path, flags & AT_SYMLINK_NOFOLLOW != 0)
+    {
+        Ok(stat) => {
+            unsafe { out.write(stat) };
+            0
+        }
+        Err(err) => errno(&err),
+    }
+}
+
+unsafe extern "C" fn guest_csr_read(ctx: *mut c_void, csr: u32) -> u64 {
+    let hook = unsafe { &mut *ctx.cast::<Box<dyn CsrHook>>() };
+    // The generated code only passes 12-bit CSR numbers
+    hook.read(u16::try_from(csr).unwrap_or(u16::MAX))
+}
+
+unsafe extern "C" fn guest_csr_write(ctx: *mut c_void, csr: u32, value: u64) {
+    let hook = unsafe { &mut *ctx.cast::<Box<dyn CsrHook>>() };
+    hook.write(u16::try_from(csr).unwrap_or(u16::MAX), value);
+}
+
+/// Registered host syscalls and their numbers, sorted for `rv_has_host_syscall`.
+#[derive(Default)]
+struct HostSyscalls {
+    handlers: BTreeMap<u64, SyscallFn>,
+    nums: Vec<u64>,
+}
+
+unsafe extern "C" fn guest_syscall(
+    ctx: *mut c_void,
+    num: u64,
+    args: *const u64,
+    memory: *mut u8,
+    memory_size: u64,
+) -> i64 {
+    let syscalls = unsafe { &mut *ctx.cast::<HostSyscalls>() };
+    let Some(handler) = syscalls.handlers.get_mut(&num) else {
+        return -ENOSYS;
+    };
+    let args = unsafe { *args.cast::<[u64; 6]>() };
+    let len = usize::try_from(memory_size).unwrap_or(usize::MAX);
+    let memory = unsafe { std::slice::from_raw_parts_mut(memory, len) };
+    handler(&mut GuestContext { num, args, memory })
+}
+
+/// Registered function hooks by guest symbol.
+type HostFunctions = BTreeMap<String, HookFn>;
+
+unsafe extern "C" fn guest_hook(
+    ctx: *mut c_void,
+    name: *const c_char,
+    args: *mut u64,
+    memory: *mut u8,
+    memory_size: u64,
+) -> i32 {
+    let functions = unsafe { &mut *ctx.cast::<HostFunctions>() };
+    let name = unsafe { CStr::from_ptr(name) };
+    let Some(handler) = name.to_str().ok().and_then(|name| functions.get_mut(name)) else {
+        return NO_HOOK;
+    };
+    let args = unsafe { &mut *args.cast::<[u64; 6]>() };
+    let len = usize::try_from(memory_size).unwrap_or(usize::MAX);
+    let memory = unsafe { std::slice::from_raw_parts_mut(memory, len) };
+    let mut context = GuestContext {
+        num: 0,
+        args: *args,
+        memory,
+    };
+    handler(&mut context);
+    *args = context.args;
+    0
+}
+
+unsafe extern "C" fn guest_write(ctx: *mut c_void, fd: u32, buf: *const u8, len: usize) -> i64 {
+    let streams = unsafe { &mut *ctx.cast::<GuestStreams>() };
+    let buf = unsafe { std::slice::from_raw_parts(buf, len) };
+    streams.write(fd, buf).map_or_else(
+        |err| errno(&err),
+        |()| i64::try_from(len).unwrap_or(i64::MAX),
+    )
+}
+
+/// Redirected guest stdio, the CSR hook, host syscalls, function hooks and
+/// the hook table pointing at them.
+///
+/// All live on the heap so the table handed to the generated code stays
+/// valid when the owning [`Runner`](super::Runner) moves.
+pub(super) struct HostHooks {
+    streams: Box<GuestStreams>,
+    csr_hook: Option<Box<Box<dyn CsrHook>>>,
+    syscalls: Box<HostSyscalls>,
+    functions: Box<HostFunctions>,
+    hooks: Box<GuestIo>,
+}
+
+impl HostHooks {
+    pub(super) fn new() -> Self {
+        Self {
+            streams: Box::default(),
+            csr_hook: None,
+            syscalls: Box::default(),
+            functions: Box::default(),
+            hooks: Box::new(GuestIo {
+                ctx: std::ptr::null_mut(),
+                read: guest_read,
+                write: guest_write,
+                csr_ctx: std::ptr::null_mut(),
+                csr_read: None,
+                csr_write: None,
+                syscall_ctx: std::ptr::null_mut(),
+                syscall_nums: std::ptr::null(),
+                syscall_count: 0,
+                syscall: None,
+                open: None,
+                close: None,
+                hook_ctx: std::ptr::null_mut(),
+                hook: None,
+                seek: None,
+                stat: None,
+            }),
+        }
+    }
+
+    pub(super) fn set_stdin(&mut self, reader: Box<dyn Read + Send>) {
+        self.streams.stdin = Some(reader);
+    }
+
+    pub(super) fn set_stdout(&mut self, writer: Box<dyn Write + Send>) {
+        self.streams.stdout = Some(writer);
+    }
+
+    pub(super) fn set_stderr(&mut self, writer: Box<dyn Write + Send>) {
+        self.streams.stderr = Some(writer);
+    }
+
+    pub(super) fn preopen(&mut self, guest: PathBuf, host: &Path) -> io::Result<()> {
+        self.streams.files.preopen(guest, host)
+    }
+
+    pub(super) fn set_csr_hook(&mut self, hook: Box<dyn CsrHook>) {
+        self.csr_hook = Some(Box::new(hook));
+    }
+
+    pub(super) fn register_syscall(&mut self, num: u64, handler: SyscallFn) {
+        self.syscalls.handlers.insert(num, handler);
+        self.syscalls.nums = self.syscalls.handlers.keys().copied().collect();
+    }
+
+    pub(super) fn register_hook(&mut self, name: String, handler: HookFn) {
+        self.functions.insert(name, handler);
+    }
+
+    /// Hook table to install into the guest state.
+    pub(super) fn table(&mut self) -> *mut GuestIo {
+        self.hooks.ctx = std::ptr::from_mut(self.streams.as_mut()).cast();
+        if self.streams.files.has_preopens() {
+            self.hooks.open = Some(guest_open);
+            self.hooks.close = Some(guest_close);
+            self.hooks.seek = Some(guest_seek);
+            self.hooks.stat = Some(guest_stat);
+        }
+        if let Some(hook) = &mut self.csr_hook {
+            self.hooks.csr_ctx = std::ptr::from_mut(hook.as_mut()).cast();
+            self.hooks.csr_read = Some(guest_csr_read);
+            self.hooks.csr_write = Some(guest_csr_write);
+        }
+        if !self.syscalls.nums.is_empty() {
+            self.hooks.syscall_nums = self.syscalls.nums.as_ptr();
+            self.hooks.syscall_count = self.syscalls.nums.len() as u64;
+            self.hooks.syscall_ctx = std::ptr::from_mut(self.syscalls.as_mut()).cast();
+            self.hooks.syscall = Some(guest_syscall);
+        }
+        if !self.functions.is_empty() {
+            self.hooks.hook_ctx = std::ptr::from_mut(self.functions.as_mut()).cast();
+            self.hooks.hook = Some(guest_hook);
+        }
+        std::ptr::from_mut(self.hooks.as_mut())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    /// Reader that hands out at most `chunk` bytes per call.
+    struct Trickle {
+        data: Vec<u8>,
+        pos: usize,
+        chunk: usize,
+    }
+
+    impl Read for Trickle {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = buf.len().min(self.chunk).min(self.data.len() - self.pos);
+            buf[..n].copy_from_slice(&self.data[self.pos..self.pos + n]);
+            self.pos += n;
+            Ok(n)
+        }
+    }
+
+    /// Writer whose contents stay readable after it is handed off.
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            // Accept at most one page per call so `write_all` has to loop
+            let n = buf.len().min(4096);
+            self.0.lock().unwrap().extend_from_slice(&buf[..n]);
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn call_read(stdio: &mut HostHooks, fd: u32, buf: &mut [u8]) -> i64 {
+        let hooks = unsafe { &*stdio.table() };
+        unsafe { (hooks.read)(hooks.ctx, fd, buf.as_mut_ptr(), buf.len()) }
+    }
+
+    fn call_write(stdio: &mut HostHooks, fd: u32, buf: &[u8]) -> i64 {
+        let hooks = unsafe { &*stdio.table() };
+        unsafe { (hooks.write)(hooks.ctx, fd, buf.as_ptr(), buf.len()) }
+    }
+
+    #[test]
+    fn test_read_partial_then_eof() {
+        let mut stdio = HostHooks::new();
+        stdio.set_stdin(Box::new(Trickle {
+            data: b"hello".to_vec(),
+            pos: 0,
+            chunk: 3,
+        }));
+
+        let mut buf = [0u8; 16];
+        assert_eq!(call_read(&mut stdio, 0, &mut buf), 3);
+        assert_eq!(&buf[..3], b"hel");
+        assert_eq!(call_read(&mut stdio, 0, &mut buf), 2);
+        assert_eq!(&buf[..2], b"lo");
+        assert_eq!(call_read(&mut stdio, 0, &mut buf), 0);
+        assert_eq!(call_read(&mut stdio, 0, &mut buf), 0);
+    }
+
+    #[test]
+    fn test_large_write_is_complete() {
+        let out = Shared::default();
+        let err = Shared::default();
+        let mut stdio = HostHooks::new();
+        stdio.set_stdout(Box::new(out.clone()));
+        stdio.set_stderr(Box::new(err.clone()));
+
+        // Spans several pages, starting mid-page
+        let data: Vec<u8> = (0..=250).cycle().take(3 * 4096 + 100).collect();
+        let len = i64::try_from(data.len() - 7).unwrap();
+        assert_eq!(call_write(&mut stdio, 1, &data[7..]), len);
+        assert_eq!(call_write(&mut stdio, 2, b"oops"), 4);
+
+        assert_eq!(*out.0.lock().unwrap(), data[7..]);
+        assert_eq!(*err.0.lock().unwrap(), b"oops");
+    }
+
+    #[test]
+    fn test_bad_fd() {
+        let mut stdio = HostHooks::new();
+        let mut buf = [0u8; 4];
+        assert_eq!(call_read(&mut stdio, 1, &mut buf), -i64::from(EBADF));
+        assert_eq!(call_write(&mut stdio, 0, b"x"), -i64::from(EBADF));
+        assert_eq!(call_read(&mut stdio, 3, &mut buf), -i64::from(EBADF));
+        assert_eq!(call_write(&mut stdio, 3, b"x"), -i64::from(EBADF));
+    }
+
+    #[test]
+    fn test_stream_error_is_negative_errno() {
+        struct Broken;
+
+        impl Write for Broken {
+            fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+                Err(io::Error::other("broken"))
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut stdio = HostHooks::new();
+        stdio.set_stdout(Box::new(Broken));
+        assert_eq!(call_write(&mut stdio, 1, b"x"), -i64::from(EIO));
+    }
+
+    #[test]
+    fn test_host_syscall() {
+        let mut hooks = HostHooks::new();
+        assert!(unsafe { &*hooks.table() }.syscall.is_none());
+        hooks.register_syscall(
+            0x1000,
+            Box::new(|ctx| {
+                let len = ctx.arg(1);
+                let copied = ctx.memory(0, len).map(<[u8]>::to_vec);
+                match copied {
+                    Some(data) if ctx.write(ctx.arg(0), &data) => i64::try_from(len).unwrap(),
+                    _ => -14,
+                }
+            }),
+        );
+        hooks.register_syscall(7, Box::new(|ctx| i64::try_from(ctx.num()).unwrap()));
+
+        let table = unsafe { &*hooks.table() };
+        let nums = unsafe {
+            std::slice::from_raw_parts(
+                table.syscall_nums,
+                usize::try_from(table.syscall_count).unwrap(),
+            )
+        };
+        assert_eq!(nums, [7, 0x1000]);
+
+        let mut memory = *b"abcd\0\0\0\0";
+        let syscall = |num, args: [u64; 6], memory: &mut [u8]| unsafe {
+            (table.syscall.unwrap())(
+                table.syscall_ctx,
+                num,
+                args.as_ptr(),
+                memory.as_mut_ptr(),
+                memory.len() as u64,
+            )
+        };
+        assert_eq!(syscall(0x1000, [4, 4, 0, 0, 0, 0], &mut memory), 4);
+        assert_eq!(&memory, b"abcdabcd");
+        // Out of bounds: nothing written
+        assert_eq!(syscall(0x1000, [6, 4, 0, 0, 0, 0], &mut memory), -14);
+        assert_eq!(&memory, b"abcdabcd");
+        assert_eq!(syscall(7, [0; 6], &mut memory), 7);
+        assert_eq!(syscall(8, [0; 6], &mut memory), -ENOSYS);
+    }
+}
@@ -0,0 +1,139 @@
+//! Request loop of the isolated child.
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::os::unix::net::UnixStream;
+
+use nix::libc;
+
+use super::protocol::{self, ChildInfo, IsolatedState, Request, Response};
+use super::{IsolationOptions, seccomp};
+use crate::runner::{RunError, Runner, Snapshot};
+
+/// Exit status of a child that lost its parent or hit a protocol error.
+pub const PROTOCOL_EXIT: i32 = 2;
+
+/// Load the guest and serve requests on `stream` until shutdown. Returns the
+/// child's exit status.
+pub fn serve(mut stream: UnixStream, options: &IsolationOptions) -> i32 {
+    let mut runner = match load(options) {
+        Ok(runner) => runner,
+        Err(err) => {
+            let _ = protocol::send(&mut stream, &Response::Error(err.into()));
+            return PROTOCOL_EXIT;
+        }
+    };
+    let info = ChildInfo {
+        xlen: runner.xlen(),
+        num_regs: runner.num_regs(),
+        entry_point: runner.entry_point(),
+        supports_suspend: runner.supports_suspend(),
+    };
+    if protocol::send(&mut stream, &Response::Ready(info)).is_err() {
+        return PROTOCOL_EXIT;
+    }
+
+    let mut snapshots = HashMap::new();
+    loop {
+        let Ok(request) = protocol::receive::<Request>(&mut stream) else {
+            return PROTOCOL_EXIT;
+        };
+        if matches!(request, Request::Shutdown) {
+            return 0;
+        }
+        let response = handle(&mut runner, &mut snapshots, request)
+            .unwrap_or_else(|err| Response::Error(err.into()));
+        if protocol::send(&mut stream, &response).is_err() {
+            return PROTOCOL_EXIT;
+        }
+    }
+}
+
+/// Load the library and ELF, then drop to the seccomp filter if asked to.
+fn load(options: &IsolationOptions) -> Result<Runner, RunError> {
+    let runner =
+        Runner::load_with_memory(&options.lib_dir, &options.elf_path, options.memory_size)?;
+    if options.seccomp {
+        seccomp::install().map_err(|e| RunError::Isolation(format!("seccomp: {e}")))?;
+    }
+    Ok(runner)
+}
+
+fn handle(
+    runner: &mut Runner,
+    snapshots: &mut HashMap<u64, Snapshot>,
+    request: Request,
+) -> Result<Response, RunError> {
+    Ok(match request {
+        Request::Prepare => {
+            runner.prepare();
+            Response::Done
+        }
+        Request::Run => Response::Run(Box::new(runner.run()?)),
+        Request::ExecuteFrom { pc } => {
+            let (elapsed, instret) = runner.execute_from(pc)?;
+            Response::Executed {
+                nanos: u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX),
+                instret,
+            }
+        }
+        Request::SetTargetInstret { target } => {
+            Response::TargetSet(runner.set_target_instret(target))
+        }
+        Request::State => Response::State(IsolatedState {
+            regs: (0..runner.num_regs())
+                .map(|reg| runner.get_register(reg))
+                .collect(),
+            pc: runner.get_pc(),
+            instret: runner.instret(),
+            exited: runner.has_exited(),
+            exit_reason: runner.exit_reason(),
+        }),
+        Request::SetRegister { reg, value } => {
+            runner.set_register(reg, value);
+            Response::Done
+        }
+        Request::SetPc { pc } => {
+            runner.set_pc(pc);
+            Response::Done
+        }
+        Request::ReadMemory { addr, len } => {
+            let mut buf = vec![0; len];
+            let read = runner.read_memory(addr, &mut buf);
+            buf.truncate(read);
+            Response::Memory(buf)
+        }
+        Request::WriteMemory { addr, data } => Response::Written(runner.write_memory(addr, &data)),
+        Request::Snapshot => {
+            let snapshot = runner.snapshot()?;
+            let id = u64::try_from(snapshots.len()).unwrap_or(u64::MAX);
+            let response = Response::Snapshot {
+                id,
+                pc: snapshot.pc(),
+                instret: snapshot.instret(),
+            };
+            snapshots.insert(id, snapshot);
+            response
+        }
+        Request::Restore { id } => {
+            let snapshot = snapshots
+                .get(&id)
+                .ok_or_else(|| RunError::Isolation(format!("no snapshot {id}")))?;
+            runner.restore(snapshot)?;
+            Response::Done
+        }
+        Request::Shutdown => unreachable!("shutdown ends the loop"),
+    })
+}
+
+/// Flush the Rust and C stdio buffers guest output went through, then exit without running
+/// atexit handlers or destructors.
+pub fn exit(status: i32) -> ! {
+    let _ = std::io::stdout().flush();
+    let _ = std::io::stderr().flush();
+    // SAFETY: flushing all streams and `_exit` are sound in the child.
+    unsafe {
+        libc::fflush(std::ptr::null_mut());
+        libc::_exit(status)
+    }
+}
@@ -0,0 +1,504 @@
+//! Out-of-process execution.
+//!
+//! [`Runner::spawn_isolated`] starts the `rvr-isolated` helper binary, which
+//! loads the library and maps guest memory, so a miscompiled or malicious
+//! guest can only take down the helper. The helper is a fresh exec rather
+//! than a bare fork, so it never inherits allocator or loader locks that
+//! another thread of the parent held. The parent drives it over a socket with the control-point subset
+//! of the [`Runner`] API (run, suspend and resume, registers, memory,
+//! snapshots); guest execution itself never crosses the socket. Optionally
+//! the child runs under a seccomp filter.
+//!
+//! The helper is looked up, in order, from
+//! [`IsolationOptions::with_helper`], the `RVR_ISOLATED_HELPER` environment
+//! variable, and the directory of the running executable (or its parent,
+//! where cargo puts test binaries).
+
+mod child;
+mod protocol;
+mod seccomp;
+
+use std::ffi::OsString;
+use std::io;
+use std::os::fd::{AsRawFd, FromRawFd, RawFd};
+use std::os::unix::net::UnixStream;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+use nix::libc;
+use nix::sys::signal::{Signal, kill};
+use nix::sys::wait::{WaitStatus, waitpid};
+use nix::unistd::Pid;
+use rvr_state::DEFAULT_MEMORY_SIZE;
+use tracing::debug;
+
+use super::{ExitReason, RunError, RunResult, Runner};
+use protocol::{ChildInfo, Request, Response};
+
+pub use protocol::IsolatedState;
+
+/// Exit status of a child that panicked.
+const PANIC_EXIT: i32 = 101;
+
+/// Environment variable naming the helper binary.
+pub const HELPER_ENV: &str = "RVR_ISOLATED_HELPER";
+
+/// File name of the helper binary.
+const HELPER_NAME: &str = "rvr-isolated";
+
+/// Descriptor the helper finds its end of the control socket on.
+const CONTROL_FD: RawFd = 3;
+
+/// What [`Runner::spawn_isolated`] loads and how the child is confined.
+#[derive(Clone, Debug)]
+pub struct IsolationOptions {
+    lib_dir: PathBuf,
+    elf_path: PathBuf,
+    memory_size: usize,
+    seccomp: bool,
+    helper: Option<PathBuf>,
+}
+
+impl IsolationOptions {
+    /// Load the library in `lib_dir` compiled from `elf_path`, with the
+    /// default memory size and no seccomp filter.
+    pub fn new(lib_dir: impl AsRef<Path>, elf_path: impl AsRef<Path>) -> Self {
+        Self {
+            lib_dir: lib_dir.as_ref().to_path_buf(),
+            elf_path: elf_path.as_ref().to_path_buf(),
+            memory_size: DEFAULT_MEMORY_SIZE,
+            seccomp: false,
+            helper: None,
+        }
+    }
+
+    /// Set the guest memory size.
+    #[must_use]
+    pub const fn with_memory_size(mut self, memory_size: usize) -> Self {
+        self.memory_size = memory_size;
+        self
+    }
+
+    /// Restrict the child to the syscalls running the guest needs once the
+    /// library is loaded; any other syscall kills it with `SIGSYS`.
+    #[must_use]
+    pub const fn with_seccomp(mut self, seccomp: bool) -> Self {
+        self.seccomp = seccomp;
+        self
+    }
+
+    /// Run `helper` as the child instead of looking up `rvr-isolated`.
+    #[must_use]
+    pub fn with_helper(mut self, helper: impl AsRef<Path>) -> Self {
+        self.helper = Some(helper.as_ref().to_path_buf());
+        self
+    }
+
+    /// Path of the helper binary to execute.
+    fn helper(&self) -> Result<PathBuf, RunError> {
+        if let Some(helper) = &self.helper {
+            return Ok(helper.clone());
+        }
+        if let Some(helper) = std::env::var_os(HELPER_ENV) {
+            return Ok(helper.into());
+        }
+        let exe = std::env::current_exe()?;
+        exe.ancestors()
+            .skip(1)
+            .take(2)
+            .map(|dir| dir.join(HELPER_NAME))
+            .find(|path| path.is_file())
+            .ok_or_else(|| {
+                RunError::Isolation(format!(
+                    "{HELPER_NAME} not found next to {}; set {HELPER_ENV}",
+                    exe.display()
+                ))
+            })
+    }
+
+    /// Command-line arguments passing these options to the helper.
+    fn to_args(&self) -> [OsString; 4] {
+        [
+            self.lib_dir.clone().into(),
+            self.elf_path.clone().into(),
+            self.memory_size.to_string().into(),
+            if self.seccomp {
+                "seccomp"
+            } else {
+                "no-seccomp"
+            }
+            .into(),
+        ]
+    }
+
+    /// Options parsed back from [`Self::to_args`].
+    fn from_args(mut args: impl Iterator<Item = OsString>) -> Option<Self> {
+        let lib_dir = args.next()?;
+        let elf_path = args.next()?;
+        let memory_size = args.next()?.to_str()?.parse().ok()?;
+        let seccomp = match args.next()?.to_str()? {
+            "seccomp" => true,
+            "no-seccomp" => false,
+            _ => return None,
+        };
+        args.next().is_none().then(|| {
+            Self::new(lib_dir, elf_path)
+                .with_memory_size(memory_size)
+                .with_seccomp(seccomp)
+        })
+    }
+}
+
+/// Guest snapshot held by the child of an [`IsolatedRunner`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IsolatedSnapshot {
+    id: u64,
+    pc: u64,
+    instret: u64,
+}
+
+impl IsolatedSnapshot {
+    /// Program counter at the time of the snapshot.
+    #[must_use]
+    pub const fn pc(&self) -> u64 {
+        self.pc
+    }
+
+    /// Instruction count at the time of the snapshot.
+    #[must_use]
+    pub const fn instret(&self) -> u64 {
+        self.instret
+    }
+}
+
+/// Runner whose guest executes in a child process.
+///
+/// Every method is one round trip to the child. Once the child dies, they
+/// all fail with [`RunError::ChildCrashed`] (killed by a signal) or
+/// [`RunError::Isolation`]. Dropping the runner shuts the child down.
+pub struct IsolatedRunner {
+    child: Pid,
+    stream: UnixStream,
+    info: ChildInfo,
+    /// How the child ended, once it has.
+    status: Option<WaitStatus>,
+}
+
+impl Runner {
+    /// Load a library in a child process and return a handle that proxies
+    /// the runner API to it.
+    ///
+    /// # Errors
+    /// Returns an error if the helper cannot be found or started, or the
+    /// child cannot load the library, ELF or seccomp filter.
+    pub fn spawn_isolated(options: &IsolationOptions) -> Result<IsolatedRunner, RunError> {
+        let helper = options.helper()?;
+        let (parent_stream, child_stream) = UnixStream::pair()?;
+        let fd = child_stream.as_raw_fd();
+        let mut command = Command::new(&helper);
+        command.args(options.to_args());
+        // SAFETY: the hook only makes async-signal-safe calls before exec.
+        unsafe {
+            command.pre_exec(move || pass_control_fd(fd));
+        }
+        let child = command
+            .spawn()
+            .map_err(|e| RunError::Isolation(format!("spawn {}: {e}", helper.display())))?;
+        drop(child_stream);
+        let child = Pid::from_raw(child.id().cast_signed());
+        debug!(pid = child.as_raw(), "spawned isolated runner");
+        IsolatedRunner::connect(child, parent_stream)
+    }
+}
+
+/// Entry point of the `rvr-isolated` helper: load the guest named on the
+/// command line and serve requests on the inherited control socket.
+pub fn serve_isolated() -> ! {
+    let status = std::panic::catch_unwind(|| {
+        let Some(options) = IsolationOptions::from_args(std::env::args_os().skip(1)) else {
+            eprintln!("usage: {HELPER_NAME} <lib-dir> <elf> <memory-size> <seccomp|no-seccomp>");
+            return child::PROTOCOL_EXIT;
+        };
+        // SAFETY: `spawn_isolated` passes the child end of the socket pair
+        // as `CONTROL_FD`, and nothing else in the helper owns it.
+        let stream = unsafe { UnixStream::from_raw_fd(CONTROL_FD) };
+        child::serve(stream, &options)
+    })
+    .unwrap_or(PANIC_EXIT);
+    child::exit(status)
+}
+
+/// Move the child end of the control socket to [`CONTROL_FD`] across exec.
+fn pass_control_fd(fd: RawFd) -> io::Result<()> {
+    // SAFETY: `dup2` and `fcntl` are async-signal-safe and touch only fds.
+    let ret = unsafe {
+        if fd == CONTROL_FD {
+            // `dup2` onto itself would leave close-on-exec set.
+            libc::fcntl(fd, libc::F_SETFD, 0)
+        } else {
+            libc::dup2(fd, CONTROL_FD)
+        }
+    };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+impl IsolatedRunner {
+    /// Wait for the child to report that it loaded the guest.
+    fn connect(child: Pid, stream: UnixStream) -> Result<Self, RunError> {
+        let mut runner = Self {
+            child,
+            stream,
+            info: ChildInfo {
+                xlen: 0,
+                num_regs: 0,
+                entry_point: 0,
+                supports_suspend: false,
+            },
+            status: None,
+        };
+        match runner.receive()? {
+            Response::Ready(info) => {
+                runner.info = info;
+                Ok(runner)
+            }
+            response => Err(runner.unexpected(response)),
+        }
+    }
+
+    /// Process id of the child.
+    #[must_use]
+    pub const fn pid(&self) -> u32 {
+        self.child.as_raw().cast_unsigned()
+    }
+
+    /// Guest XLEN (32 or 64).
+    #[must_use]
+    pub const fn xlen(&self) -> u8 {
+        self.info.xlen
+    }
+
+    /// Number of general-purpose registers.
+    #[must_use]
+    pub const fn num_regs(&self) -> usize {
+        self.info.num_regs
+    }
+
+    /// ELF entry point.
+    #[must_use]
+    pub const fn entry_point(&self) -> u64 {
+        self.info.entry_point
+    }
+
+    /// Whether the library was compiled with suspend support.
+    #[must_use]
+    pub const fn supports_suspend(&self) -> bool {
+        self.info.supports_suspend
+    }
+
+    /// Reset guest memory and registers, as [`Runner::prepare`].
+    ///
+    /// # Errors
+    /// Returns an error if the child is gone.
+    pub fn prepare(&mut self) -> Result<(), RunError> {
+        self.expect_done(&Request::Prepare)
+    }
+
+    /// Run the program, as [`Runner::run`].
+    ///
+    /// # Errors
+    /// Returns the run's error, or `ChildCrashed` if the guest took down the
+    /// child.
+    pub fn run(&mut self) -> Result<RunResult, RunError> {
+        match self.call(&Request::Run)? {
+            Response::Run(result) => Ok(*result),
+            response => Err(self.unexpected(response)),
+        }
+    }
+
+    /// Execute from `pc` until exit or suspension, as
+    /// [`Runner::execute_from`].
+    ///
+    /// # Errors
+    /// Returns the run's error, or `ChildCrashed` if the guest took down the
+    /// child.
+    pub fn execute_from(&mut self, pc: u64) -> Result<(Duration, u64), RunError> {
+        match self.call(&Request::ExecuteFrom { pc })? {
+            Response::Executed { nanos, instret } => Ok((Duration::from_nanos(nanos), instret)),
+            response => Err(self.unexpected(response)),
+        }
+    }
+
+    /// Suspend at `target` retired instructions, as
+    /// [`Runner::set_target_instret`]. Returns false without suspend support.
+    ///
+    /// # Errors
+    /// Returns an error if the child is gone.
+    pub fn set_target_instret(&mut self, target: u64) -> Result<bool, RunError> {
+        match self.call(&Request::SetTargetInstret { target })? {
+            Response::TargetSet(set) => Ok(set),
+            response => Err(self.unexpected(response)),
+        }
+    }
+
+    /// Registers, PC, instret and exit state.
+    ///
+    /// # Errors
+    /// Returns an error if the child is gone.
+    pub fn state(&mut self) -> Result<IsolatedState, RunError> {
+        match self.call(&Request::State)? {
+            Response::State(state) => Ok(state),
+            response => Err(self.unexpected(response)),
+        }
+    }
+
+    /// Why execution stopped.
+    ///
+    /// # Errors
+    /// Returns an error if the child is gone.
+    pub fn exit_reason(&mut self) -> Result<ExitReason, RunError> {
+        Ok(self.state()?.exit_reason)
+    }
+
+    /// Set a general-purpose register.
+    ///
+    /// # Errors
+    /// Returns an error if the child is gone.
+    pub fn set_register(&mut self, reg: usize, value: u64) -> Result<(), RunError> {
+        self.expect_done(&Request::SetRegister { reg, value })
+    }
+
+    /// Set the program counter.
+    ///
+    /// # Errors
+    /// Returns an error if the child is gone.
+    pub fn set_pc(&mut self, pc: u64) -> Result<(), RunError> {
+        self.expect_done(&Request::SetPc { pc })
+    }
+
+    /// Read guest memory into `buf`, returning the number of bytes read.
+    ///
+    /// # Errors
+    /// Returns an error if the child is gone.
+    pub fn read_memory(&mut self, addr: u64, buf: &mut [u8]) -> Result<usize, RunError> {
+        match self.call(&Request::ReadMemory {
+            addr,
+            len: buf.len(),
+        })? {
+            Response::Memory(data) => {
+                let len = data.len().min(buf.len());
+                buf[..len].copy_from_slice(&data[..len]);
+                Ok(len)
+            }
+            response => Err(self.unexpected(response)),
+        }
+    }
+
+    /// Write `data` to guest memory, returning the number of bytes written.
+    ///
+    /// # Errors
+    /// Returns an error if the child is gone.
+    pub fn write_memory(&mut self, addr: u64, data: &[u8]) -> Result<usize, RunError> {
+        match self.call(&Request::WriteMemory {
+            addr,
+            data: data.to_vec(),
+        })? {
+            Response::Written(len) => Ok(len),
+            response => Err(self.unexpected(response)),
+        }
+    }
+
+    /// Snapshot the guest state in the child, as [`Runner::snapshot`].
+    /// Snapshots live until the child exits.
+    ///
+    /// # Errors
+    /// Returns an error if guest memory cannot be frozen or the child is
+    /// gone.
+    pub fn snapshot(&mut self) -> Result<IsolatedSnapshot, RunError> {
+        match self.call(&Request::Snapshot)? {
+            Response::Snapshot { id, pc, instret } => Ok(IsolatedSnapshot { id, pc, instret }),
+            response => Err(self.unexpected(response)),
+        }
+    }
+
+    /// Restore a snapshot taken by this runner, as [`Runner::restore`].
+    ///
+    /// # Errors
+    /// Returns an error if the snapshot cannot be restored or the child is
+    /// gone.
+    pub fn restore(&mut self, snapshot: &IsolatedSnapshot) -> Result<(), RunError> {
+        self.expect_done(&Request::Restore { id: snapshot.id })
+    }
+
+    fn expect_done(&mut self, request: &Request) -> Result<(), RunError> {
+        match self.call(request)? {
+            Response::Done => Ok(()),
+            response => Err(self.unexpected(response)),
+        }
+    }
+
+    /// Send `request` and wait for its response, turning a child-side
+    /// error into the matching `RunError`.
+    fn call(&mut self, request: &Request) -> Result<Response, RunError> {
+        if let Some(status) = self.status {
+            return Err(lost(status));
+        }
+        if protocol::send(&mut self.stream, request).is_err() {
+            return Err(self.reap());
+        }
+        match self.receive()? {
+            Response::Error(err) => Err(err.into()),
+            response => Ok(response),
+        }
+    }
+
+    fn receive(&mut self) -> Result<Response, RunError> {
+        protocol::receive(&mut self.stream).map_err(|_| self.reap())
+    }
+
+    /// The child closed the socket: wait for it and report how it ended.
+    fn reap(&mut self) -> RunError {
+        let status = match waitpid(self.child, None) {
+            Ok(status) => status,
+            Err(e) => return RunError::Isolation(format!("lost child: {e}")),
+        };
+        debug!(pid = self.child.as_raw(), ?status, "isolated runner exited");
+        self.status = Some(status);
+        lost(status)
+    }
+
+    fn unexpected(&mut self, response: Response) -> RunError {
+        match response {
+            Response::Error(err) => {
+                // Startup failed; the child exits after reporting it.
+                let _ = self.reap();
+                err.into()
+            }
+            response => RunError::Isolation(format!("unexpected response {response:?}")),
+        }
+    }
+}
+
+/// Error for a request to a child that ended with `status`.
+fn lost(status: WaitStatus) -> RunError {
+    match status {
+        WaitStatus::Signaled(_, signal, _) => RunError::ChildCrashed(signal as i32),
+        status => RunError::Isolation(format!("child exited: {status:?}")),
+    }
+}
+
+impl Drop for IsolatedRunner {
+    fn drop(&mut self) {
+        if self.status.is_some() {
+            return;
+        }
+        if protocol::send(&mut self.stream, &Request::Shutdown).is_err() {
+            let _ = kill(self.child, Signal::SIGKILL);
+        }
+        let _ = waitpid(self.child, None);
+    }
+}
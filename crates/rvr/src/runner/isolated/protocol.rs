@@ -0,0 +1,206 @@
+//! Messages between an [`IsolatedRunner`](super::IsolatedRunner) and its
+//! child.
+//!
+//! Each message is a little-endian `u32` length followed by that many bytes
+//! of JSON. Every request gets exactly one response.
+
+use std::io::{self, Read, Write};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::runner::{ExitReason, RunError, RunResult};
+
+/// Largest message accepted (guards against a corrupted length prefix).
+const MAX_MESSAGE_LEN: usize = 1 << 30;
+
+/// Operation requested of the child.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum Request {
+    /// [`Runner::prepare`](crate::Runner::prepare).
+    Prepare,
+    /// [`Runner::run`](crate::Runner::run).
+    Run,
+    /// [`Runner::execute_from`](crate::Runner::execute_from).
+    ExecuteFrom { pc: u64 },
+    /// [`Runner::set_target_instret`](crate::Runner::set_target_instret).
+    SetTargetInstret { target: u64 },
+    /// Registers, PC and exit state.
+    State,
+    /// [`Runner::set_register`](crate::Runner::set_register).
+    SetRegister { reg: usize, value: u64 },
+    /// [`Runner::set_pc`](crate::Runner::set_pc).
+    SetPc { pc: u64 },
+    /// [`Runner::read_memory`](crate::Runner::read_memory) of `len` bytes.
+    ReadMemory { addr: u64, len: usize },
+    /// [`Runner::write_memory`](crate::Runner::write_memory).
+    WriteMemory { addr: u64, data: Vec<u8> },
+    /// [`Runner::snapshot`](crate::Runner::snapshot), kept in the child.
+    Snapshot,
+    /// [`Runner::restore`](crate::Runner::restore) of a snapshot by id.
+    Restore { id: u64 },
+    /// Flush output and exit.
+    Shutdown,
+}
+
+/// Child reply to a [`Request`] (or to startup).
+#[derive(Debug, Serialize, Deserialize)]
+pub enum Response {
+    /// The library is loaded and the child serves requests.
+    Ready(ChildInfo),
+    /// The request succeeded with nothing to return.
+    Done,
+    /// Result of [`Request::Run`].
+    Run(Box<RunResult>),
+    /// Execution time and instret of [`Request::ExecuteFrom`].
+    Executed { nanos: u64, instret: u64 },
+    /// Whether [`Request::SetTargetInstret`] took effect.
+    TargetSet(bool),
+    /// Reply to [`Request::State`].
+    State(IsolatedState),
+    /// Bytes read by [`Request::ReadMemory`].
+    Memory(Vec<u8>),
+    /// Bytes written by [`Request::WriteMemory`].
+    Written(usize),
+    /// Id, PC and instret of a [`Request::Snapshot`].
+    Snapshot { id: u64, pc: u64, instret: u64 },
+    /// The request failed.
+    Error(ChildError),
+}
+
+/// Properties of the loaded guest, fixed for the child's lifetime.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct ChildInfo {
+    pub xlen: u8,
+    pub num_regs: usize,
+    pub entry_point: u64,
+    pub supports_suspend: bool,
+}
+
+/// Architectural and exit state of an isolated guest.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IsolatedState {
+    /// General-purpose registers (`x0` first).
+    pub regs: Vec<u64>,
+    /// Program counter.
+    pub pc: u64,
+    /// Instructions retired.
+    pub instret: u64,
+    /// Whether the guest has exited.
+    pub exited: bool,
+    /// Why execution stopped.
+    pub exit_reason: ExitReason,
+}
+
+/// A [`RunError`] as sent by the child. Variants the parent reacts to keep
+/// their fields; the rest travel as their message.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum ChildError {
+    Execution(ExitReason),
+    QuarantinedBlock { pc: u64, reason: String },
+    CodeWrite { pc: u64, addr: u64 },
+    StackOverflow { pc: u64, sp: u64, limit: u64 },
+    UnresolvedJump { site: u64, target: u64, reg: u8 },
+    TimedOut { instret_so_far: u64 },
+    Cancelled,
+    Other(String),
+}
+
+impl From<RunError> for ChildError {
+    fn from(err: RunError) -> Self {
+        match err {
+            RunError::ExecutionError(reason) => Self::Execution(reason),
+            RunError::QuarantinedBlock { pc, reason } => Self::QuarantinedBlock { pc, reason },
+            RunError::CodeWrite { pc, addr } => Self::CodeWrite { pc, addr },
+            RunError::StackOverflow { pc, sp, limit } => Self::StackOverflow { pc, sp, limit },
+            RunError::UnresolvedJump { site, target, reg } => {
+                Self::UnresolvedJump { site, target, reg }
+            }
+            RunError::TimedOut { instret_so_far } => Self::TimedOut { instret_so_far },
+            RunError::Cancelled => Self::Cancelled,
+            err => Self::Other(err.to_string()),
+        }
+    }
+}
+
+impl From<ChildError> for RunError {
+    fn from(err: ChildError) -> Self {
+        match err {
+            ChildError::Execution(reason) => Self::ExecutionError(reason),
+            ChildError::QuarantinedBlock { pc, reason } => Self::QuarantinedBlock { pc, reason },
+            ChildError::CodeWrite { pc, addr } => Self::CodeWrite { pc, addr },
+            ChildError::StackOverflow { pc, sp, limit } => Self::StackOverflow { pc, sp, limit },
+            ChildError::UnresolvedJump { site, target, reg } => {
+                Self::UnresolvedJump { site, target, reg }
+            }
+            ChildError::TimedOut { instret_so_far } => Self::TimedOut { instret_so_far },
+            ChildError::Cancelled => Self::Cancelled,
+            ChildError::Other(message) => Self::Isolation(message),
+        }
+    }
+}
+
+/// Write one length-prefixed message.
+pub fn send<T: Serialize>(stream: &mut impl Write, message: &T) -> io::Result<()> {
+    let body = serde_json::to_vec(message)?;
+    let len = u32::try_from(body.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "message too large"))?;
+    stream.write_all(&len.to_le_bytes())?;
+    stream.write_all(&body)?;
+    stream.flush()
+}
+
+/// Read one length-prefixed message.
+pub fn receive<T: DeserializeOwned>(stream: &mut impl Read) -> io::Result<T> {
+    let mut len = [0; 4];
+    stream.read_exact(&mut len)?;
+    let len = u32::from_le_bytes(len) as usize;
+    if len > MAX_MESSAGE_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("message of {len} bytes"),
+        ));
+    }
+    let mut body = vec![0; len];
+    stream.read_exact(&mut body)?;
+    Ok(serde_json::from_slice(&body)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut wire = Vec::new();
+        send(
+            &mut wire,
+            &Request::WriteMemory {
+                addr: 0x1000,
+                data: vec![1, 2, 3],
+            },
+        )
+        .unwrap();
+        send(
+            &mut wire,
+            &Response::Error(ChildError::Execution(ExitReason::HostStop)),
+        )
+        .unwrap();
+
+        let mut reader = wire.as_slice();
+        let Request::WriteMemory { addr, data } = receive(&mut reader).unwrap() else {
+            panic!("expected a memory write");
+        };
+        assert_eq!((addr, data), (0x1000, vec![1, 2, 3]));
+        let response: Response = receive(&mut reader).unwrap();
+        let err = RunError::from(match response {
+            Response::Error(err) => err,
+            other => panic!("expected an error, got {other:?}"),
+        });
+        assert!(matches!(
+            err,
+            RunError::ExecutionError(ExitReason::HostStop)
+        ));
+        assert!(receive::<Request>(&mut reader).is_err());
+    }
+}
@@ -0,0 +1,158 @@
+//! Seccomp filter for the isolated child.
+//!
+//! Installed after the library is loaded, so only what running the guest
+//! needs stays allowed: memory management (including on-demand commit and
+//! snapshots), the host syscall shim's calls on already open descriptors,
+//! time, and the control socket. Any other syscall kills the child with
+//! `SIGSYS`.
+//!
+//! The child has no preopened directories, so opening a path fails with
+//! `EPERM`, and nothing may become executable once the library is mapped:
+//! `mmap` or `mprotect` with `PROT_EXEC` kills the child.
+
+use std::io;
+
+use nix::libc;
+
+/// `AUDIT_ARCH_*` of the host (`linux/audit.h`).
+#[cfg(target_arch = "x86_64")]
+const AUDIT_ARCH: u32 = 0xc000_003e;
+#[cfg(target_arch = "aarch64")]
+const AUDIT_ARCH: u32 = 0xc000_00b7;
+
+/// Offsets of `nr`, `arch` and the low word of `args[2]` (the `prot` of
+/// `mmap` and `mprotect`) in little-endian `struct seccomp_data`.
+const NR_OFFSET: u32 = 0;
+const ARCH_OFFSET: u32 = 4;
+const PROT_OFFSET: u32 = 32;
+
+/// Syscalls that open a path, refused with `EPERM`.
+const DENIED: &[libc::c_long] = &[libc::SYS_openat, libc::SYS_openat2];
+
+/// Syscalls allowed only without `PROT_EXEC`.
+const NO_EXEC: &[libc::c_long] = &[libc::SYS_mmap, libc::SYS_mprotect];
+
+/// Syscalls the child may make once the filter is installed.
+const ALLOWED: &[libc::c_long] = &[
+    // Control socket and guest stdio.
+    libc::SYS_read,
+    libc::SYS_write,
+    libc::SYS_readv,
+    libc::SYS_writev,
+    libc::SYS_pread64,
+    libc::SYS_pwrite64,
+    libc::SYS_recvfrom,
+    libc::SYS_sendto,
+    // Host syscall shim file access.
+    libc::SYS_close,
+    libc::SYS_lseek,
+    libc::SYS_fstat,
+    libc::SYS_newfstatat,
+    libc::SYS_statx,
+    libc::SYS_getdents64,
+    libc::SYS_fcntl,
+    // Guest memory, allocator, snapshots and on-demand commit.
+    libc::SYS_munmap,
+    libc::SYS_madvise,
+    libc::SYS_mremap,
+    libc::SYS_mincore,
+    libc::SYS_brk,
+    libc::SYS_memfd_create,
+    libc::SYS_ftruncate,
+    libc::SYS_futex,
+    // Time and randomness.
+    libc::SYS_clock_gettime,
+    libc::SYS_clock_nanosleep,
+    libc::SYS_nanosleep,
+    libc::SYS_gettimeofday,
+    libc::SYS_getrandom,
+    libc::SYS_sched_yield,
+    // Signals, so faults still reach their handlers and crash the child.
+    libc::SYS_rt_sigreturn,
+    libc::SYS_rt_sigaction,
+    libc::SYS_rt_sigprocmask,
+    libc::SYS_sigaltstack,
+    libc::SYS_getpid,
+    libc::SYS_gettid,
+    libc::SYS_tgkill,
+    libc::SYS_exit,
+    libc::SYS_exit_group,
+];
+
+const fn statement(code: u32, k: u32) -> libc::sock_filter {
+    jump(code, k, 0, 0)
+}
+
+#[allow(clippy::cast_possible_truncation)] // BPF opcodes fit in 16 bits
+const fn jump(code: u32, k: u32, jt: u8, jf: u8) -> libc::sock_filter {
+    libc::sock_filter {
+        code: code as u16,
+        jt,
+        jf,
+        k,
+    }
+}
+
+fn syscall_nr(nr: libc::c_long) -> u32 {
+    u32::try_from(nr).expect("syscall numbers fit in 32 bits")
+}
+
+/// BPF program refusing [`DENIED`], allowing [`NO_EXEC`] without
+/// `PROT_EXEC`, and allowing [`ALLOWED`] on the host architecture.
+fn program() -> Vec<libc::sock_filter> {
+    let load = libc::BPF_LD | libc::BPF_W | libc::BPF_ABS;
+    let equals = libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K;
+    let any_set = libc::BPF_JMP | libc::BPF_JSET | libc::BPF_K;
+    let ret = libc::BPF_RET | libc::BPF_K;
+    let eperm = libc::SECCOMP_RET_ERRNO | libc::EPERM.cast_unsigned();
+    let mut program = vec![
+        statement(load, ARCH_OFFSET),
+        jump(equals, AUDIT_ARCH, 1, 0),
+        statement(ret, libc::SECCOMP_RET_KILL_PROCESS),
+        statement(load, NR_OFFSET),
+    ];
+    for &nr in DENIED {
+        program.push(jump(equals, syscall_nr(nr), 0, 1));
+        program.push(statement(ret, eperm));
+    }
+    for &nr in NO_EXEC {
+        // On a match, test `prot`; otherwise skip the four checks below with
+        // the syscall number still loaded.
+        program.push(jump(equals, syscall_nr(nr), 0, 4));
+        program.push(statement(load, PROT_OFFSET));
+        program.push(jump(any_set, libc::PROT_EXEC.cast_unsigned(), 0, 1));
+        program.push(statement(ret, libc::SECCOMP_RET_KILL_PROCESS));
+        program.push(statement(ret, libc::SECCOMP_RET_ALLOW));
+    }
+    for &nr in ALLOWED {
+        program.push(jump(equals, syscall_nr(nr), 0, 1));
+        program.push(statement(ret, libc::SECCOMP_RET_ALLOW));
+    }
+    program.push(statement(ret, libc::SECCOMP_RET_KILL_PROCESS));
+    program
+}
+
+/// Restrict the calling process to the syscalls [`program`] allows.
+///
+/// # Errors
+/// Returns the OS error if the kernel rejects the filter.
+pub fn install() -> io::Result<()> {
+    let mut filter = program();
+    let prog = libc::sock_fprog {
+        len: u16::try_from(filter.len()).expect("filter fits in a BPF program"),
+        filter: filter.as_mut_ptr(),
+    };
+    // SAFETY: `prog` points at `filter`, which outlives both calls.
+    unsafe {
+        if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0
+            || libc::prctl(
+                libc::PR_SET_SECCOMP,
+                libc::SECCOMP_MODE_FILTER,
+                &raw const prog,
+            ) != 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,95 @@
+//! Symbolized sites of unresolved dynamic jumps.
+//!
+//! A dynamic jump whose target has no compiled block stops the run with
+//! [`RunError::UnresolvedJump`](super::RunError::UnresolvedJump).
+//! [`Runner::jump_site`] resolves its site to symbol+offset from the ELF
+//! and disassembles the jump from guest memory.
+
+use std::fmt;
+
+use rvr_elf::{ElfImage, get_elf_xlen};
+use rvr_isa::{ExtensionRegistry, Rv32, Rv64, Xlen};
+
+use super::Runner;
+
+/// The jump instruction of an unresolved dynamic jump.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JumpSite {
+    /// Guest PC of the jump.
+    pub pc: u64,
+    /// Function containing the jump, if the ELF has a symbol for it.
+    pub function: Option<String>,
+    /// Offset of `pc` from the start of `function`.
+    pub offset: u64,
+    /// Disassembly of the jump, if it decodes.
+    pub disasm: Option<String>,
+}
+
+impl fmt::Display for JumpSite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#x}", self.pc)?;
+        if let Some(function) = &self.function {
+            write!(f, " in {function}+{:#x}", self.offset)?;
+        }
+        if let Some(disasm) = &self.disasm {
+            write!(f, ": {disasm}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Runner {
+    /// Symbolize and disassemble the instruction at `pc`, the site of an
+    /// unresolved dynamic jump.
+    ///
+    /// The function is looked up in the ELF the runner was loaded with and
+    /// left out if it cannot be read; the instruction is read from guest
+    /// memory.
+    #[must_use]
+    pub fn jump_site(&self, pc: u64) -> JumpSite {
+        let data = self
+            .elf_path
+            .as_ref()
+            .and_then(|path| std::fs::read(path).ok());
+        let function = data.as_deref().and_then(|data| {
+            let load_bias = self.api.load_bias;
+            match get_elf_xlen(data).ok()? {
+                32 => symbol_at(
+                    &ElfImage::<Rv32>::parse_with_load_bias(data, load_bias).ok()?,
+                    pc,
+                ),
+                _ => symbol_at(
+                    &ElfImage::<Rv64>::parse_with_load_bias(data, load_bias).ok()?,
+                    pc,
+                ),
+            }
+        });
+        let mut bytes = [0; 4];
+        let read = self.read_memory(pc, &mut bytes);
+        let bytes = &bytes[..read];
+        let disasm = if self.xlen() == Rv32::VALUE {
+            disassemble::<Rv32>(bytes, pc)
+        } else {
+            disassemble::<Rv64>(bytes, pc)
+        };
+        JumpSite {
+            pc,
+            offset: function.as_ref().map_or(0, |(_, start)| pc - start),
+            function: function.map(|(name, _)| name),
+            disasm,
+        }
+    }
+}
+
+/// Name and start of the function symbol containing `pc`.
+fn symbol_at<X: Xlen>(image: &ElfImage<X>, pc: u64) -> Option<(String, u64)> {
+    image
+        .function_symbol(pc)
+        .map(|symbol| (symbol.name.clone(), X::to_u64(symbol.value)))
+}
+
+fn disassemble<X: Xlen>(bytes: &[u8], pc: u64) -> Option<String> {
+    let registry = ExtensionRegistry::<X>::standard();
+    let instr = registry.decode(bytes, X::from_u64(pc))?;
+    Some(registry.disasm(&instr))
+}
@@ -0,0 +1,456 @@
+//! Loading a compiled library and its ELF into a [`Runner`].
+
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
+
+use libloading::os::unix::{Library, RTLD_NOW};
+use rvr_elf::{ElfImage, get_elf_xlen};
+use rvr_emit::{AddrRange, LayoutError, MemoryLayout};
+use rvr_ir::{Rv32, Rv64, Xlen};
+use rvr_isa::{REG_GP, REG_RA, REG_SP};
+use rvr_state::{DEFAULT_MEMORY_SIZE, GuardedMemory, NUM_REGS_E};
+use tracing::{debug, error, trace, warn};
+
+use super::api::{load_layout, load_memory_layout, load_preopens, load_quarantine};
+use super::argv;
+use super::factory::{create_fixed_addr_runner, create_runner_impl};
+use super::reset::PristineMemory;
+use super::symbols::Symbols;
+use super::{InstretMode, RunError, Runner, RvApi, RvEmbedInfo, TracerKind, library_path};
+use crate::SyntheticProgram;
+use crate::layout::{STACK_TOP_SYMBOL, image_layout};
+use crate::programs::ProgramManifest;
+use crate::segment_image::{SegmentImage, segment_image_path};
+
+/// Initialize guest memory with the segments of `elf_image`.
+///
+/// Maps them from the segment image when there is one, falling back to
+/// copying from the ELF if reading the image fails.
+pub(super) fn init_memory<X: Xlen>(
+    memory: &mut GuardedMemory,
+    elf_image: &ElfImage<X>,
+    segments: Option<&SegmentImage>,
+) {
+    if let Some(segments) = segments {
+        match segments.load(memory) {
+            Ok(()) => return,
+            Err(err) => warn!(%err, "loading segment image failed, copying segments"),
+        }
+    }
+    memory.clear();
+    for seg in &elf_image.memory_segments {
+        let vaddr = usize::try_from(X::to_u64(seg.virtual_start))
+            .expect("segment address does not fit in host usize");
+        // Saves a fault per chunk; an uncommitted page is committed on touch anyway.
+        let _ = memory.commit(vaddr, seg.data.len());
+        unsafe { memory.copy_from(vaddr, &seg.data) };
+    }
+}
+
+/// Open the shared library at `lib_path`; the program's symbols start with
+/// `prefix`.
+fn open_library(lib_path: &Path, prefix: &str) -> Result<Symbols, RunError> {
+    if !lib_path.exists() {
+        error!(path = %lib_path.display(), "shared library not found");
+        return Err(RunError::LibraryNotFound(lib_path.display().to_string()));
+    }
+    // RTLD_NOW is required - RTLD_LAZY causes execution failures because
+    // PLT lazy resolution corrupts registers used by preserve_none functions.
+    debug!(path = %lib_path.display(), "loading shared library");
+    let lib = unsafe { Library::open(Some(lib_path), RTLD_NOW)? };
+    Ok(Symbols::Library {
+        lib,
+        prefix: prefix.to_string(),
+    })
+}
+
+/// Open the segment image of a library compiled with lazy segment init and
+/// check it against the ELF.
+fn open_segment_image<X: Xlen>(
+    path: &Path,
+    image: &ElfImage<X>,
+    memory_size: usize,
+) -> Result<SegmentImage, RunError> {
+    let error = |reason: String| RunError::SegmentImage {
+        path: path.display().to_string(),
+        reason,
+    };
+    let segments = SegmentImage::open(path).map_err(|err| error(err.to_string()))?;
+    segments.validate(image, memory_size).map_err(error)?;
+    debug!(path = %path.display(), ?segments, "mapping guest memory from segment image");
+    Ok(segments)
+}
+
+/// Whether the library was compiled for 16 registers.
+///
+/// Trusts `RV_NUM_REGS` and falls back to the ELF's RVE flag for libraries
+/// that predate it.
+pub(super) fn library_is_rve<X: Xlen>(num_regs: Option<u32>, image: &ElfImage<X>) -> bool {
+    let Some(num_regs) = num_regs else {
+        return image.is_rve();
+    };
+    if image.is_rve() != (num_regs as usize == NUM_REGS_E) {
+        warn!(
+            num_regs,
+            elf_rve = image.is_rve(),
+            "library register count does not match the ELF ABI"
+        );
+    }
+    num_regs as usize == NUM_REGS_E
+}
+
+impl Runner {
+    pub(super) fn setup_initial_regs(&mut self) {
+        if let Some(gp) = self.inner.lookup_symbol("__global_pointer$") {
+            self.inner.set_register(REG_GP as usize, gp);
+        }
+        let stack_top = self
+            .inner
+            .lookup_symbol(STACK_TOP_SYMBOL)
+            .or_else(|| self.layout.map(|layout| layout.stack.end))
+            .or_else(|| self.memory_layout.as_ref().map(|layout| layout.stack.end));
+        if let Some(mut sp) = stack_top {
+            if !self.args.is_empty() || !self.env.is_empty() {
+                let (args_sp, stack) = argv::initial_stack(sp, self.xlen(), &self.args, &self.env);
+                self.inner.write_memory(args_sp, &stack);
+                sp = args_sp;
+            }
+            self.inner.set_register(REG_SP as usize, sp);
+        }
+        // Trap on unexpected returns from entry points.
+        self.inner.set_register(REG_RA as usize, 0);
+    }
+
+    /// Load a compiled shared library and its corresponding ELF with default memory size.
+    ///
+    /// # Errors
+    /// Returns an error if the library or ELF cannot be loaded.
+    pub fn load(lib_dir: impl AsRef<Path>, elf_path: impl AsRef<Path>) -> Result<Self, RunError> {
+        Self::load_with_memory(lib_dir, elf_path, DEFAULT_MEMORY_SIZE)
+    }
+
+    /// Load a compiled shared library and its corresponding ELF with specified memory size.
+    ///
+    /// # Errors
+    /// Returns an error if the library or ELF cannot be loaded.
+    pub fn load_with_memory(
+        lib_dir: impl AsRef<Path>,
+        elf_path: impl AsRef<Path>,
+        memory_size: usize,
+    ) -> Result<Self, RunError> {
+        let start = Instant::now();
+        let lib_dir = lib_dir.as_ref();
+
+        // Derive library name from directory name
+        let dir_name = lib_dir.file_name().and_then(|n| n.to_str()).unwrap_or("rv");
+        let segments_path = segment_image_path(lib_dir, dir_name);
+        Self::open(
+            start,
+            &library_path(lib_dir),
+            "",
+            &segments_path,
+            elf_path.as_ref(),
+            memory_size,
+        )
+    }
+
+    /// Load a shared library whose symbols start with `prefix` (compiled
+    /// with a symbol prefix), and its ELF.
+    ///
+    /// # Errors
+    /// Returns an error if the library or ELF cannot be loaded.
+    pub fn load_with_prefix(
+        lib_dir: impl AsRef<Path>,
+        prefix: &str,
+        elf_path: impl AsRef<Path>,
+    ) -> Result<Self, RunError> {
+        let start = Instant::now();
+        let lib_dir = lib_dir.as_ref();
+        let dir_name = lib_dir.file_name().and_then(|n| n.to_str()).unwrap_or("rv");
+        Self::open(
+            start,
+            &library_path(lib_dir),
+            prefix,
+            &segment_image_path(lib_dir, dir_name),
+            elf_path.as_ref(),
+            DEFAULT_MEMORY_SIZE,
+        )
+    }
+
+    /// Load a program statically linked into the host (compiled with a
+    /// static archive), and its ELF.
+    ///
+    /// `info` is the program's `rv_embed_info`, declared in an `extern "C"`
+    /// block under its prefixed name. Execution is the same as for a
+    /// loaded library, except that lazy segment init is unsupported.
+    ///
+    /// # Errors
+    /// Returns an error if the ELF cannot be loaded or its XLEN differs
+    /// from the program's.
+    pub fn load_embedded(
+        info: &'static RvEmbedInfo,
+        elf_path: impl AsRef<Path>,
+    ) -> Result<Self, RunError> {
+        let start = Instant::now();
+        let elf_path = elf_path.as_ref();
+        if !elf_path.exists() {
+            error!(path = %elf_path.display(), "ELF file not found");
+            return Err(RunError::ElfNotFound(elf_path.display().to_string()));
+        }
+
+        let elf_data = std::fs::read(elf_path)?;
+        let xlen = get_elf_xlen(&elf_data)?;
+        if info.xlen != u32::from(xlen) {
+            return Err(RunError::XlenMismatch {
+                embedded: info.xlen,
+                elf: xlen,
+            });
+        }
+        Self::open_elf(
+            start,
+            Symbols::Embedded(info),
+            None,
+            &elf_data,
+            xlen,
+            elf_path,
+            DEFAULT_MEMORY_SIZE,
+        )
+    }
+
+    /// Load one program from a library built by
+    /// [`Recompiler::compile_many`](crate::Recompiler::compile_many).
+    ///
+    /// `name` selects the program's symbols in the combined library; its
+    /// memory size comes from the library's [`ProgramManifest`].
+    ///
+    /// # Errors
+    /// Returns an error if the manifest does not list `name`, or the
+    /// library or ELF cannot be loaded.
+    pub fn load_program(
+        lib_dir: impl AsRef<Path>,
+        name: &str,
+        elf_path: impl AsRef<Path>,
+    ) -> Result<Self, RunError> {
+        let start = Instant::now();
+        let lib_dir = lib_dir.as_ref();
+        let manifest = ProgramManifest::load(lib_dir)?;
+        let program = manifest
+            .get(name)
+            .ok_or_else(|| RunError::ProgramNotFound(name.to_string()))?;
+        let memory_size = usize::try_from(program.memory_size).unwrap_or(usize::MAX);
+        Self::open(
+            start,
+            &library_path(lib_dir),
+            &program.symbol_prefix(),
+            &segment_image_path(&lib_dir.join(name), name),
+            elf_path.as_ref(),
+            memory_size,
+        )
+    }
+
+    /// Load a library compiled from a [`SyntheticProgram`] with
+    /// [`Recompiler::compile_program`](crate::Recompiler::compile_program).
+    ///
+    /// The program stands in for the ELF: it gives the entry point and the
+    /// data segments. There are no symbols, so backtraces are empty.
+    ///
+    /// # Errors
+    /// Returns an error if the library cannot be loaded.
+    pub fn load_synthetic<X: Xlen + 'static>(
+        lib_dir: impl AsRef<Path>,
+        program: &SyntheticProgram<X>,
+    ) -> Result<Self, RunError> {
+        let start = Instant::now();
+        let lib_dir = lib_dir.as_ref();
+        let dir_name = lib_dir.file_name().and_then(|n| n.to_str()).unwrap_or("rv");
+        Self::open_image(
+            start,
+            &library_path(lib_dir),
+            "",
+            &segment_image_path(lib_dir, dir_name),
+            |_| Ok(program.image()),
+            None,
+            DEFAULT_MEMORY_SIZE,
+        )
+    }
+
+    /// Open `lib_path` and load the program whose symbols start with
+    /// `prefix`.
+    pub(super) fn open(
+        start: Instant,
+        lib_path: &Path,
+        prefix: &str,
+        segments_path: &Path,
+        elf_path: &Path,
+        memory_size: usize,
+    ) -> Result<Self, RunError> {
+        if !elf_path.exists() {
+            error!(path = %elf_path.display(), "ELF file not found");
+            return Err(RunError::ElfNotFound(elf_path.display().to_string()));
+        }
+
+        let elf_data = std::fs::read(elf_path)?;
+        let xlen = get_elf_xlen(&elf_data)?;
+        let symbols = open_library(lib_path, prefix)?;
+        Self::open_elf(
+            start,
+            symbols,
+            Some(segments_path),
+            &elf_data,
+            xlen,
+            elf_path,
+            memory_size,
+        )
+    }
+
+    /// Load the program `symbols` looks up with the ELF `elf_data` of
+    /// width `xlen`.
+    fn open_elf(
+        start: Instant,
+        symbols: Symbols,
+        segments_path: Option<&Path>,
+        elf_data: &[u8],
+        xlen: u8,
+        elf_path: &Path,
+        memory_size: usize,
+    ) -> Result<Self, RunError> {
+        if xlen == Rv32::VALUE {
+            Self::from_symbols(
+                start,
+                symbols,
+                segments_path,
+                |load_bias| ElfImage::<Rv32>::parse_with_load_bias(elf_data, load_bias),
+                Some(elf_path),
+                memory_size,
+            )
+        } else {
+            Self::from_symbols(
+                start,
+                symbols,
+                segments_path,
+                |load_bias| ElfImage::<Rv64>::parse_with_load_bias(elf_data, load_bias),
+                Some(elf_path),
+                memory_size,
+            )
+        }
+    }
+
+    /// Open `lib_path` and load the program whose symbols start with
+    /// `prefix`, with the image `load_image` returns for the library's
+    /// load bias.
+    fn open_image<X: Xlen + 'static>(
+        start: Instant,
+        lib_path: &Path,
+        prefix: &str,
+        segments_path: &Path,
+        load_image: impl FnOnce(Option<u64>) -> Result<ElfImage<X>, rvr_elf::ElfError>,
+        elf_path: Option<&Path>,
+        memory_size: usize,
+    ) -> Result<Self, RunError> {
+        let symbols = open_library(lib_path, prefix)?;
+        Self::from_symbols(
+            start,
+            symbols,
+            Some(segments_path),
+            load_image,
+            elf_path,
+            memory_size,
+        )
+    }
+
+    /// Load the program `symbols` looks up, with the image `load_image`
+    /// returns for its load bias. Shared libraries and programs linked into
+    /// the host differ only in `symbols`.
+    ///
+    /// `segments_path` is the segment image of a program compiled with lazy
+    /// segment init (`None` if there is none to map).
+    fn from_symbols<X: Xlen + 'static>(
+        start: Instant,
+        symbols: Symbols,
+        segments_path: Option<&Path>,
+        load_image: impl FnOnce(Option<u64>) -> Result<ElfImage<X>, rvr_elf::ElfError>,
+        elf_path: Option<&Path>,
+        memory_size: usize,
+    ) -> Result<Self, RunError> {
+        let api = unsafe { RvApi::load::<X>(&symbols)? };
+        let quarantine = unsafe { load_quarantine(&symbols) };
+        let preopen_paths = unsafe { load_preopens(&symbols) };
+        let tracer_kind = TracerKind::from_raw(api.tracer_kind);
+        let instret_mode = InstretMode::from_raw(api.instret_mode);
+
+        // Load the image and create typed runner
+        let image = load_image(api.load_bias)?;
+        let initial_brk = X::to_u64(image.get_initial_program_break());
+        let layout = unsafe { load_layout(&symbols) };
+        if let Some((profile, regions)) = &layout {
+            regions
+                .validate(&image_layout(&image))
+                .map_err(|mismatch| LayoutError::new(profile.as_str(), mismatch))?;
+        }
+        let image_segments: Vec<AddrRange> = image_layout(&image)
+            .segments
+            .iter()
+            .map(|s| s.range)
+            .collect();
+        let memory_layout = unsafe { load_memory_layout(&symbols) }
+            .map(|words| MemoryLayout::from_words(words, image_segments.clone()));
+
+        let segments = if api.lazy_segments {
+            let path = segments_path.ok_or(RunError::EmbeddedUnsupported("lazy segment init"))?;
+            Some(open_segment_image(path, &image, memory_size)?)
+        } else {
+            None
+        };
+
+        // Use fixed-address runner if the library was compiled with fixed addresses
+        let inner = if let Some(fixed) = api.fixed_addresses {
+            debug!(
+                state_addr = format!("{:#x}", fixed.state_addr),
+                memory_addr = format!("{:#x}", fixed.memory_addr),
+                "using fixed addresses"
+            );
+            create_fixed_addr_runner(image, api.num_regs, fixed, memory_size)?
+        } else {
+            create_runner_impl(
+                image,
+                api.num_regs,
+                tracer_kind,
+                instret_mode,
+                api.tracer_buffers,
+                memory_size,
+            )?
+        };
+
+        trace!(
+            entry_point = format!("{:#x}", inner.entry_point()),
+            tracer_kind = ?tracer_kind,
+            instret_mode = ?instret_mode,
+            memory_size = memory_size,
+            fixed_addresses = api.fixed_addresses.is_some(),
+            "loaded runner"
+        );
+
+        Ok(Self {
+            _symbols: symbols,
+            api,
+            inner,
+            quarantine,
+            preopen_paths,
+            layout: layout.map(|(_, regions)| regions),
+            memory_layout,
+            image_segments,
+            host_buffers: Vec::new(),
+            hooks: None,
+            journal: None,
+            segments,
+            load_secs: start.elapsed().as_secs_f64(),
+            elf_path: elf_path.map(Path::to_path_buf),
+            args: Vec::new(),
+            env: Vec::new(),
+            initial_brk,
+            cancel: Arc::default(),
+            pristine: PristineMemory::Unset,
+        })
+    }
+}
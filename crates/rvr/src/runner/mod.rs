@@ -2,413 +2,162 @@
 //!
 //! State management is handled in Rust; only the hot execution loop is in C.
 //! Uses trait-based type erasure to support RV32/RV64 × I/E × Tracer variants.
-
 mod api;
+mod argv;
+mod backtrace;
+mod block_profile;
 mod buffered_diff;
+mod cancel;
+mod coverage;
 mod debug;
 mod diff;
+mod env;
 mod error;
+mod exec;
+mod exit;
+mod factory;
 mod fixed;
+mod host;
+mod host_buffer;
+mod host_profile;
+mod io;
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+mod isolated;
+mod jumps;
+mod load;
+mod page_access;
 mod preflight;
+mod preopen;
+mod reload;
+mod replay;
+mod reset;
+mod result;
+mod snapshot;
+mod state_file;
 mod stats;
 mod suspend;
+mod symbols;
 mod traits;
 mod typed;
 
+use io::HostHooks;
 use traits::BufferedDiffEntry;
 
-use std::fs::File;
-use std::io::{BufReader, BufWriter, Read as IoRead, Write as IoWrite};
-use std::path::Path;
-use std::time::Instant;
-
-use libloading::os::unix::{Library, RTLD_NOW};
-use rvr_elf::{ElfImage, get_elf_xlen};
-use rvr_ir::{Rv32, Rv64};
-use rvr_isa::{REG_GP, REG_RA, REG_SP};
-use rvr_state::{DEFAULT_MEMORY_SIZE, GuardedMemory, NUM_REGS_E, NUM_REGS_I};
-use tracing::{debug, error, trace};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-fn u64_to_f64(value: u64) -> f64 {
-    let hi = u32::try_from(value >> 32).unwrap_or(u32::MAX);
-    let lo = u32::try_from(value & 0xFFFF_FFFF).unwrap_or(u32::MAX);
-    f64::from(hi) * 4_294_967_296.0 + f64::from(lo)
-}
+use rvr_emit::htif::FROMHOST_ADDR;
+use rvr_emit::{AddrRange, LayoutRegions, MemoryLayout};
+use rvr_isa::extensions::counter_csr_value;
 
-fn usize_to_f64(value: usize) -> f64 {
-    let value = u64::try_from(value).unwrap_or(u64::MAX);
-    u64_to_f64(value)
-}
+use crate::segment_image::SegmentImage;
+use symbols::Symbols;
 
-pub use api::{FixedAddresses, InstretMode, RvApi, TracerKind};
+pub use api::{
+    FixedAddresses, InstretMode, PageBitmapSize, ProfileSlots, RvApi, TracerBuffers, TracerKind,
+};
+pub use backtrace::Frame;
+pub use cancel::CancelHandle;
 pub use error::RunError;
+pub use exit::{ExitReason, TrapCause};
+pub use io::{CsrHook, GuestContext, HookFn, SyscallFn};
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+pub use isolated::{
+    HELPER_ENV, IsolatedRunner, IsolatedSnapshot, IsolatedState, IsolationOptions, serve_isolated,
+};
+pub use jumps::JumpSite;
+pub use page_access::PageAccessLog;
+pub use replay::InputRecording;
+pub use result::{PerfCounters, RunPhases, RunResult, RunResultWithPerf};
+pub use snapshot::Snapshot;
+pub use symbols::{RvEmbedInfo, RvEmbedSymbol};
 pub use traits::RunnerImpl;
 
-use buffered_diff::BufferedDiffRunner;
-use debug::DebugRunner;
-use diff::DiffRunner;
-use fixed::FixedAddrRunner;
-use preflight::PreflightRunner;
-use stats::StatsRunner;
-use suspend::SuspendRunner;
-use typed::TypedRunner;
-
-// ============================================================================
-// Factory functions
-// ============================================================================
-
-/// Create runner implementation based on architecture, tracer, and instret mode.
-fn create_runner_impl(
-    elf_data: &[u8],
-    tracer_kind: TracerKind,
-    instret_mode: InstretMode,
-    memory_size: usize,
-) -> Result<Box<dyn RunnerImpl>, RunError> {
-    let memory = GuardedMemory::new(memory_size)?;
-    let xlen = get_elf_xlen(elf_data)?;
-
-    match xlen {
-        32 => create_rv32_runner(elf_data, tracer_kind, instret_mode, memory),
-        64 => create_rv64_runner(elf_data, tracer_kind, instret_mode, memory),
-        _ => unreachable!("get_elf_xlen only returns 32 or 64"),
-    }
-}
-
-/// Create runner implementation with fixed addresses for state and memory.
-fn create_fixed_addr_runner(
-    elf_data: &[u8],
-    fixed: FixedAddresses,
-    memory_size: usize,
-) -> Result<Box<dyn RunnerImpl>, RunError> {
-    let xlen = get_elf_xlen(elf_data)?;
-
-    match xlen {
-        32 => {
-            let image = ElfImage::<Rv32>::parse(elf_data)?;
-            let is_rve = image.is_rve();
-            if is_rve {
-                Ok(Box::new(FixedAddrRunner::<Rv32, NUM_REGS_E>::new(
-                    image,
-                    fixed,
-                    memory_size,
-                )?))
-            } else {
-                Ok(Box::new(FixedAddrRunner::<Rv32, NUM_REGS_I>::new(
-                    image,
-                    fixed,
-                    memory_size,
-                )?))
-            }
-        }
-        64 => {
-            let image = ElfImage::<Rv64>::parse(elf_data)?;
-            let is_rve = image.is_rve();
-            if is_rve {
-                Ok(Box::new(FixedAddrRunner::<Rv64, NUM_REGS_E>::new(
-                    image,
-                    fixed,
-                    memory_size,
-                )?))
-            } else {
-                Ok(Box::new(FixedAddrRunner::<Rv64, NUM_REGS_I>::new(
-                    image,
-                    fixed,
-                    memory_size,
-                )?))
-            }
-        }
-        _ => unreachable!("get_elf_xlen only returns 32 or 64"),
-    }
-}
-
-fn create_rv32_runner(
-    elf_data: &[u8],
-    tracer_kind: TracerKind,
-    instret_mode: InstretMode,
-    memory: GuardedMemory,
-) -> Result<Box<dyn RunnerImpl>, RunError> {
-    let image = ElfImage::<Rv32>::parse(elf_data)?;
-    let is_rve = image.is_rve();
-
-    match (tracer_kind, is_rve) {
-        (TracerKind::Preflight, false) => Ok(Box::new(PreflightRunner::<Rv32, NUM_REGS_I>::new(
-            image, memory,
-        ))),
-        (TracerKind::Preflight, true) => Ok(Box::new(PreflightRunner::<Rv32, NUM_REGS_E>::new(
-            image, memory,
-        ))),
-        (TracerKind::Stats, false) => Ok(Box::new(StatsRunner::<Rv32, NUM_REGS_I>::new(
-            image, memory,
-        ))),
-        (TracerKind::Stats, true) => Ok(Box::new(StatsRunner::<Rv32, NUM_REGS_E>::new(
-            image, memory,
-        ))),
-        (TracerKind::Debug, false) => Ok(Box::new(DebugRunner::<Rv32, NUM_REGS_I>::new(
-            image, memory,
-        ))),
-        (TracerKind::Debug, true) => Ok(Box::new(DebugRunner::<Rv32, NUM_REGS_E>::new(
-            image, memory,
-        ))),
-        (TracerKind::Diff, false) => {
-            Ok(Box::new(DiffRunner::<Rv32, NUM_REGS_I>::new(image, memory)))
-        }
-        (TracerKind::Diff, true) => {
-            Ok(Box::new(DiffRunner::<Rv32, NUM_REGS_E>::new(image, memory)))
-        }
-        (TracerKind::BufferedDiff, false) => Ok(Box::new(
-            BufferedDiffRunner::<Rv32, NUM_REGS_I>::new(image, memory),
-        )),
-        (TracerKind::BufferedDiff, true) => Ok(Box::new(
-            BufferedDiffRunner::<Rv32, NUM_REGS_E>::new(image, memory),
-        )),
-        (_, false) if instret_mode.is_suspend() => Ok(Box::new(
-            SuspendRunner::<Rv32, NUM_REGS_I>::new(image, memory),
-        )),
-        (_, true) if instret_mode.is_suspend() => Ok(Box::new(
-            SuspendRunner::<Rv32, NUM_REGS_E>::new(image, memory),
-        )),
-        (_, false) => Ok(Box::new(TypedRunner::<Rv32, (), NUM_REGS_I>::new(
-            image, memory,
-        ))),
-        (_, true) => Ok(Box::new(TypedRunner::<Rv32, (), NUM_REGS_E>::new(
-            image, memory,
-        ))),
-    }
-}
-
-fn create_rv64_runner(
-    elf_data: &[u8],
-    tracer_kind: TracerKind,
-    instret_mode: InstretMode,
-    memory: GuardedMemory,
-) -> Result<Box<dyn RunnerImpl>, RunError> {
-    let image = ElfImage::<Rv64>::parse(elf_data)?;
-    let is_rve = image.is_rve();
-
-    match (tracer_kind, is_rve) {
-        (TracerKind::Preflight, false) => Ok(Box::new(PreflightRunner::<Rv64, NUM_REGS_I>::new(
-            image, memory,
-        ))),
-        (TracerKind::Preflight, true) => Ok(Box::new(PreflightRunner::<Rv64, NUM_REGS_E>::new(
-            image, memory,
-        ))),
-        (TracerKind::Stats, false) => Ok(Box::new(StatsRunner::<Rv64, NUM_REGS_I>::new(
-            image, memory,
-        ))),
-        (TracerKind::Stats, true) => Ok(Box::new(StatsRunner::<Rv64, NUM_REGS_E>::new(
-            image, memory,
-        ))),
-        (TracerKind::Debug, false) => Ok(Box::new(DebugRunner::<Rv64, NUM_REGS_I>::new(
-            image, memory,
-        ))),
-        (TracerKind::Debug, true) => Ok(Box::new(DebugRunner::<Rv64, NUM_REGS_E>::new(
-            image, memory,
-        ))),
-        (TracerKind::Diff, false) => {
-            Ok(Box::new(DiffRunner::<Rv64, NUM_REGS_I>::new(image, memory)))
-        }
-        (TracerKind::Diff, true) => {
-            Ok(Box::new(DiffRunner::<Rv64, NUM_REGS_E>::new(image, memory)))
-        }
-        (TracerKind::BufferedDiff, false) => Ok(Box::new(
-            BufferedDiffRunner::<Rv64, NUM_REGS_I>::new(image, memory),
-        )),
-        (TracerKind::BufferedDiff, true) => Ok(Box::new(
-            BufferedDiffRunner::<Rv64, NUM_REGS_E>::new(image, memory),
-        )),
-        (_, false) if instret_mode.is_suspend() => Ok(Box::new(
-            SuspendRunner::<Rv64, NUM_REGS_I>::new(image, memory),
-        )),
-        (_, true) if instret_mode.is_suspend() => Ok(Box::new(
-            SuspendRunner::<Rv64, NUM_REGS_E>::new(image, memory),
-        )),
-        (_, false) => Ok(Box::new(TypedRunner::<Rv64, (), NUM_REGS_I>::new(
-            image, memory,
-        ))),
-        (_, true) => Ok(Box::new(TypedRunner::<Rv64, (), NUM_REGS_E>::new(
-            image, memory,
-        ))),
-    }
-}
-
-// ============================================================================
-// Public API types
-// ============================================================================
+use cancel::CancelState;
+use load::init_memory;
+use replay::Journal;
+use reset::PristineMemory;
 
-/// Execution result.
-#[derive(Debug, Clone)]
-pub struct RunResult {
-    /// Exit code from the program.
-    pub exit_code: u8,
-    /// Instruction count (guest instructions retired).
-    pub instret: u64,
-    /// Wall-clock time in seconds.
-    pub time_secs: f64,
-    /// Speed in MIPS (million instructions per second).
-    pub mips: f64,
+/// Shared library in `lib_dir`, named after the directory.
+pub fn library_path(lib_dir: &Path) -> PathBuf {
+    let dir_name = lib_dir.file_name().and_then(|n| n.to_str()).unwrap_or("rv");
+    lib_dir.join(format!("lib{dir_name}.so"))
 }
 
-impl RunResult {
-    /// Print result in raw key-value format (for scripting).
-    pub fn print_raw_format(&self) {
-        println!("instret: {}", self.instret);
-        println!("time: {:.6}", self.time_secs);
-        println!("mips: {:.2}", self.mips);
-    }
-
-    /// Print result in JSON format.
-    pub fn print_json(&self) {
-        println!(
-            r#"{{"instret":{},"time":{:.6},"mips":{:.2},"exit_code":{}}}"#,
-            self.instret, self.time_secs, self.mips, self.exit_code
-        );
-    }
-}
-
-/// Hardware performance counters from perf.
-#[derive(Debug, Clone, Default)]
-pub struct PerfCounters {
-    /// Host CPU cycles.
-    pub cycles: Option<u64>,
-    /// Host instructions executed.
-    pub instructions: Option<u64>,
-    /// Branch instructions.
-    pub branches: Option<u64>,
-    /// Branch misses.
-    pub branch_misses: Option<u64>,
-}
-
-impl PerfCounters {
-    /// Calculate instructions per cycle.
-    #[must_use]
-    pub fn ipc(&self) -> Option<f64> {
-        match (self.instructions, self.cycles) {
-            (Some(i), Some(c)) if c > 0 => Some(u64_to_f64(i) / u64_to_f64(c)),
-            _ => None,
-        }
-    }
-
-    /// Calculate branch miss rate as percentage.
-    #[must_use]
-    pub fn branch_miss_rate(&self) -> Option<f64> {
-        match (self.branch_misses, self.branches) {
-            (Some(m), Some(b)) if b > 0 => Some((u64_to_f64(m) / u64_to_f64(b)) * 100.0),
-            _ => None,
-        }
-    }
-}
-
-/// Execution result with hardware performance counters.
-#[derive(Debug, Clone)]
-pub struct RunResultWithPerf {
-    /// Core execution result.
-    pub result: RunResult,
-    /// Hardware performance counters (if available).
-    pub perf: Option<PerfCounters>,
-}
-
-// ============================================================================
-// Runner - public API
-// ============================================================================
-
 /// Runner for compiled RISC-V programs.
 ///
 /// State is managed entirely in Rust; only the execution loop is in C.
 pub struct Runner {
-    _lib: Library,
+    /// Where the program's symbols come from (keeps a shared library open).
+    _symbols: Symbols,
     api: RvApi,
     inner: Box<dyn RunnerImpl>,
+    /// Quarantined stub PCs and the lift errors they replaced.
+    quarantine: HashMap<u64, String>,
+    /// Guest paths the library's syscall policy allows to preopen (any
+    /// path when `None`).
+    preopen_paths: Option<Vec<String>>,
+    /// Layout the library was compiled for (checked against the ELF at load).
+    layout: Option<LayoutRegions>,
+    /// Heap and stack placement the library was compiled with.
+    memory_layout: Option<MemoryLayout>,
+    /// Loadable segments of the ELF.
+    image_segments: Vec<AddrRange>,
+    /// Guest ranges of mapped host buffers and whether they are writable.
+    host_buffers: Vec<(AddrRange, bool)>,
+    /// Redirected guest stdio and CSR hook (host stdio and CSR slots when
+    /// `None`).
+    hooks: Option<HostHooks>,
+    /// Recorder or replayer of host inputs wrapping `hooks`.
+    journal: Option<Box<Journal>>,
+    /// Segment image guest memory is mapped from (lazy segment init).
+    segments: Option<SegmentImage>,
+    /// Load time not yet charged to a run.
+    load_secs: f64,
+    /// ELF the library was compiled from (symbols and CFI for backtraces);
+    /// `None` for synthetic programs.
+    elf_path: Option<PathBuf>,
+    /// Guest command line (`argv[0]` first); nothing is put on the stack
+    /// when empty.
+    args: Vec<String>,
+    /// Guest environment as `KEY=VALUE` strings, in the order set.
+    env: Vec<String>,
+    /// Program break the ELF starts the guest with.
+    initial_brk: u64,
+    /// Cancellation shared with [`CancelHandle`]s.
+    cancel: Arc<CancelState>,
+    /// Initialized guest memory for [`Runner::reset_memory_fast`].
+    pristine: PristineMemory,
 }
 
 impl Runner {
-    fn setup_initial_regs(&mut self) {
-        if let Some(gp) = self.inner.lookup_symbol("__global_pointer$") {
-            self.inner.set_register(REG_GP as usize, gp);
-        }
-        if let Some(sp) = self.inner.lookup_symbol("__stack_top") {
-            self.inner.set_register(REG_SP as usize, sp);
-        }
-        // Trap on unexpected returns from entry points.
-        self.inner.set_register(REG_RA as usize, 0);
-    }
-    /// Load a compiled shared library and its corresponding ELF with default memory size.
-    ///
-    /// # Errors
-    /// Returns an error if the library or ELF cannot be loaded.
-    pub fn load(lib_dir: impl AsRef<Path>, elf_path: impl AsRef<Path>) -> Result<Self, RunError> {
-        Self::load_with_memory(lib_dir, elf_path, DEFAULT_MEMORY_SIZE)
+    /// Check if library was compiled with export functions mode.
+    #[must_use]
+    pub const fn has_export_functions(&self) -> bool {
+        self.api.export_functions
     }
 
-    /// Load a compiled shared library and its corresponding ELF with specified memory size.
+    /// Check if library was compiled with fixed state and memory addresses.
     ///
-    /// # Errors
-    /// Returns an error if the library or ELF cannot be loaded.
-    pub fn load_with_memory(
-        lib_dir: impl AsRef<Path>,
-        elf_path: impl AsRef<Path>,
-        memory_size: usize,
-    ) -> Result<Self, RunError> {
-        let lib_dir = lib_dir.as_ref();
-        let elf_path = elf_path.as_ref();
-
-        // Derive library name from directory name
-        let dir_name = lib_dir.file_name().and_then(|n| n.to_str()).unwrap_or("rv");
-        let lib_path = lib_dir.join(format!("lib{dir_name}.so"));
-
-        if !lib_path.exists() {
-            error!(path = %lib_path.display(), "shared library not found");
-            return Err(RunError::LibraryNotFound(lib_path.display().to_string()));
-        }
-
-        if !elf_path.exists() {
-            error!(path = %elf_path.display(), "ELF file not found");
-            return Err(RunError::ElfNotFound(elf_path.display().to_string()));
-        }
-
-        // Load library and API
-        // RTLD_NOW is required - RTLD_LAZY causes execution failures because
-        // PLT lazy resolution corrupts registers used by preserve_none functions.
-        debug!(path = %lib_path.display(), "loading shared library");
-        let lib = unsafe { Library::open(Some(&lib_path), RTLD_NOW)? };
-        let api = unsafe { RvApi::load(&lib)? };
-        let tracer_kind = TracerKind::from_raw(api.tracer_kind);
-        let instret_mode = InstretMode::from_raw(api.instret_mode);
-
-        // Load ELF and create typed runner
-        let elf_data = std::fs::read(elf_path)?;
-
-        // Use fixed-address runner if the library was compiled with fixed addresses
-        let inner = if let Some(fixed) = api.fixed_addresses {
-            debug!(
-                state_addr = format!("{:#x}", fixed.state_addr),
-                memory_addr = format!("{:#x}", fixed.memory_addr),
-                "using fixed addresses"
-            );
-            create_fixed_addr_runner(&elf_data, fixed, memory_size)?
-        } else {
-            create_runner_impl(&elf_data, tracer_kind, instret_mode, memory_size)?
-        };
-
-        trace!(
-            entry_point = format!("{:#x}", inner.entry_point()),
-            tracer_kind = ?tracer_kind,
-            instret_mode = ?instret_mode,
-            memory_size = memory_size,
-            fixed_addresses = api.fixed_addresses.is_some(),
-            "loaded runner"
-        );
-
-        Ok(Self {
-            _lib: lib,
-            api,
-            inner,
-        })
+    /// Such libraries use one global state, so only one runner can execute
+    /// at a time.
+    #[must_use]
+    pub const fn has_fixed_addresses(&self) -> bool {
+        self.api.fixed_addresses.is_some()
     }
 
-    /// Check if library was compiled with export functions mode.
+    /// Instructions per tracer sample: the tracer hooks see one instruction
+    /// in this many (1 when the tracer sees every instruction).
     #[must_use]
-    pub const fn has_export_functions(&self) -> bool {
-        self.api.export_functions
+    pub const fn tracer_sample_interval(&self) -> u32 {
+        self.api.sample_interval
     }
 
     /// Look up a symbol by name and return its address.
@@ -423,12 +172,6 @@ impl Runner {
         self.inner.entry_point()
     }
 
-    /// Load segments and reset state for a fresh run.
-    pub fn prepare(&mut self) {
-        self.inner.load_segments();
-        self.inner.reset();
-    }
-
     /// Set a register value.
     pub fn set_register(&mut self, reg: usize, value: u64) {
         self.inner.set_register(reg, value);
@@ -457,6 +200,34 @@ impl Runner {
         self.inner.has_exited()
     }
 
+    /// Why execution last stopped, decoded from the exit fields of the state
+    /// (plus `fromhost` and the last HTIF syscall for an HTIF stall).
+    #[must_use]
+    pub fn exit_reason(&self) -> ExitReason {
+        let inner = &self.inner;
+        let reason = ExitReason::decode(
+            inner.has_exited(),
+            inner.exit_code(),
+            inner.exit_cause(),
+            inner.exit_info(),
+            inner.get_pc(),
+            inner.instret(),
+        );
+        match reason {
+            ExitReason::HtifStall { pc, tohost, .. } => {
+                let mut fromhost = [0u8; 8];
+                inner.read_memory(FROMHOST_ADDR, &mut fromhost);
+                ExitReason::HtifStall {
+                    pc,
+                    tohost,
+                    fromhost: u64::from_le_bytes(fromhost),
+                    syscall: Some(inner.htif_syscall()).filter(|&num| num != 0),
+                }
+            }
+            reason => reason,
+        }
+    }
+
     /// Get a register value.
     #[must_use]
     pub fn get_register(&self, reg: usize) -> u64 {
@@ -475,13 +246,27 @@ impl Runner {
     }
 
     /// Get a CSR (Control and Status Register) value.
+    ///
+    /// Counters read as the guest sees them (see
+    /// [`counter_csr_value`](crate::counter_csr_value)),
+    /// including the `*h` halves on RV32. Every other CSR reads its
+    /// `RvState::csrs` slot, including custom storage CSRs. Hook CSRs only
+    /// use the slot until [`set_csr_hook`](Self::set_csr_hook) is called.
     #[must_use]
     pub fn get_csr(&self, csr: u16) -> u64 {
-        self.inner.get_csr(csr)
+        counter_csr_value(csr, self.inner.instret(), self.inner.xlen())
+            .unwrap_or_else(|| self.inner.get_csr(csr))
     }
 
     /// Set a CSR (Control and Status Register) value.
+    ///
+    /// Writes the CSR's `RvState::csrs` slot (see [`get_csr`](Self::get_csr)).
+    /// Counters are read-only, as they are for the guest, so writes to them
+    /// are ignored.
     pub fn set_csr(&mut self, csr: u16, value: u64) {
+        if counter_csr_value(csr, 0, self.inner.xlen()).is_some() {
+            return;
+        }
         self.inner.set_csr(csr, value);
     }
 
@@ -492,9 +277,18 @@ impl Runner {
     }
 
     /// Write memory at the given address from the buffer.
+    ///
+    /// Stops short of read-only host buffers
+    /// ([`map_host_buffer`](Self::map_host_buffer)).
     #[must_use]
     pub fn write_memory(&mut self, addr: u64, data: &[u8]) -> usize {
-        self.inner.write_memory(addr, data)
+        let len = self.writable_len(addr, data.len());
+        self.inner.write_memory(addr, &data[..len])
+    }
+
+    /// Address of the guest state (`RvState`) in this process.
+    pub(crate) fn state_addr(&mut self) -> usize {
+        self.inner.as_void_ptr() as usize
     }
 
     /// Get the number of general-purpose registers (16 for E extension, 32 for I).
@@ -515,6 +309,19 @@ impl Runner {
         self.inner.memory_size()
     }
 
+    /// Bytes of guest memory resident in host memory, if they can be
+    /// measured.
+    ///
+    /// Guest memory is reserved up front but committed as the guest touches
+    /// it, so this is the guest's actual footprint rather than
+    /// [`memory_size`](Self::memory_size).
+    #[must_use]
+    pub fn resident_bytes(&self) -> Option<u64> {
+        self.inner
+            .resident_bytes()
+            .and_then(|bytes| u64::try_from(bytes).ok())
+    }
+
     /// Check if the runner supports suspend mode (for single-stepping).
     #[must_use]
     pub fn supports_suspend(&self) -> bool {
@@ -564,6 +371,12 @@ impl Runner {
         self.inner.diff_traced_mem()
     }
 
+    /// Get CSR write info: (csr, value).
+    #[must_use]
+    pub fn diff_traced_csr(&self) -> Option<(u16, u64)> {
+        self.inner.diff_traced_csr()
+    }
+
     /// Check if diff tracer captured valid state.
     #[must_use]
     pub fn diff_tracer_valid(&self) -> bool {
@@ -590,7 +403,8 @@ impl Runner {
         self.inner.buffered_diff_dropped()
     }
 
-    /// Get buffered diff entry at index: (pc, opcode, rd, `rd_value`, `mem_access`).
+    /// Get buffered diff entry at index: (pc, opcode, rd, `rd_value`, `mem_access`, `csr_write`,
+    /// instret).
     #[must_use]
     pub fn buffered_diff_get(&self, index: usize) -> Option<BufferedDiffEntry> {
         self.inner.buffered_diff_get(index)
@@ -601,6 +415,57 @@ impl Runner {
         self.inner.buffered_diff_reset();
     }
 
+    // Page access tracer methods - available when compiled with --tracer page-access
+
+    /// Guest pages read and written since the last [`prepare`](Self::prepare).
+    ///
+    /// To recover the pre-images of the pages a run depends on, take a
+    /// [`snapshot`](Self::snapshot) after `prepare` and pass it to
+    /// [`PageAccessLog::pre_images`] once the run finishes.
+    #[must_use]
+    pub fn page_access_log(&self) -> Option<PageAccessLog> {
+        self.inner.page_access_log()
+    }
+
+    // Block profile tracer methods - available when compiled with --tracer block-profile
+
+    /// Entry count of each block entered since the last [`prepare`](Self::prepare),
+    /// as `(guest_pc, count)` in ascending PC order.
+    ///
+    /// Code merged into a block (superblocks, merged and tail-duplicated
+    /// blocks) is counted at the block's entry PC. See [`crate::BlockProfile`]
+    /// for symbolizing the counts.
+    #[must_use]
+    pub fn block_profile(&self) -> Option<Vec<(u64, u64)>> {
+        self.inner.block_profile()
+    }
+
+    // Coverage tracer methods - available when compiled with --tracer coverage
+
+    /// Coverage flags of each instruction reached since the last
+    /// [`prepare`](Self::prepare), as `(guest_pc, flags)` in ascending PC order.
+    ///
+    /// Flags are the `COVERAGE_*` bits of [`rvr_emit::c`]. See
+    /// [`crate::Coverage`] for turning them into an lcov report.
+    #[must_use]
+    pub fn coverage(&self) -> Option<Vec<(u64, u8)>> {
+        self.inner.coverage()
+    }
+
+    /// Load bias the library was compiled for (position-independent ELFs).
+    #[must_use]
+    pub const fn load_bias(&self) -> Option<u64> {
+        self.api.load_bias
+    }
+
+    /// Heap and stack placement the library was compiled with
+    /// (`CompileOptions::with_heap`/`with_stack_size`), with the ELF's
+    /// segments.
+    #[must_use]
+    pub const fn memory_layout(&self) -> Option<&MemoryLayout> {
+        self.memory_layout.as_ref()
+    }
+
     /// Dump register state to stderr for debugging.
     /// Useful for comparing execution between different backends.
     pub fn dump_registers(&self) {
@@ -630,397 +495,6 @@ impl Runner {
         }
         checksum
     }
-
-    /// Execute from a specific address.
-    ///
-    /// # Errors
-    /// Returns an error if execution fails or the runtime reports a failure.
-    pub fn execute_from(&mut self, pc: u64) -> Result<(std::time::Duration, u64), RunError> {
-        let start = Instant::now();
-        unsafe { (self.api.execute_from)(self.inner.as_void_ptr(), pc) };
-        let elapsed = start.elapsed();
-        let exit_code = self.inner.exit_code();
-        if exit_code != 0 {
-            Err(RunError::ExecutionError(exit_code))
-        } else {
-            Ok((elapsed, self.inner.instret()))
-        }
-    }
-
-    pub(crate) fn reset_and_run_to_instret(
-        &mut self,
-        target_instret: u64,
-    ) -> Result<(std::time::Duration, u64), RunError> {
-        self.inner.load_segments();
-        self.inner.reset();
-        self.setup_initial_regs();
-        self.inner.set_target_instret(target_instret);
-        self.clear_exit();
-        let entry_point = self.inner.entry_point();
-        self.execute_from(entry_point)
-    }
-
-    /// Run the program and return the result.
-    ///
-    /// # Errors
-    /// Returns an error if execution fails or the runtime reports a failure.
-    pub fn run(&mut self) -> Result<RunResult, RunError> {
-        // Save target_instret before reset (reset() disables the suspender)
-        let saved_target = self.inner.get_target_instret();
-
-        self.inner.load_segments();
-        self.inner.reset();
-        self.setup_initial_regs();
-
-        // Restore target_instret if it was set
-        if let Some(target) = saved_target
-            && target != u64::MAX
-        {
-            self.inner.set_target_instret(target);
-        }
-
-        let entry_point = self.inner.entry_point();
-        trace!(entry_point = format!("{:#x}", entry_point), "executing");
-
-        let start = Instant::now();
-        unsafe { (self.api.execute_from)(self.inner.as_void_ptr(), entry_point) };
-        let elapsed = start.elapsed();
-
-        let instret = self.inner.instret();
-        let exit_code = self.inner.exit_code();
-        let time_secs = elapsed.as_secs_f64();
-        let mips = (u64_to_f64(instret) / time_secs) / 1_000_000.0;
-
-        trace!(
-            instret = instret,
-            exit_code = exit_code,
-            time_secs = format!("{:.6}", time_secs),
-            "execution complete"
-        );
-
-        Ok(RunResult {
-            exit_code,
-            instret,
-            time_secs,
-            mips,
-        })
-    }
-
-    /// Run multiple times.
-    ///
-    /// # Errors
-    /// Returns an error if execution fails or the runtime reports a failure.
-    pub fn run_multiple(&mut self, count: usize) -> Result<Vec<RunResult>, RunError> {
-        let entry_point = self.inner.entry_point();
-        let mut results = Vec::with_capacity(count);
-
-        for _ in 0..count {
-            self.inner.load_segments();
-            self.inner.reset();
-
-            let start = Instant::now();
-            unsafe { (self.api.execute_from)(self.inner.as_void_ptr(), entry_point) };
-            let elapsed = start.elapsed();
-
-            let instret = self.inner.instret();
-            let exit_code = self.inner.exit_code();
-            let time_secs = elapsed.as_secs_f64();
-            let mips = (u64_to_f64(instret) / time_secs) / 1_000_000.0;
-
-            results.push(RunResult {
-                exit_code,
-                instret,
-                time_secs,
-                mips,
-            });
-        }
-
-        Ok(results)
-    }
-
-    /// Run with hardware performance counters.
-    ///
-    /// # Errors
-    /// Returns an error if execution fails or the runtime reports a failure.
-    pub fn run_with_counters(&mut self) -> Result<RunResultWithPerf, RunError> {
-        self.inner.load_segments();
-        self.inner.reset();
-        self.setup_initial_regs();
-
-        let entry_point = self.inner.entry_point();
-        trace!(
-            entry_point = format!("{:#x}", entry_point),
-            "executing with perf counters"
-        );
-
-        let mut perf_group = crate::perf::PerfGroup::new();
-
-        let start = Instant::now();
-        if let Some(ref mut group) = perf_group {
-            let _ = group.enable();
-        }
-        unsafe { (self.api.execute_from)(self.inner.as_void_ptr(), entry_point) };
-        if let Some(ref mut group) = perf_group {
-            let _ = group.disable();
-        }
-        let elapsed = start.elapsed();
-
-        let instret = self.inner.instret();
-        let exit_code = self.inner.exit_code();
-        let time_secs = elapsed.as_secs_f64();
-        let mips = (u64_to_f64(instret) / time_secs) / 1_000_000.0;
-
-        let perf = perf_group.as_mut().and_then(crate::perf::PerfGroup::read);
-
-        let result = RunResult {
-            exit_code,
-            instret,
-            time_secs,
-            mips,
-        };
-
-        crate::metrics::record_run("unknown", &result, perf.as_ref());
-
-        Ok(RunResultWithPerf { result, perf })
-    }
-
-    /// Call a guest function by name with the given arguments.
-    /// Call an exported function by name.
-    ///
-    /// # Errors
-    /// Returns an error if the function cannot be resolved or execution fails.
-    pub fn call(&mut self, name: &str, args: &[u64]) -> Result<u64, RunError> {
-        let addr = self
-            .lookup_symbol(name)
-            .ok_or_else(|| RunError::FunctionNotFound(name.to_string()))?;
-        self.call_addr(addr, args)
-    }
-
-    /// Call a guest function by address with the given arguments.
-    /// Call an exported function by address.
-    ///
-    /// # Errors
-    /// Returns an error if execution fails.
-    pub fn call_addr(&mut self, addr: u64, args: &[u64]) -> Result<u64, RunError> {
-        if args.len() > 8 {
-            return Err(RunError::TracerSetupFailed(
-                "too many arguments (max 8)".to_string(),
-            ));
-        }
-
-        self.inner.load_segments();
-        self.inner.reset();
-
-        // Set up arguments in a0-a7 (registers 10-17)
-        for (i, &arg) in args.iter().enumerate() {
-            self.inner.set_register(10 + i, arg);
-        }
-
-        // Set ra (register 1) to 0 - this will trap when the function returns
-        self.inner.set_register(1, 0);
-
-        debug!(addr = format!("{:#x}", addr), "calling guest function");
-        unsafe { (self.api.execute_from)(self.inner.as_void_ptr(), addr) };
-
-        Ok(self.inner.get_register(10))
-    }
-
-    /// Save the current machine state to a file (zstd compressed).
-    /// Save the current state to a file.
-    ///
-    /// # Errors
-    /// Returns an error if the state cannot be serialized or written.
-    pub fn save_state(&self, path: impl AsRef<Path>) -> Result<(), RunError> {
-        const MAGIC: &[u8; 4] = b"RVR\0";
-        const VERSION: u32 = 1;
-
-        let file = File::create(path)?;
-        let mut writer = BufWriter::new(file);
-
-        // Header (uncompressed)
-        writer.write_all(MAGIC)?;
-        writer.write_all(&VERSION.to_le_bytes())?;
-        writer.write_all(&[self.inner.xlen()])?;
-        let num_regs = u8::try_from(self.inner.num_regs())
-            .map_err(|_| RunError::StateError("num_regs does not fit in u8".to_string()))?;
-        writer.write_all(&[num_regs])?;
-        writer.write_all(&(self.inner.memory_size() as u64).to_le_bytes())?;
-
-        // Data (zstd compressed)
-        let mut encoder = zstd::stream::Encoder::new(&mut writer, 3)?;
-
-        encoder.write_all(&self.inner.get_pc().to_le_bytes())?;
-        encoder.write_all(&self.inner.instret().to_le_bytes())?;
-
-        for i in 0..self.inner.num_regs() {
-            encoder.write_all(&self.inner.get_register(i).to_le_bytes())?;
-        }
-
-        let mem_size = self.inner.memory_size();
-        let mut buf = vec![0u8; 64 * 1024];
-        let mut offset = 0;
-        while offset < mem_size {
-            let chunk_size = buf.len().min(mem_size - offset);
-            self.inner
-                .read_memory(offset as u64, &mut buf[..chunk_size]);
-            encoder.write_all(&buf[..chunk_size])?;
-            offset += chunk_size;
-        }
-
-        encoder.finish()?;
-        debug!(size = mem_size, "state saved");
-        Ok(())
-    }
-
-    /// Load machine state from a file.
-    /// Load a previously saved state from a file.
-    ///
-    /// # Errors
-    /// Returns an error if the state cannot be read or is incompatible.
-    pub fn load_state(&mut self, path: impl AsRef<Path>) -> Result<(), RunError> {
-        const MAGIC: &[u8; 4] = b"RVR\0";
-        const VERSION: u32 = 1;
-
-        let file = File::open(path)?;
-        let mut reader = BufReader::new(file);
-
-        let mut magic = [0u8; 4];
-        reader.read_exact(&mut magic)?;
-        if &magic != MAGIC {
-            return Err(RunError::StateError("invalid state file magic".to_string()));
-        }
-
-        let mut version = [0u8; 4];
-        reader.read_exact(&mut version)?;
-        if u32::from_le_bytes(version) != VERSION {
-            return Err(RunError::StateError(
-                "unsupported state version".to_string(),
-            ));
-        }
-
-        let mut xlen = [0u8; 1];
-        reader.read_exact(&mut xlen)?;
-        if xlen[0] != self.inner.xlen() {
-            return Err(RunError::StateError(format!(
-                "xlen mismatch: file has {}, runner has {}",
-                xlen[0],
-                self.inner.xlen()
-            )));
-        }
-
-        let mut num_regs = [0u8; 1];
-        reader.read_exact(&mut num_regs)?;
-        if num_regs[0] as usize != self.inner.num_regs() {
-            return Err(RunError::StateError(format!(
-                "num_regs mismatch: file has {}, runner has {}",
-                num_regs[0],
-                self.inner.num_regs()
-            )));
-        }
-
-        let mut mem_size_bytes = [0u8; 8];
-        reader.read_exact(&mut mem_size_bytes)?;
-        let file_mem_size = usize::try_from(u64::from_le_bytes(mem_size_bytes)).map_err(|_| {
-            RunError::StateError("memory size does not fit in host usize".to_string())
-        })?;
-        if file_mem_size != self.inner.memory_size() {
-            return Err(RunError::StateError(format!(
-                "memory size mismatch: file has {}, runner has {}",
-                file_mem_size,
-                self.inner.memory_size()
-            )));
-        }
-
-        let mut decoder = zstd::stream::Decoder::new(reader)?;
-
-        let mut pc = [0u8; 8];
-        decoder.read_exact(&mut pc)?;
-        self.inner.set_pc(u64::from_le_bytes(pc));
-
-        let mut instret = [0u8; 8];
-        decoder.read_exact(&mut instret)?;
-
-        for i in 0..self.inner.num_regs() {
-            let mut reg = [0u8; 8];
-            decoder.read_exact(&mut reg)?;
-            self.inner.set_register(i, u64::from_le_bytes(reg));
-        }
-
-        let mut buf = vec![0u8; 64 * 1024];
-        let mut offset = 0;
-        while offset < file_mem_size {
-            let chunk_size = buf.len().min(file_mem_size - offset);
-            decoder.read_exact(&mut buf[..chunk_size])?;
-            self.inner.write_memory(offset as u64, &buf[..chunk_size]);
-            offset += chunk_size;
-        }
-
-        debug!(size = file_mem_size, "state loaded");
-        Ok(())
-    }
-
-    /// Run multiple times with hardware performance counters.
-    ///
-    /// # Errors
-    ///
-    /// Returns errors from perf counter setup or execution.
-    pub fn run_multiple_with_counters(
-        &mut self,
-        count: usize,
-    ) -> Result<RunResultWithPerf, RunError> {
-        let entry_point = self.inner.entry_point();
-        let mut perf_group = crate::perf::PerfGroup::new();
-
-        let mut total_time = 0.0;
-        let mut total_mips = 0.0;
-        let mut last_instret = 0;
-        let mut last_exit_code = 0;
-
-        for _ in 0..count {
-            self.inner.load_segments();
-            self.inner.reset();
-            self.setup_initial_regs();
-
-            if let Some(ref mut group) = perf_group {
-                let _ = group.reset();
-            }
-
-            let start = Instant::now();
-            if let Some(ref mut group) = perf_group {
-                let _ = group.enable();
-            }
-            unsafe { (self.api.execute_from)(self.inner.as_void_ptr(), entry_point) };
-            if let Some(ref mut group) = perf_group {
-                let _ = group.disable();
-            }
-            let elapsed = start.elapsed();
-
-            last_instret = self.inner.instret();
-            last_exit_code = self.inner.exit_code();
-            let time_secs = elapsed.as_secs_f64();
-            let mips = (u64_to_f64(last_instret) / time_secs) / 1_000_000.0;
-
-            total_time += time_secs;
-            total_mips += mips;
-        }
-
-        let count_f64 = usize_to_f64(count);
-        let avg_time = total_time / count_f64;
-        let avg_mips = total_mips / count_f64;
-
-        let perf = perf_group.as_mut().and_then(crate::perf::PerfGroup::read);
-
-        let result = RunResult {
-            exit_code: last_exit_code,
-            instret: last_instret,
-            time_secs: avg_time,
-            mips: avg_mips,
-        };
-
-        crate::metrics::record_run("unknown", &result, perf.as_ref());
-
-        Ok(RunResultWithPerf { result, perf })
-    }
 }
 
 #[cfg(test)]
@@ -1029,13 +503,46 @@ mod tests {
 
     #[test]
     fn test_run_result_format() {
-        let result = RunResult {
-            exit_code: 0,
-            instret: 1_234_567,
-            time_secs: 1.234_567,
-            mips: 1.0,
-        };
+        let result = RunResult::new(
+            0,
+            1_234_567,
+            RunPhases {
+                load_secs: 0.25,
+                init_secs: 0.5,
+                execute_secs: 1.234_567,
+                teardown_secs: 0.0,
+            },
+        );
+        assert!((result.mips - 1.0).abs() < 1e-9);
+        assert!((result.total_secs() - 1.984_567).abs() < 1e-9);
         result.print_raw_format();
         result.print_json();
     }
+
+    #[test]
+    fn test_run_result_average() {
+        let phases = |load_secs, execute_secs| RunPhases {
+            load_secs,
+            init_secs: 0.5,
+            execute_secs,
+            teardown_secs: 0.0,
+        };
+        let results = [
+            RunResult::new(0, 1_000_000, phases(1.0, 1.0)),
+            RunResult::new(0, 1_000_000, phases(0.0, 0.5)),
+        ];
+        let avg = RunResult::average(&results).unwrap();
+        assert_eq!(avg.instret, 1_000_000);
+        assert!((avg.time_secs - 0.75).abs() < 1e-9);
+        assert!((avg.mips - 1.5).abs() < 1e-9);
+        assert_eq!(avg.phases, phases(0.5, 0.75));
+        assert_eq!(avg.reset_secs, Some(0.5));
+        assert!(
+            RunResult::average(&results[..1])
+                .unwrap()
+                .reset_secs
+                .is_none()
+        );
+        assert!(RunResult::average(&[]).is_none());
+    }
 }
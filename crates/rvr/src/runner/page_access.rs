@@ -0,0 +1,305 @@
+//! `PageAccessRunner` - runner with page access tracer for memory access logs.
+
+use std::ffi::c_void;
+
+use rvr_elf::ElfImage;
+use rvr_ir::Xlen;
+use rvr_state::{GuardedMemory, GuestIo, PageAccessTracer, RvState};
+
+use super::api::PageBitmapSize;
+use crate::segment_image::SegmentImage;
+
+use super::{RunError, RunnerImpl, Snapshot, init_memory};
+
+/// Guest pages read and written during a run.
+///
+/// Recorded by the page access tracer as one bit per page. Page numbers are
+/// guest physical addresses divided by [`page_size`](Self::page_size).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PageAccessLog {
+    page_size: u64,
+    read: Vec<u64>,
+    written: Vec<u64>,
+    read_before_write: Vec<u64>,
+}
+
+impl PageAccessLog {
+    /// Page size in bytes.
+    #[must_use]
+    pub const fn page_size(&self) -> u64 {
+        self.page_size
+    }
+
+    /// Pages read at least once, in ascending order.
+    pub fn read_pages(&self) -> impl Iterator<Item = u64> + '_ {
+        set_bits(&self.read)
+    }
+
+    /// Pages written at least once, in ascending order.
+    pub fn written_pages(&self) -> impl Iterator<Item = u64> + '_ {
+        set_bits(&self.written)
+    }
+
+    /// Pages whose first read came before any write, in ascending order.
+    ///
+    /// These are the pages whose initial contents the run depends on.
+    pub fn read_before_write_pages(&self) -> impl Iterator<Item = u64> + '_ {
+        set_bits(&self.read_before_write)
+    }
+
+    /// Contents of each read-before-write page in `initial`, as
+    /// `(page, bytes)` pairs.
+    ///
+    /// `initial` must be a [`Runner::snapshot`](super::Runner::snapshot)
+    /// taken after [`Runner::prepare`](super::Runner::prepare) and before
+    /// executing, so it holds memory as the run found it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading the snapshot fails.
+    pub fn pre_images(&self, initial: &Snapshot) -> Result<Vec<(u64, Vec<u8>)>, RunError> {
+        let page_size = usize::try_from(self.page_size).unwrap_or(usize::MAX);
+        self.read_before_write_pages()
+            .map(|page| {
+                let mut bytes = vec![0u8; page_size];
+                let addr = usize::try_from(page * self.page_size).unwrap_or(usize::MAX);
+                let len = initial.memory().read_at(addr, &mut bytes)?;
+                bytes.truncate(len);
+                Ok((page, bytes))
+            })
+            .collect()
+    }
+}
+
+/// Indices of the set bits of `bitmap`, in ascending order.
+fn set_bits(bitmap: &[u64]) -> impl Iterator<Item = u64> + '_ {
+    bitmap
+        .iter()
+        .enumerate()
+        .filter(|&(_, &word)| word != 0)
+        .flat_map(|(index, &word)| {
+            let base = index as u64 * u64::from(u64::BITS);
+            (0..u64::BITS)
+                .filter(move |&bit| word & (1 << bit) != 0)
+                .map(move |bit| base + u64::from(bit))
+        })
+}
+
+/// Typed runner with page access tracer (needs bitmap management).
+pub struct PageAccessRunner<X: Xlen, const NUM_REGS: usize> {
+    state: RvState<X, PageAccessTracer, (), NUM_REGS>,
+    memory: GuardedMemory,
+    elf_image: ElfImage<X>,
+    page_shift: u32,
+    read: Vec<u64>,
+    written: Vec<u64>,
+    read_before_write: Vec<u64>,
+}
+
+impl<X: Xlen, const NUM_REGS: usize> PageAccessRunner<X, NUM_REGS> {
+    pub fn new(elf_image: ElfImage<X>, memory: GuardedMemory, bitmaps: PageBitmapSize) -> Self {
+        let mut state = RvState::new();
+        state.set_memory(memory.as_ptr());
+        let brk = elf_image.get_initial_program_break();
+        state.brk = brk;
+        state.start_brk = brk;
+        Self {
+            state,
+            memory,
+            elf_image,
+            page_shift: bitmaps.page_shift,
+            read: vec![0u64; bitmaps.words],
+            written: vec![0u64; bitmaps.words],
+            read_before_write: vec![0u64; bitmaps.words],
+        }
+    }
+}
+
+impl<X: Xlen, const NUM_REGS: usize> RunnerImpl for PageAccessRunner<X, NUM_REGS> {
+    fn load_segments(&mut self, segments: Option<&SegmentImage>) {
+        init_memory(&mut self.memory, &self.elf_image, segments);
+    }
+
+    fn reset(&mut self) {
+        self.state.reset();
+        self.state.set_memory(self.memory.as_ptr());
+        self.read.fill(0);
+        self.written.fill(0);
+        self.read_before_write.fill(0);
+        self.state.tracer.setup(
+            self.read.as_mut_ptr(),
+            self.written.as_mut_ptr(),
+            self.read_before_write.as_mut_ptr(),
+        );
+    }
+
+    fn as_void_ptr(&mut self) -> *mut c_void {
+        self.state.as_void_ptr()
+    }
+
+    fn instret(&self) -> u64 {
+        self.state.instret()
+    }
+
+    fn exit_code(&self) -> u8 {
+        self.state.exit_code()
+    }
+
+    fn has_exited(&self) -> bool {
+        self.state.has_exited()
+    }
+
+    fn is_trapped(&self) -> bool {
+        self.state.is_trapped()
+    }
+
+    fn exit_cause(&self) -> u32 {
+        self.state.exit_cause()
+    }
+
+    fn exit_info(&self) -> u64 {
+        self.state.exit_info()
+    }
+
+    fn htif_syscall(&self) -> u64 {
+        self.state.htif_syscall()
+    }
+
+    fn cancel_flag(&mut self) -> *mut u8 {
+        self.state.cancel_flag()
+    }
+
+    fn entry_point(&self) -> u64 {
+        X::to_u64(self.elf_image.entry_point)
+    }
+
+    fn lookup_symbol(&self, name: &str) -> Option<u64> {
+        self.elf_image.lookup_symbol(name)
+    }
+
+    fn set_register(&mut self, reg: usize, value: u64) {
+        self.state.set_reg(reg, X::from_u64(value));
+    }
+
+    fn get_register(&self, reg: usize) -> u64 {
+        X::to_u64(self.state.get_reg(reg))
+    }
+
+    fn get_pc(&self) -> u64 {
+        X::to_u64(self.state.pc())
+    }
+
+    fn set_pc(&mut self, pc: u64) {
+        self.state.set_pc(X::from_u64(pc));
+    }
+
+    fn get_csr(&self, csr: u16) -> u64 {
+        X::to_u64(self.state.csrs[csr as usize])
+    }
+
+    fn set_csr(&mut self, csr: u16, value: u64) {
+        self.state.csrs[csr as usize] = X::from_u64(value);
+    }
+
+    fn read_memory(&self, addr: u64, buf: &mut [u8]) -> usize {
+        let mem_size = self.memory.size();
+        let Ok(addr) = usize::try_from(addr) else {
+            return 0;
+        };
+        if addr >= mem_size {
+            return 0;
+        }
+        let len = buf.len().min(mem_size - addr);
+        let src = unsafe { std::slice::from_raw_parts(self.memory.as_ptr().add(addr), len) };
+        buf[..len].copy_from_slice(src);
+        len
+    }
+
+    fn write_memory(&mut self, addr: u64, data: &[u8]) -> usize {
+        let mem_size = self.memory.size();
+        let Ok(addr) = usize::try_from(addr) else {
+            return 0;
+        };
+        if addr >= mem_size {
+            return 0;
+        }
+        let len = data.len().min(mem_size - addr);
+        unsafe {
+            std::ptr::copy_nonoverlapping(data.as_ptr(), self.memory.as_ptr().add(addr), len);
+        }
+        len
+    }
+
+    fn num_regs(&self) -> usize {
+        NUM_REGS
+    }
+
+    fn xlen(&self) -> u8 {
+        X::VALUE
+    }
+
+    fn memory_size(&self) -> usize {
+        self.memory.size()
+    }
+
+    fn resident_bytes(&self) -> Option<usize> {
+        self.memory.resident_bytes().ok()
+    }
+
+    fn memory_mut(&mut self) -> &mut GuardedMemory {
+        &mut self.memory
+    }
+
+    fn clear_exit(&mut self) {
+        self.state.clear_exit();
+    }
+
+    fn set_io(&mut self, io: *mut GuestIo) {
+        self.state.set_io(io);
+    }
+
+    fn snapshot(&mut self) -> Result<Snapshot, RunError> {
+        let memory = self.memory.snapshot()?;
+        Ok(Snapshot::new(X::VALUE, self.state.capture(), memory))
+    }
+
+    fn restore(&mut self, snapshot: &Snapshot) -> Result<(), RunError> {
+        self.memory.restore(snapshot.memory())?;
+        self.state.restore(snapshot.state());
+        Ok(())
+    }
+
+    fn page_access_log(&self) -> Option<PageAccessLog> {
+        Some(PageAccessLog {
+            page_size: 1 << self.page_shift,
+            read: self.read.clone(),
+            written: self.written.clone(),
+            read_before_write: self.read_before_write.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_bits() {
+        let bitmap = [0b1001, 0, 1 << 63];
+        assert_eq!(set_bits(&bitmap).collect::<Vec<_>>(), [0, 3, 191]);
+        assert_eq!(set_bits(&[0; 4]).count(), 0);
+    }
+
+    #[test]
+    fn test_page_access_log_pages() {
+        let log = PageAccessLog {
+            page_size: 4096,
+            read: vec![0b0110],
+            written: vec![0b1100],
+            read_before_write: vec![0b0010],
+        };
+        assert_eq!(log.read_pages().collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(log.written_pages().collect::<Vec<_>>(), [2, 3]);
+        assert_eq!(log.read_before_write_pages().collect::<Vec<_>>(), [1]);
+    }
+}
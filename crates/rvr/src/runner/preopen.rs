@@ -0,0 +1,425 @@
+//! Preopened directories: the only host files a guest can open.
+//!
+//! WASI-style capabilities for `SyscallMode::Linux`: the host binds guest
+//! directory paths to host directories, which the guest sees as fds 3, 4,
+//! ... in binding order. The generated `rv_sys_openat` hands every path to
+//! [`GuestFiles::open`], which resolves it under one of those directories
+//! and refuses (`EPERM`) anything else: paths outside every preopen, `..`
+//! components, and symlinks leading out of the preopen. `fstatat` paths
+//! resolve the same way.
+//!
+//! The kernel does the resolution: each preopen is held open and paths are
+//! opened relative to it with `openat2(RESOLVE_BENEATH)`, so a symlink
+//! (dangling or swapped in while the guest runs) cannot point the open
+//! outside the directory. Hosts without `openat2` refuse every path.
+//!
+//! The fd table lives with the runner rather than in guest memory, so
+//! files stay open (at their offsets) across suspension and resumption of
+//! the same runner. Like Linux, the guest gets the lowest free fd, up to
+//! [`MAX_FDS`] (`EMFILE` beyond).
+
+use std::collections::BTreeMap;
+use std::fs::{File, Metadata};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Component, Path, PathBuf};
+
+use rvr_state::GuestStat;
+
+/// `EPERM`, returned for paths outside the preopened directories.
+pub(super) const EPERM: i32 = 1;
+/// `EBADF`, returned for fds that are not open.
+const EBADF: i32 = 9;
+/// `EISDIR`, returned for reads and writes of a preopened directory.
+const EISDIR: i32 = 21;
+/// `EINVAL`, returned for bad `lseek` whences and negative offsets.
+const EINVAL: i32 = 22;
+/// `EMFILE`, returned when every guest fd below [`MAX_FDS`] is taken.
+const EMFILE: i32 = 24;
+
+/// Guest fds are below this, like Linux's default `RLIMIT_NOFILE`.
+const MAX_FDS: u32 = 1024;
+
+/// `dirfd` meaning "relative to the working directory" (which guests lack).
+const AT_FDCWD: i32 = -100;
+/// Guest fd of the first preopened directory.
+const FIRST_PREOPEN_FD: u32 = 3;
+
+/// Linux `open` flags (RISC-V uses the generic values).
+const O_ACCMODE: u64 = 0o3;
+const O_WRONLY: u64 = 0o1;
+const O_RDWR: u64 = 0o2;
+const O_CREAT: u64 = 0o100;
+const O_EXCL: u64 = 0o200;
+const O_TRUNC: u64 = 0o1000;
+const O_APPEND: u64 = 0o2000;
+const O_NOFOLLOW: u64 = 0o400_000;
+const O_PATH: u64 = 0o10_000_000;
+/// Permission bits of the `mode` argument.
+const MODE_MASK: u64 = 0o777;
+
+/// `lseek` whences.
+const SEEK_SET: u32 = 0;
+const SEEK_CUR: u32 = 1;
+const SEEK_END: u32 = 2;
+
+/// A guest directory path bound to an open host directory.
+struct Preopen {
+    guest: PathBuf,
+    dir: File,
+}
+
+/// Preopened directories and the files the guest opened under them.
+#[derive(Default)]
+pub(super) struct GuestFiles {
+    preopens: Vec<Preopen>,
+    files: BTreeMap<u32, File>,
+}
+
+impl GuestFiles {
+    /// Bind guest directory `guest` to host directory `host`, which stays
+    /// open: renaming or replacing `host` later does not move the preopen.
+    pub(super) fn preopen(&mut self, guest: PathBuf, host: &Path) -> io::Result<()> {
+        let dir = File::open(host)?;
+        self.preopens.push(Preopen { guest, dir });
+        Ok(())
+    }
+
+    /// True if at least one directory is preopened.
+    pub(super) const fn has_preopens(&self) -> bool {
+        !self.preopens.is_empty()
+    }
+
+    /// Open `path` relative to guest fd `dirfd`; returns the new guest fd.
+    pub(super) fn open(
+        &mut self,
+        dirfd: i32,
+        path: &str,
+        flags: u64,
+        mode: u64,
+    ) -> io::Result<u32> {
+        let (dir, rest) = self.resolve(dirfd, path)?;
+        let fd = self.next_fd()?;
+        let flags = flags & (O_ACCMODE | O_CREAT | O_EXCL | O_TRUNC | O_APPEND);
+        let mode = u32::try_from(mode & MODE_MASK).unwrap_or(0);
+        let file = open_beneath(dir, &rest, flags, mode)?;
+        self.files.insert(fd, file);
+        Ok(fd)
+    }
+
+    /// Close guest fd `fd`.
+    pub(super) fn close(&mut self, fd: u32) -> io::Result<()> {
+        self.files
+            .remove(&fd)
+            .map(drop)
+            .ok_or_else(|| io::Error::from_raw_os_error(EBADF))
+    }
+
+    /// Read once from the file behind `fd`.
+    pub(super) fn read(&mut self, fd: u32, buf: &mut [u8]) -> io::Result<usize> {
+        self.file(fd)?.read(buf)
+    }
+
+    /// Write all of `buf` to the file behind `fd`.
+    pub(super) fn write(&mut self, fd: u32, buf: &[u8]) -> io::Result<()> {
+        self.file(fd)?.write_all(buf)
+    }
+
+    /// Move the offset of the file behind `fd`; returns the new offset.
+    pub(super) fn seek(&mut self, fd: u32, offset: i64, whence: u32) -> io::Result<u64> {
+        let invalid = || io::Error::from_raw_os_error(EINVAL);
+        let pos = match whence {
+            SEEK_SET => SeekFrom::Start(u64::try_from(offset).map_err(|_| invalid())?),
+            SEEK_CUR => SeekFrom::Current(offset),
+            SEEK_END => SeekFrom::End(offset),
+            _ => return Err(invalid()),
+        };
+        self.file(fd)?.seek(pos)
+    }
+
+    /// Metadata of `dirfd` itself (`path` `None`), or of `path` relative
+    /// to it; `nofollow` does not follow a final symlink.
+    pub(super) fn stat(
+        &mut self,
+        dirfd: i32,
+        path: Option<&str>,
+        nofollow: bool,
+    ) -> io::Result<GuestStat> {
+        let metadata = if let Some(path) = path {
+            let (dir, rest) = self.resolve(dirfd, path)?;
+            let nofollow = if nofollow { O_NOFOLLOW } else { 0 };
+            open_beneath(dir, &rest, O_PATH | nofollow, 0)?.metadata()?
+        } else {
+            let fd = u32::try_from(dirfd).map_err(|_| io::Error::from_raw_os_error(EBADF))?;
+            match self.preopen_index(fd) {
+                Some(index) => self.preopens[index].dir.metadata()?,
+                None => self.file(fd)?.metadata()?,
+            }
+        };
+        Ok(guest_stat(&metadata))
+    }
+
+    fn file(&mut self, fd: u32) -> io::Result<&mut File> {
+        if self.preopen_index(fd).is_some() {
+            return Err(io::Error::from_raw_os_error(EISDIR));
+        }
+        self.files
+            .get_mut(&fd)
+            .ok_or_else(|| io::Error::from_raw_os_error(EBADF))
+    }
+
+    fn preopen_index(&self, fd: u32) -> Option<usize> {
+        let index = usize::try_from(fd.checked_sub(FIRST_PREOPEN_FD)?).ok()?;
+        (index < self.preopens.len()).then_some(index)
+    }
+
+    /// Lowest fd that is neither a preopen nor an open file.
+    fn next_fd(&self) -> io::Result<u32> {
+        let first = FIRST_PREOPEN_FD + u32::try_from(self.preopens.len()).unwrap_or(u32::MAX);
+        lowest_free_fd(first, |fd| self.files.contains_key(&fd))
+    }
+
+    /// Preopen that guest `path` lies under and the path relative to it.
+    ///
+    /// Absolute paths ignore `dirfd`, as on Linux; relative paths need a
+    /// preopened `dirfd`. Symlinks are left to [`open_beneath`].
+    fn resolve(&self, dirfd: i32, path: &str) -> io::Result<(&File, PathBuf)> {
+        let denied = || io::Error::from_raw_os_error(EPERM);
+        let path = Path::new(path);
+        let (preopen, rest) = if path.is_absolute() {
+            self.preopens
+                .iter()
+                .find_map(|p| path.strip_prefix(&p.guest).ok().map(|rest| (p, rest)))
+                .ok_or_else(denied)?
+        } else if dirfd == AT_FDCWD {
+            return Err(denied());
+        } else {
+            let index = u32::try_from(dirfd)
+                .ok()
+                .and_then(|fd| self.preopen_index(fd))
+                .ok_or_else(|| io::Error::from_raw_os_error(EBADF))?;
+            (&self.preopens[index], path)
+        };
+
+        let mut relative = PathBuf::from(".");
+        for component in rest.components() {
+            match component {
+                Component::Normal(name) => relative.push(name),
+                Component::CurDir => {}
+                _ => return Err(denied()),
+            }
+        }
+        Ok((&preopen.dir, relative))
+    }
+}
+
+/// Open `path` (relative, without `..`) under directory `dir` with guest
+/// `open` flags. The kernel refuses symlinks and mounts that resolve out
+/// of `dir`, which the guest sees as `EPERM`.
+#[cfg(target_os = "linux")]
+fn open_beneath(dir: &File, path: &Path, flags: u64, mode: u32) -> io::Result<File> {
+    use std::os::fd::{AsRawFd, FromRawFd};
+
+    use nix::errno::Errno;
+    use nix::fcntl::{OFlag, OpenHow, ResolveFlag, openat2};
+    use nix::sys::stat::Mode;
+
+    let mut oflag = match flags & O_ACCMODE {
+        O_WRONLY => OFlag::O_WRONLY,
+        O_RDWR => OFlag::O_RDWR,
+        _ => OFlag::O_RDONLY,
+    } | OFlag::O_CLOEXEC;
+    for (guest, host) in [
+        (O_CREAT, OFlag::O_CREAT),
+        (O_EXCL, OFlag::O_EXCL),
+        (O_TRUNC, OFlag::O_TRUNC),
+        (O_APPEND, OFlag::O_APPEND),
+        (O_NOFOLLOW, OFlag::O_NOFOLLOW),
+        (O_PATH, OFlag::O_PATH),
+    ] {
+        oflag.set(host, flags & guest != 0);
+    }
+    let how = OpenHow::new()
+        .flags(oflag)
+        .mode(Mode::from_bits_truncate(mode))
+        .resolve(ResolveFlag::RESOLVE_BENEATH | ResolveFlag::RESOLVE_NO_MAGICLINKS);
+    match openat2(dir.as_raw_fd(), path, how) {
+        // SAFETY: `openat2` returned a new fd that nothing else owns.
+        Ok(fd) => Ok(unsafe { File::from_raw_fd(fd) }),
+        Err(Errno::EXDEV) => Err(io::Error::from_raw_os_error(EPERM)),
+        Err(errno) => Err(errno.into()),
+    }
+}
+
+/// Without `openat2`, no path can be opened without racing the checks.
+#[cfg(not(target_os = "linux"))]
+fn open_beneath(_dir: &File, _path: &Path, _flags: u64, _mode: u32) -> io::Result<File> {
+    Err(io::Error::from_raw_os_error(EPERM))
+}
+
+/// Lowest fd from `first` on that is not `taken`, or `EMFILE`.
+fn lowest_free_fd(first: u32, taken: impl Fn(u32) -> bool) -> io::Result<u32> {
+    (first..MAX_FDS)
+        .find(|&fd| !taken(fd))
+        .ok_or_else(|| io::Error::from_raw_os_error(EMFILE))
+}
+
+/// The parts of `metadata` a guest `struct stat` carries.
+fn guest_stat(metadata: &Metadata) -> GuestStat {
+    GuestStat {
+        size: metadata.size(),
+        mode: metadata.mode(),
+        blksize: u32::try_from(metadata.blksize()).unwrap_or(u32::MAX),
+        blocks: metadata.blocks(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(dir: &Path) -> GuestFiles {
+        let mut files = GuestFiles::default();
+        files.preopen(PathBuf::from("/data"), dir).unwrap();
+        files
+    }
+
+    fn raw(err: &io::Error) -> Option<i32> {
+        err.raw_os_error()
+    }
+
+    #[test]
+    fn test_open_under_preopen() {
+        let temp = tempfile::tempdir().unwrap();
+        std::fs::write(temp.path().join("a.txt"), b"abc").unwrap();
+        let mut files = files(temp.path());
+
+        let fd = files.open(AT_FDCWD, "/data/a.txt", 0, 0).unwrap();
+        assert_eq!(fd, 4);
+        let mut buf = [0u8; 8];
+        assert_eq!(files.read(fd, &mut buf).unwrap(), 3);
+        assert_eq!(&buf[..3], b"abc");
+
+        // Relative to the preopen's fd
+        let again = files.open(3, "./a.txt", 0, 0).unwrap();
+        assert_eq!(again, 5);
+        files.close(fd).unwrap();
+        assert_eq!(files.open(3, "a.txt", 0, 0).unwrap(), 4);
+    }
+
+    #[test]
+    fn test_escape_is_denied() {
+        let temp = tempfile::tempdir().unwrap();
+        let inner = temp.path().join("inner");
+        std::fs::create_dir(&inner).unwrap();
+        std::fs::write(temp.path().join("secret"), b"x").unwrap();
+        std::os::unix::fs::symlink(temp.path().join("secret"), inner.join("link")).unwrap();
+        let mut files = files(&inner);
+
+        for path in [
+            "/etc/passwd",
+            "/data/../secret",
+            "/database/x",
+            "/data/link",
+        ] {
+            let err = files.open(AT_FDCWD, path, 0, 0).unwrap_err();
+            assert_eq!(raw(&err), Some(EPERM), "{path}");
+        }
+        let err = files.open(AT_FDCWD, "secret", 0, 0).unwrap_err();
+        assert_eq!(raw(&err), Some(EPERM));
+        let err = files.open(7, "secret", 0, 0).unwrap_err();
+        assert_eq!(raw(&err), Some(EBADF));
+    }
+
+    #[test]
+    fn test_dangling_symlink_is_denied() {
+        let temp = tempfile::tempdir().unwrap();
+        let inner = temp.path().join("inner");
+        std::fs::create_dir(&inner).unwrap();
+        let outside = temp.path().join("created");
+        std::os::unix::fs::symlink(&outside, inner.join("dangling")).unwrap();
+        std::os::unix::fs::symlink("../created", inner.join("relative")).unwrap();
+        let mut files = files(&inner);
+
+        for path in ["/data/dangling", "/data/relative"] {
+            let err = files
+                .open(AT_FDCWD, path, O_WRONLY | O_CREAT, 0o644)
+                .unwrap_err();
+            assert_eq!(raw(&err), Some(EPERM), "{path}");
+            let err = files.stat(AT_FDCWD, Some(path), false).unwrap_err();
+            assert_eq!(raw(&err), Some(EPERM), "{path}");
+        }
+        assert!(!outside.exists());
+
+        // The link itself is inside the preopen
+        let link = files.stat(3, Some("dangling"), true).unwrap();
+        assert_eq!(link.mode & 0o170_000, 0o120_000);
+    }
+
+    #[test]
+    fn test_create_and_write() {
+        let temp = tempfile::tempdir().unwrap();
+        let mut files = files(temp.path());
+
+        let fd = files
+            .open(
+                AT_FDCWD,
+                "/data/out.txt",
+                O_WRONLY | O_CREAT | O_TRUNC,
+                0o644,
+            )
+            .unwrap();
+        files.write(fd, b"written").unwrap();
+        files.close(fd).unwrap();
+        assert_eq!(
+            std::fs::read(temp.path().join("out.txt")).unwrap(),
+            b"written"
+        );
+        assert_eq!(raw(&files.close(fd).unwrap_err()), Some(EBADF));
+        assert_eq!(raw(&files.write(3, b"x").unwrap_err()), Some(EISDIR));
+    }
+
+    #[test]
+    fn test_seek_and_stat() {
+        let temp = tempfile::tempdir().unwrap();
+        std::fs::write(temp.path().join("a.txt"), b"abcdef").unwrap();
+        let mut files = files(temp.path());
+        let fd = files.open(AT_FDCWD, "/data/a.txt", 0, 0).unwrap();
+
+        assert_eq!(files.seek(fd, -2, SEEK_END).unwrap(), 4);
+        let mut buf = [0u8; 8];
+        assert_eq!(files.read(fd, &mut buf).unwrap(), 2);
+        assert_eq!(&buf[..2], b"ef");
+        assert_eq!(files.seek(fd, 1, SEEK_SET).unwrap(), 1);
+        assert_eq!(files.seek(fd, 2, SEEK_CUR).unwrap(), 3);
+        for (offset, whence) in [(-1, SEEK_SET), (0, 3), (-10, SEEK_CUR)] {
+            let err = files.seek(fd, offset, whence).unwrap_err();
+            assert_eq!(raw(&err), Some(EINVAL), "{offset} {whence}");
+        }
+
+        let stat = files.stat(i32::try_from(fd).unwrap(), None, false).unwrap();
+        assert_eq!(stat.size, 6);
+        assert_eq!(stat.mode & 0o170_000, 0o100_000);
+        assert!(stat.blksize > 0);
+        let by_path = files.stat(3, Some("a.txt"), false).unwrap();
+        assert_eq!(by_path, stat);
+        let dir = files.stat(3, None, false).unwrap();
+        assert_eq!(dir.mode & 0o170_000, 0o040_000);
+        let err = files
+            .stat(AT_FDCWD, Some("/etc/passwd"), false)
+            .unwrap_err();
+        assert_eq!(raw(&err), Some(EPERM));
+        let err = files.stat(9, None, false).unwrap_err();
+        assert_eq!(raw(&err), Some(EBADF));
+    }
+
+    #[test]
+    fn test_fd_limit_is_emfile() {
+        assert_eq!(lowest_free_fd(4, |fd| fd < 7).unwrap(), 7);
+        assert_eq!(lowest_free_fd(4, |fd| fd != 10).unwrap(), 10);
+        let err = lowest_free_fd(4, |_| true).unwrap_err();
+        assert_eq!(raw(&err), Some(EMFILE));
+        let err = lowest_free_fd(MAX_FDS, |_| false).unwrap_err();
+        assert_eq!(raw(&err), Some(EMFILE));
+    }
+}
@@ -0,0 +1,133 @@
+//! Hot reload: swap a recompiled library in under a live runner.
+//!
+//! [`Runner::reload`] keeps the host-side configuration (syscall handlers,
+//! stdio redirection, CSR hook and host buffers) and replaces everything
+//! derived from the library and ELF. The new library is opened through a
+//! uniquely named copy: `dlopen` of the original path would return the
+//! handle it already has for the old library, even after the file was
+//! rebuilt.
+
+use std::path::Path;
+use std::time::Instant;
+
+use tracing::{debug, warn};
+
+use super::{InstretMode, RunError, Runner, TracerKind, library_path};
+use crate::segment_image::segment_image_path;
+
+impl Runner {
+    /// Replace the library and ELF with the ones in `lib_dir` and at
+    /// `elf_path`, typically after recompiling the guest, without
+    /// restarting the host process.
+    ///
+    /// The new library must match the old one in XLEN, register count,
+    /// tracer and instret mode. Guest memory is re-initialized from the new
+    /// ELF and the state is reset to its entry point. Registered syscalls,
+    /// stdio redirection, the CSR hook and the guest command line carry
+    /// over, as do host buffers that still fit around the new segments; the
+    /// others are unmapped with a warning. Memory size stays the same.
+    ///
+    /// No guest code can be running: execution borrows the runner for its
+    /// whole duration and handlers only see a [`GuestContext`]. A guest
+    /// suspended mid-run is discarded. The old library is closed once the
+    /// new one is in place; nothing else holds pointers into it (the gdb
+    /// stub owns its runner). Libraries loaded with
+    /// [`load_program`](Self::load_program) or
+    /// [`load_synthetic`](Self::load_synthetic) cannot be reloaded this
+    /// way.
+    ///
+    /// # Errors
+    /// Returns `ReloadMismatch` if the new library differs in one of the
+    /// modes above, `Reload` if either library uses fixed addresses (the
+    /// old and new state would share them), and load errors as
+    /// [`load`](Self::load) does. The runner is unchanged on error.
+    ///
+    /// [`GuestContext`]: super::GuestContext
+    pub fn reload(
+        &mut self,
+        lib_dir: impl AsRef<Path>,
+        elf_path: impl AsRef<Path>,
+    ) -> Result<(), RunError> {
+        let start = Instant::now();
+        if self.has_fixed_addresses() {
+            return Err(RunError::Reload(
+                "the library uses fixed addresses".to_string(),
+            ));
+        }
+        let lib_dir = lib_dir.as_ref();
+        let lib_path = library_path(lib_dir);
+        if !lib_path.exists() {
+            return Err(RunError::LibraryNotFound(lib_path.display().to_string()));
+        }
+        let dir_name = lib_dir.file_name().and_then(|n| n.to_str()).unwrap_or("rv");
+
+        // The copy can go once the library is mapped
+        let copy = tempfile::Builder::new()
+            .prefix(".reload-")
+            .suffix(".so")
+            .tempfile_in(lib_dir)?
+            .into_temp_path();
+        std::fs::copy(&lib_path, &copy)?;
+        let mut next = Self::open(
+            start,
+            &copy,
+            "",
+            &segment_image_path(lib_dir, dir_name),
+            elf_path.as_ref(),
+            self.memory_size(),
+        )?;
+        drop(copy);
+        if next.has_fixed_addresses() {
+            return Err(RunError::Reload(
+                "the new library uses fixed addresses".to_string(),
+            ));
+        }
+        self.check_reload(&next)?;
+
+        next.hooks = self.hooks.take();
+        next.journal = self.journal.take();
+        next.args = std::mem::take(&mut self.args);
+        next.install_hooks();
+        for (range, writable) in std::mem::take(&mut self.host_buffers) {
+            match next.carry_host_buffer(self, range) {
+                Ok(()) => next.host_buffers.push((range, writable)),
+                Err(err) => warn!(%err, "host buffer not carried over the reload"),
+            }
+        }
+        next.prepare();
+        next.setup_initial_regs();
+        next.set_pc(next.entry_point());
+        debug!(
+            path = %lib_path.display(),
+            entry_point = format!("{:#x}", next.entry_point()),
+            "reloaded library"
+        );
+        // Drops the old state and memory, then closes the old library
+        *self = next;
+        Ok(())
+    }
+
+    /// Fail if `next` was compiled with other modes than this runner.
+    fn check_reload(&self, next: &Self) -> Result<(), RunError> {
+        let modes = |runner: &Self| {
+            [
+                ("XLEN", runner.xlen().to_string()),
+                ("register count", runner.num_regs().to_string()),
+                (
+                    "tracer",
+                    format!("{:?}", TracerKind::from_raw(runner.api.tracer_kind)),
+                ),
+                (
+                    "instret mode",
+                    format!("{:?}", InstretMode::from_raw(runner.api.instret_mode)),
+                ),
+            ]
+        };
+        for ((what, old), (_, new)) in modes(self).into_iter().zip(modes(next)) {
+            if old != new {
+                return Err(RunError::ReloadMismatch { what, old, new });
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,801 @@
+//! Input recording and byte-exact replay.
+//!
+//! [`Runner::record_inputs`] wraps the runner's host hook table in a
+//! [`Journal`] that passes every call through to the host and logs what
+//! the guest got back: read bytes, file opens, seeks and stats, the clock
+//! and `getrandom` output. [`Runner::replay_inputs`] swaps the host for the
+//! log: each call must match the next recorded one (syscall number and
+//! arguments) and gets its recorded result without touching host files,
+//! stdin or the clock, so the guest runs exactly as it did. Writes to
+//! stdout and stderr still reach the runner's streams during replay.
+//!
+//! The emitted code is the same in both modes: the journal sits behind the
+//! `RvIo` callbacks, and serves `clock_gettime` and `getrandom` as host
+//! syscalls (libraries compiled with `SyscallMode::Linux`). Syscalls served
+//! by registered handlers and hooked functions run as they are in both
+//! modes.
+
+use std::ffi::{CStr, c_char, c_void};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rvr_isa::syscalls::syscall_nr::{
+    SYS_CLOCK_GETTIME, SYS_CLOCK_GETTIME64, SYS_CLOSE, SYS_GETRANDOM, SYS_LSEEK, SYS_NEWFSTATAT,
+    SYS_OPENAT, SYS_READ, SYS_WRITE,
+};
+use rvr_state::{GuestIo, GuestStat};
+
+use super::io::GuestContext;
+use super::{RunError, Runner};
+
+/// First bytes of a recording file.
+const MAGIC: &[u8; 8] = b"RVRINPUT";
+/// Recording file format version.
+const VERSION: u64 = 1;
+
+/// Syscalls the journal serves itself unless a handler is registered.
+const JOURNALED_SYSCALLS: [u64; 3] = [SYS_CLOCK_GETTIME, SYS_GETRANDOM, SYS_CLOCK_GETTIME64];
+
+/// `EPERM`, `EBADF`, `EIO` and `EFAULT`, as the generated code returns them.
+const EPERM: i64 = 1;
+const EIO: i64 = 5;
+const EBADF: i64 = 9;
+const EFAULT: i64 = 14;
+
+/// Source of `getrandom` bytes while recording.
+const ENTROPY_PATH: &str = "/dev/urandom";
+
+/// A host call as the guest made it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Call {
+    /// Linux syscall number the call serves.
+    num: u64,
+    /// Arguments that identify the call (fds, lengths, flags, offsets).
+    args: Vec<u64>,
+    /// Path argument of `openat` and `fstatat` (empty for none).
+    path: Vec<u8>,
+}
+
+impl Call {
+    fn new(num: u64, args: &[u64]) -> Self {
+        Self {
+            num,
+            args: args.to_vec(),
+            path: Vec::new(),
+        }
+    }
+
+    fn with_path(mut self, path: &[u8]) -> Self {
+        self.path = path.to_vec();
+        self
+    }
+}
+
+impl std::fmt::Display for Call {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self.num {
+            SYS_READ => "read",
+            SYS_WRITE => "write",
+            SYS_OPENAT => "openat",
+            SYS_CLOSE => "close",
+            SYS_LSEEK => "lseek",
+            SYS_NEWFSTATAT => "fstatat",
+            SYS_CLOCK_GETTIME | SYS_CLOCK_GETTIME64 => "clock_gettime",
+            SYS_GETRANDOM => "getrandom",
+            num => return write!(f, "syscall {num}{:?}", self.args),
+        };
+        let mut args: Vec<String> = self.args.iter().map(|arg| format!("{arg:#x}")).collect();
+        if !self.path.is_empty() {
+            args.push(format!("{:?}", String::from_utf8_lossy(&self.path)));
+        }
+        write!(f, "{name}({})", args.join(", "))
+    }
+}
+
+/// One recorded input: a call and what the guest got back.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct InputEvent {
+    call: Call,
+    /// Value returned to the guest.
+    ret: i64,
+    /// Bytes handed to the guest: read data, stat fields, the timespec or
+    /// random bytes.
+    data: Vec<u8>,
+}
+
+/// Every input a guest consumed from the host, in order, with the command
+/// line and environment it started with.
+///
+/// Saved as a magic and version followed by length-prefixed little-endian
+/// records: a header, then one record per input.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct InputRecording {
+    args: Vec<String>,
+    env: Vec<String>,
+    /// Program break the ELF starts the guest with.
+    initial_brk: u64,
+    events: Vec<InputEvent>,
+}
+
+impl InputRecording {
+    /// Number of recorded inputs.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// True if the guest consumed no host input.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Write the recording to `path`.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be written.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), RunError> {
+        std::fs::write(path, self.to_bytes())?;
+        Ok(())
+    }
+
+    /// Read a recording saved with [`save`](Self::save).
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or is not a recording.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, RunError> {
+        let path = path.as_ref();
+        Self::from_bytes(&std::fs::read(path)?).map_err(|reason| RunError::InputRecording {
+            path: path.display().to_string(),
+            reason,
+        })
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Encoder(MAGIC.to_vec());
+        out.u64(VERSION);
+        let mut header = Encoder::default();
+        header.u64(self.initial_brk);
+        header.strings(&self.args);
+        header.strings(&self.env);
+        out.bytes(&header.0);
+        for event in &self.events {
+            let mut record = Encoder::default();
+            record.u64(event.call.num);
+            record.u64(event.call.args.len() as u64);
+            for &arg in &event.call.args {
+                record.u64(arg);
+            }
+            record.bytes(&event.call.path);
+            record.u64(event.ret.cast_unsigned());
+            record.bytes(&event.data);
+            out.bytes(&record.0);
+        }
+        out.0
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let mut input = Decoder(bytes);
+        if input.take(MAGIC.len())? != MAGIC {
+            return Err("not an input recording".to_string());
+        }
+        let version = input.u64()?;
+        if version != VERSION {
+            return Err(format!("format version {version}, expected {VERSION}"));
+        }
+        let mut header = input.record()?;
+        let initial_brk = header.u64()?;
+        let args = header.strings()?;
+        let env = header.strings()?;
+        let mut events = Vec::new();
+        while !input.0.is_empty() {
+            let mut record = input.record()?;
+            let num = record.u64()?;
+            let count = record.u64()?;
+            let args = (0..count).map(|_| record.u64()).collect::<Result<_, _>>()?;
+            let path = record.bytes()?.to_vec();
+            let ret = record.u64()?.cast_signed();
+            let data = record.bytes()?.to_vec();
+            events.push(InputEvent {
+                call: Call { num, args, path },
+                ret,
+                data,
+            });
+        }
+        Ok(Self {
+            args,
+            env,
+            initial_brk,
+            events,
+        })
+    }
+}
+
+/// Little-endian writer of recording fields.
+#[derive(Default)]
+struct Encoder(Vec<u8>);
+
+impl Encoder {
+    fn u64(&mut self, value: u64) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    /// `bytes`, prefixed with its length.
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.u64(bytes.len() as u64);
+        self.0.extend_from_slice(bytes);
+    }
+
+    fn strings(&mut self, strings: &[String]) {
+        self.u64(strings.len() as u64);
+        for string in strings {
+            self.bytes(string.as_bytes());
+        }
+    }
+}
+
+/// Reader of the fields [`Encoder`] writes.
+struct Decoder<'a>(&'a [u8]);
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        if len > self.0.len() {
+            return Err("truncated record".to_string());
+        }
+        let (head, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(head)
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        let bytes = self.take(8)?;
+        Ok(u64::from_le_bytes(bytes.try_into().expect("8 bytes")))
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8], String> {
+        let len = usize::try_from(self.u64()?).map_err(|_| "oversized record".to_string())?;
+        self.take(len)
+    }
+
+    /// The next length-prefixed record.
+    fn record(&mut self) -> Result<Self, String> {
+        Ok(Self(self.bytes()?))
+    }
+
+    fn strings(&mut self) -> Result<Vec<String>, String> {
+        let count = self.u64()?;
+        (0..count)
+            .map(|_| {
+                String::from_utf8(self.bytes()?.to_vec())
+                    .map_err(|_| "string is not UTF-8".to_string())
+            })
+            .collect()
+    }
+}
+
+/// Inputs handed out during a replay.
+struct Replay {
+    events: Vec<InputEvent>,
+    /// Index of the next input to hand out.
+    next: usize,
+    /// First call that did not match its recorded input, and why.
+    diverged: Option<(usize, String)>,
+}
+
+impl Replay {
+    /// The recorded input for `call`, or `None` (noting why) if the guest
+    /// left the recorded sequence, now or before.
+    fn next(&mut self, call: &Call) -> Option<&InputEvent> {
+        if self.diverged.is_some() {
+            return None;
+        }
+        let index = self.next;
+        let reason = match self.events.get(index) {
+            None => format!("guest made {call} after the last recorded input"),
+            Some(event) if event.call != *call => {
+                format!("recorded {}, guest made {call}", event.call)
+            }
+            Some(_) => {
+                self.next += 1;
+                return self.events.get(index);
+            }
+        };
+        self.diverged = Some((index, reason));
+        None
+    }
+}
+
+enum Mode {
+    Record(Vec<InputEvent>),
+    Replay(Replay),
+}
+
+/// What a journaled call does.
+enum Input<'a> {
+    /// Make the call on the host, then [`Journal::record`] its result.
+    Host,
+    /// Return the recorded value after handing the guest the recorded bytes.
+    Replayed(i64, &'a [u8]),
+    /// The replay diverged: fail the call.
+    Diverged,
+}
+
+/// Hook table wrapping the runner's own, recording or replaying every host
+/// input that passes through it.
+///
+/// Lives on the heap so the wrapped table stays valid when the owning
+/// [`Runner`] moves.
+pub(super) struct Journal {
+    /// The runner's table, which recording passes calls through to.
+    inner: GuestIo,
+    /// Table installed into the guest state.
+    table: GuestIo,
+    /// `inner`'s host syscalls and [`JOURNALED_SYSCALLS`], sorted.
+    nums: Vec<u64>,
+    mode: Mode,
+}
+
+impl Journal {
+    fn new(mode: Mode, inner: *mut GuestIo) -> Box<Self> {
+        let inner = unsafe { *inner };
+        Box::new(Self {
+            inner,
+            table: inner,
+            nums: Vec::new(),
+            mode,
+        })
+    }
+
+    /// Wrap the runner's table `inner`; returns the table to install.
+    pub(super) fn wrap(&mut self, inner: *mut GuestIo) -> *mut GuestIo {
+        self.inner = unsafe { *inner };
+        self.nums = inner_nums(&self.inner).to_vec();
+        self.nums.extend(JOURNALED_SYSCALLS);
+        self.nums.sort_unstable();
+        self.nums.dedup();
+        let ctx = std::ptr::from_mut(self).cast();
+        // fds above 2 always reach the journal, so guest opens fail the same
+        // way with and without the preopens of the recording
+        self.table = GuestIo {
+            ctx,
+            read: journal_read,
+            write: journal_write,
+            syscall_ctx: ctx,
+            syscall_nums: self.nums.as_ptr(),
+            syscall_count: self.nums.len() as u64,
+            syscall: Some(journal_syscall),
+            open: Some(journal_open),
+            close: Some(journal_close),
+            seek: Some(journal_seek),
+            stat: Some(journal_stat),
+            ..self.inner
+        };
+        std::ptr::from_mut(&mut self.table)
+    }
+
+    fn input(&mut self, call: &Call) -> Input<'_> {
+        match &mut self.mode {
+            Mode::Record(_) => Input::Host,
+            Mode::Replay(replay) => replay.next(call).map_or(Input::Diverged, |event| {
+                Input::Replayed(event.ret, &event.data)
+            }),
+        }
+    }
+
+    /// Inputs recorded so far (none when replaying).
+    fn into_events(self) -> Vec<InputEvent> {
+        match self.mode {
+            Mode::Record(events) => events,
+            Mode::Replay(_) => Vec::new(),
+        }
+    }
+
+    fn record(&mut self, call: Call, ret: i64, data: Vec<u8>) -> i64 {
+        if let Mode::Record(events) = &mut self.mode {
+            events.push(InputEvent { call, ret, data });
+        }
+        ret
+    }
+
+    /// Fail with `ReplayDiverged` if the guest left the recorded sequence,
+    /// or if it `exited` before consuming all of it.
+    fn check(&self, exited: bool) -> Result<(), RunError> {
+        let Mode::Replay(replay) = &self.mode else {
+            return Ok(());
+        };
+        if let Some((index, reason)) = &replay.diverged {
+            return Err(RunError::ReplayDiverged {
+                index: *index,
+                reason: reason.clone(),
+            });
+        }
+        let left = replay.events.len() - replay.next;
+        if exited && left > 0 {
+            return Err(RunError::ReplayDiverged {
+                index: replay.next,
+                reason: format!("guest exited with {left} recorded inputs left"),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Host syscall numbers of `io`.
+fn inner_nums(io: &GuestIo) -> &[u64] {
+    if io.syscall_count == 0 {
+        return &[];
+    }
+    let len = usize::try_from(io.syscall_count).unwrap_or(0);
+    unsafe { std::slice::from_raw_parts(io.syscall_nums, len) }
+}
+
+unsafe extern "C" fn journal_read(ctx: *mut c_void, fd: u32, buf: *mut u8, len: usize) -> i64 {
+    let journal = unsafe { &mut *ctx.cast::<Journal>() };
+    let call = Call::new(SYS_READ, &[u64::from(fd), len as u64]);
+    match journal.input(&call) {
+        Input::Host => {
+            let inner = journal.inner;
+            let ret = unsafe { (inner.read)(inner.ctx, fd, buf, len) };
+            let read = usize::try_from(ret).unwrap_or(0).min(len);
+            let data = unsafe { std::slice::from_raw_parts(buf, read) }.to_vec();
+            journal.record(call, ret, data)
+        }
+        Input::Replayed(ret, data) => {
+            let read = data.len().min(len);
+            unsafe { std::ptr::copy_nonoverlapping(data.as_ptr(), buf, read) };
+            ret
+        }
+        Input::Diverged => -EIO,
+    }
+}
+
+unsafe extern "C" fn journal_write(ctx: *mut c_void, fd: u32, buf: *const u8, len: usize) -> i64 {
+    let journal = unsafe { &mut *ctx.cast::<Journal>() };
+    let inner = journal.inner;
+    let call = Call::new(SYS_WRITE, &[u64::from(fd), len as u64]);
+    match journal.input(&call) {
+        Input::Host => {
+            let ret = unsafe { (inner.write)(inner.ctx, fd, buf, len) };
+            journal.record(call, ret, Vec::new())
+        }
+        Input::Replayed(ret, _) => {
+            if fd == 1 || fd == 2 {
+                unsafe { (inner.write)(inner.ctx, fd, buf, len) };
+            }
+            ret
+        }
+        Input::Diverged => -EIO,
+    }
+}
+
+/// Bytes of NUL-terminated `path` (empty for null).
+///
+/// # Safety
+/// `path` must be null or point to a NUL-terminated string that stays
+/// valid and unmodified for `'a`.
+const unsafe fn path_bytes<'a>(path: *const c_char) -> &'a [u8] {
+    if path.is_null() {
+        return &[];
+    }
+    unsafe { CStr::from_ptr(path) }.to_bytes()
+}
+
+unsafe extern "C" fn journal_open(
+    ctx: *mut c_void,
+    dirfd: i32,
+    path: *const c_char,
+    flags: u64,
+    mode: u64,
+) -> i64 {
+    let journal = unsafe { &mut *ctx.cast::<Journal>() };
+    let dirfd_arg = i64::from(dirfd).cast_unsigned();
+    // SAFETY: the guest path is NUL-terminated and outlives the call.
+    let call =
+        Call::new(SYS_OPENAT, &[dirfd_arg, flags, mode]).with_path(unsafe { path_bytes(path) });
+    match journal.input(&call) {
+        Input::Host => {
+            let inner = journal.inner;
+            let ret = inner.open.map_or(-EPERM, |open| unsafe {
+                open(inner.ctx, dirfd, path, flags, mode)
+            });
+            journal.record(call, ret, Vec::new())
+        }
+        Input::Replayed(ret, _) => ret,
+        Input::Diverged => -EIO,
+    }
+}
+
+unsafe extern "C" fn journal_close(ctx: *mut c_void, fd: u32) -> i64 {
+    let journal = unsafe { &mut *ctx.cast::<Journal>() };
+    let call = Call::new(SYS_CLOSE, &[u64::from(fd)]);
+    match journal.input(&call) {
+        Input::Host => {
+            let inner = journal.inner;
+            let ret = inner
+                .close
+                .map_or(-EBADF, |close| unsafe { close(inner.ctx, fd) });
+            journal.record(call, ret, Vec::new())
+        }
+        Input::Replayed(ret, _) => ret,
+        Input::Diverged => -EIO,
+    }
+}
+
+unsafe extern "C" fn journal_seek(ctx: *mut c_void, fd: u32, offset: i64, whence: u32) -> i64 {
+    let journal = unsafe { &mut *ctx.cast::<Journal>() };
+    let args = [u64::from(fd), offset.cast_unsigned(), u64::from(whence)];
+    let call = Call::new(SYS_LSEEK, &args);
+    match journal.input(&call) {
+        Input::Host => {
+            let inner = journal.inner;
+            let ret = inner.seek.map_or(-EBADF, |seek| unsafe {
+                seek(inner.ctx, fd, offset, whence)
+            });
+            journal.record(call, ret, Vec::new())
+        }
+        Input::Replayed(ret, _) => ret,
+        Input::Diverged => -EIO,
+    }
+}
+
+unsafe extern "C" fn journal_stat(
+    ctx: *mut c_void,
+    dirfd: i32,
+    path: *const c_char,
+    flags: u32,
+    out: *mut GuestStat,
+) -> i64 {
+    let journal = unsafe { &mut *ctx.cast::<Journal>() };
+    let args = [i64::from(dirfd).cast_unsigned(), u64::from(flags)];
+    // SAFETY: the guest path is NUL-terminated and outlives the call.
+    let call = Call::new(SYS_NEWFSTATAT, &args).with_path(unsafe { path_bytes(path) });
+    match journal.input(&call) {
+        Input::Host => {
+            let inner = journal.inner;
+            let fallback = if path.is_null() { -EBADF } else { -EPERM };
+            let ret = inner.stat.map_or(fallback, |stat| unsafe {
+                stat(inner.ctx, dirfd, path, flags, out)
+            });
+            let data = if ret == 0 {
+                stat_bytes(unsafe { &*out })
+            } else {
+                Vec::new()
+            };
+            journal.record(call, ret, data)
+        }
+        Input::Replayed(ret, data) => {
+            if let Some(stat) = stat_from_bytes(data) {
+                unsafe { out.write(stat) };
+            }
+            ret
+        }
+        Input::Diverged => -EIO,
+    }
+}
+
+fn stat_bytes(stat: &GuestStat) -> Vec<u8> {
+    let mut out = Encoder::default();
+    out.u64(stat.size);
+    out.u64(u64::from(stat.mode));
+    out.u64(u64::from(stat.blksize));
+    out.u64(stat.blocks);
+    out.0
+}
+
+fn stat_from_bytes(data: &[u8]) -> Option<GuestStat> {
+    let mut input = Decoder(data);
+    Some(GuestStat {
+        size: input.u64().ok()?,
+        mode: u32::try_from(input.u64().ok()?).ok()?,
+        blksize: u32::try_from(input.u64().ok()?).ok()?,
+        blocks: input.u64().ok()?,
+    })
+}
+
+unsafe extern "C" fn journal_syscall(
+    ctx: *mut c_void,
+    num: u64,
+    args: *const u64,
+    memory: *mut u8,
+    memory_size: u64,
+) -> i64 {
+    let journal = unsafe { &mut *ctx.cast::<Journal>() };
+    let inner = journal.inner;
+    if inner_nums(&inner).binary_search(&num).is_ok()
+        && let Some(syscall) = inner.syscall
+    {
+        return unsafe { syscall(inner.syscall_ctx, num, args, memory, memory_size) };
+    }
+    let args = unsafe { *args.cast::<[u64; 6]>() };
+    let len = usize::try_from(memory_size).unwrap_or(usize::MAX);
+    let memory = unsafe { std::slice::from_raw_parts_mut(memory, len) };
+    let mut guest = GuestContext::new(num, args, memory);
+    // clock_gettime(clk_id, tp) and getrandom(buf, len, flags)
+    let (call, addr) = if num == SYS_GETRANDOM {
+        (Call::new(num, &args[..3]), args[0])
+    } else {
+        (Call::new(num, &args[..2]), args[1])
+    };
+    match journal.input(&call) {
+        Input::Host => {
+            let data = if num == SYS_GETRANDOM {
+                match host_random(&guest, addr, args[1]) {
+                    Ok(data) => data,
+                    Err(errno) => return journal.record(call, errno, Vec::new()),
+                }
+            } else {
+                host_clock()
+            };
+            if !guest.write(addr, &data) {
+                return journal.record(call, -EFAULT, Vec::new());
+            }
+            let ret = if num == SYS_GETRANDOM {
+                i64::try_from(data.len()).unwrap_or(i64::MAX)
+            } else {
+                0
+            };
+            journal.record(call, ret, data)
+        }
+        Input::Replayed(ret, data) => {
+            guest.write(addr, data);
+            ret
+        }
+        Input::Diverged => -EIO,
+    }
+}
+
+/// `struct timespec` (64-bit fields, as the built-in `clock_gettime`
+/// writes it) for the host's realtime clock.
+fn host_clock() -> Vec<u8> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let mut out = Encoder::default();
+    out.u64(now.as_secs());
+    out.u64(u64::from(now.subsec_nanos()));
+    out.0
+}
+
+/// `len` bytes of host entropy for the guest buffer at `addr`, or a
+/// negative errno.
+fn host_random(guest: &GuestContext<'_>, addr: u64, len: u64) -> Result<Vec<u8>, i64> {
+    if guest.memory(addr, len).is_none() {
+        return Err(-EFAULT);
+    }
+    let mut data = vec![0; usize::try_from(len).map_err(|_| -EFAULT)?];
+    File::open(ENTROPY_PATH)
+        .and_then(|mut file| file.read_exact(&mut data))
+        .map_err(|_| -EIO)?;
+    Ok(data)
+}
+
+impl Runner {
+    /// Record every input the guest takes from the host from now on, for
+    /// [`take_recording`](Self::take_recording).
+    ///
+    /// Guest reads (stdin and opened files), opens, seeks, stats and
+    /// closes, writes' results, `clock_gettime` and `getrandom` are logged
+    /// in order; the guest sees the host as it would without recording,
+    /// except that `getrandom` returns host entropy. Takes effect in
+    /// libraries compiled with `SyscallMode::Linux`.
+    pub fn record_inputs(&mut self) {
+        let table = self.host_hooks().table();
+        self.journal = Some(Journal::new(Mode::Record(Vec::new()), table));
+        self.install_hooks();
+    }
+
+    /// Stop recording and return the inputs of every run since
+    /// [`record_inputs`](Self::record_inputs), with the guest's command
+    /// line and environment; `None` if not recording.
+    pub fn take_recording(&mut self) -> Option<InputRecording> {
+        let events = self
+            .journal
+            .take_if(|journal| matches!(journal.mode, Mode::Record(_)))?
+            .into_events();
+        self.install_hooks();
+        Some(InputRecording {
+            args: self.args.clone(),
+            env: self.env.clone(),
+            initial_brk: self.initial_brk,
+            events,
+        })
+    }
+
+    /// Serve the guest's host inputs from `recording` instead of the host,
+    /// and start it with the recording's command line and environment.
+    ///
+    /// Each input must match the next recorded one; the first that does
+    /// not, and every one after it, fails with `EIO`, and the run returns
+    /// `ReplayDiverged`, as does a guest exiting before it consumed the
+    /// whole recording.
+    ///
+    /// # Errors
+    /// Returns `ReplayDiverged` if the recording was made with an ELF that
+    /// starts the program break elsewhere.
+    pub fn replay_inputs(&mut self, recording: InputRecording) -> Result<(), RunError> {
+        if recording.initial_brk != self.initial_brk {
+            return Err(RunError::ReplayDiverged {
+                index: 0,
+                reason: format!(
+                    "recorded initial brk {:#x}, the ELF starts it at {:#x}",
+                    recording.initial_brk, self.initial_brk
+                ),
+            });
+        }
+        self.args = recording.args;
+        self.env = recording.env;
+        let replay = Replay {
+            events: recording.events,
+            next: 0,
+            diverged: None,
+        };
+        let table = self.host_hooks().table();
+        self.journal = Some(Journal::new(Mode::Replay(replay), table));
+        self.install_hooks();
+        Ok(())
+    }
+
+    /// Fail with `ReplayDiverged` if a replay left its recording.
+    pub(super) fn check_replay(&self) -> Result<(), RunError> {
+        self.journal
+            .as_ref()
+            .map_or(Ok(()), |journal| journal.check(self.inner.has_exited()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(num: u64, args: &[u64], ret: i64, data: &[u8]) -> InputEvent {
+        InputEvent {
+            call: Call::new(num, args),
+            ret,
+            data: data.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_recording_round_trip() {
+        let recording = InputRecording {
+            args: vec!["guest".to_string(), "-v".to_string()],
+            env: vec!["TZ=UTC".to_string()],
+            initial_brk: 0x2_1000,
+            events: vec![
+                InputEvent {
+                    call: Call::new(SYS_OPENAT, &[(-100i64).cast_unsigned(), 0, 0])
+                        .with_path(b"/data/in.txt"),
+                    ret: 4,
+                    data: Vec::new(),
+                },
+                event(SYS_READ, &[4, 64], 5, b"hello"),
+                event(SYS_READ, &[4, 64], -9, &[]),
+                event(SYS_CLOCK_GETTIME, &[0, 0x3000], 0, &[7; 16]),
+            ],
+        };
+        let bytes = recording.to_bytes();
+        assert_eq!(InputRecording::from_bytes(&bytes), Ok(recording));
+        assert!(InputRecording::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(InputRecording::from_bytes(b"NOTINPUT").is_err());
+    }
+
+    #[test]
+    fn test_replay_diverges_on_other_call() {
+        let mut replay = Replay {
+            events: vec![event(SYS_READ, &[0, 16], 3, b"abc")],
+            next: 0,
+            diverged: None,
+        };
+        assert!(replay.next(&Call::new(SYS_READ, &[0, 8])).is_none());
+        let (index, reason) = replay.diverged.clone().unwrap();
+        assert_eq!(index, 0);
+        assert_eq!(
+            reason,
+            "recorded read(0x0, 0x10), guest made read(0x0, 0x8)"
+        );
+        // Once diverged, even the recorded call fails
+        assert!(replay.next(&Call::new(SYS_READ, &[0, 16])).is_none());
+    }
+}
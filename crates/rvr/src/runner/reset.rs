@@ -0,0 +1,67 @@
+//! Fast guest memory reset between runs.
+//!
+//! Re-initializing guest memory copies every ELF segment, which dominates
+//! short runs of programs with large images. After the first
+//! initialization the image is frozen into a [`MemorySnapshot`] and guest
+//! memory is mapped copy-on-write over it, so the kernel keeps track of
+//! the pages a run dirties and a reset only has to drop those.
+
+use rvr_state::MemorySnapshot;
+use tracing::{debug, warn};
+
+use super::Runner;
+
+/// Initialized guest memory a fast reset returns to.
+#[derive(Debug, Default)]
+pub(super) enum PristineMemory {
+    /// Not captured yet.
+    #[default]
+    Unset,
+    /// The image right after initialization.
+    Frozen(MemorySnapshot),
+    /// Guest memory cannot be frozen; resets re-initialize it in full.
+    Unavailable,
+}
+
+impl Runner {
+    /// Re-initialize guest memory and state like [`prepare`](Self::prepare),
+    /// restoring only the pages dirtied since the last reset.
+    ///
+    /// The first call initializes memory in full and freezes the result;
+    /// later calls remap guest memory over that copy, at a cost
+    /// proportional to the pages the guest wrote rather than to the image
+    /// size. Falls back to a full re-initialization when memory cannot be
+    /// frozen or remapped. Returns whether the fast path was taken.
+    ///
+    /// [`run_multiple`](Self::run_multiple) resets this way between runs.
+    pub fn reset_memory_fast(&mut self) -> bool {
+        let fast = match &self.pristine {
+            PristineMemory::Frozen(image) => match self.inner.memory_mut().restore(image) {
+                Ok(()) => true,
+                Err(err) => {
+                    warn!(%err, "restoring initialized memory failed, re-initializing");
+                    self.pristine = PristineMemory::Unavailable;
+                    self.inner.load_segments(self.segments.as_ref());
+                    false
+                }
+            },
+            PristineMemory::Unset => {
+                self.inner.load_segments(self.segments.as_ref());
+                self.pristine = match self.inner.memory_mut().snapshot() {
+                    Ok(image) => PristineMemory::Frozen(image),
+                    Err(err) => {
+                        debug!(%err, "cannot freeze initialized memory, resets copy segments");
+                        PristineMemory::Unavailable
+                    }
+                };
+                false
+            }
+            PristineMemory::Unavailable => {
+                self.inner.load_segments(self.segments.as_ref());
+                false
+            }
+        };
+        self.inner.reset();
+        fast
+    }
+}
@@ -0,0 +1,237 @@
+//! Run results, phase timings and hardware performance counters.
+
+use serde::{Deserialize, Serialize};
+
+use super::ExitReason;
+
+fn u64_to_f64(value: u64) -> f64 {
+    let hi = u32::try_from(value >> 32).unwrap_or(u32::MAX);
+    let lo = u32::try_from(value & 0xFFFF_FFFF).unwrap_or(u32::MAX);
+    f64::from(hi) * 4_294_967_296.0 + f64::from(lo)
+}
+
+fn usize_to_f64(value: usize) -> f64 {
+    let value = u64::try_from(value).unwrap_or(u64::MAX);
+    u64_to_f64(value)
+}
+
+/// Wall-clock time of each phase of a run, in seconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunPhases {
+    /// Loading the library and ELF: dlopen, symbol resolution and runner
+    /// setup. Only the first run of a runner pays for it.
+    pub load_secs: f64,
+    /// Guest memory init and register setup.
+    pub init_secs: f64,
+    /// The `rv_execute_from` call.
+    pub execute_secs: f64,
+    /// Collecting the exit code and instret after execution.
+    pub teardown_secs: f64,
+}
+
+impl RunPhases {
+    /// Combined time of all phases.
+    #[must_use]
+    pub fn total_secs(&self) -> f64 {
+        self.load_secs + self.init_secs + self.execute_secs + self.teardown_secs
+    }
+
+    /// Phases as a JSON object.
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        format!(
+            r#"{{"load":{:.6},"init":{:.6},"execute":{:.6},"teardown":{:.6}}}"#,
+            self.load_secs, self.init_secs, self.execute_secs, self.teardown_secs
+        )
+    }
+}
+
+/// Execution result.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunResult {
+    /// Exit code from the program.
+    pub exit_code: u8,
+    /// Why execution stopped.
+    pub exit_reason: ExitReason,
+    /// Instruction count (guest instructions retired).
+    pub instret: u64,
+    /// Execution time in seconds (`phases.execute_secs`).
+    pub time_secs: f64,
+    /// Speed in MIPS (million instructions per second) over the execution
+    /// time.
+    pub mips: f64,
+    /// Wall-clock breakdown of the run.
+    pub phases: RunPhases,
+    /// Guest memory resident in host memory at the end of the run, if the
+    /// runner could measure it.
+    pub resident_bytes: Option<u64>,
+    /// Mean init time of the runs after the first, for an average of
+    /// several: the per-run reset cost without the first full
+    /// initialization.
+    #[serde(default)]
+    pub reset_secs: Option<f64>,
+}
+
+impl RunResult {
+    /// Result of a run with the given phase timings. The exit reason is a
+    /// guest exit with `exit_code` until set with
+    /// [`with_exit_reason`](Self::with_exit_reason).
+    #[must_use]
+    pub fn new(exit_code: u8, instret: u64, phases: RunPhases) -> Self {
+        Self {
+            exit_code,
+            exit_reason: ExitReason::Exited(exit_code),
+            instret,
+            time_secs: phases.execute_secs,
+            mips: mips(instret, phases.execute_secs),
+            phases,
+            resident_bytes: None,
+            reset_secs: None,
+        }
+    }
+
+    /// The same result with the exit reason set.
+    #[must_use]
+    pub const fn with_exit_reason(mut self, exit_reason: ExitReason) -> Self {
+        self.exit_reason = exit_reason;
+        self
+    }
+
+    /// The same result with the resident guest memory set.
+    #[must_use]
+    pub const fn with_resident_bytes(mut self, resident_bytes: Option<u64>) -> Self {
+        self.resident_bytes = resident_bytes;
+        self
+    }
+
+    /// Average of several runs: mean time, MIPS and phases, the largest
+    /// resident memory, the mean init time after the first run, and the
+    /// exit code, exit reason and instret of the first run.
+    /// `None` if `results` is empty.
+    #[must_use]
+    pub fn average(results: &[Self]) -> Option<Self> {
+        let first = results.first()?;
+        let count = usize_to_f64(results.len());
+        let mean = |field: fn(&Self) -> f64| results.iter().map(field).sum::<f64>() / count;
+        Some(Self {
+            exit_code: first.exit_code,
+            exit_reason: first.exit_reason,
+            instret: first.instret,
+            time_secs: mean(|r| r.time_secs),
+            mips: mean(|r| r.mips),
+            phases: RunPhases {
+                load_secs: mean(|r| r.phases.load_secs),
+                init_secs: mean(|r| r.phases.init_secs),
+                execute_secs: mean(|r| r.phases.execute_secs),
+                teardown_secs: mean(|r| r.phases.teardown_secs),
+            },
+            resident_bytes: results.iter().filter_map(|r| r.resident_bytes).max(),
+            reset_secs: (results.len() > 1).then(|| {
+                let resets = &results[1..];
+                resets.iter().map(|r| r.phases.init_secs).sum::<f64>() / usize_to_f64(resets.len())
+            }),
+        })
+    }
+
+    /// Wall-clock time of the whole run, load and init included.
+    #[must_use]
+    pub fn total_secs(&self) -> f64 {
+        self.phases.total_secs()
+    }
+
+    /// Speed in MIPS over the whole run, load and init included.
+    #[must_use]
+    pub fn total_mips(&self) -> f64 {
+        mips(self.instret, self.total_secs())
+    }
+
+    /// Print result in raw key-value format (for scripting).
+    pub fn print_raw_format(&self) {
+        println!("instret: {}", self.instret);
+        println!("time: {:.6}", self.time_secs);
+        println!("mips: {:.2}", self.mips);
+        println!("load: {:.6}", self.phases.load_secs);
+        println!("init: {:.6}", self.phases.init_secs);
+        println!("execute: {:.6}", self.phases.execute_secs);
+        println!("teardown: {:.6}", self.phases.teardown_secs);
+        println!("total_time: {:.6}", self.total_secs());
+        println!("total_mips: {:.2}", self.total_mips());
+        if let Some(bytes) = self.resident_bytes {
+            println!("resident_bytes: {bytes}");
+        }
+    }
+
+    /// Print result in JSON format.
+    pub fn print_json(&self) {
+        println!(
+            r#"{{"instret":{},"time":{:.6},"mips":{:.2},"exit_code":{},"phases":{},"total_time":{:.6},"total_mips":{:.2},"resident_bytes":{}}}"#,
+            self.instret,
+            self.time_secs,
+            self.mips,
+            self.exit_code,
+            self.phases.to_json(),
+            self.total_secs(),
+            self.total_mips(),
+            self.resident_bytes_json()
+        );
+    }
+
+    /// `resident_bytes` as a JSON value (`null` if unknown).
+    #[must_use]
+    pub fn resident_bytes_json(&self) -> String {
+        self.resident_bytes
+            .map_or_else(|| "null".to_string(), |bytes| bytes.to_string())
+    }
+}
+
+fn mips(instret: u64, secs: f64) -> f64 {
+    (u64_to_f64(instret) / secs) / 1_000_000.0
+}
+
+/// Hardware performance counters from perf.
+#[derive(Debug, Clone, Default)]
+pub struct PerfCounters {
+    /// Host CPU cycles.
+    pub cycles: Option<u64>,
+    /// Host instructions executed.
+    pub instructions: Option<u64>,
+    /// Branch instructions.
+    pub branches: Option<u64>,
+    /// Branch misses.
+    pub branch_misses: Option<u64>,
+    /// Data TLB read misses (if the host PMU counts them).
+    pub dtlb_misses: Option<u64>,
+}
+
+impl PerfCounters {
+    /// Calculate instructions per cycle.
+    #[must_use]
+    pub fn ipc(&self) -> Option<f64> {
+        match (self.instructions, self.cycles) {
+            (Some(i), Some(c)) if c > 0 => Some(u64_to_f64(i) / u64_to_f64(c)),
+            _ => None,
+        }
+    }
+
+    /// Calculate branch miss rate as percentage.
+    #[must_use]
+    pub fn branch_miss_rate(&self) -> Option<f64> {
+        match (self.branch_misses, self.branches) {
+            (Some(m), Some(b)) if b > 0 => Some((u64_to_f64(m) / u64_to_f64(b)) * 100.0),
+            _ => None,
+        }
+    }
+}
+
+/// Execution result with hardware performance counters.
+#[derive(Debug, Clone)]
+pub struct RunResultWithPerf {
+    /// Core execution result.
+    pub result: RunResult,
+    /// Hardware performance counters (if available).
+    pub perf: Option<PerfCounters>,
+}
+
+// ============================================================================
+// Runner - public API
+// ============================================================================
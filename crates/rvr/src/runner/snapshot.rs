@@ -0,0 +1,102 @@
+//! Guest state checkpointing.
+//!
+//! A [`Snapshot`] holds the architectural state and a frozen copy of guest
+//! memory. Taking a snapshot maps guest memory copy-on-write over the frozen
+//! copy, so [`Runner::restore`] only has to discard the pages dirtied since.
+
+use rvr_state::{MemorySnapshot, StateSnapshot};
+use tracing::debug;
+
+use super::{RunError, Runner};
+
+/// Checkpoint of a runner's guest state.
+///
+/// Captures registers, PC, CSRs, instret, exit flags and guest memory.
+/// Host-side settings such as the suspend target and tracer buffers are not
+/// part of the snapshot. Cloning is cheap; clones share the frozen memory.
+#[derive(Clone, Debug)]
+pub struct Snapshot {
+    xlen: u8,
+    state: StateSnapshot,
+    memory: MemorySnapshot,
+}
+
+impl Snapshot {
+    pub(super) const fn new(xlen: u8, state: StateSnapshot, memory: MemorySnapshot) -> Self {
+        Self {
+            xlen,
+            state,
+            memory,
+        }
+    }
+
+    /// Program counter at the time of the snapshot.
+    #[must_use]
+    pub const fn pc(&self) -> u64 {
+        self.state.pc
+    }
+
+    /// Instruction count at the time of the snapshot.
+    #[must_use]
+    pub const fn instret(&self) -> u64 {
+        self.state.instret
+    }
+
+    /// Captured architectural state.
+    #[must_use]
+    pub const fn state(&self) -> &StateSnapshot {
+        &self.state
+    }
+
+    /// Captured guest memory.
+    #[must_use]
+    pub const fn memory(&self) -> &MemorySnapshot {
+        &self.memory
+    }
+}
+
+impl Runner {
+    /// Snapshot the current guest state.
+    ///
+    /// Guest memory is remapped copy-on-write over the snapshot, so a later
+    /// [`restore`](Self::restore) costs time proportional to the pages dirtied
+    /// in between rather than to the memory size.
+    ///
+    /// # Errors
+    /// Returns an error if guest memory cannot be frozen.
+    pub fn snapshot(&mut self) -> Result<Snapshot, RunError> {
+        let snapshot = self.inner.snapshot()?;
+        debug!(
+            pc = format!("{:#x}", snapshot.pc()),
+            instret = snapshot.instret(),
+            "snapshot taken"
+        );
+        Ok(snapshot)
+    }
+
+    /// Restore guest state from a snapshot.
+    ///
+    /// The suspend target and tracer state are left as they are; execution
+    /// continues with [`execute_from`](Self::execute_from) at [`Snapshot::pc`].
+    ///
+    /// # Errors
+    /// Returns an error if the snapshot came from an incompatible runner or
+    /// guest memory cannot be remapped.
+    pub fn restore(&mut self, snapshot: &Snapshot) -> Result<(), RunError> {
+        if snapshot.xlen != self.inner.xlen() {
+            return Err(RunError::StateError(format!(
+                "xlen mismatch: snapshot has {}, runner has {}",
+                snapshot.xlen,
+                self.inner.xlen()
+            )));
+        }
+        if snapshot.state.regs.len() != self.inner.num_regs() {
+            return Err(RunError::StateError(format!(
+                "num_regs mismatch: snapshot has {}, runner has {}",
+                snapshot.state.regs.len(),
+                self.inner.num_regs()
+            )));
+        }
+        self.inner.restore(snapshot)
+    }
+}
@@ -0,0 +1,145 @@
+//! Saving guest registers and memory to a file and restoring them.
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read as IoRead, Write as IoWrite};
+use std::path::Path;
+
+use tracing::debug;
+
+use super::{RunError, Runner};
+
+impl Runner {
+    /// Save the current machine state to a file (zstd compressed).
+    /// Save the current state to a file.
+    ///
+    /// # Errors
+    /// Returns an error if the state cannot be serialized or written.
+    pub fn save_state(&self, path: impl AsRef<Path>) -> Result<(), RunError> {
+        const MAGIC: &[u8; 4] = b"RVR\0";
+        const VERSION: u32 = 1;
+
+        let file = File::create(path)?;
+        let mut writer = BufWriter::new(file);
+
+        // Header (uncompressed)
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.write_all(&[self.inner.xlen()])?;
+        let num_regs = u8::try_from(self.inner.num_regs())
+            .map_err(|_| RunError::StateError("num_regs does not fit in u8".to_string()))?;
+        writer.write_all(&[num_regs])?;
+        writer.write_all(&(self.inner.memory_size() as u64).to_le_bytes())?;
+
+        // Data (zstd compressed)
+        let mut encoder = zstd::stream::Encoder::new(&mut writer, 3)?;
+
+        encoder.write_all(&self.inner.get_pc().to_le_bytes())?;
+        encoder.write_all(&self.inner.instret().to_le_bytes())?;
+
+        for i in 0..self.inner.num_regs() {
+            encoder.write_all(&self.inner.get_register(i).to_le_bytes())?;
+        }
+
+        let mem_size = self.inner.memory_size();
+        let mut buf = vec![0u8; 64 * 1024];
+        let mut offset = 0;
+        while offset < mem_size {
+            let chunk_size = buf.len().min(mem_size - offset);
+            self.inner
+                .read_memory(offset as u64, &mut buf[..chunk_size]);
+            encoder.write_all(&buf[..chunk_size])?;
+            offset += chunk_size;
+        }
+
+        encoder.finish()?;
+        debug!(size = mem_size, "state saved");
+        Ok(())
+    }
+
+    /// Load machine state from a file.
+    /// Load a previously saved state from a file.
+    ///
+    /// # Errors
+    /// Returns an error if the state cannot be read or is incompatible.
+    pub fn load_state(&mut self, path: impl AsRef<Path>) -> Result<(), RunError> {
+        const MAGIC: &[u8; 4] = b"RVR\0";
+        const VERSION: u32 = 1;
+
+        let file = File::open(path)?;
+        let mut reader = BufReader::new(file);
+
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(RunError::StateError("invalid state file magic".to_string()));
+        }
+
+        let mut version = [0u8; 4];
+        reader.read_exact(&mut version)?;
+        if u32::from_le_bytes(version) != VERSION {
+            return Err(RunError::StateError(
+                "unsupported state version".to_string(),
+            ));
+        }
+
+        let mut xlen = [0u8; 1];
+        reader.read_exact(&mut xlen)?;
+        if xlen[0] != self.inner.xlen() {
+            return Err(RunError::StateError(format!(
+                "xlen mismatch: file has {}, runner has {}",
+                xlen[0],
+                self.inner.xlen()
+            )));
+        }
+
+        let mut num_regs = [0u8; 1];
+        reader.read_exact(&mut num_regs)?;
+        if num_regs[0] as usize != self.inner.num_regs() {
+            return Err(RunError::StateError(format!(
+                "num_regs mismatch: file has {}, runner has {}",
+                num_regs[0],
+                self.inner.num_regs()
+            )));
+        }
+
+        let mut mem_size_bytes = [0u8; 8];
+        reader.read_exact(&mut mem_size_bytes)?;
+        let file_mem_size = usize::try_from(u64::from_le_bytes(mem_size_bytes)).map_err(|_| {
+            RunError::StateError("memory size does not fit in host usize".to_string())
+        })?;
+        if file_mem_size != self.inner.memory_size() {
+            return Err(RunError::StateError(format!(
+                "memory size mismatch: file has {}, runner has {}",
+                file_mem_size,
+                self.inner.memory_size()
+            )));
+        }
+
+        let mut decoder = zstd::stream::Decoder::new(reader)?;
+
+        let mut pc = [0u8; 8];
+        decoder.read_exact(&mut pc)?;
+        self.inner.set_pc(u64::from_le_bytes(pc));
+
+        let mut instret = [0u8; 8];
+        decoder.read_exact(&mut instret)?;
+
+        for i in 0..self.inner.num_regs() {
+            let mut reg = [0u8; 8];
+            decoder.read_exact(&mut reg)?;
+            self.inner.set_register(i, u64::from_le_bytes(reg));
+        }
+
+        let mut buf = vec![0u8; 64 * 1024];
+        let mut offset = 0;
+        while offset < file_mem_size {
+            let chunk_size = buf.len().min(file_mem_size - offset);
+            decoder.read_exact(&mut buf[..chunk_size])?;
+            self.inner.write_memory(offset as u64, &buf[..chunk_size]);
+            offset += chunk_size;
+        }
+
+        debug!(size = file_mem_size, "state loaded");
+        Ok(())
+    }
+}
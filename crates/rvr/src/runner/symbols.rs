@@ -0,0 +1,147 @@
+//! Symbol lookup: where the runner finds a program's entry points and
+//! `RV_*` metadata.
+//!
+//! A shared library is searched with `dlsym`; a program linked into the
+//! host (a static archive, see `rv_embed.h`) through the table of its
+//! `rv_embed_info`. Everything past the lookup is the same for both.
+
+use std::ffi::{CStr, c_char, c_void};
+
+use libloading::os::unix::Library;
+use rvr_ir::Xlen;
+use tracing::error;
+
+use super::RunError;
+use super::api::{RvExecuteFrom, RvInitMemory};
+
+/// A metadata constant or entry point of an embedded program, by its
+/// unprefixed name (`RvEmbedSymbol` in `rv_embed.h`).
+#[repr(C)]
+#[derive(Debug)]
+pub struct RvEmbedSymbol {
+    pub name: *const c_char,
+    /// Null if the program does not define the symbol.
+    pub addr: *const c_void,
+}
+
+/// Build metadata and symbol table of a program linked into the host
+/// (`RvEmbedInfo` in `rv_embed.h`).
+///
+/// Declare the program's `<prefix>rv_embed_info` in an `extern "C"` block,
+/// link `lib<name>.a`, and pass it to
+/// [`Runner::load_embedded`](crate::Runner::load_embedded).
+#[repr(C)]
+#[derive(Debug)]
+pub struct RvEmbedInfo {
+    pub state_layout_version: u32,
+    pub xlen: u32,
+    pub num_regs: u32,
+    pub instret_mode: u32,
+    pub tracer_kind: u32,
+    pub export_functions: u32,
+    pub execute_from: RvExecuteFrom,
+    pub init_memory: Option<RvInitMemory>,
+    pub symbols: *const RvEmbedSymbol,
+    pub symbol_count: u64,
+}
+
+impl RvEmbedInfo {
+    /// Entries of the symbol table.
+    fn symbols(&self) -> &[RvEmbedSymbol] {
+        let count = usize::try_from(self.symbol_count).unwrap_or(0);
+        if self.symbols.is_null() || count == 0 {
+            return &[];
+        }
+        // SAFETY: the generated table has `symbol_count` entries and lives
+        // as long as the program.
+        unsafe { std::slice::from_raw_parts(self.symbols, count) }
+    }
+}
+
+/// Where a program's symbols are looked up.
+pub enum Symbols {
+    /// A shared library; the program's symbols start with `prefix`.
+    Library { lib: Library, prefix: String },
+    /// A program linked into the host.
+    Embedded(&'static RvEmbedInfo),
+}
+
+impl Symbols {
+    /// Address of the symbol `name` (unprefixed), if the program defines it.
+    pub fn address(&self, name: &str) -> Option<*const c_void> {
+        match self {
+            Self::Library { lib, prefix } => unsafe {
+                lib.get::<*const c_void>(format!("{prefix}{name}").as_bytes())
+                    .ok()
+                    .map(|sym| *sym)
+            },
+            Self::Embedded(info) => info
+                .symbols()
+                .iter()
+                .filter(|sym| !sym.addr.is_null() && !sym.name.is_null())
+                // SAFETY: names are NUL-terminated string literals.
+                .find(|sym| unsafe { CStr::from_ptr(sym.name) }.to_bytes() == name.as_bytes())
+                .map(|sym| sym.addr),
+        }
+    }
+
+    /// The symbol `name` as a value of the pointer type `T` (a function
+    /// or data pointer).
+    ///
+    /// # Safety
+    /// `T` must be the type of the symbol.
+    pub unsafe fn get<T: Copy>(&self, name: &str) -> Result<T, RunError> {
+        match self {
+            Self::Library { lib, prefix } => {
+                let symbol = format!("{prefix}{name}");
+                unsafe {
+                    lib.get::<T>(symbol.as_bytes())
+                        .map(|sym| *sym)
+                        .map_err(|e| {
+                            error!(symbol = %symbol, "symbol not found in library");
+                            RunError::SymbolNotFound(symbol, e)
+                        })
+                }
+            }
+            Self::Embedded(_) => unsafe { self.get_optional(name) }.ok_or_else(|| {
+                error!(symbol = %name, "symbol not found in embedded program");
+                RunError::EmbeddedSymbolNotFound(name.to_string())
+            }),
+        }
+    }
+
+    /// The optional symbol `name` as a value of the pointer type `T`, or
+    /// `None` if the program does not define it.
+    ///
+    /// # Safety
+    /// `T` must be the type of the symbol.
+    pub unsafe fn get_optional<T: Copy>(&self, name: &str) -> Option<T> {
+        let addr = self.address(name)?;
+        assert_eq!(size_of::<T>(), size_of::<*const c_void>());
+        // SAFETY: `T` is a pointer type of the same size.
+        Some(unsafe { std::mem::transmute_copy(&addr) })
+    }
+
+    /// Value of the `u32` constant `name`, if the program defines it.
+    pub unsafe fn data_u32(&self, name: &str) -> Option<u32> {
+        self.address(name)
+            .map(|addr| unsafe { *addr.cast::<u32>() })
+    }
+
+    /// Value of the `u64` constant `name`, if the program defines it.
+    pub unsafe fn data_u64(&self, name: &str) -> Option<u64> {
+        self.address(name)
+            .map(|addr| unsafe { *addr.cast::<u64>() })
+    }
+
+    /// Value of the XLEN-sized constant `name`, if the program defines it.
+    pub unsafe fn data_reg<X: Xlen>(&self, name: &str) -> Option<u64> {
+        unsafe {
+            if X::VALUE == 32 {
+                self.data_u32(name).map(u64::from)
+            } else {
+                self.data_u64(name)
+            }
+        }
+    }
+}
@@ -2,19 +2,28 @@
 
 use std::ffi::c_void;
 
-/// Entry from buffered diff tracer: (pc, opcode, rd, `rd_value`, (`mem_addr`, `mem_value`, `mem_width`, `is_write`))
+use rvr_state::{GuardedMemory, GuestIo};
+
+use crate::segment_image::SegmentImage;
+
+use super::{PageAccessLog, RunError, Snapshot};
+
+/// Entry from buffered diff tracer: (pc, opcode, rd, `rd_value`, (`mem_addr`, `mem_value`, `mem_width`, `is_write`), (csr, `csr_value`))
 pub type BufferedDiffEntry = (
     u64,
     u32,
     Option<u8>,
     Option<u64>,
     Option<(u64, u64, u8, bool)>,
+    Option<(u16, u64)>,
+    u64,
 );
 
 /// Trait for type-erased runner implementations.
 pub trait RunnerImpl {
-    /// Load ELF segments into memory.
-    fn load_segments(&mut self);
+    /// Load ELF segments into memory, mapping them from `segments` when the
+    /// library was compiled with lazy segment init.
+    fn load_segments(&mut self, segments: Option<&SegmentImage>);
 
     /// Reset state to initial values.
     fn reset(&mut self);
@@ -31,6 +40,22 @@ pub trait RunnerImpl {
     /// Check if the VM has exited.
     fn has_exited(&self) -> bool;
 
+    /// Check if the VM stopped on a guest trap.
+    fn is_trapped(&self) -> bool;
+
+    /// Get the raw exit cause (an `rvr_emit::ExitCause` code).
+    fn exit_cause(&self) -> u32;
+
+    /// Get the auxiliary word of the exit cause.
+    fn exit_info(&self) -> u64;
+
+    /// Get the number of the last HTIF syscall the guest requested (0 if
+    /// none).
+    fn htif_syscall(&self) -> u64;
+
+    /// Pointer to `RvState::cancel_requested`.
+    fn cancel_flag(&mut self) -> *mut u8;
+
     /// Get entry point from ELF.
     fn entry_point(&self) -> u64;
 
@@ -72,9 +97,24 @@ pub trait RunnerImpl {
     /// Get the memory size.
     fn memory_size(&self) -> usize;
 
+    /// Bytes of guest memory resident in host memory, if it can be measured.
+    fn resident_bytes(&self) -> Option<usize>;
+
+    /// Guest memory, for mapping host buffers into it.
+    fn memory_mut(&mut self) -> &mut GuardedMemory;
+
     /// Clear the exit flag to allow further execution.
     fn clear_exit(&mut self);
 
+    /// Install guest stdio hooks (null restores the host's stdio).
+    fn set_io(&mut self, io: *mut GuestIo);
+
+    /// Capture architectural state and freeze guest memory for copy-on-write restore.
+    fn snapshot(&mut self) -> Result<Snapshot, RunError>;
+
+    /// Restore architectural state and guest memory from a snapshot.
+    fn restore(&mut self, snapshot: &Snapshot) -> Result<(), RunError>;
+
     /// Check if the runner supports instret suspension (for single-stepping).
     fn supports_suspend(&self) -> bool {
         false
@@ -117,6 +157,11 @@ pub trait RunnerImpl {
         None
     }
 
+    /// Get CSR write info: (csr, value).
+    fn diff_traced_csr(&self) -> Option<(u16, u64)> {
+        None
+    }
+
     /// Check if diff tracer captured valid state.
     fn diff_tracer_valid(&self) -> bool {
         false
@@ -139,11 +184,32 @@ pub trait RunnerImpl {
         None
     }
 
-    /// Get entry at index: (pc, opcode, rd, `rd_value`, `mem_access`).
+    /// Get entry at index: (pc, opcode, rd, `rd_value`, `mem_access`, `csr_write`).
     fn buffered_diff_get(&self, _index: usize) -> Option<BufferedDiffEntry> {
         None
     }
 
     /// Reset the buffered diff tracer (clear entries, keep allocation).
     fn buffered_diff_reset(&mut self) {}
+
+    // Page access tracer methods - returns None for runners without page access tracer
+
+    /// Get the pages read and written since the last reset.
+    fn page_access_log(&self) -> Option<PageAccessLog> {
+        None
+    }
+
+    // Block profile tracer methods - returns None for runners without block profile tracer
+
+    /// Get `(pc, count)` for each block entered since the last reset.
+    fn block_profile(&self) -> Option<Vec<(u64, u64)>> {
+        None
+    }
+
+    // Coverage tracer methods - returns None for runners without coverage tracer
+
+    /// Get `(pc, flags)` for each instruction flagged since the last reset.
+    fn coverage(&self) -> Option<Vec<(u64, u8)>> {
+        None
+    }
 }
@@ -0,0 +1,380 @@
+//! Segment images for lazy guest memory initialization.
+//!
+//! With `EmitConfig::lazy_segment_init`, compilation writes the loadable
+//! segment data to `<name>.segments` next to the library. Each segment's bytes
+//! sit at a file offset congruent to its guest address modulo
+//! [`IMAGE_ALIGN`], so the runner can map the whole pages of a segment
+//! copy-on-write into guest memory and only copy the partial pages at its
+//! edges. BSS is never stored: it stays the zero pages of freshly discarded
+//! guest memory.
+//!
+//! Layout (little-endian):
+//! - header: magic `RVRSEGS\0`, version `u32`, alignment `u32`, count `u32`
+//! - one entry per segment: guest address, file offset and data size (`u64`)
+//! - segment data at the recorded offsets
+
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+
+use rvr_elf::ElfImage;
+use rvr_isa::Xlen;
+use rvr_state::{GuardedMemory, page_size};
+use tracing::{debug, warn};
+
+const MAGIC: [u8; 8] = *b"RVRSEGS\0";
+const VERSION: u32 = 1;
+const HEADER_BYTES: usize = 20;
+const ENTRY_BYTES: usize = 24;
+
+/// Congruence of segment data offsets: the largest host page size the image
+/// can be mapped with.
+const IMAGE_ALIGN_BYTES: u32 = 1 << 16;
+pub const IMAGE_ALIGN: u64 = IMAGE_ALIGN_BYTES as u64;
+
+/// Path of the segment image for the library `base_name` in `dir`.
+#[must_use]
+pub fn segment_image_path(dir: &Path, base_name: &str) -> PathBuf {
+    dir.join(format!("{base_name}.segments"))
+}
+
+/// How much of a segment image maps instead of copies.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SegmentImageStats {
+    /// Segment data bytes in whole [`IMAGE_ALIGN`] pages, mapped on any host.
+    pub mapped_bytes: u64,
+    /// Bytes in partial pages at segment edges, copied at load.
+    pub copied_bytes: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Entry {
+    vaddr: u64,
+    offset: u64,
+    filesz: u64,
+}
+
+impl Entry {
+    /// Guest range of whole `page`-sized pages inside the segment data, if
+    /// the data offset is congruent to the guest address modulo `page`.
+    fn mappable(&self, page: u64) -> Option<(u64, u64)> {
+        if self.offset % page != self.vaddr % page {
+            return None;
+        }
+        let start = self.vaddr.next_multiple_of(page);
+        let end = (self.vaddr + self.filesz) / page * page;
+        (end > start).then_some((start, end))
+    }
+}
+
+/// Write the segment image of `image` to `path`.
+///
+/// Data offsets are congruent to guest addresses modulo [`IMAGE_ALIGN`], so
+/// segments never fall back to copying because of their alignment; only the
+/// partial pages at segment edges are copied at load. The image replaces
+/// `path` atomically, so a runner that already mapped the old one keeps it.
+///
+/// # Errors
+///
+/// Returns an error if the image cannot be written.
+pub fn write_segment_image<X: Xlen>(
+    path: &Path,
+    image: &ElfImage<X>,
+) -> io::Result<SegmentImageStats> {
+    let count = image.memory_segments.len();
+    let table_end = (HEADER_BYTES + count * ENTRY_BYTES) as u64;
+
+    let mut entries = Vec::with_capacity(count);
+    let mut cursor = table_end;
+    for seg in &image.memory_segments {
+        let vaddr = X::to_u64(seg.virtual_start);
+        let filesz = seg.data.len() as u64;
+        // Smallest offset past the previous data congruent to the address
+        let offset =
+            cursor + (vaddr % IMAGE_ALIGN + IMAGE_ALIGN - cursor % IMAGE_ALIGN) % IMAGE_ALIGN;
+        entries.push(Entry {
+            vaddr,
+            offset,
+            filesz,
+        });
+        cursor = offset + filesz;
+    }
+
+    let mut table = Vec::with_capacity(HEADER_BYTES + count * ENTRY_BYTES);
+    table.extend_from_slice(&MAGIC);
+    table.extend_from_slice(&VERSION.to_le_bytes());
+    table.extend_from_slice(&IMAGE_ALIGN_BYTES.to_le_bytes());
+    table.extend_from_slice(&u32::try_from(count).map_err(invalid_data)?.to_le_bytes());
+    for entry in &entries {
+        table.extend_from_slice(&entry.vaddr.to_le_bytes());
+        table.extend_from_slice(&entry.offset.to_le_bytes());
+        table.extend_from_slice(&entry.filesz.to_le_bytes());
+    }
+
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let temp = tempfile::NamedTempFile::new_in(dir)?;
+    let file = temp.as_file();
+    file.write_all_at(&table, 0)?;
+    let mut stats = SegmentImageStats::default();
+    for (entry, seg) in entries.iter().zip(&image.memory_segments) {
+        // Padding between segments is left as a hole
+        file.write_all_at(&seg.data, entry.offset)?;
+        let mapped = entry
+            .mappable(IMAGE_ALIGN)
+            .map_or(0, |(start, end)| end - start);
+        stats.mapped_bytes += mapped;
+        stats.copied_bytes += entry.filesz - mapped;
+    }
+    file.set_len(cursor)?;
+    temp.persist(path).map_err(|e| e.error)?;
+    Ok(stats)
+}
+
+/// An open segment image, ready to be mapped into guest memory.
+pub struct SegmentImage {
+    file: File,
+    align: u64,
+    entries: Vec<Entry>,
+}
+
+impl SegmentImage {
+    /// Open the segment image at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a segment image.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let mut reader = BufReader::new(&file);
+        let mut header = [0u8; HEADER_BYTES];
+        reader.read_exact(&mut header)?;
+        if header[..8] != MAGIC {
+            return Err(invalid_data("not a segment image"));
+        }
+        let word = |at: usize| u32::from_le_bytes(header[at..at + 4].try_into().unwrap());
+        if word(8) != VERSION {
+            return Err(invalid_data(format!(
+                "unsupported segment image version {}",
+                word(8)
+            )));
+        }
+        let align = u64::from(word(12));
+        if !align.is_power_of_two() {
+            return Err(invalid_data(format!("invalid alignment {align:#x}")));
+        }
+
+        let mut entries = Vec::new();
+        for _ in 0..word(16) {
+            let mut raw = [0u8; ENTRY_BYTES];
+            reader.read_exact(&mut raw)?;
+            let field = |at: usize| u64::from_le_bytes(raw[at..at + 8].try_into().unwrap());
+            entries.push(Entry {
+                vaddr: field(0),
+                offset: field(8),
+                filesz: field(16),
+            });
+        }
+        drop(reader);
+        Ok(Self {
+            file,
+            align,
+            entries,
+        })
+    }
+
+    /// Check that the image holds the segments of `image` and fits in
+    /// `memory_size` bytes of guest memory.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first mismatch, typically because the
+    /// ELF changed since the library was compiled.
+    pub fn validate<X: Xlen>(&self, image: &ElfImage<X>, memory_size: usize) -> Result<(), String> {
+        if self.entries.len() != image.memory_segments.len() {
+            return Err(format!(
+                "{} segments, but the ELF has {}",
+                self.entries.len(),
+                image.memory_segments.len()
+            ));
+        }
+        for (entry, seg) in self.entries.iter().zip(&image.memory_segments) {
+            let vaddr = X::to_u64(seg.virtual_start);
+            if entry.vaddr != vaddr || entry.filesz != seg.data.len() as u64 {
+                return Err(format!(
+                    "segment at {:#x} ({} bytes) does not match the ELF segment at {vaddr:#x} ({} bytes)",
+                    entry.vaddr,
+                    entry.filesz,
+                    seg.data.len()
+                ));
+            }
+            if entry.vaddr + entry.filesz > memory_size as u64 {
+                return Err(format!(
+                    "segment at {:#x} does not fit in {memory_size:#x} bytes of memory",
+                    entry.vaddr
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Initialize `memory` from the image.
+    ///
+    /// Discards the whole region, then maps the whole pages of each segment
+    /// from the file and copies the partial pages at its edges. Segments
+    /// that cannot be mapped (host pages larger than the image alignment, or
+    /// a failed mmap) are copied instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading segment data from the image fails.
+    pub fn load(&self, memory: &mut GuardedMemory) -> io::Result<()> {
+        if let Err(err) = memory.discard() {
+            warn!(%err, "failed to discard guest memory, clearing it instead");
+            memory.clear();
+        }
+        let page = page_size() as u64;
+        if !self.align.is_multiple_of(page) {
+            warn!(
+                page_size = page,
+                align = self.align,
+                "host pages are larger than the segment image alignment, copying segments"
+            );
+        }
+
+        for entry in &self.entries {
+            let mapped = self
+                .align
+                .is_multiple_of(page)
+                .then(|| entry.mappable(page))
+                .flatten()
+                .filter(|&(start, end)| {
+                    let file_offset = entry.offset + (start - entry.vaddr);
+                    to_usize(start)
+                        .zip(to_usize(end - start))
+                        .is_some_and(|(offset, len)| {
+                            memory
+                                .map_file(offset, len, &self.file, file_offset)
+                                .inspect_err(|err| {
+                                    warn!(vaddr = entry.vaddr, %err, "mapping segment failed, copying it");
+                                })
+                                .is_ok()
+                        })
+                });
+            let end = entry.vaddr + entry.filesz;
+            match mapped {
+                Some((start, stop)) => {
+                    debug!(vaddr = entry.vaddr, mapped = stop - start, "mapped segment");
+                    self.copy(memory, entry, entry.vaddr, start)?;
+                    self.copy(memory, entry, stop, end)?;
+                }
+                None => self.copy(memory, entry, entry.vaddr, end)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Copy the guest range `start..end` of `entry` into memory.
+    fn copy(
+        &self,
+        memory: &mut GuardedMemory,
+        entry: &Entry,
+        start: u64,
+        end: u64,
+    ) -> io::Result<()> {
+        let (Some(addr), Some(len)) = (to_usize(start), to_usize(end - start)) else {
+            return Err(invalid_data("segment does not fit in host usize"));
+        };
+        debug_assert!(addr + len <= memory.size());
+        // SAFETY: `validate` checked that segments fit in guest memory.
+        let dst = unsafe { std::slice::from_raw_parts_mut(memory.as_ptr().add(addr), len) };
+        self.file
+            .read_exact_at(dst, entry.offset + (start - entry.vaddr))
+    }
+}
+
+impl std::fmt::Debug for SegmentImage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SegmentImage")
+            .field("align", &self.align)
+            .field("segments", &self.entries.len())
+            .finish_non_exhaustive()
+    }
+}
+
+fn to_usize(value: u64) -> Option<usize> {
+    usize::try_from(value).ok()
+}
+
+fn invalid_data(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rvr_elf::{ElfWriter, PF_R, PF_W, PF_X};
+    use rvr_isa::Rv64;
+
+    /// Text ending mid-page, then data sharing its last page and spanning
+    /// several more.
+    fn test_image() -> ElfImage<Rv64> {
+        let text = vec![0x13; 0x1800];
+        let data: Vec<u8> = (0..3 * IMAGE_ALIGN).map(|i| (i % 251) as u8).collect();
+        let elf = ElfWriter::<Rv64>::new(0x1_0000)
+            .with_segment(0x1_0000, PF_R | PF_X, text)
+            .with_segment(0x1_1800, PF_R | PF_W, data)
+            .build();
+        ElfImage::parse(&elf).expect("parse ELF")
+    }
+
+    #[test]
+    fn test_segment_image_round_trip() {
+        let image = test_image();
+        let temp = tempfile::tempdir().expect("tempdir");
+        let path = segment_image_path(temp.path(), "test");
+        let stats = write_segment_image(&path, &image).expect("write image");
+        // Only the data segment has whole 64K pages
+        assert_eq!(stats.mapped_bytes, 2 * IMAGE_ALIGN);
+        assert_eq!(stats.copied_bytes, 0x1800 + IMAGE_ALIGN);
+
+        let segments = SegmentImage::open(&path).expect("open image");
+        for entry in &segments.entries {
+            assert_eq!(entry.offset % IMAGE_ALIGN, entry.vaddr % IMAGE_ALIGN);
+        }
+        let memory_size = 0x10_0000;
+        segments
+            .validate(&image, memory_size)
+            .expect("image matches");
+
+        let mut memory = GuardedMemory::new(memory_size).expect("memory");
+        unsafe { memory.write_u8(0x200, 0xFF) };
+        segments.load(&mut memory).expect("load");
+        // SAFETY: the region is mapped readable for `memory_size` bytes.
+        let guest = unsafe { std::slice::from_raw_parts(memory.as_ptr(), memory_size) };
+        assert_eq!(guest[0x200], 0);
+        for seg in &image.memory_segments {
+            let start = usize::try_from(seg.virtual_start).unwrap();
+            assert_eq!(&guest[start..start + seg.data.len()], seg.data.as_slice());
+        }
+    }
+
+    #[test]
+    fn test_segment_image_rejects_stale_elf() {
+        let image = test_image();
+        let temp = tempfile::tempdir().expect("tempdir");
+        let path = segment_image_path(temp.path(), "test");
+        write_segment_image(&path, &image).expect("write image");
+        let segments = SegmentImage::open(&path).expect("open image");
+
+        let elf = ElfWriter::<Rv64>::new(0x1_0000)
+            .with_segment(0x1_0000, PF_R | PF_X, vec![0x13; 4])
+            .build();
+        let other = ElfImage::<Rv64>::parse(&elf).expect("parse ELF");
+        let err = segments.validate(&other, 0x10_0000).unwrap_err();
+        assert!(err.contains("2 segments"), "{err}");
+
+        let err = segments.validate(&image, 0x2_0000).unwrap_err();
+        assert!(err.contains("does not fit"), "{err}");
+    }
+}
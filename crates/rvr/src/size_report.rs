@@ -0,0 +1,182 @@
+//! Per-function code size attribution (`size_report.tsv`).
+//!
+//! Guest code size turns into host code size and icache pressure. The
+//! report lists, for every guest function, the guest instructions it lifted
+//! to, the C it emitted and which part file that landed in, and, once the
+//! library is built, the host bytes its block functions compiled to. Host
+//! bytes over guest instructions is the expansion factor, which points at
+//! functions that lower badly.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write as _;
+use std::path::Path;
+
+use rvr_elf::{ElfFile, ElfImage, STT_FUNC};
+use rvr_emit::c::{EmittedBlock, block_name, hot_body_name};
+use rvr_ir::BlockIR;
+use rvr_isa::{Rv64, Xlen};
+
+use crate::Result;
+use crate::pipeline::{u64_to_f64, usize_to_f64};
+
+/// File name of the report in the output directory.
+pub const SIZE_REPORT: &str = "size_report.tsv";
+
+/// Code size of one guest function.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FunctionSize {
+    /// Entry PC (the block PC for blocks outside any known function).
+    pub pc: u64,
+    /// ELF symbol of the function, if any.
+    pub name: Option<String>,
+    /// Start PCs of the function's blocks.
+    pub block_pcs: Vec<u64>,
+    /// Lifted guest instructions.
+    pub guest_instrs: usize,
+    /// Lines of emitted C.
+    pub c_lines: usize,
+    /// Bytes of emitted C.
+    pub c_bytes: usize,
+    /// Part file holding the function's first block.
+    pub part: usize,
+    /// Bytes of host code of the block functions, once read from the
+    /// library (see [`SizeReport::add_host_sizes`]).
+    pub host_bytes: Option<u64>,
+}
+
+impl FunctionSize {
+    /// Host bytes per guest instruction.
+    #[must_use]
+    pub fn expansion(&self) -> Option<f64> {
+        let host = self.host_bytes?;
+        (self.guest_instrs > 0).then(|| u64_to_f64(host) / usize_to_f64(self.guest_instrs))
+    }
+}
+
+/// Code size per guest function of one emission.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SizeReport {
+    /// Functions by descending emitted C bytes.
+    pub functions: Vec<FunctionSize>,
+}
+
+impl SizeReport {
+    /// Attribute `emitted` blocks to guest functions.
+    ///
+    /// Blocks are grouped by the sized ELF function symbol containing them,
+    /// falling back to the function `function_of` their PC (from CFG
+    /// analysis) in code without symbols.
+    #[must_use]
+    pub fn new<X: Xlen>(
+        image: &ElfImage<X>,
+        function_of: impl Fn(u64) -> Option<u64>,
+        blocks: &HashMap<u64, BlockIR<X>>,
+        emitted: &[EmittedBlock],
+    ) -> Self {
+        let mut functions: BTreeMap<u64, FunctionSize> = BTreeMap::new();
+        for block in emitted {
+            let pc = image
+                .function_symbol(block.pc)
+                .map(|s| X::to_u64(s.value))
+                .or_else(|| function_of(block.pc))
+                .unwrap_or(block.pc);
+            let function = functions.entry(pc).or_insert_with(|| FunctionSize {
+                pc,
+                name: image.function_containing(pc).map(str::to_string),
+                block_pcs: Vec::new(),
+                guest_instrs: 0,
+                c_lines: 0,
+                c_bytes: 0,
+                part: block.part,
+                host_bytes: None,
+            });
+            function.block_pcs.push(block.pc);
+            function.guest_instrs += blocks.get(&block.pc).map_or(0, |b| b.instructions.len());
+            function.c_lines += block.lines;
+            function.c_bytes += block.bytes;
+        }
+
+        let mut functions: Vec<_> = functions.into_values().collect();
+        functions.sort_by(|a, b| b.c_bytes.cmp(&a.c_bytes).then(a.pc.cmp(&b.pc)));
+        Self { functions }
+    }
+
+    /// Add host code sizes from the symbol table of the compiled `library`.
+    ///
+    /// A block function's size is its `STT_FUNC` symbol size, plus its
+    /// `_hot` body under per-function hot registers; aliases of
+    /// deduplicated blocks share an address and are counted once. Blocks
+    /// the compiler inlined away have no symbol and count nothing.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the library cannot be read or parsed.
+    pub fn add_host_sizes<X: Xlen>(&mut self, library: &Path, prefix: &str) -> Result<()> {
+        let data = std::fs::read(library)?;
+        let elf = ElfFile::<Rv64>::parse(&data)?;
+        let symbols: HashMap<&str, (u64, u64)> = elf
+            .symbols
+            .iter()
+            .filter(|s| s.sym_type == STT_FUNC)
+            .map(|s| (s.name.as_str(), (s.value, s.size)))
+            .collect();
+
+        let mut seen = HashSet::new();
+        let mut by_pc: Vec<&mut FunctionSize> = self.functions.iter_mut().collect();
+        by_pc.sort_by_key(|f| f.pc);
+        for function in by_pc {
+            let mut host_bytes = 0;
+            for &pc in &function.block_pcs {
+                // A block with its own hot registers has its body apart
+                let names = [block_name::<X>(prefix, pc), hot_body_name::<X>(prefix, pc)];
+                for name in &names {
+                    if let Some(&(addr, size)) = symbols.get(name.as_str())
+                        && seen.insert(addr)
+                    {
+                        host_bytes += size;
+                    }
+                }
+            }
+            function.host_bytes = Some(host_bytes);
+        }
+        Ok(())
+    }
+
+    /// Tab-separated report, one function per line after a header.
+    #[must_use]
+    pub fn render(&self) -> String {
+        let mut out = String::from(
+            "pc\tfunction\tblocks\tguest_instrs\tc_lines\tc_bytes\tpart\thost_bytes\texpansion\n",
+        );
+        for f in &self.functions {
+            let host_bytes = f
+                .host_bytes
+                .map_or_else(|| "-".to_string(), |b| b.to_string());
+            let expansion = f
+                .expansion()
+                .map_or_else(|| "-".to_string(), |e| format!("{e:.1}"));
+            let _ = writeln!(
+                out,
+                "{:#x}\t{}\t{}\t{}\t{}\t{}\t{}\t{host_bytes}\t{expansion}",
+                f.pc,
+                f.name.as_deref().unwrap_or("-"),
+                f.block_pcs.len(),
+                f.guest_instrs,
+                f.c_lines,
+                f.c_bytes,
+                f.part,
+            );
+        }
+        out
+    }
+
+    /// Write the report to [`SIZE_REPORT`] in `dir`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn write(&self, dir: &Path) -> Result<()> {
+        std::fs::write(dir.join(SIZE_REPORT), self.render())?;
+        Ok(())
+    }
+}
@@ -30,6 +30,12 @@ impl Spinner {
         Self { bar }
     }
 
+    /// Setter of the spinner's message that can be called from other threads.
+    pub fn message_setter(&self) -> impl Fn(String) + Send + Sync + 'static {
+        let bar = self.bar.clone();
+        move |message| bar.set_message(message)
+    }
+
     /// Finish the spinner with a success message.
     pub fn finish_with_success(&self, message: &str) {
         self.bar.finish_and_clear();
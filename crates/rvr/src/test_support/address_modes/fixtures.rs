@@ -0,0 +1,245 @@
+//! Negative-test fixtures for the address-mode differential.
+//!
+//! Each fixture is a tiny RV64 program that makes one deliberately
+//! out-of-range access at a known PC. The expected outcome is stated per
+//! mode: `Bounds` must trap on that access, while `Wrap` must alias it into
+//! guest memory by dropping the address bits above `MEMORY_BITS`. Note that
+//! `Wrap` masks the base register only; the signed 12-bit offset is applied
+//! after translation.
+
+use rvr_elf::{ElfWriter, PF_R, PF_W, PF_X};
+use rvr_emit::AddressMode;
+use rvr_isa::{
+    REG_A0, REG_A1, REG_A2, REG_T0, REG_T1, REG_ZERO, Rv64, encode_i, encode_r, encode_s, encode_u,
+};
+
+use crate::test_support::guest::{
+    ECALL, FUNCT3_ADD, FUNCT3_B, FUNCT3_BU, FUNCT3_SLL, OPCODE_LOAD, OPCODE_LUI, OPCODE_OP,
+    OPCODE_OP_IMM, OPCODE_STORE, addi,
+};
+
+/// Load address of the fixture text segment.
+pub const TEXT_BASE: u64 = 0x8000_0000;
+/// Page number of the fixture data segment (the `lui` immediate).
+const DATA_PAGE: u32 = 0x8_0001;
+/// Load address of the fixture data segment.
+pub const DATA_BASE: u64 = (DATA_PAGE as u64) << 12;
+/// Byte stored at `DATA_BASE`; loads that alias there exit with it.
+pub const DATA_MARKER: u8 = 0x5A;
+/// Byte written by out-of-range stores.
+pub const STORE_MARKER: u8 = 0x21;
+/// Guest address bits the fixtures are compiled for (the `EmitConfig` default).
+pub const FIXTURE_MEMORY_BITS: u8 = 32;
+/// `DATA_BASE` plus one full wrap of guest memory: out of range for `Bounds`,
+/// aliases `DATA_BASE` under `Wrap`.
+pub const PAST_END_ADDR: u64 = (1 << FIXTURE_MEMORY_BITS) + DATA_BASE;
+
+/// Size of the fixture data segment.
+const DATA_SIZE: usize = 16;
+/// Fixtures use uncompressed instructions only.
+const INSN_BYTES: u64 = 4;
+
+// `past_end_address` builds `PAST_END_ADDR` as `(3 << 31) + 0x1000`.
+const PAST_END_HIGH: i32 = 3;
+const PAST_END_SHIFT: i32 = 31;
+const PAST_END_PAGE: u32 = 1;
+const _: () = assert!(PAST_END_ADDR == (3 << 31) + 0x1000);
+
+const fn slli(rd: u8, rs1: u8, shamt: i32) -> u32 {
+    encode_i(OPCODE_OP_IMM, rd, FUNCT3_SLL, rs1, shamt)
+}
+
+const fn add(rd: u8, rs1: u8, rs2: u8) -> u32 {
+    encode_r(OPCODE_OP, rd, FUNCT3_ADD, rs1, rs2, 0)
+}
+
+const fn lui(rd: u8, imm: u32) -> u32 {
+    encode_u(OPCODE_LUI, rd, imm)
+}
+
+const fn lbu(rd: u8, rs1: u8, imm: i32) -> u32 {
+    encode_i(OPCODE_LOAD, rd, FUNCT3_BU, rs1, imm)
+}
+
+const fn sb(rs2: u8, rs1: u8, imm: i32) -> u32 {
+    encode_s(OPCODE_STORE, FUNCT3_B, rs1, rs2, imm)
+}
+
+/// Materialize `PAST_END_ADDR` in `rd` (clobbers `t0`).
+const fn past_end_address(rd: u8) -> [u32; 4] {
+    [
+        addi(rd, REG_ZERO, PAST_END_HIGH),
+        slli(rd, rd, PAST_END_SHIFT),
+        lui(REG_T0, PAST_END_PAGE),
+        add(rd, rd, REG_T0),
+    ]
+}
+
+/// An out-of-range access inside a fixture.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FaultSite {
+    /// Instructions retired before the access.
+    pub index: u64,
+    /// Base register of the access.
+    pub base_reg: u8,
+    /// Immediate offset of the access.
+    pub offset: i64,
+    /// Effective guest address (`base + offset`).
+    pub addr: u64,
+}
+
+impl FaultSite {
+    /// PC of the faulting instruction.
+    #[must_use]
+    pub const fn pc(&self) -> u64 {
+        TEXT_BASE + self.index * INSN_BYTES
+    }
+}
+
+/// Outcome a fixture must produce in one address mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Expected {
+    /// The guest exits normally with this code.
+    Exit(u8),
+    /// The guest traps on this access.
+    Fault(FaultSite),
+}
+
+/// A synthesized guest with per-mode expectations.
+#[derive(Clone, Debug)]
+pub struct Fixture {
+    /// Short identifier (used for output directories and test names).
+    pub name: &'static str,
+    /// What the fixture exercises.
+    pub description: &'static str,
+    /// Bug this fixture reproduces, for regression entries.
+    pub regression: Option<&'static str>,
+    /// Program text, starting at `TEXT_BASE`.
+    pub text: Vec<u32>,
+    /// Expected outcome under `AddressMode::Wrap`.
+    pub wrap: Expected,
+    /// Expected outcome under `AddressMode::Bounds`.
+    pub bounds: Expected,
+}
+
+impl Fixture {
+    /// Expected outcome under `mode`, if the fixture covers it.
+    #[must_use]
+    pub const fn expected(&self, mode: AddressMode) -> Option<Expected> {
+        match mode {
+            AddressMode::Wrap => Some(self.wrap),
+            AddressMode::Bounds => Some(self.bounds),
+            AddressMode::Unchecked => None,
+        }
+    }
+
+    /// Serialize the fixture as an RV64 ELF.
+    #[must_use]
+    pub fn elf(&self) -> Vec<u8> {
+        let text = self.text.iter().flat_map(|i| i.to_le_bytes()).collect();
+        let mut data = vec![0; DATA_SIZE];
+        data[0] = DATA_MARKER;
+        ElfWriter::<Rv64>::new(TEXT_BASE)
+            .with_segment(TEXT_BASE, PF_R | PF_X, text)
+            .with_segment(DATA_BASE, PF_R | PF_W, data)
+            .build()
+    }
+}
+
+/// All address-mode fixtures, negative tests and regressions alike.
+#[must_use]
+pub fn fixtures() -> Vec<Fixture> {
+    vec![
+        load_past_end(),
+        load_past_end_with_offset(),
+        store_past_end(),
+        sign_extended_alias(),
+    ]
+}
+
+fn load_past_end() -> Fixture {
+    let mut text = past_end_address(REG_A1).to_vec();
+    let index = text.len() as u64;
+    text.extend([lbu(REG_A0, REG_A1, 0), ECALL]);
+    Fixture {
+        name: "load_past_end",
+        description: "byte load one full memory size past DATA_BASE",
+        regression: None,
+        text,
+        wrap: Expected::Exit(DATA_MARKER),
+        bounds: Expected::Fault(FaultSite {
+            index,
+            base_reg: REG_A1,
+            offset: 0,
+            addr: PAST_END_ADDR,
+        }),
+    }
+}
+
+fn load_past_end_with_offset() -> Fixture {
+    const OFFSET: i32 = 8;
+    let mut text = past_end_address(REG_A1).to_vec();
+    text.push(addi(REG_A1, REG_A1, OFFSET));
+    let index = text.len() as u64;
+    text.extend([lbu(REG_A0, REG_A1, -OFFSET), ECALL]);
+    Fixture {
+        name: "load_past_end_with_offset",
+        description: "out-of-range base with a negative offset back onto the alias",
+        regression: None,
+        text,
+        wrap: Expected::Exit(DATA_MARKER),
+        bounds: Expected::Fault(FaultSite {
+            index,
+            base_reg: REG_A1,
+            offset: i64::from(-OFFSET),
+            addr: PAST_END_ADDR,
+        }),
+    }
+}
+
+fn store_past_end() -> Fixture {
+    let mut text = past_end_address(REG_A1).to_vec();
+    text.push(addi(REG_T1, REG_ZERO, i32::from(STORE_MARKER)));
+    let index = text.len() as u64;
+    text.extend([
+        sb(REG_T1, REG_A1, 0),
+        // lui sign-extends: a2 = 0xffff_ffff_8000_1000, the in-range alias
+        lui(REG_A2, DATA_PAGE),
+        lbu(REG_A0, REG_A2, 0),
+        ECALL,
+    ]);
+    Fixture {
+        name: "store_past_end",
+        description: "store through an out-of-range base, read back through the in-range alias",
+        regression: Some(
+            "masking-mode wraparound: a store whose base register has bits above MEMORY_BITS \
+             must land on the same byte as its in-range alias; this went unnoticed while CI \
+             only ran one address mode",
+        ),
+        text,
+        wrap: Expected::Exit(STORE_MARKER),
+        bounds: Expected::Fault(FaultSite {
+            index,
+            base_reg: REG_A1,
+            offset: 0,
+            addr: PAST_END_ADDR,
+        }),
+    }
+}
+
+fn sign_extended_alias() -> Fixture {
+    let text = vec![
+        // a1 = 0xffff_ffff_8000_1000: canonical, so Bounds accepts it
+        lui(REG_A1, DATA_PAGE),
+        lbu(REG_A0, REG_A1, 0),
+        ECALL,
+    ];
+    Fixture {
+        name: "sign_extended_alias",
+        description: "sign-extended upper-half address is valid in both modes",
+        regression: None,
+        text,
+        wrap: Expected::Exit(DATA_MARKER),
+        bounds: Expected::Exit(DATA_MARKER),
+    }
+}
@@ -0,0 +1,385 @@
+//! Dual address-mode differential testing.
+//!
+//! Compiles a guest once per address mode (sharing the lift), runs each build
+//! in its own `rvr run` process so a trap cannot take down the harness, and
+//! compares the outcomes:
+//!
+//! - Ordinary guests (riscv-tests) must behave identically under `Wrap` and
+//!   `Bounds`: same pass/fail, exit code, and retired instruction count.
+//! - [`fixtures`] access out-of-range addresses on purpose; their per-mode
+//!   outcome is asserted exactly.
+//!
+//! Suites are sampled with [`sample_suite`]; set [`FULL_MATRIX_ENV`] to run
+//! every case (the nightly matrix).
+
+pub mod fixtures;
+
+use std::fmt;
+use std::fs::File;
+use std::os::unix::process::ExitStatusExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rvr_emit::AddressMode;
+
+use crate::test_support::trace::run_command_with_timeout;
+use crate::{CompileOptions, Compiler, InstretMode, Runner, compile_address_modes};
+
+pub use fixtures::{Expected, FaultSite, Fixture, fixtures};
+
+/// Address modes compared by the harness.
+pub const COMPARED_MODES: [AddressMode; 2] = [AddressMode::Wrap, AddressMode::Bounds];
+/// Environment variable that disables sampling (nightly full matrix).
+pub const FULL_MATRIX_ENV: &str = "RVR_ADDRESS_MODES_FULL";
+/// Environment variable that overrides the rotation slot.
+pub const ROTATION_ENV: &str = "RVR_ADDRESS_MODES_ROTATION";
+/// Number of rotation slots; every sampled case runs at least once per period.
+pub const ROTATION_PERIOD: u64 = 7;
+/// Cases with this name prefix are never sampled out.
+pub const ALWAYS_RUN_PREFIX: &str = "rv64ui-";
+
+/// riscv-tests that cannot run under static recompilation (self-modifying code).
+const SKIP_TESTS: &[&str] = &["rv32ui-p-fence_i", "rv64ui-p-fence_i"];
+
+/// Default per-run timeout.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+const SECS_PER_DAY: u64 = 86_400;
+/// Signals raised by `__builtin_trap()`: SIGILL on x86-64, SIGTRAP on `AArch64`.
+const SIGILL: i32 = 4;
+const SIGTRAP: i32 = 5;
+/// Prefix of the `rvr run --format json` result line.
+const JSON_RESULT_PREFIX: &str = "{\"instret\":";
+/// FNV-1a parameters (stable across toolchains, unlike `DefaultHasher`).
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+/// How a run in one address mode ended.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ModeOutcome {
+    /// The guest exited after retiring `instret` instructions.
+    Exited {
+        /// Guest exit code.
+        exit_code: u8,
+        /// Retired instruction count.
+        instret: u64,
+    },
+    /// The host process was killed by a signal.
+    Signaled(i32),
+    /// The run exceeded the timeout.
+    TimedOut,
+    /// The runner failed to load or execute the library.
+    Error(String),
+}
+
+impl ModeOutcome {
+    /// True if the guest exited with code 0.
+    #[must_use]
+    pub const fn passed(&self) -> bool {
+        matches!(self, Self::Exited { exit_code: 0, .. })
+    }
+
+    /// True if the process died on an explicit trap (not a guard-page fault).
+    #[must_use]
+    pub const fn is_trap(&self) -> bool {
+        matches!(self, Self::Signaled(SIGILL | SIGTRAP))
+    }
+}
+
+impl fmt::Display for ModeOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Exited { exit_code, instret } => {
+                write!(f, "exit={exit_code} instret={instret}")
+            }
+            Self::Signaled(signal) => write!(f, "signal {signal}"),
+            Self::TimedOut => write!(f, "timed out"),
+            Self::Error(err) => write!(f, "error: {err}"),
+        }
+    }
+}
+
+/// Outcomes of one guest under each compared mode.
+#[derive(Clone, Debug)]
+pub struct ModeComparison {
+    /// Outcome under `AddressMode::Wrap`.
+    pub wrap: ModeOutcome,
+    /// Outcome under `AddressMode::Bounds`.
+    pub bounds: ModeOutcome,
+}
+
+impl ModeComparison {
+    /// Check that both modes passed with identical exit codes and instret.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the mismatch.
+    pub fn check(&self) -> Result<(), String> {
+        if self.wrap != self.bounds {
+            return Err(format!(
+                "address modes diverge: wrap {}, bounds {}",
+                self.wrap, self.bounds
+            ));
+        }
+        if !self.wrap.passed() {
+            return Err(format!("both modes failed: {}", self.wrap));
+        }
+        Ok(())
+    }
+}
+
+/// Runs guests under each address mode and compares the results.
+pub struct AddressModeHarness {
+    rvr_exe: PathBuf,
+    work_dir: PathBuf,
+    compiler: Compiler,
+    timeout: Duration,
+    htif: bool,
+}
+
+impl AddressModeHarness {
+    /// Create a harness that runs builds with `rvr_exe` and writes them to `work_dir`.
+    ///
+    /// HTIF is enabled by default so riscv-tests report through `tohost`.
+    #[must_use]
+    pub fn new(rvr_exe: impl Into<PathBuf>, work_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            rvr_exe: rvr_exe.into(),
+            work_dir: work_dir.into(),
+            compiler: Compiler::default(),
+            timeout: DEFAULT_TIMEOUT,
+            htif: true,
+        }
+    }
+
+    /// Enable or disable HTIF for [`Self::compare_elf`] builds.
+    #[must_use]
+    pub const fn with_htif(mut self, enabled: bool) -> Self {
+        self.htif = enabled;
+        self
+    }
+
+    /// Set the C compiler.
+    #[must_use]
+    pub fn with_compiler(mut self, compiler: Compiler) -> Self {
+        self.compiler = compiler;
+        self
+    }
+
+    /// Set the per-run timeout.
+    #[must_use]
+    pub const fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Compile `elf_path` for every compared mode and run each build.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if compilation fails.
+    pub fn compare_elf(&self, elf_path: &Path, name: &str) -> Result<ModeComparison, String> {
+        let out_dir = self.work_dir.join(name);
+        let options = CompileOptions::new()
+            .with_htif(self.htif)
+            .with_compiler(self.compiler.clone())
+            .with_quiet(true);
+        compile_address_modes(elf_path, &out_dir, &options, &COMPARED_MODES)
+            .map_err(|e| format!("compile failed: {e}"))?;
+
+        Ok(ModeComparison {
+            wrap: self.run_isolated(&out_dir.join(AddressMode::Wrap.as_str()), elf_path),
+            bounds: self.run_isolated(&out_dir.join(AddressMode::Bounds.as_str()), elf_path),
+        })
+    }
+
+    /// Check a fixture's expected outcome under every compared mode.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first expectation that does not hold.
+    pub fn check_fixture(&self, fixture: &Fixture) -> Result<(), String> {
+        let out_dir = self.work_dir.join(fixture.name);
+        std::fs::create_dir_all(&out_dir).map_err(|e| format!("failed to create dir: {e}"))?;
+        let elf_path = out_dir.join(format!("{}.elf", fixture.name));
+        std::fs::write(&elf_path, fixture.elf())
+            .map_err(|e| format!("failed to write ELF: {e}"))?;
+
+        // Per-instruction suspension lets the fault site be reached exactly.
+        let options = CompileOptions::new()
+            .with_instret_mode(InstretMode::PerInstruction)
+            .with_compiler(self.compiler.clone())
+            .with_quiet(true);
+        compile_address_modes(&elf_path, &out_dir, &options, &COMPARED_MODES)
+            .map_err(|e| format!("compile failed: {e}"))?;
+
+        for mode in COMPARED_MODES {
+            let Some(expected) = fixture.expected(mode) else {
+                continue;
+            };
+            let lib_dir = out_dir.join(mode.as_str());
+            let outcome = self.run_isolated(&lib_dir, &elf_path);
+            let mode_name = mode.as_str();
+            match expected {
+                Expected::Exit(code) => match outcome {
+                    ModeOutcome::Exited { exit_code, .. } if exit_code == code => {}
+                    other => {
+                        return Err(format!("{mode_name}: expected exit={code}, got {other}"));
+                    }
+                },
+                Expected::Fault(site) => {
+                    check_fault_site(&lib_dir, &elf_path, &site)
+                        .map_err(|e| format!("{mode_name}: {e}"))?;
+                    if !outcome.is_trap() {
+                        return Err(format!(
+                            "{mode_name}: expected trap at {:#x}, got {outcome}",
+                            site.pc()
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Run a compiled library in a child `rvr run` process.
+    fn run_isolated(&self, lib_dir: &Path, elf_path: &Path) -> ModeOutcome {
+        let stdout_path = lib_dir.join("run.json");
+        let stdout = match File::create(&stdout_path) {
+            Ok(file) => file,
+            Err(e) => return ModeOutcome::Error(format!("failed to create output: {e}")),
+        };
+
+        let mut cmd = Command::new(&self.rvr_exe);
+        cmd.arg("--silent")
+            .arg("run")
+            .arg(lib_dir)
+            .arg(elf_path)
+            .arg("--format")
+            .arg("json")
+            .stdout(stdout)
+            .stderr(Stdio::null());
+
+        let status = match run_command_with_timeout(&mut cmd, self.timeout) {
+            Ok(status) => status,
+            Err(e) if e.kind() == std::io::ErrorKind::TimedOut => return ModeOutcome::TimedOut,
+            Err(e) => return ModeOutcome::Error(format!("failed to run rvr: {e}")),
+        };
+        if let Some(signal) = status.signal() {
+            return ModeOutcome::Signaled(signal);
+        }
+
+        let output = std::fs::read_to_string(&stdout_path).unwrap_or_default();
+        parse_run_result(&output).unwrap_or_else(|| {
+            ModeOutcome::Error(format!("rvr run failed with status {:?}", status.code()))
+        })
+    }
+}
+
+/// Run up to the fault site in-process and check the PC and effective address.
+///
+/// Execution stops before the faulting instruction, so this is safe to do
+/// in the harness process.
+fn check_fault_site(lib_dir: &Path, elf_path: &Path, site: &FaultSite) -> Result<(), String> {
+    let mut runner = Runner::load(lib_dir, elf_path).map_err(|e| format!("failed to load: {e}"))?;
+    let _ = runner.reset_and_run_to_instret(site.index);
+    if runner.has_exited() {
+        return Err(format!(
+            "exited with {} before reaching {:#x}",
+            runner.exit_code(),
+            site.pc()
+        ));
+    }
+
+    let pc = runner.get_pc();
+    if pc != site.pc() {
+        return Err(format!(
+            "expected fault at pc={:#x}, got {pc:#x}",
+            site.pc()
+        ));
+    }
+    let addr = runner
+        .get_register(usize::from(site.base_reg))
+        .wrapping_add_signed(site.offset);
+    if addr != site.addr {
+        return Err(format!(
+            "expected fault address {:#x}, got {addr:#x}",
+            site.addr
+        ));
+    }
+    Ok(())
+}
+
+fn parse_run_result(output: &str) -> Option<ModeOutcome> {
+    let line = output
+        .lines()
+        .find(|line| line.starts_with(JSON_RESULT_PREFIX))?;
+    Some(ModeOutcome::Exited {
+        exit_code: u8::try_from(json_u64_field(line, "exit_code")?).ok()?,
+        instret: json_u64_field(line, "instret")?,
+    })
+}
+
+fn json_u64_field(line: &str, key: &str) -> Option<u64> {
+    let start = line.find(&format!("\"{key}\":"))? + key.len() + 3;
+    let digits: String = line[start..]
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect();
+    digits.parse().ok()
+}
+
+/// Current rotation slot: [`ROTATION_ENV`] if set, otherwise the day number.
+#[must_use]
+pub fn rotation_slot() -> u64 {
+    if let Some(slot) = std::env::var(ROTATION_ENV)
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+    {
+        return slot % ROTATION_PERIOD;
+    }
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() / SECS_PER_DAY);
+    days % ROTATION_PERIOD
+}
+
+/// True if [`FULL_MATRIX_ENV`] requests the full matrix.
+#[must_use]
+pub fn full_matrix_requested() -> bool {
+    std::env::var_os(FULL_MATRIX_ENV).is_some_and(|v| !v.is_empty() && v != "0")
+}
+
+/// True if a riscv-test cannot run under static recompilation or needs
+/// machine/supervisor mode.
+#[must_use]
+pub fn should_skip(name: &str) -> bool {
+    SKIP_TESTS.contains(&name) || name.contains("mi-p-") || name.contains("si-p-")
+}
+
+/// Select the cases to run, dropping those rejected by [`should_skip`].
+///
+/// With `full`, every remaining case is kept. Otherwise cases whose names start with
+/// [`ALWAYS_RUN_PREFIX`] are always kept and the rest are split into
+/// [`ROTATION_PERIOD`] stable buckets by name, keeping the bucket for `slot`.
+#[must_use]
+pub fn sample_suite(cases: &[PathBuf], slot: u64, full: bool) -> Vec<PathBuf> {
+    cases
+        .iter()
+        .filter(|path| {
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            if should_skip(name) {
+                return false;
+            }
+            full || name.starts_with(ALWAYS_RUN_PREFIX)
+                || fnv1a(name.as_bytes()) % ROTATION_PERIOD == slot % ROTATION_PERIOD
+        })
+        .cloned()
+        .collect()
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(FNV_OFFSET_BASIS, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(FNV_PRIME)
+    })
+}
@@ -76,7 +76,7 @@ static const uint64_t kInitialBrk = 0x{initial_brk:x}ULL;
 static const uint64_t kInitialSp = 0x{initial_sp:x}ULL;
 static const uint64_t kInitialGp = 0x{initial_gp:x}ULL;
 static const int kClockMonotonic = 1;
-enum {{ kNumRegs = {num_regs}, kNumCsrs = 4096 }};
+enum {{ kNumRegs = {num_regs}, kNumCsrs = 4096, kMmapMaxRegions = 128 }};
 
 // RvState struct - must match the generated code layout exactly
 // This is the ABI contract between the comparison and the backends
@@ -89,12 +89,16 @@ typedef struct {{
     uint8_t reservation_valid;
     uint8_t has_exited;
     uint8_t exit_code;
-    uint8_t _pad0;
+    uint8_t cancel_requested;
+    uint32_t exit_cause;
+    uint64_t exit_info;
     {reg_type} brk;
     {reg_type} start_brk;
     uint8_t* memory;
+    void* io;
     // Note: CSRs and tracer fields follow but we don't access them
     {reg_type} csrs[kNumCsrs];
+    struct {{ uint32_t count; uint32_t _pad; uint64_t regions[kMmapMaxRegions][2]; }} mmap;
 }} RvState;
 
 // Function pointer type for rv_execute_from
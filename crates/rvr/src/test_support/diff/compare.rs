@@ -71,6 +71,25 @@ fn run_to_target(runner: &mut crate::Runner, target_instret: u64) -> RunSnapshot
     }
 }
 
+/// Resume from the current PC until suspended at or after `target_instret`.
+fn resume_to_target(runner: &mut crate::Runner, target_instret: u64) -> RunSnapshot {
+    runner.set_target_instret(target_instret);
+    runner.clear_exit();
+    let pc = runner.get_pc();
+    let result = runner.execute_from(pc);
+    RunSnapshot {
+        state: DiffState {
+            pc: runner.get_pc(),
+            instret: runner.instret(),
+            is_exit: runner.has_exited(),
+            ..DiffState::default()
+        },
+        has_exited: runner.has_exited(),
+        exit_code: runner.exit_code(),
+        error: result.is_err(),
+    }
+}
+
 fn checkpoint_match(
     ref_snap: &RunSnapshot,
     test_snap: &RunSnapshot,
@@ -516,6 +535,68 @@ pub fn compare_checkpoint(
     }
 }
 
+/// Suspend-slice comparison.
+///
+/// Resumes both runners in slices of `slice` instructions and compares PC and
+/// all register values at every suspension point. Unlike `compare_checkpoint`
+/// neither side is ever reset, so every slice goes through the backend's
+/// suspend and resume paths. Backends that only suspend at block boundaries
+/// may stop past the target; the runner that is behind is resumed up to the
+/// other until both stop at the same instret.
+///
+/// The divergence index is the instret at the start of the failing slice.
+pub fn compare_suspend_slices(
+    ref_runner: &mut crate::Runner,
+    test_runner: &mut crate::Runner,
+    slice: u64,
+    max_instrs: Option<u64>,
+) -> CompareResult {
+    let limit = max_instrs.unwrap_or(u64::MAX);
+    let start = ref_runner.instret();
+    let mut matched: u64 = 0;
+
+    while matched < limit {
+        let target = start + matched + slice.min(limit - matched);
+        let mut ref_snap = resume_to_target(ref_runner, target);
+        let mut test_snap = resume_to_target(test_runner, ref_snap.state.instret);
+        while !(ref_snap.error || test_snap.error) {
+            let (ref_instret, test_instret) = (ref_snap.state.instret, test_snap.state.instret);
+            if ref_instret < test_instret && !ref_snap.has_exited {
+                ref_snap = resume_to_target(ref_runner, test_instret);
+            } else if test_instret < ref_instret && !test_snap.has_exited {
+                test_snap = resume_to_target(test_runner, ref_instret);
+            } else {
+                break;
+            }
+        }
+
+        let instret = ref_snap.state.instret;
+        if !checkpoint_match(&ref_snap, &test_snap, ref_runner, test_runner, instret) {
+            let kind = divergence_kind(&ref_snap, &test_snap, ref_runner, test_runner, instret);
+            let index = u64_to_usize(matched);
+            return CompareResult {
+                matched: index,
+                divergence: Some(Divergence {
+                    index,
+                    expected: ref_snap.state,
+                    actual: test_snap.state,
+                    kind,
+                }),
+            };
+        }
+
+        matched = instret - start;
+        if ref_snap.has_exited {
+            break;
+        }
+    }
+
+    CompareResult {
+        matched: u64_to_usize(matched),
+        divergence: None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -640,6 +721,71 @@ mod tests {
         );
     }
 
+    /// `count` instructions, the one at `csr_index` writing `csr_value` to
+    /// minstret (`csrw minstret, a0`).
+    fn minstret_write_states(count: u64, csr_index: u64, csr_value: u64) -> Vec<DiffState> {
+        (0..count)
+            .map(|i| {
+                let is_csr = i == csr_index;
+                DiffState {
+                    pc: 0x1000 + i * 4,
+                    opcode: if is_csr { 0xb025_1073 } else { 0x13 },
+                    csr: is_csr.then_some(0xb02),
+                    csr_value: is_csr.then_some(csr_value),
+                    ..DiffState::default()
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_compare_csr_value() {
+        // The sides disagree on instret after a fixed count; only the CSR
+        // write shows it
+        let mut ref_exec = MockExecutor::new(minstret_write_states(32, 20, 20));
+        let mut test_exec = MockExecutor::new(minstret_write_states(32, 20, 21));
+
+        let result = compare_lockstep(
+            &mut ref_exec,
+            &mut test_exec,
+            &CompareConfig::default(),
+            None,
+        );
+
+        assert_eq!(result.matched, 20);
+        let divergence = result.divergence.unwrap();
+        assert_eq!(divergence.kind, DivergenceKind::CsrValue);
+        assert_eq!(divergence.expected.csr_value, Some(20));
+        assert_eq!(divergence.actual.csr_value, Some(21));
+    }
+
+    #[test]
+    fn test_compare_clock_csr_read() {
+        // `csrr a0, cycle` reads a different count on each side
+        let read_cycle = |value| DiffState {
+            pc: 0x1000,
+            opcode: 0xc000_2573,
+            rd: Some(10),
+            rd_value: Some(value),
+            ..DiffState::default()
+        };
+        let compare = |config: &CompareConfig| {
+            let mut ref_exec = MockExecutor::new(vec![read_cycle(1000)]);
+            let mut test_exec = MockExecutor::new(vec![read_cycle(12)]);
+            compare_lockstep(&mut ref_exec, &mut test_exec, config, None)
+        };
+
+        assert!(compare(&CompareConfig::default()).divergence.is_none());
+        let strict = CompareConfig {
+            strict_csrs: true,
+            ..CompareConfig::default()
+        };
+        assert_eq!(
+            compare(&strict).divergence.unwrap().kind,
+            DivergenceKind::RegValue
+        );
+    }
+
     #[test]
     fn test_compare_with_limit() {
         let states: Vec<_> = (0..100)
@@ -1,9 +1,13 @@
+use std::fmt::Write;
 use std::path::{Path, PathBuf};
 
 use rvr_emit::Backend;
 use rvr_emit::c::TracerKind;
 
-use crate::{CompileOptions, Compiler, InstretMode, TracerConfig, compile_with_options};
+use crate::recompiler::compile_x86_to_shared;
+use crate::{
+    CompileOptions, Compiler, HotRegsMode, InstretMode, TracerConfig, compile_with_options,
+};
 
 /// Compilation mode for differential execution.
 #[derive(Clone, Copy, Debug)]
@@ -14,6 +18,11 @@ enum DiffCompileMode {
     Block,
     /// Checkpoint mode: suspend mode only, no tracer needed.
     Checkpoint,
+    /// Suspend mode: block-boundary suspension, superblocks, no tracer, HTIF
+    /// for riscv-tests.
+    Suspend,
+    /// Ptrace mode: no tracer, guest PC line tables for single-stepping.
+    Ptrace,
 }
 
 fn compile_for_diff_mode(
@@ -22,9 +31,26 @@ fn compile_for_diff_mode(
     backend: Backend,
     compiler: &Compiler,
     mode: DiffCompileMode,
+) -> Result<PathBuf, String> {
+    let options = diff_options(backend, compiler, mode)?;
+    compile_diff_options(elf_path, output_dir, &options)
+}
+
+fn compile_diff_options(
+    elf_path: &Path,
+    output_dir: &Path,
+    options: &CompileOptions,
 ) -> Result<PathBuf, String> {
     std::fs::create_dir_all(output_dir).map_err(|e| format!("failed to create output dir: {e}"))?;
+    compile_with_options(elf_path, output_dir, options).map_err(|e| format!("compile failed: {e}"))
+}
 
+/// Compile options of differential execution `mode`.
+fn diff_options(
+    backend: Backend,
+    compiler: &Compiler,
+    mode: DiffCompileMode,
+) -> Result<CompileOptions, String> {
     if matches!(mode, DiffCompileMode::Block) && !super::backend_supports_buffered_diff(backend) {
         return Err(format!(
             "buffered diff tracer not supported for backend {backend:?}"
@@ -46,6 +72,8 @@ fn compile_for_diff_mode(
             true,
         ),
         DiffCompileMode::Checkpoint => (InstretMode::PerInstruction, TracerConfig::none(), false),
+        DiffCompileMode::Suspend => (InstretMode::Suspend, TracerConfig::none(), true),
+        DiffCompileMode::Ptrace => (InstretMode::Count, TracerConfig::none(), false),
     };
 
     let mut options = CompileOptions::new()
@@ -53,13 +81,14 @@ fn compile_for_diff_mode(
         .with_instret_mode(instret_mode)
         .with_tracer_config(tracer_config)
         .with_compiler(compiler.clone())
+        .with_htif(matches!(mode, DiffCompileMode::Suspend))
+        .with_guest_pc_map(matches!(mode, DiffCompileMode::Ptrace))
         .with_quiet(true);
 
     if !superblock {
         options = options.with_superblock(false);
     }
-
-    compile_with_options(elf_path, output_dir, &options).map_err(|e| format!("compile failed: {e}"))
+    Ok(options)
 }
 
 /// Compile an ELF for linear differential execution (per-instruction stepping).
@@ -121,3 +150,110 @@ pub fn compile_for_checkpoint(
         DiffCompileMode::Checkpoint,
     )
 }
+
+/// Compile an ELF for suspend-slice comparison (`InstretMode::Suspend`, as
+/// used for fuel metering).
+///
+/// # Errors
+///
+/// Returns errors from compilation.
+pub fn compile_for_suspend(
+    elf_path: &Path,
+    output_dir: &Path,
+    backend: Backend,
+    compiler: &Compiler,
+) -> Result<PathBuf, String> {
+    compile_for_diff_mode(
+        elf_path,
+        output_dir,
+        backend,
+        compiler,
+        DiffCompileMode::Suspend,
+    )
+}
+
+/// Compile an ELF like `compile_for_suspend` with `hot_regs_mode`, to
+/// compare register assignments (C backend).
+///
+/// # Errors
+///
+/// Returns errors from compilation.
+pub fn compile_for_suspend_hot_regs(
+    elf_path: &Path,
+    output_dir: &Path,
+    compiler: &Compiler,
+    hot_regs_mode: HotRegsMode,
+) -> Result<PathBuf, String> {
+    let options = diff_options(Backend::C, compiler, DiffCompileMode::Suspend)?
+        .with_hot_regs_mode(hot_regs_mode);
+    compile_diff_options(elf_path, output_dir, &options)
+}
+
+/// Compile an ELF for single-stepping under ptrace: counted instret (so every
+/// guest instruction has host code) and guest PC line tables, no tracer.
+///
+/// # Errors
+///
+/// Returns errors from compilation.
+pub fn compile_for_ptrace(
+    elf_path: &Path,
+    output_dir: &Path,
+    backend: Backend,
+    compiler: &Compiler,
+) -> Result<PathBuf, String> {
+    compile_for_diff_mode(
+        elf_path,
+        output_dir,
+        backend,
+        compiler,
+        DiffCompileMode::Ptrace,
+    )
+}
+
+/// Miscompile the x86 build in `output_dir` so that every XOR is off by one.
+///
+/// Flips the low bit of every register-to-register `xor` result in the
+/// assembly and rebuilds the library, so differential tests can check that
+/// the divergence is caught.
+///
+/// # Errors
+///
+/// Returns an error if the assembly has no such `xor` or cannot be rebuilt.
+pub fn inject_x86_xor_divergence(output_dir: &Path, compiler: &Compiler) -> Result<(), String> {
+    let base_name = output_dir
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("rv");
+    let asm_path = output_dir.join(format!("{base_name}.s"));
+    let asm = std::fs::read_to_string(&asm_path)
+        .map_err(|e| format!("failed to read {}: {e}", asm_path.display()))?;
+
+    let mut patched = String::with_capacity(asm.len());
+    let mut injected = 0;
+    for line in asm.lines() {
+        writeln!(patched, "{line}").unwrap();
+        if let Some((suffix, dest)) = xor_dest(line) {
+            writeln!(patched, "    xor{suffix} $1, %{dest}").unwrap();
+            injected += 1;
+        }
+    }
+    if injected == 0 {
+        return Err(format!("no register xor in {}", asm_path.display()));
+    }
+    std::fs::write(&asm_path, patched)
+        .map_err(|e| format!("failed to write {}: {e}", asm_path.display()))?;
+    compile_x86_to_shared(output_dir, base_name, compiler, true)
+        .map_err(|e| format!("rebuild failed: {e}"))
+}
+
+/// Size suffix and destination of `xor{l,q} %src, %dest` with distinct
+/// registers; a register xored with itself is a zeroing idiom, not a guest
+/// XOR.
+fn xor_dest(line: &str) -> Option<(char, &str)> {
+    let rest = line.trim_start().strip_prefix("xor")?;
+    let suffix = rest.chars().next().filter(|c| matches!(c, 'l' | 'q'))?;
+    let (src, dest) = rest[1..].trim().split_once(',')?;
+    let src = src.trim().strip_prefix('%')?;
+    let dest = dest.trim().strip_prefix('%')?;
+    (src != dest).then_some((suffix, dest))
+}
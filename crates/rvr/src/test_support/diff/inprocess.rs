@@ -11,6 +11,17 @@ use crate::{RunError, Runner};
 use super::executor::Executor;
 use super::state::DiffState;
 
+/// Fail for libraries with a sampled tracer: lockstep comparison needs every
+/// instruction's state, and a sampled trace has gaps.
+fn check_unsampled(runner: &Runner) -> Result<(), RunError> {
+    match runner.tracer_sample_interval() {
+        1 => Ok(()),
+        interval => Err(RunError::TracerSetupFailed(format!(
+            "library traces one instruction in {interval}; lockstep comparison needs every one"
+        ))),
+    }
+}
+
 /// In-process executor using compiled rvr code.
 ///
 /// Uses `SuspendRunner` with instret stepping to execute one instruction at a time.
@@ -25,13 +36,17 @@ impl InProcessExecutor {
     ///
     /// # Errors
     ///
-    /// Returns errors from loading the compiled library or missing suspend support.
+    /// Returns errors from loading the compiled library, missing suspend
+    /// support or a sampled tracer.
     pub fn new(lib_dir: &Path, elf_path: &Path) -> Result<Self, RunError> {
         let mut runner = Runner::load(lib_dir, elf_path)?;
+        check_unsampled(&runner)?;
 
         // Verify the library supports suspend mode
         if !runner.supports_suspend() {
-            return Err(RunError::ExecutionError(255));
+            return Err(RunError::SuspendRequired(
+                "in-process differential execution",
+            ));
         }
 
         // Prepare for execution
@@ -76,6 +91,7 @@ impl Executor for InProcessExecutor {
                 (None::<u64>, None::<u64>, None::<u8>, false),
                 |(addr, val, width, is_write)| (Some(addr), Some(val), Some(width), is_write),
             );
+            let (csr, csr_value) = self.runner.diff_traced_csr().unzip();
 
             Some(DiffState {
                 pc: pc_before,
@@ -87,6 +103,8 @@ impl Executor for InProcessExecutor {
                 mem_value,
                 mem_width,
                 is_write,
+                csr,
+                csr_value,
                 is_exit,
             })
         } else {
@@ -125,9 +143,11 @@ impl BufferedInProcessExecutor {
     ///
     /// # Errors
     ///
-    /// Returns errors from loading the compiled library or missing buffered diff support.
+    /// Returns errors from loading the compiled library, missing buffered
+    /// diff support or a sampled tracer.
     pub fn new(lib_dir: &Path, elf_path: &Path) -> Result<Self, RunError> {
         let mut runner = Runner::load(lib_dir, elf_path)?;
+        check_unsampled(&runner)?;
 
         // Verify the library has buffered diff tracer support
         if runner.buffered_diff_count().is_none() {
@@ -201,21 +221,25 @@ impl BufferedInProcessExecutor {
     /// Get entry at index from the capture buffer.
     #[must_use]
     pub fn get_entry(&self, index: usize) -> Option<DiffState> {
-        let (pc, opcode, rd, rd_value, mem_access) = self.runner.buffered_diff_get(index)?;
+        let (pc, opcode, rd, rd_value, mem_access, csr_write, instret) =
+            self.runner.buffered_diff_get(index)?;
         let (mem_addr, mem_value, mem_width, is_write) = mem_access.map_or(
             (None::<u64>, None::<u64>, None::<u8>, false),
             |(addr, val, width, is_write)| (Some(addr), Some(val), Some(width), is_write),
         );
+        let (csr, csr_value) = csr_write.unzip();
         Some(DiffState {
             pc,
             opcode,
-            instret: 0, // Not tracked per-entry in buffer
+            instret,
             rd,
             rd_value,
             mem_addr,
             mem_value,
             mem_width,
             is_write,
+            csr,
+            csr_value,
             is_exit: false,
         })
     }
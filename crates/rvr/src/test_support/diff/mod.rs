@@ -5,25 +5,48 @@
 //! - `spike-arm64`: Spike (reference) vs ARM64 backend
 //! - `c-arm64`: C backend vs ARM64 backend
 //!
+//! The assembly backends can also be single-stepped under ptrace without a
+//! tracer (see [`ptrace`]).
+//!
 //! Unlike trace comparison which writes traces to disk, differential execution
-//! runs in lockstep and compares state in memory.
+//! runs in lockstep and compares state in memory. Spike's side can be cached
+//! and replayed (see [`trace_cache`]).
 
 pub mod c_compare;
 pub mod compare;
 pub mod compile;
 pub mod executor;
 pub mod inprocess;
+#[cfg(all(
+    target_os = "linux",
+    target_env = "gnu",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+pub mod ptrace;
 pub mod spike;
 pub mod state;
+pub mod trace_cache;
 
 pub use c_compare::{CCompareConfig, compile_c_compare, generate_c_compare, run_c_compare};
-pub use compare::{compare_block_vs_linear, compare_checkpoint, compare_lockstep};
-pub use compile::{compile_for_checkpoint, compile_for_diff, compile_for_diff_block};
+pub use compare::{
+    compare_block_vs_linear, compare_checkpoint, compare_lockstep, compare_suspend_slices,
+};
+pub use compile::{
+    compile_for_checkpoint, compile_for_diff, compile_for_diff_block, compile_for_ptrace,
+    compile_for_suspend, compile_for_suspend_hot_regs, inject_x86_xor_divergence,
+};
 pub use inprocess::{BufferedInProcessExecutor, InProcessExecutor};
+#[cfg(all(
+    target_os = "linux",
+    target_env = "gnu",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+pub use ptrace::PtraceExecutor;
 pub use spike::{SpikeExecutor, find_spike};
 pub use state::{
     CompareConfig, CompareResult, DiffGranularity, DiffState, Divergence, DivergenceKind,
 };
+pub use trace_cache::{CachedTraceExecutor, TraceCache, TraceSource};
 
 /// Check if a backend supports diff tracing.
 #[must_use]
@@ -39,3 +62,24 @@ pub const fn backend_supports_diff(backend: rvr_emit::Backend) -> bool {
 pub const fn backend_supports_buffered_diff(backend: rvr_emit::Backend) -> bool {
     matches!(backend, rvr_emit::Backend::C)
 }
+
+/// Check if a backend can be single-stepped by [`PtraceExecutor`]: the
+/// assembly backend for the host architecture.
+#[must_use]
+pub const fn backend_supports_ptrace(backend: rvr_emit::Backend) -> bool {
+    if cfg!(all(
+        target_os = "linux",
+        target_env = "gnu",
+        target_arch = "x86_64"
+    )) {
+        matches!(backend, rvr_emit::Backend::X86Asm)
+    } else if cfg!(all(
+        target_os = "linux",
+        target_env = "gnu",
+        target_arch = "aarch64"
+    )) {
+        matches!(backend, rvr_emit::Backend::ARM64Asm)
+    } else {
+        false
+    }
+}
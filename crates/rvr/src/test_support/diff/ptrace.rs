@@ -0,0 +1,400 @@
+//! Ptrace executor for the assembly backends.
+//!
+//! Runs the compiled library in a forked child and single-steps its host
+//! code with `PTRACE_SINGLESTEP`. The library is compiled with the guest PC
+//! map (see [`compile_for_ptrace`](super::compile_for_ptrace)), whose line
+//! table gives the host address where each guest instruction's code starts.
+//! Reaching one of those addresses retires the previous guest instruction,
+//! whose register write is read back from the child: hot registers from the
+//! host registers the library's `RV_HOT_REGS` names, the rest from `RvState`
+//! in the child's memory.
+//!
+//! No tracer is involved, so this works for backends whose diff tracing is
+//! incomplete, at the cost of a ptrace stop per host instruction. Memory
+//! accesses and CSR writes are not reconstructed.
+
+use std::collections::HashMap;
+use std::io::IoSliceMut;
+use std::path::Path;
+
+use libloading::os::unix::{Library, RTLD_NOW};
+use nix::libc::{Dl_info, dladdr, user_regs_struct};
+use nix::sys::ptrace;
+use nix::sys::signal::{Signal, kill, raise};
+use nix::sys::uio::{RemoteIoVec, process_vm_readv};
+use nix::sys::wait::{WaitStatus, waitpid};
+use nix::unistd::{ForkResult, Pid, fork};
+use rvr_elf::{ElfImage, get_elf_xlen};
+use rvr_ir::{Stmt, WriteTarget, Xlen};
+use rvr_isa::{ExtensionRegistry, Rv32, Rv64};
+
+use super::executor::Executor;
+use super::state::DiffState;
+use crate::runner::library_path;
+use crate::{Recompiler, RunError, Runner, guest_pc_entries};
+
+/// Symbol listing the guest register in each hot register slot.
+const HOT_REGS_SYMBOL: &str = "RV_HOT_REGS";
+
+/// Host registers of the native assembly backend.
+#[cfg(target_arch = "x86_64")]
+mod host {
+    use nix::libc::user_regs_struct;
+    pub use rvr_emit::x86::HOT_REG_SLOTS;
+
+    /// Host register holding each hot guest register.
+    pub fn hot_regs(slots: &[u8]) -> Vec<(u8, &'static str)> {
+        rvr_emit::x86::RegMap::new(slots, false)
+            .hot_regs_64()
+            .collect()
+    }
+
+    pub const fn pc(regs: &user_regs_struct) -> u64 {
+        regs.rip
+    }
+
+    pub const fn reg(regs: &user_regs_struct, name: &str) -> Option<u64> {
+        Some(match name.as_bytes() {
+            b"r14" => regs.r14,
+            b"r13" => regs.r13,
+            b"r12" => regs.r12,
+            b"rbp" => regs.rbp,
+            b"rdi" => regs.rdi,
+            b"rsi" => regs.rsi,
+            b"r9" => regs.r9,
+            b"r8" => regs.r8,
+            _ => return None,
+        })
+    }
+}
+
+/// Host registers of the native assembly backend.
+#[cfg(target_arch = "aarch64")]
+mod host {
+    use nix::libc::user_regs_struct;
+    pub use rvr_emit::arm64::HOT_REG_SLOTS;
+
+    /// Host register holding each hot guest register.
+    pub fn hot_regs(slots: &[u8]) -> Vec<(u8, &'static str)> {
+        rvr_emit::arm64::RegMap::new(slots, false)
+            .hot_regs_64()
+            .collect()
+    }
+
+    pub const fn pc(regs: &user_regs_struct) -> u64 {
+        regs.pc
+    }
+
+    pub fn reg(regs: &user_regs_struct, name: &str) -> Option<u64> {
+        let index: usize = name.strip_prefix('x')?.parse().ok()?;
+        regs.regs.get(index).copied()
+    }
+}
+
+/// Decodes guest instructions for what the diff tracer would report.
+trait GuestDecoder {
+    /// Raw opcode and destination register (never x0) of `bytes` at `pc`.
+    fn opcode_and_rd(&self, bytes: &[u8], pc: u64) -> Option<(u32, Option<u8>)>;
+}
+
+impl<X: Xlen> GuestDecoder for ExtensionRegistry<X> {
+    fn opcode_and_rd(&self, bytes: &[u8], pc: u64) -> Option<(u32, Option<u8>)> {
+        let instr = self.decode(bytes, X::from_u64(pc))?;
+        let ir = self.lift(&instr);
+        Some((instr.raw, last_reg_write(&ir.statements)))
+    }
+}
+
+fn last_reg_write<X: Xlen>(stmts: &[Stmt<X>]) -> Option<u8> {
+    stmts.iter().rev().find_map(|stmt| match stmt {
+        Stmt::Write {
+            target: WriteTarget::Reg(reg),
+            ..
+        } if *reg != 0 => Some(*reg),
+        Stmt::If {
+            then_stmts,
+            else_stmts,
+            ..
+        } => last_reg_write(then_stmts).or_else(|| last_reg_write(else_stmts)),
+        _ => None,
+    })
+}
+
+/// Decoder for the extensions the ELF was compiled with.
+fn guest_decoder<X: Xlen>(elf: &[u8]) -> Result<Box<dyn GuestDecoder>, RunError> {
+    let image = ElfImage::<X>::parse(elf)?;
+    let registry = Recompiler::<X>::with_defaults()
+        .extension_registry(&image)
+        .map_err(|e| RunError::Ptrace(e.to_string()))?;
+    Ok(Box::new(registry))
+}
+
+/// Load address of `library` and the guest register in each hot slot.
+fn library_layout(library: &Path) -> Result<(u64, Vec<u8>), RunError> {
+    // The runner has loaded the library already, so this is its mapping.
+    let lib = unsafe { Library::open(Some(library), RTLD_NOW)? };
+    let symbol = unsafe { lib.get::<*const u8>(HOT_REGS_SYMBOL.as_bytes()) }
+        .map_err(|e| RunError::SymbolNotFound(HOT_REGS_SYMBOL.to_string(), e))?;
+    let addr: *const u8 = *symbol;
+    // SAFETY: the symbol is an array of `HOT_REG_SLOTS` bytes.
+    let slots = unsafe { std::slice::from_raw_parts(addr, host::HOT_REG_SLOTS) }.to_vec();
+    let mut info: Dl_info = unsafe { std::mem::zeroed() };
+    if unsafe { dladdr(addr.cast(), &raw mut info) } == 0 {
+        return Err(RunError::Ptrace(format!(
+            "no load address for {}",
+            library.display()
+        )));
+    }
+    Ok((info.dli_fbase as u64, slots))
+}
+
+fn ptrace_error(err: nix::Error) -> RunError {
+    RunError::Ptrace(err.to_string())
+}
+
+/// Fork a traced child that runs the guest from `entry`, stopping before
+/// and after the run.
+fn spawn(runner: &mut Runner, entry: u64) -> Result<Pid, RunError> {
+    // SAFETY: the child only runs the guest and then exits.
+    match unsafe { fork() }.map_err(ptrace_error)? {
+        ForkResult::Child => {
+            if ptrace::traceme().is_ok() && raise(Signal::SIGSTOP).is_ok() {
+                let _ = runner.execute_from(entry);
+                let _ = raise(Signal::SIGSTOP);
+            }
+            unsafe { nix::libc::_exit(0) }
+        }
+        ForkResult::Parent { child } => match waitpid(child, None).map_err(ptrace_error)? {
+            WaitStatus::Stopped(_, Signal::SIGSTOP) => Ok(child),
+            status => Err(RunError::Ptrace(format!(
+                "child did not stop for tracing: {status:?}"
+            ))),
+        },
+    }
+}
+
+/// Where single-stepping the child stopped.
+enum Stop {
+    /// At the code of the guest instruction at `pc`, with these registers.
+    Instruction { pc: u64, regs: Vec<u64> },
+    /// The run returned, with the final registers.
+    Finished(Vec<u64>),
+    /// The child is gone or ptrace failed.
+    Lost,
+}
+
+/// Executor single-stepping assembly-backend code in a traced child.
+///
+/// Forks, so create it before starting other threads: the child inherits
+/// only the calling thread.
+pub struct PtraceExecutor {
+    /// Parent copy of the loaded guest, for reading instructions.
+    runner: Runner,
+    child: Pid,
+    /// Guest PC of each runtime address where a guest instruction's code
+    /// starts.
+    entries: HashMap<u64, u64>,
+    /// Host register holding each hot guest register.
+    hot_regs: Vec<(u8, &'static str)>,
+    state_addr: usize,
+    num_regs: usize,
+    xlen: u8,
+    decoder: Box<dyn GuestDecoder>,
+    /// Opcode and destination register by PC.
+    decoded: HashMap<u64, (u32, Option<u8>)>,
+    /// Guest instruction in flight and the registers before it.
+    pending: Option<(u64, Vec<u64>)>,
+    /// Signal to deliver to the child on the next step.
+    signal: Option<Signal>,
+    instret: u64,
+    finished: bool,
+}
+
+impl PtraceExecutor {
+    /// Load the library in `lib_dir` (compiled with
+    /// [`compile_for_ptrace`](super::compile_for_ptrace)) and fork a child
+    /// stopped at the guest entry point.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from loading the library, reading its guest PC map or
+    /// starting the traced child.
+    pub fn new(lib_dir: &Path, elf_path: &Path) -> Result<Self, RunError> {
+        let mut runner = Runner::load(lib_dir, elf_path)?;
+        runner.prepare();
+        let entry = runner.entry_point();
+        runner.set_pc(entry);
+
+        let library = library_path(lib_dir);
+        let (base, slots) = library_layout(&library)?;
+        let entries = guest_pc_entries(&library)
+            .map_err(|e| RunError::Ptrace(e.to_string()))?
+            .into_iter()
+            .map(|(addr, pc)| (base + addr, pc))
+            .collect();
+        let elf = std::fs::read(elf_path)?;
+        let decoder = if get_elf_xlen(&elf)? == 32 {
+            guest_decoder::<Rv32>(&elf)?
+        } else {
+            guest_decoder::<Rv64>(&elf)?
+        };
+
+        let state_addr = runner.state_addr();
+        let child = spawn(&mut runner, entry)?;
+        Ok(Self {
+            num_regs: runner.num_regs(),
+            xlen: runner.xlen(),
+            runner,
+            child,
+            entries,
+            hot_regs: host::hot_regs(&slots),
+            state_addr,
+            decoder,
+            decoded: HashMap::new(),
+            pending: None,
+            signal: None,
+            instret: 0,
+            finished: false,
+        })
+    }
+
+    /// Guest registers as stored in the child's `RvState`.
+    fn state_regs(&self) -> Option<Vec<u64>> {
+        let width = usize::from(self.xlen / 8);
+        let mut buf = vec![0u8; self.num_regs * width];
+        let remote = [RemoteIoVec {
+            base: self.state_addr,
+            len: buf.len(),
+        }];
+        let read = process_vm_readv(self.child, &mut [IoSliceMut::new(&mut buf)], &remote).ok()?;
+        (read == buf.len()).then(|| {
+            buf.chunks_exact(width)
+                .map(|bytes| {
+                    let mut value = [0u8; 8];
+                    value[..width].copy_from_slice(bytes);
+                    u64::from_le_bytes(value)
+                })
+                .collect()
+        })
+    }
+
+    /// Guest registers mid-run: hot ones live in host registers.
+    fn guest_regs(&self, host_regs: &user_regs_struct) -> Option<Vec<u64>> {
+        let mut regs = self.state_regs()?;
+        let mask = u64::MAX >> (u64::BITS - u32::from(self.xlen));
+        for &(reg, name) in &self.hot_regs {
+            if let Some(value) = regs.get_mut(usize::from(reg)) {
+                *value = host::reg(host_regs, name)? & mask;
+            }
+        }
+        Some(regs)
+    }
+
+    /// Single-step the child to the next guest instruction or the end of
+    /// the run.
+    fn next_stop(&mut self) -> Stop {
+        loop {
+            if ptrace::step(self.child, self.signal.take()).is_err() {
+                return Stop::Lost;
+            }
+            match waitpid(self.child, None) {
+                Ok(WaitStatus::Stopped(_, Signal::SIGTRAP)) => {
+                    let Ok(host_regs) = ptrace::getregs(self.child) else {
+                        return Stop::Lost;
+                    };
+                    let Some(&pc) = self.entries.get(&host::pc(&host_regs)) else {
+                        continue;
+                    };
+                    return self
+                        .guest_regs(&host_regs)
+                        .map_or(Stop::Lost, |regs| Stop::Instruction { pc, regs });
+                }
+                Ok(WaitStatus::Stopped(_, Signal::SIGSTOP)) => {
+                    return self.state_regs().map_or(Stop::Lost, Stop::Finished);
+                }
+                // E.g. SIGSEGV committing guest memory: let the child handle it.
+                Ok(WaitStatus::Stopped(_, signal)) => self.signal = Some(signal),
+                _ => return Stop::Lost,
+            }
+        }
+    }
+
+    /// State of the guest instruction at `pc` that took registers from
+    /// `before` to `after`.
+    fn retire(&mut self, pc: u64, before: &[u64], after: &[u64], is_exit: bool) -> DiffState {
+        let (opcode, rd) = self.opcode_and_rd(pc);
+        // Without a decoded destination, report whatever register changed.
+        let rd = rd.or_else(|| {
+            (1..after.len())
+                .find(|&reg| before.get(reg) != after.get(reg))
+                .and_then(|reg| u8::try_from(reg).ok())
+        });
+        self.instret += 1;
+        DiffState {
+            pc,
+            opcode,
+            instret: self.instret,
+            rd,
+            rd_value: rd.and_then(|reg| after.get(usize::from(reg)).copied()),
+            is_exit,
+            ..Default::default()
+        }
+    }
+
+    fn opcode_and_rd(&mut self, pc: u64) -> (u32, Option<u8>) {
+        if let Some(&decoded) = self.decoded.get(&pc) {
+            return decoded;
+        }
+        let mut bytes = [0u8; 4];
+        let read = self.runner.read_memory(pc, &mut bytes);
+        let decoded = self
+            .decoder
+            .opcode_and_rd(&bytes[..read], pc)
+            .unwrap_or((0, None));
+        self.decoded.insert(pc, decoded);
+        decoded
+    }
+
+    fn stop_child(&self) {
+        let _ = kill(self.child, Signal::SIGKILL);
+        let _ = waitpid(self.child, None);
+    }
+}
+
+impl Executor for PtraceExecutor {
+    fn step(&mut self) -> Option<DiffState> {
+        if self.finished {
+            return None;
+        }
+        loop {
+            match self.next_stop() {
+                Stop::Instruction { pc, regs } => {
+                    let previous = self.pending.replace((pc, regs));
+                    if let Some((prev_pc, before)) = previous {
+                        let after = self.pending.as_ref().map(|(_, regs)| regs.clone())?;
+                        return Some(self.retire(prev_pc, &before, &after, false));
+                    }
+                }
+                Stop::Finished(regs) => {
+                    self.finished = true;
+                    self.stop_child();
+                    let (pc, before) = self.pending.take()?;
+                    return Some(self.retire(pc, &before, &regs, true));
+                }
+                Stop::Lost => {
+                    self.finished = true;
+                    self.stop_child();
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+impl Drop for PtraceExecutor {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.stop_child();
+        }
+    }
+}
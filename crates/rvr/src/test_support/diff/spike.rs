@@ -11,6 +11,7 @@ use regex::Regex;
 
 use super::executor::Executor;
 use super::state::DiffState;
+use crate::test_support::trace::parse_spike_csr_write;
 
 /// Executor that runs Spike and parses its commit log output.
 pub struct SpikeExecutor {
@@ -142,12 +143,17 @@ impl SpikeExecutor {
             .captures(line)
             .and_then(|caps| u64::from_str_radix(caps.get(1)?.as_str(), 16).ok());
 
+        // Parse CSR write: c<CSR>_<name> 0x<VALUE>
+        let (csr, csr_value) = parse_spike_csr_write(line, opcode).unzip();
+
         Some(DiffState {
             pc,
             opcode,
             rd,
             rd_value,
             mem_addr,
+            csr,
+            csr_value,
             ..Default::default()
         })
     }
@@ -241,6 +247,17 @@ mod tests {
         assert!(state.rd_value.is_none());
     }
 
+    #[test]
+    fn test_parse_line_with_csr() {
+        // `csrw minstret, a0`; Spike numbers CSRs in decimal
+        let line = "core   0: 3 0x80000020 (0xb0251073) c2818_minstret 0x0000000000000010";
+        let state = SpikeExecutor::parse_line(line).unwrap();
+
+        assert!(state.rd.is_none());
+        assert_eq!(state.csr, Some(0xb02));
+        assert_eq!(state.csr_value, Some(0x10));
+    }
+
     #[test]
     fn test_parse_line_non_trace() {
         assert!(SpikeExecutor::parse_line("some random output").is_none());
@@ -2,6 +2,8 @@
 //!
 //! Defines the state captured after each instruction and comparison algorithms.
 
+use crate::test_support::trace::accesses_clock_csr;
+
 /// Effects observed for one instruction execution.
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct DiffState {
@@ -23,6 +25,10 @@ pub struct DiffState {
     pub mem_width: Option<u8>,
     /// True if this was a store, false if load.
     pub is_write: bool,
+    /// CSR written (if any).
+    pub csr: Option<u16>,
+    /// Value written to the CSR (if any).
+    pub csr_value: Option<u64>,
     /// True if this instruction caused program exit.
     pub is_exit: bool,
 }
@@ -83,6 +89,8 @@ pub enum DivergenceKind {
     MemAddr,
     /// Memory value mismatch.
     MemValue,
+    /// CSR write mismatch (different CSR or value).
+    CsrValue,
     /// Reference wrote a register but test didn't.
     MissingRegWrite,
     /// Test wrote a register but reference didn't.
@@ -106,6 +114,7 @@ impl std::fmt::Display for DivergenceKind {
             Self::RegValue => write!(f, "register value mismatch"),
             Self::MemAddr => write!(f, "memory address mismatch"),
             Self::MemValue => write!(f, "memory value mismatch"),
+            Self::CsrValue => write!(f, "CSR write mismatch"),
             Self::MissingRegWrite => write!(f, "missing register write"),
             Self::ExtraRegWrite => write!(f, "extra register write"),
             Self::MissingMemAccess => write!(f, "missing memory access"),
@@ -136,6 +145,9 @@ pub struct CompareConfig {
     pub strict_reg_writes: bool,
     /// Require exact memory access matching.
     pub strict_mem_access: bool,
+    /// Compare reads and writes of clock counters (`cycle`, `time`), which
+    /// normally differ between implementations.
+    pub strict_csrs: bool,
 }
 
 impl Default for CompareConfig {
@@ -143,6 +155,7 @@ impl Default for CompareConfig {
         Self {
             strict_reg_writes: true,
             strict_mem_access: false, // Spike doesn't always log mem for loads
+            strict_csrs: false,
         }
     }
 }
@@ -153,6 +166,10 @@ impl Default for CompareConfig {
 /// - Always ignore x0 writes (both sides).
 /// - PC and opcode must match exactly.
 /// - Register writes: if either has a write (non-x0), compare.
+/// - CSR writes: compare if both have one; Spike logs side-effect writes
+///   the tracers don't see, so a write on one side only is tolerated.
+/// - Clock counters (`cycle`, `time`): values read or written are only
+///   compared if `strict_csrs` is true.
 /// - Memory: only compare if `strict_mem_access` is true.
 #[must_use]
 pub const fn compare_states(
@@ -170,6 +187,8 @@ pub const fn compare_states(
         return Some(DivergenceKind::Opcode);
     }
 
+    let clock = !config.strict_csrs && accesses_clock_csr(expected.opcode);
+
     // Register write comparison
     if config.strict_reg_writes {
         match (expected.rd, actual.rd) {
@@ -180,6 +199,7 @@ pub const fn compare_states(
                 // Same register, compare values
                 if let (Some(e_val), Some(a_val)) = (expected.rd_value, actual.rd_value)
                     && e_val != a_val
+                    && !clock
                 {
                     return Some(DivergenceKind::RegValue);
                 }
@@ -190,6 +210,20 @@ pub const fn compare_states(
         }
     }
 
+    // CSR write comparison
+    if let (Some(e_csr), Some(a_csr)) = (expected.csr, actual.csr)
+        && !clock
+    {
+        if e_csr != a_csr {
+            return Some(DivergenceKind::CsrValue);
+        }
+        if let (Some(e_val), Some(a_val)) = (expected.csr_value, actual.csr_value)
+            && e_val != a_val
+        {
+            return Some(DivergenceKind::CsrValue);
+        }
+    }
+
     // Memory access comparison
     if config.strict_mem_access {
         match (expected.mem_addr, actual.mem_addr) {
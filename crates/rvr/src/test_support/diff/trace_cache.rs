@@ -0,0 +1,572 @@
+//! Reference trace cache for Spike.
+//!
+//! Spike dominates the time of a lockstep diff, and its trace only depends
+//! on the ELF and the ISA string. The first run streams the commit log into
+//! a binary trace under the cache directory; later runs replay it through
+//! [`CachedTraceExecutor`] instead of starting Spike.
+//!
+//! A trace file is a header followed by fixed-width records:
+//!
+//! ```text
+//! header (32 bytes): magic "RVRREFTR", version u32, record size u32,
+//!                    complete u8, 7 reserved bytes, record count u64
+//! record (40 bytes): pc u64, rd value u64, mem addr u64, opcode u32,
+//!                    rd u8 (0 = no write), flags u8 (bit 0: mem addr,
+//!                    bit 1: CSR write), csr u16, csr value u64
+//! ```
+//!
+//! All fields are little-endian. A trace is *complete* when Spike ran to
+//! the end; an incomplete one (the comparison stopped first) is replayed as
+//! far as it goes, then Spike is started and fast-forwarded past it, and the
+//! longer trace replaces the cached one. A header with another magic,
+//! version or record size is treated as a miss and overwritten.
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use rvr_emit::c::content_hash;
+use tempfile::NamedTempFile;
+use tracing::warn;
+
+use super::executor::Executor;
+use super::spike::SpikeExecutor;
+use super::state::DiffState;
+
+const MAGIC: [u8; 8] = *b"RVRREFTR";
+/// Bumped whenever the header or record layout changes.
+pub const TRACE_VERSION: u32 = 2;
+const HEADER_SIZE: usize = 32;
+/// Record size, as stored in the header.
+const RECORD_BYTES: u32 = 40;
+const RECORD_SIZE: usize = RECORD_BYTES as usize;
+const FLAG_MEM_ADDR: u8 = 1;
+const FLAG_CSR: u8 = 2;
+/// Extension of trace files in the cache directory.
+const TRACE_EXTENSION: &str = "rvrtrace";
+
+/// Directory of cached reference traces, capped in size.
+#[derive(Clone, Debug)]
+pub struct TraceCache {
+    dir: PathBuf,
+    max_bytes: u64,
+}
+
+impl TraceCache {
+    /// Default size cap.
+    pub const DEFAULT_MAX_BYTES: u64 = 8 << 30;
+
+    /// Cache in `dir`, evicting least recently used traces above `max_bytes`.
+    pub fn new(dir: impl Into<PathBuf>, max_bytes: u64) -> Self {
+        Self {
+            dir: dir.into(),
+            max_bytes,
+        }
+    }
+
+    /// `$XDG_CACHE_HOME/rvr/ref-traces`, else `~/.cache/rvr/ref-traces`.
+    #[must_use]
+    pub fn default_dir() -> Option<PathBuf> {
+        let base = std::env::var_os("XDG_CACHE_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))?;
+        Some(base.join("rvr").join("ref-traces"))
+    }
+
+    /// Cache directory.
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Cache key of a trace: a hash of the ELF contents and the ISA string.
+    #[must_use]
+    pub fn key(elf: &[u8], isa: &str) -> String {
+        let mut bytes = Vec::with_capacity(elf.len() + isa.len() + 1);
+        bytes.extend_from_slice(elf);
+        bytes.push(0);
+        bytes.extend_from_slice(isa.as_bytes());
+        format!("{:016x}", content_hash(&bytes))
+    }
+
+    /// Path of the trace for `key`.
+    #[must_use]
+    pub fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.{TRACE_EXTENSION}"))
+    }
+
+    /// Remove least recently used traces until the cache fits its cap,
+    /// never removing `keep`. Returns the number of traces removed.
+    ///
+    /// # Errors
+    /// Returns errors from listing the cache directory.
+    pub fn evict(&self, keep: &Path) -> io::Result<usize> {
+        let mut traces = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != TRACE_EXTENSION) {
+                continue;
+            }
+            let Ok(meta) = fs::metadata(&path) else {
+                continue;
+            };
+            let used = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            traces.push((used, meta.len(), path));
+        }
+        traces.sort();
+
+        let mut total: u64 = traces.iter().map(|(_, len, _)| len).sum();
+        let mut removed = 0;
+        for (_, len, path) in &traces {
+            if total <= self.max_bytes {
+                break;
+            }
+            if path == keep {
+                continue;
+            }
+            if fs::remove_file(path).is_ok() {
+                total -= len;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+}
+
+/// Where a [`CachedTraceExecutor`] gets its states from at the start.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraceSource {
+    /// Replaying a cached trace.
+    Cached { records: u64, complete: bool },
+    /// Running Spike (cache miss, stale trace, or refresh requested).
+    Spike,
+}
+
+/// Reference executor that replays a cached Spike trace, running Spike only
+/// for what the cache does not cover and recording it for next time.
+pub struct CachedTraceExecutor {
+    cache: TraceCache,
+    path: PathBuf,
+    elf: PathBuf,
+    isa: String,
+    entry_point: u64,
+    source: TraceSource,
+    replay: Option<TraceReader>,
+    live: Option<SpikeExecutor>,
+    /// Records every state when the run may extend the cache.
+    writer: Option<TraceWriter>,
+    steps: u64,
+    spike_exited: bool,
+}
+
+impl CachedTraceExecutor {
+    /// Open the cached trace for `elf` and `isa`, or start Spike when there
+    /// is none (or `refresh` is set).
+    ///
+    /// # Errors
+    /// Returns errors from reading the ELF, creating the cache directory, or
+    /// starting Spike.
+    pub fn open(
+        cache: TraceCache,
+        elf: &Path,
+        isa: &str,
+        entry_point: u64,
+        refresh: bool,
+    ) -> io::Result<Self> {
+        let path = cache.path(&TraceCache::key(&fs::read(elf)?, isa));
+        fs::create_dir_all(cache.dir())?;
+        let replay = if refresh {
+            None
+        } else {
+            TraceReader::open(&path)?
+        };
+
+        let (source, live) = if let Some(reader) = &replay {
+            // Reading counts as a use for LRU eviction
+            if let Ok(file) = File::options().write(true).open(&path) {
+                let _ = file.set_modified(SystemTime::now());
+            }
+            let source = TraceSource::Cached {
+                records: reader.remaining,
+                complete: reader.complete,
+            };
+            (source, None)
+        } else {
+            let spike = SpikeExecutor::start(elf, isa, entry_point)?;
+            (TraceSource::Spike, Some(spike))
+        };
+        let writer = match source {
+            TraceSource::Cached { complete: true, .. } => None,
+            _ => Some(TraceWriter::create(cache.dir())?),
+        };
+
+        Ok(Self {
+            cache,
+            path,
+            elf: elf.to_path_buf(),
+            isa: isa.to_string(),
+            entry_point,
+            source,
+            replay,
+            live,
+            writer,
+            steps: 0,
+            spike_exited: false,
+        })
+    }
+
+    /// Where states came from when the executor was opened.
+    #[must_use]
+    pub const fn source(&self) -> TraceSource {
+        self.source
+    }
+
+    /// Path of the cached trace.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Store the recorded trace if it covers more than the cached one, then
+    /// enforce the cache size cap. Returns whether the cache was updated.
+    ///
+    /// # Errors
+    /// Returns errors from writing the trace or evicting old ones.
+    pub fn finish(mut self) -> io::Result<bool> {
+        let cached = match self.source {
+            TraceSource::Cached { records, .. } => records,
+            TraceSource::Spike => 0,
+        };
+        let Some(writer) = self.writer.take() else {
+            return Ok(false);
+        };
+        if writer.count <= cached {
+            return Ok(false);
+        }
+        writer.persist(self.spike_exited, &self.path)?;
+        self.cache.evict(&self.path)?;
+        Ok(true)
+    }
+
+    fn next_state(&mut self) -> Option<DiffState> {
+        if let Some(reader) = &mut self.replay {
+            if let Some(mut state) = reader.next() {
+                self.steps += 1;
+                state.instret = self.steps;
+                return Some(state);
+            }
+            let complete = reader.complete;
+            self.replay = None;
+            if complete {
+                return None;
+            }
+            self.live = self.resume_spike();
+        }
+
+        let state = self.live.as_mut()?.step();
+        if state.is_some() {
+            self.steps += 1;
+        } else {
+            self.spike_exited = true;
+            self.live = None;
+        }
+        state
+    }
+
+    /// Start Spike and skip the states already replayed.
+    fn resume_spike(&mut self) -> Option<SpikeExecutor> {
+        let mut spike = match SpikeExecutor::start(&self.elf, &self.isa, self.entry_point) {
+            Ok(spike) => spike,
+            Err(err) => {
+                warn!(%err, "failed to start Spike past the cached trace");
+                return None;
+            }
+        };
+        for _ in 0..self.steps {
+            if spike.step().is_none() {
+                warn!(steps = self.steps, "Spike ended before the cached trace");
+                self.spike_exited = true;
+                return None;
+            }
+        }
+        Some(spike)
+    }
+}
+
+impl Executor for CachedTraceExecutor {
+    fn step(&mut self) -> Option<DiffState> {
+        let state = self.next_state()?;
+        if let Some(writer) = &mut self.writer
+            && let Err(err) = writer.push(&state)
+        {
+            warn!(%err, "failed to record reference trace; not caching this run");
+            self.writer = None;
+        }
+        Some(state)
+    }
+}
+
+/// Streams records into a temporary file in the cache directory.
+struct TraceWriter {
+    file: BufWriter<NamedTempFile>,
+    count: u64,
+}
+
+impl TraceWriter {
+    fn create(dir: &Path) -> io::Result<Self> {
+        let mut file = BufWriter::new(NamedTempFile::new_in(dir)?);
+        file.write_all(&encode_header(false, 0))?;
+        Ok(Self { file, count: 0 })
+    }
+
+    fn push(&mut self, state: &DiffState) -> io::Result<()> {
+        self.file.write_all(&encode_record(state))?;
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Fill in the header and move the trace to `path`.
+    fn persist(self, complete: bool, path: &Path) -> io::Result<()> {
+        let mut file = self
+            .file
+            .into_inner()
+            .map_err(io::IntoInnerError::into_error)?;
+        file.seek(SeekFrom::Start(0))?;
+        file.write_all(&encode_header(complete, self.count))?;
+        file.as_file().sync_all()?;
+        file.persist(path).map_err(|err| err.error)?;
+        Ok(())
+    }
+}
+
+/// Reads the records of a cached trace.
+struct TraceReader {
+    file: BufReader<File>,
+    remaining: u64,
+    complete: bool,
+}
+
+impl TraceReader {
+    /// Open a trace; `None` if it is missing, stale or truncated.
+    fn open(path: &Path) -> io::Result<Option<Self>> {
+        let mut file = match File::open(path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        let mut header = [0u8; HEADER_SIZE];
+        if file.read_exact(&mut header).is_err() {
+            return Ok(None);
+        }
+        let Some((complete, count)) = decode_header(&header) else {
+            return Ok(None);
+        };
+        let expected = HEADER_SIZE as u64 + count * RECORD_SIZE as u64;
+        if file.metadata()?.len() != expected {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            file: BufReader::new(file),
+            remaining: count,
+            complete,
+        }))
+    }
+
+    fn next(&mut self) -> Option<DiffState> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let mut record = [0u8; RECORD_SIZE];
+        if let Err(err) = self.file.read_exact(&mut record) {
+            warn!(%err, "failed to read cached reference trace");
+            self.remaining = 0;
+            return None;
+        }
+        self.remaining -= 1;
+        Some(decode_record(&record))
+    }
+}
+
+fn encode_header(complete: bool, count: u64) -> [u8; HEADER_SIZE] {
+    let mut header = [0u8; HEADER_SIZE];
+    header[..8].copy_from_slice(&MAGIC);
+    header[8..12].copy_from_slice(&TRACE_VERSION.to_le_bytes());
+    header[12..16].copy_from_slice(&RECORD_BYTES.to_le_bytes());
+    header[16] = u8::from(complete);
+    header[24..32].copy_from_slice(&count.to_le_bytes());
+    header
+}
+
+/// `(complete, record count)`, if the header matches this format.
+fn decode_header(header: &[u8; HEADER_SIZE]) -> Option<(bool, u64)> {
+    let word = |at: usize| {
+        u32::from_le_bytes([header[at], header[at + 1], header[at + 2], header[at + 3]])
+    };
+    if header[..8] != MAGIC || word(8) != TRACE_VERSION || word(12) != RECORD_BYTES {
+        return None;
+    }
+    let count = u64::from_le_bytes(header[24..32].try_into().ok()?);
+    Some((header[16] != 0, count))
+}
+
+fn encode_record(state: &DiffState) -> [u8; RECORD_SIZE] {
+    let mut record = [0u8; RECORD_SIZE];
+    record[..8].copy_from_slice(&state.pc.to_le_bytes());
+    record[8..16].copy_from_slice(&state.rd_value.unwrap_or(0).to_le_bytes());
+    record[16..24].copy_from_slice(&state.mem_addr.unwrap_or(0).to_le_bytes());
+    record[24..28].copy_from_slice(&state.opcode.to_le_bytes());
+    record[28] = state.rd.unwrap_or(0);
+    if state.mem_addr.is_some() {
+        record[29] |= FLAG_MEM_ADDR;
+    }
+    if let Some(csr) = state.csr {
+        record[29] |= FLAG_CSR;
+        record[30..32].copy_from_slice(&csr.to_le_bytes());
+        record[32..40].copy_from_slice(&state.csr_value.unwrap_or(0).to_le_bytes());
+    }
+    record
+}
+
+fn decode_record(record: &[u8; RECORD_SIZE]) -> DiffState {
+    let u64_at = |at: usize| {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&record[at..at + 8]);
+        u64::from_le_bytes(bytes)
+    };
+    let mut opcode = [0u8; 4];
+    opcode.copy_from_slice(&record[24..28]);
+    let rd = (record[28] != 0).then_some(record[28]);
+    let csr = (record[29] & FLAG_CSR != 0).then(|| u16::from_le_bytes([record[30], record[31]]));
+    DiffState {
+        pc: u64_at(0),
+        opcode: u32::from_le_bytes(opcode),
+        rd,
+        rd_value: rd.map(|_| u64_at(8)),
+        mem_addr: (record[29] & FLAG_MEM_ADDR != 0).then(|| u64_at(16)),
+        csr,
+        csr_value: csr.map(|_| u64_at(32)),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn states() -> Vec<DiffState> {
+        vec![
+            DiffState {
+                pc: 0x8000_0000,
+                opcode: 0x0000_0093,
+                rd: Some(1),
+                rd_value: Some(0xdead_beef),
+                ..Default::default()
+            },
+            DiffState {
+                pc: 0x8000_0004,
+                opcode: 0x0182_b283,
+                rd: Some(5),
+                rd_value: Some(0),
+                mem_addr: Some(0x1018),
+                ..Default::default()
+            },
+            DiffState {
+                pc: 0x8000_0008,
+                opcode: 0xb025_1073,
+                csr: Some(0xb02),
+                csr_value: Some(2),
+                ..Default::default()
+            },
+            DiffState {
+                pc: 0x8000_000c,
+                opcode: 0x0500_006f,
+                ..Default::default()
+            },
+        ]
+    }
+
+    fn write_trace(dir: &Path, path: &Path, complete: bool) {
+        let mut writer = TraceWriter::create(dir).unwrap();
+        for state in &states() {
+            writer.push(state).unwrap();
+        }
+        writer.persist(complete, path).unwrap();
+    }
+
+    #[test]
+    fn test_record_round_trip() {
+        for state in states() {
+            assert_eq!(decode_record(&encode_record(&state)), state);
+        }
+    }
+
+    #[test]
+    fn test_replay_complete_trace() {
+        let temp = tempfile::tempdir().unwrap();
+        let elf = temp.path().join("test.elf");
+        fs::write(&elf, b"not really an ELF").unwrap();
+        let cache = TraceCache::new(temp.path().join("cache"), TraceCache::DEFAULT_MAX_BYTES);
+        fs::create_dir_all(cache.dir()).unwrap();
+        let key = TraceCache::key(&fs::read(&elf).unwrap(), "rv64imac");
+        write_trace(cache.dir(), &cache.path(&key), true);
+
+        // Spike is never started for a complete trace
+        let mut exec = CachedTraceExecutor::open(cache, &elf, "rv64imac", 0, false).unwrap();
+        assert_eq!(
+            exec.source(),
+            TraceSource::Cached {
+                records: 4,
+                complete: true
+            }
+        );
+        let replayed: Vec<DiffState> = std::iter::from_fn(|| exec.step()).collect();
+        let expected: Vec<DiffState> = states()
+            .into_iter()
+            .zip(1..)
+            .map(|(state, instret)| DiffState { instret, ..state })
+            .collect();
+        assert_eq!(replayed, expected);
+        assert!(!exec.finish().unwrap());
+    }
+
+    #[test]
+    fn test_stale_version_is_a_miss() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("stale.rvrtrace");
+        write_trace(temp.path(), &path, true);
+        assert!(TraceReader::open(&path).unwrap().is_some());
+
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[8..12].copy_from_slice(&(TRACE_VERSION + 1).to_le_bytes());
+        fs::write(&path, &bytes).unwrap();
+        assert!(TraceReader::open(&path).unwrap().is_none());
+
+        // Truncated traces are misses too
+        write_trace(temp.path(), &path, true);
+        let bytes = fs::read(&path).unwrap();
+        fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+        assert!(TraceReader::open(&path).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_evict_least_recently_used() {
+        let temp = tempfile::tempdir().unwrap();
+        let trace_size = (HEADER_SIZE + 4 * RECORD_SIZE) as u64;
+        let cache = TraceCache::new(temp.path(), 2 * trace_size);
+        let base = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000);
+        let paths: Vec<PathBuf> = (0..4).map(|i| cache.path(&format!("{i}"))).collect();
+        for (secs, path) in (0..).zip(&paths) {
+            write_trace(temp.path(), path, true);
+            let file = File::options().write(true).open(path).unwrap();
+            file.set_modified(base + std::time::Duration::from_secs(secs))
+                .unwrap();
+        }
+
+        // The oldest trace is kept when asked to, so the next two go
+        assert_eq!(cache.evict(&paths[0]).unwrap(), 2);
+        let left: Vec<bool> = paths.iter().map(|path| path.exists()).collect();
+        assert_eq!(left, [true, false, false, true]);
+    }
+}
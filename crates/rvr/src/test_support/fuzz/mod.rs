@@ -0,0 +1,530 @@
+//! Random instruction sequences for differential fuzzing.
+//!
+//! [`generate`] turns a seed into a small bare-metal ELF:
+//! - a prologue that points t6 at a scratch buffer, sets `mtvec` and loads
+//!   x1-x29 from the buffer;
+//! - a body of random instructions from [`OPS`], with loads, stores and AMOs
+//!   confined to the scratch buffer and branches only jumping forward;
+//! - the riscv-tests exit sequence (`ecall` with a7 = 93), plus a trap
+//!   handler that writes `tohost` so Spike stops as well.
+//!
+//! The same seed and config always produce the same program, so a seed is
+//! enough to reproduce a divergence.
+
+mod ops;
+
+use std::fmt::Write as _;
+
+use rvr_elf::{ElfWriter, PF_R, PF_W, PF_X, STT_OBJECT};
+use rvr_isa::{
+    EXT_A, EXT_I, EXT_M, EXT_ZBA, EXT_ZBB, EXT_ZBKB, EXT_ZBS, EXT_ZICOND, ExtensionRegistry,
+    REG_A0, REG_A7, REG_GP, REG_T0, REG_T1, REG_T5, REG_T6, REG_ZERO, Xlen, encode_b, encode_i,
+    encode_j, encode_r, encode_s, encode_u,
+};
+
+use crate::test_support::guest::{
+    FUNCT3_ADDI, FUNCT3_CSRRW, FUNCT3_D, FUNCT3_W, OPCODE_AUIPC, OPCODE_JAL, OPCODE_LOAD,
+    OPCODE_OP_IMM, OPCODE_STORE, OPCODE_SYSTEM,
+};
+
+pub use ops::{Form, OPS, OpSpec, Width};
+
+/// Address of the first instruction (Spike's DRAM base).
+pub const TEXT_BASE: u64 = 0x8000_0000;
+/// Extensions drawn from when none are given.
+pub const DEFAULT_EXTENSIONS: &str = "i,m,a,zba,zbb,zbs,zbkb,zicond";
+
+/// Bytes of scratch memory the body may access.
+const SCRATCH_BYTES: u32 = 2048;
+/// Holds the scratch base for the whole program.
+const BASE_REG: u8 = REG_T6;
+/// Holds the address of the next AMO, always 8-byte aligned in scratch.
+const AMO_REG: u8 = REG_T5;
+/// Registers the body writes: everything but x0 and the two above.
+const DEST_REGS: u8 = 29;
+/// Longest forward jump, in instructions.
+const MAX_SKIP: usize = 16;
+
+const CSR_MTVEC: i32 = 0x305;
+const EXIT_SYSCALL: i32 = 93;
+
+const PROLOGUE_WORDS: usize = 5 + DEST_REGS as usize + 1;
+const EXIT_WORDS: usize = 4;
+const HANDLER_WORDS: usize = 4;
+
+/// Register values the prologue prefers over random ones.
+const EDGE_VALUES: [u64; 8] = [
+    0,
+    1,
+    u64::MAX,
+    0x8000_0000_0000_0000,
+    0x7FFF_FFFF_FFFF_FFFF,
+    0x8000_0000,
+    0xFFFF_FFFF,
+    0x7FFF_FFFF,
+];
+
+/// Parse an extension name accepted by `--ext`.
+///
+/// # Errors
+///
+/// Returns an error for unknown extensions and ones the fuzzer cannot
+/// generate yet (C, Zcb, Zcmp, Zicsr, Zifencei).
+pub fn parse_extension(name: &str) -> Result<u8, String> {
+    match name.trim().to_ascii_lowercase().as_str() {
+        "i" => Ok(EXT_I),
+        "m" => Ok(EXT_M),
+        "a" => Ok(EXT_A),
+        "zba" => Ok(EXT_ZBA),
+        "zbb" => Ok(EXT_ZBB),
+        "zbs" => Ok(EXT_ZBS),
+        "zbkb" => Ok(EXT_ZBKB),
+        "zicond" => Ok(EXT_ZICOND),
+        "c" | "zcb" | "zcmp" | "zicsr" | "zifencei" => {
+            Err(format!("extension {name} is not fuzzed yet"))
+        }
+        _ => Err(format!("unknown extension: {name}")),
+    }
+}
+
+const fn extension_name(ext: u8) -> &'static str {
+    match ext {
+        EXT_I => "i",
+        EXT_M => "m",
+        EXT_A => "a",
+        EXT_ZBA => "zba",
+        EXT_ZBB => "zbb",
+        EXT_ZBS => "zbs",
+        EXT_ZBKB => "zbkb",
+        EXT_ZICOND => "zicond",
+        _ => "?",
+    }
+}
+
+/// What [`generate`] produces.
+#[derive(Clone, Debug)]
+pub struct FuzzConfig {
+    /// Instructions in the random body.
+    pub length: usize,
+    /// Extensions (`EXT_*`) the body draws from.
+    pub extensions: Vec<u8>,
+}
+
+impl FuzzConfig {
+    #[must_use]
+    pub const fn new(length: usize, extensions: Vec<u8>) -> Self {
+        Self { length, extensions }
+    }
+
+    /// Instructions of [`OPS`] available for this config at `xlen`.
+    #[must_use]
+    pub fn ops(&self, xlen: u8) -> Vec<&'static OpSpec> {
+        OPS.iter()
+            .filter(|spec| spec.width.allows(xlen) && self.extensions.contains(&spec.opid.ext))
+            .collect()
+    }
+
+    /// ISA string for Spike, e.g. `rv64ima_zba_zicsr`.
+    #[must_use]
+    pub fn spike_isa(&self, xlen: u8) -> String {
+        let mut isa = format!("rv{xlen}i");
+        for ext in [EXT_M, EXT_A] {
+            if self.extensions.contains(&ext) {
+                isa.push_str(extension_name(ext));
+            }
+        }
+        for ext in [EXT_ZBA, EXT_ZBB, EXT_ZBS, EXT_ZBKB, EXT_ZICOND] {
+            if self.extensions.contains(&ext) {
+                isa.push('_');
+                isa.push_str(extension_name(ext));
+            }
+        }
+        // The prologue writes mtvec.
+        isa.push_str("_zicsr");
+        isa
+    }
+
+    /// Comma-separated extension names, as `--ext` takes them.
+    #[must_use]
+    pub fn extension_list(&self) -> String {
+        self.extensions
+            .iter()
+            .map(|&ext| extension_name(ext))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+/// One generated instruction.
+#[derive(Clone, Debug)]
+pub struct FuzzLine {
+    pub pc: u64,
+    pub word: u32,
+    pub disasm: String,
+}
+
+/// A generated program.
+#[derive(Clone, Debug)]
+pub struct FuzzCase {
+    pub seed: u64,
+    pub xlen: u8,
+    pub entry: u64,
+    pub elf: Vec<u8>,
+    /// Every instruction, prologue to trap handler.
+    pub lines: Vec<FuzzLine>,
+    /// Index of the first body, exit and trap handler instruction.
+    sections: [usize; 3],
+}
+
+impl FuzzCase {
+    /// Upper bound on instructions executed before the exit `ecall`.
+    #[must_use]
+    pub const fn max_instrs(&self) -> usize {
+        self.sections[2]
+    }
+
+    /// Assembly listing of the program; `mark` flags the instruction at that
+    /// PC. Every instruction is a `.word`, so the listing reassembles to the
+    /// same text.
+    #[must_use]
+    pub fn listing(&self, mark: Option<u64>) -> String {
+        let mut out = format!("# fuzz --seed {} (rv{})\n", self.seed, self.xlen);
+        out.push_str("    .text\n    .globl _start\n_start:\n");
+        let headers = ["# body", "# exit", "# trap handler (stops Spike)"];
+        for (i, line) in self.lines.iter().enumerate() {
+            if let Some(section) = self.sections.iter().position(|&start| start == i) {
+                let _ = writeln!(out, "    {}", headers[section]);
+            }
+            let _ = write!(
+                out,
+                "    .word 0x{:08x}  # {:x}: {}",
+                line.word, line.pc, line.disasm
+            );
+            if mark == Some(line.pc) {
+                out.push_str("  <-- divergence");
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// Deterministic splitmix64 generator.
+struct Rng(u64);
+
+impl Rng {
+    const fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    const fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `0..n`.
+    fn below(&mut self, n: usize) -> usize {
+        let n = u64::try_from(n).expect("usize fits in u64");
+        usize::try_from(self.next_u64() % n).expect("below n")
+    }
+
+    fn below_u32(&mut self, n: u32) -> u32 {
+        u32::try_from(self.next_u64() % u64::from(n)).expect("below n")
+    }
+
+    fn chance(&mut self, one_in: usize) -> bool {
+        self.below(one_in) == 0
+    }
+
+    fn dest(&mut self) -> u8 {
+        1 + u8::try_from(self.below(usize::from(DEST_REGS))).expect("register")
+    }
+
+    fn src(&mut self) -> u8 {
+        u8::try_from(self.below(32)).expect("register")
+    }
+
+    fn imm12(&mut self) -> i32 {
+        let imm = match self.below(4) {
+            0 => self.below_u32(32),
+            1 => [0, 0xFFF, 0x7FF, 0x800][self.below(4)],
+            _ => self.below_u32(1 << 12),
+        };
+        // Sign-extend from 12 bits.
+        (imm.cast_signed() << 20) >> 20
+    }
+
+    /// Offset into scratch aligned to `width` bytes.
+    fn scratch_offset(&mut self, width: u8) -> i32 {
+        let width = u32::from(width);
+        (self.below_u32(SCRATCH_BYTES / width) * width).cast_signed()
+    }
+}
+
+/// Split `to - from` into `auipc`/`addi` immediates.
+fn pc_relative(from: u64, to: u64) -> (u32, i32) {
+    let delta = u32::try_from(to - from).expect("target within 2 GiB");
+    let hi = (delta + 0x800) >> 12;
+    (hi, delta.wrapping_sub(hi << 12).cast_signed())
+}
+
+const fn addi(rd: u8, rs1: u8, imm: i32) -> u32 {
+    encode_i(OPCODE_OP_IMM, rd, FUNCT3_ADDI, rs1, imm)
+}
+
+/// Append the instruction(s) for `spec` to `body`. `remaining` is how many
+/// body slots are left, which bounds forward jumps to the exit sequence.
+fn emit(rng: &mut Rng, spec: &OpSpec, xlen: u8, remaining: usize, body: &mut Vec<u32>) {
+    let word = match spec.form {
+        Form::R => encode_r(0, rng.dest(), 0, rng.src(), rng.src(), 0),
+        Form::Unary => encode_r(0, rng.dest(), 0, rng.src(), 0, 0),
+        Form::Imm => encode_i(0, rng.dest(), 0, rng.src(), rng.imm12()),
+        Form::Shift => {
+            let shamt = rng.below_u32(u32::from(xlen)).cast_signed();
+            encode_i(0, rng.dest(), 0, rng.src(), shamt)
+        }
+        Form::ShiftW => encode_i(0, rng.dest(), 0, rng.src(), rng.below_u32(32).cast_signed()),
+        Form::Upper => encode_u(0, rng.dest(), rng.below_u32(1 << 20)),
+        Form::Load(width) => encode_i(0, rng.dest(), 0, BASE_REG, rng.scratch_offset(width)),
+        Form::Store(width) => encode_s(0, 0, BASE_REG, rng.src(), rng.scratch_offset(width)),
+        Form::Branch => encode_b(0, 0, rng.src(), rng.src(), skip(rng, remaining)),
+        Form::Jal => encode_j(0, rng.dest(), skip(rng, remaining)),
+        Form::Amo(_) => {
+            // Always 8-byte aligned, so the address suits both widths.
+            body.push(addi(AMO_REG, BASE_REG, rng.scratch_offset(8)));
+            if remaining < 2 {
+                return;
+            }
+            encode_r(0, rng.dest(), 0, AMO_REG, rng.src(), 0)
+        }
+    };
+    body.push(spec.bits | word);
+}
+
+/// Byte offset of a forward jump landing at most `remaining` slots ahead.
+fn skip(rng: &mut Rng, remaining: usize) -> i32 {
+    let slots = 1 + rng.below(remaining.min(MAX_SKIP));
+    i32::try_from(slots * 4).expect("short jump")
+}
+
+/// Generate the program for `seed`.
+///
+/// # Panics
+///
+/// Panics if `config` selects no instruction for this XLEN.
+#[must_use]
+pub fn generate<X: Xlen>(config: &FuzzConfig, seed: u64) -> FuzzCase {
+    let xlen = X::VALUE;
+    let ops = config.ops(xlen);
+    assert!(!ops.is_empty(), "no instructions to fuzz for rv{xlen}");
+    let mut rng = Rng::new(seed);
+    let reg_bytes = u8::try_from(X::REG_BYTES).expect("register width");
+
+    let handler_start = PROLOGUE_WORDS + config.length + EXIT_WORDS;
+    let text_words = handler_start + HANDLER_WORDS;
+    let text_len = u64::try_from(text_words * 4).expect("text size");
+    let scratch = (TEXT_BASE + text_len).next_multiple_of(0x1000);
+    let tohost = scratch + u64::from(SCRATCH_BYTES);
+    let pc_of = |index: usize| TEXT_BASE + u64::try_from(index * 4).expect("text size");
+
+    // Scratch holds the initial register values in its first slots.
+    let mut data: Vec<u8> = (0..SCRATCH_BYTES)
+        .map(|_| rng.next_u64().to_le_bytes()[0])
+        .collect();
+    for reg in 1..=DEST_REGS {
+        if rng.chance(4) {
+            let value = EDGE_VALUES[rng.below(EDGE_VALUES.len())].to_le_bytes();
+            let slot = usize::from(reg) * usize::from(reg_bytes);
+            data[slot..slot + usize::from(reg_bytes)]
+                .copy_from_slice(&value[..usize::from(reg_bytes)]);
+        }
+    }
+    // tohost and fromhost
+    data.extend_from_slice(&[0; 16]);
+
+    let mut text = Vec::with_capacity(text_words);
+    let (hi, lo) = pc_relative(pc_of(0), scratch);
+    text.push(encode_u(OPCODE_AUIPC, BASE_REG, hi));
+    text.push(addi(BASE_REG, BASE_REG, lo));
+    let (hi, lo) = pc_relative(pc_of(2), pc_of(handler_start));
+    text.push(encode_u(OPCODE_AUIPC, REG_T0, hi));
+    text.push(addi(REG_T0, REG_T0, lo));
+    text.push(encode_i(
+        OPCODE_SYSTEM,
+        REG_ZERO,
+        FUNCT3_CSRRW,
+        REG_T0,
+        CSR_MTVEC,
+    ));
+    let funct3_load = if xlen == 64 { FUNCT3_D } else { FUNCT3_W };
+    for reg in 1..=DEST_REGS {
+        let slot = i32::from(reg) * i32::from(reg_bytes);
+        text.push(encode_i(OPCODE_LOAD, reg, funct3_load, BASE_REG, slot));
+    }
+    text.push(addi(AMO_REG, BASE_REG, 0));
+
+    let mut body = Vec::with_capacity(config.length);
+    while body.len() < config.length {
+        let spec = ops[rng.below(ops.len())];
+        emit(&mut rng, spec, xlen, config.length - body.len(), &mut body);
+    }
+    text.extend(body);
+
+    text.push(addi(REG_GP, REG_ZERO, 1));
+    text.push(addi(REG_A7, REG_ZERO, EXIT_SYSCALL));
+    text.push(addi(REG_A0, REG_ZERO, 0));
+    text.push(encode_i(OPCODE_SYSTEM, REG_ZERO, 0, REG_ZERO, 0));
+
+    let (hi, lo) = pc_relative(pc_of(handler_start), tohost);
+    text.push(encode_u(OPCODE_AUIPC, REG_T0, hi));
+    text.push(addi(REG_T1, REG_ZERO, 1));
+    text.push(encode_s(OPCODE_STORE, FUNCT3_W, REG_T0, REG_T1, lo));
+    text.push(encode_j(OPCODE_JAL, REG_ZERO, 0));
+
+    let registry = ExtensionRegistry::<X>::standard();
+    let lines = text
+        .iter()
+        .enumerate()
+        .map(|(index, &word)| {
+            let pc = pc_of(index);
+            let disasm = registry
+                .decode(&word.to_le_bytes(), X::from_u64(pc))
+                .map_or_else(|| "<unknown>".to_string(), |instr| registry.disasm(&instr));
+            FuzzLine { pc, word, disasm }
+        })
+        .collect();
+
+    let elf = ElfWriter::<X>::new(TEXT_BASE)
+        .with_segment(
+            TEXT_BASE,
+            PF_R | PF_X,
+            text.iter().flat_map(|word| word.to_le_bytes()).collect(),
+        )
+        .with_segment(scratch, PF_R | PF_W, data)
+        .with_symbol("tohost", tohost, STT_OBJECT)
+        .with_symbol("fromhost", tohost + 8, STT_OBJECT)
+        .build();
+
+    FuzzCase {
+        seed,
+        xlen,
+        entry: TEXT_BASE,
+        elf,
+        lines,
+        sections: [
+            PROLOGUE_WORDS,
+            PROLOGUE_WORDS + config.length,
+            handler_start,
+        ],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rvr_isa::{
+        InstrArgs, OP_ADDI, OP_PACK, OP_PACKW, OP_ZEXT_H, Rv32, Rv64, decode_rd, decode_rs2,
+    };
+
+    use super::*;
+
+    fn all_extensions() -> FuzzConfig {
+        let extensions = DEFAULT_EXTENSIONS
+            .split(',')
+            .map(|name| parse_extension(name).unwrap())
+            .collect();
+        FuzzConfig::new(256, extensions)
+    }
+
+    fn check_table<X: Xlen>() {
+        let registry = ExtensionRegistry::<X>::standard();
+        let mut rng = Rng::new(1);
+        for spec in OPS.iter().filter(|spec| spec.width.allows(X::VALUE)) {
+            for _ in 0..8 {
+                let mut words = Vec::new();
+                emit(&mut rng, spec, X::VALUE, MAX_SKIP, &mut words);
+                let word = *words.last().unwrap();
+                let instr = registry
+                    .decode(&word.to_le_bytes(), X::from_u64(TEXT_BASE))
+                    .unwrap_or_else(|| panic!("{:?} ({word:08x}) does not decode", spec.opid));
+                // pack with rs2 = x0 is zext.h
+                let zext_h = matches!(spec.opid, OP_PACK | OP_PACKW) && decode_rs2(word) == 0;
+                let expected = if zext_h { OP_ZEXT_H } else { spec.opid };
+                assert_eq!(instr.opid, expected, "{word:08x}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_table_decodes_rv32() {
+        check_table::<Rv32>();
+    }
+
+    #[test]
+    fn test_table_decodes_rv64() {
+        check_table::<Rv64>();
+    }
+
+    fn check_program<X: Xlen>(seed: u64) {
+        let config = all_extensions();
+        let case = generate::<X>(&config, seed);
+        assert_eq!(case.elf, generate::<X>(&config, seed).elf);
+        assert_eq!(case.lines.len(), case.max_instrs() + HANDLER_WORDS);
+
+        let registry = ExtensionRegistry::<X>::standard();
+        let allowed: Vec<_> = config.ops(X::VALUE).iter().map(|spec| spec.opid).collect();
+        let body = &case.lines[PROLOGUE_WORDS..PROLOGUE_WORDS + config.length];
+        for (index, line) in body.iter().enumerate() {
+            let instr = registry
+                .decode(&line.word.to_le_bytes(), X::from_u64(line.pc))
+                .unwrap_or_else(|| panic!("{:08x} does not decode", line.word));
+            if instr.opid == OP_ADDI && decode_rd(line.word) == AMO_REG {
+                continue;
+            }
+            assert!(allowed.contains(&instr.opid), "{}", line.disasm);
+            let (rd, offset) = match instr.args {
+                InstrArgs::R { rd, .. }
+                | InstrArgs::I { rd, .. }
+                | InstrArgs::U { rd, .. }
+                | InstrArgs::Amo { rd, .. } => (Some(rd), None),
+                InstrArgs::J { rd, imm } => (Some(rd), Some(imm)),
+                InstrArgs::B { imm, .. } => (None, Some(imm)),
+                _ => (None, None),
+            };
+            if let Some(rd) = rd {
+                assert!(rd != BASE_REG && rd != AMO_REG, "{}", line.disasm);
+            }
+            if let Some(offset) = offset {
+                let target = index + usize::try_from(offset / 4).unwrap();
+                assert!(offset > 0 && target <= config.length, "{}", line.disasm);
+            }
+        }
+    }
+
+    #[test]
+    fn test_generate_rv32() {
+        for seed in 0..16 {
+            check_program::<Rv32>(seed);
+        }
+    }
+
+    #[test]
+    fn test_generate_rv64() {
+        for seed in 0..16 {
+            check_program::<Rv64>(seed);
+        }
+    }
+
+    #[test]
+    fn test_parse_extension() {
+        assert_eq!(parse_extension("Zbb"), Ok(EXT_ZBB));
+        assert!(parse_extension("c").unwrap_err().contains("not fuzzed"));
+        assert!(parse_extension("zfoo").is_err());
+        assert_eq!(
+            all_extensions().spike_isa(32),
+            "rv32ima_zba_zbb_zbs_zbkb_zicond_zicsr"
+        );
+    }
+}
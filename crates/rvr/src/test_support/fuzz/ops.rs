@@ -0,0 +1,248 @@
+//! Instructions the fuzzer draws from.
+//!
+//! Each entry pairs an [`OpId`] with its fixed encoding bits (the `MATCH_*`
+//! values of riscv-opcodes) and the form of its variable fields. The
+//! generator ORs random operands into `bits`; the table test checks that
+//! every entry decodes back to its `OpId`.
+
+use rvr_isa::OpId;
+use rvr_isa::extensions::{
+    OP_ADD, OP_ADD_UW, OP_ADDI, OP_ADDIW, OP_ADDW, OP_AMOADD_D, OP_AMOADD_W, OP_AMOAND_D,
+    OP_AMOAND_W, OP_AMOMAX_D, OP_AMOMAX_W, OP_AMOMAXU_D, OP_AMOMAXU_W, OP_AMOMIN_D, OP_AMOMIN_W,
+    OP_AMOMINU_D, OP_AMOMINU_W, OP_AMOOR_D, OP_AMOOR_W, OP_AMOSWAP_D, OP_AMOSWAP_W, OP_AMOXOR_D,
+    OP_AMOXOR_W, OP_AND, OP_ANDI, OP_ANDN, OP_AUIPC, OP_BCLR, OP_BCLRI, OP_BEQ, OP_BEXT, OP_BEXTI,
+    OP_BGE, OP_BGEU, OP_BINV, OP_BINVI, OP_BLT, OP_BLTU, OP_BNE, OP_BREV8, OP_BSET, OP_BSETI,
+    OP_CLZ, OP_CLZW, OP_CPOP, OP_CPOPW, OP_CTZ, OP_CTZW, OP_CZERO_EQZ, OP_CZERO_NEZ, OP_DIV,
+    OP_DIVU, OP_DIVUW, OP_DIVW, OP_JAL, OP_LB, OP_LBU, OP_LD, OP_LH, OP_LHU, OP_LUI, OP_LW, OP_LWU,
+    OP_MAX, OP_MAXU, OP_MIN, OP_MINU, OP_MUL, OP_MULH, OP_MULHSU, OP_MULHU, OP_MULW, OP_OR,
+    OP_ORC_B, OP_ORI, OP_ORN, OP_PACK, OP_PACKH, OP_PACKW, OP_REM, OP_REMU, OP_REMUW, OP_REMW,
+    OP_REV8, OP_ROL, OP_ROLW, OP_ROR, OP_RORI, OP_RORIW, OP_RORW, OP_SB, OP_SD, OP_SEXT_B,
+    OP_SEXT_H, OP_SH, OP_SH1ADD, OP_SH1ADD_UW, OP_SH2ADD, OP_SH2ADD_UW, OP_SH3ADD, OP_SH3ADD_UW,
+    OP_SLL, OP_SLLI, OP_SLLI_UW, OP_SLLIW, OP_SLLW, OP_SLT, OP_SLTI, OP_SLTIU, OP_SLTU, OP_SRA,
+    OP_SRAI, OP_SRAIW, OP_SRAW, OP_SRL, OP_SRLI, OP_SRLIW, OP_SRLW, OP_SUB, OP_SUBW, OP_SW,
+    OP_UNZIP, OP_XNOR, OP_XOR, OP_XORI, OP_ZEXT_H, OP_ZIP,
+};
+
+/// Variable fields of an instruction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Form {
+    /// `rd, rs1, rs2`.
+    R,
+    /// `rd, rs1` with `rs2` fixed by the encoding.
+    Unary,
+    /// `rd, rs1, imm12`.
+    Imm,
+    /// `rd, rs1, shamt` with an XLEN-wide shift amount.
+    Shift,
+    /// `rd, rs1, shamt` with a 5-bit shift amount.
+    ShiftW,
+    /// `rd, imm20`.
+    Upper,
+    /// `rd, offset(base)` loading this many bytes.
+    Load(u8),
+    /// `rs2, offset(base)` storing this many bytes.
+    Store(u8),
+    /// `rs1, rs2, offset` to a later instruction.
+    Branch,
+    /// `rd, offset` to a later instruction.
+    Jal,
+    /// `rd, rs2, (addr)` on this many bytes.
+    Amo(u8),
+}
+
+/// Register widths an instruction exists in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Width {
+    Any,
+    Rv32,
+    Rv64,
+}
+
+impl Width {
+    #[must_use]
+    pub const fn allows(self, xlen: u8) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Rv32 => xlen == 32,
+            Self::Rv64 => xlen == 64,
+        }
+    }
+}
+
+/// One instruction the fuzzer can generate.
+#[derive(Clone, Copy, Debug)]
+pub struct OpSpec {
+    pub opid: OpId,
+    pub form: Form,
+    /// Encoding with every variable field zero.
+    pub bits: u32,
+    pub width: Width,
+}
+
+const fn op(opid: OpId, form: Form, bits: u32) -> OpSpec {
+    OpSpec {
+        opid,
+        form,
+        bits,
+        width: Width::Any,
+    }
+}
+
+const fn rv32(opid: OpId, form: Form, bits: u32) -> OpSpec {
+    OpSpec {
+        width: Width::Rv32,
+        ..op(opid, form, bits)
+    }
+}
+
+const fn rv64(opid: OpId, form: Form, bits: u32) -> OpSpec {
+    OpSpec {
+        width: Width::Rv64,
+        ..op(opid, form, bits)
+    }
+}
+
+/// Every instruction the fuzzer generates.
+///
+/// Left out: `jalr` (targets are not static), `fence`/`ecall`/`ebreak`,
+/// `lr`/`sc` (reservation behaviour is implementation-defined), Zicsr and
+/// compressed instructions.
+pub const OPS: &[OpSpec] = &[
+    // RV32I / RV64I
+    op(OP_LUI, Form::Upper, 0x0000_0037),
+    op(OP_AUIPC, Form::Upper, 0x0000_0017),
+    op(OP_JAL, Form::Jal, 0x0000_006f),
+    op(OP_BEQ, Form::Branch, 0x0000_0063),
+    op(OP_BNE, Form::Branch, 0x0000_1063),
+    op(OP_BLT, Form::Branch, 0x0000_4063),
+    op(OP_BGE, Form::Branch, 0x0000_5063),
+    op(OP_BLTU, Form::Branch, 0x0000_6063),
+    op(OP_BGEU, Form::Branch, 0x0000_7063),
+    op(OP_LB, Form::Load(1), 0x0000_0003),
+    op(OP_LH, Form::Load(2), 0x0000_1003),
+    op(OP_LW, Form::Load(4), 0x0000_2003),
+    op(OP_LBU, Form::Load(1), 0x0000_4003),
+    op(OP_LHU, Form::Load(2), 0x0000_5003),
+    rv64(OP_LWU, Form::Load(4), 0x0000_6003),
+    rv64(OP_LD, Form::Load(8), 0x0000_3003),
+    op(OP_SB, Form::Store(1), 0x0000_0023),
+    op(OP_SH, Form::Store(2), 0x0000_1023),
+    op(OP_SW, Form::Store(4), 0x0000_2023),
+    rv64(OP_SD, Form::Store(8), 0x0000_3023),
+    op(OP_ADDI, Form::Imm, 0x0000_0013),
+    op(OP_SLTI, Form::Imm, 0x0000_2013),
+    op(OP_SLTIU, Form::Imm, 0x0000_3013),
+    op(OP_XORI, Form::Imm, 0x0000_4013),
+    op(OP_ORI, Form::Imm, 0x0000_6013),
+    op(OP_ANDI, Form::Imm, 0x0000_7013),
+    op(OP_SLLI, Form::Shift, 0x0000_1013),
+    op(OP_SRLI, Form::Shift, 0x0000_5013),
+    op(OP_SRAI, Form::Shift, 0x4000_5013),
+    op(OP_ADD, Form::R, 0x0000_0033),
+    op(OP_SUB, Form::R, 0x4000_0033),
+    op(OP_SLL, Form::R, 0x0000_1033),
+    op(OP_SLT, Form::R, 0x0000_2033),
+    op(OP_SLTU, Form::R, 0x0000_3033),
+    op(OP_XOR, Form::R, 0x0000_4033),
+    op(OP_SRL, Form::R, 0x0000_5033),
+    op(OP_SRA, Form::R, 0x4000_5033),
+    op(OP_OR, Form::R, 0x0000_6033),
+    op(OP_AND, Form::R, 0x0000_7033),
+    rv64(OP_ADDIW, Form::Imm, 0x0000_001b),
+    rv64(OP_SLLIW, Form::ShiftW, 0x0000_101b),
+    rv64(OP_SRLIW, Form::ShiftW, 0x0000_501b),
+    rv64(OP_SRAIW, Form::ShiftW, 0x4000_501b),
+    rv64(OP_ADDW, Form::R, 0x0000_003b),
+    rv64(OP_SUBW, Form::R, 0x4000_003b),
+    rv64(OP_SLLW, Form::R, 0x0000_103b),
+    rv64(OP_SRLW, Form::R, 0x0000_503b),
+    rv64(OP_SRAW, Form::R, 0x4000_503b),
+    // M
+    op(OP_MUL, Form::R, 0x0200_0033),
+    op(OP_MULH, Form::R, 0x0200_1033),
+    op(OP_MULHSU, Form::R, 0x0200_2033),
+    op(OP_MULHU, Form::R, 0x0200_3033),
+    op(OP_DIV, Form::R, 0x0200_4033),
+    op(OP_DIVU, Form::R, 0x0200_5033),
+    op(OP_REM, Form::R, 0x0200_6033),
+    op(OP_REMU, Form::R, 0x0200_7033),
+    rv64(OP_MULW, Form::R, 0x0200_003b),
+    rv64(OP_DIVW, Form::R, 0x0200_403b),
+    rv64(OP_DIVUW, Form::R, 0x0200_503b),
+    rv64(OP_REMW, Form::R, 0x0200_603b),
+    rv64(OP_REMUW, Form::R, 0x0200_703b),
+    // A
+    op(OP_AMOSWAP_W, Form::Amo(4), 0x0800_202f),
+    op(OP_AMOADD_W, Form::Amo(4), 0x0000_202f),
+    op(OP_AMOXOR_W, Form::Amo(4), 0x2000_202f),
+    op(OP_AMOAND_W, Form::Amo(4), 0x6000_202f),
+    op(OP_AMOOR_W, Form::Amo(4), 0x4000_202f),
+    op(OP_AMOMIN_W, Form::Amo(4), 0x8000_202f),
+    op(OP_AMOMAX_W, Form::Amo(4), 0xa000_202f),
+    op(OP_AMOMINU_W, Form::Amo(4), 0xc000_202f),
+    op(OP_AMOMAXU_W, Form::Amo(4), 0xe000_202f),
+    rv64(OP_AMOSWAP_D, Form::Amo(8), 0x0800_302f),
+    rv64(OP_AMOADD_D, Form::Amo(8), 0x0000_302f),
+    rv64(OP_AMOXOR_D, Form::Amo(8), 0x2000_302f),
+    rv64(OP_AMOAND_D, Form::Amo(8), 0x6000_302f),
+    rv64(OP_AMOOR_D, Form::Amo(8), 0x4000_302f),
+    rv64(OP_AMOMIN_D, Form::Amo(8), 0x8000_302f),
+    rv64(OP_AMOMAX_D, Form::Amo(8), 0xa000_302f),
+    rv64(OP_AMOMINU_D, Form::Amo(8), 0xc000_302f),
+    rv64(OP_AMOMAXU_D, Form::Amo(8), 0xe000_302f),
+    // Zba
+    op(OP_SH1ADD, Form::R, 0x2000_2033),
+    op(OP_SH2ADD, Form::R, 0x2000_4033),
+    op(OP_SH3ADD, Form::R, 0x2000_6033),
+    rv64(OP_ADD_UW, Form::R, 0x0800_003b),
+    rv64(OP_SH1ADD_UW, Form::R, 0x2000_203b),
+    rv64(OP_SH2ADD_UW, Form::R, 0x2000_403b),
+    rv64(OP_SH3ADD_UW, Form::R, 0x2000_603b),
+    rv64(OP_SLLI_UW, Form::Shift, 0x0800_101b),
+    // Zbb
+    op(OP_ANDN, Form::R, 0x4000_7033),
+    op(OP_ORN, Form::R, 0x4000_6033),
+    op(OP_XNOR, Form::R, 0x4000_4033),
+    op(OP_CLZ, Form::Unary, 0x6000_1013),
+    op(OP_CTZ, Form::Unary, 0x6010_1013),
+    op(OP_CPOP, Form::Unary, 0x6020_1013),
+    rv64(OP_CLZW, Form::Unary, 0x6000_101b),
+    rv64(OP_CTZW, Form::Unary, 0x6010_101b),
+    rv64(OP_CPOPW, Form::Unary, 0x6020_101b),
+    op(OP_MAX, Form::R, 0x0a00_6033),
+    op(OP_MAXU, Form::R, 0x0a00_7033),
+    op(OP_MIN, Form::R, 0x0a00_4033),
+    op(OP_MINU, Form::R, 0x0a00_5033),
+    op(OP_SEXT_B, Form::Unary, 0x6040_1013),
+    op(OP_SEXT_H, Form::Unary, 0x6050_1013),
+    rv32(OP_ZEXT_H, Form::Unary, 0x0800_4033),
+    rv64(OP_ZEXT_H, Form::Unary, 0x0800_403b),
+    op(OP_ROL, Form::R, 0x6000_1033),
+    op(OP_ROR, Form::R, 0x6000_5033),
+    op(OP_RORI, Form::Shift, 0x6000_5013),
+    rv64(OP_ROLW, Form::R, 0x6000_103b),
+    rv64(OP_RORW, Form::R, 0x6000_503b),
+    rv64(OP_RORIW, Form::ShiftW, 0x6000_501b),
+    op(OP_ORC_B, Form::Unary, 0x2870_5013),
+    rv32(OP_REV8, Form::Unary, 0x6980_5013),
+    rv64(OP_REV8, Form::Unary, 0x6b80_5013),
+    // Zbs
+    op(OP_BCLR, Form::R, 0x4800_1033),
+    op(OP_BCLRI, Form::Shift, 0x4800_1013),
+    op(OP_BEXT, Form::R, 0x4800_5033),
+    op(OP_BEXTI, Form::Shift, 0x4800_5013),
+    op(OP_BINV, Form::R, 0x6800_1033),
+    op(OP_BINVI, Form::Shift, 0x6800_1013),
+    op(OP_BSET, Form::R, 0x2800_1033),
+    op(OP_BSETI, Form::Shift, 0x2800_1013),
+    // Zbkb
+    op(OP_PACK, Form::R, 0x0800_4033),
+    op(OP_PACKH, Form::R, 0x0800_7033),
+    rv64(OP_PACKW, Form::R, 0x0800_403b),
+    op(OP_BREV8, Form::Unary, 0x6870_5013),
+    rv32(OP_ZIP, Form::Unary, 0x08f0_1013),
+    rv32(OP_UNZIP, Form::Unary, 0x08f0_5013),
+    // Zicond
+    op(OP_CZERO_EQZ, Form::R, 0x0e00_5033),
+    op(OP_CZERO_NEZ, Form::R, 0x0e00_7033),
+];
@@ -0,0 +1,220 @@
+//! Hand-assembled guests for the integration tests.
+//!
+//! Base opcodes and the function fields the tests share, a few instruction
+//! helpers, guest text with checks that branch to a failure exit, an ELF
+//! builder for a text segment, a compile helper and a writer to capture
+//! guest output in.
+
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use rvr_elf::{ElfWriter, PF_R, PF_X};
+use rvr_isa::{
+    REG_A0, REG_A7, REG_S11, REG_T1, REG_ZERO, Xlen, encode_b, encode_i, encode_j, encode_s,
+    encode_u,
+};
+
+use crate::{CompileOptions, SyscallMode};
+
+pub const OPCODE_LOAD: u8 = 0b000_0011;
+pub const OPCODE_LOAD_FP: u8 = 0b000_0111;
+pub const OPCODE_OP_IMM: u8 = 0b001_0011;
+pub const OPCODE_AUIPC: u8 = 0b001_0111;
+pub const OPCODE_OP_IMM_32: u8 = 0b001_1011;
+pub const OPCODE_STORE: u8 = 0b010_0011;
+pub const OPCODE_STORE_FP: u8 = 0b010_0111;
+pub const OPCODE_AMO: u8 = 0b010_1111;
+pub const OPCODE_OP: u8 = 0b011_0011;
+pub const OPCODE_LUI: u8 = 0b011_0111;
+pub const OPCODE_OP_32: u8 = 0b011_1011;
+pub const OPCODE_OP_V: u8 = 0b101_0111;
+pub const OPCODE_BRANCH: u8 = 0b110_0011;
+pub const OPCODE_JALR: u8 = 0b110_0111;
+pub const OPCODE_JAL: u8 = 0b110_1111;
+pub const OPCODE_SYSTEM: u8 = 0b111_0011;
+
+pub const FUNCT3_ADD: u8 = 0b000;
+pub const FUNCT3_ADDI: u8 = 0b000;
+pub const FUNCT3_SLL: u8 = 0b001;
+pub const FUNCT3_SLLI: u8 = 0b001;
+pub const FUNCT3_BEQ: u8 = 0b000;
+pub const FUNCT3_BNE: u8 = 0b001;
+pub const FUNCT3_BLT: u8 = 0b100;
+pub const FUNCT3_BLTU: u8 = 0b110;
+pub const FUNCT3_BGEU: u8 = 0b111;
+pub const FUNCT3_XOR: u8 = 0b100;
+pub const FUNCT3_SRLI: u8 = 0b101;
+/// Width of a byte load or store.
+pub const FUNCT3_B: u8 = 0b000;
+/// Width of a word load or store.
+pub const FUNCT3_W: u8 = 0b010;
+/// Width of a doubleword load or store.
+pub const FUNCT3_D: u8 = 0b011;
+/// Width of an unsigned byte load.
+pub const FUNCT3_BU: u8 = 0b100;
+pub const FUNCT3_SB: u8 = 0b000;
+pub const FUNCT3_SW: u8 = 0b010;
+pub const FUNCT3_SD: u8 = 0b011;
+pub const FUNCT3_LD: u8 = 0b011;
+pub const FUNCT3_CSRRW: u8 = 0b001;
+pub const FUNCT3_CSRRS: u8 = 0b010;
+pub const FUNCT7_SUB: u8 = 0b010_0000;
+pub const FUNCT7_MULDIV: u8 = 0b000_0001;
+
+pub const ECALL: u32 = encode_i(OPCODE_SYSTEM, REG_ZERO, 0, REG_ZERO, 0);
+
+/// Index of the failure exit of a [`Text`], which exits with the step in
+/// `s11`.
+pub const FAIL: usize = 1;
+
+#[must_use]
+pub const fn addi(rd: u8, rs1: u8, imm: i32) -> u32 {
+    encode_i(OPCODE_OP_IMM, rd, FUNCT3_ADDI, rs1, imm)
+}
+
+#[must_use]
+pub const fn ld(rd: u8, rs1: u8, imm: i32) -> u32 {
+    encode_i(OPCODE_LOAD, rd, FUNCT3_D, rs1, imm)
+}
+
+#[must_use]
+pub const fn sd(rs2: u8, rs1: u8, imm: i32) -> u32 {
+    encode_s(OPCODE_STORE, FUNCT3_D, rs1, rs2, imm)
+}
+
+/// `lui` of the upper 20 bits of `value`.
+///
+/// # Panics
+/// Panics if `value` is 2^44 or more.
+#[must_use]
+pub fn lui(rd: u8, value: u64) -> u32 {
+    encode_u(OPCODE_LUI, rd, u32::try_from(value >> 12).unwrap())
+}
+
+/// `addi` of a 12-bit `value` from zero.
+///
+/// # Panics
+/// Panics if `value` does not fit the sign-extended immediate.
+#[must_use]
+pub const fn li(rd: u8, value: u64) -> u32 {
+    assert!(value < 1 << 11, "li immediate must be below 2048");
+    #[allow(clippy::cast_possible_truncation)] // checked above
+    addi(rd, REG_ZERO, value as i32)
+}
+
+/// Instruction offset from `from` to `to`, in bytes.
+///
+/// # Panics
+/// Panics if either index does not fit an `i32`.
+#[must_use]
+pub fn offset(from: usize, to: usize) -> i32 {
+    (i32::try_from(to).unwrap() - i32::try_from(from).unwrap()) * 4
+}
+
+/// Guest text, with checks that branch back to the failure exit at
+/// [`FAIL`].
+pub struct Text(pub Vec<u32>);
+
+impl Text {
+    /// Text that jumps over a failure exit calling syscall `exit` with the
+    /// step in `s11`.
+    #[must_use]
+    pub fn new(exit: u64) -> Self {
+        Self(vec![
+            encode_j(OPCODE_JAL, REG_ZERO, 16),
+            addi(REG_A0, REG_S11, 0),
+            li(REG_A7, exit),
+            ECALL,
+        ])
+    }
+
+    pub fn push(&mut self, insns: impl IntoIterator<Item = u32>) {
+        self.0.extend(insns);
+    }
+
+    /// Exit with `step` if `rs1 <funct3> rs2` holds.
+    pub fn fail_if(&mut self, step: i32, funct3: u8, rs1: u8, rs2: u8) {
+        self.push([addi(REG_S11, REG_ZERO, step)]);
+        let at = self.0.len();
+        self.push([encode_b(OPCODE_BRANCH, funct3, rs1, rs2, offset(at, FAIL))]);
+    }
+
+    /// Exit with `step` unless `a0 == expected`. Clobbers `t1`.
+    pub fn expect_a0(&mut self, step: i32, expected: i32) {
+        self.push([addi(REG_T1, REG_ZERO, expected)]);
+        self.fail_if(step, FUNCT3_BNE, REG_A0, REG_T1);
+    }
+}
+
+/// Little-endian bytes of `insns`.
+#[must_use]
+pub fn code(insns: &[u32]) -> Vec<u8> {
+    insns.iter().flat_map(|i| i.to_le_bytes()).collect()
+}
+
+/// Executable entering at `text`, with `insns` as a read/execute segment
+/// there.
+#[must_use]
+pub fn text_elf<X: Xlen>(text: u64, insns: &[u32]) -> ElfWriter<X> {
+    ElfWriter::<X>::new(text).with_segment(text, PF_R | PF_X, code(insns))
+}
+
+/// A guest compiled by [`compile`].
+pub struct Compiled {
+    /// The guest ELF.
+    pub elf: PathBuf,
+    /// The output directory holding the library.
+    pub out: PathBuf,
+}
+
+/// Write `elf` to `dir/<name>.elf` and compile it quietly into
+/// `dir/<name>` with `options`.
+///
+/// # Panics
+/// Panics if writing or compiling fails.
+#[must_use]
+pub fn compile(dir: &Path, name: &str, elf: &[u8], options: &CompileOptions) -> Compiled {
+    let elf_path = dir.join(format!("{name}.elf"));
+    std::fs::write(&elf_path, elf).expect("write ELF");
+    let out = dir.join(name);
+    let options = options.clone().with_quiet(true);
+    crate::compile_with_options(&elf_path, &out, &options).expect("compile");
+    Compiled { elf: elf_path, out }
+}
+
+/// [`compile`] with Linux syscalls.
+///
+/// # Panics
+/// Panics if writing or compiling fails.
+#[must_use]
+pub fn compile_linux(dir: &Path, name: &str, elf: &[u8]) -> Compiled {
+    let options = CompileOptions::new().with_syscall_mode(SyscallMode::Linux);
+    compile(dir, name, elf, &options)
+}
+
+/// Writer whose contents stay readable after it is handed off.
+#[derive(Clone, Default)]
+pub struct SharedWriter(Arc<Mutex<Vec<u8>>>);
+
+impl SharedWriter {
+    /// Everything written so far.
+    ///
+    /// # Panics
+    /// Panics if a writer panicked while holding the buffer.
+    #[must_use]
+    pub fn contents(&self) -> Vec<u8> {
+        self.0.lock().expect("lock").clone()
+    }
+}
+
+impl Write for SharedWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().expect("lock").extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -1,4 +1,7 @@
 //! Test support utilities for integration tests and CLI wrappers.
 
+pub mod address_modes;
 pub mod diff;
+pub mod fuzz;
+pub mod guest;
 pub mod trace;
@@ -0,0 +1,286 @@
+//! Binary trace files, as written by the `binary-trace` tracer.
+//!
+//! A file starts with a [`TraceHeader`] (magic, version, xlen and the hash
+//! of the traced ELF) followed by one fixed-size little-endian record per
+//! retired instruction (48 bytes for RV64, 32 for RV32), which read back
+//! without any text parsing.
+
+use std::fs::File;
+use std::io::{self, BufReader, Read, Write};
+use std::path::Path;
+
+use rvr_emit::c::{
+    BINARY_TRACE_HEADER_SIZE, BINARY_TRACE_MAGIC, BINARY_TRACE_VERSION, TRACE_FLAG_CSR,
+    TRACE_FLAG_MEM, TRACE_FLAG_RD, binary_trace_record_size,
+};
+
+use super::TraceEntry;
+
+/// Header of a binary trace file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceHeader {
+    /// Format version (`BINARY_TRACE_VERSION`).
+    pub version: u32,
+    /// Register width of the traced guest (32 or 64).
+    pub xlen: u8,
+    /// Content hash of the traced ELF.
+    pub elf_hash: u64,
+}
+
+/// One record of a binary trace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceRecord {
+    /// 1-based index of the instruction in the trace.
+    pub instret: u64,
+    /// The traced instruction.
+    pub entry: TraceEntry,
+}
+
+/// Whether `path` starts with the binary trace magic.
+///
+/// # Errors
+/// Returns an error if the file cannot be opened or read.
+pub fn is_binary_trace(path: &Path) -> io::Result<bool> {
+    let mut magic = [0; BINARY_TRACE_MAGIC.len()];
+    let mut file = File::open(path)?;
+    match file.read_exact(&mut magic) {
+        Ok(()) => Ok(magic == BINARY_TRACE_MAGIC),
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+/// Streaming reader of a binary trace.
+///
+/// Iterates over the trace's entries; [`TraceReader::read_record`] also
+/// yields each entry's instret.
+pub struct TraceReader<R: Read> {
+    reader: R,
+    header: TraceHeader,
+    record: Vec<u8>,
+}
+
+impl TraceReader<BufReader<File>> {
+    /// Open the binary trace at `path`.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or has no valid header.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> TraceReader<R> {
+    /// Read the header from `reader`.
+    ///
+    /// # Errors
+    /// Returns `InvalidData` for a bad magic, an unknown version or an xlen
+    /// other than 32 or 64, and any error from reading.
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut header = [0; BINARY_TRACE_HEADER_SIZE];
+        reader.read_exact(&mut header)?;
+        let (magic, rest) = header.split_at(BINARY_TRACE_MAGIC.len());
+        if magic != BINARY_TRACE_MAGIC {
+            return Err(invalid("not a binary trace".to_string()));
+        }
+        let (version, rest) = rest.split_at(size_of::<u32>());
+        let (xlen, elf_hash) = rest.split_at(size_of::<u32>());
+        let version = u32::from_le_bytes(version.try_into().unwrap_or_default());
+        if version != BINARY_TRACE_VERSION {
+            return Err(invalid(format!(
+                "binary trace version {version}, expected {BINARY_TRACE_VERSION}"
+            )));
+        }
+        let xlen = match u32::from_le_bytes(xlen.try_into().unwrap_or_default()) {
+            32 => 32,
+            64 => 64,
+            xlen => return Err(invalid(format!("binary trace xlen {xlen}"))),
+        };
+        let elf_hash = u64::from_le_bytes(elf_hash.try_into().unwrap_or_default());
+        Ok(Self {
+            reader,
+            header: TraceHeader {
+                version,
+                xlen,
+                elf_hash,
+            },
+            record: vec![0; binary_trace_record_size(xlen)],
+        })
+    }
+
+    /// The trace's header.
+    #[must_use]
+    pub const fn header(&self) -> &TraceHeader {
+        &self.header
+    }
+
+    /// Read the next record, or `None` at the end of the trace.
+    ///
+    /// # Errors
+    /// Returns `UnexpectedEof` for a truncated record and any error from
+    /// reading.
+    pub fn read_record(&mut self) -> io::Result<Option<TraceRecord>> {
+        let read = read_full(&mut self.reader, &mut self.record)?;
+        if read == 0 {
+            return Ok(None);
+        }
+        if read < self.record.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "truncated binary trace record",
+            ));
+        }
+        Ok(Some(decode_record(&self.record, self.header.xlen)))
+    }
+}
+
+impl<R: Read> Iterator for TraceReader<R> {
+    type Item = io::Result<TraceEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record()
+            .transpose()
+            .map(|record| record.map(|record| record.entry))
+    }
+}
+
+/// Writer of binary traces, e.g. to convert a text trace.
+pub struct TraceWriter<W: Write> {
+    writer: W,
+    xlen: u8,
+    instret: u64,
+}
+
+impl<W: Write> TraceWriter<W> {
+    /// Write the header of a trace of an `xlen`-bit guest to `writer`.
+    ///
+    /// # Errors
+    /// Returns any error from writing.
+    pub fn new(mut writer: W, xlen: u8, elf_hash: u64) -> io::Result<Self> {
+        writer.write_all(&BINARY_TRACE_MAGIC)?;
+        writer.write_all(&BINARY_TRACE_VERSION.to_le_bytes())?;
+        writer.write_all(&u32::from(xlen).to_le_bytes())?;
+        writer.write_all(&elf_hash.to_le_bytes())?;
+        Ok(Self {
+            writer,
+            xlen,
+            instret: 0,
+        })
+    }
+
+    /// Append `entry` as the next record.
+    ///
+    /// # Errors
+    /// Returns any error from writing.
+    pub fn write(&mut self, entry: &TraceEntry) -> io::Result<()> {
+        self.instret += 1;
+        let mut flags = 0;
+        if entry.rd.is_some() {
+            flags |= TRACE_FLAG_RD;
+        }
+        if entry.mem_addr.is_some() {
+            flags |= TRACE_FLAG_MEM;
+        }
+        if entry.csr.is_some() {
+            flags |= TRACE_FLAG_CSR;
+        }
+        let mut record = Vec::with_capacity(binary_trace_record_size(self.xlen));
+        record.extend_from_slice(&self.instret.to_le_bytes());
+        for value in [
+            entry.pc,
+            entry.rd_value.unwrap_or(0),
+            entry.mem_addr.unwrap_or(0),
+            entry.csr_value.unwrap_or(0),
+        ] {
+            record.extend_from_slice(&value.to_le_bytes()[..reg_bytes(self.xlen)]);
+        }
+        record.extend_from_slice(&entry.opcode.to_le_bytes());
+        record.extend_from_slice(&entry.csr.unwrap_or(0).to_le_bytes());
+        record.push(entry.rd.unwrap_or(0));
+        record.push(flags);
+        self.writer.write_all(&record)
+    }
+
+    /// Flush and return the underlying writer.
+    ///
+    /// # Errors
+    /// Returns any error from flushing.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Decode one record of an `xlen`-bit trace.
+fn decode_record(record: &[u8], xlen: u8) -> TraceRecord {
+    let mut fields = Fields(record);
+    let instret = fields.u64(size_of::<u64>());
+    let pc = fields.u64(reg_bytes(xlen));
+    let rd_value = fields.u64(reg_bytes(xlen));
+    let mem_addr = fields.u64(reg_bytes(xlen));
+    let csr_value = fields.u64(reg_bytes(xlen));
+    let opcode = u32::from_le_bytes(fields.array());
+    let csr = u16::from_le_bytes(fields.array());
+    let [rd] = fields.array();
+    let [flags] = fields.array();
+    let has = |flag: u8| flags & flag != 0;
+    TraceRecord {
+        instret,
+        entry: TraceEntry {
+            pc,
+            opcode,
+            rd: has(TRACE_FLAG_RD).then_some(rd),
+            rd_value: has(TRACE_FLAG_RD).then_some(rd_value),
+            mem_addr: has(TRACE_FLAG_MEM).then_some(mem_addr),
+            csr: has(TRACE_FLAG_CSR).then_some(csr),
+            csr_value: has(TRACE_FLAG_CSR).then_some(csr_value),
+        },
+    }
+}
+
+/// Little-endian fields read off the front of a record.
+struct Fields<'a>(&'a [u8]);
+
+impl Fields<'_> {
+    const fn take(&mut self, len: usize) -> &[u8] {
+        let (field, rest) = self.0.split_at(len);
+        self.0 = rest;
+        field
+    }
+
+    const fn array<const N: usize>(&mut self) -> [u8; N] {
+        let mut bytes = [0; N];
+        bytes.copy_from_slice(self.take(N));
+        bytes
+    }
+
+    /// A zero-extended field of `len` bytes.
+    fn u64(&mut self, len: usize) -> u64 {
+        let mut bytes = [0; size_of::<u64>()];
+        bytes[..len].copy_from_slice(self.take(len));
+        u64::from_le_bytes(bytes)
+    }
+}
+
+/// Bytes of an XLEN-wide record field.
+fn reg_bytes(xlen: u8) -> usize {
+    usize::from(xlen) / u8::BITS as usize
+}
+
+/// Fill `buf` from `reader`, returning fewer bytes only at end of input.
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(filled)
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
@@ -13,6 +13,20 @@ const fn is_sc(opcode: u32) -> bool {
     op == 0x2f && funct5 == 0b00011
 }
 
+/// Check if an opcode is a Zicsr instruction on a clock counter (`cycle`,
+/// `time`, `mcycle` or their high halves).
+///
+/// These count differently in every implementation, so the value read (and
+/// any value written) is only compared with `strict_csrs`.
+#[must_use]
+pub const fn accesses_clock_csr(opcode: u32) -> bool {
+    let funct3 = (opcode >> 12) & 0x7;
+    if opcode & 0x7f != 0x73 || funct3 == 0 || funct3 == 4 {
+        return false;
+    }
+    matches!(opcode >> 20, 0xC00 | 0xC01 | 0xC80 | 0xC81 | 0xB00 | 0xB80)
+}
+
 /// Check if a PC is likely in the trap handler region.
 ///
 /// Uses the entry point to determine: trap handlers are typically placed
@@ -75,6 +89,9 @@ fn compare_same_pc(
     if let Some(step) = check_reg_dest_value(expected, actual, matched, config, first_divergence) {
         return step;
     }
+    if let Some(step) = check_csr(expected, actual, matched, config, first_divergence) {
+        return step;
+    }
     if let Some(step) = check_mem_access(expected, actual, matched, config, first_divergence) {
         return step;
     }
@@ -167,7 +184,8 @@ fn check_reg_dest_value(
             first_divergence,
         ));
     }
-    if expected.rd_value != actual.rd_value && !is_sc(expected.opcode) {
+    let clock = !config.strict_csrs && accesses_clock_csr(expected.opcode);
+    if expected.rd_value != actual.rd_value && !is_sc(expected.opcode) && !clock {
         return Some(divergence_step(
             config,
             matched,
@@ -180,6 +198,36 @@ fn check_reg_dest_value(
     None
 }
 
+/// Compare CSR writes logged on both sides.
+///
+/// A write logged on one side only is tolerated: Spike also logs CSRs an
+/// instruction changes as a side effect, which rvr does not trace.
+fn check_csr(
+    expected: &TraceEntry,
+    actual: &TraceEntry,
+    matched: usize,
+    config: &CompareConfig,
+    first_divergence: &mut Option<TraceDivergence>,
+) -> Option<CompareStep> {
+    if expected.csr.is_none() || actual.csr.is_none() {
+        return None;
+    }
+    if !config.strict_csrs && accesses_clock_csr(expected.opcode) {
+        return None;
+    }
+    if expected.csr != actual.csr || expected.csr_value != actual.csr_value {
+        return Some(divergence_step(
+            config,
+            matched,
+            expected,
+            actual,
+            DivergenceKind::CsrValue,
+            first_divergence,
+        ));
+    }
+    None
+}
+
 fn check_mem_access(
     expected: &TraceEntry,
     actual: &TraceEntry,
@@ -1,8 +1,10 @@
 //! Trace comparison for differential testing.
 //!
-//! Compares instruction traces between rvr and Spike (the RISC-V reference simulator)
-//! to catch bugs at the instruction level rather than just end-state.
+//! Compares instruction traces between rvr and a reference simulator (Spike,
+//! or QEMU with its execlog plugin) to catch bugs at the instruction level
+//! rather than just end-state.
 
+mod binary;
 mod compare;
 mod parse;
 mod util;
@@ -10,10 +12,12 @@ mod util;
 #[cfg(test)]
 mod tests;
 
-pub use compare::{align_traces_at, compare_traces_with_config};
-pub use parse::parse_trace_file;
+pub use binary::{TraceHeader, TraceReader, TraceRecord, TraceWriter, is_binary_trace};
+pub use compare::{accesses_clock_csr, align_traces_at, compare_traces_with_config};
+pub use parse::{parse_spike_csr_write, parse_trace_file, parse_trace_file_with_format};
 pub use util::{
-    elf_entry_point, elf_to_isa, find_spike, isa_from_test_name, run_command_with_timeout,
+    elf_entry_point, elf_to_isa, find_qemu, find_spike, isa_from_test_name,
+    run_command_with_timeout,
 };
 
 use std::fmt;
@@ -31,6 +35,29 @@ pub struct TraceEntry {
     pub rd_value: Option<u64>,
     /// Memory address accessed (if any).
     pub mem_addr: Option<u64>,
+    /// CSR written (if any).
+    pub csr: Option<u16>,
+    /// Value written to the CSR (if any).
+    pub csr_value: Option<u64>,
+}
+
+/// Trace file format of a reference simulator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TraceFormat {
+    /// Spike `--log-commits` output (also what rvr's spike tracer writes).
+    #[default]
+    Spike,
+    /// QEMU `-plugin libexeclog.so,reg=*` output.
+    QemuExeclog,
+}
+
+impl fmt::Display for TraceFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Spike => write!(f, "spike"),
+            Self::QemuExeclog => write!(f, "qemu"),
+        }
+    }
 }
 
 /// Result of comparing two traces.
@@ -47,7 +74,7 @@ pub struct TraceComparison {
 pub struct TraceDivergence {
     /// Instruction index in the aligned stream where divergence occurred.
     pub index: usize,
-    /// Expected entry (from Spike).
+    /// Expected entry (from the reference simulator).
     pub expected: TraceEntry,
     /// Actual entry (from rvr).
     pub actual: TraceEntry,
@@ -68,6 +95,8 @@ pub enum DivergenceKind {
     RegValue,
     /// Memory address mismatch.
     MemAddr,
+    /// CSR write mismatch (different CSR or value).
+    CsrValue,
     /// Expected had register write, actual didn't.
     MissingRegWrite,
     /// Actual had register write, expected didn't.
@@ -90,6 +119,7 @@ impl fmt::Display for DivergenceKind {
             Self::RegDest => write!(f, "register destination mismatch"),
             Self::RegValue => write!(f, "register value mismatch"),
             Self::MemAddr => write!(f, "memory address mismatch"),
+            Self::CsrValue => write!(f, "CSR write mismatch"),
             Self::MissingRegWrite => write!(f, "missing register write in actual"),
             Self::ExtraRegWrite => write!(f, "extra register write in actual"),
             Self::MissingMemAccess => write!(f, "missing memory access in actual"),
@@ -102,6 +132,8 @@ impl fmt::Display for DivergenceKind {
 
 /// Configuration for trace comparison behavior.
 #[derive(Debug, Clone)]
+// Independent strictness switches, each set directly by a CLI flag
+#[allow(clippy::struct_excessive_bools)]
 pub struct CompareConfig {
     /// Entry point address for alignment (from ELF).
     pub entry_point: u64,
@@ -111,6 +143,9 @@ pub struct CompareConfig {
     /// Whether to require matching memory accesses (strict mode).
     /// If false, missing mem accesses on one side are tolerated.
     pub strict_mem_access: bool,
+    /// Whether to compare reads and writes of clock counters (`cycle`,
+    /// `time`), which normally differ between implementations.
+    pub strict_csrs: bool,
     /// Whether to stop on the first divergence.
     pub stop_on_first: bool,
 }
@@ -121,7 +156,24 @@ impl Default for CompareConfig {
             entry_point: 0x8000_0000,
             strict_reg_writes: true,
             strict_mem_access: false, // Spike doesn't always log mem for loads
+            strict_csrs: false,
             stop_on_first: true,
         }
     }
 }
+
+impl CompareConfig {
+    /// Default comparison for a reference trace in `format`.
+    ///
+    /// QEMU's execlog only logs registers whose value changed, so register
+    /// writes are not strict for it; memory checks are off for both.
+    #[must_use]
+    pub fn for_format(format: TraceFormat, entry_point: u64) -> Self {
+        let strict = format == TraceFormat::Spike;
+        Self {
+            entry_point,
+            strict_reg_writes: strict,
+            ..Self::default()
+        }
+    }
+}
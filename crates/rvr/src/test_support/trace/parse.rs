@@ -4,8 +4,21 @@ use std::path::Path;
 use std::sync::OnceLock;
 
 use regex::Regex;
+use rvr_isa::REG_ABI_NAMES;
 
-use super::TraceEntry;
+use super::binary::{TraceReader, is_binary_trace};
+use super::{TraceEntry, TraceFormat};
+
+impl TraceFormat {
+    /// Parse one trace line in this format.
+    #[must_use]
+    pub fn parse_line(self, line: &str) -> Option<TraceEntry> {
+        match self {
+            Self::Spike => TraceEntry::parse(line),
+            Self::QemuExeclog => TraceEntry::parse_qemu_execlog(line),
+        }
+    }
+}
 
 impl TraceEntry {
     /// Parse a Spike-format trace line using regex.
@@ -14,6 +27,8 @@ impl TraceEntry {
     /// - `core   0: 3 0x<PC> (0x<OPCODE>) [x<RD> 0x<VALUE>] [mem 0x<ADDR> [0x<VAL>]]`
     /// - `core   0: 0 0x<PC> (0x<OPCODE>) c<CSR>_name 0x<VALUE>`
     ///
+    /// See [`parse_spike_csr_write`] for which CSR write is taken.
+    ///
     /// Uses pattern matching rather than positional parsing to handle format variations.
     ///
     /// # Panics
@@ -56,28 +71,146 @@ impl TraceEntry {
             .captures(line)
             .and_then(|caps| u64::from_str_radix(caps.get(1)?.as_str(), 16).ok());
 
+        let (csr, csr_value) = parse_spike_csr_write(line, opcode).unzip();
+
+        Some(Self {
+            pc,
+            opcode,
+            rd,
+            rd_value,
+            mem_addr,
+            csr,
+            csr_value,
+        })
+    }
+
+    /// Parse a QEMU execlog plugin line.
+    ///
+    /// Format (`-plugin libexeclog.so,reg=*`, user mode):
+    /// - `<cpu>, 0x<PC>, 0x<OPCODE>, "<disasm>"[, load|store, 0x<ADDR>][, <reg> -> 0x<VALUE>]...`
+    ///
+    /// Registers are logged by ABI name (`a0`, `s0`/`fp`), and only when
+    /// their value changes; the first general-purpose register is taken as
+    /// `rd`. CSR changes (`mstatus -> 0x...`) are not register writes, and
+    /// are logged by name only, so no CSR write is recorded.
+    ///
+    /// # Panics
+    /// Panics if the internal regex patterns fail to compile (should be unreachable).
+    pub fn parse_qemu_execlog(line: &str) -> Option<Self> {
+        let insn_pattern = QEMU_INSN_PATTERN.get_or_init(|| {
+            Regex::new(r#"^\d+,\s*0x([0-9a-fA-F]+),\s*0x([0-9a-fA-F]+),\s*"[^"]*""#).unwrap()
+        });
+        let line = line.trim();
+        let caps = insn_pattern.captures(line)?;
+        let pc = u64::from_str_radix(caps.get(1)?.as_str(), 16).ok()?;
+        let opcode = u32::from_str_radix(caps.get(2)?.as_str(), 16).ok()?;
+        // Operands after the disassembly (which may itself contain commas)
+        let rest = &line[caps.get(0)?.end()..];
+
+        let reg_pattern = QEMU_REG_PATTERN
+            .get_or_init(|| Regex::new(r"\b(\w+)\s+->\s+0x([0-9a-fA-F]+)").unwrap());
+        let (rd, rd_value) = reg_pattern
+            .captures_iter(rest)
+            .find_map(|caps| {
+                let reg = gpr_index(caps.get(1)?.as_str())?;
+                let val = u64::from_str_radix(caps.get(2)?.as_str(), 16).ok()?;
+                (reg != 0).then_some((reg, val))
+            })
+            .unzip();
+
+        let mem_pattern = QEMU_MEM_PATTERN
+            .get_or_init(|| Regex::new(r"\b(?:load|store),\s*0x([0-9a-fA-F]+)").unwrap());
+        let mem_addr = mem_pattern
+            .captures(rest)
+            .and_then(|caps| u64::from_str_radix(caps.get(1)?.as_str(), 16).ok());
+
         Some(Self {
             pc,
             opcode,
             rd,
             rd_value,
             mem_addr,
+            csr: None,
+            csr_value: None,
+        })
+    }
+}
+
+/// Parse the CSR write of a Spike trace line: `c<CSR>_<name> 0x<VALUE>`,
+/// with the CSR number in decimal (`c773_mtvec`).
+///
+/// Spike also logs CSRs an instruction changes as a side effect (`mstatus`
+/// on `mret`), so a write to the CSR a Zicsr `opcode` names is preferred
+/// over the first one logged.
+///
+/// # Panics
+/// Panics if the internal regex pattern fails to compile (should be unreachable).
+#[must_use]
+pub fn parse_spike_csr_write(line: &str, opcode: u32) -> Option<(u16, u64)> {
+    let csr_pattern =
+        CSR_PATTERN.get_or_init(|| Regex::new(r"\bc(\d+)_\w+\s+0x([0-9a-fA-F]+)").unwrap());
+    let named = (opcode & 0x7f == 0x73 && (opcode >> 12) & 0x7 != 0)
+        .then(|| u16::try_from(opcode >> 20).ok())
+        .flatten();
+    let writes: Vec<(u16, u64)> = csr_pattern
+        .captures_iter(line)
+        .filter_map(|caps| {
+            let csr = caps.get(1)?.as_str().parse::<u16>().ok()?;
+            let val = u64::from_str_radix(caps.get(2)?.as_str(), 16).ok()?;
+            Some((csr, val))
         })
+        .collect();
+    writes
+        .iter()
+        .find(|&&(csr, _)| Some(csr) == named)
+        .or_else(|| writes.first())
+        .copied()
+}
+
+/// Index of a general-purpose register named `x<N>` or by ABI name.
+fn gpr_index(name: &str) -> Option<u8> {
+    if name == "fp" {
+        return Some(8);
+    }
+    if let Some(num) = name.strip_prefix('x') {
+        return num.parse::<u8>().ok().filter(|&n| n < 32);
     }
+    REG_ABI_NAMES
+        .iter()
+        .position(|&abi| abi == name)
+        .and_then(|n| u8::try_from(n).ok())
 }
 
-/// Parse a trace file into entries.
+/// Parse a Spike trace file (or a binary trace) into entries.
 ///
 /// # Errors
 /// Returns an error if the trace file cannot be read.
 pub fn parse_trace_file(path: &Path) -> std::io::Result<Vec<TraceEntry>> {
+    parse_trace_file_with_format(path, TraceFormat::Spike)
+}
+
+/// Parse a trace file in the given format into entries.
+///
+/// Lines that are not instruction entries are skipped. Binary traces are
+/// recognized by their magic and read regardless of `format`.
+///
+/// # Errors
+/// Returns an error if the trace file cannot be read, or is a malformed
+/// binary trace.
+pub fn parse_trace_file_with_format(
+    path: &Path,
+    format: TraceFormat,
+) -> std::io::Result<Vec<TraceEntry>> {
+    if is_binary_trace(path)? {
+        return TraceReader::open(path)?.collect();
+    }
     let file = File::open(path)?;
     let reader = BufReader::new(file);
     let mut entries = Vec::new();
 
     for line in reader.lines() {
         let line = line?;
-        if let Some(entry) = TraceEntry::parse(&line) {
+        if let Some(entry) = format.parse_line(&line) {
             entries.push(entry);
         }
     }
@@ -88,3 +221,7 @@ pub fn parse_trace_file(path: &Path) -> std::io::Result<Vec<TraceEntry>> {
 static PC_PATTERN: OnceLock<Regex> = OnceLock::new();
 static REG_PATTERN: OnceLock<Regex> = OnceLock::new();
 static MEM_PATTERN: OnceLock<Regex> = OnceLock::new();
+static CSR_PATTERN: OnceLock<Regex> = OnceLock::new();
+static QEMU_INSN_PATTERN: OnceLock<Regex> = OnceLock::new();
+static QEMU_REG_PATTERN: OnceLock<Regex> = OnceLock::new();
+static QEMU_MEM_PATTERN: OnceLock<Regex> = OnceLock::new();
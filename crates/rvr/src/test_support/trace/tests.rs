@@ -58,6 +58,26 @@ fn test_parse_trace_entry_with_csr() {
     assert_eq!(entry.opcode, 0x3052_9073);
     // CSR write is not parsed as xN, so rd should be None
     assert_eq!(entry.rd, None);
+    // Spike numbers CSRs in decimal: 773 is mtvec (0x305)
+    assert_eq!(entry.csr, Some(0x305));
+    assert_eq!(entry.csr_value, Some(0x8000_00e4));
+}
+
+#[test]
+fn test_parse_trace_entry_prefers_named_csr() {
+    // `csrrw a0, mscratch, a1` with a side-effect write logged first
+    let line = "core   0: 3 0x80000100 (0x34059573) x10 0x0000000000000000 \
+                c768_mstatus 0x0000000000001800 c832_mscratch 0x0000000000000007";
+    let entry = TraceEntry::parse(line).unwrap();
+
+    assert_eq!(entry.rd, Some(10));
+    assert_eq!(entry.csr, Some(0x340));
+    assert_eq!(entry.csr_value, Some(7));
+
+    // Not a Zicsr instruction: the first write logged is taken
+    let line = "core   0: 3 0x80000104 (0x30200073) c768_mstatus 0x0000000000000080";
+    let entry = TraceEntry::parse(line).unwrap();
+    assert_eq!(entry.csr, Some(0x300));
 }
 
 #[test]
@@ -72,6 +92,104 @@ fn test_parse_trace_entry_priv_level_0() {
     assert_eq!(entry.rd_value, Some(0x337));
 }
 
+#[test]
+fn test_parse_trace_entry_compressed() {
+    // Compressed instructions are logged with their 16-bit encoding
+    let line = "core   0: 3 0x0000000080000004 (0x4501) x10 0x0000000000000000";
+    let entry = TraceFormat::Spike.parse_line(line).unwrap();
+
+    assert_eq!(entry.pc, 0x8000_0004);
+    assert_eq!(entry.opcode, 0x4501);
+    assert_eq!(entry.rd, Some(10));
+    assert_eq!(entry.rd_value, Some(0));
+}
+
+#[test]
+fn test_parse_qemu_execlog_entry() {
+    let line = r#"0, 0x10078, 0x00000297, "auipc t0,0", t0 -> 0x0000000000010078"#;
+    let entry = TraceFormat::QemuExeclog.parse_line(line).unwrap();
+
+    assert_eq!(entry.pc, 0x10078);
+    assert_eq!(entry.opcode, 0x0000_0297);
+    assert_eq!(entry.rd, Some(5));
+    assert_eq!(entry.rd_value, Some(0x10078));
+    assert_eq!(entry.mem_addr, None);
+}
+
+#[test]
+fn test_parse_qemu_execlog_entry_with_mem() {
+    let line = r#"0, 0x100a4, 0x0082b283, "ld t0,8(t0)", load, 0x11008, t0 -> 0x0000000000000042"#;
+    let entry = TraceFormat::QemuExeclog.parse_line(line).unwrap();
+
+    assert_eq!(entry.pc, 0x100a4);
+    assert_eq!(entry.rd, Some(5));
+    assert_eq!(entry.rd_value, Some(0x42));
+    assert_eq!(entry.mem_addr, Some(0x11008));
+
+    let line = r#"0, 0x100a8, 0x00a13023, "sd a0,0(sp)", store, 0x40007ff0"#;
+    let entry = TraceFormat::QemuExeclog.parse_line(line).unwrap();
+    assert_eq!(entry.rd, None);
+    assert_eq!(entry.mem_addr, Some(0x4000_7ff0));
+}
+
+#[test]
+fn test_parse_qemu_execlog_entry_compressed() {
+    // Registers by ABI name, including the `fp` alias of s0
+    let line = r#"0, 0x100b0, 0x4501, "c.li a0,0", a0 -> 0x0000000000000000"#;
+    let entry = TraceFormat::QemuExeclog.parse_line(line).unwrap();
+    assert_eq!(entry.opcode, 0x4501);
+    assert_eq!(entry.rd, Some(10));
+    assert_eq!(entry.rd_value, Some(0));
+
+    let line = r#"0, 0x100b2, 0x1000, "c.addi4spn fp,sp,32", fp -> 0x0000000040008010"#;
+    let entry = TraceFormat::QemuExeclog.parse_line(line).unwrap();
+    assert_eq!(entry.opcode, 0x1000);
+    assert_eq!(entry.rd, Some(8));
+}
+
+#[test]
+fn test_parse_qemu_execlog_entry_with_csr() {
+    // CSR changes are not register writes
+    let line = r#"0, 0x100c0, 0x30529073, "csrw mtvec,t0", mtvec -> 0x00000000000100e4"#;
+    let entry = TraceFormat::QemuExeclog.parse_line(line).unwrap();
+    assert_eq!(entry.opcode, 0x3052_9073);
+    assert_eq!(entry.rd, None);
+    assert_eq!(entry.rd_value, None);
+
+    // ... but the GPR written alongside one is
+    let line = r#"0, 0x100c4, 0x00202573, "csrr a0,frm", a0 -> 0x0000000000000002"#;
+    let entry = TraceFormat::QemuExeclog.parse_line(line).unwrap();
+    assert_eq!(entry.rd, Some(10));
+    assert_eq!(entry.rd_value, Some(2));
+}
+
+#[test]
+fn test_parse_qemu_execlog_skips_other_lines() {
+    assert!(TraceFormat::QemuExeclog.parse_line("").is_none());
+    assert!(
+        TraceFormat::QemuExeclog
+            .parse_line("core   0: 3 0x80000000 (0x0500006f)")
+            .is_none()
+    );
+    assert!(
+        TraceFormat::Spike
+            .parse_line(r#"0, 0x10078, 0x00000297, "auipc t0,0""#)
+            .is_none()
+    );
+}
+
+#[test]
+fn test_compare_config_for_format() {
+    let spike = CompareConfig::for_format(TraceFormat::Spike, 0x8000_0000);
+    assert!(spike.strict_reg_writes);
+    assert!(!spike.strict_mem_access);
+
+    let qemu = CompareConfig::for_format(TraceFormat::QemuExeclog, 0x10078);
+    assert_eq!(qemu.entry_point, 0x10078);
+    assert!(!qemu.strict_reg_writes);
+    assert!(!qemu.strict_mem_access);
+}
+
 #[test]
 fn test_compare_traces_match() {
     let traces = vec![
@@ -81,6 +199,8 @@ fn test_compare_traces_match() {
             rd: None,
             rd_value: None,
             mem_addr: None,
+            csr: None,
+            csr_value: None,
         },
         TraceEntry {
             pc: 0x8000_0050,
@@ -88,6 +208,8 @@ fn test_compare_traces_match() {
             rd: Some(1),
             rd_value: Some(0),
             mem_addr: None,
+            csr: None,
+            csr_value: None,
         },
     ];
 
@@ -104,6 +226,8 @@ fn test_compare_traces_missing_reg_strict() {
         rd: Some(1),
         rd_value: Some(0),
         mem_addr: None,
+        csr: None,
+        csr_value: None,
     }];
 
     let actual = vec![TraceEntry {
@@ -112,6 +236,8 @@ fn test_compare_traces_missing_reg_strict() {
         rd: None, // Missing!
         rd_value: None,
         mem_addr: None,
+        csr: None,
+        csr_value: None,
     }];
 
     let config = CompareConfig {
@@ -134,6 +260,8 @@ fn test_compare_traces_missing_reg_lenient() {
         rd: Some(1),
         rd_value: Some(0),
         mem_addr: None,
+        csr: None,
+        csr_value: None,
     }];
 
     let actual = vec![TraceEntry {
@@ -142,6 +270,8 @@ fn test_compare_traces_missing_reg_lenient() {
         rd: None,
         rd_value: None,
         mem_addr: None,
+        csr: None,
+        csr_value: None,
     }];
 
     let config = CompareConfig {
@@ -161,6 +291,8 @@ fn test_compare_traces_diverge_value() {
         rd: Some(1),
         rd_value: Some(0),
         mem_addr: None,
+        csr: None,
+        csr_value: None,
     }];
 
     let actual = vec![TraceEntry {
@@ -169,6 +301,8 @@ fn test_compare_traces_diverge_value() {
         rd: Some(1),
         rd_value: Some(42), // Different!
         mem_addr: None,
+        csr: None,
+        csr_value: None,
     }];
 
     let result = compare_traces_with_config(&expected, &actual, &CompareConfig::default());
@@ -188,6 +322,8 @@ fn test_align_traces_at_entry() {
             rd: None,
             rd_value: None,
             mem_addr: None,
+            csr: None,
+            csr_value: None,
         },
         TraceEntry {
             pc: 0x1004,
@@ -195,6 +331,8 @@ fn test_align_traces_at_entry() {
             rd: None,
             rd_value: None,
             mem_addr: None,
+            csr: None,
+            csr_value: None,
         },
         TraceEntry {
             pc: 0x8000_0000,
@@ -202,6 +340,8 @@ fn test_align_traces_at_entry() {
             rd: None,
             rd_value: None,
             mem_addr: None,
+            csr: None,
+            csr_value: None,
         },
     ];
 
@@ -211,6 +351,8 @@ fn test_align_traces_at_entry() {
         rd: None,
         rd_value: None,
         mem_addr: None,
+        csr: None,
+        csr_value: None,
     }];
 
     let (aligned_spike, aligned_rvr) = align_traces_at(&spike, &rvr, 0x8000_0000);
@@ -228,6 +370,8 @@ fn test_compare_traces_expected_tail() {
             rd: None,
             rd_value: None,
             mem_addr: None,
+            csr: None,
+            csr_value: None,
         },
         TraceEntry {
             pc: 0x8000_0004,
@@ -235,6 +379,8 @@ fn test_compare_traces_expected_tail() {
             rd: None,
             rd_value: None,
             mem_addr: None,
+            csr: None,
+            csr_value: None,
         },
     ];
     let actual = vec![TraceEntry {
@@ -243,6 +389,8 @@ fn test_compare_traces_expected_tail() {
         rd: None,
         rd_value: None,
         mem_addr: None,
+        csr: None,
+        csr_value: None,
     }];
 
     let result = compare_traces_with_config(&expected, &actual, &CompareConfig::default());
@@ -261,6 +409,8 @@ fn test_compare_traces_actual_tail() {
         rd: None,
         rd_value: None,
         mem_addr: None,
+        csr: None,
+        csr_value: None,
     }];
     let actual = vec![
         TraceEntry {
@@ -269,6 +419,8 @@ fn test_compare_traces_actual_tail() {
             rd: None,
             rd_value: None,
             mem_addr: None,
+            csr: None,
+            csr_value: None,
         },
         TraceEntry {
             pc: 0x8000_0004,
@@ -276,6 +428,8 @@ fn test_compare_traces_actual_tail() {
             rd: None,
             rd_value: None,
             mem_addr: None,
+            csr: None,
+            csr_value: None,
         },
     ];
 
@@ -296,6 +450,8 @@ fn test_compare_traces_stop_on_first_false_records_first_divergence() {
             rd: None,
             rd_value: None,
             mem_addr: None,
+            csr: None,
+            csr_value: None,
         },
         TraceEntry {
             pc: 0x8000_0004,
@@ -303,6 +459,8 @@ fn test_compare_traces_stop_on_first_false_records_first_divergence() {
             rd: Some(1),
             rd_value: Some(1),
             mem_addr: None,
+            csr: None,
+            csr_value: None,
         },
     ];
 
@@ -313,6 +471,8 @@ fn test_compare_traces_stop_on_first_false_records_first_divergence() {
             rd: None,
             rd_value: None,
             mem_addr: None,
+            csr: None,
+            csr_value: None,
         },
         TraceEntry {
             pc: 0x8000_0004,
@@ -320,6 +480,8 @@ fn test_compare_traces_stop_on_first_false_records_first_divergence() {
             rd: Some(1),
             rd_value: Some(2),
             mem_addr: None,
+            csr: None,
+            csr_value: None,
         },
     ];
 
@@ -334,3 +496,155 @@ fn test_compare_traces_stop_on_first_false_records_first_divergence() {
         DivergenceKind::RegValue
     );
 }
+
+/// A trace of `count` instructions where the one at `csr_index` is
+/// `opcode`, writing `csr_value` to `csr`.
+fn trace_with_csr_write(
+    count: usize,
+    csr_index: usize,
+    opcode: u32,
+    csr: u16,
+    csr_value: u64,
+) -> Vec<TraceEntry> {
+    (0..count)
+        .map(|i| {
+            let is_csr = i == csr_index;
+            TraceEntry {
+                pc: 0x8000_0000 + 4 * i as u64,
+                opcode: if is_csr { opcode } else { 0x0000_0013 },
+                rd: None,
+                rd_value: None,
+                mem_addr: None,
+                csr: is_csr.then_some(csr),
+                csr_value: is_csr.then_some(csr_value),
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn test_compare_traces_csr_value() {
+    // `csrw minstret, a0` after 16 instructions: the sides disagree on the
+    // instruction count written back
+    let expected = trace_with_csr_write(32, 16, 0xb025_1073, 0xb02, 16);
+    let actual = trace_with_csr_write(32, 16, 0xb025_1073, 0xb02, 17);
+
+    let result = compare_traces_with_config(&expected, &actual, &CompareConfig::default());
+    let divergence = result.divergence.unwrap();
+    assert_eq!(divergence.kind, DivergenceKind::CsrValue);
+    assert_eq!(divergence.index, 16);
+    assert_eq!(result.matched, 16);
+
+    // A write logged on one side only is tolerated
+    let mut actual = expected.clone();
+    actual[16].csr = None;
+    actual[16].csr_value = None;
+    let result = compare_traces_with_config(&expected, &actual, &CompareConfig::default());
+    assert!(result.divergence.is_none());
+}
+
+#[test]
+fn test_compare_traces_clock_csr() {
+    // `csrrw a0, mcycle, a0`: the cycle count read into a0 differs
+    let mut expected = trace_with_csr_write(4, 2, 0xb005_1573, 0xb00, 0);
+    let mut actual = expected.clone();
+    expected[2].rd = Some(10);
+    expected[2].rd_value = Some(1000);
+    actual[2].rd = Some(10);
+    actual[2].rd_value = Some(12);
+
+    let result = compare_traces_with_config(&expected, &actual, &CompareConfig::default());
+    assert!(result.divergence.is_none());
+
+    let config = CompareConfig {
+        strict_csrs: true,
+        ..Default::default()
+    };
+    let result = compare_traces_with_config(&expected, &actual, &config);
+    assert_eq!(result.divergence.unwrap().kind, DivergenceKind::RegValue);
+}
+
+/// Spike `--log-commits` trace of the start of `rv64ui-p-ld`.
+const RV64UI_P_LD_LOG: &str = include_str!("testdata/rv64ui-p-ld.log");
+
+fn to_binary(entries: &[TraceEntry], xlen: u8) -> Vec<u8> {
+    let mut writer = TraceWriter::new(Vec::new(), xlen, 0x1234).unwrap();
+    for entry in entries {
+        writer.write(entry).unwrap();
+    }
+    writer.finish().unwrap()
+}
+
+#[test]
+fn test_binary_trace_round_trip() {
+    let entries: Vec<TraceEntry> = RV64UI_P_LD_LOG
+        .lines()
+        .filter_map(TraceEntry::parse)
+        .collect();
+    assert_eq!(entries.len(), RV64UI_P_LD_LOG.lines().count());
+
+    let binary = to_binary(&entries, 64);
+    assert!(binary.len() < RV64UI_P_LD_LOG.len());
+
+    let reader = TraceReader::new(binary.as_slice()).unwrap();
+    assert_eq!(
+        *reader.header(),
+        TraceHeader {
+            version: rvr_emit::c::BINARY_TRACE_VERSION,
+            xlen: 64,
+            elf_hash: 0x1234,
+        }
+    );
+    let decoded: Vec<TraceEntry> = reader.collect::<std::io::Result<_>>().unwrap();
+    assert_eq!(decoded, entries);
+}
+
+#[test]
+fn test_binary_trace_records() {
+    let entries: Vec<TraceEntry> = RV64UI_P_LD_LOG
+        .lines()
+        .filter_map(TraceEntry::parse)
+        .collect();
+    let binary = to_binary(&entries, 32);
+    let mut reader = TraceReader::new(binary.as_slice()).unwrap();
+
+    let first = reader.read_record().unwrap().unwrap();
+    assert_eq!(first.instret, 1);
+    assert_eq!(first.entry, entries[0]);
+    let second = reader.read_record().unwrap().unwrap();
+    assert_eq!(second.instret, 2);
+    assert_eq!(second.entry, entries[1]);
+}
+
+#[test]
+fn test_binary_trace_truncated() {
+    let entries = vec![TraceEntry::parse("core   0: 3 0x80000000 (0x0500006f)").unwrap()];
+    let binary = to_binary(&entries, 64);
+
+    let mut reader = TraceReader::new(&binary[..binary.len() - 1]).unwrap();
+    let err = reader.read_record().unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+
+    let err = TraceReader::new(&b"core   0: 3 0x80000000 (0x0500006f)"[..])
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_parse_trace_file_sniffs_binary() {
+    let entries: Vec<TraceEntry> = RV64UI_P_LD_LOG
+        .lines()
+        .filter_map(TraceEntry::parse)
+        .collect();
+    let dir = tempfile::tempdir().unwrap();
+    let text = dir.path().join("trace.log");
+    let binary = dir.path().join("trace.bin");
+    std::fs::write(&text, RV64UI_P_LD_LOG).unwrap();
+    std::fs::write(&binary, to_binary(&entries, 64)).unwrap();
+
+    assert!(!is_binary_trace(&text).unwrap());
+    assert!(is_binary_trace(&binary).unwrap());
+    assert_eq!(parse_trace_file(&binary).unwrap(), entries);
+    assert_eq!(parse_trace_file(&text).unwrap(), entries);
+}
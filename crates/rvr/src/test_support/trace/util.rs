@@ -32,9 +32,24 @@ pub fn run_command_with_timeout(
 /// Find Spike executable in PATH.
 #[must_use]
 pub fn find_spike() -> Option<PathBuf> {
+    find_in_path("spike")
+}
+
+/// Find the QEMU user-mode emulator for `isa` (`qemu-riscv32` or
+/// `qemu-riscv64`) in PATH.
+#[must_use]
+pub fn find_qemu(isa: &str) -> Option<PathBuf> {
+    if isa.starts_with("rv32") {
+        find_in_path("qemu-riscv32")
+    } else {
+        find_in_path("qemu-riscv64")
+    }
+}
+
+fn find_in_path(name: &str) -> Option<PathBuf> {
     std::env::var_os("PATH").and_then(|paths| {
         std::env::split_paths(&paths).find_map(|dir| {
-            let full_path = dir.join("spike");
+            let full_path = dir.join(name);
             if full_path.is_file() {
                 Some(full_path)
             } else {
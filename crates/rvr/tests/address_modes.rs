@@ -0,0 +1,92 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use libtest_mimic::{Arguments, Failed, Trial};
+use rvr::test_support::address_modes::{self, AddressModeHarness, Fixture};
+
+mod test_utils;
+
+fn main() {
+    let mut args = Arguments::from_args();
+    test_utils::cap_threads(&mut args);
+
+    let mut trials = Vec::new();
+    for fixture in address_modes::fixtures() {
+        let kind = if fixture.regression.is_some() {
+            "regression"
+        } else {
+            "fixture"
+        };
+        let name = format!("address_modes::{kind}::{}", fixture.name);
+        trials.push(Trial::test(name, move || run_fixture(&fixture)));
+    }
+
+    let cases = collect_riscv_tests();
+    let full = address_modes::full_matrix_requested();
+    let selected = address_modes::sample_suite(&cases, address_modes::rotation_slot(), full);
+    for path in selected {
+        let name = format!("address_modes::riscv_tests::{}", ident_from_path(&path));
+        trials.push(Trial::test(name, move || run_case(&path)));
+    }
+
+    libtest_mimic::run(&args, trials).exit();
+}
+
+fn harness(work_dir: &Path) -> AddressModeHarness {
+    AddressModeHarness::new(env!("CARGO_BIN_EXE_rvr"), work_dir)
+}
+
+fn run_fixture(fixture: &Fixture) -> Result<(), Failed> {
+    let temp = tempfile::tempdir().map_err(|e| Failed::from(format!("tempdir: {e}")))?;
+    harness(temp.path())
+        .check_fixture(fixture)
+        .map_err(Failed::from)
+}
+
+fn run_case(path: &Path) -> Result<(), Failed> {
+    let temp = tempfile::tempdir().map_err(|e| Failed::from(format!("tempdir: {e}")))?;
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("elf");
+    harness(temp.path())
+        .compare_elf(path, name)
+        .and_then(|comparison| comparison.check())
+        .map_err(|e| Failed::from(format!("{name}: {e}")))
+}
+
+fn collect_riscv_tests() -> Vec<PathBuf> {
+    let dir = workspace_root().join("bin/riscv-tests");
+    let mut cases = Vec::new();
+    if dir.exists() {
+        let _ = collect_files(&dir, &mut cases);
+    }
+    cases.sort();
+    cases
+}
+
+fn collect_files(dir: &Path, out: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, out)?;
+        } else if path.is_file() {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn workspace_root() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .and_then(|p| p.parent())
+        .expect("missing workspace root")
+        .to_path_buf()
+}
+
+fn ident_from_path(path: &Path) -> String {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("")
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
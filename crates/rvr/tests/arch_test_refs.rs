@@ -0,0 +1,167 @@
+//! riscv-arch-test reference store: references are keyed by ELF content, so a
+//! rebuilt ELF is reported as stale (or regenerated) rather than mismatching,
+//! and legacy filename-keyed references are adopted once.
+
+use std::fs;
+use std::path::Path;
+
+#[path = "support/arch_refs.rs"]
+mod arch_refs;
+
+use arch_refs::{INDEX_FILE, RefStatus, RefStore, hash_file};
+
+const NAME: &str = "rv64i_m-I/add-01";
+const SIGNATURE: &str = "deadbeef\n";
+const NEW_SIGNATURE: &str = "cafef00d\n";
+
+/// Reference generator: `(elf, sig_path)`.
+type Generator = fn(&Path, &Path) -> Result<(), String>;
+
+/// Generator standing in for Spike.
+fn write_sig(contents: &'static str) -> impl FnOnce(&Path, &Path) -> Result<(), String> {
+    move |_elf, sig| fs::write(sig, contents).map_err(|e| e.to_string())
+}
+
+/// No generator: stale or missing references are reported.
+fn no_regen() -> Option<Generator> {
+    None
+}
+
+fn setup(elf_bytes: &[u8]) -> (tempfile::TempDir, std::path::PathBuf, RefStore) {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let elf = dir.path().join("add-01");
+    fs::write(&elf, elf_bytes).expect("write elf");
+    let store = RefStore::open(dir.path().join("references")).expect("open store");
+    (dir, elf, store)
+}
+
+#[test]
+fn generated_reference_is_keyed_by_elf_hash() {
+    let (dir, elf, store) = setup(b"elf v1");
+    let sig = store
+        .generate(NAME, &elf, write_sig(SIGNATURE))
+        .expect("generate");
+    let hash = hash_file(&elf).expect("hash");
+
+    assert_eq!(sig, store.sig_path(&hash));
+    assert_eq!(fs::read_to_string(&sig).unwrap(), SIGNATURE);
+    let index = fs::read_to_string(dir.path().join("references").join(INDEX_FILE)).unwrap();
+    assert_eq!(index, format!("{hash}  {NAME}\n"));
+    assert_eq!(store.lookup(NAME, &elf), Ok(RefStatus::Fresh(sig)));
+}
+
+#[test]
+fn rebuilt_elf_is_stale_not_mismatched() {
+    let (dir, elf, store) = setup(b"elf v1");
+    store
+        .generate(NAME, &elf, write_sig(SIGNATURE))
+        .expect("generate");
+    let recorded = hash_file(&elf).expect("hash");
+    fs::write(&elf, b"elf v2").expect("rebuild elf");
+    let actual = hash_file(&elf).expect("hash");
+
+    assert_eq!(
+        store.lookup(NAME, &elf),
+        Ok(RefStatus::Stale {
+            recorded,
+            actual: actual.clone()
+        })
+    );
+    let err = store.resolve(NAME, &elf, no_regen()).unwrap_err();
+    assert!(
+        err.starts_with("stale reference for rv64i_m-I/add-01"),
+        "{err}"
+    );
+    assert!(err.contains(&actual[..12]), "{err}");
+
+    // A fresh store sees the same staleness through the index on disk.
+    let reopened = RefStore::open(dir.path().join("references")).expect("reopen");
+    assert!(matches!(
+        reopened.lookup(NAME, &elf),
+        Ok(RefStatus::Stale { .. })
+    ));
+}
+
+#[test]
+fn stale_reference_is_regenerated_when_allowed() {
+    let (_dir, elf, store) = setup(b"elf v1");
+    store
+        .generate(NAME, &elf, write_sig(SIGNATURE))
+        .expect("generate");
+    fs::write(&elf, b"elf v2").expect("rebuild elf");
+
+    let sig = store
+        .resolve(NAME, &elf, Some(write_sig(NEW_SIGNATURE)))
+        .expect("regenerate");
+    assert_eq!(sig, store.sig_path(&hash_file(&elf).unwrap()));
+    assert_eq!(fs::read_to_string(&sig).unwrap(), NEW_SIGNATURE);
+    assert_eq!(store.lookup(NAME, &elf), Ok(RefStatus::Fresh(sig)));
+}
+
+#[test]
+fn missing_reference_is_regenerated_or_reported() {
+    let (_dir, elf, store) = setup(b"elf v1");
+
+    let err = store.resolve(NAME, &elf, no_regen()).unwrap_err();
+    assert!(err.starts_with("missing reference for"), "{err}");
+
+    let sig = store
+        .resolve(NAME, &elf, Some(write_sig(SIGNATURE)))
+        .expect("generate");
+    assert_eq!(fs::read_to_string(sig).unwrap(), SIGNATURE);
+}
+
+#[test]
+fn failed_regeneration_leaves_no_reference() {
+    let (_dir, elf, store) = setup(b"elf v1");
+    let err = store
+        .resolve(
+            NAME,
+            &elf,
+            Some(|_: &Path, _: &Path| Err("spike failed".to_string())),
+        )
+        .unwrap_err();
+
+    assert_eq!(err, "spike failed");
+    assert!(matches!(
+        store.lookup(NAME, &elf),
+        Ok(RefStatus::Missing { .. })
+    ));
+}
+
+#[test]
+fn identical_elf_reuses_existing_reference() {
+    let (dir, elf, store) = setup(b"elf v1");
+    store
+        .generate(NAME, &elf, write_sig(SIGNATURE))
+        .expect("generate");
+    let other = dir.path().join("copy");
+    fs::copy(&elf, &other).expect("copy elf");
+
+    let sig = store
+        .resolve("rv32i_m-I/add-01", &other, no_regen())
+        .expect("shared reference");
+    assert_eq!(sig, store.sig_path(&hash_file(&elf).unwrap()));
+}
+
+#[test]
+fn legacy_reference_is_adopted_once() {
+    let (dir, elf, store) = setup(b"elf v1");
+    let refs = dir.path().join("references");
+    let legacy = refs.join(format!("{NAME}.sig"));
+    fs::create_dir_all(legacy.parent().unwrap()).unwrap();
+    fs::write(&legacy, SIGNATURE).unwrap();
+
+    let sig = store.resolve(NAME, &elf, no_regen()).expect("adopt");
+    assert_eq!(sig, store.sig_path(&hash_file(&elf).unwrap()));
+    assert_eq!(fs::read_to_string(&sig).unwrap(), SIGNATURE);
+    assert!(!legacy.exists());
+
+    // Once adopted, a rebuilt ELF is stale even if a legacy file reappears.
+    fs::write(&legacy, SIGNATURE).unwrap();
+    fs::write(&elf, b"elf v2").unwrap();
+    assert!(matches!(
+        store.lookup(NAME, &elf),
+        Ok(RefStatus::Stale { .. })
+    ));
+}
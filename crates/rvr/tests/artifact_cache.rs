@@ -0,0 +1,84 @@
+//! Artifact cache: a second compile of the same ELF and config restores the
+//! cached output (marked here by a file planted in the entry) instead of
+//! building, and still runs; another config or `with_cache(false)` builds.
+
+use std::path::{Path, PathBuf};
+
+use guest::{OPCODE_OP_IMM, OPCODE_SYSTEM, li};
+use rvr::test_support::guest;
+use rvr::{ArtifactCache, CompileOptions, InstretMode, Runner};
+use rvr_elf::STT_FUNC;
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{REG_A0, REG_A7, REG_ZERO, Rv64, encode_i};
+
+const TEXT: u64 = 0x1000;
+const EXIT_CODE: u8 = 7;
+/// File planted in the cache entry; only a restored output has it.
+const MARKER: &str = "planted.txt";
+
+/// `exit(EXIT_CODE)`.
+fn guest_elf() -> Vec<u8> {
+    let text = [
+        encode_i(OPCODE_OP_IMM, REG_A0, 0, REG_ZERO, i32::from(EXIT_CODE)),
+        li(REG_A7, SYS_EXIT),
+        encode_i(OPCODE_SYSTEM, REG_ZERO, 0, REG_ZERO, 0),
+    ];
+    guest::text_elf::<Rv64>(TEXT, &text)
+        .with_symbol("_start", TEXT, STT_FUNC)
+        .build()
+}
+
+/// Compile into `<root>/<run>/out`, returning the output directory.
+fn compile(elf: &Path, root: &Path, run: &str, options: &CompileOptions) -> PathBuf {
+    let out = root.join(run).join("out");
+    rvr::compile_with_options(elf, &out, options).expect("compile");
+    out
+}
+
+fn entries(cache: &Path) -> Vec<PathBuf> {
+    std::fs::read_dir(cache)
+        .expect("read cache")
+        .map(|entry| entry.expect("cache entry").path())
+        .collect()
+}
+
+#[test]
+fn test_second_compile_hits_cache() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = temp.path().join("exit.elf");
+    std::fs::write(&elf, guest_elf()).expect("write ELF");
+    let cache_dir = temp.path().join("cache");
+    let options = CompileOptions::new()
+        .with_quiet(true)
+        .with_cache(true)
+        .with_cache_dir(&cache_dir);
+
+    compile(&elf, temp.path(), "first", &options);
+    let stored = entries(&cache_dir);
+    assert_eq!(stored.len(), 1);
+    std::fs::write(stored[0].join(MARKER), "").expect("plant marker");
+
+    let out = compile(&elf, temp.path(), "second", &options);
+    assert!(out.join(MARKER).exists(), "second compile was not cached");
+    let mut runner = Runner::load(&out, &elf).expect("load cached build");
+    assert_eq!(runner.run().expect("run guest").exit_code, EXIT_CODE);
+
+    let uncached = compile(
+        &elf,
+        temp.path(),
+        "uncached",
+        &options.clone().with_cache(false),
+    );
+    assert!(!uncached.join(MARKER).exists());
+
+    let other = options.with_instret_mode(InstretMode::Off);
+    assert!(
+        !compile(&elf, temp.path(), "other", &other)
+            .join(MARKER)
+            .exists()
+    );
+    let cache = ArtifactCache::new(&cache_dir);
+    assert_eq!(cache.stats().expect("stats").entries, 2);
+    assert_eq!(cache.clear().expect("clear").entries, 2);
+    assert!(entries(&cache_dir).is_empty());
+}
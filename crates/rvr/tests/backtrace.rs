@@ -0,0 +1,213 @@
+//! Guest backtraces: a panic-trap style handler (`unimp`) reached through
+//! `_start -> outer -> middle -> inner -> rust_begin_unwind` unwinds to the
+//! symbolized call chain, through frame pointers or through `.eh_frame`.
+
+use std::path::Path;
+
+use guest::{ECALL, FUNCT3_D, OPCODE_JAL, OPCODE_JALR, OPCODE_LOAD, OPCODE_STORE, addi, li};
+use rvr::test_support::guest;
+use rvr::{CompileOptions, Frame, Runner};
+use rvr_elf::{ElfWriter, PF_R, PF_X, STT_NOTYPE};
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{
+    REG_A0, REG_A7, REG_RA, REG_S0, REG_SP, REG_ZERO, Rv64, encode_i, encode_j, encode_s,
+};
+
+const RET: u32 = encode_i(OPCODE_JALR, REG_ZERO, 0, REG_RA, 0);
+/// `unimp` (`csrrw x0, cycle, x0`), as emitted by the panic-trap handler.
+const UNIMP: u32 = 0xc000_1073;
+
+const TEXT: u64 = 0x1000;
+const EH_FRAME: u64 = 0x3000;
+const STACK_TOP: u64 = 0x10_0000;
+const CALLERS: [&str; 3] = ["outer", "middle", "inner"];
+/// Index of the call in each caller.
+const CALL: u64 = 4;
+
+const fn sd(rs2: u8, offset: i32) -> u32 {
+    encode_s(OPCODE_STORE, FUNCT3_D, REG_SP, rs2, offset)
+}
+
+const fn ld(rd: u8, offset: i32) -> u32 {
+    encode_i(OPCODE_LOAD, rd, FUNCT3_D, REG_SP, offset)
+}
+
+/// A function of the guest: name, instructions, and the CFA program of its
+/// FDE as `(instruction index, DW_CFA ops)`.
+struct Function {
+    name: &'static str,
+    text: Vec<u32>,
+    cfa: Vec<(usize, Vec<u8>)>,
+}
+
+/// `_start` calls `outer`, each caller calls the next, and `inner` calls the
+/// handler. Callers save `ra` (and `s0`, setting it up as the frame pointer
+/// if `frame_pointers`); the call is always the instruction at `CALL`.
+fn functions(frame_pointers: bool) -> Vec<Function> {
+    let mut functions = vec![Function {
+        name: "_start",
+        text: vec![
+            encode_j(OPCODE_JAL, REG_RA, 4 * 4),
+            addi(REG_A0, REG_ZERO, 0),
+            li(REG_A7, SYS_EXIT),
+            ECALL,
+        ],
+        cfa: Vec::new(),
+    }];
+    for name in CALLERS {
+        let mut text = vec![addi(REG_SP, REG_SP, -16), sd(REG_RA, 8), sd(REG_S0, 0)];
+        // CFA = sp + 16; ra at CFA - 8; s0 at CFA - 16
+        let mut cfa = vec![(1, vec![0x0e, 16]), (2, vec![0x81, 1]), (3, vec![0x88, 2])];
+        if frame_pointers {
+            text.push(addi(REG_S0, REG_SP, 16));
+            // CFA = s0 + 16
+            cfa.push((4, vec![0x0c, 8, 16]));
+        } else {
+            text.push(addi(REG_S0, REG_ZERO, 0));
+        }
+        text.extend([
+            encode_j(OPCODE_JAL, REG_RA, 5 * 4),
+            ld(REG_RA, 8),
+            ld(REG_S0, 0),
+            addi(REG_SP, REG_SP, 16),
+            RET,
+        ]);
+        functions.push(Function { name, text, cfa });
+    }
+    functions.push(Function {
+        name: "rust_begin_unwind",
+        text: vec![UNIMP],
+        cfa: Vec::new(),
+    });
+    functions
+}
+
+/// Address of each function, laid out back to back from `TEXT`.
+fn addresses(functions: &[Function]) -> Vec<u64> {
+    functions
+        .iter()
+        .scan(TEXT, |addr, function| {
+            let start = *addr;
+            *addr += 4 * function.text.len() as u64;
+            Some(start)
+        })
+        .collect()
+}
+
+/// Pad `body` with `DW_CFA_nop` and prefix its length.
+fn entry(mut body: Vec<u8>) -> Vec<u8> {
+    while !body.len().is_multiple_of(4) {
+        body.push(0);
+    }
+    let mut out = u32::try_from(body.len()).unwrap().to_le_bytes().to_vec();
+    out.extend(body);
+    out
+}
+
+/// `.eh_frame` with one CIE (absolute `udata4` pointers, CFA = sp, `ra` in
+/// x1) and an FDE per function but `_start`.
+fn eh_frame(functions: &[Function], addrs: &[u64]) -> Vec<u8> {
+    let mut data = entry(vec![
+        0, 0, 0, 0, 1, b'z', b'R', 0, 1, 0x78, 1, 1, 0x03, 0x0c, 2, 0,
+    ]);
+    for (function, &addr) in functions.iter().zip(addrs).skip(1) {
+        let mut fde = u32::try_from(data.len() + 4)
+            .unwrap()
+            .to_le_bytes()
+            .to_vec();
+        fde.extend_from_slice(&u32::try_from(addr).unwrap().to_le_bytes());
+        fde.extend_from_slice(
+            &u32::try_from(4 * function.text.len())
+                .unwrap()
+                .to_le_bytes(),
+        );
+        fde.push(0);
+        let mut at = 0;
+        for (index, ops) in &function.cfa {
+            // DW_CFA_advance_loc
+            fde.push(0x40 | u8::try_from(4 * (index - at)).unwrap());
+            fde.extend(ops);
+            at = *index;
+        }
+        data.extend(entry(fde));
+    }
+    data.extend_from_slice(&[0, 0, 0, 0]);
+    data
+}
+
+fn guest_elf(frame_pointers: bool, cfi: bool) -> Vec<u8> {
+    let functions = functions(frame_pointers);
+    let addrs = addresses(&functions);
+    let text: Vec<u8> = functions
+        .iter()
+        .flat_map(|f| &f.text)
+        .flat_map(|i| i.to_le_bytes())
+        .collect();
+    let len = text.len() as u64;
+    let mut writer = ElfWriter::<Rv64>::new(TEXT)
+        .with_segment(TEXT, PF_R | PF_X, text)
+        .with_section(".text", TEXT, len)
+        .with_symbol("__stack_top", STACK_TOP, STT_NOTYPE);
+    for (function, &addr) in functions.iter().zip(&addrs) {
+        writer = writer.with_function(function.name, addr, 4 * function.text.len() as u64);
+    }
+    if cfi {
+        let eh_frame = eh_frame(&functions, &addrs);
+        let len = eh_frame.len() as u64;
+        writer =
+            writer
+                .with_segment(EH_FRAME, PF_R, eh_frame)
+                .with_section(".eh_frame", EH_FRAME, len);
+    }
+    writer.build()
+}
+
+/// Compile and run the guest, returning its backtrace.
+fn backtrace(dir: &Path, frame_pointers: bool, cfi: bool) -> Vec<Frame> {
+    let elf = dir.join("trap.elf");
+    std::fs::write(&elf, guest_elf(frame_pointers, cfi)).expect("write ELF");
+    let out = dir.join("out");
+    rvr::compile_with_options(&elf, &out, &CompileOptions::new().with_quiet(true))
+        .expect("compile");
+    let mut runner = Runner::load(&out, &elf).expect("load runner");
+    assert_eq!(runner.run().expect("run guest").exit_code, 1);
+    assert!(runner.is_trapped());
+    runner.backtrace()
+}
+
+/// The handler, then each caller at the instruction after its call.
+fn assert_call_chain(frames: &[Frame]) {
+    let symbols: Vec<_> = frames
+        .iter()
+        .map(|frame| (frame.function.as_deref(), frame.offset))
+        .collect();
+    let mut expected = vec![(Some("rust_begin_unwind"), 0)];
+    expected.extend(
+        CALLERS
+            .iter()
+            .rev()
+            .map(|&name| (Some(name), 4 * (CALL + 1))),
+    );
+    expected.push((Some("_start"), 4));
+    assert_eq!(symbols, expected, "{frames:#?}");
+
+    let functions = functions(true);
+    let addrs = addresses(&functions);
+    assert_eq!(frames[0].pc, addrs[4]);
+    assert_eq!(
+        frames[1].to_string(),
+        format!("{:#x} in inner+0x14", addrs[3] + 0x14)
+    );
+}
+
+#[test]
+fn test_backtrace_frame_pointers() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    assert_call_chain(&backtrace(temp.path(), true, false));
+}
+
+#[test]
+fn test_backtrace_eh_frame() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    assert_call_chain(&backtrace(temp.path(), false, true));
+}
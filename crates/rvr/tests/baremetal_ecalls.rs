@@ -0,0 +1,78 @@
+//! Bare-metal ECALLs mapped by `CompileOptions::with_baremetal_ecalls`:
+//! a guest prints "hi" with ECALL 1 and exits with ECALL 93, and any other
+//! number traps at its ECALL.
+
+use std::path::Path;
+
+use guest::{ECALL, SharedWriter, addi};
+use rvr::test_support::guest;
+use rvr::{BareMetalConfig, CompileOptions, ExitReason, RunResult, Runner, TrapCause};
+use rvr_isa::{REG_A0, REG_A7, REG_ZERO, Rv64};
+
+const ECALL_PUTCHAR: i32 = 1;
+const ECALL_EXIT: i32 = 93;
+const ECALL_UNMAPPED: i32 = 7;
+const EXIT_CODE: i32 = 5;
+
+const TEXT: u64 = 0x1000;
+const INSTR_BYTES: u64 = 4;
+
+/// `putchar('h'); putchar('i'); exit(EXIT_CODE)`.
+const HI: [u32; 8] = [
+    addi(REG_A7, REG_ZERO, ECALL_PUTCHAR),
+    addi(REG_A0, REG_ZERO, b'h' as i32),
+    ECALL,
+    addi(REG_A0, REG_ZERO, b'i' as i32),
+    ECALL,
+    addi(REG_A0, REG_ZERO, EXIT_CODE),
+    addi(REG_A7, REG_ZERO, ECALL_EXIT),
+    ECALL,
+];
+
+/// An ECALL with a number the config does not map.
+const UNMAPPED: [u32; 2] = [addi(REG_A7, REG_ZERO, ECALL_UNMAPPED), ECALL];
+
+/// Compile `text` with ECALL 1 as putchar and 93 as exit, run it, and
+/// return the result and the guest's stdout.
+fn run(dir: &Path, text: &[u32]) -> (RunResult, Vec<u8>) {
+    let elf = dir.join("ecalls.elf");
+    let image = guest::text_elf::<Rv64>(TEXT, text).build();
+    std::fs::write(&elf, image).expect("write ELF");
+    let out = dir.join("ecalls");
+    let ecalls: BareMetalConfig = "1=putchar,93=exit".parse().expect("parse ECALL map");
+    let options = CompileOptions::new()
+        .with_quiet(true)
+        .with_baremetal_ecalls(ecalls);
+    rvr::compile_with_options(&elf, &out, &options).expect("compile");
+
+    let stdout = SharedWriter::default();
+    let mut runner = Runner::load(&out, &elf).expect("load runner");
+    runner.set_stdout(stdout.clone());
+    let result = runner.run().expect("run guest");
+    drop(runner);
+    let output = stdout.contents();
+    (result, output)
+}
+
+#[test]
+fn test_putchar_and_exit_ecalls() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let (result, stdout) = run(temp.path(), &HI);
+    assert_eq!(stdout, b"hi");
+    assert_eq!(i32::from(result.exit_code), EXIT_CODE);
+}
+
+#[test]
+fn test_unmapped_ecall_traps() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let (result, stdout) = run(temp.path(), &UNMAPPED);
+    assert!(stdout.is_empty());
+    assert_eq!(
+        result.exit_reason,
+        ExitReason::Trapped {
+            cause: TrapCause::IllegalInstruction,
+            pc: TEXT + INSTR_BYTES,
+            addr: 0,
+        }
+    );
+}
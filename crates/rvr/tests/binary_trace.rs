@@ -0,0 +1,67 @@
+//! Binary trace tracer: a counted loop compiled with `binary-trace` writes
+//! one record per retired instruction, stamped with the ELF's hash, and the
+//! trace tooling reads it back like a Spike log.
+
+use guest::{ECALL, FUNCT3_BNE, OPCODE_BRANCH, addi, li};
+use rvr::test_support::guest;
+use rvr::test_support::trace::{TraceReader, parse_trace_file};
+use rvr::{CompileOptions, Runner, TracerConfig};
+use rvr_emit::c::content_hash;
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{REG_A0, REG_A1, REG_A7, REG_ZERO, Rv64, encode_b};
+
+const TEXT: u64 = 0x1000;
+const LOOP: u64 = TEXT + 8;
+const ITERATIONS: i32 = 3;
+/// Two setup instructions, two per iteration, then `li a7` and `ecall`.
+const RETIRED: usize = 2 + 2 * ITERATIONS.unsigned_abs() as usize + 2;
+
+/// `for (a0 = 0; a0 != ITERATIONS; a0++);` then `exit(a0)`.
+fn loop_elf() -> Vec<u8> {
+    let text = [
+        addi(REG_A0, REG_ZERO, 0),
+        addi(REG_A1, REG_ZERO, ITERATIONS),
+        // loop:
+        addi(REG_A0, REG_A0, 1),
+        encode_b(OPCODE_BRANCH, FUNCT3_BNE, REG_A0, REG_A1, -4),
+        li(REG_A7, SYS_EXIT),
+        ECALL,
+    ];
+    guest::text_elf::<Rv64>(TEXT, &text).build()
+}
+
+#[test]
+fn test_binary_trace_records_loop() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = temp.path().join("loop.elf");
+    let elf_data = loop_elf();
+    std::fs::write(&elf, &elf_data).expect("write ELF");
+    let out = temp.path().join("loop");
+    let options = CompileOptions::new()
+        .with_quiet(true)
+        .with_tracer_config(TracerConfig::binary_trace());
+    rvr::compile_with_options(&elf, &out, &options).expect("compile");
+
+    let trace = temp.path().join("trace.bin");
+    // The only test in this binary, so no other thread reads the environment
+    unsafe { std::env::set_var("RVR_TRACE_FILE", &trace) };
+    let mut runner = Runner::load(&out, &elf).expect("load runner");
+    let result = runner.run().expect("run guest");
+    drop(runner);
+    assert_eq!(i32::from(result.exit_code), ITERATIONS);
+
+    let reader = TraceReader::open(&trace).expect("open trace");
+    assert_eq!(reader.header().xlen, 64);
+    assert_eq!(reader.header().elf_hash, content_hash(&elf_data));
+
+    let entries = parse_trace_file(&trace).expect("parse trace");
+    assert_eq!(entries.len(), RETIRED);
+    assert_eq!(entries[0].pc, TEXT);
+    assert_eq!(entries[0].opcode, addi(REG_A0, REG_ZERO, 0));
+    assert_eq!(entries[1].rd, Some(REG_A1));
+    assert_eq!(entries[1].rd_value, Some(ITERATIONS.unsigned_abs().into()));
+    assert_eq!(entries[2].pc, LOOP);
+    assert_eq!(entries[2].rd_value, Some(1));
+    assert_eq!(entries[3].rd, None);
+    assert_eq!(entries[RETIRED - 1].opcode, ECALL);
+}
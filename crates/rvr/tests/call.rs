@@ -0,0 +1,52 @@
+//! Calling exported guest functions: `Runner::call` passes the arguments,
+//! returns through the library's trampoline at `RV_CALL_RETURN_PC`, which
+//! is XLEN wide, and yields a0 on RV32 and RV64.
+
+use guest::{ECALL, OPCODE_JALR, addi, li};
+use rvr::test_support::guest;
+use rvr::{CompileOptions, Runner};
+use rvr_elf::STT_FUNC;
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{REG_A0, REG_A7, REG_RA, REG_ZERO, Rv32, Rv64, Xlen, encode_i};
+
+const TEXT: u64 = 0x1000;
+/// `add_one`, after the three instructions of `_start`.
+const ADD_ONE: u64 = TEXT + 12;
+/// The return trampoline, just past the text.
+const RETURN_PC: u64 = TEXT + 20;
+
+/// `_start: exit(0)`, then `add_one: a0 += 1; ret`.
+fn call_elf<X: Xlen>() -> Vec<u8> {
+    let text = [
+        addi(REG_A0, REG_ZERO, 0),
+        li(REG_A7, SYS_EXIT),
+        ECALL,
+        addi(REG_A0, REG_A0, 1),
+        encode_i(OPCODE_JALR, REG_ZERO, 0, REG_RA, 0),
+    ];
+    guest::text_elf::<X>(TEXT, &text)
+        .with_symbol("_start", TEXT, STT_FUNC)
+        .with_symbol("add_one", ADD_ONE, STT_FUNC)
+        .build()
+}
+
+fn check_call<X: Xlen>() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let options = CompileOptions::new().with_export_functions(true);
+    let compiled = guest::compile(temp.path(), "call", &call_elf::<X>(), &options);
+    let mut runner = Runner::load(&compiled.out, &compiled.elf).expect("load runner");
+
+    assert_eq!(runner.prepare_call(), Some(RETURN_PC));
+    assert_eq!(runner.call("add_one", &[41]).expect("call"), 42);
+    assert_eq!(runner.get_pc(), RETURN_PC);
+}
+
+#[test]
+fn test_call_returns_through_trampoline_rv32() {
+    check_call::<Rv32>();
+}
+
+#[test]
+fn test_call_returns_through_trampoline_rv64() {
+    check_call::<Rv64>();
+}
@@ -0,0 +1,108 @@
+//! Timeouts and cancellation: an infinite loop stops with `TimedOut` or
+//! `Cancelled`, leaving its PC and registers inspectable, both when the
+//! library suspends in slices and when it checks the cancel flag.
+
+use std::thread;
+use std::time::Duration;
+
+use guest::{FUNCT3_BNE, OPCODE_BRANCH, OPCODE_OP_IMM};
+use rvr::test_support::guest;
+use rvr::{CompileOptions, InstretMode, RunError, Runner};
+use rvr_isa::{REG_A0, REG_ZERO, Rv64, encode_b, encode_i};
+
+const TEXT: u64 = 0x1000;
+/// Start of the loop body, after `li a0, 0`.
+const LOOP: u64 = TEXT + 4;
+const TIMEOUT: Duration = Duration::from_millis(50);
+
+/// `a0 = 0; do a0++; while (a0 != 0);` (never terminates in practice).
+fn infinite_loop_elf() -> Vec<u8> {
+    let text = [
+        encode_i(OPCODE_OP_IMM, REG_A0, 0, REG_ZERO, 0),
+        encode_i(OPCODE_OP_IMM, REG_A0, 0, REG_A0, 1),
+        encode_b(OPCODE_BRANCH, FUNCT3_BNE, REG_A0, REG_ZERO, -4),
+    ];
+    guest::text_elf::<Rv64>(TEXT, &text).build()
+}
+
+fn load_runner(options: &CompileOptions) -> (tempfile::TempDir, Runner) {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = temp.path().join("loop.elf");
+    std::fs::write(&elf, infinite_loop_elf()).expect("write ELF");
+    let out = temp.path().join("out");
+    rvr::compile_with_options(&elf, &out, &options.clone().with_quiet(true)).expect("compile");
+    let runner = Runner::load(&out, &elf).expect("load runner");
+    (temp, runner)
+}
+
+/// The guest stopped inside the loop with its counter advanced.
+fn assert_stopped_in_loop(runner: &Runner) {
+    assert!(!runner.has_exited());
+    assert!(runner.get_pc() == LOOP || runner.get_pc() == LOOP + 4);
+    assert!(runner.get_register(REG_A0 as usize) > 0);
+}
+
+#[test]
+fn test_suspend_timeout() {
+    let options = CompileOptions::new().with_instret_mode(InstretMode::Suspend);
+    let (_temp, mut runner) = load_runner(&options);
+    let err = runner.run_with_timeout(TIMEOUT).unwrap_err();
+    let RunError::TimedOut { instret_so_far } = err else {
+        panic!("expected a timeout, got {err}");
+    };
+    assert!(instret_so_far > 0);
+    assert_eq!(runner.instret(), instret_so_far);
+    assert_stopped_in_loop(&runner);
+}
+
+#[test]
+fn test_suspend_cancel() {
+    let options = CompileOptions::new().with_instret_mode(InstretMode::Suspend);
+    let (_temp, mut runner) = load_runner(&options);
+    let handle = runner.cancel_handle();
+    let canceller = thread::spawn(move || {
+        thread::sleep(TIMEOUT);
+        handle.cancel();
+    });
+    let err = runner.run_with_timeout(Duration::MAX).unwrap_err();
+    canceller.join().expect("cancel thread");
+    assert!(matches!(err, RunError::Cancelled), "{err}");
+    assert!(!runner.cancel_handle().is_cancelled());
+    assert_stopped_in_loop(&runner);
+}
+
+#[test]
+fn test_check_cancel_timeout() {
+    let options = CompileOptions::new().with_check_cancel(true);
+    let (_temp, mut runner) = load_runner(&options);
+    let err = runner.run_with_timeout(TIMEOUT).unwrap_err();
+    let RunError::TimedOut { instret_so_far } = err else {
+        panic!("expected a timeout, got {err}");
+    };
+    assert!(instret_so_far > 0);
+    assert_stopped_in_loop(&runner);
+}
+
+#[test]
+fn test_check_cancel_from_another_thread() {
+    let options = CompileOptions::new()
+        .with_instret_mode(InstretMode::Off)
+        .with_check_cancel(true);
+    let (_temp, mut runner) = load_runner(&options);
+    let handle = runner.cancel_handle();
+    let canceller = thread::spawn(move || {
+        thread::sleep(TIMEOUT);
+        handle.cancel();
+    });
+    let err = runner.run().unwrap_err();
+    canceller.join().expect("cancel thread");
+    assert!(matches!(err, RunError::Cancelled), "{err}");
+    assert_stopped_in_loop(&runner);
+}
+
+#[test]
+fn test_timeout_requires_suspend_or_check_cancel() {
+    let (_temp, mut runner) = load_runner(&CompileOptions::new());
+    let err = runner.run_with_timeout(TIMEOUT).unwrap_err();
+    assert!(matches!(err, RunError::TimeoutUnsupported), "{err}");
+}
@@ -0,0 +1,67 @@
+//! Self-modifying code: a guest store into recompiled code fails the run
+//! instead of silently running the stale translation.
+
+use guest::{FUNCT3_SB, OPCODE_LUI, OPCODE_OP_IMM, OPCODE_STORE, OPCODE_SYSTEM, li};
+use rvr::test_support::guest;
+use rvr::{CompileOptions, RunError, Runner};
+use rvr_elf::{ElfWriter, PF_R, PF_W, PF_X};
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{REG_A0, REG_A1, REG_A7, REG_ZERO, Rv64, encode_i, encode_s, encode_u};
+
+const TEXT: u64 = 0x1000;
+/// PC of the store.
+const STORE_PC: u64 = TEXT + 4;
+/// Address the store overwrites: the `addi a0, zero, 0` after it.
+const PATCHED: u64 = TEXT + 8;
+
+/// Zeroes the first byte of its own third instruction, then exits with 0.
+fn guest_elf() -> Vec<u8> {
+    let text = [
+        encode_u(OPCODE_LUI, REG_A1, 1),
+        encode_s(OPCODE_STORE, FUNCT3_SB, REG_A1, REG_ZERO, 8),
+        encode_i(OPCODE_OP_IMM, REG_A0, 0, REG_ZERO, 0),
+        li(REG_A7, SYS_EXIT),
+        encode_i(OPCODE_SYSTEM, REG_ZERO, 0, REG_ZERO, 0),
+    ];
+    ElfWriter::<Rv64>::new(TEXT)
+        .with_segment(TEXT, PF_R | PF_W | PF_X, guest::code(&text))
+        .build()
+}
+
+fn run(detect_code_writes: bool) -> Result<u8, RunError> {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = temp.path().join("smc.elf");
+    std::fs::write(&elf, guest_elf()).expect("write ELF");
+    let out = temp.path().join("out");
+    let options = CompileOptions::new()
+        .with_quiet(true)
+        .with_detect_code_writes(detect_code_writes);
+    rvr::compile_with_report(&elf, &out, &options).expect("compile");
+
+    let mut runner = Runner::load(&out, &elf).expect("load runner");
+    runner.run().map(|result| result.exit_code)
+}
+
+#[test]
+fn test_store_into_code_fails_run() {
+    let err = run(true).expect_err("store into code traps");
+    assert!(
+        matches!(
+            err,
+            RunError::CodeWrite {
+                pc: STORE_PC,
+                addr: PATCHED
+            }
+        ),
+        "{err:?}"
+    );
+    assert_eq!(
+        err.to_string(),
+        "guest wrote to recompiled code at pc 0x1004, address 0x1008"
+    );
+}
+
+#[test]
+fn test_store_into_code_unchecked() {
+    assert_eq!(run(false).expect("run guest"), 0);
+}
@@ -0,0 +1,108 @@
+//! Compressed segments: data segments above the threshold are embedded as
+//! LZ4 blocks, and `rv_init_memory` decodes them to the ELF's bytes.
+
+use std::path::Path;
+
+use guest::{Compiled, OPCODE_OP_IMM, OPCODE_SYSTEM, li};
+use rvr::test_support::guest;
+use rvr::{CDialect, CompileOptions, Compiler, Compression, RunError, Runner};
+use rvr_elf::{ElfWriter, PF_R, PF_W, PF_X};
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{REG_A0, REG_A7, REG_ZERO, Rv64, encode_i};
+
+const TEXT: u64 = 0x1000;
+const SMALL: u64 = 0x8000;
+const DATASET: u64 = 0x10_0000;
+const MIN_SIZE: usize = 4096;
+
+/// A JSON-like fixture with some incompressible bytes mixed in.
+fn dataset() -> Vec<u8> {
+    let mut data = Vec::new();
+    let mut x = 0x1234_5678u32;
+    for i in 0..8000u32 {
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        data.extend_from_slice(
+            format!("{{\"number\": {i}, \"gas_used\": {x}, \"receipts\": []}},\n").as_bytes(),
+        );
+    }
+    data
+}
+
+fn segments() -> [(u64, u32, Vec<u8>); 3] {
+    let text = [
+        encode_i(OPCODE_OP_IMM, REG_A0, 0, REG_ZERO, 0),
+        li(REG_A7, SYS_EXIT),
+        encode_i(OPCODE_SYSTEM, REG_ZERO, 0, REG_ZERO, 0),
+    ];
+    [
+        (TEXT, PF_R | PF_X, guest::code(&text)),
+        (SMALL, PF_R | PF_W, (0..=255).collect()),
+        (DATASET, PF_R, dataset()),
+    ]
+}
+
+fn compile(dir: &Path, options: &CompileOptions) -> Compiled {
+    let mut writer = ElfWriter::<Rv64>::new(TEXT);
+    for (vaddr, flags, data) in segments() {
+        writer = writer.with_segment(vaddr, flags, data);
+    }
+    guest::compile(dir, "guest", &writer.build(), options)
+}
+
+/// Guest memory initialized by the library holds exactly the ELF's bytes.
+fn check_embedded(compiled: &Compiled) {
+    let mut runner = Runner::load(&compiled.out, &compiled.elf).expect("load runner");
+    runner.load_embedded_segments().expect("init memory");
+    for (vaddr, _, data) in segments() {
+        let mut memory = vec![0; data.len()];
+        assert_eq!(runner.read_memory(vaddr, &mut memory), data.len());
+        assert!(memory == data, "segment at {vaddr:#x} differs");
+    }
+    assert_eq!(runner.run().expect("run guest").exit_code, 0);
+}
+
+#[test]
+fn test_compressed_segments_match_elf() {
+    let compression = Some(Compression::Lz4 { min_size: MIN_SIZE });
+    for (dialect, compiler) in [
+        (CDialect::Clang, Compiler::default()),
+        (CDialect::Portable, Compiler::gcc()),
+    ] {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let options = CompileOptions::new()
+            .with_c_dialect(dialect)
+            .with_compiler(compiler)
+            .with_compress_segments(compression);
+        let compiled = compile(temp.path(), &options);
+        check_embedded(&compiled);
+
+        if !dialect.is_portable() {
+            // Only the dataset reaches the threshold
+            let packed =
+                std::fs::metadata(compiled.out.join("segment_2.lz4")).expect("packed dataset");
+            assert!(packed.len() < dataset().len() as u64 / 2);
+            assert!(compiled.out.join("segment_1.bin").exists());
+            assert!(!compiled.out.join("segment_2.bin").exists());
+        }
+    }
+}
+
+#[test]
+fn test_plain_segments_match_elf() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let compiled = compile(temp.path(), &CompileOptions::new());
+    check_embedded(&compiled);
+    assert!(compiled.out.join("segment_2.bin").exists());
+
+    // Lazily initialized segments are not embedded at all
+    let temp = tempfile::tempdir().expect("tempdir");
+    let options = CompileOptions::new().with_lazy_segment_init(true);
+    let compiled = compile(temp.path(), &options);
+    let mut runner = Runner::load(&compiled.out, &compiled.elf).expect("load runner");
+    assert!(matches!(
+        runner.load_embedded_segments(),
+        Err(RunError::NoEmbeddedSegments)
+    ));
+}
@@ -0,0 +1,115 @@
+//! RV32 counters: a guest times a loop with the `rdcycleh`/`rdcycle`/
+//! `rdcycleh` idiom and reads `time`/`timeh`, and the runner serves the
+//! `*h` CSRs through `get_csr`.
+
+use guest::{ECALL, FUNCT3_BNE, FUNCT3_CSRRS, OPCODE_BRANCH, OPCODE_SYSTEM, addi, li};
+use rvr::test_support::guest;
+use rvr::{
+    CSR_CYCLE, CSR_CYCLEH, CSR_INSTRET, CSR_INSTRETH, CSR_TIME, CSR_TIMEH, CompileOptions, Runner,
+};
+use rvr_emit::Backend;
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{
+    REG_A0, REG_A1, REG_A7, REG_S0, REG_S1, REG_S2, REG_S3, REG_S4, REG_S5, REG_S6, REG_T0,
+    REG_ZERO, Rv32, encode_b, encode_i,
+};
+
+/// Iterations of the timed loop.
+const TERMS: i32 = 100;
+/// Instructions per loop iteration.
+const LOOP_LEN: u64 = 2;
+
+const TEXT: u64 = 0x1000;
+
+/// `csrr rd, csr`.
+const fn csrr(rd: u8, csr: u16) -> u32 {
+    encode_i(OPCODE_SYSTEM, rd, FUNCT3_CSRRS, REG_ZERO, csr as i32)
+}
+
+const fn bne(rs1: u8, rs2: u8, offset: i32) -> u32 {
+    encode_b(OPCODE_BRANCH, FUNCT3_BNE, rs1, rs2, offset)
+}
+
+/// Reads the 64-bit cycle count into `s0:s1` before and `s2:s3` after a
+/// loop of `TERMS` iterations, then `time` into `s4:s5` and `instreth`
+/// into `s6`.
+fn counters_elf() -> Vec<u8> {
+    let text = [
+        // start: retry until the high word is stable
+        csrr(REG_S0, CSR_CYCLEH),
+        csrr(REG_S1, CSR_CYCLE),
+        csrr(REG_T0, CSR_CYCLEH),
+        bne(REG_S0, REG_T0, -12),
+        addi(REG_A1, REG_ZERO, TERMS),
+        // loop:
+        addi(REG_A1, REG_A1, -1),
+        bne(REG_A1, REG_ZERO, -4),
+        // end: same read loop
+        csrr(REG_S2, CSR_CYCLEH),
+        csrr(REG_S3, CSR_CYCLE),
+        csrr(REG_T0, CSR_CYCLEH),
+        bne(REG_S2, REG_T0, -12),
+        csrr(REG_S4, CSR_TIME),
+        csrr(REG_S5, CSR_TIMEH),
+        csrr(REG_S6, CSR_INSTRETH),
+        addi(REG_A0, REG_ZERO, 0),
+        li(REG_A7, SYS_EXIT),
+        ECALL,
+    ];
+    guest::text_elf::<Rv32>(TEXT, &text).build()
+}
+
+/// 64-bit counter value from its `hi:lo` register pair.
+fn counter(runner: &Runner, hi: u8, lo: u8) -> u64 {
+    (runner.get_register(usize::from(hi)) << 32) | runner.get_register(usize::from(lo))
+}
+
+fn run(backend: Backend) {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = temp.path().join("counters.elf");
+    std::fs::write(&elf, counters_elf()).expect("write ELF");
+    let out = temp.path().join("out");
+    let options = CompileOptions::new().with_quiet(true).with_backend(backend);
+    rvr::compile_with_options(&elf, &out, &options).expect("compile");
+    let mut runner = Runner::load(&out, &elf).expect("load runner");
+    let result = runner.run().expect("run guest");
+    assert_eq!(result.exit_code, 0);
+
+    let start = counter(&runner, REG_S0, REG_S1);
+    let end = counter(&runner, REG_S2, REG_S3);
+    let elapsed = end - start;
+    let loop_cycles = u64::from(TERMS.unsigned_abs()) * LOOP_LEN;
+    assert!(
+        elapsed >= loop_cycles && elapsed < 2 * loop_cycles,
+        "elapsed {elapsed} cycles"
+    );
+    let time = counter(&runner, REG_S5, REG_S4);
+    assert!(time >= end, "time {time} before cycle {end}");
+    assert!(time <= result.instret);
+    assert_eq!(runner.get_register(usize::from(REG_S6)), 0);
+
+    assert_eq!(runner.get_csr(CSR_INSTRET), result.instret);
+    assert_eq!(runner.get_csr(CSR_CYCLE), result.instret);
+    assert_eq!(runner.get_csr(CSR_CYCLEH), 0);
+    assert_eq!(runner.get_csr(CSR_INSTRETH), 0);
+    // Counters are read-only for the host too
+    runner.set_csr(CSR_INSTRETH, 1);
+    assert_eq!(runner.get_csr(CSR_INSTRETH), 0);
+}
+
+#[test]
+fn test_rv32_counters_c() {
+    run(Backend::C);
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn test_rv32_counters_x86() {
+    run(Backend::X86Asm);
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn test_rv32_counters_arm64() {
+    run(Backend::ARM64Asm);
+}
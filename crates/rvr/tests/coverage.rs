@@ -0,0 +1,89 @@
+//! Coverage: a loop behind a never-taken guard, compiled with the coverage
+//! tracer and superblocks, reports the dead branch edge and the dead lines.
+
+use guest::{ECALL, FUNCT3_BLT, FUNCT3_BNE, OPCODE_BRANCH, addi, li};
+use rvr::test_support::guest;
+use rvr::{CompileOptions, Coverage, Runner, TracerConfig};
+use rvr_ir::SourceLoc;
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{REG_A0, REG_A1, REG_A7, REG_ZERO, Rv64, encode_b};
+
+const ITERATIONS: i32 = 10;
+
+const TEXT: u64 = 0x1000;
+const INSTR_SIZE: u64 = 4;
+const FILE: &str = "guest.c";
+/// Line of the never-taken guard `if (a1 < 0)`.
+const GUARD_LINE: u32 = 3;
+/// Line of the loop's back edge.
+const LOOP_LINE: u32 = 5;
+/// Lines of the error path, never run.
+const COLD_LINES: [u32; 3] = [8, 9, 10];
+
+/// `if (a1 < 0) exit(7);` guarding `for (a0 = 0; a0 != ITERATIONS; a0++);`
+/// then `exit(a0)`.
+fn guarded_loop_elf() -> Vec<u8> {
+    let text = [
+        addi(REG_A0, REG_ZERO, 0),
+        addi(REG_A1, REG_ZERO, ITERATIONS),
+        encode_b(OPCODE_BRANCH, FUNCT3_BLT, REG_A1, REG_ZERO, 0x14),
+        // loop:
+        addi(REG_A0, REG_A0, 1),
+        encode_b(OPCODE_BRANCH, FUNCT3_BNE, REG_A0, REG_A1, -4),
+        li(REG_A7, SYS_EXIT),
+        ECALL,
+        // cold:
+        addi(REG_A0, REG_ZERO, 7),
+        li(REG_A7, SYS_EXIT),
+        ECALL,
+    ];
+    guest::text_elf::<Rv64>(TEXT, &text).build()
+}
+
+/// Stand-in for DWARF line info: one source line per instruction.
+fn line_of(pc: u64) -> Option<SourceLoc> {
+    let line = u32::try_from((pc - TEXT) / INSTR_SIZE).ok()? + 1;
+    Some(SourceLoc::new(FILE, line, "main"))
+}
+
+#[test]
+fn test_coverage_reports_dead_branch() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = temp.path().join("guarded.elf");
+    std::fs::write(&elf, guarded_loop_elf()).expect("write ELF");
+    let out = temp.path().join("guarded");
+    let options = CompileOptions::new()
+        .with_quiet(true)
+        .with_tracer_config(TracerConfig::coverage());
+    rvr::compile_with_options(&elf, &out, &options).expect("compile");
+
+    let mut runner = Runner::load(&out, &elf).expect("load runner");
+    let result = runner.run().expect("run guest");
+    assert_eq!(i32::from(result.exit_code), ITERATIONS);
+
+    let flags = runner.coverage().expect("coverage tracer");
+    let coverage = Coverage::resolve_with_locations(&elf, runner.load_bias(), &flags, line_of)
+        .expect("resolve");
+
+    // Lines after the loop superblock's side exit are covered one by one
+    assert_eq!(coverage.line_hit(FILE, 1), Some(true));
+    assert_eq!(coverage.line_hit(FILE, 4), Some(true));
+    assert_eq!(coverage.line_hit(FILE, 7), Some(true));
+    assert_eq!(coverage.line_hit(FILE, COLD_LINES[0]), Some(false));
+    assert_eq!(coverage.line_hit(FILE, COLD_LINES[2]), Some(false));
+
+    let guard = coverage.branches(FILE, GUARD_LINE)[0];
+    assert!(guard.executed && !guard.taken && guard.not_taken);
+    let back_edge = coverage.branches(FILE, LOOP_LINE)[0];
+    assert!(back_edge.taken && back_edge.not_taken);
+
+    let mut lcov = Vec::new();
+    coverage
+        .write_lcov(&mut lcov, "guarded")
+        .expect("write lcov");
+    let lcov = String::from_utf8(lcov).expect("utf-8");
+    assert!(lcov.contains("SF:guest.c\n"));
+    assert!(lcov.contains("BRDA:3,0,0,0\nBRDA:3,0,1,1\nBRDA:5,0,0,1\nBRDA:5,0,1,1\n"));
+    assert!(lcov.contains("BRF:4\nBRH:3\n"));
+    assert!(lcov.contains("DA:7,1\nDA:8,0\nDA:9,0\nDA:10,0\nLF:10\nLH:7\n"));
+}
@@ -0,0 +1,105 @@
+//! Custom CSRs as a host-guest channel: the guest writes a sequence of values
+//! to a hook CSR, which a host callback records, and reads back a value the
+//! host supplies. A storage CSR is read through `Runner::get_csr` afterwards.
+
+use std::sync::{Arc, Mutex};
+
+use guest::{ECALL, FUNCT3_BNE, FUNCT3_CSRRS, OPCODE_BRANCH, OPCODE_SYSTEM, addi};
+use rvr::test_support::guest;
+use rvr::{CompileOptions, CsrHook, CustomCsr, Runner};
+use rvr_isa::{REG_A0, REG_T0, REG_T1, REG_ZERO, Rv32, Rv64, Xlen, encode_b, encode_i};
+
+const FUNCT3_CSRRW: u8 = 0b001;
+const FUNCT3_CSRRWI: u8 = 0b101;
+
+const TEXT: u64 = 0x1000;
+const HOOK_CSR: u16 = 0x8c0;
+const STORAGE_CSR: u16 = 0x8c1;
+/// Values the guest writes in a loop before the others.
+const COUNT: i32 = 3;
+/// Value the host returns for hook CSR reads.
+const HOST_VALUE: u64 = 0x123;
+const STORED: u8 = 0x15;
+
+const fn csr_op(funct3: u8, rd: u8, rs1: u8, csr: u16) -> u32 {
+    encode_i(OPCODE_SYSTEM, rd, funct3, rs1, csr as i32)
+}
+
+/// Writes `1..=COUNT`, then -1, to the hook CSR; reads it and writes back
+/// the value plus one; sets the storage CSR; exits with 0.
+fn csr_elf<X: Xlen>() -> Vec<u8> {
+    let text = [
+        addi(REG_T0, REG_ZERO, 0),
+        addi(REG_T1, REG_ZERO, COUNT),
+        // loop: csrw hook, ++t0
+        addi(REG_T0, REG_T0, 1),
+        csr_op(FUNCT3_CSRRW, REG_ZERO, REG_T0, HOOK_CSR),
+        encode_b(OPCODE_BRANCH, FUNCT3_BNE, REG_T0, REG_T1, -8),
+        addi(REG_T0, REG_ZERO, -1),
+        csr_op(FUNCT3_CSRRW, REG_ZERO, REG_T0, HOOK_CSR),
+        // csrr a0, hook; csrw hook, a0 + 1
+        csr_op(FUNCT3_CSRRS, REG_A0, REG_ZERO, HOOK_CSR),
+        addi(REG_A0, REG_A0, 1),
+        csr_op(FUNCT3_CSRRW, REG_ZERO, REG_A0, HOOK_CSR),
+        csr_op(FUNCT3_CSRRWI, REG_ZERO, STORED, STORAGE_CSR),
+        addi(REG_A0, REG_ZERO, 0),
+        ECALL,
+    ];
+    guest::text_elf::<X>(TEXT, &text).build()
+}
+
+/// CSR accesses seen by the host.
+#[derive(Clone, Default)]
+struct Recorder {
+    writes: Arc<Mutex<Vec<(u16, u64)>>>,
+    reads: Arc<Mutex<Vec<u16>>>,
+}
+
+impl CsrHook for Recorder {
+    fn read(&mut self, csr: u16) -> u64 {
+        self.reads.lock().expect("lock").push(csr);
+        HOST_VALUE
+    }
+
+    fn write(&mut self, csr: u16, value: u64) {
+        self.writes.lock().expect("lock").push((csr, value));
+    }
+}
+
+fn run_channel<X: Xlen>(all_ones: u64) {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = temp.path().join("csr.elf");
+    std::fs::write(&elf, csr_elf::<X>()).expect("write ELF");
+    let out = temp.path().join("csr");
+    let options = CompileOptions::new()
+        .with_quiet(true)
+        .with_custom_csrs(vec![
+            CustomCsr::hook(HOOK_CSR),
+            CustomCsr::storage(STORAGE_CSR),
+        ]);
+    rvr::compile_with_options(&elf, &out, &options).expect("compile");
+
+    let recorder = Recorder::default();
+    let mut runner = Runner::load(&out, &elf).expect("load runner");
+    runner.set_csr_hook(recorder.clone());
+    let result = runner.run().expect("run guest");
+    assert_eq!(result.exit_code, 0);
+
+    let expected: Vec<(u16, u64)> = (1..=3)
+        .chain([all_ones, HOST_VALUE + 1])
+        .map(|value| (HOOK_CSR, value))
+        .collect();
+    assert_eq!(*recorder.writes.lock().expect("lock"), expected);
+    assert_eq!(*recorder.reads.lock().expect("lock"), [HOOK_CSR]);
+    assert_eq!(runner.get_csr(STORAGE_CSR), u64::from(STORED));
+}
+
+#[test]
+fn test_hook_csr_channel_rv32() {
+    run_channel::<Rv32>(u64::from(u32::MAX));
+}
+
+#[test]
+fn test_hook_csr_channel_rv64() {
+    run_channel::<Rv64>(u64::MAX);
+}
@@ -0,0 +1,48 @@
+//! Dead register write elimination: a write overwritten before any read is
+//! dropped, except in builds that can stop or be inspected between
+//! instructions.
+
+use guest::{ECALL, li};
+use rvr::test_support::guest;
+use rvr::{ElfImage, EmitConfig, InstretMode, Pipeline};
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{REG_A0, REG_A7, REG_T0, Rv64};
+
+const TEXT: u64 = 0x1000;
+
+/// `t0 = 1; t0 = 2; exit(0)`: the first write to t0 is dead.
+fn dead_write_elf() -> Vec<u8> {
+    let text = [
+        li(REG_T0, 1),
+        li(REG_T0, 2),
+        li(REG_A0, 0),
+        li(REG_A7, SYS_EXIT),
+        ECALL,
+    ];
+    guest::text_elf::<Rv64>(TEXT, &text).build()
+}
+
+fn dead_writes_removed(config: EmitConfig<Rv64>) -> usize {
+    let image = ElfImage::parse(&dead_write_elf()).expect("parse ELF");
+    let mut pipeline = Pipeline::<Rv64>::new(image, config);
+    pipeline.build_cfg().expect("build CFG");
+    pipeline.lift_to_ir().expect("lift");
+    pipeline.stats().dead_writes_removed
+}
+
+#[test]
+fn test_dead_write_removed() {
+    assert_eq!(dead_writes_removed(EmitConfig::default()), 1);
+    assert_eq!(
+        dead_writes_removed(EmitConfig::default().with_optimize_ir(false)),
+        0
+    );
+}
+
+#[test]
+fn test_dead_writes_kept_when_debuggable() {
+    let per_instruction = EmitConfig::default().with_instret_mode(InstretMode::PerInstruction);
+    assert_eq!(dead_writes_removed(per_instruction), 0);
+    let guest_pc_map = EmitConfig::default().with_guest_pc_map(true);
+    assert_eq!(dead_writes_removed(guest_pc_map), 0);
+}
@@ -0,0 +1,97 @@
+//! Unsupported-instruction diagnostics: RVV instructions outside the lifted
+//! subset on a reachable fall-through path and in dead code of a function
+//! are both listed, and fail the lift under `strict_decode`.
+
+use guest::{ECALL, FUNCT3_BEQ, OPCODE_BRANCH, OPCODE_JALR};
+use rvr::test_support::guest;
+use rvr::{EmitConfig, Error, LiftFailureKind, Pipeline};
+use rvr_elf::{ElfImage, STT_FUNC};
+use rvr_isa::{REG_A0, REG_RA, REG_ZERO, Rv64, encode_b, encode_i};
+
+const TEXT: u64 = 0x1000;
+/// `vsub.vv` the entry block falls through to when `a0 != 0`.
+const VSUB_PC: u64 = TEXT + 4;
+/// `vlse32.v` after the `ret` of `vec_kernel`, reached by no control flow.
+const VLSE_PC: u64 = TEXT + 20;
+
+/// `vsub.vv v1, v2, v3`
+const VSUB: u32 = 0x0A21_80D7;
+/// `vlse32.v v0, (a0), a1`
+const VLSE32: u32 = 0x0AB5_6007;
+
+/// `if a0 == 0 { exit } else { vsub.vv; exit }`, plus a `vec_kernel` with
+/// a strided vector load after its return.
+fn fixture_image() -> ElfImage<Rv64> {
+    let text = [
+        encode_b(OPCODE_BRANCH, FUNCT3_BEQ, REG_A0, REG_ZERO, 12),
+        VSUB,
+        ECALL,
+        ECALL,
+        // vec_kernel:
+        encode_i(OPCODE_JALR, REG_ZERO, 0, REG_RA, 0),
+        VLSE32,
+    ];
+    let elf = guest::text_elf::<Rv64>(TEXT, &text)
+        .with_symbol("_start", TEXT, STT_FUNC)
+        .with_symbol("vec_kernel", TEXT + 16, STT_FUNC)
+        .build();
+    let mut image = ElfImage::<Rv64>::parse(&elf).expect("parse fixture");
+    // The writer emits unsized symbols; give both functions their extent
+    for symbol in &mut image.symbols {
+        symbol.size = if symbol.name == "_start" { 16 } else { 8 };
+    }
+    image
+}
+
+fn lift(config: EmitConfig<Rv64>) -> (Pipeline<Rv64>, rvr::Result<()>) {
+    let mut pipeline = Pipeline::new(fixture_image(), config);
+    pipeline.build_cfg().expect("build CFG");
+    let result = pipeline.lift_to_ir();
+    (pipeline, result)
+}
+
+#[test]
+fn test_unsupported_instructions_are_listed() {
+    let (pipeline, result) = lift(EmitConfig::default());
+    result.expect("lift without strict decode");
+
+    let unsupported = pipeline.stats().unsupported;
+    let found: Vec<_> = unsupported
+        .iter()
+        .map(|d| {
+            (
+                d.pc,
+                d.mnemonic.as_deref(),
+                d.extension,
+                d.symbol.as_deref(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (VSUB_PC, Some("vsub.vv"), Some("V"), Some("_start")),
+            (VLSE_PC, Some("vlse32.v"), Some("V"), Some("vec_kernel")),
+        ]
+    );
+    assert!(
+        unsupported
+            .iter()
+            .all(|d| d.kind == LiftFailureKind::Undecodable)
+    );
+    assert_eq!(unsupported[0].raw, VSUB.to_le_bytes());
+}
+
+#[test]
+fn test_strict_decode_fails_lift() {
+    let (_, result) = lift(EmitConfig::default().with_strict_decode(true));
+    match result {
+        Err(Error::UnsupportedInstructions(unsupported)) => {
+            assert_eq!(unsupported.len(), 2);
+            let message = Error::UnsupportedInstructions(unsupported).to_string();
+            assert!(message.contains("0x1004"), "{message}");
+            assert!(message.contains("hint:"), "{message}");
+        }
+        other => panic!("expected UnsupportedInstructions, got {other:?}"),
+    }
+}
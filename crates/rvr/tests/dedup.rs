@@ -0,0 +1,119 @@
+//! Identical-block deduplication: a guest with many copies of one function,
+//! as monomorphization produces.
+
+use std::path::{Path, PathBuf};
+
+use guest::{ECALL, FUNCT3_ADD, FUNCT3_SLLI, OPCODE_JAL, OPCODE_JALR, OPCODE_OP, OPCODE_OP_IMM};
+use rvr::test_support::guest;
+use rvr::{CompileOptions, Runner};
+use rvr_elf::{ElfWriter, PF_R, PF_X};
+use rvr_isa::{REG_A0, REG_A1, REG_RA, REG_ZERO, Rv64, encode_i, encode_j, encode_r};
+
+const TEXT_BASE: u64 = 0x8000_0000;
+/// Copies of the function.
+const COPIES: usize = 16;
+
+const RET: u32 = encode_i(OPCODE_JALR, REG_ZERO, 0, REG_RA, 0);
+/// Instruction size in bytes.
+const INSN: i32 = 4;
+
+/// `f(x) = 3x + 3`, one copy per call.
+const FUNCTION: [u32; 4] = [
+    encode_i(OPCODE_OP_IMM, REG_A1, FUNCT3_SLLI, REG_A0, 1),
+    encode_r(OPCODE_OP, REG_A0, FUNCT3_ADD, REG_A0, REG_A1, 0),
+    encode_i(OPCODE_OP_IMM, REG_A0, FUNCT3_ADD, REG_A0, 3),
+    RET,
+];
+
+/// Exit code: `a0` after calling every copy twice, starting from 0.
+fn expected_exit() -> u8 {
+    let a0 = (0..2 * COPIES).fold(0u64, |x, _| 3 * x + 3);
+    a0.to_le_bytes()[0]
+}
+
+/// `a0 = 0; f_0(); f_0(); ...; f_{COPIES-1}(); f_{COPIES-1}(); exit(a0)`.
+///
+/// Each copy has two callers, so it stays a block of its own.
+fn fixture_elf() -> Vec<u8> {
+    let calls = i32::try_from(COPIES).expect("copies fit i32");
+    let function_len = i32::try_from(FUNCTION.len()).expect("function fits i32") * INSN;
+    // a0 = 0, the calls, ecall
+    let main_len = (2 + 2 * calls) * INSN;
+
+    let mut text = vec![encode_i(OPCODE_OP_IMM, REG_A0, FUNCT3_ADD, REG_ZERO, 0)];
+    for i in 0..2 * calls {
+        let pc = (1 + i) * INSN;
+        let target = main_len + (i / 2) * function_len;
+        text.push(encode_j(OPCODE_JAL, REG_RA, target - pc));
+    }
+    text.push(ECALL);
+    for _ in 0..COPIES {
+        text.extend(FUNCTION);
+    }
+
+    let text = guest::code(&text);
+    ElfWriter::<Rv64>::new(TEXT_BASE)
+        .with_segment(TEXT_BASE, PF_R | PF_X, text)
+        .build()
+}
+
+fn write_fixture(dir: &Path) -> PathBuf {
+    let elf = dir.join("dedup.elf");
+    std::fs::write(&elf, fixture_elf()).expect("write fixture");
+    elf
+}
+
+fn options(dedup: bool) -> CompileOptions {
+    CompileOptions::new()
+        .with_quiet(true)
+        .with_dedup_blocks(dedup)
+}
+
+/// Total size of the generated partition files.
+fn partition_bytes(dir: &Path) -> u64 {
+    std::fs::read_dir(dir)
+        .expect("read output")
+        .map(|entry| entry.expect("dir entry").path())
+        .filter(|path| {
+            path.extension().is_some_and(|ext| ext == "c")
+                && path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .is_some_and(|stem| stem.contains("_part"))
+        })
+        .map(|path| std::fs::metadata(path).expect("metadata").len())
+        .sum()
+}
+
+#[test]
+fn test_dedup_shrinks_emitted_c() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = write_fixture(temp.path());
+    let plain = temp.path().join("plain");
+    let dedup = temp.path().join("dedup");
+
+    rvr::lift_to_c_with_options(&elf, &plain, &options(false)).expect("lift");
+    rvr::lift_to_c_with_options(&elf, &dedup, &options(true)).expect("lift with dedup");
+
+    let plain_bytes = partition_bytes(&plain);
+    let dedup_bytes = partition_bytes(&dedup);
+    println!("partition C bytes: {plain_bytes} -> {dedup_bytes}");
+    assert!(dedup_bytes < plain_bytes, "{dedup_bytes} >= {plain_bytes}");
+}
+
+#[test]
+fn test_dedup_guest_runs() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = write_fixture(temp.path());
+    let out = temp.path().join("dedup");
+
+    let report = rvr::compile_with_report(&elf, &out, &options(true)).expect("compile");
+    // Every copy but the first is an alias of it
+    assert_eq!(report.dedup.groups, 1);
+    assert_eq!(report.dedup.aliases, COPIES - 1);
+    assert!(report.dedup.bytes_saved > 0);
+
+    let mut runner = Runner::load(&out, &elf).expect("load runner");
+    let result = runner.run().expect("run guest");
+    assert_eq!(result.exit_code, expected_exit());
+}
@@ -2,13 +2,35 @@ use std::path::{Path, PathBuf};
 
 use libtest_mimic::{Arguments, Failed, Trial};
 use rvr::test_support::diff;
-use rvr::{Compiler, Runner};
+use rvr::{Compiler, HotRegsMode, Runner};
 use rvr_elf::{ElfImage, get_elf_xlen};
 use rvr_emit::Backend;
 use rvr_ir::{Rv32, Rv64};
+use rvr_isa::REG_SP;
 
 mod test_utils;
 
+/// Instructions traced after restoring a snapshot.
+const SNAPSHOT_TRACE_STEPS: usize = 200;
+/// Instructions executed before taking the snapshot.
+const SNAPSHOT_SETUP_STEPS: usize = 20;
+/// Bytes scribbled below the stack pointer between restores.
+const SNAPSHOT_SCRIBBLE_LEN: usize = 256;
+
+/// Instructions per suspend slice in the C vs x86 comparison.
+const SUSPEND_SLICE: u64 = 1000;
+/// Instruction cap for the C vs x86 comparison.
+const SUSPEND_MAX_INSTRS: u64 = 1_000_000;
+
+/// One traced instruction: (pc, opcode, rd, `rd_value`, `mem_access`).
+type TraceStep = (
+    u64,
+    Option<u32>,
+    Option<u8>,
+    Option<u64>,
+    Option<(u64, u64, u8, bool)>,
+);
+
 fn main() {
     let mut args = Arguments::from_args();
     test_utils::cap_threads(&mut args);
@@ -18,6 +40,10 @@ fn main() {
         Trial::test("diff_block_vs_linear_c", run_block_vs_linear),
         Trial::test("diff_checkpoint_c", run_checkpoint),
         Trial::test("diff_pure_c", run_pure_c),
+        Trial::test("diff_snapshot_restore_c", run_snapshot_restore),
+        Trial::test("diff_multi_run_reset_c", run_multi_run_reset),
+        Trial::test("diff_suspend_slices_c_x86", run_suspend_slices),
+        Trial::test("diff_hot_regs_per_function_c", run_hot_regs_per_function),
     ];
 
     libtest_mimic::run(&args, trials).exit();
@@ -44,6 +70,7 @@ fn run_lockstep() -> Result<(), Failed> {
     let config = diff::CompareConfig {
         strict_reg_writes: true,
         strict_mem_access: true,
+        strict_csrs: false,
     };
 
     let result = diff::compare_lockstep(&mut ref_exec, &mut test_exec, &config, Some(200));
@@ -76,6 +103,7 @@ fn run_block_vs_linear() -> Result<(), Failed> {
     let config = diff::CompareConfig {
         strict_reg_writes: true,
         strict_mem_access: true,
+        strict_csrs: false,
     };
 
     let result =
@@ -195,6 +223,262 @@ fn run_pure_c_for<X: rvr_ir::Xlen>(
     Ok(())
 }
 
+fn run_snapshot_restore() -> Result<(), Failed> {
+    let Some(elf_path) = diff_elf_path() else {
+        return Ok(());
+    };
+
+    let temp = tempfile::tempdir().map_err(|e| Failed::from(format!("tempdir: {e}")))?;
+    let lib_dir = temp.path().join("snapshot");
+
+    let compiler = Compiler::default();
+    diff::compile_for_diff(&elf_path, &lib_dir, Backend::C, &compiler).map_err(Failed::from)?;
+
+    let mut runner =
+        Runner::load(&lib_dir, &elf_path).map_err(|e| Failed::from(format!("load: {e}")))?;
+    runner.prepare();
+    let entry = runner.entry_point();
+    runner.set_pc(entry);
+    trace_steps(&mut runner, SNAPSHOT_SETUP_STEPS);
+
+    let snapshot = runner
+        .snapshot()
+        .map_err(|e| Failed::from(format!("snapshot: {e}")))?;
+    let first = trace_steps(&mut runner, SNAPSHOT_TRACE_STEPS);
+
+    // Clobber registers and stack so the second run only matches if restore undoes it.
+    let sp = runner.get_register(REG_SP as usize);
+    let scribble = [0xA5u8; SNAPSHOT_SCRIBBLE_LEN];
+    let _ = runner.write_memory(sp.saturating_sub(scribble.len() as u64), &scribble);
+    for reg in 1..runner.num_regs() {
+        runner.set_register(reg, u64::MAX);
+    }
+
+    runner
+        .restore(&snapshot)
+        .map_err(|e| Failed::from(format!("restore: {e}")))?;
+    if (runner.get_pc(), runner.instret()) != (snapshot.pc(), snapshot.instret()) {
+        return Err(Failed::from("restore did not reset pc/instret"));
+    }
+    let second = trace_steps(&mut runner, SNAPSHOT_TRACE_STEPS);
+
+    if first.is_empty() {
+        return Err(Failed::from("no instructions traced after snapshot"));
+    }
+    if let Some(idx) = first.iter().zip(&second).position(|(a, b)| a != b) {
+        return Err(Failed::from(format!(
+            "restored runs diverge at step {idx}: {:?} vs {:?}",
+            first[idx], second[idx]
+        )));
+    }
+    if first.len() != second.len() {
+        return Err(Failed::from(format!(
+            "restored runs differ in length: {} vs {}",
+            first.len(),
+            second.len()
+        )));
+    }
+
+    Ok(())
+}
+
+/// Repeated runs after `reset_memory_fast` trace and end like the first.
+fn run_multi_run_reset() -> Result<(), Failed> {
+    const RUNS: usize = 3;
+    let Some(elf_path) = diff_elf_path() else {
+        return Ok(());
+    };
+
+    let temp = tempfile::tempdir().map_err(|e| Failed::from(format!("tempdir: {e}")))?;
+    let lib_dir = temp.path().join("reset");
+
+    let compiler = Compiler::default();
+    diff::compile_for_diff(&elf_path, &lib_dir, Backend::C, &compiler).map_err(Failed::from)?;
+
+    let mut runner =
+        Runner::load(&lib_dir, &elf_path).map_err(|e| Failed::from(format!("load: {e}")))?;
+    let entry = runner.entry_point();
+    let mut traces = Vec::with_capacity(RUNS);
+    for run in 0..RUNS {
+        let fast = runner.reset_memory_fast();
+        if run > 0 && !fast {
+            return Err(Failed::from(format!(
+                "run {run} re-initialized memory in full"
+            )));
+        }
+        runner.set_pc(entry);
+        traces.push(trace_steps(&mut runner, SNAPSHOT_TRACE_STEPS));
+
+        // Scribble on the stack so later runs only match if the reset undoes it.
+        let sp = runner.get_register(REG_SP as usize);
+        let scribble = [0xA5u8; SNAPSHOT_SCRIBBLE_LEN];
+        let _ = runner.write_memory(sp.saturating_sub(scribble.len() as u64), &scribble);
+    }
+
+    let first = &traces[0];
+    if first.is_empty() {
+        return Err(Failed::from("no instructions traced"));
+    }
+    for (run, trace) in traces.iter().enumerate().skip(1) {
+        if trace != first {
+            let idx = first.iter().zip(trace).position(|(a, b)| a != b);
+            return Err(Failed::from(format!(
+                "run {run} diverges from run 0 at step {idx:?}"
+            )));
+        }
+    }
+
+    let results = runner
+        .run_multiple(RUNS)
+        .map_err(|e| Failed::from(format!("run_multiple: {e}")))?;
+    let outcome = |r: &rvr::RunResult| (r.exit_code, r.exit_reason, r.instret);
+    if let Some(run) = results
+        .iter()
+        .position(|r| outcome(r) != outcome(&results[0]))
+    {
+        return Err(Failed::from(format!(
+            "run {run} ended with {:?}, run 0 with {:?}",
+            outcome(&results[run]),
+            outcome(&results[0])
+        )));
+    }
+
+    Ok(())
+}
+
+/// Resume C and x86 builds in suspend slices; the x86 backend keeps guest
+/// registers in host registers, which must survive every suspend/resume.
+fn run_suspend_slices() -> Result<(), Failed> {
+    if !cfg!(target_arch = "x86_64") {
+        return Ok(());
+    }
+    let Some(elf_path) = riscv_test_elf_path() else {
+        return Ok(());
+    };
+
+    let temp = tempfile::tempdir().map_err(|e| Failed::from(format!("tempdir: {e}")))?;
+    let c_dir = temp.path().join("c");
+    let x86_dir = temp.path().join("x86");
+
+    let compiler = Compiler::default();
+    diff::compile_for_suspend(&elf_path, &c_dir, Backend::C, &compiler).map_err(Failed::from)?;
+    diff::compile_for_suspend(&elf_path, &x86_dir, Backend::X86Asm, &compiler)
+        .map_err(Failed::from)?;
+
+    let mut c_runner =
+        Runner::load(&c_dir, &elf_path).map_err(|e| Failed::from(format!("c load: {e}")))?;
+    let mut x86_runner =
+        Runner::load(&x86_dir, &elf_path).map_err(|e| Failed::from(format!("x86 load: {e}")))?;
+
+    c_runner.prepare();
+    x86_runner.prepare();
+
+    let entry = c_runner.entry_point();
+    c_runner.set_pc(entry);
+    x86_runner.set_pc(entry);
+
+    let result = diff::compare_suspend_slices(
+        &mut c_runner,
+        &mut x86_runner,
+        SUSPEND_SLICE,
+        Some(SUSPEND_MAX_INSTRS),
+    );
+    if let Some(div) = result.divergence {
+        return Err(Failed::from(format!(
+            "divergence in slice at instret {}: {} (c pc {:#x}, x86 pc {:#x})",
+            div.index, div.kind, div.expected.pc, div.actual.pc
+        )));
+    }
+    if !c_runner.has_exited() || c_runner.exit_code() != 0 {
+        return Err(Failed::from(format!(
+            "guest did not pass (exited {}, code {})",
+            c_runner.has_exited(),
+            c_runner.exit_code()
+        )));
+    }
+
+    Ok(())
+}
+
+/// Resume global and per-function hot register builds in suspend slices;
+/// every suspend saves the registers hot in the block it stops in.
+fn run_hot_regs_per_function() -> Result<(), Failed> {
+    let Some(elf_path) = riscv_test_elf_path() else {
+        return Ok(());
+    };
+
+    let temp = tempfile::tempdir().map_err(|e| Failed::from(format!("tempdir: {e}")))?;
+    let global_dir = temp.path().join("global");
+    let function_dir = temp.path().join("function");
+
+    let compiler = Compiler::default();
+    diff::compile_for_suspend_hot_regs(&elf_path, &global_dir, &compiler, HotRegsMode::Global)
+        .map_err(Failed::from)?;
+    diff::compile_for_suspend_hot_regs(
+        &elf_path,
+        &function_dir,
+        &compiler,
+        HotRegsMode::PerFunction,
+    )
+    .map_err(Failed::from)?;
+
+    let mut global = Runner::load(&global_dir, &elf_path)
+        .map_err(|e| Failed::from(format!("global load: {e}")))?;
+    let mut function = Runner::load(&function_dir, &elf_path)
+        .map_err(|e| Failed::from(format!("per-function load: {e}")))?;
+
+    global.prepare();
+    function.prepare();
+
+    let entry = global.entry_point();
+    global.set_pc(entry);
+    function.set_pc(entry);
+
+    let result = diff::compare_suspend_slices(
+        &mut global,
+        &mut function,
+        SUSPEND_SLICE,
+        Some(SUSPEND_MAX_INSTRS),
+    );
+    if let Some(div) = result.divergence {
+        return Err(Failed::from(format!(
+            "divergence in slice at instret {}: {} (global pc {:#x}, per-function pc {:#x})",
+            div.index, div.kind, div.expected.pc, div.actual.pc
+        )));
+    }
+    if !function.has_exited() || function.exit_code() != 0 {
+        return Err(Failed::from(format!(
+            "guest did not pass (exited {}, code {})",
+            function.has_exited(),
+            function.exit_code()
+        )));
+    }
+
+    Ok(())
+}
+
+/// Single-step up to `steps` instructions, recording the diff tracer output.
+fn trace_steps(runner: &mut Runner, steps: usize) -> Vec<TraceStep> {
+    let mut trace = Vec::with_capacity(steps);
+    for _ in 0..steps {
+        let pc = runner.get_pc();
+        let target = runner.instret() + 1;
+        runner.set_target_instret(target);
+        runner.clear_exit();
+        if runner.execute_from(pc).is_err() {
+            break;
+        }
+        trace.push((
+            pc,
+            runner.diff_traced_opcode(),
+            runner.diff_traced_rd(),
+            runner.diff_traced_rd_value(),
+            runner.diff_traced_mem(),
+        ));
+    }
+    trace
+}
+
 fn diff_elf_path() -> Option<PathBuf> {
     let root = workspace_root();
     let candidates = [
@@ -212,6 +496,17 @@ fn diff_elf_path() -> Option<PathBuf> {
     None
 }
 
+fn riscv_test_elf_path() -> Option<PathBuf> {
+    let root = workspace_root();
+    [
+        "bin/riscv-tests/rv64ui-p-add",
+        "bin/riscv-tests/rv32ui-p-add",
+    ]
+    .into_iter()
+    .map(|rel| root.join(rel))
+    .find(|path| path.exists())
+}
+
 fn find_library_in_dir(dir: &Path) -> Option<PathBuf> {
     let entries = std::fs::read_dir(dir).ok()?;
     for entry in entries.flatten() {
@@ -0,0 +1,64 @@
+//! Relative dispatch table: with `with_dispatch_table_relative` the flat
+//! table holds offsets from the table, and both the entry lookup and an
+//! indirect jump through it land on the right block.
+
+use guest::{FUNCT3_LD, OPCODE_JALR, OPCODE_LOAD, OPCODE_LUI, OPCODE_OP_IMM, OPCODE_SYSTEM, li};
+use rvr::test_support::guest;
+use rvr::{CompileOptions, Runner};
+use rvr_elf::PF_R;
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{REG_A0, REG_A1, REG_A7, REG_T0, REG_ZERO, Rv64, encode_i, encode_u};
+
+const TEXT: u64 = 0x1000;
+/// Holds the address of `TARGET`, so the jump can only go through the table.
+const DATA: u64 = 0x2000;
+const TARGET: u64 = TEXT + 16;
+const EXIT_CODE: i32 = 42;
+
+/// Loads `TARGET` from `DATA`, jumps to it and exits with `EXIT_CODE`.
+fn guest_elf() -> Vec<u8> {
+    let text = [
+        encode_u(OPCODE_LUI, REG_A1, u32::try_from(DATA >> 12).unwrap()),
+        encode_i(OPCODE_LOAD, REG_T0, FUNCT3_LD, REG_A1, 0),
+        encode_i(OPCODE_JALR, REG_ZERO, 0, REG_T0, 0),
+        // Skipped by the jump
+        encode_i(OPCODE_OP_IMM, REG_A0, 0, REG_ZERO, 1),
+        encode_i(OPCODE_OP_IMM, REG_A0, 0, REG_ZERO, EXIT_CODE),
+        li(REG_A7, SYS_EXIT),
+        encode_i(OPCODE_SYSTEM, REG_ZERO, 0, REG_ZERO, 0),
+    ];
+    guest::text_elf::<Rv64>(TEXT, &text)
+        .with_segment(DATA, PF_R, TARGET.to_le_bytes().to_vec())
+        .build()
+}
+
+/// Exit code of the guest and its generated dispatch.c.
+fn run(relative: bool) -> (i32, String) {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = temp.path().join("jump.elf");
+    std::fs::write(&elf, guest_elf()).expect("write ELF");
+    let out = temp.path().join("jump");
+    let options = CompileOptions::new()
+        .with_quiet(true)
+        .with_dispatch_table_relative(relative);
+    rvr::compile_with_options(&elf, &out, &options).expect("compile");
+    let dispatch = std::fs::read_to_string(out.join("jump_dispatch.c")).expect("read dispatch.c");
+    let mut runner = Runner::load(&out, &elf).expect("load runner");
+    let exit_code = runner.run().expect("run guest").exit_code;
+    (i32::from(exit_code), dispatch)
+}
+
+#[test]
+fn test_relative_dispatch_table() {
+    let (exit_code, dispatch) = run(true);
+    assert_eq!(exit_code, EXIT_CODE);
+    assert!(dispatch.contains(".long B_0000000000001010 - dispatch_table"));
+    assert!(!dispatch.contains("const rv_fn dispatch_table[]"));
+}
+
+#[test]
+fn test_absolute_dispatch_table() {
+    let (exit_code, dispatch) = run(false);
+    assert_eq!(exit_code, EXIT_CODE);
+    assert!(dispatch.contains("const rv_fn dispatch_table[]"));
+}
@@ -0,0 +1,82 @@
+//! In-memory emission: `Pipeline::emit_c_artifacts` renders byte for byte
+//! the files `Pipeline::emit_c` writes into an empty directory.
+
+use std::collections::BTreeMap;
+
+use guest::{ECALL, OPCODE_OP_IMM, li};
+use rvr::test_support::guest;
+use rvr::{CDialect, EmitConfig, Pipeline, SyscallMode};
+use rvr_elf::{ElfImage, PF_R, PF_W, STT_FUNC};
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{REG_A0, REG_A7, REG_ZERO, Rv64, encode_i};
+
+const TEXT: u64 = 0x1000;
+/// Initialized data, so the project has a memory source.
+const DATA: u64 = 0x2_0000;
+const BASE_NAME: &str = "artifacts";
+
+/// `exit(7)`, plus a data segment.
+fn guest_image() -> ElfImage<Rv64> {
+    let text = [
+        encode_i(OPCODE_OP_IMM, REG_A0, 0, REG_ZERO, 7),
+        li(REG_A7, SYS_EXIT),
+        ECALL,
+    ];
+    let elf = guest::text_elf::<Rv64>(TEXT, &text)
+        .with_segment(DATA, PF_R | PF_W, b"initialized data".to_vec())
+        .with_symbol("_start", TEXT, STT_FUNC)
+        .build();
+    ElfImage::parse(&elf).expect("parse guest")
+}
+
+/// Emit the guest both ways with `dialect` and compare the files.
+fn assert_artifacts_match_files(dialect: CDialect) {
+    let mut config = EmitConfig::<Rv64>::default();
+    config.syscall_mode = SyscallMode::Linux;
+    config.c_dialect = dialect;
+    let mut pipeline = Pipeline::new(guest_image(), config);
+    pipeline.build_cfg().expect("build CFG");
+    pipeline.lift_to_ir().expect("lift");
+
+    let temp = tempfile::tempdir().expect("tempdir");
+    pipeline.emit_c(temp.path(), BASE_NAME).expect("emit C");
+    let artifacts = pipeline
+        .emit_c_artifacts(BASE_NAME)
+        .expect("emit artifacts");
+
+    let expected: BTreeMap<String, Vec<u8>> = artifacts
+        .text_files()
+        .map(|(name, contents)| (name.to_string(), contents.as_bytes().to_vec()))
+        .chain(artifacts.segment_bins.iter().cloned())
+        .collect();
+    // Sources, headers, segment binaries and the Makefile on disk
+    let emitted: BTreeMap<String, Vec<u8>> = std::fs::read_dir(temp.path())
+        .expect("list output")
+        .map(|entry| entry.expect("dir entry").path())
+        .filter(|path| {
+            path.file_name().is_some_and(|name| name == "Makefile")
+                || path
+                    .extension()
+                    .is_some_and(|ext| ["c", "h", "bin", "lz4"].iter().any(|e| ext == *e))
+        })
+        .map(|path| {
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            (name, std::fs::read(&path).expect("read emitted file"))
+        })
+        .collect();
+    assert_eq!(emitted, expected);
+    assert!(expected.contains_key(&format!("{BASE_NAME}_memory.c")));
+    assert!(expected.contains_key(&format!("{BASE_NAME}_syscalls.c")));
+    assert_eq!(artifacts.header_name, format!("{BASE_NAME}.h"));
+    assert_eq!(artifacts.stats.partitions, artifacts.parts.len());
+}
+
+#[test]
+fn test_artifacts_match_files() {
+    assert_artifacts_match_files(CDialect::default());
+}
+
+#[test]
+fn test_portable_artifacts_match_files() {
+    assert_artifacts_match_files(CDialect::Portable);
+}
@@ -0,0 +1,109 @@
+//! Guest command line: `Runner::with_args` puts `argc`/`argv` on the initial
+//! stack, and `rvr exec` compiles, runs and forwards the exit code.
+
+use std::path::Path;
+use std::process::{Command, Output};
+
+use guest::{ECALL, FUNCT3_BU, FUNCT3_D, OPCODE_LOAD, OPCODE_LUI, OPCODE_STORE, li};
+use rvr::test_support::guest;
+use rvr::{CompileOptions, Runner, SyscallMode};
+use rvr_elf::{PF_R, PF_W, STT_FUNC, STT_NOTYPE};
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{REG_A0, REG_A1, REG_A7, REG_S1, REG_S2, REG_SP, Rv64, encode_i, encode_s, encode_u};
+
+const TEXT: u64 = 0x1000;
+/// Slot the guest stores `argc` in.
+const RESULT: u64 = 0x2_0000;
+const STACK_TOP: u64 = 0x10_0000;
+
+/// `*RESULT = argc`, exit with `argv[1][0]`.
+fn guest_elf() -> Vec<u8> {
+    let text = [
+        encode_i(OPCODE_LOAD, REG_S1, FUNCT3_D, REG_SP, 0),
+        encode_u(OPCODE_LUI, REG_S2, u32::try_from(RESULT >> 12).unwrap()),
+        encode_s(OPCODE_STORE, FUNCT3_D, REG_S2, REG_S1, 0),
+        encode_i(OPCODE_LOAD, REG_A1, FUNCT3_D, REG_SP, 16),
+        encode_i(OPCODE_LOAD, REG_A0, FUNCT3_BU, REG_A1, 0),
+        li(REG_A7, SYS_EXIT),
+        ECALL,
+    ];
+    guest::text_elf::<Rv64>(TEXT, &text)
+        .with_segment(RESULT, PF_R | PF_W, vec![0; 8])
+        .with_symbol("_start", TEXT, STT_FUNC)
+        .with_symbol("__stack_top", STACK_TOP, STT_NOTYPE)
+        .build()
+}
+
+fn write_elf(dir: &Path) -> std::path::PathBuf {
+    let path = dir.join("args.elf");
+    std::fs::write(&path, guest_elf()).expect("write ELF");
+    path
+}
+
+/// Run `rvr exec` with a cache under `dir`, logging without colors.
+fn rvr_exec(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rvr"))
+        .arg("exec")
+        .args(args)
+        .env("RVR_CACHE_DIR", dir.join("cache"))
+        .env("NO_COLOR", "1")
+        .output()
+        .expect("run rvr")
+}
+
+#[test]
+fn test_runner_passes_args() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = write_elf(temp.path());
+    let out = temp.path().join("out");
+    let options = CompileOptions::new()
+        .with_quiet(true)
+        .with_syscall_mode(SyscallMode::Linux);
+    rvr::compile_with_options(&elf, &out, &options).expect("compile");
+    let mut runner = Runner::load(&out, &elf)
+        .expect("load runner")
+        .with_args(["args", "Z", "more"]);
+
+    let result = runner.run().expect("run guest");
+    assert_eq!(result.exit_code, b'Z');
+    let mut argc = [0; 8];
+    assert_eq!(runner.read_memory(RESULT, &mut argc), 8);
+    assert_eq!(u64::from_le_bytes(argc), 3);
+}
+
+#[test]
+fn test_exec_forwards_exit_code() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = write_elf(temp.path());
+    let elf = elf.to_str().unwrap();
+    let output = rvr_exec(
+        temp.path(),
+        &["--syscalls", "linux", "--quiet", elf, "--", "A"],
+    );
+    assert_eq!(output.status.code(), Some(i32::from(b'A')));
+
+    // The build was cached; a second run reuses it
+    let cached = std::fs::read_dir(temp.path().join("cache")).expect("cache dir");
+    assert!(cached.count() > 0);
+    let output = rvr_exec(temp.path(), &["--syscalls", "linux", elf, "--", "B"]);
+    assert_eq!(output.status.code(), Some(i32::from(b'B')));
+}
+
+#[test]
+fn test_exec_keeps_output_on_failure() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = temp.path().join("broken.elf");
+    std::fs::write(&elf, b"not an ELF").expect("write file");
+
+    let output = rvr_exec(temp.path(), &[elf.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    let log = String::from_utf8_lossy(&output.stdout);
+    let kept = log
+        .lines()
+        .find(|line| line.contains("kept the compiled output"))
+        .and_then(|line| line.split("path=").nth(1))
+        .expect("kept path in log");
+    let kept = Path::new(kept.trim());
+    assert!(kept.is_dir(), "{}", kept.display());
+    std::fs::remove_dir_all(kept).expect("remove kept output");
+}
@@ -0,0 +1,117 @@
+//! Exit reasons: a riscv-tests style HTIF exit decodes to a pass or to the
+//! failing test number, and an illegal instruction to a trap, instead of a
+//! bare exit code.
+
+use guest::{FUNCT3_SW, OPCODE_LUI, OPCODE_OP_IMM, OPCODE_STORE};
+use rvr::test_support::guest;
+use rvr::{Backend, CompileOptions, ExitReason, RunResult, Runner, TrapCause};
+use rvr_isa::{REG_A0, REG_T0, REG_ZERO, Rv32, encode_i, encode_s, encode_u};
+
+/// `unimp` (`csrrw zero, cycle, zero`), a write to a read-only CSR.
+const UNIMP: u32 = 0xc000_1073;
+
+const TEXT: u64 = 0x8000_0000;
+const TOHOST: u64 = 0x8000_1000;
+const FAILED_TEST: u64 = 5;
+
+/// Writes `value` to `tohost`; traps if that does not stop the guest.
+fn htif_elf(value: u64) -> Vec<u8> {
+    let text = [
+        encode_u(OPCODE_LUI, REG_T0, u32::try_from(TOHOST >> 12).unwrap()),
+        encode_i(
+            OPCODE_OP_IMM,
+            REG_A0,
+            0,
+            REG_ZERO,
+            i32::try_from(value).unwrap(),
+        ),
+        encode_s(OPCODE_STORE, FUNCT3_SW, REG_T0, REG_A0, 0),
+        UNIMP,
+    ];
+    elf(&text)
+}
+
+fn elf(text: &[u32]) -> Vec<u8> {
+    guest::text_elf::<Rv32>(TEXT, text).build()
+}
+
+fn run(elf_bytes: &[u8], options: CompileOptions) -> RunResult {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = temp.path().join("guest.elf");
+    std::fs::write(&elf, elf_bytes).expect("write ELF");
+    let out = temp.path().join("out");
+    let options = options.with_quiet(true);
+    rvr::compile_with_options(&elf, &out, &options).expect("compile");
+    let mut runner = Runner::load(&out, &elf).expect("load runner");
+    runner.run().expect("run guest")
+}
+
+#[test]
+fn test_htif_pass() {
+    let result = run(&htif_elf(1), CompileOptions::new().with_htif(true));
+    assert_eq!(result.exit_reason, ExitReason::Exited(0));
+}
+
+#[test]
+fn test_htif_fail_reports_test_number() {
+    let result = run(
+        &htif_elf((FAILED_TEST << 1) | 1),
+        CompileOptions::new().with_htif(true),
+    );
+    assert_eq!(
+        result.exit_reason,
+        ExitReason::HtifFail {
+            test_num: FAILED_TEST
+        }
+    );
+    assert_eq!(u64::from(result.exit_code), FAILED_TEST);
+}
+
+#[test]
+fn test_illegal_instruction_traps() {
+    let result = run(&elf(&[UNIMP]), CompileOptions::new());
+    assert_eq!(
+        result.exit_reason,
+        ExitReason::Trapped {
+            cause: TrapCause::IllegalInstruction,
+            pc: TEXT,
+            addr: 0,
+        }
+    );
+    assert_eq!(
+        result.exit_reason.to_string(),
+        "trapped at pc 0x80000000: illegal instruction"
+    );
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn test_illegal_instruction_traps_x86() {
+    let result = run(
+        &elf(&[UNIMP]),
+        CompileOptions::new().with_backend(Backend::X86Asm),
+    );
+    assert!(matches!(
+        result.exit_reason,
+        ExitReason::Trapped {
+            cause: TrapCause::IllegalInstruction,
+            ..
+        }
+    ));
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn test_illegal_instruction_traps_arm64() {
+    let result = run(
+        &elf(&[UNIMP]),
+        CompileOptions::new().with_backend(Backend::ARM64Asm),
+    );
+    assert!(matches!(
+        result.exit_reason,
+        ExitReason::Trapped {
+            cause: TrapCause::IllegalInstruction,
+            ..
+        }
+    ));
+}
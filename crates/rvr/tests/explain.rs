@@ -0,0 +1,88 @@
+//! `Pipeline::explain` snapshot for a block with an override and a merged
+//! successor.
+//!
+//! Set `RVR_UPDATE_SNAPSHOTS=1` to rewrite the snapshot after an intended
+//! change to the report or the emitted C.
+
+use std::path::Path;
+
+use guest::{ECALL, FUNCT3_ADDI, FUNCT7_MULDIV, OPCODE_JAL, OPCODE_OP, OPCODE_OP_IMM};
+use rvr::test_support::guest;
+use rvr::{Error, Pipeline};
+use rvr_elf::{ElfImage, ElfWriter, PF_R, PF_X};
+use rvr_ir::InstrIR;
+use rvr_isa::{
+    DecodedInstr, ExtensionRegistry, InstructionOverride, OP_MUL, REG_A0, REG_A1, REG_ZERO, Rv64,
+    encode_i, encode_j, encode_r,
+};
+
+const TEXT_BASE: u64 = 0x1000;
+const SNAPSHOT: &str = "tests/snapshots/explain.txt";
+
+const FUNCT3_MUL: u8 = 0b000;
+
+/// `a0 = 5; a1 = a0 * a0; j +4; a0 = a1 + 1; exit(a0)`.
+///
+/// The jump's target has no other predecessor, so it is merged into the
+/// entry block.
+fn fixture_elf() -> Vec<u8> {
+    let text = [
+        encode_i(OPCODE_OP_IMM, REG_A0, FUNCT3_ADDI, REG_ZERO, 5),
+        encode_r(OPCODE_OP, REG_A1, FUNCT3_MUL, REG_A0, REG_A0, FUNCT7_MULDIV),
+        encode_j(OPCODE_JAL, REG_ZERO, 4),
+        encode_i(OPCODE_OP_IMM, REG_A0, FUNCT3_ADDI, REG_A1, 1),
+        ECALL,
+    ];
+    let text = guest::code(&text);
+    ElfWriter::<Rv64>::new(TEXT_BASE)
+        .with_segment(TEXT_BASE, PF_R | PF_X, text)
+        .build()
+}
+
+/// Override that keeps the default lift (it still shows up as an override).
+struct PassThrough;
+
+impl InstructionOverride<Rv64> for PassThrough {
+    fn lift(
+        &self,
+        instr: &DecodedInstr<Rv64>,
+        default_lift: &dyn Fn(&DecodedInstr<Rv64>) -> InstrIR<Rv64>,
+    ) -> InstrIR<Rv64> {
+        default_lift(instr)
+    }
+}
+
+fn lifted_pipeline() -> Pipeline<Rv64> {
+    let image = ElfImage::<Rv64>::parse(&fixture_elf()).expect("parse fixture");
+    let registry = ExtensionRegistry::standard().with_override(OP_MUL, PassThrough);
+    let mut pipeline = Pipeline::with_registry(image, rvr::EmitConfig::default(), registry);
+    pipeline.build_cfg().expect("build CFG");
+    pipeline.lift_to_ir().expect("lift");
+    pipeline
+}
+
+#[test]
+fn test_explain_snapshot() {
+    let pipeline = lifted_pipeline();
+    // PC of the merged `addi a0, a1, 1`
+    let report = pipeline
+        .explain(TEXT_BASE + 12)
+        .expect("explain")
+        .to_string();
+
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(SNAPSHOT);
+    if std::env::var_os("RVR_UPDATE_SNAPSHOTS").is_some() {
+        std::fs::write(&path, &report).expect("write snapshot");
+    }
+    let expected = std::fs::read_to_string(&path).expect("read snapshot");
+    assert_eq!(report, expected, "explanation differs from {SNAPSHOT}");
+}
+
+#[test]
+fn test_explain_unknown_pc() {
+    let pipeline = lifted_pipeline();
+    assert!(matches!(
+        pipeline.explain(0x8000_0000),
+        Err(Error::NoBlockAtPc(0x8000_0000))
+    ));
+}
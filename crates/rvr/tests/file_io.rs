@@ -0,0 +1,304 @@
+//! File I/O in `SyscallMode::Linux`: a guest opens a file under a
+//! preopened directory, reads it through `readv` in small chunks the way a
+//! buffered reader does, seeks, stats and closes it, and `readv`/`writev`
+//! reject iovecs that leave guest memory before moving any byte.
+
+use std::io::Cursor;
+
+use guest::{
+    ECALL, FUNCT3_BNE, FUNCT3_BU, FUNCT3_D, OPCODE_BRANCH, OPCODE_JAL, OPCODE_LOAD, OPCODE_OP,
+    OPCODE_STORE, SharedWriter, addi, li, lui,
+};
+use rvr::Runner;
+use rvr::test_support::guest;
+use rvr_elf::{ElfWriter, PF_R, PF_W, STT_FUNC};
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::syscalls::syscall_nr::{
+    SYS_CLOSE, SYS_FSTAT, SYS_LSEEK, SYS_OPENAT, SYS_READ, SYS_READV, SYS_WRITEV,
+};
+use rvr_isa::{
+    REG_A0, REG_A1, REG_A2, REG_A3, REG_A7, REG_S1, REG_S2, REG_S3, REG_S4, REG_T0, REG_T1, REG_T2,
+    REG_ZERO, Rv64, encode_b, encode_i, encode_j, encode_r, encode_s,
+};
+
+const FUNCT3_BGE: u8 = 0b101;
+const AT_FDCWD: i32 = -100;
+const SEEK_END: u64 = 2;
+const EBADF: i64 = 9;
+const EFAULT: i64 = 14;
+
+const TEXT: u64 = 0x1000;
+/// NUL-terminated path of the fixture.
+const PATH: u64 = 0x2_0000;
+/// Two iovecs splitting `BUF` into chunks of `CHUNK0` and `CHUNK1` bytes.
+const IOV: u64 = 0x2_1000;
+/// Two iovecs over `MESSAGE`, written to stdout.
+const OUT_IOV: u64 = 0x2_2000;
+const MESSAGE: &[u8] = b"hello world\n";
+const BUF: u64 = 0x3_0000;
+const CHUNK0: u64 = 7;
+const CHUNK1: u64 = 25;
+const STAT: u64 = 0x3_1000;
+/// Where the guest reads the last bytes of the fixture.
+const TAIL: u64 = 0x3_2000;
+/// Eight-byte slots the guests store syscall results in.
+const RESULT: u64 = 0x4_0000;
+/// Guest memory size the edge iovecs are placed against.
+const MEMORY_SIZE: u64 = 1 << 32;
+
+/// Offsets of the generic Linux `struct stat` fields the guest reads.
+const ST_MODE: usize = 16;
+const ST_SIZE: usize = 48;
+const ST_BLKSIZE: usize = 56;
+
+const fn add(rd: u8, rs1: u8, rs2: u8) -> u32 {
+    encode_r(OPCODE_OP, rd, 0, rs1, rs2, 0)
+}
+
+const fn lbu(rd: u8, rs1: u8, imm: i32) -> u32 {
+    encode_i(OPCODE_LOAD, rd, FUNCT3_BU, rs1, imm)
+}
+
+const fn ld(rd: u8, rs1: u8, imm: i32) -> u32 {
+    encode_i(OPCODE_LOAD, rd, FUNCT3_D, rs1, imm)
+}
+
+const fn sd(rs1: u8, rs2: u8, imm: i32) -> u32 {
+    encode_s(OPCODE_STORE, FUNCT3_D, rs1, rs2, imm)
+}
+
+/// Store a0 in result slot `slot`.
+const fn store_result(slot: i32) -> u32 {
+    sd(REG_S2, REG_A0, slot * 8)
+}
+
+/// Open the fixture, fstat it, `readv` it to EOF while checksumming, read
+/// its last 4 bytes after `lseek(-4, SEEK_END)`, close it twice and
+/// `writev` the message; every result lands in a slot.
+fn checksum_text() -> Vec<u32> {
+    vec![
+        // s1 = openat(AT_FDCWD, PATH, O_RDONLY)
+        addi(REG_A0, REG_ZERO, AT_FDCWD),
+        lui(REG_A1, PATH),
+        li(REG_A2, 0),
+        li(REG_A3, 0),
+        li(REG_A7, SYS_OPENAT),
+        ECALL,
+        addi(REG_S1, REG_A0, 0),
+        lui(REG_S2, RESULT),
+        store_result(0),
+        // fstat(s1, STAT)
+        addi(REG_A0, REG_S1, 0),
+        lui(REG_A1, STAT),
+        li(REG_A7, SYS_FSTAT),
+        ECALL,
+        store_result(1),
+        // s3 = sum of bytes, s4 = sum of running sums
+        li(REG_S3, 0),
+        li(REG_S4, 0),
+        // loop: a0 = readv(s1, IOV, 2); done if a0 <= 0
+        addi(REG_A0, REG_S1, 0),
+        lui(REG_A1, IOV),
+        li(REG_A2, 2),
+        li(REG_A7, SYS_READV),
+        ECALL,
+        encode_b(OPCODE_BRANCH, FUNCT3_BGE, REG_ZERO, REG_A0, 36),
+        lui(REG_T0, BUF),
+        add(REG_T1, REG_T0, REG_A0),
+        lbu(REG_T2, REG_T0, 0),
+        add(REG_S3, REG_S3, REG_T2),
+        add(REG_S4, REG_S4, REG_S3),
+        addi(REG_T0, REG_T0, 1),
+        encode_b(OPCODE_BRANCH, FUNCT3_BNE, REG_T0, REG_T1, -16),
+        encode_j(OPCODE_JAL, REG_ZERO, -52),
+        // done
+        store_result(2),
+        sd(REG_S2, REG_S3, 3 * 8),
+        sd(REG_S2, REG_S4, 4 * 8),
+        // lseek(s1, -4, SEEK_END)
+        addi(REG_A0, REG_S1, 0),
+        addi(REG_A1, REG_ZERO, -4),
+        li(REG_A2, SEEK_END),
+        li(REG_A7, SYS_LSEEK),
+        ECALL,
+        store_result(5),
+        // read(s1, TAIL, 8)
+        addi(REG_A0, REG_S1, 0),
+        lui(REG_A1, TAIL),
+        li(REG_A2, 8),
+        li(REG_A7, SYS_READ),
+        ECALL,
+        store_result(6),
+        // close(s1), twice
+        addi(REG_A0, REG_S1, 0),
+        li(REG_A7, SYS_CLOSE),
+        ECALL,
+        store_result(7),
+        addi(REG_A0, REG_S1, 0),
+        ECALL,
+        store_result(8),
+        // writev(1, OUT_IOV, 2)
+        li(REG_A0, 1),
+        lui(REG_A1, OUT_IOV),
+        li(REG_A2, 2),
+        li(REG_A7, SYS_WRITEV),
+        ECALL,
+        store_result(9),
+        li(REG_A0, 0),
+        li(REG_A7, SYS_EXIT),
+        ECALL,
+    ]
+}
+
+/// `writev(1, IOV, 2)` and `readv(0, IOV, 2)` with the second iovec
+/// straddling the end of guest memory, then `readv(0, edge, 1)` with the
+/// iovec array itself straddling it.
+fn edge_text() -> Vec<u32> {
+    vec![
+        lui(REG_S2, RESULT),
+        li(REG_A0, 1),
+        lui(REG_A1, IOV),
+        li(REG_A2, 2),
+        li(REG_A7, SYS_WRITEV),
+        ECALL,
+        store_result(0),
+        li(REG_A0, 0),
+        lui(REG_A1, IOV),
+        li(REG_A2, 2),
+        li(REG_A7, SYS_READV),
+        ECALL,
+        store_result(1),
+        lui(REG_T0, IOV),
+        ld(REG_A1, REG_T0, 32),
+        li(REG_A0, 0),
+        li(REG_A2, 1),
+        ECALL,
+        store_result(2),
+        li(REG_A0, 0),
+        li(REG_A7, SYS_EXIT),
+        ECALL,
+    ]
+}
+
+fn words(values: &[u64]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn checksum_elf() -> Vec<u8> {
+    let path = b"/data/fixture.bin\0".to_vec();
+    let iov = words(&[BUF, CHUNK0, BUF + CHUNK0, CHUNK1]);
+    let message = OUT_IOV + 0x100;
+    let mut out_iov = words(&[message, 6, message + 6, MESSAGE.len() as u64 - 6]);
+    out_iov.resize(0x100, 0);
+    out_iov.extend_from_slice(MESSAGE);
+    guest_elf(&checksum_text())
+        .with_segment(PATH, PF_R | PF_W, path)
+        .with_segment(IOV, PF_R | PF_W, iov)
+        .with_segment(OUT_IOV, PF_R | PF_W, out_iov)
+        .build()
+}
+
+/// Buffer of the first edge iovec, which must stay untouched.
+const EDGE_BUF: u64 = IOV + 0x100;
+
+fn edge_elf() -> Vec<u8> {
+    let mut iov = words(&[EDGE_BUF, 6, MEMORY_SIZE - 4, 16, MEMORY_SIZE - 8]);
+    iov.resize(0x100, 0);
+    iov.extend_from_slice(b"intact");
+    guest_elf(&edge_text())
+        .with_segment(IOV, PF_R | PF_W, iov)
+        .build()
+}
+
+fn guest_elf(text: &[u32]) -> ElfWriter<Rv64> {
+    guest::text_elf::<Rv64>(TEXT, text).with_symbol("_start", TEXT, STT_FUNC)
+}
+
+fn results<const N: usize>(runner: &Runner) -> [i64; N] {
+    let mut bytes = vec![0; N * 8];
+    assert_eq!(runner.read_memory(RESULT, &mut bytes), bytes.len());
+    std::array::from_fn(|i| i64::from_le_bytes(bytes[i * 8..i * 8 + 8].try_into().unwrap()))
+}
+
+#[test]
+fn test_readv_checksum_matches_host() {
+    // Not a multiple of the 32-byte readv chunk
+    let fixture: Vec<u8> = (0..1000u32).map(|i| (i * 7 + 3).to_le_bytes()[0]).collect();
+    let (sum, running) = fixture.iter().fold((0u64, 0u64), |(sum, running), &b| {
+        let sum = sum + u64::from(b);
+        (sum, running + sum)
+    });
+
+    let temp = tempfile::tempdir().expect("tempdir");
+    let data = temp.path().join("data");
+    std::fs::create_dir(&data).expect("create data dir");
+    std::fs::write(data.join("fixture.bin"), &fixture).expect("write fixture");
+    let compiled = guest::compile_linux(temp.path(), "file_io", &checksum_elf());
+    let mut runner = Runner::load(&compiled.out, &compiled.elf)
+        .expect("load runner")
+        .with_preopened_dir("/data", &data)
+        .expect("preopen");
+    let stdout = SharedWriter::default();
+    runner.set_stdout(stdout.clone());
+
+    let result = runner.run().expect("run guest");
+    assert_eq!(result.exit_code, 0);
+    let [
+        fd,
+        fstat,
+        last,
+        guest_sum,
+        guest_running,
+        seek,
+        tail,
+        close,
+        reclose,
+        written,
+    ] = results::<10>(&runner);
+    assert_eq!(fd, 4);
+    assert_eq!(fstat, 0);
+    assert_eq!(last, 0, "readv ends at EOF");
+    assert_eq!(
+        (guest_sum.cast_unsigned(), guest_running.cast_unsigned()),
+        (sum, running)
+    );
+    assert_eq!(seek, 996);
+    assert_eq!(tail, 4);
+    let mut tail_bytes = [0; 4];
+    assert_eq!(runner.read_memory(TAIL, &mut tail_bytes), 4);
+    assert_eq!(tail_bytes, fixture[996..]);
+    assert_eq!((close, reclose), (0, -EBADF));
+    assert_eq!(written, i64::try_from(MESSAGE.len()).unwrap());
+    assert_eq!(stdout.contents(), MESSAGE);
+
+    let mut stat = [0; 64];
+    assert_eq!(runner.read_memory(STAT, &mut stat), stat.len());
+    let field = |offset: usize, len: usize| {
+        let mut bytes = [0; 8];
+        bytes[..len].copy_from_slice(&stat[offset..offset + len]);
+        u64::from_le_bytes(bytes)
+    };
+    assert_eq!(field(ST_SIZE, 8), fixture.len() as u64);
+    assert_eq!(field(ST_MODE, 4) & 0o170_000, 0o100_000, "regular file");
+    assert!(field(ST_BLKSIZE, 4) > 0);
+}
+
+#[test]
+fn test_iovec_past_memory_end_is_efault() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let compiled = guest::compile_linux(temp.path(), "file_io", &edge_elf());
+    let mut runner = Runner::load(&compiled.out, &compiled.elf).expect("load runner");
+    assert_eq!(runner.memory_size() as u64, MEMORY_SIZE);
+    let stdout = SharedWriter::default();
+    runner.set_stdout(stdout.clone());
+    runner.set_stdin(Cursor::new(b"stdin data".to_vec()));
+
+    let result = runner.run().expect("run guest");
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(results::<3>(&runner), [-EFAULT; 3]);
+    // Nothing moved, not even through the valid first iovec
+    assert!(stdout.contents().is_empty());
+    let mut buf = [0; 6];
+    assert_eq!(runner.read_memory(EDGE_BUF, &mut buf), buf.len());
+    assert_eq!(&buf, b"intact");
+}
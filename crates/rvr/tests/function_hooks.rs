@@ -0,0 +1,149 @@
+//! Function hooks: a guest byte-sum `checksum` function replaced through
+//! `CompileOptions::with_function_hook` gives the same result as the guest
+//! code when a host closure serves it, in fewer instructions, and the other
+//! hook kinds skip or stop at the function.
+
+use std::path::{Path, PathBuf};
+
+use guest::{
+    ECALL, FUNCT3_BNE, FUNCT3_BU, FUNCT3_D, OPCODE_BRANCH, OPCODE_JAL, OPCODE_JALR, OPCODE_LOAD,
+    OPCODE_OP, OPCODE_STORE, addi, li, lui,
+};
+use rvr::test_support::guest;
+use rvr::{CompileOptions, ExitReason, HookKind, RunResult, Runner};
+use rvr_elf::{PF_R, PF_W, STT_FUNC};
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{
+    REG_A0, REG_A1, REG_A7, REG_RA, REG_T0, REG_T1, REG_T2, REG_ZERO, Rv64, encode_b, encode_i,
+    encode_j, encode_r, encode_s,
+};
+
+const RET: u32 = encode_i(OPCODE_JALR, REG_ZERO, 0, REG_RA, 0);
+const INSTR_SIZE: u64 = 4;
+
+const TEXT: u64 = 0x1000;
+/// Bytes summed by `checksum`, then the doubleword slot for its result.
+const DATA: u64 = 0x2000;
+const LEN: i32 = 64;
+const RESULT: u64 = DATA + LEN as u64;
+/// Index of `checksum` in the text.
+const CHECKSUM: usize = 7;
+const REPLACE_CODE: u8 = 7;
+
+fn offset(from: usize, to: usize) -> i32 {
+    (i32::try_from(to).unwrap() - i32::try_from(from).unwrap()) * 4
+}
+
+/// `*RESULT = checksum(DATA, LEN)`, exit with the low byte of the sum.
+fn guest_elf() -> Vec<u8> {
+    let text = [
+        lui(REG_A0, DATA),
+        addi(REG_A1, REG_ZERO, LEN),
+        encode_j(OPCODE_JAL, REG_RA, offset(2, CHECKSUM)),
+        lui(REG_T0, DATA),
+        encode_s(OPCODE_STORE, FUNCT3_D, REG_T0, REG_A0, LEN),
+        li(REG_A7, SYS_EXIT),
+        ECALL,
+        // checksum(a0 = buf, a1 = len): sum of the bytes
+        addi(REG_T1, REG_ZERO, 0),
+        encode_i(OPCODE_LOAD, REG_T2, FUNCT3_BU, REG_A0, 0),
+        encode_r(OPCODE_OP, REG_T1, 0, REG_T1, REG_T2, 0),
+        addi(REG_A0, REG_A0, 1),
+        addi(REG_A1, REG_A1, -1),
+        encode_b(OPCODE_BRANCH, FUNCT3_BNE, REG_A1, REG_ZERO, offset(12, 8)),
+        addi(REG_A0, REG_T1, 0),
+        RET,
+    ];
+    let checksum_len = (text.len() - CHECKSUM) as u64 * INSTR_SIZE;
+    let mut data: Vec<u8> = (1..=u8::try_from(LEN).unwrap()).collect();
+    data.extend([0; 8]);
+    guest::text_elf::<Rv64>(TEXT, &text)
+        .with_segment(DATA, PF_R | PF_W, data)
+        .with_symbol("_start", TEXT, STT_FUNC)
+        .with_function(
+            "checksum",
+            TEXT + CHECKSUM as u64 * INSTR_SIZE,
+            checksum_len,
+        )
+        .build()
+}
+
+fn compile(dir: &Path, name: &str, hook: Option<HookKind>) -> (PathBuf, PathBuf) {
+    let elf = dir.join("hooks.elf");
+    std::fs::write(&elf, guest_elf()).expect("write ELF");
+    let out = dir.join(name);
+    let mut options = CompileOptions::new().with_quiet(true);
+    if let Some(kind) = hook {
+        options = options.with_function_hook("checksum", kind);
+    }
+    rvr::compile_with_options(&elf, &out, &options).expect("compile");
+    (elf, out)
+}
+
+/// Run and return the result and the stored checksum.
+fn run(runner: &mut Runner) -> (RunResult, u64) {
+    let result = runner.run().expect("run guest");
+    let mut sum = [0; 8];
+    assert_eq!(runner.read_memory(RESULT, &mut sum), sum.len());
+    (result, u64::from_le_bytes(sum))
+}
+
+fn host_checksum(runner: &mut Runner) {
+    runner.register_hook(
+        "checksum",
+        Box::new(|ctx| {
+            let sum = ctx
+                .memory(ctx.arg(0), ctx.arg(1))
+                .map_or(0, |bytes| bytes.iter().map(|&b| u64::from(b)).sum());
+            ctx.set_arg(0, sum);
+        }),
+    );
+}
+
+#[test]
+fn test_host_hook_matches_guest() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let (elf, out) = compile(temp.path(), "guest", None);
+    let (guest, guest_sum) = run(&mut Runner::load(&out, &elf).expect("load runner"));
+    assert_ne!(guest_sum, 0);
+
+    let (elf, out) = compile(temp.path(), "hooked", Some(HookKind::HostCall));
+    let mut runner = Runner::load(&out, &elf).expect("load runner");
+    host_checksum(&mut runner);
+    let (hooked, hooked_sum) = run(&mut runner);
+    assert_eq!(hooked_sum, guest_sum);
+    assert_eq!(hooked.exit_reason, guest.exit_reason);
+    assert!(
+        hooked.instret < guest.instret,
+        "hooked {} vs guest {}",
+        hooked.instret,
+        guest.instret
+    );
+}
+
+#[test]
+fn test_unserved_host_hook_stops() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let (elf, out) = compile(temp.path(), "hooked", Some(HookKind::HostCall));
+    let mut runner = Runner::load(&out, &elf).expect("load runner");
+    let result = runner.run().expect("run guest");
+    assert_eq!(result.exit_reason, ExitReason::HostStop);
+}
+
+#[test]
+fn test_nop_and_replace_hooks() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let (elf, out) = compile(temp.path(), "nop", Some(HookKind::Nop));
+    // `checksum` returns its first argument unchanged
+    let (_, sum) = run(&mut Runner::load(&out, &elf).expect("load runner"));
+    assert_eq!(sum, DATA);
+
+    let (elf, out) = compile(
+        temp.path(),
+        "replace",
+        Some(HookKind::Replace(REPLACE_CODE)),
+    );
+    let (result, sum) = run(&mut Runner::load(&out, &elf).expect("load runner"));
+    assert_eq!(result.exit_reason, ExitReason::Exited(REPLACE_CODE));
+    assert_eq!(sum, 0);
+}
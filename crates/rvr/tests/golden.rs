@@ -0,0 +1,135 @@
+//! Golden traces: a run of a towers-of-Hanoi guest compiled with the golden
+//! tracer records register checksums at sampled block entries; a build
+//! embedding them runs through, and one embedding a corrupted trace stops
+//! at the trace point that no longer matches.
+//!
+//! The guest recurses like the towers benchmark, which cannot be used
+//! itself: the prebuilt benchmark binaries are not checked out here.
+
+use std::path::Path;
+
+use guest::{
+    ECALL, FUNCT3_BEQ, FUNCT3_D, OPCODE_BRANCH, OPCODE_JAL, OPCODE_JALR, OPCODE_LOAD, OPCODE_LUI,
+    OPCODE_OP, OPCODE_STORE, addi, li,
+};
+use rvr::test_support::guest;
+use rvr::{CompileOptions, ExitReason, Runner, TracerConfig};
+use rvr_emit::c::{GoldenTrace, content_hash};
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{
+    REG_A0, REG_A1, REG_A2, REG_A3, REG_A7, REG_RA, REG_S1, REG_S2, REG_SP, REG_T0, REG_ZERO, Rv64,
+    encode_b, encode_i, encode_j, encode_r, encode_s, encode_u,
+};
+
+const TEXT: u64 = 0x1000;
+const STACK_TOP: u32 = 0x10_0000;
+const DISKS: i32 = 7;
+/// The guest exits with the number of moves.
+const MOVES: u8 = (1 << DISKS) - 1;
+/// Blocks entered per trace point.
+const INTERVAL: u32 = 5;
+
+const fn sd(rs2: u8, offset: i32) -> u32 {
+    encode_s(OPCODE_STORE, FUNCT3_D, REG_SP, rs2, offset)
+}
+
+const fn ld(rd: u8, offset: i32) -> u32 {
+    encode_i(OPCODE_LOAD, rd, FUNCT3_D, REG_SP, offset)
+}
+
+/// `hanoi(DISKS, 1, 3, 2)` counting moves in `s1` (and summing the target
+/// pegs in `s2`), then `exit(s1)`.
+fn towers_elf() -> Vec<u8> {
+    let text = [
+        encode_u(OPCODE_LUI, REG_SP, STACK_TOP >> 12),
+        addi(REG_A0, REG_ZERO, DISKS),
+        addi(REG_A1, REG_ZERO, 1),
+        addi(REG_A2, REG_ZERO, 3),
+        addi(REG_A3, REG_ZERO, 2),
+        encode_j(OPCODE_JAL, REG_RA, 16),
+        addi(REG_A0, REG_S1, 0),
+        li(REG_A7, SYS_EXIT),
+        ECALL,
+        // hanoi(n = a0, from = a1, to = a2, via = a3)
+        encode_b(OPCODE_BRANCH, FUNCT3_BEQ, REG_A0, REG_ZERO, 100),
+        addi(REG_SP, REG_SP, -48),
+        sd(REG_RA, 0),
+        sd(REG_A0, 8),
+        sd(REG_A1, 16),
+        sd(REG_A2, 24),
+        sd(REG_A3, 32),
+        // hanoi(n - 1, from, via, to)
+        addi(REG_A0, REG_A0, -1),
+        addi(REG_T0, REG_A2, 0),
+        addi(REG_A2, REG_A3, 0),
+        addi(REG_A3, REG_T0, 0),
+        encode_j(OPCODE_JAL, REG_RA, -44),
+        ld(REG_A0, 8),
+        ld(REG_A1, 16),
+        ld(REG_A2, 24),
+        ld(REG_A3, 32),
+        // move a disk from `from` to `to`
+        addi(REG_S1, REG_S1, 1),
+        encode_r(OPCODE_OP, REG_S2, 0, REG_S2, REG_A2, 0),
+        // hanoi(n - 1, via, to, from)
+        addi(REG_A0, REG_A0, -1),
+        addi(REG_T0, REG_A1, 0),
+        addi(REG_A1, REG_A3, 0),
+        addi(REG_A3, REG_T0, 0),
+        encode_j(OPCODE_JAL, REG_RA, -88),
+        ld(REG_RA, 0),
+        addi(REG_SP, REG_SP, 48),
+        encode_i(OPCODE_JALR, REG_ZERO, 0, REG_RA, 0),
+    ];
+    guest::text_elf::<Rv64>(TEXT, &text).build()
+}
+
+fn compile_and_run(elf: &Path, out: &Path, options: &CompileOptions) -> ExitReason {
+    rvr::compile_with_options(elf, out, &options.clone().with_quiet(true)).expect("compile");
+    let mut runner = Runner::load(out, elf).expect("load runner");
+    runner.run().expect("run guest").exit_reason
+}
+
+#[test]
+fn test_golden_trace_checks_towers() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = temp.path().join("towers.elf");
+    let elf_data = towers_elf();
+    std::fs::write(&elf, &elf_data).expect("write ELF");
+
+    // Capture
+    let golden = temp.path().join("towers.golden");
+    // The only test in this binary, so no other thread reads the environment
+    unsafe { std::env::set_var("RVR_TRACE_FILE", &golden) };
+    let tracer = TracerConfig::golden().with_sample_interval(INTERVAL);
+    let capture = CompileOptions::new().with_tracer_config(tracer);
+    let reason = compile_and_run(&elf, &temp.path().join("capture"), &capture);
+    assert_eq!(reason, ExitReason::Exited(MOVES));
+
+    let trace = GoldenTrace::parse(&std::fs::read(&golden).expect("read trace")).expect("parse");
+    assert_eq!(trace.interval, INTERVAL);
+    assert_eq!(trace.elf_hash, content_hash(&elf_data));
+    assert!(trace.points.len() > 100, "{} points", trace.points.len());
+    assert_eq!(trace.points[0].pc, TEXT);
+
+    // Self-check against the golden run
+    let check = CompileOptions::new().with_embedded_golden(&golden);
+    let reason = compile_and_run(&elf, &temp.path().join("check"), &check);
+    assert_eq!(reason, ExitReason::Exited(MOVES));
+
+    // A diverging run stops at the first trace point that differs
+    let index = trace.points.len() / 2;
+    let mut diverged = trace.clone();
+    diverged.points[index].checksum ^= 1;
+    let diverged_path = temp.path().join("diverged.golden");
+    std::fs::write(&diverged_path, diverged.to_bytes()).expect("write trace");
+    let check = CompileOptions::new().with_embedded_golden(&diverged_path);
+    let reason = compile_and_run(&elf, &temp.path().join("diverged"), &check);
+    assert_eq!(
+        reason,
+        ExitReason::GoldenMismatch {
+            pc: trace.points[index].pc,
+            index: index as u64,
+        }
+    );
+}
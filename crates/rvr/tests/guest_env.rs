@@ -0,0 +1,188 @@
+//! Guest environment: `Runner::with_env` lays out `envp` and an `AT_RANDOM`
+//! auxv entry on the initial stack for RV32 and RV64 guests, and `rvr exec`
+//! takes `--env` and `--env-file`.
+
+use std::process::Command;
+
+use guest::{
+    ECALL, FUNCT3_BEQ, FUNCT3_BNE, FUNCT3_BU, FUNCT3_SLL, OPCODE_BRANCH, OPCODE_LOAD, OPCODE_LUI,
+    OPCODE_OP, OPCODE_OP_IMM, OPCODE_STORE, addi, li,
+};
+use rvr::Runner;
+use rvr::test_support::guest;
+use rvr_elf::{PF_R, PF_W, STT_FUNC, STT_NOTYPE};
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{
+    REG_A0, REG_A1, REG_A7, REG_S1, REG_S2, REG_SP, REG_T0, REG_T1, REG_T2, REG_ZERO, Rv32, Rv64,
+    Xlen, encode_b, encode_i, encode_r, encode_s, encode_u,
+};
+
+const AT_RANDOM: u64 = 25;
+
+const TEXT: u64 = 0x1000;
+/// Slot the guest stores the address of its auxiliary vector in.
+const RESULT: u64 = 0x2_0000;
+const STACK_TOP: u64 = 0x10_0000;
+/// Exit code of a guest without `N` in its environment.
+const MISSING: u8 = 1;
+
+const fn lbu(rd: u8, rs1: u8, imm: i32) -> u32 {
+    encode_i(OPCODE_LOAD, rd, FUNCT3_BU, rs1, imm)
+}
+
+const fn branch(funct3: u8, rs1: u8, rs2: u8, from: i32, to: i32) -> u32 {
+    encode_b(OPCODE_BRANCH, funct3, rs1, rs2, (to - from) * 4)
+}
+
+/// Find the first `envp` entry starting with `N=` and exit with the byte
+/// after it, storing where `auxv` starts; exit with [`MISSING`] without
+/// one. Loads and stores are pointer-sized.
+fn guest_elf<X: Xlen>() -> Vec<u8> {
+    let word = i32::from(X::VALUE / 8);
+    let (funct3, shift) = if word == 8 { (0b011, 3) } else { (0b010, 2) };
+    let load = |rd, rs1, imm| encode_i(OPCODE_LOAD, rd, funct3, rs1, imm);
+    // Instruction indices of the branch targets
+    let (scan_env, scan_auxv, missing) = (5, 15, 21);
+    let text = [
+        encode_u(OPCODE_LUI, REG_S2, u32::try_from(RESULT >> 12).unwrap()),
+        // s1 = envp = sp + (argc + 2) words
+        load(REG_S1, REG_SP, 0),
+        addi(REG_T0, REG_S1, 2),
+        encode_i(OPCODE_OP_IMM, REG_T0, FUNCT3_SLL, REG_T0, shift),
+        encode_r(OPCODE_OP, REG_S1, 0, REG_SP, REG_T0, 0),
+        // scan_env: a1 = *s1++, missing at the NULL
+        load(REG_A1, REG_S1, 0),
+        branch(FUNCT3_BEQ, REG_A1, REG_ZERO, 6, missing),
+        addi(REG_S1, REG_S1, word),
+        lbu(REG_T1, REG_A1, 0),
+        addi(REG_T2, REG_ZERO, i32::from(b'N')),
+        branch(FUNCT3_BNE, REG_T1, REG_T2, 10, scan_env),
+        lbu(REG_T1, REG_A1, 1),
+        addi(REG_T2, REG_ZERO, i32::from(b'=')),
+        branch(FUNCT3_BNE, REG_T1, REG_T2, 13, scan_env),
+        lbu(REG_A0, REG_A1, 2),
+        // scan_auxv: skip to past the envp NULL
+        load(REG_T0, REG_S1, 0),
+        addi(REG_S1, REG_S1, word),
+        branch(FUNCT3_BNE, REG_T0, REG_ZERO, 17, scan_auxv),
+        encode_s(OPCODE_STORE, funct3, REG_S2, REG_S1, 0),
+        li(REG_A7, SYS_EXIT),
+        ECALL,
+        // missing
+        addi(REG_A0, REG_ZERO, i32::from(MISSING)),
+        li(REG_A7, SYS_EXIT),
+        ECALL,
+    ];
+    guest::text_elf::<X>(TEXT, &text)
+        .with_segment(RESULT, PF_R | PF_W, vec![0; 8])
+        .with_symbol("_start", TEXT, STT_FUNC)
+        .with_symbol("__stack_top", STACK_TOP, STT_NOTYPE)
+        .build()
+}
+
+/// Pointer-sized little-endian word at `addr`.
+fn read_word(runner: &Runner, addr: u64, size: usize) -> u64 {
+    let mut bytes = [0; 8];
+    assert_eq!(runner.read_memory(addr, &mut bytes[..size]), size);
+    u64::from_le_bytes(bytes)
+}
+
+/// Run the guest with `N=*` among other variables and check its exit code
+/// and its `AT_RANDOM` entry.
+fn check_env<X: Xlen>() {
+    let size = usize::from(X::VALUE / 8);
+    let temp = tempfile::tempdir().expect("tempdir");
+    let compiled = guest::compile_linux(temp.path(), "env", &guest_elf::<X>());
+    let mut runner = Runner::load(&compiled.out, &compiled.elf)
+        .expect("load runner")
+        .with_args(["env"])
+        .with_env(&[("HOME", "/"), ("N", "!")])
+        .with_env(&[("N", "*"), ("TZ", "UTC")]);
+
+    let result = runner.run().expect("run guest");
+    assert_eq!(result.exit_code, b'*', "RV{}", X::VALUE);
+    let auxv = read_word(&runner, RESULT, size);
+    assert!(auxv < STACK_TOP);
+    assert_eq!(read_word(&runner, auxv, size), AT_RANDOM);
+    let random = read_word(&runner, auxv + size as u64, size);
+    let mut bytes = [0; 16];
+    assert_eq!(runner.read_memory(random, &mut bytes), 16);
+    assert_ne!(bytes, [0; 16]);
+    assert_eq!(
+        read_word(&runner, auxv + 2 * size as u64, size),
+        0,
+        "AT_NULL"
+    );
+
+    // Same bytes on every run
+    runner.run().expect("rerun guest");
+    let mut again = [0; 16];
+    assert_eq!(runner.read_memory(random, &mut again), 16);
+    assert_eq!(again, bytes);
+}
+
+#[test]
+fn test_env_rv64() {
+    check_env::<Rv64>();
+}
+
+#[test]
+fn test_env_rv32() {
+    check_env::<Rv32>();
+}
+
+#[test]
+fn test_env_unset() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let compiled = guest::compile_linux(temp.path(), "env", &guest_elf::<Rv64>());
+    let mut runner = Runner::load(&compiled.out, &compiled.elf)
+        .expect("load runner")
+        .with_env(&[("NN", "x")]);
+    let result = runner.run().expect("run guest");
+    assert_eq!(result.exit_code, MISSING);
+}
+
+#[test]
+fn test_env_file_errors() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let compiled = guest::compile_linux(temp.path(), "env", &guest_elf::<Rv64>());
+    let env_file = temp.path().join("bad.env");
+    std::fs::write(&env_file, "A=1\nB='open\n").expect("write env file");
+    let err = Runner::load(&compiled.out, &compiled.elf)
+        .expect("load runner")
+        .with_env_file(&env_file)
+        .err()
+        .expect("invalid env file");
+    assert!(
+        err.to_string()
+            .ends_with("line 2: unterminated single quote"),
+        "{err}"
+    );
+}
+
+#[test]
+fn test_exec_env_flags() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = temp.path().join("env.elf");
+    std::fs::write(&elf, guest_elf::<Rv64>()).expect("write ELF");
+    let env_file = temp.path().join("guest.env");
+    std::fs::write(&env_file, "# guest\nexport N=\"A\"\nHOME=/\n").expect("write env file");
+
+    let exec = |extra: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rvr"))
+            .args(["exec", "--syscalls", "linux", "--quiet"])
+            .arg("--env-file")
+            .arg(&env_file)
+            .args(extra)
+            .arg(&elf)
+            .env("RVR_CACHE_DIR", temp.path().join("cache"))
+            .env("NO_COLOR", "1")
+            .output()
+            .expect("run rvr")
+            .status
+            .code()
+    };
+    assert_eq!(exec(&[]), Some(i32::from(b'A')));
+    // --env overrides the file
+    assert_eq!(exec(&["--env", "N=B"]), Some(i32::from(b'B')));
+}
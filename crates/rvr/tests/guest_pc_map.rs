@@ -0,0 +1,76 @@
+//! Guest PC map: a library compiled with `with_guest_pc_map` has a
+//! `guest_pc.map` sidecar, and a host address in a block function resolves
+//! to a guest PC of that block.
+
+use std::path::Path;
+use std::process::Command;
+
+use guest::{OPCODE_OP_IMM, OPCODE_SYSTEM, li};
+use rvr::test_support::guest;
+use rvr::{CompileOptions, Runner, guest_pc_at};
+use rvr_elf::STT_FUNC;
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{REG_A0, REG_A7, REG_ZERO, Rv64, encode_i};
+
+const TEXT: u64 = 0x1000;
+const EXIT_CODE: u8 = 3;
+
+/// `exit(EXIT_CODE)`.
+fn guest_elf() -> Vec<u8> {
+    let text = [
+        encode_i(OPCODE_OP_IMM, REG_A0, 0, REG_ZERO, i32::from(EXIT_CODE)),
+        li(REG_A7, SYS_EXIT),
+        encode_i(OPCODE_SYSTEM, REG_ZERO, 0, REG_ZERO, 0),
+    ];
+    guest::text_elf::<Rv64>(TEXT, &text)
+        .with_symbol("_start", TEXT, STT_FUNC)
+        .build()
+}
+
+/// Address of `symbol` in `library`, from `nm`.
+fn symbol_addr(library: &Path, symbol: &str) -> u64 {
+    let output = Command::new("nm")
+        .arg("--defined-only")
+        .arg(library)
+        .output()
+        .expect("run nm");
+    let symbols = String::from_utf8_lossy(&output.stdout);
+    symbols
+        .lines()
+        .find_map(|line| {
+            let mut fields = line.split_whitespace();
+            let addr = fields.next()?;
+            (fields.nth(1)? == symbol).then(|| u64::from_str_radix(addr, 16).ok())?
+        })
+        .unwrap_or_else(|| panic!("{symbol} not in {}", library.display()))
+}
+
+#[test]
+fn test_host_addr_resolves_to_guest_pc() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = temp.path().join("exit.elf");
+    std::fs::write(&elf, guest_elf()).expect("write ELF");
+    let out = temp.path().join("out");
+    let options = CompileOptions::new()
+        .with_quiet(true)
+        .with_guest_pc_map(true);
+    let report = rvr::compile_with_report(&elf, &out, &options).expect("compile");
+
+    let map = std::fs::read_to_string(out.join("guest_pc.map")).expect("read map");
+    assert_eq!(map, "0x1000 addi\n0x1004 addi\n0x1008 ecall\n");
+
+    // Optimized code is scheduled freely: any instruction of the block
+    let block = symbol_addr(&report.library, "B_0000000000001000");
+    let pc = guest_pc_at(&report.library, block).expect("resolve address");
+    assert!(
+        pc.is_some_and(|pc| (TEXT..TEXT + 12).contains(&pc)),
+        "{pc:x?}"
+    );
+    assert_eq!(
+        guest_pc_at(&report.library, 0).expect("resolve address"),
+        None
+    );
+
+    let mut runner = Runner::load(&out, &elf).expect("load runner");
+    assert_eq!(runner.run().expect("run guest").exit_code, EXIT_CODE);
+}
@@ -0,0 +1,175 @@
+//! Guest unit tests: an image with a passing, a panicking and a
+//! non-terminating test, registered the way `rvr_rt::rvr_test!` does.
+
+use std::path::{Path, PathBuf};
+
+use guest::{ECALL, FUNCT3_SD, OPCODE_JAL, OPCODE_JALR, OPCODE_LUI, OPCODE_STORE, li};
+use rvr::test_support::guest;
+use rvr::{CompileOptions, GuestTestOptions, InstretMode, PANIC_EXIT_CODE, TestOutcome};
+use rvr_elf::{ElfWriter, GUEST_TESTS_SECTION, PF_R, PF_W, PF_X, STT_OBJECT};
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{
+    REG_A0, REG_A1, REG_A7, REG_RA, REG_ZERO, Rv64, encode_i, encode_j, encode_s, encode_u,
+};
+
+const TEXT_BASE: u64 = 0x1000_0000;
+const DATA_BASE: u64 = 0x1000_1000;
+/// Instruction budget for the non-terminating test.
+const MAX_INSNS: u64 = 10_000;
+/// Panic message as the `rvr-rt` handler formats it.
+const PANIC_MESSAGE: &str = "panicked at src/lib.rs:12:9:\nboom";
+
+const RET: u32 = encode_i(OPCODE_JALR, REG_ZERO, 0, REG_RA, 0);
+/// Instruction size in bytes.
+const INSN: u64 = 4;
+/// Bytes per `.rvr_tests` entry (three RV64 words).
+const ENTRY_SIZE: u64 = 24;
+
+/// Test names and the offsets of their functions in the text segment.
+const TESTS: [(&str, u64); 3] = [
+    ("tests::passes", 2 * INSN),
+    ("tests::panics", 4 * INSN),
+    ("tests::spins", 10 * INSN),
+];
+
+/// Data segment: the `.rvr_tests` entries, the panic message length and
+/// buffer, then the test names.
+fn data_segment(msg_len_addr: u64) -> Vec<u8> {
+    let msg_addr = msg_len_addr + 8;
+    let mut names_addr = msg_addr + PANIC_MESSAGE.len() as u64;
+    let mut data = Vec::new();
+    let mut names = Vec::new();
+    for (name, offset) in TESTS {
+        for word in [names_addr, name.len() as u64, TEXT_BASE + offset] {
+            data.extend_from_slice(&word.to_le_bytes());
+        }
+        names.extend_from_slice(name.as_bytes());
+        names_addr += name.len() as u64;
+    }
+    data.extend_from_slice(&0u64.to_le_bytes());
+    data.extend_from_slice(PANIC_MESSAGE.as_bytes());
+    data.extend_from_slice(&names);
+    data
+}
+
+fn fixture_elf() -> Vec<u8> {
+    let entries_size = ENTRY_SIZE * TESTS.len() as u64;
+    let msg_len_addr = DATA_BASE + entries_size;
+    let msg_len_offset = i32::try_from(entries_size).expect("offset fits i32");
+    let msg_len = PANIC_MESSAGE.len() as u64;
+    let data_hi = u32::try_from(DATA_BASE >> 12).expect("data base fits u32");
+
+    let text = [
+        // main: exit(0)
+        li(REG_A0, 0),
+        ECALL,
+        // passes: return
+        li(REG_A0, 1),
+        RET,
+        // panics: record the message length, exit(101)
+        encode_u(OPCODE_LUI, REG_A1, data_hi),
+        li(REG_A0, msg_len),
+        encode_s(OPCODE_STORE, FUNCT3_SD, REG_A1, REG_A0, msg_len_offset),
+        li(REG_A0, u64::from(PANIC_EXIT_CODE)),
+        li(REG_A7, SYS_EXIT),
+        ECALL,
+        // spins: loop forever
+        encode_j(OPCODE_JAL, REG_ZERO, 0),
+    ];
+    let text = guest::code(&text);
+
+    ElfWriter::<Rv64>::new(TEXT_BASE)
+        .with_segment(TEXT_BASE, PF_R | PF_X, text)
+        .with_segment(DATA_BASE, PF_R | PF_W, data_segment(msg_len_addr))
+        .with_section(GUEST_TESTS_SECTION, DATA_BASE, entries_size)
+        .with_symbol("RVR_TEST_PANIC_LEN", msg_len_addr, STT_OBJECT)
+        .with_symbol("RVR_TEST_PANIC_MSG", msg_len_addr + 8, STT_OBJECT)
+        .build()
+}
+
+fn write_fixture(dir: &Path) -> PathBuf {
+    let elf = dir.join("guest_test.elf");
+    std::fs::write(&elf, fixture_elf()).expect("write fixture");
+    elf
+}
+
+fn options() -> CompileOptions {
+    CompileOptions::new()
+        .with_quiet(true)
+        .with_export_functions(true)
+        .with_instret_mode(InstretMode::Suspend)
+}
+
+#[test]
+fn test_guest_tests_listed_and_lifted() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = write_fixture(temp.path());
+    let out = temp.path().join("guest_test");
+
+    let tests = rvr::list_guest_tests(&elf).expect("list tests");
+    let listed: Vec<_> = tests.iter().map(|t| (t.name.as_str(), t.addr)).collect();
+    let expected: Vec<_> = TESTS
+        .iter()
+        .map(|&(name, offset)| (name, TEXT_BASE + offset))
+        .collect();
+    assert_eq!(listed, expected);
+
+    // Nothing calls the tests, so they are only lifted as entry points
+    rvr::lift_to_c_with_options(&elf, &out, &options()).expect("lift");
+    let code: String = std::fs::read_dir(&out)
+        .expect("read output")
+        .map(|entry| entry.expect("dir entry").path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "c"))
+        .map(|path| std::fs::read_to_string(path).expect("read C"))
+        .collect();
+    for (_, offset) in TESTS {
+        let block = format!("B_{:016x}(", TEXT_BASE + offset);
+        assert!(code.contains(&block), "missing {block}");
+    }
+}
+
+#[test]
+fn test_guest_test_verdicts() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = write_fixture(temp.path());
+    let out = temp.path().join("guest_test");
+    rvr::compile_with_options(&elf, &out, &options()).expect("compile");
+
+    for jobs in [1, 3] {
+        let options = GuestTestOptions::new()
+            .with_max_insns(Some(MAX_INSNS))
+            .with_jobs(jobs);
+        let report = rvr::run_guest_tests(&out, &elf, &options).expect("run tests");
+
+        let outcomes: Vec<_> = report
+            .results
+            .iter()
+            .map(|r| (r.test.name.as_str(), &r.outcome))
+            .collect();
+        assert_eq!(
+            outcomes,
+            [
+                ("tests::passes", &TestOutcome::Passed),
+                (
+                    "tests::panics",
+                    &TestOutcome::Failed {
+                        message: PANIC_MESSAGE.to_string()
+                    }
+                ),
+                ("tests::spins", &TestOutcome::TimedOut),
+            ],
+            "jobs = {jobs}"
+        );
+        assert!(!report.success());
+        assert_eq!(
+            (report.passed(), report.failed(), report.timed_out()),
+            (1, 1, 1)
+        );
+    }
+
+    let options = GuestTestOptions::new().with_filter("passes");
+    let report = rvr::run_guest_tests(&out, &elf, &options).expect("run filtered");
+    assert_eq!(report.results.len(), 1);
+    assert_eq!(report.filtered_out, 2);
+    assert!(report.success());
+}
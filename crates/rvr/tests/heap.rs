@@ -0,0 +1,98 @@
+//! Heap limit: a guest that grows its heap past `with_heap` gets ENOMEM
+//! instead of running into the stack.
+
+use guest::{ECALL, FUNCT3_ADD, FUNCT3_BNE, OPCODE_BRANCH, OPCODE_LUI, OPCODE_OP, addi, li};
+use rvr::test_support::guest;
+use rvr::{CompileOptions, Error, Runner, SyscallMode};
+use rvr_isa::syscalls::syscall_nr::{SYS_BRK, SYS_EXIT, SYS_MMAP};
+use rvr_isa::{
+    REG_A0, REG_A1, REG_A7, REG_S0, REG_S1, REG_T0, REG_ZERO, Rv64, encode_b, encode_r, encode_u,
+};
+
+const ENOMEM: i32 = 12;
+
+const TEXT: u64 = 0x1000;
+/// Heap size, in 4 KiB pages for `lui`.
+const HEAP_PAGES: u32 = 2;
+const HEAP: u64 = (HEAP_PAGES as u64) << 12;
+
+const fn add(rd: u8, rs1: u8, rs2: u8) -> u32 {
+    encode_r(OPCODE_OP, rd, FUNCT3_ADD, rs1, rs2, 0)
+}
+
+const fn bne(rs1: u8, rs2: u8, offset: i32) -> u32 {
+    encode_b(OPCODE_BRANCH, FUNCT3_BNE, rs1, rs2, offset)
+}
+
+/// Exits 0 if brk past the heap keeps the old break, brk to the heap end
+/// succeeds and a further mmap fails with ENOMEM; else the failed step.
+fn heap_elf() -> Vec<u8> {
+    let text = [
+        // s0 = brk(0)
+        li(REG_A7, SYS_BRK),
+        addi(REG_A0, REG_ZERO, 0),
+        ECALL,
+        addi(REG_S0, REG_A0, 0),
+        // brk(s0 + HEAP + 1 page) must return s0
+        encode_u(OPCODE_LUI, REG_T0, HEAP_PAGES + 1),
+        add(REG_A0, REG_S0, REG_T0),
+        ECALL,
+        addi(REG_A1, REG_ZERO, 1),
+        bne(REG_A0, REG_S0, 60),
+        // brk(s0 + HEAP) must succeed
+        encode_u(OPCODE_LUI, REG_T0, HEAP_PAGES),
+        add(REG_S1, REG_S0, REG_T0),
+        addi(REG_A0, REG_S1, 0),
+        ECALL,
+        addi(REG_A1, REG_ZERO, 2),
+        bne(REG_A0, REG_S1, 36),
+        // mmap(0, 1 page, ...) must fail with -ENOMEM
+        li(REG_A7, SYS_MMAP),
+        addi(REG_A0, REG_ZERO, 0),
+        encode_u(OPCODE_LUI, REG_A1, 1),
+        ECALL,
+        addi(REG_A1, REG_ZERO, 3),
+        addi(REG_T0, REG_ZERO, -ENOMEM),
+        bne(REG_A0, REG_T0, 8),
+        addi(REG_A1, REG_ZERO, 0),
+        // exit(a1)
+        addi(REG_A0, REG_A1, 0),
+        li(REG_A7, SYS_EXIT),
+        ECALL,
+    ];
+    guest::text_elf::<Rv64>(TEXT, &text).build()
+}
+
+fn options() -> CompileOptions {
+    CompileOptions::new()
+        .with_quiet(true)
+        .with_syscall_mode(SyscallMode::Linux)
+}
+
+#[test]
+fn test_brk_past_heap_limit_fails() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = temp.path().join("heap.elf");
+    std::fs::write(&elf, heap_elf()).expect("write ELF");
+    let out = temp.path().join("heap");
+    let report = rvr::compile_with_report(&elf, &out, &options().with_heap(HEAP)).expect("compile");
+    let planned = report.memory_layout.expect("memory layout");
+    assert_eq!(planned.heap.end - planned.heap.start, HEAP);
+
+    let mut runner = Runner::load(&out, &elf).expect("load runner");
+    assert_eq!(runner.memory_layout(), Some(&planned));
+    let result = runner.run().expect("run guest");
+    assert_eq!(result.exit_code, 0);
+}
+
+#[test]
+fn test_heap_overlapping_stack_is_rejected() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = temp.path().join("heap.elf");
+    std::fs::write(&elf, heap_elf()).expect("write ELF");
+    let options = options()
+        .with_heap(0x8000_0000)
+        .with_stack_size(0x8000_0000);
+    let err = rvr::compile_with_report(&elf, &temp.path().join("heap"), &options).unwrap_err();
+    assert!(matches!(err, Error::MemoryLayout(_)), "{err}");
+}
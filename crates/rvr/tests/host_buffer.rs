@@ -0,0 +1,148 @@
+//! Host buffers: a guest checksums 64MB of input the host mapped into its
+//! memory, and writes the result to a second, writable buffer.
+
+use std::path::Path;
+use std::time::Instant;
+
+use guest::{
+    Compiled, FUNCT3_D, OPCODE_BRANCH, OPCODE_LOAD, OPCODE_LUI, OPCODE_OP, OPCODE_OP_IMM,
+    OPCODE_STORE, OPCODE_SYSTEM, li,
+};
+use rvr::test_support::guest;
+use rvr::{CompileOptions, HostBuffer, RunError, Runner};
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{
+    REG_A0, REG_A1, REG_A2, REG_A3, REG_A7, REG_T0, REG_ZERO, Rv64, encode_b, encode_i, encode_r,
+    encode_s, encode_u,
+};
+use rvr_state::page_size;
+
+const FUNCT3_BLTU: u8 = 0b110;
+const TEXT: u64 = 0x1000;
+/// Agreed guest address of the input.
+const INPUT: u64 = 0x1000_0000;
+const INPUT_LEN: usize = 64 << 20;
+/// Agreed guest address of the output, right after the input.
+const OUTPUT: u64 = INPUT + INPUT_LEN as u64;
+
+/// `lui` immediate of a page-aligned address.
+fn upper(addr: u64) -> u32 {
+    u32::try_from(addr >> 12).expect("address fits lui")
+}
+
+/// Sums the 64-bit words of the input and stores the sum at the output.
+fn guest_elf() -> Vec<u8> {
+    let text = [
+        encode_u(OPCODE_LUI, REG_A1, upper(INPUT)),
+        encode_u(OPCODE_LUI, REG_A2, upper(OUTPUT)),
+        encode_i(OPCODE_OP_IMM, REG_A3, 0, REG_ZERO, 0),
+        // loop: a3 += *a1; a1 += 8
+        encode_i(OPCODE_LOAD, REG_T0, FUNCT3_D, REG_A1, 0),
+        encode_r(OPCODE_OP, REG_A3, 0, REG_A3, REG_T0, 0),
+        encode_i(OPCODE_OP_IMM, REG_A1, 0, REG_A1, 8),
+        encode_b(OPCODE_BRANCH, FUNCT3_BLTU, REG_A1, REG_A2, -12),
+        encode_s(OPCODE_STORE, FUNCT3_D, REG_A2, REG_A3, 0),
+        encode_i(OPCODE_OP_IMM, REG_A0, 0, REG_ZERO, 0),
+        li(REG_A7, SYS_EXIT),
+        encode_i(OPCODE_SYSTEM, REG_ZERO, 0, REG_ZERO, 0),
+    ];
+    guest::text_elf::<Rv64>(TEXT, &text).build()
+}
+
+fn compile(dir: &Path) -> Compiled {
+    guest::compile(dir, "checksum", &guest_elf(), &CompileOptions::new())
+}
+
+fn fill(data: &mut [u8], seed: u64) -> u64 {
+    let mut sum = 0u64;
+    for (idx, word) in data.chunks_exact_mut(8).enumerate() {
+        let value = (idx as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ seed;
+        word.copy_from_slice(&value.to_le_bytes());
+        sum = sum.wrapping_add(value);
+    }
+    sum
+}
+
+fn read_u64(bytes: &[u8]) -> u64 {
+    u64::from_le_bytes(bytes[..8].try_into().expect("8 bytes"))
+}
+
+#[test]
+fn test_guest_checksums_host_buffer() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let compiled = compile(temp.path());
+    let mut runner = Runner::load(&compiled.out, &compiled.elf).expect("load runner");
+
+    let mut input = HostBuffer::new(INPUT_LEN).expect("input buffer");
+    let output = HostBuffer::new(page_size()).expect("output buffer");
+    let sum = fill(input.as_mut_slice(), 1);
+    let start = Instant::now();
+    runner
+        .map_host_buffer(INPUT, &input, false)
+        .expect("map input");
+    let mapped = start.elapsed();
+    runner
+        .map_host_buffer(OUTPUT, &output, true)
+        .expect("map output");
+
+    assert_eq!(runner.run().expect("run guest").exit_code, 0);
+    assert_eq!(read_u64(output.as_slice()), sum);
+
+    // The mapping outlives the run; the next one sees the host's new input
+    let sum = fill(input.as_mut_slice(), 2);
+    assert_eq!(runner.run().expect("rerun guest").exit_code, 0);
+    assert_eq!(read_u64(output.as_slice()), sum);
+
+    // Host writes stop short of the read-only input
+    assert_eq!(runner.write_memory(INPUT - 4, &[0xFF; 8]), 4);
+    assert_eq!(input.as_slice()[0], 2);
+
+    // The copying path: the same input written into guest memory
+    runner.unmap_host_buffer(INPUT).expect("unmap input");
+    runner.prepare();
+    let start = Instant::now();
+    assert_eq!(runner.write_memory(INPUT, input.as_slice()), INPUT_LEN);
+    let written = start.elapsed();
+    runner
+        .execute_from(runner.entry_point())
+        .expect("run guest on copied input");
+    assert_eq!(read_u64(output.as_slice()), sum);
+    assert!(
+        mapped < written,
+        "mapping took {mapped:?}, copying took {written:?}"
+    );
+}
+
+#[test]
+fn test_map_host_buffer_rejects_bad_ranges() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let compiled = compile(temp.path());
+    let mut runner = Runner::load(&compiled.out, &compiled.elf).expect("load runner");
+    let buffer = HostBuffer::new(page_size()).expect("buffer");
+
+    let message = |result: Result<(), RunError>| result.expect_err("mapping fails").to_string();
+    assert!(message(runner.map_host_buffer(INPUT + 8, &buffer, false)).contains("page size"));
+    let odd = HostBuffer::new(page_size() + 1).expect("buffer");
+    assert!(message(runner.map_host_buffer(INPUT, &odd, false)).contains("page size"));
+    assert_eq!(
+        message(runner.map_host_buffer(TEXT, &buffer, false)),
+        format!(
+            "host buffer at 0x1000: [0x1000, {:#x}) overlaps the segment at [0x1000, 0x102c)",
+            TEXT + page_size() as u64
+        )
+    );
+    let end = runner.memory_size() as u64;
+    assert!(message(runner.map_host_buffer(end, &buffer, false)).contains("do not fit"));
+
+    runner
+        .map_host_buffer(INPUT, &buffer, false)
+        .expect("map buffer");
+    assert!(
+        message(runner.map_host_buffer(INPUT, &buffer, true)).contains("overlaps the host buffer")
+    );
+    assert_eq!(
+        message(runner.unmap_host_buffer(OUTPUT)),
+        format!("host buffer at {OUTPUT:#x}: no buffer is mapped there")
+    );
+    runner.unmap_host_buffer(INPUT).expect("unmap buffer");
+}
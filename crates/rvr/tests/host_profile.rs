@@ -0,0 +1,205 @@
+//! Host cost sampling: `Runner::run_host_profile` suspends at a fixed
+//! instret interval and `HostProfile` charges each interval to the guest
+//! function it ended in, so a function that costs more host time per guest
+//! instruction gets a larger host share than guest share.
+
+use guest::{
+    FUNCT3_BNE, OPCODE_BRANCH, OPCODE_JAL, OPCODE_JALR, OPCODE_LUI, OPCODE_OP_IMM, OPCODE_SYSTEM,
+    li,
+};
+use rvr::test_support::guest;
+use rvr::{CompileOptions, HostProfile, InstretMode, RunError, Runner, SyscallMode};
+use rvr_elf::{ElfWriter, PF_R, PF_W, PF_X};
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{
+    REG_A0, REG_A1, REG_A7, REG_RA, REG_T0, REG_ZERO, Rv64, encode_b, encode_i, encode_j, encode_u,
+    syscalls::syscall_nr::SYS_CLOCK_GETTIME,
+};
+
+const TEXT: u64 = 0x1000;
+/// Instructions of `_start`; `spin` follows it.
+const START_LEN: u64 = 4;
+const SPIN: u64 = TEXT + 4 * START_LEN;
+/// Iterations of the `spin` loop, in units of 4096 (`lui`).
+const SPIN_PAGES: u32 = 16;
+const INTERVAL: u64 = 1000;
+
+/// Instructions of `_start` in the two-function guest; `cheap` and then
+/// `costly` follow it.
+const PHASES_START_LEN: u64 = 5;
+const CHEAP: u64 = TEXT + 4 * PHASES_START_LEN;
+const CHEAP_LEN: u64 = 4;
+const COSTLY: u64 = CHEAP + 4 * CHEAP_LEN;
+/// `struct timespec` that `costly` reads the host clock into.
+const TIMESPEC: u64 = 0x2_0000;
+const CLOCK_MONOTONIC: i32 = 1;
+/// Iterations of the two-instruction `cheap` loop, in units of 4096.
+const CHEAP_PAGES: u32 = 64;
+/// Iterations of the four-instruction `costly` loop, in units of 4096, so
+/// both functions retire as many guest instructions.
+const COSTLY_PAGES: u32 = 32;
+/// Points by which the host share of the syscall loop must exceed its
+/// guest share, and the add loop's fall short of it.
+const MIN_SKEW_PERCENT: f64 = 20.0;
+
+/// `_start` calls `spin`, which counts down a register, then exits with 0.
+fn guest_elf() -> Vec<u8> {
+    let start = [
+        encode_j(OPCODE_JAL, REG_RA, i32::try_from(SPIN - TEXT).unwrap()),
+        encode_i(OPCODE_OP_IMM, REG_A0, 0, REG_ZERO, 0),
+        li(REG_A7, SYS_EXIT),
+        encode_i(OPCODE_SYSTEM, REG_ZERO, 0, REG_ZERO, 0),
+    ];
+    let spin = [
+        encode_u(OPCODE_LUI, REG_T0, SPIN_PAGES),
+        encode_i(OPCODE_OP_IMM, REG_T0, 0, REG_T0, -1),
+        encode_b(OPCODE_BRANCH, FUNCT3_BNE, REG_T0, REG_ZERO, -4),
+        encode_i(OPCODE_JALR, REG_ZERO, 0, REG_RA, 0),
+    ];
+    ElfWriter::<Rv64>::new(TEXT)
+        .with_segment(
+            TEXT,
+            PF_R | PF_X,
+            start
+                .iter()
+                .chain(&spin)
+                .flat_map(|i| i.to_le_bytes())
+                .collect(),
+        )
+        .with_function("_start", TEXT, 4 * START_LEN)
+        .with_function("spin", SPIN, 4 * spin.len() as u64)
+        .build()
+}
+
+/// `_start` calls `cheap`, a countdown loop, then `costly`, a loop around a
+/// host `clock_gettime` call, then exits with 0. Each runs for many
+/// sampling intervals.
+fn phases_elf() -> Vec<u8> {
+    let start = [
+        encode_j(OPCODE_JAL, REG_RA, i32::try_from(CHEAP - TEXT).unwrap()),
+        encode_j(
+            OPCODE_JAL,
+            REG_RA,
+            i32::try_from(COSTLY - TEXT - 4).unwrap(),
+        ),
+        encode_i(OPCODE_OP_IMM, REG_A0, 0, REG_ZERO, 0),
+        li(REG_A7, SYS_EXIT),
+        encode_i(OPCODE_SYSTEM, REG_ZERO, 0, REG_ZERO, 0),
+    ];
+    let cheap = [
+        encode_u(OPCODE_LUI, REG_T0, CHEAP_PAGES),
+        encode_i(OPCODE_OP_IMM, REG_T0, 0, REG_T0, -1),
+        encode_b(OPCODE_BRANCH, FUNCT3_BNE, REG_T0, REG_ZERO, -4),
+        encode_i(OPCODE_JALR, REG_ZERO, 0, REG_RA, 0),
+    ];
+    let costly = [
+        encode_u(OPCODE_LUI, REG_A1, u32::try_from(TIMESPEC >> 12).unwrap()),
+        encode_i(
+            OPCODE_OP_IMM,
+            REG_A7,
+            0,
+            REG_ZERO,
+            i32::try_from(SYS_CLOCK_GETTIME).unwrap(),
+        ),
+        encode_u(OPCODE_LUI, REG_T0, COSTLY_PAGES),
+        encode_i(OPCODE_OP_IMM, REG_A0, 0, REG_ZERO, CLOCK_MONOTONIC),
+        encode_i(OPCODE_SYSTEM, REG_ZERO, 0, REG_ZERO, 0),
+        encode_i(OPCODE_OP_IMM, REG_T0, 0, REG_T0, -1),
+        encode_b(OPCODE_BRANCH, FUNCT3_BNE, REG_T0, REG_ZERO, -12),
+        encode_i(OPCODE_JALR, REG_ZERO, 0, REG_RA, 0),
+    ];
+    assert_eq!(cheap.len() as u64, CHEAP_LEN);
+    ElfWriter::<Rv64>::new(TEXT)
+        .with_segment(
+            TEXT,
+            PF_R | PF_X,
+            start
+                .iter()
+                .chain(&cheap)
+                .chain(&costly)
+                .flat_map(|i| i.to_le_bytes())
+                .collect(),
+        )
+        .with_segment(TIMESPEC, PF_R | PF_W, vec![0; 16])
+        .with_function("_start", TEXT, 4 * PHASES_START_LEN)
+        .with_function("cheap", CHEAP, 4 * CHEAP_LEN)
+        .with_function("costly", COSTLY, 4 * costly.len() as u64)
+        .build()
+}
+
+fn load_runner(instret: InstretMode) -> (tempfile::TempDir, std::path::PathBuf, Runner) {
+    load_guest(&guest_elf(), instret, SyscallMode::BareMetal)
+}
+
+fn load_guest(
+    elf_bytes: &[u8],
+    instret: InstretMode,
+    syscalls: SyscallMode,
+) -> (tempfile::TempDir, std::path::PathBuf, Runner) {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = temp.path().join("guest.elf");
+    std::fs::write(&elf, elf_bytes).expect("write ELF");
+    let out = temp.path().join("out");
+    let options = CompileOptions::new()
+        .with_quiet(true)
+        .with_instret_mode(instret)
+        .with_syscall_mode(syscalls);
+    rvr::compile_with_options(&elf, &out, &options).expect("compile");
+    let runner = Runner::load(&out, &elf).expect("load runner");
+    (temp, elf, runner)
+}
+
+#[test]
+fn test_host_profile_attributes_spin() {
+    let (_temp, elf, mut runner) = load_runner(InstretMode::Suspend);
+    let (result, samples) = runner.run_host_profile(INTERVAL).expect("run guest");
+    assert_eq!(result.exit_code, 0);
+    assert!(runner.has_exited());
+    assert!(samples.samples.len() as u64 >= result.instret / INTERVAL);
+    let retired: u64 = samples.samples.iter().map(|s| s.instructions).sum();
+    assert_eq!(retired, result.instret);
+
+    let profile = HostProfile::resolve(&elf, runner.load_bias(), &samples).expect("resolve");
+    assert_eq!(profile.instructions, result.instret);
+    let spin = &profile.functions[0];
+    assert_eq!(spin.symbol, "spin");
+    assert!(spin.guest_percent > 99.0, "{profile:?}");
+    assert!(spin.host_percent > 50.0, "{profile:?}");
+}
+
+#[test]
+fn test_host_profile_separates_host_and_guest_share() {
+    let (_temp, elf, mut runner) =
+        load_guest(&phases_elf(), InstretMode::Suspend, SyscallMode::Linux);
+    let (result, samples) = runner.run_host_profile(INTERVAL).expect("run guest");
+    assert_eq!(result.exit_code, 0);
+
+    let profile = HostProfile::resolve(&elf, runner.load_bias(), &samples).expect("resolve");
+    let function = |name: &str| {
+        profile
+            .functions
+            .iter()
+            .find(|f| f.symbol == name)
+            .unwrap_or_else(|| panic!("{name} in {profile:?}"))
+    };
+    let (cheap, costly) = (function("cheap"), function("costly"));
+    assert!(
+        (cheap.guest_percent - costly.guest_percent).abs() < 5.0,
+        "{profile:?}"
+    );
+    assert!(
+        costly.host_percent > costly.guest_percent + MIN_SKEW_PERCENT,
+        "{profile:?}"
+    );
+    assert!(
+        cheap.host_percent < cheap.guest_percent - MIN_SKEW_PERCENT,
+        "{profile:?}"
+    );
+}
+
+#[test]
+fn test_host_profile_requires_suspend() {
+    let (_temp, _elf, mut runner) = load_runner(InstretMode::Count);
+    let err = runner.run_host_profile(INTERVAL).unwrap_err();
+    assert!(matches!(err, RunError::SuspendRequired(_)), "{err}");
+}
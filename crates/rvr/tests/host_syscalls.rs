@@ -0,0 +1,80 @@
+//! Host syscalls: a guest calls custom syscall 0x1000, whose handler
+//! registered with `Runner::register_syscall` copies a host buffer into
+//! guest memory; unregistered numbers keep the built-in `ENOSYS`.
+
+use guest::{ECALL, FUNCT3_D, OPCODE_STORE, addi, li, lui};
+use rvr::Runner;
+use rvr::test_support::guest;
+use rvr_elf::STT_FUNC;
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{REG_A0, REG_A1, REG_A7, REG_S1, REG_S2, REG_ZERO, Rv64, encode_s};
+
+const TEXT: u64 = 0x1000;
+/// Guest buffer the host fills, and the slot for the unregistered result.
+const BUF: u64 = 0x2_0000;
+const RESULT: u64 = 0x3_0000;
+const SYS_HOST_COPY: u64 = 0x1000;
+const SYS_UNREGISTERED: i32 = 0x7ff;
+const PAYLOAD: &[u8] = b"hello from the host";
+/// Bytes the guest asks for (the handler copies at most the payload).
+const LEN: i32 = 64;
+
+/// `s1 = host_copy(BUF, LEN)`, `*RESULT = unregistered()`, exit with s1.
+fn guest_elf() -> Vec<u8> {
+    let text = [
+        lui(REG_A0, BUF),
+        addi(REG_A1, REG_ZERO, LEN),
+        lui(REG_A7, SYS_HOST_COPY),
+        ECALL,
+        addi(REG_S1, REG_A0, 0),
+        addi(REG_A7, REG_ZERO, SYS_UNREGISTERED),
+        ECALL,
+        lui(REG_S2, RESULT),
+        encode_s(OPCODE_STORE, FUNCT3_D, REG_S2, REG_A0, 0),
+        addi(REG_A0, REG_S1, 0),
+        li(REG_A7, SYS_EXIT),
+        ECALL,
+    ];
+    guest::text_elf::<Rv64>(TEXT, &text)
+        .with_symbol("_start", TEXT, STT_FUNC)
+        .build()
+}
+
+#[test]
+fn test_host_syscall_copies_to_guest() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let compiled = guest::compile_linux(temp.path(), "host", &guest_elf());
+    let mut runner = Runner::load(&compiled.out, &compiled.elf).expect("load runner");
+    runner.register_syscall(
+        SYS_HOST_COPY,
+        Box::new(|ctx| {
+            let len = PAYLOAD.len().min(usize::try_from(ctx.arg(1)).unwrap_or(0));
+            if ctx.write(ctx.arg(0), &PAYLOAD[..len]) {
+                i64::try_from(len).unwrap()
+            } else {
+                -14 // EFAULT
+            }
+        }),
+    );
+
+    let result = runner.run().expect("run guest");
+    assert_eq!(usize::from(result.exit_code), PAYLOAD.len());
+    let mut buf = vec![0; PAYLOAD.len() + 1];
+    assert_eq!(runner.read_memory(BUF, &mut buf), buf.len());
+    assert_eq!(&buf[..PAYLOAD.len()], PAYLOAD);
+    assert_eq!(buf[PAYLOAD.len()], 0);
+
+    let mut errno = [0; 8];
+    assert_eq!(runner.read_memory(RESULT, &mut errno), 8);
+    assert_eq!(i64::from_le_bytes(errno), -38);
+}
+
+#[test]
+fn test_unregistered_host_syscall() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let compiled = guest::compile_linux(temp.path(), "host", &guest_elf());
+    let mut runner = Runner::load(&compiled.out, &compiled.elf).expect("load runner");
+    // Without a handler, syscall 0x1000 is ENOSYS (exit code 0xda)
+    let result = runner.run().expect("run guest");
+    assert_eq!(result.exit_code, (-38i64).to_le_bytes()[0]);
+}
@@ -0,0 +1,345 @@
+//! HTIF syscall proxying: a riscv-tests style guest writes through
+//! `tohost`/`magic_mem`, polls `fromhost` for completion and exits with
+//! syscall 93, with its stdio redirected through the runner. A guest that
+//! waits for `fromhost` without a request trips the poll watchdog, on the C
+//! backend and on the host's assembly backend.
+
+use std::io::Cursor;
+
+use guest::{
+    FUNCT3_ADD, FUNCT3_BEQ, FUNCT3_BNE, FUNCT3_SLLI, OPCODE_BRANCH, OPCODE_JAL, OPCODE_LOAD,
+    OPCODE_LUI, OPCODE_OP, OPCODE_OP_IMM, SharedWriter, Text, addi, ld, li, offset, sd,
+};
+use rvr::test_support::guest;
+use rvr::{CDialect, CompileOptions, Compiler, ExitReason, Runner};
+use rvr_elf::{ElfWriter, PF_R, PF_W, PF_X};
+use rvr_emit::htif::{SYS_EXIT, SYS_FSTAT, SYS_READ, SYS_WRITE};
+use rvr_isa::{
+    REG_A0, REG_A1, REG_A2, REG_S0, REG_S1, REG_S2, REG_S3, REG_S11, REG_T0, REG_T1, REG_ZERO,
+    Rv64, encode_b, encode_i, encode_j, encode_r, encode_u,
+};
+
+const FUNCT3_WU: u8 = 0b110;
+const EBADF: i32 = 9;
+/// `S_IFCHR | 0620`, the mode fstat reports for stdio.
+const CHAR_DEVICE_MODE: u32 = 0o20620;
+
+const TEXT: u64 = 0x8000_0000;
+/// `tohost`, with `fromhost` on the next 64-byte line.
+const TOHOST: u64 = 0x8000_1000;
+const FROMHOST_OFFSET: i32 = 0x40;
+const MAGIC: u64 = 0x8000_2000;
+/// Message, then the stat buffer at `+0x100` and the read buffer at `+0x200`.
+const DATA: u64 = 0x8000_3000;
+const MESSAGE: &[u8] = b"hello\n";
+const EXIT_CODE: i32 = 7;
+/// Watchdog limit of the stalling guest.
+const POLL_LIMIT: u64 = 1000;
+/// Value the marked stalling guest holds in `s3` at its poll.
+const POLL_MARK: i32 = 42;
+
+/// `rd = addr` for an address in `0x8000_0000..0x8000_8000` (zero-extended,
+/// unlike `lui`).
+fn li_addr(rd: u8, addr: u64) -> [u32; 4] {
+    let page = u32::try_from((addr - TEXT) >> 12).unwrap();
+    [
+        addi(rd, REG_ZERO, 1),
+        encode_i(OPCODE_OP_IMM, rd, FUNCT3_SLLI, rd, 31),
+        encode_u(OPCODE_LUI, REG_T0, page),
+        encode_r(OPCODE_OP, rd, FUNCT3_ADD, rd, REG_T0, 0),
+    ]
+}
+
+/// Text after the jump over the failure exit, which exits through `tohost`
+/// with the step in `s11`, and the base addresses.
+fn htif_text() -> Text {
+    let mut text = Text(Vec::new());
+    text.push([encode_j(OPCODE_JAL, REG_ZERO, 20)]);
+    // fail: tohost = (s11 << 1) | 1
+    let at = text.0.len();
+    text.push([
+        encode_i(OPCODE_OP_IMM, REG_T1, FUNCT3_SLLI, REG_S11, 1),
+        addi(REG_T1, REG_T1, 1),
+        sd(REG_T1, REG_S0, 0),
+        encode_j(OPCODE_JAL, REG_ZERO, offset(at + 3, at)),
+    ]);
+    text.push(li_addr(REG_S0, TOHOST));
+    text.push(li_addr(REG_S1, MAGIC));
+    text.push(li_addr(REG_S2, DATA));
+    text
+}
+
+/// HTIF requests of the guests, with `tohost` in `s0`, `magic_mem` in `s1`
+/// and the data in `s2`.
+trait HtifText {
+    /// HTIF syscall `num(a0, a1, a2)`: fill `magic_mem`, point `tohost` at
+    /// it, wait for `fromhost`, then load the result into `a0`. Clobbers `t1`.
+    fn syscall(&mut self, num: u64);
+
+    /// Wait for `fromhost`, clear it and load the result into `a0`.
+    /// Returns the index of the polling load.
+    fn wait(&mut self) -> usize;
+
+    /// `num(fd, DATA + buf, len)`.
+    fn io(&mut self, num: u64, fd: i32, buf: i32, len: i32);
+
+    fn elf(&self) -> Vec<u8>;
+}
+
+impl HtifText for Text {
+    fn syscall(&mut self, num: u64) {
+        self.push([
+            li(REG_T1, num),
+            sd(REG_T1, REG_S1, 0),
+            sd(REG_A0, REG_S1, 8),
+            sd(REG_A1, REG_S1, 16),
+            sd(REG_A2, REG_S1, 24),
+            sd(REG_S1, REG_S0, 0),
+        ]);
+        self.wait();
+    }
+
+    fn wait(&mut self) -> usize {
+        let poll = self.0.len();
+        self.push([ld(REG_T1, REG_S0, FROMHOST_OFFSET)]);
+        let at = self.0.len();
+        self.push([
+            encode_b(
+                OPCODE_BRANCH,
+                FUNCT3_BEQ,
+                REG_T1,
+                REG_ZERO,
+                offset(at, poll),
+            ),
+            sd(REG_ZERO, REG_S0, FROMHOST_OFFSET),
+            ld(REG_A0, REG_S1, 0),
+        ]);
+        poll
+    }
+
+    fn io(&mut self, num: u64, fd: i32, buf: i32, len: i32) {
+        self.push([
+            addi(REG_A0, REG_ZERO, fd),
+            addi(REG_A1, REG_S2, buf),
+            addi(REG_A2, REG_ZERO, len),
+        ]);
+        self.syscall(num);
+    }
+
+    fn elf(&self) -> Vec<u8> {
+        ElfWriter::<Rv64>::new(TEXT)
+            .with_segment(TEXT, PF_R | PF_X, guest::code(&self.0))
+            .with_segment(TOHOST, PF_R | PF_W, vec![0; 0x80])
+            .with_segment(MAGIC, PF_R | PF_W, vec![0; 32])
+            .with_segment(DATA, PF_R | PF_W, MESSAGE.to_vec())
+            .build()
+    }
+}
+
+/// Exits with `EXIT_CODE` if every check passes; else the failed step.
+///
+/// Writes the message in two transactions, checks stdout is a character
+/// device and that other fds are rejected, echoes one read from stdin, then
+/// exits.
+fn htif_elf() -> Vec<u8> {
+    let mut text = htif_text();
+
+    // "hel", then "lo\n"
+    for (step, buf) in [(1, 0), (2, 3)] {
+        text.io(SYS_WRITE, 1, buf, 3);
+        text.expect_a0(step, 3);
+    }
+
+    text.io(SYS_FSTAT, 1, 0x100, 0);
+    text.fail_if(3, FUNCT3_BNE, REG_A0, REG_ZERO);
+    text.push([
+        encode_i(OPCODE_LOAD, REG_T0, FUNCT3_WU, REG_S2, 0x100 + 16),
+        encode_u(OPCODE_LUI, REG_T1, CHAR_DEVICE_MODE >> 12),
+        addi(REG_T1, REG_T1, (CHAR_DEVICE_MODE & 0xfff).cast_signed()),
+    ]);
+    text.fail_if(4, FUNCT3_BNE, REG_T0, REG_T1);
+
+    text.io(SYS_WRITE, 5, 0, 1);
+    text.expect_a0(5, -EBADF);
+
+    // Echo whatever one read returns
+    text.io(SYS_READ, 0, 0x200, 64);
+    text.push([addi(REG_S3, REG_A0, 0)]);
+    text.push([
+        addi(REG_A0, REG_ZERO, 1),
+        addi(REG_A1, REG_S2, 0x200),
+        addi(REG_A2, REG_S3, 0),
+    ]);
+    text.syscall(SYS_WRITE);
+    text.fail_if(6, FUNCT3_BNE, REG_A0, REG_S3);
+
+    text.push([addi(REG_A0, REG_ZERO, EXIT_CODE)]);
+    text.syscall(SYS_EXIT);
+    // Not reached: the host stops at the exit request
+    text.fail_if(7, FUNCT3_BNE, REG_ZERO, REG_S0);
+    text.elf()
+}
+
+/// Writes the message, then waits for `fromhost` again without a new
+/// request, a broken handshake the host never answers. Returns the ELF and
+/// the PC of the second wait's poll.
+fn stalled_elf() -> (Vec<u8>, u64) {
+    let mut text = htif_text();
+    text.io(SYS_WRITE, 1, 0, 6);
+    let poll = text.wait();
+    let pc = TEXT + 4 * u64::try_from(poll).unwrap();
+    (text.elf(), pc)
+}
+
+/// Waits for `fromhost` without ever making a request. Returns the ELF and
+/// the PC of the poll.
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+fn unanswered_elf() -> (Vec<u8>, u64) {
+    let mut text = htif_text();
+    let poll = text.wait();
+    let pc = TEXT + 4 * u64::try_from(poll).unwrap();
+    (text.elf(), pc)
+}
+
+/// Writes the message, then waits for `fromhost` in a loop that sets `s3`
+/// to `POLL_MARK` before the poll and clears it after, so the mark is only
+/// live if the poll can stop the guest. Returns the ELF.
+fn marked_stall_elf() -> Vec<u8> {
+    let mut text = htif_text();
+    text.io(SYS_WRITE, 1, 0, 6);
+    let top = text.0.len();
+    text.push([
+        addi(REG_S3, REG_ZERO, POLL_MARK),
+        ld(REG_T1, REG_S0, FROMHOST_OFFSET),
+        addi(REG_S3, REG_ZERO, 0),
+    ]);
+    let at = text.0.len();
+    text.push([encode_b(
+        OPCODE_BRANCH,
+        FUNCT3_BEQ,
+        REG_T1,
+        REG_ZERO,
+        offset(at, top),
+    )]);
+    text.elf()
+}
+
+/// Compile `elf` for HTIF with `options` into `dir` and load it.
+fn load(dir: &std::path::Path, elf: &[u8], options: &CompileOptions) -> Runner {
+    let elf_path = dir.join("htif.elf");
+    std::fs::write(&elf_path, elf).expect("write ELF");
+    let out = dir.join("htif");
+    rvr::compile_with_options(
+        &elf_path,
+        &out,
+        &options.clone().with_quiet(true).with_htif(true),
+    )
+    .expect("compile");
+    Runner::load(&out, &elf_path).expect("load runner")
+}
+
+/// Compile the HTIF guest with `options`, run it, and check its exit code
+/// and output.
+fn run_htif(options: &CompileOptions) {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let mut runner = load(temp.path(), &htif_elf(), options);
+    let stdout = SharedWriter::default();
+    runner.set_stdin(Cursor::new(b"ping\n".to_vec()));
+    runner.set_stdout(stdout.clone());
+    let result = runner.run().expect("run guest");
+    assert_eq!(result.exit_code, u8::try_from(EXIT_CODE).unwrap());
+    assert_eq!(stdout.contents(), b"hello\nping\n");
+}
+
+#[test]
+fn test_htif_syscalls_proxied() {
+    run_htif(&CompileOptions::new());
+}
+
+#[test]
+fn test_htif_portable_dialect() {
+    run_htif(
+        &CompileOptions::new()
+            .with_compiler(Compiler::gcc())
+            .with_c_dialect(CDialect::Portable),
+    );
+}
+
+#[test]
+fn test_htif_stall_diagnosed() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let (elf, poll_pc) = stalled_elf();
+    let options = CompileOptions::new().with_htif_poll_limit(POLL_LIMIT);
+    let mut runner = load(temp.path(), &elf, &options);
+    runner.set_stdout(SharedWriter::default());
+    let result = runner.run().expect("run guest");
+    assert_eq!(
+        result.exit_reason,
+        ExitReason::HtifStall {
+            pc: poll_pc,
+            tohost: 0,
+            fromhost: 0,
+            syscall: Some(SYS_WRITE),
+        }
+    );
+    assert!(
+        result
+            .exit_reason
+            .to_string()
+            .starts_with("HTIF handshake stalled: tohost=0x0, last syscall=64"),
+        "{}",
+        result.exit_reason
+    );
+}
+
+#[test]
+fn test_htif_stall_keeps_writes_before_poll() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let options = CompileOptions::new().with_htif_poll_limit(POLL_LIMIT);
+    let mut runner = load(temp.path(), &marked_stall_elf(), &options);
+    runner.set_stdout(SharedWriter::default());
+    let result = runner.run().expect("run guest");
+    assert!(
+        matches!(result.exit_reason, ExitReason::HtifStall { .. }),
+        "{}",
+        result.exit_reason
+    );
+    assert_eq!(
+        runner.get_register(usize::from(REG_S3)),
+        u64::try_from(POLL_MARK).unwrap()
+    );
+}
+
+/// Run the unanswered guest on the assembly `backend` and check the
+/// watchdog stops it at its poll.
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+fn check_asm_stall(backend: rvr::Backend) {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let (elf, poll_pc) = unanswered_elf();
+    let options = CompileOptions::new()
+        .with_backend(backend)
+        .with_htif_poll_limit(POLL_LIMIT);
+    let mut runner = load(temp.path(), &elf, &options);
+    let result = runner.run().expect("run guest");
+    assert_eq!(
+        result.exit_reason,
+        ExitReason::HtifStall {
+            pc: poll_pc,
+            tohost: 0,
+            fromhost: 0,
+            syscall: None,
+        }
+    );
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn test_htif_stall_diagnosed_x86() {
+    check_asm_stall(rvr::Backend::X86Asm);
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn test_htif_stall_diagnosed_arm64() {
+    check_asm_stall(rvr::Backend::ARM64Asm);
+}
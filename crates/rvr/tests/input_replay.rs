@@ -0,0 +1,164 @@
+//! Input recording and replay: a guest that reads a preopened file and the
+//! clock is recorded with `Runner::record_inputs`, and the saved recording
+//! replays it on a runner without the file to the same exit code, instret
+//! and memory; a guest that makes other calls than the recorded ones fails
+//! with `ReplayDiverged`.
+
+use std::path::{Path, PathBuf};
+
+use guest::{Compiled, ECALL, FUNCT3_D, OPCODE_STORE, addi, li, lui};
+use rvr::test_support::guest;
+use rvr::{InputRecording, RunError, Runner};
+use rvr_elf::{PF_R, PF_W, STT_FUNC, STT_NOTYPE};
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::syscalls::syscall_nr::{SYS_CLOCK_GETTIME, SYS_CLOSE, SYS_OPENAT, SYS_READ};
+use rvr_isa::{
+    REG_A0, REG_A1, REG_A2, REG_A3, REG_A7, REG_S1, REG_S2, REG_S3, REG_ZERO, Rv64, encode_s,
+};
+
+const AT_FDCWD: i32 = -100;
+
+const TEXT: u64 = 0x1000;
+/// NUL-terminated path of the file the guest reads.
+const PATH: u64 = 0x2_0000;
+const BUF: u64 = 0x3_0000;
+/// Bytes the guest asks `read` for.
+const BUF_LEN: u64 = 64;
+/// `struct timespec` the guest reads the clock into.
+const TIMESPEC: u64 = 0x3_1000;
+/// Eight-byte slots the guest stores syscall results in.
+const RESULT: u64 = 0x4_0000;
+const STACK_TOP: u64 = 0x10_0000;
+/// Stack bytes below `STACK_TOP` holding `argv` and `envp`.
+const STACK_CHECKED: u64 = 0x1000;
+
+const FIXTURE: &[u8] = b"recorded file contents\n";
+
+const fn sd(rs1: u8, rs2: u8, imm: i32) -> u32 {
+    encode_s(OPCODE_STORE, FUNCT3_D, rs1, rs2, imm)
+}
+
+/// Store a0 in result slot `slot`.
+const fn store_result(slot: i32) -> u32 {
+    sd(REG_S2, REG_A0, slot * 8)
+}
+
+/// Open `path`, read it into `BUF`, close it, read the clock and exit
+/// with the number of bytes read; every result lands in a slot.
+fn guest_elf(path: &str) -> Vec<u8> {
+    let text = [
+        // s1 = openat(AT_FDCWD, PATH, O_RDONLY)
+        addi(REG_A0, REG_ZERO, AT_FDCWD),
+        lui(REG_A1, PATH),
+        li(REG_A2, 0),
+        li(REG_A3, 0),
+        li(REG_A7, SYS_OPENAT),
+        ECALL,
+        addi(REG_S1, REG_A0, 0),
+        lui(REG_S2, RESULT),
+        store_result(0),
+        // s3 = read(s1, BUF, BUF_LEN)
+        addi(REG_A0, REG_S1, 0),
+        lui(REG_A1, BUF),
+        li(REG_A2, BUF_LEN),
+        li(REG_A7, SYS_READ),
+        ECALL,
+        addi(REG_S3, REG_A0, 0),
+        store_result(1),
+        // close(s1)
+        addi(REG_A0, REG_S1, 0),
+        li(REG_A7, SYS_CLOSE),
+        ECALL,
+        store_result(2),
+        // clock_gettime(CLOCK_REALTIME, TIMESPEC)
+        li(REG_A0, 0),
+        lui(REG_A1, TIMESPEC),
+        li(REG_A7, SYS_CLOCK_GETTIME),
+        ECALL,
+        store_result(3),
+        addi(REG_A0, REG_S3, 0),
+        li(REG_A7, SYS_EXIT),
+        ECALL,
+    ];
+    let mut path = path.as_bytes().to_vec();
+    path.push(0);
+    guest::text_elf::<Rv64>(TEXT, &text)
+        .with_segment(PATH, PF_R | PF_W, path)
+        .with_symbol("_start", TEXT, STT_FUNC)
+        .with_symbol("__stack_top", STACK_TOP, STT_NOTYPE)
+        .build()
+}
+
+/// Checksum of the guest's data and of the stack holding its arguments.
+fn checksum(runner: &Runner) -> (u64, u64) {
+    let data = usize::try_from(RESULT + 0x1000 - PATH).unwrap();
+    let stack = usize::try_from(STACK_CHECKED).unwrap();
+    (
+        runner.memory_checksum(PATH, data),
+        runner.memory_checksum(STACK_TOP - STACK_CHECKED, stack),
+    )
+}
+
+/// Record a run of the guest reading `/data/input.txt`; returns the
+/// recording's path and the run's exit code, instret and checksums.
+fn record(dir: &Path, compiled: &Compiled) -> (PathBuf, u8, u64, (u64, u64)) {
+    let data = dir.join("data");
+    std::fs::create_dir(&data).expect("create data dir");
+    std::fs::write(data.join("input.txt"), FIXTURE).expect("write fixture");
+    let mut runner = Runner::load(&compiled.out, &compiled.elf)
+        .expect("load runner")
+        .with_args(["reader", "input.txt"])
+        .with_env(&[("TZ", "UTC")])
+        .with_preopened_dir("/data", &data)
+        .expect("preopen");
+    runner.record_inputs();
+    let result = runner.run().expect("run guest");
+    let recording = runner.take_recording().expect("recording");
+    assert_eq!(recording.len(), 4, "openat, read, close, clock_gettime");
+    let path = dir.join("inputs.bin");
+    recording.save(&path).expect("save recording");
+    // The machine replaying has no such file
+    std::fs::remove_dir_all(&data).expect("remove data dir");
+    (path, result.exit_code, result.instret, checksum(&runner))
+}
+
+#[test]
+fn test_replay_matches_recorded_run() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let compiled = guest::compile_linux(temp.path(), "reader", &guest_elf("/data/input.txt"));
+    let (path, exit_code, instret, sums) = record(temp.path(), &compiled);
+    assert_eq!(usize::from(exit_code), FIXTURE.len());
+
+    let mut runner = Runner::load(&compiled.out, &compiled.elf).expect("load runner");
+    runner
+        .replay_inputs(InputRecording::load(&path).expect("load recording"))
+        .expect("replay");
+    let result = runner.run().expect("replay guest");
+    assert_eq!(result.exit_code, exit_code);
+    assert_eq!(result.instret, instret);
+    assert_eq!(checksum(&runner), sums);
+    let mut buf = [0; FIXTURE.len()];
+    assert_eq!(runner.read_memory(BUF, &mut buf), buf.len());
+    assert_eq!(buf, FIXTURE);
+}
+
+#[test]
+fn test_replay_of_other_calls_diverges() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let compiled = guest::compile_linux(temp.path(), "reader", &guest_elf("/data/input.txt"));
+    let (path, ..) = record(temp.path(), &compiled);
+
+    let other = guest::compile_linux(temp.path(), "other", &guest_elf("/data/other.txt"));
+    let mut runner = Runner::load(&other.out, &other.elf).expect("load runner");
+    runner
+        .replay_inputs(InputRecording::load(&path).expect("load recording"))
+        .expect("replay");
+    match runner.run() {
+        Err(RunError::ReplayDiverged { index, reason }) => {
+            assert_eq!(index, 0);
+            assert!(reason.contains("\"/data/input.txt\""), "{reason}");
+            assert!(reason.contains("\"/data/other.txt\""), "{reason}");
+        }
+        other => panic!("expected ReplayDiverged, got {other:?}"),
+    }
+}
@@ -0,0 +1,122 @@
+//! ELF summaries: what `rvr inspect` reports about an image without
+//! compiling it.
+
+use guest::{ECALL, FUNCT7_MULDIV, OPCODE_OP, OPCODE_OP_IMM, li};
+use rvr::test_support::guest;
+use rvr::{InspectOptions, InspectWarning, inspect_elf};
+use rvr_elf::{
+    ArchAttributes, EF_RISCV_FLOAT_ABI_DOUBLE, EF_RISCV_RVE, ElfWriter, PF_R, PF_W, PF_X,
+};
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{REG_A0, REG_A7, REG_ZERO, Rv32, Rv64, encode_i, encode_r};
+
+const TEXT: u64 = 0x1000;
+const DATA: u64 = 0x2000;
+
+/// `a0 = 3; mul a0, a0, a0; exit(a0)`.
+fn text() -> Vec<u8> {
+    [
+        encode_i(OPCODE_OP_IMM, REG_A0, 0, REG_ZERO, 3),
+        encode_r(OPCODE_OP, REG_A0, 0, REG_A0, REG_A0, FUNCT7_MULDIV),
+        li(REG_A7, SYS_EXIT),
+        ECALL,
+    ]
+    .iter()
+    .flat_map(|i| i.to_le_bytes())
+    .collect()
+}
+
+fn attributes(arch: &str) -> ArchAttributes {
+    ArchAttributes {
+        arch: Some(arch.to_string()),
+        stack_align: Some(16),
+    }
+}
+
+#[test]
+fn test_inspect_summary() {
+    let elf = ElfWriter::<Rv64>::new(TEXT)
+        .with_e_flags(EF_RISCV_FLOAT_ABI_DOUBLE)
+        .with_segment(TEXT, PF_R | PF_X, text())
+        .with_segment(DATA, PF_R | PF_W, vec![0; 8])
+        .with_function("_start", TEXT, 16)
+        .with_attributes(attributes("rv64i2p1_m2p0"))
+        .build();
+    let options = InspectOptions {
+        decode: true,
+        ..InspectOptions::default()
+    };
+    let summary = inspect_elf(&elf, &options).unwrap();
+
+    assert_eq!(summary.xlen, 64);
+    assert_eq!(summary.abi, "lp64d");
+    assert_eq!(summary.arch.as_deref(), Some("rv64i2p1_m2p0"));
+    assert_eq!(summary.entry_point, TEXT);
+    assert_eq!(summary.functions, 1);
+    assert_eq!(summary.code_bytes, 16);
+    assert_eq!(summary.segments.len(), 2);
+    assert_eq!(summary.segments[0].permissions, "r-x");
+    assert!(summary.segments[0].code);
+    assert_eq!(summary.segments[1].permissions, "rw-");
+    assert!(!summary.segments[1].code);
+    assert!(summary.warnings.is_empty(), "{:?}", summary.warnings);
+
+    let decode = summary.decode.unwrap();
+    assert_eq!(decode.extensions["I"], 3);
+    assert_eq!(decode.extensions["M"], 1);
+    assert_eq!(decode.undecoded_slots, 0);
+}
+
+#[test]
+fn test_inspect_warnings() {
+    // Entry in a data segment, text past a 64 KiB memory, RVE flag on a
+    // non-E ISA with floating point
+    let elf = ElfWriter::<Rv32>::new(DATA)
+        .with_e_flags(EF_RISCV_RVE)
+        .with_segment(0xFFF8, PF_R | PF_X, text())
+        .with_segment(DATA, PF_R | PF_W, vec![0; 8])
+        .with_attributes(attributes("rv32imf"))
+        .build();
+    let options = InspectOptions {
+        memory_bits: 16,
+        ..InspectOptions::default()
+    };
+    let summary = inspect_elf(&elf, &options).unwrap();
+
+    assert_eq!(summary.abi, "ilp32e");
+    assert_eq!(
+        summary.warnings,
+        [
+            InspectWarning::EntryNotInCode { entry_point: DATA },
+            InspectWarning::GuardOverlap {
+                start: 0xFFF8,
+                end: 0x1_0008,
+                memory_size: 0x1_0000,
+            },
+            InspectWarning::RveMismatch {
+                rve_flag: true,
+                arch: "rv32imf".to_string(),
+            },
+            InspectWarning::UnsupportedExtensions {
+                extensions: vec!["f".to_string()],
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_inspect_json() {
+    let elf = ElfWriter::<Rv64>::new(TEXT)
+        .with_segment(TEXT, PF_R | PF_X, text())
+        .with_attributes(attributes("rv64e"))
+        .build();
+    let summary = inspect_elf(&elf, &InspectOptions::default()).unwrap();
+    let json = serde_json::to_value(&summary).unwrap();
+
+    assert_eq!(json["xlen"], 64);
+    assert_eq!(json["abi"], "lp64");
+    assert_eq!(json["segments"][0]["permissions"], "r-x");
+    assert!(json["decode"].is_null());
+    assert_eq!(json["warnings"][0]["kind"], "rve_mismatch");
+    assert_eq!(json["warnings"][0]["rve_flag"], false);
+}
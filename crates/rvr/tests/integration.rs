@@ -124,7 +124,7 @@ fn test_emit_c_code() {
     let partition =
         std::fs::read_to_string(temp_dir.join("rv64_part0.c")).expect("Failed to read partition");
     assert!(
-        partition.contains("#include \"rv64_blocks.h\""),
+        partition.contains("#include \"rv64.h\""),
         "Include missing in partition"
     );
     assert!(
@@ -0,0 +1,286 @@
+//! Interpreted blocks: a loop block mixing M, A, C and bit-manipulation
+//! instructions runs in the block interpreter and reaches the same result
+//! as compiled, with the same diff trace, whether forced by PC or
+//! interpreted after its part file fails to compile.
+
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use guest::{
+    ECALL, FUNCT3_BNE, FUNCT3_D, OPCODE_AMO, OPCODE_BRANCH, OPCODE_LUI, OPCODE_OP, OPCODE_OP_32,
+    OPCODE_OP_IMM, OPCODE_OP_IMM_32, li,
+};
+use rvr::test_support::diff::{self, CompareConfig, InProcessExecutor};
+use rvr::test_support::guest;
+use rvr::{CompileOptions, CompilerLauncher, Error, InstretMode, Runner, TracerConfig};
+use rvr_elf::{ElfWriter, PF_R, PF_X};
+use rvr_emit::c::TracerKind;
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{
+    REG_A0, REG_A1, REG_A2, REG_A3, REG_A4, REG_A5, REG_A7, REG_S1, REG_SP, REG_T0, REG_T1,
+    REG_ZERO, Rv64, encode_b, encode_i, encode_r, encode_u,
+};
+
+const TEXT: u64 = 0x1000;
+const STACK_TOP: u32 = 0x10_0000;
+const ITERATIONS: i32 = 3;
+const NAME: &str = "interp";
+
+/// One instruction of the guest, full-size or compressed.
+#[derive(Clone, Copy)]
+enum Instr {
+    Full(u32),
+    Compressed(u16),
+}
+
+impl Instr {
+    const fn size(self) -> usize {
+        match self {
+            Self::Full(_) => 4,
+            Self::Compressed(_) => 2,
+        }
+    }
+}
+
+const fn op(rd: u8, funct3: u8, rs1: u8, rs2: u8, funct7: u8) -> Instr {
+    Instr::Full(encode_r(OPCODE_OP, rd, funct3, rs1, rs2, funct7))
+}
+
+const fn op_imm(rd: u8, funct3: u8, rs1: u8, imm: i32) -> Instr {
+    Instr::Full(encode_i(OPCODE_OP_IMM, rd, funct3, rs1, imm))
+}
+
+const fn addi(rd: u8, rs1: u8, imm: i32) -> Instr {
+    op_imm(rd, 0, rs1, imm)
+}
+
+const fn add(rd: u8, rs1: u8, rs2: u8) -> Instr {
+    op(rd, 0, rs1, rs2, 0)
+}
+
+/// A extension doubleword operation `funct5` on `(sp)`.
+const fn amo_d(rd: u8, rs2: u8, funct5: u8) -> Instr {
+    Instr::Full(encode_r(OPCODE_AMO, rd, FUNCT3_D, REG_SP, rs2, funct5 << 2))
+}
+
+/// The loop body, folding every result into `s1`.
+fn loop_body() -> Vec<Instr> {
+    vec![
+        addi(REG_A0, REG_T1, 7),
+        // M
+        op(REG_A1, 0, REG_A0, REG_A0, 1),
+        op(REG_A2, 5, REG_A1, REG_T1, 1),
+        op(REG_A3, 6, REG_A1, REG_A0, 1),
+        op(REG_A4, 3, REG_S1, REG_A1, 1),
+        add(REG_S1, REG_S1, REG_A1),
+        add(REG_S1, REG_S1, REG_A2),
+        op(REG_S1, 4, REG_S1, REG_A3, 0),
+        add(REG_S1, REG_S1, REG_A4),
+        // C: c.addi a0, 5; c.mv a1, a0; c.slli a1, 3; c.add a0, a1;
+        // c.sub a0, a1
+        Instr::Compressed(0x0515),
+        Instr::Compressed(0x85aa),
+        Instr::Compressed(0x058e),
+        Instr::Compressed(0x952e),
+        Instr::Compressed(0x8d0d),
+        add(REG_S1, REG_S1, REG_A0),
+        // c.swsp a0, 8(sp); c.lwsp a2, 8(sp)
+        Instr::Compressed(0xc42a),
+        Instr::Compressed(0x4622),
+        add(REG_S1, REG_S1, REG_A2),
+        // A: amoadd.d, lr.d, sc.d
+        amo_d(REG_A4, REG_A0, 0b00000),
+        amo_d(REG_A5, REG_ZERO, 0b00010),
+        amo_d(REG_T0, REG_S1, 0b00011),
+        add(REG_S1, REG_S1, REG_A4),
+        add(REG_S1, REG_S1, REG_A5),
+        add(REG_S1, REG_S1, REG_T0),
+        // Zba sh2add, Zbb clz, cpop, rev8, orc.b, rori, max, andn, Zbs bseti
+        op(REG_A3, 4, REG_A0, REG_S1, 0x10),
+        op_imm(REG_A4, 1, REG_S1, 0x600),
+        op_imm(REG_A5, 1, REG_S1, 0x602),
+        op_imm(REG_A2, 5, REG_S1, 0x6b8),
+        op_imm(REG_A1, 5, REG_A2, 0x287),
+        op_imm(REG_A2, 5, REG_A1, 0x60d),
+        op(REG_A4, 6, REG_A4, REG_A5, 0x05),
+        op(REG_A1, 7, REG_A2, REG_A3, 0x20),
+        op_imm(REG_A1, 1, REG_A1, 0x2a8),
+        add(REG_S1, REG_S1, REG_A4),
+        op(REG_S1, 4, REG_S1, REG_A1, 0),
+        // Zicond czero.eqz; addiw, mulw, add.uw
+        op(REG_T0, 5, REG_S1, REG_T1, 0x07),
+        Instr::Full(encode_i(OPCODE_OP_IMM_32, REG_A0, 0, REG_T0, -3)),
+        Instr::Full(encode_r(OPCODE_OP_32, REG_A1, 0, REG_A0, REG_S1, 1)),
+        Instr::Full(encode_r(OPCODE_OP_32, REG_A2, 0, REG_A1, REG_S1, 0x04)),
+        add(REG_S1, REG_S1, REG_A2),
+        addi(REG_T1, REG_T1, -1),
+    ]
+}
+
+/// Start PC of the loop block.
+const LOOP: u64 = TEXT + 16;
+
+/// Run the loop `ITERATIONS` times, then `exit(s1 & 0xff)`.
+fn guest_elf() -> Vec<u8> {
+    let mut text = vec![
+        Instr::Full(encode_u(OPCODE_LUI, REG_SP, STACK_TOP >> 12)),
+        addi(REG_SP, REG_SP, -16),
+        addi(REG_T1, REG_ZERO, ITERATIONS),
+        addi(REG_S1, REG_ZERO, 0),
+    ];
+    let body = loop_body();
+    let body_size: usize = body.iter().map(|i| i.size()).sum();
+    let back = -i32::try_from(body_size).expect("loop size");
+    text.extend(body);
+    text.push(Instr::Full(encode_b(
+        OPCODE_BRANCH,
+        FUNCT3_BNE,
+        REG_T1,
+        REG_ZERO,
+        back,
+    )));
+    text.extend([
+        op_imm(REG_A0, 7, REG_S1, 0xff),
+        Instr::Full(li(REG_A7, SYS_EXIT)),
+        Instr::Full(ECALL),
+    ]);
+    let bytes = text
+        .iter()
+        .flat_map(|instr| match *instr {
+            Instr::Full(raw) => raw.to_le_bytes().to_vec(),
+            Instr::Compressed(raw) => raw.to_le_bytes().to_vec(),
+        })
+        .collect();
+    ElfWriter::<Rv64>::new(TEXT)
+        .with_segment(TEXT, PF_R | PF_X, bytes)
+        .build()
+}
+
+fn write_guest(dir: &Path) -> PathBuf {
+    let elf = dir.join("interp.elf");
+    std::fs::write(&elf, guest_elf()).expect("write ELF");
+    elf
+}
+
+/// Options keeping the loop and exit blocks apart.
+fn options() -> CompileOptions {
+    CompileOptions::new()
+        .with_quiet(true)
+        .with_superblock(false)
+}
+
+fn compile_and_run(elf: &Path, out: &Path, options: &CompileOptions) -> u8 {
+    rvr::compile_with_options(elf, out, options).expect("compile");
+    let mut runner = Runner::load(out, elf).expect("load runner");
+    runner.run().expect("run guest").exit_code
+}
+
+#[test]
+fn test_interpreted_block_matches_compiled() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = write_guest(temp.path());
+    let compiled = compile_and_run(&elf, &temp.path().join("compiled"), &options());
+
+    for instret in [InstretMode::Count, InstretMode::Suspend, InstretMode::Off] {
+        let out = temp.path().join(NAME);
+        let options = options()
+            .with_instret_mode(instret)
+            .with_interpret_blocks([LOOP]);
+        assert_eq!(
+            compile_and_run(&elf, &out, &options),
+            compiled,
+            "{instret:?}"
+        );
+        assert!(out.join(format!("{NAME}_interp.c")).exists());
+    }
+}
+
+#[test]
+fn test_interpreted_block_diff_trace_is_coherent() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = write_guest(temp.path());
+    let diff_options = options()
+        .with_instret_mode(InstretMode::PerInstruction)
+        .with_tracer_config(TracerConfig::builtin(TracerKind::Diff));
+    let compiled = temp.path().join("compiled");
+    let interpreted = temp.path().join(NAME);
+    rvr::compile_with_options(&elf, &compiled, &diff_options).expect("compile");
+    let interp_options = diff_options.with_interpret_blocks([LOOP]);
+    rvr::compile_with_options(&elf, &interpreted, &interp_options).expect("compile");
+
+    let mut expected = InProcessExecutor::new(&compiled, &elf).expect("load compiled");
+    let mut actual = InProcessExecutor::new(&interpreted, &elf).expect("load interpreted");
+    let config = CompareConfig {
+        strict_reg_writes: true,
+        strict_mem_access: true,
+        strict_csrs: false,
+    };
+    let result = diff::compare_lockstep(&mut expected, &mut actual, &config, None);
+    if let Some(div) = result.divergence {
+        panic!(
+            "divergence after {} instructions at {:#x}: {:?}",
+            div.index, div.expected.pc, div.kind
+        );
+    }
+    let instructions = ITERATIONS as usize * (loop_body().len() + 1);
+    assert!(result.matched > instructions, "{} matched", result.matched);
+}
+
+/// Launcher that prepends an `#error` to the part files until they call
+/// the block interpreter.
+fn write_launcher(dir: &Path) -> String {
+    let path = dir.join("inject.sh");
+    let script = "#!/bin/sh\n\
+         for arg in \"$@\"; do\n\
+         case \"$arg\" in *_part*.c)\n\
+         grep -q 'rv_interpret(' \"$arg\" || { printf '#error not interpreted\\n' | cat - \"$arg\" > \"$arg.tmp\" && mv \"$arg.tmp\" \"$arg\"; } ;;\n\
+         esac\n\
+         done\n\
+         exec \"$@\"\n";
+    std::fs::write(&path, script).expect("write launcher");
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+        .expect("make launcher executable");
+    path.display().to_string()
+}
+
+#[test]
+fn test_failed_part_is_interpreted() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = write_guest(temp.path());
+    let compiled = compile_and_run(&elf, &temp.path().join("compiled"), &options());
+
+    let failing = options()
+        .with_compiler_launcher(CompilerLauncher::Command(write_launcher(temp.path())))
+        .with_fallback_opt_level(None);
+    let err = rvr::compile_with_options(&elf, &temp.path().join("failing"), &failing)
+        .expect_err("part fails");
+    assert!(matches!(err, Error::PartsFailed { .. }), "{err}");
+
+    let out = temp.path().join(NAME);
+    let retried = failing.with_interpret_failed_blocks(true);
+    assert_eq!(compile_and_run(&elf, &out, &retried), compiled);
+    assert!(out.join(format!("{NAME}_interp.c")).exists());
+}
+
+#[test]
+fn test_uninterpretable_block_is_rejected() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = write_guest(temp.path());
+    // The exit block holds the ECALL
+    let exit_block = LOOP + (loop_body().iter().map(|i| i.size()).sum::<usize>() + 4) as u64;
+    let cases = [
+        (exit_block, "ecall"),
+        (LOOP + 4, "not the start of a lifted block"),
+    ];
+    for (pc, reason) in cases {
+        let options = options().with_interpret_blocks([pc]);
+        let err = rvr::compile_with_options(&elf, &temp.path().join(NAME), &options)
+            .expect_err("cannot interpret");
+        let message = err.to_string();
+        assert!(
+            message.contains(&format!("block {pc:#x} cannot be interpreted")),
+            "{message}"
+        );
+        assert!(message.contains(reason), "{message}");
+    }
+}
@@ -0,0 +1,87 @@
+//! Extension selection: the ELF's `Tag_RISCV_arch` attribute picks the
+//! decoded extensions unless `EmitConfig::isa` overrides it.
+
+use guest::{ECALL, FUNCT7_MULDIV, OPCODE_OP, OPCODE_OP_IMM, li};
+use rvr::test_support::guest;
+use rvr::{ElfImage, EmitConfig, Error, Pipeline, PipelineStats, Recompiler};
+use rvr_elf::ArchAttributes;
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{REG_A0, REG_A7, REG_ZERO, Rv64, encode_i, encode_r};
+
+const TEXT: u64 = 0x1000;
+const ALL: [&str; 14] = [
+    "Zcmp", "Zcb", "C", "I", "M", "A", "Zicsr", "Zifencei", "Zba", "Zbb", "Zbs", "Zbkb", "Zicond",
+    "V",
+];
+
+/// `a0 = 3; mul a0, a0, a0; exit(a0)`, with `arch` as its ISA attribute.
+fn elf(arch: Option<&str>) -> Vec<u8> {
+    let text = [
+        encode_i(OPCODE_OP_IMM, REG_A0, 0, REG_ZERO, 3),
+        encode_r(OPCODE_OP, REG_A0, 0, REG_A0, REG_A0, FUNCT7_MULDIV),
+        li(REG_A7, SYS_EXIT),
+        ECALL,
+    ];
+    let writer = guest::text_elf::<Rv64>(TEXT, &text);
+    let writer = match arch {
+        Some(arch) => writer.with_attributes(ArchAttributes {
+            arch: Some(arch.to_string()),
+            stack_align: Some(16),
+        }),
+        None => writer,
+    };
+    writer.build()
+}
+
+fn lift_stats(arch: Option<&str>, config: EmitConfig<Rv64>) -> rvr::Result<PipelineStats> {
+    let image = ElfImage::<Rv64>::parse(&elf(arch)).expect("parse ELF");
+    let registry = Recompiler::new(config.clone()).extension_registry(&image)?;
+    let mut pipeline = Pipeline::with_registry(image, config, registry);
+    pipeline.build_cfg()?;
+    pipeline.lift_to_ir()?;
+    Ok(pipeline.stats())
+}
+
+#[test]
+fn test_extensions_from_attributes() {
+    let stats = lift_stats(Some("rv64i2p1_m2p0_c2p0_zicsr2p0"), EmitConfig::default()).unwrap();
+    assert_eq!(stats.extensions, ["C", "I", "M", "Zicsr"]);
+    assert!(stats.unsupported.is_empty());
+
+    // Unsupported extensions are left out; the rest still decode
+    let stats = lift_stats(Some("rv64gcv"), EmitConfig::default()).unwrap();
+    assert_eq!(
+        stats.extensions,
+        ["C", "I", "M", "A", "Zicsr", "Zifencei", "V"]
+    );
+
+    // Without M, the multiply no longer decodes
+    let stats = lift_stats(Some("rv64i2p1"), EmitConfig::default()).unwrap();
+    assert_eq!(stats.extensions, ["I"]);
+    assert_eq!(stats.unsupported.len(), 1);
+    assert_eq!(stats.unsupported[0].pc, TEXT + 4);
+}
+
+#[test]
+fn test_extensions_without_attributes() {
+    let stats = lift_stats(None, EmitConfig::default()).unwrap();
+    assert_eq!(stats.extensions, ALL);
+
+    // A malformed attribute falls back to every extension
+    let stats = lift_stats(Some("x86_64"), EmitConfig::default()).unwrap();
+    assert_eq!(stats.extensions, ALL);
+}
+
+#[test]
+fn test_isa_override() {
+    let config = EmitConfig::default().with_isa("rv64im_zba");
+    let stats = lift_stats(Some("rv64i2p1"), config).unwrap();
+    assert_eq!(stats.extensions, ["I", "M", "Zba"]);
+    assert!(stats.unsupported.is_empty());
+
+    let config = EmitConfig::default().with_isa("imac");
+    assert!(matches!(
+        lift_stats(None, config),
+        Err(Error::InvalidIsa(_))
+    ));
+}
@@ -0,0 +1,158 @@
+//! Out-of-process execution: an isolated runner reports the same results as
+//! an in-process one, and a guest that segfaults with address checks off
+//! takes down only its child, which the parent reports as `ChildCrashed`.
+#![cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+
+use std::path::Path;
+
+use guest::{
+    Compiled, ECALL, FUNCT3_BNE, FUNCT3_SD, FUNCT3_SLL, OPCODE_BRANCH, OPCODE_LUI, OPCODE_OP_IMM,
+    OPCODE_STORE, addi, li,
+};
+use rvr::test_support::guest;
+use rvr::{
+    AddressMode, CompileOptions, ExitReason, InstretMode, IsolationOptions, RunError, Runner,
+};
+use rvr_elf::{PF_R, PF_W};
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{REG_A0, REG_A7, REG_T0, REG_ZERO, Rv64, encode_b, encode_i, encode_s, encode_u};
+
+const EXIT_CODE: i32 = 5;
+/// Signal of a host segfault.
+const SIGSEGV: i32 = 11;
+
+const TEXT: u64 = 0x1000;
+/// Page number of the data segment (the `lui` immediate).
+const DATA_PAGE: u32 = 2;
+const DATA: u64 = (DATA_PAGE as u64) << 12;
+/// Written to `DATA` by the exiting guest.
+const MARKER: i32 = 0x5a;
+
+/// `*DATA = MARKER; exit(EXIT_CODE)`.
+const EXIT: [u32; 6] = [
+    encode_u(OPCODE_LUI, REG_T0, DATA_PAGE),
+    addi(REG_A0, REG_ZERO, MARKER),
+    encode_s(OPCODE_STORE, FUNCT3_SD, REG_T0, REG_A0, 0),
+    addi(REG_A0, REG_ZERO, EXIT_CODE),
+    li(REG_A7, SYS_EXIT),
+    ECALL,
+];
+
+/// `for (a0 = 10; a0 != 0; a0--); exit(0)`.
+const COUNTDOWN: [u32; 5] = [
+    addi(REG_A0, REG_ZERO, 10),
+    addi(REG_A0, REG_A0, -1),
+    encode_b(OPCODE_BRANCH, FUNCT3_BNE, REG_A0, REG_ZERO, -4),
+    li(REG_A7, SYS_EXIT),
+    ECALL,
+];
+
+/// Store far outside guest memory: `t0 = 0x7ffff << 36; *t0 = 0`.
+const OUT_OF_BOUNDS: [u32; 4] = [
+    encode_u(OPCODE_LUI, REG_T0, 0x7ffff),
+    encode_i(OPCODE_OP_IMM, REG_T0, FUNCT3_SLL, REG_T0, 24),
+    encode_s(OPCODE_STORE, FUNCT3_SD, REG_T0, REG_ZERO, 0),
+    ECALL,
+];
+
+fn elf(text: &[u32]) -> Vec<u8> {
+    guest::text_elf::<Rv64>(TEXT, text)
+        .with_segment(DATA, PF_R | PF_W, vec![0; 8])
+        .build()
+}
+
+/// Compile `text` into `dir`.
+fn compile(dir: &Path, text: &[u32], options: &CompileOptions) -> Compiled {
+    guest::compile(dir, "guest", &elf(text), options)
+}
+
+/// Options running the helper cargo built for this test.
+fn isolation(compiled: &Compiled) -> IsolationOptions {
+    IsolationOptions::new(&compiled.out, &compiled.elf)
+        .with_helper(env!("CARGO_BIN_EXE_rvr-isolated"))
+}
+
+#[test]
+fn test_isolated_run_matches_in_process() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let compiled = compile(temp.path(), &EXIT, &CompileOptions::new());
+
+    let expected = Runner::load(&compiled.out, &compiled.elf)
+        .unwrap()
+        .run()
+        .unwrap();
+    let mut isolated = Runner::spawn_isolated(&isolation(&compiled)).unwrap();
+    let result = isolated.run().unwrap();
+    assert_eq!(result.exit_reason, ExitReason::Exited(5));
+    assert_eq!(result.instret, expected.instret);
+
+    let state = isolated.state().unwrap();
+    assert!(state.exited);
+    assert_eq!(state.regs[usize::from(REG_A0)], 5);
+    let mut data = [0; 1];
+    assert_eq!(isolated.read_memory(DATA, &mut data).unwrap(), 1);
+    assert_eq!(i32::from(data[0]), MARKER);
+}
+
+#[test]
+fn test_out_of_bounds_guest_crashes_only_the_child() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let options = CompileOptions::new().with_address_mode(AddressMode::Unchecked);
+    let compiled = compile(temp.path(), &OUT_OF_BOUNDS, &options);
+
+    let mut isolated = Runner::spawn_isolated(&isolation(&compiled)).unwrap();
+    let err = isolated.run().unwrap_err();
+    assert!(matches!(err, RunError::ChildCrashed(SIGSEGV)), "{err}");
+    // The dead child keeps reporting the crash.
+    assert!(matches!(
+        isolated.state(),
+        Err(RunError::ChildCrashed(SIGSEGV))
+    ));
+
+    // The parent is intact and can start another guest.
+    let good = temp.path().join("good");
+    std::fs::create_dir_all(&good).expect("mkdir");
+    let compiled = compile(&good, &EXIT, &CompileOptions::new());
+    let mut isolated = Runner::spawn_isolated(&isolation(&compiled)).unwrap();
+    assert_eq!(isolated.run().unwrap().exit_reason, ExitReason::Exited(5));
+}
+
+#[test]
+fn test_seccomp_child_runs_guest() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let compiled = compile(temp.path(), &EXIT, &CompileOptions::new());
+    let options = isolation(&compiled).with_seccomp(true);
+    let mut isolated = Runner::spawn_isolated(&options).unwrap();
+    assert_eq!(isolated.run().unwrap().exit_reason, ExitReason::Exited(5));
+}
+
+#[test]
+fn test_isolated_suspend_and_snapshot() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let options = CompileOptions::new()
+        .with_instret_mode(InstretMode::Suspend)
+        .with_superblock(false);
+    let compiled = compile(temp.path(), &COUNTDOWN, &options);
+
+    let mut isolated = Runner::spawn_isolated(&isolation(&compiled)).unwrap();
+    assert!(isolated.supports_suspend());
+    isolated.prepare().unwrap();
+    assert!(isolated.set_target_instret(5).unwrap());
+    let entry = isolated.entry_point();
+    isolated.execute_from(entry).unwrap();
+    let suspended = isolated.state().unwrap();
+    assert!(!suspended.exited);
+    assert!(suspended.regs[usize::from(REG_A0)] > 0);
+
+    let snapshot = isolated.snapshot().unwrap();
+    assert_eq!(snapshot.pc(), suspended.pc);
+    assert!(isolated.set_target_instret(u64::MAX).unwrap());
+    isolated.execute_from(snapshot.pc()).unwrap();
+    assert_eq!(isolated.exit_reason().unwrap(), ExitReason::Exited(0));
+
+    isolated.restore(&snapshot).unwrap();
+    assert_eq!(isolated.state().unwrap(), suspended);
+}
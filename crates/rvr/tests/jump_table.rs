@@ -0,0 +1,139 @@
+//! Jump tables: a bounds-checked `switch` over a read-only table of code
+//! addresses is resolved by CFG analysis into direct jumps, and every case
+//! still runs correctly.
+//!
+//! The guests are hand-encoded in the shape compilers emit for a `switch`:
+//! `bgeu idx, n, default; slli; add table; lw; jr`. A loop walks the index
+//! over every table entry so one run takes every case.
+
+use guest::{
+    ECALL, FUNCT3_ADD, FUNCT3_SLLI, OPCODE_BRANCH, OPCODE_JAL, OPCODE_JALR, OPCODE_LOAD,
+    OPCODE_LUI, OPCODE_OP, OPCODE_OP_IMM, addi, li,
+};
+use rvr::test_support::guest;
+use rvr::{CompileOptions, ElfImage, EmitConfig, ExitReason, Pipeline, PipelineStats, Runner};
+use rvr_elf::PF_R;
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{
+    REG_A0, REG_A7, REG_S0, REG_S1, REG_T0, REG_T1, REG_T2, REG_ZERO, Rv64, encode_b, encode_i,
+    encode_j, encode_r, encode_u,
+};
+
+const FUNCT3_LW: u8 = 0b010;
+const FUNCT3_BGEU: u8 = 0b111;
+
+const TEXT: u64 = 0x1000;
+/// Read-only jump table of 4-byte absolute case addresses.
+const TABLE: u64 = 0x2000;
+/// Instructions before the first case.
+const HEADER_INSTRS: usize = 9;
+/// Instructions per case.
+const CASE_INSTRS: usize = 3;
+/// Index of the loop head (`li t0, n`).
+const LOOP_HEAD: usize = 2;
+/// Shift from table index to byte offset.
+const ENTRY_SHIFT: i32 = 2;
+
+fn offset(from: usize, to: usize) -> i32 {
+    (i32::try_from(to).unwrap() - i32::try_from(from).unwrap()) * 4
+}
+
+fn case_addr(case: usize) -> u32 {
+    u32::try_from(TEXT).unwrap() + u32::try_from((HEADER_INSTRS + case * CASE_INSTRS) * 4).unwrap()
+}
+
+/// Runs `s1 += values[entries[i]]` for every `i`, then exits with `s1`.
+fn switch_elf(entries: &[usize], values: &[i32]) -> Vec<u8> {
+    let n = i32::try_from(entries.len()).unwrap();
+    let done = HEADER_INSTRS + values.len() * CASE_INSTRS;
+    let mut text = vec![
+        addi(REG_S0, REG_ZERO, 0),
+        addi(REG_S1, REG_ZERO, 0),
+        // loop: if (s0 >= n) goto done
+        addi(REG_T0, REG_ZERO, n),
+        encode_b(
+            OPCODE_BRANCH,
+            FUNCT3_BGEU,
+            REG_S0,
+            REG_T0,
+            offset(LOOP_HEAD + 1, done),
+        ),
+        // t1 = table[s0]; goto t1
+        encode_i(OPCODE_OP_IMM, REG_T1, FUNCT3_SLLI, REG_S0, ENTRY_SHIFT),
+        encode_u(OPCODE_LUI, REG_T2, u32::try_from(TABLE >> 12).unwrap()),
+        encode_r(OPCODE_OP, REG_T1, FUNCT3_ADD, REG_T1, REG_T2, 0),
+        encode_i(OPCODE_LOAD, REG_T1, FUNCT3_LW, REG_T1, 0),
+        encode_i(OPCODE_JALR, REG_ZERO, 0, REG_T1, 0),
+    ];
+    assert_eq!(text.len(), HEADER_INSTRS);
+    for &value in values {
+        let at = text.len() + CASE_INSTRS - 1;
+        text.extend([
+            addi(REG_S1, REG_S1, value),
+            addi(REG_S0, REG_S0, 1),
+            encode_j(OPCODE_JAL, REG_ZERO, offset(at, LOOP_HEAD)),
+        ]);
+    }
+    text.extend([addi(REG_A0, REG_S1, 0), li(REG_A7, SYS_EXIT), ECALL]);
+
+    let table = entries
+        .iter()
+        .flat_map(|&case| case_addr(case).to_le_bytes())
+        .collect();
+    guest::text_elf::<Rv64>(TEXT, &text)
+        .with_segment(TABLE, PF_R, table)
+        .build()
+}
+
+/// Four cases, one entry each.
+fn dense_elf() -> Vec<u8> {
+    switch_elf(&[0, 1, 2, 3], &[1, 2, 4, 8])
+}
+
+/// Three cases spread over eight entries; the holes go to the default case.
+fn sparse_elf() -> Vec<u8> {
+    switch_elf(&[0, 3, 3, 1, 3, 3, 2, 3], &[1, 2, 4, 16])
+}
+
+fn lift_stats(elf: &[u8]) -> PipelineStats {
+    let image = ElfImage::parse(elf).expect("parse ELF");
+    let mut pipeline = Pipeline::<Rv64>::new(image, EmitConfig::default());
+    pipeline.build_cfg().expect("build CFG");
+    pipeline.lift_to_ir().expect("lift");
+    pipeline.stats()
+}
+
+fn run(elf_bytes: &[u8]) -> ExitReason {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = temp.path().join("switch.elf");
+    std::fs::write(&elf, elf_bytes).expect("write ELF");
+    let out = temp.path().join("out");
+    let options = CompileOptions::new().with_quiet(true);
+    rvr::compile_with_options(&elf, &out, &options).expect("compile");
+    let mut runner = Runner::load(&out, &elf).expect("load runner");
+    runner.run().expect("run guest").exit_reason
+}
+
+#[test]
+fn test_dense_switch_resolved() {
+    let stats = lift_stats(&dense_elf());
+    assert_eq!(stats.resolved_jumps, 1);
+    assert_eq!(stats.unresolved_jumps, 0);
+}
+
+#[test]
+fn test_sparse_switch_resolved() {
+    let stats = lift_stats(&sparse_elf());
+    assert_eq!(stats.resolved_jumps, 1);
+    assert_eq!(stats.unresolved_jumps, 0);
+}
+
+#[test]
+fn test_dense_switch_runs() {
+    assert_eq!(run(&dense_elf()), ExitReason::Exited(15));
+}
+
+#[test]
+fn test_sparse_switch_runs() {
+    assert_eq!(run(&sparse_elf()), ExitReason::Exited(87));
+}
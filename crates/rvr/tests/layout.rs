@@ -0,0 +1,194 @@
+//! Layout profiles: a guest laid out per profile, and images that do not match.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use guest::{ECALL, FUNCT3_ADD, OPCODE_LOAD, OPCODE_LUI, OPCODE_OP, OPCODE_OP_IMM, OPCODE_STORE};
+use rvr::test_support::guest;
+use rvr::{
+    AddrRange, CompileOptions, Error, LayoutMismatch, LayoutProfile, RunError, Runner, image_layout,
+};
+use rvr_elf::{ElfImage, PF_R, PF_W};
+use rvr_isa::{
+    REG_A0, REG_A1, REG_SP, REG_T0, Rv32, Rv64, Xlen, encode_i, encode_r, encode_s, encode_u,
+};
+
+const FUNCT3_WORD: u8 = 0b010;
+const PAGE_SHIFT: u32 = 12;
+/// Word stored in the data segment; the guest exits with `2 * DATA_WORD + 2`.
+const DATA_WORD: u32 = 20;
+const EXIT_CODE: u8 = 42;
+
+/// Load `DATA_WORD` from `data`, round-trip it through the stack (`sp` comes
+/// from the layout) and exit with `EXIT_CODE`.
+fn guest_elf<X: Xlen>(text: u64, data: u64) -> Vec<u8> {
+    let data_page = u32::try_from(data >> PAGE_SHIFT).expect("data address fits lui");
+    let code = [
+        encode_u(OPCODE_LUI, REG_T0, data_page),
+        encode_i(OPCODE_LOAD, REG_A0, FUNCT3_WORD, REG_T0, 0),
+        encode_i(OPCODE_OP_IMM, REG_SP, FUNCT3_ADD, REG_SP, -16),
+        encode_s(OPCODE_STORE, FUNCT3_WORD, REG_SP, REG_A0, 0),
+        encode_i(OPCODE_LOAD, REG_A1, FUNCT3_WORD, REG_SP, 0),
+        encode_r(OPCODE_OP, REG_A0, FUNCT3_ADD, REG_A0, REG_A1, 0),
+        encode_i(OPCODE_OP_IMM, REG_A0, FUNCT3_ADD, REG_A0, 2),
+        ECALL,
+    ];
+    guest::text_elf::<X>(text, &code)
+        .with_segment(data, PF_R | PF_W, DATA_WORD.to_le_bytes().to_vec())
+        .build()
+}
+
+fn write_elf(dir: &Path, name: &str, elf: &[u8]) -> PathBuf {
+    let path = dir.join(name);
+    std::fs::write(&path, elf).expect("write ELF");
+    path
+}
+
+fn options(layout: LayoutProfile) -> CompileOptions {
+    CompileOptions::new().with_quiet(true).with_layout(layout)
+}
+
+/// Start of the profile's code region, and a data address one page above it.
+const fn placement(layout: LayoutProfile) -> (u64, u64) {
+    let code = layout.spec().regions.code.start;
+    (code, code + (1 << PAGE_SHIFT))
+}
+
+fn compile_and_run(layout: LayoutProfile, elf: &[u8]) {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = write_elf(temp.path(), "guest.elf", elf);
+    let out = temp.path().join("guest");
+
+    rvr::compile_with_options(&elf, &out, &options(layout)).expect("compile matching guest");
+    let mut runner = Runner::load(&out, &elf).expect("load runner");
+    let result = runner.run().expect("run guest");
+    assert_eq!(result.exit_code, EXIT_CODE);
+}
+
+#[test]
+fn test_baremetal_guest_runs() {
+    let (text, data) = placement(LayoutProfile::Baremetal);
+    compile_and_run(LayoutProfile::Baremetal, &guest_elf::<Rv64>(text, data));
+}
+
+#[test]
+fn test_rv32_zkvm_guest_runs() {
+    let (text, data) = placement(LayoutProfile::Rv32ZkVm);
+    compile_and_run(LayoutProfile::Rv32ZkVm, &guest_elf::<Rv32>(text, data));
+}
+
+#[test]
+fn test_mismatched_guest_fails_compile() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    // riscv-tests style placement
+    let elf = write_elf(
+        temp.path(),
+        "guest.elf",
+        &guest_elf::<Rv32>(0x8000_0000, 0x8000_1000),
+    );
+
+    match rvr::compile_with_options(
+        &elf,
+        &temp.path().join("guest"),
+        &options(LayoutProfile::Rv32ZkVm),
+    ) {
+        Err(Error::Layout(err)) => {
+            assert_eq!(
+                err.mismatch,
+                LayoutMismatch::Segment {
+                    kind: "code",
+                    segment: AddrRange::new(0x8000_0000, 0x8000_0020),
+                    region: AddrRange::new(0x0020_1000, 0x1000_0000),
+                }
+            );
+            assert_eq!(
+                err.to_string(),
+                "image does not match layout profile `rv32-zkvm`: code segment [0x80000000, 0x80000020) is outside the code region [0x201000, 0x10000000)"
+            );
+        }
+        other => panic!("expected a layout mismatch, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_mismatched_elf_fails_load() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let (text, data) = placement(LayoutProfile::Rv32ZkVm);
+    let elf = write_elf(temp.path(), "guest.elf", &guest_elf::<Rv32>(text, data));
+    let out = temp.path().join("guest");
+    rvr::compile_with_options(&elf, &out, &options(LayoutProfile::Rv32ZkVm))
+        .expect("compile matching guest");
+
+    // Same code, data moved into the stack region
+    let moved = write_elf(
+        temp.path(),
+        "moved.elf",
+        &guest_elf::<Rv32>(text, 0x0010_0000),
+    );
+    match Runner::load(&out, &moved) {
+        Err(RunError::LayoutMismatch(err)) => assert_eq!(
+            err.to_string(),
+            "image does not match layout profile `rv32-zkvm`: data segment [0x100000, 0x100004) is outside the data region [0x201000, 0x78000000)"
+        ),
+        Err(other) => panic!("expected a layout mismatch, got {other:?}"),
+        Ok(_) => panic!("expected a layout mismatch, got a runner"),
+    }
+}
+
+#[test]
+fn test_profile_guests_match_their_profiles() {
+    for layout in [LayoutProfile::Baremetal, LayoutProfile::Rv32ZkVm] {
+        let (text, data) = placement(layout);
+        let elf = guest_elf::<Rv32>(text, data);
+        let image = ElfImage::<Rv32>::parse(&elf).expect("parse guest");
+        assert_eq!(
+            layout.validate(&image_layout(&image)),
+            Ok(()),
+            "{}",
+            layout.name()
+        );
+    }
+}
+
+/// `MEMORY` regions (`NAME : ORIGIN = <hex>, LENGTH = <hex or N M>`) of a
+/// linker script.
+fn memory_regions(script: &str) -> HashMap<&str, AddrRange> {
+    let parse = |value: &str| {
+        let value = value.trim();
+        value.strip_prefix("0x").map_or_else(
+            || value.trim_end_matches('M').parse::<u64>().expect("size") << 20,
+            |hex| u64::from_str_radix(hex, 16).expect("hex"),
+        )
+    };
+    script
+        .lines()
+        .filter_map(|line| {
+            let (name, rest) = line.split_once(": ORIGIN =")?;
+            let (origin, length) = rest.split_once(", LENGTH =")?;
+            let start = parse(origin);
+            Some((name.trim(), AddrRange::new(start, start + parse(length))))
+        })
+        .collect()
+}
+
+fn rvr_rt_script(name: &str) -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../rvr-rt")
+        .join(name);
+    std::fs::read_to_string(path).expect("read rvr-rt linker script")
+}
+
+#[test]
+fn test_rvr_rt_scripts_match_profiles() {
+    let baremetal = LayoutProfile::Baremetal.spec().regions;
+    let ram = memory_regions(&rvr_rt_script("link.x"))["RAM"];
+    assert_eq!(ram.start, baremetal.code.start);
+    assert_eq!(ram.end, baremetal.stack.end);
+
+    let zkvm = LayoutProfile::Rv32ZkVm.spec().regions;
+    let script = rvr_rt_script("link-rv32-zkvm.x");
+    let regions = memory_regions(&script);
+    assert_eq!(regions["STACK"], zkvm.stack);
+    assert_eq!(regions["RAM"].start, zkvm.code.start);
+    assert_eq!(regions["RAM"].end, zkvm.heap.end);
+}
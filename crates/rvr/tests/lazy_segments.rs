@@ -0,0 +1,86 @@
+//! Lazy segment init: segment data goes to `<name>.segments` instead of the
+//! library, and every run maps it afresh so guest writes never leak into
+//! the image or the next run.
+
+use guest::{FUNCT3_ADDI, OPCODE_LOAD, OPCODE_LUI, OPCODE_OP_IMM, OPCODE_STORE, OPCODE_SYSTEM};
+use rvr::test_support::guest;
+use rvr::{CompileOptions, Runner};
+use rvr_elf::{PF_R, PF_W};
+use rvr_isa::{REG_A0, REG_A1, REG_A2, REG_ZERO, Rv64, encode_i, encode_s, encode_u};
+
+const TEXT: u64 = 0x1_0000;
+/// Data segment spanning several pages, starting mid-page.
+const DATA: u64 = 0x2_0800;
+const DATA_LEN: u64 = 0x3_0000;
+/// Word the guest reads and overwrites, on a page inside the data segment.
+const WORD: u64 = 0x3_0000;
+const VALUE: u8 = 42;
+
+const FUNCT3_W: u8 = 0b010;
+
+/// Exits with the word at `WORD` after overwriting it with 7.
+fn guest_elf() -> Vec<u8> {
+    let text = [
+        encode_u(OPCODE_LUI, REG_A1, u32::try_from(WORD >> 12).unwrap()),
+        encode_i(OPCODE_LOAD, REG_A0, FUNCT3_W, REG_A1, 0),
+        encode_i(OPCODE_OP_IMM, REG_A2, FUNCT3_ADDI, REG_ZERO, 7),
+        encode_s(OPCODE_STORE, FUNCT3_W, REG_A1, REG_A2, 0),
+        encode_i(OPCODE_SYSTEM, REG_ZERO, 0, REG_ZERO, 0),
+    ];
+    let mut data = vec![0xA5; usize::try_from(DATA_LEN).unwrap()];
+    let word = usize::try_from(WORD - DATA).unwrap();
+    data[word..word + 4].copy_from_slice(&u32::from(VALUE).to_le_bytes());
+
+    guest::text_elf::<Rv64>(TEXT, &text)
+        .with_segment(DATA, PF_R | PF_W, data)
+        .build()
+}
+
+#[test]
+fn test_lazy_segments_round_trip() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = temp.path().join("lazy.elf");
+    std::fs::write(&elf, guest_elf()).expect("write ELF");
+    let out = temp.path().join("lazy");
+    let options = CompileOptions::new()
+        .with_quiet(true)
+        .with_lazy_segment_init(true);
+    rvr::compile_with_options(&elf, &out, &options).expect("compile");
+
+    let image = out.join("lazy.segments");
+    let image_len = std::fs::metadata(&image).expect("segment image").len();
+    assert!(image_len >= DATA + DATA_LEN - TEXT, "{image_len}");
+    assert!(!out.join("lazy_memory.c").exists());
+
+    let mut runner = Runner::load(&out, &elf).expect("load runner");
+    for _ in 0..2 {
+        let result = runner.run().expect("run guest");
+        assert_eq!(result.exit_code, VALUE);
+        let mut word = [0u8; 4];
+        assert_eq!(runner.read_memory(WORD, &mut word), 4);
+        assert_eq!(u32::from_le_bytes(word), 7);
+        let mut edge = [0u8; 2];
+        assert_eq!(runner.read_memory(DATA - 1, &mut edge), 2);
+        assert_eq!(edge, [0, 0xA5]);
+    }
+
+    // Guest writes stay private to guest memory, not the image
+    let mut runner = Runner::load(&out, &elf).expect("load runner");
+    assert_eq!(runner.run().expect("run guest").exit_code, VALUE);
+}
+
+#[test]
+fn test_lazy_segments_missing_image() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = temp.path().join("lazy.elf");
+    std::fs::write(&elf, guest_elf()).expect("write ELF");
+    let out = temp.path().join("lazy");
+    let options = CompileOptions::new()
+        .with_quiet(true)
+        .with_lazy_segment_init(true);
+    rvr::compile_with_options(&elf, &out, &options).expect("compile");
+
+    std::fs::remove_file(out.join("lazy.segments")).expect("remove image");
+    let err = Runner::load(&out, &elf).err().expect("load without image");
+    assert!(err.to_string().contains("lazy.segments"), "{err}");
+}
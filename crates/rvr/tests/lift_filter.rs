@@ -0,0 +1,98 @@
+//! `CompileOptions::with_filter`: a filtered lift keeps the selected
+//! function's blocks and stubs out its callees.
+
+use guest::{ECALL, OPCODE_JAL, OPCODE_JALR, OPCODE_OP_IMM, li};
+use rvr::test_support::guest;
+use rvr::{CompileOptions, Error, FilterSpec, lift_to_c_with_options};
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{REG_A0, REG_A7, REG_RA, REG_ZERO, Rv64, encode_i, encode_j};
+
+const MAIN: u64 = 0x1000;
+/// Return site of the call in `main`.
+const MAIN_RETURN: u64 = MAIN + 8;
+const HELPER: u64 = 0x1014;
+
+/// `main` calls `helper`, which adds 3 to `a0`, then exits with `a0`.
+fn guest_elf() -> Vec<u8> {
+    let text = [
+        // main
+        encode_i(OPCODE_OP_IMM, REG_A0, 0, REG_ZERO, 1),
+        encode_j(OPCODE_JAL, REG_RA, 0x10),
+        encode_i(OPCODE_OP_IMM, REG_A0, 0, REG_A0, 2),
+        li(REG_A7, SYS_EXIT),
+        ECALL,
+        // helper
+        encode_i(OPCODE_OP_IMM, REG_A0, 0, REG_A0, 3),
+        encode_i(OPCODE_JALR, REG_ZERO, 0, REG_RA, 0),
+    ];
+    guest::text_elf::<Rv64>(MAIN, &text)
+        .with_function("main", MAIN, HELPER - MAIN)
+        .with_function("helper", HELPER, 8)
+        .build()
+}
+
+fn lift_filtered(filter: FilterSpec) -> Result<String, Error> {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = temp.path().join("guest");
+    std::fs::write(&elf, guest_elf()).expect("write ELF");
+    let options = CompileOptions::new().with_filter(filter);
+    let source = lift_to_c_with_options(&elf, &temp.path().join("out"), &options)?;
+    Ok(std::fs::read_to_string(source).expect("read part"))
+}
+
+/// Definition of the block function at `pc`.
+fn block_def(pc: u64) -> String {
+    format!("void B_{pc:016x}(")
+}
+
+#[test]
+fn test_filter_keeps_selected_blocks() {
+    // (filter, blocks defined with their own code, blocks stubbed out)
+    let cases = [
+        (
+            FilterSpec::new().with_symbol("main"),
+            vec![MAIN, MAIN_RETURN],
+            vec![HELPER],
+        ),
+        (
+            FilterSpec::new().with_range(HELPER..HELPER + 8),
+            vec![HELPER],
+            vec![],
+        ),
+    ];
+    for (filter, kept, stubs) in cases {
+        let source = lift_filtered(filter.clone()).expect("lift");
+        let defined: Vec<&str> = source
+            .split("// Block: ")
+            .skip(1)
+            .filter(|block| !block.contains("left filtered region"))
+            .collect();
+        let stubbed = source.matches("TRAP: left filtered region").count();
+        for pc in &kept {
+            assert!(
+                defined.iter().any(|block| block.contains(&block_def(*pc))),
+                "{filter:?}: no block at {pc:#x}"
+            );
+        }
+        assert_eq!(defined.len(), kept.len(), "{filter:?}: extra blocks");
+        assert_eq!(stubbed, stubs.len(), "{filter:?}: stubs");
+        for pc in stubs {
+            assert!(
+                source.contains(&block_def(pc)),
+                "{filter:?}: no stub at {pc:#x}"
+            );
+        }
+    }
+    // The callee's body is lifted on its own, not with its caller
+    let helper_body = "a0 = (a0 + 0x3ULL);";
+    let helper = lift_filtered(FilterSpec::new().with_symbol("helper")).expect("lift");
+    assert!(helper.contains(helper_body));
+    let main = lift_filtered(FilterSpec::new().with_symbol("main")).expect("lift");
+    assert!(!main.contains(helper_body));
+}
+
+#[test]
+fn test_filter_rejects_unknown_function() {
+    let err = lift_filtered(FilterSpec::new().with_symbol("missing")).unwrap_err();
+    assert!(matches!(err, Error::InvalidFilter(_)), "{err}");
+}
@@ -0,0 +1,159 @@
+//! LR/SC reservation rules: under `LrScModel::AddressReservation` an SC
+//! fails after a store to the reserved doubleword, at another address, or
+//! after another SC; `LrScModel::AlwaysSucceed` lets every SC through.
+//! Both models behave the same on every backend.
+
+use guest::{
+    FUNCT3_ADDI, FUNCT3_D, FUNCT3_SD, FUNCT3_SLLI, FUNCT3_SW, OPCODE_AMO, OPCODE_LUI, OPCODE_OP,
+    OPCODE_OP_IMM, OPCODE_STORE, OPCODE_SYSTEM, li,
+};
+use rvr::test_support::guest;
+use rvr::{Backend, CompileOptions, LrScModel, Runner};
+use rvr_elf::{PF_R, PF_W};
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{
+    REG_A0, REG_A1, REG_A2, REG_A7, REG_T0, REG_T1, REG_T2, REG_ZERO, Rv64, encode_i, encode_r,
+    encode_s, encode_u,
+};
+
+const FUNCT3_OR: u8 = 0b110;
+const FUNCT5_LR: u8 = 0b00010;
+const FUNCT5_SC: u8 = 0b00011;
+
+const TEXT: u64 = 0x1000;
+/// Reserved doubleword; the next one starts at `DATA + 8`.
+const DATA: u64 = 0x2000;
+const DATA_LEN: usize = 16;
+
+/// Exit code bits, set when the SC of that case failed.
+const FAIL_SAME_DOUBLEWORD: u8 = 1 << 0;
+const FAIL_OTHER_DOUBLEWORD: u8 = 1 << 1;
+const FAIL_WRONG_ADDRESS: u8 = 1 << 2;
+const FAIL_SECOND_SC: u8 = 1 << 3;
+const FAIL_FIRST_SC: u8 = 1 << 4;
+/// SCs the spec requires to fail.
+const RESERVATION_FAILURES: u8 = FAIL_SAME_DOUBLEWORD | FAIL_WRONG_ADDRESS | FAIL_SECOND_SC;
+
+/// `lr.d rd, (rs1)`
+const fn lr_d(rd: u8, rs1: u8) -> u32 {
+    encode_r(OPCODE_AMO, rd, FUNCT3_D, rs1, REG_ZERO, FUNCT5_LR << 2)
+}
+
+/// `sc.d rd, zero, (rs1)`
+const fn sc_d(rd: u8, rs1: u8) -> u32 {
+    encode_r(OPCODE_AMO, rd, FUNCT3_D, rs1, REG_ZERO, FUNCT5_SC << 2)
+}
+
+/// `a0 |= rs << bit.trailing_zeros()`
+fn record(rs: u8, bit: u8) -> [u32; 2] {
+    let shift = i32::try_from(bit.trailing_zeros()).unwrap();
+    [
+        encode_i(OPCODE_OP_IMM, rs, FUNCT3_SLLI, rs, shift),
+        encode_r(OPCODE_OP, REG_A0, FUNCT3_OR, REG_A0, rs, 0),
+    ]
+}
+
+/// Runs each case with a1 = DATA and a2 = DATA + 8, and exits with the
+/// `FAIL_*` bits of the SCs that failed.
+fn guest_elf() -> Vec<u8> {
+    let data_page = u32::try_from(DATA >> 12).unwrap();
+    let text = [
+        vec![
+            encode_u(OPCODE_LUI, REG_A1, data_page),
+            encode_i(OPCODE_OP_IMM, REG_A2, FUNCT3_ADDI, REG_A1, 8),
+            encode_i(OPCODE_OP_IMM, REG_A0, FUNCT3_ADDI, REG_ZERO, 0),
+            // Store to the upper word of the reserved doubleword
+            lr_d(REG_T0, REG_A1),
+            encode_s(OPCODE_STORE, FUNCT3_SW, REG_A1, REG_ZERO, 4),
+            sc_d(REG_T1, REG_A1),
+        ],
+        record(REG_T1, FAIL_SAME_DOUBLEWORD).to_vec(),
+        vec![
+            // Store to the next doubleword
+            lr_d(REG_T0, REG_A1),
+            encode_s(OPCODE_STORE, FUNCT3_SD, REG_A2, REG_ZERO, 0),
+            sc_d(REG_T1, REG_A1),
+        ],
+        record(REG_T1, FAIL_OTHER_DOUBLEWORD).to_vec(),
+        vec![
+            // SC to an address other than the reserved one
+            lr_d(REG_T0, REG_A1),
+            sc_d(REG_T1, REG_A2),
+        ],
+        record(REG_T1, FAIL_WRONG_ADDRESS).to_vec(),
+        vec![
+            // The first SC consumes the reservation
+            lr_d(REG_T0, REG_A1),
+            sc_d(REG_T2, REG_A1),
+            sc_d(REG_T1, REG_A1),
+        ],
+        record(REG_T1, FAIL_SECOND_SC).to_vec(),
+        record(REG_T2, FAIL_FIRST_SC).to_vec(),
+        vec![
+            li(REG_A7, SYS_EXIT),
+            encode_i(OPCODE_SYSTEM, REG_ZERO, 0, REG_ZERO, 0),
+        ],
+    ]
+    .concat();
+    guest::text_elf::<Rv64>(TEXT, &text)
+        .with_segment(DATA, PF_R | PF_W, vec![0; DATA_LEN])
+        .build()
+}
+
+/// Exit code of the guest compiled for `backend` under `model`.
+fn run(backend: Backend, model: LrScModel) -> u8 {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = temp.path().join("lrsc.elf");
+    std::fs::write(&elf, guest_elf()).expect("write ELF");
+    let out = temp.path().join("out");
+    let options = CompileOptions::new()
+        .with_quiet(true)
+        .with_backend(backend)
+        .with_lrsc_model(model);
+    rvr::compile_with_options(&elf, &out, &options).expect("compile");
+    let mut runner = Runner::load(&out, &elf).expect("load runner");
+    runner.run().expect("run guest").exit_code
+}
+
+#[test]
+fn test_address_reservation_c() {
+    assert_eq!(
+        run(Backend::C, LrScModel::AddressReservation),
+        RESERVATION_FAILURES
+    );
+}
+
+#[test]
+fn test_always_succeed_c() {
+    assert_eq!(run(Backend::C, LrScModel::AlwaysSucceed), 0);
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn test_address_reservation_x86() {
+    assert_eq!(
+        run(Backend::X86Asm, LrScModel::AddressReservation),
+        RESERVATION_FAILURES
+    );
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn test_always_succeed_x86() {
+    assert_eq!(run(Backend::X86Asm, LrScModel::AlwaysSucceed), 0);
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn test_address_reservation_arm64() {
+    assert_eq!(
+        run(Backend::ARM64Asm, LrScModel::AddressReservation),
+        RESERVATION_FAILURES
+    );
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn test_always_succeed_arm64() {
+    assert_eq!(run(Backend::ARM64Asm, LrScModel::AlwaysSucceed), 0);
+}
@@ -0,0 +1,268 @@
+//! Native memory intrinsics: a guest with byte-loop `memcpy`, `memset` and
+//! `memcmp` leaves the same guest memory with and without
+//! `with_native_mem_intrinsics`, for every source/destination alignment in
+//! 0..16 and every length in 0..258.
+
+use std::path::Path;
+
+use guest::{
+    ECALL, FUNCT3_B, FUNCT3_BEQ, FUNCT3_BLT, FUNCT3_BNE, FUNCT3_BU, FUNCT3_D, FUNCT7_SUB,
+    OPCODE_BRANCH, OPCODE_JAL, OPCODE_JALR, OPCODE_LOAD, OPCODE_LUI, OPCODE_OP, OPCODE_STORE, addi,
+    li,
+};
+use rvr::test_support::guest;
+use rvr::{CompileOptions, Runner, TracerConfig};
+use rvr_elf::{ElfWriter, PF_R, PF_W, PF_X, STT_FUNC};
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{
+    REG_A0, REG_A1, REG_A2, REG_A3, REG_A4, REG_A7, REG_RA, REG_S0, REG_S1, REG_S2, REG_S3, REG_S4,
+    REG_S5, REG_S6, REG_S7, REG_S8, REG_S9, REG_ZERO, Rv64, encode_b, encode_i, encode_j, encode_r,
+    encode_s, encode_u,
+};
+
+const RET: u32 = encode_i(OPCODE_JALR, REG_ZERO, 0, REG_RA, 0);
+
+const TEXT: u64 = 0x1000;
+/// Source buffer, and a copy of it with a few bytes changed for `memcmp`.
+const SRC: u64 = 0x2_0000;
+const CMP: u64 = 0x2_1000;
+const BUF_LEN: usize = 0x400;
+const CHANGED: [usize; 3] = [40, 100, 250];
+/// One zeroed slot per call; slots are wide enough to catch overruns.
+const COPY_SLOTS: u64 = 0x100_0000;
+const SET_SLOTS: u64 = 0x500_0000;
+/// `memcmp` results, one doubleword per call.
+const RESULTS: u64 = 0x400_0000;
+
+const ALIGNMENTS: i32 = 16;
+const LENGTHS: i32 = 258;
+const SLOT: i32 = 0x200;
+const COPIES: usize = (ALIGNMENTS * ALIGNMENTS * LENGTHS) as usize;
+const SETS: usize = (ALIGNMENTS * LENGTHS) as usize;
+
+const fn add(rd: u8, rs1: u8, rs2: u8) -> u32 {
+    encode_r(OPCODE_OP, rd, 0, rs1, rs2, 0)
+}
+
+fn lui(rd: u8, addr: u64) -> u32 {
+    encode_u(OPCODE_LUI, rd, u32::try_from(addr >> 12).unwrap())
+}
+
+/// Offset in bytes from instruction `from` to instruction `to`.
+fn offset(from: usize, to: usize) -> i32 {
+    (i32::try_from(to).unwrap() - i32::try_from(from).unwrap()) * 4
+}
+
+/// Append `jal rd, <to>` (instruction index).
+fn jal(text: &mut Vec<u32>, rd: u8, to: usize) {
+    text.push(encode_j(OPCODE_JAL, rd, offset(text.len(), to)));
+}
+
+/// Append `blt rs1, rs2, <to>` (instruction index).
+fn blt(text: &mut Vec<u32>, rs1: u8, rs2: u8, to: usize) {
+    text.push(encode_b(
+        OPCODE_BRANCH,
+        FUNCT3_BLT,
+        rs1,
+        rs2,
+        offset(text.len(), to),
+    ));
+}
+
+/// Byte-loop `memcpy`, `memset` and `memcmp`; returns their indices.
+fn lib_text(text: &mut Vec<u32>) -> [usize; 3] {
+    // memcpy(a0 = dst, a1 = src, a2 = n)
+    let memcpy = text.len();
+    text.push(addi(REG_A3, REG_A0, 0));
+    let copy_loop = text.len();
+    text.extend([
+        encode_b(OPCODE_BRANCH, FUNCT3_BEQ, REG_A2, REG_ZERO, 28),
+        encode_i(OPCODE_LOAD, REG_A4, FUNCT3_BU, REG_A1, 0),
+        encode_s(OPCODE_STORE, FUNCT3_B, REG_A3, REG_A4, 0),
+        addi(REG_A1, REG_A1, 1),
+        addi(REG_A3, REG_A3, 1),
+        addi(REG_A2, REG_A2, -1),
+    ]);
+    jal(text, REG_ZERO, copy_loop);
+    text.push(RET);
+
+    // memset(a0 = dst, a1 = c, a2 = n)
+    let memset = text.len();
+    text.push(addi(REG_A3, REG_A0, 0));
+    let set_loop = text.len();
+    text.extend([
+        encode_b(OPCODE_BRANCH, FUNCT3_BEQ, REG_A2, REG_ZERO, 20),
+        encode_s(OPCODE_STORE, FUNCT3_B, REG_A3, REG_A1, 0),
+        addi(REG_A3, REG_A3, 1),
+        addi(REG_A2, REG_A2, -1),
+    ]);
+    jal(text, REG_ZERO, set_loop);
+    text.push(RET);
+
+    // memcmp(a0 = a, a1 = b, a2 = n)
+    let memcmp = text.len();
+    text.extend([
+        encode_b(OPCODE_BRANCH, FUNCT3_BEQ, REG_A2, REG_ZERO, 40),
+        encode_i(OPCODE_LOAD, REG_A3, FUNCT3_BU, REG_A0, 0),
+        encode_i(OPCODE_LOAD, REG_A4, FUNCT3_BU, REG_A1, 0),
+        encode_b(OPCODE_BRANCH, FUNCT3_BNE, REG_A3, REG_A4, 20),
+        addi(REG_A0, REG_A0, 1),
+        addi(REG_A1, REG_A1, 1),
+        addi(REG_A2, REG_A2, -1),
+    ]);
+    jal(text, REG_ZERO, memcmp);
+    text.extend([
+        encode_r(OPCODE_OP, REG_A0, 0, REG_A3, REG_A4, FUNCT7_SUB),
+        RET,
+        addi(REG_A0, REG_ZERO, 0),
+        RET,
+    ]);
+    [memcpy, memset, memcmp]
+}
+
+/// For s0 = source alignment, s1 = destination alignment, s2 = length:
+/// `memcpy` into the next copy slot and store `memcmp(SRC + s0, CMP + s1)`;
+/// then for s1, s2: `memset` the next set slot with `0x700 + s2` (the fill
+/// is its low byte). Exits with 0.
+fn driver_text(text: &mut Vec<u32>, [memcpy, memset, memcmp]: [usize; 3]) {
+    text.extend([
+        lui(REG_S3, COPY_SLOTS),
+        lui(REG_S4, RESULTS),
+        lui(REG_S5, SET_SLOTS),
+        addi(REG_S6, REG_ZERO, LENGTHS),
+        addi(REG_S7, REG_ZERO, ALIGNMENTS),
+        lui(REG_S8, SRC),
+        lui(REG_S9, CMP),
+        addi(REG_S0, REG_ZERO, 0),
+    ]);
+    let sa_loop = text.len();
+    text.push(addi(REG_S1, REG_ZERO, 0));
+    let da_loop = text.len();
+    text.push(addi(REG_S2, REG_ZERO, 0));
+    let n_loop = text.len();
+    text.extend([
+        add(REG_A0, REG_S3, REG_S1),
+        add(REG_A1, REG_S8, REG_S0),
+        addi(REG_A2, REG_S2, 0),
+    ]);
+    jal(text, REG_RA, memcpy);
+    text.extend([
+        add(REG_A0, REG_S8, REG_S0),
+        add(REG_A1, REG_S9, REG_S1),
+        addi(REG_A2, REG_S2, 0),
+    ]);
+    jal(text, REG_RA, memcmp);
+    text.extend([
+        encode_s(OPCODE_STORE, FUNCT3_D, REG_S4, REG_A0, 0),
+        addi(REG_S4, REG_S4, 8),
+        addi(REG_S3, REG_S3, SLOT),
+        addi(REG_S2, REG_S2, 1),
+    ]);
+    blt(text, REG_S2, REG_S6, n_loop);
+    text.push(addi(REG_S1, REG_S1, 1));
+    blt(text, REG_S1, REG_S7, da_loop);
+    text.push(addi(REG_S0, REG_S0, 1));
+    blt(text, REG_S0, REG_S7, sa_loop);
+
+    text.push(addi(REG_S1, REG_ZERO, 0));
+    let set_da_loop = text.len();
+    text.push(addi(REG_S2, REG_ZERO, 0));
+    let set_n_loop = text.len();
+    text.extend([
+        add(REG_A0, REG_S5, REG_S1),
+        addi(REG_A1, REG_S2, 0x700),
+        addi(REG_A2, REG_S2, 0),
+    ]);
+    jal(text, REG_RA, memset);
+    text.extend([addi(REG_S5, REG_S5, SLOT), addi(REG_S2, REG_S2, 1)]);
+    blt(text, REG_S2, REG_S6, set_n_loop);
+    text.push(addi(REG_S1, REG_S1, 1));
+    blt(text, REG_S1, REG_S7, set_da_loop);
+
+    text.extend([addi(REG_A0, REG_ZERO, 0), li(REG_A7, SYS_EXIT), ECALL]);
+}
+
+fn guest_elf() -> Vec<u8> {
+    let mut text = Vec::new();
+    let lib = lib_text(&mut text);
+    let start = text.len();
+    driver_text(&mut text, lib);
+    let addr = |index: usize| TEXT + 4 * u64::try_from(index).unwrap();
+
+    let src: Vec<u8> = (0..BUF_LEN)
+        .map(|i| u8::try_from(i * 37 % 251).unwrap())
+        .collect();
+    let mut cmp = src.clone();
+    for (i, &pos) in CHANGED.iter().enumerate() {
+        cmp[pos] ^= 0x41 << i;
+    }
+    ElfWriter::<Rv64>::new(addr(start))
+        .with_segment(TEXT, PF_R | PF_X, guest::code(&text))
+        .with_segment(SRC, PF_R, src)
+        .with_segment(CMP, PF_R | PF_W, cmp)
+        .with_symbol("memcpy", addr(lib[0]), STT_FUNC)
+        .with_symbol("memset", addr(lib[1]), STT_FUNC)
+        .with_symbol("memcmp", addr(lib[2]), STT_FUNC)
+        .with_symbol("_start", addr(start), STT_FUNC)
+        .build()
+}
+
+/// Compile and run `elf`, returning the copy slots, set slots and results.
+fn run(elf: &Path, out: &Path, intrinsics: bool) -> [Vec<u8>; 3] {
+    let options = CompileOptions::new()
+        .with_quiet(true)
+        .with_native_mem_intrinsics(intrinsics);
+    rvr::compile_with_options(elf, out, &options).expect("compile");
+    let mut runner = Runner::load(out, elf).expect("load runner");
+    assert_eq!(runner.run().expect("run guest").exit_code, 0);
+
+    let slot = usize::try_from(SLOT).unwrap();
+    [
+        (COPY_SLOTS, COPIES * slot),
+        (SET_SLOTS, SETS * slot),
+        (RESULTS, COPIES * 8),
+    ]
+    .map(|(addr, len)| {
+        let mut buf = vec![0; len];
+        assert_eq!(runner.read_memory(addr, &mut buf), buf.len());
+        buf
+    })
+}
+
+#[test]
+fn test_mem_intrinsics_match_guest() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = temp.path().join("mem.elf");
+    std::fs::write(&elf, guest_elf()).expect("write ELF");
+
+    let generated = run(&elf, &temp.path().join("generic"), false);
+    let native = run(&elf, &temp.path().join("native"), true);
+    let names = ["memcpy", "memset", "memcmp"];
+    for ((name, expected), actual) in names.iter().zip(&generated).zip(&native) {
+        if let Some(i) = (0..expected.len()).find(|&i| expected[i] != actual[i]) {
+            panic!(
+                "{name}: byte {i:#x} differs: {:#04x} != {:#04x}",
+                expected[i], actual[i]
+            );
+        }
+    }
+
+    // The runs did something: the last copy is full, and some compares differ
+    let last = &generated[0][generated[0].len() - usize::try_from(SLOT).unwrap()..];
+    assert!(last.iter().any(|&b| b != 0));
+    assert!(generated[2].iter().any(|&b| b != 0));
+}
+
+#[test]
+fn test_mem_intrinsics_refuse_tracer() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = temp.path().join("mem.elf");
+    std::fs::write(&elf, guest_elf()).expect("write ELF");
+    let options = CompileOptions::new()
+        .with_quiet(true)
+        .with_native_mem_intrinsics(true)
+        .with_tracer_config(TracerConfig::preflight());
+    let err = rvr::lift_to_c_with_options(&elf, temp.path(), &options)
+        .expect_err("tracer with intrinsics");
+    assert!(err.to_string().contains("tracer"), "{err}");
+}
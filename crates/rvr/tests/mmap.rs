@@ -0,0 +1,169 @@
+//! mmap arena: a guest interleaving a few thousand anonymous mmap/munmap
+//! calls gets zeroed, non-overlapping mappings back, and can grow one with
+//! mremap.
+
+use guest::{
+    ECALL, FUNCT3_ADD, FUNCT3_BEQ, FUNCT3_BLT, FUNCT3_BNE, FUNCT3_SLLI, OPCODE_BRANCH, OPCODE_LUI,
+    OPCODE_OP, OPCODE_OP_IMM, Text, addi, ld, li, offset, sd,
+};
+use rvr::test_support::guest;
+use rvr::{CompileOptions, Runner, SyscallMode};
+use rvr_elf::{ElfWriter, PF_R, PF_X};
+use rvr_isa::syscalls::syscall_nr::{SYS_EXIT, SYS_MMAP, SYS_MREMAP, SYS_MUNMAP};
+use rvr_isa::{
+    REG_A0, REG_A1, REG_A2, REG_A3, REG_A4, REG_A5, REG_A7, REG_S0, REG_S1, REG_S2, REG_S3, REG_S4,
+    REG_S5, REG_T0, REG_T1, REG_ZERO, Rv64, encode_b, encode_i, encode_r, encode_u,
+};
+
+const FUNCT3_ANDI: u8 = 0b111;
+const PROT_READ_WRITE: i32 = 3;
+const MAP_PRIVATE: i32 = 0x2;
+const MAP_FIXED: i32 = 0x10;
+const MAP_ANONYMOUS: i32 = 0x20;
+const MREMAP_MAYMOVE: i32 = 1;
+const EINVAL: i32 = 22;
+
+const TEXT: u64 = 0x1000;
+const ITERATIONS: i32 = 2000;
+const ARENA: u64 = 1 << 20;
+
+/// The mmap calls of the guest.
+trait MmapText {
+    /// `mmap(a0, a1, PROT_READ | PROT_WRITE, flags, fd, 0)`.
+    fn mmap(&mut self, flags: i32, fd: i32);
+}
+
+impl MmapText for Text {
+    fn mmap(&mut self, flags: i32, fd: i32) {
+        self.push([
+            addi(REG_A2, REG_ZERO, PROT_READ_WRITE),
+            addi(REG_A3, REG_ZERO, flags),
+            addi(REG_A4, REG_ZERO, fd),
+            addi(REG_A5, REG_ZERO, 0),
+            li(REG_A7, SYS_MMAP),
+            ECALL,
+        ]);
+    }
+}
+
+/// Exits 0 if every check passes; else the failed step.
+///
+/// Each iteration maps 1-4 pages (s3, size s5), checks the mapping is
+/// zeroed, tags it with the iteration, checks the previous mapping (s0,
+/// size s4) kept its tag and unmaps it. The freed pages are reused by later
+/// mappings, so the zero check covers recycled memory.
+fn mmap_elf() -> Vec<u8> {
+    let mut text = Text::new(SYS_EXIT);
+    text.push([
+        addi(REG_S0, REG_ZERO, 0),
+        addi(REG_S1, REG_ZERO, 0),
+        addi(REG_S2, REG_ZERO, ITERATIONS),
+    ]);
+
+    let head = text.0.len();
+    text.push([
+        encode_i(OPCODE_OP_IMM, REG_T0, FUNCT3_ANDI, REG_S1, 3),
+        addi(REG_T0, REG_T0, 1),
+        encode_i(OPCODE_OP_IMM, REG_S5, FUNCT3_SLLI, REG_T0, 12),
+        addi(REG_A0, REG_ZERO, 0),
+        addi(REG_A1, REG_S5, 0),
+    ]);
+    text.mmap(MAP_PRIVATE | MAP_ANONYMOUS, -1);
+    text.fail_if(1, FUNCT3_BLT, REG_A0, REG_ZERO);
+    text.push([addi(REG_S3, REG_A0, 0), ld(REG_T0, REG_S3, 0)]);
+    text.fail_if(2, FUNCT3_BNE, REG_T0, REG_ZERO);
+    text.push([sd(REG_S1, REG_S3, 0)]);
+
+    let skip = text.0.len();
+    text.push([0, addi(REG_T1, REG_S1, -1), ld(REG_T0, REG_S0, 0)]);
+    text.fail_if(3, FUNCT3_BNE, REG_T0, REG_T1);
+    text.push([
+        addi(REG_A0, REG_S0, 0),
+        addi(REG_A1, REG_S4, 0),
+        li(REG_A7, SYS_MUNMAP),
+        ECALL,
+    ]);
+    text.fail_if(4, FUNCT3_BNE, REG_A0, REG_ZERO);
+    text.0[skip] = encode_b(
+        OPCODE_BRANCH,
+        FUNCT3_BEQ,
+        REG_S0,
+        REG_ZERO,
+        offset(skip, text.0.len()),
+    );
+
+    text.push([
+        addi(REG_S0, REG_S3, 0),
+        addi(REG_S4, REG_S5, 0),
+        addi(REG_S1, REG_S1, 1),
+    ]);
+    let at = text.0.len();
+    text.push([encode_b(
+        OPCODE_BRANCH,
+        FUNCT3_BNE,
+        REG_S1,
+        REG_S2,
+        offset(at, head),
+    )]);
+
+    // Grow the last mapping to 16 pages: the tag moves with it, the rest is zero
+    text.push([
+        addi(REG_A0, REG_S0, 0),
+        addi(REG_A1, REG_S4, 0),
+        encode_u(OPCODE_LUI, REG_A2, 16),
+        addi(REG_A3, REG_ZERO, MREMAP_MAYMOVE),
+        addi(REG_A4, REG_ZERO, 0),
+        li(REG_A7, SYS_MREMAP),
+        ECALL,
+    ]);
+    text.fail_if(5, FUNCT3_BLT, REG_A0, REG_ZERO);
+    text.push([
+        addi(REG_S3, REG_A0, 0),
+        ld(REG_T0, REG_S3, 0),
+        addi(REG_T1, REG_S2, -1),
+    ]);
+    text.fail_if(6, FUNCT3_BNE, REG_T0, REG_T1);
+    text.push([
+        encode_r(OPCODE_OP, REG_T1, FUNCT3_ADD, REG_S3, REG_S4, 0),
+        ld(REG_T0, REG_T1, 0),
+    ]);
+    text.fail_if(7, FUNCT3_BNE, REG_T0, REG_ZERO);
+
+    // File-backed mappings and MAP_FIXED outside the arena fail with EINVAL
+    text.push([addi(REG_A0, REG_ZERO, 0), encode_u(OPCODE_LUI, REG_A1, 1)]);
+    text.mmap(MAP_PRIVATE, 3);
+    text.expect_a0(8, -EINVAL);
+    text.push([
+        encode_u(OPCODE_LUI, REG_A0, 1),
+        encode_u(OPCODE_LUI, REG_A1, 1),
+    ]);
+    text.mmap(MAP_PRIVATE | MAP_ANONYMOUS | MAP_FIXED, -1);
+    text.fail_if(9, FUNCT3_BNE, REG_A0, REG_T1);
+
+    text.push([addi(REG_A0, REG_ZERO, 0), li(REG_A7, SYS_EXIT), ECALL]);
+    ElfWriter::<Rv64>::new(TEXT)
+        .with_segment(TEXT, PF_R | PF_X, guest::code(&text.0))
+        .build()
+}
+
+#[test]
+fn test_interleaved_mmap_munmap() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = temp.path().join("mmap.elf");
+    std::fs::write(&elf, mmap_elf()).expect("write ELF");
+    let out = temp.path().join("mmap");
+    let options = CompileOptions::new()
+        .with_quiet(true)
+        .with_syscall_mode(SyscallMode::Linux)
+        .with_mmap_size(ARENA);
+    let report = rvr::compile_with_report(&elf, &out, &options).expect("compile");
+    let arena = report
+        .memory_layout
+        .and_then(|layout| layout.mmap)
+        .expect("mmap arena");
+    assert_eq!(arena.end - arena.start, ARENA);
+
+    let mut runner = Runner::load(&out, &elf).expect("load runner");
+    let result = runner.run().expect("run guest");
+    assert_eq!(result.exit_code, 0);
+}
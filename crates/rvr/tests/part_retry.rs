@@ -0,0 +1,132 @@
+//! Part retry: a compiler launcher injects an `#error` into the first part
+//! file, and the build driver retries it at the fallback level, reporting
+//! the part and the guest function it holds.
+
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+use guest::{ECALL, addi, li};
+use rvr::test_support::guest;
+use rvr::{CompileOptions, CompilerLauncher, Error, PartFailureKind, Runner};
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{REG_A0, REG_A7, REG_ZERO, Rv64};
+
+const EXIT_CODE: u8 = 42;
+const INSTR_SIZE: u64 = 4;
+
+const TEXT: u64 = 0x1000;
+const NAME: &str = "retry";
+const FUNCTION: &str = "main";
+const PART: &str = "retry_part0.c";
+/// Text of the injected error.
+const INJECTED: &str = "injected failure";
+
+/// `main: exit(EXIT_CODE)`.
+fn exit_elf() -> Vec<u8> {
+    let text = [
+        addi(REG_A0, REG_ZERO, i32::from(EXIT_CODE)),
+        li(REG_A7, SYS_EXIT),
+        ECALL,
+    ];
+    let size = text.len() as u64 * INSTR_SIZE;
+    guest::text_elf::<Rv64>(TEXT, &text)
+        .with_function(FUNCTION, TEXT, size)
+        .build()
+}
+
+/// Launcher that prepends `#if <condition>` / `#error` to the first part
+/// file before running the compile command.
+fn write_launcher(dir: &Path, condition: &str) -> String {
+    let path = dir.join("inject.sh");
+    let script = format!(
+        "#!/bin/sh\n\
+         for arg in \"$@\"; do\n\
+         case \"$arg\" in *_part0.c)\n\
+         grep -q '{INJECTED}' \"$arg\" || {{ printf '#if {condition}\\n#error {INJECTED}\\n#endif\\n' | cat - \"$arg\" > \"$arg.tmp\" && mv \"$arg.tmp\" \"$arg\"; }} ;;\n\
+         esac\n\
+         done\n\
+         exec \"$@\"\n"
+    );
+    std::fs::write(&path, script).expect("write launcher");
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+        .expect("make launcher executable");
+    path.display().to_string()
+}
+
+fn compile(
+    temp: &Path,
+    condition: &str,
+    fallback_opt_level: Option<u8>,
+) -> (std::path::PathBuf, rvr::Result<rvr::CompileReport>) {
+    let elf = temp.join("retry.elf");
+    std::fs::write(&elf, exit_elf()).expect("write ELF");
+    let options = CompileOptions::new()
+        .with_quiet(true)
+        .with_compiler_launcher(CompilerLauncher::Command(write_launcher(temp, condition)))
+        .with_fallback_opt_level(fallback_opt_level);
+    let report = rvr::compile_with_report(&elf, &temp.join(NAME), &options);
+    (elf, report)
+}
+
+#[test]
+fn test_failed_part_builds_at_fallback_level() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    // Only optimized compiles fail, so the -O0 retry succeeds
+    let (elf, report) = compile(temp.path(), "defined(__OPTIMIZE__)", Some(0));
+    let report = report.expect("compile with retry");
+
+    let [retried] = report.retried_parts.as_slice() else {
+        panic!("expected one retried part, got {:?}", report.retried_parts);
+    };
+    assert_eq!(retried.file, PART);
+    assert_eq!(retried.kind, PartFailureKind::Error);
+    assert!(retried.message.contains(INJECTED), "{}", retried.message);
+    assert_eq!(retried.functions, [FUNCTION]);
+    assert!(report.part_times.iter().any(|part| part.file == PART));
+
+    let mut runner = Runner::load(temp.path().join(NAME), &elf).expect("load runner");
+    assert_eq!(runner.run().expect("run guest").exit_code, EXIT_CODE);
+}
+
+#[test]
+fn test_part_failing_at_every_level_is_reported() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let (_, report) = compile(temp.path(), "1", Some(1));
+    let err = report.expect_err("part fails at every level");
+    let message = err.to_string();
+    let Error::PartsFailed {
+        failures,
+        fallback_opt_level,
+    } = err
+    else {
+        panic!("expected a part failure, got {message}");
+    };
+    assert_eq!(fallback_opt_level, Some(1));
+    let [failure] = failures.as_slice() else {
+        panic!("expected one failed part, got {failures:?}");
+    };
+    assert_eq!(failure.file, PART);
+    assert_eq!(failure.functions, [FUNCTION]);
+    assert!(message.contains("1 part file(s) failed to compile at the default level and at -O1"));
+    assert!(message.contains(&format!("{PART} (compile error; functions {FUNCTION})")));
+    assert!(message.contains(INJECTED));
+    // Nothing half-built is left for the next build to trust
+    assert!(!temp.path().join(NAME).join("retry_part0.o").exists());
+}
+
+#[test]
+fn test_no_fallback_fails_at_once() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let (_, report) = compile(temp.path(), "defined(__OPTIMIZE__)", None);
+    let err = report.expect_err("no retry");
+    assert!(
+        matches!(
+            err,
+            Error::PartsFailed {
+                fallback_opt_level: None,
+                ..
+            }
+        ),
+        "{err}"
+    );
+}
@@ -0,0 +1,195 @@
+//! Position-independent (`ET_DYN`) guests: a PIE linked at 0 that reads
+//! its exit code through a pointer fixed up by `R_RISCV_RELATIVE`, and a
+//! static-PIE hello world from a stock RISC-V gcc run under `rvr run`.
+//!
+//! The gcc test is skipped when no RISC-V gcc is on `PATH`.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use guest::{ECALL, FUNCT3_LD, OPCODE_AUIPC, OPCODE_LOAD};
+use rvr::test_support::guest;
+use rvr::{CompileOptions, DEFAULT_LOAD_BIAS, Error, Runner};
+use rvr_elf::{ELF_TYPE_DYN, ElfError, ElfWriter, PF_R, PF_W, PF_X, R_RISCV_RELATIVE, STT_FUNC};
+use rvr_isa::{REG_A0, Rv64, encode_i, encode_u};
+
+/// Link-time address of the data segment: a pointer, then the exit code.
+const DATA: u64 = 0x1000;
+const EXIT_CODE: u8 = 42;
+/// Custom load bias (a multiple of the 4 KiB segment alignment).
+const LOAD_BIAS: u64 = 0x40_0000;
+
+/// gcc prefixes tried in order.
+const PREFIXES: &[&str] = &[
+    "riscv64-linux-gnu-",
+    "riscv64-unknown-linux-gnu-",
+    "riscv64-unknown-elf-",
+    "riscv64-elf-",
+];
+
+/// Freestanding hello world. The message is reached through a pointer in
+/// `.data`, so the PIE carries an `R_RISCV_RELATIVE` relocation, and no
+/// startup code applies it: rvr must.
+const HELLO: &str = r#"
+static const char message[] = "hello, pie\n";
+static const char *const messages[] = {message};
+
+static long syscall3(long nr, long a0, long a1, long a2) {
+    register long r_a0 __asm__("a0") = a0;
+    register long r_a1 __asm__("a1") = a1;
+    register long r_a2 __asm__("a2") = a2;
+    register long r_a7 __asm__("a7") = nr;
+    __asm__ volatile("ecall" : "+r"(r_a0) : "r"(r_a1), "r"(r_a2), "r"(r_a7) : "memory");
+    return r_a0;
+}
+
+void _start(void) {
+    syscall3(64, 1, (long)messages[0], sizeof(message) - 1);
+    syscall3(93, 0, 0, 0);
+    for (;;) {
+    }
+}
+"#;
+
+/// `a0 = *(u64*)DATA; a0 = *(u64*)a0; exit(a0)`, where the pointer at
+/// `DATA` is `DATA + 8` after relocation.
+fn pie_writer() -> ElfWriter<Rv64> {
+    let text = [
+        encode_u(OPCODE_AUIPC, REG_A0, 1),
+        encode_i(OPCODE_LOAD, REG_A0, FUNCT3_LD, REG_A0, 0),
+        encode_i(OPCODE_LOAD, REG_A0, FUNCT3_LD, REG_A0, 0),
+        ECALL,
+    ];
+    let mut data = vec![0u8; 8];
+    data.extend_from_slice(&u64::from(EXIT_CODE).to_le_bytes());
+
+    ElfWriter::<Rv64>::new(0)
+        .with_e_type(ELF_TYPE_DYN)
+        .with_segment(0, PF_R | PF_X, guest::code(&text))
+        .with_segment(DATA, PF_R | PF_W, data)
+        .with_section(".text", 0, 16)
+        .with_symbol("_start", 0, STT_FUNC)
+        .with_relocation(DATA, R_RISCV_RELATIVE, 8)
+}
+
+/// First RISC-V gcc found on `PATH`.
+fn gcc() -> Option<String> {
+    PREFIXES
+        .iter()
+        .map(|prefix| format!("{prefix}gcc"))
+        .find(|cmd| {
+            Command::new(cmd)
+                .arg("--version")
+                .output()
+                .is_ok_and(|o| o.status.success())
+        })
+}
+
+fn write_elf(dir: &Path, elf: &[u8]) -> PathBuf {
+    let path = dir.join("pie.elf");
+    std::fs::write(&path, elf).expect("write ELF");
+    path
+}
+
+fn options() -> CompileOptions {
+    CompileOptions::new().with_quiet(true)
+}
+
+/// Emitted C for the guest, concatenated.
+fn lifted_code(elf: &Path, out: &Path, options: &CompileOptions) -> String {
+    rvr::lift_to_c_with_options(elf, out, options).expect("lift");
+    std::fs::read_dir(out)
+        .expect("read output")
+        .map(|entry| entry.expect("dir entry").path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "c"))
+        .map(|path| std::fs::read_to_string(path).expect("read C"))
+        .collect()
+}
+
+#[test]
+fn test_pie_lifted_at_load_bias() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = write_elf(temp.path(), &pie_writer().build());
+
+    let code = lifted_code(&elf, &temp.path().join("default"), &options());
+    assert!(code.contains(&format!("B_{DEFAULT_LOAD_BIAS:016x}(")));
+    assert!(!code.contains("RV_LOAD_BIAS"));
+
+    let options = options().with_load_bias(LOAD_BIAS);
+    let code = lifted_code(&elf, &temp.path().join("biased"), &options);
+    assert!(code.contains(&format!("B_{LOAD_BIAS:016x}(")));
+    assert!(code.contains(&format!("const uint64_t RV_LOAD_BIAS = {LOAD_BIAS:#x}ull;")));
+}
+
+#[test]
+fn test_pie_runs() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = write_elf(temp.path(), &pie_writer().build());
+
+    for (name, options, entry) in [
+        ("default", options(), DEFAULT_LOAD_BIAS),
+        ("biased", options().with_load_bias(LOAD_BIAS), LOAD_BIAS),
+    ] {
+        let out = temp.path().join(name);
+        rvr::compile_with_options(&elf, &out, &options).expect("compile");
+        let mut runner = Runner::load(&out, &elf).expect("load runner");
+        assert_eq!(runner.entry_point(), entry, "{name}");
+        let result = runner.run().expect("run guest");
+        assert_eq!(result.exit_code, EXIT_CODE, "{name}");
+    }
+}
+
+#[test]
+fn test_dynamically_linked_pie_rejected() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = write_elf(temp.path(), &pie_writer().with_needed("libc.so.6").build());
+
+    let err = rvr::lift_to_c_with_options(&elf, &temp.path().join("out"), &options())
+        .expect_err("dynamically linked ELF must not lift");
+    assert!(
+        matches!(&err, Error::Elf(ElfError::DynamicDependency(lib)) if lib == "libc.so.6"),
+        "{err}"
+    );
+}
+
+#[test]
+fn test_gcc_static_pie_runs() {
+    let Some(gcc) = gcc() else {
+        eprintln!("Skipping test: no RISC-V gcc found");
+        return;
+    };
+    let temp = tempfile::tempdir().expect("tempdir");
+    let source = temp.path().join("hello.c");
+    std::fs::write(&source, HELLO).expect("write source");
+    let elf = temp.path().join("hello.elf");
+    let status = Command::new(&gcc)
+        .args(["-O2", "-ffreestanding", "-nostdlib", "-fPIE", "-static-pie"])
+        .args(["-march=rv64imac", "-mabi=lp64", "-o"])
+        .arg(&elf)
+        .arg(&source)
+        .status()
+        .expect("run gcc");
+    assert!(status.success());
+    let object = std::fs::read(&elf).expect("read ELF");
+    assert_eq!(u16::from_le_bytes([object[16], object[17]]), ELF_TYPE_DYN);
+
+    let out = temp.path().join("out");
+    let rvr = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rvr"))
+            .args(args)
+            .env("RVR_CACHE_DIR", temp.path().join("cache"))
+            .env("NO_COLOR", "1")
+            .output()
+            .expect("run rvr")
+    };
+    let (elf, out) = (elf.to_str().unwrap(), out.to_str().unwrap());
+    let compile = rvr(&["compile", elf, "-o", out, "--syscalls", "linux"]);
+    assert!(
+        compile.status.success(),
+        "{}",
+        String::from_utf8_lossy(&compile.stderr)
+    );
+    let run = rvr(&["run", out, elf]);
+    assert_eq!(run.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&run.stdout).contains("hello, pie\n"));
+}
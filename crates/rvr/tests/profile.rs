@@ -0,0 +1,167 @@
+//! Block profile: a counted loop compiled with the block profile tracer
+//! reports per-block entry counts, symbolized into folded stacks, and saved
+//! counts steer a profile-guided recompile.
+
+use std::path::Path;
+
+use guest::{ECALL, FUNCT3_BLT, FUNCT3_BNE, OPCODE_BRANCH, addi, li};
+use rvr::test_support::guest;
+use rvr::{BlockProfile, CompileOptions, ProfileCounts, Runner, TracerConfig};
+use rvr_elf::STT_FUNC;
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{REG_A0, REG_A1, REG_A7, REG_ZERO, Rv64, encode_b};
+
+const TEXT: u64 = 0x1000;
+const LOOP: u64 = TEXT + 8;
+const ITERATIONS: i32 = 10;
+/// Error path of `guarded_loop_elf`, never taken.
+const COLD: u64 = TEXT + 0x1c;
+
+/// `for (a0 = 0; a0 != ITERATIONS; a0++);` then `exit(a0)`.
+fn loop_elf() -> Vec<u8> {
+    let text = [
+        addi(REG_A0, REG_ZERO, 0),
+        addi(REG_A1, REG_ZERO, ITERATIONS),
+        // loop:
+        addi(REG_A0, REG_A0, 1),
+        encode_b(OPCODE_BRANCH, FUNCT3_BNE, REG_A0, REG_A1, -4),
+        li(REG_A7, SYS_EXIT),
+        ECALL,
+    ];
+    guest::text_elf::<Rv64>(TEXT, &text)
+        .with_symbol("_start", TEXT, STT_FUNC)
+        .with_symbol("spin", LOOP, STT_FUNC)
+        .build()
+}
+
+/// `loop_elf` behind a never-taken `if (a1 < 0) exit(7)`.
+fn guarded_loop_elf() -> Vec<u8> {
+    let text = [
+        addi(REG_A0, REG_ZERO, 0),
+        addi(REG_A1, REG_ZERO, ITERATIONS),
+        encode_b(OPCODE_BRANCH, FUNCT3_BLT, REG_A1, REG_ZERO, 0x14),
+        // loop:
+        addi(REG_A0, REG_A0, 1),
+        encode_b(OPCODE_BRANCH, FUNCT3_BNE, REG_A0, REG_A1, -4),
+        li(REG_A7, SYS_EXIT),
+        ECALL,
+        // cold:
+        addi(REG_A0, REG_ZERO, 7),
+        li(REG_A7, SYS_EXIT),
+        ECALL,
+    ];
+    guest::text_elf::<Rv64>(TEXT, &text).build()
+}
+
+/// Lift `elf` with the profile at `profile` and return the generated C.
+fn lift_with_profile(elf: &Path, profile: &Path, out: &Path) -> String {
+    let options = CompileOptions::new().with_profile(profile);
+    rvr::lift_to_c_with_options(elf, out, &options).expect("lift");
+    let mut code = String::new();
+    for entry in std::fs::read_dir(out).expect("read output") {
+        let path = entry.expect("entry").path();
+        if path.extension().is_some_and(|ext| ext == "c") {
+            code += &std::fs::read_to_string(path).expect("read C");
+        }
+    }
+    code
+}
+
+#[test]
+fn test_block_profile_counts_loop() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = temp.path().join("loop.elf");
+    std::fs::write(&elf, loop_elf()).expect("write ELF");
+    let out = temp.path().join("loop");
+    let options = CompileOptions::new()
+        .with_quiet(true)
+        .with_tracer_config(TracerConfig::block_profile());
+    rvr::compile_with_options(&elf, &out, &options).expect("compile");
+
+    let mut runner = Runner::load(&out, &elf).expect("load runner");
+    let result = runner.run().expect("run guest");
+    assert_eq!(i32::from(result.exit_code), ITERATIONS);
+
+    let counts = runner.block_profile().expect("block profile tracer");
+    assert!(counts.contains(&(TEXT, 1)));
+    assert!(counts.contains(&(LOOP, ITERATIONS.unsigned_abs().into())));
+
+    // No line info: frames fall back to the block PC
+    let profile =
+        BlockProfile::resolve(&elf, runner.load_bias(), &counts, "false").expect("resolve");
+    assert_eq!(profile.blocks()[0].pc, LOOP);
+    let mut folded = Vec::new();
+    profile.write_folded(&mut folded).expect("write folded");
+    let folded = String::from_utf8(folded).expect("utf-8");
+    assert!(folded.contains("_start;0x1000 1\n"));
+    assert!(folded.contains(&format!("spin;0x1008 {ITERATIONS}\n")));
+}
+
+#[test]
+fn test_profile_guided_recompile() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = temp.path().join("guarded.elf");
+    let elf_data = guarded_loop_elf();
+    std::fs::write(&elf, &elf_data).expect("write ELF");
+    let out = temp.path().join("guarded");
+    let options = CompileOptions::new()
+        .with_quiet(true)
+        .with_tracer_config(TracerConfig::block_profile());
+    rvr::compile_with_options(&elf, &out, &options).expect("compile");
+    let mut runner = Runner::load(&out, &elf).expect("load runner");
+    assert_eq!(
+        i32::from(runner.run().expect("run guest").exit_code),
+        ITERATIONS
+    );
+
+    let counts = runner.block_profile().expect("block profile tracer");
+    let profile = temp.path().join("guarded.counts");
+    let mut file = std::fs::File::create(&profile).expect("create profile");
+    ProfileCounts::new(&elf_data, &counts)
+        .write(&mut file)
+        .expect("write profile");
+
+    let code = lift_with_profile(&elf, &profile, &temp.path().join("lifted"));
+    assert!(code.contains("cold)) void B_0000000000001018("), "{code}");
+    assert!(code.contains("if (unlikely("), "{code}");
+
+    let out = temp.path().join("guided");
+    let options = CompileOptions::new()
+        .with_quiet(true)
+        .with_profile(&profile);
+    rvr::compile_with_options(&elf, &out, &options).expect("compile with profile");
+    let mut runner = Runner::load(&out, &elf).expect("load runner");
+    assert_eq!(
+        i32::from(runner.run().expect("run guest").exit_code),
+        ITERATIONS
+    );
+}
+
+#[test]
+fn test_stale_profile_is_ignored() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = temp.path().join("guarded.elf");
+    std::fs::write(&elf, guarded_loop_elf()).expect("write ELF");
+    let counts = [(TEXT, 1), (LOOP + 4, 10)];
+
+    // Counts of the same guest mark the error path cold
+    let profile = temp.path().join("fresh.counts");
+    let mut file = std::fs::File::create(&profile).expect("create profile");
+    ProfileCounts::new(&guarded_loop_elf(), &counts)
+        .write(&mut file)
+        .expect("write profile");
+    let code = lift_with_profile(&elf, &profile, &temp.path().join("fresh"));
+    assert!(
+        code.contains(&format!("cold)) void B_{COLD:016x}(")),
+        "{code}"
+    );
+
+    // Counts collected from another ELF are not applied
+    let profile = temp.path().join("stale.counts");
+    let mut file = std::fs::File::create(&profile).expect("create profile");
+    ProfileCounts::new(&loop_elf(), &counts)
+        .write(&mut file)
+        .expect("write profile");
+    let code = lift_with_profile(&elf, &profile, &temp.path().join("stale"));
+    assert!(!code.contains("cold)) void B_"), "{code}");
+}
@@ -0,0 +1,69 @@
+//! Compile progress: a callback sees every phase in order, and the emit
+//! and cc phases count up to their totals.
+
+use std::sync::{Arc, Mutex};
+
+use guest::{ECALL, addi, li};
+use rvr::test_support::guest;
+use rvr::{CompileOptions, CompilePhase, CompileProgress};
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{REG_A0, REG_A7, REG_ZERO, Rv64};
+
+const TEXT: u64 = 0x1000;
+
+/// `exit(0)`.
+fn exit_elf() -> Vec<u8> {
+    let text = [addi(REG_A0, REG_ZERO, 0), li(REG_A7, SYS_EXIT), ECALL];
+    guest::text_elf::<Rv64>(TEXT, &text).build()
+}
+
+/// Last report of `phase`.
+fn last(reports: &[CompileProgress], phase: CompilePhase) -> CompileProgress {
+    *reports
+        .iter()
+        .rev()
+        .find(|p| p.phase == phase)
+        .unwrap_or_else(|| panic!("no {phase} report in {reports:?}"))
+}
+
+#[test]
+fn test_progress_reports_every_phase() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = temp.path().join("progress.elf");
+    std::fs::write(&elf, exit_elf()).expect("write ELF");
+
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&reports);
+    let options = CompileOptions::new()
+        .with_quiet(true)
+        .with_progress(Box::new(move |progress| {
+            sink.lock().expect("progress lock").push(progress);
+        }));
+    rvr::compile_with_options(&elf, &temp.path().join("out"), &options).expect("compile");
+    let reports = std::mem::take(&mut *reports.lock().expect("progress lock"));
+
+    let mut phases: Vec<CompilePhase> = reports.iter().map(|p| p.phase).collect();
+    phases.dedup();
+    assert_eq!(
+        phases,
+        [
+            CompilePhase::Parse,
+            CompilePhase::Cfg,
+            CompilePhase::Lift,
+            CompilePhase::Emit,
+            CompilePhase::Cc,
+        ]
+    );
+
+    let lift = last(&reports, CompilePhase::Lift);
+    assert!(lift.total > 0);
+    assert_eq!(lift.done, lift.total);
+    let emit = last(&reports, CompilePhase::Emit);
+    assert!(emit.total > 0);
+    assert_eq!(emit.done, emit.total);
+    // Parts, dispatch, memory and syscalls are compiled from scratch
+    let cc = last(&reports, CompilePhase::Cc);
+    assert!(cc.total > 1, "{cc:?}");
+    assert_eq!(cc.done, cc.total);
+    assert_eq!(cc.percent(), Some(100));
+}
@@ -0,0 +1,83 @@
+//! Ptrace lockstep diff: the x86 backend single-stepped under ptrace against
+//! the C backend with the diff tracer.
+//!
+//! With the x86 assembly patched to miscompile XOR, the divergence must be
+//! reported at the guest's `xor`.
+#![cfg(all(target_os = "linux", target_env = "gnu", target_arch = "x86_64"))]
+
+use std::path::Path;
+
+use guest::{ECALL, OPCODE_OP, OPCODE_OP_IMM, li};
+use rvr::Compiler;
+use rvr::test_support::diff;
+use rvr::test_support::guest;
+use rvr_elf::{ElfWriter, PF_R, PF_X, STT_NOTYPE};
+use rvr_emit::Backend;
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{REG_A0, REG_A1, REG_A2, REG_A7, REG_ZERO, Rv64, encode_i, encode_r};
+
+const FUNCT3_XOR: u8 = 0b100;
+
+const TEXT: u64 = 0x1000;
+const STACK_TOP: u64 = 0x10_0000;
+/// PC of the guest's `xor`, its third instruction.
+const XOR_PC: u64 = TEXT + 2 * 4;
+
+/// `exit((12 ^ 10) + 10)`
+fn elf() -> Vec<u8> {
+    let text = [
+        encode_i(OPCODE_OP_IMM, REG_A0, 0, REG_ZERO, 12),
+        encode_i(OPCODE_OP_IMM, REG_A1, 0, REG_ZERO, 10),
+        encode_r(OPCODE_OP, REG_A2, FUNCT3_XOR, REG_A0, REG_A1, 0),
+        encode_r(OPCODE_OP, REG_A0, 0, REG_A2, REG_A1, 0),
+        li(REG_A7, SYS_EXIT),
+        ECALL,
+    ];
+    let code = text.iter().flat_map(|word| word.to_le_bytes()).collect();
+    ElfWriter::<Rv64>::new(TEXT)
+        .with_segment(TEXT, PF_R | PF_X, code)
+        .with_symbol("__stack_top", STACK_TOP, STT_NOTYPE)
+        .build()
+}
+
+/// Lockstep-compare the C and x86 builds; with `inject`, the x86 build's
+/// XOR results are off by one.
+fn compare(dir: &Path, inject: bool) -> diff::CompareResult {
+    let elf_path = dir.join("xor.elf");
+    std::fs::write(&elf_path, elf()).expect("write ELF");
+    let ref_dir = dir.join("ref");
+    let test_dir = dir.join("test");
+    let compiler = Compiler::default();
+    diff::compile_for_diff(&elf_path, &ref_dir, Backend::C, &compiler).expect("compile C");
+    diff::compile_for_ptrace(&elf_path, &test_dir, Backend::X86Asm, &compiler)
+        .expect("compile x86");
+    if inject {
+        diff::inject_x86_xor_divergence(&test_dir, &compiler).expect("inject divergence");
+    }
+
+    let mut reference = diff::InProcessExecutor::new(&ref_dir, &elf_path).expect("load C");
+    let mut test = diff::PtraceExecutor::new(&test_dir, &elf_path).expect("start ptrace");
+    diff::compare_lockstep(
+        &mut reference,
+        &mut test,
+        &diff::CompareConfig::default(),
+        None,
+    )
+}
+
+#[test]
+fn test_ptrace_matches_c() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let result = compare(temp.path(), false);
+    assert!(result.divergence.is_none(), "{:?}", result.divergence);
+    assert_eq!(result.matched, 6);
+}
+
+#[test]
+fn test_ptrace_catches_injected_divergence() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let divergence = compare(temp.path(), true).divergence.expect("divergence");
+    assert_eq!(divergence.kind, diff::DivergenceKind::RegValue);
+    assert_eq!(divergence.expected.pc, XOR_PC);
+    assert_eq!(divergence.actual.rd, Some(REG_A2));
+}
@@ -0,0 +1,90 @@
+//! Lift-error quarantine: a guest with one undecodable function.
+
+use std::path::Path;
+
+use guest::{ECALL, FUNCT3_BEQ, OPCODE_BRANCH, OPCODE_JAL, OPCODE_OP_IMM};
+use rvr::test_support::guest;
+use rvr::{CompileOptions, Error, LiftErrorMode, LiftFailureKind, RunError, Runner};
+use rvr_elf::{ElfWriter, PF_R, PF_X};
+use rvr_isa::{REG_A0, REG_RA, REG_ZERO, Rv64, encode_b, encode_i, encode_j};
+
+const TEXT_BASE: u64 = 0x8000_0000;
+/// PC of the undecodable "function" the fixture calls when `a0 != 0`.
+const BAD_PC: u64 = TEXT_BASE + 0x14;
+/// Exit code of the path that never reaches the bad function.
+const GOOD_EXIT: u8 = 42;
+
+/// All-ones is reserved (>= 192-bit encoding) and never decodes.
+const UNDECODABLE: u32 = u32::MAX;
+
+/// `if a0 == 0 { exit(42) } else { bad() }`, with `bad` undecodable.
+fn fixture_elf() -> Vec<u8> {
+    let text = [
+        encode_b(OPCODE_BRANCH, FUNCT3_BEQ, REG_A0, REG_ZERO, 12),
+        encode_j(OPCODE_JAL, REG_RA, 16),
+        ECALL,
+        encode_i(OPCODE_OP_IMM, REG_A0, 0, REG_ZERO, i32::from(GOOD_EXIT)),
+        ECALL,
+        UNDECODABLE,
+    ];
+    let text = guest::code(&text);
+    ElfWriter::<Rv64>::new(TEXT_BASE)
+        .with_segment(TEXT_BASE, PF_R | PF_X, text)
+        .build()
+}
+
+fn write_fixture(dir: &Path) -> std::path::PathBuf {
+    let elf = dir.join("quarantine.elf");
+    std::fs::write(&elf, fixture_elf()).expect("write fixture");
+    elf
+}
+
+fn options(mode: LiftErrorMode) -> CompileOptions {
+    CompileOptions::new()
+        .with_quiet(true)
+        .with_on_lift_error(mode)
+}
+
+#[test]
+fn test_quarantine_compiles_and_faults_when_called() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = write_fixture(temp.path());
+    let out = temp.path().join("quarantine");
+
+    let report = rvr::compile_with_report(&elf, &out, &options(LiftErrorMode::Quarantine))
+        .expect("quarantine compile succeeds");
+    assert_eq!(report.quarantined.len(), 1);
+    let failure = &report.quarantined[0];
+    assert_eq!(failure.kind, LiftFailureKind::Undecodable);
+    assert_eq!(failure.pc, BAD_PC);
+    assert_eq!(failure.raw, UNDECODABLE.to_le_bytes());
+
+    let mut runner = Runner::load(&out, &elf).expect("load runner");
+    let result = runner.run().expect("bad function not called");
+    assert_eq!(result.exit_code, GOOD_EXIT);
+
+    runner.prepare();
+    runner.set_register(REG_A0 as usize, 1);
+    match runner.execute_from(TEXT_BASE) {
+        Err(RunError::QuarantinedBlock { pc, reason }) => {
+            assert_eq!(pc, BAD_PC);
+            assert!(
+                reason.contains("undecodable instruction at 0x80000014"),
+                "{reason}"
+            );
+        }
+        other => panic!("expected QuarantinedBlock fault, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_abort_fails_compile() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = write_fixture(temp.path());
+    let out = temp.path().join("abort");
+
+    match rvr::compile_with_report(&elf, &out, &options(LiftErrorMode::Abort)) {
+        Err(Error::LiftFailed(failure)) => assert_eq!(failure.pc, BAD_PC),
+        other => panic!("expected LiftFailed, got {other:?}"),
+    }
+}
@@ -0,0 +1,123 @@
+//! Hot reload: a runner swaps in a recompiled guest and runs the new code in
+//! the same process, keeping its syscall handler and host buffer.
+
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use guest::{Compiled, ECALL, FUNCT3_D, OPCODE_STORE, addi, li, lui};
+use rvr::test_support::guest;
+use rvr::{CompileOptions, HostBuffer, InstretMode, RunError, Runner, SyscallMode};
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{REG_A0, REG_A1, REG_A7, REG_T0, REG_ZERO, Rv64, encode_s};
+use rvr_state::page_size;
+
+/// Custom syscall the guest reports its version with.
+const SYS_VERSION: u64 = 0x1000;
+
+const TEXT: u64 = 0x1000;
+/// Host buffer the guest writes its version to.
+const OUTPUT: u64 = 0x10_0000;
+
+/// `*OUTPUT = version; version(version); exit(0)`.
+fn guest_elf(version: i32) -> Vec<u8> {
+    let text = [
+        lui(REG_A1, OUTPUT),
+        addi(REG_T0, REG_ZERO, version),
+        encode_s(OPCODE_STORE, FUNCT3_D, REG_A1, REG_T0, 0),
+        addi(REG_A0, REG_ZERO, version),
+        lui(REG_A7, SYS_VERSION),
+        ECALL,
+        addi(REG_A0, REG_ZERO, 0),
+        li(REG_A7, SYS_EXIT),
+        ECALL,
+    ];
+    guest::text_elf::<Rv64>(TEXT, &text).build()
+}
+
+/// Compile `version` of the guest into `dir/guest`, overwriting the last
+/// one.
+fn compile(dir: &Path, version: i32, options: CompileOptions) -> Compiled {
+    let options = options.with_syscall_mode(SyscallMode::Linux);
+    guest::compile(dir, "guest", &guest_elf(version), &options)
+}
+
+fn output(buffer: &HostBuffer) -> u64 {
+    u64::from_le_bytes(buffer.as_slice()[..8].try_into().unwrap())
+}
+
+#[test]
+fn test_reload_runs_new_guest() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let compiled = compile(temp.path(), 1, CompileOptions::new());
+    let mut runner = Runner::load(&compiled.out, &compiled.elf).expect("load runner");
+    let versions = Arc::new(Mutex::new(Vec::new()));
+    let seen = Arc::clone(&versions);
+    runner.register_syscall(
+        SYS_VERSION,
+        Box::new(move |ctx| {
+            seen.lock().unwrap().push(ctx.arg(0));
+            0
+        }),
+    );
+    let buffer = HostBuffer::new(page_size()).expect("buffer");
+    runner
+        .map_host_buffer(OUTPUT, &buffer, true)
+        .expect("map buffer");
+
+    runner.run().expect("run v1");
+    assert_eq!(output(&buffer), 1);
+
+    let compiled = compile(temp.path(), 2, CompileOptions::new());
+    runner
+        .reload(&compiled.out, &compiled.elf)
+        .expect("reload v2");
+    assert_eq!(runner.get_pc(), runner.entry_point());
+    runner.run().expect("run v2");
+    assert_eq!(output(&buffer), 2);
+    assert_eq!(*versions.lock().unwrap(), [1, 2]);
+
+    // No copies of the library are left behind
+    let leftovers = std::fs::read_dir(&compiled.out)
+        .unwrap()
+        .filter(|entry| {
+            let name = entry.as_ref().unwrap().file_name();
+            name.to_string_lossy().starts_with(".reload-")
+        })
+        .count();
+    assert_eq!(leftovers, 0);
+}
+
+#[test]
+fn test_reload_rejects_mismatched_library() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let compiled = compile(temp.path(), 1, CompileOptions::new());
+    let mut runner = Runner::load(&compiled.out, &compiled.elf).expect("load runner");
+    let buffer = HostBuffer::new(page_size()).expect("buffer");
+    runner
+        .map_host_buffer(OUTPUT, &buffer, true)
+        .expect("map buffer");
+
+    let options = CompileOptions::new().with_instret_mode(InstretMode::Off);
+    let compiled = compile(temp.path(), 2, options);
+    let err = runner
+        .reload(&compiled.out, &compiled.elf)
+        .expect_err("instret modes differ");
+    assert!(
+        matches!(
+            err,
+            RunError::ReloadMismatch {
+                what: "instret mode",
+                ..
+            }
+        ),
+        "{err:?}"
+    );
+    assert_eq!(
+        err.to_string(),
+        "reloaded library instret mode differs: was Count, now Off"
+    );
+
+    // The runner keeps the library and buffer it had
+    runner.run().expect("run v1");
+    assert_eq!(output(&buffer), 1);
+}
@@ -0,0 +1,179 @@
+//! Relocatable objects: a two-function `.o` from the RISC-V assembler is
+//! linked by `rvr-elf` with an explicit section layout and with the default
+//! one, recompiled, and run to the exit code it computes.
+//!
+//! Skipped when no RISC-V assembler is on `PATH`.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use rvr::{CompileOptions, Runner};
+use rvr_elf::{
+    ENTRY_SYMBOL, ElfImage, ElfWriter, R_RISCV_64, R_RISCV_CALL, R_RISCV_CALL_PLT, R_RISCV_HI20,
+    R_RISCV_LO12_I, R_RISCV_LO12_S, R_RISCV_PCREL_HI20, SectionLayout,
+};
+use rvr_isa::Rv64;
+
+/// Assembler prefixes tried in order.
+const PREFIXES: &[&str] = &["riscv64-elf-", "riscv64-unknown-elf-", "riscv64-linux-gnu-"];
+
+/// `_start` computes `38 + 2` through a direct call and `+ 2` through a
+/// function pointer in `.data`, round-trips the sum through `.bss` and
+/// exits with it.
+const SOURCE: &str = r"
+    .text
+    .globl _start
+    .type _start, @function
+_start:
+    li a0, 38
+    call add_two
+    la t0, table
+    ld t1, 0(t0)
+    jalr t1
+    lui t2, %hi(result)
+    sd a0, %lo(result)(t2)
+    li a0, 0
+    ld a0, %lo(result)(t2)
+    bnez a0, done
+    li a0, 1
+done:
+    li a7, 93
+    ecall
+    j done
+    .size _start, .-_start
+
+    .globl add_two
+    .type add_two, @function
+add_two:
+    addi a0, a0, 2
+    ret
+    .size add_two, .-add_two
+
+    .data
+    .align 3
+table:
+    .dword add_two
+
+    .bss
+    .align 3
+result:
+    .zero 8
+";
+const EXIT_CODE: u8 = 42;
+
+const TEXT: u64 = 0x4_0000;
+const DATA: u64 = 0x8_0000;
+const BSS: u64 = 0x8_1000;
+
+/// First RISC-V assembler found on `PATH`.
+fn assembler() -> Option<String> {
+    PREFIXES
+        .iter()
+        .map(|prefix| format!("{prefix}as"))
+        .find(|cmd| {
+            Command::new(cmd)
+                .arg("--version")
+                .output()
+                .is_ok_and(|o| o.status.success())
+        })
+}
+
+/// Assemble [`SOURCE`] into `dir/two.o`.
+fn assemble(dir: &Path, assembler: &str) -> PathBuf {
+    let source = dir.join("two.s");
+    std::fs::write(&source, SOURCE).expect("write source");
+    let object = dir.join("two.o");
+    let status = Command::new(assembler)
+        .args(["-march=rv64imac", "-mabi=lp64", "-o"])
+        .arg(&object)
+        .arg(&source)
+        .status()
+        .expect("run assembler");
+    assert!(status.success());
+    object
+}
+
+/// Compile `elf` and run it to its exit code.
+fn run(dir: &Path, name: &str, elf: &Path) -> u8 {
+    let out = dir.join(name);
+    let options = CompileOptions::new().with_quiet(true);
+    rvr::compile_with_options(elf, &out, &options).expect("compile");
+    let mut runner = Runner::load(&out, elf).expect("load runner");
+    runner.run().expect("run guest").exit_code
+}
+
+/// Executable with the segments, named symbols and entry point of `image`.
+fn executable(image: &ElfImage<Rv64>) -> Vec<u8> {
+    let mut writer = ElfWriter::<Rv64>::new(image.entry_point).with_e_flags(image.e_flags);
+    for segment in &image.memory_segments {
+        // BSS becomes file data
+        let mut data = segment.data.clone();
+        data.resize(usize::try_from(segment.memsz()).unwrap(), 0);
+        writer = writer.with_segment(segment.virtual_start, segment.flags, data);
+    }
+    for symbol in image.symbols.iter().filter(|s| !s.name.is_empty()) {
+        writer = writer.with_symbol(&symbol.name, symbol.value, symbol.sym_type);
+    }
+    if let Some(attributes) = image.arch_attributes() {
+        writer = writer.with_attributes(attributes.clone());
+    }
+    writer.build()
+}
+
+#[test]
+fn test_relocatable_object_with_layout() {
+    let Some(assembler) = assembler() else {
+        eprintln!("Skipping test: no RISC-V assembler found");
+        return;
+    };
+    let temp = tempfile::tempdir().expect("tempdir");
+    let object = std::fs::read(assemble(temp.path(), &assembler)).expect("read object");
+
+    let layout = SectionLayout::new()
+        .with_section(".text", TEXT)
+        .with_section(".data", DATA)
+        .with_section(".bss", BSS);
+    let image =
+        ElfImage::<Rv64>::parse_relocatable(&object, &layout, ENTRY_SYMBOL).expect("link object");
+    assert_eq!(image.entry_point, TEXT);
+    let add_two = image.lookup_function("add_two").expect("add_two");
+    assert!(add_two > TEXT && add_two < DATA);
+    assert_eq!(image.lookup_symbol("table"), Some(DATA));
+    assert_eq!(image.lookup_symbol("result"), Some(BSS));
+    assert_eq!(image.bytes_at(DATA, 8), Some(&add_two.to_le_bytes()[..]));
+
+    let r_types: Vec<u32> = image.relocations().iter().map(|r| r.r_type).collect();
+    for r_type in [
+        R_RISCV_PCREL_HI20,
+        R_RISCV_HI20,
+        R_RISCV_LO12_I,
+        R_RISCV_LO12_S,
+        R_RISCV_64,
+    ] {
+        assert!(r_types.contains(&r_type), "{r_type} in {r_types:?}");
+    }
+    assert!(r_types.contains(&R_RISCV_CALL) || r_types.contains(&R_RISCV_CALL_PLT));
+    let table = image.relocations().iter().find(|r| r.r_type == R_RISCV_64);
+    assert_eq!(table.map(|r| r.offset), Some(DATA));
+
+    let elf = temp.path().join("two.elf");
+    std::fs::write(&elf, executable(&image)).expect("write ELF");
+    assert_eq!(run(temp.path(), "two", &elf), EXIT_CODE);
+}
+
+#[test]
+fn test_relocatable_object_default_layout() {
+    let Some(assembler) = assembler() else {
+        eprintln!("Skipping test: no RISC-V assembler found");
+        return;
+    };
+    let temp = tempfile::tempdir().expect("tempdir");
+    let object = assemble(temp.path(), &assembler);
+
+    // Packed from the default load bias, entering at `_start`
+    let image = ElfImage::<Rv64>::parse(&std::fs::read(&object).expect("read object"))
+        .expect("link object");
+    assert_eq!(image.entry_point, rvr::DEFAULT_LOAD_BIAS);
+    assert_eq!(image.load_bias, 0);
+    assert_eq!(run(temp.path(), "two", &object), EXIT_CODE);
+}
@@ -1,14 +1,21 @@
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 use std::time::Duration;
 
 use libtest_mimic::{Arguments, Failed, Trial};
 use rvr_emit::Backend;
 
+#[path = "support/arch_refs.rs"]
+mod arch_refs;
 #[path = "support/riscv_arch_test.rs"]
 mod support;
 mod test_utils;
 
+/// Set to fail on stale or missing references instead of regenerating them
+/// with Spike.
+const NO_AUTO_REFS_ENV: &str = "RVR_NO_AUTO_REFS";
+
 fn main() {
     let mut args = Arguments::from_args();
     test_utils::cap_threads(&mut args);
@@ -27,32 +34,30 @@ fn main() {
     let mut trials = Vec::new();
     for backend in backends {
         let backend_name = backend_label(backend);
-        for (elf, reference) in &cases {
+        for elf in &cases {
             let name = format!("{}::{}", backend_name, ident_from_path(elf));
             let elf = elf.clone();
-            let reference = reference.clone();
-            trials.push(Trial::test(name, move || {
-                run_case(&elf, &reference, backend)
-            }));
+            trials.push(Trial::test(name, move || run_case(&elf, backend)));
         }
     }
 
     libtest_mimic::run(&args, trials).exit();
 }
 
-fn run_case(elf: &Path, reference: &Path, backend: Backend) -> Result<(), Failed> {
+fn run_case(elf: &Path, backend: Backend) -> Result<(), Failed> {
     let _ = maybe_rebuild_elfs();
     let timeout = Duration::from_secs(10);
     let compiler = rvr::Compiler::default();
     let root = workspace_root();
     let elf_path = root.join(elf);
-    let ref_path = root.join(reference);
-    if !ref_path.exists() || !elf_path.exists() {
+    if !elf_path.exists() {
         return Ok(());
     }
+    let (refs, auto_refs) = reference_store()?;
     let result = support::run_test(
         elf_path.as_path(),
-        ref_path.as_path(),
+        refs,
+        auto_refs,
         timeout,
         &compiler,
         backend,
@@ -63,6 +68,24 @@ fn run_case(elf: &Path, reference: &Path, backend: Backend) -> Result<(), Failed
     }
 }
 
+/// `SharedWriter` reference store, and whether stale references are regenerated.
+fn reference_store() -> Result<(&'static arch_refs::RefStore, bool), Failed> {
+    static STORE: OnceLock<Result<arch_refs::RefStore, String>> = OnceLock::new();
+    static AUTO_REFS: OnceLock<bool> = OnceLock::new();
+    let store = STORE
+        .get_or_init(|| arch_refs::RefStore::open(references_dir()))
+        .as_ref()
+        .map_err(|err| Failed::from(err.clone()))?;
+    let auto_refs = *AUTO_REFS.get_or_init(|| {
+        std::env::var(NO_AUTO_REFS_ENV).is_err() && support::find_spike().is_some()
+    });
+    Ok((store, auto_refs))
+}
+
+fn references_dir() -> PathBuf {
+    workspace_root().join("bin/riscv-arch-test/references")
+}
+
 fn enabled_backends() -> Vec<Backend> {
     let mut backends = vec![Backend::C];
     #[cfg(target_arch = "aarch64")]
@@ -101,7 +124,7 @@ fn maybe_rebuild_elfs() -> Result<(), Failed> {
         let config = support::ArchBuildConfig::new(support::ArchTestCategory::ALL.to_vec())
             .with_src_dir(root.join("programs/riscv-arch-test/riscv-test-suite"))
             .with_out_dir(root.join("bin/riscv-arch-test"))
-            .with_refs_dir(root.join("bin/riscv-arch-test/references"))
+            .with_refs_dir(references_dir())
             .with_toolchain(toolchain)
             .with_gen_refs(true);
 
@@ -122,28 +145,24 @@ fn build_only() -> Result<(), String> {
     let config = support::ArchBuildConfig::new(support::ArchTestCategory::ALL.to_vec())
         .with_src_dir(root.join("programs/riscv-arch-test/riscv-test-suite"))
         .with_out_dir(root.join("bin/riscv-arch-test"))
-        .with_refs_dir(root.join("bin/riscv-arch-test/references"))
+        .with_refs_dir(references_dir())
         .with_toolchain(toolchain)
         .with_gen_refs(gen_refs);
     support::build_tests(&config).map_err(|err| format!("failed to build arch tests: {err}"))
 }
 
-fn collect_arch_tests() -> Vec<(PathBuf, PathBuf)> {
+fn collect_arch_tests() -> Vec<PathBuf> {
     let root = workspace_root();
     let dir = root.join("bin/riscv-arch-test");
     let mut cases = Vec::new();
     if dir.exists() {
-        let _ = collect_arch_cases(&dir, &dir, &mut cases);
+        let _ = collect_arch_cases(&dir, &mut cases);
     }
-    cases.sort_by(|a, b| a.0.cmp(&b.0));
+    cases.sort();
     cases
 }
 
-fn collect_arch_cases(
-    root: &Path,
-    dir: &Path,
-    out: &mut Vec<(PathBuf, PathBuf)>,
-) -> std::io::Result<()> {
+fn collect_arch_cases(dir: &Path, out: &mut Vec<PathBuf>) -> std::io::Result<()> {
     for entry in fs::read_dir(dir)? {
         let entry = entry?;
         let path = entry.path();
@@ -151,20 +170,12 @@ fn collect_arch_cases(
             if path.file_name().and_then(|n| n.to_str()) == Some("references") {
                 continue;
             }
-            collect_arch_cases(root, &path, out)?;
+            collect_arch_cases(&path, out)?;
         } else if path.is_file() {
             if path.extension().and_then(|e| e.to_str()) == Some("sig") {
                 continue;
             }
-            let category = path
-                .parent()
-                .and_then(|p| p.file_name())
-                .unwrap_or_default();
-            let ref_path = root.join("references").join(category).join(format!(
-                "{}.sig",
-                path.file_name().and_then(|n| n.to_str()).unwrap_or("")
-            ));
-            out.push((path, ref_path));
+            out.push(path);
         }
     }
     Ok(())
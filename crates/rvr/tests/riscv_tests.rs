@@ -3,6 +3,7 @@ use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 use libtest_mimic::{Arguments, Failed, Trial};
+use rvr::{CDialect, CompileOptions, Compiler, DispatchMode};
 use rvr_emit::Backend;
 
 #[path = "support/riscv_tests.rs"]
@@ -25,52 +26,151 @@ fn main() {
     let backends = enabled_backends();
 
     let mut trials = Vec::new();
-    for backend in backends {
-        let backend_name = backend_label(backend);
+    for (backend, dispatch_mode) in backends {
+        let backend_name = backend_label(backend, dispatch_mode);
         for path in &cases {
             let name = format!("{}::{}", backend_name, ident_from_path(path));
+            let skip = is_skipped(path);
             let path = path.clone();
-            trials.push(Trial::test(name, move || run_case(&path, backend)));
+            trials.push(
+                Trial::test(name, move || {
+                    run_case(&path, backend, dispatch_mode, false, false, CDialect::Clang)
+                })
+                .with_ignored_flag(skip),
+            );
         }
     }
+    // LSE lowering of AMOs: only the atomics tests differ from backend_arm64
+    #[cfg(target_arch = "aarch64")]
+    for path in cases.iter().filter(|path| is_atomics_test(path)) {
+        let name = format!("backend_arm64_lse::{}", ident_from_path(path));
+        let path = path.clone();
+        trials.push(Trial::test(name, move || {
+            run_case(
+                &path,
+                Backend::ARM64Asm,
+                DispatchMode::Flat,
+                false,
+                true,
+                CDialect::Clang,
+            )
+        }));
+    }
+    // Portable C, built with GCC
+    for path in &cases {
+        let name = format!("backend_c_portable::{}", ident_from_path(path));
+        let skip = is_skipped(path);
+        let path = path.clone();
+        trials.push(
+            Trial::test(name, move || {
+                run_case(
+                    &path,
+                    Backend::C,
+                    DispatchMode::Flat,
+                    false,
+                    false,
+                    CDialect::Portable,
+                )
+            })
+            .with_ignored_flag(skip),
+        );
+    }
+    // Flat table of offsets instead of function pointers
+    for path in &cases {
+        let name = format!("backend_c_relative::{}", ident_from_path(path));
+        let skip = is_skipped(path);
+        let path = path.clone();
+        trials.push(
+            Trial::test(name, move || {
+                run_case(
+                    &path,
+                    Backend::C,
+                    DispatchMode::Flat,
+                    true,
+                    false,
+                    CDialect::Clang,
+                )
+            })
+            .with_ignored_flag(skip),
+        );
+    }
 
-    libtest_mimic::run(&args, trials).exit();
+    let conclusion = libtest_mimic::run(&args, trials);
+    support::print_hang_summary();
+    conclusion.exit();
 }
 
-fn run_case(path: &Path, backend: Backend) -> Result<(), Failed> {
+fn run_case(
+    path: &Path,
+    backend: Backend,
+    dispatch_mode: DispatchMode,
+    dispatch_table_relative: bool,
+    arm64_lse: bool,
+    c_dialect: CDialect,
+) -> Result<(), Failed> {
     let _ = maybe_rebuild_elfs();
     let timeout = Duration::from_secs(10);
-    let compiler = rvr::Compiler::default();
+    let compiler = if c_dialect.is_portable() {
+        Compiler::gcc()
+    } else {
+        Compiler::default()
+    };
     let root = workspace_root();
     let full_path = root.join(path);
     if !full_path.exists() {
         return Ok(());
     }
-    let result = support::run_test(full_path.as_path(), timeout, &compiler, backend);
+    let options = CompileOptions::new()
+        .with_compiler(compiler)
+        .with_backend(backend)
+        .with_dispatch_mode(dispatch_mode)
+        .with_dispatch_table_relative(dispatch_table_relative)
+        .with_arm64_lse(arm64_lse)
+        .with_c_dialect(c_dialect);
+    let result = support::run_test(full_path.as_path(), timeout, options);
     match result {
         Ok(()) => Ok(()),
         Err(err) => Err(Failed::from(err)),
     }
 }
 
-fn enabled_backends() -> Vec<Backend> {
-    let mut backends = vec![Backend::C];
+fn enabled_backends() -> Vec<(Backend, DispatchMode)> {
+    let mut backends = vec![
+        (Backend::C, DispatchMode::Flat),
+        (Backend::C, DispatchMode::PerFunction),
+    ];
     #[cfg(target_arch = "aarch64")]
     {
-        backends.push(Backend::ARM64Asm);
+        backends.push((Backend::ARM64Asm, DispatchMode::Flat));
     }
     #[cfg(target_arch = "x86_64")]
     {
-        backends.push(Backend::X86Asm);
+        backends.push((Backend::X86Asm, DispatchMode::Flat));
     }
     backends
 }
 
-const fn backend_label(backend: Backend) -> &'static str {
-    match backend {
-        Backend::C => "backend_c",
-        Backend::ARM64Asm => "backend_arm64",
-        Backend::X86Asm => "backend_x86",
+/// Tests on the skip list, reported as ignored.
+fn is_skipped(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(support::should_skip)
+}
+
+/// rv32ua/rv64ua tests.
+#[cfg(target_arch = "aarch64")]
+fn is_atomics_test(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.contains("ua-p-"))
+}
+
+const fn backend_label(backend: Backend, dispatch_mode: DispatchMode) -> &'static str {
+    match (backend, dispatch_mode) {
+        (Backend::C, DispatchMode::Flat) => "backend_c",
+        (Backend::C, DispatchMode::PerFunction) => "backend_c_per_function",
+        (Backend::ARM64Asm, _) => "backend_arm64",
+        (Backend::X86Asm, _) => "backend_x86",
     }
 }
 
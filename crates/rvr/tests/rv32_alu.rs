@@ -0,0 +1,282 @@
+//! RV32 arithmetic on every backend: a guest applies each RV32IM register,
+//! immediate, branch, load and store operation to edge-case operands and
+//! stores the 32-bit results, which must match a host model of the ISA.
+
+use guest::{
+    ECALL, FUNCT3_SB, FUNCT3_SW, FUNCT7_MULDIV, FUNCT7_SUB, OPCODE_BRANCH, OPCODE_LOAD, OPCODE_LUI,
+    OPCODE_OP, OPCODE_OP_IMM, OPCODE_STORE, addi,
+};
+use rvr::test_support::guest;
+use rvr::{CompileOptions, Runner};
+use rvr_elf::{PF_R, PF_W};
+use rvr_emit::Backend;
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{
+    REG_A0, REG_A1, REG_A7, REG_S0, REG_S1, REG_S2, REG_S3, REG_S4, REG_S5, REG_S6, REG_T0,
+    REG_ZERO, Rv32, encode_b, encode_i, encode_r, encode_s, encode_u,
+};
+
+const FUNCT3_SH: u8 = 0b001;
+/// Skips the instruction after a taken branch.
+const BRANCH_SKIP: i32 = 8;
+const WORD: i32 = 4;
+
+const TEXT: u64 = 0x1000;
+/// Bytes the loads read.
+const DATA: u64 = 0x8_0000;
+const DATA_BYTES: [u8; 8] = [0x01, 0x7f, 0xff, 0x80, 0x34, 0x12, 0xcd, 0xab];
+/// One word per result, zeroed.
+const RESULTS: u64 = 0x10_0000;
+
+/// Operands, and the registers holding them while the guest runs.
+const VALUE_REGS: [u8; 7] = [REG_S0, REG_S1, REG_S2, REG_S3, REG_S4, REG_S5, REG_S6];
+const VALUES: [u32; 7] = [0, 1, 0xffff_ffff, 0x8000_0000, 0x7fff_ffff, 0xfedc_ba98, 33];
+const IMMS: [i32; 5] = [-2048, -1, 0, 1, 2047];
+const SHAMTS: [i32; 4] = [0, 1, 15, 31];
+
+type BinOp = fn(u32, u32) -> u32;
+type Cond = fn(u32, u32) -> bool;
+
+/// `(funct3, funct7, model)` of the OP register-register operations.
+const REG_OPS: [(u8, u8, BinOp); 18] = [
+    (0b000, 0, u32::wrapping_add),
+    (0b000, FUNCT7_SUB, u32::wrapping_sub),
+    (0b001, 0, |a, b| a << (b & 31)),
+    (0b010, 0, |a, b| {
+        u32::from(a.cast_signed() < b.cast_signed())
+    }),
+    (0b011, 0, |a, b| u32::from(a < b)),
+    (0b100, 0, |a, b| a ^ b),
+    (0b101, 0, |a, b| a >> (b & 31)),
+    (0b101, FUNCT7_SUB, |a, b| {
+        (a.cast_signed() >> (b & 31)).cast_unsigned()
+    }),
+    (0b110, 0, |a, b| a | b),
+    (0b111, 0, |a, b| a & b),
+    (0b000, FUNCT7_MULDIV, u32::wrapping_mul),
+    (0b001, FUNCT7_MULDIV, |a, b| {
+        high(i64::from(a.cast_signed()) * i64::from(b.cast_signed()))
+    }),
+    (0b010, FUNCT7_MULDIV, |a, b| {
+        high(i64::from(a.cast_signed()) * i64::from(b))
+    }),
+    (0b011, FUNCT7_MULDIV, |a, b| {
+        high((u64::from(a) * u64::from(b)).cast_signed())
+    }),
+    (0b100, FUNCT7_MULDIV, |a, b| {
+        a.cast_signed()
+            .checked_div(b.cast_signed())
+            .map_or(if b == 0 { u32::MAX } else { a }, i32::cast_unsigned)
+    }),
+    (0b101, FUNCT7_MULDIV, |a, b| {
+        a.checked_div(b).unwrap_or(u32::MAX)
+    }),
+    (0b110, FUNCT7_MULDIV, |a, b| {
+        a.cast_signed()
+            .checked_rem(b.cast_signed())
+            .map_or(if b == 0 { a } else { 0 }, i32::cast_unsigned)
+    }),
+    (0b111, FUNCT7_MULDIV, |a, b| a.checked_rem(b).unwrap_or(a)),
+];
+
+/// `(funct3, model)` of the OP-IMM operations taking a 12-bit immediate.
+const IMM_OPS: [(u8, BinOp); 6] = [
+    (0b000, u32::wrapping_add),
+    (0b010, |a, b| u32::from(a.cast_signed() < b.cast_signed())),
+    (0b011, |a, b| u32::from(a < b)),
+    (0b100, |a, b| a ^ b),
+    (0b110, |a, b| a | b),
+    (0b111, |a, b| a & b),
+];
+
+/// `(funct3, imm high bits, model)` of the shift-immediate operations.
+const SHIFT_OPS: [(u8, i32, BinOp); 3] = [
+    (0b001, 0, |a, b| a << b),
+    (0b101, 0, |a, b| a >> b),
+    (0b101, 0x400, |a, b| (a.cast_signed() >> b).cast_unsigned()),
+];
+
+/// `(funct3, model)` of the branches.
+const BRANCHES: [(u8, Cond); 6] = [
+    (0b000, |a, b| a == b),
+    (0b001, |a, b| a != b),
+    (0b100, |a, b| a.cast_signed() < b.cast_signed()),
+    (0b101, |a, b| a.cast_signed() >= b.cast_signed()),
+    (0b110, |a, b| a < b),
+    (0b111, |a, b| a >= b),
+];
+
+/// `(funct3, offsets)` of the loads.
+const LOADS: [(u8, &[i32]); 5] = [
+    (0b000, &[0, 1, 2, 3]),
+    (0b001, &[0, 2, 4, 6]),
+    (0b010, &[0, 4]),
+    (0b100, &[0, 1, 2, 3]),
+    (0b101, &[0, 2, 4, 6]),
+];
+
+fn high(product: i64) -> u32 {
+    u32::try_from(product.cast_unsigned() >> 32).unwrap()
+}
+
+const fn value_reg(index: usize) -> u8 {
+    VALUE_REGS[index]
+}
+
+/// `lui` + `addi` loading `value`.
+const fn li(rd: u8, value: u32) -> [u32; 2] {
+    let hi = value.wrapping_add(0x800) >> 12;
+    let lo = value.wrapping_sub(hi << 12).cast_signed();
+    [encode_u(OPCODE_LUI, rd, hi), addi(rd, rd, lo)]
+}
+
+/// Store t0 as the next result.
+const fn push_result() -> [u32; 2] {
+    [
+        encode_s(OPCODE_STORE, FUNCT3_SW, REG_A0, REG_T0, 0),
+        addi(REG_A0, REG_A0, WORD),
+    ]
+}
+
+/// Model of a load of `width` bytes at `offset` into `DATA_BYTES`.
+fn load(funct3: u8, offset: i32) -> u32 {
+    let offset = usize::try_from(offset).unwrap();
+    let width = 1 << (funct3 & 0b11);
+    let mut bytes = [0; 4];
+    bytes[..width].copy_from_slice(&DATA_BYTES[offset..offset + width]);
+    let value = u32::from_le_bytes(bytes);
+    let unused = 32 - 8 * u32::try_from(width).unwrap();
+    if funct3 & 0b100 == 0 && unused > 0 {
+        ((value << unused).cast_signed() >> unused).cast_unsigned()
+    } else {
+        value
+    }
+}
+
+/// Guest text and the results it must store.
+fn program() -> (Vec<u32>, Vec<u32>) {
+    let mut text: Vec<u32> = VALUES
+        .iter()
+        .enumerate()
+        .flat_map(|(i, &value)| li(value_reg(i), value))
+        .collect();
+    text.extend(li(REG_A0, u32::try_from(RESULTS).unwrap()));
+    text.extend(li(REG_A1, u32::try_from(DATA).unwrap()));
+    let mut expected = Vec::new();
+    let pairs = || (0..VALUES.len()).flat_map(|i| (0..VALUES.len()).map(move |j| (i, j)));
+
+    for &(funct3, funct7, model) in &REG_OPS {
+        for (i, j) in pairs() {
+            let (rs1, rs2) = (value_reg(i), value_reg(j));
+            text.push(encode_r(OPCODE_OP, REG_T0, funct3, rs1, rs2, funct7));
+            text.extend(push_result());
+            expected.push(model(VALUES[i], VALUES[j]));
+        }
+    }
+    for &(funct3, model) in &IMM_OPS {
+        for (i, &imm) in (0..VALUES.len()).flat_map(|i| IMMS.iter().map(move |imm| (i, imm))) {
+            text.push(encode_i(OPCODE_OP_IMM, REG_T0, funct3, value_reg(i), imm));
+            text.extend(push_result());
+            expected.push(model(VALUES[i], imm.cast_unsigned()));
+        }
+    }
+    for &(funct3, high_bits, model) in &SHIFT_OPS {
+        for (i, &shamt) in (0..VALUES.len()).flat_map(|i| SHAMTS.iter().map(move |s| (i, s))) {
+            let imm = high_bits | shamt;
+            text.push(encode_i(OPCODE_OP_IMM, REG_T0, funct3, value_reg(i), imm));
+            text.extend(push_result());
+            expected.push(model(VALUES[i], shamt.cast_unsigned()));
+        }
+    }
+    for &(funct3, model) in &BRANCHES {
+        for (i, j) in pairs() {
+            text.push(addi(REG_T0, REG_ZERO, 1));
+            text.push(encode_b(
+                OPCODE_BRANCH,
+                funct3,
+                value_reg(i),
+                value_reg(j),
+                BRANCH_SKIP,
+            ));
+            text.push(addi(REG_T0, REG_ZERO, 0));
+            text.extend(push_result());
+            expected.push(u32::from(model(VALUES[i], VALUES[j])));
+        }
+    }
+    for &(funct3, offsets) in &LOADS {
+        for &offset in offsets {
+            text.push(encode_i(OPCODE_LOAD, REG_T0, funct3, REG_A1, offset));
+            text.extend(push_result());
+            expected.push(load(funct3, offset));
+        }
+    }
+    // Narrow stores into zeroed result words
+    let stored = VALUES[5];
+    for (funct3, mask) in [(FUNCT3_SB, 0xff), (FUNCT3_SH, 0xffff), (FUNCT3_SW, !0)] {
+        text.push(encode_s(OPCODE_STORE, funct3, REG_A0, value_reg(5), 0));
+        text.push(addi(REG_A0, REG_A0, WORD));
+        expected.push(stored & mask);
+    }
+
+    text.extend([
+        addi(REG_A0, REG_ZERO, 0),
+        guest::li(REG_A7, SYS_EXIT),
+        ECALL,
+    ]);
+    (text, expected)
+}
+
+fn run(backend: Backend) {
+    let (text, expected) = program();
+    let elf = guest::text_elf::<Rv32>(TEXT, &text)
+        .with_segment(DATA, PF_R, DATA_BYTES.to_vec())
+        .with_segment(RESULTS, PF_R | PF_W, vec![0; expected.len() * 4])
+        .build();
+
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf_path = temp.path().join("rv32_alu.elf");
+    std::fs::write(&elf_path, elf).expect("write ELF");
+    let out = temp.path().join("out");
+    let options = CompileOptions::new().with_quiet(true).with_backend(backend);
+    rvr::compile_with_options(&elf_path, &out, &options).expect("compile");
+    let mut runner = Runner::load(&out, &elf_path).expect("load runner");
+    let result = runner.run().expect("run guest");
+    assert_eq!(result.exit_code, 0);
+
+    let mut bytes = vec![0; expected.len() * 4];
+    assert_eq!(runner.read_memory(RESULTS, &mut bytes), bytes.len());
+    let results: Vec<u32> = bytes
+        .chunks_exact(4)
+        .map(|word| u32::from_le_bytes(word.try_into().unwrap()))
+        .collect();
+    let mismatches: Vec<_> = results
+        .iter()
+        .zip(&expected)
+        .enumerate()
+        .filter(|(_, (got, want))| got != want)
+        .map(|(i, (got, want))| format!("#{i}: {got:#x} != {want:#x}"))
+        .collect();
+    assert!(mismatches.is_empty(), "{backend:?}: {mismatches:?}");
+    // Registers stay 32-bit
+    assert_eq!(
+        runner.get_register(usize::from(value_reg(2))),
+        u64::from(VALUES[2])
+    );
+}
+
+#[test]
+fn test_rv32_alu_c() {
+    run(Backend::C);
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn test_rv32_alu_x86() {
+    run(Backend::X86Asm);
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn test_rv32_alu_arm64() {
+    run(Backend::ARM64Asm);
+}
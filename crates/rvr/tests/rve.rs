@@ -0,0 +1,98 @@
+//! RVE (16-register) guests: an ELF flagged `EF_RISCV_RVE` compiles to a
+//! 16-entry register file, the runner picks it up from the library, and
+//! instructions naming x16-x31 are reported at lift time.
+
+use guest::{ECALL, FUNCT3_ADD, FUNCT3_BNE, OPCODE_BRANCH, OPCODE_OP, addi};
+use rvr::test_support::guest;
+use rvr::{CompileOptions, EmitConfig, Error, LiftFailureKind, Pipeline, Runner};
+use rvr_elf::{EF_RISCV_RVE, ElfImage, ElfWriter, PF_R, PF_X};
+use rvr_isa::{REG_A0, REG_A4, REG_A5, REG_A6, REG_ZERO, Rv32, Rv64, Xlen, encode_b, encode_r};
+
+const TEXT: u64 = 0x1000;
+/// Loop bound of the guest.
+const COUNT: u8 = 10;
+
+fn rve_elf<X: Xlen>(text: &[u32]) -> Vec<u8> {
+    ElfWriter::<X>::new(TEXT)
+        .with_e_flags(EF_RISCV_RVE)
+        .with_segment(TEXT, PF_R | PF_X, guest::code(text))
+        .build()
+}
+
+/// Sums `1..=COUNT` into a0 with a5 (x15) as the counter, then exits with it.
+fn sum_elf<X: Xlen>() -> Vec<u8> {
+    rve_elf::<X>(&[
+        addi(REG_A0, REG_ZERO, 0),
+        addi(REG_A5, REG_ZERO, 0),
+        addi(REG_A4, REG_ZERO, i32::from(COUNT)),
+        // loop: a5 += 1; a0 += a5
+        addi(REG_A5, REG_A5, 1),
+        encode_r(OPCODE_OP, REG_A0, FUNCT3_ADD, REG_A0, REG_A5, 0),
+        encode_b(OPCODE_BRANCH, FUNCT3_BNE, REG_A5, REG_A4, -8),
+        ECALL,
+    ])
+}
+
+fn run_sum<X: Xlen>() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = temp.path().join("rve.elf");
+    std::fs::write(&elf, sum_elf::<X>()).expect("write ELF");
+    let out = temp.path().join("rve");
+    let options = CompileOptions::new().with_quiet(true);
+    rvr::compile_with_options(&elf, &out, &options).expect("compile");
+
+    let header = std::fs::read_to_string(out.join("rve.h")).expect("read header");
+    assert!(header.contains("regs[16];"), "{header}");
+
+    let mut runner = Runner::load(&out, &elf).expect("load runner");
+    assert_eq!(runner.num_regs(), 16);
+    let result = runner.run().expect("run guest");
+    assert_eq!(result.exit_code, (1..=COUNT).sum::<u8>());
+    assert_eq!(runner.get_register(usize::from(REG_A5)), u64::from(COUNT));
+}
+
+#[test]
+fn test_rv32e_round_trip() {
+    run_sum::<Rv32>();
+}
+
+#[test]
+fn test_rv64e_round_trip() {
+    run_sum::<Rv64>();
+}
+
+/// `addi a6, zero, 1` is not an RVE instruction.
+fn lift_out_of_range(config: EmitConfig<Rv32>) -> (Pipeline<Rv32>, rvr::Result<()>) {
+    let elf = rve_elf::<Rv32>(&[addi(REG_A6, REG_ZERO, 1), ECALL]);
+    let image = ElfImage::<Rv32>::parse(&elf).expect("parse ELF");
+    let mut pipeline = Pipeline::new(image, config);
+    pipeline.build_cfg().expect("build CFG");
+    let result = pipeline.lift_to_ir();
+    (pipeline, result)
+}
+
+#[test]
+fn test_rve_register_out_of_range_is_listed() {
+    let (pipeline, result) = lift_out_of_range(EmitConfig::default());
+    result.expect("lift without strict decode");
+
+    let unsupported = pipeline.stats().unsupported;
+    assert_eq!(unsupported.len(), 1);
+    assert_eq!(unsupported[0].kind, LiftFailureKind::RegisterOutOfRange);
+    assert_eq!(unsupported[0].pc, TEXT);
+    assert_eq!(unsupported[0].mnemonic.as_deref(), Some("addi"));
+    assert!(pipeline.config().hot_regs.iter().all(|&reg| reg < 16));
+}
+
+#[test]
+fn test_rve_register_out_of_range_fails_strict_decode() {
+    let (_, result) = lift_out_of_range(EmitConfig::default().with_strict_decode(true));
+    match result {
+        Err(Error::UnsupportedInstructions(unsupported)) => {
+            let message = Error::UnsupportedInstructions(unsupported).to_string();
+            assert!(message.contains("1 RVE"), "{message}");
+            assert!(message.contains("EF_RISCV_RVE"), "{message}");
+        }
+        other => panic!("expected UnsupportedInstructions, got {other:?}"),
+    }
+}
@@ -0,0 +1,210 @@
+//! Single-threaded Linux runtime: the futex, signal and thread-id calls
+//! std makes at startup succeed, `clone` fails with `EAGAIN` so a thread
+//! spawn reports an error, `kill` with signal 0 or an ignored signal
+//! returns 0, and `tgkill` exits with 128 + signal.
+
+use guest::{ECALL, FUNCT3_LD, OPCODE_LOAD, OPCODE_LUI, Text, addi, li};
+use rvr::test_support::guest;
+use rvr::{CompileOptions, ExitReason, RunResult, Runner, SyscallMode};
+use rvr_elf::{ElfWriter, PF_R, PF_W, PF_X};
+use rvr_isa::syscalls::syscall_nr::{
+    SYS_CLONE, SYS_EXIT, SYS_EXIT_GROUP, SYS_FUTEX, SYS_GETTID, SYS_KILL, SYS_RT_SIGACTION,
+    SYS_RT_SIGPROCMASK, SYS_SIGALTSTACK, SYS_TGKILL,
+};
+use rvr_isa::{REG_A0, REG_A1, REG_A2, REG_A3, REG_A7, REG_T1, REG_ZERO, Rv64, encode_i, encode_u};
+
+const FUTEX_WAIT_PRIVATE: i32 = 128;
+const FUTEX_WAKE_PRIVATE: i32 = 129;
+const SIGINT: i32 = 2;
+const SIGABRT: i32 = 6;
+const SIGCHLD: i32 = 17;
+const EAGAIN: i32 = 11;
+const EFAULT: i32 = 14;
+const EDEADLK: i32 = 35;
+const ETIMEDOUT: i32 = 110;
+/// Exit code of a guest killed by `SIGABRT`.
+const SIGABRT_EXIT: u8 = 134;
+
+const TEXT: u64 = 0x1000;
+/// Futex word, zero-initialised.
+const FUTEX: u64 = 0x2_0000;
+/// Zero `struct timespec` after the futex word.
+const TIMEOUT_OFFSET: i32 = 8;
+/// Old `struct sigaction` buffer, filled with ones until the call clears it.
+const OLDACT: u64 = 0x3_0000;
+/// Bytes of a `struct sigaction` on RV64.
+const SIGACTION_BYTES: usize = 24;
+
+/// Syscalls of the guests.
+trait SingleThreadText {
+    /// `futex(FUTEX, op, val, timeout)`, with a zero `struct timespec` as
+    /// the timeout if `timed`.
+    fn futex(&mut self, op: i32, val: i32, timed: bool);
+
+    /// A syscall with every argument zero.
+    fn syscall0(&mut self, num: u64);
+
+    /// `kill(1, signal)`.
+    fn kill(&mut self, signal: i32);
+
+    /// Exit with `step` unless every doubleword at `OLDACT` is zero.
+    fn expect_oldact_zeroed(&mut self, step: i32);
+
+    fn elf(self) -> Vec<u8>;
+}
+
+impl SingleThreadText for Text {
+    fn futex(&mut self, op: i32, val: i32, timed: bool) {
+        let timeout = if timed {
+            addi(REG_A3, REG_A0, TIMEOUT_OFFSET)
+        } else {
+            addi(REG_A3, REG_ZERO, 0)
+        };
+        self.push([
+            encode_u(OPCODE_LUI, REG_A0, u32::try_from(FUTEX >> 12).unwrap()),
+            addi(REG_A1, REG_ZERO, op),
+            addi(REG_A2, REG_ZERO, val),
+            timeout,
+            li(REG_A7, SYS_FUTEX),
+            ECALL,
+        ]);
+    }
+
+    fn syscall0(&mut self, num: u64) {
+        self.push([
+            addi(REG_A0, REG_ZERO, 0),
+            addi(REG_A1, REG_ZERO, 0),
+            addi(REG_A2, REG_ZERO, 0),
+            li(REG_A7, num),
+            ECALL,
+        ]);
+    }
+
+    fn kill(&mut self, signal: i32) {
+        self.push([
+            addi(REG_A0, REG_ZERO, 1),
+            addi(REG_A1, REG_ZERO, signal),
+            li(REG_A7, SYS_KILL),
+            ECALL,
+        ]);
+    }
+
+    fn expect_oldact_zeroed(&mut self, step: i32) {
+        for offset in (0..SIGACTION_BYTES).step_by(8) {
+            self.push([
+                encode_u(OPCODE_LUI, REG_T1, u32::try_from(OLDACT >> 12).unwrap()),
+                encode_i(
+                    OPCODE_LOAD,
+                    REG_A0,
+                    FUNCT3_LD,
+                    REG_T1,
+                    i32::try_from(offset).unwrap(),
+                ),
+            ]);
+            self.expect_a0(step, 0);
+        }
+    }
+
+    fn elf(self) -> Vec<u8> {
+        ElfWriter::<Rv64>::new(TEXT)
+            .with_segment(TEXT, PF_R | PF_X, guest::code(&self.0))
+            .with_segment(FUTEX, PF_R | PF_W, vec![0; 24])
+            .with_segment(OLDACT, PF_R | PF_W, vec![0xff; SIGACTION_BYTES])
+            .build()
+    }
+}
+
+/// Exits 0 if every single-threaded startup call behaves; else the step.
+fn startup_elf() -> Vec<u8> {
+    let mut text = Text::new(SYS_EXIT);
+    // The word is 0, not 1: the wait fails at once
+    text.futex(FUTEX_WAIT_PRIVATE, 1, false);
+    text.expect_a0(1, -EAGAIN);
+    // The word matches: no other thread could wake the wait
+    text.futex(FUTEX_WAIT_PRIVATE, 0, false);
+    text.expect_a0(2, -EDEADLK);
+    text.futex(FUTEX_WAKE_PRIVATE, 1, false);
+    text.expect_a0(3, 0);
+    text.syscall0(SYS_RT_SIGACTION);
+    text.expect_a0(4, 0);
+    text.syscall0(SYS_RT_SIGPROCMASK);
+    text.expect_a0(5, 0);
+    text.syscall0(SYS_SIGALTSTACK);
+    text.expect_a0(6, 0);
+    text.syscall0(SYS_GETTID);
+    text.expect_a0(7, 1);
+    text.syscall0(SYS_CLONE);
+    text.expect_a0(8, -EAGAIN);
+    // A liveness probe and a signal ignored by default do not exit
+    text.kill(0);
+    text.expect_a0(9, 0);
+    text.kill(SIGCHLD);
+    text.expect_a0(10, 0);
+    // rt_sigaction(SIGINT, NULL, OLDACT, 8) reports the default action
+    text.push([
+        addi(REG_A0, REG_ZERO, SIGINT),
+        addi(REG_A1, REG_ZERO, 0),
+        encode_u(OPCODE_LUI, REG_A2, u32::try_from(OLDACT >> 12).unwrap()),
+        addi(REG_A3, REG_ZERO, 8),
+        li(REG_A7, SYS_RT_SIGACTION),
+        ECALL,
+    ]);
+    text.expect_a0(11, 0);
+    text.expect_oldact_zeroed(12);
+    // A futex word outside guest memory faults instead of being read
+    text.push([
+        addi(REG_A0, REG_ZERO, -4),
+        addi(REG_A1, REG_ZERO, FUTEX_WAIT_PRIVATE),
+        addi(REG_A2, REG_ZERO, 0),
+        li(REG_A7, SYS_FUTEX),
+        ECALL,
+    ]);
+    text.expect_a0(13, -EFAULT);
+    // With a timeout, a wait on a matching word times out instead
+    text.futex(FUTEX_WAIT_PRIVATE, 0, true);
+    text.expect_a0(14, -ETIMEDOUT);
+    text.push([addi(REG_A0, REG_ZERO, 0), li(REG_A7, SYS_EXIT_GROUP), ECALL]);
+    text.elf()
+}
+
+/// `tgkill(1, 1, SIGABRT)`, as `abort()` does.
+fn abort_elf() -> Vec<u8> {
+    let mut text = Text::new(SYS_EXIT);
+    text.push([
+        addi(REG_A0, REG_ZERO, 1),
+        addi(REG_A1, REG_ZERO, 1),
+        addi(REG_A2, REG_ZERO, SIGABRT),
+        li(REG_A7, SYS_TGKILL),
+        ECALL,
+        // Not reached
+        addi(REG_A0, REG_ZERO, 0),
+        li(REG_A7, SYS_EXIT),
+        ECALL,
+    ]);
+    text.elf()
+}
+
+fn run(elf_bytes: &[u8]) -> RunResult {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = temp.path().join("guest.elf");
+    std::fs::write(&elf, elf_bytes).expect("write ELF");
+    let out = temp.path().join("out");
+    let options = CompileOptions::new()
+        .with_quiet(true)
+        .with_syscall_mode(SyscallMode::Linux);
+    rvr::compile_with_options(&elf, &out, &options).expect("compile");
+    let mut runner = Runner::load(&out, &elf).expect("load runner");
+    runner.run().expect("run guest")
+}
+
+#[test]
+fn test_single_thread_startup_calls() {
+    let result = run(&startup_elf());
+    assert_eq!(result.exit_reason, ExitReason::Exited(0));
+}
+
+#[test]
+fn test_tgkill_exits_with_signal() {
+    let result = run(&abort_elf());
+    assert_eq!(result.exit_reason, ExitReason::Exited(SIGABRT_EXIT));
+}
@@ -0,0 +1,65 @@
+//! Size report: a library compiled with `with_size_report` has a
+//! `size_report.tsv` attributing emitted C and host code to guest functions.
+
+use guest::{OPCODE_JAL, OPCODE_JALR, OPCODE_OP_IMM, OPCODE_SYSTEM, li};
+use rvr::test_support::guest;
+use rvr::{CompileOptions, Runner, SIZE_REPORT};
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{REG_A0, REG_A7, REG_RA, REG_ZERO, Rv64, encode_i, encode_j};
+
+const TEXT: u64 = 0x1000;
+const FIVE: u64 = TEXT + 12;
+const EXIT_CODE: u8 = 5;
+
+/// `_start` calls `five`, which returns 5, and exits with its result.
+fn guest_elf() -> Vec<u8> {
+    let text = [
+        // _start
+        encode_j(OPCODE_JAL, REG_RA, 3 * 4),
+        li(REG_A7, SYS_EXIT),
+        encode_i(OPCODE_SYSTEM, REG_ZERO, 0, REG_ZERO, 0),
+        // five
+        encode_i(OPCODE_OP_IMM, REG_A0, 0, REG_ZERO, i32::from(EXIT_CODE)),
+        encode_i(OPCODE_JALR, REG_ZERO, 0, REG_RA, 0),
+    ];
+    guest::text_elf::<Rv64>(TEXT, &text)
+        .with_function("_start", TEXT, 12)
+        .with_function("five", FIVE, 8)
+        .build()
+}
+
+#[test]
+fn test_size_report_per_function() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = temp.path().join("five.elf");
+    std::fs::write(&elf, guest_elf()).expect("write ELF");
+    let out = temp.path().join("out");
+    let options = CompileOptions::new()
+        .with_quiet(true)
+        .with_size_report(true);
+    let report = rvr::compile_with_report(&elf, &out, &options).expect("compile");
+
+    let sizes = report.size_report.expect("size report");
+    let mut names: Vec<_> = sizes
+        .functions
+        .iter()
+        .map(|f| (f.name.as_deref(), f.guest_instrs))
+        .collect();
+    names.sort_unstable();
+    assert_eq!(names, [(Some("_start"), 3), (Some("five"), 2)]);
+    for function in &sizes.functions {
+        assert!(function.c_bytes > 0 && function.c_lines > 0);
+        assert!(function.host_bytes.is_some());
+    }
+    // Every block of the guest compiled to some host code
+    let host: u64 = sizes.functions.iter().filter_map(|f| f.host_bytes).sum();
+    assert!(host > 0);
+
+    let tsv = std::fs::read_to_string(out.join(SIZE_REPORT)).expect("read report");
+    assert_eq!(tsv, sizes.render());
+    assert!(tsv.starts_with("pc\tfunction\tblocks\t"));
+    assert_eq!(tsv.lines().count(), 3);
+
+    let mut runner = Runner::load(&out, &elf).expect("load runner");
+    assert_eq!(runner.run().expect("run guest").exit_code, EXIT_CODE);
+}
@@ -0,0 +1,131 @@
+//! Stack guard: a guest recursing past a deliberately tiny stack fails with
+//! a stack overflow diagnosis instead of corrupting memory below it.
+//!
+//! The guest recurses like the towers benchmark, which cannot be used
+//! itself: it sets up its own stack in `crt.S`, ignoring the planned one.
+
+use std::path::Path;
+
+use guest::{
+    ECALL, FUNCT3_BEQ, FUNCT3_D, OPCODE_BRANCH, OPCODE_JAL, OPCODE_JALR, OPCODE_LOAD, OPCODE_STORE,
+    addi, li,
+};
+use rvr::test_support::guest;
+use rvr::{AddressMode, CompileOptions, RunError, Runner, SyscallMode};
+use rvr_elf::{STT_FUNC, STT_NOTYPE};
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{
+    REG_A0, REG_A7, REG_RA, REG_SP, REG_ZERO, Rv64, encode_b, encode_i, encode_j, encode_s,
+};
+
+const NOP: u32 = addi(REG_ZERO, REG_ZERO, 0);
+/// All-zero word: an illegal instruction.
+const ILLEGAL: u32 = 0;
+
+const TEXT: u64 = 0x1000;
+/// Address of the `sd ra` that pushes each frame.
+const PUSH_PC: u64 = TEXT + 5 * 4;
+/// Bytes per frame.
+const FRAME: i32 = 16;
+const STACK_SIZE: u64 = 0x1000;
+/// Inside the range `Bounds` accepts, well above the text.
+const STACK_TOP: u64 = 0x10_0000;
+/// Frames that overflow `STACK_SIZE` and end inside the one-page guard.
+const DEEP: i32 = 300;
+/// Frames that fit in `STACK_SIZE`.
+const SHALLOW: i32 = 100;
+
+/// Recurse `depth` frames, run `bottom` in the deepest one, unwind and exit
+/// with 0.
+fn recursion_elf(depth: i32, bottom: u32) -> Vec<u8> {
+    let text = [
+        // _start: rec(depth); exit(0)
+        addi(REG_A0, REG_ZERO, depth),
+        encode_j(OPCODE_JAL, REG_RA, 12),
+        li(REG_A7, SYS_EXIT),
+        ECALL,
+        // rec: push a frame, recurse while --a0 != 0
+        addi(REG_SP, REG_SP, -FRAME),
+        encode_s(OPCODE_STORE, FUNCT3_D, REG_SP, REG_RA, 8),
+        addi(REG_A0, REG_A0, -1),
+        encode_b(OPCODE_BRANCH, FUNCT3_BEQ, REG_A0, REG_ZERO, 12),
+        encode_j(OPCODE_JAL, REG_RA, -16),
+        encode_j(OPCODE_JAL, REG_ZERO, 8),
+        bottom,
+        // pop the frame and return
+        encode_i(OPCODE_LOAD, REG_RA, FUNCT3_D, REG_SP, 8),
+        addi(REG_SP, REG_SP, FRAME),
+        encode_i(OPCODE_JALR, REG_ZERO, 0, REG_RA, 0),
+    ];
+    guest::text_elf::<Rv64>(TEXT, &text)
+        .with_symbol("_start", TEXT, STT_FUNC)
+        .with_symbol("__stack_top", STACK_TOP, STT_NOTYPE)
+        .build()
+}
+
+/// Compile the guest with a `STACK_SIZE` stack and run it.
+fn run(dir: &Path, elf: &[u8], mode: AddressMode) -> (Runner, Result<u8, RunError>) {
+    let elf_path = dir.join("recurse.elf");
+    std::fs::write(&elf_path, elf).expect("write ELF");
+    let out = dir.join("out");
+    let options = CompileOptions::new()
+        .with_quiet(true)
+        .with_syscall_mode(SyscallMode::Linux)
+        .with_address_mode(mode)
+        .with_stack_size(STACK_SIZE);
+    rvr::compile_with_options(&elf_path, &out, &options).expect("compile");
+    let mut runner = Runner::load(&out, &elf_path).expect("load runner");
+    let result = runner.run().map(|result| result.exit_code);
+    (runner, result)
+}
+
+/// Recurse past the stack in `mode`; the run must fail as a stack overflow
+/// inside the guard, reported at `expected_pc`.
+fn assert_overflow(mode: AddressMode, bottom: u32, expected_pc: Option<u64>) {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let (runner, result) = run(temp.path(), &recursion_elf(DEEP, bottom), mode);
+    let guard = runner
+        .memory_layout()
+        .and_then(|layout| layout.stack_guard)
+        .expect("stack guard");
+
+    let err = result.expect_err("overflow must fail the run");
+    let message = err.to_string();
+    let RunError::StackOverflow { pc, sp, limit } = err else {
+        panic!("expected a stack overflow, got {message}");
+    };
+    assert_eq!(limit, STACK_TOP - STACK_SIZE);
+    assert!(guard.start <= sp && sp < guard.end, "sp {sp:#x}");
+    if let Some(expected) = expected_pc {
+        assert_eq!(pc, expected);
+    }
+    assert!(
+        message.starts_with(&format!(
+            "guest stack overflow (sp={sp:#x}, limit={limit:#x})"
+        )),
+        "{message}"
+    );
+}
+
+#[test]
+fn test_overflow_traps_with_wrap() {
+    assert_overflow(AddressMode::Wrap, NOP, Some(PUSH_PC));
+}
+
+#[test]
+fn test_overflow_traps_with_bounds() {
+    assert_overflow(AddressMode::Bounds, NOP, Some(PUSH_PC));
+}
+
+#[test]
+fn test_unchecked_overflow_diagnosed_on_trap() {
+    assert_overflow(AddressMode::Unchecked, ILLEGAL, None);
+}
+
+#[test]
+fn test_recursion_within_stack_runs() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = recursion_elf(SHALLOW, NOP);
+    let (_, result) = run(temp.path(), &elf, AddressMode::Wrap);
+    assert_eq!(result.expect("run guest"), 0);
+}
@@ -0,0 +1,174 @@
+//! Static archives: a host program that links `lib<name>.a` and drives it
+//! through `rv_embed.h`, and a runner over the program's `rv_embed_info`,
+//! both get the results of the `dlopen` path.
+
+use std::path::Path;
+use std::process::Command;
+
+use guest::{ECALL, FUNCT3_ADD, FUNCT3_BNE, OPCODE_BRANCH, OPCODE_OP, addi, li};
+use libloading::os::unix::{Library, RTLD_NOW};
+use rvr::test_support::guest;
+use rvr::{CompileOptions, Error, RunError, Runner, RvEmbedInfo};
+use rvr_elf::ElfWriter;
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{REG_A0, REG_A1, REG_A7, REG_ZERO, Rv32, Rv64, encode_b, encode_r};
+
+const FUNCT7_ADD: u8 = 0;
+const TERMS: i32 = 10;
+/// `1 + 2 + ... + TERMS`.
+const SUM: u8 = 55;
+
+const TEXT: u64 = 0x1000;
+const NAME: &str = "sum";
+const PREFIX: &str = "sum_";
+
+/// `a0 = 0; for (a1 = TERMS; a1 != 0; a1--) a0 += a1; exit(a0);`
+fn sum_elf() -> Vec<u8> {
+    let text = [
+        addi(REG_A0, REG_ZERO, 0),
+        addi(REG_A1, REG_ZERO, TERMS),
+        // loop:
+        encode_r(OPCODE_OP, REG_A0, FUNCT3_ADD, REG_A0, REG_A1, FUNCT7_ADD),
+        addi(REG_A1, REG_A1, -1),
+        encode_b(OPCODE_BRANCH, FUNCT3_BNE, REG_A1, REG_ZERO, -8),
+        li(REG_A7, SYS_EXIT),
+        ECALL,
+    ];
+    guest::text_elf::<Rv64>(TEXT, &text).build()
+}
+
+/// Host that runs the program from its entry point and prints the exit
+/// code and instruction count.
+const HOST_C: &str = r#"#define _GNU_SOURCE
+#include <stdio.h>
+#include <stdlib.h>
+#include <sys/mman.h>
+
+#include "sum.h"
+#include "rv_embed.h"
+
+int main(void) {
+    const RvEmbedInfo* info = &sum_rv_embed_info;
+    RvState* state = calloc(1, sizeof(RvState));
+    state->memory = mmap(NULL, RV_MEMORY_SIZE, PROT_READ | PROT_WRITE,
+                         MAP_PRIVATE | MAP_ANONYMOUS | MAP_NORESERVE, -1, 0);
+    if (state == NULL || state->memory == MAP_FAILED) {
+        return 1;
+    }
+    if (info->init_memory != NULL) {
+        info->init_memory(state);
+    }
+    info->execute_from(state, RV_ENTRY_POINT);
+    printf("%u %llu\n", state->exit_code, (unsigned long long)state->instret);
+    return 0;
+}
+"#;
+
+fn compile(temp: &Path, options: &CompileOptions) -> (std::path::PathBuf, std::path::PathBuf) {
+    let elf = temp.join("sum.elf");
+    std::fs::write(&elf, sum_elf()).expect("write ELF");
+    let out = temp.join(NAME);
+    rvr::compile_with_options(&elf, &out, &options.clone().with_quiet(true)).expect("compile");
+    (elf, out)
+}
+
+fn static_options() -> CompileOptions {
+    CompileOptions::new()
+        .with_static_archive(true)
+        .with_symbol_prefix(PREFIX)
+}
+
+/// Exit code and instruction count of the `dlopen` path.
+fn dlopen_result(out: &Path, elf: &Path) -> (u8, u64) {
+    let mut runner = Runner::load_with_prefix(out, PREFIX, elf).expect("load runner");
+    let result = runner.run().expect("run guest");
+    (result.exit_code, result.instret)
+}
+
+#[test]
+fn test_static_host_matches_dlopen() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let (elf, out) = compile(temp.path(), &static_options());
+    let archive = out.join(format!("lib{NAME}.a"));
+    assert!(archive.exists());
+    assert!(out.join("rv_embed.h").exists());
+
+    let host = temp.path().join("host");
+    let host_c = temp.path().join("host.c");
+    std::fs::write(&host_c, HOST_C).expect("write host");
+    let cc = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let status = Command::new(cc)
+        .arg("-std=c2x")
+        .arg("-I")
+        .arg(&out)
+        .arg(&host_c)
+        .arg(&archive)
+        .arg("-o")
+        .arg(&host)
+        .status()
+        .expect("run cc");
+    assert!(status.success());
+
+    let output = Command::new(&host).output().expect("run host");
+    assert!(output.status.success());
+    let (exit_code, instret) = dlopen_result(&out, &elf);
+    assert_eq!(exit_code, SUM);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        format!("{exit_code} {instret}\n")
+    );
+}
+
+#[test]
+fn test_load_embedded_matches_dlopen() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let (elf, out) = compile(temp.path(), &static_options());
+
+    // Stands in for a host that links the archive: the library stays
+    // loaded for the rest of the process.
+    let lib = unsafe { Library::open(Some(out.join(format!("lib{NAME}.so"))), RTLD_NOW) }
+        .expect("open library");
+    let lib: &'static Library = Box::leak(Box::new(lib));
+    let info: &'static RvEmbedInfo = unsafe {
+        let symbol = lib
+            .get::<*const RvEmbedInfo>(format!("{PREFIX}rv_embed_info").as_bytes())
+            .expect("rv_embed_info");
+        &**symbol
+    };
+    assert_eq!(info.xlen, 64);
+
+    let mut runner = Runner::load_embedded(info, &elf).expect("load embedded");
+    let result = runner.run().expect("run guest");
+    assert_eq!(
+        (result.exit_code, result.instret),
+        dlopen_result(&out, &elf)
+    );
+
+    let rv32 = temp.path().join("rv32.elf");
+    std::fs::write(&rv32, ElfWriter::<Rv32>::new(TEXT).build()).expect("write ELF");
+    let err = Runner::load_embedded(info, &rv32)
+        .err()
+        .expect("XLEN mismatch");
+    assert!(
+        matches!(
+            err,
+            RunError::XlenMismatch {
+                embedded: 64,
+                elf: 32
+            }
+        ),
+        "{err}"
+    );
+}
+
+#[test]
+fn test_invalid_symbol_prefix() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = temp.path().join("sum.elf");
+    std::fs::write(&elf, sum_elf()).expect("write ELF");
+    let options = CompileOptions::new()
+        .with_quiet(true)
+        .with_symbol_prefix("my-prog");
+    let err = rvr::compile_with_options(&elf, &temp.path().join(NAME), &options).unwrap_err();
+    assert!(matches!(err, Error::InvalidSymbolPrefix(_)), "{err}");
+}
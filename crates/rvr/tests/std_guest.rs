@@ -0,0 +1,301 @@
+//! Rust std guests under `--syscalls linux`: hello world runs to
+//! completion, and `std::thread::spawn` panics instead of starting a second
+//! thread.
+//!
+//! Hand-assembled guests replay the syscalls a static std binary makes at
+//! startup, in hello world and in a failed spawn, and always run. The
+//! toolchain-built guests in `programs/std-hello` need the
+//! `riscv64gc-unknown-linux-gnu` std and a `riscv64-linux-gnu-gcc` cross
+//! linker, so those tests are ignored by default; run them with
+//! `cargo test -p rvr --test std_guest -- --ignored`.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use guest::{
+    ECALL, FUNCT3_ADD, FUNCT3_BEQ, FUNCT3_BLT, OPCODE_OP, SharedWriter, Text, addi, li, lui,
+};
+use rvr::test_support::guest;
+use rvr::{CompileOptions, ExitReason, RunResult, Runner, SyscallMode};
+use rvr_elf::{ElfWriter, PF_R, PF_W, PF_X};
+use rvr_isa::syscalls::syscall_nr::{
+    SYS_BRK, SYS_CLONE, SYS_EXIT_GROUP, SYS_FUTEX, SYS_MMAP, SYS_MUNMAP, SYS_RSEQ,
+    SYS_RT_SIGACTION, SYS_SET_TID_ADDRESS, SYS_SIGALTSTACK, SYS_WRITE,
+};
+use rvr_isa::{
+    REG_A0, REG_A1, REG_A2, REG_A3, REG_A4, REG_A5, REG_A7, REG_S0, REG_T1, REG_ZERO, Rv64,
+    encode_r,
+};
+
+const TARGET: &str = "riscv64gc-unknown-linux-gnu";
+const LINKER: &str = "riscv64-linux-gnu-gcc";
+/// Exit code of a Rust process whose main thread panicked.
+const PANIC_EXIT: u8 = 101;
+
+const HELLO: &[u8] = b"Hello, world!\n";
+/// What std prints when `clone` fails with `EAGAIN`.
+const SPAWN_PANIC: &[u8] = b"thread 'main' panicked at src/bin/spawn.rs:5:18:\n\
+failed to spawn thread: Os { code: 11, kind: WouldBlock, message: \"Resource temporarily unavailable\" }\n";
+const SIGPIPE: i32 = 13;
+const FUTEX_WAKE_PRIVATE: i32 = 129;
+const PROT_READ_WRITE: i32 = 3;
+const MAP_PRIVATE_ANONYMOUS: i32 = 0x22;
+/// `CLONE_VM | CLONE_FS | CLONE_FILES | CLONE_SIGHAND | CLONE_THREAD |
+/// CLONE_SYSVSEM | CLONE_SETTLS | CLONE_PARENT_SETTID |
+/// CLONE_CHILD_CLEARTID`, as glibc's `pthread_create` passes it.
+const CLONE_THREAD_FLAGS: u64 = 0x003d_0f00;
+const ENOSYS: i32 = 38;
+const EAGAIN: i32 = 11;
+/// Bytes of the alternate signal stack and of a spawned thread's stack.
+const STACK_BYTES: u64 = 0x4000;
+
+const TEXT: u64 = 0x1000;
+/// Data page: the messages, then the words and structs std passes by
+/// pointer at the offsets below.
+const DATA: u64 = 0x2_0000;
+const PANIC_OFFSET: i32 = 0x40;
+const TID_OFFSET: i32 = 0x100;
+const ONCE_OFFSET: i32 = 0x108;
+/// `struct sigaction` with `SIG_IGN`, then room for the old one.
+const SIGACTION_OFFSET: i32 = 0x200;
+const OLDACT_OFFSET: i32 = 0x240;
+const STACK_T_OFFSET: i32 = 0x300;
+
+/// Build `bin` from `programs/std-hello` as a static RISC-V Linux ELF.
+fn build(bin: &str) -> PathBuf {
+    let project = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../programs/std-hello");
+    let status = Command::new("cargo")
+        .arg("build")
+        .arg("--release")
+        .arg("--target")
+        .arg(TARGET)
+        .arg("--bin")
+        .arg(bin)
+        .arg("--manifest-path")
+        .arg(project.join("Cargo.toml"))
+        .env("CARGO_TARGET_RISCV64GC_UNKNOWN_LINUX_GNU_LINKER", LINKER)
+        .env("RUSTFLAGS", "-Ctarget-feature=+crt-static")
+        .status()
+        .expect("run cargo");
+    assert!(status.success(), "building {bin} for {TARGET} failed");
+    project
+        .join("target")
+        .join(TARGET)
+        .join("release")
+        .join(bin)
+}
+
+/// Startup and I/O calls of the guests, with the data page at `DATA`.
+trait StdText {
+    /// `rd = DATA + offset`.
+    fn data(&mut self, rd: u8, offset: i32);
+
+    /// `num(a0..)`, with the arguments already in place.
+    fn syscall(&mut self, num: u64);
+
+    /// `mmap(NULL, STACK_BYTES, PROT_READ | PROT_WRITE, MAP_PRIVATE |
+    /// MAP_ANONYMOUS, -1, 0)` into `s0`, exiting with `step` on an error.
+    fn map_stack(&mut self, step: i32);
+
+    /// `write(fd, DATA + offset, len)`, exiting with `step` unless all of it
+    /// is written.
+    fn write(&mut self, step: i32, fd: i32, offset: i32, len: usize);
+
+    /// `exit_group(code)`.
+    fn exit(&mut self, code: u8);
+
+    /// Startup of a static std binary: glibc's thread and heap setup, then
+    /// std ignoring `SIGPIPE`, installing its overflow alternate stack and
+    /// running a `Once`.
+    fn startup(&mut self);
+
+    fn elf(&self) -> Vec<u8>;
+}
+
+impl StdText for Text {
+    fn data(&mut self, rd: u8, offset: i32) {
+        self.push([lui(rd, DATA), addi(rd, rd, offset)]);
+    }
+
+    fn syscall(&mut self, num: u64) {
+        self.push([li(REG_A7, num), ECALL]);
+    }
+
+    fn map_stack(&mut self, step: i32) {
+        self.push([
+            addi(REG_A0, REG_ZERO, 0),
+            lui(REG_A1, STACK_BYTES),
+            addi(REG_A2, REG_ZERO, PROT_READ_WRITE),
+            addi(REG_A3, REG_ZERO, MAP_PRIVATE_ANONYMOUS),
+            addi(REG_A4, REG_ZERO, -1),
+            addi(REG_A5, REG_ZERO, 0),
+        ]);
+        self.syscall(SYS_MMAP);
+        self.fail_if(step, FUNCT3_BLT, REG_A0, REG_ZERO);
+        self.push([addi(REG_S0, REG_A0, 0)]);
+    }
+
+    fn write(&mut self, step: i32, fd: i32, offset: i32, len: usize) {
+        let len = i32::try_from(len).unwrap();
+        self.push([addi(REG_A0, REG_ZERO, fd)]);
+        self.data(REG_A1, offset);
+        self.push([addi(REG_A2, REG_ZERO, len)]);
+        self.syscall(SYS_WRITE);
+        self.expect_a0(step, len);
+    }
+
+    fn exit(&mut self, code: u8) {
+        self.push([addi(REG_A0, REG_ZERO, i32::from(code))]);
+        self.syscall(SYS_EXIT_GROUP);
+    }
+
+    fn startup(&mut self) {
+        self.data(REG_A0, TID_OFFSET);
+        self.syscall(SYS_SET_TID_ADDRESS);
+        self.expect_a0(1, 1);
+        // glibc runs without restartable sequences
+        self.syscall(SYS_RSEQ);
+        self.expect_a0(2, -ENOSYS);
+        self.push([addi(REG_A0, REG_ZERO, 0)]);
+        self.syscall(SYS_BRK);
+        self.fail_if(3, FUNCT3_BEQ, REG_A0, REG_ZERO);
+
+        self.push([addi(REG_A0, REG_ZERO, SIGPIPE)]);
+        self.data(REG_A1, SIGACTION_OFFSET);
+        self.data(REG_A2, OLDACT_OFFSET);
+        self.push([addi(REG_A3, REG_ZERO, 8)]);
+        self.syscall(SYS_RT_SIGACTION);
+        self.expect_a0(4, 0);
+
+        // No alternate stack yet, so std maps one and installs it
+        self.push([addi(REG_A0, REG_ZERO, 0)]);
+        self.data(REG_A1, STACK_T_OFFSET);
+        self.syscall(SYS_SIGALTSTACK);
+        self.expect_a0(5, 0);
+        self.map_stack(6);
+        self.data(REG_A0, STACK_T_OFFSET);
+        self.push([addi(REG_A1, REG_ZERO, 0)]);
+        self.syscall(SYS_SIGALTSTACK);
+        self.expect_a0(7, 0);
+
+        self.data(REG_A0, ONCE_OFFSET);
+        self.push([addi(REG_A1, REG_ZERO, FUTEX_WAKE_PRIVATE)]);
+        self.push([addi(REG_A2, REG_ZERO, 1)]);
+        self.syscall(SYS_FUTEX);
+        self.expect_a0(8, 0);
+    }
+
+    fn elf(&self) -> Vec<u8> {
+        let mut data = vec![0; 0x400];
+        data[..HELLO.len()].copy_from_slice(HELLO);
+        let panic = usize::try_from(PANIC_OFFSET).unwrap();
+        data[panic..panic + SPAWN_PANIC.len()].copy_from_slice(SPAWN_PANIC);
+        // sa_handler = SIG_IGN
+        data[usize::try_from(SIGACTION_OFFSET).unwrap()] = 1;
+        ElfWriter::<Rv64>::new(TEXT)
+            .with_segment(TEXT, PF_R | PF_X, guest::code(&self.0))
+            .with_segment(DATA, PF_R | PF_W, data)
+            .build()
+    }
+}
+
+/// Hello world: startup, one `println!` and a clean exit. Exits with the
+/// failed step if a call misbehaves.
+fn hello_elf() -> Vec<u8> {
+    let mut text = Text::new(SYS_EXIT_GROUP);
+    text.startup();
+    text.write(9, 1, 0, HELLO.len());
+    text.exit(0);
+    text.elf()
+}
+
+/// `thread::spawn`: startup, then the thread stack `pthread_create` maps
+/// and the `clone` that fails, after which std unmaps the stack, prints
+/// the panic and exits with 101. Exits with the failed step if a call
+/// misbehaves.
+fn spawn_elf() -> Vec<u8> {
+    let mut text = Text::new(SYS_EXIT_GROUP);
+    text.startup();
+    text.map_stack(9);
+    // clone(flags, stack top, ...)
+    let high = (CLONE_THREAD_FLAGS + 0x800) & !0xfff;
+    let low = i32::try_from(CLONE_THREAD_FLAGS.cast_signed() - high.cast_signed()).unwrap();
+    text.push([
+        lui(REG_A0, high),
+        addi(REG_A0, REG_A0, low),
+        lui(REG_T1, STACK_BYTES),
+        encode_r(OPCODE_OP, REG_A1, FUNCT3_ADD, REG_S0, REG_T1, 0),
+    ]);
+    text.syscall(SYS_CLONE);
+    text.expect_a0(10, -EAGAIN);
+    text.push([addi(REG_A0, REG_S0, 0), lui(REG_A1, STACK_BYTES)]);
+    text.syscall(SYS_MUNMAP);
+    text.expect_a0(11, 0);
+    text.write(12, 2, PANIC_OFFSET, SPAWN_PANIC.len());
+    text.exit(PANIC_EXIT);
+    text.elf()
+}
+
+/// Compile and run `elf`; return the result and the guest's stdout and
+/// stderr.
+fn run_elf(elf: &Path, out: &Path) -> (RunResult, Vec<u8>, Vec<u8>) {
+    let options = CompileOptions::new()
+        .with_quiet(true)
+        .with_syscall_mode(SyscallMode::Linux);
+    rvr::compile_with_options(elf, out, &options).expect("compile");
+
+    let (stdout, stderr) = (SharedWriter::default(), SharedWriter::default());
+    let mut runner = Runner::load(out, elf).expect("load runner");
+    runner.set_stdout(stdout.clone());
+    runner.set_stderr(stderr.clone());
+    let result = runner.run().expect("run guest");
+    drop(runner);
+    (result, stdout.contents(), stderr.contents())
+}
+
+/// Compile and run the hand-assembled `elf`.
+fn run_assembled(elf: &[u8]) -> (RunResult, Vec<u8>, Vec<u8>) {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let path = temp.path().join("guest.elf");
+    std::fs::write(&path, elf).expect("write ELF");
+    run_elf(&path, &temp.path().join("out"))
+}
+
+/// Build, compile and run `bin`; return the result and the guest's stdout.
+fn run(bin: &str) -> (RunResult, Vec<u8>) {
+    let elf = build(bin);
+    let temp = tempfile::tempdir().expect("tempdir");
+    let (result, stdout, _) = run_elf(&elf, &temp.path().join(bin));
+    (result, stdout)
+}
+
+#[test]
+fn test_assembled_hello_world() {
+    let (result, stdout, stderr) = run_assembled(&hello_elf());
+    assert_eq!(result.exit_reason, ExitReason::Exited(0));
+    assert_eq!(stdout, HELLO);
+    assert!(stderr.is_empty());
+}
+
+#[test]
+fn test_assembled_thread_spawn_panics() {
+    let (result, stdout, stderr) = run_assembled(&spawn_elf());
+    assert_eq!(result.exit_reason, ExitReason::Exited(PANIC_EXIT));
+    assert!(stdout.is_empty());
+    assert_eq!(stderr, SPAWN_PANIC);
+}
+
+#[test]
+#[ignore = "needs the riscv64gc-unknown-linux-gnu std and a riscv64-linux-gnu-gcc linker"]
+fn test_std_hello_world() {
+    let (result, stdout) = run("hello");
+    assert_eq!(result.exit_reason, ExitReason::Exited(0));
+    assert_eq!(stdout, HELLO);
+}
+
+#[test]
+#[ignore = "needs the riscv64gc-unknown-linux-gnu std and a riscv64-linux-gnu-gcc linker"]
+fn test_std_thread_spawn_panics() {
+    let (result, _) = run("spawn");
+    assert_eq!(result.exit_reason, ExitReason::Exited(PANIC_EXIT));
+}
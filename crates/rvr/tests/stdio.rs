@@ -0,0 +1,114 @@
+//! Guest stdio redirected through `Runner::set_stdin`/`set_stdout`/`set_stderr`.
+//!
+//! The guest copies stdin to stdout in 3-page reads into a buffer that
+//! starts mid-page, then writes a marker to stderr at EOF.
+
+use std::io::{self, Cursor, Read};
+use std::path::{Path, PathBuf};
+
+use guest::{ECALL, FUNCT3_BEQ, OPCODE_BRANCH, OPCODE_JAL, OPCODE_LUI, SharedWriter, addi, li};
+use rvr::test_support::guest;
+use rvr::{CompileOptions, Runner, SyscallMode};
+use rvr_elf::{PF_R, PF_W};
+use rvr_isa::syscalls::syscall_nr::{SYS_EXIT, SYS_READ, SYS_WRITE};
+use rvr_isa::{REG_A0, REG_A1, REG_A2, REG_A7, REG_ZERO, Rv64, encode_b, encode_j, encode_u};
+
+const TEXT: u64 = 0x1000;
+/// Data page: the EOF marker, then the copy buffer at `+0x400`.
+const DATA: u64 = 0x1_0000;
+const EOF_MARKER: &[u8] = b"eof\n";
+
+fn stdio_elf() -> Vec<u8> {
+    let text = [
+        // loop: n = read(0, DATA + 0x400, 3 pages)
+        li(REG_A7, SYS_READ),
+        addi(REG_A0, REG_ZERO, 0),
+        encode_u(OPCODE_LUI, REG_A1, 0x10),
+        addi(REG_A1, REG_A1, 0x400),
+        encode_u(OPCODE_LUI, REG_A2, 3),
+        ECALL,
+        // if n == 0 goto eof
+        encode_b(OPCODE_BRANCH, FUNCT3_BEQ, REG_A0, REG_ZERO, 24),
+        // write(1, DATA + 0x400, n); goto loop
+        addi(REG_A2, REG_A0, 0),
+        addi(REG_A0, REG_ZERO, 1),
+        li(REG_A7, SYS_WRITE),
+        ECALL,
+        encode_j(OPCODE_JAL, REG_ZERO, -44),
+        // eof: write(2, DATA, 4); exit(0)
+        addi(REG_A0, REG_ZERO, 2),
+        encode_u(OPCODE_LUI, REG_A1, 0x10),
+        li(REG_A7, SYS_WRITE),
+        addi(REG_A2, REG_ZERO, 4),
+        ECALL,
+        addi(REG_A0, REG_ZERO, 0),
+        li(REG_A7, SYS_EXIT),
+        ECALL,
+    ];
+    guest::text_elf::<Rv64>(TEXT, &text)
+        .with_segment(DATA, PF_R | PF_W, EOF_MARKER.to_vec())
+        .build()
+}
+
+/// Reader that hands out at most `chunk` bytes per call.
+struct Trickle {
+    data: Cursor<Vec<u8>>,
+    chunk: usize,
+}
+
+impl Read for Trickle {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = buf.len().min(self.chunk);
+        self.data.read(&mut buf[..len])
+    }
+}
+
+fn compile(dir: &Path) -> (PathBuf, PathBuf) {
+    let elf = dir.join("stdio.elf");
+    std::fs::write(&elf, stdio_elf()).expect("write ELF");
+    let out = dir.join("stdio");
+    let options = CompileOptions::new()
+        .with_quiet(true)
+        .with_syscall_mode(SyscallMode::Linux);
+    rvr::compile_with_options(&elf, &out, &options).expect("compile");
+    (out, elf)
+}
+
+/// Run the guest on `stdin`, returning its stdout and stderr.
+fn run(lib: &Path, elf: &Path, stdin: impl Read + Send + 'static) -> (Vec<u8>, Vec<u8>) {
+    let (stdout, stderr) = (SharedWriter::default(), SharedWriter::default());
+    let mut runner = Runner::load(lib, elf).expect("load runner");
+    runner.set_stdin(stdin);
+    runner.set_stdout(stdout.clone());
+    runner.set_stderr(stderr.clone());
+    let result = runner.run().expect("run guest");
+    assert_eq!(result.exit_code, 0);
+    (stdout.contents(), stderr.contents())
+}
+
+#[test]
+fn test_guest_stdio_redirected() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let (lib, elf) = compile(temp.path());
+    // Larger than one 3-page read, so writes cross several page boundaries
+    let input: Vec<u8> = (0..=250).cycle().take(20_000).collect();
+
+    // Immediate EOF
+    let (stdout, stderr) = run(&lib, &elf, io::empty());
+    assert!(stdout.is_empty());
+    assert_eq!(stderr, EOF_MARKER);
+
+    // Full reads
+    let (stdout, stderr) = run(&lib, &elf, Cursor::new(input.clone()));
+    assert_eq!(stdout, input);
+    assert_eq!(stderr, EOF_MARKER);
+
+    // Short reads
+    let trickle = Trickle {
+        data: Cursor::new(input.clone()),
+        chunk: 1000,
+    };
+    let (stdout, stderr) = run(&lib, &elf, trickle);
+    assert_eq!(stdout, input);
+    assert_eq!(stderr, EOF_MARKER);
+}
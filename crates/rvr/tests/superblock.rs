@@ -0,0 +1,70 @@
+//! Block size limits: a long straight-line guest is split into blocks of at
+//! most `superblock_max_instrs` instructions and still runs correctly.
+
+use guest::{ECALL, FUNCT3_ADDI, OPCODE_OP_IMM, li};
+use rvr::test_support::guest;
+use rvr::{
+    BlockSizeHistogram, CompileOptions, ElfImage, EmitConfig, Pipeline, PipelineStats, Runner,
+};
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{REG_A0, REG_A7, Rv64, encode_i};
+
+const TEXT: u64 = 0x1000;
+/// Number of `addi a0, a0, 1` instructions; the exit code is this mod 256.
+const INCREMENTS: usize = 1000;
+const MAX_INSTRS: usize = 128;
+
+/// `INCREMENTS` x `addi a0, a0, 1`, then `exit(a0)`, as one basic block.
+fn straight_line_elf() -> Vec<u8> {
+    let mut text = vec![encode_i(OPCODE_OP_IMM, REG_A0, FUNCT3_ADDI, REG_A0, 1); INCREMENTS];
+    text.extend([li(REG_A7, SYS_EXIT), ECALL]);
+    guest::text_elf::<Rv64>(TEXT, &text).build()
+}
+
+fn lift_stats(config: EmitConfig<Rv64>) -> PipelineStats {
+    let image = ElfImage::parse(&straight_line_elf()).expect("parse ELF");
+    let mut pipeline = Pipeline::<Rv64>::new(image, config);
+    pipeline.build_cfg().expect("build CFG");
+    pipeline.lift_to_ir().expect("lift");
+    pipeline.stats()
+}
+
+#[test]
+fn test_straight_line_split_at_limit() {
+    let total = INCREMENTS + 2;
+
+    let stats = lift_stats(EmitConfig::default());
+    assert_eq!(stats.num_blocks, 1);
+    assert_eq!(stats.block_sizes.max, total);
+
+    for config in [
+        EmitConfig::default().with_superblock_max_instrs(MAX_INSTRS),
+        EmitConfig::default()
+            .with_superblock(false)
+            .with_superblock_max_instrs(MAX_INSTRS),
+    ] {
+        let stats = lift_stats(config);
+        assert_eq!(stats.num_blocks, total.div_ceil(MAX_INSTRS));
+        assert_eq!(stats.block_sizes.max, MAX_INSTRS);
+        let full = BlockSizeHistogram::bucket(MAX_INSTRS);
+        let rest = BlockSizeHistogram::bucket(total % MAX_INSTRS);
+        assert_eq!(stats.block_sizes.counts[full], total / MAX_INSTRS);
+        assert_eq!(stats.block_sizes.counts[rest], 1);
+    }
+}
+
+#[test]
+fn test_split_guest_runs() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = temp.path().join("straight.elf");
+    std::fs::write(&elf, straight_line_elf()).expect("write ELF");
+    let out = temp.path().join("straight");
+    let options = CompileOptions::new()
+        .with_quiet(true)
+        .with_superblock_max_instrs(MAX_INSTRS);
+    rvr::compile_with_options(&elf, &out, &options).expect("compile");
+
+    let mut runner = Runner::load(&out, &elf).expect("load runner");
+    let result = runner.run().expect("run guest");
+    assert_eq!(usize::from(result.exit_code), INCREMENTS % 256);
+}
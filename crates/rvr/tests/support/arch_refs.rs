@@ -0,0 +1,189 @@
+//! Content-addressed store for riscv-arch-test reference signatures.
+//!
+//! References live at `<dir>/<sha256 of ELF>.sig`, so a rebuilt ELF never
+//! matches a reference produced for its previous build. `<dir>/index.txt`
+//! maps test names (`<category>/<elf name>`) to hashes in `sha256sum` format,
+//! which is what tells a stale reference apart from a missing one.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use parking_lot::Mutex;
+use sha2::{Digest, Sha256};
+
+/// Index file mapping test names to ELF hashes.
+pub const INDEX_FILE: &str = "index.txt";
+
+/// Hex digits of a hash shown in diagnostics.
+const SHORT_HASH_LEN: usize = 12;
+
+/// Where a test's reference stands relative to its current ELF.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RefStatus {
+    /// A reference exists for the current ELF contents.
+    Fresh(PathBuf),
+    /// The index records a reference for different ELF contents.
+    Stale { recorded: String, actual: String },
+    /// No reference has ever been recorded for this test.
+    Missing { actual: String },
+}
+
+/// Reference signatures keyed by ELF content hash.
+pub struct RefStore {
+    dir: PathBuf,
+    index: Mutex<BTreeMap<String, String>>,
+}
+
+impl RefStore {
+    /// Open the store in `dir`, loading its index if present.
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self, String> {
+        let dir = dir.into();
+        let index_path = dir.join(INDEX_FILE);
+        let index = match fs::read_to_string(&index_path) {
+            Ok(text) => parse_index(&text),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(format!("failed to read {}: {e}", index_path.display())),
+        };
+        Ok(Self {
+            dir,
+            index: Mutex::new(index),
+        })
+    }
+
+    /// Path of the reference for ELF contents hashing to `hash`.
+    pub fn sig_path(&self, hash: &str) -> PathBuf {
+        self.dir.join(format!("{hash}.sig"))
+    }
+
+    /// Classify the reference for `name`, adopting a legacy
+    /// `<dir>/<name>.sig` reference the first time the test is seen.
+    pub fn lookup(&self, name: &str, elf: &Path) -> Result<RefStatus, String> {
+        let actual = hash_file(elf)?;
+        let sig = self.sig_path(&actual);
+        let recorded = self.index.lock().get(name).cloned();
+        if sig.exists() {
+            if recorded.as_ref() != Some(&actual) {
+                self.record(name, actual)?;
+            }
+            return Ok(RefStatus::Fresh(sig));
+        }
+        if let Some(recorded) = recorded {
+            return Ok(RefStatus::Stale { recorded, actual });
+        }
+        let legacy = self.dir.join(format!("{name}.sig"));
+        if !legacy.exists() {
+            return Ok(RefStatus::Missing { actual });
+        }
+        // Another test sharing this ELF may have adopted it first.
+        if let Err(e) = fs::rename(&legacy, &sig)
+            && !sig.exists()
+        {
+            return Err(format!(
+                "failed to adopt legacy reference {}: {e}",
+                legacy.display()
+            ));
+        }
+        self.record(name, actual)?;
+        Ok(RefStatus::Fresh(sig))
+    }
+
+    /// Produce the reference for `elf` with `generate(elf, sig_path)` and
+    /// record it under `name`, replacing any previous entry.
+    pub fn generate<F>(&self, name: &str, elf: &Path, generate: F) -> Result<PathBuf, String>
+    where
+        F: FnOnce(&Path, &Path) -> Result<(), String>,
+    {
+        let actual = hash_file(elf)?;
+        fs::create_dir_all(&self.dir).map_err(|e| format!("failed to create refs dir: {e}"))?;
+        let sig = self.sig_path(&actual);
+        let partial = tempfile::Builder::new()
+            .suffix(".sig.partial")
+            .tempfile_in(&self.dir)
+            .map_err(|e| format!("failed to create reference: {e}"))?;
+        generate(elf, partial.path())?;
+        partial
+            .persist(&sig)
+            .map_err(|e| format!("failed to store reference: {e}"))?;
+        self.record(name, actual)?;
+        Ok(sig)
+    }
+
+    /// Resolve the reference for `name`, regenerating a stale or missing one
+    /// with `regenerate` when given and reporting it otherwise.
+    pub fn resolve<F>(
+        &self,
+        name: &str,
+        elf: &Path,
+        regenerate: Option<F>,
+    ) -> Result<PathBuf, String>
+    where
+        F: FnOnce(&Path, &Path) -> Result<(), String>,
+    {
+        let status = self.lookup(name, elf)?;
+        if let RefStatus::Fresh(sig) = status {
+            return Ok(sig);
+        }
+        if let Some(regenerate) = regenerate {
+            return self.generate(name, elf, regenerate);
+        }
+        Err(match status {
+            RefStatus::Stale { recorded, actual } => format!(
+                "stale reference for {name}: recorded for ELF {}, now {} \
+                 (rebuilt ELF; regenerate references with Spike)",
+                short_hash(&recorded),
+                short_hash(&actual)
+            ),
+            RefStatus::Missing { actual } => format!(
+                "missing reference for {name} (ELF {}); regenerate references with Spike",
+                short_hash(&actual)
+            ),
+            RefStatus::Fresh(_) => unreachable!("fresh references return early"),
+        })
+    }
+
+    /// Map `name` to `hash` and rewrite the index.
+    // Holding the lock across the write keeps concurrent writers ordered.
+    #[allow(clippy::significant_drop_tightening)]
+    fn record(&self, name: &str, hash: String) -> Result<(), String> {
+        let mut index = self.index.lock();
+        index.insert(name.to_string(), hash);
+        write_index(&self.dir.join(INDEX_FILE), &index)
+    }
+}
+
+/// Write `index` to `path` in `sha256sum` format.
+fn write_index(path: &Path, index: &BTreeMap<String, String>) -> Result<(), String> {
+    let text = index.iter().fold(String::new(), |mut text, (name, hash)| {
+        let _ = writeln!(text, "{hash}  {name}");
+        text
+    });
+    let partial = path.with_extension("txt.partial");
+    fs::write(&partial, text)
+        .and_then(|()| fs::rename(&partial, path))
+        .map_err(|e| format!("failed to write {}: {e}", path.display()))
+}
+
+/// Lowercase hex SHA-256 of the file at `path`.
+pub fn hash_file(path: &Path) -> Result<String, String> {
+    let bytes = fs::read(path).map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+    Ok(Sha256::digest(&bytes)
+        .iter()
+        .fold(String::new(), |mut hex, b| {
+            let _ = write!(hex, "{b:02x}");
+            hex
+        }))
+}
+
+fn short_hash(hash: &str) -> &str {
+    &hash[..hash.len().min(SHORT_HASH_LEN)]
+}
+
+fn parse_index(text: &str) -> BTreeMap<String, String> {
+    text.lines()
+        .filter_map(|line| line.split_once("  "))
+        .map(|(hash, name)| (name.trim().to_string(), hash.trim().to_string()))
+        .collect()
+}
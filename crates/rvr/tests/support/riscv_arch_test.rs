@@ -8,6 +8,8 @@ use std::time::Duration;
 use rvr::{CompileOptions, Compiler, Runner, build_utils, compile_with_options};
 use rvr_emit::Backend;
 
+use crate::arch_refs::RefStore;
+
 /// Maximum signature region size (64KB should be enough for any test).
 const MAX_SIG_SIZE: usize = 0x10000;
 
@@ -56,6 +58,14 @@ impl ArchTestCategory {
         Self::Rv32iZicond,
     ];
 
+    /// Category whose `out_subdir` is `subdir`.
+    pub fn from_out_subdir(subdir: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|category| category.out_subdir() == subdir)
+    }
+
     pub const fn src_subdir(self) -> &'static str {
         match self {
             Self::Rv64iI => "rv64i_m/I",
@@ -166,9 +176,18 @@ pub fn find_spike() -> Option<String> {
     None
 }
 
+/// Reference store name of an arch-test ELF: `<category>/<elf name>`.
+pub fn reference_name(elf_path: &Path) -> Option<(ArchTestCategory, String)> {
+    let name = elf_path.file_name()?.to_str()?;
+    let subdir = elf_path.parent()?.file_name()?.to_str()?;
+    let category = ArchTestCategory::from_out_subdir(subdir)?;
+    Some((category, format!("{subdir}/{name}")))
+}
+
 pub fn run_test(
     elf_path: &Path,
-    ref_path: &Path,
+    refs: &RefStore,
+    auto_refs: bool,
     timeout: Duration,
     compiler: &Compiler,
     backend: Backend,
@@ -183,9 +202,11 @@ pub fn run_test(
         return Ok(());
     }
 
-    if !ref_path.exists() {
-        return Err("missing reference signature".to_string());
-    }
+    let (category, ref_name) =
+        reference_name(elf_path).ok_or("ELF is not under a category directory")?;
+    let regenerate =
+        auto_refs.then_some(|elf: &Path, sig: &Path| generate_reference(elf, sig, category));
+    let ref_path = refs.resolve(&ref_name, elf_path, regenerate)?;
 
     let temp_dir = tempfile::tempdir().map_err(|e| format!("temp dir failed: {e}"))?;
     let out_dir = temp_dir.path().join("out");
@@ -201,7 +222,7 @@ pub fn run_test(
 
     let signature = run_and_extract_signature(&out_dir, elf_path, timeout)?;
     let reference =
-        fs::read_to_string(ref_path).map_err(|e| format!("failed to read reference: {e}"))?;
+        fs::read_to_string(&ref_path).map_err(|e| format!("failed to read reference: {e}"))?;
 
     if compare_signatures(&signature, &reference) {
         Ok(())
@@ -306,9 +327,10 @@ pub fn build_tests(config: &ArchBuildConfig) -> Result<(), String> {
         return Err("Spike not found".to_string());
     }
 
+    let refs = RefStore::open(&config.refs_dir)?;
     let mut failures = 0usize;
     for &category in &config.categories {
-        if let Err(e) = build_category(category, config) {
+        if let Err(e) = build_category(category, config, &refs) {
             eprintln!("  {}: {}", category.out_subdir(), e);
             failures += 1;
         }
@@ -321,19 +343,19 @@ pub fn build_tests(config: &ArchBuildConfig) -> Result<(), String> {
     }
 }
 
-fn build_category(category: ArchTestCategory, config: &ArchBuildConfig) -> Result<(), String> {
+fn build_category(
+    category: ArchTestCategory,
+    config: &ArchBuildConfig,
+    refs: &RefStore,
+) -> Result<(), String> {
     let src_dir = config.src_dir.join(category.src_subdir());
     let out_dir = config.out_dir.join(category.out_subdir());
-    let refs_dir = config.refs_dir.join(category.out_subdir());
 
     if !src_dir.exists() {
         return Err(format!("source directory not found: {}", src_dir.display()));
     }
 
     fs::create_dir_all(&out_dir).map_err(|e| format!("failed to create output dir: {e}"))?;
-    if config.gen_refs {
-        fs::create_dir_all(&refs_dir).map_err(|e| format!("failed to create refs dir: {e}"))?;
-    }
 
     let (march, mabi) = category.march_mabi();
     let gcc = format!("{}gcc", config.toolchain);
@@ -379,8 +401,11 @@ fn build_category(category: ArchTestCategory, config: &ArchBuildConfig) -> Resul
         }
 
         if config.gen_refs {
-            let ref_path = refs_dir.join(format!("{out_name}.sig"));
-            if let Err(e) = generate_reference(&out_path, &ref_path, category) {
+            let ref_name = format!("{}/{out_name}", category.out_subdir());
+            let generated = refs.generate(&ref_name, &out_path, |elf, sig| {
+                generate_reference(elf, sig, category)
+            });
+            if let Err(e) = generated {
                 eprintln!("  {out_name}: {e}");
                 failed += 1;
             }
@@ -3,10 +3,15 @@
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Duration;
 
-use rvr::{CompileOptions, Compiler, Runner, build_utils, compile_with_options};
-use rvr_emit::Backend;
+use rvr::{CompileOptions, ExitReason, Runner, build_utils, compile_with_options};
+
+/// Tests that stopped on a stalled HTIF handshake, and tests that hit the
+/// timeout without one, for [`print_hang_summary`].
+static STALLS: AtomicUsize = AtomicUsize::new(0);
+static TIMEOUTS: AtomicUsize = AtomicUsize::new(0);
 
 /// Tests to skip (not compatible with static recompilation).
 const SKIP_TESTS: &[&str] = &[
@@ -27,13 +32,8 @@ pub fn should_skip(name: &str) -> bool {
     false
 }
 
-/// Run a single test.
-pub fn run_test(
-    elf_path: &Path,
-    timeout: Duration,
-    compiler: &Compiler,
-    backend: Backend,
-) -> Result<(), String> {
+/// Run a single test compiled with `options` (plus HTIF, quietly).
+pub fn run_test(elf_path: &Path, timeout: Duration, options: CompileOptions) -> Result<(), String> {
     let name = elf_path
         .file_name()
         .and_then(|n| n.to_str())
@@ -47,11 +47,7 @@ pub fn run_test(
     let temp_dir = tempfile::tempdir().map_err(|e| format!("temp dir failed: {e}"))?;
     let out_dir = temp_dir.path().join("out");
 
-    let options = CompileOptions::new()
-        .with_htif(true)
-        .with_quiet(true)
-        .with_compiler(compiler.clone())
-        .with_backend(backend);
+    let options = options.with_htif(true).with_quiet(true);
 
     compile_with_options(elf_path, &out_dir, &options)
         .map_err(|e| format!("compile failed: {e}"))?;
@@ -74,10 +70,14 @@ fn run_with_timeout(lib_dir: &Path, elf_path: &Path, timeout: Duration) -> Resul
         };
         match runner.run() {
             Ok(result) => {
-                if result.exit_code == 0 {
+                if result.exit_reason.is_success() {
                     let _ = tx.send(Ok(()));
+                } else if matches!(result.exit_reason, ExitReason::HtifStall { .. }) {
+                    STALLS.fetch_add(1, Ordering::Relaxed);
+                    let _ = tx.send(Err(result.exit_reason.to_string()));
                 } else {
-                    let _ = tx.send(Err(format!("exit={}", result.exit_code)));
+                    // HTIF failures name the failing test case
+                    let _ = tx.send(Err(result.exit_reason.to_string()));
                 }
             }
             Err(e) => {
@@ -88,11 +88,26 @@ fn run_with_timeout(lib_dir: &Path, elf_path: &Path, timeout: Duration) -> Resul
 
     match rx.recv_timeout(timeout) {
         Ok(result) => result,
-        Err(std::sync::mpsc::RecvTimeoutError::Timeout) => Err("timeout".to_string()),
+        Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
+            TIMEOUTS.fetch_add(1, Ordering::Relaxed);
+            Err(format!(
+                "timeout after {timeout:?} (no HTIF stall detected)"
+            ))
+        }
         Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => Err("crash".to_string()),
     }
 }
 
+/// Print how many failures were HTIF stalls and how many real timeouts,
+/// if any were.
+pub fn print_hang_summary() {
+    let stalls = STALLS.load(Ordering::Relaxed);
+    let timeouts = TIMEOUTS.load(Ordering::Relaxed);
+    if stalls + timeouts > 0 {
+        println!("hangs: {stalls} HTIF handshake stalls; {timeouts} timed out");
+    }
+}
+
 /// RISC-V test categories (directory names under riscv-tests/isa).
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum TestCategory {
@@ -0,0 +1,144 @@
+//! Suspension inside a loop: a guest spinning in one superblock suspends at
+//! the instret limit, late by at most the instructions of that superblock,
+//! under either `SuspendGranularity`. C blocks are re-entered through their
+//! entry check on every iteration; the assembly backends check at the end
+//! of every basic block.
+
+use guest::{FUNCT3_BNE, OPCODE_BRANCH, OPCODE_JAL, OPCODE_SYSTEM, addi, li};
+use rvr::test_support::guest;
+use rvr::{Backend, CompileOptions, InstretMode, Runner, SuspendGranularity};
+use rvr_elf::STT_FUNC;
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{REG_A0, REG_A7, REG_T0, REG_T1, REG_ZERO, Rv64, encode_b, encode_i, encode_j};
+
+const TEXT: u64 = 0x1000;
+const LIMIT: u64 = 10_000;
+/// Instructions of the superblock, the most a check can be late by.
+const EPSILON: u64 = 5;
+const GRANULARITIES: [SuspendGranularity; 2] = [
+    SuspendGranularity::BlockEntry,
+    SuspendGranularity::BackEdges,
+];
+
+/// Iterations of the inlined loop.
+const ITERATIONS: u16 = 100;
+
+/// `a0 = 0; a7 = exit; ecall`.
+const EXIT: [u32; 3] = [
+    li(REG_A0, 0),
+    li(REG_A7, SYS_EXIT),
+    encode_i(OPCODE_SYSTEM, REG_ZERO, 0, REG_ZERO, 0),
+];
+
+fn elf(text: &[u32]) -> Vec<u8> {
+    guest::text_elf::<Rv64>(TEXT, text)
+        .with_symbol("_start", TEXT, STT_FUNC)
+        .build()
+}
+
+/// `1: t0 += 1; bnez t0, 1b; exit(0)`: the exit only follows 2^64
+/// iterations, and superblock formation absorbs it into the loop block.
+fn spin_elf() -> Vec<u8> {
+    let mut text = vec![
+        addi(REG_T0, REG_T0, 1),
+        encode_b(OPCODE_BRANCH, FUNCT3_BNE, REG_T0, REG_ZERO, -4),
+    ];
+    text.extend(EXIT);
+    elf(&text)
+}
+
+/// A loop closed by a branch back to a block only it reaches, which the C
+/// backend inlines into the branch's block once superblocks are one block
+/// long: `t0 = -ITERATIONS; j 2f; 1: t1 += 1; j 2f; 2: t0 += 1;
+/// bnez t0, 1b; exit(0)`.
+fn inlined_elf() -> Vec<u8> {
+    let mut text = vec![
+        addi(REG_T0, REG_ZERO, -i32::from(ITERATIONS)),
+        encode_j(OPCODE_JAL, REG_ZERO, 12),
+        addi(REG_T1, REG_T1, 1),
+        encode_j(OPCODE_JAL, REG_ZERO, 4),
+        addi(REG_T0, REG_T0, 1),
+        encode_b(OPCODE_BRANCH, FUNCT3_BNE, REG_T0, REG_ZERO, -12),
+    ];
+    text.extend(EXIT);
+    elf(&text)
+}
+
+fn compile(elf: &[u8], options: &CompileOptions) -> (tempfile::TempDir, Runner) {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let path = temp.path().join("loop.elf");
+    std::fs::write(&path, elf).expect("write ELF");
+    let out = temp.path().join("out");
+    rvr::compile_with_options(&path, &out, options).expect("compile");
+    let runner = Runner::load(&out, &path).expect("load runner");
+    (temp, runner)
+}
+
+fn options(backend: Backend, granularity: SuspendGranularity) -> CompileOptions {
+    CompileOptions::new()
+        .with_quiet(true)
+        .with_instret_mode(InstretMode::Suspend)
+        .with_suspend_granularity(granularity)
+        .with_backend(backend)
+}
+
+fn run(backend: Backend) {
+    for granularity in GRANULARITIES {
+        let (_temp, mut runner) = compile(&spin_elf(), &options(backend, granularity));
+        runner.set_target_instret(LIMIT);
+        let result = runner.run().expect("run guest");
+        assert!(!runner.has_exited());
+        assert!(
+            (LIMIT..LIMIT + EPSILON).contains(&result.instret),
+            "{granularity:?}: suspended at {}",
+            result.instret
+        );
+        assert_eq!(runner.get_pc(), TEXT);
+    }
+}
+
+#[test]
+fn test_loop_in_superblock_suspends() {
+    run(Backend::C);
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn test_loop_in_superblock_suspends_x86() {
+    run(Backend::X86Asm);
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn test_loop_in_superblock_suspends_arm64() {
+    run(Backend::ARM64Asm);
+}
+
+/// A loop through an inlined taken branch suspends near the limit and,
+/// resumed, retires exactly the guest's instructions.
+#[test]
+fn test_loop_through_inlined_branch_suspends() {
+    let iterations = u64::from(ITERATIONS);
+    // Two entry instructions, two per pass through the loop block and one
+    // fewer through the inlined block, and three exit instructions
+    let expected = 2 + 2 * iterations + 2 * (iterations - 1) + 3;
+    let limit = 2 * iterations;
+    for granularity in GRANULARITIES {
+        let options = options(Backend::C, granularity).with_superblock_max_blocks(1);
+        let (_temp, mut runner) = compile(&inlined_elf(), &options);
+        runner.set_target_instret(limit);
+        let result = runner.run().expect("run guest");
+        assert!(!runner.has_exited());
+        assert!(
+            (limit..limit + EPSILON).contains(&result.instret),
+            "{granularity:?}: suspended at {}",
+            result.instret
+        );
+
+        runner.set_target_instret(u64::MAX);
+        let pc = runner.get_pc();
+        let (_, instret) = runner.execute_from(pc).expect("resume guest");
+        assert!(runner.has_exited());
+        assert_eq!(instret, expected, "{granularity:?}");
+    }
+}
@@ -0,0 +1,115 @@
+//! Synthetic guests: programs built from IR blocks, compiled and run
+//! without an ELF.
+
+use std::collections::HashMap;
+
+use rvr::{Backend, EmitConfig, Error, Pipeline, Recompiler, Runner, Rv64, SyntheticProgram};
+use rvr_ir::{BlockIR, Expr, IRBuilder, Terminator};
+use rvr_isa::{REG_A0, REG_T0, REG_T1};
+
+const ENTRY: u64 = 0x1000;
+const LOOP: u64 = 0x1004;
+/// Not adjacent to the loop, so its fall-through must be a jump.
+const TAIL: u64 = 0x2000;
+const DATA: u64 = 0x3000;
+const ITERATIONS: u64 = 10;
+const EXIT_CODE: u8 = 7;
+
+/// `t0 = 0; do t0 += 1 while t0 != 10; *DATA = t0; exit(*(DATA + 8))`.
+fn program() -> SyntheticProgram<Rv64> {
+    let mut entry = BlockIR::new(ENTRY);
+    entry.push(
+        IRBuilder::new(ENTRY, 4)
+            .write_reg(REG_T0, Expr::imm(0))
+            .build_fall(),
+    );
+
+    let mut body = BlockIR::new(LOOP);
+    body.push(
+        IRBuilder::new(LOOP, 4)
+            .write_reg(REG_T0, Expr::add(Expr::read(REG_T0), Expr::imm(1)))
+            .build_fall(),
+    );
+    body.push(
+        IRBuilder::new(LOOP + 4, 4).build(Terminator::branch_with_fall(
+            Expr::ne(Expr::read(REG_T0), Expr::imm(ITERATIONS)),
+            LOOP,
+            TAIL,
+        )),
+    );
+
+    let mut tail = BlockIR::new(TAIL);
+    tail.push(
+        IRBuilder::new(TAIL, 4)
+            .write_reg(REG_T1, Expr::imm(DATA))
+            .build_fall(),
+    );
+    tail.push(
+        IRBuilder::new(TAIL + 4, 4)
+            .write_mem(Expr::read(REG_T1), 0, Expr::read(REG_T0), 8)
+            .build_fall(),
+    );
+    tail.push(
+        IRBuilder::new(TAIL + 8, 4)
+            .write_reg(REG_A0, Expr::mem_u(Expr::imm(DATA + 8), 8))
+            .build_exit(Expr::read(REG_A0)),
+    );
+
+    let mut data = vec![0; 16];
+    data[8] = EXIT_CODE;
+    SyntheticProgram::new(ENTRY)
+        .with_block(entry)
+        .with_block(body)
+        .with_block(tail)
+        .with_data(DATA, data)
+}
+
+fn run_on(backend: Backend) {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let out = temp.path().join("synthetic");
+    let mut config = EmitConfig::<Rv64>::default();
+    config.backend = backend;
+    Recompiler::new(config)
+        .with_quiet(true)
+        .compile_program(program(), &out, 0)
+        .expect("compile");
+
+    let mut runner = Runner::load_synthetic(&out, &program()).expect("load runner");
+    runner.run().expect("run guest");
+    assert!(runner.has_exited());
+    assert_eq!(runner.exit_code(), EXIT_CODE);
+    let mut stored = [0; 8];
+    assert_eq!(runner.read_memory(DATA, &mut stored), stored.len());
+    assert_eq!(u64::from_le_bytes(stored), ITERATIONS);
+    assert!(runner.backtrace().is_empty());
+}
+
+#[test]
+fn test_synthetic_program_c() {
+    run_on(Backend::C);
+}
+
+#[test]
+#[cfg(target_arch = "x86_64")]
+fn test_synthetic_program_x86() {
+    run_on(Backend::X86Asm);
+}
+
+#[test]
+#[cfg(target_arch = "aarch64")]
+fn test_synthetic_program_arm64() {
+    run_on(Backend::ARM64Asm);
+}
+
+#[test]
+fn test_synthetic_program_rejects_missing_target() {
+    let mut block = BlockIR::new(ENTRY);
+    block.push(IRBuilder::new(ENTRY, 4).build_jump(TAIL));
+    let blocks = HashMap::from([(ENTRY, block)]);
+
+    let result = Pipeline::from_ir_blocks(blocks.clone(), ENTRY, EmitConfig::<Rv64>::default());
+    assert!(matches!(result, Err(Error::InvalidSynthetic(_))));
+
+    let program = SyntheticProgram::from_blocks(blocks, ENTRY).with_external_target(TAIL);
+    assert!(program.validate().is_ok());
+}
@@ -0,0 +1,182 @@
+//! Syscall policy: guests open files only under directories the host
+//! preopened with `Runner::with_preopened_dir`, and syscalls the policy
+//! leaves out return `EPERM` without reaching the host.
+
+use std::path::{Path, PathBuf};
+
+use guest::{ECALL, FUNCT3_D, OPCODE_STORE, SharedWriter, addi, li, lui};
+use rvr::test_support::guest;
+use rvr::{CompileOptions, Runner, SyscallMode, SyscallPolicy};
+use rvr_elf::{PF_R, PF_W, STT_FUNC};
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::syscalls::syscall_nr::{SYS_CLOSE, SYS_OPENAT, SYS_READ, SYS_WRITE};
+use rvr_isa::{REG_A0, REG_A1, REG_A2, REG_A3, REG_A7, REG_S1, REG_S2, REG_ZERO, Rv64, encode_s};
+
+const AT_FDCWD: i32 = -100;
+/// `-EPERM` as the guest's exit code.
+const EPERM_EXIT: u8 = 0xff;
+
+const TEXT: u64 = 0x1000;
+/// NUL-terminated path the guest opens.
+const PATH: u64 = 0x2_0000;
+/// Buffer the guest reads into, and the slot for the `openat` result.
+const BUF: u64 = 0x3_0000;
+const RESULT: u64 = 0x4_0000;
+/// Bytes the guest asks `read` for.
+const LEN: i32 = 64;
+const CONTENTS: &[u8] = b"preopened file contents";
+
+/// `*RESULT = s1 = openat(AT_FDCWD, PATH, O_RDONLY)`, exit with
+/// `read(s1, BUF, LEN)`.
+fn open_read_text() -> Vec<u32> {
+    vec![
+        addi(REG_A0, REG_ZERO, AT_FDCWD),
+        lui(REG_A1, PATH),
+        li(REG_A2, 0),
+        li(REG_A3, 0),
+        li(REG_A7, SYS_OPENAT),
+        ECALL,
+        addi(REG_S1, REG_A0, 0),
+        lui(REG_S2, RESULT),
+        encode_s(OPCODE_STORE, FUNCT3_D, REG_S2, REG_A0, 0),
+        addi(REG_A0, REG_S1, 0),
+        lui(REG_A1, BUF),
+        addi(REG_A2, REG_ZERO, LEN),
+        li(REG_A7, SYS_READ),
+        ECALL,
+        li(REG_A7, SYS_EXIT),
+        ECALL,
+    ]
+}
+
+/// Exit with `write(1, PATH, 4)`.
+fn write_text() -> Vec<u32> {
+    vec![
+        li(REG_A0, 1),
+        lui(REG_A1, PATH),
+        li(REG_A2, 4),
+        li(REG_A7, SYS_WRITE),
+        ECALL,
+        li(REG_A7, SYS_EXIT),
+        ECALL,
+    ]
+}
+
+fn guest_elf(text: &[u32], path: &str) -> Vec<u8> {
+    let mut data = path.as_bytes().to_vec();
+    data.push(0);
+    guest::text_elf::<Rv64>(TEXT, text)
+        .with_segment(PATH, PF_R | PF_W, data)
+        .with_symbol("_start", TEXT, STT_FUNC)
+        .build()
+}
+
+fn compile(dir: &Path, elf: &[u8], policy: SyscallPolicy) -> (PathBuf, PathBuf) {
+    let elf_path = dir.join("policy.elf");
+    std::fs::write(&elf_path, elf).expect("write ELF");
+    let out = dir.join("out");
+    let options = CompileOptions::new()
+        .with_quiet(true)
+        .with_syscall_mode(SyscallMode::Linux)
+        .with_syscall_policy(policy);
+    rvr::compile_with_options(&elf_path, &out, &options).expect("compile");
+    (elf_path, out)
+}
+
+fn file_policy() -> SyscallPolicy {
+    SyscallPolicy::new()
+        .allow_all([SYS_OPENAT, SYS_READ, SYS_CLOSE])
+        .with_preopen("/data")
+}
+
+/// Host directory with `hello.txt`, preopened as `/data`.
+fn data_dir(temp: &Path) -> PathBuf {
+    let dir = temp.join("data");
+    std::fs::create_dir(&dir).expect("create data dir");
+    std::fs::write(dir.join("hello.txt"), CONTENTS).expect("write data file");
+    dir
+}
+
+fn open_result(runner: &Runner) -> i64 {
+    let mut result = [0; 8];
+    assert_eq!(runner.read_memory(RESULT, &mut result), 8);
+    i64::from_le_bytes(result)
+}
+
+#[test]
+fn test_read_under_preopen() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = guest_elf(&open_read_text(), "/data/hello.txt");
+    let (elf, out) = compile(temp.path(), &elf, file_policy());
+    let mut runner = Runner::load(&out, &elf)
+        .expect("load runner")
+        .with_preopened_dir("/data", data_dir(temp.path()))
+        .expect("preopen");
+
+    let result = runner.run().expect("run guest");
+    // fd 3 is the preopen itself
+    assert_eq!(open_result(&runner), 4);
+    assert_eq!(usize::from(result.exit_code), CONTENTS.len());
+    let mut buf = vec![0; CONTENTS.len()];
+    assert_eq!(runner.read_memory(BUF, &mut buf), buf.len());
+    assert_eq!(buf, CONTENTS);
+}
+
+/// Run a guest opening `path` with `/data` preopened; the open must fail
+/// with `EPERM` and read nothing.
+fn assert_open_denied(temp: &Path, path: &str) {
+    let elf = guest_elf(&open_read_text(), path);
+    let (elf, out) = compile(temp, &elf, file_policy());
+    let mut runner = Runner::load(&out, &elf)
+        .expect("load runner")
+        .with_preopened_dir("/data", data_dir(temp))
+        .expect("preopen");
+
+    runner.run().expect("run guest");
+    assert_eq!(open_result(&runner), -1);
+    let mut buf = [0; 6];
+    assert_eq!(runner.read_memory(BUF, &mut buf), buf.len());
+    assert_eq!(buf, [0; 6]);
+}
+
+#[test]
+fn test_open_outside_preopen_is_eperm() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let secret = temp.path().join("secret.txt");
+    std::fs::write(&secret, b"secret").expect("write secret");
+    assert_open_denied(temp.path(), secret.to_str().unwrap());
+}
+
+#[test]
+fn test_dotdot_out_of_preopen_is_eperm() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    std::fs::write(temp.path().join("secret.txt"), b"secret").expect("write secret");
+    assert_open_denied(temp.path(), "/data/../secret.txt");
+}
+
+#[test]
+fn test_preopen_outside_policy_is_rejected() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = guest_elf(&open_read_text(), "/data/hello.txt");
+    let (elf, out) = compile(temp.path(), &elf, file_policy());
+    let runner = Runner::load(&out, &elf).expect("load runner");
+    assert!(
+        runner
+            .with_preopened_dir("/home", data_dir(temp.path()))
+            .is_err()
+    );
+}
+
+#[test]
+fn test_denied_write_is_eperm() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = guest_elf(&write_text(), "leak");
+    let (elf, out) = compile(temp.path(), &elf, file_policy());
+    let mut runner = Runner::load(&out, &elf).expect("load runner");
+    let stdout = SharedWriter::default();
+    runner.set_stdout(stdout.clone());
+
+    let result = runner.run().expect("run guest");
+    assert_eq!(result.exit_code, EPERM_EXIT);
+    assert!(stdout.contents().is_empty());
+}
@@ -0,0 +1,71 @@
+//! Tracer sampling: a buffered-diff tracer sampled every N instructions
+//! records exactly every Nth entry of the unsampled trace.
+
+use std::path::Path;
+
+use guest::{FUNCT3_BNE, OPCODE_BRANCH, OPCODE_OP_IMM, OPCODE_SYSTEM, li};
+use rvr::test_support::guest;
+use rvr::{CompileOptions, InstretMode, Runner, TracerConfig};
+use rvr_emit::c::TracerKind;
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{REG_A0, REG_A1, REG_A7, REG_ZERO, Rv64, encode_b, encode_i};
+
+const TEXT: u64 = 0x1000;
+const ITERATIONS: i32 = 50;
+const INTERVAL: u32 = 7;
+
+/// Counts `a0` up `ITERATIONS` times in a loop, then exits with it.
+fn guest_elf() -> Vec<u8> {
+    let text = [
+        encode_i(OPCODE_OP_IMM, REG_A1, 0, REG_ZERO, ITERATIONS),
+        // loop: a0 += 1; a1 -= 1; bne a1, zero, loop
+        encode_i(OPCODE_OP_IMM, REG_A0, 0, REG_A0, 1),
+        encode_i(OPCODE_OP_IMM, REG_A1, 0, REG_A1, -1),
+        encode_b(OPCODE_BRANCH, FUNCT3_BNE, REG_A1, REG_ZERO, -8),
+        li(REG_A7, SYS_EXIT),
+        encode_i(OPCODE_SYSTEM, REG_ZERO, 0, REG_ZERO, 0),
+    ];
+    guest::text_elf::<Rv64>(TEXT, &text).build()
+}
+
+/// Run the guest traced every `interval` instructions; returns the traced
+/// `(instret, pc, rd_value)` entries.
+fn trace(dir: &Path, interval: u32) -> Vec<(u64, u64, Option<u64>)> {
+    let elf = dir.join("loop.elf");
+    std::fs::write(&elf, guest_elf()).expect("write ELF");
+    let out = dir.join(format!("out-{interval}"));
+    let options = CompileOptions::new()
+        .with_instret_mode(InstretMode::Suspend)
+        .with_tracer_config(
+            TracerConfig::builtin(TracerKind::BufferedDiff).with_sample_interval(interval),
+        )
+        .with_quiet(true);
+    rvr::compile_with_options(&elf, &out, &options).expect("compile");
+
+    let mut runner = Runner::load(&out, &elf).expect("load runner");
+    assert_eq!(runner.tracer_sample_interval(), interval);
+    let result = runner.run().expect("run guest");
+    assert_eq!(result.exit_code, u8::try_from(ITERATIONS).unwrap());
+    assert_eq!(runner.buffered_diff_has_overflow(), Some(false));
+    let count = runner.buffered_diff_count().expect("buffered diff tracer");
+    (0..count)
+        .map(|i| {
+            let (pc, _, _, rd_value, _, _, instret) =
+                runner.buffered_diff_get(i).expect("entry in range");
+            (instret, pc, rd_value)
+        })
+        .collect()
+}
+
+#[test]
+fn test_sampled_trace_is_every_nth_entry() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let full = trace(temp.path(), 1);
+    let total = 3 * usize::try_from(ITERATIONS).unwrap() + 3;
+    assert_eq!(full.len(), total);
+    assert!(full.iter().zip(0..).all(|(entry, i)| entry.0 == i));
+
+    let sampled = trace(temp.path(), INTERVAL);
+    let expected: Vec<_> = full.into_iter().step_by(INTERVAL as usize).collect();
+    assert_eq!(sampled, expected);
+}
@@ -0,0 +1,150 @@
+//! Unresolved dynamic jumps: a computed goto to code the static analysis
+//! cannot find stops with `RunError::UnresolvedJump` (with
+//! `--report-jump-sites`), `rvr run
+//! --collect-jump-targets` saves the target, and a recompile with it as an
+//! extra entry point runs through.
+
+use std::path::Path;
+use std::process::{Command, Output};
+
+use guest::{ECALL, OPCODE_JAL, OPCODE_JALR, OPCODE_LOAD, OPCODE_LUI, addi, li};
+use rvr::test_support::guest;
+use rvr::{CompileOptions, JumpTargets, RunError, Runner};
+use rvr_elf::{ElfWriter, PF_R, PF_W, PF_X, STT_NOTYPE};
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{
+    REG_A0, REG_A7, REG_T0, REG_T1, REG_ZERO, Rv32, Rv64, Xlen, encode_i, encode_j, encode_u,
+};
+
+const START: u64 = 0x1000;
+/// The `jalr`, fifth instruction of `_start`.
+const SITE: u64 = START + 16;
+/// Jump target: the second instruction of a block after the `j .` that
+/// ends `_start`, so no block starts there.
+const HIDDEN: u64 = SITE + 12;
+/// Slot holding the jump target plus [`DISGUISE`].
+const SLOT: u64 = 0x2_0000;
+/// Offset that keeps the slot from looking like a code pointer.
+const DISGUISE: i32 = 0x400;
+const STACK_TOP: u64 = 0x10_0000;
+/// Exit code of a run that reaches [`HIDDEN`].
+const REACHED: u8 = 42;
+
+/// Compute [`HIDDEN`] from a writable slot and jump there through `t1`,
+/// skipping the `a0` store before it to exit with [`REACHED`].
+fn guest_elf<X: Xlen>() -> Vec<u8> {
+    let funct3 = if X::VALUE == 64 { 0b011 } else { 0b010 };
+    let start = [
+        encode_u(OPCODE_LUI, REG_T0, u32::try_from(SLOT >> 12).unwrap()),
+        encode_i(OPCODE_LOAD, REG_T1, funct3, REG_T0, 0),
+        addi(REG_T1, REG_T1, -DISGUISE),
+        addi(REG_A0, REG_ZERO, i32::from(REACHED)),
+        encode_i(OPCODE_JALR, REG_ZERO, 0, REG_T1, 0),
+        encode_j(OPCODE_JAL, REG_ZERO, 0),
+    ];
+    let hidden = [addi(REG_A0, REG_ZERO, 1), li(REG_A7, SYS_EXIT), ECALL];
+    let text = start
+        .iter()
+        .chain(&hidden)
+        .flat_map(|i| i.to_le_bytes())
+        .collect();
+    ElfWriter::<X>::new(START)
+        .with_segment(START, PF_R | PF_X, text)
+        .with_segment(
+            SLOT,
+            PF_R | PF_W,
+            (HIDDEN + DISGUISE as u64).to_le_bytes()[..usize::from(X::VALUE / 8)].to_vec(),
+        )
+        .with_function("_start", START, SITE + 8 - START)
+        .with_symbol("__stack_top", STACK_TOP, STT_NOTYPE)
+        .build()
+}
+
+fn run(elf: &Path, out: &Path, options: &CompileOptions) -> Result<u8, RunError> {
+    rvr::compile_with_options(elf, out, options).expect("compile");
+    let mut runner = Runner::load(out, elf).expect("load runner");
+    runner.run().map(|result| result.exit_code)
+}
+
+fn check_unresolved_jump<X: Xlen>() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = temp.path().join("jump.elf");
+    std::fs::write(&elf, guest_elf::<X>()).expect("write ELF");
+    let options = CompileOptions::new().with_quiet(true);
+
+    // Without site reports a miss is a plain trap in `rv_trap`
+    let plain = run(&elf, &temp.path().join("plain"), &options);
+    assert!(
+        !matches!(plain, Err(RunError::UnresolvedJump { .. }) | Ok(REACHED)),
+        "RV{}: {plain:?}",
+        X::VALUE
+    );
+
+    let options = options.with_report_jump_sites(true);
+    let err = run(&elf, &temp.path().join("out"), &options).expect_err("unresolved jump");
+    let RunError::UnresolvedJump { site, target, reg } = err else {
+        panic!("RV{}: expected an unresolved jump, got {err}", X::VALUE);
+    };
+    assert_eq!(
+        (site, target, reg),
+        (SITE, HIDDEN, REG_T1),
+        "RV{}",
+        X::VALUE
+    );
+
+    let options = options.with_extra_entry_points([target]);
+    let exit_code = run(&elf, &temp.path().join("resolved"), &options).expect("run guest");
+    assert_eq!(exit_code, REACHED, "RV{}", X::VALUE);
+}
+
+#[test]
+fn test_unresolved_jump_rv64() {
+    check_unresolved_jump::<Rv64>();
+}
+
+#[test]
+fn test_unresolved_jump_rv32() {
+    check_unresolved_jump::<Rv32>();
+}
+
+#[test]
+fn test_collect_jump_targets() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = temp.path().join("jump.elf");
+    std::fs::write(&elf, guest_elf::<Rv64>()).expect("write ELF");
+    let jumps = temp.path().join("jumps.json");
+    let out = temp.path().join("out");
+    let rvr = |args: &[&str]| -> Output {
+        Command::new(env!("CARGO_BIN_EXE_rvr"))
+            .args(args)
+            .env("RVR_CACHE_DIR", temp.path().join("cache"))
+            .env("NO_COLOR", "1")
+            .output()
+            .expect("run rvr")
+    };
+    let (elf, out, jumps) = (
+        elf.to_str().unwrap(),
+        out.to_str().unwrap(),
+        jumps.to_str().unwrap(),
+    );
+
+    assert!(
+        rvr(&["compile", elf, "-o", out, "--report-jump-sites"])
+            .status
+            .success()
+    );
+    let run = rvr(&["run", out, elf, "--collect-jump-targets", jumps]);
+    assert!(!run.status.success());
+    let stderr = String::from_utf8_lossy(&run.stderr);
+    assert!(
+        stderr.contains("jump site: 0x1010 in _start+0x10: jalr"),
+        "{stderr}"
+    );
+    let targets = JumpTargets::load(Path::new(jumps)).expect("load jump targets");
+    assert_eq!(targets.jumps().collect::<Vec<_>>(), [(SITE, HIDDEN)]);
+
+    let compile = rvr(&["compile", elf, "-o", out, "--jump-targets", jumps]);
+    assert!(compile.status.success());
+    let run = rvr(&["run", out, elf]);
+    assert_eq!(run.status.code(), Some(i32::from(REACHED)));
+}
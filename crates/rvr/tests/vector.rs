@@ -0,0 +1,142 @@
+//! Vector code: a strip-mined `vle8.v`/`vse8.v` copy loop, as LLVM emits
+//! for `memcpy` with `+v`, copies the same bytes at every VLEN.
+
+use guest::{
+    FUNCT3_BNE, FUNCT7_SUB, OPCODE_BRANCH, OPCODE_LOAD_FP, OPCODE_LUI, OPCODE_OP, OPCODE_OP_IMM,
+    OPCODE_OP_V, OPCODE_STORE_FP, OPCODE_SYSTEM, li,
+};
+use rvr::test_support::guest;
+use rvr::{Backend, CompileOptions, Error, Runner};
+use rvr_elf::{PF_R, PF_W};
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{
+    REG_A0, REG_A1, REG_A2, REG_A3, REG_A7, REG_T0, REG_ZERO, Rv64, encode_b, encode_i, encode_r,
+    encode_u,
+};
+
+const FUNCT3_OPIVV: u8 = 0b000;
+const FUNCT3_OPIVI: u8 = 0b011;
+const FUNCT3_OPCFG: u8 = 0b111;
+/// `e8, m8, ta, ma`.
+const VTYPE_E8_M8: i32 = 0xC3;
+/// `vsetivli` marker bits over `e8, m1, ta, ma`.
+const VSETIVLI_E8_M1: i32 = 0xCC0;
+/// `vm` bit (unmasked) in the immediate of a unit-stride load or store.
+const UNMASKED: i32 = 1 << 5;
+const TEXT: u64 = 0x1000;
+const SRC: u64 = 0x2000;
+const DST: u64 = 0x3000;
+/// Not a multiple of any VLEN, so the last strip is partial.
+const LEN: usize = 300;
+/// Leading bytes the guest increments with `vadd.vi` after the copy.
+const BUMPED: usize = 8;
+
+/// `vle8.v vd, (rs1)` or `vse8.v vd, (rs1)`.
+const fn unit_stride(opcode: u8, vd: u8, rs1: u8) -> u32 {
+    encode_i(opcode, vd, 0, rs1, UNMASKED)
+}
+
+fn source() -> Vec<u8> {
+    (0..LEN)
+        .map(|i| u8::try_from(i * 7 % 251).expect("byte"))
+        .collect()
+}
+
+/// Copies `LEN` bytes from `SRC` to `DST`, then adds 1 to the first
+/// `BUMPED` bytes of the copy. `extra` runs before the exit.
+fn guest_elf(extra: &[u32]) -> Vec<u8> {
+    let mut text = vec![
+        encode_u(OPCODE_LUI, REG_A0, 2),
+        encode_u(OPCODE_LUI, REG_A1, 3),
+        encode_i(
+            OPCODE_OP_IMM,
+            REG_A2,
+            0,
+            REG_ZERO,
+            i32::try_from(LEN).expect("LEN fits"),
+        ),
+        // loop: t0 = vsetvli(a2); copy t0 bytes; advance
+        encode_i(OPCODE_OP_V, REG_T0, FUNCT3_OPCFG, REG_A2, VTYPE_E8_M8),
+        unit_stride(OPCODE_LOAD_FP, 8, REG_A0),
+        unit_stride(OPCODE_STORE_FP, 8, REG_A1),
+        encode_r(OPCODE_OP, REG_A0, 0, REG_A0, REG_T0, 0),
+        encode_r(OPCODE_OP, REG_A1, 0, REG_A1, REG_T0, 0),
+        encode_r(OPCODE_OP, REG_A2, 0, REG_A2, REG_T0, FUNCT7_SUB),
+        encode_b(OPCODE_BRANCH, FUNCT3_BNE, REG_A2, REG_ZERO, -24),
+        // vsetivli zero, 8, e8, m1; v1 = DST[..8] + 1
+        encode_i(OPCODE_OP_V, REG_ZERO, FUNCT3_OPCFG, 8, VSETIVLI_E8_M1),
+        encode_u(OPCODE_LUI, REG_A3, 3),
+        unit_stride(OPCODE_LOAD_FP, 1, REG_A3),
+        encode_r(OPCODE_OP_V, 1, FUNCT3_OPIVI, 1, 1, 1),
+        unit_stride(OPCODE_STORE_FP, 1, REG_A3),
+    ];
+    text.extend_from_slice(extra);
+    text.extend([
+        encode_i(OPCODE_OP_IMM, REG_A0, 0, REG_ZERO, 0),
+        li(REG_A7, SYS_EXIT),
+        encode_i(OPCODE_SYSTEM, REG_ZERO, 0, REG_ZERO, 0),
+    ]);
+    guest::text_elf::<Rv64>(TEXT, &text)
+        .with_segment(SRC, PF_R | PF_W, source())
+        .build()
+}
+
+fn compile(extra: &[u32], options: &CompileOptions) -> rvr::Result<Vec<u8>> {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf = temp.path().join("vcopy.elf");
+    std::fs::write(&elf, guest_elf(extra)).expect("write ELF");
+    let out = temp.path().join("out");
+    let options = options.clone().with_quiet(true);
+    rvr::compile_with_report(&elf, &out, &options)?;
+
+    let mut runner = Runner::load(&out, &elf).expect("load runner");
+    assert_eq!(runner.run().expect("run guest").exit_code, 0);
+    let mut copy = vec![0; LEN];
+    assert_eq!(runner.read_memory(DST, &mut copy), LEN);
+    Ok(copy)
+}
+
+#[test]
+fn test_vector_copy_matches_scalar() {
+    let mut expected = source();
+    for byte in &mut expected[..BUMPED] {
+        *byte = byte.wrapping_add(1);
+    }
+    for vlen in [64, 128, 1024] {
+        let options = CompileOptions::new().with_vlen(vlen);
+        assert_eq!(
+            compile(&[], &options).expect("compile"),
+            expected,
+            "VLEN {vlen}"
+        );
+    }
+}
+
+#[test]
+fn test_unsupported_vector_instruction_is_diagnosed() {
+    // vsub.vv v8, v8, v8
+    let vsub = encode_r(OPCODE_OP_V, 8, FUNCT3_OPIVV, 8, 8, 0b000_0101);
+    let options = CompileOptions::new().with_strict_decode(true);
+    let err = compile(&[vsub], &options).expect_err("vsub.vv is rejected");
+    assert!(
+        matches!(&err, Error::UnsupportedInstructions(diags) if diags.len() == 1),
+        "{err:?}"
+    );
+}
+
+#[test]
+fn test_vector_requires_c_backend_and_valid_vlen() {
+    let message = |options: CompileOptions| {
+        compile(&[], &options)
+            .expect_err("compile fails")
+            .to_string()
+    };
+    assert!(
+        message(CompileOptions::new().with_backend(Backend::X86Asm))
+            .contains("vector instructions require the C backend")
+    );
+    assert!(
+        message(CompileOptions::new().with_vlen(96))
+            .contains("VLEN 96 is not a power of two from 64 to 1024")
+    );
+}
@@ -0,0 +1,115 @@
+//! Zcb and Zcmp: an RV32 guest whose ISA attribute names `zcb_zcmp` calls a
+//! function that saves and restores its registers with `cm.push` and
+//! `cm.popret` and round-trips a byte through `c.sb`/`c.lbu`.
+//!
+//! The guest is hand-encoded: no toolchain here emits Zcmp.
+
+use guest::{ECALL, FUNCT7_SUB, OPCODE_JAL, OPCODE_OP, OPCODE_OP_IMM, li};
+use rvr::test_support::guest;
+use rvr::{CompileOptions, ExitReason, Runner};
+use rvr_elf::{ArchAttributes, ElfWriter, PF_R, PF_X, STT_NOTYPE};
+use rvr_isa::syscalls::syscall_nr::SYS_EXIT;
+use rvr_isa::{
+    REG_A0, REG_A1, REG_A3, REG_A4, REG_A5, REG_A6, REG_A7, REG_RA, REG_S0, REG_S1, REG_SP,
+    REG_ZERO, Rv32, encode_i, encode_j, encode_r,
+};
+
+const TEXT: u64 = 0x1000;
+const STACK_TOP: u64 = 0x10_0000;
+/// Offset of `func` (after twelve words) from the `jal` (the sixth) that
+/// calls it.
+const CALL_OFFSET: i32 = 12 * 4 - 5 * 4;
+const ARCH: &str = "rv32i2p1_m2p0_c2p0_zcb1p0_zcmp1p0";
+
+// Arguments, the caller's saved registers and the byte, all summed up
+const ARG0: i32 = 1;
+const ARG1: i32 = 2;
+const CALLER_S0: i32 = 10;
+const CALLER_S1: i32 = 20;
+const BYTE: i32 = 42;
+const EXIT_CODE: i32 = ARG0 + ARG1 + BYTE + CALLER_S0 + CALLER_S1;
+
+/// `cm.push {ra, s0-s1}, -32`
+const CM_PUSH: u16 = 0xb866;
+/// `cm.mvsa01 s0, s1`
+const CM_MVSA01: u16 = 0xac26;
+/// `c.sb a4, 1(a5)`
+const C_SB: u16 = 0x8bd8;
+/// `c.lbu a3, 1(a5)`
+const C_LBU: u16 = 0x83d4;
+/// `cm.popret {ra, s0-s1}, 32`
+const CM_POPRET: u16 = 0xbe66;
+
+enum Insn {
+    C(u16),
+    W(u32),
+}
+
+const fn addi(rd: u8, rs1: u8, imm: i32) -> Insn {
+    Insn::W(encode_i(OPCODE_OP_IMM, rd, 0, rs1, imm))
+}
+
+const fn add(rd: u8, rs1: u8, rs2: u8) -> Insn {
+    Insn::W(encode_r(OPCODE_OP, rd, 0, rs1, rs2, 0))
+}
+
+/// `exit(f(1, 2) + s0 + s1 + sp_after - sp_before)`, where `f` clobbers
+/// `s0`/`s1` between its push and popret and returns `a0 + a1 + BYTE`.
+fn elf() -> Vec<u8> {
+    let text = [
+        addi(REG_A0, REG_ZERO, ARG0),
+        addi(REG_A1, REG_ZERO, ARG1),
+        addi(REG_S0, REG_ZERO, CALLER_S0),
+        addi(REG_S1, REG_ZERO, CALLER_S1),
+        addi(REG_A6, REG_SP, 0),
+        Insn::W(encode_j(OPCODE_JAL, REG_RA, CALL_OFFSET)),
+        add(REG_A0, REG_A0, REG_S0),
+        add(REG_A0, REG_A0, REG_S1),
+        Insn::W(encode_r(OPCODE_OP, REG_A6, 0, REG_SP, REG_A6, FUNCT7_SUB)),
+        add(REG_A0, REG_A0, REG_A6),
+        Insn::W(li(REG_A7, SYS_EXIT)),
+        Insn::W(ECALL),
+        // func:
+        Insn::C(CM_PUSH),
+        Insn::C(CM_MVSA01),
+        addi(REG_A5, REG_SP, 0),
+        addi(REG_A4, REG_ZERO, BYTE),
+        Insn::C(C_SB),
+        Insn::C(C_LBU),
+        add(REG_A0, REG_S0, REG_S1),
+        add(REG_A0, REG_A0, REG_A3),
+        Insn::C(CM_POPRET),
+    ];
+    let code = text
+        .iter()
+        .flat_map(|insn| match insn {
+            Insn::C(half) => half.to_le_bytes().to_vec(),
+            Insn::W(word) => word.to_le_bytes().to_vec(),
+        })
+        .collect();
+    ElfWriter::<Rv32>::new(TEXT)
+        .with_segment(TEXT, PF_R | PF_X, code)
+        .with_symbol("__stack_top", STACK_TOP, STT_NOTYPE)
+        .with_attributes(ArchAttributes {
+            arch: Some(ARCH.to_string()),
+            stack_align: Some(16),
+        })
+        .build()
+}
+
+#[test]
+fn test_push_popret_call() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let elf_path = temp.path().join("zcmp.elf");
+    std::fs::write(&elf_path, elf()).expect("write ELF");
+    let out = temp.path().join("zcmp");
+    let options = CompileOptions::new().with_quiet(true);
+    rvr::compile_with_options(&elf_path, &out, &options).expect("compile");
+
+    let mut runner = Runner::load(&out, &elf_path).expect("load runner");
+    let result = runner.run().expect("run guest");
+    assert_eq!(
+        result.exit_reason,
+        ExitReason::Exited(EXIT_CODE.try_into().unwrap())
+    );
+}
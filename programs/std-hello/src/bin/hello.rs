@@ -0,0 +1,5 @@
+//! Plain std hello world: startup, stdout and exit through `--syscalls linux`.
+
+fn main() {
+    println!("Hello, world!");
+}
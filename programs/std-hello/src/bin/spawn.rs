@@ -0,0 +1,7 @@
+//! Spawns a thread, which the single-threaded runtime refuses: std panics
+//! with the `clone` error and the guest exits with code 101.
+
+fn main() {
+    let handle = std::thread::spawn(|| 1);
+    std::process::exit(handle.join().unwrap_or(0));
+}
@@ -0,0 +1,10 @@
+#!/bin/bash
+# Nightly script for rvr
+# Runs the slow full matrices that CI samples
+
+set -e
+
+echo "=== Running address-mode differential (full matrix) ==="
+RVR_ADDRESS_MODES_FULL=1 cargo test -p rvr --test address_modes
+
+echo "=== All nightly checks passed ==="